@@ -0,0 +1,278 @@
+#!/usr/bin/env python3
+"""Generate client bindings for the lianad JSONRPC API from its OpenRPC schema.
+
+The schema is returned by the `rpc.discover` command of the daemon:
+
+    liana-cli --conf config.toml rpc.discover > schema.json
+    ./generate.py python schema.json > liana_client.py
+    ./generate.py typescript schema.json > liana_client.ts
+
+If no schema file is given, it is read from the standard input.
+"""
+
+import json
+import sys
+
+HEADER = "Generated by contrib/rpc_bindings/generate.py from the lianad API schema version {}. Do not edit."
+
+
+def load_schema(content):
+    doc = json.loads(content)
+    # Accept the output of liana-cli as well as the bare document.
+    if "result" in doc and "openrpc" not in doc:
+        doc = doc["result"]
+    return doc
+
+
+def ref_name(schema):
+    return schema["$ref"].rsplit("/", 1)[-1]
+
+
+def sorted_params(method):
+    """Required parameters first, as optional ones have a default value."""
+    return sorted(method["params"], key=lambda p: not p.get("required", False))
+
+
+def python_type(schema):
+    if "$ref" in schema:
+        return ref_name(schema)
+    if "oneOf" in schema:
+        return "Union[{}]".format(", ".join(python_type(s) for s in schema["oneOf"]))
+    ty = schema.get("type")
+    if isinstance(ty, list):
+        types = [python_type({**schema, "type": t}) for t in ty if t != "null"]
+        inner = types[0] if len(types) == 1 else "Union[{}]".format(", ".join(types))
+        return f"Optional[{inner}]" if "null" in ty else inner
+    if ty == "null":
+        return "None"
+    if ty == "string":
+        return "str"
+    if ty == "integer":
+        return "int"
+    if ty == "number":
+        return "float"
+    if ty == "boolean":
+        return "bool"
+    if ty == "array":
+        return "List[{}]".format(python_type(schema["items"]))
+    if ty == "object":
+        values = schema.get("additionalProperties")
+        if isinstance(values, dict) and "properties" not in schema:
+            return "Dict[str, {}]".format(python_type(values))
+        return "Dict[str, Any]"
+    return "Any"
+
+
+def python_client(doc):
+    lines = [
+        f"# {HEADER.format(doc['info']['version'])}",
+        "import json",
+        "import socket",
+        "from typing import Any, Dict, List, Optional, TypedDict, Union",
+        "",
+        "",
+        "class LianaRpcError(Exception):",
+        "    def __init__(self, method, error):",
+        "        self.method = method",
+        "        self.error = error",
+        "        super().__init__(f\"{method}: {error}\")",
+        "",
+    ]
+
+    for name, schema in doc["components"]["schemas"].items():
+        lines.append("")
+        if "properties" in schema:
+            lines.append(f"class {name}(TypedDict):")
+            for field, field_schema in schema["properties"].items():
+                lines.append(f"    {field}: {python_type(field_schema)}")
+            if not schema["properties"]:
+                lines.append("    pass")
+        else:
+            lines.append(f"{name} = {python_type(schema)}")
+        lines.append("")
+
+    lines += [
+        "",
+        "class LianaClient:",
+        '    """Client for the JSONRPC API of lianad, over its Unix domain socket."""',
+        "",
+        "    def __init__(self, socket_path):",
+        "        self.socket_path = socket_path",
+        "",
+        "    def _call(self, method, params):",
+        "        request = {\"jsonrpc\": \"2.0\", \"id\": 0, \"method\": method, \"params\": params}",
+        "        with socket.socket(socket.AF_UNIX, socket.SOCK_STREAM) as sock:",
+        "            sock.connect(self.socket_path)",
+        "            sock.sendall(json.dumps(request).encode() + b\"\\n\")",
+        "            buff = b\"\"",
+        "            while not buff.endswith(b\"\\n\"):",
+        "                chunk = sock.recv(4096)",
+        "                if not chunk:",
+        "                    break",
+        "                buff += chunk",
+        "        response = json.loads(buff)",
+        "        if \"error\" in response:",
+        "            raise LianaRpcError(method, response[\"error\"])",
+        "        return response[\"result\"]",
+    ]
+
+    for method in doc["methods"]:
+        name = method["name"]
+        args = ["self"]
+        body = ["        params: Dict[str, Any] = {}"]
+        for param in sorted_params(method):
+            pname, ptype = param["name"], python_type(param["schema"])
+            if param.get("required", False):
+                args.append(f"{pname}: {ptype}")
+                body.append(f"        params[\"{pname}\"] = {pname}")
+            else:
+                args.append(f"{pname}: Optional[{ptype}] = None")
+                body.append(f"        if {pname} is not None:")
+                body.append(f"            params[\"{pname}\"] = {pname}")
+        result = python_type(method["result"]["schema"])
+        lines += [
+            "",
+            f"    def {name.replace('.', '_')}({', '.join(args)}) -> {result}:",
+            f'        """{method.get("summary", "")}"""',
+            *body,
+            f"        return self._call(\"{name}\", params)",
+        ]
+
+    return "\n".join(lines) + "\n"
+
+
+def typescript_type(schema):
+    if "$ref" in schema:
+        return ref_name(schema)
+    if "oneOf" in schema:
+        return " | ".join(typescript_type(s) for s in schema["oneOf"])
+    ty = schema.get("type")
+    if isinstance(ty, list):
+        return " | ".join(typescript_type({**schema, "type": t}) for t in ty)
+    if ty == "null":
+        return "null"
+    if ty == "string":
+        if "enum" in schema:
+            return " | ".join(json.dumps(v) for v in schema["enum"])
+        return "string"
+    if ty in ("integer", "number"):
+        return "number"
+    if ty == "boolean":
+        return "boolean"
+    if ty == "array":
+        return "Array<{}>".format(typescript_type(schema["items"]))
+    if ty == "object":
+        values = schema.get("additionalProperties")
+        if isinstance(values, dict) and "properties" not in schema:
+            return "Record<string, {}>".format(typescript_type(values))
+        if "properties" in schema:
+            fields = "; ".join(
+                f"{f}: {typescript_type(s)}" for f, s in schema["properties"].items()
+            )
+            return f"{{ {fields} }}" if fields else "Record<string, never>"
+        return "Record<string, unknown>"
+    return "unknown"
+
+
+def typescript_client(doc):
+    lines = [
+        f"// {HEADER.format(doc['info']['version'])}",
+        "",
+        "/** Sends a request to lianad and returns the response object. */",
+        "export type Transport = (request: object) => Promise<{ result?: unknown; error?: unknown }>;",
+        "",
+        "export class LianaRpcError extends Error {",
+        "  constructor(public method: string, public error: unknown) {",
+        "    super(`${method}: ${JSON.stringify(error)}`);",
+        "  }",
+        "}",
+    ]
+
+    for name, schema in doc["components"]["schemas"].items():
+        lines.append("")
+        lines.append(f"export type {name} = {typescript_type(schema)};")
+
+    lines += [
+        "",
+        "/** Client for the JSONRPC API of lianad. */",
+        "export class LianaClient {",
+        "  private nextId = 0;",
+        "",
+        "  constructor(private transport: Transport) {}",
+        "",
+        "  private async call<T>(method: string, params: Record<string, unknown>): Promise<T> {",
+        "    const request = { jsonrpc: \"2.0\", id: this.nextId++, method, params };",
+        "    const response = await this.transport(request);",
+        "    if (response.error !== undefined) {",
+        "      throw new LianaRpcError(method, response.error);",
+        "    }",
+        "    return response.result as T;",
+        "  }",
+    ]
+
+    for method in doc["methods"]:
+        name = method["name"]
+        args = []
+        for param in sorted_params(method):
+            pname, ptype = param["name"], typescript_type(param["schema"])
+            optional = "" if param.get("required", False) else "?"
+            args.append(f"{pname}{optional}: {ptype}")
+        result = typescript_type(method["result"]["schema"])
+        signature = f"params: {{ {'; '.join(args)} }}" if args else ""
+        if args and all(not p.get("required", False) for p in method["params"]):
+            signature += " = {}"
+        lines += [
+            "",
+            f"  /** {method.get('summary', '')} */",
+            f"  {name.replace('.', '_')}({signature}): Promise<{result}> {{",
+            f"    return this.call<{result}>(\"{name}\", {'params' if args else '{}'});",
+            "  }",
+        ]
+
+    lines += [
+        "}",
+        "",
+        "/** A transport over the Unix domain socket of lianad, for Node.js. */",
+        "export function unixSocketTransport(socketPath: string): Transport {",
+        "  return (request) =>",
+        "    new Promise((resolve, reject) => {",
+        "      // eslint-disable-next-line @typescript-eslint/no-var-requires",
+        "      const net = require(\"net\");",
+        "      const socket = net.createConnection(socketPath);",
+        "      let buff = \"\";",
+        "      socket.on(\"connect\", () => socket.write(JSON.stringify(request) + \"\\n\"));",
+        "      socket.on(\"data\", (data: Buffer) => {",
+        "        buff += data.toString();",
+        "        if (buff.endsWith(\"\\n\")) {",
+        "          socket.end();",
+        "          resolve(JSON.parse(buff));",
+        "        }",
+        "      });",
+        "      socket.on(\"error\", reject);",
+        "    });",
+        "}",
+    ]
+
+    return "\n".join(lines) + "\n"
+
+
+GENERATORS = {
+    "python": python_client,
+    "typescript": typescript_client,
+}
+
+
+def main(args):
+    if len(args) not in (1, 2) or args[0] not in GENERATORS:
+        sys.stderr.write(f"Usage: {sys.argv[0]} python|typescript [schema.json]\n")
+        sys.exit(1)
+    if len(args) == 2:
+        with open(args[1]) as f:
+            doc = load_schema(f.read())
+    else:
+        doc = load_schema(sys.stdin.read())
+    sys.stdout.write(GENERATORS[args[0]](doc))
+
+
+if __name__ == "__main__":
+    main(sys.argv[1:])
@@ -7,23 +7,6 @@ use secp256k1::global::SECP256K1;
 
 use std::str::{self, FromStr};
 
-// Hacky way to detect too deeply nested wrappers, which would make rust-miniscript stack overflow
-// when parsing them.
-fn too_deep_wrappers(desc_str: &str) -> bool {
-    let mut wrapper_count = 0;
-    for c in desc_str.chars() {
-        if c == ':' || c == '(' || c == ')' || c == ',' {
-            if c == ':' && wrapper_count > 10 {
-                return true;
-            }
-            wrapper_count = 0;
-        } else {
-            wrapper_count += 1;
-        }
-    }
-    false
-}
-
 fuzz_target!(|data: &[u8]| {
     let desc_str = match str::from_utf8(data) {
         Ok(s) => s,
@@ -33,14 +16,6 @@ fuzz_target!(|data: &[u8]| {
         return;
     }
 
-    // Rust-miniscript uses a recursive parsing algorithm which could make us crash when trying to
-    // parse nested fragments. Until they fix it, rule out the most trivial recursion overflow the
-    // fuzzer can generate: too deep wrappers.
-    // FIXME: this shouldn't be necessary when upgrading to the next rust-miniscript version.
-    if too_deep_wrappers(desc_str) {
-        return;
-    }
-
     let desc = match LianaDescriptor::from_str(desc_str) {
         Ok(d) => d,
         Err(_) => return,
@@ -177,7 +177,7 @@ fuzz_target!(|config: Config| {
         desc.change_indexes(&psbt, SECP256K1);
 
         // Get the spend info without populating the PSBT at all.
-        let _ = desc.partial_spend_info(&psbt);
+        let _ = desc.partial_spend_info(&psbt, SECP256K1);
 
         // Populate the PSBT. We arbitrarily use the receive desc for inputs and the change desc
         // for outputs.
@@ -191,12 +191,12 @@ fuzz_target!(|config: Config| {
         }
 
         // Now get the spend info again with these info.
-        let _ = desc.partial_spend_info(&psbt);
+        let _ = desc.partial_spend_info(&psbt, SECP256K1);
 
         // Prune all the info but those for the latest available path, and get the spend info
         // again.
-        if let Ok(psbt) = desc.prune_bip32_derivs_last_avail(psbt) {
-            let _ = desc.partial_spend_info(&psbt);
+        if let Ok(psbt) = desc.prune_bip32_derivs_last_avail(psbt, SECP256K1) {
+            let _ = desc.partial_spend_info(&psbt, SECP256K1);
         }
     }
 });
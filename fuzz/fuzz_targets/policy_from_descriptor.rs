@@ -0,0 +1,40 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use liana::{
+    descriptors::{AnalysisLimits, LianaDescriptor, LianaPolicy},
+    miniscript::descriptor::{Descriptor, DescriptorPublicKey},
+};
+
+use std::str::{self, FromStr};
+
+fuzz_target!(|data: &[u8]| {
+    let desc_str = match str::from_utf8(data) {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+    // Rust-miniscript parses recursively, rule out too deeply nested descriptors like Liana does.
+    if data.len() > 10_000 || AnalysisLimits::default().check_str(desc_str).is_err() {
+        return;
+    }
+
+    // Any Miniscript descriptor, not necessarily a Liana one.
+    let desc = match Descriptor::<DescriptorPublicKey>::from_str(desc_str) {
+        Ok(d) => d,
+        Err(_) => return,
+    };
+    let policy = match LianaPolicy::from_multipath_descriptor(&desc) {
+        Ok(p) => p,
+        Err(_) => return,
+    };
+
+    // A policy inferred from a descriptor must be valid: it can be compiled into a descriptor,
+    // from which we get back the same policy.
+    let compiled = LianaDescriptor::new(policy.clone());
+    assert_eq!(compiled.policy(), policy);
+    assert_eq!(
+        compiled,
+        LianaDescriptor::from_str(&compiled.to_string()).unwrap()
+    );
+});
@@ -0,0 +1,525 @@
+//! The functions and records exposed to the foreign code.
+
+use crate::LianaError;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
+
+use liana::{
+    descriptors::{LianaDescriptor, LianaPolicy, PathInfo, PathSpendInfo},
+    miniscript::{
+        bitcoin::{self, absolute::LockTime, bip32, consensus::encode, psbt::Psbt, secp256k1},
+        descriptor::DescriptorPublicKey,
+    },
+    spend::{self, AddrInfo, CandidateCoin, SpendCreationError, SpendOutputAddress, SpendTxFees},
+};
+
+/// The network the addresses are for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum Network {
+    Bitcoin,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+impl From<Network> for bitcoin::Network {
+    fn from(network: Network) -> Self {
+        match network {
+            Network::Bitcoin => bitcoin::Network::Bitcoin,
+            Network::Testnet => bitcoin::Network::Testnet,
+            Network::Signet => bitcoin::Network::Signet,
+            Network::Regtest => bitcoin::Network::Regtest,
+        }
+    }
+}
+
+/// A key of a spending path, along with the fingerprint of the signer it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct PathKey {
+    pub fingerprint: String,
+    pub key: String,
+}
+
+/// The number of signatures required among a set of keys to spend through a path.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct SpendingPath {
+    pub threshold: u32,
+    pub keys: Vec<PathKey>,
+}
+
+/// A spending path which is only available after the coin is `timelock` blocks old.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct RecoveryPath {
+    pub timelock: u16,
+    pub path: SpendingPath,
+}
+
+/// The spending policy and the characteristics of a Liana descriptor.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct DescriptorInfo {
+    pub descriptor: String,
+    pub receive_descriptor: String,
+    pub change_descriptor: String,
+    pub is_taproot: bool,
+    pub primary_path: SpendingPath,
+    pub recovery_paths: Vec<RecoveryPath>,
+    /// Maximum size of the satisfaction of an input spent through the primary path.
+    pub primary_path_max_sat_vbytes: u64,
+    /// Maximum size of the satisfaction of an input spent through any recovery path.
+    pub recovery_path_max_sat_vbytes: u64,
+}
+
+/// The keys of a spending path to create a descriptor with. A single key must come with a
+/// threshold of 1.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct PolicyPath {
+    pub threshold: u32,
+    pub keys: Vec<String>,
+}
+
+/// The keys of a recovery path to create a descriptor with.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct RecoveryPolicyPath {
+    pub timelock: u16,
+    pub path: PolicyPath,
+}
+
+/// The spending policy to create a descriptor with.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct Policy {
+    /// Whether to create a Taproot descriptor instead of a P2WSH one.
+    pub taproot: bool,
+    pub primary_path: PolicyPath,
+    pub recovery_paths: Vec<RecoveryPolicyPath>,
+}
+
+/// The signatures provided for a spending path.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct PathSignatures {
+    pub threshold: u32,
+    pub sigs_count: u32,
+    /// The number of signatures provided by each signer, by fingerprint.
+    pub signed_fingerprints: HashMap<String, u32>,
+}
+
+/// The signing status of a PSBT for each spending path.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct SpendInfo {
+    pub primary_path: PathSignatures,
+    /// The recovery paths by timelock.
+    pub recovery_paths: HashMap<u16, PathSignatures>,
+}
+
+/// A coin of the descriptor to spend from.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct SpendCoin {
+    /// As `txid:vout`.
+    pub outpoint: String,
+    pub amount: u64,
+    pub derivation_index: u32,
+    pub is_change: bool,
+    /// Whether the coin must be spent. Otherwise it is only a candidate for coin selection.
+    pub must_select: bool,
+    /// The serialized transaction which created this coin.
+    pub prev_tx: Vec<u8>,
+}
+
+/// An output of the spend transaction.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct Destination {
+    pub address: String,
+    pub amount: u64,
+}
+
+/// The parameters of a spend transaction.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct SpendParams {
+    pub network: Network,
+    pub coins: Vec<SpendCoin>,
+    pub destinations: Vec<Destination>,
+    /// Target feerate in sats/vb.
+    pub feerate: u64,
+    /// The derivation index of the change address, if a change output is needed.
+    pub change_index: u32,
+    /// The block height to set as locktime.
+    pub locktime: u32,
+}
+
+/// A spend transaction to be signed.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct Spend {
+    /// The base64-encoded PSBT.
+    pub psbt: String,
+    pub has_change: bool,
+    pub warnings: Vec<String>,
+}
+
+fn parse_descriptor(descriptor: &str) -> Result<LianaDescriptor, LianaError> {
+    LianaDescriptor::from_str(descriptor).map_err(|e| LianaError::InvalidDescriptor {
+        reason: e.to_string(),
+    })
+}
+
+fn child_number(index: u32) -> Result<bip32::ChildNumber, LianaError> {
+    bip32::ChildNumber::from_normal_idx(index)
+        .map_err(|_| LianaError::InvalidDerivationIndex { index })
+}
+
+impl From<&PathInfo> for SpendingPath {
+    fn from(path: &PathInfo) -> Self {
+        let (threshold, keys) = match path {
+            PathInfo::Single(key) => (1, vec![key]),
+            PathInfo::Multi(threshold, keys) => (*threshold, keys.iter().collect()),
+        };
+        SpendingPath {
+            threshold: threshold as u32,
+            keys: keys
+                .into_iter()
+                .map(|key| PathKey {
+                    fingerprint: key.master_fingerprint().to_string(),
+                    key: key.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<&PathSpendInfo> for PathSignatures {
+    fn from(info: &PathSpendInfo) -> Self {
+        PathSignatures {
+            threshold: info.threshold as u32,
+            sigs_count: info.sigs_count as u32,
+            signed_fingerprints: info
+                .signed_pubkeys
+                .iter()
+                .map(|(fg, count)| (fg.to_string(), *count as u32))
+                .collect(),
+        }
+    }
+}
+
+impl PolicyPath {
+    fn path_info(&self) -> Result<PathInfo, LianaError> {
+        let keys = self
+            .keys
+            .iter()
+            .map(|k| {
+                DescriptorPublicKey::from_str(k).map_err(|e| LianaError::InvalidKey {
+                    key: k.clone(),
+                    reason: e.to_string(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        PathInfo::from_keys(self.threshold as usize, keys).map_err(|e| LianaError::InvalidPolicy {
+            reason: e.to_string(),
+        })
+    }
+}
+
+/// Analyze a Liana descriptor.
+#[uniffi::export]
+pub fn descriptor_info(descriptor: String) -> Result<DescriptorInfo, LianaError> {
+    let desc = parse_descriptor(&descriptor)?;
+    let policy = desc.policy();
+    Ok(DescriptorInfo {
+        descriptor: desc.to_string(),
+        receive_descriptor: desc.receive_descriptor().to_string(),
+        change_descriptor: desc.change_descriptor().to_string(),
+        is_taproot: desc.is_taproot(),
+        primary_path: policy.primary_path().into(),
+        recovery_paths: policy
+            .recovery_paths()
+            .iter()
+            .map(|(timelock, path)| RecoveryPath {
+                timelock: *timelock,
+                path: path.into(),
+            })
+            .collect(),
+        primary_path_max_sat_vbytes: desc.max_sat_vbytes(true) as u64,
+        recovery_path_max_sat_vbytes: desc.max_sat_vbytes(false) as u64,
+    })
+}
+
+/// The address at the given derivation index of the receive or change keychain.
+#[uniffi::export]
+pub fn derive_address(
+    descriptor: String,
+    network: Network,
+    index: u32,
+    is_change: bool,
+) -> Result<String, LianaError> {
+    let desc = parse_descriptor(&descriptor)?;
+    let secp = secp256k1::Secp256k1::verification_only();
+    let single_desc = if is_change {
+        desc.change_descriptor()
+    } else {
+        desc.receive_descriptor()
+    };
+    let address = single_desc
+        .derive(child_number(index)?, &secp)
+        .address(network.into());
+    Ok(address.to_string())
+}
+
+/// The descriptor corresponding to a spending policy.
+#[uniffi::export]
+pub fn policy_descriptor(policy: Policy) -> Result<String, LianaError> {
+    let primary_path = policy.primary_path.path_info()?;
+    let mut recovery_paths = BTreeMap::new();
+    for recovery in &policy.recovery_paths {
+        recovery_paths.insert(recovery.timelock, recovery.path.path_info()?);
+    }
+    let policy = if policy.taproot {
+        LianaPolicy::new(primary_path, recovery_paths)
+    } else {
+        LianaPolicy::new_legacy(primary_path, recovery_paths)
+    }
+    .map_err(|e| LianaError::InvalidPolicy {
+        reason: e.to_string(),
+    })?;
+    Ok(LianaDescriptor::new(policy).to_string())
+}
+
+/// The signing status of a base64-encoded PSBT spending coins of the descriptor.
+#[uniffi::export]
+pub fn spend_info(descriptor: String, psbt: String) -> Result<SpendInfo, LianaError> {
+    let desc = parse_descriptor(&descriptor)?;
+    let psbt = Psbt::from_str(&psbt).map_err(|e| LianaError::InvalidPsbt {
+        reason: e.to_string(),
+    })?;
+    let secp = secp256k1::Secp256k1::verification_only();
+    let info = desc
+        .partial_spend_info(&psbt, &secp)
+        .map_err(|e| LianaError::InvalidPsbt {
+            reason: e.to_string(),
+        })?;
+    Ok(SpendInfo {
+        primary_path: info.primary_path().into(),
+        recovery_paths: info
+            .recovery_paths()
+            .iter()
+            .map(|(timelock, path)| (*timelock, path.into()))
+            .collect(),
+    })
+}
+
+/// Create a PSBT spending coins of the descriptor.
+#[uniffi::export]
+pub fn create_spend(descriptor: String, params: SpendParams) -> Result<Spend, LianaError> {
+    let desc = parse_descriptor(&descriptor)?;
+    let network = params.network.into();
+    let secp = secp256k1::Secp256k1::verification_only();
+
+    let mut prev_txs = HashMap::with_capacity(params.coins.len());
+    let mut candidate_coins = Vec::with_capacity(params.coins.len());
+    for coin in &params.coins {
+        let outpoint = bitcoin::OutPoint::from_str(&coin.outpoint).map_err(|e| {
+            LianaError::InvalidOutpoint {
+                outpoint: coin.outpoint.clone(),
+                reason: e.to_string(),
+            }
+        })?;
+        let tx: bitcoin::Transaction =
+            encode::deserialize(&coin.prev_tx).map_err(|e| LianaError::InvalidTransaction {
+                outpoint: coin.outpoint.clone(),
+                reason: e.to_string(),
+            })?;
+        if tx.compute_txid() != outpoint.txid {
+            return Err(LianaError::InvalidTransaction {
+                outpoint: coin.outpoint.clone(),
+                reason: format!("transaction has txid {}", tx.compute_txid()),
+            });
+        }
+        prev_txs.insert(outpoint.txid, tx);
+        candidate_coins.push(CandidateCoin {
+            outpoint,
+            amount: bitcoin::Amount::from_sat(coin.amount),
+            deriv_index: child_number(coin.derivation_index)?,
+            is_change: coin.is_change,
+            must_select: coin.must_select,
+            sequence: None,
+            ancestor_info: None,
+        });
+    }
+
+    let destinations = params
+        .destinations
+        .iter()
+        .map(|dest| {
+            let addr = bitcoin::Address::from_str(&dest.address)
+                .and_then(|addr| addr.require_network(network))
+                .map_err(|e| LianaError::InvalidAddress {
+                    address: dest.address.clone(),
+                    reason: e.to_string(),
+                })?;
+            Ok((
+                SpendOutputAddress { addr, info: None },
+                bitcoin::Amount::from_sat(dest.amount),
+            ))
+        })
+        .collect::<Result<Vec<_>, LianaError>>()?;
+
+    let change_index = child_number(params.change_index)?;
+    let change_addr = SpendOutputAddress {
+        addr: desc
+            .change_descriptor()
+            .derive(change_index, &secp)
+            .address(network),
+        info: Some(AddrInfo {
+            index: change_index,
+            is_change: true,
+        }),
+    };
+    let locktime =
+        LockTime::from_height(params.locktime).map_err(|_| LianaError::InvalidLocktime {
+            locktime: params.locktime,
+        })?;
+
+    match spend::create_spend(
+        &desc,
+        &secp,
+        &mut prev_txs,
+        &destinations,
+        &candidate_coins,
+        SpendTxFees::Regular(params.feerate),
+        change_addr,
+        locktime,
+    ) {
+        Ok(res) => Ok(Spend {
+            psbt: res.psbt.to_string(),
+            has_change: res.has_change,
+            warnings: res.warnings.iter().map(|w| w.to_string()).collect(),
+        }),
+        Err(SpendCreationError::CoinSelection(e)) => {
+            Err(LianaError::InsufficientFunds { missing: e.missing })
+        }
+        Err(e) => Err(LianaError::SpendCreation {
+            reason: e.to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESC: &str = "wsh(or_d(pk([f5acc2fd/48'/1'/0'/2']tpubDFAqEGNyad35aBCKUAXbQGDjdVhNueno5ZZVEn3sQbW5ci457gLR7HyTmHBg93oourBssgUxuWz1jX5uhc1qaqFo9VsybY1J5FuedLfm4dK/<0;1>/*),and_v(v:pkh([da2ee873/48'/1'/0'/2']tpubDEbXY6RbN9mxAvQW797WxReGGkrdyRfdYcehVVaQQcQ3kyfhxSMcnU9qGpUVRHXXALvBtc99jcuxx5tkzcLaJbAukSNpP9h2ti4XFRosv1g/<0;1>/*),older(100))))";
+
+    #[test]
+    fn descriptor_analysis() {
+        let info = descriptor_info(DESC.to_string()).unwrap();
+        assert!(!info.is_taproot);
+        assert_eq!(info.primary_path.threshold, 1);
+        assert_eq!(info.primary_path.keys[0].fingerprint, "f5acc2fd");
+        assert_eq!(info.recovery_paths[0].timelock, 100);
+        assert!(matches!(
+            descriptor_info("wsh(pk(A))".to_string()),
+            Err(LianaError::InvalidDescriptor { .. })
+        ));
+
+        // A descriptor with the same policy can be built back from it.
+        let policy = Policy {
+            taproot: false,
+            primary_path: PolicyPath {
+                threshold: 1,
+                keys: vec![info.primary_path.keys[0].key.clone()],
+            },
+            recovery_paths: vec![RecoveryPolicyPath {
+                timelock: 100,
+                path: PolicyPath {
+                    threshold: 1,
+                    keys: vec![info.recovery_paths[0].path.keys[0].key.clone()],
+                },
+            }],
+        };
+        let rebuilt = descriptor_info(policy_descriptor(policy.clone()).unwrap()).unwrap();
+        assert_eq!(rebuilt.primary_path, info.primary_path);
+        assert_eq!(rebuilt.recovery_paths, info.recovery_paths);
+
+        // A single key with a threshold of 2, or an invalid key.
+        let mut invalid_policy = policy.clone();
+        invalid_policy.primary_path.threshold = 2;
+        assert!(matches!(
+            policy_descriptor(invalid_policy),
+            Err(LianaError::InvalidPolicy { .. })
+        ));
+        let mut invalid_policy = policy;
+        invalid_policy.primary_path.keys = vec!["xpub".to_string()];
+        assert!(matches!(
+            policy_descriptor(invalid_policy),
+            Err(LianaError::InvalidKey { .. })
+        ));
+
+        let receive = derive_address(DESC.to_string(), Network::Testnet, 0, false).unwrap();
+        let change = derive_address(DESC.to_string(), Network::Testnet, 0, true).unwrap();
+        assert_ne!(receive, change);
+        assert_eq!(
+            derive_address(DESC.to_string(), Network::Testnet, 1 << 31, false),
+            Err(LianaError::InvalidDerivationIndex { index: 1 << 31 })
+        );
+    }
+
+    #[test]
+    fn spend_creation() {
+        let desc = parse_descriptor(DESC).unwrap();
+        let secp = secp256k1::Secp256k1::verification_only();
+        let prev_tx = bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![bitcoin::TxIn::default()],
+            output: vec![bitcoin::TxOut {
+                value: bitcoin::Amount::from_sat(100_000),
+                script_pubkey: desc
+                    .receive_descriptor()
+                    .derive(0.into(), &secp)
+                    .script_pubkey(),
+            }],
+        };
+        let destination = derive_address(DESC.to_string(), Network::Testnet, 1, false).unwrap();
+        let mut params = SpendParams {
+            network: Network::Testnet,
+            coins: vec![SpendCoin {
+                outpoint: format!("{}:0", prev_tx.compute_txid()),
+                amount: 100_000,
+                derivation_index: 0,
+                is_change: false,
+                must_select: false,
+                prev_tx: encode::serialize(&prev_tx),
+            }],
+            destinations: vec![Destination {
+                address: destination,
+                amount: 50_000,
+            }],
+            feerate: 2,
+            change_index: 0,
+            locktime: 0,
+        };
+        let res = create_spend(DESC.to_string(), params.clone()).unwrap();
+        assert!(res.has_change);
+
+        // Nothing is signed yet.
+        let info = spend_info(DESC.to_string(), res.psbt).unwrap();
+        assert_eq!(info.primary_path.threshold, 1);
+        assert_eq!(info.primary_path.sigs_count, 0);
+
+        // Not enough funds.
+        params.destinations[0].amount = 200_000;
+        match create_spend(DESC.to_string(), params.clone()) {
+            Err(LianaError::InsufficientFunds { missing }) => assert!(missing > 100_000),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        // The transaction must be the one which created the coin.
+        params.coins[0].prev_tx = encode::serialize(&bitcoin::Transaction {
+            input: vec![],
+            ..prev_tx
+        });
+        assert!(matches!(
+            create_spend(DESC.to_string(), params),
+            Err(LianaError::InvalidTransaction { .. })
+        ));
+    }
+}
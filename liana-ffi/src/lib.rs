@@ -0,0 +1,91 @@
+//! Kotlin and Swift bindings to the Liana wallet logic.
+//!
+//! This exposes the IO-free parts of the `liana` crate (descriptor analysis, address derivation,
+//! spend creation and PSBT analysis) to mobile applications through UniFFI, so they apply the
+//! exact same validation rules as the Liana wallet. Parameters and results are typed records and
+//! failures are reported as a [`LianaError`], which the bindings turn into an exception.
+//!
+//! The bindings are generated from the compiled library by the `uniffi-bindgen` binary of this
+//! crate, see the README.
+
+pub mod api;
+
+pub use api::*;
+
+use std::{error, fmt};
+
+uniffi::setup_scaffolding!();
+
+/// An error returned to the foreign code.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Error)]
+pub enum LianaError {
+    InvalidDescriptor {
+        reason: String,
+    },
+    InvalidKey {
+        key: String,
+        reason: String,
+    },
+    InvalidPolicy {
+        reason: String,
+    },
+    InvalidDerivationIndex {
+        index: u32,
+    },
+    InvalidAddress {
+        address: String,
+        reason: String,
+    },
+    InvalidOutpoint {
+        outpoint: String,
+        reason: String,
+    },
+    /// The transaction given for a coin could not be parsed or did not create this coin.
+    InvalidTransaction {
+        outpoint: String,
+        reason: String,
+    },
+    InvalidLocktime {
+        locktime: u32,
+    },
+    InvalidPsbt {
+        reason: String,
+    },
+    /// The coins are not sufficient to pay for the destinations and the fees.
+    InsufficientFunds {
+        missing: u64,
+    },
+    SpendCreation {
+        reason: String,
+    },
+}
+
+impl fmt::Display for LianaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidDescriptor { reason } => write!(f, "Invalid descriptor: {}", reason),
+            Self::InvalidKey { key, reason } => write!(f, "Invalid key '{}': {}", key, reason),
+            Self::InvalidPolicy { reason } => write!(f, "Invalid policy: {}", reason),
+            Self::InvalidDerivationIndex { index } => {
+                write!(f, "Invalid derivation index: {}", index)
+            }
+            Self::InvalidAddress { address, reason } => {
+                write!(f, "Invalid address '{}': {}", address, reason)
+            }
+            Self::InvalidOutpoint { outpoint, reason } => {
+                write!(f, "Invalid outpoint '{}': {}", outpoint, reason)
+            }
+            Self::InvalidTransaction { outpoint, reason } => {
+                write!(f, "Invalid transaction for coin {}: {}", outpoint, reason)
+            }
+            Self::InvalidLocktime { locktime } => write!(f, "Invalid locktime: {}", locktime),
+            Self::InvalidPsbt { reason } => write!(f, "Invalid PSBT: {}", reason),
+            Self::InsufficientFunds { missing } => {
+                write!(f, "Insufficient funds: missing {} sats", missing)
+            }
+            Self::SpendCreation { reason } => write!(f, "Error creating spend: {}", reason),
+        }
+    }
+}
+
+impl error::Error for LianaError {}
@@ -1,3 +1,4 @@
+use crate::app::settings::{AccentColor, BlockExplorer};
 use crate::daemon::model::Coin;
 use liana::miniscript::bitcoin::Network;
 use std::path::PathBuf;
@@ -14,6 +15,21 @@ pub struct Cache {
     pub last_poll_timestamp: Option<u32>,
     /// The `last_poll_timestamp` when starting the application.
     pub last_poll_at_startup: Option<u32>,
+    /// The number of writes waiting for the remote backend to be reachable again.
+    pub queued_writes: usize,
+    /// The accent color of the wallet, if the user picked one.
+    pub accent_color: Option<AccentColor>,
+    /// The block explorer chosen by the user, if they did.
+    pub block_explorer: Option<BlockExplorer>,
+    /// Whether a passphrase was set to lock the application.
+    pub app_lock: bool,
+}
+
+impl Cache {
+    /// The block explorer to link the transactions and addresses to.
+    pub fn block_explorer(&self) -> BlockExplorer {
+        self.block_explorer.clone().unwrap_or_default()
+    }
 }
 
 /// only used for tests.
@@ -28,6 +44,10 @@ impl std::default::Default for Cache {
             sync_progress: 1.0,
             last_poll_timestamp: None,
             last_poll_at_startup: None,
+            queued_writes: 0,
+            accent_color: None,
+            block_explorer: None,
+            app_lock: false,
         }
     }
 }
@@ -4,6 +4,8 @@ use std::io::Write;
 use std::path::{Path, PathBuf};
 use tracing_subscriber::filter;
 
+use crate::update::UpdateCheckConfig;
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Config {
     /// Path to lianad configuration file.
@@ -17,6 +19,58 @@ pub struct Config {
     /// Start internal bitcoind executable.
     #[serde(default)]
     pub start_internal_bitcoind: bool,
+    /// Keep running in the system tray when the window is closed.
+    #[serde(default)]
+    pub minimize_to_tray: bool,
+    /// Which events to show a desktop notification for.
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    /// Whether and how to check for new releases.
+    #[serde(default)]
+    pub update_check: UpdateCheckConfig,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Per category opt-outs of desktop notifications. They are all enabled by default.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NotificationsConfig {
+    #[serde(default = "default_true")]
+    pub deposit_received: bool,
+    #[serde(default = "default_true")]
+    pub spend_confirmed: bool,
+    #[serde(default = "default_true")]
+    pub recovery_soon_available: bool,
+    #[serde(default = "default_true")]
+    pub backend_down: bool,
+    #[serde(default = "default_true")]
+    pub unauthorized_spend: bool,
+    #[serde(default = "default_true")]
+    pub scheduled_spend: bool,
+    #[serde(default = "default_true")]
+    pub backends_disagree: bool,
+    #[serde(default = "default_true")]
+    pub stale_tip: bool,
+    #[serde(default = "default_true")]
+    pub clock_skew: bool,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            deposit_received: true,
+            spend_confirmed: true,
+            recovery_soon_available: true,
+            backend_down: true,
+            unauthorized_spend: true,
+            scheduled_spend: true,
+            backends_disagree: true,
+            stale_tip: true,
+            clock_skew: true,
+        }
+    }
 }
 
 pub const DEFAULT_FILE_NAME: &str = "gui.toml";
@@ -29,6 +83,9 @@ impl Config {
             log_level: None,
             debug: None,
             start_internal_bitcoind,
+            minimize_to_tray: false,
+            notifications: NotificationsConfig::default(),
+            update_check: UpdateCheckConfig::default(),
         }
     }
 
@@ -7,6 +7,9 @@ use lianad::config::ConfigError;
 use crate::{
     app::{settings::SettingsError, wallet::WalletError},
     daemon::DaemonError,
+    i18n::{tr, tr_args},
+    remote_signer::RemoteSignerError,
+    tapsigner::TapsignerError,
 };
 
 #[derive(Debug)]
@@ -16,6 +19,8 @@ pub enum Error {
     Daemon(DaemonError),
     Unexpected(String),
     HardwareWallet(async_hwi::Error),
+    Tapsigner(TapsignerError),
+    RemoteSigner(RemoteSignerError),
     Desc(LianaDescError),
     Spend(SpendCreationError),
 }
@@ -28,10 +33,10 @@ impl std::fmt::Display for Error {
             Self::Spend(e) => write!(f, "{}", e),
             Self::Daemon(e) => match e {
                 DaemonError::Unexpected(e) => write!(f, "{}", e),
-                DaemonError::NoAnswer => write!(f, "Daemon did not answer"),
-                DaemonError::DaemonStopped => write!(f, "Daemon stopped"),
+                DaemonError::NoAnswer => write!(f, "{}", tr("Daemon did not answer")),
+                DaemonError::DaemonStopped => write!(f, "{}", tr("Daemon stopped")),
                 DaemonError::RpcSocket(Some(ErrorKind::ConnectionRefused), _) => {
-                    write!(f, "Failed to connect to daemon")
+                    write!(f, "{}", tr("Failed to connect to daemon"))
                 }
                 DaemonError::RpcSocket(kind, e) => {
                     if let Some(k) = kind {
@@ -41,10 +46,10 @@ impl std::fmt::Display for Error {
                     }
                 }
                 DaemonError::Start(e) => {
-                    write!(f, "Failed to start daemon: {}", e)
+                    write!(f, "{}", tr_args("Failed to start daemon: {error}", &[("error", e)]))
                 }
                 DaemonError::ClientNotSupported => {
-                    write!(f, "Daemon client is not supported")
+                    write!(f, "{}", tr("Daemon client is not supported"))
                 }
                 DaemonError::Rpc(code, e) => {
                     write!(f, "[{:?}] {}", code, e)
@@ -54,9 +59,11 @@ impl std::fmt::Display for Error {
                 }
                 DaemonError::CoinSelectionError => write!(f, "{}", e),
             },
-            Self::Unexpected(e) => write!(f, "Unexpected error: {}", e),
-            Self::HardwareWallet(e) => write!(f, "error: {}\nPlease check if the device is still connected and unlocked with the correct firmware open for the current network and no other application is accessing the device.", e),
-            Self::Desc(e) => write!(f, "Liana descriptor error: {}", e),
+            Self::Unexpected(e) => write!(f, "{}", tr_args("Unexpected error: {error}", &[("error", e)])),
+            Self::HardwareWallet(e) => write!(f, "{}", tr_args("error: {error}\nPlease check if the device is still connected and unlocked with the correct firmware open for the current network and no other application is accessing the device.", &[("error", e)])),
+            Self::Tapsigner(e) => write!(f, "{}", tr_args("Tapsigner: {error}", &[("error", e)])),
+            Self::RemoteSigner(e) => write!(f, "{}", tr_args("Remote signer: {error}", &[("error", e)])),
+            Self::Desc(e) => write!(f, "{}", tr_args("Liana descriptor error: {error}", &[("error", e)])),
         }
     }
 }
@@ -91,6 +98,18 @@ impl From<async_hwi::Error> for Error {
     }
 }
 
+impl From<TapsignerError> for Error {
+    fn from(error: TapsignerError) -> Self {
+        Error::Tapsigner(error)
+    }
+}
+
+impl From<RemoteSignerError> for Error {
+    fn from(error: RemoteSignerError) -> Self {
+        Error::RemoteSigner(error)
+    }
+}
+
 impl From<SpendCreationError> for Error {
     fn from(error: SpendCreationError) -> Self {
         Error::Spend(error)
@@ -0,0 +1,152 @@
+//! Help for the users new to Liana: the concepts of the wallet are explained in the tooltips of
+//! the views, and in a guided tour through them shown the first time the GUI is started.
+//!
+//! The tour is described by the list of its steps, see [`TOUR`], each opening a view and
+//! explaining one of the topics there. Adding a step only takes adding it to the list.
+
+use crate::app::preferences::View;
+
+/// The concepts of the wallet the help explains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topic {
+    Wallet,
+    PrimaryPath,
+    Timelock,
+    Psbt,
+    RecoveryPath,
+}
+
+impl Topic {
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Wallet => "Your Liana wallet",
+            Self::PrimaryPath => "The primary path",
+            Self::Timelock => "The timelock",
+            Self::Psbt => "PSBTs",
+            Self::RecoveryPath => "The recovery path",
+        }
+    }
+
+    pub fn text(&self) -> &'static str {
+        match self {
+            Self::Wallet => {
+                "The coins of a Liana wallet can be spent in two ways: through the primary path, \
+                 used every day, and through the recovery path, which only becomes available after \
+                 a while. This tour shows where to find them in the wallet."
+            }
+            Self::PrimaryPath => {
+                "The keys of the primary path can spend the coins at any time. Sending from the \
+                 wallet uses them, as long as they are available."
+            }
+            Self::Timelock => {
+                "Each coin starts a countdown, the timelock, once it is confirmed. When it is over, \
+                 the keys of the recovery path can spend the coin as well. Refreshing a coin, by \
+                 sending it to yourself, starts its countdown again."
+            }
+            Self::Psbt => {
+                "A PSBT (Partially Signed Bitcoin Transaction) is a transaction waiting for its \
+                 signatures. Sending creates one, to be signed with the signing devices of the \
+                 keys, possibly on other computers, before it is broadcast."
+            }
+            Self::RecoveryPath => {
+                "If the keys of the primary path are lost, the keys of the recovery path can move \
+                 the coins whose timelock is over, from here. Keep them safe, and apart from the \
+                 keys of the primary path."
+            }
+        }
+    }
+}
+
+/// A step of the guided tour: the view to open, and the topic to explain there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TourStep {
+    pub view: View,
+    pub topic: Topic,
+}
+
+/// The steps of the guided tour, in order.
+pub const TOUR: [TourStep; 5] = [
+    TourStep {
+        view: View::Home,
+        topic: Topic::Wallet,
+    },
+    TourStep {
+        view: View::Home,
+        topic: Topic::PrimaryPath,
+    },
+    TourStep {
+        view: View::Coins,
+        topic: Topic::Timelock,
+    },
+    TourStep {
+        view: View::Psbts,
+        topic: Topic::Psbt,
+    },
+    TourStep {
+        view: View::Recovery,
+        topic: Topic::RecoveryPath,
+    },
+];
+
+/// The progress through the guided tour.
+#[derive(Debug, Default)]
+pub struct Tour {
+    step: Option<usize>,
+}
+
+impl Tour {
+    /// The index of the current step, along with the step, if the tour is running.
+    pub fn current(&self) -> Option<(usize, &'static TourStep)> {
+        self.step.map(|i| (i, &TOUR[i]))
+    }
+
+    pub fn start(&mut self) -> Option<&'static TourStep> {
+        self.step = Some(0);
+        TOUR.first()
+    }
+
+    /// Go to the next step. Returns None once the tour is over.
+    pub fn next(&mut self) -> Option<&'static TourStep> {
+        self.step = self.step.map(|i| i + 1).filter(|i| *i < TOUR.len());
+        self.current().map(|(_, step)| step)
+    }
+
+    pub fn previous(&mut self) -> Option<&'static TourStep> {
+        self.step = self.step.map(|i| i.saturating_sub(1));
+        self.current().map(|(_, step)| step)
+    }
+
+    pub fn close(&mut self) {
+        self.step = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guided_tour() {
+        let mut tour = Tour::default();
+        assert!(tour.current().is_none());
+        assert!(tour.next().is_none());
+
+        assert_eq!(tour.start(), Some(&TOUR[0]));
+        assert_eq!(tour.previous(), Some(&TOUR[0]));
+        assert_eq!(tour.next(), Some(&TOUR[1]));
+        assert_eq!(tour.current(), Some((1, &TOUR[1])));
+        for _ in 2..TOUR.len() {
+            assert!(tour.next().is_some());
+        }
+        assert_eq!(
+            tour.current(),
+            Some((TOUR.len() - 1, &TOUR[TOUR.len() - 1]))
+        );
+        assert!(tour.next().is_none());
+        assert!(tour.current().is_none());
+
+        tour.start();
+        tour.close();
+        assert!(tour.current().is_none());
+    }
+}
@@ -0,0 +1,158 @@
+//! Hiding the wallet behind a passphrase, after some inactivity or on demand.
+//!
+//! Anyone passing by the computer could otherwise see the balance and the history of the wallet.
+//! The lock only hides them: the daemon keeps running, and nothing is encrypted.
+
+use std::time::{Duration, Instant};
+
+use liana_ui::component::form;
+
+use crate::app::settings::global::LockSettings;
+
+/// The delays of inactivity after which the application can be locked, in minutes.
+pub const LOCK_DELAYS: [u16; 4] = [1, 5, 15, 30];
+
+/// The minimum length of the lock passphrase.
+pub const PASSPHRASE_MIN_LEN: usize = 6;
+
+pub struct AppLock {
+    settings: Option<LockSettings>,
+    locked: bool,
+    last_activity: Instant,
+    /// The passphrase typed to unlock the application.
+    pub passphrase: form::Value<String>,
+    /// Whether the typed passphrase is being checked.
+    pub checking: bool,
+}
+
+impl AppLock {
+    /// The application starts locked if the lock is set, the balance of the wallet would be shown
+    /// otherwise to whoever starts it.
+    pub fn new(settings: Option<LockSettings>) -> Self {
+        Self {
+            locked: settings.is_some(),
+            settings,
+            last_activity: Instant::now(),
+            passphrase: form::Value::default(),
+            checking: false,
+        }
+    }
+
+    pub fn settings(&self) -> Option<&LockSettings> {
+        self.settings.as_ref()
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Whether the application is to be locked after some inactivity.
+    pub fn locks_when_inactive(&self) -> bool {
+        !self.locked && self.settings.as_ref().is_some_and(|s| s.after.is_some())
+    }
+
+    /// The passphrase or the delay changed, or the lock was removed.
+    pub fn set_settings(&mut self, settings: Option<LockSettings>) {
+        if settings.is_none() {
+            self.locked = false;
+        }
+        self.settings = settings;
+        self.last_activity = Instant::now();
+    }
+
+    /// The user interacted with the application.
+    pub fn activity(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    /// Lock the application, if a passphrase was set to unlock it.
+    pub fn lock(&mut self) {
+        if self.settings.is_some() {
+            self.locked = true;
+            self.passphrase = form::Value::default();
+            self.checking = false;
+        }
+    }
+
+    /// Lock the application if the user didn't interact with it for long enough.
+    pub fn lock_if_inactive(&mut self, now: Instant) {
+        if let Some(after) = self.settings.as_ref().and_then(|s| s.after) {
+            if now.saturating_duration_since(self.last_activity)
+                >= Duration::from_secs(u64::from(after) * 60)
+            {
+                self.lock();
+            }
+        }
+    }
+
+    /// The result of checking the typed passphrase.
+    pub fn unlock(&mut self, is_passphrase: bool) {
+        self.checking = false;
+        if is_passphrase {
+            self.locked = false;
+            self.passphrase = form::Value::default();
+            self.activity();
+        } else {
+            self.passphrase.valid = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_after_inactivity() {
+        let mut lock = AppLock::new(None);
+        assert!(!lock.is_locked());
+        // Can't lock without a passphrase.
+        lock.lock();
+        assert!(!lock.is_locked());
+
+        let settings = LockSettings {
+            salt: String::new(),
+            passphrase_hash: String::new(),
+            after: Some(5),
+        };
+        lock.set_settings(Some(settings.clone()));
+        assert!(!lock.is_locked());
+        assert!(lock.locks_when_inactive());
+        let now = Instant::now();
+        lock.lock_if_inactive(now + Duration::from_secs(4 * 60));
+        assert!(!lock.is_locked());
+        lock.lock_if_inactive(now + Duration::from_secs(5 * 60));
+        assert!(lock.is_locked());
+        assert!(!lock.locks_when_inactive());
+
+        lock.unlock(false);
+        assert!(lock.is_locked());
+        assert!(!lock.passphrase.valid);
+        lock.unlock(true);
+        assert!(!lock.is_locked());
+        assert!(lock.passphrase.valid);
+
+        // Only locked on demand without a delay.
+        lock.set_settings(Some(LockSettings {
+            after: None,
+            ..settings
+        }));
+        assert!(!lock.locks_when_inactive());
+        lock.lock_if_inactive(now + Duration::from_secs(24 * 60 * 60));
+        assert!(!lock.is_locked());
+        lock.lock();
+        assert!(lock.is_locked());
+
+        // Removing the lock unlocks.
+        lock.set_settings(None);
+        assert!(!lock.is_locked());
+
+        // Locked at startup once set.
+        assert!(AppLock::new(Some(LockSettings {
+            salt: String::new(),
+            passphrase_hash: String::new(),
+            after: None,
+        }))
+        .is_locked());
+    }
+}
@@ -12,4 +12,40 @@ pub enum Menu {
     Recovery,
     RefreshCoins(Vec<OutPoint>),
     PsbtPreSelected(Txid),
+    Activity,
+}
+
+impl Menu {
+    /// The menu opened by pressing the command key (Ctrl, or Cmd on macOS) along with this
+    /// character, if any.
+    pub fn from_shortcut(c: &str) -> Option<Menu> {
+        match c.to_lowercase().as_str() {
+            "h" => Some(Menu::Home),
+            "s" => Some(Menu::CreateSpendTx),
+            "r" => Some(Menu::Receive),
+            "o" => Some(Menu::Coins),
+            "t" => Some(Menu::Transactions),
+            "p" => Some(Menu::PSBTs),
+            "y" => Some(Menu::Activity),
+            "," => Some(Menu::Settings),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortcuts() {
+        assert_eq!(Menu::from_shortcut("s"), Some(Menu::CreateSpendTx));
+        assert_eq!(Menu::from_shortcut("R"), Some(Menu::Receive));
+        assert_eq!(Menu::from_shortcut(","), Some(Menu::Settings));
+        assert_eq!(Menu::from_shortcut("y"), Some(Menu::Activity));
+        // Keep the usual editing shortcuts for the text inputs.
+        for c in ["a", "c", "v", "x", "z"] {
+            assert_eq!(Menu::from_shortcut(c), None);
+        }
+    }
 }
@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use liana::miniscript::bitcoin::{
@@ -9,31 +10,59 @@ use liana::miniscript::bitcoin::{
 use lianad::config::Config as DaemonConfig;
 
 use crate::{
-    app::{cache::Cache, error::Error, view, wallet::Wallet},
+    app::{cache::Cache, error::Error, settings::global::LockSettings, view, wallet::Wallet},
     daemon::model::*,
+    download,
     export::ExportMessage,
+    file_drop::{DroppedFile, DroppedFileError},
     hw::HardwareWalletMessage,
+    update,
 };
 
 #[derive(Debug)]
 pub enum Message {
     Tick,
+    PollEvents,
+    CheckUpdate,
+    // A newer release of Liana, if any.
+    Update(Result<Option<update::Release>, update::UpdateError>),
+    Events(Result<Vec<EventEntry>, Error>),
+    // The events listed in the activity feed.
+    ActivityEvents(Result<Vec<EventEntry>, Error>),
     UpdateCache(Result<Cache, Error>),
     UpdatePanelCache(/* is current panel */ bool),
     View(view::Message),
     LoadDaemonConfig(Box<DaemonConfig>),
     DaemonConfigLoaded(Result<(), Error>),
+    // Whether the managed bitcoind service is installed after it was toggled.
+    BitcoindService(Result<bool, Error>),
+    BitcoindRelease(Result<String, Error>),
+    // Whether Liana handles the bitcoin: links after it was toggled.
+    UriHandler(Result<bool, Error>),
+    BitcoindDownload(Result<download::Progress, download::DownloadError>),
+    // The managed bitcoind version selected to run from the next start of the node.
+    BitcoindVersion(Result<&'static str, Error>),
     LoadWallet(Wallet),
     Info(Result<GetInfoResult, Error>),
+    HealthCheck(Result<HealthCheckResult, Error>),
     ReceiveAddress(Result<(Address, ChildNumber), Error>),
     Coins(Result<Vec<Coin>, Error>),
+    Balance(Result<GetBalanceResult, Error>),
     Labels(Result<HashMap<String, String>, Error>),
+    SpendTemplates(Result<Vec<SpendTemplate>, Error>),
     SpendTxs(Result<Vec<SpendTx>, Error>),
     Psbt(Result<(Psbt, Vec<String>), Error>),
     RbfPsbt(Result<Txid, Error>),
     Recovery(Result<SpendTx, Error>),
     Signed(Fingerprint, Result<Psbt, Error>),
+    // The PSBT signed by the Tapsigner of this id, with the fingerprint of its master key.
+    TapsignerSigned(String, Result<(Fingerprint, Psbt), Error>),
     WalletUpdated(Result<Arc<Wallet>, Error>),
+    // The folder the key sheets were written to, if one was chosen.
+    KeySheetsExported(Result<Option<PathBuf>, Error>),
+    DescriptorSaved(Result<Option<PathBuf>, Error>),
+    // The path the receipt of a transaction was written to, if one was chosen.
+    ReceiptSaved(Result<Option<PathBuf>, Error>),
     Updated(Result<(), Error>),
     Saved(Result<(), Error>),
     Verified(Fingerprint, Result<(), Error>),
@@ -44,8 +73,17 @@ pub enum Message {
     Payments(Result<Vec<Payment>, Error>),
     PaymentsExtension(Result<Vec<Payment>, Error>),
     Payment(Result<(HistoryTransaction, usize), Error>),
+    RecoveryReadiness(Result<RecoveryReadiness, Error>),
+    MempoolStats(Result<GetMempoolStatsResult, Error>),
+    RecoverySchedule(Result<GetRecoveryScheduleResult, Error>),
     LabelsUpdated(Result<HashMap<String, Option<String>>, Error>),
     BroadcastModal(Result<HashSet<Txid>, Error>),
     RbfModal(Box<HistoryTransaction>, bool, Result<HashSet<Txid>, Error>),
     Export(ExportMessage),
+    FileDropped(Result<DroppedFile, DroppedFileError>),
+    // The lock settings once saved, none if the lock was removed.
+    LockUpdated(Result<Option<LockSettings>, Error>),
+    // Whether the passphrase typed unlocks the application.
+    Unlocked(bool),
+    CheckLock,
 }
@@ -1,7 +1,11 @@
 pub mod cache;
 pub mod config;
+pub mod help;
+pub mod lock;
 pub mod menu;
 pub mod message;
+pub mod notify;
+pub mod preferences;
 pub mod settings;
 pub mod state;
 pub mod view;
@@ -13,15 +17,19 @@ use std::fs::OpenOptions;
 use std::io::Write;
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use iced::{clipboard, time, Subscription, Task};
+use iced::{time, Subscription, Task};
 use tokio::runtime::Handle;
 use tracing::{error, info, warn};
 
 pub use liana::miniscript::bitcoin;
 use liana_ui::{
-    component::network_banner,
+    component::{
+        amount::{amounts_hidden, set_amounts_hidden},
+        modal::Modal,
+        network_banner,
+    },
     widget::{Column, Element},
 };
 pub use lianad::{commands::CoinStatus, config::Config as DaemonConfig};
@@ -30,15 +38,36 @@ pub use config::Config;
 pub use message::Message;
 
 use state::{
-    CoinsPanel, CreateSpendPanel, Home, PsbtsPanel, ReceivePanel, RecoveryPanel, State,
-    TransactionsPanel,
+    ActivityPanel, CoinsPanel, CreateSpendPanel, Home, PsbtsPanel, ReceivePanel, RecoveryPanel,
+    State, TransactionsPanel,
 };
 use wallet::{sync_status, SyncStatus};
 
+use lianad::config::BitcoinBackend;
+
 use crate::{
-    app::{cache::Cache, error::Error, menu::Menu, wallet::Wallet},
-    daemon::{embedded::EmbeddedDaemon, Daemon, DaemonBackend},
-    node::bitcoind::Bitcoind,
+    app::{
+        cache::Cache,
+        error::Error,
+        help::Tour,
+        lock::AppLock,
+        menu::Menu,
+        preferences::{self, Preferences, View, WindowGeometry},
+        settings::global::{self, LockSettings},
+        wallet::Wallet,
+    },
+    clipboard,
+    daemon::{embedded::EmbeddedDaemon, model::Event, Daemon, DaemonBackend},
+    file_drop::DroppedFile,
+    i18n::tr_args,
+    keychain,
+    node::{
+        bitcoind::{stop_bitcoind, Bitcoind},
+        service,
+    },
+    payment_request::PaymentRequest,
+    psbt_format::Content,
+    update, utils,
 };
 
 use self::state::SettingsState;
@@ -53,6 +82,7 @@ struct Panels {
     receive: ReceivePanel,
     create_spend: CreateSpendPanel,
     settings: SettingsState,
+    activity: ActivityPanel,
 }
 
 impl Panels {
@@ -77,7 +107,11 @@ impl Panels {
                 ),
                 cache.blockheight,
             ),
-            coins: CoinsPanel::new(&cache.coins, wallet.main_descriptor.first_timelock_value()),
+            coins: CoinsPanel::new(
+                &cache.coins,
+                wallet.main_descriptor.first_timelock_value(),
+                wallet.preferences.coins,
+            ),
             transactions: TransactionsPanel::new(wallet.clone()),
             psbts: PsbtsPanel::new(wallet.clone()),
             recovery: RecoveryPanel::new(wallet.clone(), &cache.coins, cache.blockheight),
@@ -92,8 +126,10 @@ impl Panels {
                 data_dir,
                 wallet.clone(),
                 daemon_backend,
-                internal_bitcoind.is_some(),
+                internal_bitcoind.is_some()
+                    || service::is_installed(&cache.datadir_path, cache.network),
             ),
+            activity: ActivityPanel::new(wallet),
         }
     }
 
@@ -110,6 +146,7 @@ impl Panels {
             Menu::Recovery => &self.recovery,
             Menu::RefreshCoins(_) => &self.create_spend,
             Menu::PsbtPreSelected(_) => &self.psbts,
+            Menu::Activity => &self.activity,
         }
     }
 
@@ -126,6 +163,7 @@ impl Panels {
             Menu::Recovery => &mut self.recovery,
             Menu::RefreshCoins(_) => &mut self.create_spend,
             Menu::PsbtPreSelected(_) => &mut self.psbts,
+            Menu::Activity => &mut self.activity,
         }
     }
 }
@@ -136,19 +174,44 @@ pub struct App {
     wallet: Arc<Wallet>,
     daemon: Arc<dyn Daemon + Sync + Send>,
     internal_bitcoind: Option<Bitcoind>,
+    // Whether the service running the node was removed during this session, the node it started
+    // must then be stopped along with the GUI.
+    bitcoind_service_removed: bool,
+    // Identifier of the latest event of the daemon we got, if we polled events already.
+    last_event_id: Option<u64>,
+    events_polled: bool,
+    // The latest warnings about the tip of the chain not advancing and about the system clock
+    // being off, while they last.
+    stale_tip: Option<Event>,
+    clock_skew: Option<Event>,
+    // A newer release of Liana the user wasn't told about yet.
+    update: Option<update::Release>,
+    // Why the last file dropped onto the window, or the last payment link opened, wasn't
+    // imported, if so.
+    dropped_file_error: Option<String>,
+    // The balance last recorded in the settings, for the launcher.
+    recorded_balance: Option<u64>,
+    lock: AppLock,
+    tour: Tour,
 
     panels: Panels,
 }
 
 impl App {
     pub fn new(
-        cache: Cache,
+        mut cache: Cache,
         wallet: Arc<Wallet>,
         config: Config,
         daemon: Arc<dyn Daemon + Sync + Send>,
         data_dir: PathBuf,
         internal_bitcoind: Option<Bitcoind>,
     ) -> (App, Task<Message>) {
+        let lock = LockSettings::from_file(&cache.datadir_path).unwrap_or_else(|e| {
+            warn!("Failed to read the lock settings: {}", e);
+            None
+        });
+        cache.app_lock = lock.is_some();
+        set_amounts_hidden(wallet.hide_amounts);
         let mut panels = Panels::new(
             &cache,
             wallet.clone(),
@@ -156,18 +219,117 @@ impl App {
             daemon.backend(),
             internal_bitcoind.as_ref(),
         );
-        let cmd = panels.home.reload(daemon.clone(), wallet.clone());
-        (
-            Self {
-                panels,
-                cache,
-                config,
-                daemon,
-                wallet,
-                internal_bitcoind,
-            },
-            cmd,
-        )
+        let cmd = Task::batch([
+            panels.home.reload(daemon.clone(), wallet.clone()),
+            Task::done(Message::CheckUpdate),
+        ]);
+        let last_view = wallet.preferences.last_view;
+        let mut app = Self {
+            panels,
+            cache,
+            config,
+            daemon,
+            wallet,
+            internal_bitcoind,
+            bitcoind_service_removed: false,
+            last_event_id: None,
+            events_polled: false,
+            stale_tip: None,
+            clock_skew: None,
+            update: None,
+            dropped_file_error: None,
+            recorded_balance: None,
+            lock: AppLock::new(lock),
+            tour: Tour::default(),
+        };
+        // The guided tour is shown the first time the GUI is started, or the wallet is opened on
+        // the view it was closed on.
+        let tour_done = global::tour_done(&app.cache.datadir_path).unwrap_or_else(|e| {
+            warn!("Failed to read whether the guided tour was done: {}", e);
+            true
+        });
+        let first_view = if tour_done {
+            last_view
+        } else {
+            app.tour.start().map(|step| step.view)
+        };
+        let cmd = match first_view.map(View::menu) {
+            Some(menu) if menu != Menu::Home => Task::batch([cmd, app.set_current_panel(menu)]),
+            _ => cmd,
+        };
+        (app, cmd)
+    }
+
+    // Record the balance of the wallet in the settings if it changed, to show it in the launcher.
+    fn record_balance(&mut self) {
+        let balance = self.cache.coins.iter().map(|c| c.amount.to_sat()).sum();
+        if self.recorded_balance == Some(balance) {
+            return;
+        }
+        self.recorded_balance = Some(balance);
+        let checksum = self.wallet.descriptor_checksum();
+        let datadir = self.cache.datadir_path.clone();
+        match settings::Settings::from_file(datadir.clone(), self.cache.network) {
+            Ok(mut s) => {
+                if let Some(w) = s
+                    .wallets
+                    .iter_mut()
+                    .find(|w| w.descriptor_checksum == checksum)
+                {
+                    w.last_balance = Some(balance);
+                    if let Err(e) = s.to_file(datadir, self.cache.network) {
+                        warn!("Failed to record the balance: {}", e);
+                    }
+                }
+            }
+            Err(e) => warn!("Failed to record the balance: {}", e),
+        }
+    }
+
+    // Change the preferences of the wallet in its settings, logging if it failed.
+    fn update_preferences(&self, f: impl FnOnce(&mut Preferences)) {
+        if let Err(e) = preferences::update(
+            self.cache.datadir_path.clone(),
+            self.cache.network,
+            &self.wallet.descriptor_checksum(),
+            f,
+        ) {
+            warn!("Failed to save the preferences: {}", e);
+        }
+    }
+
+    /// Remember the window and the view the wallet is closed on, to restore them the next time it
+    /// is opened.
+    pub fn save_preferences(&self, window: Option<WindowGeometry>) {
+        let view = View::from_menu(&self.panels.current);
+        self.update_preferences(|p| {
+            if window.is_some() {
+                p.window = window;
+            }
+            p.last_view = Some(view);
+        });
+    }
+
+    // Mask or reveal the amounts, and remember it for the next time the wallet is opened.
+    fn hide_amounts(&mut self, hidden: bool) {
+        set_amounts_hidden(hidden);
+        let checksum = self.wallet.descriptor_checksum();
+        let datadir = self.cache.datadir_path.clone();
+        match settings::Settings::from_file(datadir.clone(), self.cache.network) {
+            Ok(mut s) => {
+                if let Some(w) = s
+                    .wallets
+                    .iter_mut()
+                    .find(|w| w.descriptor_checksum == checksum)
+                {
+                    w.hide_amounts = hidden;
+                    if let Err(e) = s.to_file(datadir, self.cache.network) {
+                        warn!("Failed to record the privacy mode: {}", e);
+                    }
+                }
+            }
+            Err(e) => warn!("Failed to record the privacy mode: {}", e),
+        }
     }
 
     fn set_current_panel(&mut self, menu: Menu) -> Task<Message> {
@@ -230,6 +392,29 @@ impl App {
             .reload(self.daemon.clone(), self.wallet.clone())
     }
 
+    /// Open the send screen prefilled with a payment request, for instance from a `bitcoin:` link
+    /// clicked while the wallet is running.
+    pub fn open_payment_request(&mut self, request: &PaymentRequest) -> Task<Message> {
+        if !request.address.is_valid_for_network(self.cache.network) {
+            self.dropped_file_error = Some(tr_args(
+                "The payment link is for another network than {network}",
+                &[("network", &self.cache.network)],
+            ));
+            return Task::none();
+        }
+        self.dropped_file_error = None;
+        self.panels.current_mut().interrupt();
+        self.panels.create_spend = CreateSpendPanel::new_payment_request(
+            self.wallet.clone(),
+            &self.cache.coins,
+            self.cache.blockheight as u32,
+            request,
+            self.cache.network,
+        );
+        // The panel is only recreated by the menu if the spend is past its first step.
+        self.set_current_panel(Menu::CreateSpendTx)
+    }
+
     pub fn subscription(&self) -> Subscription<Message> {
         Subscription::batch(vec![
             time::every(Duration::from_secs(
@@ -261,10 +446,22 @@ impl App {
                 },
             ))
             .map(|_| Message::Tick),
+            time::every(Duration::from_secs(5)).map(|_| Message::PollEvents),
+            time::every(Duration::from_secs(24 * 60 * 60)).map(|_| Message::CheckUpdate),
+            if self.lock.locks_when_inactive() {
+                time::every(Duration::from_secs(10)).map(|_| Message::CheckLock)
+            } else {
+                Subscription::none()
+            },
             self.panels.current().subscription(),
         ])
     }
 
+    /// Whether to keep running in the system tray when the window is closed.
+    pub fn minimize_to_tray(&self) -> bool {
+        self.config.minimize_to_tray
+    }
+
     pub fn stop(&mut self) {
         info!("Close requested");
         if self.daemon.backend().is_embedded() {
@@ -275,17 +472,39 @@ impl App {
             }
             if let Some(bitcoind) = &self.internal_bitcoind {
                 bitcoind.stop();
+            } else if self.bitcoind_service_removed {
+                if let Some(BitcoinBackend::Bitcoind(bitcoind_config)) = self
+                    .daemon
+                    .config()
+                    .and_then(|cfg| cfg.bitcoin_backend.as_ref())
+                {
+                    stop_bitcoind(bitcoind_config);
+                }
             }
         }
     }
 
     pub fn update(&mut self, message: Message) -> Task<Message> {
+        if let Message::View(msg) = &message {
+            if !self.lock.is_locked() {
+                self.lock.activity();
+            } else if !matches!(
+                msg,
+                view::Message::UnlockPassphraseEdited(_) | view::Message::Unlock
+            ) {
+                // Nothing of the wallet can be reached while it's locked.
+                return Task::none();
+            }
+        }
         match message {
             Message::Tick => {
                 let daemon = self.daemon.clone();
                 let datadir_path = self.cache.datadir_path.clone();
                 let network = self.cache.network;
                 let last_poll_at_startup = self.cache.last_poll_at_startup;
+                let accent_color = self.cache.accent_color;
+                let block_explorer = self.cache.block_explorer.clone();
+                let app_lock = self.cache.app_lock;
                 Task::perform(
                     async move {
                         // we check every 10 second if the daemon poller is alive
@@ -305,6 +524,10 @@ impl App {
                             sync_progress: info.sync,
                             last_poll_timestamp: info.last_poll_timestamp,
                             last_poll_at_startup, // doesn't change
+                            queued_writes: daemon.queued_writes(),
+                            accent_color,
+                            block_explorer,
+                            app_lock,
                         })
                     },
                     Message::UpdateCache,
@@ -314,6 +537,7 @@ impl App {
                 match res {
                     Ok(cache) => {
                         self.cache.clone_from(&cache);
+                        self.record_balance();
                         let current = &self.panels.current;
                         let daemon = self.daemon.clone();
                         // These are the panels to update with the cache.
@@ -337,6 +561,111 @@ impl App {
                 }
                 Task::none()
             }
+            Message::PollEvents => {
+                let daemon = self.daemon.clone();
+                let since = self.last_event_id;
+                Task::perform(
+                    async move {
+                        daemon
+                            .list_events(since)
+                            .await
+                            .map(|res| res.events)
+                            .map_err(|e| e.into())
+                    },
+                    Message::Events,
+                )
+            }
+            Message::Events(res) => {
+                match res {
+                    Ok(events) => {
+                        // Those which happened before we started may still be ongoing though.
+                        for entry in &events {
+                            match entry.event {
+                                Event::StaleTip { .. } => {
+                                    self.stale_tip = Some(entry.event.clone())
+                                }
+                                Event::TipAdvanced { .. } => self.stale_tip = None,
+                                Event::ClockSkewed { .. } => {
+                                    self.clock_skew = Some(entry.event.clone())
+                                }
+                                Event::ClockSynced => self.clock_skew = None,
+                                _ => {}
+                            }
+                        }
+                        // The events which happened before we started are not news.
+                        if self.events_polled {
+                            for entry in &events {
+                                notify::notify(&self.config.notifications, &entry.event);
+                            }
+                        }
+                        self.events_polled = true;
+                        if let Some(entry) = events.last() {
+                            self.last_event_id = Some(entry.id);
+                            // Keep the activity feed up to date while it's shown.
+                            if self.panels.current == Menu::Activity {
+                                return self
+                                    .panels
+                                    .activity
+                                    .reload(self.daemon.clone(), self.wallet.clone());
+                            }
+                        }
+                    }
+                    Err(e) => tracing::error!("Failed to poll events: {}", e),
+                }
+                Task::none()
+            }
+            Message::CheckUpdate => {
+                if !self.config.update_check.enabled {
+                    return Task::none();
+                }
+                Task::perform(
+                    update::check(self.config.update_check.clone()),
+                    Message::Update,
+                )
+            }
+            Message::Update(res) => {
+                match res {
+                    Ok(Some(release)) => {
+                        info!("Liana {} is available", release.version);
+                        self.update = Some(release);
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!("Failed to check for updates: {}", e),
+                }
+                Task::none()
+            }
+            Message::View(view::Message::DismissUpdate) => {
+                self.update = None;
+                Task::none()
+            }
+            Message::FileDropped(res) => {
+                self.dropped_file_error = None;
+                match res {
+                    Ok(DroppedFile::Psbt(psbt)) => {
+                        let cmd = self.set_current_panel(Menu::PSBTs);
+                        self.panels.psbts.import(Content::Psbt(psbt));
+                        return cmd;
+                    }
+                    Ok(DroppedFile::SignedTransaction(tx)) => {
+                        let cmd = self.set_current_panel(Menu::PSBTs);
+                        self.panels.psbts.import(Content::SignedTransaction(tx));
+                        return cmd;
+                    }
+                    // Descriptors and mnemonics are only imported when installing a wallet.
+                    Ok(file) => {
+                        self.dropped_file_error = Some(tr_args(
+                            "A {kind} can only be imported when adding a wallet",
+                            &[("kind", &file.kind())],
+                        ))
+                    }
+                    Err(e) => self.dropped_file_error = Some(e.to_string()),
+                }
+                Task::none()
+            }
+            Message::View(view::Message::DismissDroppedFile) => {
+                self.dropped_file_error = None;
+                Task::none()
+            }
             Message::LoadDaemonConfig(cfg) => {
                 let path = self.config.daemon_config_path.clone().expect(
                     "Application config must have a daemon configuration file path at this point.",
@@ -345,6 +674,8 @@ impl App {
                 self.update(Message::DaemonConfigLoaded(res))
             }
             Message::WalletUpdated(Ok(wallet)) => {
+                self.cache.accent_color = wallet.accent_color;
+                self.cache.block_explorer = wallet.block_explorer.clone();
                 self.wallet = wallet.clone();
                 self.panels.current_mut().update(
                     self.daemon.clone(),
@@ -352,8 +683,106 @@ impl App {
                     Message::WalletUpdated(Ok(wallet)),
                 )
             }
+            Message::BitcoindService(res) => {
+                if let Ok(installed) = &res {
+                    self.bitcoind_service_removed = !installed && self.internal_bitcoind.is_none();
+                }
+                self.panels.current_mut().update(
+                    self.daemon.clone(),
+                    &self.cache,
+                    Message::BitcoindService(res),
+                )
+            }
+            Message::LockUpdated(Ok(lock)) => {
+                self.cache.app_lock = lock.is_some();
+                self.lock.set_settings(lock.clone());
+                self.panels.current_mut().update(
+                    self.daemon.clone(),
+                    &self.cache,
+                    Message::LockUpdated(Ok(lock)),
+                )
+            }
+            Message::CheckLock => {
+                self.lock.lock_if_inactive(Instant::now());
+                Task::none()
+            }
+            Message::View(view::Message::Lock) => {
+                self.lock.lock();
+                Task::none()
+            }
+            Message::View(view::Message::UnlockPassphraseEdited(passphrase)) => {
+                if !self.lock.checking {
+                    self.lock.passphrase.value = passphrase;
+                    self.lock.passphrase.valid = true;
+                }
+                Task::none()
+            }
+            Message::View(view::Message::Unlock) => {
+                match self.lock.settings().cloned() {
+                    Some(settings) if self.lock.is_locked() && !self.lock.checking => {
+                        self.lock.checking = true;
+                        let passphrase = self.lock.passphrase.value.clone();
+                        // Hashing the passphrase takes a little while, by design.
+                        Task::perform(
+                            async move { settings.is_passphrase(&passphrase) },
+                            Message::Unlocked,
+                        )
+                    }
+                    _ => Task::none(),
+                }
+            }
+            Message::Unlocked(is_passphrase) => {
+                self.lock.unlock(is_passphrase);
+                Task::none()
+            }
+            Message::View(view::Message::ToggleAmounts) => {
+                self.hide_amounts(!amounts_hidden());
+                Task::none()
+            }
+            Message::View(view::Message::Tour(msg)) => {
+                let step = match msg {
+                    view::TourMessage::Start => self.tour.start(),
+                    view::TourMessage::Next => self.tour.next(),
+                    view::TourMessage::Previous => self.tour.previous(),
+                    view::TourMessage::Close => {
+                        self.tour.close();
+                        None
+                    }
+                };
+                match step {
+                    Some(step) => self.set_current_panel(step.view.menu()),
+                    None => {
+                        if let Err(e) = global::set_tour_done(&self.cache.datadir_path) {
+                            warn!("Failed to record the guided tour was done: {}", e);
+                        }
+                        Task::none()
+                    }
+                }
+            }
+            Message::View(view::Message::SortCoins(sort)) => {
+                self.update_preferences(|p| p.coins.sort = sort);
+                self.panels.current_mut().update(
+                    self.daemon.clone(),
+                    &self.cache,
+                    Message::View(view::Message::SortCoins(sort)),
+                )
+            }
+            Message::View(view::Message::ToggleCoinColumn(column)) => {
+                self.update_preferences(|p| p.coins.columns.toggle(column));
+                self.panels.current_mut().update(
+                    self.daemon.clone(),
+                    &self.cache,
+                    Message::View(view::Message::ToggleCoinColumn(column)),
+                )
+            }
             Message::View(view::Message::Menu(menu)) => self.set_current_panel(menu),
             Message::View(view::Message::Clipboard(text)) => clipboard::write(text),
+            Message::View(view::Message::OpenUrl(url)) => {
+                if let Err(e) = utils::open(url.as_ref()) {
+                    error!("Failed to open {}: {}", url, e);
+                }
+                Task::none()
+            }
             _ => self
                 .panels
                 .current_mut()
@@ -369,9 +798,20 @@ impl App {
         Handle::current().block_on(async { self.daemon.stop().await })?;
         let daemon = EmbeddedDaemon::start(cfg)?;
         self.daemon = Arc::new(daemon);
+        // The identifiers of the events of the new daemon start over.
+        self.last_event_id = None;
+        self.stale_tip = None;
+        self.clock_skew = None;
 
-        let content =
-            toml::to_string(&self.daemon.config()).map_err(|e| Error::Config(e.to_string()))?;
+        let mut file_cfg = self.daemon.config().cloned();
+        // The password of the bitcoind we manage is kept out of the file.
+        if let Some(cfg) = file_cfg.as_mut() {
+            if self.config.start_internal_bitcoind {
+                keychain::store_rpc_password(&keychain::secrets(&self.cache.datadir_path), cfg)
+                    .map_err(|e| Error::Config(e.to_string()))?;
+            }
+        }
+        let content = toml::to_string(&file_cfg).map_err(|e| Error::Config(e.to_string()))?;
 
         OpenOptions::new()
             .write(true)
@@ -386,11 +826,41 @@ impl App {
     }
 
     pub fn view(&self) -> Element<Message> {
-        let content = self.panels.current().view(&self.cache).map(Message::View);
-        if self.cache.network != bitcoin::Network::Bitcoin {
-            Column::with_children(vec![network_banner(self.cache.network).into(), content]).into()
+        let content = if self.lock.is_locked() {
+            view::lock_screen(&self.lock.passphrase, self.lock.checking).map(Message::View)
+        } else if let Some((index, step)) = self.tour.current() {
+            Element::from(Modal::new(
+                self.panels.current().view(&self.cache),
+                view::tour_step(index, step),
+            ))
+            .map(Message::View)
         } else {
+            self.panels.current().view(&self.cache).map(Message::View)
+        };
+        let mut banners = Vec::new();
+        if self.cache.network != bitcoin::Network::Bitcoin {
+            banners.push(network_banner(self.cache.network).into());
+        }
+        if let Some(release) = self.update.as_ref().filter(|_| !self.lock.is_locked()) {
+            banners.push(view::update_banner(release).map(Message::View));
+        }
+        if !self.lock.is_locked() {
+            for warning in self.stale_tip.iter().chain(self.clock_skew.iter()) {
+                banners.push(view::chain_warning_banner(warning).map(Message::View));
+            }
+        }
+        if let Some(error) = self
+            .dropped_file_error
+            .as_ref()
+            .filter(|_| !self.lock.is_locked())
+        {
+            banners.push(view::dropped_file_banner(error).map(Message::View));
+        }
+        if banners.is_empty() {
             content
+        } else {
+            banners.push(content);
+            Column::with_children(banners).into()
         }
     }
 }
@@ -0,0 +1,253 @@
+//! Desktop notifications for the events recorded by the daemon.
+
+use liana::descriptors::SpendingPath;
+use liana_ui::component::amount::{amount_as_string, amounts_hidden, display_unit};
+use lianad::{commands::ReviewDecision, events::Event};
+use notify_rust::Notification;
+
+use crate::{
+    app::config::NotificationsConfig,
+    i18n::{tr, tr_args},
+};
+
+fn is_enabled(config: &NotificationsConfig, event: &Event) -> bool {
+    match event {
+        Event::CoinReceived { .. } => config.deposit_received,
+        Event::SpendConfirmed { .. } => config.spend_confirmed,
+        Event::RecoveryPathSoonAvailable { .. } => config.recovery_soon_available,
+        Event::BackendUnreachable { .. } | Event::BackendReachable => config.backend_down,
+        Event::UnauthorizedSpend { .. } => config.unauthorized_spend,
+        Event::ScheduledSpendBroadcast { .. } | Event::ScheduledSpendConflicted { .. } => {
+            config.scheduled_spend
+        }
+        Event::BackendsDisagree { .. } | Event::BackendsAgree => config.backends_disagree,
+        Event::StaleTip { .. } | Event::TipAdvanced { .. } => config.stale_tip,
+        Event::ClockSkewed { .. } | Event::ClockSynced => config.clock_skew,
+        // The user signing a Spend from this app needs no notification about it.
+        Event::SpendSigned { .. } => false,
+        // Nor about reviewing one, unless a co-signer didn't approve it.
+        Event::SpendReviewed { decision, .. } => *decision != ReviewDecision::Approved,
+        // Nor about the changes made from this app, which are only listed in the activity feed.
+        Event::SpendSaved { .. } | Event::SpendBroadcast { .. } | Event::SettingsChanged { .. } => {
+            false
+        }
+        Event::RescanCompleted { .. } => true,
+    }
+}
+
+/// The title and body of the notification for this event, in the language of the interface.
+pub(crate) fn content(event: &Event) -> (String, String) {
+    let (title, body) = match event {
+        // Notifications outlive the window and may show on the lock screen: don't disclose the
+        // amount in privacy mode.
+        Event::CoinReceived { amount, .. } => (
+            tr("Payment received"),
+            if amounts_hidden() {
+                tr("You received a payment.").to_string()
+            } else {
+                tr_args(
+                    "You received {amount} {unit}.",
+                    &[
+                        ("amount", &amount_as_string(*amount)),
+                        ("unit", &display_unit()),
+                    ],
+                )
+            },
+        ),
+        Event::SpendConfirmed { txid, .. } => (
+            tr("Payment confirmed"),
+            tr_args("Transaction {txid} was confirmed.", &[("txid", txid)]),
+        ),
+        Event::RecoveryPathSoonAvailable {
+            coins,
+            blocks_remaining,
+            ..
+        } => (
+            tr("Recovery path soon available"),
+            tr_args(
+                "The recovery path of {count} coin(s) will be available in {blocks} block(s). \
+                 Refresh them to keep the recovery path timelocked.",
+                &[("count", &coins.len()), ("blocks", blocks_remaining)],
+            ),
+        ),
+        Event::BackendUnreachable { error } => (
+            tr("Bitcoin backend unreachable"),
+            tr_args("The wallet can't be updated: {error}", &[("error", error)]),
+        ),
+        Event::BackendReachable => (
+            tr("Bitcoin backend reachable"),
+            tr("The wallet is being updated again.").to_string(),
+        ),
+        Event::UnauthorizedSpend {
+            coins,
+            spending_path,
+            ..
+        } => (
+            tr("Unexpected spend of your coins"),
+            match spending_path {
+                Some(SpendingPath::Primary) => tr_args(
+                    "{count} coin(s) were spent by a transaction not created by this wallet, \
+                     signed with the primary keys.",
+                    &[("count", &coins.len())],
+                ),
+                Some(SpendingPath::Recovery(timelock)) => tr_args(
+                    "{count} coin(s) were spent by a transaction not created by this wallet, \
+                     signed with the keys of the recovery path after {blocks} blocks.",
+                    &[("count", &coins.len()), ("blocks", timelock)],
+                ),
+                None => tr_args(
+                    "{count} coin(s) were spent by a transaction not created by this wallet.",
+                    &[("count", &coins.len())],
+                ),
+            },
+        ),
+        Event::ScheduledSpendBroadcast { txid } => (
+            tr("Scheduled payment sent"),
+            tr_args(
+                "Scheduled transaction {txid} was broadcast.",
+                &[("txid", txid)],
+            ),
+        ),
+        Event::ScheduledSpendConflicted { txid, coins } => (
+            tr("Scheduled payment cancelled"),
+            tr_args(
+                "Scheduled transaction {txid} won't be broadcast: {count} of its coin(s) were \
+                 spent by another transaction.",
+                &[("txid", txid), ("count", &coins.len())],
+            ),
+        ),
+        Event::BackendsDisagree { disagreements } => (
+            tr("Bitcoin backends disagree"),
+            tr_args(
+                "Your Bitcoin backend may be lying or isolated from the network: {reasons}.",
+                &[("reasons", &disagreements.join(", "))],
+            ),
+        ),
+        Event::BackendsAgree => (
+            tr("Bitcoin backends agree"),
+            tr("Your Bitcoin backend agrees with the cross-checking one again.").to_string(),
+        ),
+        Event::SpendSigned { txid, signers } => (
+            tr("Payment signed"),
+            tr_args(
+                "Transaction {txid} was signed by {count} signer(s).",
+                &[("txid", txid), ("count", &signers.len())],
+            ),
+        ),
+        Event::SpendReviewed {
+            txid,
+            signer,
+            decision,
+        } => (
+            tr("Payment reviewed"),
+            tr_args(
+                match decision {
+                    ReviewDecision::Approved => "Signer {signer} approved transaction {txid}.",
+                    ReviewDecision::ChangesRequested => {
+                        "Signer {signer} requested changes to transaction {txid}."
+                    }
+                    ReviewDecision::Rejected => "Signer {signer} rejected transaction {txid}.",
+                },
+                &[("signer", signer), ("txid", txid)],
+            ),
+        ),
+        Event::SpendSaved { txid } => (
+            tr("Payment drafted"),
+            tr_args("Transaction {txid} was saved.", &[("txid", txid)]),
+        ),
+        Event::SpendBroadcast { txid } => (
+            tr("Payment sent"),
+            tr_args("Transaction {txid} was broadcast.", &[("txid", txid)]),
+        ),
+        Event::RescanCompleted { .. } => (
+            tr("Rescan completed"),
+            tr("The blockchain rescan is over, the wallet is up to date.").to_string(),
+        ),
+        // The description of the change comes from the daemon.
+        Event::SettingsChanged { change } => (tr("Settings changed"), change.clone()),
+        Event::StaleTip { height, minutes } => (
+            tr("No new block"),
+            tr_args(
+                "No new block for {minutes} minutes, the tip is still at height {height}. Your \
+                 Bitcoin backend may be stuck or isolated from the network: the timelocks \
+                 displayed may be wrong.",
+                &[("minutes", minutes), ("height", height)],
+            ),
+        ),
+        Event::TipAdvanced { height } => (
+            tr("New block"),
+            tr_args(
+                "The tip of the chain advanced to height {height}.",
+                &[("height", height)],
+            ),
+        ),
+        Event::ClockSkewed { skew_secs } => (
+            tr("System clock is off"),
+            tr_args(
+                if *skew_secs > 0 {
+                    "Your system clock is {minutes} minutes ahead of the time of the latest \
+                     block. The dates displayed may be wrong."
+                } else {
+                    "Your system clock is {minutes} minutes behind the time of the latest \
+                     block. The dates displayed may be wrong."
+                },
+                &[("minutes", &(skew_secs.unsigned_abs() / 60))],
+            ),
+        ),
+        Event::ClockSynced => (
+            tr("System clock is on time"),
+            tr("Your system clock agrees with the time of the latest block again.").to_string(),
+        ),
+    };
+    (title.to_string(), body)
+}
+
+/// Show a desktop notification for this event if its category is enabled.
+pub fn notify(config: &NotificationsConfig, event: &Event) {
+    if !is_enabled(config, event) {
+        return;
+    }
+    let (summary, body) = content(event);
+    let mut notification = Notification::new();
+    notification.appname("Liana").summary(&summary).body(&body);
+    // Only the XDG notification servers support an urgency.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    notification.urgency(match event.level() {
+        lianad::events::EventLevel::Info => notify_rust::Urgency::Low,
+        lianad::events::EventLevel::Warning => notify_rust::Urgency::Normal,
+        lianad::events::EventLevel::Critical => notify_rust::Urgency::Critical,
+    });
+    // Showing a notification may block on the notification server, don't block the GUI.
+    std::thread::spawn(move || {
+        if let Err(e) = notification.show() {
+            tracing::warn!("Failed to show desktop notification: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use liana::miniscript::bitcoin::{hashes::Hash, Amount, OutPoint, Txid};
+    use liana_ui::component::amount::set_amounts_hidden;
+
+    #[test]
+    fn notification_categories() {
+        let deposit = Event::CoinReceived {
+            outpoint: OutPoint::new(Txid::all_zeros(), 0),
+            amount: Amount::from_sat(150_000),
+        };
+        let mut config = NotificationsConfig::default();
+        assert!(is_enabled(&config, &deposit));
+        config.deposit_received = false;
+        assert!(!is_enabled(&config, &deposit));
+        assert!(is_enabled(&config, &Event::BackendReachable));
+        assert_eq!(content(&deposit).1, "You received 0.00 150 000 BTC.");
+        set_amounts_hidden(true);
+        assert_eq!(content(&deposit).1, "You received a payment.");
+        set_amounts_hidden(false);
+        assert!(content(&Event::ClockSkewed { skew_secs: -5_400 })
+            .1
+            .starts_with("Your system clock is 90 minutes behind the time of the latest block."));
+    }
+}
@@ -0,0 +1,264 @@
+//! The preferences of the GUI for a wallet: the state the window and the views were left in, to
+//! restore them the next time the wallet is opened.
+//!
+//! They are stored along with the other settings of the wallet, see the `settings` module, and
+//! changed through [`update`] so that all of them are persisted the same way.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::path::PathBuf;
+
+use liana::miniscript::bitcoin::Network;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::{
+        menu::Menu,
+        settings::{Settings, SettingsError},
+    },
+    daemon::model::Coin,
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Preferences {
+    /// The window of the wallet when it was last closed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<WindowGeometry>,
+    /// The view the wallet was last closed on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_view: Option<View>,
+    #[serde(default)]
+    pub coins: CoinsPreferences,
+}
+
+impl Preferences {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// The size and the position of the window, in logical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct WindowGeometry {
+    pub width: f32,
+    pub height: f32,
+    /// Not reported on every platform.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<[f32; 2]>,
+}
+
+/// The views of the wallet which can be opened again at startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum View {
+    Home,
+    Send,
+    Receive,
+    Coins,
+    Transactions,
+    Psbts,
+    Activity,
+    Recovery,
+    Settings,
+}
+
+impl View {
+    /// The view a menu opens. The transaction or PSBT a menu preselects is not kept.
+    pub fn from_menu(menu: &Menu) -> Self {
+        match menu {
+            Menu::Home => Self::Home,
+            Menu::CreateSpendTx => Self::Send,
+            Menu::Receive => Self::Receive,
+            Menu::Coins | Menu::RefreshCoins(_) => Self::Coins,
+            Menu::Transactions | Menu::TransactionPreSelected(_) => Self::Transactions,
+            Menu::PSBTs | Menu::PsbtPreSelected(_) => Self::Psbts,
+            Menu::Activity => Self::Activity,
+            Menu::Recovery => Self::Recovery,
+            Menu::Settings => Self::Settings,
+        }
+    }
+
+    pub fn menu(self) -> Menu {
+        match self {
+            Self::Home => Menu::Home,
+            Self::Send => Menu::CreateSpendTx,
+            Self::Receive => Menu::Receive,
+            Self::Coins => Menu::Coins,
+            Self::Transactions => Menu::Transactions,
+            Self::Psbts => Menu::PSBTs,
+            Self::Activity => Menu::Activity,
+            Self::Recovery => Menu::Recovery,
+            Self::Settings => Menu::Settings,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CoinsPreferences {
+    #[serde(default)]
+    pub sort: CoinsSort,
+    #[serde(default)]
+    pub columns: CoinsColumns,
+}
+
+/// The order of the list of coins.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CoinsSort {
+    /// The coins confirmed first at the top, the unconfirmed ones at the bottom.
+    #[default]
+    Oldest,
+    Newest,
+    Largest,
+    Smallest,
+}
+
+impl CoinsSort {
+    pub const ALL: [CoinsSort; 4] = [Self::Oldest, Self::Newest, Self::Largest, Self::Smallest];
+
+    pub fn compare(&self, a: &Coin, b: &Coin) -> Ordering {
+        match self {
+            Self::Oldest => by_age(a, b),
+            Self::Newest => by_age(b, a),
+            Self::Largest => b.amount.cmp(&a.amount).then_with(|| by_age(a, b)),
+            Self::Smallest => a.amount.cmp(&b.amount).then_with(|| by_age(a, b)),
+        }
+    }
+}
+
+fn by_age(a: &Coin, b: &Coin) -> Ordering {
+    match (a.block_height, b.block_height) {
+        (Some(a_height), Some(b_height)) => a_height
+            .cmp(&b_height)
+            .then_with(|| a.outpoint.vout.cmp(&b.outpoint.vout)),
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (None, None) => a.outpoint.vout.cmp(&b.outpoint.vout),
+    }
+}
+
+impl fmt::Display for CoinsSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Oldest => write!(f, "Oldest first"),
+            Self::Newest => write!(f, "Newest first"),
+            Self::Largest => write!(f, "Largest first"),
+            Self::Smallest => write!(f, "Smallest first"),
+        }
+    }
+}
+
+/// The optional columns of the list of coins, along with the label and the amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoinColumn {
+    /// Whether the coin is unconfirmed, or the blocks left before its recovery path is available.
+    Status,
+    Address,
+    BlockHeight,
+}
+
+impl CoinColumn {
+    pub const ALL: [CoinColumn; 3] = [Self::Status, Self::Address, Self::BlockHeight];
+}
+
+impl fmt::Display for CoinColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Status => write!(f, "Status"),
+            Self::Address => write!(f, "Address"),
+            Self::BlockHeight => write!(f, "Block height"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CoinsColumns {
+    pub status: bool,
+    pub address: bool,
+    pub block_height: bool,
+}
+
+impl Default for CoinsColumns {
+    fn default() -> Self {
+        Self {
+            status: true,
+            address: false,
+            block_height: false,
+        }
+    }
+}
+
+impl CoinsColumns {
+    pub fn shows(&self, column: CoinColumn) -> bool {
+        match column {
+            CoinColumn::Status => self.status,
+            CoinColumn::Address => self.address,
+            CoinColumn::BlockHeight => self.block_height,
+        }
+    }
+
+    pub fn toggle(&mut self, column: CoinColumn) {
+        let shown = match column {
+            CoinColumn::Status => &mut self.status,
+            CoinColumn::Address => &mut self.address,
+            CoinColumn::BlockHeight => &mut self.block_height,
+        };
+        *shown = !*shown;
+    }
+}
+
+/// Change the preferences of the wallet with this descriptor checksum in its settings file.
+pub fn update(
+    datadir: PathBuf,
+    network: Network,
+    descriptor_checksum: &str,
+    f: impl FnOnce(&mut Preferences),
+) -> Result<(), SettingsError> {
+    let mut settings = Settings::from_file(datadir.clone(), network)?;
+    if let Some(wallet) = settings
+        .wallets
+        .iter_mut()
+        .find(|w| w.descriptor_checksum == descriptor_checksum)
+    {
+        f(&mut wallet.preferences);
+        settings.to_file(datadir, network)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preferences_serde() {
+        // The settings written before the preferences, or without any set.
+        let prefs: Preferences = serde_json::from_str("{}").unwrap();
+        assert!(prefs.is_default());
+        assert!(prefs.coins.columns.shows(CoinColumn::Status));
+        assert!(!prefs.coins.columns.shows(CoinColumn::Address));
+
+        let mut prefs = Preferences {
+            window: Some(WindowGeometry {
+                width: 1200.0,
+                height: 800.0,
+                position: None,
+            }),
+            last_view: Some(View::from_menu(&Menu::PSBTs)),
+            coins: CoinsPreferences::default(),
+        };
+        prefs.coins.sort = CoinsSort::Largest;
+        prefs.coins.columns.toggle(CoinColumn::Status);
+        prefs.coins.columns.toggle(CoinColumn::BlockHeight);
+        let json = serde_json::to_string(&prefs).unwrap();
+        assert_eq!(
+            json,
+            "{\"window\":{\"width\":1200.0,\"height\":800.0},\"last_view\":\"psbts\",\
+             \"coins\":{\"sort\":\"largest\",\"columns\":{\"status\":false,\"address\":false,\
+             \"block_height\":true}}}"
+        );
+        assert_eq!(serde_json::from_str::<Preferences>(&json).unwrap(), prefs);
+        assert_eq!(prefs.last_view.unwrap().menu(), Menu::PSBTs);
+    }
+}
@@ -3,12 +3,19 @@
 use std::collections::HashMap;
 use std::fs::OpenOptions;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use liana::miniscript::bitcoin::{bip32::Fingerprint, Network};
+use liana::miniscript::bitcoin::{
+    bip32::Fingerprint,
+    hashes::{sha256, Hash},
+    Amount, Network, Txid,
+};
 use serde::{Deserialize, Serialize};
 
-use crate::hw::HardwareWalletConfig;
+use crate::{
+    app::preferences::Preferences, hw::HardwareWalletConfig, lianalite::session,
+    remote_signer::RemoteSigner,
+};
 
 pub const DEFAULT_FILE_NAME: &str = "settings.json";
 
@@ -57,15 +64,73 @@ impl Settings {
             SettingsError::WritingFile(e.to_string())
         })
     }
+
+    /// The authentication of the first wallet of each remote backend account the user signed in
+    /// with, in the order of the wallets.
+    pub fn remote_backend_accounts(&self) -> Vec<AuthConfig> {
+        let mut accounts: Vec<AuthConfig> = Vec::new();
+        for auth in self
+            .wallets
+            .iter()
+            .filter_map(|w| w.remote_backend_auth.as_ref())
+        {
+            if !accounts.iter().any(|a| a.email == auth.email) {
+                accounts.push(auth.clone());
+            }
+        }
+        accounts
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AuthConfig {
     pub email: String,
     pub wallet_id: String,
+    /// The refresh token is kept in the secrets store, see the `keychain` module. It is only set
+    /// if it couldn't be stored there, or if it was written by a previous version.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub refresh_token: String,
 }
 
+impl AuthConfig {
+    /// Store the refresh token of the session in the secrets store, or in the returned
+    /// configuration if it can't be.
+    pub fn new(
+        datadir: &Path,
+        network: Network,
+        email: String,
+        wallet_id: String,
+        refresh_token: String,
+    ) -> Self {
+        let refresh_token =
+            match session::store_refresh_token(datadir, network, &email, &refresh_token) {
+                Ok(()) => String::new(),
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to store the session, keeping it in the settings file: {}",
+                        e
+                    );
+                    refresh_token
+                }
+            };
+        Self {
+            email,
+            wallet_id,
+            refresh_token,
+        }
+    }
+
+    /// The refresh token of the session, wherever it is stored.
+    pub fn refresh_token(&self, datadir: &Path, network: Network) -> Option<String> {
+        session::refresh_token(datadir, network, &self.email)
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to read the session: {}", e);
+                None
+            })
+            .or_else(|| Some(self.refresh_token.clone()).filter(|t| !t.is_empty()))
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct WalletSetting {
     pub name: String,
@@ -79,6 +144,27 @@ pub struct WalletSetting {
     #[serde(default)]
     pub hardware_wallets: Vec<HardwareWalletConfig>,
     pub remote_backend_auth: Option<AuthConfig>,
+    /// The balance of the wallet in satoshis when it was last opened, shown in the launcher.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_balance: Option<u64>,
+    /// The color used to tell this wallet apart from the others.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accent_color: Option<AccentColor>,
+    /// The confirmation asked before broadcasting the large spends of this wallet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spend_confirmation: Option<SpendConfirmation>,
+    /// The block explorer the links to transactions and addresses of this wallet open.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_explorer: Option<BlockExplorer>,
+    /// The hot signer of a lianad on another machine this wallet can sign with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_signer: Option<RemoteSigner>,
+    /// Whether the amounts are masked when opening this wallet.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hide_amounts: bool,
+    /// The state the GUI was left in for this wallet.
+    #[serde(default, skip_serializing_if = "Preferences::is_default")]
+    pub preferences: Preferences,
 }
 
 impl WalletSetting {
@@ -97,6 +183,117 @@ pub struct KeySetting {
     pub master_fingerprint: Fingerprint,
 }
 
+/// The accent color of a wallet, shown in its header and when sending from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccentColor {
+    #[default]
+    Green,
+    Blue,
+    Orange,
+    Red,
+}
+
+impl AccentColor {
+    pub const ALL: [AccentColor; 4] = [Self::Green, Self::Blue, Self::Orange, Self::Red];
+}
+
+impl std::fmt::Display for AccentColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Green => write!(f, "Green"),
+            Self::Blue => write!(f, "Blue"),
+            Self::Orange => write!(f, "Orange"),
+            Self::Red => write!(f, "Red"),
+        }
+    }
+}
+
+/// The block explorer used to look up the transactions and addresses of a wallet. A public
+/// explorer learns which transactions and addresses are looked up together, and from which IP
+/// address: an instance run by the user on their own node doesn't leak anything.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockExplorer {
+    #[default]
+    MempoolSpace,
+    /// The base URL of a mempool or esplora instance, such as `http://umbrel.local:3006`.
+    SelfHosted(String),
+    /// Don't show links to a block explorer.
+    None,
+}
+
+impl BlockExplorer {
+    /// Whether looking up a transaction on this explorer tells a third party about it.
+    pub fn is_public(&self) -> bool {
+        matches!(self, Self::MempoolSpace)
+    }
+
+    fn base_url(&self, network: Network) -> Option<String> {
+        match self {
+            Self::MempoolSpace => match network {
+                Network::Bitcoin => Some("https://mempool.space"),
+                Network::Testnet => Some("https://mempool.space/testnet"),
+                Network::Signet => Some("https://mempool.space/signet"),
+                _ => None,
+            }
+            .map(|url| url.to_string()),
+            Self::SelfHosted(url) => Some(url.trim_end_matches('/').to_string()),
+            Self::None => None,
+        }
+    }
+
+    /// The URL to which a txid is appended to link to the transaction.
+    pub fn tx_url_prefix(&self, network: Network) -> Option<String> {
+        self.base_url(network).map(|url| format!("{}/tx/", url))
+    }
+
+    pub fn tx_url(&self, network: Network, txid: &Txid) -> Option<String> {
+        self.tx_url_prefix(network)
+            .map(|prefix| format!("{}{}", prefix, txid))
+    }
+}
+
+/// Spends sending more than the threshold to others must be confirmed by retyping the amount sent,
+/// or the PIN if one is set, before being broadcast. This guards against mistakes, not against
+/// someone with access to the computer: the PIN is only stored hashed but is short enough to be
+/// guessed from its hash.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SpendConfirmation {
+    /// In satoshis.
+    pub threshold: u64,
+    /// The hex-encoded SHA256 of the PIN.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin_sha256: Option<String>,
+}
+
+impl SpendConfirmation {
+    pub fn new(threshold: Amount, pin: Option<&str>) -> Self {
+        Self {
+            threshold: threshold.to_sat(),
+            pin_sha256: pin.map(pin_hash),
+        }
+    }
+
+    /// Whether a spend sending this amount to others must be confirmed.
+    pub fn is_required(&self, sent: Amount) -> bool {
+        sent.to_sat() > self.threshold
+    }
+
+    /// Whether the user input confirms the spend of this amount: the PIN if one is set, else the
+    /// amount in the display unit.
+    pub fn is_confirmed_by(&self, sent: Amount, input: &str) -> bool {
+        match &self.pin_sha256 {
+            Some(hash) => pin_hash(input.trim()) == *hash,
+            None => liana_ui::component::amount::parse_amount(input).is_ok_and(|a| a == sent),
+        }
+    }
+}
+
+fn pin_hash(pin: &str) -> String {
+    sha256::Hash::hash(pin.as_bytes()).to_string()
+}
+
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub enum SettingsError {
     NotFound,
@@ -119,15 +316,37 @@ impl std::fmt::Display for SettingsError {
 /// global settings.
 pub mod global {
     use async_hwi::bitbox::{ConfigError, NoiseConfig, NoiseConfigData};
-    use serde::{Deserialize, Serialize};
+    use liana::{
+        miniscript::bitcoin::hashes::{hmac, sha256, Hash, HashEngine},
+        random::random_bytes,
+    };
+    use liana_ui::component::amount::{
+        set_display_unit, set_number_format, BitcoinDisplayUnit, NumberFormat,
+    };
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
     use std::io::{Read, Write};
     use std::path::{Path, PathBuf};
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicU16, Ordering};
+
+    use crate::{
+        clipboard::set_clear_after,
+        i18n::{set_language, Language},
+    };
 
     pub const DEFAULT_FILE_NAME: &str = "global_settings.json";
 
     #[derive(Debug, Deserialize, Serialize)]
     pub struct Settings {
         pub bitbox: Option<BitboxSettings>,
+        pub display: Option<DisplaySettings>,
+        #[serde(default)]
+        pub remote_backend: Option<RemoteBackendSettings>,
+        #[serde(default)]
+        pub lock: Option<LockSettings>,
+        /// Whether the guided tour was completed or dismissed, not to show it again at startup.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        pub tour_done: bool,
     }
 
     #[derive(Debug, Deserialize, Serialize)]
@@ -135,6 +354,303 @@ pub mod global {
         pub noise_config: NoiseConfigData,
     }
 
+    /// How amounts are displayed and entered across the application.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+    pub struct DisplaySettings {
+        #[serde(
+            default,
+            deserialize_with = "deserialize_fromstr",
+            serialize_with = "serialize_to_string"
+        )]
+        pub unit: BitcoinDisplayUnit,
+        /// How the digits of the amounts are grouped. Follows the system locale if not set.
+        #[serde(
+            default,
+            deserialize_with = "deserialize_option_fromstr",
+            serialize_with = "serialize_option_to_string"
+        )]
+        pub number_format: Option<NumberFormat>,
+        /// The language of the user interface. Follows the system locale if not set.
+        #[serde(default)]
+        pub language: Option<Language>,
+        /// The scale of the user interface in percents, on top of the scaling of the display
+        /// reported by the system. Only follows the latter if not set.
+        #[serde(default)]
+        pub ui_scale: Option<u16>,
+        /// The delay in seconds after which what the app copied is cleared from the clipboard.
+        /// Never cleared if not set.
+        #[serde(default)]
+        pub clipboard_clear_after: Option<u16>,
+    }
+
+    /// How to reach the remote backend, for instance to use a self-hosted instance.
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+    pub struct RemoteBackendSettings {
+        /// URL of the backend API to use instead of the hosted service.
+        #[serde(default)]
+        pub url: Option<String>,
+        /// Hex-encoded SHA256 hashes of the DER-encoded certificates, or of their public key
+        /// (SubjectPublicKeyInfo), the servers must present. If not empty, connections to servers
+        /// presenting none of them are refused.
+        #[serde(default)]
+        pub pinned_sha256: Vec<String>,
+        /// Hex-encoded BIP340 public key the manifest of the backend must be signed with. If not
+        /// set, the service configuration isn't authenticated beyond TLS.
+        #[serde(default)]
+        pub manifest_public_key: Option<String>,
+    }
+
+    impl RemoteBackendSettings {
+        /// Read the remote backend settings from the global settings file, if any.
+        pub fn from_file(global_datadir: &Path) -> Result<Self, String> {
+            let path = global_datadir.join(DEFAULT_FILE_NAME);
+            if !path.exists() {
+                return Ok(Self::default());
+            }
+            let content = std::fs::read(&path).map_err(|e| e.to_string())?;
+            let settings =
+                serde_json::from_slice::<Settings>(&content).map_err(|e| e.to_string())?;
+            Ok(settings.remote_backend.unwrap_or_default())
+        }
+    }
+
+    /// The number of iterations of PBKDF2 to hash the lock passphrase with.
+    const PASSPHRASE_ITERATIONS: u32 = 100_000;
+
+    /// The passphrase to type to show the wallet again once the application is locked. This hides
+    /// the wallet from someone passing by the computer, it doesn't encrypt anything.
+    #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+    pub struct LockSettings {
+        /// Hex-encoded random salt of the passphrase hash.
+        pub salt: String,
+        /// Hex-encoded PBKDF2-HMAC-SHA256 of the passphrase.
+        pub passphrase_hash: String,
+        /// The minutes of inactivity after which the application is locked. Only locked on
+        /// demand if not set.
+        #[serde(default)]
+        pub after: Option<u16>,
+    }
+
+    impl LockSettings {
+        pub fn new(passphrase: &str, after: Option<u16>) -> Result<Self, String> {
+            let salt = random_bytes().map_err(|e| e.to_string())?[..16].to_vec();
+            Ok(Self {
+                passphrase_hash: hex::encode(pbkdf2_sha256(
+                    passphrase.as_bytes(),
+                    &salt,
+                    PASSPHRASE_ITERATIONS,
+                )),
+                salt: hex::encode(salt),
+                after,
+            })
+        }
+
+        /// Whether the input is the passphrase. Takes a little while, by design.
+        pub fn is_passphrase(&self, input: &str) -> bool {
+            hex::decode(&self.salt).is_ok_and(|salt| {
+                hex::encode(pbkdf2_sha256(
+                    input.as_bytes(),
+                    &salt,
+                    PASSPHRASE_ITERATIONS,
+                )) == self.passphrase_hash
+            })
+        }
+
+        /// Read the lock settings from the global settings file, if the lock was set.
+        pub fn from_file(global_datadir: &Path) -> Result<Option<Self>, String> {
+            let path = global_datadir.join(DEFAULT_FILE_NAME);
+            if !path.exists() {
+                return Ok(None);
+            }
+            let content = std::fs::read(&path).map_err(|e| e.to_string())?;
+            let settings =
+                serde_json::from_slice::<Settings>(&content).map_err(|e| e.to_string())?;
+            Ok(settings.lock)
+        }
+
+        /// Persist the lock settings to the global settings file, or remove them, leaving the
+        /// other settings untouched.
+        pub fn to_file(lock: Option<&Self>, global_datadir: &Path) -> Result<(), String> {
+            let path = global_datadir.join(DEFAULT_FILE_NAME);
+            let mut settings = if path.exists() {
+                let content = std::fs::read(&path).map_err(|e| e.to_string())?;
+                serde_json::from_slice::<Settings>(&content).map_err(|e| e.to_string())?
+            } else {
+                Settings {
+                    bitbox: None,
+                    display: None,
+                    remote_backend: None,
+                    lock: None,
+                    tour_done: false,
+                }
+            };
+            settings.lock = lock.cloned();
+            let content = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+            std::fs::write(&path, content.as_bytes()).map_err(|e| e.to_string())
+        }
+    }
+
+    /// Whether the guided tour was completed or dismissed.
+    pub fn tour_done(global_datadir: &Path) -> Result<bool, String> {
+        let path = global_datadir.join(DEFAULT_FILE_NAME);
+        if !path.exists() {
+            return Ok(false);
+        }
+        let content = std::fs::read(&path).map_err(|e| e.to_string())?;
+        let settings = serde_json::from_slice::<Settings>(&content).map_err(|e| e.to_string())?;
+        Ok(settings.tour_done)
+    }
+
+    /// Record in the global settings file the guided tour was completed or dismissed, leaving the
+    /// other settings untouched.
+    pub fn set_tour_done(global_datadir: &Path) -> Result<(), String> {
+        let path = global_datadir.join(DEFAULT_FILE_NAME);
+        let mut settings = if path.exists() {
+            let content = std::fs::read(&path).map_err(|e| e.to_string())?;
+            serde_json::from_slice::<Settings>(&content).map_err(|e| e.to_string())?
+        } else {
+            Settings {
+                bitbox: None,
+                display: None,
+                remote_backend: None,
+                lock: None,
+                tour_done: false,
+            }
+        };
+        settings.tour_done = true;
+        let content = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+        std::fs::write(&path, content.as_bytes()).map_err(|e| e.to_string())
+    }
+
+    fn hmac_sha256(key: &[u8], data: &[&[u8]]) -> [u8; 32] {
+        let mut engine = hmac::HmacEngine::<sha256::Hash>::new(key);
+        for d in data {
+            engine.input(d);
+        }
+        hmac::Hmac::<sha256::Hash>::from_engine(engine).to_byte_array()
+    }
+
+    // PBKDF2 with a single block of output, as long as the hash.
+    fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32) -> [u8; 32] {
+        let mut u = hmac_sha256(password, &[salt, &1u32.to_be_bytes()]);
+        let mut block = u;
+        for _ in 1..iterations {
+            u = hmac_sha256(password, &[&u]);
+            block.iter_mut().zip(u.iter()).for_each(|(b, u)| *b ^= u);
+        }
+        block
+    }
+
+    /// The scales of the user interface which can be chosen, in percents.
+    pub const UI_SCALES: [u16; 6] = [75, 100, 125, 150, 175, 200];
+
+    // In percents, 0 to follow the scaling of the display.
+    static UI_SCALE: AtomicU16 = AtomicU16::new(0);
+
+    /// Set the scale of the user interface, in percents. Takes effect on the next rendering.
+    pub fn set_ui_scale(scale: Option<u16>) {
+        let scale = scale.map(|s| s.clamp(UI_SCALES[0], UI_SCALES[UI_SCALES.len() - 1]));
+        UI_SCALE.store(scale.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// The factor to apply to the scaling of the display reported by the system.
+    pub fn ui_scale_factor() -> f64 {
+        match UI_SCALE.load(Ordering::Relaxed) {
+            0 => 1.0,
+            scale => f64::from(scale) / 100.0,
+        }
+    }
+
+    impl DisplaySettings {
+        /// Read the display settings from the global settings file, if any.
+        pub fn from_file(global_datadir: &Path) -> Result<Self, String> {
+            let path = global_datadir.join(DEFAULT_FILE_NAME);
+            if !path.exists() {
+                return Ok(Self::default());
+            }
+            let content = std::fs::read(&path).map_err(|e| e.to_string())?;
+            let settings =
+                serde_json::from_slice::<Settings>(&content).map_err(|e| e.to_string())?;
+            Ok(settings.display.unwrap_or_default())
+        }
+
+        /// Persist the display settings to the global settings file, leaving the other
+        /// settings untouched.
+        pub fn to_file(&self, global_datadir: &Path) -> Result<(), String> {
+            let path = global_datadir.join(DEFAULT_FILE_NAME);
+            let mut settings = if path.exists() {
+                let content = std::fs::read(&path).map_err(|e| e.to_string())?;
+                serde_json::from_slice::<Settings>(&content).map_err(|e| e.to_string())?
+            } else {
+                Settings {
+                    bitbox: None,
+                    display: None,
+                    remote_backend: None,
+                    lock: None,
+                    tour_done: false,
+                }
+            };
+            settings.display = Some(*self);
+            let content = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+            std::fs::write(&path, content.as_bytes()).map_err(|e| e.to_string())
+        }
+
+        /// Make all the views use these settings.
+        pub fn apply(&self) {
+            set_display_unit(self.unit);
+            set_number_format(
+                self.number_format
+                    .or_else(NumberFormat::from_system_locale)
+                    .unwrap_or_default(),
+            );
+            set_language(
+                self.language
+                    .or_else(Language::from_system_locale)
+                    .unwrap_or_default(),
+            );
+            set_ui_scale(self.ui_scale);
+            set_clear_after(self.clipboard_clear_after);
+        }
+    }
+
+    fn deserialize_fromstr<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: FromStr,
+        <T as FromStr>::Err: std::fmt::Display,
+    {
+        let string = String::deserialize(deserializer)?;
+        T::from_str(&string).map_err(serde::de::Error::custom)
+    }
+
+    fn serialize_to_string<T: std::fmt::Display, S: Serializer>(
+        field: T,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&field.to_string())
+    }
+
+    fn deserialize_option_fromstr<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: FromStr,
+        <T as FromStr>::Err: std::fmt::Display,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|string| T::from_str(&string).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+
+    fn serialize_option_to_string<T: std::fmt::Display, S: Serializer>(
+        field: &Option<T>,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        match field {
+            Some(field) => s.serialize_some(&field.to_string()),
+            None => s.serialize_none(),
+        }
+    }
+
     pub struct PersistedBitboxNoiseConfig {
         file_path: PathBuf,
     }
@@ -195,6 +711,10 @@ pub mod global {
                     bitbox: Some(BitboxSettings {
                         noise_config: conf.clone(),
                     }),
+                    display: None,
+                    remote_backend: None,
+                    lock: None,
+                    tour_done: false,
                 })
                 .map_err(|e| ConfigError(e.to_string()))?
             };
@@ -210,4 +730,44 @@ pub mod global {
                 .map_err(|e| ConfigError(e.to_string()))
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn lock_passphrase() {
+            // Known test vectors of PBKDF2-HMAC-SHA256.
+            assert_eq!(
+                hex::encode(pbkdf2_sha256(b"password", b"salt", 1)),
+                "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"
+            );
+            assert_eq!(
+                hex::encode(pbkdf2_sha256(b"password", b"salt", 4096)),
+                "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a"
+            );
+
+            let lock = LockSettings::new("correct horse", Some(5)).unwrap();
+            assert!(lock.is_passphrase("correct horse"));
+            assert!(!lock.is_passphrase("correct horse "));
+            assert!(!lock.is_passphrase(""));
+            // The same passphrase isn't hashed the same twice.
+            assert_ne!(LockSettings::new("correct horse", Some(5)).unwrap(), lock);
+        }
+
+        #[test]
+        fn display_settings_number_format() {
+            // Follows the system locale unless one was chosen.
+            let settings: DisplaySettings = serde_json::from_str("{}").unwrap();
+            assert_eq!(settings.number_format, None);
+            let settings: DisplaySettings =
+                serde_json::from_str(r#"{"number_format": "period"}"#).unwrap();
+            assert_eq!(settings.number_format, Some(NumberFormat::Period));
+            assert_eq!(
+                serde_json::from_str::<DisplaySettings>(&serde_json::to_string(&settings).unwrap())
+                    .unwrap(),
+                settings
+            );
+        }
+    }
 }
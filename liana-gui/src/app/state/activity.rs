@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use iced::Task;
+use liana_ui::widget::Element;
+
+use super::State;
+use crate::{
+    app::{cache::Cache, error::Error, menu::Menu, message::Message, view, wallet::Wallet},
+    daemon::{model::EventEntry, Daemon},
+};
+
+/// The feed of the events recorded by the daemon, to follow what happened in a wallet shared
+/// with co-signers.
+pub struct ActivityPanel {
+    wallet: Arc<Wallet>,
+    events: Vec<EventEntry>,
+    filter: view::activity::ActivityFilter,
+    warning: Option<Error>,
+}
+
+impl ActivityPanel {
+    pub fn new(wallet: Arc<Wallet>) -> Self {
+        Self {
+            wallet,
+            events: Vec::new(),
+            filter: view::activity::ActivityFilter::default(),
+            warning: None,
+        }
+    }
+}
+
+impl State for ActivityPanel {
+    fn view<'a>(&'a self, cache: &'a Cache) -> Element<'a, view::Message> {
+        view::dashboard(
+            &Menu::Activity,
+            cache,
+            self.warning.as_ref(),
+            view::activity::activity_view(&self.events, self.filter, &self.wallet.keys_aliases),
+        )
+    }
+
+    fn update(
+        &mut self,
+        daemon: Arc<dyn Daemon + Sync + Send>,
+        _cache: &Cache,
+        message: Message,
+    ) -> Task<Message> {
+        match message {
+            Message::View(view::Message::Reload) => {
+                return self.reload(daemon, self.wallet.clone());
+            }
+            Message::ActivityEvents(res) => match res {
+                Err(e) => self.warning = Some(e),
+                Ok(events) => {
+                    self.warning = None;
+                    self.events = events;
+                }
+            },
+            Message::View(view::Message::FilterActivity(filter)) => {
+                self.filter = filter;
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+
+    fn reload(
+        &mut self,
+        daemon: Arc<dyn Daemon + Sync + Send>,
+        wallet: Arc<Wallet>,
+    ) -> Task<Message> {
+        self.wallet = wallet;
+        Task::perform(
+            async move {
+                daemon
+                    .list_events(None)
+                    .await
+                    .map(|res| res.events)
+                    .map_err(|e| e.into())
+            },
+            Message::ActivityEvents,
+        )
+    }
+}
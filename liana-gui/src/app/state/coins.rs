@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::{cmp::Ordering, collections::HashSet};
+
+use liana::miniscript::bitcoin::OutPoint;
+use std::collections::HashSet;
 
 use iced::Task;
 
@@ -14,6 +16,7 @@ use crate::{
         error::Error,
         menu::Menu,
         message::Message,
+        preferences::CoinsPreferences,
         state::{label::LabelsEdited, State},
         view,
         wallet::Wallet,
@@ -48,20 +51,25 @@ impl Labelled for Coins {
 pub struct CoinsPanel {
     coins: Coins,
     selected: Vec<usize>,
+    /// The coins to refresh together, in a single self-send.
+    to_refresh: Vec<OutPoint>,
     labels_edited: LabelsEdited,
     warning: Option<Error>,
     /// timelock value to pass for the heir to consume a coin.
     timelock: u16,
+    preferences: CoinsPreferences,
 }
 
 impl CoinsPanel {
-    pub fn new(coins: &[Coin], timelock: u16) -> Self {
+    pub fn new(coins: &[Coin], timelock: u16, preferences: CoinsPreferences) -> Self {
         let mut panel = Self {
             labels_edited: LabelsEdited::default(),
             coins: Coins::default(),
             selected: Vec::new(),
+            to_refresh: Vec::new(),
             warning: None,
             timelock,
+            preferences,
         };
         panel.update_coins(coins);
         panel
@@ -74,20 +82,11 @@ impl CoinsPanel {
             .cloned()
             .collect();
 
-        self.coins
-            .list
-            .sort_by(|a, b| match (a.block_height, b.block_height) {
-                (Some(a_height), Some(b_height)) => {
-                    if a_height == b_height {
-                        a.outpoint.vout.cmp(&b.outpoint.vout)
-                    } else {
-                        a_height.cmp(&b_height)
-                    }
-                }
-                (None, Some(_)) => Ordering::Greater,
-                (Some(_), None) => Ordering::Less,
-                (None, None) => a.outpoint.vout.cmp(&b.outpoint.vout),
-            })
+        let sort = self.preferences.sort;
+        self.coins.list.sort_by(|a, b| sort.compare(a, b));
+        let list = &self.coins.list;
+        self.to_refresh
+            .retain(|outpoint| list.iter().any(|c| c.outpoint == *outpoint));
     }
 }
 
@@ -101,7 +100,9 @@ impl State for CoinsPanel {
                 cache,
                 &self.coins.list,
                 self.timelock,
+                &self.preferences,
                 &self.selected,
+                &self.to_refresh,
                 &self.coins.labels,
                 self.labels_edited.cache(),
             ),
@@ -148,6 +149,23 @@ impl State for CoinsPanel {
                     self.selected.push(i);
                 }
             }
+            Message::View(view::Message::SortCoins(sort)) => {
+                self.preferences.sort = sort;
+                // The indexes of the expanded coins would point to other coins once sorted.
+                self.selected = Vec::new();
+                let sort = self.preferences.sort;
+                self.coins.list.sort_by(|a, b| sort.compare(a, b));
+            }
+            Message::View(view::Message::ToggleCoinColumn(column)) => {
+                self.preferences.columns.toggle(column);
+            }
+            Message::View(view::Message::SelectCoinToRefresh(outpoint)) => {
+                if let Some(position) = self.to_refresh.iter().position(|o| *o == outpoint) {
+                    self.to_refresh.remove(position);
+                } else {
+                    self.to_refresh.push(outpoint);
+                }
+            }
             _ => {}
         };
         Task::none()
@@ -207,7 +225,7 @@ mod tests {
 
     #[test]
     fn test_coins_panel_update_coins() {
-        let mut panel = CoinsPanel::new(&[], 0);
+        let mut panel = CoinsPanel::new(&[], 0, CoinsPreferences::default());
         let txid = bitcoin::Txid::from_str(
             "f7bd1b2a995b689d326e51eb742eb1088c4a8f110d9cb56128fd553acc9f88e5",
         )
@@ -224,6 +242,7 @@ mod tests {
                 block_height: Some(3),
                 spend_info: None,
                 is_immature: false,
+                maturity_height: None,
                 address: dummy_address.clone(),
                 derivation_index: 0.into(),
                 is_change: false,
@@ -235,6 +254,7 @@ mod tests {
                 block_height: None,
                 spend_info: None,
                 is_immature: false,
+                maturity_height: None,
                 address: dummy_address.clone(),
                 derivation_index: 1.into(),
                 is_change: false,
@@ -246,6 +266,7 @@ mod tests {
                 block_height: Some(2),
                 spend_info: None,
                 is_immature: false,
+                maturity_height: None,
                 address: dummy_address.clone(),
                 derivation_index: 2.into(),
                 is_change: false,
@@ -257,6 +278,7 @@ mod tests {
                 block_height: Some(3),
                 spend_info: None,
                 is_immature: false,
+                maturity_height: None,
                 address: dummy_address,
                 derivation_index: 3.into(),
                 is_change: false,
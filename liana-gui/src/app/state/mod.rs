@@ -1,3 +1,4 @@
+mod activity;
 mod coins;
 mod export;
 mod label;
@@ -31,9 +32,13 @@ pub const HISTORY_EVENT_PAGE_SIZE: u64 = 20;
 
 use crate::daemon::model::{coin_is_owned, LabelsLoader};
 use crate::daemon::{
-    model::{remaining_sequence, Coin, HistoryTransaction, Payment},
-    Daemon,
+    model::{
+        remaining_sequence, Coin, GetMempoolStatsResult, GetRecoveryScheduleResult,
+        HistoryTransaction, Payment, RecoveryReadiness,
+    },
+    Daemon, DaemonError,
 };
+pub use activity::ActivityPanel;
 pub use coins::CoinsPanel;
 use label::LabelsEdited;
 pub use psbts::PsbtsPanel;
@@ -81,7 +86,8 @@ pub fn redirect(menu: Menu) -> Task<Message> {
 ///   all confirmed coins, if any.
 ///
 /// The confirmed balance includes the values of any unconfirmed coins
-/// from self.
+/// from self. Immature coinbase deposits are in neither balance, see
+/// [`immature_balance`].
 fn coins_summary(
     coins: &[Coin],
     tip_height: u32,
@@ -92,7 +98,7 @@ fn coins_summary(
     let mut expiring_coins = Vec::new();
     let mut remaining_seq = None;
     for coin in coins {
-        if coin.spend_info.is_none() {
+        if coin.spend_info.is_none() && !coin.is_immature {
             // Include unconfirmed coins from self in confirmed balance.
             if coin_is_owned(coin) {
                 balance += coin.amount;
@@ -122,18 +128,78 @@ fn coins_summary(
     (balance, unconfirmed_balance, expiring_coins, remaining_seq)
 }
 
+/// The upper bounds, in blocks, of the spans of time the confirmed coins are grouped by in the
+/// recovery countdown: now, a week, a month and six months.
+const RECOVERY_COUNTDOWN_SPANS: [u32; 4] = [0, 1_008, 4_383, 26_298];
+
+/// Groups the confirmed coins by how many blocks remain until their recovery path becomes
+/// available given the `timelock` of the first recovery path. Empty groups are left out.
+fn recovery_countdown(
+    coins: &[Coin],
+    tip_height: u32,
+    timelock: u16,
+) -> Vec<view::home::RecoveryCountdownGroup> {
+    let mut groups: Vec<view::home::RecoveryCountdownGroup> = RECOVERY_COUNTDOWN_SPANS
+        .iter()
+        .map(|max| Some(*max))
+        .chain(std::iter::once(None))
+        .map(|max_blocks| view::home::RecoveryCountdownGroup {
+            max_blocks,
+            min_blocks: u32::MAX,
+            coins: Vec::new(),
+            total: Amount::from_sat(0),
+        })
+        .collect();
+    for coin in coins {
+        if coin.spend_info.is_some()
+            || coin.is_immature
+            || coin.block_height.is_none()
+            || !coin_is_owned(coin)
+        {
+            continue;
+        }
+        let seq = remaining_sequence(coin, tip_height, timelock);
+        let group = groups
+            .iter_mut()
+            .find(|group| group.max_blocks.map(|max| seq <= max).unwrap_or(true))
+            .expect("The last group is unbounded");
+        group.min_blocks = std::cmp::min(group.min_blocks, seq);
+        group.coins.push(coin.outpoint);
+        group.total += coin.amount;
+    }
+    groups.retain(|group| !group.coins.is_empty());
+    groups
+}
+
+/// Returns the value of the coinbase deposits which can't be spent yet.
+fn immature_balance(coins: &[Coin]) -> Amount {
+    coins
+        .iter()
+        .filter(|coin| coin.is_immature && coin.spend_info.is_none())
+        .map(|coin| coin.amount)
+        .sum()
+}
+
 pub struct Home {
     wallet: Arc<Wallet>,
     sync_status: SyncStatus,
     balance: Amount,
     unconfirmed_balance: Amount,
+    immature_balance: Amount,
     remaining_sequence: Option<u32>,
     expiring_coins: Vec<OutPoint>,
+    recovery_countdown: Vec<view::home::RecoveryCountdownGroup>,
     events: Vec<Payment>,
     is_last_page: bool,
     processing: bool,
     selected_event: Option<(HistoryTransaction, usize)>,
     labels_edited: LabelsEdited,
+    // None if the backend doesn't support it.
+    readiness: Option<RecoveryReadiness>,
+    // None if the backend doesn't support it.
+    mempool: Option<GetMempoolStatsResult>,
+    // None if the backend doesn't support it.
+    recovery_schedule: Option<GetRecoveryScheduleResult>,
     warning: Option<Error>,
 }
 
@@ -149,17 +215,27 @@ impl Home {
             tip_height as u32,
             wallet.main_descriptor.first_timelock_value(),
         );
+        let recovery_countdown = recovery_countdown(
+            coins,
+            tip_height as u32,
+            wallet.main_descriptor.first_timelock_value(),
+        );
 
         Self {
             wallet,
             sync_status,
             balance,
             unconfirmed_balance,
+            immature_balance: immature_balance(coins),
             remaining_sequence: remaining_seq,
             expiring_coins,
+            recovery_countdown,
             selected_event: None,
             events: Vec::new(),
             labels_edited: LabelsEdited::default(),
+            readiness: None,
+            mempool: None,
+            recovery_schedule: None,
             warning: None,
             is_last_page: false,
             processing: false,
@@ -185,8 +261,13 @@ impl State for Home {
                 view::home::home_view(
                     &self.balance,
                     &self.unconfirmed_balance,
+                    &self.immature_balance,
                     &self.remaining_sequence,
                     &self.expiring_coins,
+                    &self.recovery_countdown,
+                    self.readiness.as_ref(),
+                    self.mempool.as_ref(),
+                    self.recovery_schedule.as_ref(),
                     &self.events,
                     self.is_last_page,
                     self.processing,
@@ -207,18 +288,29 @@ impl State for Home {
                 Err(e) => self.warning = Some(e),
                 Ok(coins) => {
                     self.warning = None;
-                    (
-                        self.balance,
-                        self.unconfirmed_balance,
-                        self.expiring_coins,
-                        self.remaining_sequence,
-                    ) = coins_summary(
+                    // Only the confirmed coins are listed, the balances are queried separately.
+                    (_, _, self.expiring_coins, self.remaining_sequence) = coins_summary(
+                        &coins,
+                        cache.blockheight as u32,
+                        self.wallet.main_descriptor.first_timelock_value(),
+                    );
+                    self.recovery_countdown = recovery_countdown(
                         &coins,
                         cache.blockheight as u32,
                         self.wallet.main_descriptor.first_timelock_value(),
                     );
                 }
             },
+            Message::Balance(res) => match res {
+                Err(e) => self.warning = Some(e),
+                Ok(balance) => {
+                    self.warning = None;
+                    // Unconfirmed coins from self are included in the confirmed balance.
+                    self.balance = balance.confirmed + balance.unconfirmed_from_self;
+                    self.unconfirmed_balance = balance.unconfirmed;
+                    self.immature_balance = balance.immature;
+                }
+            },
             Message::Payments(res) => match res {
                 Err(e) => self.warning = Some(e),
                 Ok(events) => {
@@ -276,6 +368,38 @@ impl State for Home {
                     self.warning = Some(e);
                 }
             },
+            Message::RecoveryReadiness(res) => match res {
+                Ok(readiness) => self.readiness = Some(readiness),
+                Err(Error::Daemon(DaemonError::ClientNotSupported)) => self.readiness = None,
+                Err(e) => self.warning = Some(e),
+            },
+            Message::MempoolStats(res) => match res {
+                Ok(stats) => self.mempool = Some(stats),
+                Err(Error::Daemon(DaemonError::ClientNotSupported)) => self.mempool = None,
+                Err(e) => self.warning = Some(e),
+            },
+            Message::RecoverySchedule(res) => match res {
+                Ok(schedule) => self.recovery_schedule = Some(schedule),
+                Err(Error::Daemon(DaemonError::ClientNotSupported)) => {
+                    self.recovery_schedule = None
+                }
+                Err(e) => self.warning = Some(e),
+            },
+            Message::View(view::Message::RefreshMempool) => {
+                return Task::perform(
+                    async move { daemon.get_mempool_stats().await.map_err(|e| e.into()) },
+                    Message::MempoolStats,
+                );
+            }
+            Message::View(view::Message::SetReadiness(item, done)) => {
+                return Task::perform(
+                    async move {
+                        daemon.set_recovery_readiness(item, done).await?;
+                        daemon.get_recovery_readiness().await.map_err(|e| e.into())
+                    },
+                    Message::RecoveryReadiness,
+                );
+            }
             Message::View(view::Message::SelectPayment(outpoint)) => {
                 return Task::perform(
                     async move {
@@ -377,6 +501,10 @@ impl State for Home {
         self.selected_event = None;
         self.wallet = wallet;
         let daemon2 = daemon.clone();
+        let daemon3 = daemon.clone();
+        let daemon4 = daemon.clone();
+        let daemon5 = daemon.clone();
+        let daemon6 = daemon.clone();
         let now: u32 = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
@@ -397,16 +525,37 @@ impl State for Home {
                 },
                 Message::Payments,
             ),
+            Task::perform(
+                async move { daemon4.get_balance().await.map_err(|e| e.into()) },
+                Message::Balance,
+            ),
             Task::perform(
                 async move {
                     daemon2
-                        .list_coins(&[CoinStatus::Unconfirmed, CoinStatus::Confirmed], &[])
+                        .list_coins(&[CoinStatus::Confirmed], &[])
                         .await
                         .map(|res| res.coins)
                         .map_err(|e| e.into())
                 },
                 Message::Coins,
             ),
+            Task::perform(
+                async move { daemon3.get_recovery_readiness().await.map_err(|e| e.into()) },
+                Message::RecoveryReadiness,
+            ),
+            Task::perform(
+                async move { daemon5.get_mempool_stats().await.map_err(|e| e.into()) },
+                Message::MempoolStats,
+            ),
+            Task::perform(
+                async move {
+                    daemon6
+                        .get_recovery_schedule(None)
+                        .await
+                        .map_err(|e| e.into())
+                },
+                Message::RecoverySchedule,
+            ),
         ])
     }
 }
@@ -420,7 +569,7 @@ impl From<Home> for Box<dyn State> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::daemon::model::Coin;
+    use crate::daemon::model::{balance_from_coins, Coin};
     use liana::miniscript::bitcoin;
     use lianad::commands::LCSpendInfo;
     use std::str::FromStr;
@@ -453,6 +602,7 @@ mod tests {
             derivation_index: bitcoin::bip32::ChildNumber::Normal { index: 0 },
             block_height: Some(1),
             is_immature: false,
+            maturity_height: None,
             is_change: false,
             is_from_self: false,
             spend_info: Some(LCSpendInfo {
@@ -473,6 +623,7 @@ mod tests {
             derivation_index: bitcoin::bip32::ChildNumber::Normal { index: 1 },
             block_height: None,
             is_immature: false,
+            maturity_height: None,
             is_change: true,
             is_from_self: false,
             spend_info: None,
@@ -490,6 +641,7 @@ mod tests {
             derivation_index: bitcoin::bip32::ChildNumber::Normal { index: 2 },
             block_height: None,
             is_immature: false,
+            maturity_height: None,
             is_change: false,
             is_from_self: true,
             spend_info: None,
@@ -512,6 +664,7 @@ mod tests {
             derivation_index: bitcoin::bip32::ChildNumber::Normal { index: 3 },
             block_height: Some(791_001), // 791_001 + timelock - tip_height = 1_001 > 1_000 = (timelock / 10)
             is_immature: false,
+            maturity_height: None,
             is_change: false,
             is_from_self: false,
             spend_info: None,
@@ -547,6 +700,7 @@ mod tests {
             derivation_index: bitcoin::bip32::ChildNumber::Normal { index: 4 },
             block_height: Some(792_000),
             is_immature: false,
+            maturity_height: None,
             is_change: false,
             is_from_self: false,
             spend_info: None,
@@ -569,6 +723,7 @@ mod tests {
             derivation_index: bitcoin::bip32::ChildNumber::Normal { index: 5 },
             block_height: Some(790_500),
             is_immature: false,
+            maturity_height: None,
             is_change: false,
             is_from_self: false,
             spend_info: None,
@@ -583,5 +738,58 @@ mod tests {
                 Some(500)
             )
         );
+        // An immature coinbase deposit is in neither balance.
+        coins.push(Coin {
+            outpoint: OutPoint::new(dummy_txid, 6),
+            amount: Amount::from_sat(5_000),
+            address: dummy_address.clone(),
+            derivation_index: bitcoin::bip32::ChildNumber::Normal { index: 6 },
+            block_height: None,
+            is_immature: true,
+            maturity_height: Some(792_050),
+            is_change: false,
+            is_from_self: false,
+            spend_info: None,
+        });
+        assert_eq!(
+            coins_summary(&coins, tip_height, timelock),
+            (
+                Amount::from_sat(425),
+                Amount::from_sat(109),
+                vec![OutPoint::new(dummy_txid, 3), OutPoint::new(dummy_txid, 5)],
+                Some(500)
+            )
+        );
+        assert_eq!(immature_balance(&coins), Amount::from_sat(5_000));
+
+        // Only the confirmed coins are counted down, grouped by span of time.
+        assert_eq!(
+            recovery_countdown(&coins, tip_height, timelock),
+            vec![
+                view::home::RecoveryCountdownGroup {
+                    max_blocks: Some(1_008),
+                    min_blocks: 500,
+                    coins: vec![OutPoint::new(dummy_txid, 3), OutPoint::new(dummy_txid, 5)],
+                    total: Amount::from_sat(209),
+                },
+                view::home::RecoveryCountdownGroup {
+                    max_blocks: Some(4_383),
+                    min_blocks: 2_000,
+                    coins: vec![OutPoint::new(dummy_txid, 4)],
+                    total: Amount::from_sat(105),
+                },
+            ]
+        );
+
+        // The balances computed by backends which don't maintain them are the same.
+        let balance = balance_from_coins(&coins);
+        assert_eq!(
+            balance.confirmed + balance.unconfirmed_from_self,
+            Amount::from_sat(425)
+        );
+        assert_eq!(balance.unconfirmed_from_self, Amount::from_sat(111));
+        assert_eq!(balance.unconfirmed, Amount::from_sat(109));
+        assert_eq!(balance.immature, Amount::from_sat(5_000));
+        assert_eq!(balance.spending, Amount::from_sat(100));
     }
 }
@@ -1,14 +1,22 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::path::PathBuf;
-use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
-use iced::Subscription;
+use iced::{
+    widget::qr_code::{self, ErrorCorrection},
+    Subscription,
+};
 
 use iced::Task;
 use liana::{
     descriptors::LianaPolicy,
-    miniscript::bitcoin::{bip32::Fingerprint, psbt::Psbt, Network, Txid},
+    miniscript::bitcoin::{
+        bip32::{DerivationPath, Fingerprint},
+        psbt::Psbt,
+        secp256k1, Amount, Network, Txid,
+    },
+    spend::{add_signatures_from_tx, SpendSighash},
 };
 use lianad::commands::CoinStatus;
 
@@ -24,6 +32,7 @@ use crate::{
         cache::Cache,
         error::Error,
         message::Message,
+        settings::{AccentColor, SpendConfirmation},
         state::label::{label_item_from_str, LabelsEdited},
         view,
         wallet::{Wallet, WalletError},
@@ -32,7 +41,12 @@ use crate::{
         model::{LabelItem, Labelled, SpendStatus, SpendTx},
         Daemon,
     },
-    hw::{HardwareWallet, HardwareWallets},
+    hw::{
+        requires_previous_transactions, supports_custom_sighash, HardwareWallet, HardwareWallets,
+    },
+    psbt_format::{self, Content},
+    qr_signer::{self, QrSigner, Step},
+    tapsigner::{self, Tapsigner},
 };
 
 pub trait Action {
@@ -215,8 +229,19 @@ impl PsbtState {
             Message::BroadcastModal(res) => match res {
                 Ok(conflicting_txids) => {
                     self.action = Some(PsbtAction::Broadcast(BroadcastAction {
+                        broadcast: false,
+                        error: None,
                         conflicting_txids,
-                        ..Default::default()
+                        wallet_name: self.wallet.name.clone(),
+                        network: cache.network,
+                        accent_color: self.wallet.accent_color,
+                        confirmation: self
+                            .wallet
+                            .spend_confirmation
+                            .clone()
+                            .filter(|c| c.is_required(self.tx.spend_amount)),
+                        confirmation_input: String::new(),
+                        spend_amount: self.tx.spend_amount,
                     }));
                 }
                 Err(e) => {
@@ -302,12 +327,27 @@ impl Action for SaveAction {
     }
 }
 
-#[derive(Default)]
 pub struct BroadcastAction {
     broadcast: bool,
     error: Option<Error>,
     /// IDs of any directly conflicting transactions.
     conflicting_txids: HashSet<Txid>,
+    wallet_name: String,
+    network: Network,
+    accent_color: Option<AccentColor>,
+    /// Set if the spend sends more than the threshold of the wallet spend confirmation.
+    confirmation: Option<SpendConfirmation>,
+    confirmation_input: String,
+    spend_amount: Amount,
+}
+
+impl BroadcastAction {
+    fn is_confirmed(&self) -> bool {
+        self.confirmation
+            .as_ref()
+            .map(|c| c.is_confirmed_by(self.spend_amount, &self.confirmation_input))
+            .unwrap_or(true)
+    }
 }
 
 impl Action for BroadcastAction {
@@ -318,14 +358,24 @@ impl Action for BroadcastAction {
         tx: &mut SpendTx,
     ) -> Task<Message> {
         match message {
+            Message::View(view::Message::Spend(view::SpendTxMessage::ConfirmationEdited(
+                input,
+            ))) => {
+                self.confirmation_input = input;
+            }
             Message::View(view::Message::Spend(view::SpendTxMessage::Confirm)) => {
+                if !self.is_confirmed() {
+                    return Task::none();
+                }
                 let daemon = daemon.clone();
                 let psbt = tx.psbt.clone();
+                // The daemon may enforce its own threshold, tell it the user confirmed the amount.
+                let confirmed_amount = self.confirmation.as_ref().map(|_| self.spend_amount);
                 self.error = None;
                 return Task::perform(
                     async move {
                         daemon
-                            .broadcast_spend_tx(&psbt.unsigned_tx.compute_txid())
+                            .broadcast_spend_tx(&psbt.unsigned_tx.compute_txid(), confirmed_amount)
                             .await
                             .map_err(|e| e.into())
                     },
@@ -348,8 +398,15 @@ impl Action for BroadcastAction {
             content,
             view::psbt::broadcast_action(
                 &self.conflicting_txids,
+                &self.wallet_name,
+                self.network,
+                self.accent_color,
                 self.error.as_ref(),
                 self.broadcast,
+                self.confirmation
+                    .as_ref()
+                    .map(|c| (c, self.spend_amount, self.confirmation_input.as_str())),
+                self.is_confirmed(),
             ),
         )
         .on_blur(Some(view::Message::Spend(view::SpendTxMessage::Cancel)))
@@ -411,6 +468,15 @@ pub struct SignAction {
     signed: HashSet<Fingerprint>,
     is_saved: bool,
     display_modal: bool,
+    qr: Option<QrSigning>,
+    tapsigner: Option<TapsignerSigning>,
+}
+
+/// The CVC entry to sign with the Tapsigner of this id.
+struct TapsignerSigning {
+    id: String,
+    cvc: form::Value<String>,
+    processing: bool,
 }
 
 impl SignAction {
@@ -429,13 +495,26 @@ impl SignAction {
             signed,
             is_saved,
             display_modal: true,
+            qr: None,
+            tapsigner: None,
         }
     }
 }
 
 impl Action for SignAction {
     fn subscription(&self) -> Subscription<Message> {
-        self.hws.refresh().map(Message::HardwareWallets)
+        let hws = self.hws.refresh().map(Message::HardwareWallets);
+        match &self.qr {
+            Some(qr) if self.display_modal && qr.is_animated() => Subscription::batch(vec![
+                hws,
+                iced::time::every(Duration::from_millis(300)).map(|_| {
+                    Message::View(view::Message::Spend(view::SpendTxMessage::QrSign(
+                        view::QrSignMessage::NextFrame,
+                    )))
+                }),
+            ]),
+            _ => hws,
+        }
     }
 
     fn update(
@@ -445,22 +524,92 @@ impl Action for SignAction {
         tx: &mut SpendTx,
     ) -> Task<Message> {
         match message {
-            Message::View(view::Message::SelectHardwareWallet(i)) => {
-                if let Some(HardwareWallet::Supported {
+            Message::View(view::Message::SelectHardwareWallet(i)) => match self.hws.list.get(i) {
+                Some(HardwareWallet::Supported {
                     fingerprint,
                     device,
                     ..
-                }) = self.hws.list.get(i)
-                {
+                }) => {
                     self.display_modal = false;
                     self.signing.insert(*fingerprint);
                     let psbt = tx.psbt.clone();
                     let fingerprint = *fingerprint;
                     return Task::perform(
-                        sign_psbt(self.wallet.clone(), device.clone(), psbt),
+                        sign_psbt(self.wallet.clone(), daemon.clone(), device.clone(), psbt),
                         move |res| Message::Signed(fingerprint, res),
                     );
                 }
+                Some(HardwareWallet::Tapsigner { id, .. }) => {
+                    self.error = None;
+                    self.tapsigner = Some(TapsignerSigning {
+                        id: id.clone(),
+                        cvc: form::Value::default(),
+                        processing: false,
+                    });
+                }
+                _ => {}
+            },
+            Message::View(view::Message::Spend(view::SpendTxMessage::Tapsigner(msg))) => {
+                if let Some(signing) = &mut self.tapsigner {
+                    match msg {
+                        view::TapsignerMessage::CvcEdited(cvc) => {
+                            signing.cvc.value = cvc;
+                            signing.cvc.valid = true;
+                        }
+                        view::TapsignerMessage::Cancel => self.tapsigner = None,
+                        view::TapsignerMessage::Confirm => {
+                            let device = self.hws.list.iter().find_map(|hw| match hw {
+                                HardwareWallet::Tapsigner { id, device, .. }
+                                    if *id == signing.id =>
+                                {
+                                    Some(device.clone())
+                                }
+                                _ => None,
+                            });
+                            if tapsigner::check_cvc(&signing.cvc.value).is_err() {
+                                signing.cvc.valid = false;
+                            } else if let Some(device) = device {
+                                self.error = None;
+                                signing.processing = true;
+                                let id = signing.id.clone();
+                                return Task::perform(
+                                    sign_psbt_with_tapsigner(
+                                        device,
+                                        signing.cvc.value.clone(),
+                                        tx.psbt.clone(),
+                                    ),
+                                    move |res| Message::TapsignerSigned(id, res),
+                                );
+                            } else {
+                                // The card was removed from the reader.
+                                self.tapsigner = None;
+                            }
+                        }
+                    }
+                }
+            }
+            Message::TapsignerSigned(id, res) => {
+                if let Some(signing) = &mut self.tapsigner {
+                    signing.processing = false;
+                }
+                match res {
+                    Ok((fingerprint, psbt)) => {
+                        self.hws.set_tapsigner_fingerprint(&id, fingerprint);
+                        self.tapsigner = None;
+                        self.display_modal = false;
+                        return Task::done(Message::Signed(fingerprint, Ok(psbt)));
+                    }
+                    Err(e) => {
+                        if let (
+                            Some(signing),
+                            Error::Tapsigner(tapsigner::TapsignerError::WrongCvc),
+                        ) = (&mut self.tapsigner, &e)
+                        {
+                            signing.cvc.valid = false;
+                        }
+                        self.error = Some(e);
+                    }
+                }
             }
             Message::View(view::Message::Spend(view::SpendTxMessage::SelectHotSigner)) => {
                 return Task::perform(
@@ -468,6 +617,32 @@ impl Action for SignAction {
                     |(fg, res)| Message::Signed(fg, res),
                 );
             }
+            Message::View(view::Message::Spend(view::SpendTxMessage::SelectRemoteSigner)) => {
+                if let Some(remote_signer) = self.wallet.remote_signer.clone() {
+                    let fingerprint = remote_signer.fingerprint;
+                    self.signing.insert(fingerprint);
+                    let psbt = tx.psbt.clone();
+                    return Task::perform(
+                        async move { remote_signer.sign_psbt(&psbt).await.map_err(|e| e.into()) },
+                        move |res| Message::Signed(fingerprint, res),
+                    );
+                }
+            }
+            Message::View(view::Message::Spend(view::SpendTxMessage::QrSign(msg))) => {
+                if let view::QrSignMessage::Start(signer) = msg {
+                    self.qr = Some(QrSigning::new(signer, &self.wallet, &tx.psbt, &self.signed));
+                } else if let Some(qr) = &mut self.qr {
+                    match qr.update(msg, &self.wallet, &tx.psbt) {
+                        QrSigningStatus::Running => {}
+                        QrSigningStatus::Cancelled => self.qr = None,
+                        QrSigningStatus::Signed(fingerprint, psbt) => {
+                            self.qr = None;
+                            self.display_modal = false;
+                            return Task::done(Message::Signed(fingerprint, Ok(psbt)));
+                        }
+                    }
+                }
+            }
             Message::Signed(fingerprint, res) => {
                 self.signing.remove(&fingerprint);
                 match res {
@@ -507,8 +682,18 @@ impl Action for SignAction {
                 }
             }
             Message::Updated(res) => match res {
-                Ok(()) => match self.wallet.main_descriptor.partial_spend_info(&tx.psbt) {
-                    Ok(sigs) => tx.sigs = sigs,
+                Ok(()) => match self
+                    .wallet
+                    .main_descriptor
+                    .partial_spend_info(&tx.psbt, &secp256k1::Secp256k1::verification_only())
+                {
+                    Ok(sigs) => {
+                        tx.sigs = sigs;
+                        tx.finalization = self
+                            .wallet
+                            .main_descriptor
+                            .finalizability(&tx.psbt, &secp256k1::Secp256k1::verification_only());
+                    }
                     Err(e) => self.error = Some(Error::Unexpected(e.to_string())),
                 },
                 Err(e) => self.error = Some(e),
@@ -532,7 +717,34 @@ impl Action for SignAction {
             view::psbt::sign_action_toasts(self.error.as_ref(), &self.hws.list, &self.signing),
         )
         .into();
-        if self.display_modal {
+        if let (true, Some(signing)) = (self.display_modal, &self.tapsigner) {
+            modal::Modal::new(
+                content,
+                view::psbt::tapsigner_sign_view(
+                    self.error.as_ref(),
+                    &signing.cvc,
+                    signing.processing,
+                ),
+            )
+            .on_blur(Some(view::Message::Spend(view::SpendTxMessage::Cancel)))
+            .into()
+        } else if let (true, Some(qr)) = (self.display_modal, &self.qr) {
+            modal::Modal::new(
+                content,
+                view::psbt::qr_sign_view(
+                    qr.signer,
+                    qr.step,
+                    &qr.keys,
+                    &self.wallet.keys_aliases,
+                    qr.key,
+                    qr.current_qr(),
+                    &qr.signed_psbt,
+                    qr.status.as_ref(),
+                ),
+            )
+            .on_blur(Some(view::Message::Spend(view::SpendTxMessage::Cancel)))
+            .into()
+        } else if self.display_modal {
             modal::Modal::new(
                 content,
                 view::psbt::sign_action(
@@ -543,6 +755,11 @@ impl Action for SignAction {
                         .signer
                         .as_ref()
                         .and_then(|signer| self.wallet.keys_aliases.get(&signer.fingerprint)),
+                    self.wallet.remote_signer.as_ref(),
+                    self.wallet
+                        .remote_signer
+                        .as_ref()
+                        .and_then(|signer| self.wallet.keys_aliases.get(&signer.fingerprint)),
                     &self.signed,
                     &self.signing,
                 ),
@@ -555,6 +772,161 @@ impl Action for SignAction {
     }
 }
 
+/// A signing session with an airgapped device through QR codes, following the steps of its
+/// profile. It is kept when the modal is closed, to resume where the user left it.
+struct QrSigning {
+    signer: QrSigner,
+    step: usize,
+    /// The keys which didn't sign yet, with the derivation paths of their accounts.
+    keys: BTreeMap<Fingerprint, BTreeSet<DerivationPath>>,
+    key: Option<Fingerprint>,
+    /// The frames of the animated QR codes, empty if a part is too large to be displayed.
+    descriptor_qr: Vec<qr_code::Data>,
+    psbt_qr: Vec<qr_code::Data>,
+    frame: usize,
+    signed_psbt: form::Value<String>,
+    /// The format detected for the signed PSBT, or why it can't be imported.
+    status: Option<Result<String, String>>,
+    imported: Option<Psbt>,
+}
+
+enum QrSigningStatus {
+    Running,
+    Cancelled,
+    Signed(Fingerprint, Psbt),
+}
+
+fn animated_qr(parts: Vec<String>) -> Vec<qr_code::Data> {
+    parts
+        .into_iter()
+        .map(|part| qr_code::Data::with_error_correction(part, ErrorCorrection::Low))
+        .collect::<Result<_, _>>()
+        .unwrap_or_default()
+}
+
+impl QrSigning {
+    fn new(signer: QrSigner, wallet: &Wallet, psbt: &Psbt, signed: &HashSet<Fingerprint>) -> Self {
+        let mut keys = qr_signer::keys_derivation_paths(&wallet.main_descriptor.policy());
+        keys.retain(|fingerprint, _| !signed.contains(fingerprint));
+        Self {
+            signer,
+            step: 0,
+            // Spare a click when a single key is left to sign with.
+            key: if keys.len() == 1 {
+                keys.keys().next().copied()
+            } else {
+                None
+            },
+            keys,
+            descriptor_qr: animated_qr(
+                wallet
+                    .main_descriptor
+                    .to_ur_parts(signer.max_fragment_len()),
+            ),
+            psbt_qr: animated_qr(liana::ur::encode_psbt(psbt, signer.max_fragment_len())),
+            frame: 0,
+            signed_psbt: form::Value::default(),
+            status: None,
+            imported: None,
+        }
+    }
+
+    fn current_step(&self) -> Option<Step> {
+        self.signer.steps().get(self.step).copied()
+    }
+
+    /// The frame of the QR code to display at this step, with its index and the number of frames.
+    fn current_qr(&self) -> Option<(&qr_code::Data, usize, usize)> {
+        let frames = match self.current_step()? {
+            Step::ShowDescriptor => &self.descriptor_qr,
+            Step::ShowPsbt => &self.psbt_qr,
+            Step::LoadSeed | Step::ScanSignedPsbt => return None,
+        };
+        if frames.is_empty() {
+            return None;
+        }
+        let index = self.frame % frames.len();
+        frames.get(index).map(|data| (data, index, frames.len()))
+    }
+
+    fn is_animated(&self) -> bool {
+        self.current_qr()
+            .map(|(_, _, frames)| frames > 1)
+            .unwrap_or(false)
+    }
+
+    fn update(
+        &mut self,
+        message: view::QrSignMessage,
+        wallet: &Wallet,
+        draft: &Psbt,
+    ) -> QrSigningStatus {
+        match message {
+            view::QrSignMessage::Start(_) => {}
+            view::QrSignMessage::SelectKey(fingerprint) => {
+                if self.keys.contains_key(&fingerprint) {
+                    self.key = Some(fingerprint);
+                }
+            }
+            view::QrSignMessage::Next => {
+                let can_continue = match self.current_step() {
+                    Some(Step::LoadSeed) => self.key.is_some(),
+                    _ => true,
+                };
+                if can_continue && self.step + 1 < self.signer.steps().len() {
+                    self.step += 1;
+                    self.frame = 0;
+                }
+            }
+            view::QrSignMessage::Previous => {
+                if self.step == 0 {
+                    return QrSigningStatus::Cancelled;
+                }
+                self.step -= 1;
+                self.frame = 0;
+            }
+            view::QrSignMessage::NextFrame => {
+                self.frame = self.frame.wrapping_add(1);
+            }
+            view::QrSignMessage::SignedPsbtEdited(s) => {
+                self.signed_psbt.value = s;
+                let key = self.key;
+                let res = psbt_format::decode_str(&self.signed_psbt.value)
+                    .map_err(|e| e.to_string())
+                    .and_then(|decoded| {
+                        imported_psbt(draft, &decoded.content)
+                            .map(|psbt| (decoded.to_string(), psbt))
+                    })
+                    .and_then(|(format, psbt)| match key {
+                        // A stateless signer signs with whatever seed was loaded.
+                        Some(fg) if !qr_signer::has_signature_from(&psbt, fg) => Err(format!(
+                            "This transaction has no signature of {}, was its seed loaded?",
+                            wallet
+                                .keys_aliases
+                                .get(&fg)
+                                .cloned()
+                                .unwrap_or_else(|| fg.to_string())
+                        )),
+                        _ => Ok((format, psbt)),
+                    });
+                self.imported = res.as_ref().ok().map(|(_, psbt)| psbt.clone());
+                self.status = if self.signed_psbt.value.trim().is_empty() {
+                    None
+                } else {
+                    Some(res.map(|(format, _)| format))
+                };
+                self.signed_psbt.valid = self.imported.is_some();
+            }
+            view::QrSignMessage::Confirm => {
+                if let (Some(fingerprint), Some(psbt)) = (self.key, self.imported.take()) {
+                    return QrSigningStatus::Signed(fingerprint, psbt);
+                }
+            }
+        }
+        QrSigningStatus::Running
+    }
+}
+
 fn merge_signatures(psbt: &mut Psbt, signed_psbt: &Psbt) {
     for i in 0..signed_psbt.inputs.len() {
         let psbtin = match psbt.inputs.get_mut(i) {
@@ -577,6 +949,39 @@ fn merge_signatures(psbt: &mut Psbt, signed_psbt: &Psbt) {
     }
 }
 
+/// The PSBT to store when importing this content for this draft: the content itself if it's a PSBT
+/// of the same transaction, or the draft with the signatures of the signed transaction otherwise.
+pub fn imported_psbt(draft: &Psbt, content: &Content) -> Result<Psbt, String> {
+    let txid = draft.unsigned_tx.compute_txid();
+    match content {
+        Content::Psbt(psbt) => {
+            if psbt.unsigned_tx.compute_txid() != txid {
+                return Err("This PSBT is not for the same transaction".to_string());
+            }
+            Ok(psbt.clone())
+        }
+        Content::SignedTransaction(signed_tx) => {
+            if signed_tx.compute_txid() != txid {
+                return Err("This signed transaction is not the same transaction".to_string());
+            }
+            let mut psbt = draft.clone();
+            let added = add_signatures_from_tx(
+                &secp256k1::Secp256k1::verification_only(),
+                &mut psbt,
+                signed_tx,
+            )
+            .map_err(|e| e.to_string())?;
+            if added == 0 {
+                return Err(
+                    "The signed transaction doesn't contain any new signature for this PSBT"
+                        .to_string(),
+                );
+            }
+            Ok(psbt)
+        }
+    }
+}
+
 async fn sign_psbt_with_hot_signer(
     wallet: Arc<Wallet>,
     psbt: Psbt,
@@ -595,11 +1000,74 @@ async fn sign_psbt_with_hot_signer(
     }
 }
 
+async fn sign_psbt_with_tapsigner(
+    device: Arc<Tapsigner>,
+    cvc: String,
+    mut psbt: Psbt,
+) -> Result<(Fingerprint, Psbt), Error> {
+    let fingerprint = device.sign_psbt(&cvc, &mut psbt)?;
+    Ok((fingerprint, psbt))
+}
+
+/// Set the previous transaction of the Segwit v0 inputs missing it, as queried from the daemon.
+/// Inputs whose previous transaction isn't known are left untouched, the device will tell.
+async fn add_previous_transactions(
+    daemon: Arc<dyn Daemon + Sync + Send>,
+    psbt: &mut Psbt,
+) -> Result<(), Error> {
+    let missing: Vec<Txid> = psbt
+        .unsigned_tx
+        .input
+        .iter()
+        .zip(psbt.inputs.iter())
+        .filter(|(_, psbt_in)| psbt_in.non_witness_utxo.is_none())
+        .map(|(txin, _)| txin.previous_output.txid)
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let prev_txs: HashMap<Txid, _> = daemon
+        .list_txs(&missing)
+        .await?
+        .transactions
+        .into_iter()
+        .map(|info| (info.tx.compute_txid(), info.tx))
+        .collect();
+    for (txin, psbt_in) in psbt.unsigned_tx.input.iter().zip(psbt.inputs.iter_mut()) {
+        if psbt_in.non_witness_utxo.is_none() {
+            psbt_in.non_witness_utxo = prev_txs.get(&txin.previous_output.txid).cloned();
+            if psbt_in.non_witness_utxo.is_none() {
+                log::warn!(
+                    "Previous transaction of input '{}' not found.",
+                    txin.previous_output
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
 async fn sign_psbt(
     wallet: Arc<Wallet>,
+    daemon: Arc<dyn Daemon + Sync + Send>,
     hw: std::sync::Arc<dyn async_hwi::HWI + Send + Sync>,
     mut psbt: Psbt,
 ) -> Result<Psbt, Error> {
+    // Don't let a device which doesn't support it sign with SIGHASH_ALL what was meant to be signed
+    // with another sighash type.
+    if SpendSighash::from_psbt(&psbt) != Some(SpendSighash::All)
+        && !supports_custom_sighash(&hw.device_kind())
+    {
+        return Err(Error::Unexpected(format!(
+            "{} devices cannot sign with the sighash type requested by this transaction.",
+            hw.device_kind()
+        )));
+    }
+    // Drafts created before their previous transactions were always included may miss some. Only
+    // the signatures are merged back into the draft so this doesn't modify it.
+    if !wallet.main_descriptor.is_taproot() && requires_previous_transactions(&hw.device_kind()) {
+        add_previous_transactions(daemon, &mut psbt).await?;
+    }
     // The BitBox02 is only going to produce a signature for a single key in the Script. In order
     // to make sure it doesn't sign for a public key from another spending path we remove the BIP32
     // derivation for the other paths.
@@ -610,7 +1078,7 @@ async fn sign_psbt(
         // signatures back into the original PSBT.
         let mut pruned_psbt = wallet
             .main_descriptor
-            .prune_bip32_derivs_last_avail(psbt.clone())
+            .prune_bip32_derivs_last_avail(psbt.clone(), &secp256k1::Secp256k1::verification_only())
             .map_err(Error::Desc)?;
         hw.sign_tx(&mut pruned_psbt).await.map_err(Error::from)?;
         for (i, psbt_in) in psbt.inputs.iter_mut().enumerate() {
@@ -641,6 +1109,10 @@ pub struct UpdateAction {
     wallet: Arc<Wallet>,
     psbt: String,
     updated: form::Value<String>,
+    /// The format detected for the updated PSBT, or why it can't be imported.
+    status: Option<Result<String, String>>,
+    /// The PSBT to store, once the updated one was checked.
+    imported: Option<Psbt>,
     processing: bool,
     error: Option<Error>,
     success: bool,
@@ -652,6 +1124,8 @@ impl UpdateAction {
             wallet,
             psbt,
             updated: form::Value::default(),
+            status: None,
+            imported: None,
             processing: false,
             error: None,
             success: false,
@@ -669,6 +1143,7 @@ impl Action for UpdateAction {
                 view::psbt::update_spend_view(
                     self.psbt.clone(),
                     &self.updated,
+                    self.status.as_ref(),
                     self.error.as_ref(),
                     self.processing,
                 )
@@ -691,30 +1166,41 @@ impl Action for UpdateAction {
                     Ok(()) => {
                         self.success = true;
                         self.error = None;
-                        let psbt = Psbt::from_str(&self.updated.value).expect("Already checked");
-                        merge_signatures(&mut tx.psbt, &psbt);
+                        if let Some(psbt) = &self.imported {
+                            merge_signatures(&mut tx.psbt, psbt);
+                        }
+                        let secp = secp256k1::Secp256k1::verification_only();
                         tx.sigs = self
                             .wallet
                             .main_descriptor
-                            .partial_spend_info(&tx.psbt)
+                            .partial_spend_info(&tx.psbt, &secp)
                             .unwrap();
+                        tx.finalization =
+                            self.wallet.main_descriptor.finalizability(&tx.psbt, &secp);
                     }
                     Err(e) => self.error = e.into(),
                 }
             }
             Message::View(view::Message::ImportSpend(view::ImportSpendMessage::PsbtEdited(s))) => {
                 self.updated.value = s;
-                if let Ok(psbt) = Psbt::from_str(&self.updated.value) {
-                    self.updated.valid =
-                        tx.psbt.unsigned_tx.compute_txid() == psbt.unsigned_tx.compute_txid();
+                let res = psbt_format::decode_str(&self.updated.value)
+                    .map_err(|e| e.to_string())
+                    .and_then(|decoded| {
+                        imported_psbt(&tx.psbt, &decoded.content)
+                            .map(|psbt| (decoded.to_string(), psbt))
+                    });
+                self.imported = res.as_ref().ok().map(|(_, psbt)| psbt.clone());
+                self.status = if self.updated.value.trim().is_empty() {
+                    None
                 } else {
-                    self.updated.valid = false;
-                }
+                    Some(res.map(|(format, _)| format))
+                };
+                self.updated.valid = self.imported.is_some();
             }
             Message::View(view::Message::ImportSpend(view::ImportSpendMessage::Confirm)) => {
-                self.processing = true;
-                self.error = None;
-                if let Ok(updated) = Psbt::from_str(&self.updated.value) {
+                if let Some(updated) = self.imported.clone() {
+                    self.processing = true;
+                    self.error = None;
                     return Task::perform(
                         async move { daemon.update_spend_tx(&updated).await.map_err(|e| e.into()) },
                         Message::Updated,
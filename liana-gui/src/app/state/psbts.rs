@@ -1,9 +1,8 @@
-use std::str::FromStr;
 use std::sync::Arc;
 
 use iced::{Subscription, Task};
 
-use liana::miniscript::bitcoin::psbt::Psbt;
+use liana::miniscript::bitcoin::consensus;
 use liana_ui::{
     component::{form, modal},
     widget::Element,
@@ -13,6 +12,7 @@ use super::{psbt, State};
 use crate::{
     app::{cache::Cache, error::Error, menu::Menu, message::Message, view, wallet::Wallet},
     daemon::{model::SpendTx, Daemon},
+    psbt_format::{self, Content},
 };
 
 pub struct PsbtsPanel {
@@ -40,6 +40,18 @@ impl PsbtsPanel {
         self.warning = None;
         self.import_tx = None;
     }
+
+    /// Open the import modal filled with this PSBT or signed transaction, for the user to review
+    /// and confirm it.
+    pub fn import(&mut self, content: Content) {
+        let mut modal = ImportPsbtModal::new();
+        modal.edit(match content {
+            Content::Psbt(psbt) => psbt.to_string(),
+            Content::SignedTransaction(tx) => consensus::encode::serialize_hex(&tx),
+        });
+        self.selected_tx = None;
+        self.import_tx = Some(modal);
+    }
 }
 
 impl State for PsbtsPanel {
@@ -162,6 +174,8 @@ impl From<PsbtsPanel> for Box<dyn State> {
 
 pub struct ImportPsbtModal {
     imported: form::Value<String>,
+    /// The PSBT, or signed transaction, decoded from the imported text.
+    decoded: Option<Result<psbt_format::Decoded, psbt_format::ParseError>>,
     processing: bool,
     error: Option<Error>,
     success: bool,
@@ -171,11 +185,22 @@ impl ImportPsbtModal {
     pub fn new() -> Self {
         Self {
             imported: form::Value::default(),
+            decoded: None,
             processing: false,
             error: None,
             success: false,
         }
     }
+
+    fn edit(&mut self, value: String) {
+        self.imported.value = value;
+        self.decoded = if self.imported.value.trim().is_empty() {
+            None
+        } else {
+            Some(psbt_format::decode_str(&self.imported.value))
+        };
+        self.imported.valid = matches!(self.decoded, Some(Ok(_)));
+    }
 }
 
 impl ImportPsbtModal {
@@ -183,7 +208,19 @@ impl ImportPsbtModal {
         if self.success {
             view::psbts::import_psbt_success_view()
         } else {
-            view::psbts::import_psbt_view(&self.imported, self.error.as_ref(), self.processing)
+            view::psbts::import_psbt_view(
+                &self.imported,
+                self.decoded
+                    .as_ref()
+                    .map(|res| {
+                        res.as_ref()
+                            .map(|decoded| decoded.to_string())
+                            .map_err(|e| e.to_string())
+                    })
+                    .as_ref(),
+                self.error.as_ref(),
+                self.processing,
+            )
         }
     }
 
@@ -205,20 +242,41 @@ impl ImportPsbtModal {
                 }
             }
             Message::View(view::Message::ImportSpend(view::ImportSpendMessage::PsbtEdited(s))) => {
-                self.imported.value = s;
-                self.imported.valid = Psbt::from_str(&self.imported.value).ok().is_some();
+                self.edit(s);
             }
             Message::View(view::Message::ImportSpend(view::ImportSpendMessage::Confirm)) => {
-                if self.imported.valid {
+                if let Some(Ok(decoded)) = &self.decoded {
                     self.processing = true;
                     self.error = None;
-                    let imported = Psbt::from_str(&self.imported.value).expect("Already checked");
+                    let content = decoded.content.clone();
                     return Task::perform(
                         async move {
-                            daemon
-                                .update_spend_tx(&imported)
-                                .await
-                                .map_err(|e| e.into())
+                            let psbt = match content {
+                                Content::Psbt(psbt) => psbt,
+                                // The signatures of a signed transaction are added to the draft
+                                // of the same transaction.
+                                Content::SignedTransaction(tx) => {
+                                    let txid = tx.compute_txid();
+                                    let draft = daemon
+                                        .list_spend_txs()
+                                        .await?
+                                        .spend_txs
+                                        .into_iter()
+                                        .find(|entry| entry.psbt.unsigned_tx.compute_txid() == txid)
+                                        .ok_or_else(|| {
+                                            Error::Unexpected(format!(
+                                                "No PSBT for transaction {}",
+                                                txid
+                                            ))
+                                        })?;
+                                    psbt::imported_psbt(
+                                        &draft.psbt,
+                                        &Content::SignedTransaction(tx),
+                                    )
+                                    .map_err(Error::Unexpected)?
+                                }
+                            };
+                            daemon.update_spend_tx(&psbt).await.map_err(Error::from)
                         },
                         Message::Updated,
                     );
@@ -4,12 +4,12 @@ use std::sync::Arc;
 
 use iced::{widget::qr_code, Subscription, Task};
 use liana::miniscript::bitcoin::{
-    bip32::{ChildNumber, Fingerprint},
-    Address, Network,
+    bip32::{ChildNumber, DerivationPath, Fingerprint},
+    secp256k1, Address, Network,
 };
 use liana_ui::{component::modal, widget::*};
 
-use crate::daemon::model::LabelsLoader;
+use crate::daemon::model::{Coin, LabelsLoader};
 use crate::{
     app::{
         cache::Cache,
@@ -31,6 +31,7 @@ use crate::daemon::{
 pub enum Modal {
     VerifyAddress(VerifyAddressModal),
     ShowQrCode(ShowQrCodeModal),
+    AddressDetail(AddressDetailModal),
     None,
 }
 
@@ -95,6 +96,9 @@ impl State for ReceivePanel {
             Modal::ShowQrCode(m) => modal::Modal::new(content, m.view())
                 .on_blur(Some(view::Message::Close))
                 .into(),
+            Modal::AddressDetail(m) => modal::Modal::new(content, m.view(cache))
+                .on_blur(Some(view::Message::Close))
+                .into(),
             Modal::None => content,
         }
     }
@@ -180,13 +184,39 @@ impl State for ReceivePanel {
                 }
                 Task::none()
             }
-            _ => {
-                if let Modal::VerifyAddress(ref mut m) = self.modal {
-                    m.update(daemon, cache, message)
-                } else {
-                    Task::none()
+            Message::View(view::Message::ShowAddressDetail(i)) => {
+                if let (Some(address), Some(index)) = (
+                    self.addresses.list.get(i),
+                    self.addresses.derivation_indexes.get(i),
+                ) {
+                    let modal = AddressDetailModal::new(
+                        &self.wallet,
+                        cache.network,
+                        address.clone(),
+                        *index,
+                    );
+                    self.modal = Modal::AddressDetail(modal);
+                    return Task::perform(
+                        async move {
+                            daemon
+                                .list_coins(&[], &[])
+                                .await
+                                .map(|res| res.coins)
+                                .map_err(|e| e.into())
+                        },
+                        Message::Coins,
+                    );
                 }
+                Task::none()
             }
+            _ => match self.modal {
+                Modal::VerifyAddress(ref mut m) => m.update(daemon, cache, message),
+                Modal::AddressDetail(ref mut m) => {
+                    m.update(message);
+                    Task::none()
+                }
+                _ => Task::none(),
+            },
         }
     }
 
@@ -312,6 +342,121 @@ impl ShowQrCodeModal {
     }
 }
 
+/// What we know about a receive address: how it's derived from the wallet descriptor and the
+/// coins it received.
+pub struct AddressDetailModal {
+    address: Address,
+    derivation_index: ChildNumber,
+    change_address: Address,
+    // The origin of each key of the receive descriptor at this index, with its alias.
+    key_paths: Vec<(Fingerprint, Option<String>, DerivationPath)>,
+    proof: String,
+    qr_code: Option<qr_code::Data>,
+    // The coins received on this address, once loaded.
+    coins: Option<Vec<Coin>>,
+    warning: Option<Error>,
+}
+
+impl AddressDetailModal {
+    pub fn new(
+        wallet: &Wallet,
+        network: Network,
+        address: Address,
+        derivation_index: ChildNumber,
+    ) -> Self {
+        let secp = secp256k1::Secp256k1::verification_only();
+        let change_address = wallet
+            .main_descriptor
+            .change_descriptor()
+            .derive(derivation_index, &secp)
+            .address(network);
+        // A key may appear in several spending paths.
+        let mut key_paths = Vec::new();
+        for pk in wallet
+            .main_descriptor
+            .receive_descriptor()
+            .as_descriptor_public_key()
+            .iter_pk()
+        {
+            if let Some(key_path) = pk
+                .at_derivation_index(derivation_index.into())
+                .ok()
+                .and_then(|key| {
+                    let fingerprint = key.master_fingerprint();
+                    Some((
+                        fingerprint,
+                        wallet.keys_aliases.get(&fingerprint).cloned(),
+                        key.full_derivation_path()?,
+                    ))
+                })
+            {
+                if !key_paths.contains(&key_path) {
+                    key_paths.push(key_path);
+                }
+            }
+        }
+        Self {
+            proof: ownership_proof(wallet, &address, derivation_index),
+            qr_code: qr_code::Data::new(format!("bitcoin:{}", address)).ok(),
+            address,
+            derivation_index,
+            change_address,
+            key_paths,
+            coins: None,
+            warning: None,
+        }
+    }
+
+    fn view<'a>(&'a self, cache: &'a Cache) -> Element<'a, view::Message> {
+        view::receive::address_detail_modal(
+            self.warning.as_ref(),
+            &self.address,
+            &self.derivation_index,
+            &self.change_address,
+            &self.key_paths,
+            self.qr_code.as_ref(),
+            self.coins.as_deref(),
+            &self.proof,
+            cache.blockheight,
+        )
+    }
+
+    fn update(&mut self, message: Message) {
+        if let Message::Coins(res) = message {
+            match res {
+                Ok(coins) => {
+                    self.coins = Some(
+                        coins
+                            .into_iter()
+                            .filter(|coin| coin.address == self.address)
+                            .collect(),
+                    )
+                }
+                Err(e) => self.warning = Some(e),
+            }
+        }
+    }
+}
+
+/// A statement allowing anyone with the wallet descriptor to check this address belongs to the
+/// wallet, by deriving the receive descriptor at the given index. For instance with Bitcoin Core:
+/// `bitcoin-cli deriveaddresses "<descriptor>" "[<index>,<index>]"`.
+pub fn ownership_proof(wallet: &Wallet, address: &Address, index: ChildNumber) -> String {
+    let descriptor = wallet.main_descriptor.receive_descriptor().to_string();
+    format!(
+        "Address: {address}\n\
+         Derivation index: {index}\n\
+         Receive descriptor: {descriptor}\n\
+         \n\
+         To verify this address belongs to the wallet, derive the receive descriptor at this \
+         index and compare the result with the address. For instance with Bitcoin Core:\n\
+         bitcoin-cli deriveaddresses \"{descriptor}\" \"[{index},{index}]\"\n",
+        address = address,
+        index = index,
+        descriptor = descriptor,
+    )
+}
+
 async fn verify_address(
     hw: std::sync::Arc<dyn async_hwi::HWI + Send + Sync>,
     index: ChildNumber,
@@ -368,4 +513,36 @@ mod tests {
         let panel = sandbox.state();
         assert_eq!(panel.addresses.list, vec![addr]);
     }
+
+    #[test]
+    fn address_detail() {
+        let wallet = Wallet::new(LianaDescriptor::from_str(DESC).unwrap());
+        let index = ChildNumber::from_normal_idx(3).unwrap();
+        let secp = secp256k1::Secp256k1::verification_only();
+        let address = wallet
+            .main_descriptor
+            .receive_descriptor()
+            .derive(index, &secp)
+            .address(Network::Testnet);
+        let modal = AddressDetailModal::new(&wallet, Network::Testnet, address.clone(), index);
+
+        // The two keys of the primary path, the first one also being the recovery key.
+        assert_eq!(
+            modal
+                .key_paths
+                .iter()
+                .map(|(fg, _, path)| format!("{}/{}", fg, path))
+                .collect::<Vec<_>>(),
+            vec![
+                "ffd63c8d/48'/1'/0'/2'/0/3",
+                "de6eb005/48'/1'/0'/2'/0/3",
+                "ffd63c8d/48'/1'/0'/2'/2/3",
+            ]
+        );
+        assert_ne!(modal.change_address, address);
+        assert!(modal.proof.contains(&format!(
+            "deriveaddresses \"{}\" \"[3,3]\"",
+            wallet.main_descriptor.receive_descriptor()
+        )));
+    }
 }
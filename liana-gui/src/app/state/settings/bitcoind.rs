@@ -5,7 +5,7 @@ use std::str::FromStr;
 use std::sync::Arc;
 
 use chrono::{NaiveDate, Utc};
-use iced::{clipboard, Task};
+use iced::{Subscription, Task};
 use tracing::info;
 
 use liana::miniscript::bitcoin::Network;
@@ -16,11 +16,15 @@ use lianad::config::{
 use liana_ui::{component::form, widget::Element};
 
 use crate::{
-    app::{cache::Cache, error::Error, message::Message, state::settings::State, view},
+    app::{
+        cache::Cache, error::Error, message::Message, state::settings::State, view, wallet::Wallet,
+    },
+    clipboard,
     daemon::Daemon,
+    download,
     node::{
-        bitcoind::{RpcAuthType, RpcAuthValues},
-        NodeType,
+        bitcoind::{self, RpcAuthType, RpcAuthValues},
+        service, NodeType,
     },
 };
 
@@ -32,6 +36,37 @@ pub struct BitcoindSettingsState {
     bitcoind_settings: Option<BitcoindSettings>,
     electrum_settings: Option<ElectrumSettings>,
     rescan_settings: RescanSetting,
+    // Whether the managed bitcoind service is installed, if bitcoind is managed by Liana.
+    bitcoind_service: Option<bool>,
+    bitcoind_service_processing: bool,
+    // The versions of the managed bitcoind, if bitcoind is managed by Liana.
+    bitcoind_versions: Option<BitcoindVersions>,
+}
+
+#[derive(Debug)]
+struct BitcoindVersions {
+    datadir_path: PathBuf,
+    network: Network,
+    installed: Vec<&'static str>,
+    selected: Option<&'static str>,
+    latest_release: Option<String>,
+    download_progress: Option<f32>,
+    processing: bool,
+    restart_required: bool,
+}
+
+// Select the managed bitcoind version to run and have the service running the node, if any, use
+// it.
+fn select_bitcoind_version(
+    datadir_path: &PathBuf,
+    network: Network,
+    version: &'static str,
+) -> Result<&'static str, Error> {
+    bitcoind::select_version(datadir_path, &network, version).map_err(Error::Unexpected)?;
+    if service::is_installed(datadir_path, network) {
+        service::install(datadir_path, network).map_err(|e| Error::Unexpected(e.to_string()))?;
+    }
+    Ok(version)
 }
 
 impl BitcoindSettingsState {
@@ -54,9 +89,27 @@ impl BitcoindSettingsState {
                 }
                 _ => (None, None),
             };
+        let bitcoind_service = if bitcoind_is_internal && bitcoind_config.is_some() {
+            Some(service::is_installed(&cache.datadir_path, cache.network))
+        } else {
+            None
+        };
+        let bitcoind_versions = bitcoind_service.map(|_| BitcoindVersions {
+            datadir_path: cache.datadir_path.clone(),
+            network: cache.network,
+            installed: bitcoind::installed_versions(&cache.datadir_path),
+            selected: bitcoind::selected_version(&cache.datadir_path),
+            latest_release: None,
+            download_progress: None,
+            processing: false,
+            restart_required: false,
+        });
         BitcoindSettingsState {
             warning: None,
             config_updated: false,
+            bitcoind_service,
+            bitcoind_service_processing: false,
+            bitcoind_versions,
             bitcoind_settings: bitcoind_config.map(|bitcoind_config| {
                 BitcoindSettings::new(
                     configured_node_type,
@@ -156,11 +209,148 @@ impl State for BitcoindSettingsState {
             Message::View(view::Message::Settings(view::SettingsMessage::RescanSettings(msg))) => {
                 return self.rescan_settings.update(daemon, cache, msg);
             }
+            Message::View(view::Message::Settings(
+                view::SettingsMessage::BitcoindServiceToggled(enable),
+            )) => {
+                if self.bitcoind_service.is_some() && !self.bitcoind_service_processing {
+                    self.bitcoind_service_processing = true;
+                    let datadir_path = cache.datadir_path.clone();
+                    let network = cache.network;
+                    return Task::perform(
+                        async move {
+                            if enable {
+                                service::install(&datadir_path, network)
+                            } else {
+                                service::uninstall(network)
+                            }
+                            .map(|_| enable)
+                            .map_err(|e| Error::Unexpected(e.to_string()))
+                        },
+                        Message::BitcoindService,
+                    );
+                }
+            }
+            Message::View(view::Message::Settings(view::SettingsMessage::BitcoindUpgrade)) => {
+                if let Some(versions) = &mut self.bitcoind_versions {
+                    if !versions.processing {
+                        info!("Downloading bitcoind version {}...", bitcoind::VERSION);
+                        versions.processing = true;
+                        versions.download_progress = Some(0.0);
+                    }
+                }
+            }
+            Message::BitcoindDownload(progress) => {
+                if let Some(versions) = &mut self.bitcoind_versions {
+                    match progress {
+                        Ok(download::Progress::Downloading(progress)) => {
+                            versions.download_progress = Some(progress);
+                        }
+                        Ok(download::Progress::Finished(bytes)) => {
+                            info!("Download of bitcoind complete.");
+                            versions.download_progress = None;
+                            let datadir_path = versions.datadir_path.clone();
+                            let network = versions.network;
+                            return Task::perform(
+                                async move {
+                                    bitcoind::install_bitcoind(
+                                        &bitcoind::internal_bitcoind_directory(&datadir_path),
+                                        &bytes,
+                                    )
+                                    .map_err(|e| Error::Unexpected(e.to_string()))?;
+                                    select_bitcoind_version(
+                                        &datadir_path,
+                                        network,
+                                        bitcoind::VERSION,
+                                    )
+                                },
+                                Message::BitcoindVersion,
+                            );
+                        }
+                        Err(e) => {
+                            versions.download_progress = None;
+                            versions.processing = false;
+                            self.warning = Some(Error::Unexpected(e.to_string()));
+                        }
+                    }
+                }
+            }
+            Message::View(view::Message::Settings(
+                view::SettingsMessage::BitcoindVersionSelected(version),
+            )) => {
+                if let Some(versions) = &mut self.bitcoind_versions {
+                    if !versions.processing {
+                        versions.processing = true;
+                        let datadir_path = versions.datadir_path.clone();
+                        let network = versions.network;
+                        return Task::perform(
+                            async move { select_bitcoind_version(&datadir_path, network, version) },
+                            Message::BitcoindVersion,
+                        );
+                    }
+                }
+            }
+            Message::BitcoindVersion(res) => {
+                if let Some(versions) = &mut self.bitcoind_versions {
+                    versions.processing = false;
+                    versions.installed = bitcoind::installed_versions(&versions.datadir_path);
+                    match res {
+                        Ok(version) => {
+                            self.warning = None;
+                            versions.restart_required = true;
+                            versions.selected = Some(version);
+                        }
+                        Err(e) => self.warning = Some(e),
+                    }
+                }
+            }
+            Message::BitcoindRelease(res) => match res {
+                Ok(release) => {
+                    if let Some(versions) = &mut self.bitcoind_versions {
+                        versions.latest_release = Some(release);
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to check the latest Bitcoin Core release: {}", e),
+            },
+            Message::BitcoindService(res) => {
+                self.bitcoind_service_processing = false;
+                match res {
+                    Ok(installed) => {
+                        self.warning = None;
+                        self.bitcoind_service = Some(installed);
+                    }
+                    Err(e) => self.warning = Some(e),
+                }
+            }
             _ => {}
         };
         Task::none()
     }
 
+    fn subscription(&self) -> Subscription<Message> {
+        match &self.bitcoind_versions {
+            Some(BitcoindVersions {
+                download_progress: Some(_),
+                ..
+            }) => download::file(0, bitcoind::download_url())
+                .map(|(_, progress)| Message::BitcoindDownload(progress)),
+            _ => Subscription::none(),
+        }
+    }
+
+    fn reload(
+        &mut self,
+        _daemon: Arc<dyn Daemon + Sync + Send>,
+        _wallet: Arc<Wallet>,
+    ) -> Task<Message> {
+        if self.bitcoind_versions.is_some() {
+            Task::perform(bitcoind::latest_release(), |res| {
+                Message::BitcoindRelease(res.map_err(Error::Unexpected))
+            })
+        } else {
+            Task::none()
+        }
+    }
+
     fn view<'a>(&'a self, cache: &'a Cache) -> Element<'a, view::Message> {
         let can_edit_bitcoind_settings =
             self.bitcoind_settings.is_some() && !self.rescan_settings.processing;
@@ -196,6 +386,22 @@ impl State for BitcoindSettingsState {
                         },
                     ))
                 }
+                if let Some(versions) = &self.bitcoind_versions {
+                    setting_panels.push(view::settings::bitcoind_versions(
+                        &versions.installed,
+                        versions.selected,
+                        versions.latest_release.as_ref(),
+                        versions.download_progress,
+                        versions.processing,
+                        versions.restart_required,
+                    ));
+                }
+                if let Some(installed) = self.bitcoind_service {
+                    setting_panels.push(view::settings::bitcoind_service(
+                        installed,
+                        self.bitcoind_service_processing,
+                    ));
+                }
                 setting_panels.push(self.rescan_settings.view(cache, can_do_rescan).map(
                     move |msg| view::Message::Settings(view::SettingsMessage::RescanSettings(msg)),
                 ));
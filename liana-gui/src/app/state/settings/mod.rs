@@ -16,12 +16,15 @@ use crate::{
     app::{
         cache::Cache,
         error::Error,
+        lock::PASSPHRASE_MIN_LEN,
         message::Message,
+        settings::global::{DisplaySettings, LockSettings},
         state::State,
         view::{self},
         wallet::Wallet,
     },
-    daemon::{Daemon, DaemonBackend},
+    daemon::{model::HealthCheckResult, Daemon, DaemonBackend},
+    uri_handler,
 };
 
 pub struct SettingsState {
@@ -79,6 +82,10 @@ impl State for SettingsState {
                 self.setting = Some(BackendSettingsState::new().into());
                 Task::none()
             }
+            Message::View(view::Message::Settings(view::SettingsMessage::EditDisplaySettings)) => {
+                self.setting = Some(DisplaySettingsState::new(cache.datadir_path.clone()).into());
+                Task::none()
+            }
             Message::View(view::Message::Settings(view::SettingsMessage::AboutSection)) => {
                 self.setting = Some(AboutSettingsState::default().into());
                 let wallet = self.wallet.clone();
@@ -87,6 +94,14 @@ impl State for SettingsState {
                     .map(|s| s.reload(daemon, wallet))
                     .unwrap_or_else(Task::none)
             }
+            Message::View(view::Message::Settings(view::SettingsMessage::DiagnosticsSection)) => {
+                self.setting = Some(DiagnosticsSettingsState::default().into());
+                let wallet = self.wallet.clone();
+                self.setting
+                    .as_mut()
+                    .map(|s| s.reload(daemon, wallet))
+                    .unwrap_or_else(Task::none)
+            }
             Message::View(view::Message::Settings(view::SettingsMessage::EditWalletSettings)) => {
                 self.setting = Some(
                     WalletSettingsState::new(self.data_dir.clone(), self.wallet.clone()).into(),
@@ -196,6 +211,303 @@ impl From<AboutSettingsState> for Box<dyn State> {
     }
 }
 
+/// The report of the `healthcheck` command of the daemon, run again each time the section is
+/// opened.
+#[derive(Default)]
+pub struct DiagnosticsSettingsState {
+    report: Option<HealthCheckResult>,
+    running: bool,
+    warning: Option<Error>,
+}
+
+impl State for DiagnosticsSettingsState {
+    fn view<'a>(&'a self, cache: &'a Cache) -> Element<'a, view::Message> {
+        view::settings::diagnostics_section(
+            cache,
+            self.warning.as_ref(),
+            self.report.as_ref(),
+            self.running,
+        )
+    }
+
+    fn update(
+        &mut self,
+        _daemon: Arc<dyn Daemon + Sync + Send>,
+        _cache: &Cache,
+        message: Message,
+    ) -> Task<Message> {
+        if let Message::HealthCheck(res) = message {
+            self.running = false;
+            match res {
+                Ok(report) => {
+                    self.report = Some(report);
+                    self.warning = None;
+                }
+                Err(e) => self.warning = Some(e),
+            }
+        }
+
+        Task::none()
+    }
+
+    fn reload(
+        &mut self,
+        daemon: Arc<dyn Daemon + Sync + Send>,
+        _wallet: Arc<Wallet>,
+    ) -> Task<Message> {
+        self.running = true;
+        Task::perform(
+            async move { daemon.health_check().await.map_err(|e| e.into()) },
+            Message::HealthCheck,
+        )
+    }
+}
+
+impl From<DiagnosticsSettingsState> for Box<dyn State> {
+    fn from(s: DiagnosticsSettingsState) -> Box<dyn State> {
+        Box::new(s)
+    }
+}
+
+pub struct DisplaySettingsState {
+    global_datadir: PathBuf,
+    settings: DisplaySettings,
+    // Whether Liana handles the bitcoin: links, if it's supported on this platform.
+    uri_handler: Option<bool>,
+    uri_handler_processing: bool,
+    lock: Option<LockSettings>,
+    lock_passphrase: form::Value<String>,
+    lock_passphrase_confirmation: form::Value<String>,
+    lock_processing: bool,
+    warning: Option<Error>,
+}
+
+impl DisplaySettingsState {
+    pub fn new(global_datadir: PathBuf) -> Self {
+        let (settings, warning) = match DisplaySettings::from_file(&global_datadir) {
+            Ok(settings) => (settings, None),
+            Err(e) => (
+                DisplaySettings::default(),
+                Some(Error::Unexpected(format!(
+                    "Failed to read display settings: {}",
+                    e
+                ))),
+            ),
+        };
+        let (lock, warning) = match LockSettings::from_file(&global_datadir) {
+            Ok(lock) => (lock, warning),
+            Err(e) => (
+                None,
+                Some(Error::Unexpected(format!(
+                    "Failed to read lock settings: {}",
+                    e
+                ))),
+            ),
+        };
+        let uri_handler = if cfg!(any(target_os = "linux", target_os = "windows")) {
+            Some(uri_handler::is_registered())
+        } else {
+            None
+        };
+        Self {
+            global_datadir,
+            settings,
+            uri_handler,
+            uri_handler_processing: false,
+            lock,
+            lock_passphrase: form::Value::default(),
+            lock_passphrase_confirmation: form::Value::default(),
+            lock_processing: false,
+            warning,
+        }
+    }
+
+    fn save(&mut self) {
+        self.settings.apply();
+        self.warning = self
+            .settings
+            .to_file(&self.global_datadir)
+            .err()
+            .map(|e| Error::Unexpected(format!("Failed to save display settings: {}", e)));
+    }
+}
+
+impl State for DisplaySettingsState {
+    fn view<'a>(&'a self, cache: &'a Cache) -> Element<'a, view::Message> {
+        view::settings::display_section(
+            cache,
+            &self.settings,
+            self.uri_handler,
+            self.uri_handler_processing,
+            self.lock.as_ref(),
+            &self.lock_passphrase,
+            &self.lock_passphrase_confirmation,
+            self.lock_processing,
+            self.warning.as_ref(),
+        )
+    }
+
+    fn update(
+        &mut self,
+        _daemon: Arc<dyn Daemon + Sync + Send>,
+        _cache: &Cache,
+        message: Message,
+    ) -> Task<Message> {
+        match message {
+            Message::View(view::Message::Settings(view::SettingsMessage::DisplayUnitSelected(
+                unit,
+            ))) => {
+                self.settings.unit = unit;
+                self.save();
+            }
+            Message::View(view::Message::Settings(
+                view::SettingsMessage::NumberFormatSelected(format),
+            )) => {
+                self.settings.number_format = Some(format);
+                self.save();
+            }
+            Message::View(view::Message::Settings(view::SettingsMessage::LanguageSelected(
+                language,
+            ))) => {
+                self.settings.language = Some(language);
+                self.save();
+            }
+            Message::View(view::Message::Settings(view::SettingsMessage::UiScaleSelected(
+                scale,
+            ))) => {
+                self.settings.ui_scale = scale;
+                self.save();
+            }
+            Message::View(view::Message::Settings(
+                view::SettingsMessage::ClipboardClearSelected(delay),
+            )) => {
+                self.settings.clipboard_clear_after = delay;
+                self.save();
+            }
+            Message::View(view::Message::Settings(
+                view::SettingsMessage::LockPassphraseEdited(passphrase),
+            )) => {
+                self.lock_passphrase.valid = passphrase.chars().count() >= PASSPHRASE_MIN_LEN;
+                self.lock_passphrase.value = passphrase;
+                self.lock_passphrase_confirmation.valid =
+                    self.lock_passphrase_confirmation.value.is_empty()
+                        || self.lock_passphrase_confirmation.value == self.lock_passphrase.value;
+            }
+            Message::View(view::Message::Settings(
+                view::SettingsMessage::LockPassphraseConfirmationEdited(passphrase),
+            )) => {
+                self.lock_passphrase_confirmation.valid = passphrase == self.lock_passphrase.value;
+                self.lock_passphrase_confirmation.value = passphrase;
+            }
+            Message::View(view::Message::Settings(view::SettingsMessage::SaveLockPassphrase)) => {
+                if !self.lock_processing
+                    && self.lock_passphrase.valid
+                    && !self.lock_passphrase.value.is_empty()
+                    && self.lock_passphrase_confirmation.value == self.lock_passphrase.value
+                {
+                    self.lock_processing = true;
+                    let global_datadir = self.global_datadir.clone();
+                    let passphrase = self.lock_passphrase.value.clone();
+                    // Keep the delay when changing the passphrase.
+                    let after = self.lock.as_ref().and_then(|l| l.after);
+                    return Task::perform(
+                        async move {
+                            let lock = LockSettings::new(&passphrase, after)
+                                .map_err(|e| Error::Unexpected(e.to_string()))?;
+                            save_lock(global_datadir, Some(lock)).await
+                        },
+                        Message::LockUpdated,
+                    );
+                }
+            }
+            Message::View(view::Message::Settings(view::SettingsMessage::RemoveLock)) => {
+                if !self.lock_processing {
+                    self.lock_processing = true;
+                    return Task::perform(
+                        save_lock(self.global_datadir.clone(), None),
+                        Message::LockUpdated,
+                    );
+                }
+            }
+            Message::View(view::Message::Settings(view::SettingsMessage::LockDelaySelected(
+                after,
+            ))) => {
+                if let Some(lock) = self.lock.as_ref().filter(|_| !self.lock_processing) {
+                    self.lock_processing = true;
+                    return Task::perform(
+                        save_lock(
+                            self.global_datadir.clone(),
+                            Some(LockSettings {
+                                after,
+                                ..lock.clone()
+                            }),
+                        ),
+                        Message::LockUpdated,
+                    );
+                }
+            }
+            Message::LockUpdated(res) => {
+                self.lock_processing = false;
+                match res {
+                    Ok(lock) => {
+                        self.warning = None;
+                        self.lock = lock;
+                        self.lock_passphrase = form::Value::default();
+                        self.lock_passphrase_confirmation = form::Value::default();
+                    }
+                    Err(e) => self.warning = Some(e),
+                }
+            }
+            Message::View(view::Message::Settings(
+                view::SettingsMessage::BitcoinUriHandlerToggled(enable),
+            )) => {
+                if self.uri_handler.is_some() && !self.uri_handler_processing {
+                    self.uri_handler_processing = true;
+                    return Task::perform(
+                        async move {
+                            if enable {
+                                uri_handler::register()
+                            } else {
+                                uri_handler::unregister()
+                            }
+                            .map(|_| enable)
+                            .map_err(|e| Error::Unexpected(e.to_string()))
+                        },
+                        Message::UriHandler,
+                    );
+                }
+            }
+            Message::UriHandler(res) => {
+                self.uri_handler_processing = false;
+                match res {
+                    Ok(registered) => {
+                        self.warning = None;
+                        self.uri_handler = Some(registered);
+                    }
+                    Err(e) => self.warning = Some(e),
+                }
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+}
+
+impl From<DisplaySettingsState> for Box<dyn State> {
+    fn from(s: DisplaySettingsState) -> Box<dyn State> {
+        Box::new(s)
+    }
+}
+
+async fn save_lock(
+    global_datadir: PathBuf,
+    lock: Option<LockSettings>,
+) -> Result<Option<LockSettings>, Error> {
+    LockSettings::to_file(lock.as_ref(), &global_datadir)
+        .map_err(|e| Error::Unexpected(format!("Failed to save lock settings: {}", e)))?;
+    Ok(lock)
+}
+
 #[derive(Default)]
 pub struct BackendSettingsState {
     email_form: form::Value<String>,
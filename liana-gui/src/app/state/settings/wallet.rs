@@ -1,17 +1,20 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::convert::From;
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use iced::{Subscription, Task};
+use iced::{
+    widget::qr_code::{self, ErrorCorrection},
+    Subscription, Task,
+};
 
 use liana::{
-    descriptors::LianaDescriptor,
-    miniscript::bitcoin::{bip32::Fingerprint, Network},
+    descriptors::{key_sheets, LianaDescriptor, WalletPolicy},
+    miniscript::bitcoin::{bip32::Fingerprint, Amount, Network},
 };
 
 use liana_ui::{
-    component::{form, modal},
+    component::{amount, form, modal},
     widget::Element,
 };
 
@@ -21,8 +24,13 @@ use crate::{
     },
     daemon::{Daemon, DaemonBackend},
     hw::{HardwareWallet, HardwareWalletConfig, HardwareWallets},
+    remote_signer::{self, RemoteSignerError},
+    utils,
 };
 
+/// A shorter PIN is too easy to type by mistake.
+const MIN_PIN_LENGTH: usize = 4;
+
 pub struct WalletSettingsState {
     data_dir: PathBuf,
     warning: Option<Error>,
@@ -32,6 +40,31 @@ pub struct WalletSettingsState {
     modal: Option<RegisterWalletModal>,
     processing: bool,
     updated: bool,
+    key_sheets_dir: Option<PathBuf>,
+    descriptor_export: Option<DescriptorExport>,
+    spend_confirmation_threshold: form::Value<String>,
+    spend_confirmation_pin: form::Value<String>,
+    block_explorer_url: form::Value<String>,
+    remote_signer_url: form::Value<String>,
+    remote_signer_token: form::Value<String>,
+}
+
+/// The descriptor in the formats it can be exported as.
+pub struct DescriptorExport {
+    policy: Option<WalletPolicy>,
+    /// None if the descriptor is too large to fit in a QR code.
+    qr: Option<qr_code::Data>,
+    saved: Option<PathBuf>,
+}
+
+impl DescriptorExport {
+    fn new(descriptor: &LianaDescriptor) -> Self {
+        Self {
+            policy: descriptor.wallet_policy(),
+            qr: qr_code::Data::with_error_correction(descriptor.to_ur(), ErrorCorrection::Low).ok(),
+            saved: None,
+        }
+    }
 }
 
 impl WalletSettingsState {
@@ -40,14 +73,61 @@ impl WalletSettingsState {
             data_dir,
             descriptor: wallet.main_descriptor.clone(),
             keys_aliases: Self::keys_aliases(&wallet),
-            wallet,
             warning: None,
             modal: None,
             processing: false,
             updated: false,
+            key_sheets_dir: None,
+            descriptor_export: None,
+            spend_confirmation_threshold: Self::spend_confirmation_threshold(&wallet),
+            spend_confirmation_pin: form::Value::default(),
+            block_explorer_url: Self::block_explorer_url(&wallet),
+            remote_signer_url: Self::remote_signer_form(&wallet).0,
+            remote_signer_token: Self::remote_signer_form(&wallet).1,
+            wallet,
         }
     }
 
+    fn spend_confirmation_threshold(wallet: &Wallet) -> form::Value<String> {
+        form::Value {
+            value: wallet
+                .spend_confirmation
+                .as_ref()
+                .map(|c| amount::amount_as_input(Amount::from_sat(c.threshold)))
+                .unwrap_or_default(),
+            valid: true,
+        }
+    }
+
+    fn block_explorer_url(wallet: &Wallet) -> form::Value<String> {
+        form::Value {
+            value: match &wallet.block_explorer {
+                Some(settings::BlockExplorer::SelfHosted(url)) => url.clone(),
+                _ => String::new(),
+            },
+            valid: true,
+        }
+    }
+
+    // The URL and the token of the remote signer.
+    fn remote_signer_form(wallet: &Wallet) -> (form::Value<String>, form::Value<String>) {
+        let (url, token) = wallet
+            .remote_signer
+            .as_ref()
+            .map(|s| (s.url.clone(), s.token.clone()))
+            .unwrap_or_default();
+        (
+            form::Value {
+                value: url,
+                valid: true,
+            },
+            form::Value {
+                value: token,
+                valid: true,
+            },
+        )
+    }
+
     fn keys_aliases(wallet: &Wallet) -> Vec<(Fingerprint, form::Value<String>)> {
         let mut keys_aliases: Vec<(Fingerprint, form::Value<String>)> = wallet
             .keys_aliases
@@ -84,11 +164,31 @@ impl State for WalletSettingsState {
             &self.keys_aliases,
             self.processing,
             self.updated,
+            self.key_sheets_dir.as_deref(),
+            self.wallet.spend_confirmation.as_ref(),
+            &self.spend_confirmation_threshold,
+            &self.spend_confirmation_pin,
+            &self.block_explorer_url,
+            self.wallet.remote_signer.as_ref(),
+            &self.remote_signer_url,
+            &self.remote_signer_token,
         );
         if let Some(m) = &self.modal {
             modal::Modal::new(content, m.view())
                 .on_blur(Some(view::Message::Close))
                 .into()
+        } else if let Some(export) = &self.descriptor_export {
+            modal::Modal::new(
+                content,
+                view::settings::descriptor_export_modal(
+                    &self.descriptor,
+                    export.policy.as_ref(),
+                    export.qr.as_ref(),
+                    export.saved.as_deref(),
+                ),
+            )
+            .on_blur(Some(view::Message::Close))
+            .into()
         } else {
             content
         }
@@ -117,6 +217,12 @@ impl State for WalletSettingsState {
                     match res {
                         Ok(wallet) => {
                             self.keys_aliases = Self::keys_aliases(&wallet);
+                            self.spend_confirmation_threshold =
+                                Self::spend_confirmation_threshold(&wallet);
+                            self.spend_confirmation_pin = form::Value::default();
+                            self.block_explorer_url = Self::block_explorer_url(&wallet);
+                            (self.remote_signer_url, self.remote_signer_token) =
+                                Self::remote_signer_form(&wallet);
                             self.wallet = wallet;
                             self.updated = true;
                         }
@@ -155,8 +261,195 @@ impl State for WalletSettingsState {
                     Message::WalletUpdated,
                 )
             }
+            Message::View(view::Message::Settings(view::SettingsMessage::AccentColorSelected(
+                color,
+            ))) => Task::perform(
+                update_accent_color(
+                    self.data_dir.clone(),
+                    cache.network,
+                    self.wallet.clone(),
+                    color,
+                ),
+                Message::WalletUpdated,
+            ),
+            Message::View(view::Message::Settings(
+                view::SettingsMessage::SpendConfirmationThresholdEdited(value),
+            )) => {
+                self.spend_confirmation_threshold.valid =
+                    value.is_empty() || amount::parse_amount(&value).is_ok();
+                self.spend_confirmation_threshold.value = value;
+                Task::none()
+            }
+            Message::View(view::Message::Settings(
+                view::SettingsMessage::SpendConfirmationPinEdited(value),
+            )) => {
+                self.spend_confirmation_pin.valid =
+                    value.is_empty() || value.chars().count() >= MIN_PIN_LENGTH;
+                self.spend_confirmation_pin.value = value;
+                Task::none()
+            }
+            Message::View(view::Message::Settings(
+                view::SettingsMessage::SaveSpendConfirmation,
+            )) => {
+                let threshold = match amount::parse_amount(&self.spend_confirmation_threshold.value)
+                {
+                    Ok(threshold) => threshold,
+                    Err(_) => {
+                        self.spend_confirmation_threshold.valid = false;
+                        return Task::none();
+                    }
+                };
+                if !self.spend_confirmation_pin.valid {
+                    return Task::none();
+                }
+                let pin =
+                    Some(self.spend_confirmation_pin.value.as_str()).filter(|p| !p.is_empty());
+                self.updated = false;
+                Task::perform(
+                    update_spend_confirmation(
+                        self.data_dir.clone(),
+                        cache.network,
+                        self.wallet.clone(),
+                        Some(settings::SpendConfirmation::new(threshold, pin)),
+                    ),
+                    Message::WalletUpdated,
+                )
+            }
+            Message::View(view::Message::Settings(
+                view::SettingsMessage::RemoveSpendConfirmation,
+            )) => {
+                self.updated = false;
+                Task::perform(
+                    update_spend_confirmation(
+                        self.data_dir.clone(),
+                        cache.network,
+                        self.wallet.clone(),
+                        None,
+                    ),
+                    Message::WalletUpdated,
+                )
+            }
+            Message::View(view::Message::Settings(
+                view::SettingsMessage::BlockExplorerUrlEdited(value),
+            )) => {
+                let url = value.trim();
+                self.block_explorer_url.valid =
+                    url.is_empty() || url.starts_with("http://") || url.starts_with("https://");
+                self.block_explorer_url.value = value;
+                Task::none()
+            }
+            Message::View(view::Message::Settings(
+                view::SettingsMessage::BlockExplorerSelected(explorer),
+            )) => {
+                self.updated = false;
+                Task::perform(
+                    update_block_explorer(
+                        self.data_dir.clone(),
+                        cache.network,
+                        self.wallet.clone(),
+                        explorer,
+                    ),
+                    Message::WalletUpdated,
+                )
+            }
+            Message::View(view::Message::Settings(
+                view::SettingsMessage::RemoteSignerUrlEdited(value),
+            )) => {
+                self.remote_signer_url.valid =
+                    value.trim().is_empty() || remote_signer::check_url(&value).is_ok();
+                self.remote_signer_url.value = value;
+                Task::none()
+            }
+            Message::View(view::Message::Settings(
+                view::SettingsMessage::RemoteSignerTokenEdited(value),
+            )) => {
+                self.remote_signer_token.valid = true;
+                self.remote_signer_token.value = value;
+                Task::none()
+            }
+            Message::View(view::Message::Settings(view::SettingsMessage::SaveRemoteSigner)) => {
+                if remote_signer::check_url(&self.remote_signer_url.value).is_err() {
+                    self.remote_signer_url.valid = false;
+                    return Task::none();
+                }
+                if self.remote_signer_token.value.trim().is_empty() {
+                    self.remote_signer_token.valid = false;
+                    return Task::none();
+                }
+                self.warning = None;
+                self.processing = true;
+                self.updated = false;
+                Task::perform(
+                    update_remote_signer(
+                        self.data_dir.clone(),
+                        cache.network,
+                        self.wallet.clone(),
+                        Some((
+                            self.remote_signer_url.value.clone(),
+                            self.remote_signer_token.value.trim().to_string(),
+                        )),
+                    ),
+                    Message::WalletUpdated,
+                )
+            }
+            Message::View(view::Message::Settings(view::SettingsMessage::RemoveRemoteSigner)) => {
+                self.updated = false;
+                Task::perform(
+                    update_remote_signer(
+                        self.data_dir.clone(),
+                        cache.network,
+                        self.wallet.clone(),
+                        None,
+                    ),
+                    Message::WalletUpdated,
+                )
+            }
+            Message::View(view::Message::Settings(view::SettingsMessage::ExportKeySheets)) => {
+                self.key_sheets_dir = None;
+                Task::perform(
+                    export_key_sheets(self.descriptor.clone(), self.wallet.keys_aliases.clone()),
+                    Message::KeySheetsExported,
+                )
+            }
+            Message::KeySheetsExported(res) => {
+                match res {
+                    Ok(dir) => self.key_sheets_dir = dir,
+                    Err(e) => self.warning = Some(e),
+                }
+                Task::none()
+            }
+            Message::View(view::Message::Settings(view::SettingsMessage::ExportDescriptor)) => {
+                self.descriptor_export = Some(DescriptorExport::new(&self.descriptor));
+                Task::none()
+            }
+            Message::View(view::Message::Settings(view::SettingsMessage::SaveDescriptor)) => {
+                Task::perform(
+                    save_descriptor(self.descriptor.clone()),
+                    Message::DescriptorSaved,
+                )
+            }
+            Message::View(view::Message::Settings(view::SettingsMessage::PrintDescriptor)) => {
+                Task::perform(
+                    print_descriptor(self.descriptor.clone(), self.wallet.name.clone()),
+                    |res| Message::DescriptorSaved(res.map(|_| None)),
+                )
+            }
+            Message::DescriptorSaved(res) => {
+                match res {
+                    Ok(path) => {
+                        if let Some(export) = &mut self.descriptor_export {
+                            if path.is_some() {
+                                export.saved = path;
+                            }
+                        }
+                    }
+                    Err(e) => self.warning = Some(e),
+                }
+                Task::none()
+            }
             Message::View(view::Message::Close) => {
                 self.modal = None;
+                self.descriptor_export = None;
                 Task::none()
             }
             Message::View(view::Message::Settings(view::SettingsMessage::RegisterWallet)) => {
@@ -190,6 +483,81 @@ impl State for WalletSettingsState {
     }
 }
 
+/// Write a key sheet for each signer of the descriptor in a folder chosen by the user.
+async fn export_key_sheets(
+    descriptor: LianaDescriptor,
+    aliases: HashMap<Fingerprint, String>,
+) -> Result<Option<PathBuf>, Error> {
+    let dir = match rfd::AsyncFileDialog::new()
+        .set_title("Choose a folder for the key sheets...")
+        .pick_folder()
+        .await
+    {
+        Some(dir) => dir.path().to_path_buf(),
+        None => return Ok(None),
+    };
+    for sheet in key_sheets(&descriptor, &aliases) {
+        let path = dir.join(format!("liana-key-sheet-{}.txt", sheet.fingerprint));
+        std::fs::write(&path, sheet.to_string())
+            .map_err(|e| Error::Unexpected(format!("Failed to write {}: {}", path.display(), e)))?;
+    }
+    Ok(Some(dir))
+}
+
+// The descriptor and its wallet policy, as a text document.
+fn descriptor_document(descriptor: &LianaDescriptor) -> String {
+    let mut doc = format!("Descriptor:\n\n{}\n", descriptor);
+    if let Some(policy) = descriptor.wallet_policy() {
+        doc.push_str(&format!("\nWallet policy (BIP388):\n\n{}\n", policy));
+    }
+    doc
+}
+
+async fn save_descriptor(descriptor: LianaDescriptor) -> Result<Option<PathBuf>, Error> {
+    let path = match rfd::AsyncFileDialog::new()
+        .set_title("Save the descriptor...")
+        .set_file_name("liana-descriptor.txt")
+        .save_file()
+        .await
+    {
+        Some(file) => file.path().to_path_buf(),
+        None => return Ok(None),
+    };
+    std::fs::write(&path, descriptor_document(&descriptor))
+        .map_err(|e| Error::Unexpected(format!("Failed to write {}: {}", path.display(), e)))?;
+    Ok(Some(path))
+}
+
+// Write a printable page with the descriptor and open it in the default application of the
+// system, from which it can be printed.
+async fn print_descriptor(descriptor: LianaDescriptor, wallet_name: String) -> Result<(), Error> {
+    let escape = |s: &str| {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    };
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{name}</title>\
+         <style>pre {{ white-space: pre-wrap; word-break: break-all; }}</style></head><body>\
+         <h1>{name}</h1><h2>Descriptor</h2><pre>{desc}</pre>",
+        name = escape(&wallet_name),
+        desc = escape(&descriptor.to_string()),
+    );
+    if let Some(policy) = descriptor.wallet_policy() {
+        page.push_str(&format!(
+            "<h2>Wallet policy (BIP388)</h2><pre>{}</pre>",
+            escape(&policy.to_string())
+        ));
+    }
+    page.push_str("</body></html>\n");
+
+    let path = std::env::temp_dir().join("liana-descriptor.html");
+    std::fs::write(&path, page)
+        .map_err(|e| Error::Unexpected(format!("Failed to write {}: {}", path.display(), e)))?;
+    utils::open(path.as_os_str())
+        .map_err(|e| Error::Unexpected(format!("Failed to open {}: {}", path.display(), e)))
+}
+
 impl From<WalletSettingsState> for Box<dyn State> {
     fn from(s: WalletSettingsState) -> Box<dyn State> {
         Box::new(s)
@@ -405,3 +773,109 @@ async fn update_keys_aliases(
 
     Ok(Arc::new(wallet))
 }
+
+// The accent color is only stored locally, also for the wallets using the remote backend.
+async fn update_accent_color(
+    data_dir: PathBuf,
+    network: Network,
+    wallet: Arc<Wallet>,
+    color: settings::AccentColor,
+) -> Result<Arc<Wallet>, Error> {
+    let mut settings = settings::Settings::from_file(data_dir.clone(), network)?;
+    let checksum = wallet.descriptor_checksum();
+    if let Some(wallet_setting) = settings
+        .wallets
+        .iter_mut()
+        .find(|w| w.descriptor_checksum == checksum)
+    {
+        wallet_setting.accent_color = Some(color);
+    }
+    settings.to_file(data_dir, network)?;
+
+    Ok(Arc::new(
+        wallet.as_ref().clone().with_accent_color(Some(color)),
+    ))
+}
+
+// As the accent color, the spend confirmation is only stored locally.
+async fn update_spend_confirmation(
+    data_dir: PathBuf,
+    network: Network,
+    wallet: Arc<Wallet>,
+    spend_confirmation: Option<settings::SpendConfirmation>,
+) -> Result<Arc<Wallet>, Error> {
+    let mut settings = settings::Settings::from_file(data_dir.clone(), network)?;
+    let checksum = wallet.descriptor_checksum();
+    if let Some(wallet_setting) = settings
+        .wallets
+        .iter_mut()
+        .find(|w| w.descriptor_checksum == checksum)
+    {
+        wallet_setting.spend_confirmation = spend_confirmation.clone();
+    }
+    settings.to_file(data_dir, network)?;
+
+    Ok(Arc::new(
+        wallet
+            .as_ref()
+            .clone()
+            .with_spend_confirmation(spend_confirmation),
+    ))
+}
+
+// As the accent color, the block explorer is only stored locally.
+async fn update_block_explorer(
+    data_dir: PathBuf,
+    network: Network,
+    wallet: Arc<Wallet>,
+    explorer: settings::BlockExplorer,
+) -> Result<Arc<Wallet>, Error> {
+    let mut settings = settings::Settings::from_file(data_dir.clone(), network)?;
+    let checksum = wallet.descriptor_checksum();
+    if let Some(wallet_setting) = settings
+        .wallets
+        .iter_mut()
+        .find(|w| w.descriptor_checksum == checksum)
+    {
+        wallet_setting.block_explorer = Some(explorer.clone());
+    }
+    settings.to_file(data_dir, network)?;
+
+    Ok(Arc::new(
+        wallet.as_ref().clone().with_block_explorer(Some(explorer)),
+    ))
+}
+
+// As the accent color, the remote signer is only stored locally. It's queried before being stored,
+// to check it can be reached and to learn the key it signs with.
+async fn update_remote_signer(
+    data_dir: PathBuf,
+    network: Network,
+    wallet: Arc<Wallet>,
+    server: Option<(String, String)>,
+) -> Result<Arc<Wallet>, Error> {
+    let remote_signer = match server {
+        Some((url, token)) => {
+            let signer = remote_signer::connect(url, token, network).await?;
+            if !wallet.descriptor_keys().contains(&signer.fingerprint) {
+                return Err(RemoteSignerError::NotPartOfWallet(signer.fingerprint).into());
+            }
+            Some(signer)
+        }
+        None => None,
+    };
+    let mut settings = settings::Settings::from_file(data_dir.clone(), network)?;
+    let checksum = wallet.descriptor_checksum();
+    if let Some(wallet_setting) = settings
+        .wallets
+        .iter_mut()
+        .find(|w| w.descriptor_checksum == checksum)
+    {
+        wallet_setting.remote_signer = remote_signer.clone();
+    }
+    settings.to_file(data_dir, network)?;
+
+    Ok(Arc::new(
+        wallet.as_ref().clone().with_remote_signer(remote_signer),
+    ))
+}
@@ -16,6 +16,7 @@ use crate::{
         model::{Coin, LabelItem},
         Daemon,
     },
+    payment_request::PaymentRequest,
 };
 
 pub struct CreateSpendPanel {
@@ -65,6 +66,29 @@ impl CreateSpendPanel {
         }
     }
 
+    pub fn new_payment_request(
+        wallet: Arc<Wallet>,
+        coins: &[Coin],
+        blockheight: u32,
+        request: &PaymentRequest,
+        network: Network,
+    ) -> Self {
+        let descriptor = wallet.main_descriptor.clone();
+        let timelock = descriptor.first_timelock_value();
+        Self {
+            draft: step::TransactionDraft::new(network),
+            current: 0,
+            steps: vec![
+                Box::new(
+                    step::DefineSpend::new(network, descriptor, coins, timelock)
+                        .with_coins_sorted(blockheight)
+                        .with_payment_request(request),
+                ),
+                Box::new(step::SaveSpend::new(wallet)),
+            ],
+        }
+    }
+
     pub fn is_first_step(&self) -> bool {
         self.current == 0
     }
@@ -124,7 +148,17 @@ impl State for CreateSpendPanel {
     ) -> Task<Message> {
         let daemon1 = daemon.clone();
         let daemon2 = daemon.clone();
+        let daemon3 = daemon.clone();
+        let daemon4 = daemon.clone();
         Task::batch(vec![
+            Task::perform(
+                async move { daemon4.get_mempool_stats().await.map_err(|e| e.into()) },
+                Message::MempoolStats,
+            ),
+            Task::perform(
+                async move { daemon3.list_spend_templates().await.map_err(|e| e.into()) },
+                Message::SpendTemplates,
+            ),
             Task::perform(
                 async move {
                     daemon1
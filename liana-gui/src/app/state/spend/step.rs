@@ -9,26 +9,39 @@ use std::{
 use iced::{Subscription, Task};
 use liana::{
     descriptors::LianaDescriptor,
-    miniscript::bitcoin::{
-        address, psbt::Psbt, secp256k1, Address, Amount, Denomination, Network, OutPoint,
-    },
-    spend::{SpendCreationError, MAX_FEERATE},
+    miniscript::bitcoin::{address, psbt::Psbt, secp256k1, Address, Amount, Network, OutPoint},
+    spend::{OutputType, SpendCreationError, MAX_FEERATE},
 };
 use lianad::commands::ListCoinsEntry;
 
-use liana_ui::{component::form, widget::Element};
+use liana_ui::{
+    component::{
+        amount::{amount_as_input, parse_amount},
+        form,
+    },
+    widget::Element,
+};
 
 use crate::{
     app::{cache::Cache, error::Error, message::Message, state::psbt, view, wallet::Wallet},
+    clipboard,
     daemon::{
-        model::{coin_is_owned, remaining_sequence, Coin, CreateSpendResult, SpendTx},
-        Daemon,
+        model::{
+            coin_is_owned, remaining_sequence, Coin, CreateSpendResult, GetMempoolStatsResult,
+            SpendTemplate, SpendTx, TemplateRecipient,
+        },
+        Daemon, DaemonError,
     },
+    payment_request::PaymentRequest,
 };
 
 /// See: https://github.com/wizardsardine/liana/blob/master/src/commands/mod.rs#L32
 const DUST_OUTPUT_SATS: u64 = 5_000;
 
+/// Number of blocks within which a self-send is aimed to confirm when prefilling its feerate.
+/// Refreshing coins is rarely urgent.
+const SELF_SEND_TARGET_BLOCKS: u64 = 6;
+
 #[derive(Clone)]
 pub struct TransactionDraft {
     network: Network,
@@ -90,6 +103,13 @@ pub struct DefineSpend {
     feerate: form::Value<String>,
     generated: Option<(Psbt, Vec<String>)>,
     warning: Option<Error>,
+    /// The stored spend templates, `None` if the daemon doesn't support them.
+    templates: Option<Vec<SpendTemplate>>,
+    template_name: form::Value<String>,
+    /// The state of the mempool, `None` if the daemon doesn't expose it.
+    mempool: Option<GetMempoolStatsResult>,
+    /// For a self-send, the fee of sending the selected coins back to the wallet.
+    self_send_fee: Option<Amount>,
 }
 
 impl DefineSpend {
@@ -127,6 +147,10 @@ impl DefineSpend {
             feerate: form::Value::default(),
             amount_left_to_select: None,
             warning: None,
+            templates: None,
+            template_name: form::Value::default(),
+            mempool: None,
+            self_send_fee: None,
         }
     }
 
@@ -164,6 +188,38 @@ impl DefineSpend {
         self
     }
 
+    /// Fill the recipient from a payment request, such as a `bitcoin:` link opened with Liana.
+    pub fn with_payment_request(mut self, request: &PaymentRequest) -> Self {
+        let mut recipient = Recipient::default();
+        recipient.update(
+            self.network,
+            view::CreateSpendMessage::RecipientEdited(
+                0,
+                "address",
+                request.address.assume_checked_ref().to_string(),
+            ),
+        );
+        if let Some(amount) = request.amount {
+            recipient.update(
+                self.network,
+                view::CreateSpendMessage::RecipientEdited(0, "amount", amount_as_input(amount)),
+            );
+        }
+        if let Some(description) = request.description() {
+            // Labels are limited to 100 characters.
+            recipient.update(
+                self.network,
+                view::CreateSpendMessage::RecipientEdited(
+                    0,
+                    "label",
+                    description.chars().take(100).collect(),
+                ),
+            );
+        }
+        self.recipients = vec![recipient];
+        self
+    }
+
     // If `is_redraft`, the validation of recipients will take into account
     // whether any should receive the max amount. Otherwise, all recipients
     // will be fully validated.
@@ -176,6 +232,87 @@ impl DefineSpend {
             r.valid() || (is_redraft && self.send_max_to_recipient == Some(i) && r.address_valid()))
     }
 
+    /// Fill the form from a template. The recipients whose amount is asked by the template are
+    /// left for the user to complete.
+    fn apply_template(&mut self, template: &SpendTemplate) {
+        self.recipients = template
+            .recipients
+            .iter()
+            .map(|r| {
+                let mut recipient = Recipient::default();
+                recipient.update(
+                    self.network,
+                    view::CreateSpendMessage::RecipientEdited(
+                        0,
+                        "address",
+                        r.address.assume_checked_ref().to_string(),
+                    ),
+                );
+                if let Some(amount) = r.amount {
+                    recipient.update(
+                        self.network,
+                        view::CreateSpendMessage::RecipientEdited(
+                            0,
+                            "amount",
+                            amount_as_input(amount),
+                        ),
+                    );
+                }
+                recipient
+            })
+            .collect();
+        self.send_max_to_recipient = None;
+        self.feerate.value = template.feerate.to_string();
+        self.feerate.valid = true;
+        self.is_user_coin_selection = !template.coins.is_empty();
+        if self.is_user_coin_selection {
+            for (coin, selected) in &mut self.coins {
+                *selected = template.coins.contains(&coin.outpoint);
+            }
+        }
+        self.template_name.value.clone_from(&template.name);
+        self.template_name.valid = true;
+    }
+
+    /// A template of the spend being defined, if the form is complete enough. Amounts left empty
+    /// are to be asked when instantiating it.
+    fn as_template(&self) -> Option<SpendTemplate> {
+        if self.template_name.value.is_empty()
+            || !self.template_name.valid
+            || !self.feerate.valid
+            || self.recipients.is_empty()
+        {
+            return None;
+        }
+        let recipients = self
+            .recipients
+            .iter()
+            .map(|r| {
+                if !r.address_valid() || !r.amount.valid {
+                    return None;
+                }
+                Some(TemplateRecipient {
+                    address: Address::from_str(&r.address.value).ok()?,
+                    amount: r.amount().ok().map(Amount::from_sat),
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(SpendTemplate {
+            name: self.template_name.value.clone(),
+            recipients,
+            feerate: self.feerate.value.parse().ok()?,
+            coins: if self.is_user_coin_selection {
+                self.coins
+                    .iter()
+                    .filter_map(|(c, selected)| selected.then_some(c.outpoint))
+                    .collect()
+            } else {
+                Vec::new()
+            },
+            schedule: None,
+        })
+    }
+
     fn exists_duplicate(&self) -> bool {
         for (i, recipient) in self.recipients.iter().enumerate() {
             if !recipient.address.value.is_empty()
@@ -190,13 +327,72 @@ impl DefineSpend {
     }
 
     fn check_valid(&mut self) {
-        self.is_valid =
-            self.form_values_are_valid(false) && self.coins.iter().any(|(_, selected)| *selected);
+        self.is_valid = self.form_values_are_valid(false)
+            && self.coins.iter().any(|(_, selected)| *selected)
+            // The selected coins must be able to pay for sending them to ourselves.
+            && (!self.recipients.is_empty()
+                || self.amount_left_to_select.map_or(true, |a| a.to_sat() == 0));
         self.is_duplicate = self.exists_duplicate();
     }
+
+    /// Estimate the fee of sending the selected coins back to the wallet, which is the cost of
+    /// refreshing them.
+    fn estimate_self_send_fee(&mut self, daemon: Arc<dyn Daemon + Sync + Send>) {
+        self.self_send_fee = None;
+        self.amount_left_to_select = None;
+        let outpoints: Vec<OutPoint> = self
+            .coins
+            .iter()
+            .filter_map(|(c, selected)| selected.then_some(c.outpoint))
+            .collect();
+        if !self.form_values_are_valid(true) || outpoints.is_empty() {
+            return;
+        }
+        let change_address = self
+            .descriptor
+            .change_descriptor()
+            .derive(0.into(), &self.curve)
+            .address(self.network)
+            .as_unchecked()
+            .clone();
+        let feerate_vb = self.feerate.value.parse::<u64>().expect("Checked before");
+        match tokio::runtime::Handle::current().block_on(async {
+            daemon
+                .create_spend_tx(
+                    &outpoints,
+                    &HashMap::new(),
+                    feerate_vb,
+                    Some(change_address),
+                    false,
+                )
+                .await
+        }) {
+            Ok(CreateSpendResult::Success { psbt, .. }) => {
+                self.warning = None;
+                let inputs: Amount = self
+                    .coins
+                    .iter()
+                    .filter_map(|(c, selected)| selected.then_some(c.amount))
+                    .sum();
+                let outputs: Amount = psbt.unsigned_tx.output.iter().map(|o| o.value).sum();
+                self.self_send_fee = Some(inputs - outputs);
+                self.amount_left_to_select = Some(Amount::from_sat(0));
+            }
+            Ok(CreateSpendResult::InsufficientFunds { missing }) => {
+                self.amount_left_to_select = Some(Amount::from_sat(missing));
+            }
+            Err(e) => {
+                self.warning = Some(e.into());
+            }
+        }
+    }
     /// redraft calculates the amount left to select and auto selects coins
     /// if the user did not select a coin manually
     fn redraft(&mut self, daemon: Arc<dyn Daemon + Sync + Send>) {
+        if self.recipients.is_empty() {
+            self.estimate_self_send_fee(daemon);
+            return;
+        }
         if !self.form_values_are_valid(true)
             || self.exists_duplicate()
             || self.recipients.is_empty()
@@ -318,6 +514,8 @@ impl DefineSpend {
                     &destinations,
                     feerate_vb,
                     Some(change_address.clone()),
+                    // The user confirmed any future witness address, checked by the form validation.
+                    true,
                 )
                 .await
         }) {
@@ -348,9 +546,8 @@ impl DefineSpend {
                             o.script_pubkey
                                 == change_address.clone().assume_checked().script_pubkey()
                         })
-                        .map(|change_output| change_output.value.to_btc())
-                        .unwrap_or(0.0)
-                        .to_string();
+                        .map(|change_output| amount_as_input(change_output.value))
+                        .unwrap_or_else(|| amount_as_input(Amount::ZERO));
                     recipient.update(
                         self.network,
                         view::CreateSpendMessage::RecipientEdited(i, "amount", amount),
@@ -367,7 +564,7 @@ impl DefineSpend {
                     }
                 }
                 if let Some((i, recipient)) = recipient_with_max {
-                    let amount = Amount::from_sat(if destinations.is_empty() {
+                    let amount = amount_as_input(Amount::from_sat(if destinations.is_empty() {
                         // If there are no other recipients, then the missing value will
                         // be the amount left to select in order to create an output at the dust
                         // threshold. Therefore, set this recipient's amount to this value so
@@ -377,9 +574,7 @@ impl DefineSpend {
                         DUST_OUTPUT_SATS
                     } else {
                         0
-                    })
-                    .to_btc()
-                    .to_string();
+                    }));
                     recipient.update(
                         self.network,
                         view::CreateSpendMessage::RecipientEdited(i, "amount", amount),
@@ -408,6 +603,9 @@ impl Step for DefineSpend {
                         self.batch_label.value = label;
                     }
                     view::CreateSpendMessage::Clear => {
+                        let templates = self.templates.take();
+                        let mempool = self.mempool.take();
+                        let is_self_send = self.recipients.is_empty();
                         *self = Self::new(
                             self.network,
                             self.descriptor.clone(),
@@ -418,6 +616,11 @@ impl Step for DefineSpend {
                                 .as_slice(),
                             self.timelock,
                         );
+                        self.templates = templates;
+                        self.mempool = mempool;
+                        if is_self_send {
+                            self.recipients = Vec::new();
+                        }
                         return Task::none();
                     }
                     view::CreateSpendMessage::AddRecipient => {
@@ -491,7 +694,8 @@ impl Step for DefineSpend {
                         return Task::perform(
                             async move {
                                 daemon
-                                    .create_spend_tx(&inputs, &outputs, feerate_vb, None)
+                                    // Future witness addresses were confirmed by the user.
+                                    .create_spend_tx(&inputs, &outputs, feerate_vb, None, true)
                                     .await
                                     .map_err(|e| e.into())
                                     .and_then(|res| match res {
@@ -516,6 +720,63 @@ impl Step for DefineSpend {
                             self.is_user_coin_selection = true;
                         }
                     }
+                    view::CreateSpendMessage::ApplyTemplate(i) => {
+                        if let Some(template) =
+                            self.templates.as_ref().and_then(|t| t.get(i)).cloned()
+                        {
+                            self.apply_template(&template);
+                        }
+                    }
+                    view::CreateSpendMessage::TemplateNameEdited(name) => {
+                        self.template_name.valid = !name.is_empty() && name.len() <= 100;
+                        self.template_name.value = name;
+                        return Task::none();
+                    }
+                    view::CreateSpendMessage::SaveTemplate => {
+                        if let Some(mut template) = self.as_template() {
+                            // Keep the schedule of the template we overwrite, if it can still be
+                            // scheduled.
+                            if template.recipients.iter().all(|r| r.amount.is_some()) {
+                                template.schedule = self
+                                    .templates
+                                    .iter()
+                                    .flatten()
+                                    .find(|t| t.name == template.name)
+                                    .and_then(|t| t.schedule.clone());
+                            }
+                            return Task::perform(
+                                async move {
+                                    daemon.save_spend_template(template).await?;
+                                    daemon.list_spend_templates().await
+                                },
+                                |res| Message::SpendTemplates(res.map_err(|e| e.into())),
+                            );
+                        }
+                        return Task::none();
+                    }
+                    view::CreateSpendMessage::DeleteTemplate(i) => {
+                        if let Some(name) = self
+                            .templates
+                            .as_ref()
+                            .and_then(|t| t.get(i))
+                            .map(|t| t.name.clone())
+                        {
+                            return Task::perform(
+                                async move {
+                                    daemon.delete_spend_template(&name).await?;
+                                    daemon.list_spend_templates().await
+                                },
+                                |res| Message::SpendTemplates(res.map_err(|e| e.into())),
+                            );
+                        }
+                        return Task::none();
+                    }
+                    view::CreateSpendMessage::ConfirmFutureWitness(i) => {
+                        if let Some(recipient) = self.recipients.get_mut(i) {
+                            recipient.future_witness_confirmed =
+                                !recipient.future_witness_confirmed;
+                        }
+                    }
                     view::CreateSpendMessage::SendMaxToRecipient(i) => {
                         if self.recipients.get(i).is_some() {
                             if self.send_max_to_recipient == Some(i) {
@@ -550,6 +811,36 @@ impl Step for DefineSpend {
                 }
                 Err(e) => self.warning = Some(e),
             },
+            Message::SpendTemplates(res) => match res {
+                Ok(templates) => self.templates = Some(templates),
+                Err(Error::Daemon(DaemonError::ClientNotSupported)) => self.templates = None,
+                Err(e) => self.warning = Some(e),
+            },
+            Message::MempoolStats(res) => match res {
+                Ok(stats) => {
+                    // Suggest a feerate for a self-send, so refreshing coins takes a single click.
+                    if self.recipients.is_empty() && self.feerate.value.is_empty() {
+                        if let Some(feerate) = stats.lowest_feerate_within(SELF_SEND_TARGET_BLOCKS)
+                        {
+                            self.feerate.value = feerate.to_string();
+                            self.feerate.valid = true;
+                            self.mempool = Some(stats);
+                            self.redraft(daemon);
+                            self.check_valid();
+                            return Task::none();
+                        }
+                    }
+                    self.mempool = Some(stats);
+                }
+                Err(Error::Daemon(DaemonError::ClientNotSupported)) => self.mempool = None,
+                Err(e) => self.warning = Some(e),
+            },
+            Message::View(view::Message::RefreshMempool) => {
+                return Task::perform(
+                    async move { daemon.get_mempool_stats().await.map_err(|e| e.into()) },
+                    Message::MempoolStats,
+                );
+            }
             Message::Coins(res) => match res {
                 Ok(coins) => {
                     let selected: HashSet<OutPoint> =
@@ -646,6 +937,11 @@ impl Step for DefineSpend {
             &self.batch_label,
             self.amount_left_to_select.as_ref(),
             &self.feerate,
+            self.templates.as_deref(),
+            &self.template_name,
+            self.as_template().is_some(),
+            self.mempool.as_ref(),
+            self.self_send_fee.as_ref(),
             self.warning.as_ref(),
         )
     }
@@ -656,6 +952,10 @@ struct Recipient {
     label: form::Value<String>,
     address: form::Value<String>,
     amount: form::Value<String>,
+    // Whether the pasted address differs from the one the app copied just before.
+    address_swapped: bool,
+    // Whether the user acknowledged the coins sent to a future witness version may be lost.
+    future_witness_confirmed: bool,
 }
 
 impl Recipient {
@@ -664,7 +964,7 @@ impl Recipient {
             return Err(Error::Unexpected("Amount should be non-zero".to_string()));
         }
 
-        let amount = Amount::from_str_in(&self.amount.value, Denomination::Bitcoin)
+        let amount = parse_amount(&self.amount.value)
             .map_err(|_| Error::Unexpected("cannot parse output amount".to_string()))?;
 
         if amount.to_sat() == 0 {
@@ -686,8 +986,18 @@ impl Recipient {
         Ok(amount.to_sat())
     }
 
+    /// Whether the address uses a witness version not defined yet, which anyone can spend.
+    fn is_future_witness(&self) -> bool {
+        Address::from_str(&self.address.value)
+            .ok()
+            .and_then(|addr| OutputType::from_script(&addr.assume_checked().script_pubkey()))
+            .is_some_and(|t| t.is_future_witness())
+    }
+
     fn address_valid(&self) -> bool {
-        !self.address.value.is_empty() && self.address.valid
+        !self.address.value.is_empty()
+            && self.address.valid
+            && (!self.is_future_witness() || self.future_witness_confirmed)
     }
 
     fn valid(&self) -> bool {
@@ -700,6 +1010,10 @@ impl Recipient {
     fn update(&mut self, network: Network, message: view::CreateSpendMessage) {
         match message {
             view::CreateSpendMessage::RecipientEdited(_, "address", address) => {
+                // Several characters inserted at once were pasted.
+                let pasted = address.len() > self.address.value.len() + 1;
+                self.address_swapped = pasted && clipboard::is_swapped_address(&address);
+                self.future_witness_confirmed = false;
                 self.address.value = address;
                 if let Ok(address) = Address::from_str(&self.address.value) {
                     self.address.valid = address.is_valid_for_network(network);
@@ -731,7 +1045,16 @@ impl Recipient {
     }
 
     fn view(&self, i: usize, is_max_selected: bool) -> Element<view::CreateSpendMessage> {
-        view::spend::recipient_view(i, &self.address, &self.amount, &self.label, is_max_selected)
+        view::spend::recipient_view(
+            i,
+            &self.address,
+            self.address_swapped,
+            self.is_future_witness()
+                .then_some(self.future_witness_confirmed),
+            &self.amount,
+            &self.label,
+            is_max_selected,
+        )
     }
 }
 
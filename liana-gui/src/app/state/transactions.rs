@@ -1,10 +1,12 @@
 use std::{
     collections::{HashMap, HashSet},
     convert::TryInto,
+    path::PathBuf,
     sync::Arc,
     time::{SystemTime, UNIX_EPOCH},
 };
 
+use base64::Engine;
 use iced::Task;
 use liana::{
     miniscript::bitcoin::{OutPoint, Txid},
@@ -50,6 +52,8 @@ pub struct TransactionsPanel {
     txs: Vec<HistoryTransaction>,
     labels_edited: LabelsEdited,
     selected_tx: Option<HistoryTransaction>,
+    // Where the receipt of the selected transaction was saved.
+    receipt_saved: Option<PathBuf>,
     warning: Option<Error>,
     modal: TransactionsModal,
     is_last_page: bool,
@@ -61,6 +65,7 @@ impl TransactionsPanel {
         Self {
             wallet,
             selected_tx: None,
+            receipt_saved: None,
             txs: Vec::new(),
             labels_edited: LabelsEdited::default(),
             warning: None,
@@ -72,6 +77,7 @@ impl TransactionsPanel {
 
     pub fn preselect(&mut self, tx: HistoryTransaction) {
         self.selected_tx = Some(tx);
+        self.receipt_saved = None;
         self.warning = None;
         self.modal = TransactionsModal::None;
     }
@@ -84,6 +90,7 @@ impl State for TransactionsPanel {
                 cache,
                 tx,
                 self.labels_edited.cache(),
+                self.receipt_saved.as_deref(),
                 self.warning.as_ref(),
             );
             match &self.modal {
@@ -108,7 +115,7 @@ impl State for TransactionsPanel {
     fn update(
         &mut self,
         daemon: Arc<dyn Daemon + Sync + Send>,
-        _cache: &Cache,
+        cache: &Cache,
         message: Message,
     ) -> Task<Message> {
         match message {
@@ -158,6 +165,7 @@ impl State for TransactionsPanel {
             }
             Message::View(view::Message::Select(i)) => {
                 self.selected_tx = self.txs.get(i).cloned();
+                self.receipt_saved = None;
                 // Clear modal if it's for a different tx.
                 if let TransactionsModal::CreateRbf(modal) = &self.modal {
                     if Some(modal.tx.tx.compute_txid())
@@ -170,6 +178,27 @@ impl State for TransactionsPanel {
                     }
                 }
             }
+            Message::View(view::Message::SaveReceipt) => {
+                if let Some(tx) = &self.selected_tx {
+                    return Task::perform(
+                        save_receipt(
+                            daemon,
+                            tx.tx.compute_txid(),
+                            cache.block_explorer().tx_url_prefix(cache.network),
+                        ),
+                        Message::ReceiptSaved,
+                    );
+                }
+            }
+            Message::ReceiptSaved(res) => match res {
+                Ok(path) => {
+                    if path.is_some() {
+                        self.warning = None;
+                        self.receipt_saved = path;
+                    }
+                }
+                Err(e) => self.warning = Some(e),
+            },
             Message::View(view::Message::CreateRbf(view::CreateRbfMessage::Cancel)) => {
                 self.modal = TransactionsModal::None;
             }
@@ -281,7 +310,7 @@ impl State for TransactionsPanel {
             }
             ref msg => {
                 return match &mut self.modal {
-                    TransactionsModal::CreateRbf(modal) => modal.update(daemon, _cache, message),
+                    TransactionsModal::CreateRbf(modal) => modal.update(daemon, cache, message),
                     TransactionsModal::Export(modal) => {
                         if let Message::View(view::Message::Export(m)) = msg {
                             modal.update(m.clone())
@@ -341,6 +370,29 @@ impl From<TransactionsPanel> for Box<dyn State> {
     }
 }
 
+async fn save_receipt(
+    daemon: Arc<dyn Daemon + Sync + Send>,
+    txid: Txid,
+    explorer_url: Option<String>,
+) -> Result<Option<PathBuf>, Error> {
+    let receipt = daemon.get_receipt(&txid, explorer_url).await?;
+    let pdf = base64::engine::general_purpose::STANDARD
+        .decode(&receipt.pdf)
+        .map_err(|e| Error::Unexpected(format!("Invalid receipt: {}", e)))?;
+    let path = match rfd::AsyncFileDialog::new()
+        .set_title("Save the receipt...")
+        .set_file_name(format!("liana-receipt-{}.pdf", txid))
+        .save_file()
+        .await
+    {
+        Some(file) => file.path().to_path_buf(),
+        None => return Ok(None),
+    };
+    std::fs::write(&path, pdf)
+        .map_err(|e| Error::Unexpected(format!("Failed to write {}: {}", path.display(), e)))?;
+    Ok(Some(path))
+}
+
 #[derive(Debug)]
 pub struct CreateRbfModal {
     /// Transaction to replace.
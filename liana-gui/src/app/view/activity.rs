@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local, Utc};
+use iced::{widget::Space, Alignment, Length};
+
+use liana::miniscript::bitcoin::bip32::Fingerprint;
+use liana_ui::{
+    component::{button, card, text::*},
+    theme,
+    widget::*,
+};
+
+use crate::{
+    app::notify,
+    daemon::model::{Event, EventEntry},
+};
+
+use super::message::Message;
+
+/// The kinds of events shown in the activity feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ActivityFilter {
+    #[default]
+    All,
+    /// Transactions received, sent or confirmed, and the coins of the wallet.
+    OnChain,
+    /// The transactions drafted, signed and reviewed by the participants.
+    Drafts,
+    /// The state of the wallet: its settings, rescans and Bitcoin backend.
+    Wallet,
+}
+
+impl ActivityFilter {
+    pub const ALL: [ActivityFilter; 4] = [
+        ActivityFilter::All,
+        ActivityFilter::OnChain,
+        ActivityFilter::Drafts,
+        ActivityFilter::Wallet,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ActivityFilter::All => "All",
+            ActivityFilter::OnChain => "On-chain",
+            ActivityFilter::Drafts => "Drafts",
+            ActivityFilter::Wallet => "Wallet",
+        }
+    }
+
+    pub fn matches(&self, event: &Event) -> bool {
+        match self {
+            ActivityFilter::All => true,
+            ActivityFilter::OnChain => matches!(
+                event,
+                Event::CoinReceived { .. }
+                    | Event::SpendConfirmed { .. }
+                    | Event::SpendBroadcast { .. }
+                    | Event::ScheduledSpendBroadcast { .. }
+                    | Event::UnauthorizedSpend { .. }
+                    | Event::RecoveryPathSoonAvailable { .. }
+            ),
+            ActivityFilter::Drafts => matches!(
+                event,
+                Event::SpendSaved { .. }
+                    | Event::SpendSigned { .. }
+                    | Event::SpendReviewed { .. }
+                    | Event::ScheduledSpendConflicted { .. }
+            ),
+            ActivityFilter::Wallet => matches!(
+                event,
+                Event::SettingsChanged { .. }
+                    | Event::RescanCompleted { .. }
+                    | Event::BackendUnreachable { .. }
+                    | Event::BackendReachable
+                    | Event::BackendsDisagree { .. }
+                    | Event::BackendsAgree
+                    | Event::StaleTip { .. }
+                    | Event::TipAdvanced { .. }
+                    | Event::ClockSkewed { .. }
+                    | Event::ClockSynced
+            ),
+        }
+    }
+}
+
+/// The events recorded by the daemon matching the `filter`, newest first.
+pub fn activity_view<'a>(
+    events: &'a [EventEntry],
+    filter: ActivityFilter,
+    keys_aliases: &'a HashMap<Fingerprint, String>,
+) -> Element<'a, Message> {
+    let entries: Vec<&EventEntry> = events
+        .iter()
+        .rev()
+        .filter(|entry| filter.matches(&entry.event))
+        .collect();
+    Column::new()
+        .push(Container::new(h3("Activity")).width(Length::Fill))
+        .push(
+            ActivityFilter::ALL
+                .iter()
+                .fold(Row::new().spacing(10), |row, f| {
+                    row.push(if *f == filter {
+                        button::primary(None, f.name())
+                    } else {
+                        button::secondary(None, f.name()).on_press(Message::FilterActivity(*f))
+                    })
+                }),
+        )
+        .push(if entries.is_empty() {
+            Column::new().push(
+                p1_regular("Nothing happened in this wallet yet.").style(theme::text::secondary),
+            )
+        } else {
+            entries
+                .into_iter()
+                .fold(Column::new().spacing(10), |col, entry| {
+                    col.push(event_view(entry, keys_aliases))
+                })
+        })
+        .spacing(20)
+        .into()
+}
+
+fn event_view<'a>(
+    entry: &'a EventEntry,
+    keys_aliases: &'a HashMap<Fingerprint, String>,
+) -> Element<'a, Message> {
+    let (title, body) = notify::content(&entry.event);
+    // Name the signers as the user did, the notifications only know their fingerprints.
+    let signers = match &entry.event {
+        Event::SpendSigned { signers, .. } => Some(signers.as_slice()),
+        Event::SpendReviewed { signer, .. } => Some(std::slice::from_ref(signer)),
+        _ => None,
+    }
+    .map(|signers| {
+        signers
+            .iter()
+            .map(|fg| {
+                keys_aliases
+                    .get(fg)
+                    .cloned()
+                    .unwrap_or_else(|| fg.to_string())
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    });
+    card::simple(
+        Row::new()
+            .align_y(Alignment::Center)
+            .spacing(20)
+            .push(
+                Column::new()
+                    .spacing(5)
+                    .width(Length::Fill)
+                    .push(p1_bold(title))
+                    .push(p2_regular(body))
+                    .push_maybe(signers.map(|s| {
+                        p2_regular(format!("Signer(s): {}", s)).style(theme::text::secondary)
+                    })),
+            )
+            .push(Space::with_width(Length::Fixed(10.0)))
+            .push(
+                text(
+                    DateTime::<Utc>::from_timestamp(entry.time as i64, 0)
+                        .expect("Correct unix timestamp")
+                        .with_timezone(&Local)
+                        .format("%b. %d, %Y - %T")
+                        .to_string(),
+                )
+                .style(theme::text::secondary)
+                .small(),
+            ),
+    )
+    .into()
+}
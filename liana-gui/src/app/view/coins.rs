@@ -1,7 +1,11 @@
 use std::collections::HashMap;
 
-use iced::{widget::Space, Alignment, Length};
+use iced::{
+    widget::{checkbox, pick_list, Space},
+    Alignment, Length,
+};
 
+use liana::miniscript::bitcoin;
 use liana_ui::{
     component::{amount::*, badge, button, form, text::*},
     icon, theme,
@@ -11,8 +15,10 @@ use liana_ui::{
 use crate::{
     app::{
         cache::Cache,
+        help::Topic,
         menu::Menu,
-        view::{label, message::Message},
+        preferences::{CoinColumn, CoinsColumns, CoinsPreferences, CoinsSort},
+        view::{help, label, message::Message},
     },
     daemon::model::{remaining_sequence, Coin},
 };
@@ -21,12 +27,73 @@ pub fn coins_view<'a>(
     cache: &Cache,
     coins: &'a [Coin],
     timelock: u16,
+    preferences: &CoinsPreferences,
     selected: &[usize],
+    to_refresh: &[bitcoin::OutPoint],
     labels: &'a HashMap<String, String>,
     labels_editing: &'a HashMap<String, form::Value<String>>,
 ) -> Element<'a, Message> {
     Column::new()
-        .push(Container::new(h3("Coins")).width(Length::Fill))
+        .push(
+            Row::new()
+                .align_y(Alignment::Center)
+                .spacing(20)
+                .push(
+                    Row::new()
+                        .spacing(10)
+                        .align_y(Alignment::Center)
+                        .push(h3("Coins"))
+                        .push(help(Topic::Timelock))
+                        .width(Length::Fill),
+                )
+                .push(
+                    CoinColumn::ALL
+                        .into_iter()
+                        .fold(Row::new().spacing(10), |row, column| {
+                            row.push(
+                                checkbox(column.to_string(), preferences.columns.shows(column))
+                                    .on_toggle(move |_| Message::ToggleCoinColumn(column)),
+                            )
+                        }),
+                )
+                .push(
+                    pick_list(
+                        &CoinsSort::ALL[..],
+                        Some(preferences.sort),
+                        Message::SortCoins,
+                    )
+                    .style(theme::pick_list::primary)
+                    .padding(10),
+                ),
+        )
+        .push_maybe(if to_refresh.is_empty() {
+            None
+        } else {
+            let total: bitcoin::Amount = coins
+                .iter()
+                .filter(|c| to_refresh.contains(&c.outpoint))
+                .map(|c| c.amount)
+                .sum();
+            Some(
+                Container::new(
+                    Row::new()
+                        .spacing(10)
+                        .align_y(Alignment::Center)
+                        .push(p1_regular(format!(
+                            "{} coin{} selected",
+                            to_refresh.len(),
+                            if to_refresh.len() > 1 { "s" } else { "" }
+                        )))
+                        .push(Container::new(amount(&total)).width(Length::Fill))
+                        .push(
+                            button::secondary(Some(icon::arrow_repeat()), "Refresh selected coins")
+                                .on_press(Message::Menu(Menu::RefreshCoins(to_refresh.to_vec()))),
+                        ),
+                )
+                .padding(15)
+                .style(theme::card::simple),
+            )
+        })
         .push(
             Column::new()
                 .spacing(10)
@@ -36,9 +103,11 @@ pub fn coins_view<'a>(
                         col.push(coin_list_view(
                             coin,
                             timelock,
+                            &preferences.columns,
                             cache.blockheight as u32,
                             i,
                             selected.contains(&i),
+                            to_refresh.contains(&coin.outpoint),
                             labels,
                             labels_editing,
                         ))
@@ -50,90 +119,123 @@ pub fn coins_view<'a>(
         .into()
 }
 
-#[allow(clippy::collapsible_else_if)]
+#[allow(clippy::collapsible_else_if, clippy::too_many_arguments)]
 fn coin_list_view<'a>(
     coin: &'a Coin,
     timelock: u16,
+    columns: &CoinsColumns,
     blockheight: u32,
     index: usize,
     collapsed: bool,
+    to_refresh: bool,
     labels: &'a HashMap<String, String>,
     labels_editing: &'a HashMap<String, form::Value<String>>,
 ) -> Container<'a, Message> {
+    // Only the confirmed coins can be refreshed, the others have no timelock running yet.
+    let refreshable = coin.spend_info.is_none() && coin.block_height.is_some() && !coin.is_immature;
     let outpoint = coin.outpoint.to_string();
     let address = coin.address.to_string();
     let txid = coin.outpoint.txid.to_string();
     Container::new(
         Column::new()
             .push(
-                Button::new(
-                    Row::new()
-                        .push(
+                Row::new()
+                    .align_y(Alignment::Center)
+                    .push_maybe(refreshable.then(|| {
+                        Container::new(
+                            checkbox("", to_refresh)
+                                .on_toggle(move |_| Message::SelectCoinToRefresh(coin.outpoint)),
+                        )
+                        .padding([0, 10])
+                    }))
+                    .push(
+                        Button::new(
                             Row::new()
-                                .push(badge::coin())
-                                .push(if !collapsed {
-                                    if let Some(label) = labels.get(&outpoint) {
-                                        if !label.is_empty() {
-                                            Container::new(p1_regular(label)).width(Length::Fill)
-                                        } else if let Some(label) = labels.get(&txid) {
-                                            Container::new(
-                                                Row::new()
-                                                    .spacing(5)
-                                                    .push(
-                                                        // It is not possible to know if a coin is a
-                                                        // change coin or not so for now, From is
-                                                        // enough
-                                                        p1_regular("From")
-                                                            .style(theme::text::secondary),
+                                .push(
+                                    Row::new()
+                                        .push(badge::coin())
+                                        .push(if !collapsed {
+                                            if let Some(label) = labels.get(&outpoint) {
+                                                if !label.is_empty() {
+                                                    Container::new(p1_regular(label))
+                                                        .width(Length::Fill)
+                                                } else if let Some(label) = labels.get(&txid) {
+                                                    Container::new(
+                                                        Row::new()
+                                                            .spacing(5)
+                                                            .push(
+                                                                // It is not possible to know if a coin is a
+                                                                // change coin or not so for now, From is
+                                                                // enough
+                                                                p1_regular("From")
+                                                                    .style(theme::text::secondary),
+                                                            )
+                                                            .push(p1_regular(label)),
                                                     )
-                                                    .push(p1_regular(label)),
-                                            )
-                                            .width(Length::Fill)
+                                                    .width(Length::Fill)
+                                                } else {
+                                                    Container::new(Space::with_width(Length::Fill))
+                                                        .width(Length::Fill)
+                                                }
+                                            } else if let Some(label) = labels.get(&txid) {
+                                                Container::new(
+                                                    Row::new()
+                                                        .spacing(5)
+                                                        .push(
+                                                            // It is not possible to know if a coin is a
+                                                            // change coin or not so for now, From is
+                                                            // enough
+                                                            p1_regular("From")
+                                                                .style(theme::text::secondary),
+                                                        )
+                                                        .push(p1_regular(label)),
+                                                )
+                                                .width(Length::Fill)
+                                            } else {
+                                                Container::new(Space::with_width(Length::Fill))
+                                                    .width(Length::Fill)
+                                            }
                                         } else {
                                             Container::new(Space::with_width(Length::Fill))
                                                 .width(Length::Fill)
-                                        }
-                                    } else if let Some(label) = labels.get(&txid) {
-                                        Container::new(
-                                            Row::new()
-                                                .spacing(5)
-                                                .push(
-                                                    // It is not possible to know if a coin is a
-                                                    // change coin or not so for now, From is
-                                                    // enough
-                                                    p1_regular("From")
-                                                        .style(theme::text::secondary),
-                                                )
-                                                .push(p1_regular(label)),
+                                        })
+                                        .push_maybe(columns.address.then(|| {
+                                            p2_regular(address.clone())
+                                                .style(theme::text::secondary)
+                                        }))
+                                        .push_maybe(
+                                            coin.block_height.filter(|_| columns.block_height).map(
+                                                |h| {
+                                                    p2_regular(format!("Block {}", h))
+                                                        .style(theme::text::secondary)
+                                                },
+                                            ),
                                         )
-                                        .width(Length::Fill)
-                                    } else {
-                                        Container::new(Space::with_width(Length::Fill))
-                                            .width(Length::Fill)
-                                    }
-                                } else {
-                                    Container::new(Space::with_width(Length::Fill))
-                                        .width(Length::Fill)
-                                })
-                                .push(if coin.spend_info.is_some() {
-                                    badge::spent()
-                                } else if coin.block_height.is_none() {
-                                    badge::unconfirmed()
-                                } else {
-                                    let seq = remaining_sequence(coin, blockheight, timelock);
-                                    coin_sequence_label(seq, timelock as u32)
-                                })
-                                .spacing(10)
+                                        .push_maybe(columns.status.then(|| {
+                                            if coin.spend_info.is_some() {
+                                                badge::spent()
+                                            } else if coin.is_immature {
+                                                badge::immature()
+                                            } else if coin.block_height.is_none() {
+                                                badge::unconfirmed()
+                                            } else {
+                                                let seq =
+                                                    remaining_sequence(coin, blockheight, timelock);
+                                                coin_sequence_label(seq, timelock as u32)
+                                            }
+                                        }))
+                                        .spacing(10)
+                                        .align_y(Alignment::Center)
+                                        .width(Length::Fill),
+                                )
+                                .push(amount(&coin.amount))
                                 .align_y(Alignment::Center)
-                                .width(Length::Fill),
+                                .spacing(20),
                         )
-                        .push(amount(&coin.amount))
-                        .align_y(Alignment::Center)
-                        .spacing(20),
-                )
-                .style(theme::button::transparent_border)
-                .padding(10)
-                .on_press(Message::Select(index)),
+                        .style(theme::button::transparent_border)
+                        .padding(10)
+                        .on_press(Message::Select(index)),
+                    ),
             )
             .push_maybe(if collapsed {
                 Some(
@@ -152,6 +254,12 @@ fn coin_list_view<'a>(
                             })
                             .width(Length::Fill),
                         )
+                        .push_maybe(coin.maturity_height.map(|h| {
+                            Container::new(p1_bold(format!(
+                                "Spendable in {} blocks",
+                                (h as u32).saturating_sub(blockheight + 1)
+                            )))
+                        }))
                         .push_maybe(if coin.spend_info.is_none() {
                             if let Some(b) = coin.block_height {
                                 if blockheight > b as u32 + timelock as u32 {
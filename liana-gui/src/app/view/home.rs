@@ -3,7 +3,7 @@ use std::{collections::HashMap, time::Duration, vec};
 
 use iced::{
     alignment,
-    widget::{Container, Row, Space},
+    widget::{tooltip, Container, Row, Space},
     Alignment, Length,
 };
 
@@ -19,26 +19,72 @@ use crate::{
     app::{
         cache::Cache,
         error::Error,
+        help::Topic,
         menu::Menu,
-        view::{coins, dashboard, label, message::Message},
+        view::{
+            coins, dashboard, explorer_tx_button, help, help_icon, label, mempool, message::Message,
+        },
         wallet::SyncStatus,
     },
-    daemon::model::{HistoryTransaction, Payment, PaymentKind, TransactionKind},
+    daemon::model::{
+        GetMempoolStatsResult, GetRecoveryScheduleResult, HistoryTransaction, Payment, PaymentKind,
+        ReadinessItem, RecoveryReadiness, TransactionKind,
+    },
 };
 
+/// The confirmed coins whose recovery path becomes available within the same span of time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryCountdownGroup {
+    /// The largest number of blocks until the recovery path of a coin of this group becomes
+    /// available, `None` for the coins beyond the last span.
+    pub max_blocks: Option<u32>,
+    /// The smallest number of blocks until the recovery path of a coin of this group becomes
+    /// available.
+    pub min_blocks: u32,
+    pub coins: Vec<bitcoin::OutPoint>,
+    pub total: bitcoin::Amount,
+}
+
+impl RecoveryCountdownGroup {
+    fn title(&self) -> String {
+        match self.max_blocks {
+            Some(0) => "Available now".to_string(),
+            Some(blocks) => format!(
+                "Within {}",
+                coins::expire_message_units(blocks)
+                    .first()
+                    .cloned()
+                    .unwrap_or_default()
+            ),
+            None => "Later".to_string(),
+        }
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn home_view<'a>(
     balance: &'a bitcoin::Amount,
     unconfirmed_balance: &'a bitcoin::Amount,
+    immature_balance: &'a bitcoin::Amount,
     remaining_sequence: &Option<u32>,
     expiring_coins: &[bitcoin::OutPoint],
+    countdown: &'a [RecoveryCountdownGroup],
+    readiness: Option<&RecoveryReadiness>,
+    mempool: Option<&GetMempoolStatsResult>,
+    recovery_schedule: Option<&GetRecoveryScheduleResult>,
     events: &'a [Payment],
     is_last_page: bool,
     processing: bool,
     sync_status: &SyncStatus,
 ) -> Element<'a, Message> {
     Column::new()
-        .push(h3("Balance"))
+        .push(
+            Row::new()
+                .spacing(10)
+                .align_y(Alignment::Center)
+                .push(h3("Balance"))
+                .push(help(Topic::PrimaryPath)),
+        )
         .push(
             Column::new()
                 .push(if sync_status.is_synced() {
@@ -97,6 +143,23 @@ pub fn home_view<'a>(
                     } else {
                         None
                     },
+                )
+                .push_maybe(
+                    if immature_balance.to_sat() != 0 && sync_status.is_synced() {
+                        Some(
+                            Row::new()
+                                .spacing(10)
+                                .push(text("+").size(H3_SIZE).style(theme::text::secondary))
+                                .push(unconfirmed_amount_with_size(immature_balance, H3_SIZE))
+                                .push(
+                                    text("immature (spendable after 100 confirmations)")
+                                        .size(H3_SIZE)
+                                        .style(theme::text::secondary),
+                                ),
+                        )
+                    } else {
+                        None
+                    },
                 ),
         )
         .push_maybe(if expiring_coins.is_empty() {
@@ -112,12 +175,13 @@ pub fn home_view<'a>(
                             ))
                             .width(Length::Fill),
                         )
-                        .push(
+                        .push(help_icon(
                             icon::tooltip_icon()
                                 .size(20)
                                 .style(theme::text::secondary)
                                 .width(Length::Fixed(20.0)),
-                        )
+                            Topic::Timelock,
+                        ))
                         .width(Length::Fill),
                 )
                 .padding(25)
@@ -147,6 +211,22 @@ pub fn home_view<'a>(
                 .style(theme::card::invalid),
             )
         })
+        .push_maybe(if countdown.is_empty() {
+            None
+        } else {
+            Some(recovery_countdown_card(countdown))
+        })
+        .push_maybe(
+            recovery_schedule
+                .filter(|s| s.buckets.iter().any(|b| b.coins > 0))
+                .map(recovery_schedule_card),
+        )
+        .push_maybe(
+            readiness
+                .filter(|r| r.score < 100)
+                .map(|r| readiness_card(r, expiring_coins)),
+        )
+        .push_maybe(mempool.map(|stats| mempool::mempool_card(stats, None)))
         .push(
             Column::new()
                 .spacing(10)
@@ -190,6 +270,233 @@ pub fn home_view<'a>(
         .into()
 }
 
+/// The confirmed coins grouped by how soon their recovery path becomes available, with the
+/// date it is projected to and the action to take on them.
+fn recovery_countdown_card(countdown: &[RecoveryCountdownGroup]) -> Element<Message> {
+    Container::new(
+        Column::new()
+            .spacing(10)
+            .push(h4_bold("Recovery countdown"))
+            .push(
+                text(
+                    "The recovery path of a coin becomes available a fixed number of blocks after \
+                     its confirmation. Refresh the coins to push it back.",
+                )
+                .style(theme::text::secondary),
+            )
+            .push(
+                countdown
+                    .iter()
+                    .fold(Column::new().spacing(10), |col, group| {
+                        // Assume a block every 10 minutes.
+                        let date = (Local::now()
+                            + chrono::Duration::minutes(group.min_blocks as i64 * 10))
+                        .format("%b. %d, %Y");
+                        col.push(
+                            Row::new()
+                                .spacing(10)
+                                .align_y(Alignment::Center)
+                                .push(p1_bold(group.title()).width(Length::Fixed(150.0)))
+                                .push(
+                                    p1_regular(format!(
+                                        "{} coin{}",
+                                        group.coins.len(),
+                                        if group.coins.len() > 1 { "s" } else { "" }
+                                    ))
+                                    .width(Length::Fixed(100.0)),
+                                )
+                                .push(Container::new(amount(&group.total)).width(Length::Fill))
+                                .push(
+                                    p1_regular(if group.min_blocks == 0 {
+                                        "Recovery path available".to_string()
+                                    } else {
+                                        format!("From ≈ {}", date)
+                                    })
+                                    .style(theme::text::secondary),
+                                )
+                                .push_maybe(if group.min_blocks == 0 {
+                                    Some(
+                                        button::secondary(None, "Prepare recovery")
+                                            .on_press(Message::Menu(Menu::Recovery)),
+                                    )
+                                } else {
+                                    None
+                                })
+                                .push(
+                                    button::secondary(Some(icon::arrow_repeat()), "Refresh coins")
+                                        .on_press(Message::Menu(Menu::RefreshCoins(
+                                            group.coins.clone(),
+                                        ))),
+                                ),
+                        )
+                    }),
+            ),
+    )
+    .padding(25)
+    .style(theme::card::simple)
+    .into()
+}
+
+const SCHEDULE_CHART_HEIGHT: f32 = 80.0;
+
+/// The value of the coins becoming spendable through the recovery path in each of the next ranges
+/// of blocks, to spot the bulks worth refreshing ahead of time.
+fn recovery_schedule_card<'a>(schedule: &GetRecoveryScheduleResult) -> Element<'a, Message> {
+    let max = schedule
+        .buckets
+        .iter()
+        .map(|b| b.amount)
+        .max()
+        .unwrap_or(bitcoin::Amount::ZERO)
+        .to_sat()
+        .max(1);
+    let within = |height: i32| {
+        coins::expire_message_units((height - schedule.tip_height).max(0) as u32)
+            .first()
+            .cloned()
+            .unwrap_or_default()
+    };
+    let chart = schedule
+        .buckets
+        .iter()
+        .fold(Row::new().spacing(2), |row, bucket| {
+            let height = if bucket.coins == 0 {
+                0.0
+            } else {
+                (SCHEDULE_CHART_HEIGHT * bucket.amount.to_sat() as f32 / max as f32).max(1.0)
+            };
+            let bar = Column::new()
+                .width(Length::Fill)
+                .push(Space::with_height(Length::Fixed(
+                    SCHEDULE_CHART_HEIGHT - height,
+                )))
+                .push(
+                    Container::new(Space::new(Length::Fill, Length::Fixed(height)))
+                        .style(theme::container::custom(color::GREY_4)),
+                );
+            row.push(tooltip::Tooltip::new(
+                bar,
+                text(format!(
+                    "{} in {} coin{}, within {}",
+                    bucket.amount,
+                    bucket.coins,
+                    if bucket.coins > 1 { "s" } else { "" },
+                    within(bucket.end_height),
+                )),
+                tooltip::Position::Top,
+            ))
+        });
+    Container::new(
+        Column::new()
+            .spacing(10)
+            .push(h4_bold("Time-locked value"))
+            .push(
+                text(
+                    "The value becoming spendable through the recovery path over time. Refresh \
+                     the coins ahead of the largest bars, in batches when fees are low.",
+                )
+                .style(theme::text::secondary),
+            )
+            .push_maybe(if schedule.available > bitcoin::Amount::ZERO {
+                Some(
+                    Row::new()
+                        .spacing(5)
+                        .push(amount(&schedule.available))
+                        .push(p1_regular("already spendable through the recovery path")),
+                )
+            } else {
+                None
+            })
+            .push(chart)
+            .push(
+                Row::new()
+                    .push(
+                        caption("Now")
+                            .style(theme::text::secondary)
+                            .width(Length::Fill),
+                    )
+                    .push_maybe(schedule.buckets.last().map(|bucket| {
+                        caption(format!("In {}", within(bucket.end_height)))
+                            .style(theme::text::secondary)
+                    })),
+            ),
+    )
+    .padding(25)
+    .style(theme::card::simple)
+    .into()
+}
+
+/// The items of the recovery readiness checklist which remain to be done, each with the action
+/// to take.
+fn readiness_card<'a>(
+    readiness: &'a RecoveryReadiness,
+    expiring_coins: &[bitcoin::OutPoint],
+) -> Element<'a, Message> {
+    Container::new(
+        Column::new()
+            .spacing(10)
+            .push(
+                Row::new()
+                    .push(h4_bold("Recovery readiness").width(Length::Fill))
+                    .push(h4_bold(format!("{}%", readiness.score))),
+            )
+            .push(
+                text("Make sure the funds can be recovered if keys are lost, or by your heirs.")
+                    .style(theme::text::secondary),
+            )
+            .push(
+                readiness
+                    .pending()
+                    .fold(Column::new().spacing(10), |col, check| {
+                        let (description, action) = match check.item {
+                            ReadinessItem::DescriptorBackup => (
+                                "Back up the wallet descriptor and verify the backup",
+                                Some(("Settings", Message::Menu(Menu::Settings))),
+                            ),
+                            ReadinessItem::DevicesRegistered => (
+                                "Register the descriptor on all the signing devices",
+                                Some(("Settings", Message::Menu(Menu::Settings))),
+                            ),
+                            ReadinessItem::RecoveryKeysTested => (
+                                "Test the recovery keys by signing a recovery transaction",
+                                Some(("Recovery", Message::Menu(Menu::Recovery))),
+                            ),
+                            ReadinessItem::HeirsKit => {
+                                ("Prepare a kit for your heirs to recover the funds", None)
+                            }
+                            ReadinessItem::TimelockMargin => (
+                                "Refresh the coins whose recovery path is soon available",
+                                Some((
+                                    "Refresh coins",
+                                    Message::Menu(Menu::RefreshCoins(expiring_coins.to_owned())),
+                                )),
+                            ),
+                        };
+                        col.push(
+                            Row::new()
+                                .spacing(10)
+                                .align_y(Alignment::Center)
+                                .push(p1_regular(description).width(Length::Fill))
+                                .push_maybe(action.map(|(label, msg)| {
+                                    button::secondary(None, label).on_press(msg)
+                                }))
+                                .push_maybe(if check.item.is_attested() {
+                                    Some(
+                                        button::primary(None, "Mark as done")
+                                            .on_press(Message::SetReadiness(check.item, true)),
+                                    )
+                                } else {
+                                    None
+                                }),
+                        )
+                    }),
+            ),
+    )
+    .padding(25)
+    .style(theme::card::simple)
+    .into()
+}
+
 fn event_list_view(event: &Payment) -> Element<'_, Message> {
     let label = if let Some(label) = &event.label {
         Some(p1_regular(label))
@@ -343,6 +650,7 @@ pub fn payment_view<'a>(
                                             ))
                                             .style(theme::button::transparent_border),
                                     )
+                                    .push_maybe(explorer_tx_button(cache, &tx.tx.compute_txid()))
                                     .width(Length::Shrink),
                             ),
                     )
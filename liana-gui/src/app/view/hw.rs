@@ -68,14 +68,58 @@ pub fn hw_list_view(
         HardwareWallet::Locked {
             kind, pairing_code, ..
         } => hw::locked_hardware_wallet(kind, pairing_code.as_ref()),
+        HardwareWallet::Tapsigner {
+            device,
+            fingerprint,
+            alias,
+            incompatible,
+            ..
+        } => {
+            if *incompatible {
+                hw::incompatible_hardware_wallet(
+                    "Tapsigner",
+                    Some(&device.version),
+                    "The Tapsigner cannot sign for Taproot descriptors",
+                )
+            } else if let Some(fingerprint) = fingerprint {
+                if signed {
+                    hw::sign_success_hardware_wallet(
+                        "Tapsigner",
+                        Some(&device.version),
+                        fingerprint,
+                        alias.as_ref(),
+                    )
+                } else {
+                    hw::supported_hardware_wallet(
+                        "Tapsigner",
+                        Some(&device.version),
+                        fingerprint,
+                        alias.as_ref(),
+                    )
+                }
+            } else {
+                hw::unidentified_hardware_wallet(
+                    "Tapsigner",
+                    Some(&device.version),
+                    "Enter its CVC to sign",
+                )
+            }
+        }
     })
     .style(theme::button::secondary)
     .width(Length::Fill);
     if !signing {
-        if let HardwareWallet::Supported { registered, .. } = hw {
-            if *registered != Some(false) {
+        match hw {
+            HardwareWallet::Supported { registered, .. } if *registered != Some(false) => {
+                bttn = bttn.on_press(Message::SelectHardwareWallet(i));
+            }
+            HardwareWallet::Tapsigner {
+                incompatible: false,
+                ..
+            } => {
                 bttn = bttn.on_press(Message::SelectHardwareWallet(i));
             }
+            _ => {}
         }
     }
     bttn.into()
@@ -133,6 +177,11 @@ pub fn hw_list_view_for_registration(
         HardwareWallet::Locked {
             kind, pairing_code, ..
         } => hw::locked_hardware_wallet(kind, pairing_code.as_ref()),
+        HardwareWallet::Tapsigner { device, .. } => hw::unidentified_hardware_wallet(
+            "Tapsigner",
+            Some(&device.version),
+            "The Tapsigner does not need the wallet to be registered",
+        ),
     })
     .style(theme::button::secondary)
     .width(Length::Fill);
@@ -214,6 +263,14 @@ pub fn hw_list_view_verify_address(
             hw::locked_hardware_wallet(kind, pairing_code.as_ref()),
             false,
         ),
+        HardwareWallet::Tapsigner { device, .. } => (
+            hw::unidentified_hardware_wallet(
+                "Tapsigner",
+                Some(&device.version),
+                "The Tapsigner cannot display addresses",
+            ),
+            false,
+        ),
     };
     let mut bttn = Button::new(content)
         .style(theme::button::secondary)
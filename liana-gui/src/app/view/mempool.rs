@@ -0,0 +1,140 @@
+use iced::{
+    widget::{Container, Row, Space},
+    Alignment, Length,
+};
+
+use liana_ui::{
+    color,
+    component::{button, text::*},
+    icon, theme,
+    widget::*,
+};
+
+use crate::{
+    app::view::{coins, message::Message},
+    daemon::model::{FeeHistogramBucket, GetMempoolStatsResult},
+};
+
+use lianad::commands::MAX_BLOCK_VSIZE;
+
+const HISTOGRAM_HEIGHT: f32 = 60.0;
+
+/// The congestion of the mempool of the backend: the size of the transactions waiting by
+/// feerate, the minimum feerates it accepts and, if a feerate is given, how fast a transaction
+/// paying it would confirm.
+pub fn mempool_card<'a>(
+    stats: &GetMempoolStatsResult,
+    feerate: Option<u64>,
+) -> Element<'a, Message> {
+    let vsize = stats.mempool_vsize();
+    Container::new(
+        Column::new()
+            .spacing(10)
+            .push(
+                Row::new()
+                    .align_y(Alignment::Center)
+                    .push(h4_bold("Mempool").width(Length::Fill))
+                    .push(
+                        button::secondary(Some(icon::arrow_repeat()), "Refresh")
+                            .on_press(Message::RefreshMempool),
+                    ),
+            )
+            .push_maybe(if stats.fee_histogram.is_empty() {
+                None
+            } else {
+                Some(
+                    p1_regular(format!(
+                        "{:.1} MvB of transactions waiting, about {} block{}.",
+                        vsize as f64 / 1_000_000.0,
+                        vsize.div_ceil(MAX_BLOCK_VSIZE),
+                        if vsize > MAX_BLOCK_VSIZE { "s" } else { "" },
+                    ))
+                    .style(theme::text::secondary),
+                )
+            })
+            .push_maybe(if stats.fee_histogram.is_empty() {
+                None
+            } else {
+                Some(histogram(&stats.fee_histogram, feerate))
+            })
+            .push_maybe(stats.min_relay_feerate.map(|relay| {
+                p1_regular(format!("Minimum relay feerate: {} sats/vbyte", relay))
+                    .style(theme::text::secondary)
+            }))
+            .push_maybe(
+                stats
+                    .mempool_min_feerate
+                    .filter(|min| stats.min_relay_feerate.is_some_and(|relay| min > &relay))
+                    .map(|min| {
+                        p1_regular(format!(
+                            "The mempool is full, transactions must pay at least {} sats/vbyte.",
+                            min
+                        ))
+                        .style(theme::text::warning)
+                    }),
+            )
+            .push_maybe(
+                feerate
+                    .filter(|_| !stats.fee_histogram.is_empty())
+                    .map(|feerate| p1_bold(projection(stats, feerate))),
+            ),
+    )
+    .padding(20)
+    .style(theme::card::simple)
+    .into()
+}
+
+fn projection(stats: &GetMempoolStatsResult, feerate: u64) -> String {
+    match stats.projected_blocks(feerate) {
+        None => format!(
+            "At {} sats/vbyte, the transaction would not be accepted in the mempool.",
+            feerate
+        ),
+        Some(1) => format!(
+            "At {} sats/vbyte, the transaction would likely confirm in the next block.",
+            feerate
+        ),
+        Some(blocks) => format!(
+            "At {} sats/vbyte, the transaction would confirm in about {} blocks (≈ {}) if no \
+             transaction paying more is broadcast meanwhile.",
+            feerate,
+            blocks,
+            coins::expire_message_units(blocks as u32).join(", ")
+        ),
+    }
+}
+
+/// A bar per bucket, from the lowest feerate to the highest. The bucket of the given feerate is
+/// highlighted.
+fn histogram<'a>(buckets: &[FeeHistogramBucket], feerate: Option<u64>) -> Element<'a, Message> {
+    let max = buckets.iter().map(|b| b.vsize).max().unwrap_or(0).max(1);
+    let selected = feerate.and_then(|feerate| buckets.iter().position(|b| b.feerate <= feerate));
+    buckets
+        .iter()
+        .enumerate()
+        .rev()
+        .fold(Row::new().spacing(2), |row, (i, bucket)| {
+            let height = if bucket.vsize == 0 {
+                0.0
+            } else {
+                (HISTOGRAM_HEIGHT * bucket.vsize as f32 / max as f32).max(1.0)
+            };
+            row.push(
+                Column::new()
+                    .width(Length::Fill)
+                    .align_x(Alignment::Center)
+                    .push(Space::with_height(Length::Fixed(HISTOGRAM_HEIGHT - height)))
+                    .push(
+                        Container::new(Space::new(Length::Fill, Length::Fixed(height))).style(
+                            theme::container::custom(if selected == Some(i) {
+                                color::GREEN
+                            } else {
+                                color::GREY_4
+                            }),
+                        ),
+                    )
+                    .push(caption(bucket.feerate).style(theme::text::secondary)),
+            )
+        })
+        .into()
+}
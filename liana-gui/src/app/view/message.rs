@@ -1,5 +1,18 @@
-use crate::{app::menu::Menu, export::ExportMessage, node::bitcoind::RpcAuthType};
+use crate::{
+    app::{
+        menu::Menu,
+        preferences::{CoinColumn, CoinsSort},
+        settings::{AccentColor, BlockExplorer},
+        view::activity::ActivityFilter,
+    },
+    daemon::model::ReadinessItem,
+    export::ExportMessage,
+    i18n::Language,
+    node::bitcoind::RpcAuthType,
+    qr_signer::QrSigner,
+};
 use liana::miniscript::bitcoin::{bip32::Fingerprint, OutPoint};
+use liana_ui::component::amount::{BitcoinDisplayUnit, NumberFormat};
 
 #[derive(Debug, Clone)]
 pub enum Message {
@@ -19,7 +32,40 @@ pub enum Message {
     SelectHardwareWallet(usize),
     CreateRbf(CreateRbfMessage),
     ShowQrCode(usize),
+    ShowAddressDetail(usize),
     Export(ExportMessage),
+    DismissUpdate,
+    DismissDroppedFile,
+    /// Mark an item of the recovery readiness checklist as done or not.
+    SetReadiness(ReadinessItem, bool),
+    /// Fetch the state of the mempool again.
+    RefreshMempool,
+    /// Add a coin to the ones to refresh together, or remove it.
+    SelectCoinToRefresh(OutPoint),
+    /// Show only these kinds of events in the activity feed.
+    FilterActivity(ActivityFilter),
+    /// Save the PDF receipt of the selected transaction.
+    SaveReceipt,
+    /// Open a link, to a block explorer, in the browser.
+    OpenUrl(String),
+    /// Lock the application until the passphrase is typed.
+    Lock,
+    UnlockPassphraseEdited(String),
+    Unlock,
+    /// Mask or reveal the amounts in all the views.
+    ToggleAmounts,
+    SortCoins(CoinsSort),
+    ToggleCoinColumn(CoinColumn),
+    Tour(TourMessage),
+}
+
+#[derive(Debug, Clone)]
+pub enum TourMessage {
+    Start,
+    Next,
+    Previous,
+    /// Skip the rest of the tour.
+    Close,
 }
 
 #[derive(Debug, Clone)]
@@ -40,7 +86,12 @@ pub enum CreateSpendMessage {
     SelectPath(usize),
     Generate,
     SendMaxToRecipient(usize),
+    ConfirmFutureWitness(usize),
     Clear,
+    ApplyTemplate(usize),
+    DeleteTemplate(usize),
+    TemplateNameEdited(String),
+    SaveTemplate,
 }
 
 #[derive(Debug, Clone)]
@@ -59,23 +110,79 @@ pub enum SpendTxMessage {
     Confirm,
     Cancel,
     SelectHotSigner,
+    SelectRemoteSigner,
     EditPsbt,
     PsbtEdited(String),
+    ConfirmationEdited(String),
     Next,
+    QrSign(QrSignMessage),
+    Tapsigner(TapsignerMessage),
+}
+
+/// The steps of signing with an airgapped device through QR codes.
+#[derive(Debug, Clone)]
+pub enum QrSignMessage {
+    Start(QrSigner),
+    SelectKey(Fingerprint),
+    Next,
+    Previous,
+    /// Display the next part of the animated QR code.
+    NextFrame,
+    SignedPsbtEdited(String),
+    Confirm,
+}
+
+/// Signing with a Tapsigner, unlocked by its CVC.
+#[derive(Debug, Clone)]
+pub enum TapsignerMessage {
+    CvcEdited(String),
+    Confirm,
+    Cancel,
 }
 
 #[derive(Debug, Clone)]
 pub enum SettingsMessage {
     EditBitcoindSettings,
     BitcoindSettings(SettingsEditMessage),
+    BitcoindServiceToggled(bool),
+    BitcoindUpgrade,
+    BitcoindVersionSelected(&'static str),
     ElectrumSettings(SettingsEditMessage),
     RescanSettings(SettingsEditMessage),
     EditRemoteBackendSettings,
     RemoteBackendSettings(RemoteBackendSettingsMessage),
     EditWalletSettings,
+    EditDisplaySettings,
+    DisplayUnitSelected(BitcoinDisplayUnit),
+    NumberFormatSelected(NumberFormat),
+    LanguageSelected(Language),
+    UiScaleSelected(Option<u16>),
+    ClipboardClearSelected(Option<u16>),
+    LockPassphraseEdited(String),
+    LockPassphraseConfirmationEdited(String),
+    SaveLockPassphrase,
+    RemoveLock,
+    LockDelaySelected(Option<u16>),
+    BitcoinUriHandlerToggled(bool),
     AboutSection,
+    DiagnosticsSection,
     RegisterWallet,
+    ExportKeySheets,
+    ExportDescriptor,
+    SaveDescriptor,
+    PrintDescriptor,
     FingerprintAliasEdited(Fingerprint, String),
+    AccentColorSelected(AccentColor),
+    SpendConfirmationThresholdEdited(String),
+    SpendConfirmationPinEdited(String),
+    SaveSpendConfirmation,
+    RemoveSpendConfirmation,
+    BlockExplorerUrlEdited(String),
+    BlockExplorerSelected(BlockExplorer),
+    RemoteSignerUrlEdited(String),
+    RemoteSignerTokenEdited(String),
+    SaveRemoteSigner,
+    RemoveRemoteSigner,
     Save,
 }
 
@@ -2,10 +2,12 @@ mod label;
 mod message;
 mod warning;
 
+pub mod activity;
 pub mod coins;
 pub mod export;
 pub mod home;
 pub mod hw;
+pub mod mempool;
 pub mod psbt;
 pub mod psbts;
 pub mod receive;
@@ -18,65 +20,107 @@ pub use message::*;
 use warning::warn;
 
 use iced::{
-    widget::{column, row, scrollable, Space},
+    widget::{column, row, scrollable, tooltip, Space},
     Length,
 };
 
 use liana_ui::{
     color,
-    component::{button, text::*},
+    component::{amount::amounts_hidden, button, card, form, network_badge, text::*},
     icon::{
-        coins_icon, cross_icon, history_icon, home_icon, receive_icon, send_icon, settings_icon,
+        block_icon, clipboard_icon, clock_icon, coins_icon, cross_icon, eye_icon, eye_slash_icon,
+        history_icon, home_icon, key_icon, receive_icon, send_icon, settings_icon, tooltip_icon,
+        warning_icon,
     },
     image::*,
     theme,
     widget::*,
 };
 
-use crate::app::{cache::Cache, error::Error, menu::Menu};
+use crate::{
+    app::{
+        cache::Cache,
+        error::Error,
+        help::{Topic, TourStep, TOUR},
+        menu::Menu,
+        notify,
+        settings::AccentColor,
+    },
+    daemon::model::{Event, Txid},
+    i18n::{tr, tr_args},
+    update::Release,
+};
+
+/// The color of the elements telling this wallet apart from the others.
+pub fn accent_color(accent: Option<AccentColor>) -> iced::Color {
+    match accent.unwrap_or_default() {
+        AccentColor::Green => color::GREEN,
+        AccentColor::Blue => color::BLUE,
+        AccentColor::Orange => color::ORANGE,
+        AccentColor::Red => color::RED,
+    }
+}
+
+/// A button opening the transaction on the block explorer of the wallet, unless the user disabled
+/// the links to a block explorer.
+pub fn explorer_tx_button<'a>(cache: &Cache, txid: &Txid) -> Option<Element<'a, Message>> {
+    cache
+        .block_explorer()
+        .tx_url(cache.network, txid)
+        .map(|url| {
+            tooltip::Tooltip::new(
+                Button::new(block_icon())
+                    .on_press(Message::OpenUrl(url))
+                    .style(theme::button::transparent_border),
+                "View on block explorer",
+                tooltip::Position::Top,
+            )
+            .into()
+        })
+}
 
-fn menu_green_bar<'a, T: 'a>() -> Container<'a, T> {
+fn menu_bar<'a, T: 'a>(accent: Option<AccentColor>) -> Container<'a, T> {
     Container::new(Space::with_width(Length::Fixed(2.0)))
         .height(Length::Fixed(50.0))
-        .style(theme::container::custom(color::GREEN))
+        .style(theme::container::custom(accent_color(accent)))
 }
 
 pub fn sidebar<'a>(menu: &Menu, cache: &'a Cache) -> Container<'a, Message> {
     let home_button = if *menu == Menu::Home {
         row!(
-            button::menu_active(Some(home_icon()), "Home")
+            button::menu_active(Some(home_icon()), tr("Home"))
                 .on_press(Message::Reload)
                 .width(iced::Length::Fill),
-            menu_green_bar(),
+            menu_bar(cache.accent_color),
         )
     } else {
-        row!(button::menu(Some(home_icon()), "Home")
+        row!(button::menu(Some(home_icon()), tr("Home"))
             .on_press(Message::Menu(Menu::Home))
             .width(iced::Length::Fill),)
     };
 
     let transactions_button = if *menu == Menu::Transactions {
         row!(
-            button::menu_active(Some(history_icon()), "Transactions")
+            button::menu_active(Some(history_icon()), tr("Transactions"))
                 .on_press(Message::Reload)
                 .width(iced::Length::Fill),
-            menu_green_bar()
+            menu_bar(cache.accent_color)
         )
     } else {
-        row!(button::menu(Some(history_icon()), "Transactions")
+        row!(button::menu(Some(history_icon()), tr("Transactions"))
             .on_press(Message::Menu(Menu::Transactions))
             .width(iced::Length::Fill))
     };
 
     let coins_button = if *menu == Menu::Coins {
         row!(
-            button::menu_active(Some(coins_icon()), "Coins")
+            button::menu_active(Some(coins_icon()), tr("Coins"))
                 .on_press(Message::Reload)
                 .width(iced::Length::Fill),
-            menu_green_bar()
+            menu_bar(cache.accent_color)
         )
     } else {
-        row!(button::menu(Some(coins_icon()), "Coins")
+        row!(button::menu(Some(coins_icon()), tr("Coins"))
             .style(theme::button::menu)
             .on_press(Message::Menu(Menu::Coins))
             .width(iced::Length::Fill))
@@ -84,52 +128,65 @@ pub fn sidebar<'a>(menu: &Menu, cache: &'a Cache) -> Container<'a, Message> {
 
     let psbt_button = if *menu == Menu::PSBTs {
         row!(
-            button::menu_active(Some(history_icon()), "PSBTs")
+            button::menu_active(Some(history_icon()), tr("PSBTs"))
                 .on_press(Message::Reload)
                 .width(iced::Length::Fill),
-            menu_green_bar()
+            menu_bar(cache.accent_color)
         )
     } else {
-        row!(button::menu(Some(history_icon()), "PSBTs")
+        row!(button::menu(Some(history_icon()), tr("PSBTs"))
             .on_press(Message::Menu(Menu::PSBTs))
             .width(iced::Length::Fill))
     };
 
     let spend_button = if *menu == Menu::CreateSpendTx {
         row!(
-            button::menu_active(Some(send_icon()), "Send")
+            button::menu_active(Some(send_icon()), tr("Send"))
                 .on_press(Message::Reload)
                 .width(iced::Length::Fill),
-            menu_green_bar()
+            menu_bar(cache.accent_color)
         )
     } else {
-        row!(button::menu(Some(send_icon()), "Send")
+        row!(button::menu(Some(send_icon()), tr("Send"))
             .on_press(Message::Menu(Menu::CreateSpendTx))
             .width(iced::Length::Fill))
     };
 
     let receive_button = if *menu == Menu::Receive {
         row!(
-            button::menu_active(Some(receive_icon()), "Receive")
+            button::menu_active(Some(receive_icon()), tr("Receive"))
                 .on_press(Message::Reload)
                 .width(iced::Length::Fill),
-            menu_green_bar()
+            menu_bar(cache.accent_color)
         )
     } else {
-        row!(button::menu(Some(receive_icon()), "Receive")
+        row!(button::menu(Some(receive_icon()), tr("Receive"))
             .on_press(Message::Menu(Menu::Receive))
             .width(iced::Length::Fill))
     };
 
+    let activity_button = if *menu == Menu::Activity {
+        row!(
+            button::menu_active(Some(clock_icon()), tr("Activity"))
+                .on_press(Message::Reload)
+                .width(iced::Length::Fill),
+            menu_bar(cache.accent_color)
+        )
+    } else {
+        row!(button::menu(Some(clock_icon()), tr("Activity"))
+            .on_press(Message::Menu(Menu::Activity))
+            .width(iced::Length::Fill))
+    };
+
     let settings_button = if *menu == Menu::Settings {
         row!(
-            button::menu_active(Some(settings_icon()), "Settings")
+            button::menu_active(Some(settings_icon()), tr("Settings"))
                 .on_press(Message::Menu(Menu::Settings))
                 .width(iced::Length::Fill),
-            menu_green_bar()
+            menu_bar(cache.accent_color)
         )
     } else {
-        row!(button::menu(Some(settings_icon()), "Settings")
+        row!(button::menu(Some(settings_icon()), tr("Settings"))
             .on_press(Message::Menu(Menu::Settings))
             .width(iced::Length::Fill))
     };
@@ -146,12 +203,21 @@ pub fn sidebar<'a>(menu: &Menu, cache: &'a Cache) -> Container<'a, Message> {
                         )
                         .padding(10),
                     )
+                    .push(
+                        Container::new(network_badge(
+                            cache.network,
+                            accent_color(cache.accent_color),
+                        ))
+                        .padding([0, 10]),
+                    )
+                    .push(Space::with_height(Length::Fixed(20.0)))
                     .push(home_button)
                     .push(spend_button)
                     .push(receive_button)
                     .push(coins_button)
                     .push(transactions_button)
                     .push(psbt_button)
+                    .push(activity_button)
                     .height(Length::Fill),
             )
             .push(
@@ -159,9 +225,40 @@ pub fn sidebar<'a>(menu: &Menu, cache: &'a Cache) -> Container<'a, Message> {
                     Column::new()
                         .spacing(10)
                         .push_maybe(cache.rescan_progress.map(|p| {
-                            Container::new(text(format!("  Rescan...{:.2}%  ", p * 100.0)))
-                                .padding(5)
-                                .style(theme::pill::simple)
+                            Container::new(text(format!(
+                                "  {}  ",
+                                tr_args(
+                                    "Rescan...{progress}%",
+                                    &[("progress", &format!("{:.2}", p * 100.0))]
+                                )
+                            )))
+                            .padding(5)
+                            .style(theme::pill::simple)
+                        }))
+                        .push_maybe((cache.queued_writes > 0).then(|| {
+                            Container::new(text(format!(
+                                "  {}  ",
+                                tr_args(
+                                    "{count} change(s) waiting for connection",
+                                    &[("count", &cache.queued_writes)]
+                                )
+                            )))
+                            .padding(5)
+                            .style(theme::pill::warning)
+                        }))
+                        .push(if amounts_hidden() {
+                            button::menu(Some(eye_icon()), tr("Show amounts"))
+                                .on_press(Message::ToggleAmounts)
+                                .width(iced::Length::Fill)
+                        } else {
+                            button::menu(Some(eye_slash_icon()), tr("Hide amounts"))
+                                .on_press(Message::ToggleAmounts)
+                                .width(iced::Length::Fill)
+                        })
+                        .push_maybe(cache.app_lock.then(|| {
+                            button::menu(Some(key_icon()), tr("Lock"))
+                                .on_press(Message::Lock)
+                                .width(iced::Length::Fill)
                         }))
                         .push(settings_button),
                 )
@@ -185,6 +282,11 @@ pub fn dashboard<'a, T: Into<Element<'a, Message>>>(
         )
         .push(
             Column::new()
+                .push(
+                    Container::new(Space::with_height(Length::Fixed(4.0)))
+                        .width(Length::Fill)
+                        .style(theme::container::custom(accent_color(cache.accent_color))),
+                )
                 .push(warn(warning))
                 .push(
                     Container::new(scrollable(row!(
@@ -205,6 +307,191 @@ pub fn dashboard<'a, T: Into<Element<'a, Message>>>(
         .into()
 }
 
+/// Tell the user a new release of Liana is available, with a summary of its changes.
+pub fn update_banner<'a>(release: &'a Release) -> Element<'a, Message> {
+    Container::new(
+        Row::new()
+            .spacing(10)
+            .align_y(iced::Alignment::Center)
+            .push(
+                Column::new()
+                    .spacing(5)
+                    .width(Length::Fill)
+                    .push(
+                        text(tr_args(
+                            "Liana {version} is available",
+                            &[("version", &release.version)],
+                        ))
+                        .bold(),
+                    )
+                    .push(
+                        release
+                            .changelog
+                            .iter()
+                            .take(3)
+                            .fold(Column::new(), |col, change| {
+                                col.push(text(format!("- {}", change)).small())
+                            }),
+                    )
+                    .push(
+                        Row::new()
+                            .spacing(5)
+                            .align_y(iced::Alignment::Center)
+                            .push(text(&release.url).small())
+                            .push(
+                                Button::new(clipboard_icon())
+                                    .on_press(Message::Clipboard(release.url.clone()))
+                                    .style(theme::button::transparent_border),
+                            ),
+                    ),
+            )
+            .push(
+                button::transparent(Some(cross_icon()), tr("Dismiss"))
+                    .on_press(Message::DismissUpdate),
+            ),
+    )
+    .padding(10)
+    .width(Length::Fill)
+    .style(theme::banner::warning)
+    .into()
+}
+
+/// A banner telling the tip of the chain is stale or the system clock is off, until it's no longer
+/// the case. The timelocks displayed depend on them.
+pub fn chain_warning_banner<'a>(event: &Event) -> Element<'a, Message> {
+    let (title, body) = notify::content(event);
+    Container::new(
+        Row::new()
+            .spacing(10)
+            .align_y(iced::Alignment::Center)
+            .push(warning_icon())
+            .push(
+                Column::new()
+                    .spacing(5)
+                    .width(Length::Fill)
+                    .push(text(title).bold())
+                    .push(text(body).small()),
+            ),
+    )
+    .padding(10)
+    .width(Length::Fill)
+    .style(theme::banner::warning)
+    .into()
+}
+
+/// A banner telling why a file dropped onto the window wasn't imported.
+pub fn dropped_file_banner<'a>(error: &'a str) -> Element<'a, Message> {
+    Container::new(
+        Row::new()
+            .spacing(10)
+            .align_y(iced::Alignment::Center)
+            .push(warning_icon())
+            .push(text(error).width(Length::Fill))
+            .push(
+                button::transparent(Some(cross_icon()), tr("Dismiss"))
+                    .on_press(Message::DismissDroppedFile),
+            ),
+    )
+    .padding(10)
+    .width(Length::Fill)
+    .style(theme::banner::warning)
+    .into()
+}
+
+/// Shown instead of the wallet while the application is locked.
+pub fn lock_screen<'a>(passphrase: &form::Value<String>, checking: bool) -> Element<'a, Message> {
+    Container::new(
+        Column::new()
+            .spacing(20)
+            .align_x(iced::Alignment::Center)
+            .max_width(400)
+            .push(
+                liana_grey_logo()
+                    .height(Length::Fixed(120.0))
+                    .width(Length::Fixed(60.0)),
+            )
+            .push(h3(tr("Liana is locked")))
+            .push(
+                TextInput::new(tr("Passphrase"), &passphrase.value)
+                    .on_input(Message::UnlockPassphraseEdited)
+                    .on_submit(Message::Unlock)
+                    .secure(true)
+                    .size(P1_SIZE)
+                    .padding(10),
+            )
+            .push_maybe(
+                (!passphrase.valid)
+                    .then(|| caption(tr("Wrong passphrase")).style(theme::text::error)),
+            )
+            .push(if checking {
+                button::primary(None, tr("Unlocking")).width(Length::Fill)
+            } else {
+                button::primary(None, tr("Unlock"))
+                    .on_press_maybe((!passphrase.value.is_empty()).then_some(Message::Unlock))
+                    .width(Length::Fill)
+            }),
+    )
+    .center_x(Length::Fill)
+    .center_y(Length::Fill)
+    .style(theme::container::background)
+    .into()
+}
+
+/// A tooltip explaining a concept of the wallet.
+pub fn help<'a, T: 'a>(topic: Topic) -> Element<'a, T> {
+    help_icon(tooltip_icon(), topic)
+}
+
+/// A tooltip explaining a concept of the wallet, over the given icon.
+pub fn help_icon<'a, T: 'a>(icon: Text<'a>, topic: Topic) -> Element<'a, T> {
+    tooltip::Tooltip::new(
+        icon,
+        Container::new(text(tr(topic.text()))).max_width(400),
+        tooltip::Position::Right,
+    )
+    .padding(10)
+    .style(theme::card::simple)
+    .into()
+}
+
+/// The card of a step of the guided tour, shown over the view the step opened.
+pub fn tour_step<'a>(index: usize, step: &TourStep) -> Element<'a, Message> {
+    let last = index + 1 == TOUR.len();
+    card::simple(
+        Column::new()
+            .spacing(20)
+            .push(
+                caption(tr_args(
+                    "Step {step} of {count}",
+                    &[("step", &(index + 1)), ("count", &TOUR.len())],
+                ))
+                .style(theme::text::secondary),
+            )
+            .push(h4_bold(tr(step.topic.title())))
+            .push(text(tr(step.topic.text())))
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_y(iced::Alignment::Center)
+                    .push_maybe((!last).then(|| {
+                        button::transparent(None, tr("Skip the tour"))
+                            .on_press(Message::Tour(TourMessage::Close))
+                    }))
+                    .push(Space::with_width(Length::Fill))
+                    .push_maybe((index > 0).then(|| {
+                        button::secondary(None, tr("Previous"))
+                            .on_press(Message::Tour(TourMessage::Previous))
+                    }))
+                    .push(
+                        button::primary(None, if last { tr("Done") } else { tr("Next") })
+                            .on_press(Message::Tour(TourMessage::Next)),
+                    ),
+            ),
+    )
+    .width(Length::Fixed(500.0))
+    .into()
+}
+
 pub fn modal<'a, T: Into<Element<'a, Message>>, F: Into<Element<'a, Message>>>(
     is_previous: bool,
     warning: Option<&Error>,
@@ -219,14 +506,17 @@ pub fn modal<'a, T: Into<Element<'a, Message>>, F: Into<Element<'a, Message>>>(
                     .push(if is_previous {
                         Column::new()
                             .push(
-                                button::transparent(None, "< Previous").on_press(Message::Previous),
+                                button::transparent(None, tr("< Previous"))
+                                    .on_press(Message::Previous),
                             )
                             .width(Length::Fill)
                     } else {
                         Column::new().width(Length::Fill)
                     })
                     .align_y(iced::Alignment::Center)
-                    .push(button::secondary(Some(cross_icon()), "Close").on_press(Message::Close)),
+                    .push(
+                        button::secondary(Some(cross_icon()), tr("Close")).on_press(Message::Close),
+                    ),
             )
             .padding(10)
             .style(theme::container::background),
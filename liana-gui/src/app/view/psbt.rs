@@ -1,15 +1,19 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 use iced::{
-    widget::{scrollable, tooltip, Space},
+    widget::{
+        qr_code::{self, QRCode},
+        scrollable, tooltip, Space,
+    },
     Alignment, Length,
 };
 
 use liana::{
     descriptors::{LianaPolicy, PathInfo, PathSpendInfo},
     miniscript::bitcoin::{
-        bip32::Fingerprint, blockdata::transaction::TxOut, Address, Network, OutPoint, Transaction,
-        Txid,
+        bip32::{DerivationPath, Fingerprint},
+        blockdata::transaction::TxOut,
+        Address, Network, OutPoint, Transaction, Txid,
     },
 };
 
@@ -18,7 +22,7 @@ use liana_ui::{
         amount::*,
         badge, button, card,
         collapse::Collapse,
-        form, hw, separation,
+        form, hw, network_badge, separation,
         text::{self, *},
     },
     icon, theme,
@@ -30,10 +34,13 @@ use crate::{
         cache::Cache,
         error::Error,
         menu::Menu,
-        view::{dashboard, hw::hw_list_view, label, message::*, warning::warn},
+        settings::{AccentColor, SpendConfirmation},
+        view::{accent_color, dashboard, hw::hw_list_view, label, message::*, warning::warn},
     },
-    daemon::model::{Coin, SpendStatus, SpendTx},
+    daemon::model::{Coin, ReviewDecision, SpendStatus, SpendTx},
     hw::HardwareWallet,
+    qr_signer::{QrSigner, Step},
+    remote_signer::RemoteSigner,
 };
 
 #[allow(clippy::too_many_arguments)]
@@ -70,6 +77,8 @@ pub fn psbt_view<'a>(
                         _ => None,
                     }),
             )
+            .push_maybe(spend_conflicts_view(tx))
+            .push_maybe(spend_reviews_view(tx, key_aliases))
             .push(spend_header(tx, labels_editing))
             .push(spend_overview_view(tx, desc_info, key_aliases))
             .push(
@@ -111,6 +120,98 @@ pub fn psbt_view<'a>(
     )
 }
 
+/// The confirmed transaction which invalidated this draft and the other drafts it conflicts
+/// with, if any.
+fn spend_conflicts_view<'a>(tx: &'a SpendTx) -> Option<Element<'a, Message>> {
+    if tx.invalidated_by.is_none() && tx.conflicting_spends.is_empty() {
+        return None;
+    }
+    Some(
+        card::simple(
+            Column::new()
+                .spacing(5)
+                .push_maybe(tx.invalidated_by.map(|txid| {
+                    Row::new()
+                        .spacing(10)
+                        .align_y(Alignment::Center)
+                        .push(icon::warning_icon())
+                        .push(text(format!(
+                            "This transaction can't be broadcast: one of its inputs was spent \
+                             by the confirmed transaction {}.",
+                            txid
+                        )))
+                }))
+                .push_maybe(if tx.conflicting_spends.is_empty() {
+                    None
+                } else {
+                    Some(tx.conflicting_spends.iter().fold(
+                        Column::new().spacing(5).push(text(
+                            "Other saved transactions spend some of the same inputs. \
+                             Broadcasting one of them invalidates the others:",
+                        )),
+                        |col, txid| {
+                            col.push(
+                                Row::new()
+                                    .padding([0, 30])
+                                    .push(text(txid.to_string()).style(theme::text::secondary)),
+                            )
+                        },
+                    ))
+                }),
+        )
+        .width(Length::Fill)
+        .into(),
+    )
+}
+
+/// The reviews of the co-signers who reviewed this transaction, if any.
+fn spend_reviews_view<'a>(
+    tx: &'a SpendTx,
+    key_aliases: &'a HashMap<Fingerprint, String>,
+) -> Option<Element<'a, Message>> {
+    if tx.reviews.is_empty() {
+        return None;
+    }
+    Some(
+        card::simple(tx.reviews.iter().fold(
+            Column::new().spacing(10).push(h4_bold("Reviews")),
+            |col, review| {
+                let signer = key_aliases
+                    .get(&review.signer)
+                    .cloned()
+                    .unwrap_or_else(|| review.signer.to_string());
+                col.push(
+                    Column::new()
+                        .spacing(5)
+                        .push(
+                            Row::new()
+                                .spacing(10)
+                                .align_y(Alignment::Center)
+                                .push_maybe(if review.decision == ReviewDecision::Approved {
+                                    None
+                                } else {
+                                    Some(icon::warning_icon())
+                                })
+                                .push(p1_bold(signer))
+                                .push(text(match review.decision {
+                                    ReviewDecision::Approved => "approved",
+                                    ReviewDecision::ChangesRequested => "requested changes",
+                                    ReviewDecision::Rejected => "rejected",
+                                })),
+                        )
+                        .push_maybe(review.comment.as_ref().map(|comment| {
+                            Row::new()
+                                .padding([0, 30])
+                                .push(text(comment).style(theme::text::secondary))
+                        })),
+                )
+            },
+        ))
+        .width(Length::Fill)
+        .into(),
+    )
+}
+
 pub fn save_action<'a>(warning: Option<&Error>, saved: bool) -> Element<'a, Message> {
     if saved {
         card::simple(text("Transaction is saved"))
@@ -142,11 +243,18 @@ pub fn save_action<'a>(warning: Option<&Error>, saved: bool) -> Element<'a, Mess
 /// Return the modal view to broadcast a transaction.
 ///
 /// `conflicting_txids` contains the IDs of any directly conflicting transactions
-/// of the transaction to be broadcast.
+/// of the transaction to be broadcast. The wallet and its network are shown so the
+/// user can check the payment is sent from the intended one.
+#[allow(clippy::too_many_arguments)]
 pub fn broadcast_action<'a>(
     conflicting_txids: &HashSet<Txid>,
+    wallet_name: &'a str,
+    network: Network,
+    accent: Option<AccentColor>,
     warning: Option<&Error>,
     saved: bool,
+    confirmation: Option<(&SpendConfirmation, Amount, &str)>,
+    confirmed: bool,
 ) -> Element<'a, Message> {
     if saved {
         card::simple(text("Transaction is broadcast"))
@@ -159,6 +267,13 @@ pub fn broadcast_action<'a>(
                 .spacing(10)
                 .push_maybe(warning.map(|w| warn(Some(w))))
                 .push(Container::new(h4_bold("Broadcast the transaction")).width(Length::Fill))
+                .push(
+                    Row::new()
+                        .spacing(10)
+                        .align_y(Alignment::Center)
+                        .push(network_badge(network, accent_color(accent)))
+                        .push(text(wallet_name).bold()),
+                )
                 .push_maybe(if conflicting_txids.is_empty() {
                     None
                 } else {
@@ -212,12 +327,38 @@ pub fn broadcast_action<'a>(
                         ),
                     )
                 })
-                .push(
-                    Row::new().push(Column::new().width(Length::Fill)).push(
-                        button::secondary(None, "Broadcast")
-                            .on_press(Message::Spend(SpendTxMessage::Confirm)),
+                .push_maybe(confirmation.map(|(confirmation, spend_amount, input)| {
+                    Column::new()
+                        .spacing(5)
+                        .push(text(if confirmation.pin_sha256.is_some() {
+                            format!(
+                                "This transaction sends {} {}. Enter the PIN to confirm:",
+                                amount_as_input(spend_amount),
+                                display_unit()
+                            )
+                        } else {
+                            format!(
+                                "This transaction sends more than {} {}. Retype the amount \
+                                 sent, in {}, to confirm:",
+                                amount_as_input(Amount::from_sat(confirmation.threshold)),
+                                display_unit(),
+                                display_unit()
+                            )
+                        }))
+                        .push(
+                            TextInput::new("", input)
+                                .on_input(|s| Message::Spend(SpendTxMessage::ConfirmationEdited(s)))
+                                .on_submit(Message::Spend(SpendTxMessage::Confirm))
+                                .secure(confirmation.pin_sha256.is_some())
+                                .size(text::P1_SIZE)
+                                .padding(10),
+                        )
+                }))
+                .push(Row::new().push(Column::new().width(Length::Fill)).push(
+                    button::secondary(None, "Broadcast").on_press_maybe(
+                        confirmed.then_some(Message::Spend(SpendTxMessage::Confirm)),
                     ),
-                ),
+                )),
         )
         .width(Length::Fixed(if conflicting_txids.is_empty() {
             400.0
@@ -380,18 +521,30 @@ pub fn spend_overview_view<'a>(
             Some(
                 Row::new()
                     .push(Space::with_width(Length::Fill))
-                    .push_maybe(if tx.path_ready().is_none() {
-                        Some(
-                            button::secondary(None, "Sign")
-                                .on_press(Message::Spend(SpendTxMessage::Sign))
-                                .width(Length::Fixed(150.0)),
-                        )
-                    } else {
-                        Some(
+                    .push(if tx.is_finalizable() {
+                        Element::from(
                             button::secondary(None, "Broadcast")
                                 .on_press(Message::Spend(SpendTxMessage::Broadcast))
                                 .width(Length::Fixed(150.0)),
                         )
+                    } else if tx.path_ready().is_some() {
+                        // The signatures are there but the witness can't be constructed.
+                        tooltip::Tooltip::new(
+                            button::secondary(None, "Broadcast").width(Length::Fixed(150.0)),
+                            text(
+                                tx.finalization_error()
+                                    .unwrap_or("The transaction cannot be finalized.")
+                                    .to_string(),
+                            ),
+                            tooltip::Position::Top,
+                        )
+                        .style(theme::card::simple)
+                        .into()
+                    } else {
+                        button::secondary(None, "Sign")
+                            .on_press(Message::Spend(SpendTxMessage::Sign))
+                            .width(Length::Fixed(150.0))
+                            .into()
                     })
                     .align_y(Alignment::Center)
                     .spacing(20),
@@ -1013,11 +1166,14 @@ fn change_view(output: &TxOut, network: Network) -> Element<Message> {
         .into()
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn sign_action<'a>(
     warning: Option<&Error>,
     hws: &'a [HardwareWallet],
     signer: Option<Fingerprint>,
     signer_alias: Option<&'a String>,
+    remote_signer: Option<&'a RemoteSigner>,
+    remote_signer_alias: Option<&'a String>,
     signed: &HashSet<Fingerprint>,
     signing: &HashSet<Fingerprint>,
 ) -> Element<'a, Message> {
@@ -1059,6 +1215,58 @@ pub fn sign_action<'a>(
                             .style(theme::button::secondary)
                             .width(Length::Fill)
                         }))
+                        .push_maybe(remote_signer.map(|remote_signer| {
+                            let fingerprint = remote_signer.fingerprint;
+                            let host = Some(remote_signer.host());
+                            let mut bttn = Button::new(if signing.contains(&fingerprint) {
+                                hw::processing_hardware_wallet(
+                                    "Remote signer",
+                                    host,
+                                    fingerprint,
+                                    remote_signer_alias,
+                                )
+                            } else if signed.contains(&fingerprint) {
+                                hw::sign_success_hardware_wallet(
+                                    "Remote signer",
+                                    host,
+                                    fingerprint,
+                                    remote_signer_alias,
+                                )
+                            } else {
+                                hw::supported_hardware_wallet(
+                                    "Remote signer",
+                                    host,
+                                    fingerprint,
+                                    remote_signer_alias,
+                                )
+                            })
+                            .style(theme::button::secondary)
+                            .width(Length::Fill);
+                            if !signing.contains(&fingerprint) {
+                                bttn = bttn
+                                    .on_press(Message::Spend(SpendTxMessage::SelectRemoteSigner));
+                            }
+                            bttn
+                        }))
+                        .width(Length::Fill),
+                )
+                .push(
+                    Column::new()
+                        .spacing(10)
+                        .push(text("Or sign through QR codes with:").bold())
+                        .push(
+                            QrSigner::ALL
+                                .iter()
+                                .fold(Row::new().spacing(10), |row, signer| {
+                                    row.push(
+                                        button::secondary(None, signer.name())
+                                            .on_press(Message::Spend(SpendTxMessage::QrSign(
+                                                QrSignMessage::Start(*signer),
+                                            )))
+                                            .width(Length::Fill),
+                                    )
+                                }),
+                        )
                         .width(Length::Fill),
                 )
                 .spacing(20)
@@ -1069,6 +1277,203 @@ pub fn sign_action<'a>(
         .into()
 }
 
+/// The current step of signing with an airgapped device through QR codes.
+#[allow(clippy::too_many_arguments)]
+pub fn qr_sign_view<'a>(
+    signer: QrSigner,
+    step: usize,
+    keys: &'a BTreeMap<Fingerprint, BTreeSet<DerivationPath>>,
+    keys_aliases: &'a HashMap<Fingerprint, String>,
+    selected_key: Option<Fingerprint>,
+    qr: Option<(&'a qr_code::Data, usize, usize)>,
+    signed_psbt: &form::Value<String>,
+    status: Option<&Result<String, String>>,
+) -> Element<'a, Message> {
+    let steps = signer.steps();
+    let current = steps.get(step).copied().unwrap_or(Step::LoadSeed);
+    let key_name = |fg: &Fingerprint| {
+        keys_aliases
+            .get(fg)
+            .map(|alias| format!("{} ({})", alias, fg))
+            .unwrap_or_else(|| fg.to_string())
+    };
+    let content: Element<'a, Message> = match current {
+        Step::LoadSeed => Column::new()
+            .spacing(10)
+            .push(if keys.is_empty() {
+                Column::new().push(
+                    text("All the keys of the wallet already signed this transaction.")
+                        .style(theme::text::secondary),
+                )
+            } else {
+                keys.keys().fold(Column::new().spacing(10), |col, fg| {
+                    col.push(
+                        Button::new(
+                            Row::new()
+                                .spacing(10)
+                                .align_y(Alignment::Center)
+                                .push(icon::key_icon())
+                                .push(text(key_name(fg)).width(Length::Fill))
+                                .push_maybe(
+                                    (selected_key == Some(*fg)).then(icon::circle_check_icon),
+                                ),
+                        )
+                        .on_press(Message::Spend(SpendTxMessage::QrSign(
+                            QrSignMessage::SelectKey(*fg),
+                        )))
+                        .padding(10)
+                        .style(theme::button::secondary)
+                        .width(Length::Fill),
+                    )
+                })
+            })
+            .push_maybe(selected_key.and_then(|fg| {
+                keys.get(&fg).map(|paths| {
+                    text(signer.load_seed_prompt(&key_name(&fg), paths))
+                        .style(theme::text::secondary)
+                })
+            }))
+            .into(),
+        Step::ShowDescriptor | Step::ShowPsbt => {
+            if let Some((data, frame, frames)) = qr {
+                Column::new()
+                    .spacing(10)
+                    .align_x(Alignment::Center)
+                    .push(
+                        Container::new(QRCode::<liana_ui::theme::Theme>::new(data).cell_size(4))
+                            .center_x(Length::Fill),
+                    )
+                    .push_maybe((frames > 1).then(|| {
+                        text(format!("Part {} of {}", frame + 1, frames))
+                            .small()
+                            .style(theme::text::secondary)
+                    }))
+                    .into()
+            } else {
+                text("The data is too large to be displayed as a QR code.")
+                    .style(theme::text::error)
+                    .into()
+            }
+        }
+        Step::ScanSignedPsbt => Column::new()
+            .spacing(10)
+            .push(
+                form::Form::new_trimmed("Signed PSBT", signed_psbt, move |msg| {
+                    Message::Spend(SpendTxMessage::QrSign(QrSignMessage::SignedPsbtEdited(msg)))
+                })
+                .warning("Please enter the PSBT signed by the device")
+                .size(P1_SIZE)
+                .padding(10),
+            )
+            .push_maybe(status.map(super::psbts::import_status))
+            .into(),
+    };
+    let next = match current {
+        Step::LoadSeed => button::secondary(None, "Next").on_press_maybe(
+            selected_key.map(|_| Message::Spend(SpendTxMessage::QrSign(QrSignMessage::Next))),
+        ),
+        Step::ShowDescriptor | Step::ShowPsbt => button::secondary(None, "Next")
+            .on_press(Message::Spend(SpendTxMessage::QrSign(QrSignMessage::Next))),
+        Step::ScanSignedPsbt => button::primary(None, "Sign").on_press_maybe(
+            (signed_psbt.valid && !signed_psbt.value.is_empty()).then_some(Message::Spend(
+                SpendTxMessage::QrSign(QrSignMessage::Confirm),
+            )),
+        ),
+    };
+    card::simple(
+        Column::new()
+            .spacing(20)
+            .push(
+                Row::new()
+                    .align_y(Alignment::Center)
+                    .push(
+                        text(format!("Sign with {}", signer))
+                            .bold()
+                            .width(Length::Fill),
+                    )
+                    .push(
+                        text(format!("Step {} of {}", step + 1, steps.len()))
+                            .style(theme::text::secondary),
+                    ),
+            )
+            .push(text(signer.prompt(current)))
+            .push(content)
+            .push(
+                Row::new()
+                    .push(
+                        button::secondary(Some(icon::previous_icon()), "Previous").on_press(
+                            Message::Spend(SpendTxMessage::QrSign(QrSignMessage::Previous)),
+                        ),
+                    )
+                    .push(Space::with_width(Length::Fill))
+                    .push(next),
+            ),
+    )
+    .width(Length::Fixed(500.0))
+    .into()
+}
+
+/// The CVC entry to sign with a Tapsigner.
+pub fn tapsigner_sign_view<'a>(
+    warning: Option<&Error>,
+    cvc: &form::Value<String>,
+    processing: bool,
+) -> Element<'a, Message> {
+    Column::new()
+        .push_maybe(warning.map(|w| warn(Some(w))))
+        .push(card::simple(
+            Column::new()
+                .spacing(20)
+                .push(text("Sign with your Tapsigner").bold())
+                .push(text(
+                    "Keep the card on the NFC reader and enter its CVC, the 6 digits printed on \
+                     its back.",
+                ))
+                .push(
+                    Column::new()
+                        .spacing(5)
+                        .push(
+                            TextInput::new("CVC", &cvc.value)
+                                .on_input(|msg| {
+                                    Message::Spend(SpendTxMessage::Tapsigner(
+                                        TapsignerMessage::CvcEdited(msg),
+                                    ))
+                                })
+                                .secure(true)
+                                .size(P1_SIZE)
+                                .padding(10),
+                        )
+                        .push_maybe(if cvc.valid {
+                            None
+                        } else {
+                            Some(
+                                text("The CVC is the 6 digits on the back of the card")
+                                    .small()
+                                    .style(theme::text::error),
+                            )
+                        }),
+                )
+                .push(
+                    Row::new()
+                        .push(button::secondary(None, "Cancel").on_press(Message::Spend(
+                            SpendTxMessage::Tapsigner(TapsignerMessage::Cancel),
+                        )))
+                        .push(Space::with_width(Length::Fill))
+                        .push(if processing {
+                            button::primary(None, "Signing...")
+                        } else {
+                            button::primary(None, "Sign").on_press_maybe(
+                                (!cvc.value.is_empty()).then_some(Message::Spend(
+                                    SpendTxMessage::Tapsigner(TapsignerMessage::Confirm),
+                                )),
+                            )
+                        }),
+                ),
+        ))
+        .width(Length::Fixed(500.0))
+        .into()
+}
+
 pub fn sign_action_toasts<'a>(
     error: Option<&Error>,
     hws: &'a [HardwareWallet],
@@ -1121,6 +1526,7 @@ pub fn sign_action_toasts<'a>(
 pub fn update_spend_view<'a>(
     psbt: String,
     updated: &form::Value<String>,
+    status: Option<&Result<String, String>>,
     error: Option<&Error>,
     processing: bool,
 ) -> Element<'a, Message> {
@@ -1142,15 +1548,16 @@ pub fn update_spend_view<'a>(
                 .push(
                     Column::new()
                         .spacing(10)
-                        .push(text("Insert updated PSBT:").bold())
+                        .push(text("Insert updated PSBT or signed transaction:").bold())
                         .push(
                             form::Form::new_trimmed("PSBT", updated, move |msg| {
                                 Message::ImportSpend(ImportSpendMessage::PsbtEdited(msg))
                             })
-                            .warning("Please enter the correct base64 encoded PSBT")
+                            .warning("Please enter the PSBT or signed transaction of this spend")
                             .size(P1_SIZE)
                             .padding(10),
                         )
+                        .push_maybe(status.map(super::psbts::import_status))
                         .push(Row::new().push(Space::with_width(Length::Fill)).push(
                             if updated.valid && !updated.value.is_empty() && !processing {
                                 button::secondary(None, "Update")
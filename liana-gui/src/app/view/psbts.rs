@@ -7,14 +7,15 @@ use liana_ui::{
 };
 
 use crate::{
-    app::{error::Error, menu::Menu},
+    app::{error::Error, help::Topic, menu::Menu},
     daemon::model::{SpendStatus, SpendTx},
 };
 
-use super::{message::*, warning::warn};
+use super::{help, message::*, warning::warn};
 
 pub fn import_psbt_view<'a>(
     imported: &form::Value<String>,
+    status: Option<&Result<String, String>>,
     error: Option<&Error>,
     processing: bool,
 ) -> Element<'a, Message> {
@@ -23,15 +24,16 @@ pub fn import_psbt_view<'a>(
         .push(card::simple(
             Column::new()
                 .spacing(10)
-                .push(text("Insert PSBT:").bold())
+                .push(text("Insert PSBT or signed transaction:").bold())
                 .push(
                     form::Form::new_trimmed("PSBT", imported, move |msg| {
                         Message::ImportSpend(ImportSpendMessage::PsbtEdited(msg))
                     })
-                    .warning("Please enter a base64 encoded PSBT")
+                    .warning("Please enter a PSBT (base64, hex) or a signed transaction (hex)")
                     .size(P1_SIZE)
                     .padding(10),
                 )
+                .push_maybe(status.map(import_status))
                 .push(Row::new().push(Space::with_width(Length::Fill)).push(
                     if imported.valid && !imported.value.is_empty() && !processing {
                         button::secondary(None, "Import")
@@ -47,6 +49,16 @@ pub fn import_psbt_view<'a>(
         .into()
 }
 
+/// The format detected for the imported PSBT or transaction, or why it can't be imported.
+pub fn import_status<'a>(status: &Result<String, String>) -> Element<'a, Message> {
+    match status {
+        Ok(format) => p2_regular(format!("Detected {}", format))
+            .style(theme::text::secondary)
+            .into(),
+        Err(e) => p2_regular(e.clone()).style(theme::text::error).into(),
+    }
+}
+
 pub fn import_psbt_success_view<'a>() -> Element<'a, Message> {
     Column::new()
         .push(
@@ -66,7 +78,14 @@ pub fn psbts_view(spend_txs: &[SpendTx]) -> Element<'_, Message> {
             Row::new()
                 .align_y(Alignment::Center)
                 .spacing(10)
-                .push(Container::new(h3("PSBTs")).width(Length::Fill))
+                .push(
+                    Row::new()
+                        .spacing(10)
+                        .align_y(Alignment::Center)
+                        .push(h3("PSBTs"))
+                        .push(help(Topic::Psbt))
+                        .width(Length::Fill),
+                )
                 .push(
                     button::secondary(Some(icon::import_icon()), "Import")
                         .on_press(Message::ImportSpend(ImportSpendMessage::Import)),
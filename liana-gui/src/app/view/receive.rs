@@ -10,12 +10,13 @@ use iced::{
 
 use liana::miniscript::bitcoin::{
     self,
-    bip32::{ChildNumber, Fingerprint},
+    bip32::{ChildNumber, DerivationPath, Fingerprint},
     Address,
 };
 
 use liana_ui::{
     component::{
+        amount::amount,
         button, card, form,
         text::{self, *},
     },
@@ -28,6 +29,7 @@ use crate::{
         error::Error,
         view::{hw, label, warning::warn},
     },
+    daemon::model::Coin,
     hw::HardwareWallet,
 };
 
@@ -119,10 +121,15 @@ pub fn receive<'a>(
                                                 .on_press(Message::Select(i)),
                                             )
                                             .push(Space::with_width(Length::Fill))
+                                            .push(
+                                                button::secondary(None, "Details")
+                                                    .on_press(Message::ShowAddressDetail(i)),
+                                            )
                                             .push(
                                                 button::secondary(None, "Show QR Code")
                                                     .on_press(Message::ShowQrCode(i)),
-                                            ),
+                                            )
+                                            .spacing(10),
                                     )
                                     .spacing(10),
                             )
@@ -235,3 +242,131 @@ pub fn qr_modal<'a>(qr: &'a qr_code::Data, address: &'a String) -> Element<'a, M
         .max_width(400)
         .into()
 }
+
+fn detail_row<'a>(label: &'static str, value: String) -> Row<'a, Message> {
+    Row::new()
+        .width(Length::Fill)
+        .align_y(Alignment::Center)
+        .spacing(10)
+        .push(Container::new(text(label).bold()).width(Length::Fixed(200.0)))
+        .push(Container::new(text(value).small()).width(Length::Fill))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn address_detail_modal<'a>(
+    warning: Option<&Error>,
+    address: &'a Address,
+    derivation_index: &ChildNumber,
+    change_address: &'a Address,
+    key_paths: &'a [(Fingerprint, Option<String>, DerivationPath)],
+    qr: Option<&'a qr_code::Data>,
+    coins: Option<&'a [Coin]>,
+    proof: &'a str,
+    blockheight: i32,
+) -> Element<'a, Message> {
+    let history: Element<'a, Message> = match coins {
+        None => text("Loading...").small().into(),
+        Some([]) => text("This address did not receive any coin yet.")
+            .small()
+            .into(),
+        Some(coins) => coins
+            .iter()
+            .fold(Column::new().spacing(5), |col, coin| {
+                let status = match (&coin.spend_info, coin.block_height) {
+                    (Some(spend), _) => format!("Spent by {}", spend.txid),
+                    (None, Some(height)) => format!(
+                        "Received at block {} ({} confirmations)",
+                        height,
+                        blockheight - height + 1
+                    ),
+                    (None, None) => "Unconfirmed".to_string(),
+                };
+                col.push(
+                    Row::new()
+                        .spacing(10)
+                        .align_y(Alignment::Center)
+                        .push(Container::new(amount(&coin.amount)).width(Length::Fixed(200.0)))
+                        .push(text(status).small()),
+                )
+            })
+            .into(),
+    };
+
+    Column::new()
+        .push_maybe(warning.map(|w| warn(Some(w))))
+        .push(card::simple(
+            Column::new()
+                .spacing(20)
+                .push(
+                    Row::new()
+                        .spacing(20)
+                        .align_y(Alignment::Center)
+                        .push(
+                            Column::new()
+                                .spacing(5)
+                                .width(Length::Fill)
+                                .push(detail_row("Address:", address.to_string()))
+                                .push(detail_row(
+                                    "Derivation index:",
+                                    derivation_index.to_string(),
+                                ))
+                                .push(detail_row(
+                                    "Change address at this index:",
+                                    change_address.to_string(),
+                                )),
+                        )
+                        .push_maybe(qr.map(|qr| {
+                            Container::new(QRCode::<liana_ui::theme::Theme>::new(qr).cell_size(4))
+                                .padding(10)
+                        })),
+                )
+                .push(
+                    Column::new()
+                        .spacing(5)
+                        .push(text("Key derivation paths").bold())
+                        .push(key_paths.iter().fold(
+                            Column::new().spacing(5),
+                            |col, (fingerprint, alias, path)| {
+                                col.push(
+                                    text(format!(
+                                        "{}[{}/{}]",
+                                        alias
+                                            .as_ref()
+                                            .map(|alias| format!("{} ", alias))
+                                            .unwrap_or_default(),
+                                        fingerprint,
+                                        path
+                                    ))
+                                    .small(),
+                                )
+                            },
+                        )),
+                )
+                .push(
+                    Column::new()
+                        .spacing(5)
+                        .push(text("Usage history").bold())
+                        .push(history),
+                )
+                .push(
+                    Row::new()
+                        .spacing(10)
+                        .align_y(Alignment::Center)
+                        .push(
+                            text(
+                                "The ownership proof allows your co-signers to verify this \
+                                 address belongs to the wallet using their copy of the descriptor.",
+                            )
+                            .small()
+                            .width(Length::Fill),
+                        )
+                        .push(
+                            button::secondary(Some(icon::clipboard_icon()), "Copy ownership proof")
+                                .on_press(Message::Clipboard(proof.to_string())),
+                        ),
+                ),
+        ))
+        .width(Length::Fill)
+        .max_width(900)
+        .into()
+}
@@ -18,9 +18,10 @@ use liana_ui::{
 
 use crate::app::{
     cache::Cache,
+    help::Topic,
     menu::Menu,
     view::{
-        dashboard,
+        dashboard, help,
         message::{CreateSpendMessage, Message},
     },
     Error,
@@ -55,7 +56,8 @@ pub fn recovery<'a>(
                         Button::new(text("Recovery").size(30).bold())
                             .style(theme::button::transparent)
                             .on_press(Message::Menu(Menu::Recovery)),
-                    ),
+                    )
+                    .push(help(Topic::RecoveryPath)),
             )
             .push(Space::with_height(Length::Fixed(20.0)))
             .push(
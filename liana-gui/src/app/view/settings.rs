@@ -1,14 +1,19 @@
 use std::collections::HashSet;
+use std::path::Path;
 use std::str::FromStr;
 
 use iced::{
     alignment,
-    widget::{radio, scrollable, tooltip as iced_tooltip, Space},
+    widget::{
+        checkbox, progress_bar,
+        qr_code::{self, QRCode},
+        radio, scrollable, tooltip as iced_tooltip, Space,
+    },
     Alignment, Length,
 };
 
 use liana::{
-    descriptors::{LianaDescriptor, LianaPolicy},
+    descriptors::{LianaDescriptor, LianaPolicy, WalletPolicy},
     miniscript::bitcoin::{bip32::Fingerprint, Network},
 };
 use lianad::config::BitcoindRpcAuth;
@@ -16,7 +21,16 @@ use lianad::config::BitcoindRpcAuth;
 use super::{dashboard, message::*};
 
 use liana_ui::{
-    component::{badge, button, card, form, separation, text::*, tooltip::tooltip},
+    color,
+    component::{
+        amount::{
+            amount, display_unit, format_amount, number_format, Amount, BitcoinDisplayUnit,
+            NumberFormat,
+        },
+        badge, button, card, form, network_badge, separation,
+        text::*,
+        tooltip::tooltip,
+    },
     icon,
     theme::{self},
     widget::*,
@@ -26,28 +40,37 @@ use crate::{
     app::{
         cache::Cache,
         error::Error,
+        lock::{LOCK_DELAYS, PASSPHRASE_MIN_LEN},
         menu::Menu,
-        view::{hw, warning::warn},
+        settings::{
+            global::{DisplaySettings, LockSettings, UI_SCALES},
+            AccentColor, BlockExplorer, SpendConfirmation,
+        },
+        view::{accent_color, hw, warning::warn},
     },
+    clipboard::CLEAR_DELAYS,
+    daemon::model::{HealthCheckResult, HealthStatus},
     hw::HardwareWallet,
+    i18n::{self, tr, tr_args, Language},
     node::{
-        bitcoind::{RpcAuthType, RpcAuthValues},
+        bitcoind::{self, RpcAuthType, RpcAuthValues},
         electrum,
     },
+    remote_signer::RemoteSigner,
 };
 
-fn header(title: &str, msg: SettingsMessage) -> Row<'static, Message> {
+fn header(title: &'static str, msg: SettingsMessage) -> Row<'static, Message> {
     Row::new()
         .spacing(10)
         .align_y(Alignment::Center)
         .push(
-            Button::new(text("Settings").size(30).bold())
+            Button::new(text(tr("Settings")).size(30).bold())
                 .style(theme::button::transparent)
                 .on_press(Message::Menu(Menu::Settings)),
         )
         .push(icon::chevron_right().size(30))
         .push(
-            Button::new(text(title).size(30).bold())
+            Button::new(text(tr(title)).size(30).bold())
                 .style(theme::button::transparent)
                 .on_press(Message::Settings(msg)),
         )
@@ -59,7 +82,7 @@ fn settings_section(
     icon: liana_ui::widget::Text<'static>,
     msg: Message,
 ) -> Container<'static, Message> {
-    let tt = tool_tip.map(tooltip);
+    let tt = tool_tip.map(|t| tooltip(tr(t)));
     Container::new(
         Button::new(
             Row::new()
@@ -80,40 +103,61 @@ fn settings_section(
 }
 
 pub fn list(cache: &Cache, is_remote_backend: bool) -> Element<Message> {
-    let header = Button::new(text("Settings").size(30).bold())
+    let header = Button::new(text(tr("Settings")).size(30).bold())
         .style(theme::button::transparent)
         .on_press(Message::Menu(Menu::Settings));
 
     let node = settings_section(
-        "Node",
+        tr("Node"),
         None,
         icon::bitcoin_icon(),
         Message::Settings(SettingsMessage::EditBitcoindSettings),
     );
 
     let backend = settings_section(
-        "Backend",
+        tr("Backend"),
         None,
         icon::bitcoin_icon(),
         Message::Settings(SettingsMessage::EditRemoteBackendSettings),
     );
 
     let wallet = settings_section(
-        "Wallet",
+        tr("Wallet"),
         None,
         icon::wallet_icon(),
         Message::Settings(SettingsMessage::EditWalletSettings),
     );
 
+    let display = settings_section(
+        tr("Display"),
+        Some("Language, unit and number format of the amounts, and locking of the application."),
+        icon::tooltip_icon(),
+        Message::Settings(SettingsMessage::EditDisplaySettings),
+    );
+
     let recovery = settings_section(
-        "Recovery",
+        tr("Recovery"),
         Some("In case of loss of the main key, the recovery key can move the funds after a certain time."),
         icon::recovery_icon(),
         Message::Menu(Menu::Recovery),
     );
 
+    let diagnostics = settings_section(
+        tr("Diagnostics"),
+        Some("Check the node, the database and the coins of the wallet."),
+        icon::tooltip_icon(),
+        Message::Settings(SettingsMessage::DiagnosticsSection),
+    );
+
+    let tour = settings_section(
+        tr("Guided tour"),
+        Some("What the primary path, the timelock, PSBTs and the recovery path are."),
+        icon::tooltip_icon(),
+        Message::Tour(TourMessage::Start),
+    );
+
     let about = settings_section(
-        "About",
+        tr("About"),
         None,
         icon::tooltip_icon(),
         Message::Settings(SettingsMessage::AboutSection),
@@ -129,7 +173,10 @@ pub fn list(cache: &Cache, is_remote_backend: bool) -> Element<Message> {
             .push(header)
             .push(if !is_remote_backend { node } else { backend })
             .push(wallet)
+            .push(display)
             .push(recovery)
+            .push_maybe((!is_remote_backend).then_some(diagnostics))
+            .push(tour)
             .push(about),
     )
 }
@@ -195,6 +242,397 @@ pub fn about_section<'a>(
     )
 }
 
+fn health_status_pill<'a>(status: HealthStatus) -> Container<'a, Message> {
+    match status {
+        HealthStatus::Ok => Container::new(p2_regular("Ok")).style(theme::pill::success),
+        HealthStatus::Warning => Container::new(p2_regular("Warning")).style(theme::pill::warning),
+        HealthStatus::Error => {
+            Container::new(p2_regular("Error")).style(theme::pill::custom(color::RED))
+        }
+    }
+    .padding(10)
+}
+
+pub fn diagnostics_section<'a>(
+    cache: &'a Cache,
+    warning: Option<&Error>,
+    report: Option<&'a HealthCheckResult>,
+    running: bool,
+) -> Element<'a, Message> {
+    let header = header("Diagnostics", SettingsMessage::DiagnosticsSection);
+
+    let checks = report.map(|report| {
+        report
+            .checks
+            .iter()
+            .fold(Column::new().spacing(10), |col, check| {
+                let name = match check.name.as_str() {
+                    "backend" => "Bitcoin backend",
+                    "drafts" => "Saved drafts",
+                    "database" => "Database integrity",
+                    "coins" => "Coins match the descriptor",
+                    "derivation_indexes" => "Unused addresses",
+                    name => name,
+                };
+                col.push(card::simple(
+                    Column::new()
+                        .spacing(10)
+                        .push(
+                            Row::new()
+                                .spacing(20)
+                                .align_y(Alignment::Center)
+                                .push(text(name).bold().width(Length::Fill))
+                                .push(health_status_pill(check.status)),
+                        )
+                        .push(
+                            check
+                                .messages
+                                .iter()
+                                .fold(Column::new().spacing(5), |col, msg| {
+                                    col.push(p2_regular(msg).style(theme::text::secondary))
+                                }),
+                        ),
+                ))
+            })
+    });
+
+    let summary = Row::new()
+        .spacing(20)
+        .align_y(Alignment::Center)
+        .push(
+            text(match report.map(|r| r.status) {
+                _ if running => "Running the checks...",
+                None => "The checks could not be run.",
+                Some(HealthStatus::Ok) => "No problem found.",
+                Some(HealthStatus::Warning) => "Some checks need attention.",
+                Some(HealthStatus::Error) => "Some checks failed.",
+            })
+            .width(Length::Fill),
+        )
+        .push(button::secondary(None, "Run again").on_press_maybe(
+            (!running).then_some(Message::Settings(SettingsMessage::DiagnosticsSection)),
+        ));
+
+    dashboard(
+        &Menu::Settings,
+        cache,
+        warning,
+        Column::new()
+            .spacing(20)
+            .push(header)
+            .push(summary)
+            .push_maybe(checks)
+            .width(Length::Fill),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn display_section<'a>(
+    cache: &'a Cache,
+    settings: &DisplaySettings,
+    uri_handler: Option<bool>,
+    uri_handler_processing: bool,
+    lock: Option<&LockSettings>,
+    lock_passphrase: &'a form::Value<String>,
+    lock_passphrase_confirmation: &'a form::Value<String>,
+    lock_processing: bool,
+    warning: Option<&Error>,
+) -> Element<'a, Message> {
+    let header = header("Display", SettingsMessage::EditDisplaySettings);
+
+    let example = Amount::from_sat(123_456_789);
+    let content = card::simple(
+        Column::new()
+            .spacing(20)
+            .push(
+                Language::ALL.iter().fold(
+                    Row::new()
+                        .push(text(tr("Language:")).bold().width(Length::Fixed(150.0)))
+                        .spacing(30)
+                        .align_y(Alignment::Center),
+                    |row, language| {
+                        row.push(radio(
+                            language.name(),
+                            *language,
+                            Some(settings.language.unwrap_or_else(i18n::language)),
+                            |language| {
+                                Message::Settings(SettingsMessage::LanguageSelected(language))
+                            },
+                        ))
+                    },
+                ),
+            )
+            .push(
+                BitcoinDisplayUnit::ALL.iter().fold(
+                    Row::new()
+                        .push(text(tr("Unit:")).bold().width(Length::Fixed(150.0)))
+                        .spacing(30)
+                        .align_y(Alignment::Center),
+                    |row, unit| {
+                        row.push(radio(unit.label(), *unit, Some(settings.unit), |unit| {
+                            Message::Settings(SettingsMessage::DisplayUnitSelected(unit))
+                        }))
+                    },
+                ),
+            )
+            .push(
+                NumberFormat::ALL.iter().fold(
+                    Row::new()
+                        .push(
+                            text(tr("Number format:"))
+                                .bold()
+                                .width(Length::Fixed(150.0)),
+                        )
+                        .spacing(30)
+                        .align_y(Alignment::Center),
+                    |row, format| {
+                        row.push(radio(
+                            format_amount(example, BitcoinDisplayUnit::BTC, *format),
+                            *format,
+                            Some(settings.number_format.unwrap_or_else(number_format)),
+                            |format| {
+                                Message::Settings(SettingsMessage::NumberFormatSelected(format))
+                            },
+                        ))
+                    },
+                ),
+            )
+            .push(
+                UI_SCALES.iter().fold(
+                    Row::new()
+                        .push(text(tr("Scale:")).bold().width(Length::Fixed(150.0)))
+                        .spacing(30)
+                        .align_y(Alignment::Center)
+                        .push(radio(
+                            tr("System"),
+                            None,
+                            Some(settings.ui_scale),
+                            |scale| Message::Settings(SettingsMessage::UiScaleSelected(scale)),
+                        )),
+                    |row, scale| {
+                        row.push(radio(
+                            format!("{}%", scale),
+                            Some(*scale),
+                            Some(settings.ui_scale),
+                            |scale| Message::Settings(SettingsMessage::UiScaleSelected(scale)),
+                        ))
+                    },
+                ),
+            )
+            .push(
+                CLEAR_DELAYS.iter().fold(
+                    Row::new()
+                        .push(
+                            text(tr("Clear clipboard:"))
+                                .bold()
+                                .width(Length::Fixed(150.0)),
+                        )
+                        .spacing(30)
+                        .align_y(Alignment::Center)
+                        .push(radio(
+                            tr("Never"),
+                            None,
+                            Some(settings.clipboard_clear_after),
+                            |delay| {
+                                Message::Settings(SettingsMessage::ClipboardClearSelected(delay))
+                            },
+                        )),
+                    |row, delay| {
+                        row.push(radio(
+                            tr_args("After {seconds}s", &[("seconds", delay)]),
+                            Some(*delay),
+                            Some(settings.clipboard_clear_after),
+                            |delay| {
+                                Message::Settings(SettingsMessage::ClipboardClearSelected(delay))
+                            },
+                        ))
+                    },
+                ),
+            )
+            .push(separation().width(Length::Fill))
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_y(Alignment::Center)
+                    .push(text(tr("Preview:")).bold().width(Length::Fixed(150.0)))
+                    .push(amount(&example)),
+            ),
+    );
+
+    dashboard(
+        &Menu::Settings,
+        cache,
+        warning,
+        Column::new()
+            .spacing(20)
+            .push(header)
+            .push(content)
+            .push(app_lock(
+                lock,
+                lock_passphrase,
+                lock_passphrase_confirmation,
+                lock_processing,
+            ))
+            .push_maybe(
+                uri_handler
+                    .map(|registered| bitcoin_uri_handler(registered, uri_handler_processing)),
+            )
+            .width(Length::Fill),
+    )
+}
+
+fn app_lock<'a>(
+    lock: Option<&LockSettings>,
+    passphrase: &'a form::Value<String>,
+    confirmation: &'a form::Value<String>,
+    processing: bool,
+) -> Element<'a, Message> {
+    let delay = lock.and_then(|l| l.after);
+    card::simple(
+        Column::new()
+            .spacing(20)
+            .push(
+                Row::new()
+                    .push(badge::badge(icon::key_icon()))
+                    .push(text(tr("App lock")).bold())
+                    .spacing(20)
+                    .align_y(Alignment::Center)
+                    .width(Length::Fill),
+            )
+            .push(separation().width(Length::Fill))
+            .push(text(tr(
+                "Hide the wallet until a passphrase is typed, when you step away from the \
+                 computer. It doesn't encrypt anything: the wallet keeps running meanwhile.",
+            )))
+            .push_maybe(lock.map(|_| {
+                LOCK_DELAYS.iter().fold(
+                    Row::new()
+                        .push(text(tr("Lock:")).bold().width(Length::Fixed(150.0)))
+                        .spacing(30)
+                        .align_y(Alignment::Center)
+                        .push(radio(tr("On demand"), None, Some(delay), |after| {
+                            Message::Settings(SettingsMessage::LockDelaySelected(after))
+                        })),
+                    |row, after| {
+                        row.push(radio(
+                            tr_args("After {minutes} min of inactivity", &[("minutes", after)]),
+                            Some(*after),
+                            Some(delay),
+                            |after| Message::Settings(SettingsMessage::LockDelaySelected(after)),
+                        ))
+                    },
+                )
+            }))
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_y(Alignment::Start)
+                    .push(
+                        Column::new()
+                            .width(Length::Fill)
+                            .spacing(5)
+                            .push(
+                                TextInput::new(
+                                    if lock.is_some() {
+                                        tr("New passphrase")
+                                    } else {
+                                        tr("Passphrase")
+                                    },
+                                    &passphrase.value,
+                                )
+                                .on_input(|msg| {
+                                    Message::Settings(SettingsMessage::LockPassphraseEdited(msg))
+                                })
+                                .secure(true)
+                                .size(P1_SIZE)
+                                .padding(10),
+                            )
+                            .push_maybe((!passphrase.valid).then(|| {
+                                caption(tr_args(
+                                    "The passphrase must be at least {count} characters long",
+                                    &[("count", &PASSPHRASE_MIN_LEN)],
+                                ))
+                                .style(theme::text::error)
+                            })),
+                    )
+                    .push(
+                        Column::new()
+                            .width(Length::Fill)
+                            .spacing(5)
+                            .push(
+                                TextInput::new(tr("Confirm the passphrase"), &confirmation.value)
+                                    .on_input(|msg| {
+                                        Message::Settings(
+                                            SettingsMessage::LockPassphraseConfirmationEdited(msg),
+                                        )
+                                    })
+                                    .secure(true)
+                                    .size(P1_SIZE)
+                                    .padding(10),
+                            )
+                            .push_maybe((!confirmation.valid).then(|| {
+                                caption(tr("The passphrases don't match")).style(theme::text::error)
+                            })),
+                    ),
+            )
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(Space::with_width(Length::Fill))
+                    .push_maybe(lock.map(|_| {
+                        button::secondary(None, tr("Remove")).on_press_maybe(
+                            (!processing).then_some(Message::Settings(SettingsMessage::RemoveLock)),
+                        )
+                    }))
+                    .push(
+                        button::secondary(None, tr("Save passphrase")).on_press_maybe(
+                            (!processing
+                                && passphrase.valid
+                                && !passphrase.value.is_empty()
+                                && confirmation.value == passphrase.value)
+                                .then_some(Message::Settings(SettingsMessage::SaveLockPassphrase)),
+                        ),
+                    ),
+            ),
+    )
+    .width(Length::Fill)
+    .into()
+}
+
+fn bitcoin_uri_handler<'a>(registered: bool, processing: bool) -> Element<'a, Message> {
+    card::simple(Container::new(
+        Column::new()
+            .push(
+                Row::new()
+                    .push(badge::badge(icon::bitcoin_icon()))
+                    .push(text(tr("Payment links")).bold())
+                    .spacing(20)
+                    .align_y(Alignment::Center)
+                    .width(Length::Fill),
+            )
+            .push(separation().width(Length::Fill))
+            .push(text(tr(
+                "Open the bitcoin: links you click with Liana. The payment is prefilled in the \
+                 send screen of the wallet for the network of the address, it is never sent \
+                 without your confirmation.",
+            )))
+            .push(
+                checkbox(tr("Open payment links with Liana"), registered).on_toggle_maybe(
+                    if processing {
+                        None
+                    } else {
+                        Some(|enable| {
+                            Message::Settings(SettingsMessage::BitcoinUriHandlerToggled(enable))
+                        })
+                    },
+                ),
+            )
+            .spacing(20),
+    ))
+    .width(Length::Fill)
+    .into()
+}
+
 pub fn remote_backend_section<'a>(
     cache: &'a Cache,
     email_form: &form::Value<String>,
@@ -502,6 +940,152 @@ pub fn bitcoind<'a>(
     .into()
 }
 
+pub fn bitcoind_service<'a>(installed: bool, processing: bool) -> Element<'a, Message> {
+    card::simple(Container::new(
+        Column::new()
+            .push(
+                Row::new()
+                    .push(badge::badge(icon::bitcoin_icon()))
+                    .push(text("Background node").bold())
+                    .spacing(20)
+                    .align_y(Alignment::Center)
+                    .width(Length::Fill),
+            )
+            .push(separation().width(Length::Fill))
+            .push(text(
+                "Run the node as a service of your system, started when you log in. It keeps \
+                 syncing when Liana is closed and Liana connects to it when opened.",
+            ))
+            .push(
+                checkbox("Keep the node running in the background", installed).on_toggle_maybe(
+                    if processing {
+                        None
+                    } else {
+                        Some(|enable| {
+                            Message::Settings(SettingsMessage::BitcoindServiceToggled(enable))
+                        })
+                    },
+                ),
+            )
+            .push_maybe(if installed {
+                None
+            } else {
+                Some(
+                    text("Once disabled, the running node is stopped when you quit Liana.").small(),
+                )
+            })
+            .spacing(20),
+    ))
+    .width(Length::Fill)
+    .into()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn bitcoind_versions<'a>(
+    installed: &[&'static str],
+    selected: Option<&'static str>,
+    latest_release: Option<&String>,
+    download_progress: Option<f32>,
+    processing: bool,
+    restart_required: bool,
+) -> Element<'a, Message> {
+    let mut col = Column::new()
+        .push(
+            Row::new()
+                .push(badge::badge(icon::bitcoin_icon()))
+                .push(text("Bitcoin Core version").bold())
+                .spacing(20)
+                .align_y(Alignment::Center)
+                .width(Length::Fill),
+        )
+        .push(separation().width(Length::Fill))
+        .push(
+            Row::new()
+                .push(
+                    Container::new(text("Version used by the node:").bold().small())
+                        .width(Length::Fill),
+                )
+                .push(text(selected.unwrap_or("-")).small()),
+        )
+        .spacing(20);
+
+    if restart_required {
+        col = col.push(
+            text("The node will use this version once restarted, the next time Liana starts it.")
+                .small()
+                .style(theme::text::success),
+        );
+    }
+
+    if !installed.contains(&bitcoind::VERSION) {
+        col = col.push(if let Some(progress) = download_progress {
+            Column::new()
+                .push(text(format!("Downloading Bitcoin Core {}...", bitcoind::VERSION)).small())
+                .push(progress_bar(0.0..=100.0, progress))
+                .spacing(10)
+        } else {
+            Column::new().push(
+                Row::new()
+                    .push(
+                        Container::new(
+                            text(format!("Bitcoin Core {} is available.", bitcoind::VERSION))
+                                .small(),
+                        )
+                        .width(Length::Fill),
+                    )
+                    .push(
+                        button::secondary(None, "Upgrade")
+                            .on_press_maybe(if processing {
+                                None
+                            } else {
+                                Some(Message::Settings(SettingsMessage::BitcoindUpgrade))
+                            })
+                            .padding(5),
+                    )
+                    .align_y(Alignment::Center),
+            )
+        });
+    }
+
+    for version in installed.iter().filter(|v| Some(**v) != selected) {
+        let is_newer =
+            bitcoind::parse_version(version) > selected.and_then(bitcoind::parse_version);
+        col = col.push(
+            Row::new()
+                .push(
+                    Container::new(text(format!("Bitcoin Core {} is installed.", version)).small())
+                        .width(Length::Fill),
+                )
+                .push(
+                    button::secondary(None, if is_newer { "Upgrade" } else { "Roll back" })
+                        .on_press_maybe(if processing {
+                            None
+                        } else {
+                            Some(Message::Settings(SettingsMessage::BitcoindVersionSelected(
+                                *version,
+                            )))
+                        })
+                        .padding(5),
+                )
+                .align_y(Alignment::Center),
+        );
+    }
+
+    if let Some(release) = latest_release
+        .filter(|r| bitcoind::parse_version(r) > bitcoind::parse_version(bitcoind::VERSION))
+    {
+        col = col.push(
+            text(format!(
+                "Bitcoin Core {} was released, a future version of Liana will allow to upgrade to it.",
+                release
+            ))
+            .small(),
+        );
+    }
+
+    card::simple(Container::new(col)).width(Length::Fill).into()
+}
+
 pub fn electrum_edit<'a>(
     is_configured_node_type: bool,
     network: Network,
@@ -844,6 +1428,7 @@ fn is_ok_and<T, E>(res: &Result<T, E>, f: impl FnOnce(&T) -> bool) -> bool {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn wallet_settings<'a>(
     cache: &'a Cache,
     warning: Option<&Error>,
@@ -851,6 +1436,14 @@ pub fn wallet_settings<'a>(
     keys_aliases: &'a [(Fingerprint, form::Value<String>)],
     processing: bool,
     updated: bool,
+    key_sheets_dir: Option<&'a Path>,
+    spend_confirmation: Option<&SpendConfirmation>,
+    spend_confirmation_threshold: &'a form::Value<String>,
+    spend_confirmation_pin: &'a form::Value<String>,
+    block_explorer_url: &'a form::Value<String>,
+    remote_signer: Option<&RemoteSigner>,
+    remote_signer_url: &'a form::Value<String>,
+    remote_signer_token: &'a form::Value<String>,
 ) -> Element<'a, Message> {
     let header = header("Wallet", SettingsMessage::EditWalletSettings);
 
@@ -875,6 +1468,10 @@ pub fn wallet_settings<'a>(
                         button::secondary(Some(icon::clipboard_icon()), "Copy")
                             .on_press(Message::Clipboard(descriptor.to_string())),
                     )
+                    .push(
+                        button::secondary(None, "Export")
+                            .on_press(Message::Settings(SettingsMessage::ExportDescriptor)),
+                    )
                     .push(
                         button::secondary(Some(icon::chip_icon()), "Register on hardware device")
                             .on_press(Message::Settings(SettingsMessage::RegisterWallet)),
@@ -884,6 +1481,299 @@ pub fn wallet_settings<'a>(
     )
     .width(Length::Fill);
 
+    let sheets = card::simple(
+        Column::new()
+            .push(text("Key sheets:").bold())
+            .push(text(
+                "Export a document for each signer with only its own keys, their role in the \
+                 wallet and instructions. Signers don't need the whole descriptor, which is \
+                 kept by the coordinator of the wallet.",
+            ))
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_y(Alignment::Center)
+                    .push_maybe(key_sheets_dir.map(|dir| {
+                        text(format!("Key sheets exported to {}", dir.display()))
+                            .style(theme::text::success)
+                    }))
+                    .push(Space::with_width(Length::Fill))
+                    .push(
+                        button::secondary(Some(icon::key_icon()), "Export key sheets")
+                            .on_press(Message::Settings(SettingsMessage::ExportKeySheets)),
+                    ),
+            )
+            .spacing(10),
+    )
+    .width(Length::Fill);
+
+    let color = card::simple(
+        Column::new()
+            .spacing(10)
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_y(Alignment::Center)
+                    .push(text("Wallet color:").bold())
+                    .push(network_badge(
+                        cache.network,
+                        accent_color(cache.accent_color),
+                    )),
+            )
+            .push(text(
+                "Shown in the header of the wallet and when sending from it, to tell it \
+                 apart from your other wallets.",
+            ))
+            .push(AccentColor::ALL.iter().fold(
+                Row::new().spacing(30).align_y(Alignment::Center),
+                |row, color| {
+                    row.push(radio(
+                        color.to_string(),
+                        *color,
+                        Some(cache.accent_color.unwrap_or_default()),
+                        |color| Message::Settings(SettingsMessage::AccentColorSelected(color)),
+                    ))
+                },
+            )),
+    )
+    .width(Length::Fill);
+
+    let confirmation = card::simple(
+        Column::new()
+            .spacing(10)
+            .push(text("Spend confirmation:").bold())
+            .push(text(
+                "Ask to retype the amount sent, or a PIN, before broadcasting a transaction \
+                 sending more than the threshold. This prevents broadcasting a large payment \
+                 by mistake.",
+            ))
+            .push(text(match spend_confirmation {
+                None => "No confirmation is asked.".to_string(),
+                Some(c) => format!(
+                    "The {} of the spends above {} is asked.",
+                    if c.pin_sha256.is_some() {
+                        "PIN"
+                    } else {
+                        "amount"
+                    },
+                    format_amount(Amount::from_sat(c.threshold), display_unit())
+                ),
+            }))
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_y(Alignment::Center)
+                    .push(
+                        form::Form::new_amount(
+                            &format!("Threshold ({})", display_unit()),
+                            spend_confirmation_threshold,
+                            |msg| {
+                                Message::Settings(
+                                    SettingsMessage::SpendConfirmationThresholdEdited(msg),
+                                )
+                            },
+                        )
+                        .warning("Please enter a valid amount")
+                        .size(P1_SIZE)
+                        .padding(10),
+                    )
+                    .push(
+                        Column::new()
+                            .width(Length::Fill)
+                            .spacing(5)
+                            .push(
+                                TextInput::new(
+                                    "PIN (optional, else the amount is retyped)",
+                                    &spend_confirmation_pin.value,
+                                )
+                                .on_input(|msg| {
+                                    Message::Settings(SettingsMessage::SpendConfirmationPinEdited(
+                                        msg,
+                                    ))
+                                })
+                                .secure(true)
+                                .size(P1_SIZE)
+                                .padding(10),
+                            )
+                            .push_maybe(if spend_confirmation_pin.valid {
+                                None
+                            } else {
+                                Some(
+                                    caption("The PIN must be at least 4 characters long")
+                                        .style(theme::text::error),
+                                )
+                            }),
+                    ),
+            )
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(Space::with_width(Length::Fill))
+                    .push_maybe(spend_confirmation.map(|_| {
+                        button::secondary(None, "Remove")
+                            .on_press(Message::Settings(SettingsMessage::RemoveSpendConfirmation))
+                    }))
+                    .push(
+                        button::secondary(None, "Save").on_press_maybe(
+                            (!spend_confirmation_threshold.value.is_empty()
+                                && spend_confirmation_threshold.valid
+                                && spend_confirmation_pin.valid)
+                                .then_some(Message::Settings(
+                                    SettingsMessage::SaveSpendConfirmation,
+                                )),
+                        ),
+                    ),
+            ),
+    )
+    .width(Length::Fill);
+
+    let block_explorer = cache.block_explorer();
+    let explorer = card::simple(
+        Column::new()
+            .spacing(10)
+            .push(text("Block explorer:").bold())
+            .push(text(
+                "Transactions can be looked up on a block explorer from their page. Prefer an \
+                 instance you host, for instance on your node: it is used by default once set.",
+            ))
+            .push(text(match block_explorer {
+                BlockExplorer::None => "Links to a block explorer are hidden.".to_string(),
+                _ => match block_explorer.tx_url_prefix(cache.network) {
+                    Some(url) => format!("Transactions are looked up on {}", url),
+                    None => "No public block explorer is known for this network.".to_string(),
+                },
+            }))
+            .push_maybe(block_explorer.is_public().then(|| {
+                text(
+                    "Looking up a transaction on a public block explorer tells its operator that \
+                     you are interested in it, along with your IP address.",
+                )
+                .style(theme::text::warning)
+            }))
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_y(Alignment::Center)
+                    .push(
+                        form::Form::new(
+                            "URL of your own instance, such as http://umbrel.local:3006",
+                            block_explorer_url,
+                            |msg| Message::Settings(SettingsMessage::BlockExplorerUrlEdited(msg)),
+                        )
+                        .warning("Please enter an http:// or https:// URL")
+                        .size(P1_SIZE)
+                        .padding(10),
+                    )
+                    .push(
+                        button::secondary(None, "Use").on_press_maybe(
+                            (!block_explorer_url.value.is_empty() && block_explorer_url.valid)
+                                .then_some(Message::Settings(
+                                    SettingsMessage::BlockExplorerSelected(
+                                        BlockExplorer::SelfHosted(
+                                            block_explorer_url.value.trim().to_string(),
+                                        ),
+                                    ),
+                                )),
+                        ),
+                    ),
+            )
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(Space::with_width(Length::Fill))
+                    .push(button::secondary(None, "Use mempool.space").on_press_maybe(
+                        (block_explorer != BlockExplorer::MempoolSpace).then_some(
+                            Message::Settings(SettingsMessage::BlockExplorerSelected(
+                                BlockExplorer::MempoolSpace,
+                            )),
+                        ),
+                    ))
+                    .push(button::secondary(None, "Hide links").on_press_maybe(
+                        (block_explorer != BlockExplorer::None).then_some(Message::Settings(
+                            SettingsMessage::BlockExplorerSelected(BlockExplorer::None),
+                        )),
+                    )),
+            ),
+    )
+    .width(Length::Fill);
+
+    let signer = card::simple(
+        Column::new()
+            .spacing(10)
+            .push(text("Remote signer:").bold())
+            .push(text(
+                "A key of this wallet can be kept on a server running Liana with its signer \
+                 server enabled. Reach it through HTTPS, or through an SSH tunnel to a port of \
+                 this machine: the token is stored in clear along with these settings.",
+            ))
+            .push_maybe(remote_signer.map(|signer| {
+                text(format!(
+                    "Signing with key #{} on {}",
+                    signer.fingerprint,
+                    signer.host()
+                ))
+            }))
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_y(Alignment::Center)
+                    .push(
+                        form::Form::new(
+                            "URL, such as http://localhost:8090",
+                            remote_signer_url,
+                            |msg| Message::Settings(SettingsMessage::RemoteSignerUrlEdited(msg)),
+                        )
+                        .warning("Please enter an https:// URL, or http://localhost for a tunnel")
+                        .size(P1_SIZE)
+                        .padding(10),
+                    )
+                    .push(
+                        Column::new()
+                            .width(Length::Fill)
+                            .spacing(5)
+                            .push(
+                                TextInput::new("Token", &remote_signer_token.value)
+                                    .on_input(|msg| {
+                                        Message::Settings(SettingsMessage::RemoteSignerTokenEdited(
+                                            msg,
+                                        ))
+                                    })
+                                    .secure(true)
+                                    .size(P1_SIZE)
+                                    .padding(10),
+                            )
+                            .push_maybe(if remote_signer_token.valid {
+                                None
+                            } else {
+                                Some(
+                                    caption("Please enter the token of the signer server")
+                                        .style(theme::text::error),
+                                )
+                            }),
+                    ),
+            )
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(Space::with_width(Length::Fill))
+                    .push_maybe(remote_signer.map(|_| {
+                        button::secondary(None, "Remove")
+                            .on_press(Message::Settings(SettingsMessage::RemoveRemoteSigner))
+                    }))
+                    .push(if processing {
+                        button::secondary(None, "Connecting")
+                    } else {
+                        button::secondary(None, "Save").on_press_maybe(
+                            (!remote_signer_url.value.is_empty()
+                                && remote_signer_url.valid
+                                && !remote_signer_token.value.is_empty())
+                            .then_some(Message::Settings(SettingsMessage::SaveRemoteSigner)),
+                        )
+                    }),
+            ),
+    )
+    .width(Length::Fill);
+
     let aliases = card::simple(
         Column::new()
             .push(text("Fingerprint aliases:").bold())
@@ -941,14 +1831,107 @@ pub fn wallet_settings<'a>(
         Column::new()
             .spacing(20)
             .push(header)
+            .push(color)
+            .push(confirmation)
+            .push(explorer)
+            .push(signer)
             .push(descr)
             .push(
                 card::simple(display_policy(descriptor.policy(), keys_aliases)).width(Length::Fill),
             )
-            .push(aliases),
+            .push(aliases)
+            .push(sheets),
     )
 }
 
+/// The descriptor as text, as a QR code and as a BIP388 wallet policy, with the actions to copy,
+/// save and print it.
+pub fn descriptor_export_modal<'a>(
+    descriptor: &'a LianaDescriptor,
+    policy: Option<&'a WalletPolicy>,
+    qr: Option<&'a qr_code::Data>,
+    saved: Option<&'a Path>,
+) -> Element<'a, Message> {
+    card::simple(scrollable(
+        Column::new()
+            .spacing(20)
+            .push(text("Export the descriptor").bold())
+            .push(
+                Column::new()
+                    .spacing(10)
+                    .push(text("Descriptor:").bold())
+                    .push(text(descriptor.to_string()).small())
+                    .push(
+                        Row::new().push(Space::with_width(Length::Fill)).push(
+                            button::secondary(Some(icon::clipboard_icon()), "Copy")
+                                .on_press(Message::Clipboard(descriptor.to_string())),
+                        ),
+                    ),
+            )
+            .push(
+                Column::new()
+                    .spacing(10)
+                    .push(text("QR code (UR output-descriptor):").bold())
+                    .push(if let Some(qr) = qr {
+                        Container::new(QRCode::<liana_ui::theme::Theme>::new(qr).cell_size(3))
+                            .center_x(Length::Fill)
+                    } else {
+                        Container::new(text(
+                            "The descriptor is too large to be displayed as a QR code.",
+                        ))
+                    }),
+            )
+            .push(
+                Column::new()
+                    .spacing(10)
+                    .push(text("Wallet policy (BIP388):").bold())
+                    .push(if let Some(policy) = policy {
+                        Column::new()
+                            .spacing(10)
+                            .push(text(&policy.template).small())
+                            .push(
+                                policy.keys.iter().enumerate().fold(
+                                    Column::new().spacing(5),
+                                    |col, (i, key)| {
+                                        col.push(text(format!("@{}: {}", i, key)).small())
+                                    },
+                                ),
+                            )
+                            .push(
+                                Row::new().push(Space::with_width(Length::Fill)).push(
+                                    button::secondary(Some(icon::clipboard_icon()), "Copy")
+                                        .on_press(Message::Clipboard(policy.to_string())),
+                                ),
+                            )
+                    } else {
+                        Column::new().push(text(
+                            "The descriptor can't be expressed as a wallet policy.",
+                        ))
+                    }),
+            )
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_y(Alignment::Center)
+                    .push_maybe(saved.map(|path| {
+                        text(format!("Saved to {}", path.display())).style(theme::text::success)
+                    }))
+                    .push(Space::with_width(Length::Fill))
+                    .push(
+                        button::secondary(None, "Print")
+                            .on_press(Message::Settings(SettingsMessage::PrintDescriptor)),
+                    )
+                    .push(
+                        button::secondary(None, "Save to file")
+                            .on_press(Message::Settings(SettingsMessage::SaveDescriptor)),
+                    ),
+            ),
+    ))
+    .width(Length::Fixed(800.0))
+    .max_height(700)
+    .into()
+}
+
 fn display_policy(
     policy: LianaPolicy,
     keys_aliases: &[(Fingerprint, form::Value<String>)],
@@ -8,7 +8,7 @@ use iced::{
 
 use liana::{
     descriptors::LianaPolicy,
-    miniscript::bitcoin::{bip32::Fingerprint, Amount, Denomination, Network},
+    miniscript::bitcoin::{bip32::Fingerprint, Amount, Network},
 };
 
 use liana_ui::{
@@ -22,9 +22,9 @@ use crate::{
         cache::Cache,
         error::Error,
         menu::Menu,
-        view::{coins, dashboard, message::*, psbt},
+        view::{coins, dashboard, mempool, message::*, psbt},
     },
-    daemon::model::{remaining_sequence, Coin, SpendTx},
+    daemon::model::{remaining_sequence, Coin, GetMempoolStatsResult, SpendTemplate, SpendTx},
 };
 
 #[allow(clippy::too_many_arguments)]
@@ -119,9 +119,15 @@ pub fn create_spend_tx<'a>(
     batch_label: &form::Value<String>,
     amount_left: Option<&Amount>,
     feerate: &form::Value<String>,
+    templates: Option<&'a [SpendTemplate]>,
+    template_name: &form::Value<String>,
+    can_save_template: bool,
+    mempool: Option<&GetMempoolStatsResult>,
+    self_send_fee: Option<&Amount>,
     error: Option<&Error>,
 ) -> Element<'a, Message> {
     let is_self_send = recipients.is_empty();
+    let templates = templates.filter(|_| !is_self_send);
     dashboard(
         &Menu::CreateSpendTx,
         cache,
@@ -132,6 +138,18 @@ pub fn create_spend_tx<'a>(
             } else {
                 "Send"
             }))
+            .push_maybe(is_self_send.then(|| {
+                text(
+                    "Send the selected coins back to the wallet to push back the availability of \
+                     their recovery path. Several coins are combined into a single one.",
+                )
+                .style(theme::text::secondary)
+            }))
+            .push_maybe(
+                templates
+                    .filter(|t| !t.is_empty())
+                    .map(spend_templates_view),
+            )
             .push_maybe(if recipients.len() > 1 {
                 Some(
                     form::Form::new("Batch label", batch_label, |s| {
@@ -199,6 +217,31 @@ pub fn create_spend_tx<'a>(
                     )
                     .push(Space::with_width(Length::FillPortion(1))),
             )
+            .push_maybe(mempool.map(|stats| {
+                mempool::mempool_card(
+                    stats,
+                    feerate.value.parse::<u64>().ok().filter(|_| feerate.valid),
+                )
+            }))
+            .push_maybe(templates.map(|_| {
+                Row::new()
+                    .spacing(10)
+                    .align_y(Alignment::Start)
+                    .push(
+                        form::Form::new("Template name", template_name, |s| {
+                            Message::CreateSpend(CreateSpendMessage::TemplateNameEdited(s))
+                        })
+                        .warning("Invalid name length, cannot be superior to 100")
+                        .size(P1_SIZE)
+                        .padding(10),
+                    )
+                    .push(
+                        button::secondary(None, "Save as template").on_press_maybe(
+                            can_save_template
+                                .then_some(Message::CreateSpend(CreateSpendMessage::SaveTemplate)),
+                        ),
+                    )
+            }))
             .push(
                 Container::new(
                     Column::new()
@@ -226,6 +269,27 @@ pub fn create_spend_tx<'a>(
                                             P2_SIZE,
                                         ))
                                         .push(p2_regular("selected").style(theme::text::secondary))
+                                        .push_maybe(self_send_fee.map(|fee| {
+                                            Row::new()
+                                                .spacing(5)
+                                                .push(
+                                                    p2_regular(", refreshed for")
+                                                        .style(theme::text::secondary),
+                                                )
+                                                .push(amount_with_size(fee, P2_SIZE))
+                                                .push(
+                                                    p2_regular("in fees")
+                                                        .style(theme::text::secondary),
+                                                )
+                                        }))
+                                        .push_maybe(amount_left.filter(|a| a.to_sat() > 0).map(
+                                            |_| {
+                                                p2_regular(
+                                                    ", too small to pay the fee at this feerate",
+                                                )
+                                                .style(theme::text::warning)
+                                            },
+                                        ))
                                 } else if let Some(amount_left) = amount_left {
                                     if amount_left.to_sat() == 0 && !is_valid {
                                         // If amount left is set, the current configuration must be redraftable.
@@ -314,9 +378,54 @@ pub fn create_spend_tx<'a>(
     )
 }
 
+fn spend_templates_view(templates: &[SpendTemplate]) -> Element<Message> {
+    Container::new(
+        Column::new().spacing(10).push(p1_bold("Templates")).push(
+            templates
+                .iter()
+                .enumerate()
+                .fold(Row::new().spacing(10), |row, (i, template)| {
+                    row.push(
+                        Row::new()
+                            .align_y(Alignment::Center)
+                            .push(
+                                Button::new(
+                                    Row::new()
+                                        .spacing(5)
+                                        .align_y(Alignment::Center)
+                                        .push_maybe(
+                                            template.schedule.as_ref().map(|_| icon::clock_icon()),
+                                        )
+                                        .push(text(&template.name)),
+                                )
+                                .padding(10)
+                                .style(theme::button::secondary)
+                                .on_press(Message::CreateSpend(CreateSpendMessage::ApplyTemplate(
+                                    i,
+                                ))),
+                            )
+                            .push(
+                                Button::new(icon::cross_icon())
+                                    .style(theme::button::transparent)
+                                    .on_press(Message::CreateSpend(
+                                        CreateSpendMessage::DeleteTemplate(i),
+                                    )),
+                            ),
+                    )
+                }),
+        ),
+    )
+    .padding(20)
+    .style(theme::card::simple)
+    .into()
+}
+
 pub fn recipient_view<'a>(
     index: usize,
     address: &'a form::Value<String>,
+    address_swapped: bool,
+    // Whether the user confirmed paying to a future witness version, if the address uses one.
+    future_witness: Option<bool>,
     amount: &'a form::Value<String>,
     label: &'a form::Value<String>,
     is_max_selected: bool,
@@ -351,6 +460,51 @@ pub fn recipient_view<'a>(
                         .padding(10),
                     ),
             )
+            .push_maybe(address_swapped.then(|| {
+                Row::new()
+                    .spacing(10)
+                    .align_y(Alignment::Center)
+                    .push(Space::with_width(Length::Fixed(110.0)))
+                    .push(icon::warning_icon().style(theme::text::warning))
+                    .push(
+                        text(
+                            "This is not the address you copied in Liana. Your clipboard may \
+                             have been modified by malware, check the address with its recipient.",
+                        )
+                        .small()
+                        .style(theme::text::warning)
+                        .width(Length::Fill),
+                    )
+            }))
+            .push_maybe(future_witness.map(|confirmed| {
+                Row::new()
+                    .spacing(10)
+                    .align_y(Alignment::Center)
+                    .push(Space::with_width(Length::Fixed(110.0)))
+                    .push(icon::warning_icon().style(theme::text::warning))
+                    .push(
+                        Column::new()
+                            .spacing(5)
+                            .width(Length::Fill)
+                            .push(
+                                text(
+                                    "This address uses a witness version which is not defined \
+                                     yet. Until it is, anyone can spend the coins sent to it.",
+                                )
+                                .small()
+                                .style(theme::text::warning),
+                            )
+                            .push(
+                                checkbox(
+                                    "I understand the coins sent to this address may be lost",
+                                    confirmed,
+                                )
+                                .on_toggle(move |_| {
+                                    CreateSpendMessage::ConfirmFutureWitness(index)
+                                }),
+                            ),
+                    )
+            }))
             .push(
                 Row::new()
                     .align_y(Alignment::Start)
@@ -381,7 +535,7 @@ pub fn recipient_view<'a>(
                             .width(Length::Fixed(110.0)),
                     )
                     .push_maybe(if is_max_selected {
-                        let amount_txt = Amount::from_str_in(&amount.value, Denomination::Bitcoin)
+                        let amount_txt = parse_amount(&amount.value)
                             .ok()
                             .map(amount_as_string)
                             .unwrap_or(amount.value.clone());
@@ -396,14 +550,20 @@ pub fn recipient_view<'a>(
                         None
                     })
                     .push_maybe(if !is_max_selected {
-                        Some(form::Form::new_amount_btc("0.001 (in BTC)", amount, move |msg| {
-                            CreateSpendMessage::RecipientEdited(index, "amount", msg)
-                        })
-                        .warning(
-                            "Invalid amount. (Note amounts lower than 0.00005 BTC are invalid.)",
+                        Some(
+                            form::Form::new_amount(
+                                &format!("Amount in {}", display_unit()),
+                                amount,
+                                move |msg| {
+                                    CreateSpendMessage::RecipientEdited(index, "amount", msg)
+                                },
+                            )
+                            .warning(
+                                "Invalid amount. (Note amounts lower than 5 000 sats are invalid.)",
+                            )
+                            .size(P1_SIZE)
+                            .padding(10),
                         )
-                        .size(P1_SIZE)
-                        .padding(10))
                     } else {
                         None
                     })
@@ -1,4 +1,7 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
 
 use chrono::{DateTime, Local, Utc};
 use iced::{
@@ -19,7 +22,7 @@ use crate::{
         error::Error,
         menu::Menu,
         view::{
-            dashboard, label,
+            dashboard, explorer_tx_button, label,
             message::{CreateRbfMessage, Message},
             warning::warn,
         },
@@ -95,13 +98,17 @@ fn tx_list_view(i: usize, tx: &HistoryTransaction) -> Element<'_, Message> {
             Row::new()
                 .push(
                     Row::new()
-                        .push(if tx.is_external() {
-                            badge::receive()
-                        } else if tx.is_send_to_self() {
-                            badge::cycle()
-                        } else {
-                            badge::spend()
-                        })
+                        .push(
+                            if tx.is_external()
+                                || (tx.is_collaborative() && tx.net_amount().is_positive())
+                            {
+                                badge::receive()
+                            } else if tx.is_send_to_self() {
+                                badge::cycle()
+                            } else {
+                                badge::spend()
+                            },
+                        )
                         .push(
                             Column::new()
                                 .push_maybe(if let Some(outpoint) = tx.is_single_payment() {
@@ -136,6 +143,8 @@ fn tx_list_view(i: usize, tx: &HistoryTransaction) -> Element<'_, Message> {
                 })
                 .push_maybe(if tx.is_batch() {
                     Some(badge::batch())
+                } else if tx.is_collaborative() {
+                    Some(badge::collaborative())
                 } else {
                     None
                 })
@@ -145,6 +154,13 @@ fn tx_list_view(i: usize, tx: &HistoryTransaction) -> Element<'_, Message> {
                         .push(text("+"))
                         .push(amount(&tx.incoming_amount))
                         .align_y(Alignment::Center)
+                } else if tx.is_collaborative() {
+                    let net_amount = tx.net_amount();
+                    Row::new()
+                        .spacing(5)
+                        .push(text(if net_amount.is_negative() { "-" } else { "+" }))
+                        .push(amount(&net_amount.unsigned_abs()))
+                        .align_y(Alignment::Center)
                 } else if tx.outgoing_amount != Amount::from_sat(0) {
                     Row::new()
                         .spacing(5)
@@ -298,6 +314,7 @@ pub fn tx_view<'a>(
     cache: &'a Cache,
     tx: &'a HistoryTransaction,
     labels_editing: &'a HashMap<String, form::Value<String>>,
+    receipt_saved: Option<&'a Path>,
     warning: Option<&'a Error>,
 ) -> Element<'a, Message> {
     let txid = tx.tx.compute_txid().to_string();
@@ -310,6 +327,8 @@ pub fn tx_view<'a>(
                 Container::new(h3("Transaction")).width(Length::Fill)
             } else if tx.is_external() {
                 Container::new(h3("Incoming transaction")).width(Length::Fill)
+            } else if tx.is_collaborative() {
+                Container::new(h3("Collaborative transaction")).width(Length::Fill)
             } else {
                 Container::new(h3("Outgoing transaction")).width(Length::Fill)
             })
@@ -338,6 +357,33 @@ pub fn tx_view<'a>(
                             Container::new(h1("Self-transfer"))
                         } else if tx.is_external() {
                             Container::new(amount_with_size(&tx.incoming_amount, H1_SIZE))
+                        } else if tx.is_collaborative() {
+                            let net_amount = tx.net_amount();
+                            Container::new(
+                                Column::new()
+                                    .spacing(5)
+                                    .push(
+                                        Row::new()
+                                            .spacing(5)
+                                            .align_y(Alignment::Center)
+                                            .push(h1(if net_amount.is_negative() {
+                                                "-"
+                                            } else {
+                                                "+"
+                                            }))
+                                            .push(amount_with_size(
+                                                &net_amount.unsigned_abs(),
+                                                H1_SIZE,
+                                            )),
+                                    )
+                                    .push(
+                                        p2_regular(
+                                            "Coins of other participants are spent along with \
+                                             yours. Only the change of your balance is known.",
+                                        )
+                                        .style(theme::text::secondary),
+                                    ),
+                            )
                         } else {
                             Container::new(amount_with_size(&tx.outgoing_amount, H1_SIZE))
                         })
@@ -382,6 +428,21 @@ pub fn tx_view<'a>(
             } else {
                 None
             })
+            // A receipt can only be made of a confirmed transaction.
+            .push_maybe(tx.time.map(|_| {
+                Row::new()
+                    .spacing(10)
+                    .align_y(Alignment::Center)
+                    .push(
+                        button::secondary(None, "Save receipt")
+                            .width(Length::Fixed(200.0))
+                            .on_press(Message::SaveReceipt),
+                    )
+                    .push_maybe(receipt_saved.map(|path| {
+                        p2_regular(format!("Receipt saved to {}", path.display()))
+                            .style(theme::text::secondary)
+                    }))
+            }))
             .push(card::simple(
                 Column::new()
                     .push_maybe(tx.time.map(|t| {
@@ -408,6 +469,10 @@ pub fn tx_view<'a>(
                                             .on_press(Message::Clipboard(txid.clone()))
                                             .style(theme::button::transparent_border),
                                     )
+                                    .push_maybe(explorer_tx_button(
+                                        cache,
+                                        &tx.tx.compute_txid(),
+                                    ))
                                     .width(Length::Shrink),
                             ),
                     )
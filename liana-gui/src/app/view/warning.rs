@@ -44,6 +44,8 @@ impl From<&Error> for WarningMessage {
             },
             Error::Unexpected(_) => WarningMessage("Unknown error".to_string()),
             Error::HardwareWallet(_) => WarningMessage("Hardware wallet error".to_string()),
+            Error::Tapsigner(e) => WarningMessage(format!("Tapsigner error: '{}'.", e)),
+            Error::RemoteSigner(e) => WarningMessage(format!("Remote signer error: '{}'.", e)),
             Error::Desc(e) => WarningMessage(format!("Descriptor analysis error: '{}'.", e)),
             Error::Spend(e) => WarningMessage(format!("Spend creation error: '{}'.", e)),
         }
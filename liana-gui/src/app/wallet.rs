@@ -3,7 +3,12 @@ use std::path::Path;
 use std::sync::Arc;
 
 use crate::{
-    app::settings, daemon::DaemonBackend, hw::HardwareWalletConfig, node::NodeType, signer::Signer,
+    app::{preferences::Preferences, settings},
+    daemon::DaemonBackend,
+    hw::HardwareWalletConfig,
+    node::NodeType,
+    remote_signer::RemoteSigner,
+    signer::Signer,
 };
 
 use liana::{miniscript::bitcoin, signer::HotSigner};
@@ -34,6 +39,12 @@ pub struct Wallet {
     pub keys_aliases: HashMap<Fingerprint, String>,
     pub hardware_wallets: Vec<HardwareWalletConfig>,
     pub signer: Option<Arc<Signer>>,
+    pub accent_color: Option<settings::AccentColor>,
+    pub spend_confirmation: Option<settings::SpendConfirmation>,
+    pub block_explorer: Option<settings::BlockExplorer>,
+    pub remote_signer: Option<RemoteSigner>,
+    pub hide_amounts: bool,
+    pub preferences: Preferences,
 }
 
 impl Wallet {
@@ -44,6 +55,12 @@ impl Wallet {
             keys_aliases: HashMap::new(),
             hardware_wallets: Vec::new(),
             signer: None,
+            accent_color: None,
+            spend_confirmation: None,
+            block_explorer: None,
+            remote_signer: None,
+            hide_amounts: false,
+            preferences: Preferences::default(),
         }
     }
 
@@ -62,6 +79,39 @@ impl Wallet {
         self
     }
 
+    pub fn with_accent_color(mut self, accent_color: Option<settings::AccentColor>) -> Self {
+        self.accent_color = accent_color;
+        self
+    }
+
+    pub fn with_spend_confirmation(
+        mut self,
+        spend_confirmation: Option<settings::SpendConfirmation>,
+    ) -> Self {
+        self.spend_confirmation = spend_confirmation;
+        self
+    }
+
+    pub fn with_block_explorer(mut self, block_explorer: Option<settings::BlockExplorer>) -> Self {
+        self.block_explorer = block_explorer;
+        self
+    }
+
+    pub fn with_remote_signer(mut self, remote_signer: Option<RemoteSigner>) -> Self {
+        self.remote_signer = remote_signer;
+        self
+    }
+
+    pub fn with_hide_amounts(mut self, hide_amounts: bool) -> Self {
+        self.hide_amounts = hide_amounts;
+        self
+    }
+
+    pub fn with_preferences(mut self, preferences: Preferences) -> Self {
+        self.preferences = preferences;
+        self
+    }
+
     pub fn with_signer(mut self, signer: Signer) -> Self {
         self.signer = Some(Arc::new(signer));
         self
@@ -101,6 +151,12 @@ impl Wallet {
                     self.with_name(wallet_setting.name.clone())
                         .with_hardware_wallets(wallet_setting.hardware_wallets.clone())
                         .with_key_aliases(wallet_setting.keys_aliases())
+                        .with_accent_color(wallet_setting.accent_color)
+                        .with_spend_confirmation(wallet_setting.spend_confirmation.clone())
+                        .with_block_explorer(wallet_setting.block_explorer.clone())
+                        .with_remote_signer(wallet_setting.remote_signer.clone())
+                        .with_hide_amounts(wallet_setting.hide_amounts)
+                        .with_preferences(wallet_setting.preferences.clone())
                 } else {
                     self
                 }
@@ -123,6 +179,13 @@ impl Wallet {
                         // Only local wallet from previous version of Liana GUI may not have a
                         // settings.json file
                         remote_backend_auth: None,
+                        last_balance: None,
+                        accent_color: None,
+                        spend_confirmation: None,
+                        block_explorer: None,
+                        remote_signer: None,
+                        hide_amounts: false,
+                        preferences: Preferences::default(),
                     }],
                 };
 
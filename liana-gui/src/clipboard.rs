@@ -0,0 +1,98 @@
+//! Writing to the clipboard, with some hygiene against malware monitoring it.
+//!
+//! What the app copies can be cleared from the clipboard after a delay, and an address pasted
+//! into the app can be checked against the last one it copied to detect it was swapped in the
+//! meantime.
+
+use std::{
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU16, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use iced::Task;
+use liana::miniscript::bitcoin::{address::NetworkUnchecked, Address};
+
+/// The delays after which the clipboard can be cleared, in seconds.
+pub const CLEAR_DELAYS: [u16; 3] = [30, 60, 120];
+
+/// How long after being copied an address is expected to be pasted. An address pasted later may
+/// legitimately have been copied from somewhere else.
+const SWAP_DETECTION_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+// In seconds, 0 to never clear the clipboard.
+static CLEAR_AFTER: AtomicU16 = AtomicU16::new(0);
+
+// The content the app last copied to the clipboard, and when.
+static LAST_COPY: Mutex<Option<(String, Instant)>> = Mutex::new(None);
+
+/// Set the delay in seconds after which what the app copies is cleared from the clipboard, if
+/// it's still there.
+pub fn set_clear_after(delay: Option<u16>) {
+    CLEAR_AFTER.store(delay.unwrap_or(0), Ordering::Relaxed);
+}
+
+fn clear_after() -> Option<Duration> {
+    match CLEAR_AFTER.load(Ordering::Relaxed) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs.into())),
+    }
+}
+
+/// Write this content to the clipboard, and clear it after the configured delay unless it was
+/// replaced in the meantime.
+pub fn write<T: Send + 'static>(content: String) -> Task<T> {
+    *LAST_COPY.lock().unwrap() = Some((content.clone(), Instant::now()));
+    let write = iced::clipboard::write(content.clone());
+    if let Some(delay) = clear_after() {
+        write.chain(
+            Task::perform(tokio::time::sleep(delay), |_| ())
+                .then(|_| iced::clipboard::read())
+                .then(move |current| {
+                    if current.as_ref() == Some(&content) {
+                        iced::clipboard::write(String::new())
+                    } else {
+                        Task::none()
+                    }
+                }),
+        )
+    } else {
+        write
+    }
+}
+
+/// Whether this address, just pasted, may have been swapped in the clipboard by malware: the app
+/// recently copied another address.
+pub fn is_swapped_address(pasted: &str) -> bool {
+    match &*LAST_COPY.lock().unwrap() {
+        Some((copied, at)) if at.elapsed() < SWAP_DETECTION_WINDOW => is_swapped(copied, pasted),
+        _ => false,
+    }
+}
+
+fn is_swapped(copied: &str, pasted: &str) -> bool {
+    let is_address = |s: &str| Address::<NetworkUnchecked>::from_str(s.trim()).is_ok();
+    is_address(copied) && is_address(pasted) && copied.trim() != pasted.trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swapped_address() {
+        let copied = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+        assert!(!is_swapped(copied, copied));
+        assert!(!is_swapped(copied, &format!(" {}\n", copied)));
+        assert!(is_swapped(
+            copied,
+            "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l"
+        ));
+        // Only addresses are compared.
+        assert!(!is_swapped("some label", copied));
+        assert!(!is_swapped(copied, "some label"));
+    }
+}
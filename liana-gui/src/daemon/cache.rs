@@ -0,0 +1,130 @@
+//! A cache of the information computed from a spend draft's PSBT against the wallet descriptor.
+//!
+//! Analyzing the signatures of a PSBT, its satisfaction weight and deriving its change outputs is
+//! done every time the drafts are listed. The result only depends on the descriptor and the PSBT,
+//! so it is kept per draft and recomputed only once the PSBT of the draft was updated.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use liana::{
+    descriptors::{InputFinalization, LianaDescriptor, PartialSpendInfo},
+    miniscript::bitcoin::{
+        hashes::{sha256, Hash},
+        psbt::Psbt,
+        secp256k1, Txid,
+    },
+};
+
+/// What is computed from a PSBT for a [`super::model::SpendTx`].
+#[derive(Debug, Clone)]
+pub struct SpendInfo {
+    pub sigs: PartialSpendInfo,
+    /// Maximum possible size of the unsigned transaction after satisfaction.
+    pub max_vbytes: u64,
+    pub change_indexes: Vec<usize>,
+    /// Whether a witness can be constructed for each input with the present signatures.
+    pub finalization: Vec<InputFinalization>,
+}
+
+struct Entry {
+    desc_hash: sha256::Hash,
+    psbt_hash: sha256::Hash,
+    info: SpendInfo,
+}
+
+// A single entry per draft, replaced when its PSBT is updated.
+static CACHE: Mutex<Option<HashMap<Txid, Entry>>> = Mutex::new(None);
+
+fn compute(
+    desc: &LianaDescriptor,
+    psbt: &Psbt,
+    secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+) -> SpendInfo {
+    // Use primary path if no inputs are using a relative locktime.
+    let use_primary_path = !psbt
+        .unsigned_tx
+        .input
+        .iter()
+        .map(|txin| txin.sequence)
+        .any(|seq| seq.is_relative_lock_time());
+    SpendInfo {
+        sigs: desc
+            .partial_spend_info(psbt, secp)
+            .expect("PSBT must be generated by Liana"),
+        max_vbytes: desc.unsigned_tx_max_vbytes(&psbt.unsigned_tx, use_primary_path),
+        change_indexes: desc
+            .change_indexes(psbt, secp)
+            .into_iter()
+            .map(|c| c.index())
+            .collect(),
+        finalization: desc.finalizability(psbt, secp),
+    }
+}
+
+/// Get the spend info of this PSBT, computing it only if it isn't cached already for this
+/// descriptor and this exact PSBT.
+pub fn spend_info(
+    desc: &LianaDescriptor,
+    psbt: &Psbt,
+    secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+) -> SpendInfo {
+    let txid = psbt.unsigned_tx.compute_txid();
+    let desc_hash = sha256::Hash::hash(desc.to_string().as_bytes());
+    let psbt_hash = sha256::Hash::hash(&psbt.serialize());
+
+    let mut cache = CACHE.lock().unwrap();
+    let cache = cache.get_or_insert_with(HashMap::new);
+    match cache.get(&txid) {
+        Some(entry) if entry.desc_hash == desc_hash && entry.psbt_hash == psbt_hash => {
+            entry.info.clone()
+        }
+        _ => {
+            let info = compute(desc, psbt, secp);
+            cache.insert(
+                txid,
+                Entry {
+                    desc_hash,
+                    psbt_hash,
+                    info: info.clone(),
+                },
+            );
+            info
+        }
+    }
+}
+
+/// Only keep the entries for these drafts, the others were deleted.
+pub fn retain(txids: &[Txid]) {
+    if let Some(cache) = CACHE.lock().unwrap().as_mut() {
+        cache.retain(|txid, _| txids.contains(txid));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn spend_info_cache() {
+        let secp = secp256k1::Secp256k1::verification_only();
+        let desc = LianaDescriptor::from_str("wsh(or_d(pk([f5acc2fd]tpubD6NzVbkrYhZ4YgUx2ZLNt2rLYAMTdYysCRzKoLu2BeSHKvzqPaBDvf17GeBPnExUVPkuBpx4kniP964e2MxyzzazcXLptxLXModSVCVEV1T/<0;1>/*),and_v(v:pkh([8a64f2a9]tpubD6NzVbkrYhZ4WmzFjvQrp7sDa4ECUxTi9oby8K4FZkd3XCBtEdKwUiQyYJaxiJo5y42gyDWEczrFpozEjeLxMPxjf2WtkfcbpUdfvNnozWF/<0;1>/*),older(10))))#d72le4dr").unwrap();
+        let psbt = Psbt::from_str("cHNidP8BAHECAAAAAUSHuliRtuCX1S6JxRuDRqDCKkWfKmWL5sV9ukZ/wzvfAAAAAAD9////AogTAAAAAAAAFgAUIxe7UY6LJ6y5mFBoWTOoVispDmdwFwAAAAAAABYAFKqO83TK+t/KdpAt21z2HGC7/Z2FAAAAAAABASsQJwAAAAAAACIAIIIySQjGCTeyx/rKUQx8qobjhJeNCiVCliBJPdyRX6XKAQVBIQI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDKxzZHapFNYASzIYkEdH9bJz6nnqUG3uBB8kiK1asmgiBgI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDAz1rML9AAAAAG8AAAAiBgMLcbOxsfLe6+3r1UcjQo77HY0As8OKE4l37yj0/qhIyQyKZPKpAAAAAG8AAAAAAAA=").unwrap();
+        let txid = psbt.unsigned_tx.compute_txid();
+
+        let info = spend_info(&desc, &psbt, &secp);
+        assert_eq!(info.sigs.primary_path().sigs_count, 0);
+        assert!(CACHE.lock().unwrap().as_ref().unwrap().contains_key(&txid));
+
+        // Once the PSBT is updated with a signature, the cached entry isn't used anymore.
+        let psbt = Psbt::from_str("cHNidP8BAHECAAAAAUSHuliRtuCX1S6JxRuDRqDCKkWfKmWL5sV9ukZ/wzvfAAAAAAD9////AogTAAAAAAAAFgAUIxe7UY6LJ6y5mFBoWTOoVispDmdwFwAAAAAAABYAFKqO83TK+t/KdpAt21z2HGC7/Z2FAAAAAAABASsQJwAAAAAAACIAIIIySQjGCTeyx/rKUQx8qobjhJeNCiVCliBJPdyRX6XKIgICNnKlqXPVAFtoGbdlpCo74vCqDArotNKUZS+sBt9rigxIMEUCIQCYZusUL8bdi2PnjWao4bIDDgMQ9Dj2Lcup3/VmkGbYJAIgX/wF5HsqugC5JzvU2cGOmUWtHr2Pg0N4912qogYgDH4BAQVBIQI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDKxzZHapFNYASzIYkEdH9bJz6nnqUG3uBB8kiK1asmgiBgI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDAz1rML9AAAAAG8AAAAiBgMLcbOxsfLe6+3r1UcjQo77HY0As8OKE4l37yj0/qhIyQyKZPKpAAAAAG8AAAAAAAA=").unwrap();
+        assert_eq!(psbt.unsigned_tx.compute_txid(), txid);
+        let info = spend_info(&desc, &psbt, &secp);
+        assert_eq!(info.sigs.primary_path().sigs_count, 1);
+        assert_eq!(info.sigs, compute(&desc, &psbt, &secp).sigs);
+
+        // Deleted drafts are evicted.
+        retain(&[]);
+        assert!(!CACHE.lock().unwrap().as_ref().unwrap().contains_key(&txid));
+    }
+}
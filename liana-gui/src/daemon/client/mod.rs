@@ -12,7 +12,7 @@ use tracing::{error, info};
 pub mod error;
 pub mod jsonrpc;
 
-use liana::miniscript::bitcoin::{address, psbt::Psbt, Address, Network, OutPoint, Txid};
+use liana::miniscript::bitcoin::{address, psbt::Psbt, Address, Amount, Network, OutPoint, Txid};
 use lianad::{
     commands::{CoinStatus, CreateRecoveryResult, LabelItem},
     config::Config,
@@ -77,6 +77,10 @@ impl<C: Client + Send + Sync + Debug> Daemon for Lianad<C> {
         self.call("getinfo", Option::<Request>::None)
     }
 
+    async fn get_balance(&self) -> Result<GetBalanceResult, DaemonError> {
+        self.call("getbalance", Option::<Request>::None)
+    }
+
     async fn get_new_address(&self) -> Result<GetAddressResult, DaemonError> {
         self.call("getnewaddress", Option::<Request>::None)
     }
@@ -105,14 +109,17 @@ impl<C: Client + Send + Sync + Debug> Daemon for Lianad<C> {
         destinations: &HashMap<Address<address::NetworkUnchecked>, u64>,
         feerate_vb: u64,
         change_address: Option<Address<address::NetworkUnchecked>>,
+        allow_future_witness: bool,
     ) -> Result<CreateSpendResult, DaemonError> {
-        let mut input = vec![
-            json!(destinations),
-            json!(coins_outpoints),
-            json!(feerate_vb),
-        ];
+        // Use named parameters so we don't have to fill in all the optional ones in between.
+        let mut input = json!({
+            "destinations": destinations,
+            "outpoints": coins_outpoints,
+            "feerate": feerate_vb,
+            "allow_future_witness": allow_future_witness,
+        });
         if let Some(change_address) = change_address {
-            input.push(json!(change_address));
+            input["change_address"] = json!(change_address);
         }
         self.call("createspend", Some(input))
     }
@@ -142,9 +149,22 @@ impl<C: Client + Send + Sync + Debug> Daemon for Lianad<C> {
         Ok(())
     }
 
-    async fn broadcast_spend_tx(&self, txid: &Txid) -> Result<(), DaemonError> {
-        let _res: serde_json::value::Value =
-            self.call("broadcastspend", Some(vec![txid.to_string()]))?;
+    async fn broadcast_spend_tx(
+        &self,
+        txid: &Txid,
+        confirmed_amount: Option<Amount>,
+    ) -> Result<(), DaemonError> {
+        let _res: serde_json::value::Value = match confirmed_amount {
+            // The confirmed amount comes after the optional idempotency key, pass it by name.
+            Some(amount) => self.call(
+                "broadcastspend",
+                Some(json!({
+                    "txid": txid.to_string(),
+                    "confirmed_amount": amount.to_sat(),
+                })),
+            )?,
+            None => self.call("broadcastspend", Some(vec![txid.to_string()]))?,
+        };
         Ok(())
     }
 
@@ -169,6 +189,94 @@ impl<C: Client + Send + Sync + Debug> Daemon for Lianad<C> {
         self.call("listtransactions", Some(vec![txids]))
     }
 
+    async fn list_events(&self, since: Option<u64>) -> Result<ListEventsResult, DaemonError> {
+        self.call("listevents", Some(vec![since]))
+    }
+
+    async fn get_recovery_readiness(&self) -> Result<RecoveryReadiness, DaemonError> {
+        self.call("getrecoveryreadiness", Option::<Request>::None)
+    }
+
+    async fn set_recovery_readiness(
+        &self,
+        item: ReadinessItem,
+        done: bool,
+    ) -> Result<(), DaemonError> {
+        let _res: serde_json::value::Value =
+            self.call("setrecoveryreadiness", Some(vec![json!(item), json!(done)]))?;
+        Ok(())
+    }
+
+    async fn health_check(&self) -> Result<HealthCheckResult, DaemonError> {
+        self.call("healthcheck", Option::<Request>::None)
+    }
+
+    async fn get_mempool_stats(&self) -> Result<GetMempoolStatsResult, DaemonError> {
+        self.call("getmempoolstats", Option::<Request>::None)
+    }
+
+    async fn get_recovery_schedule(
+        &self,
+        timelock: Option<u16>,
+    ) -> Result<GetRecoveryScheduleResult, DaemonError> {
+        match timelock {
+            Some(timelock) => self.call("getrecoveryschedule", Some(vec![timelock])),
+            None => self.call("getrecoveryschedule", Option::<Request>::None),
+        }
+    }
+
+    async fn get_receipt(
+        &self,
+        txid: &Txid,
+        explorer_url: Option<String>,
+    ) -> Result<GetReceiptResult, DaemonError> {
+        match explorer_url {
+            Some(url) => self.call("getreceipt", Some(json!([txid, url]))),
+            None => self.call("getreceipt", Some(vec![txid])),
+        }
+    }
+
+    async fn list_spend_templates(&self) -> Result<Vec<SpendTemplate>, DaemonError> {
+        #[derive(Deserialize)]
+        struct ListSpendTemplatesResult {
+            templates: Vec<SpendTemplate>,
+        }
+        let res: ListSpendTemplatesResult =
+            self.call("listspendtemplates", Option::<Request>::None)?;
+        Ok(res.templates)
+    }
+
+    async fn save_spend_template(&self, template: SpendTemplate) -> Result<(), DaemonError> {
+        let _res: serde_json::value::Value =
+            self.call("savespendtemplate", Some(vec![json!(template)]))?;
+        Ok(())
+    }
+
+    async fn delete_spend_template(&self, name: &str) -> Result<(), DaemonError> {
+        let _res: serde_json::value::Value =
+            self.call("delspendtemplate", Some(vec![json!(name)]))?;
+        Ok(())
+    }
+
+    async fn review_spend(
+        &self,
+        txid: &Txid,
+        signer: Fingerprint,
+        decision: ReviewDecision,
+        comment: Option<String>,
+    ) -> Result<(), DaemonError> {
+        let _res: serde_json::value::Value = self.call(
+            "reviewspend",
+            Some(vec![
+                json!(txid),
+                json!(signer),
+                json!(decision),
+                json!(comment),
+            ]),
+        )?;
+        Ok(())
+    }
+
     async fn create_recovery(
         &self,
         address: Address<address::NetworkUnchecked>,
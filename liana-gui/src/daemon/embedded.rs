@@ -4,10 +4,11 @@ use tokio::sync::Mutex;
 
 use super::{model::*, node, Daemon, DaemonBackend, DaemonError};
 use async_trait::async_trait;
-use liana::miniscript::bitcoin::{address, psbt::Psbt, Address, Network, OutPoint, Txid};
+use liana::miniscript::bitcoin::{address, psbt::Psbt, Address, Amount, Network, OutPoint, Txid};
 use lianad::{
-    commands::{CoinStatus, LabelItem},
+    commands::{CoinStatus, CreateSpendOptions, LabelItem},
     config::Config,
+    spans::{Span, SpanKind},
     DaemonControl, DaemonHandle,
 };
 
@@ -26,15 +27,32 @@ impl EmbeddedDaemon {
         })
     }
 
-    pub async fn command<T, F>(&self, method: F) -> Result<T, DaemonError>
+    /// Run a command which only reads from the wallet. The handle is only locked to get the
+    /// controller, so that commands run in parallel. The command is logged under this name if
+    /// it's slow.
+    pub async fn command<T, F>(&self, name: &str, method: F) -> Result<T, DaemonError>
     where
-        F: FnOnce(&mut DaemonControl) -> Result<T, DaemonError>,
+        F: FnOnce(&DaemonControl) -> Result<T, DaemonError>,
     {
-        match self.handle.lock().await.as_mut() {
-            Some(DaemonHandle::Controller { control, .. }) => method(control),
+        let control = match self.handle.lock().await.as_ref() {
+            Some(DaemonHandle::Controller { control, .. }) => control.clone(),
             Some(_) => unreachable!("No lianad rpc server must be started"),
-            None => Err(DaemonError::DaemonStopped),
-        }
+            None => return Err(DaemonError::DaemonStopped),
+        };
+        let _span = Span::enter(SpanKind::Command, name);
+        method(&control)
+    }
+
+    /// Run a command which modifies the wallet, once the ones modifying it already are done.
+    pub async fn write_command<T, F>(&self, name: &str, method: F) -> Result<T, DaemonError>
+    where
+        F: FnOnce(&DaemonControl) -> Result<T, DaemonError>,
+    {
+        self.command(name, |daemon| {
+            let _writes = daemon.lock_writes();
+            method(daemon)
+        })
+        .await
     }
 }
 
@@ -90,11 +108,18 @@ impl Daemon for EmbeddedDaemon {
     }
 
     async fn get_info(&self) -> Result<GetInfoResult, DaemonError> {
-        self.command(|daemon| Ok(daemon.get_info())).await
+        self.command("get_info", |daemon| Ok(daemon.get_info()))
+            .await
+    }
+
+    async fn get_balance(&self) -> Result<GetBalanceResult, DaemonError> {
+        self.command("get_balance", |daemon| Ok(daemon.get_balance()))
+            .await
     }
 
     async fn get_new_address(&self) -> Result<GetAddressResult, DaemonError> {
-        self.command(|daemon| Ok(daemon.get_new_address())).await
+        self.write_command("get_new_address", |daemon| Ok(daemon.get_new_address()))
+            .await
     }
 
     async fn list_coins(
@@ -102,14 +127,16 @@ impl Daemon for EmbeddedDaemon {
         statuses: &[CoinStatus],
         outpoints: &[OutPoint],
     ) -> Result<ListCoinsResult, DaemonError> {
-        self.command(|daemon| Ok(daemon.list_coins(statuses, outpoints)))
-            .await
+        self.command("list_coins", |daemon| {
+            Ok(daemon.list_coins(statuses, outpoints))
+        })
+        .await
     }
 
     async fn list_spend_txs(&self) -> Result<ListSpendResult, DaemonError> {
-        self.command(|daemon| {
+        self.command("list_spend_txs", |daemon| {
             daemon
-                .list_spend(None)
+                .list_spend(None, &[], None)
                 .map_err(|e| DaemonError::Unexpected(e.to_string()))
         })
         .await
@@ -121,25 +148,136 @@ impl Daemon for EmbeddedDaemon {
         end: u32,
         limit: u64,
     ) -> Result<ListTransactionsResult, DaemonError> {
-        self.command(|daemon| Ok(daemon.list_confirmed_transactions(start, end, limit)))
-            .await
+        self.command("list_confirmed_txs", |daemon| {
+            Ok(daemon.list_confirmed_transactions(start, end, limit))
+        })
+        .await
     }
 
     async fn list_txs(&self, txids: &[Txid]) -> Result<ListTransactionsResult, DaemonError> {
-        self.command(|daemon| Ok(daemon.list_transactions(txids)))
+        self.command("list_txs", |daemon| Ok(daemon.list_transactions(txids)))
+            .await
+    }
+
+    async fn list_events(&self, since: Option<u64>) -> Result<ListEventsResult, DaemonError> {
+        self.command("list_events", |daemon| Ok(daemon.list_events(since)))
             .await
     }
 
+    async fn get_recovery_readiness(&self) -> Result<RecoveryReadiness, DaemonError> {
+        self.command("get_recovery_readiness", |daemon| {
+            Ok(daemon.get_recovery_readiness())
+        })
+        .await
+    }
+
+    async fn set_recovery_readiness(
+        &self,
+        item: ReadinessItem,
+        done: bool,
+    ) -> Result<(), DaemonError> {
+        self.write_command("set_recovery_readiness", |daemon| {
+            daemon
+                .set_recovery_readiness(item, done)
+                .map_err(|e| DaemonError::Unexpected(e.to_string()))
+        })
+        .await
+    }
+
+    async fn health_check(&self) -> Result<HealthCheckResult, DaemonError> {
+        self.command("health_check", |daemon| Ok(daemon.health_check()))
+            .await
+    }
+
+    async fn get_mempool_stats(&self) -> Result<GetMempoolStatsResult, DaemonError> {
+        self.command("get_mempool_stats", |daemon| Ok(daemon.get_mempool_stats()))
+            .await
+    }
+
+    async fn get_recovery_schedule(
+        &self,
+        timelock: Option<u16>,
+    ) -> Result<GetRecoveryScheduleResult, DaemonError> {
+        self.command("get_recovery_schedule", |daemon| {
+            daemon
+                .get_recovery_schedule(timelock, None)
+                .map_err(|e| DaemonError::Unexpected(e.to_string()))
+        })
+        .await
+    }
+
+    async fn get_receipt(
+        &self,
+        txid: &Txid,
+        explorer_url: Option<String>,
+    ) -> Result<GetReceiptResult, DaemonError> {
+        self.command("get_receipt", |daemon| {
+            daemon
+                .get_receipt(txid, explorer_url.as_deref())
+                .map_err(|e| DaemonError::Unexpected(e.to_string()))
+        })
+        .await
+    }
+
+    async fn list_spend_templates(&self) -> Result<Vec<SpendTemplate>, DaemonError> {
+        self.command("list_spend_templates", |daemon| {
+            Ok(daemon.list_spend_templates().templates)
+        })
+        .await
+    }
+
+    async fn save_spend_template(&self, template: SpendTemplate) -> Result<(), DaemonError> {
+        self.write_command("save_spend_template", |daemon| {
+            daemon
+                .save_spend_template(template)
+                .map_err(|e| DaemonError::Unexpected(e.to_string()))
+        })
+        .await
+    }
+
+    async fn delete_spend_template(&self, name: &str) -> Result<(), DaemonError> {
+        self.write_command("delete_spend_template", |daemon| {
+            daemon.delete_spend_template(name);
+            Ok(())
+        })
+        .await
+    }
+
+    async fn review_spend(
+        &self,
+        txid: &Txid,
+        signer: Fingerprint,
+        decision: ReviewDecision,
+        comment: Option<String>,
+    ) -> Result<(), DaemonError> {
+        self.write_command("review_spend", |daemon| {
+            daemon
+                .review_spend(txid, signer, decision, comment)
+                .map_err(|e| DaemonError::Unexpected(e.to_string()))
+        })
+        .await
+    }
+
     async fn create_spend_tx(
         &self,
         coins_outpoints: &[OutPoint],
         destinations: &HashMap<Address<address::NetworkUnchecked>, u64>,
         feerate_vb: u64,
         change_address: Option<Address<address::NetworkUnchecked>>,
+        allow_future_witness: bool,
     ) -> Result<CreateSpendResult, DaemonError> {
-        self.command(|daemon| {
+        self.write_command("create_spend_tx", |daemon| {
             daemon
-                .create_spend(destinations, coins_outpoints, feerate_vb, change_address)
+                .create_spend(
+                    destinations,
+                    coins_outpoints,
+                    feerate_vb,
+                    change_address,
+                    &CreateSpendOptions {
+                        allow_future_witness,
+                        ..Default::default()
+                    },
+                )
                 .map_err(|e| DaemonError::Unexpected(e.to_string()))
         })
         .await
@@ -151,7 +289,7 @@ impl Daemon for EmbeddedDaemon {
         is_cancel: bool,
         feerate_vb: Option<u64>,
     ) -> Result<CreateSpendResult, DaemonError> {
-        self.command(|daemon| {
+        self.write_command("rbf_psbt", |daemon| {
             daemon
                 .rbf_psbt(txid, is_cancel, feerate_vb)
                 .map_err(|e| DaemonError::Unexpected(e.to_string()))
@@ -160,7 +298,7 @@ impl Daemon for EmbeddedDaemon {
     }
 
     async fn update_spend_tx(&self, psbt: &Psbt) -> Result<(), DaemonError> {
-        self.command(|daemon| {
+        self.write_command("update_spend_tx", |daemon| {
             daemon
                 .update_spend(psbt.clone())
                 .map_err(|e| DaemonError::Unexpected(e.to_string()))
@@ -169,24 +307,28 @@ impl Daemon for EmbeddedDaemon {
     }
 
     async fn delete_spend_tx(&self, txid: &Txid) -> Result<(), DaemonError> {
-        self.command(|daemon| {
+        self.write_command("delete_spend_tx", |daemon| {
             daemon.delete_spend(txid);
             Ok(())
         })
         .await
     }
 
-    async fn broadcast_spend_tx(&self, txid: &Txid) -> Result<(), DaemonError> {
-        self.command(|daemon| {
+    async fn broadcast_spend_tx(
+        &self,
+        txid: &Txid,
+        confirmed_amount: Option<Amount>,
+    ) -> Result<(), DaemonError> {
+        self.write_command("broadcast_spend_tx", |daemon| {
             daemon
-                .broadcast_spend(txid)
+                .broadcast_spend(txid, confirmed_amount)
                 .map_err(|e| DaemonError::Unexpected(e.to_string()))
         })
         .await
     }
 
     async fn start_rescan(&self, t: u32) -> Result<(), DaemonError> {
-        self.command(|daemon| {
+        self.write_command("start_rescan", |daemon| {
             daemon
                 .start_rescan(t)
                 .map_err(|e| DaemonError::Unexpected(e.to_string()))
@@ -200,7 +342,7 @@ impl Daemon for EmbeddedDaemon {
         feerate_vb: u64,
         sequence: Option<u16>,
     ) -> Result<Psbt, DaemonError> {
-        self.command(|daemon| {
+        self.write_command("create_recovery", |daemon| {
             daemon
                 .create_recovery(address, feerate_vb, sequence)
                 .map(|res| res.psbt)
@@ -213,7 +355,7 @@ impl Daemon for EmbeddedDaemon {
         &self,
         items: &HashSet<LabelItem>,
     ) -> Result<HashMap<String, String>, DaemonError> {
-        self.command(|daemon| Ok(daemon.get_labels(items).labels))
+        self.command("get_labels", |daemon| Ok(daemon.get_labels(items).labels))
             .await
     }
 
@@ -221,7 +363,7 @@ impl Daemon for EmbeddedDaemon {
         &self,
         items: &HashMap<LabelItem, Option<String>>,
     ) -> Result<(), DaemonError> {
-        self.command(|daemon| {
+        self.write_command("update_labels", |daemon| {
             daemon.update_labels(items);
             Ok(())
         })
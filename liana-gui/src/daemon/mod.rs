@@ -1,3 +1,4 @@
+pub mod cache;
 pub mod client;
 pub mod embedded;
 pub mod model;
@@ -12,7 +13,7 @@ use std::path::Path;
 use async_trait::async_trait;
 
 use liana::miniscript::bitcoin::{
-    address, bip32::Fingerprint, psbt::Psbt, secp256k1, Address, Network, OutPoint, Txid,
+    address, bip32::Fingerprint, psbt::Psbt, secp256k1, Address, Amount, Network, OutPoint, Txid,
 };
 use lianad::{
     commands::{CoinStatus, LabelItem, TransactionInfo},
@@ -93,6 +94,7 @@ pub trait Daemon: Debug {
         destinations: &HashMap<Address<address::NetworkUnchecked>, u64>,
         feerate_vb: u64,
         change_address: Option<Address<address::NetworkUnchecked>>,
+        allow_future_witness: bool,
     ) -> Result<model::CreateSpendResult, DaemonError>;
     async fn rbf_psbt(
         &self,
@@ -102,7 +104,13 @@ pub trait Daemon: Debug {
     ) -> Result<model::CreateSpendResult, DaemonError>;
     async fn update_spend_tx(&self, psbt: &Psbt) -> Result<(), DaemonError>;
     async fn delete_spend_tx(&self, txid: &Txid) -> Result<(), DaemonError>;
-    async fn broadcast_spend_tx(&self, txid: &Txid) -> Result<(), DaemonError>;
+    /// Broadcast a spend. The amount the user retyped to confirm it is given if the wallet
+    /// requires it, for the daemon to enforce its own confirmation threshold.
+    async fn broadcast_spend_tx(
+        &self,
+        txid: &Txid,
+        confirmed_amount: Option<Amount>,
+    ) -> Result<(), DaemonError>;
     async fn start_rescan(&self, t: u32) -> Result<(), DaemonError>;
     async fn list_confirmed_txs(
         &self,
@@ -128,6 +136,84 @@ pub trait Daemon: Debug {
     async fn send_wallet_invitation(&self, _email: &str) -> Result<(), DaemonError> {
         Ok(())
     }
+    /// The number of writes waiting for the backend to be reachable again.
+    fn queued_writes(&self) -> usize {
+        0
+    }
+    /// The value of the unspent coins by status. Backends which don't maintain it compute it
+    /// from the list of coins.
+    async fn get_balance(&self) -> Result<model::GetBalanceResult, DaemonError> {
+        let coins = self
+            .list_coins(
+                &[
+                    CoinStatus::Unconfirmed,
+                    CoinStatus::Confirmed,
+                    CoinStatus::Spending,
+                ],
+                &[],
+            )
+            .await?
+            .coins;
+        Ok(model::balance_from_coins(&coins))
+    }
+    /// List the events following the one with the given identifier. Backends which don't record
+    /// events never return any.
+    async fn list_events(
+        &self,
+        _since: Option<u64>,
+    ) -> Result<model::ListEventsResult, DaemonError> {
+        Ok(model::ListEventsResult { events: Vec::new() })
+    }
+    async fn get_recovery_readiness(&self) -> Result<model::RecoveryReadiness, DaemonError> {
+        Err(DaemonError::ClientNotSupported)
+    }
+    async fn set_recovery_readiness(
+        &self,
+        _item: model::ReadinessItem,
+        _done: bool,
+    ) -> Result<(), DaemonError> {
+        Err(DaemonError::ClientNotSupported)
+    }
+    async fn list_spend_templates(&self) -> Result<Vec<model::SpendTemplate>, DaemonError> {
+        Err(DaemonError::ClientNotSupported)
+    }
+    async fn health_check(&self) -> Result<model::HealthCheckResult, DaemonError> {
+        Err(DaemonError::ClientNotSupported)
+    }
+    async fn get_mempool_stats(&self) -> Result<model::GetMempoolStatsResult, DaemonError> {
+        Err(DaemonError::ClientNotSupported)
+    }
+    async fn get_recovery_schedule(
+        &self,
+        _timelock: Option<u16>,
+    ) -> Result<model::GetRecoveryScheduleResult, DaemonError> {
+        Err(DaemonError::ClientNotSupported)
+    }
+    async fn get_receipt(
+        &self,
+        _txid: &Txid,
+        _explorer_url: Option<String>,
+    ) -> Result<model::GetReceiptResult, DaemonError> {
+        Err(DaemonError::ClientNotSupported)
+    }
+    async fn save_spend_template(
+        &self,
+        _template: model::SpendTemplate,
+    ) -> Result<(), DaemonError> {
+        Err(DaemonError::ClientNotSupported)
+    }
+    async fn delete_spend_template(&self, _name: &str) -> Result<(), DaemonError> {
+        Err(DaemonError::ClientNotSupported)
+    }
+    async fn review_spend(
+        &self,
+        _txid: &Txid,
+        _signer: Fingerprint,
+        _decision: model::ReviewDecision,
+        _comment: Option<String>,
+    ) -> Result<(), DaemonError> {
+        Err(DaemonError::ClientNotSupported)
+    }
 
     // List spend transactions, optionally filtered to the specified `txids`.
     // Set `txids` to `None` for no filter (passing an empty slice returns no transactions).
@@ -168,14 +254,25 @@ pub trait Daemon: Debug {
                 .cloned()
                 .collect();
 
-            spend_txs.push(model::SpendTx::new(
+            let mut spend_tx = model::SpendTx::new(
                 tx.updated_at,
                 tx.psbt,
                 coins,
                 &info.descriptors.main,
                 &curve,
                 info.network,
-            ));
+            );
+            spend_tx.conflicting_spends = tx.conflicting_spends;
+            spend_tx.reviews = tx.reviews;
+            spend_txs.push(spend_tx);
+        }
+        if txids.is_none() {
+            cache::retain(
+                &spend_txs
+                    .iter()
+                    .map(|tx| tx.psbt.unsigned_tx.compute_txid())
+                    .collect::<Vec<_>>(),
+            );
         }
         load_labels(self, &mut spend_txs).await?;
         spend_txs.sort_by(|a, b| {
@@ -3,16 +3,24 @@ use std::collections::{HashMap, HashSet};
 
 use liana::descriptors::LianaDescriptor;
 pub use liana::{
-    descriptors::{LianaPolicy, PartialSpendInfo, PathSpendInfo},
+    descriptors::{InputFinalization, LianaPolicy, PartialSpendInfo, PathSpendInfo},
     miniscript::bitcoin::{
         bip32::{DerivationPath, Fingerprint},
         psbt::Psbt,
-        secp256k1, Address, Amount, Network, OutPoint, Transaction, Txid,
+        secp256k1, Address, Amount, Network, OutPoint, SignedAmount, Transaction, Txid,
     },
 };
-pub use lianad::commands::{
-    CreateSpendResult, GetAddressResult, GetInfoResult, GetLabelsResult, LabelItem, ListCoinsEntry,
-    ListCoinsResult, ListSpendEntry, ListSpendResult, ListTransactionsResult, TransactionInfo,
+pub use lianad::{
+    commands::{
+        CreateSpendResult, FeeHistogramBucket, GetAddressResult, GetBalanceResult, GetInfoResult,
+        GetLabelsResult, GetMempoolStatsResult, GetReceiptResult, GetRecoveryScheduleResult,
+        HealthCheck, HealthCheckResult, HealthStatus, LabelItem, ListCoinsEntry, ListCoinsResult,
+        ListEventsResult, ListSpendEntry, ListSpendResult, ListTransactionsResult, ReviewDecision,
+        ReviewState, SpendReview, TransactionInfo,
+    },
+    events::{Event, EventEntry},
+    readiness::{ReadinessCheck, ReadinessItem, RecoveryReadiness},
+    templates::{Schedule, SpendTemplate, TemplateRecipient},
 };
 
 pub type Coin = ListCoinsEntry;
@@ -36,6 +44,29 @@ pub fn coin_is_owned(coin: &Coin) -> bool {
     coin.block_height.is_some() || coin.is_from_self
 }
 
+/// The balances of the wallet from its unspent coins, as maintained by lianad.
+pub fn balance_from_coins(coins: &[Coin]) -> GetBalanceResult {
+    let mut balance = GetBalanceResult {
+        confirmed: Amount::ZERO,
+        unconfirmed_from_self: Amount::ZERO,
+        unconfirmed: Amount::ZERO,
+        immature: Amount::ZERO,
+        spending: Amount::ZERO,
+    };
+    for coin in coins {
+        let amount = match &coin.spend_info {
+            Some(info) if info.height.is_some() => continue,
+            Some(_) => &mut balance.spending,
+            None if coin.is_immature => &mut balance.immature,
+            None if coin.block_height.is_some() => &mut balance.confirmed,
+            None if coin.is_from_self => &mut balance.unconfirmed_from_self,
+            None => &mut balance.unconfirmed,
+        };
+        *amount += coin.amount;
+    }
+    balance
+}
+
 #[derive(Debug, Clone)]
 pub struct SpendTx {
     pub network: Network,
@@ -50,8 +81,16 @@ pub struct SpendTx {
     pub max_vbytes: u64,
     pub status: SpendStatus,
     pub sigs: PartialSpendInfo,
+    /// Whether a witness can be constructed for each input with the present signatures.
+    pub finalization: Vec<InputFinalization>,
     pub updated_at: Option<u32>,
     pub kind: TransactionKind,
+    /// The other stored drafts spending some of the same coins.
+    pub conflicting_spends: Vec<Txid>,
+    /// The confirmed transaction which spent one of the coins, making this one deprecated.
+    pub invalidated_by: Option<Txid>,
+    /// The latest review of each signer who reviewed it.
+    pub reviews: Vec<SpendReview>,
 }
 
 #[derive(PartialOrd, Ord, Debug, Clone, PartialEq, Eq)]
@@ -71,19 +110,12 @@ impl SpendTx {
         secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
         network: Network,
     ) -> Self {
-        // Use primary path if no inputs are using a relative locktime.
-        let use_primary_path = !psbt
-            .unsigned_tx
-            .input
-            .iter()
-            .map(|txin| txin.sequence)
-            .any(|seq| seq.is_relative_lock_time());
-        let max_vbytes = desc.unsigned_tx_max_vbytes(&psbt.unsigned_tx, use_primary_path);
-        let change_indexes: Vec<usize> = desc
-            .change_indexes(&psbt, secp)
-            .into_iter()
-            .map(|c| c.index())
-            .collect();
+        let super::cache::SpendInfo {
+            sigs,
+            max_vbytes,
+            change_indexes,
+            finalization,
+        } = super::cache::spend_info(desc, &psbt, secp);
         let (change_amount, spend_amount) = psbt.unsigned_tx.output.iter().enumerate().fold(
             (Amount::from_sat(0), Amount::from_sat(0)),
             |(change, spend), (i, output)| {
@@ -96,6 +128,7 @@ impl SpendTx {
         );
 
         let mut status = SpendStatus::Pending;
+        let mut invalidated_by = None;
         let mut coins_map = HashMap::<OutPoint, Coin>::with_capacity(coins.len());
         for coin in coins {
             if let Some(info) = coin.spend_info {
@@ -111,7 +144,8 @@ impl SpendTx {
                 // Once the replacement transaction has been confirmed, the PSBT for the
                 // transaction currently spending this coin will be shown as Deprecated.
                 } else if info.height.is_some() {
-                    status = SpendStatus::Deprecated
+                    status = SpendStatus::Deprecated;
+                    invalidated_by = Some(info.txid);
                 }
             }
             coins_map.insert(coin.outpoint, coin);
@@ -148,10 +182,6 @@ impl SpendTx {
             status = SpendStatus::Deprecated
         }
 
-        let sigs = desc
-            .partial_spend_info(&psbt)
-            .expect("PSBT must be generated by Liana");
-
         Self {
             labels: HashMap::new(),
             kind: if spend_amount == Amount::from_sat(0) {
@@ -188,10 +218,31 @@ impl SpendTx {
             max_vbytes,
             status,
             sigs,
+            finalization,
             network,
+            conflicting_spends: Vec::new(),
+            reviews: Vec::new(),
+            invalidated_by,
         }
     }
 
+    /// Whether the transaction can be finalized with the present signatures, ie whether a witness
+    /// can actually be constructed for all its inputs.
+    pub fn is_finalizable(&self) -> bool {
+        !self.finalization.is_empty()
+            && self
+                .finalization
+                .iter()
+                .all(InputFinalization::is_finalizable)
+    }
+
+    /// Why the transaction can't be finalized, if it can't.
+    pub fn finalization_error(&self) -> Option<&str> {
+        self.finalization
+            .iter()
+            .find_map(|input| input.error.as_deref())
+    }
+
     /// Returns the path ready if it exists.
     pub fn path_ready(&self) -> Option<&PathSpendInfo> {
         let path = self.sigs.primary_path();
@@ -248,6 +299,34 @@ impl SpendTx {
     }
 }
 
+/// Set on each of these drafts the others spending some of the same coins.
+pub fn set_conflicting_spends(spend_txs: &mut [SpendTx]) {
+    let mut drafts_by_outpoint: HashMap<OutPoint, Vec<Txid>> = HashMap::new();
+    for tx in spend_txs.iter() {
+        let txid = tx.psbt.unsigned_tx.compute_txid();
+        for txin in &tx.psbt.unsigned_tx.input {
+            drafts_by_outpoint
+                .entry(txin.previous_output)
+                .or_default()
+                .push(txid);
+        }
+    }
+    for tx in spend_txs.iter_mut() {
+        let txid = tx.psbt.unsigned_tx.compute_txid();
+        let conflicting: HashSet<Txid> = tx
+            .psbt
+            .unsigned_tx
+            .input
+            .iter()
+            .filter_map(|txin| drafts_by_outpoint.get(&txin.previous_output))
+            .flatten()
+            .filter(|draft_txid| **draft_txid != txid)
+            .copied()
+            .collect();
+        tx.conflicting_spends = conflicting.into_iter().collect();
+    }
+}
+
 impl Labelled for SpendTx {
     fn labels(&mut self) -> &mut HashMap<String, String> {
         &mut self.labels
@@ -311,7 +390,25 @@ impl HistoryTransaction {
             },
         );
 
-        let kind = if coins.is_empty() {
+        let mut inputs_amount = Amount::from_sat(0);
+        for coin in &coins {
+            inputs_amount += coin.amount;
+        }
+
+        // If some inputs aren't ours, the other outputs aren't all what we paid: part of them
+        // belong to the other participants of the transaction (payjoin, coinjoin, ...).
+        let is_collaborative = !coins.is_empty() && coins.len() < tx.input.len();
+        let outgoing_amount = if is_collaborative {
+            inputs_amount
+                .checked_sub(incoming_amount)
+                .unwrap_or(Amount::from_sat(0))
+        } else {
+            outgoing_amount
+        };
+
+        let kind = if is_collaborative {
+            TransactionKind::Collaborative
+        } else if coins.is_empty() {
             if change_indexes.len() == 1 {
                 TransactionKind::IncomingSinglePayment(OutPoint {
                     txid: tx.compute_txid(),
@@ -353,12 +450,10 @@ impl HistoryTransaction {
             }
         };
 
-        let mut inputs_amount = Amount::from_sat(0);
-        let mut coins_map = HashMap::<OutPoint, Coin>::with_capacity(coins.len());
-        for coin in coins {
-            inputs_amount += coin.amount;
-            coins_map.insert(coin.outpoint, coin);
-        }
+        let coins_map: HashMap<OutPoint, Coin> = coins
+            .into_iter()
+            .map(|coin| (coin.outpoint, coin))
+            .collect();
 
         Self {
             labels: HashMap::new(),
@@ -369,7 +464,12 @@ impl HistoryTransaction {
             change_indexes,
             outgoing_amount,
             incoming_amount,
-            fee_amount: inputs_amount.checked_sub(outgoing_amount + incoming_amount),
+            // We don't know the value of the inputs of the other participants.
+            fee_amount: if is_collaborative {
+                None
+            } else {
+                inputs_amount.checked_sub(outgoing_amount + incoming_amount)
+            },
             height,
             time,
             network,
@@ -406,6 +506,18 @@ impl HistoryTransaction {
         matches!(self.kind, TransactionKind::SendToSelf)
     }
 
+    pub fn is_collaborative(&self) -> bool {
+        matches!(self.kind, TransactionKind::Collaborative)
+    }
+
+    /// The change of the balance of the wallet caused by this transaction: the value of its
+    /// outputs to the wallet minus the value of the wallet coins it spends.
+    pub fn net_amount(&self) -> SignedAmount {
+        let inputs_amount: Amount = self.coins.values().map(|coin| coin.amount).sum();
+        SignedAmount::from_sat(self.incoming_amount.to_sat() as i64)
+            - SignedAmount::from_sat(inputs_amount.to_sat() as i64)
+    }
+
     pub fn is_single_payment(&self) -> Option<OutPoint> {
         match self.kind {
             TransactionKind::IncomingSinglePayment(outpoint) => Some(outpoint),
@@ -484,7 +596,10 @@ pub fn payments_from_tx(history_tx: HistoryTransaction) -> Vec<Payment> {
         .iter()
         .enumerate()
         .fold(Vec::new(), |mut array, (output_index, output)| {
-            if history_tx.is_external() && !history_tx.change_indexes.contains(&output_index) {
+            // The other outputs of a collaborative transaction may belong to other participants.
+            if (history_tx.is_external() || history_tx.is_collaborative())
+                && !history_tx.change_indexes.contains(&output_index)
+            {
                 return array;
             }
             let outpoint = OutPoint {
@@ -510,8 +625,12 @@ pub fn payments_from_tx(history_tx: HistoryTransaction) -> Vec<Payment> {
                         && history_tx.change_indexes.contains(&output_index))
                 {
                     PaymentKind::SendToSelf
-                } else if history_tx.is_external() {
+                } else if history_tx.is_external()
+                    || (history_tx.is_collaborative() && history_tx.net_amount().is_positive())
+                {
                     PaymentKind::Incoming
+                } else if history_tx.is_collaborative() {
+                    PaymentKind::SendToSelf
                 } else {
                     PaymentKind::Outgoing
                 },
@@ -527,6 +646,9 @@ pub enum TransactionKind {
     SendToSelf,
     OutgoingSinglePayment(OutPoint),
     OutgoingPaymentBatch(Vec<OutPoint>),
+    /// Wallet coins were spent along with coins of other participants, as in a payjoin or a
+    /// coinjoin. Only the net effect on the wallet is known.
+    Collaborative,
 }
 
 impl Labelled for HistoryTransaction {
@@ -584,3 +706,82 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use liana::miniscript::bitcoin::{
+        absolute, bip32::ChildNumber, hashes::Hash, transaction, ScriptBuf, TxIn, TxOut,
+    };
+    use std::str::FromStr;
+
+    fn coin(outpoint: OutPoint, sats: u64) -> Coin {
+        Coin {
+            amount: Amount::from_sat(sats),
+            outpoint,
+            address: Address::from_str("bc1qvrl2849aggm6qry9ea7xqp2kk39j8vaa8r3cwg")
+                .unwrap()
+                .assume_checked(),
+            block_height: Some(100),
+            derivation_index: ChildNumber::from_normal_idx(0).unwrap(),
+            spend_info: None,
+            is_immature: false,
+            maturity_height: None,
+            is_change: false,
+            is_from_self: false,
+        }
+    }
+
+    #[test]
+    fn collaborative_transaction() {
+        let ours = OutPoint::new(Txid::from_byte_array([1; 32]), 0);
+        let theirs = OutPoint::new(Txid::from_byte_array([2; 32]), 0);
+        let output = |sats| TxOut {
+            value: Amount::from_sat(sats),
+            script_pubkey: ScriptBuf::new(),
+        };
+        // We put 100k sats in a coinjoin along with another participant, and get 60k back.
+        let tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: [ours, theirs]
+                .iter()
+                .map(|outpoint| TxIn {
+                    previous_output: *outpoint,
+                    ..TxIn::default()
+                })
+                .collect(),
+            output: vec![output(60_000), output(39_000), output(99_000)],
+        };
+        let history_tx = HistoryTransaction::new(
+            tx.clone(),
+            None,
+            None,
+            vec![coin(ours, 100_000)],
+            vec![0],
+            Network::Bitcoin,
+        );
+        assert!(history_tx.is_collaborative());
+        assert_eq!(history_tx.outgoing_amount, Amount::from_sat(40_000));
+        assert_eq!(history_tx.fee_amount, None);
+        assert_eq!(history_tx.net_amount(), SignedAmount::from_sat(-40_000));
+        // Only our output is a payment.
+        let payments = payments_from_tx(history_tx);
+        assert_eq!(payments.len(), 1);
+        assert_eq!(payments[0].kind, PaymentKind::SendToSelf);
+
+        // As the receiver of a payjoin we get more than we put in.
+        let history_tx = HistoryTransaction::new(
+            tx,
+            None,
+            None,
+            vec![coin(ours, 50_000)],
+            vec![0],
+            Network::Bitcoin,
+        );
+        assert!(history_tx.is_collaborative());
+        assert_eq!(history_tx.outgoing_amount, Amount::from_sat(0));
+        assert_eq!(history_tx.net_amount(), SignedAmount::from_sat(10_000));
+        assert_eq!(payments_from_tx(history_tx)[0].kind, PaymentKind::Incoming);
+    }
+}
@@ -11,7 +11,8 @@ use std::{
 };
 
 use chrono::{DateTime, Duration, Utc};
-use liana::miniscript::bitcoin::{Amount, Txid};
+use liana::miniscript::bitcoin::{Amount, SignedAmount, Txid};
+use liana_ui::component::amount::display_unit;
 use tokio::{
     task::{JoinError, JoinHandle},
     time::sleep,
@@ -178,7 +179,14 @@ impl State {
                     }
                 };
 
-                let header = "Date,Label,Value,Fee,Txid,Block\n".to_string();
+                // Amounts are exported in the unit used for display, but never with digit
+                // grouping nor a comma decimal mark as it would break the CSV format.
+                let unit = display_unit();
+                let header = format!(
+                    "Date,Label,Value ({}),Fee ({}),Txid,Block\n",
+                    unit.label(),
+                    unit.label()
+                );
                 if let Err(e) = file.write_all(header.as_bytes()) {
                     send_error!(sender, e.into());
                     return;
@@ -299,16 +307,10 @@ impl State {
                     }
                     let txid = tx.txid.to_string();
                     let fee = tx.fee_amount.unwrap_or(Amount::ZERO).to_sat() as i128;
-                    let mut inputs_amount = 0;
-                    tx.coins.iter().for_each(|(_, coin)| {
-                        inputs_amount += coin.amount.to_sat() as i128;
-                    });
-                    let value = tx.incoming_amount.to_sat() as i128 - inputs_amount;
-                    let value = value as f64 / 100_000_000.0;
-                    let fee = fee as f64 / 100_000_000.0;
+                    let value = tx.net_amount().to_string_in(unit.denomination());
                     let block = tx.height.map(|h| h.to_string()).unwrap_or("".to_string());
-                    let fee = if fee != 0.0 {
-                        fee.to_string()
+                    let fee = if fee != 0 {
+                        SignedAmount::from_sat(fee as i64).to_string_in(unit.denomination())
                     } else {
                         "".into()
                     };
@@ -0,0 +1,151 @@
+//! Files dropped onto the window.
+//!
+//! The content of a dropped file is detected so it can be routed to the flow importing it: a PSBT
+//! or a signed transaction to the import of a spend draft, a descriptor to the import of a wallet
+//! and a mnemonic to the restoration of a hot signer.
+
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use liana::{
+    descriptors::LianaDescriptor,
+    miniscript::bitcoin::{psbt::Psbt, Network, Transaction},
+    signer::HotSigner,
+};
+
+use crate::psbt_format::{self, Content};
+
+/// Descriptors, PSBTs and backups are small, don't read anything bigger.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub enum DroppedFile {
+    Psbt(Psbt),
+    SignedTransaction(Transaction),
+    Descriptor(String),
+    /// A BIP39 mnemonic, or SLIP-39 shares one per line.
+    Mnemonic(String),
+}
+
+impl DroppedFile {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Psbt(_) => "PSBT",
+            Self::SignedTransaction(_) => "signed transaction",
+            Self::Descriptor(_) => "descriptor",
+            Self::Mnemonic(_) => "mnemonic",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum DroppedFileError {
+    Read(String),
+    TooLarge,
+    InvalidPsbt(psbt_format::ParseError),
+    Unrecognized,
+}
+
+impl fmt::Display for DroppedFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Read(e) => write!(f, "Failed to read the dropped file: {}", e),
+            Self::TooLarge => write!(f, "The dropped file is too large to be imported"),
+            Self::InvalidPsbt(e) => write!(f, "The dropped PSBT is invalid: {}", e),
+            Self::Unrecognized => write!(
+                f,
+                "The dropped file is neither a PSBT, a signed transaction, a descriptor nor a mnemonic backup"
+            ),
+        }
+    }
+}
+
+/// Read the file at this path and detect what it contains.
+pub async fn read(path: PathBuf) -> Result<DroppedFile, DroppedFileError> {
+    let metadata = std::fs::metadata(&path).map_err(|e| DroppedFileError::Read(e.to_string()))?;
+    if metadata.len() > MAX_FILE_SIZE {
+        return Err(DroppedFileError::TooLarge);
+    }
+    let content = std::fs::read(&path).map_err(|e| DroppedFileError::Read(e.to_string()))?;
+    parse(&content)
+}
+
+/// Detect the content of a file, either a PSBT or a signed transaction in any encoding, a
+/// descriptor or a mnemonic.
+pub fn parse(content: &[u8]) -> Result<DroppedFile, DroppedFileError> {
+    match psbt_format::decode(content) {
+        Ok(decoded) => {
+            return Ok(match decoded.content {
+                Content::Psbt(psbt) => DroppedFile::Psbt(psbt),
+                Content::SignedTransaction(tx) => DroppedFile::SignedTransaction(tx),
+            })
+        }
+        // Tell why what looks like a PSBT can't be imported rather than not recognizing it.
+        Err(e) if psbt_format::looks_like_psbt(content) => {
+            return Err(DroppedFileError::InvalidPsbt(e))
+        }
+        Err(_) => {}
+    }
+    let text = std::str::from_utf8(content)
+        .map_err(|_| DroppedFileError::Unrecognized)?
+        .trim();
+    if LianaDescriptor::from_str(text).is_ok() {
+        return Ok(DroppedFile::Descriptor(text.to_string()));
+    }
+    // The network doesn't matter, it's only checked that the words can be decoded.
+    if HotSigner::from_str(Network::Bitcoin, text).is_ok() {
+        return Ok(DroppedFile::Mnemonic(text.to_string()));
+    }
+    Err(DroppedFileError::Unrecognized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PSBT: &str = "cHNidP8BAHECAAAAAUSHuliRtuCX1S6JxRuDRqDCKkWfKmWL5sV9ukZ/wzvfAAAAAAD9////AogTAAAAAAAAFgAUIxe7UY6LJ6y5mFBoWTOoVispDmdwFwAAAAAAABYAFKqO83TK+t/KdpAt21z2HGC7/Z2FAAAAAAABASsQJwAAAAAAACIAIIIySQjGCTeyx/rKUQx8qobjhJeNCiVCliBJPdyRX6XKAQVBIQI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDKxzZHapFNYASzIYkEdH9bJz6nnqUG3uBB8kiK1asmgAAAA=";
+
+    #[test]
+    fn parse_dropped_file() {
+        // A base64 PSBT, possibly with a trailing newline, and its binary serialization.
+        let psbt = Psbt::from_str(PSBT).unwrap();
+        assert!(matches!(
+            parse(format!("{}\n", PSBT).as_bytes()),
+            Ok(DroppedFile::Psbt(p)) if p == psbt
+        ));
+        assert!(matches!(
+            parse(&psbt.serialize()),
+            Ok(DroppedFile::Psbt(p)) if p == psbt
+        ));
+        assert!(matches!(
+            parse(hex::encode(psbt.serialize()).as_bytes()),
+            Ok(DroppedFile::Psbt(p)) if p == psbt
+        ));
+        assert!(matches!(
+            parse(b"psbt\xffgarbage"),
+            Err(DroppedFileError::InvalidPsbt(
+                psbt_format::ParseError::Truncated { offset: 5 }
+            ))
+        ));
+
+        let desc = "wsh(or_d(pk([aabbccdd]tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/<0;1>/*),and_v(v:pkh([abcdef01]tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/<0;1>/*),older(10000))))";
+        assert!(matches!(
+            parse(desc.as_bytes()),
+            Ok(DroppedFile::Descriptor(d)) if d == desc
+        ));
+
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        assert!(matches!(
+            parse(mnemonic.as_bytes()),
+            Ok(DroppedFile::Mnemonic(m)) if m == mnemonic
+        ));
+
+        assert!(matches!(
+            parse(b"hello world"),
+            Err(DroppedFileError::Unrecognized)
+        ));
+        assert!(matches!(
+            parse(&[0xff, 0xfe, 0x00]),
+            Err(DroppedFileError::Unrecognized)
+        ));
+    }
+}
@@ -5,7 +5,10 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use crate::app::{settings, wallet::Wallet};
+use crate::{
+    app::{settings, wallet::Wallet},
+    tapsigner::{self, Tapsigner},
+};
 use async_hwi::{
     bitbox::{api::runtime, BitBox02, PairingBitbox02},
     coldcard,
@@ -53,6 +56,15 @@ pub enum HardwareWallet {
         registered: Option<bool>,
         alias: Option<String>,
     },
+    /// A Tapsigner on a NFC reader. Its fingerprint is only known once its CVC was entered.
+    Tapsigner {
+        id: String,
+        device: Arc<Tapsigner>,
+        fingerprint: Option<Fingerprint>,
+        alias: Option<String>,
+        /// The wallet uses a descriptor the card can't sign for.
+        incompatible: bool,
+    },
 }
 
 pub enum LockedDevice {
@@ -91,14 +103,17 @@ impl HardwareWallet {
             Self::Locked { id, .. } => id,
             Self::Unsupported { id, .. } => id,
             Self::Supported { id, .. } => id,
+            Self::Tapsigner { id, .. } => id,
         }
     }
 
-    pub fn kind(&self) -> &DeviceKind {
+    /// The kind of the device, if it's one supported by async-hwi.
+    pub fn kind(&self) -> Option<&DeviceKind> {
         match self {
-            Self::Locked { kind, .. } => kind,
-            Self::Unsupported { kind, .. } => kind,
-            Self::Supported { kind, .. } => kind,
+            Self::Locked { kind, .. } => Some(kind),
+            Self::Unsupported { kind, .. } => Some(kind),
+            Self::Supported { kind, .. } => Some(kind),
+            Self::Tapsigner { .. } => None,
         }
     }
 
@@ -107,6 +122,7 @@ impl HardwareWallet {
             Self::Locked { .. } => None,
             Self::Unsupported { .. } => None,
             Self::Supported { fingerprint, .. } => Some(*fingerprint),
+            Self::Tapsigner { fingerprint, .. } => *fingerprint,
         }
     }
 
@@ -186,18 +202,40 @@ impl HardwareWallets {
         // remove all (fingerprint, alias) with same alias.
         self.aliases.retain(|_, a| *a != new_alias);
         for hw in &mut self.list {
-            if let HardwareWallet::Supported {
-                fingerprint, alias, ..
+            let (fingerprint, alias) = match hw {
+                HardwareWallet::Supported {
+                    fingerprint, alias, ..
+                } => (Some(*fingerprint), alias),
+                HardwareWallet::Tapsigner {
+                    fingerprint, alias, ..
+                } => (*fingerprint, alias),
+                _ => continue,
+            };
+            if fingerprint == Some(fg) {
+                *alias = Some(new_alias.clone());
+            } else if alias.as_ref() == Some(&new_alias) {
+                *alias = None;
+            }
+        }
+        self.aliases.insert(fg, new_alias);
+    }
+
+    /// Record the fingerprint of a Tapsigner, learned once its CVC was entered.
+    pub fn set_tapsigner_fingerprint(&mut self, id: &str, fg: Fingerprint) {
+        for hw in &mut self.list {
+            if let HardwareWallet::Tapsigner {
+                id: hw_id,
+                fingerprint,
+                alias,
+                ..
             } = hw
             {
-                if *fingerprint == fg {
-                    *alias = Some(new_alias.clone());
-                } else if alias.as_ref() == Some(&new_alias) {
-                    *alias = None;
+                if hw_id == id {
+                    *fingerprint = Some(fg);
+                    *alias = self.aliases.get(&fg).cloned();
                 }
             }
         }
-        self.aliases.insert(fg, new_alias);
     }
 
     pub fn reset_watch_list(&mut self) {
@@ -231,6 +269,13 @@ impl HardwareWallets {
                         } => {
                             *alias = self.aliases.get(fingerprint).cloned();
                         }
+                        HardwareWallet::Tapsigner {
+                            fingerprint: Some(fingerprint),
+                            alias,
+                            ..
+                        } => {
+                            *alias = self.aliases.get(fingerprint).cloned();
+                        }
                         HardwareWallet::Locked { device, id, .. } => {
                             match device.lock().unwrap().take() {
                                 None => {}
@@ -670,6 +715,32 @@ fn refresh(mut state: State) -> impl Stream<Item = HardwareWalletMessage> {
                 }
             }
 
+            match tapsigner::readers() {
+                Ok(readers) => {
+                    for reader in readers {
+                        let id = format!("tapsigner-{}", reader);
+                        if state.connected_supported_hws.contains(&id) {
+                            still.push(id);
+                            continue;
+                        }
+                        match tapsigner::connect(&reader) {
+                            Ok(device) => hws.push(HardwareWallet::Tapsigner {
+                                id,
+                                device,
+                                fingerprint: None,
+                                alias: None,
+                                incompatible: state.wallet.as_ref().is_some_and(|wallet| {
+                                    !tapsigner::is_compatible_with(&wallet.main_descriptor)
+                                }),
+                            }),
+                            // Not all the cards put on a NFC reader are Tapsigners.
+                            Err(e) => debug!("{}", e),
+                        }
+                    }
+                }
+                Err(e) => debug!("Error while listing NFC readers: {}", e),
+            }
+
             if let Some(wallet) = &state.wallet {
                 let wallet_keys = wallet.descriptor_keys();
                 for hw in &mut hws {
@@ -698,6 +769,7 @@ fn refresh(mut state: State) -> impl Stream<Item = HardwareWalletMessage> {
                 .chain(hws.iter().filter_map(|hw| match hw {
                     HardwareWallet::Locked { id, .. } => Some(id),
                     HardwareWallet::Supported { id, .. } => Some(id),
+                    HardwareWallet::Tapsigner { id, .. } => Some(id),
                     HardwareWallet::Unsupported { .. } => None,
                 }))
                 .cloned()
@@ -926,3 +998,31 @@ pub fn is_compatible_with_tapminiscript(
                 }
         })
 }
+
+// Kind of devices refusing to sign a Segwit v0 input without its full previous transaction
+// (PSBT_IN_NON_WITNESS_UTXO), to protect against the fee attack on Segwit v0 signatures. Taproot
+// signatures commit to the amounts of all the inputs and don't need it.
+const DEVICES_REQUIRING_PREVIOUS_TRANSACTIONS: [DeviceKind; 4] = [
+    DeviceKind::Ledger,
+    DeviceKind::LedgerSimulator,
+    DeviceKind::Coldcard,
+    DeviceKind::BitBox02,
+];
+
+pub fn requires_previous_transactions(device_kind: &DeviceKind) -> bool {
+    DEVICES_REQUIRING_PREVIOUS_TRANSACTIONS.contains(device_kind)
+}
+
+// Kind of devices honouring the sighash type requested in the PSBT (PSBT_IN_SIGHASH_TYPE). The
+// others either refuse to sign or silently sign with SIGHASH_ALL.
+const DEVICES_SUPPORTING_CUSTOM_SIGHASH: [DeviceKind; 5] = [
+    DeviceKind::Ledger,
+    DeviceKind::LedgerSimulator,
+    DeviceKind::Coldcard,
+    DeviceKind::Specter,
+    DeviceKind::SpecterSimulator,
+];
+
+pub fn supports_custom_sighash(device_kind: &DeviceKind) -> bool {
+    DEVICES_SUPPORTING_CUSTOM_SIGHASH.contains(device_kind)
+}
@@ -0,0 +1,268 @@
+//! Localization of the user-facing strings of the GUI.
+//!
+//! Strings are translated gettext-style: the English text is the message identifier, and each
+//! other language has a catalog in the PO format mapping it to its translation. Messages that
+//! are not (yet) translated in the selected language are displayed in English.
+//! Parameters are passed by name and replace the `{name}` placeholders of the message.
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        OnceLock,
+    },
+};
+
+const FR_CATALOG: &str = include_str!("fr.po");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    #[default]
+    English,
+    French,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Self::English, Self::French];
+
+    /// Name of the language, in this language.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::English => "English",
+            Self::French => "Français",
+        }
+    }
+
+    /// Get the language from a locale identifier such as `fr_FR.UTF-8`, if supported.
+    pub fn from_locale(locale: &str) -> Option<Self> {
+        let lang = locale.split(['_', '-', '.', '@']).next()?.to_lowercase();
+        match lang.as_str() {
+            "en" => Some(Self::English),
+            "fr" => Some(Self::French),
+            _ => None,
+        }
+    }
+
+    /// Get the language from the locale of the system, if supported.
+    pub fn from_system_locale() -> Option<Self> {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|locale| !locale.is_empty())
+            .and_then(|locale| Self::from_locale(&locale))
+    }
+
+    fn catalog(&self) -> Option<&'static HashMap<String, String>> {
+        static FR: OnceLock<HashMap<String, String>> = OnceLock::new();
+        match self {
+            Self::English => None,
+            Self::French => Some(FR.get_or_init(|| parse_catalog(FR_CATALOG))),
+        }
+    }
+
+    fn from_u8(n: u8) -> Self {
+        match n {
+            1 => Self::French,
+            _ => Self::English,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::English => 0,
+            Self::French => 1,
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::English => write!(f, "en"),
+            Self::French => write!(f, "fr"),
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_locale(s).ok_or_else(|| format!("Unsupported language: '{}'", s))
+    }
+}
+
+impl Serialize for Language {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Language {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Self::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+static LANGUAGE: AtomicU8 = AtomicU8::new(0);
+
+/// Set the language of the user interface. Takes effect on the next rendering.
+pub fn set_language(language: Language) {
+    LANGUAGE.store(language.to_u8(), Ordering::Relaxed);
+}
+
+/// The current language of the user interface.
+pub fn language() -> Language {
+    Language::from_u8(LANGUAGE.load(Ordering::Relaxed))
+}
+
+/// Translate a message in the current language.
+pub fn tr(msgid: &'static str) -> &'static str {
+    translate(language(), msgid)
+}
+
+/// Translate a message in the current language, replacing its `{name}` placeholders with the
+/// given values.
+pub fn tr_args(msgid: &'static str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    args.iter()
+        .fold(tr(msgid).to_string(), |msg, (name, value)| {
+            msg.replace(&format!("{{{}}}", name), &value.to_string())
+        })
+}
+
+fn translate(language: Language, msgid: &'static str) -> &'static str {
+    language
+        .catalog()
+        .and_then(|catalog| catalog.get(msgid))
+        .map(|msgstr| msgstr.as_str())
+        .unwrap_or(msgid)
+}
+
+// Parse the content of a quoted PO string, unescaping it.
+fn parse_po_string(line: &str) -> Option<String> {
+    let inner = line.trim().strip_prefix('"')?.strip_suffix('"')?;
+    let mut res = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next()? {
+                'n' => res.push('\n'),
+                't' => res.push('\t'),
+                c => res.push(c),
+            }
+        } else {
+            res.push(c);
+        }
+    }
+    Some(res)
+}
+
+// Parse a catalog in the PO format. Only the `msgid` and `msgstr` entries are supported, which
+// may span multiple lines. Entries with an empty translation are ignored.
+fn parse_catalog(content: &str) -> HashMap<String, String> {
+    enum Field {
+        None,
+        Id,
+        Str,
+    }
+
+    let mut catalog = HashMap::new();
+    let (mut msgid, mut msgstr) = (String::new(), String::new());
+    let mut field = Field::None;
+    let mut flush = |msgid: &mut String, msgstr: &mut String| {
+        if !msgid.is_empty() && !msgstr.is_empty() {
+            catalog.insert(std::mem::take(msgid), std::mem::take(msgstr));
+        }
+        msgid.clear();
+        msgstr.clear();
+    };
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("msgid ") {
+            flush(&mut msgid, &mut msgstr);
+            msgid = parse_po_string(rest).unwrap_or_default();
+            field = Field::Id;
+        } else if let Some(rest) = line.strip_prefix("msgstr ") {
+            msgstr = parse_po_string(rest).unwrap_or_default();
+            field = Field::Str;
+        } else if let Some(s) = parse_po_string(line) {
+            match field {
+                Field::Id => msgid.push_str(&s),
+                Field::Str => msgstr.push_str(&s),
+                Field::None => {}
+            }
+        } else {
+            tracing::warn!("Ignoring invalid line in translation catalog: '{}'", line);
+        }
+    }
+    flush(&mut msgid, &mut msgstr);
+
+    catalog
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalog_parsing() {
+        let catalog = parse_catalog(
+            r#"
+# A comment.
+msgid "Home"
+msgstr "Accueil"
+
+msgid "Not translated"
+msgstr ""
+
+msgid "A message "
+"on two lines"
+msgstr "Un message "
+"sur deux \"lignes\""
+"#,
+        );
+        assert_eq!(catalog.get("Home").unwrap(), "Accueil");
+        assert!(catalog.get("Not translated").is_none());
+        assert_eq!(
+            catalog.get("A message on two lines").unwrap(),
+            "Un message sur deux \"lignes\""
+        );
+    }
+
+    #[test]
+    fn translation() {
+        assert_eq!(translate(Language::English, "Home"), "Home");
+        assert_eq!(translate(Language::French, "Home"), "Accueil");
+        assert_eq!(
+            translate(Language::French, "Some message without translation"),
+            "Some message without translation"
+        );
+        // All the entries of the catalogs must be valid.
+        for lang in Language::ALL {
+            if let Some(catalog) = lang.catalog() {
+                assert!(!catalog.is_empty());
+                for (msgid, msgstr) in catalog {
+                    for (i, _) in msgid.match_indices('{') {
+                        let name = &msgid[i..=i + msgid[i..].find('}').unwrap()];
+                        assert!(msgstr.contains(name), "{} in '{}'", name, msgstr);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn language_from_locale() {
+        assert_eq!(Language::from_locale("fr_FR.UTF-8"), Some(Language::French));
+        assert_eq!(Language::from_locale("en-GB"), Some(Language::English));
+        assert_eq!(Language::from_locale("de_DE"), None);
+        assert_eq!(Language::from_str("fr"), Ok(Language::French));
+    }
+}
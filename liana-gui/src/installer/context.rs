@@ -9,7 +9,10 @@ use crate::{
     signer::Signer,
 };
 use async_hwi::DeviceKind;
-use liana::{descriptors::LianaDescriptor, miniscript::bitcoin};
+use liana::{
+    descriptors::{LianaDescriptor, LintWarning},
+    miniscript::bitcoin,
+};
 use lianad::config::{BitcoinBackend, BitcoinConfig};
 
 #[derive(Debug, Clone)]
@@ -56,6 +59,8 @@ pub struct Context {
     pub bitcoin_backend: Option<BitcoinBackend>,
     pub descriptor_template: DescriptorTemplate,
     pub descriptor: Option<LianaDescriptor>,
+    /// Risky constructions in the descriptor, to be shown to the user before they back it up.
+    pub descriptor_warnings: Vec<LintWarning>,
     pub keys: Vec<KeySetting>,
     pub hws: Vec<(DeviceKind, bitcoin::bip32::Fingerprint, Option<[u8; 32]>)>,
     pub data_dir: PathBuf,
@@ -86,6 +91,7 @@ impl Context {
             keys: Vec::new(),
             bitcoin_backend: None,
             descriptor: None,
+            descriptor_warnings: Vec::new(),
             data_dir,
             network,
             hw_is_used: false,
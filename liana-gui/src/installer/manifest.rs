@@ -0,0 +1,393 @@
+//! Non-interactive installation of a wallet from a manifest.
+//!
+//! A manifest is a TOML or JSON file describing the wallet to install: its descriptor (or a
+//! template to build it from the participants' keys), the aliases of the keys, the connection
+//! to the Bitcoin backend and optionally the data directory. For instance:
+//!
+//! ```toml
+//! network = "signet"
+//!
+//! [template]
+//! primary = { keys = ["[f5acc2fd/48'/1'/0'/2']tpub..."] }
+//! recovery = [{ timelock = 52560, keys = ["[da2ee873/48'/1'/0'/2']tpub..."] }]
+//!
+//! [[keys]]
+//! name = "Alice"
+//! master_fingerprint = "f5acc2fd"
+//!
+//! [bitcoind_config]
+//! cookie_path = "/home/alice/.bitcoin/signet/.cookie"
+//! addr = "127.0.0.1:38332"
+//! ```
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use liana::{
+    descriptors::{LianaDescriptor, LianaPolicy, PathInfo},
+    miniscript::{
+        bitcoin::{bip32::Fingerprint, Network},
+        descriptor::DescriptorPublicKey,
+    },
+};
+use lianad::config::BitcoinBackend;
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    app::settings::KeySetting,
+    datadir::create_directory,
+    installer::{
+        context::{Context, RemoteBackend},
+        install_local_wallet,
+        step::descriptor::editor::key::new_multixkey_from_xpub,
+        Error,
+    },
+    signer::Signer,
+};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Manifest {
+    pub network: Network,
+    /// The data directory to install the wallet in. Defaults to the one of the GUI.
+    pub datadir: Option<PathBuf>,
+    /// The descriptor of the wallet. Exclusive with `template`.
+    #[serde(default, deserialize_with = "deserialize_descriptor")]
+    pub descriptor: Option<LianaDescriptor>,
+    /// The template to build the descriptor of the wallet from. Exclusive with `descriptor`.
+    pub template: Option<Template>,
+    /// Aliases of the keys of the descriptor.
+    #[serde(default)]
+    pub keys: Vec<KeySetting>,
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+    #[serde(flatten)]
+    pub bitcoin_backend: BitcoinBackend,
+}
+
+/// The spending paths of the wallet. The keys must be extended public keys with their origin.
+/// Single-path keys are derived the same way as in the installer, using distinct derivation
+/// paths for each occurrence of the same key.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Template {
+    #[serde(default = "default_taproot")]
+    pub taproot: bool,
+    pub primary: TemplatePath,
+    pub recovery: Vec<TemplateRecoveryPath>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TemplatePath {
+    #[serde(default = "default_threshold")]
+    pub threshold: usize,
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub keys: Vec<DescriptorPublicKey>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TemplateRecoveryPath {
+    /// The relative timelock of the path, in blocks.
+    pub timelock: u16,
+    #[serde(flatten)]
+    pub path: TemplatePath,
+}
+
+fn default_poll_interval_secs() -> u64 {
+    30
+}
+
+fn default_taproot() -> bool {
+    true
+}
+
+fn default_threshold() -> usize {
+    1
+}
+
+fn deserialize_descriptor<'de, D>(deserializer: D) -> Result<Option<LianaDescriptor>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| LianaDescriptor::from_str(&s).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+fn deserialize_keys<'de, D>(deserializer: D) -> Result<Vec<DescriptorPublicKey>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|s| DescriptorPublicKey::from_str(s).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+impl Manifest {
+    pub fn from_file(path: &Path) -> Result<Self, ManifestError> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| ManifestError::ReadingFile(e.to_string()))?;
+        let is_json = path
+            .extension()
+            .map(|ext| ext.eq_ignore_ascii_case("json"))
+            .unwrap_or(false);
+        if is_json {
+            serde_json::from_str(&content).map_err(|e| ManifestError::Parsing(e.to_string()))
+        } else {
+            toml::from_str(&content).map_err(|e| ManifestError::Parsing(e.to_string()))
+        }
+    }
+
+    /// The descriptor of the wallet, either given as is or built from the template.
+    pub fn descriptor(&self) -> Result<LianaDescriptor, ManifestError> {
+        let descriptor = match (&self.descriptor, &self.template) {
+            (Some(descriptor), None) => descriptor.clone(),
+            (None, Some(template)) => template.descriptor()?,
+            (Some(_), Some(_)) => {
+                return Err(ManifestError::Descriptor(
+                    "'descriptor' and 'template' are mutually exclusive".to_string(),
+                ))
+            }
+            (None, None) => {
+                return Err(ManifestError::Descriptor(
+                    "one of 'descriptor' or 'template' must be set".to_string(),
+                ))
+            }
+        };
+        if !descriptor.all_xpubs_net_is(self.network) {
+            return Err(ManifestError::Descriptor(format!(
+                "all the keys must be for the {} network",
+                self.network
+            )));
+        }
+        Ok(descriptor)
+    }
+}
+
+impl Template {
+    pub fn descriptor(&self) -> Result<LianaDescriptor, ManifestError> {
+        let mut key_derivation_index = HashMap::<Fingerprint, usize>::new();
+        let primary_path = self.primary.path_info(&mut key_derivation_index)?;
+        let mut recovery_paths = BTreeMap::new();
+        for recovery in &self.recovery {
+            let path = recovery.path.path_info(&mut key_derivation_index)?;
+            if recovery_paths.insert(recovery.timelock, path).is_some() {
+                return Err(ManifestError::Descriptor(format!(
+                    "duplicate recovery path with timelock {}",
+                    recovery.timelock
+                )));
+            }
+        }
+        let policy = if self.taproot {
+            LianaPolicy::new(primary_path, recovery_paths)
+        } else {
+            LianaPolicy::new_legacy(primary_path, recovery_paths)
+        }
+        .map_err(|e| ManifestError::Descriptor(e.to_string()))?;
+        Ok(LianaDescriptor::new(policy))
+    }
+}
+
+impl TemplatePath {
+    fn path_info(
+        &self,
+        key_derivation_index: &mut HashMap<Fingerprint, usize>,
+    ) -> Result<PathInfo, ManifestError> {
+        let mut keys = Vec::with_capacity(self.keys.len());
+        for key in &self.keys {
+            keys.push(match key {
+                DescriptorPublicKey::XPub(xpub) if xpub.origin.is_some() => {
+                    let fingerprint = key.master_fingerprint();
+                    let derivation_index = key_derivation_index.entry(fingerprint).or_insert(0);
+                    let key = DescriptorPublicKey::MultiXPub(new_multixkey_from_xpub(
+                        xpub.clone(),
+                        *derivation_index,
+                    ));
+                    *derivation_index += 1;
+                    key
+                }
+                DescriptorPublicKey::MultiXPub(_) => key.clone(),
+                _ => {
+                    return Err(ManifestError::Descriptor(format!(
+                        "key '{}' must be an extended public key with its origin",
+                        key
+                    )))
+                }
+            });
+        }
+        match keys.len() {
+            0 => Err(ManifestError::Descriptor(
+                "a spending path must have at least one key".to_string(),
+            )),
+            1 => Ok(PathInfo::Single(keys.remove(0))),
+            _ => Ok(PathInfo::Multi(self.threshold, keys)),
+        }
+    }
+}
+
+/// Install the wallet described by the manifest in the given data directory and return the
+/// path of the created GUI configuration file.
+pub async fn install(manifest: Manifest, datadir: PathBuf) -> Result<PathBuf, ManifestError> {
+    let descriptor = manifest.descriptor()?;
+
+    let network_datadir = datadir.join(manifest.network.to_string());
+    if network_datadir.join("daemon.toml").exists() {
+        return Err(ManifestError::AlreadyInstalled(network_datadir));
+    }
+    create_directory(&datadir)
+        .map_err(|e| ManifestError::Install(Error::CannotCreateDatadir(e.to_string())))?;
+
+    let mut ctx = Context::new(manifest.network, datadir, RemoteBackend::None);
+    ctx.bitcoin_config.poll_interval_secs = Duration::from_secs(manifest.poll_interval_secs);
+    ctx.bitcoin_backend = Some(manifest.bitcoin_backend);
+    ctx.keys = manifest.keys;
+    ctx.descriptor = Some(descriptor);
+
+    // The descriptor cannot contain the key of a freshly generated signer, it will not be stored.
+    let signer = Signer::generate(manifest.network)
+        .map_err(|e| ManifestError::Install(Error::Unexpected(e.to_string())))?;
+
+    install_local_wallet(ctx, Arc::new(Mutex::new(signer)))
+        .await
+        .map_err(ManifestError::Install)
+}
+
+#[derive(Debug, Clone)]
+pub enum ManifestError {
+    ReadingFile(String),
+    Parsing(String),
+    Descriptor(String),
+    AlreadyInstalled(PathBuf),
+    Install(Error),
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ReadingFile(e) => write!(f, "Error while reading manifest: {}", e),
+            Self::Parsing(e) => write!(f, "Error while parsing manifest: {}", e),
+            Self::Descriptor(e) => write!(f, "Invalid wallet descriptor: {}", e),
+            Self::AlreadyInstalled(path) => {
+                write!(f, "A wallet is already installed in {}", path.display())
+            }
+            Self::Install(e) => write!(f, "Installation failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIMARY_KEY: &str = "[f5acc2fd/48'/1'/0'/2']tpubDFAqEGNyad35aBCKUAXbQGDjdVhNueno5ZZVEn3sQbW5ci457gLR7HyTmHBg93oourBssgUxuWz1jX5uhc1qaqFo9VsybY1J5FuedLfm4dK";
+    const RECOVERY_KEY: &str = "[da2ee873/48'/1'/0'/2']tpubDEbXY6RbN9mxAvQW797WxReGGkrdyRfdYcehVVaQQcQ3kyfhxSMcnU9qGpUVRHXXALvBtc99jcuxx5tkzcLaJbAukSNpP9h2ti4XFRosv1g";
+
+    #[test]
+    fn manifest_from_template() {
+        let manifest: Manifest = toml::from_str(&format!(
+            r#"
+network = "signet"
+
+[template]
+primary = {{ keys = ["{primary}"] }}
+recovery = [{{ timelock = 52560, keys = ["{recovery}"] }}]
+
+[[keys]]
+name = "Alice"
+master_fingerprint = "f5acc2fd"
+
+[bitcoind_config]
+cookie_path = "/home/alice/.bitcoin/signet/.cookie"
+addr = "127.0.0.1:38332"
+"#,
+            primary = PRIMARY_KEY,
+            recovery = RECOVERY_KEY
+        ))
+        .unwrap();
+        assert!(matches!(
+            manifest.bitcoin_backend,
+            BitcoinBackend::Bitcoind(_)
+        ));
+        assert_eq!(manifest.keys[0].name, "Alice");
+        let descriptor = manifest.descriptor().unwrap();
+        assert!(descriptor.is_taproot());
+        assert_eq!(descriptor.first_timelock_value(), 52560);
+        let desc_str = descriptor.to_string();
+        assert!(desc_str.contains(&format!("{}/<0;1>/*", PRIMARY_KEY)));
+        assert!(desc_str.contains(&format!("{}/<0;1>/*", RECOVERY_KEY)));
+
+        // The same key used in two paths gets distinct derivation paths.
+        let template = Template {
+            taproot: false,
+            primary: TemplatePath {
+                threshold: 1,
+                keys: vec![DescriptorPublicKey::from_str(PRIMARY_KEY).unwrap()],
+            },
+            recovery: vec![TemplateRecoveryPath {
+                timelock: 10,
+                path: TemplatePath {
+                    threshold: 1,
+                    keys: vec![DescriptorPublicKey::from_str(PRIMARY_KEY).unwrap()],
+                },
+            }],
+        };
+        let desc_str = template.descriptor().unwrap().to_string();
+        assert!(desc_str.starts_with("wsh("));
+        assert!(desc_str.contains(&format!("{}/<2;3>/*", PRIMARY_KEY)));
+    }
+
+    #[test]
+    fn manifest_from_descriptor() {
+        let descriptor = "wsh(or_d(pk([f5acc2fd/48'/1'/0'/2']tpubDFAqEGNyad35aBCKUAXbQGDjdVhNueno5ZZVEn3sQbW5ci457gLR7HyTmHBg93oourBssgUxuWz1jX5uhc1qaqFo9VsybY1J5FuedLfm4dK/<0;1>/*),and_v(v:pkh([da2ee873/48'/1'/0'/2']tpubDEbXY6RbN9mxAvQW797WxReGGkrdyRfdYcehVVaQQcQ3kyfhxSMcnU9qGpUVRHXXALvBtc99jcuxx5tkzcLaJbAukSNpP9h2ti4XFRosv1g/<0;1>/*),older(100))))";
+        let manifest: Manifest = serde_json::from_str(&format!(
+            r#"{{
+                "network": "testnet",
+                "datadir": "/tmp/liana",
+                "descriptor": "{}",
+                "electrum_config": {{ "addr": "ssl://electrum.blockstream.info:60002" }}
+            }}"#,
+            descriptor
+        ))
+        .unwrap();
+        assert_eq!(manifest.datadir, Some(PathBuf::from("/tmp/liana")));
+        assert!(matches!(
+            manifest.bitcoin_backend,
+            BitcoinBackend::Electrum(_)
+        ));
+        assert_eq!(manifest.descriptor().unwrap().first_timelock_value(), 100);
+
+        // Mainnet keys are refused on test networks and conversely.
+        let manifest = Manifest {
+            network: Network::Bitcoin,
+            ..manifest
+        };
+        assert!(matches!(
+            manifest.descriptor(),
+            Err(ManifestError::Descriptor(_))
+        ));
+
+        // Descriptor and template are mutually exclusive.
+        let manifest = Manifest {
+            network: Network::Testnet,
+            template: Some(Template {
+                taproot: true,
+                primary: TemplatePath {
+                    threshold: 1,
+                    keys: vec![DescriptorPublicKey::from_str(PRIMARY_KEY).unwrap()],
+                },
+                recovery: Vec::new(),
+            }),
+            ..manifest
+        };
+        assert!(matches!(
+            manifest.descriptor(),
+            Err(ManifestError::Descriptor(_))
+        ));
+    }
+}
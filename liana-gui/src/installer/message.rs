@@ -7,6 +7,7 @@ use std::path::PathBuf;
 use super::{context, Error};
 use crate::{
     download::{DownloadError, Progress},
+    file_drop::{DroppedFile, DroppedFileError},
     hw::HardwareWalletMessage,
     installer::step::descriptor::editor::key::Key,
     lianalite::client::{auth::AuthClient, backend::api},
@@ -44,6 +45,11 @@ pub enum Message {
     WalletRegistered(Result<(Fingerprint, Option<[u8; 32]>), Error>),
     MnemonicWord(usize, String),
     ImportMnemonic(bool),
+    ImportSlip39Shares(bool),
+    Slip39Share(usize, String),
+    AddSlip39Share,
+    FileDropped(Result<DroppedFile, DroppedFileError>),
+    DismissDroppedFile,
 }
 
 #[derive(Debug, Clone)]
@@ -144,10 +150,13 @@ pub enum DefineKey {
 pub enum ImportKeyModal {
     FetchedKey(Result<Key, Error>),
     XPubEdited(String),
+    AccountEdited(String),
     NameEdited(String),
     ManuallyImportXpub,
     ConfirmXpub,
     SelectKey(usize),
+    TapsignerCvcEdited(String),
+    ConfirmTapsignerCvc,
 }
 
 #[derive(Debug, Clone)]
@@ -1,10 +1,11 @@
 mod context;
+pub mod manifest;
 mod message;
 mod prompt;
 mod step;
 mod view;
 
-use iced::{clipboard, Subscription, Task};
+use iced::{Subscription, Task};
 use liana::miniscript::bitcoin::{self, Network};
 use liana_ui::{
     component::network_banner,
@@ -21,18 +22,25 @@ use std::sync::{Arc, Mutex};
 
 use crate::{
     app::{
-        config as gui_config, settings as gui_settings,
+        config as gui_config,
+        preferences::Preferences,
+        settings as gui_settings,
         settings::{AuthConfig, Settings, SettingsError, WalletSetting},
         wallet::wallet_name,
     },
+    clipboard,
     daemon::DaemonError,
     datadir::create_directory,
     hw::{HardwareWalletConfig, HardwareWallets},
+    i18n::tr_args,
+    keychain,
     lianalite::client::{
         auth::AuthError,
         backend::{BackendClient, BackendWalletClient},
     },
     signer::Signer,
+    tapsigner::TapsignerError,
+    update::UpdateCheckConfig,
 };
 
 pub use message::Message;
@@ -61,6 +69,8 @@ pub struct Installer {
 
     /// Context is data passed through each step.
     context: Context,
+    /// Why the last file dropped onto the window wasn't imported, if so.
+    dropped_file_error: Option<String>,
 }
 
 impl Installer {
@@ -125,7 +135,7 @@ impl Installer {
                     BackupDescriptor::default().into(),
                     RegisterDescriptor::new_create_wallet().into(),
                     ChooseBackend::new(network).into(),
-                    RemoteBackendLogin::new(network).into(),
+                    RemoteBackendLogin::new(network, &context.data_dir).into(),
                     SelectBitcoindTypeStep::new().into(),
                     InternalBitcoindStep::new(&context.data_dir).into(),
                     DefineNode::default().into(),
@@ -134,7 +144,7 @@ impl Installer {
                 UserFlow::ShareXpubs => vec![ShareXpubs::new(network, signer.clone()).into()],
                 UserFlow::AddWallet => vec![
                     ChooseBackend::new(network).into(),
-                    RemoteBackendLogin::new(network).into(),
+                    RemoteBackendLogin::new(network, &context.data_dir).into(),
                     ImportRemoteWallet::new(network).into(),
                     ImportDescriptor::new(network).into(),
                     RecoverMnemonic::default().into(),
@@ -147,6 +157,7 @@ impl Installer {
             },
             context,
             signer,
+            dropped_file_error: None,
         };
         // skip the step according to the current context.
         installer.skip_steps();
@@ -235,8 +246,40 @@ impl Installer {
                 }
             },
             Message::Clibpboard(s) => clipboard::write(s),
-            Message::Next => self.next(),
-            Message::Previous => self.previous(),
+            Message::Next => {
+                self.dropped_file_error = None;
+                self.next()
+            }
+            Message::Previous => {
+                self.dropped_file_error = None;
+                self.previous()
+            }
+            Message::FileDropped(res) => {
+                self.dropped_file_error = match res {
+                    Ok(file) => {
+                        let kind = file.kind();
+                        if self
+                            .steps
+                            .get_mut(self.current)
+                            .expect("There is always a step")
+                            .drop_file(file)
+                        {
+                            None
+                        } else {
+                            Some(tr_args(
+                                "This file can't be imported at this step, it contains a {kind}",
+                                &[("kind", &kind)],
+                            ))
+                        }
+                    }
+                    Err(e) => Some(e.to_string()),
+                };
+                Task::none()
+            }
+            Message::DismissDroppedFile => {
+                self.dropped_file_error = None;
+                Task::none()
+            }
             Message::Install => {
                 let _cmd = self
                     .steps
@@ -322,10 +365,18 @@ impl Installer {
                 self.context.remote_backend.user_email(),
             );
 
+        let mut banners = Vec::new();
         if self.network != Network::Bitcoin {
-            Column::with_children(vec![network_banner(self.network).into(), content]).into()
-        } else {
+            banners.push(network_banner(self.network).into());
+        }
+        if let Some(error) = &self.dropped_file_error {
+            banners.push(view::dropped_file_banner(error));
+        }
+        if banners.is_empty() {
             content
+        } else {
+            banners.push(content);
+            Column::with_children(banners).into()
         }
     }
 }
@@ -364,8 +415,15 @@ pub async fn install_local_wallet(
     create_directory(&network_datadir_path)
         .map_err(|e| Error::Unexpected(format!("Failed to create datadir path: {}", e)))?;
 
+    // The password of the bitcoind we manage is generated by us, keep it out of the file.
+    let mut file_cfg = cfg.clone();
+    if !ctx.bitcoind_is_external {
+        keychain::store_rpc_password(&keychain::secrets(&data_dir), &mut file_cfg)
+            .map_err(|e| Error::Unexpected(format!("Failed to store RPC password: {}", e)))?;
+    }
+
     // Step needed because of ValueAfterTable error in the toml serialize implementation.
-    let daemon_config = toml::Value::try_from(&cfg)
+    let daemon_config = toml::Value::try_from(&file_cfg)
         .map_err(|e| Error::Unexpected(format!("Failed to serialize daemon config: {}", e)))?;
 
     // create lianad configuration file
@@ -491,6 +549,9 @@ pub async fn create_remote_wallet(
             log_level: Some("info".to_string()),
             debug: Some(false),
             start_internal_bitcoind: false,
+            minimize_to_tray: false,
+            notifications: gui_config::NotificationsConfig::default(),
+            update_check: UpdateCheckConfig::default(),
         })
         .map_err(|e| Error::Unexpected(format!("Failed to serialize gui config: {}", e)))?
         .as_bytes(),
@@ -592,6 +653,9 @@ pub async fn import_remote_wallet(
             log_level: Some("info".to_string()),
             debug: Some(false),
             start_internal_bitcoind: false,
+            minimize_to_tray: false,
+            notifications: gui_config::NotificationsConfig::default(),
+            update_check: UpdateCheckConfig::default(),
         })
         .map_err(|e| Error::Unexpected(format!("Failed to serialize gui config: {}", e)))?
         .as_bytes(),
@@ -633,19 +697,38 @@ pub async fn extract_remote_gui_settings(ctx: &Context, backend: &BackendWalletC
 
     let auth = backend.inner_client().auth.read().await;
 
-    Settings {
-        wallets: vec![WalletSetting {
-            name: wallet_name(descriptor),
-            descriptor_checksum,
-            keys: Vec::new(),
-            hardware_wallets: Vec::new(),
-            remote_backend_auth: Some(AuthConfig {
-                email: backend.user_email().to_string(),
-                wallet_id: backend.wallet_id(),
-                refresh_token: auth.refresh_token.clone(),
-            }),
-        }],
+    let mut wallets = vec![WalletSetting {
+        name: wallet_name(descriptor),
+        descriptor_checksum,
+        keys: Vec::new(),
+        hardware_wallets: Vec::new(),
+        remote_backend_auth: Some(AuthConfig::new(
+            &ctx.data_dir,
+            ctx.network,
+            backend.user_email().to_string(),
+            backend.wallet_id(),
+            auth.refresh_token.clone(),
+        )),
+        last_balance: None,
+        accent_color: None,
+        spend_confirmation: None,
+        block_explorer: None,
+        remote_signer: None,
+        hide_amounts: false,
+        preferences: Preferences::default(),
+    }];
+    // Keep the wallets of the other accounts the user signed in with, so they can switch back to
+    // them.
+    if let Ok(settings) = Settings::from_file(ctx.data_dir.clone(), ctx.network) {
+        wallets.extend(settings.wallets.into_iter().filter(|w| {
+            w.remote_backend_auth
+                .as_ref()
+                .map(|auth| auth.email != backend.user_email())
+                .unwrap_or(false)
+        }));
     }
+
+    Settings { wallets }
 }
 
 pub async fn extract_local_gui_settings(ctx: &Context) -> Settings {
@@ -677,6 +760,13 @@ pub async fn extract_local_gui_settings(ctx: &Context) -> Settings {
             keys: ctx.keys.clone(),
             hardware_wallets,
             remote_backend_auth: None,
+            last_balance: None,
+            accent_color: None,
+            spend_confirmation: None,
+            block_explorer: None,
+            remote_signer: None,
+            hide_amounts: false,
+            preferences: Preferences::default(),
         }],
     }
 }
@@ -689,8 +779,18 @@ pub fn extract_daemon_config(ctx: &Context) -> Config {
             .clone()
             .expect("Context must have a descriptor at this point"),
         data_dir: Some(ctx.data_dir.clone()),
+        ephemeral: false,
+        webhook_url: None,
+        coins_snapshot: None,
+        shutdown_timeout_secs: std::time::Duration::from_secs(8),
+        spend_confirmation_threshold: None,
+        slow_operation_threshold_ms: None,
         bitcoin_config: ctx.bitcoin_config.clone(),
         bitcoin_backend: ctx.bitcoin_backend.clone(),
+        watchtower: None,
+        cross_check: None,
+        dashboard: None,
+        signer_server: None,
     }
 }
 
@@ -709,6 +809,7 @@ pub enum Error {
     CannotGetAvailablePort(String),
     Unexpected(String),
     HardwareWallet(async_hwi::Error),
+    Tapsigner(TapsignerError),
 }
 
 impl From<jsonrpc::simple_http::Error> for Error {
@@ -729,6 +830,12 @@ impl From<async_hwi::Error> for Error {
     }
 }
 
+impl From<TapsignerError> for Error {
+    fn from(error: TapsignerError) -> Self {
+        Error::Tapsigner(error)
+    }
+}
+
 impl From<DaemonError> for Error {
     fn from(value: DaemonError) -> Self {
         Self::Backend(Arc::new(value))
@@ -749,18 +856,20 @@ impl From<SettingsError> for Error {
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            Self::Auth(e) => write!(f, "Authentication error: {}", e),
-            Self::Backend(e) => write!(f, "Remote backend error: {}", e),
-            Self::Settings(e) => write!(f, "Settings file error: {}", e),
-            Self::Bitcoind(e) => write!(f, "Failed to ping bitcoind: {}", e),
-            Self::Electrum(e) => write!(f, "Failed to ping Electrum: {}", e),
-            Self::CannotCreateDatadir(e) => write!(f, "Failed to create datadir: {}", e),
-            Self::CannotGetAvailablePort(e) => write!(f, "Failed to get available port: {}", e),
-            Self::CannotWriteToFile(e) => write!(f, "Failed to write to file: {}", e),
-            Self::CannotCreateFile(e) => write!(f, "Failed to create file: {}", e),
-            Self::Unexpected(e) => write!(f, "Unexpected: {}", e),
-            Self::HardwareWallet(e) => write!(f, "Hardware Wallet: {}", e),
-        }
+        let (msg, e): (&'static str, &dyn std::fmt::Display) = match self {
+            Self::Auth(e) => ("Authentication error: {error}", e),
+            Self::Backend(e) => ("Remote backend error: {error}", e),
+            Self::Settings(e) => ("Settings file error: {error}", e),
+            Self::Bitcoind(e) => ("Failed to ping bitcoind: {error}", e),
+            Self::Electrum(e) => ("Failed to ping Electrum: {error}", e),
+            Self::CannotCreateDatadir(e) => ("Failed to create datadir: {error}", e),
+            Self::CannotGetAvailablePort(e) => ("Failed to get available port: {error}", e),
+            Self::CannotWriteToFile(e) => ("Failed to write to file: {error}", e),
+            Self::CannotCreateFile(e) => ("Failed to create file: {error}", e),
+            Self::Unexpected(e) => ("Unexpected: {error}", e),
+            Self::HardwareWallet(e) => ("Hardware Wallet: {error}", e),
+            Self::Tapsigner(e) => ("Tapsigner: {error}", e),
+        };
+        write!(f, "{}", tr_args(msg, &[("error", e)]))
     }
 }
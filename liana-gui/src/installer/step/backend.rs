@@ -1,4 +1,7 @@
-use std::str::FromStr;
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use iced::Task;
 
@@ -6,6 +9,7 @@ use liana::{descriptors::LianaDescriptor, miniscript::bitcoin::Network};
 use liana_ui::{component::form, widget::Element};
 
 use crate::{
+    app::settings::global::RemoteBackendSettings,
     daemon::DaemonError,
     hw::HardwareWallets,
     installer::{
@@ -97,6 +101,7 @@ pub enum ConnectionStep {
 
 pub struct RemoteBackendLogin {
     network: Network,
+    datadir: PathBuf,
     processing: bool,
     step: ConnectionStep,
     connection_error: Option<Error>,
@@ -104,9 +109,10 @@ pub struct RemoteBackendLogin {
 }
 
 impl RemoteBackendLogin {
-    pub fn new(network: Network) -> Self {
+    pub fn new(network: Network, datadir: &Path) -> Self {
         Self {
             network,
+            datadir: datadir.to_path_buf(),
             step: ConnectionStep::EnterEmail {
                 email: form::Value::default(),
             },
@@ -146,13 +152,17 @@ impl Step for RemoteBackendLogin {
                     } else if email.valid {
                         let email = email.value.clone();
                         let network = self.network;
+                        let datadir = self.datadir.clone();
                         self.processing = true;
                         self.connection_error = None;
                         self.auth_error = None;
                         return Task::perform(
                             async move {
-                                let config =
-                                    client::get_service_config(network).await.map_err(|e| {
+                                let backend_settings = RemoteBackendSettings::from_file(&datadir)
+                                    .map_err(Error::Unexpected)?;
+                                let config = client::get_service_config(network, &backend_settings)
+                                    .await
+                                    .map_err(|e| {
                                         if e.status() == Some(reqwest::StatusCode::NOT_FOUND) {
                                             Error::Unexpected(
                                                 "Remote servers are unresponsive".to_string(),
@@ -162,6 +172,7 @@ impl Step for RemoteBackendLogin {
                                         }
                                     })?;
                                 let client = AuthClient::new(
+                                    config.http,
                                     config.auth_api_url,
                                     config.auth_api_public_key,
                                     email,
@@ -6,7 +6,7 @@ use iced::{Subscription, Task};
 use liana::miniscript::bitcoin::bip32::Xpub;
 use liana::miniscript::{
     bitcoin::{
-        bip32::{DerivationPath, Fingerprint},
+        bip32::{ChildNumber, DerivationPath, Fingerprint},
         Network,
     },
     descriptor::{DerivPaths, DescriptorMultiXKey, DescriptorPublicKey, DescriptorXKey, Wildcard},
@@ -23,6 +23,7 @@ use crate::{
         view, Error,
     },
     signer::Signer,
+    tapsigner::{self, Tapsigner},
 };
 
 pub fn new_multixkey_from_xpub(
@@ -41,6 +42,10 @@ pub fn new_multixkey_from_xpub(
     }
 }
 
+/// Identifies a key among the keys of the descriptor being edited: a signing device may hold keys
+/// for several accounts.
+pub type KeyId = (Fingerprint, DerivationPath);
+
 #[derive(Debug, Clone)]
 pub struct Key {
     pub device_kind: Option<DeviceKind>,
@@ -52,6 +57,14 @@ pub struct Key {
     pub is_compatible_taproot: bool,
 }
 
+impl Key {
+    pub fn id(&self) -> KeyId {
+        key_origin(&self.key)
+            .cloned()
+            .unwrap_or_else(|| (self.fingerprint, DerivationPath::master()))
+    }
+}
+
 pub fn check_key_network(key: &DescriptorPublicKey, network: Network) -> bool {
     match key {
         DescriptorPublicKey::XPub(key) => {
@@ -72,6 +85,28 @@ pub fn check_key_network(key: &DescriptorPublicKey, network: Network) -> bool {
     }
 }
 
+/// The origin of the key, if it is an extended key.
+pub fn key_origin(key: &DescriptorPublicKey) -> Option<&(Fingerprint, DerivationPath)> {
+    match key {
+        DescriptorPublicKey::XPub(key) => key.origin.as_ref(),
+        DescriptorPublicKey::MultiXPub(key) => key.origin.as_ref(),
+        DescriptorPublicKey::Single(key) => key.origin.as_ref(),
+    }
+}
+
+/// The account of a key derived at the standard path `m/48'/coin'/account'/2'`, if it is.
+pub fn origin_account(path: &DerivationPath) -> Option<u32> {
+    match *path.as_ref() {
+        [purpose, _, ChildNumber::Hardened { index }, script_type]
+            if purpose == ChildNumber::Hardened { index: 48 }
+                && script_type == ChildNumber::Hardened { index: 2 } =>
+        {
+            Some(index)
+        }
+        _ => None,
+    }
+}
+
 pub struct EditXpubModal {
     device_must_support_tapminiscript: bool,
     keys_coordinate: Vec<(usize, usize)>,
@@ -81,6 +116,7 @@ pub struct EditXpubModal {
 
     form_name: form::Value<String>,
     form_xpub: form::Value<String>,
+    form_account: form::Value<String>,
     manually_imported_xpub: bool,
 
     other_path_keys: HashSet<Fingerprint>,
@@ -90,6 +126,8 @@ pub struct EditXpubModal {
     hot_signer: Arc<Mutex<Signer>>,
     hot_signer_fingerprint: Fingerprint,
     chosen_signer: Option<Key>,
+    /// The id of the Tapsigner to import the key of, with its CVC.
+    tapsigner: Option<(String, form::Value<String>)>,
 }
 
 impl EditXpubModal {
@@ -109,6 +147,11 @@ impl EditXpubModal {
             .as_ref()
             .map(|k| !k.is_hot_signer && k.device_kind.is_none())
             .unwrap_or(false);
+        let account = key
+            .as_ref()
+            .and_then(|k| key_origin(&k.key))
+            .and_then(|(_, path)| origin_account(path))
+            .unwrap_or(0);
         Self {
             device_must_support_tapminiscript,
             other_path_keys,
@@ -124,6 +167,10 @@ impl EditXpubModal {
                     String::new()
                 },
             },
+            form_account: form::Value {
+                valid: true,
+                value: account.to_string(),
+            },
             manually_imported_xpub,
             keys,
             keys_coordinate,
@@ -134,12 +181,56 @@ impl EditXpubModal {
             hot_signer_fingerprint,
             hot_signer,
             duplicate_master_fg: false,
+            tapsigner: None,
         }
     }
 
     pub fn load(&self) -> Task<Message> {
         Task::none()
     }
+
+    /// The account the keys are fetched from the signing devices for, if the one entered is valid.
+    fn account(&self) -> Option<ChildNumber> {
+        u32::from_str(&self.form_account.value)
+            .ok()
+            .and_then(|index| ChildNumber::from_hardened_idx(index).ok())
+    }
+
+    /// An already added key of the same signing device as the chosen one, for another account.
+    /// Using both in the same wallet is allowed but reduces its security.
+    fn same_device_key(&self) -> Option<&Key> {
+        let chosen = self.chosen_signer.as_ref()?;
+        self.keys
+            .iter()
+            .find(|k| k.fingerprint == chosen.fingerprint && k.id() != chosen.id())
+    }
+
+    /// Whether the chosen key has the same origin as an already added key but a different
+    /// extended key. One of them has to be wrong.
+    fn duplicate_origin(&self) -> bool {
+        self.chosen_signer
+            .as_ref()
+            .map(|chosen| {
+                self.keys
+                    .iter()
+                    .any(|k| k.id() == chosen.id() && k.key != chosen.key)
+            })
+            .unwrap_or(false)
+    }
+
+    /// The name of the already added key with the same master fingerprint, if any.
+    fn known_name(&self, fingerprint: Fingerprint) -> String {
+        self.keys
+            .iter()
+            .find_map(|k| {
+                if k.fingerprint == fingerprint {
+                    Some(k.name.clone())
+                } else {
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
 }
 
 impl super::DescriptorEditModal for EditXpubModal {
@@ -154,7 +245,11 @@ impl super::DescriptorEditModal for EditXpubModal {
         self.error = None;
         match message {
             Message::Select(i) => {
-                if let Some(HardwareWallet::Supported {
+                if let Some(HardwareWallet::Tapsigner { id, .. }) = hws.list.get(i) {
+                    if !self.device_must_support_tapminiscript {
+                        self.tapsigner = Some((id.clone(), form::Value::default()));
+                    }
+                } else if let Some(HardwareWallet::Supported {
                     device,
                     fingerprint,
                     kind,
@@ -162,13 +257,19 @@ impl super::DescriptorEditModal for EditXpubModal {
                     ..
                 }) = hws.list.get(i)
                 {
+                    let account = match self.account() {
+                        Some(account) => account,
+                        None => return Task::none(),
+                    };
                     self.processing = true;
                     self.manually_imported_xpub = false;
+                    self.tapsigner = None;
                     let device_version = version.clone();
                     let fingerprint = *fingerprint;
                     let device_kind = *kind;
                     let device_cloned = device.clone();
                     let network = self.network;
+                    let derivation_path = account_derivation_path(network, account);
                     return Task::perform(
                         async move {
                             (
@@ -176,7 +277,8 @@ impl super::DescriptorEditModal for EditXpubModal {
                                 device_kind,
                                 fingerprint,
                                 network,
-                                get_extended_pubkey(device_cloned, fingerprint, network).await,
+                                get_extended_pubkey(device_cloned, fingerprint, derivation_path)
+                                    .await,
                             )
                         },
                         |(device_version, device_kind, fingerprint, network, res)| {
@@ -215,9 +317,14 @@ impl super::DescriptorEditModal for EditXpubModal {
                 return self.load();
             }
             Message::UseHotSigner => {
+                let account = match self.account() {
+                    Some(account) => account,
+                    None => return Task::none(),
+                };
                 self.manually_imported_xpub = false;
+                self.tapsigner = None;
                 let fingerprint = self.hot_signer.lock().unwrap().fingerprint();
-                let derivation_path = default_derivation_path(self.network);
+                let derivation_path = account_derivation_path(self.network, account);
                 let key_str = format!(
                     "[{}/{}]{}",
                     fingerprint,
@@ -236,17 +343,7 @@ impl super::DescriptorEditModal for EditXpubModal {
                     device_kind: None,
                     device_version: None,
                 });
-                self.form_name.value = self
-                    .keys
-                    .iter()
-                    .find_map(|k| {
-                        if k.fingerprint == fingerprint {
-                            Some(k.name.clone())
-                        } else {
-                            None
-                        }
-                    })
-                    .unwrap_or_default();
+                self.form_name.value = self.known_name(fingerprint);
                 self.form_name.valid = true;
             }
             Message::DefineDescriptor(message::DefineDescriptor::KeyModal(msg)) => match msg {
@@ -254,8 +351,11 @@ impl super::DescriptorEditModal for EditXpubModal {
                     self.processing = false;
                     match res {
                         Ok(key) => {
+                            if let Some((id, _)) = self.tapsigner.take() {
+                                hws.set_tapsigner_fingerprint(&id, key.fingerprint);
+                            }
                             self.form_name.valid = true;
-                            self.form_name.value.clone_from(&key.name);
+                            self.form_name.value = self.known_name(key.fingerprint);
                             self.chosen_signer = Some(key);
                         }
                         Err(e) => {
@@ -266,9 +366,18 @@ impl super::DescriptorEditModal for EditXpubModal {
                 }
                 message::ImportKeyModal::ManuallyImportXpub => {
                     self.chosen_signer = None;
+                    self.tapsigner = None;
                     self.manually_imported_xpub = true;
                     self.form_xpub = form::Value::default();
                 }
+                message::ImportKeyModal::AccountEdited(account) => {
+                    self.form_account.value = account;
+                    self.form_account.valid = self.account().is_some();
+                    // The key of a signing device must be fetched again for the new account.
+                    if !self.manually_imported_xpub {
+                        self.chosen_signer = None;
+                    }
+                }
                 message::ImportKeyModal::NameEdited(name) => {
                     self.form_name.valid = !self.keys.iter().any(|k| {
                         Some(&k.fingerprint) != self.chosen_signer.as_ref().map(|s| &s.fingerprint)
@@ -297,7 +406,7 @@ impl super::DescriptorEditModal for EditXpubModal {
                                     device_kind: None,
                                     device_version: None,
                                 });
-                                self.form_name.value = "".to_string();
+                                self.form_name.value = self.known_name(fingerprint);
                                 self.form_name.valid = true;
                             }
                         } else {
@@ -313,7 +422,7 @@ impl super::DescriptorEditModal for EditXpubModal {
                         key.name.clone_from(&self.form_name.value);
                         if self.other_path_keys.contains(&key.fingerprint) {
                             self.duplicate_master_fg = true;
-                        } else {
+                        } else if !self.duplicate_origin() {
                             let coordinate = self.keys_coordinate.clone();
                             return Task::perform(
                                 async move { (coordinate, key) },
@@ -332,6 +441,74 @@ impl super::DescriptorEditModal for EditXpubModal {
                         self.form_name.valid = true;
                     }
                 }
+                message::ImportKeyModal::TapsignerCvcEdited(cvc) => {
+                    if let Some((_, form_cvc)) = &mut self.tapsigner {
+                        form_cvc.value = cvc;
+                        form_cvc.valid = true;
+                    }
+                }
+                message::ImportKeyModal::ConfirmTapsignerCvc => {
+                    if self.processing {
+                        return Task::none();
+                    }
+                    let account = match self.account() {
+                        Some(account) => account,
+                        None => return Task::none(),
+                    };
+                    let (id, form_cvc) = match &mut self.tapsigner {
+                        Some(tapsigner) => tapsigner,
+                        None => return Task::none(),
+                    };
+                    if tapsigner::check_cvc(&form_cvc.value).is_err() {
+                        form_cvc.valid = false;
+                        return Task::none();
+                    }
+                    let device = match hws.list.iter().find_map(|hw| match hw {
+                        HardwareWallet::Tapsigner {
+                            id: hw_id, device, ..
+                        } if hw_id == id => Some(device.clone()),
+                        _ => None,
+                    }) {
+                        Some(device) => device,
+                        // The card was removed from the reader.
+                        None => {
+                            self.tapsigner = None;
+                            return Task::none();
+                        }
+                    };
+                    self.processing = true;
+                    self.manually_imported_xpub = false;
+                    let cvc = form_cvc.value.clone();
+                    let network = self.network;
+                    let derivation_path = account_derivation_path(network, account);
+                    return Task::perform(
+                        get_tapsigner_extended_pubkey(device, cvc, derivation_path),
+                        move |res| {
+                            Message::DefineDescriptor(message::DefineDescriptor::KeyModal(
+                                message::ImportKeyModal::FetchedKey(res.and_then(
+                                    |(fingerprint, key)| {
+                                        if check_key_network(&key, network) {
+                                            Ok(Key {
+                                                is_hot_signer: false,
+                                                fingerprint,
+                                                name: "".to_string(),
+                                                key,
+                                                is_compatible_taproot: false,
+                                                device_kind: None,
+                                                device_version: None,
+                                            })
+                                        } else {
+                                            Err(Error::Unexpected(
+                                                "Fetched key does not have the correct network"
+                                                    .to_string(),
+                                            ))
+                                        }
+                                    },
+                                )),
+                            ))
+                        },
+                    );
+                }
             },
             _ => {}
         };
@@ -347,33 +524,32 @@ impl super::DescriptorEditModal for EditXpubModal {
         view::editor::edit_key_modal(
             "Set your key",
             self.network,
+            // The connected devices are listed even if their key was already added, so that it can
+            // be fetched again for another account.
             hws.list
                 .iter()
                 .enumerate()
-                .filter_map(|(i, hw)| {
-                    if self
-                        .keys
-                        .iter()
-                        .any(|k| Some(k.fingerprint) == hw.fingerprint())
-                    {
-                        None
-                    } else {
-                        Some(view::hw_list_view(
-                            i,
-                            hw,
-                            hw.fingerprint() == chosen_signer,
-                            self.processing,
-                            hw.fingerprint() == chosen_signer,
-                            self.device_must_support_tapminiscript,
-                        ))
-                    }
+                .map(|(i, hw)| {
+                    view::hw_list_view(
+                        i,
+                        hw,
+                        hw.fingerprint() == chosen_signer,
+                        self.processing,
+                        hw.fingerprint() == chosen_signer,
+                        self.device_must_support_tapminiscript,
+                    )
                 })
                 .collect(),
             self.keys
                 .iter()
                 .enumerate()
                 .filter_map(|(i, key)| {
-                    if key.fingerprint == self.hot_signer_fingerprint {
+                    if key.fingerprint == self.hot_signer_fingerprint
+                        || hws
+                            .list
+                            .iter()
+                            .any(|hw| hw.fingerprint() == Some(key.fingerprint))
+                    {
                         None
                     } else {
                         Some(view::key_list_view(
@@ -400,33 +576,41 @@ impl super::DescriptorEditModal for EditXpubModal {
             }),
             &self.form_name,
             &self.form_xpub,
+            &self.form_account,
+            self.chosen_signer
+                .as_ref()
+                .and_then(|s| key_origin(&s.key))
+                .map(|(fg, path)| format!("[{}/{}]", fg, path)),
+            self.same_device_key().map(|k| k.name.as_str()),
+            self.tapsigner.as_ref().map(|(_, cvc)| cvc),
             self.manually_imported_xpub,
             self.duplicate_master_fg,
+            self.duplicate_origin(),
         )
     }
 }
 
 pub fn default_derivation_path(network: Network) -> DerivationPath {
-    // Note that "m" is ignored when parsing string and could be removed:
-    // https://github.com/rust-bitcoin/rust-bitcoin/pull/2677
-    DerivationPath::from_str({
-        if network == Network::Bitcoin {
-            "m/48'/0'/0'/2'"
-        } else {
-            "m/48'/1'/0'/2'"
-        }
-    })
-    .unwrap()
+    account_derivation_path(network, ChildNumber::Hardened { index: 0 })
+}
+
+/// LIANA_STANDARD_PATH: m/48'/0'/account'/2';
+/// LIANA_TESTNET_STANDARD_PATH: m/48'/1'/account'/2';
+pub fn account_derivation_path(network: Network, account: ChildNumber) -> DerivationPath {
+    let coin = if network == Network::Bitcoin { 0 } else { 1 };
+    DerivationPath::from(vec![
+        ChildNumber::Hardened { index: 48 },
+        ChildNumber::Hardened { index: coin },
+        account,
+        ChildNumber::Hardened { index: 2 },
+    ])
 }
 
-/// LIANA_STANDARD_PATH: m/48'/0'/0'/2';
-/// LIANA_TESTNET_STANDARD_PATH: m/48'/1'/0'/2';
 pub async fn get_extended_pubkey(
     hw: std::sync::Arc<dyn async_hwi::HWI + Send + Sync>,
     fingerprint: Fingerprint,
-    network: Network,
+    derivation_path: DerivationPath,
 ) -> Result<DescriptorPublicKey, Error> {
-    let derivation_path = default_derivation_path(network);
     let xkey = hw
         .get_extended_pubkey(&derivation_path)
         .await
@@ -439,6 +623,28 @@ pub async fn get_extended_pubkey(
     }))
 }
 
+/// Set up the Tapsigner if it was never used, which picks its key, and get its extended public
+/// key at this derivation path.
+pub async fn get_tapsigner_extended_pubkey(
+    device: Arc<Tapsigner>,
+    cvc: String,
+    derivation_path: DerivationPath,
+) -> Result<(Fingerprint, DescriptorPublicKey), Error> {
+    if !device.is_set_up() {
+        device.set_up(&cvc)?;
+    }
+    let (fingerprint, xkey) = device.get_extended_pubkey(&cvc, &derivation_path)?;
+    Ok((
+        fingerprint,
+        DescriptorPublicKey::XPub(DescriptorXKey {
+            origin: Some((fingerprint, derivation_path)),
+            derivation_path: DerivationPath::master(),
+            wildcard: Wildcard::None,
+            xkey,
+        }),
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -462,4 +668,24 @@ mod tests {
             "48'/1'/0'/2'"
         );
     }
+
+    #[test]
+    fn test_account_derivation_path() {
+        let account = ChildNumber::from_hardened_idx(3).unwrap();
+        let path = account_derivation_path(Network::Bitcoin, account);
+        assert_eq!(path.to_string(), "48'/0'/3'/2'");
+        assert_eq!(origin_account(&path), Some(3));
+        assert_eq!(
+            origin_account(&account_derivation_path(Network::Signet, account)),
+            Some(3)
+        );
+        assert_eq!(
+            origin_account(&default_derivation_path(Network::Bitcoin)),
+            Some(0)
+        );
+        assert_eq!(
+            origin_account(&DerivationPath::from_str("m/84'/0'/3'").unwrap()),
+            None
+        );
+    }
 }
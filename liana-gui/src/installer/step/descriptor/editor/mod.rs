@@ -8,7 +8,9 @@ use std::sync::{Arc, Mutex};
 
 use iced::{Subscription, Task};
 use liana::{
-    descriptors::{LianaDescriptor, LianaPolicy, PathInfo},
+    descriptors::{
+        lint_descriptor, LianaDescriptor, LianaPolicy, LintOptions, LintWarning, PathInfo,
+    },
     miniscript::{
         bitcoin::{bip32::Fingerprint, Network},
         descriptor::DescriptorPublicKey,
@@ -27,12 +29,15 @@ use crate::{
     installer::{
         message::{self, Message},
         step::{Context, Step},
-        view,
+        view::{
+            self,
+            graph::{GraphKey, GraphPath, PolicyGraph},
+        },
     },
     signer::Signer,
 };
 
-use key::{new_multixkey_from_xpub, EditXpubModal, Key};
+use key::{new_multixkey_from_xpub, EditXpubModal, Key, KeyId};
 
 pub trait DescriptorEditModal {
     fn processing(&self) -> bool {
@@ -48,7 +53,7 @@ pub trait DescriptorEditModal {
 }
 
 pub struct Path {
-    keys: Vec<Option<Fingerprint>>,
+    keys: Vec<Option<KeyId>>,
     threshold: usize,
     // sequence is 0 if it is a primary path.
     sequence: u16,
@@ -104,7 +109,7 @@ pub struct DefineDescriptor {
     signer: Arc<Mutex<Signer>>,
     signer_fingerprint: Fingerprint,
 
-    keys: HashMap<Fingerprint, Key>,
+    keys: HashMap<KeyId, Key>,
     paths: Vec<Path>,
     descriptor_template: DescriptorTemplate,
 
@@ -162,7 +167,7 @@ impl DefineDescriptor {
             !path.valid()
                 || (self.use_taproot
                     && path.keys.iter().any(|k| {
-                        if let Some(k) = k.and_then(|k| self.keys.get(&k)) {
+                        if let Some(k) = k.as_ref().and_then(|k| self.keys.get(k)) {
                             !k.is_compatible_taproot
                         } else {
                             false
@@ -171,6 +176,75 @@ impl DefineDescriptor {
         }) && self.paths.len() >= 2
     }
 
+    // The graph of the policy being edited, with the keys which aren't defined yet.
+    fn policy_graph(&self) -> PolicyGraph {
+        let primary_fingerprints: HashSet<Fingerprint> = self
+            .paths
+            .first()
+            .map(|path| path.keys.iter().flatten().map(|(fg, _)| *fg).collect())
+            .unwrap_or_default();
+        let paths = self
+            .paths
+            .iter()
+            .enumerate()
+            .map(|(i, path)| {
+                let timelock = if i == 0 { None } else { Some(path.sequence) };
+                let keys = self.path_keys(path);
+                let single_key = keys.len() == 1;
+                GraphPath {
+                    timelock,
+                    threshold: path.threshold,
+                    keys: keys
+                        .into_iter()
+                        .map(|key| match key {
+                            Some(key) => GraphKey {
+                                name: Some(if key.name.is_empty() {
+                                    format!("[{}]", key.fingerprint)
+                                } else {
+                                    key.name.clone()
+                                }),
+                                fingerprint: Some(key.fingerprint),
+                                error: if self.use_taproot && !key.is_compatible_taproot {
+                                    Some("This device does not support Taproot".to_string())
+                                } else {
+                                    None
+                                },
+                                warnings: timelock
+                                    .map(|timelock| {
+                                        let hot_signer = (single_key && key.is_hot_signer)
+                                            .then_some(LintWarning::HotSignerRecovery {
+                                                timelock,
+                                                fingerprint: key.fingerprint,
+                                            });
+                                        let shared_signer = primary_fingerprints
+                                            .contains(&key.fingerprint)
+                                            .then_some(LintWarning::SharedSigner {
+                                                timelock,
+                                                fingerprint: key.fingerprint,
+                                            });
+                                        hot_signer
+                                            .into_iter()
+                                            .chain(shared_signer)
+                                            .map(|w| w.to_string())
+                                            .collect()
+                                    })
+                                    .unwrap_or_default(),
+                            },
+                            None => GraphKey::default(),
+                        })
+                        .collect(),
+                    timelock_error: if path.duplicate_sequence {
+                        Some("Another recovery path has the same timelock".to_string())
+                    } else {
+                        None
+                    },
+                    ..Default::default()
+                }
+            })
+            .collect();
+        PolicyGraph { paths }
+    }
+
     fn check_setup(&mut self) {
         self.check_for_duplicate();
     }
@@ -221,9 +295,15 @@ impl Step for DefineDescriptor {
             Message::DefineDescriptor(message::DefineDescriptor::KeysEdited(coordinate, key)) => {
                 hws.set_alias(key.fingerprint, key.name.clone());
                 for (i, j) in coordinate {
-                    self.paths[i].keys[j] = Some(key.fingerprint);
+                    self.paths[i].keys[j] = Some(key.id());
                 }
-                self.keys.insert(key.fingerprint, key);
+                // The name is the one of the signing device, shared by all its keys.
+                for k in self.keys.values_mut() {
+                    if k.fingerprint == key.fingerprint {
+                        k.name.clone_from(&key.name);
+                    }
+                }
+                self.keys.insert(key.id(), key);
                 self.modal = None;
                 self.check_setup();
             }
@@ -232,13 +312,13 @@ impl Step for DefineDescriptor {
                 let mut set = HashSet::<Fingerprint>::new();
                 let key = coordinate
                     .first()
-                    .and_then(|(i, j)| self.paths[*i].keys[*j])
-                    .and_then(|f| self.keys.get(&f))
+                    .and_then(|(i, j)| self.paths[*i].keys[*j].as_ref())
+                    .and_then(|id| self.keys.get(id))
                     .cloned();
                 for (i, j) in &coordinate {
                     set.extend(self.paths[*i].keys.iter().filter_map(|key| {
                         if key.is_some() && key != &self.paths[*i].keys[*j] {
-                            *key
+                            key.as_ref().map(|(fg, _)| *fg)
                         } else {
                             None
                         }
@@ -304,12 +384,15 @@ impl Step for DefineDescriptor {
                             use_taproot,
                             HashSet::from_iter(path.keys.iter().filter_map(|key| {
                                 if key.is_some() && key != &path.keys[j] {
-                                    *key
+                                    key.as_ref().map(|(fg, _)| *fg)
                                 } else {
                                     None
                                 }
                             })),
-                            path.keys[j].and_then(|f| self.keys.get(&f)).cloned(),
+                            path.keys[j]
+                                .as_ref()
+                                .and_then(|id| self.keys.get(id))
+                                .cloned(),
                             vec![(i, j)],
                             self.network,
                             self.signer.clone(),
@@ -367,13 +450,10 @@ impl Step for DefineDescriptor {
         ctx.keys = Vec::new();
         let mut hw_is_used = false;
         let mut spending_keys: Vec<DescriptorPublicKey> = Vec::new();
-        let mut key_derivation_index = HashMap::<Fingerprint, usize>::new();
+        let mut key_derivation_index = HashMap::<KeyId, usize>::new();
         for spending_key in self.paths[0].keys.iter().clone() {
-            let fingerprint = spending_key.expect("Must be present at this step");
-            let key = self
-                .keys
-                .get(&fingerprint)
-                .expect("Must be present at this step");
+            let id = spending_key.as_ref().expect("Must be present at this step");
+            let key = self.keys.get(id).expect("Must be present at this step");
             if let DescriptorPublicKey::XPub(xpub) = &key.key {
                 if let Some((master_fingerprint, _)) = xpub.origin {
                     ctx.keys.push(KeySetting {
@@ -384,12 +464,12 @@ impl Step for DefineDescriptor {
                         hw_is_used = true;
                     }
                 }
-                let derivation_index = key_derivation_index.get(&fingerprint).unwrap_or(&0);
+                let derivation_index = key_derivation_index.get(id).unwrap_or(&0);
                 spending_keys.push(DescriptorPublicKey::MultiXPub(new_multixkey_from_xpub(
                     xpub.clone(),
                     *derivation_index,
                 )));
-                key_derivation_index.insert(fingerprint, derivation_index + 1);
+                key_derivation_index.insert(id.clone(), derivation_index + 1);
             }
         }
 
@@ -398,11 +478,8 @@ impl Step for DefineDescriptor {
         for path in &self.paths[1..] {
             let mut recovery_keys: Vec<DescriptorPublicKey> = Vec::new();
             for recovery_key in path.keys.iter().clone() {
-                let fingerprint = recovery_key.expect("Must be present at this step");
-                let key = self
-                    .keys
-                    .get(&fingerprint)
-                    .expect("Must be present at this step");
+                let id = recovery_key.as_ref().expect("Must be present at this step");
+                let key = self.keys.get(id).expect("Must be present at this step");
                 if let DescriptorPublicKey::XPub(xpub) = &key.key {
                     if let Some((master_fingerprint, _)) = xpub.origin {
                         ctx.keys.push(KeySetting {
@@ -414,12 +491,12 @@ impl Step for DefineDescriptor {
                         }
                     }
 
-                    let derivation_index = key_derivation_index.get(&fingerprint).unwrap_or(&0);
+                    let derivation_index = key_derivation_index.get(id).unwrap_or(&0);
                     recovery_keys.push(DescriptorPublicKey::MultiXPub(new_multixkey_from_xpub(
                         xpub.clone(),
                         *derivation_index,
                     )));
-                    key_derivation_index.insert(fingerprint, derivation_index + 1);
+                    key_derivation_index.insert(id.clone(), derivation_index + 1);
                 }
             }
 
@@ -454,7 +531,20 @@ impl Step for DefineDescriptor {
             }
         };
 
-        ctx.descriptor = Some(LianaDescriptor::new(policy));
+        let descriptor = LianaDescriptor::new(policy);
+        let mut lint_options = LintOptions::default();
+        for key in self.keys.values() {
+            if let Some(kind) = key.device_kind {
+                lint_options
+                    .device_models
+                    .insert(key.fingerprint, kind.to_string());
+            }
+            if key.is_hot_signer {
+                lint_options.hot_signers.insert(key.fingerprint);
+            }
+        }
+        ctx.descriptor_warnings = lint_descriptor(&descriptor, &lint_options);
+        ctx.descriptor = Some(descriptor);
         ctx.hw_is_used = hw_is_used;
         true
     }
@@ -509,6 +599,7 @@ impl Step for DefineDescriptor {
                         keys: self.path_keys(p),
                     }),
                 self.paths.len().saturating_sub(1), // subtract 1 for primary path
+                self.policy_graph(),
                 self.valid(),
             ),
         };
@@ -870,4 +961,59 @@ mod tests {
             assert!(ctx.hw_is_used);
         });
     }
+
+    #[tokio::test]
+    async fn test_define_descriptor_same_device_distinct_accounts() {
+        let mut ctx = Context::new(
+            Network::Testnet,
+            PathBuf::from_str("/").unwrap(),
+            crate::installer::context::RemoteBackend::None,
+        );
+        let sandbox: Sandbox<DefineDescriptor> = Sandbox::new(DefineDescriptor::new(
+            Network::Testnet,
+            Arc::new(Mutex::new(Signer::generate(Network::Testnet).unwrap())),
+        ));
+        sandbox.load(&ctx).await;
+
+        let device_key = |key: &str| {
+            let key = DescriptorPublicKey::from_str(key).unwrap();
+            Key {
+                name: "My device".to_string(),
+                fingerprint: key.master_fingerprint(),
+                key,
+                device_kind: Some(async_hwi::DeviceKind::Specter),
+                device_version: None,
+                is_compatible_taproot: false,
+                is_hot_signer: false,
+            }
+        };
+        let (account_0, account_2) = (
+            device_key("[573fb35b/48'/1'/0'/2']tpubDFKp9T7WAYDcENSjoifkrpq1gMDF47KGJcJrpxzX23Qor8wuGbrEVs9utNq1MDS8E2WXJSBk1qoPQLpwyokW7DiUNPwFuxQkL7owNkLAb9W"),
+            device_key("[573fb35b/48'/1'/2'/2']tpubDDwxQauiaU964vPzt5Vd7jnDHEUtp2Vc34PaWpEXg5TQ3bRccxnc1MKKh88Hi7xiMeZo9Tm6fBcq4UGXqnDtGUniJLjqAD8SjQ8Eci3aSR7"),
+        );
+        sandbox
+            .update(Message::DefineDescriptor(
+                message::DefineDescriptor::KeysEdited(vec![(0, 0)], account_0.clone()),
+            ))
+            .await;
+        sandbox
+            .update(Message::DefineDescriptor(
+                message::DefineDescriptor::KeysEdited(vec![(1, 0)], account_2.clone()),
+            ))
+            .await;
+        sandbox.check(|step| {
+            // Both keys of the device are kept, each in its own path.
+            assert_eq!(step.keys.len(), 2);
+            assert_eq!(step.paths[0].keys[0], Some(account_0.id()));
+            assert_eq!(step.paths[1].keys[0], Some(account_2.id()));
+            assert!((step).apply(&mut ctx));
+            let desc = ctx.descriptor.as_ref().unwrap().to_string();
+            assert!(desc.contains("[573fb35b/48'/1'/0'/2']"));
+            assert!(desc.contains("[573fb35b/48'/1'/2'/2']"));
+            assert!(ctx
+                .descriptor_warnings
+                .iter()
+                .any(|w| matches!(w, LintWarning::SharedSigner { .. })));
+        });
+    }
 }
@@ -7,8 +7,13 @@ use std::{
 
 use iced::{Subscription, Task};
 use liana::{
-    descriptors::LianaDescriptor,
-    miniscript::bitcoin::{bip32::Fingerprint, Network},
+    descriptors::{
+        lint_descriptor, LianaDescriptor, LianaPolicy, LintOptions, LintWarning, PathInfo,
+    },
+    miniscript::{
+        bitcoin::{bip32::Fingerprint, Network},
+        descriptor::DescriptorPublicKey,
+    },
 };
 
 use liana_ui::{component::form, widget::Element};
@@ -17,11 +22,13 @@ use async_hwi::DeviceKind;
 
 use crate::{
     app::wallet::wallet_name,
+    file_drop::DroppedFile,
     hw::{HardwareWallet, HardwareWallets},
     installer::{
         message::{self, Message},
         step::{Context, Step},
-        view, Error,
+        view::{self, graph::PolicyGraph},
+        Error,
     },
 };
 
@@ -29,6 +36,10 @@ pub struct ImportDescriptor {
     network: Network,
     imported_descriptor: form::Value<String>,
     wrong_network: bool,
+    /// Why the imported descriptor can't be parsed, if so.
+    parse_error: Option<String>,
+    /// The graph of the imported descriptor's policy, kept in sync with it.
+    graph: Option<PolicyGraph>,
     error: Option<String>,
 }
 
@@ -38,29 +49,46 @@ impl ImportDescriptor {
             network,
             imported_descriptor: form::Value::default(),
             wrong_network: false,
+            parse_error: None,
+            graph: None,
             error: None,
         }
     }
 
     fn check_descriptor(&mut self, network: Network) -> Option<LianaDescriptor> {
+        self.graph = None;
+        self.parse_error = None;
         if !self.imported_descriptor.value.is_empty() {
-            if let Ok(desc) = LianaDescriptor::from_str(&self.imported_descriptor.value) {
-                if network == Network::Bitcoin {
+            match LianaDescriptor::from_str(&self.imported_descriptor.value) {
+                Ok(desc) => {
+                    let network = if network == Network::Bitcoin {
+                        network
+                    } else {
+                        Network::Testnet
+                    };
                     self.imported_descriptor.valid = desc.all_xpubs_net_is(network);
-                } else {
-                    self.imported_descriptor.valid = desc.all_xpubs_net_is(Network::Testnet);
+                    let policy = desc.policy();
+                    let graph = PolicyGraph::from_policy(&policy, &HashMap::new())
+                        .with_warnings(&lint_descriptor(&desc, &LintOptions::default()));
+                    if self.imported_descriptor.valid {
+                        self.graph = Some(graph);
+                        self.wrong_network = false;
+                        Some(desc)
+                    } else {
+                        self.graph = Some(graph.with_key_errors(
+                            &wrong_network_keys(&policy, network),
+                            "This key is for another network.",
+                        ));
+                        self.wrong_network = true;
+                        None
+                    }
                 }
-                if self.imported_descriptor.valid {
+                Err(e) => {
+                    self.parse_error = Some(e.to_string());
+                    self.imported_descriptor.valid = false;
                     self.wrong_network = false;
-                    Some(desc)
-                } else {
-                    self.wrong_network = true;
                     None
                 }
-            } else {
-                self.imported_descriptor.valid = false;
-                self.wrong_network = false;
-                None
             }
         } else {
             self.wrong_network = false;
@@ -70,6 +98,21 @@ impl ImportDescriptor {
     }
 }
 
+// The master fingerprints of the keys of the policy which aren't for this network.
+fn wrong_network_keys(policy: &LianaPolicy, network: Network) -> HashSet<Fingerprint> {
+    std::iter::once(policy.primary_path())
+        .chain(policy.recovery_paths().values())
+        .flat_map(|path| match path {
+            PathInfo::Single(key) => std::slice::from_ref(key),
+            PathInfo::Multi(_, keys) => keys.as_slice(),
+        })
+        .filter(|key| {
+            !matches!(key, DescriptorPublicKey::MultiXPub(xpub) if xpub.xkey.network == network.into())
+        })
+        .map(|key| key.master_fingerprint())
+        .collect()
+}
+
 impl Step for ImportDescriptor {
     // ImportRemoteWallet is used instead
     fn skip(&self, ctx: &Context) -> bool {
@@ -87,6 +130,16 @@ impl Step for ImportDescriptor {
         Task::none()
     }
 
+    fn drop_file(&mut self, file: DroppedFile) -> bool {
+        if let DroppedFile::Descriptor(desc) = file {
+            self.imported_descriptor.value = desc;
+            self.check_descriptor(self.network);
+            true
+        } else {
+            false
+        }
+    }
+
     fn apply(&mut self, ctx: &mut Context) -> bool {
         ctx.bitcoin_config.network = self.network;
         // Set to true in order to force the registration process to be shown to user.
@@ -111,6 +164,8 @@ impl Step for ImportDescriptor {
             email,
             &self.imported_descriptor,
             self.wrong_network,
+            self.parse_error.as_ref(),
+            self.graph.as_ref(),
             self.error.as_ref(),
         )
     }
@@ -205,13 +260,14 @@ impl Step for RegisterDescriptor {
                 self.chosen_hw = None;
                 match res {
                     Ok((fingerprint, hmac)) => {
-                        if let Some(hw_h) = hws
+                        if let Some(kind) = hws
                             .list
                             .iter()
-                            .find(|hw_h| hw_h.fingerprint() == Some(fingerprint))
+                            .filter(|hw_h| hw_h.fingerprint() == Some(fingerprint))
+                            .find_map(|hw_h| hw_h.kind())
                         {
                             self.registered.insert(fingerprint);
-                            self.hmacs.push((fingerprint, *hw_h.kind(), hmac));
+                            self.hmacs.push((fingerprint, *kind, hmac));
                         }
                     }
                     Err(e) => {
@@ -291,6 +347,7 @@ impl From<RegisterDescriptor> for Box<dyn Step> {
 pub struct BackupDescriptor {
     done: bool,
     descriptor: Option<LianaDescriptor>,
+    warnings: Vec<LintWarning>,
     key_aliases: HashMap<Fingerprint, String>,
 }
 
@@ -306,6 +363,7 @@ impl Step for BackupDescriptor {
             self.descriptor.clone_from(&ctx.descriptor);
             self.done = false;
         }
+        self.warnings.clone_from(&ctx.descriptor_warnings);
         self.key_aliases = ctx
             .keys
             .iter()
@@ -323,6 +381,7 @@ impl Step for BackupDescriptor {
             progress,
             email,
             self.descriptor.as_ref().expect("Must be a descriptor"),
+            &self.warnings,
             &self.key_aliases,
             self.done,
         )
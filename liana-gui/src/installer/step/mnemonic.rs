@@ -7,6 +7,7 @@ use liana::{bip39, signer::HotSigner};
 use liana_ui::widget::Element;
 
 use crate::{
+    file_drop::DroppedFile,
     hw::HardwareWallets,
     installer::{context::Context, message::Message, step::Step, view},
     signer::Signer,
@@ -69,6 +70,9 @@ pub struct RecoverMnemonic {
     error: Option<String>,
     skip: bool,
     recover: bool,
+    // Recover from a set of SLIP-39 shares instead of a BIP39 mnemonic.
+    slip39: bool,
+    shares: Vec<String>,
 }
 
 impl Default for RecoverMnemonic {
@@ -81,6 +85,8 @@ impl Default for RecoverMnemonic {
             error: None,
             skip: false,
             recover: false,
+            slip39: false,
+            shares: vec![String::new()],
         }
     }
 }
@@ -113,7 +119,23 @@ impl Step for RecoverMnemonic {
                     *word = value;
                 }
             }
-            Message::ImportMnemonic(recover) => self.recover = recover,
+            Message::ImportMnemonic(recover) => {
+                self.recover = recover;
+                self.slip39 = false;
+                self.error = None;
+            }
+            Message::ImportSlip39Shares(recover) => {
+                self.recover = recover;
+                self.slip39 = recover;
+                self.error = None;
+            }
+            Message::Slip39Share(index, value) => {
+                if let Some(share) = self.shares.get_mut(index) {
+                    *share = value;
+                    self.error = None;
+                }
+            }
+            Message::AddSlip39Share => self.shares.push(String::new()),
             Message::Skip => {
                 self.skip = true;
                 return Task::perform(async {}, |_| Message::Next);
@@ -130,13 +152,23 @@ impl Step for RecoverMnemonic {
             return true;
         }
 
-        let words: Vec<String> = self
-            .words
-            .iter()
-            .filter_map(|(s, valid)| if *valid { Some(s.clone()) } else { None })
-            .collect();
-
-        let seed = match HotSigner::from_str(ctx.bitcoin_config.network, &words.join(" ")) {
+        let seed = if self.slip39 {
+            let shares: Vec<&str> = self
+                .shares
+                .iter()
+                .map(|s| s.trim())
+                .filter(|s| !s.is_empty())
+                .collect();
+            HotSigner::from_slip39_shares(ctx.bitcoin_config.network, &shares)
+        } else {
+            let words: Vec<String> = self
+                .words
+                .iter()
+                .filter_map(|(s, valid)| if *valid { Some(s.clone()) } else { None })
+                .collect();
+            HotSigner::from_str(ctx.bitcoin_config.network, &words.join(" "))
+        };
+        let seed = match seed {
             Ok(seed) => seed,
             Err(e) => {
                 self.error = Some(e.to_string());
@@ -181,7 +213,39 @@ impl Step for RecoverMnemonic {
             self.current,
             &self.suggestions,
             self.recover,
+            self.slip39.then_some(&self.shares[..]),
             self.error.as_ref(),
         )
     }
+    fn drop_file(&mut self, file: DroppedFile) -> bool {
+        let DroppedFile::Mnemonic(mnemonic) = file else {
+            return false;
+        };
+        let lines: Vec<&str> = mnemonic
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .collect();
+        let words: Vec<String> = mnemonic.split_whitespace().map(str::to_lowercase).collect();
+        // Only 12-word BIP39 mnemonics can be entered, SLIP-39 shares have 20 or 33 words.
+        if lines.len() == 1 && words.len() == self.words.len() {
+            for ((word, valid), value) in self.words.iter_mut().zip(words) {
+                *valid = self
+                    .language
+                    .words_by_prefix(&value)
+                    .contains(&value.as_ref());
+                *word = value;
+            }
+            self.slip39 = false;
+        } else if lines.len() > 1 || words.len() == 20 || words.len() == 33 {
+            self.shares = lines.into_iter().map(str::to_string).collect();
+            self.slip39 = true;
+        } else {
+            return false;
+        }
+        self.recover = true;
+        self.suggestions = Vec::new();
+        self.error = None;
+        true
+    }
 }
@@ -27,6 +27,7 @@ use iced::{Subscription, Task};
 use liana_ui::widget::*;
 
 use crate::{
+    file_drop::DroppedFile,
     hw::HardwareWallets,
     installer::{context::Context, message::Message, view},
     node::bitcoind::Bitcoind,
@@ -58,6 +59,11 @@ pub trait Step {
     }
     fn revert(&self, _ctx: &mut Context) {}
     fn stop(&self) {}
+    /// Fill the step with a file dropped onto the window. Returns false if the step can't import
+    /// this kind of file.
+    fn drop_file(&mut self, _file: DroppedFile) -> bool {
+        false
+    }
 }
 
 pub struct Final {
@@ -1,17 +1,10 @@
-#[cfg(target_os = "windows")]
-use std::io::{self, Cursor};
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener};
 use std::path::PathBuf;
 use std::str::FromStr;
 
-use bitcoin_hashes::{sha256, Hash};
-#[cfg(any(target_os = "macos", target_os = "linux"))]
-use flate2::read::GzDecoder;
 use iced::{Subscription, Task};
 use liana::miniscript::bitcoin::Network;
 use lianad::config::{BitcoinBackend, BitcoindConfig, BitcoindRpcAuth};
-#[cfg(any(target_os = "macos", target_os = "linux"))]
-use tar::Archive;
 use tracing::info;
 
 use jsonrpc::{client::Client, simple_http::SimpleHttpTransport};
@@ -28,10 +21,10 @@ use crate::{
         view, Error,
     },
     node::bitcoind::{
-        self, bitcoind_network_dir, internal_bitcoind_datadir, internal_bitcoind_directory,
-        Bitcoind, ConfigField, InternalBitcoindConfig, InternalBitcoindConfigError,
-        InternalBitcoindNetworkConfig, RpcAuth, RpcAuthType, RpcAuthValues,
-        StartInternalBitcoindError, VERSION,
+        self, bitcoind_network_dir, install_bitcoind, internal_bitcoind_datadir,
+        internal_bitcoind_directory, Bitcoind, ConfigField, InstallBitcoindError,
+        InternalBitcoindConfig, InternalBitcoindConfigError, InternalBitcoindNetworkConfig,
+        RpcAuth, RpcAuthType, RpcAuthValues, StartInternalBitcoindError, VERSION,
     },
 };
 
@@ -111,98 +104,6 @@ pub enum InstallState {
     Errored(InstallBitcoindError),
 }
 
-/// Possible errors when installing bitcoind.
-#[derive(PartialEq, Eq, Debug, Clone)]
-pub enum InstallBitcoindError {
-    HashMismatch,
-    UnpackingError(String),
-}
-
-impl std::fmt::Display for InstallBitcoindError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            Self::HashMismatch => {
-                write!(f, "Hashes do not match.")
-            }
-            Self::UnpackingError(e) => {
-                write!(f, "Error unpacking: '{}'.", e)
-            }
-        }
-    }
-}
-
-// The functions below for unpacking the bitcoin download and verifying its hash are based on
-// https://github.com/RCasatta/bitcoind/blob/bada7ebb7197b89fd67e607f815ce1e43e76da7f/build.rs#L73.
-
-/// Unpack the downloaded bytes in the specified directory.
-fn unpack_bitcoind(install_dir: &PathBuf, bytes: &[u8]) -> Result<(), InstallBitcoindError> {
-    #[cfg(any(target_os = "macos", target_os = "linux"))]
-    {
-        let d = GzDecoder::new(bytes);
-
-        let mut archive = Archive::new(d);
-        for mut entry in archive
-            .entries()
-            .map_err(|e| InstallBitcoindError::UnpackingError(e.to_string()))?
-            .flatten()
-        {
-            if let Ok(file) = entry.path() {
-                if file.ends_with("bitcoind") {
-                    if let Err(e) = entry.unpack_in(install_dir) {
-                        return Err(InstallBitcoindError::UnpackingError(e.to_string()));
-                    }
-                }
-            }
-        }
-    }
-    #[cfg(target_os = "windows")]
-    {
-        let cursor = Cursor::new(bytes);
-        let mut archive = zip::ZipArchive::new(cursor)
-            .map_err(|e| InstallBitcoindError::UnpackingError(e.to_string()))?;
-        for i in 0..zip::ZipArchive::len(&archive) {
-            let mut file = archive
-                .by_index(i)
-                .map_err(|e| InstallBitcoindError::UnpackingError(e.to_string()))?;
-            let outpath = match file.enclosed_name() {
-                Some(path) => path.to_owned(),
-                None => continue,
-            };
-            if outpath.file_name().map(|s| s.to_str()) == Some(Some("bitcoind.exe")) {
-                let mut exe_path = PathBuf::from(install_dir);
-                for d in outpath.iter() {
-                    exe_path.push(d);
-                }
-                let parent = exe_path.parent().expect("bitcoind.exe should have parent.");
-                std::fs::create_dir_all(parent)
-                    .map_err(|e| InstallBitcoindError::UnpackingError(e.to_string()))?;
-                let mut outfile = std::fs::File::create(&exe_path)
-                    .map_err(|e| InstallBitcoindError::UnpackingError(e.to_string()))?;
-                io::copy(&mut file, &mut outfile)
-                    .map_err(|e| InstallBitcoindError::UnpackingError(e.to_string()))?;
-                break;
-            }
-        }
-    }
-    Ok(())
-}
-
-/// Verify the download hash against the expected value.
-fn verify_hash(bytes: &[u8]) -> bool {
-    let bytes_hash = sha256::Hash::hash(bytes);
-    info!("Download hash: '{}'.", bytes_hash);
-    let expected_hash = sha256::Hash::from_str(bitcoind::SHA256SUM).expect("This cannot fail.");
-    expected_hash == bytes_hash
-}
-
-/// Install bitcoind by verifying the download hash and unpacking in the specified directory.
-fn install_bitcoind(install_dir: &PathBuf, bytes: &[u8]) -> Result<(), InstallBitcoindError> {
-    if !verify_hash(bytes) {
-        return Err(InstallBitcoindError::HashMismatch);
-    };
-    unpack_bitcoind(install_dir, bytes)
-}
-
 /// RPC address for internal bitcoind.
 fn internal_bitcoind_address(rpc_port: u16) -> SocketAddr {
     SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), rpc_port)
@@ -795,14 +696,3 @@ impl Step for InternalBitcoindStep {
         ctx.bitcoind_is_external || ctx.remote_backend.is_some()
     }
 }
-
-#[cfg(test)]
-mod tests {
-    use super::verify_hash;
-
-    #[test]
-    fn hash() {
-        let bytes = "this is not bitcoin".as_bytes().to_vec();
-        assert!(!verify_hash(&bytes));
-    }
-}
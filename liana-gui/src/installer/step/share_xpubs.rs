@@ -137,7 +137,12 @@ impl Step for ShareXpubs {
                         async move {
                             (
                                 fingerprint,
-                                get_extended_pubkey(device, fingerprint, network).await,
+                                get_extended_pubkey(
+                                    device,
+                                    fingerprint,
+                                    default_derivation_path(network),
+                                )
+                                .await,
                             )
                         },
                         |(fingerprint, res)| Message::ImportXpub(fingerprint, res),
@@ -263,8 +263,13 @@ pub fn edit_key_modal<'a>(
     signer_alias: Option<&'a String>,
     form_name: &'a form::Value<String>,
     form_xpub: &form::Value<String>,
+    form_account: &'a form::Value<String>,
+    chosen_origin: Option<String>,
+    same_device_key: Option<&'a str>,
+    tapsigner_cvc: Option<&'a form::Value<String>>,
     manually_imported_xpub: bool,
     duplicate_master_fg: bool,
+    duplicate_origin: bool,
 ) -> Element<'a, Message> {
     let content = Column::new()
         .padding(25)
@@ -282,9 +287,39 @@ pub fn edit_key_modal<'a>(
                     Column::new()
                         .push(p1_regular("Select the signing device for your key"))
                         .spacing(10)
+                        .push(
+                            Row::new()
+                                .spacing(10)
+                                .align_y(Alignment::Center)
+                                .push(text("Account:").bold())
+                                .push(
+                                    Container::new(
+                                        form::Form::new_trimmed("0", form_account, |msg| {
+                                            Message::DefineDescriptor(
+                                                message::DefineDescriptor::KeyModal(
+                                                    message::ImportKeyModal::AccountEdited(msg),
+                                                ),
+                                            )
+                                        })
+                                        .warning("Must be a number below 2147483648")
+                                        .size(text::P1_SIZE)
+                                        .padding(10),
+                                    )
+                                    .width(Length::Fixed(150.0)),
+                                )
+                                .push(
+                                    p1_regular(format!(
+                                        "Keys are derived at m/48'/{}'/{}'/2'",
+                                        if network == bitcoin::Network::Bitcoin { 0 } else { 1 },
+                                        if form_account.valid { form_account.value.as_str() } else { "?" },
+                                    ))
+                                    .style(theme::text::secondary),
+                                ),
+                        )
                         .push(
                             Column::with_children(hws).spacing(10)
                         )
+                        .push_maybe(tapsigner_cvc.map(tapsigner_cvc_form))
                         .push(
                             Column::with_children(keys).spacing(10)
                         )
@@ -357,6 +392,7 @@ pub fn edit_key_modal<'a>(
                                     .push(text("Key name:").bold())
                                     .push(tooltip(prompt::DEFINE_DESCRIPTOR_FINGERPRINT_TOOLTIP)),
                             )
+                            .push_maybe(chosen_origin.map(|origin| p1_regular(format!("Origin: {}", origin)).style(theme::text::secondary)))
                             .push(p1_regular("Give this key a friendly name. It helps you identify it later").style(theme::text::secondary))
                             .push(
                                 form::Form::new("Name", form_name, |msg| {
@@ -372,6 +408,13 @@ pub fn edit_key_modal<'a>(
                         None
                     }
                 )
+                .push_maybe(
+                    if duplicate_origin {
+                        Some(text("A different key with the same origin was already added. Check the extended public key and its origin.").style(theme::text::error))
+                    } else {
+                        same_device_key.map(|name| text(format!("The key \"{}\" of this signing device, for another account, was already added. Using the same device in both the primary and a recovery path reduces the security of the wallet: losing it or having it compromised affects both.", name)).style(theme::text::warning))
+                    }
+                )
                 .push_maybe(
                     if duplicate_master_fg {
                         Some(text("A single signing device may not be used more than once per path. (It can still be used in other paths.)").style(theme::text::error))
@@ -382,6 +425,7 @@ pub fn edit_key_modal<'a>(
                 .push(
                     button::primary(None, "Apply")
                         .on_press_maybe(if !duplicate_master_fg
+                            && !duplicate_origin
                             && (!manually_imported_xpub || form_xpub.valid)
                             && !form_name.value.is_empty() && form_name.valid {
                             Some(Message::DefineDescriptor(
@@ -398,6 +442,51 @@ pub fn edit_key_modal<'a>(
     scrollable(content).into()
 }
 
+fn tapsigner_cvc_form(cvc: &form::Value<String>) -> Element<Message> {
+    let on_edit = |msg| {
+        Message::DefineDescriptor(message::DefineDescriptor::KeyModal(
+            message::ImportKeyModal::TapsignerCvcEdited(msg),
+        ))
+    };
+    card::simple(
+        Column::new()
+            .spacing(10)
+            .push(p1_regular(
+                "Keep the Tapsigner on the NFC reader and enter its CVC, the 6 digits printed on \
+                 its back. If the card was never used, its key is picked now.",
+            ))
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_y(Alignment::Center)
+                    .push(
+                        Column::new()
+                            .spacing(5)
+                            .width(Length::Fill)
+                            .push(
+                                TextInput::new("CVC", &cvc.value)
+                                    .on_input(on_edit)
+                                    .secure(true)
+                                    .size(text::P1_SIZE)
+                                    .padding(10),
+                            )
+                            .push_maybe((!cvc.valid).then(|| {
+                                text::caption("The CVC is the 6 digits on the back of the card")
+                                    .style(theme::text::error)
+                            })),
+                    )
+                    .push(button::secondary(None, "Import key").on_press_maybe(
+                        (!cvc.value.is_empty()).then_some(Message::DefineDescriptor(
+                            message::DefineDescriptor::KeyModal(
+                                message::ImportKeyModal::ConfirmTapsignerCvc,
+                            ),
+                        )),
+                    )),
+            ),
+    )
+    .into()
+}
+
 fn example_xpub(network: Network) -> String {
     format!("[aabbccdd/42'/0']{}pub6DAkq8LWw91WGgUGnkR5Sbzjev5JCsXaTVZQ9MwsPV4BkNFKygtJ8GHodfDVx1udR723nT7JASqGPpKvz7zQ25pUTW6zVEBdiWoaC4aUqik",
         if network == bitcoin::Network::Bitcoin { "x" } else { "t" }
@@ -1,9 +1,13 @@
-use iced::{alignment, widget::Space, Alignment, Length};
+use iced::{
+    alignment,
+    widget::{scrollable, Space},
+    Alignment, Length,
+};
 
 use liana_ui::{
     color,
     component::{
-        button, collapse,
+        button, card, collapse,
         text::{h3, p1_regular, text, Text},
     },
     icon, image, theme,
@@ -15,6 +19,7 @@ use crate::installer::{
     step::descriptor::editor::key::Key,
     view::{
         editor::{define_descriptor_advanced_settings, defined_key, path, undefined_key},
+        graph::{policy_graph, PolicyGraph},
         layout,
     },
 };
@@ -60,6 +65,7 @@ pub fn custom_template<'a>(
     primary_path: Path<'a>,
     recovery_paths: &mut dyn Iterator<Item = Path<'a>>,
     num_recovery_paths: usize,
+    graph: PolicyGraph,
     valid: bool,
 ) -> Element<'a, Message> {
     let prim_keys_fixed = primary_path.keys.len() < 2; // can only delete a primary key if there are 2 or more
@@ -180,6 +186,26 @@ pub fn custom_template<'a>(
                     )
                 },
             ))
+            .push(
+                card::simple(
+                    Column::new()
+                        .spacing(10)
+                        .push(text("Overview:").bold())
+                        .push(
+                            scrollable(
+                                Column::new()
+                                    .push(policy_graph(&graph))
+                                    .push(Space::with_height(5)),
+                            )
+                            .direction(
+                                scrollable::Direction::Horizontal(
+                                    scrollable::Scrollbar::new().width(5).scroller_width(5),
+                                ),
+                            ),
+                        ),
+                )
+                .width(Length::Fill),
+            )
             .push(
                 Row::new()
                     .push(
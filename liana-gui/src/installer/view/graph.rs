@@ -0,0 +1,266 @@
+//! A graph of the spending policy of a wallet: a node for each spending path, with its threshold
+//! and timelock, linked to the nodes of the keys it needs.
+
+use std::collections::{HashMap, HashSet};
+
+use iced::{widget::tooltip as iced_tooltip, Alignment, Length};
+
+use liana::{
+    descriptors::{LianaPolicy, LintWarning, PathInfo},
+    miniscript::bitcoin::bip32::Fingerprint,
+};
+use liana_ui::{
+    component::text::{text, Text},
+    icon, theme,
+    widget::*,
+};
+
+use super::expire_message_units;
+use crate::installer::message::Message;
+
+/// A key needed by a spending path.
+#[derive(Debug, Clone, Default)]
+pub struct GraphKey {
+    /// The alias of the key, or its fingerprint. `None` if the key isn't defined yet.
+    pub name: Option<String>,
+    pub fingerprint: Option<Fingerprint>,
+    /// Why this key can't be used, if so.
+    pub error: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+/// A spending path of the policy.
+#[derive(Debug, Clone, Default)]
+pub struct GraphPath {
+    /// The timelock of the path, in blocks. `None` for the primary path.
+    pub timelock: Option<u16>,
+    pub threshold: usize,
+    pub keys: Vec<GraphKey>,
+    pub warnings: Vec<String>,
+    /// Why the timelock can't be used, if so.
+    pub timelock_error: Option<String>,
+    pub timelock_warnings: Vec<String>,
+}
+
+/// The spending paths of a policy, the primary path first.
+#[derive(Debug, Clone, Default)]
+pub struct PolicyGraph {
+    pub paths: Vec<GraphPath>,
+}
+
+impl PolicyGraph {
+    pub fn from_policy(policy: &LianaPolicy, aliases: &HashMap<Fingerprint, String>) -> Self {
+        let path = |timelock: Option<u16>, info: &PathInfo| {
+            let (threshold, keys) = match info {
+                PathInfo::Single(key) => (1, std::slice::from_ref(key)),
+                PathInfo::Multi(k, keys) => (*k, keys.as_slice()),
+            };
+            GraphPath {
+                timelock,
+                threshold,
+                keys: keys
+                    .iter()
+                    .map(|key| {
+                        let fingerprint = key.master_fingerprint();
+                        GraphKey {
+                            name: Some(
+                                aliases
+                                    .get(&fingerprint)
+                                    .cloned()
+                                    .unwrap_or_else(|| format!("[{}]", fingerprint)),
+                            ),
+                            fingerprint: Some(fingerprint),
+                            ..Default::default()
+                        }
+                    })
+                    .collect(),
+                ..Default::default()
+            }
+        };
+        let paths = std::iter::once(path(None, policy.primary_path()))
+            .chain(
+                policy
+                    .recovery_paths()
+                    .iter()
+                    .map(|(timelock, info)| path(Some(*timelock), info)),
+            )
+            .collect();
+        Self { paths }
+    }
+
+    /// Attach the warnings to the nodes they are about.
+    pub fn with_warnings(mut self, warnings: &[LintWarning]) -> Self {
+        for warning in warnings {
+            let message = warning.to_string();
+            match warning {
+                LintWarning::SameDeviceModel { .. } => {
+                    if let Some(path) = self.path_mut(None) {
+                        path.warnings.push(message);
+                    }
+                }
+                LintWarning::RecoveryWithPrimaryKeys { timelock } => {
+                    if let Some(path) = self.path_mut(Some(*timelock)) {
+                        path.warnings.push(message);
+                    }
+                }
+                LintWarning::ShortTimelock { timelock, .. }
+                | LintWarning::LongTimelock { timelock, .. } => {
+                    if let Some(path) = self.path_mut(Some(*timelock)) {
+                        path.timelock_warnings.push(message);
+                    }
+                }
+                LintWarning::HotSignerRecovery {
+                    timelock,
+                    fingerprint,
+                }
+                | LintWarning::SharedSigner {
+                    timelock,
+                    fingerprint,
+                } => {
+                    if let Some(path) = self.path_mut(Some(*timelock)) {
+                        for key in path
+                            .keys
+                            .iter_mut()
+                            .filter(|k| k.fingerprint == Some(*fingerprint))
+                        {
+                            key.warnings.push(message.clone());
+                        }
+                    }
+                }
+            }
+        }
+        self
+    }
+
+    /// Mark the keys with these fingerprints as unusable.
+    pub fn with_key_errors(mut self, fingerprints: &HashSet<Fingerprint>, error: &str) -> Self {
+        for key in self.paths.iter_mut().flat_map(|p| p.keys.iter_mut()) {
+            if key.fingerprint.is_some_and(|fg| fingerprints.contains(&fg)) {
+                key.error = Some(error.to_string());
+            }
+        }
+        self
+    }
+
+    fn path_mut(&mut self, timelock: Option<u16>) -> Option<&mut GraphPath> {
+        self.paths.iter_mut().find(|p| p.timelock == timelock)
+    }
+}
+
+// A node of the graph, highlighted and with its issues in a tooltip if it has any.
+fn node<'a>(
+    content: Row<'a, Message>,
+    error: Option<&String>,
+    warnings: &[String],
+) -> Element<'a, Message> {
+    let issues: Vec<&String> = error.into_iter().chain(warnings).collect();
+    let content = content
+        .spacing(5)
+        .align_y(Alignment::Center)
+        .push_maybe(if error.is_some() {
+            Some(icon::warning_icon().style(theme::text::error))
+        } else if !warnings.is_empty() {
+            Some(icon::warning_icon().style(theme::text::warning))
+        } else {
+            None
+        });
+    let node = Container::new(content)
+        .padding(10)
+        .style(if error.is_some() {
+            theme::card::invalid
+        } else if !warnings.is_empty() {
+            theme::card::warning
+        } else {
+            theme::card::border
+        });
+    if issues.is_empty() {
+        node.into()
+    } else {
+        iced_tooltip::Tooltip::new(
+            node,
+            Container::new(
+                issues
+                    .into_iter()
+                    .fold(Column::new().spacing(5).max_width(400), |col, issue| {
+                        col.push(text(issue).small())
+                    }),
+            )
+            .padding(10),
+            iced_tooltip::Position::Bottom,
+        )
+        .style(theme::card::simple)
+        .into()
+    }
+}
+
+fn link<'a>() -> Element<'a, Message> {
+    icon::arrow_right().style(theme::text::secondary).into()
+}
+
+pub fn policy_graph<'a>(graph: &PolicyGraph) -> Element<'a, Message> {
+    let root = node(
+        Row::new()
+            .push(icon::wallet_icon())
+            .push(text("Wallet").bold()),
+        None,
+        &[],
+    );
+    graph
+        .paths
+        .iter()
+        .enumerate()
+        .fold(Column::new().spacing(10).push(root), |col, (i, path)| {
+            let title = if path.timelock.is_some() {
+                format!("Recovery path #{}", i)
+            } else {
+                "Primary path".to_string()
+            };
+            let path_node = node(
+                Row::new().push(text(title).bold()).push(text(format!(
+                    "{} of {}",
+                    path.threshold,
+                    path.keys.len()
+                ))),
+                None,
+                &path.warnings,
+            );
+            let timelock_node = path.timelock.map(|timelock| {
+                node(
+                    Row::new().push(icon::clock_icon()).push(text(format!(
+                        "after {} blocks (~{})",
+                        timelock,
+                        expire_message_units(timelock as u32).join(",")
+                    ))),
+                    path.timelock_error.as_ref(),
+                    &path.timelock_warnings,
+                )
+            });
+            let keys = path.keys.iter().fold(Row::new().spacing(5), |row, key| {
+                row.push(node(
+                    Row::new().push(icon::key_icon()).push(
+                        text(key.name.clone().unwrap_or_else(|| "Undefined".to_string())).style(
+                            if key.name.is_some() {
+                                theme::text::default
+                            } else {
+                                theme::text::secondary
+                            },
+                        ),
+                    ),
+                    key.error.as_ref(),
+                    &key.warnings,
+                ))
+            });
+            col.push(
+                Row::new()
+                    .spacing(10)
+                    .align_y(Alignment::Center)
+                    .push(iced::widget::Space::with_width(Length::Fixed(20.0)))
+                    .push(icon::arrow_return_right().style(theme::text::secondary))
+                    .push(path_node)
+                    .push_maybe(timelock_node.map(|n| Row::new().spacing(10).push(link()).push(n)))
+                    .push(link())
+                    .push(keys),
+            )
+        })
+        .into()
+}
@@ -1,4 +1,5 @@
 pub mod editor;
+pub mod graph;
 
 use async_hwi::utils::extract_keys_and_template;
 use iced::widget::{checkbox, radio, scrollable, scrollable::Scrollbar, Button, Space, TextInput};
@@ -16,8 +17,9 @@ use std::path::PathBuf;
 use std::{collections::HashSet, str::FromStr};
 
 use liana::{
-    descriptors::{LianaDescriptor, LianaPolicy},
+    descriptors::{LianaDescriptor, LianaPolicy, LintWarning},
     miniscript::bitcoin::{self, bip32::Fingerprint},
+    signer::slip39,
 };
 use liana_ui::{
     component::{
@@ -30,11 +32,13 @@ use liana_ui::{
 
 use crate::{
     hw::{is_compatible_with_tapminiscript, HardwareWallet, UnsupportedReason},
+    i18n::tr,
     installer::{
         message::{self, DefineBitcoind, DefineNode, Message},
         prompt,
         step::{DownloadState, InstallState},
         view::editor::duration_from_sequence,
+        view::graph::{policy_graph, PolicyGraph},
         Error,
     },
     node::{
@@ -145,7 +149,7 @@ pub fn import_wallet_or_descriptor<'a>(
                             )
                             .push(
                                 Row::new().push(Space::with_width(Length::Fill)).push(
-                                    button::secondary(None, "Next")
+                                    button::secondary(None, tr("Next"))
                                         .width(Length::Fixed(200.0))
                                         .on_press_maybe(if !invitation.value.is_empty() {
                                             Some(Message::ImportRemoteWallet(
@@ -221,7 +225,7 @@ pub fn import_wallet_or_descriptor<'a>(
                         )
                         .push(
                             Row::new().push(Space::with_width(Length::Fill)).push(
-                                button::secondary(None, "Next")
+                                button::secondary(None, tr("Next"))
                                     .width(Length::Fixed(200.0))
                                     .on_press_maybe(
                                         if imported_descriptor.value.is_empty()
@@ -263,6 +267,8 @@ pub fn import_descriptor<'a>(
     email: Option<&'a str>,
     imported_descriptor: &form::Value<String>,
     wrong_network: bool,
+    parse_error: Option<&String>,
+    graph: Option<&PolicyGraph>,
     error: Option<&String>,
 ) -> Element<'a, Message> {
     let col_descriptor = Column::new()
@@ -279,6 +285,20 @@ pub fn import_descriptor<'a>(
             .size(text::P1_SIZE)
             .padding(10),
         )
+        .push_maybe(parse_error.map(|e| text(e).small().style(theme::text::error)))
+        .push_maybe(graph.map(|graph| {
+            card::simple(
+                scrollable(
+                    Column::new()
+                        .push(policy_graph(graph))
+                        .push(Space::with_height(5)),
+                )
+                .direction(scrollable::Direction::Horizontal(
+                    scrollable::Scrollbar::new().width(5).scroller_width(5),
+                )),
+            )
+            .width(Length::Fill)
+        }))
         .spacing(10);
     layout(
         progress,
@@ -295,9 +315,9 @@ pub fn import_descriptor<'a>(
             )))
             .push(
                 if imported_descriptor.value.is_empty() || !imported_descriptor.valid {
-                    button::secondary(None, "Next").width(Length::Fixed(200.0))
+                    button::secondary(None, tr("Next")).width(Length::Fixed(200.0))
                 } else {
-                    button::secondary(None, "Next")
+                    button::secondary(None, tr("Next"))
                         .width(Length::Fixed(200.0))
                         .on_press(Message::Next)
                 },
@@ -452,6 +472,11 @@ pub fn hardware_wallet_xpubs<'a>(
         HardwareWallet::Locked {
             kind, pairing_code, ..
         } => hw::locked_hardware_wallet(kind, pairing_code.as_ref()),
+        HardwareWallet::Tapsigner { device, .. } => hw::unidentified_hardware_wallet(
+            "Tapsigner",
+            Some(&device.version),
+            "Import its key when setting the keys of the descriptor",
+        ),
     })
     .style(theme::button::secondary)
     .width(Length::Fill);
@@ -666,11 +691,11 @@ pub fn register_descriptor<'a>(
                 done,
             ).on_toggle(Message::UserActionDone)))
             .push(if !created_desc || (done && !processing) {
-                button::secondary(None, "Next")
+                button::secondary(None, tr("Next"))
                     .on_press(Message::Next)
                     .width(Length::Fixed(200.0))
             } else {
-                button::secondary(None, "Next").width(Length::Fixed(200.0))
+                button::secondary(None, tr("Next")).width(Length::Fixed(200.0))
             })
             .spacing(50),
         true,
@@ -686,6 +711,7 @@ pub fn backup_descriptor<'a>(
     progress: (usize, usize),
     email: Option<&'a str>,
     descriptor: &'a LianaDescriptor,
+    warnings: &'a [LintWarning],
     keys_aliases: &'a HashMap<Fingerprint, String>,
     done: bool,
 ) -> Element<'a, Message> {
@@ -753,15 +779,54 @@ pub fn backup_descriptor<'a>(
                     .width(Length::Fill)
                     .max_width(1500),
             )
+            .push(
+                card::simple(
+                    Column::new()
+                        .spacing(10)
+                        .push(text("The policy graph:").bold())
+                        .push(
+                            scrollable(
+                                Column::new()
+                                    .push(policy_graph(
+                                        &PolicyGraph::from_policy(
+                                            &descriptor.policy(),
+                                            keys_aliases,
+                                        )
+                                        .with_warnings(warnings),
+                                    ))
+                                    .push(Space::with_height(5)),
+                            )
+                            .direction(
+                                scrollable::Direction::Horizontal(
+                                    scrollable::Scrollbar::new().width(5).scroller_width(5),
+                                ),
+                            ),
+                        ),
+                )
+                .width(Length::Fill)
+                .max_width(1500),
+            )
+            .push_maybe(if warnings.is_empty() {
+                None
+            } else {
+                Some(
+                    warnings
+                        .iter()
+                        .fold(Column::new().spacing(10), |col, w| {
+                            col.push(card::warning(w.to_string()).width(Length::Fill))
+                        })
+                        .max_width(1500),
+                )
+            })
             .push(
                 checkbox("I have backed up my descriptor", done).on_toggle(Message::UserActionDone),
             )
             .push(if done {
-                button::secondary(None, "Next")
+                button::secondary(None, tr("Next"))
                     .on_press(Message::Next)
                     .width(Length::Fixed(200.0))
             } else {
-                button::secondary(None, "Next").width(Length::Fixed(200.0))
+                button::secondary(None, tr("Next")).width(Length::Fixed(200.0))
             })
             .push(Space::with_height(20.0))
             .spacing(50),
@@ -1009,11 +1074,11 @@ pub fn define_bitcoin_node<'a>(
                         .width(Length::Fixed(200.0)),
                 ))
                 .push(if is_running.map(|res| res.is_ok()).unwrap_or(false) {
-                    button::secondary(None, "Next")
+                    button::secondary(None, tr("Next"))
                         .on_press(Message::Next)
                         .width(Length::Fixed(200.0))
                 } else {
-                    button::secondary(None, "Next").width(Length::Fixed(200.0))
+                    button::secondary(None, tr("Next")).width(Length::Fixed(200.0))
                 }),
         )
         .spacing(50);
@@ -1381,7 +1446,7 @@ pub fn start_internal_bitcoind<'a>(
             .spacing(50)
             .push(
                 Row::new().push(
-                    button::secondary(None, "Next")
+                    button::secondary(None, tr("Next"))
                         .width(Length::Fixed(200.0))
                         .on_press_maybe(if let Some(Ok(_)) = started {
                             Some(Message::Next)
@@ -1627,10 +1692,48 @@ pub fn hw_list_view(
         HardwareWallet::Locked {
             kind, pairing_code, ..
         } => hw::locked_hardware_wallet(kind, pairing_code.as_ref()),
+        HardwareWallet::Tapsigner {
+            device,
+            fingerprint,
+            alias,
+            ..
+        } => {
+            if device_must_support_taproot {
+                hw::incompatible_hardware_wallet(
+                    "Tapsigner",
+                    Some(&device.version),
+                    "The Tapsigner cannot sign for Taproot descriptors",
+                )
+            } else {
+                match fingerprint {
+                    Some(fingerprint) if selected => hw::selected_hardware_wallet(
+                        "Tapsigner",
+                        Some(&device.version),
+                        fingerprint,
+                        alias.as_ref(),
+                        None,
+                    ),
+                    Some(fingerprint) => hw::supported_hardware_wallet(
+                        "Tapsigner",
+                        Some(&device.version),
+                        fingerprint,
+                        alias.as_ref(),
+                    ),
+                    None => hw::unidentified_hardware_wallet(
+                        "Tapsigner",
+                        Some(&device.version),
+                        "Enter its CVC to import its key",
+                    ),
+                }
+            }
+        }
     })
     .style(theme::button::secondary)
     .width(Length::Fill);
-    if !processing && hw.is_supported() {
+    if !processing
+        && (hw.is_supported()
+            || (matches!(hw, HardwareWallet::Tapsigner { .. }) && !device_must_support_taproot))
+    {
         bttn = bttn.on_press(Message::Select(i));
     }
     bttn.into()
@@ -1715,11 +1818,11 @@ pub fn backup_mnemonic<'a>(
             )
             .push(checkbox("I have backed up my mnemonic", done).on_toggle(Message::UserActionDone))
             .push(if done {
-                button::secondary(None, "Next")
+                button::secondary(None, tr("Next"))
                     .on_press(Message::Next)
                     .width(Length::Fixed(200.0))
             } else {
-                button::secondary(None, "Next").width(Length::Fixed(200.0))
+                button::secondary(None, tr("Next")).width(Length::Fixed(200.0))
             })
             .push(Space::with_height(20.0))
             .spacing(50),
@@ -1735,6 +1838,7 @@ pub fn recover_mnemonic<'a>(
     current: usize,
     suggestions: &'a [String],
     recover: bool,
+    slip39_shares: Option<&'a [String]>,
     error: Option<&'a String>,
 ) -> Element<'a, Message> {
     layout(
@@ -1743,7 +1847,41 @@ pub fn recover_mnemonic<'a>(
         "Import Mnemonic",
         Column::new()
             .push(text(prompt::RECOVER_MNEMONIC_HELP))
-            .push_maybe(if recover {
+            .push_maybe(slip39_shares.map(|shares| {
+                Column::new()
+                    .spacing(10)
+                    .push(text(prompt::RECOVER_SLIP39_HELP))
+                    .push(shares.iter().enumerate().fold(
+                        Column::new().spacing(5),
+                        |acc, (i, share)| {
+                            acc.push(
+                                Row::new()
+                                    .spacing(10)
+                                    .align_y(Alignment::Center)
+                                    .push(
+                                        Container::new(text(format!("Share #{}", i + 1)).small())
+                                            .width(Length::Fixed(70.0)),
+                                    )
+                                    .push(
+                                        TextInput::new("Words of the share", share)
+                                            .on_input(move |msg| Message::Slip39Share(i, msg)),
+                                    )
+                                    .push_maybe(if is_slip39_share(share) {
+                                        Some(icon::circle_check_icon().style(theme::text::success))
+                                    } else {
+                                        None
+                                    }),
+                            )
+                        },
+                    ))
+                    .push(
+                        button::secondary(Some(icon::plus_icon()), "Add share")
+                            .on_press(Message::AddSlip39Share)
+                            .width(Length::Fixed(200.0)),
+                    )
+                    .push_maybe(error.map(|e| card::invalid(text(e).style(theme::text::error))))
+            }))
+            .push_maybe(if recover && slip39_shares.is_none() {
                 Some(
                     Column::new()
                         .align_x(Alignment::Center)
@@ -1809,6 +1947,11 @@ pub fn recover_mnemonic<'a>(
                             .on_press(Message::ImportMnemonic(true))
                             .width(Length::Fixed(200.0)),
                     )
+                    .push(
+                        button::secondary(None, "Import SLIP-39 shares")
+                            .on_press(Message::ImportSlip39Shares(true))
+                            .width(Length::Fixed(200.0)),
+                    )
                     .push(
                         button::secondary(None, "Skip")
                             .on_press(Message::Skip)
@@ -1823,10 +1966,15 @@ pub fn recover_mnemonic<'a>(
                             .width(Length::Fixed(200.0)),
                     )
                     .push(
-                        if words.iter().any(|(_, valid)| !valid) || error.is_some() {
-                            button::secondary(None, "Next").width(Length::Fixed(200.0))
+                        if error.is_some()
+                            || match slip39_shares {
+                                Some(shares) => !shares.iter().any(|s| is_slip39_share(s)),
+                                None => words.iter().any(|(_, valid)| !valid),
+                            }
+                        {
+                            button::secondary(None, tr("Next")).width(Length::Fixed(200.0))
                         } else {
-                            button::secondary(None, "Next")
+                            button::secondary(None, tr("Next"))
                                 .on_press(Message::Next)
                                 .width(Length::Fixed(200.0))
                         },
@@ -1838,6 +1986,13 @@ pub fn recover_mnemonic<'a>(
     )
 }
 
+// Whether all the words of this share are from the SLIP-39 wordlist. The checksum is verified
+// when combining the shares.
+fn is_slip39_share(share: &str) -> bool {
+    let mut words = share.split_whitespace().peekable();
+    words.peek().is_some() && words.all(|w| slip39::is_word(&w.to_lowercase()))
+}
+
 pub fn choose_backend(progress: (usize, usize)) -> Element<'static, Message> {
     layout(
         progress,
@@ -1936,7 +2091,7 @@ pub fn connection_step_enter_email<'a>(
             .warning("Email is not valid"),
         )
         .push(
-            button::secondary(None, "Next")
+            button::secondary(None, tr("Next"))
                 .on_press_maybe(if processing || !email.valid {
                     None
                 } else {
@@ -2027,7 +2182,7 @@ fn layout<'a>(
     padding_left: bool,
     previous_message: Option<Message>,
 ) -> Element<'a, Message> {
-    let mut prev_button = button::transparent(Some(icon::previous_icon()), "Previous");
+    let mut prev_button = button::transparent(Some(icon::previous_icon()), tr("Previous"));
     if let Some(msg) = previous_message {
         prev_button = prev_button.on_press(msg);
     }
@@ -2046,7 +2201,7 @@ fn layout<'a>(
                 Row::new()
                     .align_y(Alignment::Center)
                     .push(Container::new(prev_button).center_x(Length::FillPortion(2)))
-                    .push(Container::new(h3(title)).width(Length::FillPortion(8)))
+                    .push(Container::new(h3(tr(title))).width(Length::FillPortion(8)))
                     .push_maybe(if progress.1 > 0 {
                         Some(
                             Container::new(text(format!("{} | {}", progress.0, progress.1)))
@@ -2084,3 +2239,22 @@ fn layout<'a>(
     .style(theme::container::background)
     .into()
 }
+
+/// A banner telling why a file dropped onto the window wasn't imported.
+pub fn dropped_file_banner<'a>(error: &'a str) -> Element<'a, Message> {
+    Container::new(
+        Row::new()
+            .spacing(10)
+            .align_y(Alignment::Center)
+            .push(icon::warning_icon())
+            .push(text(error).width(Length::Fill))
+            .push(
+                button::transparent(Some(icon::cross_icon()), tr("Dismiss"))
+                    .on_press(Message::DismissDroppedFile),
+            ),
+    )
+    .padding(10)
+    .width(Length::Fill)
+    .style(theme::banner::warning)
+    .into()
+}
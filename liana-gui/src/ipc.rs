@@ -0,0 +1,329 @@
+//! Communication with the GUI already running, so another process started for the same data
+//! directory or wallet (for instance by the operating system to open a payment link) brings its
+//! window to the front and hands it over the request instead of starting a second GUI which would
+//! fight over the daemon and datadir locks.
+//!
+//! The running GUI listens on a localhost TCP port and writes the port, along with a random token
+//! the clients must present, to a file only readable by the user. The file is in the data
+//! directory for the GUI started on the launcher, and in the network data directory for the GUI
+//! running a wallet. It is created exclusively, and acts as the lock of the single instance.
+
+use std::{
+    fs,
+    io::ErrorKind,
+    io::{BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use liana::random::random_bytes;
+use serde::{Deserialize, Serialize};
+
+const ENDPOINT_FILE_NAME: &str = "gui-ipc.json";
+const TIMEOUT: Duration = Duration::from_secs(2);
+// A request is a single line, don't let a client make us buffer more than that.
+const MAX_REQUEST_SIZE: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "request", content = "params", rename_all = "snake_case")]
+pub enum Request {
+    /// Show the window and bring it to the front.
+    Focus,
+    /// Open the send screen prefilled with a `bitcoin:` payment link.
+    OpenUri(String),
+    /// Import a file, as if it was dropped onto the window.
+    OpenFile(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IpcError {
+    /// No GUI is running this wallet.
+    NotRunning,
+    /// Another GUI is running this wallet.
+    AlreadyRunning,
+    Io(String),
+    /// The running GUI refused the request.
+    Rejected,
+}
+
+impl std::fmt::Display for IpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::NotRunning => write!(f, "No running instance of the wallet"),
+            Self::AlreadyRunning => write!(f, "Another instance of the wallet is running"),
+            Self::Io(e) => write!(f, "Failed to communicate with the running instance: {}", e),
+            Self::Rejected => write!(f, "The running instance rejected the request"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Endpoint {
+    port: u16,
+    token: String,
+    pid: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    token: String,
+    request: Request,
+}
+
+fn endpoint_path(network_datadir: &Path) -> PathBuf {
+    network_datadir.join(ENDPOINT_FILE_NAME)
+}
+
+/// Send a request to the GUI running the wallet of this network data directory.
+pub fn send(network_datadir: &Path, request: &Request) -> Result<(), IpcError> {
+    let content = match fs::read(endpoint_path(network_datadir)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Err(IpcError::NotRunning),
+        Err(e) => return Err(IpcError::Io(e.to_string())),
+    };
+    let endpoint: Endpoint =
+        serde_json::from_slice(&content).map_err(|e| IpcError::Io(e.to_string()))?;
+    // The file of a GUI which didn't exit cleanly is left behind, nothing listens on the port.
+    let mut stream = TcpStream::connect_timeout(
+        &SocketAddr::from((Ipv4Addr::LOCALHOST, endpoint.port)),
+        TIMEOUT,
+    )
+    .map_err(|_| IpcError::NotRunning)?;
+    stream
+        .set_read_timeout(Some(TIMEOUT))
+        .map_err(|e| IpcError::Io(e.to_string()))?;
+
+    let mut line = serde_json::to_string(&Envelope {
+        token: endpoint.token,
+        request: request.clone(),
+    })
+    .map_err(|e| IpcError::Io(e.to_string()))?;
+    line.push('\n');
+    stream
+        .write_all(line.as_bytes())
+        .map_err(|e| IpcError::Io(e.to_string()))?;
+
+    let mut response = String::new();
+    BufReader::new(stream)
+        .read_line(&mut response)
+        .map_err(|e| IpcError::Io(e.to_string()))?;
+    if response.trim() == "ok" {
+        tracing::info!(
+            "Request handed over to the running instance (pid {})",
+            endpoint.pid
+        );
+        Ok(())
+    } else {
+        Err(IpcError::Rejected)
+    }
+}
+
+/// Listens for the requests of other processes while the GUI runs. The endpoint file is removed
+/// once dropped.
+pub struct Server {
+    path: PathBuf,
+    port: u16,
+    stopped: Arc<AtomicBool>,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl Server {
+    /// Listen for the requests of the processes started for this data directory, failing if
+    /// another GUI already does.
+    pub fn start(datadir: &Path) -> Result<Self, IpcError> {
+        let token = random_bytes()
+            .map(hex::encode)
+            .map_err(|e| IpcError::Io(e.to_string()))?;
+        let listener =
+            TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).map_err(|e| IpcError::Io(e.to_string()))?;
+        let port = listener
+            .local_addr()
+            .map_err(|e| IpcError::Io(e.to_string()))?
+            .port();
+
+        let path = endpoint_path(datadir);
+        let content = serde_json::to_string(&Endpoint {
+            port,
+            token: token.clone(),
+            pid: std::process::id(),
+        })
+        .map_err(|e| IpcError::Io(e.to_string()))?;
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        // Only the user may read the token.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let file = match options.open(&path) {
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                // The file of a GUI which didn't exit cleanly is left behind.
+                if is_running(&path) {
+                    return Err(IpcError::AlreadyRunning);
+                }
+                tracing::info!("Removing the stale {}", path.to_string_lossy());
+                fs::remove_file(&path).map_err(|e| IpcError::Io(e.to_string()))?;
+                options.open(&path)
+            }
+            res => res,
+        };
+        file.and_then(|mut file| file.write_all(content.as_bytes()))
+            .map_err(|e| IpcError::Io(e.to_string()))?;
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let (thread_stopped, thread_requests) = (stopped.clone(), requests.clone());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                if thread_stopped.load(Ordering::Relaxed) {
+                    break;
+                }
+                match stream.map(|stream| handle(stream, &token)) {
+                    Ok(Ok(request)) => {
+                        if let Ok(mut requests) = thread_requests.lock() {
+                            requests.push(request);
+                        }
+                    }
+                    Ok(Err(e)) | Err(e) => {
+                        tracing::warn!("Failed to handle an IPC connection: {}", e)
+                    }
+                }
+            }
+        });
+        tracing::info!("Listening for requests of other instances on port {}", port);
+
+        Ok(Self {
+            path,
+            port,
+            stopped,
+            requests,
+        })
+    }
+
+    /// Whether this server listens for the requests of this data directory.
+    pub fn is_for(&self, datadir: &Path) -> bool {
+        self.path == endpoint_path(datadir)
+    }
+
+    /// The requests received since the last call.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests
+            .lock()
+            .map(|mut requests| std::mem::take(&mut *requests))
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        // Wake up the listening thread so it notices it must stop.
+        let _ = TcpStream::connect_timeout(
+            &SocketAddr::from((Ipv4Addr::LOCALHOST, self.port)),
+            TIMEOUT,
+        );
+        if let Err(e) = fs::remove_file(&self.path) {
+            tracing::warn!("Failed to remove {}: {}", self.path.to_string_lossy(), e);
+        }
+    }
+}
+
+// Whether a GUI listens on the port of the endpoint file.
+fn is_running(path: &Path) -> bool {
+    fs::read(path)
+        .ok()
+        .and_then(|content| serde_json::from_slice::<Endpoint>(&content).ok())
+        .map(|endpoint| {
+            TcpStream::connect_timeout(
+                &SocketAddr::from((Ipv4Addr::LOCALHOST, endpoint.port)),
+                TIMEOUT,
+            )
+            .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+fn handle(mut stream: TcpStream, token: &str) -> Result<Request, std::io::Error> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new((&stream).take(MAX_REQUEST_SIZE)).read_line(&mut line)?;
+    match serde_json::from_str::<Envelope>(&line) {
+        Ok(envelope) if envelope.token == token => {
+            stream.write_all(b"ok\n")?;
+            Ok(envelope.request)
+        }
+        _ => {
+            stream.write_all(b"rejected\n")?;
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "invalid request or token",
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn send_request() {
+        let datadir = std::env::temp_dir().join(format!("liana-ipc-test-{}", std::process::id()));
+        fs::create_dir_all(&datadir).unwrap();
+        let request = Request::OpenUri("bitcoin:bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq".into());
+        assert_eq!(send(&datadir, &request), Err(IpcError::NotRunning));
+
+        let server = Server::start(&datadir).unwrap();
+        assert!(server.is_for(&datadir));
+        // Only one instance may run.
+        assert!(matches!(
+            Server::start(&datadir),
+            Err(IpcError::AlreadyRunning)
+        ));
+        send(&datadir, &Request::Focus).unwrap();
+        send(&datadir, &request).unwrap();
+        assert_eq!(server.requests(), vec![Request::Focus, request.clone()]);
+        assert!(server.requests().is_empty());
+
+        // A client without the token is rejected.
+        let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, server.port)).unwrap();
+        stream
+            .write_all(b"{\"token\":\"00\",\"request\":{\"request\":\"open_uri\",\"params\":\"bitcoin:\"}}\n")
+            .unwrap();
+        let mut response = String::new();
+        BufReader::new(stream).read_line(&mut response).unwrap();
+        assert_eq!(response, "rejected\n");
+        assert!(server.requests().is_empty());
+
+        drop(server);
+        assert!(!endpoint_path(&datadir).exists());
+        assert_eq!(send(&datadir, &request), Err(IpcError::NotRunning));
+
+        // The file left behind by a GUI which didn't exit cleanly doesn't prevent starting.
+        let port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        fs::write(
+            endpoint_path(&datadir),
+            serde_json::to_string(&Endpoint {
+                port,
+                token: "00".to_string(),
+                pid: 0,
+            })
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(send(&datadir, &request), Err(IpcError::NotRunning));
+        let server = Server::start(&datadir).unwrap();
+        drop(server);
+        fs::remove_dir_all(&datadir).unwrap();
+    }
+}
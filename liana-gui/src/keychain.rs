@@ -0,0 +1,277 @@
+//! Storage of the secrets of the application.
+//!
+//! The secrets, such as the sessions with the remote backend or the password of the RPC interface
+//! of the managed bitcoind, are stored in the keychain of the operating system: the Keychain on
+//! MacOS, the Credential Manager on Windows and the Secret Service on Linux. If it isn't available,
+//! they are stored in a file at the root of the data directory which only the user can read.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use liana::miniscript::bitcoin::Network;
+use lianad::config::{BitcoinBackend, BitcoindRpcAuth, Config};
+
+/// Name of the file the secrets are stored in when the keychain is not available.
+pub const SECRETS_FILE_NAME: &str = "secrets.json";
+
+const KEYCHAIN_SERVICE: &str = "Liana";
+
+#[derive(Debug)]
+pub enum KeychainError {
+    Keychain(keyring::Error),
+    File(String),
+}
+
+impl std::fmt::Display for KeychainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Keychain(e) => write!(f, "Keychain error: {}", e),
+            Self::File(e) => write!(f, "Secrets file error: {}", e),
+        }
+    }
+}
+
+impl From<keyring::Error> for KeychainError {
+    fn from(e: keyring::Error) -> Self {
+        Self::Keychain(e)
+    }
+}
+
+/// A store of secrets, identified by a key.
+pub trait SecretStore {
+    fn get(&self, key: &str) -> Result<Option<String>, KeychainError>;
+    /// Store the secret, replacing the previous one with this key.
+    fn set(&self, key: &str, secret: &str) -> Result<(), KeychainError>;
+    /// Remove the secret with this key, if any.
+    fn delete(&self, key: &str) -> Result<(), KeychainError>;
+}
+
+/// The keychain of the operating system.
+pub struct OsKeychain;
+
+impl SecretStore for OsKeychain {
+    fn get(&self, key: &str) -> Result<Option<String>, KeychainError> {
+        match keyring::Entry::new(KEYCHAIN_SERVICE, key)?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set(&self, key: &str, secret: &str) -> Result<(), KeychainError> {
+        Ok(keyring::Entry::new(KEYCHAIN_SERVICE, key)?.set_password(secret)?)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), KeychainError> {
+        match keyring::Entry::new(KEYCHAIN_SERVICE, key)?.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// A JSON file mapping the keys to the secrets.
+pub struct FileStore {
+    path: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, String>, KeychainError> {
+        match fs::read(&self.path) {
+            Ok(content) => {
+                serde_json::from_slice(&content).map_err(|e| KeychainError::File(e.to_string()))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(KeychainError::File(e.to_string())),
+        }
+    }
+
+    fn write(&self, secrets: &BTreeMap<String, String>) -> Result<(), KeychainError> {
+        let content = serde_json::to_string_pretty(secrets)
+            .map_err(|e| KeychainError::File(e.to_string()))?;
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        // Only the user may read and write the file.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(content.as_bytes()))
+            .map_err(|e| KeychainError::File(e.to_string()))
+    }
+}
+
+impl SecretStore for FileStore {
+    fn get(&self, key: &str) -> Result<Option<String>, KeychainError> {
+        Ok(self.read()?.remove(key))
+    }
+
+    fn set(&self, key: &str, secret: &str) -> Result<(), KeychainError> {
+        let mut secrets = self.read()?;
+        secrets.insert(key.to_string(), secret.to_string());
+        self.write(&secrets)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), KeychainError> {
+        let mut secrets = self.read()?;
+        if secrets.remove(key).is_some() {
+            self.write(&secrets)?;
+        }
+        Ok(())
+    }
+}
+
+/// The keychain of the operating system, falling back to a file if it isn't available.
+pub struct Secrets<K = OsKeychain> {
+    keychain: K,
+    file: FileStore,
+}
+
+/// The secrets of the application using this data directory.
+pub fn secrets(datadir: &Path) -> Secrets {
+    Secrets {
+        keychain: OsKeychain,
+        file: FileStore::new(datadir.join(SECRETS_FILE_NAME)),
+    }
+}
+
+impl<K: SecretStore> SecretStore for Secrets<K> {
+    fn get(&self, key: &str) -> Result<Option<String>, KeychainError> {
+        match self.keychain.get(key) {
+            Ok(Some(secret)) => Ok(Some(secret)),
+            Ok(None) => self.file.get(key),
+            Err(e) => {
+                tracing::warn!(
+                    "Keychain not available, reading from the secrets file: {}",
+                    e
+                );
+                self.file.get(key)
+            }
+        }
+    }
+
+    fn set(&self, key: &str, secret: &str) -> Result<(), KeychainError> {
+        match self.keychain.set(key, secret) {
+            // Don't leave a previous copy of the secret in the file.
+            Ok(()) => self.file.delete(key),
+            Err(e) => {
+                tracing::warn!("Keychain not available, writing to the secrets file: {}", e);
+                self.file.set(key, secret)
+            }
+        }
+    }
+
+    fn delete(&self, key: &str) -> Result<(), KeychainError> {
+        if let Err(e) = self.keychain.delete(key) {
+            tracing::warn!("Keychain not available: {}", e);
+        }
+        self.file.delete(key)
+    }
+}
+
+/// The key of the session with the remote backend of this account.
+pub fn remote_backend_session_key(network: Network, email: &str) -> String {
+    format!("{}/remote-backend/{}", network, email)
+}
+
+// The key of the RPC password of the bitcoind managed by the application.
+fn bitcoind_rpc_key(network: Network) -> String {
+    format!("{}/bitcoind-rpc", network)
+}
+
+/// Move the RPC password of the managed bitcoind from the daemon configuration to the store. Only
+/// the user is left in the configuration, so it can be written to disk.
+pub fn store_rpc_password(
+    store: &impl SecretStore,
+    config: &mut Config,
+) -> Result<(), KeychainError> {
+    if let Some(BitcoinBackend::Bitcoind(bitcoind_config)) = &mut config.bitcoin_backend {
+        if let BitcoindRpcAuth::UserPass(_, password) = &mut bitcoind_config.rpc_auth {
+            if !password.is_empty() {
+                store.set(&bitcoind_rpc_key(config.bitcoin_config.network), password)?;
+                password.clear();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Fill the RPC password of the managed bitcoind in the daemon configuration, if it was moved to
+/// the store.
+pub fn load_rpc_password(
+    store: &impl SecretStore,
+    config: &mut Config,
+) -> Result<(), KeychainError> {
+    if let Some(BitcoinBackend::Bitcoind(bitcoind_config)) = &mut config.bitcoin_backend {
+        if let BitcoindRpcAuth::UserPass(_, password) = &mut bitcoind_config.rpc_auth {
+            if password.is_empty() {
+                if let Some(stored) = store.get(&bitcoind_rpc_key(config.bitcoin_config.network))? {
+                    *password = stored;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A keychain which is never available.
+    struct NoKeychain;
+
+    impl SecretStore for NoKeychain {
+        fn get(&self, _key: &str) -> Result<Option<String>, KeychainError> {
+            Err(keyring::Error::PlatformFailure("no keychain".into()).into())
+        }
+        fn set(&self, _key: &str, _secret: &str) -> Result<(), KeychainError> {
+            Err(keyring::Error::PlatformFailure("no keychain".into()).into())
+        }
+        fn delete(&self, _key: &str) -> Result<(), KeychainError> {
+            Err(keyring::Error::PlatformFailure("no keychain".into()).into())
+        }
+    }
+
+    #[test]
+    fn secrets_file_fallback() {
+        let path = std::env::temp_dir().join(format!("liana-secrets-{}.json", std::process::id()));
+        let secrets = Secrets {
+            keychain: NoKeychain,
+            file: FileStore::new(path.clone()),
+        };
+
+        assert_eq!(secrets.get("signet/bitcoind-rpc").unwrap(), None);
+        secrets.set("signet/bitcoind-rpc", "password").unwrap();
+        secrets.set("bitcoin/bitcoind-rpc", "other").unwrap();
+        assert_eq!(
+            secrets.get("signet/bitcoind-rpc").unwrap().as_deref(),
+            Some("password")
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        secrets.delete("signet/bitcoind-rpc").unwrap();
+        assert_eq!(secrets.get("signet/bitcoind-rpc").unwrap(), None);
+        assert_eq!(
+            secrets.get("bitcoin/bitcoind-rpc").unwrap().as_deref(),
+            Some("other")
+        );
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf, process};
 
 use iced::{
     alignment::Horizontal,
@@ -6,15 +6,23 @@ use iced::{
     Alignment, Length, Subscription, Task,
 };
 
-use liana::miniscript::bitcoin::Network;
+use liana::miniscript::bitcoin::{Amount, Network};
 use liana_ui::{
-    component::{button, card, modal::Modal, network_banner, notification, text::*},
+    component::{
+        amount::amount, button, card, modal::Modal, network_badge, network_banner, notification,
+        text::*,
+    },
     icon, image, theme,
     widget::*,
 };
 use lianad::config::ConfigError;
 
-use crate::{app, installer::UserFlow};
+use crate::{
+    app::{self, settings::AccentColor, view::accent_color},
+    installer::UserFlow,
+    ipc,
+    lianalite::session,
+};
 
 const NETWORKS: [Network; 4] = [
     Network::Bitcoin,
@@ -34,12 +42,28 @@ pub enum State {
     NoWallet,
 }
 
+/// A wallet of the data directory.
+#[derive(Debug, Clone)]
+pub struct WalletEntry {
+    network: Network,
+    name: Option<String>,
+    /// The balance when the wallet was last opened.
+    balance: Option<Amount>,
+    accent_color: Option<AccentColor>,
+}
+
 pub struct Launcher {
     state: State,
     network: Network,
     datadir_path: PathBuf,
     error: Option<String>,
+    // Why the user is asked to choose a wallet, if not only to open it.
+    notice: Option<String>,
     delete_wallet_modal: Option<DeleteWalletModal>,
+    // The wallets of all the networks.
+    wallets: Vec<WalletEntry>,
+    // The wallets opened in another window from this one.
+    windows: HashMap<Network, process::Child>,
 }
 
 impl Launcher {
@@ -57,15 +81,33 @@ impl Launcher {
                 network,
                 datadir_path: datadir_path.clone(),
                 error: None,
+                notice: None,
                 delete_wallet_modal: None,
+                wallets: Vec::new(),
+                windows: HashMap::new(),
             },
-            Task::perform(
-                check_network_datadir(datadir_path.clone(), network),
-                Message::Checked,
-            ),
+            Task::batch([
+                Task::perform(
+                    check_network_datadir(datadir_path.clone(), network),
+                    Message::Checked,
+                ),
+                Task::perform(list_wallets(datadir_path), Message::WalletsListed),
+            ]),
         )
     }
 
+    // Whether this wallet is open in another window started from this one.
+    fn is_open_in_window(&mut self, network: Network) -> bool {
+        self.windows
+            .get_mut(&network)
+            .map(|child| matches!(child.try_wait(), Ok(None)))
+            .unwrap_or(false)
+    }
+
+    pub fn set_notice(&mut self, notice: String) {
+        self.notice = Some(notice);
+    }
+
     pub fn stop(&mut self) {}
 
     pub fn subscription(&self) -> Subscription<Message> {
@@ -119,6 +161,34 @@ impl Launcher {
             }
             Message::View(ViewMessage::DeleteWallet(DeleteWalletMessage::Deleted)) => {
                 self.state = State::NoWallet;
+                Task::perform(
+                    list_wallets(self.datadir_path.clone()),
+                    Message::WalletsListed,
+                )
+            }
+            Message::WalletsListed(wallets) => {
+                self.wallets = wallets;
+                Task::none()
+            }
+            Message::View(ViewMessage::OpenInNewWindow(network)) => {
+                // The same wallet must not be run twice at the same time.
+                if self.is_open_in_window(network) {
+                    return Task::none();
+                }
+                match std::env::current_exe().and_then(|exe| {
+                    process::Command::new(exe)
+                        .arg("--datadir")
+                        .arg(&self.datadir_path)
+                        .arg(format!("--{}", network))
+                        .spawn()
+                }) {
+                    Ok(child) => {
+                        self.windows.insert(network, child);
+                    }
+                    Err(e) => {
+                        self.error = Some(format!("Failed to open a new window: {}", e));
+                    }
+                }
                 Task::none()
             }
             Message::View(ViewMessage::DeleteWallet(DeleteWalletMessage::CloseModal)) => {
@@ -136,7 +206,19 @@ impl Launcher {
                 }
             },
             Message::View(ViewMessage::Run) => {
-                if matches!(self.state, State::Wallet { .. }) {
+                if self.is_open_in_window(self.network) {
+                    self.error = Some("This wallet is already open in another window.".to_string());
+                    Task::none()
+                } else if ipc::send(
+                    &self.datadir_path.join(self.network.to_string()),
+                    &ipc::Request::Focus,
+                )
+                .is_ok()
+                {
+                    // It was opened from another launcher or by the system, bring it to the front.
+                    self.error = Some("This wallet is already open in another window.".to_string());
+                    Task::none()
+                } else if matches!(self.state, State::Wallet { .. }) {
                     let datadir_path = self.datadir_path.clone();
                     let mut path = self.datadir_path.clone();
                     path.push(self.network.to_string());
@@ -195,6 +277,7 @@ impl Launcher {
                             } else {
                                 text("Welcome").size(50).bold()
                             })
+                            .push_maybe(self.notice.as_ref().map(|n| card::simple(text(n))))
                             .push_maybe(self.error.as_ref().map(|e| card::simple(text(e))))
                             .push(match &self.state {
                                 State::Unchecked => Column::new(),
@@ -210,13 +293,7 @@ impl Launcher {
                                                     Column::new()
                                                         .push(p1_bold(format!(
                                                             "My Liana {} wallet",
-                                                            match self.network {
-                                                                Network::Bitcoin => "Bitcoin",
-                                                                Network::Signet => "Signet",
-                                                                Network::Testnet => "Testnet",
-                                                                Network::Regtest => "Regtest",
-                                                                _ => "",
-                                                            }
+                                                            network_name(self.network)
                                                         )))
                                                         .push_maybe(checksum.as_ref().map(
                                                             |checksum| {
@@ -317,6 +394,76 @@ impl Launcher {
                     )
                     .center_x(Length::Fill),
                 )
+                .push_maybe((self.wallets.len() > 1).then(|| {
+                    Container::new(
+                        self.wallets
+                            .iter()
+                            .fold(
+                                Column::new()
+                                    .spacing(10)
+                                    .padding(20)
+                                    .push(h4_bold("Your wallets")),
+                                |col, wallet| {
+                                    col.push(
+                                        Row::new()
+                                            .spacing(10)
+                                            .align_y(Alignment::Center)
+                                            .push(
+                                                Container::new(
+                                                    Button::new(
+                                                        Row::new()
+                                                            .spacing(10)
+                                                            .align_y(Alignment::Center)
+                                                            .push(network_badge(
+                                                                wallet.network,
+                                                                accent_color(wallet.accent_color),
+                                                            ))
+                                                            .push(
+                                                                Column::new()
+                                                                    .push(p1_bold(format!(
+                                                                        "My Liana {} wallet",
+                                                                        network_name(
+                                                                            wallet.network
+                                                                        )
+                                                                    )))
+                                                                    .push_maybe(
+                                                                        wallet.name.as_ref().map(
+                                                                            |name| {
+                                                                                p1_regular(name)
+                                                                                .style(
+                                                                                theme::text::secondary,
+                                                                            )
+                                                                            },
+                                                                        ),
+                                                                    ),
+                                                            )
+                                                            .push(Space::with_width(Length::Fill))
+                                                            .push_maybe(
+                                                                wallet.balance.as_ref().map(amount),
+                                                            ),
+                                                    )
+                                                    .on_press(ViewMessage::SelectNetwork(
+                                                        wallet.network,
+                                                    ))
+                                                    .padding(15)
+                                                    .style(theme::button::container_border)
+                                                    .width(Length::Fill),
+                                                )
+                                                .style(theme::card::simple),
+                                            )
+                                            .push(
+                                                button::secondary(None, "Open in new window")
+                                                    .on_press(ViewMessage::OpenInNewWindow(
+                                                        wallet.network,
+                                                    )),
+                                            ),
+                                    )
+                                },
+                            )
+                            .max_width(700),
+                    )
+                    .center_x(Length::Fill)
+                }))
                 .push(Space::with_height(Length::Fixed(100.0))),
         ))
         .map(Message::View);
@@ -342,6 +489,7 @@ pub enum Message {
     View(ViewMessage),
     Install(PathBuf, Network, UserFlow),
     Checked(Result<State, String>),
+    WalletsListed(Vec<WalletEntry>),
     Run(PathBuf, app::config::Config, Network),
 }
 
@@ -354,6 +502,7 @@ pub enum ViewMessage {
     StartInstall(Network),
     Check,
     Run,
+    OpenInNewWindow(Network),
     DeleteWallet(DeleteWalletMessage),
 }
 
@@ -388,6 +537,24 @@ impl DeleteWalletModal {
     fn update(&mut self, message: Message) -> Task<Message> {
         if let Message::View(ViewMessage::DeleteWallet(DeleteWalletMessage::Confirm)) = message {
             self.warning = None;
+            // Forget the sessions of the remote backend accounts used on this network.
+            if let Some(datadir) = self.wallet_datadir.parent() {
+                if let Ok(settings) =
+                    app::settings::Settings::from_file(datadir.to_path_buf(), self.network)
+                {
+                    for account in settings.remote_backend_accounts() {
+                        if let Err(e) =
+                            session::delete_refresh_token(datadir, self.network, &account.email)
+                        {
+                            tracing::warn!(
+                                "Failed to delete the session of {}: {}",
+                                account.email,
+                                e
+                            );
+                        }
+                    }
+                }
+            }
             if let Err(e) = std::fs::remove_dir_all(&self.wallet_datadir) {
                 self.warning = Some(e);
             } else {
@@ -500,12 +667,7 @@ async fn check_network_datadir(path: PathBuf, network: Network) -> Result<State,
         ConfigError::UnexpectedDescriptor(_) => {
             "There is an issue with the configuration for this network. You most likely use a descriptor containing one or more public key(s) without origin. Liana v0.2 and later only support public keys with origins. Please migrate your funds using Liana v0.1.".to_string()
         }
-        ConfigError::Unexpected(e) => {
-            format!(
-                "Unexpected {}",
-                e,
-            )
-        }
+        e @ ConfigError::Invalid(_) => e.to_string(),
     })?;
     }
 
@@ -524,3 +686,45 @@ async fn check_network_datadir(path: PathBuf, network: Network) -> Result<State,
         email: None,
     })
 }
+
+fn network_name(network: Network) -> &'static str {
+    match network {
+        Network::Bitcoin => "Bitcoin",
+        Network::Signet => "Signet",
+        Network::Testnet => "Testnet",
+        Network::Regtest => "Regtest",
+        _ => "",
+    }
+}
+
+/// The wallets of all the networks in the data directory.
+async fn list_wallets(datadir_path: PathBuf) -> Vec<WalletEntry> {
+    // The balances would be shown to whoever starts the application.
+    let app_lock = app::settings::global::LockSettings::from_file(&datadir_path)
+        .ok()
+        .flatten()
+        .is_some();
+    NETWORKS
+        .iter()
+        .filter(|network| {
+            datadir_path
+                .join(network.to_string())
+                .join(app::config::DEFAULT_FILE_NAME)
+                .exists()
+        })
+        .map(|network| {
+            let wallet = app::settings::Settings::from_file(datadir_path.clone(), *network)
+                .ok()
+                .and_then(|s| s.wallets.into_iter().next());
+            WalletEntry {
+                network: *network,
+                name: wallet.as_ref().map(|w| w.name.clone()),
+                accent_color: wallet.as_ref().and_then(|w| w.accent_color),
+                balance: wallet
+                    .filter(|w| !app_lock && !w.hide_amounts)
+                    .and_then(|w| w.last_balance)
+                    .map(Amount::from_sat),
+            }
+        })
+        .collect()
+}
@@ -36,7 +36,7 @@ pub struct AccessTokenResponse {
 
 #[derive(Debug, Clone)]
 pub struct AuthClient {
-    http: reqwest::Client,
+    pub(super) http: reqwest::Client,
     url: String,
     api_public_key: String,
     pub email: String,
@@ -68,9 +68,9 @@ impl From<Error> for AuthError {
 }
 
 impl AuthClient {
-    pub fn new(url: String, api_public_key: String, email: String) -> Self {
+    pub fn new(http: reqwest::Client, url: String, api_public_key: String, email: String) -> Self {
         AuthClient {
-            http: reqwest::Client::new(),
+            http,
             url,
             api_public_key,
             email,
@@ -1,27 +1,28 @@
 pub mod api;
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
     path::Path,
-    sync::Arc,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use async_trait::async_trait;
 use chrono::Utc;
 use liana::{
     descriptors::LianaDescriptor,
-    miniscript::bitcoin::{address, psbt::Psbt, Address, Network, OutPoint, Txid},
+    miniscript::bitcoin::{address, psbt::Psbt, Address, Amount, Network, OutPoint, Txid},
 };
 use lianad::{
     commands::{CoinStatus, GetInfoDescriptors, LCSpendInfo, LabelItem},
     config::Config,
 };
-use reqwest::{Error, IntoUrl, Method, RequestBuilder, Response};
+use reqwest::{header::HeaderMap, Error, IntoUrl, Method, RequestBuilder, Response, StatusCode};
 use tokio::sync::RwLock;
 
 use crate::{
     app::settings::{AuthConfig, Settings},
-    daemon::{model::*, Daemon, DaemonBackend, DaemonError},
+    daemon::{cache, model::*, Daemon, DaemonBackend, DaemonError},
     hw::HardwareWalletConfig,
 };
 
@@ -56,6 +57,116 @@ fn request<U: IntoUrl>(
     req
 }
 
+/// How many times a request failing because of a transient issue is retried.
+const MAX_RETRIES: u32 = 3;
+
+/// The delay before the first retry, doubled for each subsequent one.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// The longest the backend may ask us to wait for before retrying a rate-limited request.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+// The delay the backend asks us to wait for before retrying, from the `Retry-After` or the
+// rate-limit headers. They are in seconds, but some servers give the reset time of the limit as a
+// timestamp.
+fn retry_after(headers: &HeaderMap, now: i64) -> Option<Duration> {
+    let secs = ["retry-after", "ratelimit-reset", "x-ratelimit-reset"]
+        .iter()
+        .find_map(|name| {
+            headers
+                .get(*name)?
+                .to_str()
+                .ok()?
+                .trim()
+                .parse::<i64>()
+                .ok()
+        })?;
+    let secs = if secs > 1_000_000_000 {
+        secs - now
+    } else {
+        secs
+    };
+    Some(Duration::from_secs(secs.max(0) as u64))
+}
+
+/// Whether this error means the backend couldn't be reached.
+fn is_offline(e: &Error) -> bool {
+    e.is_connect() || e.is_timeout()
+}
+
+/// Send the request, retrying it with an exponential backoff if it failed because of a transient
+/// issue. A request is only retried if it's safe to: if it didn't reach the backend, if it was
+/// rate limited or if it's idempotent.
+async fn send_with_retry(mut req: RequestBuilder) -> Result<Response, Error> {
+    let is_idempotent = req
+        .try_clone()
+        .and_then(|req| req.build().ok())
+        .map(|req| req.method() != Method::POST && req.method() != Method::PATCH)
+        .unwrap_or(false);
+    let mut delay = RETRY_BASE_DELAY;
+    let mut retries = 0;
+    loop {
+        // Requests with a streamed body can't be cloned, and so can't be retried.
+        let next = if retries < MAX_RETRIES {
+            req.try_clone()
+        } else {
+            None
+        };
+        let res = req.send().await;
+        let next = match next {
+            Some(next) => next,
+            None => return res,
+        };
+        let wait = match &res {
+            Err(e) if e.is_connect() || (e.is_timeout() && is_idempotent) => delay,
+            Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                retry_after(response.headers(), Utc::now().timestamp()).unwrap_or(delay)
+            }
+            Ok(response) if response.status().is_server_error() && is_idempotent => {
+                retry_after(response.headers(), Utc::now().timestamp()).unwrap_or(delay)
+            }
+            _ => return res,
+        };
+        if wait > MAX_RETRY_AFTER {
+            return res;
+        }
+        tracing::warn!(
+            "Request to the backend failed ({}), retrying in {}ms.",
+            match &res {
+                Ok(response) => response.status().to_string(),
+                Err(e) => e.to_string(),
+            },
+            wait.as_millis()
+        );
+        tokio::time::sleep(wait).await;
+        req = next;
+        retries += 1;
+        delay *= 2;
+    }
+}
+
+trait SendWithRetry {
+    async fn send_with_retry(self) -> Result<Response, Error>;
+}
+
+impl SendWithRetry for RequestBuilder {
+    async fn send_with_retry(self) -> Result<Response, Error> {
+        send_with_retry(self).await
+    }
+}
+
+// Turn an unsuccessful response into an error.
+async fn check_status(response: Response) -> Result<Response, DaemonError> {
+    if response.status().is_success() {
+        Ok(response)
+    } else {
+        Err(DaemonError::Http(
+            Some(response.status().into()),
+            response.text().await?,
+        ))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BackendClient {
     pub auth: Arc<RwLock<auth::AccessTokenResponse>>,
@@ -75,14 +186,15 @@ impl BackendClient {
         credentials: auth::AccessTokenResponse,
         network: Network,
     ) -> Result<Self, DaemonError> {
-        let http = reqwest::Client::new();
+        // Use the same client as for authenticating, which enforces any pinned certificate.
+        let http = auth_client.http.clone();
         let response = request(
             &http,
             Method::GET,
             format!("{}/v1/me", url),
             &credentials.access_token,
         )
-        .send()
+        .send_with_retry()
         .await?;
         if !response.status().is_success() {
             return Err(DaemonError::NoAnswer);
@@ -117,6 +229,7 @@ impl BackendClient {
                 curve: secp256k1::Secp256k1::verification_only(),
                 wallet_uuid: wallet.id.clone(),
                 wallet_desc: wallet.descriptor.to_owned(),
+                queue: Arc::new(Mutex::new(VecDeque::new())),
             },
             wallet,
         )
@@ -131,7 +244,7 @@ impl BackendClient {
         let response = self
             .request(Method::GET, &format!("{}/v1/wallets", self.url))
             .await
-            .send()
+            .send_with_retry()
             .await?;
         if !response.status().is_success() {
             return Err(DaemonError::Http(
@@ -153,7 +266,7 @@ impl BackendClient {
             .request(Method::POST, &format!("{}/v1/wallets", self.url))
             .await
             .json(&api::payload::CreateWallet { name, descriptor })
-            .send()
+            .send_with_retry()
             .await?;
         if !response.status().is_success() {
             return Err(DaemonError::Http(
@@ -203,7 +316,7 @@ impl BackendClient {
                         }),
                         fingerprint_aliases: None,
                     })
-                    .send()
+                    .send_with_retry()
                     .await?;
 
                 if !response.status().is_success() {
@@ -243,7 +356,7 @@ impl BackendClient {
                             .collect(),
                     ),
                 })
-                .send()
+                .send_with_retry()
                 .await?;
 
             if !response.status().is_success() {
@@ -267,7 +380,7 @@ impl BackendClient {
                 &format!("{}/v1/invitations/{}", self.url, invitation_id),
             )
             .await
-            .send()
+            .send_with_retry()
             .await?;
         if !response.status().is_success() {
             return Err(DaemonError::Http(
@@ -286,7 +399,7 @@ impl BackendClient {
                 &format!("{}/v1/invitations/{}/accept", self.url, invitation_id),
             )
             .await
-            .send()
+            .send_with_retry()
             .await?;
         if !response.status().is_success() {
             return Err(DaemonError::Http(
@@ -299,12 +412,22 @@ impl BackendClient {
     }
 }
 
+/// A write to the backend which isn't critical to the flow the user is in, and can be sent later
+/// if the backend can't be reached.
+#[derive(Debug, Clone)]
+enum QueuedWrite {
+    Labels(HashMap<LabelItem, Option<String>>),
+    Psbt(Psbt),
+}
+
 #[derive(Debug, Clone)]
 pub struct BackendWalletClient {
     inner: BackendClient,
     wallet_uuid: String,
     wallet_desc: LianaDescriptor,
     curve: secp256k1::Secp256k1<secp256k1::VerifyOnly>,
+    /// The writes waiting for the backend to be reachable again, oldest first.
+    queue: Arc<Mutex<VecDeque<QueuedWrite>>>,
 }
 
 impl BackendWalletClient {
@@ -328,6 +451,92 @@ impl BackendWalletClient {
         self.inner.user_email()
     }
 
+    async fn send_write(&self, write: &QueuedWrite) -> Result<Response, Error> {
+        match write {
+            QueuedWrite::Labels(items) => {
+                self.inner
+                    .request(
+                        Method::POST,
+                        &format!("{}/v1/wallets/{}/labels", self.inner.url, self.wallet_uuid),
+                    )
+                    .await
+                    .json(&api::payload::Labels {
+                        labels: items
+                            .iter()
+                            .map(|(item, value)| api::payload::Label {
+                                item: item.to_string(),
+                                value: value.clone(),
+                            })
+                            .collect(),
+                    })
+                    .send_with_retry()
+                    .await
+            }
+            QueuedWrite::Psbt(psbt) => {
+                self.inner
+                    .request(
+                        Method::POST,
+                        &format!("{}/v1/wallets/{}/psbts", self.inner.url, self.wallet_uuid),
+                    )
+                    .await
+                    .json(&api::payload::ImportPsbt {
+                        psbt: psbt.to_string(),
+                    })
+                    .send_with_retry()
+                    .await
+            }
+        }
+    }
+
+    /// Send this write to the backend, or queue it to be sent once the backend is reachable
+    /// again.
+    async fn write_or_queue(&self, write: QueuedWrite) -> Result<(), DaemonError> {
+        // Don't reorder the writes: if some are waiting, this one must be sent after them.
+        let is_queue_empty = self.queue.lock().unwrap().is_empty();
+        if !is_queue_empty {
+            self.queue.lock().unwrap().push_back(write);
+            self.flush_queue().await;
+            return Ok(());
+        }
+        match self.send_write(&write).await {
+            Ok(response) => check_status(response).await.map(|_| ()),
+            Err(e) if is_offline(&e) => {
+                tracing::warn!("Backend unreachable, queuing the write: {}", e);
+                self.queue.lock().unwrap().push_back(write);
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Send the queued writes, oldest first, until the backend can't be reached.
+    pub async fn flush_queue(&self) {
+        loop {
+            let write = self.queue.lock().unwrap().front().cloned();
+            let write = match write {
+                Some(write) => write,
+                None => return,
+            };
+            match self.send_write(&write).await {
+                Err(e) if is_offline(&e) => return,
+                Ok(response) if response.status().is_server_error() => {
+                    tracing::warn!(
+                        "Backend error sending a queued write: {}",
+                        response.status()
+                    );
+                    return;
+                }
+                Err(e) => tracing::error!("Dropping queued write {:?}: {}", write, e),
+                Ok(response) => {
+                    if let Err(e) = check_status(response).await {
+                        tracing::error!("Queued write {:?} was rejected: {}", write, e);
+                    }
+                }
+            }
+            self.queue.lock().unwrap().pop_front();
+        }
+    }
+
     async fn get_wallet(&self) -> Result<api::Wallet, DaemonError> {
         let list = self.inner.list_wallets().await?;
         let wallet = list
@@ -357,7 +566,7 @@ impl BackendWalletClient {
             )
             .await
             .query(&query)
-            .send()
+            .send_with_retry()
             .await?;
 
         if !response.status().is_success() {
@@ -400,7 +609,7 @@ impl BackendWalletClient {
             )
             .await
             .query(&query)
-            .send()
+            .send_with_retry()
             .await?;
 
         if !response.status().is_success() {
@@ -436,7 +645,7 @@ impl BackendWalletClient {
             )
             .await
             .query(&query)
-            .send()
+            .send_with_retry()
             .await?;
 
         if !response.status().is_success() {
@@ -483,7 +692,7 @@ impl BackendWalletClient {
             )
             .await
             .query(&query)
-            .send()
+            .send_with_retry()
             .await?;
 
         if !response.status().is_success() {
@@ -543,11 +752,13 @@ impl Daemon for BackendWalletClient {
                             false
                         }
                     }) {
-                        wallet_settings.remote_backend_auth = Some(AuthConfig {
-                            email: self.inner.auth_client.email.clone(),
-                            wallet_id: self.wallet_id(),
-                            refresh_token: new.refresh_token.clone(),
-                        });
+                        wallet_settings.remote_backend_auth = Some(AuthConfig::new(
+                            datadir,
+                            network,
+                            self.inner.auth_client.email.clone(),
+                            self.wallet_id(),
+                            new.refresh_token.clone(),
+                        ));
                     } else {
                         tracing::info!("Wallet id was not found in the settings");
                     }
@@ -566,6 +777,7 @@ impl Daemon for BackendWalletClient {
                 }
             }
         }
+        self.flush_queue().await;
         Ok(())
     }
 
@@ -585,9 +797,11 @@ impl Daemon for BackendWalletClient {
             },
             sync: 1.0,
             rescan_progress: None,
+            rescan_checkpoint: None,
             timestamp: wallet.created_at as u32,
             // We can ignore this field for remote backend as the wallet should remain synced.
             last_poll_timestamp: None,
+            poller: None,
         })
     }
 
@@ -602,7 +816,7 @@ impl Daemon for BackendWalletClient {
                 ),
             )
             .await
-            .send()
+            .send_with_retry()
             .await?;
 
         if !response.status().is_success() {
@@ -644,6 +858,7 @@ impl Daemon for BackendWalletClient {
                     outpoint: c.outpoint,
                     block_height: c.block_height,
                     is_immature: c.is_immature,
+                    maturity_height: None,
                     is_change: c.is_change_address,
                     spend_info: c.spend_info.map(|info| LCSpendInfo {
                         txid: info.txid,
@@ -657,13 +872,43 @@ impl Daemon for BackendWalletClient {
 
     async fn list_spend_txs(&self) -> Result<ListSpendResult, DaemonError> {
         let res = self.list_psbts(&[]).await?;
+        // The drafts spending each coin, to tell those conflicting with each other.
+        let mut drafts_by_outpoint: HashMap<OutPoint, Vec<Txid>> = HashMap::new();
+        for psbt in &res.psbts {
+            for txin in &psbt.raw.unsigned_tx.input {
+                drafts_by_outpoint
+                    .entry(txin.previous_output)
+                    .or_default()
+                    .push(psbt.txid);
+            }
+        }
         Ok(ListSpendResult {
             spend_txs: res
                 .psbts
                 .into_iter()
-                .map(|psbt| ListSpendEntry {
-                    psbt: psbt.raw,
-                    updated_at: Some(psbt.updated_at as u32),
+                .map(|psbt| {
+                    let conflicting_spends = psbt
+                        .raw
+                        .unsigned_tx
+                        .input
+                        .iter()
+                        .filter_map(|txin| drafts_by_outpoint.get(&txin.previous_output))
+                        .flatten()
+                        .filter(|txid| **txid != psbt.txid)
+                        .copied()
+                        .collect::<HashSet<_>>()
+                        .into_iter()
+                        .collect();
+                    ListSpendEntry {
+                        psbt: psbt.raw,
+                        updated_at: Some(psbt.updated_at as u32),
+                        conflicting_spends,
+                        // Told from the coins of the draft.
+                        invalidated_by: None,
+                        // The remote backend doesn't relay the reviews of the signers.
+                        reviews: Vec::new(),
+                        review_state: ReviewState::Pending,
+                    }
                 })
                 .collect(),
         })
@@ -714,6 +959,7 @@ impl Daemon for BackendWalletClient {
         destinations: &HashMap<Address<address::NetworkUnchecked>, u64>,
         feerate_vb: u64,
         change_address: Option<Address<address::NetworkUnchecked>>,
+        _allow_future_witness: bool,
     ) -> Result<CreateSpendResult, DaemonError> {
         let mut recipients: Vec<api::payload::Recipient> = destinations
             .iter()
@@ -746,7 +992,7 @@ impl Daemon for BackendWalletClient {
                 inputs: coins_outpoints,
                 recipients,
             })
-            .send()
+            .send_with_retry()
             .await?;
 
         let res: api::DraftPsbtResult = response.json().await?;
@@ -786,7 +1032,7 @@ impl Daemon for BackendWalletClient {
                 feerate: feerate_vb,
                 save: false,
             })
-            .send()
+            .send_with_retry()
             .await?;
 
         let res: api::DraftPsbtResult = response.json().await?;
@@ -805,27 +1051,7 @@ impl Daemon for BackendWalletClient {
     }
 
     async fn update_spend_tx(&self, psbt: &Psbt) -> Result<(), DaemonError> {
-        let response: Response = self
-            .inner
-            .request(
-                Method::POST,
-                &format!("{}/v1/wallets/{}/psbts", self.inner.url, self.wallet_uuid),
-            )
-            .await
-            .json(&api::payload::ImportPsbt {
-                psbt: psbt.to_string(),
-            })
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            return Err(DaemonError::Http(
-                Some(response.status().into()),
-                response.text().await?,
-            ));
-        }
-
-        Ok(())
+        self.write_or_queue(QueuedWrite::Psbt(psbt.clone())).await
     }
 
     async fn delete_spend_tx(&self, txid: &Txid) -> Result<(), DaemonError> {
@@ -847,7 +1073,7 @@ impl Daemon for BackendWalletClient {
                 &format!("{}/v1/psbts/{}", self.inner.url, psbt.uuid),
             )
             .await
-            .send()
+            .send_with_retry()
             .await?;
 
         if !response.status().is_success() {
@@ -860,7 +1086,12 @@ impl Daemon for BackendWalletClient {
         Ok(())
     }
 
-    async fn broadcast_spend_tx(&self, txid: &Txid) -> Result<(), DaemonError> {
+    // The spend confirmation is only enforced by the GUI with this backend.
+    async fn broadcast_spend_tx(
+        &self,
+        txid: &Txid,
+        _confirmed_amount: Option<Amount>,
+    ) -> Result<(), DaemonError> {
         let psbt = self
             .list_psbts(&[*txid])
             .await?
@@ -876,7 +1107,7 @@ impl Daemon for BackendWalletClient {
                 &format!("{}/v1/psbts/{}/broadcast", self.inner.url, psbt.uuid),
             )
             .await
-            .send()
+            .send_with_retry()
             .await?;
 
         if !response.status().is_success() {
@@ -916,7 +1147,7 @@ impl Daemon for BackendWalletClient {
                     .ok_or(DaemonError::Unexpected("Missing sequence".to_string()))?,
                 address,
             })
-            .send()
+            .send_with_retry()
             .await?;
 
         let res: api::DraftPsbt = response.json().await?;
@@ -941,7 +1172,7 @@ impl Daemon for BackendWalletClient {
                 )
                 .await
                 .query(&[("items", chunk.join(","))])
-                .send()
+                .send_with_retry()
                 .await?;
 
             if !response.status().is_success() {
@@ -962,33 +1193,12 @@ impl Daemon for BackendWalletClient {
         &self,
         items: &HashMap<LabelItem, Option<String>>,
     ) -> Result<(), DaemonError> {
-        let response: Response = self
-            .inner
-            .request(
-                Method::POST,
-                &format!("{}/v1/wallets/{}/labels", self.inner.url, self.wallet_uuid),
-            )
+        self.write_or_queue(QueuedWrite::Labels(items.clone()))
             .await
-            .json(&api::payload::Labels {
-                labels: items
-                    .iter()
-                    .map(|(item, value)| api::payload::Label {
-                        item: item.to_string(),
-                        value: value.clone(),
-                    })
-                    .collect(),
-            })
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            return Err(DaemonError::Http(
-                Some(response.status().into()),
-                response.text().await?,
-            ));
-        }
+    }
 
-        Ok(())
+    fn queued_writes(&self) -> usize {
+        self.queue.lock().unwrap().len()
     }
 
     async fn list_history_txs(
@@ -1057,12 +1267,21 @@ impl Daemon for BackendWalletClient {
             }
             spend_txs
         } else {
-            self.list_psbts(&[])
+            let mut spend_txs = self
+                .list_psbts(&[])
                 .await?
                 .psbts
                 .into_iter()
                 .map(|tx| spend_tx_from_api(tx, &self.wallet_desc, &self.curve, self.inner.network))
-                .collect()
+                .collect::<Vec<_>>();
+            set_conflicting_spends(&mut spend_txs);
+            cache::retain(
+                &spend_txs
+                    .iter()
+                    .map(|tx| tx.psbt.unsigned_tx.compute_txid())
+                    .collect::<Vec<_>>(),
+            );
+            spend_txs
         };
         spend_txs.sort_by(|a, b| {
             if a.status == b.status {
@@ -1099,7 +1318,7 @@ impl Daemon for BackendWalletClient {
             )
             .await
             .json(&api::payload::CreateWalletInvitation { email })
-            .send()
+            .send_with_retry()
             .await?;
         if !response.status().is_success() {
             return Err(DaemonError::Http(
@@ -1129,6 +1348,7 @@ fn history_tx_from_api(value: api::Transaction, network: Network) -> HistoryTran
                     outpoint: c.outpoint,
                     block_height: c.block_height,
                     is_immature: c.is_immature,
+                    maturity_height: None,
                     is_change: c.is_change_address,
                     spend_info: c.spend_info.clone().map(|info| LCSpendInfo {
                         txid: info.txid,
@@ -1185,6 +1405,7 @@ fn spend_tx_from_api(
                     outpoint: c.outpoint,
                     block_height: c.block_height,
                     is_immature: c.is_immature,
+                    maturity_height: None,
                     is_change: c.is_change_address,
                     spend_info: c.spend_info.clone().map(|info| LCSpendInfo {
                         txid: info.txid,
@@ -1218,3 +1439,44 @@ fn spend_tx_from_api(
     tx.load_labels(&labels);
     tx
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_after_headers() {
+        let now = 1_800_000_000;
+        let headers = |name: &'static str, value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(name, value.parse().unwrap());
+            headers
+        };
+        assert_eq!(retry_after(&HeaderMap::new(), now), None);
+        assert_eq!(
+            retry_after(&headers("retry-after", "5"), now),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            retry_after(&headers("ratelimit-reset", " 12 "), now),
+            Some(Duration::from_secs(12))
+        );
+        // A reset timestamp, possibly already passed.
+        assert_eq!(
+            retry_after(&headers("x-ratelimit-reset", "1800000003"), now),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(
+            retry_after(&headers("x-ratelimit-reset", "1799999990"), now),
+            Some(Duration::ZERO)
+        );
+        // HTTP dates are not supported.
+        assert_eq!(
+            retry_after(
+                &headers("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT"),
+                now
+            ),
+            None
+        );
+    }
+}
@@ -1,10 +1,19 @@
 pub mod auth;
 pub mod backend;
+pub mod tls;
 
-use liana::miniscript::bitcoin;
+use std::str::FromStr;
+
+use liana::miniscript::bitcoin::{
+    self,
+    hashes::{sha256, Hash},
+    secp256k1::{self, schnorr, XOnlyPublicKey},
+};
 
 use serde::Deserialize;
 
+use crate::app::settings::global::RemoteBackendSettings;
+
 const LIANALITE_SIGNET_URL: &str = "https://api.signet.lianalite.com";
 const LIANALITE_MAINNET_URL: &str = "https://api.lianalite.com";
 
@@ -14,28 +23,230 @@ struct ServiceConfigResource {
     pub auth_api_public_key: String,
 }
 
+/// The service configuration as signed by the operator of the backend. It commits to the backend
+/// it is served by, so it can't be replayed for another one.
+#[derive(Debug, Clone, Deserialize)]
+struct ServiceManifest {
+    pub backend_api_url: String,
+    pub network: bitcoin::Network,
+    pub auth_api_url: String,
+    pub auth_api_public_key: String,
+    /// Timestamp after which the manifest must not be trusted anymore.
+    pub expires_at: i64,
+}
+
+/// The serialized service manifest and the operator's signature of its SHA256 hash.
 #[derive(Debug, Clone, Deserialize)]
+struct SignedServiceManifest {
+    manifest: String,
+    signature: String,
+}
+
+#[derive(Debug, Clone)]
 pub struct ServiceConfig {
     pub auth_api_url: String,
     pub auth_api_public_key: String,
     pub backend_api_url: String,
+    /// The HTTP client to use with the services, which enforces the pinned certificates.
+    pub http: reqwest::Client,
+}
+
+#[derive(Debug)]
+pub enum ServiceConfigError {
+    Http(reqwest::Error),
+    Settings(String),
+    InvalidManifest(String),
+}
+
+impl ServiceConfigError {
+    pub fn status(&self) -> Option<reqwest::StatusCode> {
+        match self {
+            Self::Http(e) => e.status(),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ServiceConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Http(e) => write!(f, "{}", e),
+            Self::Settings(e) => write!(f, "Invalid remote backend settings: {}", e),
+            Self::InvalidManifest(e) => write!(f, "Invalid service manifest: {}", e),
+        }
+    }
+}
+
+impl From<reqwest::Error> for ServiceConfigError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Http(e)
+    }
+}
+
+// Check the manifest was signed with the given key and is valid for this backend, and parse it.
+fn verify_manifest(
+    signed: &SignedServiceManifest,
+    key: &XOnlyPublicKey,
+    backend_api_url: &str,
+    network: bitcoin::Network,
+    now: i64,
+) -> Result<ServiceManifest, ServiceConfigError> {
+    let msg = secp256k1::Message::from_digest(
+        sha256::Hash::hash(signed.manifest.as_bytes()).to_byte_array(),
+    );
+    let sig = hex::decode(&signed.signature)
+        .ok()
+        .and_then(|sig| schnorr::Signature::from_slice(&sig).ok())
+        .ok_or_else(|| ServiceConfigError::InvalidManifest("invalid signature".to_string()))?;
+    secp256k1::Secp256k1::verification_only()
+        .verify_schnorr(&sig, &msg, key)
+        .map_err(|_| ServiceConfigError::InvalidManifest("wrong signature".to_string()))?;
+
+    let manifest: ServiceManifest = serde_json::from_str(&signed.manifest)
+        .map_err(|e| ServiceConfigError::InvalidManifest(e.to_string()))?;
+    if manifest.backend_api_url.trim_end_matches('/') != backend_api_url.trim_end_matches('/') {
+        return Err(ServiceConfigError::InvalidManifest(format!(
+            "signed for backend '{}'",
+            manifest.backend_api_url
+        )));
+    }
+    if manifest.network != network {
+        return Err(ServiceConfigError::InvalidManifest(format!(
+            "signed for network '{}'",
+            manifest.network
+        )));
+    }
+    if manifest.expires_at <= now {
+        return Err(ServiceConfigError::InvalidManifest("expired".to_string()));
+    }
+    Ok(manifest)
 }
 
 pub async fn get_service_config(
     network: bitcoin::Network,
-) -> Result<ServiceConfig, reqwest::Error> {
-    let backend_api_url = if network == bitcoin::Network::Bitcoin {
-        LIANALITE_MAINNET_URL
+    settings: &RemoteBackendSettings,
+) -> Result<ServiceConfig, ServiceConfigError> {
+    let backend_api_url = match &settings.url {
+        Some(url) => url.trim_end_matches('/'),
+        None if network == bitcoin::Network::Bitcoin => LIANALITE_MAINNET_URL,
+        None => LIANALITE_SIGNET_URL,
+    };
+    let http = tls::http_client(settings).map_err(ServiceConfigError::Settings)?;
+
+    let res = if let Some(key) = &settings.manifest_public_key {
+        let key = XOnlyPublicKey::from_str(key)
+            .map_err(|e| ServiceConfigError::Settings(format!("manifest public key: {}", e)))?;
+        let signed: SignedServiceManifest = http
+            .get(format!("{}/v1/desktop/manifest", backend_api_url))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let manifest = verify_manifest(
+            &signed,
+            &key,
+            backend_api_url,
+            network,
+            chrono::Utc::now().timestamp(),
+        )?;
+        ServiceConfigResource {
+            auth_api_url: manifest.auth_api_url,
+            auth_api_public_key: manifest.auth_api_public_key,
+        }
     } else {
-        LIANALITE_SIGNET_URL
+        http.get(format!("{}/v1/desktop", backend_api_url))
+            .send()
+            .await?
+            .json()
+            .await?
     };
-    let res: ServiceConfigResource = reqwest::get(format!("{}/v1/desktop", backend_api_url))
-        .await?
-        .json()
-        .await?;
     Ok(ServiceConfig {
         auth_api_url: res.auth_api_url,
         auth_api_public_key: res.auth_api_public_key,
         backend_api_url: backend_api_url.to_string(),
+        http,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn service_manifest_signature() {
+        let secp = secp256k1::Secp256k1::new();
+        let keypair = |i: u8| {
+            let sk = secp256k1::SecretKey::from_slice(&[i; 32]).unwrap();
+            secp256k1::Keypair::from_secret_key(&secp, &sk)
+        };
+        let (operator, other) = (keypair(1), keypair(2));
+        let key = operator.x_only_public_key().0;
+        let manifest = r#"{"backend_api_url":"https://liana.example.com/","network":"signet","auth_api_url":"https://auth.example.com","auth_api_public_key":"pubkey","expires_at":2000000000}"#;
+        let msg = secp256k1::Message::from_digest(
+            sha256::Hash::hash(manifest.as_bytes()).to_byte_array(),
+        );
+        let signed = |signer: &secp256k1::Keypair| SignedServiceManifest {
+            manifest: manifest.to_string(),
+            signature: hex::encode(secp.sign_schnorr_no_aux_rand(&msg, signer).serialize()),
+        };
+        let now = 1_800_000_000;
+
+        let res = verify_manifest(
+            &signed(&operator),
+            &key,
+            "https://liana.example.com",
+            bitcoin::Network::Signet,
+            now,
+        )
+        .unwrap();
+        assert_eq!(res.auth_api_url, "https://auth.example.com");
+
+        // Signed by another key, for another backend or network, or expired.
+        for (signer, url, network, now) in [
+            (
+                &other,
+                "https://liana.example.com",
+                bitcoin::Network::Signet,
+                now,
+            ),
+            (
+                &operator,
+                "https://mitm.example.com",
+                bitcoin::Network::Signet,
+                now,
+            ),
+            (
+                &operator,
+                "https://liana.example.com",
+                bitcoin::Network::Bitcoin,
+                now,
+            ),
+            (
+                &operator,
+                "https://liana.example.com",
+                bitcoin::Network::Signet,
+                2_000_000_000,
+            ),
+        ] {
+            assert!(matches!(
+                verify_manifest(&signed(signer), &key, url, network, now),
+                Err(ServiceConfigError::InvalidManifest(_))
+            ));
+        }
+
+        // A tampered manifest isn't accepted.
+        let mut tampered = signed(&operator);
+        tampered.manifest = tampered
+            .manifest
+            .replace("auth.example.com", "evil.example.com");
+        assert!(verify_manifest(
+            &tampered,
+            &key,
+            "https://liana.example.com",
+            bitcoin::Network::Signet,
+            now
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,185 @@
+//! Pinning of the certificates of the remote backend.
+//!
+//! Users pointing the application to a self-hosted instance of the backend may pin the
+//! certificates its servers present, or their public key. Connections to servers presenting none
+//! of them are refused, even if the certificates are otherwise valid.
+
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use liana::miniscript::bitcoin::hashes::{sha256, Hash};
+use rustls::{
+    client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier},
+    Certificate, ClientConfig, Error, OwnedTrustAnchor, RootCertStore, ServerName,
+};
+
+use crate::app::settings::global::RemoteBackendSettings;
+
+// Read the DER element at the start of `der`. Returns the whole element, its content and what
+// follows it.
+fn der_element(der: &[u8]) -> Option<(&[u8], &[u8], &[u8])> {
+    let first_len = *der.get(1)?;
+    let (len, header_len) = if first_len & 0x80 == 0 {
+        (first_len as usize, 2)
+    } else {
+        let len_len = (first_len & 0x7f) as usize;
+        if len_len == 0 || len_len > 4 {
+            return None;
+        }
+        let len = der
+            .get(2..2 + len_len)?
+            .iter()
+            .fold(0, |len, byte| (len << 8) | *byte as usize);
+        (len, 2 + len_len)
+    };
+    let end = header_len.checked_add(len)?;
+    let element = der.get(..end)?;
+    Some((element, &element[header_len..], &der[end..]))
+}
+
+/// The DER-encoded SubjectPublicKeyInfo of this DER-encoded X.509 certificate.
+fn subject_public_key_info(cert: &[u8]) -> Option<&[u8]> {
+    let (_, cert, _) = der_element(cert)?;
+    let (_, mut tbs_cert, _) = der_element(cert)?;
+    // Skip the version, if present, then the serial number, the signature algorithm, the issuer,
+    // the validity and the subject.
+    if tbs_cert.first() == Some(&0xa0) {
+        tbs_cert = der_element(tbs_cert)?.2;
+    }
+    for _ in 0..5 {
+        tbs_cert = der_element(tbs_cert)?.2;
+    }
+    der_element(tbs_cert).map(|(spki, _, _)| spki)
+}
+
+/// Whether this certificate, or its public key, is one of the pinned ones.
+fn is_pinned(cert: &Certificate, pins: &[sha256::Hash]) -> bool {
+    pins.contains(&sha256::Hash::hash(&cert.0))
+        || subject_public_key_info(&cert.0)
+            .map(|spki| pins.contains(&sha256::Hash::hash(spki)))
+            .unwrap_or(false)
+}
+
+/// Verifies the certificate chain presented by a server contains one of the pinned certificates.
+///
+/// A pinned end-entity certificate is trusted as is, which allows the use of self-signed
+/// certificates. Otherwise the chain must be valid and one of the intermediate certificates
+/// pinned.
+struct PinnedCertVerifier {
+    webpki: WebPkiVerifier,
+    pins: Vec<sha256::Hash>,
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, Error> {
+        if is_pinned(end_entity, &self.pins) {
+            return Ok(ServerCertVerified::assertion());
+        }
+        self.webpki.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            scts,
+            ocsp_response,
+            now,
+        )?;
+        if intermediates.iter().any(|cert| is_pinned(cert, &self.pins)) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            tracing::error!(
+                "Server {:?} presented no pinned certificate. Refusing to connect.",
+                server_name
+            );
+            Err(Error::General(
+                "The server presented no pinned certificate".to_string(),
+            ))
+        }
+    }
+}
+
+/// After how long a request to the remote backend is considered failed, and may be retried.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Build the HTTP client used to talk to the remote backend, enforcing the pinned certificates
+/// if there are any.
+pub fn http_client(settings: &RemoteBackendSettings) -> Result<reqwest::Client, String> {
+    let builder = reqwest::Client::builder().timeout(REQUEST_TIMEOUT);
+    if settings.pinned_sha256.is_empty() {
+        return builder.build().map_err(|e| e.to_string());
+    }
+    let pins = settings
+        .pinned_sha256
+        .iter()
+        .map(|pin| {
+            pin.parse::<sha256::Hash>()
+                .map_err(|e| format!("Invalid pinned hash '{}': {}", pin, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut roots = RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(
+            ta.subject,
+            ta.spki,
+            ta.name_constraints,
+        )
+    }));
+    let tls_config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(PinnedCertVerifier {
+            webpki: WebPkiVerifier::new(roots, None),
+            pins,
+        }))
+        .with_no_client_auth();
+    builder
+        .use_preconfigured_tls(tls_config)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+
+    // A self-signed certificate for "backend.example.com".
+    const CERT: &str = "MIIBkTCCATegAwIBAgIUW5WQLfd5RPUa/P3fTOaGul9BWnYwCgYIKoZIzj0EAwIwHjEcMBoGA1UEAwwTYmFja2VuZC5leGFtcGxlLmNvbTAeFw0yNjEwMTUxMjI0MDdaFw0zNjEwMTIxMjI0MDdaMB4xHDAaBgNVBAMME2JhY2tlbmQuZXhhbXBsZS5jb20wWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAARbvEOpHfvZG0IMsM3HtL7vOzVC4QQ1Jw2wgOr9EvMQIgC0qhjF24oj3FrVJUeYiXLHAXRdF3jRPGf5UvvhrkTPo1MwUTAdBgNVHQ4EFgQUyTth+6cZKdS0baAjq1qDNiyQE3UwHwYDVR0jBBgwFoAUyTth+6cZKdS0baAjq1qDNiyQE3UwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBFAiEAyySiHX9qcVxfKELkkn1UKnmrzU6dMvDGwzCP3i4xelMCIDlOL5IjARqKSiDxBJ+RPLIuKFmP1lCdnTBpJDZEbu5U";
+    // Computed with `openssl x509 -outform DER | sha256sum`.
+    const CERT_SHA256: &str = "ca74112ae4bb76bbe33d33e697f218cf1b1c828f4170bf5d9b4fcae6a8166a5c";
+    // Computed with `openssl x509 -pubkey -noout | openssl pkey -pubin -outform DER | sha256sum`.
+    const SPKI_SHA256: &str = "b7b14046c9eb599fe51ab4e7726f5c11b67333bb95af27d71281bd58f3678023";
+
+    #[test]
+    fn certificate_pins() {
+        let cert = Certificate(
+            base64::engine::general_purpose::STANDARD
+                .decode(CERT)
+                .unwrap(),
+        );
+        let spki = subject_public_key_info(&cert.0).unwrap();
+        assert_eq!(
+            sha256::Hash::hash(spki),
+            SPKI_SHA256.parse::<sha256::Hash>().unwrap()
+        );
+
+        for pin in [CERT_SHA256, SPKI_SHA256] {
+            assert!(is_pinned(&cert, &[pin.parse().unwrap()]));
+        }
+        assert!(!is_pinned(&cert, &[sha256::Hash::hash(b"another key")]));
+        assert!(!is_pinned(&cert, &[]));
+
+        // Truncated certificates are not parsed.
+        assert!(subject_public_key_info(&cert.0[..cert.0.len() / 2]).is_none());
+        assert!(subject_public_key_info(&[]).is_none());
+    }
+}
@@ -10,7 +10,12 @@ use liana_ui::{
 };
 
 use crate::{
-    app::settings::{AuthConfig, Settings, SettingsError, WalletSetting},
+    app::{
+        preferences::Preferences,
+        settings::{
+            global::RemoteBackendSettings, AuthConfig, Settings, SettingsError, WalletSetting,
+        },
+    },
     daemon::DaemonError,
 };
 
@@ -73,6 +78,9 @@ pub enum Message {
 
 #[derive(Debug, Clone)]
 pub enum ViewMessage {
+    SwitchAccount,
+    SelectAccount(String),
+    UseAnotherEmail,
     RequestOTP,
     EditEmail,
     EmailEdited(String),
@@ -91,6 +99,8 @@ pub struct LianaLiteLogin {
     pub network: Network,
 
     wallet_id: String,
+    // The accounts the user signed in with before.
+    accounts: Vec<AuthConfig>,
 
     processing: bool,
     step: ConnectionStep,
@@ -103,6 +113,10 @@ pub struct LianaLiteLogin {
 
 pub enum ConnectionStep {
     CheckingAuthFile,
+    SelectAccount {
+        // The email of the account being connected to.
+        selected: Option<String>,
+    },
     EnterEmail {
         email: form::Value<String>,
     },
@@ -116,6 +130,7 @@ pub enum ConnectionStep {
 
 impl LianaLiteLogin {
     pub fn new(datadir: PathBuf, network: Network, settings: Settings) -> (Self, Task<Message>) {
+        let accounts = settings.remote_backend_accounts();
         match settings
             .wallets
             .first()
@@ -132,60 +147,121 @@ impl LianaLiteLogin {
                         email: form::Value::default(),
                     },
                     wallet_id: String::new(),
+                    accounts,
                     connection_error: Some(e),
                     auth_error: None,
                     processing: true,
                 },
                 Task::none(),
             ),
-            Ok(auth_config) => (
+            // Let the user pick the account to use if they signed in with several.
+            Ok(auth_config) if accounts.len() > 1 => (
                 Self {
+                    network,
+                    datadir,
+                    step: ConnectionStep::SelectAccount { selected: None },
+                    connection_error: None,
+                    wallet_id: auth_config.wallet_id,
+                    accounts,
+                    auth_error: None,
+                    processing: false,
+                },
+                Task::none(),
+            ),
+            Ok(auth_config) => {
+                let mut login = Self {
                     network,
                     datadir,
                     step: ConnectionStep::CheckingAuthFile,
                     connection_error: None,
                     wallet_id: auth_config.wallet_id.clone(),
+                    accounts,
                     auth_error: None,
                     processing: true,
-                },
-                Task::perform(
-                    async move {
-                        let service_config = super::client::get_service_config(network)
-                            .await
-                            .map_err(|e| Error::Unexpected(e.to_string()))?;
-                        let client = AuthClient::new(
-                            service_config.auth_api_url,
-                            service_config.auth_api_public_key,
-                            auth_config.email,
-                        );
-                        connect_with_refresh_token(
-                            client,
-                            auth_config.refresh_token,
-                            auth_config.wallet_id,
-                            service_config.backend_api_url,
-                            network,
-                        )
-                        .await
-                    },
-                    Message::Connected,
-                ),
-            ),
+                };
+                let task = login.connect_account(auth_config);
+                (login, task)
+            }
         }
     }
 
+    /// Connect to the backend with the stored session of this account.
+    fn connect_account(&mut self, auth_config: AuthConfig) -> Task<Message> {
+        let datadir = self.datadir.clone();
+        let network = self.network;
+        self.wallet_id = auth_config.wallet_id.clone();
+        self.processing = true;
+        self.connection_error = None;
+        self.auth_error = None;
+        Task::perform(
+            async move {
+                let refresh_token =
+                    auth_config
+                        .refresh_token(&datadir, network)
+                        .ok_or_else(|| {
+                            Error::Unexpected(format!("No session found for {}", auth_config.email))
+                        })?;
+                let backend_settings =
+                    RemoteBackendSettings::from_file(&datadir).map_err(Error::Unexpected)?;
+                let service_config = super::client::get_service_config(network, &backend_settings)
+                    .await
+                    .map_err(|e| Error::Unexpected(e.to_string()))?;
+                let client = AuthClient::new(
+                    service_config.http,
+                    service_config.auth_api_url,
+                    service_config.auth_api_public_key,
+                    auth_config.email,
+                );
+                connect_with_refresh_token(
+                    client,
+                    refresh_token,
+                    auth_config.wallet_id,
+                    service_config.backend_api_url,
+                    network,
+                )
+                .await
+            },
+            Message::Connected,
+        )
+    }
+
+    /// Record the session with the wallet in the settings and run it.
+    fn run(&self, client: BackendWalletClient, wallet: api::Wallet) -> Task<Message> {
+        let datadir = self.datadir.clone();
+        let network = self.network;
+        Task::perform(
+            async move {
+                update_wallet_auth_settings(
+                    datadir,
+                    network,
+                    wallet.clone(),
+                    client.user_email().to_string(),
+                    client.auth().await.refresh_token,
+                )
+                .await?;
+
+                Ok((client, wallet))
+            },
+            Message::Run,
+        )
+    }
+
     pub fn update(&mut self, message: Message) -> Task<Message> {
         match &mut self.step {
-            ConnectionStep::CheckingAuthFile => {
-                if let Message::Connected(res) = message {
+            ConnectionStep::CheckingAuthFile => match message {
+                Message::View(ViewMessage::SwitchAccount) => {
+                    // The result of the ongoing connection will be ignored.
+                    self.processing = false;
+                    self.step = ConnectionStep::SelectAccount { selected: None };
+                }
+                Message::Connected(res) => {
                     self.processing = false;
                     match res {
                         Ok(BackendState::NoWallet(_)) => {
                             self.auth_error = Some("No wallet found for the given email");
                         }
                         Ok(BackendState::WalletExists(client, wallet)) => {
-                            return Task::perform(async move { (client, wallet) }, |(c, w)| {
-                                Message::Run(Ok((c, w)))
-                            });
+                            return self.run(client, wallet);
                         }
                         Err(e) => {
                             self.connection_error = Some(e);
@@ -195,8 +271,62 @@ impl LianaLiteLogin {
                         }
                     }
                 }
-            }
+                Message::Run(Err(e)) => {
+                    self.connection_error = Some(e);
+                }
+                _ => {}
+            },
+            ConnectionStep::SelectAccount { selected } => match message {
+                Message::View(ViewMessage::SelectAccount(email)) => {
+                    if let Some(auth_config) =
+                        self.accounts.iter().find(|a| a.email == email).cloned()
+                    {
+                        *selected = Some(email);
+                        return self.connect_account(auth_config);
+                    }
+                }
+                Message::View(ViewMessage::UseAnotherEmail) => {
+                    self.processing = false;
+                    self.connection_error = None;
+                    self.step = ConnectionStep::EnterEmail {
+                        email: form::Value::default(),
+                    };
+                }
+                // Ignore the result of a connection the user gave up on.
+                Message::Connected(res) if selected.is_some() => {
+                    self.processing = false;
+                    match res {
+                        Ok(BackendState::NoWallet(client)) => {
+                            return Task::perform(async move { Some(client) }, Message::Install);
+                        }
+                        Ok(BackendState::WalletExists(client, wallet)) => {
+                            return self.run(client, wallet);
+                        }
+                        Err(e) => {
+                            // The session may have expired, the user must sign in again.
+                            tracing::warn!("{}", e);
+                            self.connection_error = Some(e);
+                            self.step = ConnectionStep::EnterEmail {
+                                email: form::Value {
+                                    value: selected.take().unwrap_or_default(),
+                                    valid: true,
+                                },
+                            };
+                        }
+                    }
+                }
+                Message::Run(Err(e)) => {
+                    self.processing = false;
+                    self.connection_error = Some(e);
+                }
+                _ => {}
+            },
             ConnectionStep::EnterEmail { email } => match message {
+                Message::View(ViewMessage::SwitchAccount) => {
+                    self.processing = false;
+                    self.connection_error = None;
+                    self.step = ConnectionStep::SelectAccount { selected: None };
+                }
                 Message::View(ViewMessage::EmailEdited(value)) => {
                     email.valid = value.is_empty()
                         || email_address::EmailAddress::parse_with_options(
@@ -212,23 +342,28 @@ impl LianaLiteLogin {
                     } else if email.valid {
                         let email = email.value.clone();
                         let network = self.network;
+                        let datadir = self.datadir.clone();
                         self.processing = true;
                         self.connection_error = None;
                         self.auth_error = None;
                         return Task::perform(
                             async move {
-                                let config = super::client::get_service_config(network)
-                                    .await
-                                    .map_err(|e| {
-                                        if e.status() == Some(reqwest::StatusCode::NOT_FOUND) {
-                                            Error::Unexpected(
-                                                "Remote servers are unresponsive".to_string(),
-                                            )
-                                        } else {
-                                            Error::Unexpected(e.to_string())
-                                        }
-                                    })?;
+                                let backend_settings = RemoteBackendSettings::from_file(&datadir)
+                                    .map_err(Error::Unexpected)?;
+                                let config =
+                                    super::client::get_service_config(network, &backend_settings)
+                                        .await
+                                        .map_err(|e| {
+                                            if e.status() == Some(reqwest::StatusCode::NOT_FOUND) {
+                                                Error::Unexpected(
+                                                    "Remote servers are unresponsive".to_string(),
+                                                )
+                                            } else {
+                                                Error::Unexpected(e.to_string())
+                                            }
+                                        })?;
                                 let client = AuthClient::new(
+                                    config.http,
                                     config.auth_api_url,
                                     config.auth_api_public_key,
                                     email,
@@ -324,23 +459,7 @@ impl LianaLiteLogin {
                             return Task::perform(async move { Some(client) }, Message::Install);
                         }
                         Ok(BackendState::WalletExists(client, wallet)) => {
-                            let datadir = self.datadir.clone();
-                            let network = self.network;
-                            return Task::perform(
-                                async move {
-                                    update_wallet_auth_settings(
-                                        datadir,
-                                        network,
-                                        wallet.clone(),
-                                        client.user_email().to_string(),
-                                        client.auth().await.refresh_token,
-                                    )
-                                    .await?;
-
-                                    Ok((client, wallet))
-                                },
-                                Message::Run,
-                            );
+                            return self.run(client, wallet);
                         }
                         Err(e) => {
                             tracing::warn!("{}", e);
@@ -385,7 +504,62 @@ impl LianaLiteLogin {
                                     .max_width(500)
                                     .spacing(20)
                                     .push(match &self.step {
-                                        ConnectionStep::CheckingAuthFile => Column::new(),
+                                        ConnectionStep::CheckingAuthFile => {
+                                            Column::new().align_x(Alignment::Center).push(
+                                                button::secondary(None, "Use another account")
+                                                    .on_press(ViewMessage::SwitchAccount),
+                                            )
+                                        }
+                                        ConnectionStep::SelectAccount { selected } => self
+                                            .accounts
+                                            .iter()
+                                            .fold(
+                                                Column::new()
+                                                    .spacing(20)
+                                                    .push(text("Choose the account to use")),
+                                                |col, account| {
+                                                    col.push(
+                                                        Button::new(
+                                                            Row::new()
+                                                                .push(p1_regular(&account.email))
+                                                                .push(Space::with_width(
+                                                                    Length::Fill,
+                                                                ))
+                                                                .push_maybe(
+                                                                    (selected.as_ref()
+                                                                        == Some(&account.email))
+                                                                    .then(|| {
+                                                                        p1_regular("Connecting...")
+                                                                            .style(
+                                                                            theme::text::secondary,
+                                                                        )
+                                                                    }),
+                                                                ),
+                                                        )
+                                                        .on_press_maybe(if self.processing {
+                                                            None
+                                                        } else {
+                                                            Some(ViewMessage::SelectAccount(
+                                                                account.email.clone(),
+                                                            ))
+                                                        })
+                                                        .padding(15)
+                                                        .style(theme::button::container_border)
+                                                        .width(Length::Fill),
+                                                    )
+                                                },
+                                            )
+                                            .push(
+                                                button::secondary(
+                                                    Some(icon::plus_icon()),
+                                                    "Use another email",
+                                                )
+                                                .on_press_maybe(if self.processing {
+                                                    None
+                                                } else {
+                                                    Some(ViewMessage::UseAnotherEmail)
+                                                }),
+                                            ),
                                         ConnectionStep::EnterEmail { email } => Column::new()
                                             .spacing(20)
                                             .push_maybe(
@@ -400,13 +574,27 @@ impl LianaLiteLogin {
                                                 .padding(10)
                                                 .warning("Email is not valid"),
                                             )
-                                            .push(button::secondary(None, "Next").on_press_maybe(
-                                                if self.processing {
-                                                    None
-                                                } else {
-                                                    Some(ViewMessage::RequestOTP)
-                                                },
-                                            )),
+                                            .push(
+                                                Row::new()
+                                                    .spacing(10)
+                                                    .push_maybe((!self.accounts.is_empty()).then(
+                                                        || {
+                                                            button::secondary(
+                                                                Some(icon::previous_icon()),
+                                                                "Saved accounts",
+                                                            )
+                                                            .on_press(ViewMessage::SwitchAccount)
+                                                        },
+                                                    ))
+                                                    .push(
+                                                        button::secondary(None, "Next")
+                                                            .on_press_maybe(if self.processing {
+                                                                None
+                                                            } else {
+                                                                Some(ViewMessage::RequestOTP)
+                                                            }),
+                                                    ),
+                                            ),
                                         ConnectionStep::EnterOtp { otp, .. } => Column::new()
                                             .push(text("An authentication was send to your email"))
                                             .push_maybe(
@@ -492,20 +680,25 @@ async fn update_wallet_auth_settings(
         .expect("Failed to get checksum from a valid LianaDescriptor")
         .to_string();
 
-    let remote_backend_auth = Some(AuthConfig {
+    let remote_backend_auth = Some(AuthConfig::new(
+        &datadir,
+        network,
         email,
-        wallet_id: wallet.id.clone(),
+        wallet.id.clone(),
         refresh_token,
-    });
+    ));
 
-    if let Some(wallet_settings) = settings.wallets.iter_mut().find(|w| {
+    // The wallet used last is the first one, it is the one connected to on next start.
+    if let Some(i) = settings.wallets.iter().position(|w| {
         if let Some(auth) = &w.remote_backend_auth {
             auth.wallet_id == wallet.id
         } else {
             false
         }
     }) {
+        let mut wallet_settings = settings.wallets.remove(i);
         wallet_settings.remote_backend_auth = remote_backend_auth;
+        settings.wallets.insert(0, wallet_settings);
     } else {
         tracing::info!("Wallet id was not found in the settings, adding now the wallet settings to the settings.json file");
         settings.wallets.insert(
@@ -516,6 +709,13 @@ async fn update_wallet_auth_settings(
                 keys: Vec::new(),
                 hardware_wallets: Vec::new(),
                 remote_backend_auth,
+                last_balance: None,
+                accent_color: None,
+                spend_confirmation: None,
+                block_explorer: None,
+                remote_signer: None,
+                hide_amounts: false,
+                preferences: Preferences::default(),
             },
         );
     }
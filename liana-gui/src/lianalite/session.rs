@@ -0,0 +1,41 @@
+//! Sessions with the remote backend.
+//!
+//! The refresh token of each account the user signed in with is kept in the secrets store, so the
+//! user can switch between accounts without asking for a new token by email every time.
+
+use std::path::Path;
+
+use liana::miniscript::bitcoin::Network;
+
+use crate::keychain::{self, KeychainError, SecretStore};
+
+/// Store the refresh token of this account, replacing the previous one.
+pub fn store_refresh_token(
+    datadir: &Path,
+    network: Network,
+    email: &str,
+    refresh_token: &str,
+) -> Result<(), KeychainError> {
+    keychain::secrets(datadir).set(
+        &keychain::remote_backend_session_key(network, email),
+        refresh_token,
+    )
+}
+
+/// The stored refresh token of this account, if any.
+pub fn refresh_token(
+    datadir: &Path,
+    network: Network,
+    email: &str,
+) -> Result<Option<String>, KeychainError> {
+    keychain::secrets(datadir).get(&keychain::remote_backend_session_key(network, email))
+}
+
+/// Forget the session of this account.
+pub fn delete_refresh_token(
+    datadir: &Path,
+    network: Network,
+    email: &str,
+) -> Result<(), KeychainError> {
+    keychain::secrets(datadir).delete(&keychain::remote_backend_session_key(network, email))
+}
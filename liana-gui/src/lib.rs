@@ -1,16 +1,30 @@
 pub mod app;
+pub mod clipboard;
 pub mod daemon;
 pub mod datadir;
 pub mod download;
 pub mod export;
+pub mod file_drop;
 pub mod hw;
+pub mod i18n;
 pub mod installer;
+pub mod ipc;
+pub mod keychain;
 pub mod launcher;
 pub mod lianalite;
 pub mod loader;
 pub mod logger;
 pub mod node;
+pub mod payment_request;
+pub mod psbt_format;
+pub mod qr_signer;
+pub mod remote_signer;
 pub mod signer;
+pub mod tapsigner;
+#[cfg(feature = "tray")]
+pub mod tray;
+pub mod update;
+pub mod uri_handler;
 pub mod utils;
 
 use lianad::Version;
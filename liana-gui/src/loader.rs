@@ -30,8 +30,13 @@ use crate::{
         wallet::{Wallet, WalletError},
     },
     daemon::{client, embedded::EmbeddedDaemon, model::*, Daemon, DaemonError},
-    node::bitcoind::{
-        internal_bitcoind_debug_log_path, stop_bitcoind, Bitcoind, StartInternalBitcoindError,
+    keychain,
+    node::{
+        bitcoind::{
+            internal_bitcoind_debug_log_path, start_bitcoind_service, stop_bitcoind, Bitcoind,
+            StartInternalBitcoindError,
+        },
+        service,
     },
 };
 
@@ -269,8 +274,16 @@ impl Loader {
             log::info!("Stopping managed bitcoind..");
             bitcoind.stop();
             log::info!("Managed bitcoind stopped.");
-        } else if self.waiting_daemon_bitcoind && self.gui_config.start_internal_bitcoind {
-            if let Ok(config) = Config::from_file(self.gui_config.daemon_config_path.clone()) {
+        } else if self.waiting_daemon_bitcoind
+            && self.gui_config.start_internal_bitcoind
+            && !service::is_installed(&self.datadir_path, self.network)
+        {
+            if let Ok(mut config) = Config::from_file(self.gui_config.daemon_config_path.clone()) {
+                if let Err(e) =
+                    keychain::load_rpc_password(&keychain::secrets(&self.datadir_path), &mut config)
+                {
+                    warn!("Failed to load the RPC password: {}", e);
+                }
                 if let Some(BitcoinBackend::Bitcoind(bitcoind_config)) = &config.bitcoin_backend {
                     let mut retry = 0;
                     while !stop_bitcoind(bitcoind_config) && retry < 10 {
@@ -418,6 +431,8 @@ pub async fn load_application(
         // Both last poll fields start with the same value.
         last_poll_timestamp: info.last_poll_timestamp,
         last_poll_at_startup: info.last_poll_timestamp,
+        accent_color: wallet.accent_color,
+        block_explorer: wallet.block_explorer.clone(),
         ..Default::default()
     };
 
@@ -534,7 +549,15 @@ pub async fn start_bitcoind_and_daemon(
     liana_datadir_path: PathBuf,
     start_internal_bitcoind: bool,
 ) -> StartedResult {
-    let config = Config::from_file(Some(config_path)).map_err(Error::Config)?;
+    let mut config = Config::from_file(Some(config_path)).map_err(Error::Config)?;
+    keychain::load_rpc_password(&keychain::secrets(&liana_datadir_path), &mut config).map_err(
+        |e| {
+            Error::Config(ConfigError::ReadingFile(format!(
+                "Loading the RPC password: {}",
+                e
+            )))
+        },
+    )?;
     let mut bitcoind: Option<Bitcoind> = None;
     if start_internal_bitcoind {
         if let Some(BitcoinBackend::Bitcoind(bitcoind_config)) = &config.bitcoin_backend {
@@ -542,6 +565,11 @@ pub async fn start_bitcoind_and_daemon(
             if lianad::BitcoinD::new(bitcoind_config, "internal_bitcoind_start".to_string()).is_ok()
             {
                 info!("Internal bitcoind is already running");
+            } else if service::is_installed(&liana_datadir_path, config.bitcoin_config.network) {
+                // The service manager supervises bitcoind, it must not be stopped with the GUI.
+                info!("Starting internal bitcoind service");
+                start_bitcoind_service(&config.bitcoin_config.network, bitcoind_config)
+                    .map_err(Error::Bitcoind)?;
             } else {
                 info!("Starting internal bitcoind");
                 bitcoind = Some(
@@ -10,7 +10,7 @@ use iced::{
     event::{self, Event},
     keyboard,
     widget::{focus_next, focus_previous},
-    Settings, Size, Subscription, Task,
+    Point, Settings, Size, Subscription, Task,
 };
 use tracing::{error, info};
 use tracing_subscriber::filter::LevelFilter;
@@ -22,10 +22,15 @@ use liana_ui::{component::text, font, image, theme, widget::Element};
 use lianad::config::Config as DaemonConfig;
 
 use liana_gui::{
-    app::{self, cache::Cache, config::default_datadir, wallet::Wallet, App},
+    app::{
+        self, cache::Cache, config::default_datadir, preferences::WindowGeometry, wallet::Wallet,
+        App,
+    },
     datadir,
+    file_drop::{self, DroppedFile, DroppedFileError},
     hw::HardwareWalletConfig,
-    installer::{self, Installer},
+    installer::{self, manifest, manifest::Manifest, Installer},
+    ipc,
     launcher::{self, Launcher},
     lianalite::{
         client::{backend::api, backend::BackendWalletClient},
@@ -33,14 +38,22 @@ use liana_gui::{
     },
     loader::{self, Loader},
     logger::Logger,
+    payment_request::PaymentRequest,
     VERSION,
 };
 
+const PAYMENT_LINK_SCHEME: &str = "bitcoin:";
+
 #[derive(Debug, PartialEq)]
 enum Arg {
     ConfigPath(PathBuf),
     DatadirPath(PathBuf),
     Network(bitcoin::Network),
+    Install(PathBuf),
+    /// A `bitcoin:` payment link, passed by the operating system when Liana is its handler.
+    PaymentRequest(String),
+    /// A file to import, as if it was dropped onto the window.
+    File(PathBuf),
 }
 
 fn parse_args(args: Vec<String>) -> Result<Vec<Arg>, Box<dyn Error>> {
@@ -54,17 +67,23 @@ fn parse_args(args: Vec<String>) -> Result<Vec<Arg>, Box<dyn Error>> {
     if args.len() > 1 && (args[1] == "--help" || args[1] == "-h") {
         eprintln!(
             r#"
-Usage: liana-gui [OPTIONS]
+Usage: liana-gui [OPTIONS] [bitcoin:<address>?<params> | <FILE>]
 
 Options:
     --conf <PATH>       Path of configuration file (gui.toml)
     --datadir <PATH>    Path of liana datadir
+    --install <PATH>    Install the wallet described by the manifest (TOML or JSON) without
+                        user interaction, and print the resulting configuration
     -v, --version       Display liana-gui version
     -h, --help          Print help
     --bitcoin           Use bitcoin network
     --testnet           Use testnet network
     --signet            Use signet network
     --regtest           Use regtest network
+
+A bitcoin: payment link opens the send screen prefilled, and a file (PSBT, transaction,
+descriptor...) is imported as if dropped onto the window. If Liana is already running, its window
+is brought to the front and handed over the link or file instead of starting another instance.
         "#
         );
         process::exit(1);
@@ -83,6 +102,22 @@ Options:
             } else {
                 return Err("missing arg to --datadir".into());
             }
+        } else if arg == "--install" {
+            if let Some(a) = args.get(i + 1) {
+                res.push(Arg::Install(PathBuf::from(a)));
+            } else {
+                return Err("missing arg to --install".into());
+            }
+        } else if arg
+            .get(..PAYMENT_LINK_SCHEME.len())
+            .is_some_and(|scheme| scheme.eq_ignore_ascii_case(PAYMENT_LINK_SCHEME))
+        {
+            res.push(Arg::PaymentRequest(arg.clone()));
+        } else if i > 0
+            && !arg.starts_with('-')
+            && !matches!(args[i - 1].as_str(), "--conf" | "--datadir" | "--install")
+        {
+            res.push(Arg::File(PathBuf::from(arg)));
         } else if arg.contains("--") {
             let network = bitcoin::Network::from_str(args[i].trim_start_matches("--"))?;
             res.push(Arg::Network(network));
@@ -97,6 +132,17 @@ pub struct GUI {
     logger: Logger,
     // if set up, it overrides the level filter of the logger.
     log_level: Option<LevelFilter>,
+    #[cfg(feature = "tray")]
+    tray: Option<liana_gui::tray::Tray>,
+    // The payment links and files to open once the wallet is running.
+    pending_requests: Vec<ipc::Request>,
+    // Receive the requests of the processes started while this one is running, for the data
+    // directory if started on the launcher and for the wallet once running.
+    ipc: Vec<ipc::Server>,
+    // The size and position of the window, saved along with the preferences of the wallet when
+    // it's closed.
+    window_size: Option<Size>,
+    window_position: Option<Point>,
 }
 
 enum State {
@@ -110,6 +156,10 @@ enum State {
 #[derive(Debug)]
 pub enum Key {
     Tab(bool),
+    /// The command key along with a character, to open a menu of the wallet.
+    Shortcut(app::menu::Menu),
+    /// The command and shift keys along with H, to mask or reveal the amounts.
+    ToggleAmounts,
 }
 
 #[derive(Debug)]
@@ -123,6 +173,14 @@ pub enum Message {
     Login(Box<login::Message>),
     KeyPressed(Key),
     Event(iced::Event),
+    /// A file was dropped onto the window.
+    FileDropped(PathBuf),
+    FileRead(Result<DroppedFile, DroppedFileError>),
+    WindowResized(Size),
+    WindowMoved(Point),
+    #[cfg(feature = "tray")]
+    TrayTick,
+    IpcTick,
 }
 
 impl From<Result<(), iced::font::Error>> for Message {
@@ -147,12 +205,33 @@ impl GUI {
         }
     }
 
-    fn new((config, log_level): (Config, Option<LevelFilter>)) -> (GUI, Task<Message>) {
+    fn new(
+        (config, log_level, requests, ipc): (
+            Config,
+            Option<LevelFilter>,
+            Vec<ipc::Request>,
+            Option<ipc::Server>,
+        ),
+    ) -> (GUI, Task<Message>) {
         let logger = Logger::setup(log_level.unwrap_or(LevelFilter::INFO));
         let mut cmds = vec![Task::perform(ctrl_c(), |_| Message::CtrlC)];
+        let datadir_path = match &config {
+            Config::Launcher(datadir_path) | Config::Run(datadir_path, ..) => datadir_path,
+        };
+        match app::settings::global::DisplaySettings::from_file(datadir_path) {
+            Ok(display_settings) => display_settings.apply(),
+            Err(e) => error!("Failed to read display settings: {}", e),
+        }
         let state = match config {
             Config::Launcher(datadir_path) => {
-                let (launcher, command) = Launcher::new(datadir_path, None);
+                let (mut launcher, command) = Launcher::new(datadir_path, None);
+                if requests
+                    .iter()
+                    .any(|request| matches!(request, ipc::Request::OpenUri(_)))
+                {
+                    launcher
+                        .set_notice("Select the wallet to open the payment link with.".to_string());
+                }
                 cmds.push(command.map(|msg| Message::Launch(Box::new(msg))));
                 State::Launcher(Box::new(launcher))
             }
@@ -172,23 +251,176 @@ impl GUI {
                 state,
                 logger,
                 log_level,
+                #[cfg(feature = "tray")]
+                tray: None,
+                pending_requests: requests,
+                ipc: ipc.into_iter().collect(),
+                window_size: None,
+                window_position: None,
             },
             Task::batch(cmds),
         )
     }
 
+    fn window_geometry(&self) -> Option<WindowGeometry> {
+        self.window_size.map(|size| WindowGeometry {
+            width: size.width,
+            height: size.height,
+            position: self.window_position.map(|p| [p.x, p.y]),
+        })
+    }
+
+    // Stop the daemon and the managed node if any, and close the window.
+    fn exit(&mut self) -> Task<Message> {
+        let window = self.window_geometry();
+        match &mut self.state {
+            State::Loader(s) => s.stop(),
+            State::Launcher(s) => s.stop(),
+            State::Installer(s) => s.stop(),
+            State::App(s) => {
+                s.save_preferences(window);
+                s.stop()
+            }
+            State::Login(_) => {}
+        };
+        iced::window::get_latest().and_then(iced::window::close)
+    }
+
+    // Create the system tray icon if the wallet is configured to keep running in the background.
+    fn maybe_setup_tray(&mut self) {
+        #[cfg(feature = "tray")]
+        if let State::App(app) = &self.state {
+            if app.minimize_to_tray() && self.tray.is_none() {
+                match liana_gui::tray::Tray::new() {
+                    Ok(tray) => self.tray = Some(tray),
+                    Err(e) => error!("Failed to set up the system tray: {}", e),
+                }
+            }
+        }
+    }
+
+    // Set up what runs along with the wallet, once it's opened.
+    fn app_started(
+        &mut self,
+        datadir_path: &std::path::Path,
+        network: bitcoin::Network,
+    ) -> Task<Message> {
+        self.maybe_setup_tray();
+        // The wallet was chosen on the launcher, other processes started for it must find this one.
+        let network_datadir = datadir_path.join(network.to_string());
+        if !self
+            .ipc
+            .iter()
+            .any(|server| server.is_for(&network_datadir))
+        {
+            match ipc::Server::start(&network_datadir) {
+                Ok(server) => self.ipc.push(server),
+                Err(e) => error!(
+                    "Failed to listen for the requests of other instances: {}",
+                    e
+                ),
+            }
+        }
+        let requests = std::mem::take(&mut self.pending_requests);
+        Task::batch(
+            requests
+                .into_iter()
+                .map(|request| self.ipc_request(request))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    // Handle the requests the GUI was started with, or of the processes started while it's
+    // running.
+    fn ipc_request(&mut self, request: ipc::Request) -> Task<Message> {
+        match (&mut self.state, request) {
+            (_, ipc::Request::Focus) => show_window(),
+            (State::App(app), ipc::Request::OpenUri(uri)) => match PaymentRequest::from_str(&uri) {
+                Ok(request) => {
+                    info!("Opening payment link");
+                    Task::batch(vec![
+                        show_window(),
+                        app.open_payment_request(&request)
+                            .map(|msg| Message::Run(Box::new(msg))),
+                    ])
+                }
+                Err(e) => {
+                    error!("Ignoring the payment link: {}", e);
+                    show_window()
+                }
+            },
+            (State::App(_) | State::Installer(_), ipc::Request::OpenFile(path)) => {
+                Task::batch(vec![show_window(), self.update(Message::FileDropped(path))])
+            }
+            // Opened once the wallet is running.
+            (state, request) => {
+                if let (State::Launcher(launcher), ipc::Request::OpenUri(_)) = (state, &request) {
+                    launcher
+                        .set_notice("Select the wallet to open the payment link with.".to_string());
+                }
+                self.pending_requests.push(request);
+                show_window()
+            }
+        }
+    }
+
+    #[cfg(feature = "tray")]
+    fn tray_action(&mut self, action: liana_gui::tray::TrayAction) -> Task<Message> {
+        use liana_gui::tray::TrayAction;
+        match action {
+            TrayAction::Open => show_window(),
+            TrayAction::ReceiveAddress => Task::batch(vec![
+                show_window(),
+                self.update(Message::Run(Box::new(app::Message::View(
+                    app::view::Message::Menu(app::menu::Menu::Receive),
+                )))),
+            ]),
+            TrayAction::Quit => self.exit(),
+        }
+    }
+
     fn update(&mut self, message: Message) -> Task<Message> {
         match (&mut self.state, message) {
-            (_, Message::CtrlC)
-            | (_, Message::Event(iced::Event::Window(iced::window::Event::CloseRequested))) => {
-                match &mut self.state {
-                    State::Loader(s) => s.stop(),
-                    State::Launcher(s) => s.stop(),
-                    State::Installer(s) => s.stop(),
-                    State::App(s) => s.stop(),
-                    State::Login(_) => {}
-                };
-                iced::window::get_latest().and_then(iced::window::close)
+            (_, Message::CtrlC) => self.exit(),
+            (_, Message::Event(iced::Event::Window(iced::window::Event::CloseRequested))) => {
+                // Keep the daemon and the managed node running, the wallet can be opened again
+                // from the system tray.
+                #[cfg(feature = "tray")]
+                if self.tray.is_some()
+                    && matches!(&self.state, State::App(app) if app.minimize_to_tray())
+                {
+                    info!("Window closed, running in the system tray.");
+                    return iced::window::get_latest()
+                        .and_then(|id| iced::window::change_mode(id, iced::window::Mode::Hidden));
+                }
+                self.exit()
+            }
+            #[cfg(feature = "tray")]
+            (_, Message::TrayTick) => {
+                let actions = self
+                    .tray
+                    .as_ref()
+                    .map(|tray| tray.actions())
+                    .unwrap_or_default();
+                Task::batch(
+                    actions
+                        .into_iter()
+                        .map(|action| self.tray_action(action))
+                        .collect::<Vec<_>>(),
+                )
+            }
+            (_, Message::IpcTick) => {
+                let requests: Vec<_> = self
+                    .ipc
+                    .iter()
+                    .flat_map(|server| server.requests())
+                    .collect();
+                Task::batch(
+                    requests
+                        .into_iter()
+                        .map(|request| self.ipc_request(request))
+                        .collect::<Vec<_>>(),
+                )
             }
             (_, Message::KeyPressed(Key::Tab(shift))) => {
                 log::debug!("Tab pressed!");
@@ -198,6 +430,30 @@ impl GUI {
                     focus_next()
                 }
             }
+            (State::App(_), Message::KeyPressed(Key::Shortcut(menu))) => self.update(Message::Run(
+                Box::new(app::Message::View(app::view::Message::Menu(menu))),
+            )),
+            (State::App(_), Message::KeyPressed(Key::ToggleAmounts)) => self.update(Message::Run(
+                Box::new(app::Message::View(app::view::Message::ToggleAmounts)),
+            )),
+            (_, Message::WindowResized(size)) => {
+                self.window_size = Some(size);
+                Task::none()
+            }
+            (_, Message::WindowMoved(position)) => {
+                self.window_position = Some(position);
+                Task::none()
+            }
+            (_, Message::FileDropped(path)) => {
+                info!("File dropped: {}", path.to_string_lossy());
+                Task::perform(file_drop::read(path), Message::FileRead)
+            }
+            (State::Installer(i), Message::FileRead(res)) => i
+                .update(installer::Message::FileDropped(res))
+                .map(|msg| Message::Install(Box::new(msg))),
+            (State::App(i), Message::FileRead(res)) => i
+                .update(app::Message::FileDropped(res))
+                .map(|msg| Message::Run(Box::new(msg))),
             (State::Launcher(l), Message::Launch(msg)) => match *msg {
                 launcher::Message::Install(datadir_path, network, init) => {
                     if !datadir_path.exists() {
@@ -283,16 +539,20 @@ impl GUI {
                         config.log_level().unwrap_or(LevelFilter::INFO),
                     );
 
+                    let (datadir_path, network) = (l.datadir.clone(), l.network);
                     let (app, command) = create_app_with_remote_backend(
                         backend_client,
                         wallet,
-                        l.datadir.clone(),
-                        l.network,
+                        datadir_path.clone(),
+                        network,
                         config,
                     );
 
                     self.state = State::App(app);
-                    command.map(|msg| Message::Run(Box::new(msg)))
+                    Task::batch(vec![
+                        command.map(|msg| Message::Run(Box::new(msg))),
+                        self.app_started(&datadir_path, network),
+                    ])
                 }
                 _ => l.update(*msg).map(|msg| Message::Login(Box::new(msg))),
             },
@@ -352,16 +612,20 @@ impl GUI {
                     command.map(|msg| Message::Launch(Box::new(msg)))
                 }
                 loader::Message::Synced(Ok((wallet, cache, daemon, bitcoind))) => {
+                    let (datadir_path, network) = (loader.datadir_path.clone(), loader.network);
                     let (app, command) = App::new(
                         cache,
                         wallet,
                         loader.gui_config.clone(),
                         daemon,
-                        loader.datadir_path.clone(),
+                        datadir_path.clone(),
                         bitcoind,
                     );
                     self.state = State::App(app);
-                    command.map(|msg| Message::Run(Box::new(msg)))
+                    Task::batch(vec![
+                        command.map(|msg| Message::Run(Box::new(msg))),
+                        self.app_started(&datadir_path, network),
+                    ])
                 }
                 _ => loader.update(*msg).map(|msg| Message::Load(Box::new(msg))),
             },
@@ -390,15 +654,60 @@ impl GUI {
                     }),
                     event::Status::Ignored,
                 ) => Some(Message::KeyPressed(Key::Tab(modifiers.shift()))),
+                // The text inputs may capture the key press, but they don't use these shortcuts.
+                (
+                    Event::Keyboard(keyboard::Event::KeyPressed {
+                        key: iced::keyboard::Key::Character(c),
+                        modifiers,
+                        ..
+                    }),
+                    _,
+                ) if modifiers.command() && modifiers.shift() && c.eq_ignore_ascii_case("h") => {
+                    Some(Message::KeyPressed(Key::ToggleAmounts))
+                }
+                (
+                    Event::Keyboard(keyboard::Event::KeyPressed {
+                        key: iced::keyboard::Key::Character(c),
+                        modifiers,
+                        ..
+                    }),
+                    _,
+                ) if modifiers.command() => app::menu::Menu::from_shortcut(c)
+                    .map(|menu| Message::KeyPressed(Key::Shortcut(menu))),
                 (
                     iced::Event::Window(iced::window::Event::CloseRequested),
                     event::Status::Ignored,
                 ) => Some(Message::Event(event)),
+                (iced::Event::Window(iced::window::Event::FileDropped(path)), _) => {
+                    Some(Message::FileDropped(path.clone()))
+                }
+                (iced::Event::Window(iced::window::Event::Resized(size)), _) => {
+                    Some(Message::WindowResized(*size))
+                }
+                (iced::Event::Window(iced::window::Event::Moved(position)), _) => {
+                    Some(Message::WindowMoved(*position))
+                }
                 _ => None,
             }),
+            self.tray_subscription(),
+            if !self.ipc.is_empty() {
+                iced::time::every(std::time::Duration::from_millis(250)).map(|_| Message::IpcTick)
+            } else {
+                Subscription::none()
+            },
         ])
     }
 
+    // Poll the actions of the tray menu, if any.
+    fn tray_subscription(&self) -> Subscription<Message> {
+        #[cfg(feature = "tray")]
+        if self.tray.is_some() {
+            return iced::time::every(std::time::Duration::from_millis(250))
+                .map(|_| Message::TrayTick);
+        }
+        Subscription::none()
+    }
+
     fn view(&self) -> Element<Message> {
         match &self.state {
             State::Installer(v) => v.view().map(|msg| Message::Install(Box::new(msg))),
@@ -410,10 +719,20 @@ impl GUI {
     }
 
     fn scale_factor(&self) -> f64 {
-        1.0
+        app::settings::global::ui_scale_factor()
     }
 }
 
+// Show the window if hidden in the system tray, and bring it to the front.
+fn show_window() -> Task<Message> {
+    iced::window::get_latest().and_then(|id| {
+        Task::batch(vec![
+            iced::window::change_mode(id, iced::window::Mode::Windowed),
+            iced::window::gain_focus(id),
+        ])
+    })
+}
+
 pub fn create_app_with_remote_backend(
     remote_backend: BackendWalletClient,
     wallet: api::Wallet,
@@ -443,6 +762,22 @@ pub fn create_app_with_remote_backend(
             }
         })
         .collect();
+    // The accent color, the spend confirmation, the block explorer and the remote signer are only
+    // known locally, they are not part of the wallet metadata.
+    let wallet_setting = app::settings::Settings::from_file(datadir.clone(), network)
+        .ok()
+        .and_then(|s| {
+            s.wallets.into_iter().find(|w| {
+                w.remote_backend_auth
+                    .as_ref()
+                    .map(|auth| auth.wallet_id == wallet.id)
+                    .unwrap_or(false)
+            })
+        });
+    let accent_color = wallet_setting.as_ref().and_then(|w| w.accent_color);
+    let block_explorer = wallet_setting
+        .as_ref()
+        .and_then(|w| w.block_explorer.clone());
     App::new(
         Cache {
             network,
@@ -454,10 +789,29 @@ pub fn create_app_with_remote_backend(
             // We ignore last poll fields for remote backend.
             last_poll_timestamp: None,
             last_poll_at_startup: None,
+            queued_writes: 0,
+            accent_color,
+            block_explorer: block_explorer.clone(),
+            app_lock: false,
         },
         Arc::new(
             Wallet::new(wallet.descriptor)
                 .with_name(wallet.name)
+                .with_accent_color(accent_color)
+                .with_block_explorer(block_explorer)
+                .with_remote_signer(
+                    wallet_setting
+                        .as_ref()
+                        .and_then(|w| w.remote_signer.clone()),
+                )
+                .with_hide_amounts(wallet_setting.as_ref().is_some_and(|w| w.hide_amounts))
+                .with_preferences(
+                    wallet_setting
+                        .as_ref()
+                        .map(|w| w.preferences.clone())
+                        .unwrap_or_default(),
+                )
+                .with_spend_confirmation(wallet_setting.and_then(|w| w.spend_confirmation))
                 .with_key_aliases(aliases)
                 .with_hardware_wallets(hws)
                 .load_hotsigners(&datadir, network)
@@ -496,6 +850,38 @@ impl Config {
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = parse_args(std::env::args().collect())?;
+    if let Some(manifest_path) = args.iter().find_map(|arg| match arg {
+        Arg::Install(path) => Some(path),
+        _ => None,
+    }) {
+        let datadir_path = args.iter().find_map(|arg| match arg {
+            Arg::DatadirPath(path) => Some(path.clone()),
+            _ => None,
+        });
+        return install_from_manifest(manifest_path, datadir_path);
+    }
+
+    // What to open once started, or to hand over to the running instance.
+    let mut requests = Vec::new();
+    let args: Vec<Arg> = args
+        .into_iter()
+        .filter_map(|arg| match arg {
+            Arg::PaymentRequest(uri) => {
+                requests.push(ipc::Request::OpenUri(uri));
+                None
+            }
+            Arg::File(path) => {
+                // The running instance may not have the same working directory.
+                let path = std::env::current_dir()
+                    .map(|dir| dir.join(&path))
+                    .unwrap_or(path);
+                requests.push(ipc::Request::OpenFile(path));
+                None
+            }
+            arg => Some(arg),
+        })
+        .collect();
+
     let config = match args.as_slice() {
         [] => {
             let datadir_path = default_datadir().unwrap();
@@ -548,6 +934,46 @@ fn main() -> Result<(), Box<dyn Error>> {
         }
     }?;
 
+    let payment_request = requests.iter().find_map(|request| match request {
+        ipc::Request::OpenUri(uri) => PaymentRequest::from_str(uri).ok(),
+        _ => None,
+    });
+    let config = match payment_request {
+        Some(payment_request) => {
+            match route_payment_request(config, &payment_request, &requests)? {
+                Some(config) => config,
+                // The running instance of the wallet opens it.
+                None => return Ok(()),
+            }
+        }
+        None => config,
+    };
+
+    // Only one GUI may run for the data directory if started on the launcher, and for the wallet
+    // otherwise. If one already does, bring it to the front instead.
+    let ipc_datadir = match &config {
+        Config::Launcher(datadir_path) => datadir_path.clone(),
+        Config::Run(datadir_path, _, network) => datadir_path.join(network.to_string()),
+    };
+    let ipc = match ipc::Server::start(&ipc_datadir) {
+        Ok(server) => Some(server),
+        Err(ipc::IpcError::AlreadyRunning) => match forward_requests(&ipc_datadir, &requests) {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                eprintln!("{}", e);
+                None
+            }
+        },
+        // The data directory is only created on installation.
+        Err(e) => {
+            eprintln!(
+                "Failed to listen for the requests of other instances: {}",
+                e
+            );
+            None
+        }
+    };
+
     let log_level = if let Ok(l) = std::env::var("LOG_LEVEL") {
         Some(LevelFilter::from_str(&l)?)
     } else {
@@ -576,6 +1002,23 @@ fn main() -> Result<(), Box<dyn Error>> {
         ..Default::default()
     };
 
+    // Open the wallet in the window it was closed in.
+    if let Config::Run(datadir_path, _, network) = &config {
+        if let Some(window) = app::settings::Settings::from_file(datadir_path.clone(), *network)
+            .ok()
+            .and_then(|s| s.wallets.into_iter().next())
+            .and_then(|w| w.preferences.window)
+        {
+            window_settings.size = Size {
+                width: window.width.max(1000.0),
+                height: window.height.max(650.0),
+            };
+            if let Some([x, y]) = window.position {
+                window_settings.position = iced::window::Position::Specific(Point::new(x, y));
+            }
+        }
+    }
+
     #[cfg(target_os = "linux")]
     {
         window_settings.platform_specific = PlatformSpecific {
@@ -590,7 +1033,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         .subscription(GUI::subscription)
         .settings(settings)
         .window(window_settings)
-        .run_with(move || GUI::new((config, log_level)))
+        .run_with(move || GUI::new((config, log_level, requests, ipc)))
     {
         log::error!("{}", e);
         Err(format!("Failed to launch UI: {}", e).into())
@@ -599,6 +1042,78 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
 }
 
+// Bring the running instance to the front and hand it over the requests.
+fn forward_requests(
+    datadir: &std::path::Path,
+    requests: &[ipc::Request],
+) -> Result<(), ipc::IpcError> {
+    std::iter::once(&ipc::Request::Focus)
+        .chain(requests)
+        .try_for_each(|request| ipc::send(datadir, request))
+}
+
+// When started on the launcher, hand the payment link over to the running instance of a wallet
+// for the network of the address, if any. Otherwise, open the wallet for this network directly
+// if there is only one. Returns the configuration to start with, or None if the link was handed
+// over.
+fn route_payment_request(
+    config: Config,
+    payment_request: &PaymentRequest,
+    requests: &[ipc::Request],
+) -> Result<Option<Config>, Box<dyn Error>> {
+    let datadir_path = match &config {
+        Config::Launcher(datadir_path) => datadir_path.clone(),
+        Config::Run(..) => return Ok(Some(config)),
+    };
+    let networks = payment_request.networks();
+    for network in &networks {
+        match forward_requests(&datadir_path.join(network.to_string()), requests) {
+            Ok(()) => return Ok(None),
+            Err(ipc::IpcError::NotRunning) => {}
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+    let installed: Vec<_> = networks
+        .into_iter()
+        .filter(|network| {
+            datadir_path
+                .join(network.to_string())
+                .join(app::config::DEFAULT_FILE_NAME)
+                .exists()
+        })
+        .collect();
+    if let [network] = installed.as_slice() {
+        return Config::new(datadir_path, Some(*network)).map(Some);
+    }
+    Ok(Some(config))
+}
+
+// Install the wallet described by the manifest without starting the user interface. The datadir
+// given on the command line takes precedence over the one of the manifest.
+fn install_from_manifest(
+    manifest_path: &std::path::Path,
+    datadir_path: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let manifest = Manifest::from_file(manifest_path)?;
+    let datadir_path = match datadir_path.or_else(|| manifest.datadir.clone()) {
+        Some(path) => path,
+        None => default_datadir()?,
+    };
+
+    let gui_config_path = tokio::runtime::Builder::new_current_thread()
+        .build()?
+        .block_on(manifest::install(manifest, datadir_path))?;
+
+    let gui_config = app::Config::from_file(&gui_config_path)?;
+    println!("# {}", gui_config_path.display());
+    println!("{}", std::fs::read_to_string(&gui_config_path)?);
+    if let Some(daemon_config_path) = gui_config.daemon_config_path {
+        println!("# {}", daemon_config_path.display());
+        println!("{}", std::fs::read_to_string(&daemon_config_path)?);
+    }
+    Ok(())
+}
+
 // A panic in any thread should stop the main thread, and print the panic.
 fn setup_panic_hook() {
     std::panic::set_hook(Box::new(move |panic_info| {
@@ -636,6 +1151,7 @@ mod tests {
         assert!(parse_args(vec!["--meth".into()]).is_err());
         assert!(parse_args(vec!["--datadir".into()]).is_err());
         assert!(parse_args(vec!["--conf".into()]).is_err());
+        assert!(parse_args(vec!["--install".into()]).is_err());
         assert_eq!(
             Some(vec![
                 Arg::DatadirPath(PathBuf::from(".")),
@@ -653,6 +1169,17 @@ mod tests {
             Some(vec![Arg::Network(bitcoin::Network::Regtest)]),
             parse_args(vec!["--regtest".into()]).ok()
         );
+        assert_eq!(
+            Some(vec![
+                Arg::PaymentRequest("BITCOIN:BC1QAR0SRRR7XFKVY5L643LYDNW9RE59GTZZWF5MDQ".into()),
+                Arg::Network(bitcoin::Network::Bitcoin),
+            ]),
+            parse_args(vec![
+                "BITCOIN:BC1QAR0SRRR7XFKVY5L643LYDNW9RE59GTZZWF5MDQ".into(),
+                "--bitcoin".into()
+            ])
+            .ok()
+        );
         assert_eq!(
             Some(vec![
                 Arg::DatadirPath(PathBuf::from("hello")),
@@ -679,5 +1206,31 @@ mod tests {
             )
             .ok()
         );
+        assert_eq!(
+            Some(vec![
+                Arg::DatadirPath(PathBuf::from("hello")),
+                Arg::File(PathBuf::from("spend.psbt")),
+            ]),
+            parse_args(
+                "liana-gui --datadir hello spend.psbt"
+                    .split(' ')
+                    .map(|a| a.to_string())
+                    .collect()
+            )
+            .ok()
+        );
+        assert_eq!(
+            Some(vec![
+                Arg::Install(PathBuf::from("wallet.toml")),
+                Arg::DatadirPath(PathBuf::from("hello"))
+            ]),
+            parse_args(
+                "--install wallet.toml --datadir hello"
+                    .split(' ')
+                    .map(|a| a.to_string())
+                    .collect()
+            )
+            .ok()
+        );
     }
 }
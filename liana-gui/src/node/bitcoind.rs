@@ -1,5 +1,7 @@
 use base64::Engine;
 use bitcoin_hashes::{sha256, Hash, HashEngine, Hmac, HmacEngine};
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+use flate2::read::GzDecoder;
 use liana::{
     miniscript::bitcoin::{self, Network},
     random::{random_bytes, RandomnessError},
@@ -8,13 +10,19 @@ use liana_ui::component::form;
 use lianad::config::BitcoindConfig;
 use std::collections::BTreeMap;
 use std::fmt;
+#[cfg(target_os = "windows")]
+use std::io::{self, Cursor};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time;
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+use tar::Archive;
 
 use tracing::{info, warn};
 
+use super::service;
+
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 
@@ -27,6 +35,10 @@ pub const VERSIONS: [&str; 6] = ["28.0", "27.1", "26.1", "26.0", "25.1", "25.0"]
 /// Current managed bitcoind version for new installations.
 pub const VERSION: &str = VERSIONS[0];
 
+/// How long to wait for managed bitcoind to exit once asked to stop. It may need to flush a large
+/// UTXO set cache to disk.
+const STOP_TIMEOUT: time::Duration = time::Duration::from_secs(60);
+
 #[cfg(all(target_os = "macos", target_arch = "x86_64"))]
 pub const SHA256SUM: &str = "77e931bbaaf47771a10c376230bf53223f5380864bad3568efc7f4d02e40a0f7";
 
@@ -92,6 +104,121 @@ pub fn internal_bitcoind_exe_path(liana_datadir: &PathBuf, bitcoind_version: &st
         })
 }
 
+/// Internal bitcoind executable to run, of the version selected by the user if any.
+pub fn internal_bitcoind_exe(liana_datadir: &PathBuf) -> Option<PathBuf> {
+    selected_version(liana_datadir).map(|v| internal_bitcoind_exe_path(liana_datadir, v))
+}
+
+/// Internal bitcoind versions available, in order of descending version.
+pub fn installed_versions(liana_datadir: &PathBuf) -> Vec<&'static str> {
+    VERSIONS
+        .iter()
+        .copied()
+        .filter(|v| internal_bitcoind_exe_path(liana_datadir, v).exists())
+        .collect()
+}
+
+// Path of the file recording the internal bitcoind version selected by the user.
+fn selected_version_path(liana_datadir: &PathBuf) -> PathBuf {
+    internal_bitcoind_directory(liana_datadir).join("version")
+}
+
+/// Internal bitcoind version to run: the one selected by the user if it is available, the most
+/// recent one available otherwise.
+pub fn selected_version(liana_datadir: &PathBuf) -> Option<&'static str> {
+    let installed = installed_versions(liana_datadir);
+    std::fs::read_to_string(selected_version_path(liana_datadir))
+        .ok()
+        .and_then(|selected| installed.iter().find(|v| **v == selected.trim()).copied())
+        .or_else(|| installed.first().copied())
+}
+
+/// Select the internal bitcoind version to run from its next start, after checking it can use
+/// the current data directory.
+pub fn select_version(
+    liana_datadir: &PathBuf,
+    network: &Network,
+    version: &str,
+) -> Result<(), String> {
+    if !installed_versions(liana_datadir).contains(&version) {
+        return Err(format!("bitcoind version {} is not installed.", version));
+    }
+    check_version_compatibility(liana_datadir, network, version)?;
+    std::fs::write(selected_version_path(liana_datadir), version).map_err(|e| e.to_string())?;
+    info!("Selected bitcoind version {}", version);
+    Ok(())
+}
+
+/// Parse a Bitcoin Core version such as `28.0` or `v27.1`.
+pub fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let (major, minor) = version.trim_start_matches('v').split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// Check the data directory of internal bitcoind can be used by this version of it. Upgrades are
+/// always possible, but previous versions may not be able to read what later ones wrote.
+pub fn check_version_compatibility(
+    liana_datadir: &PathBuf,
+    network: &Network,
+    version: &str,
+) -> Result<(), String> {
+    // Since 28.0 the block files of new data directories are obfuscated with a random key, which
+    // previous versions ignore.
+    if parse_version(version) < Some((28, 0)) {
+        let mut xor_key_path = internal_bitcoind_datadir(liana_datadir);
+        if let Some(dir) = bitcoind_network_dir(network) {
+            xor_key_path.push(dir);
+        }
+        xor_key_path.push("blocks");
+        xor_key_path.push("xor.dat");
+        if let Ok(key) = std::fs::read(&xor_key_path) {
+            if key.iter().any(|b| *b != 0) {
+                return Err(format!(
+                    "The block files were written by Bitcoin Core 28.0 or later with an \
+                     obfuscation key, Bitcoin Core {} can't read them.",
+                    version
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Latest Bitcoin Core release, as published on GitHub.
+pub async fn latest_release() -> Result<String, String> {
+    #[derive(serde::Deserialize)]
+    struct Release {
+        tag_name: String,
+    }
+    let release: Release = reqwest::Client::new()
+        .get("https://api.github.com/repos/bitcoin/bitcoin/releases/latest")
+        .header("User-Agent", "liana-gui")
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(release.tag_name.trim_start_matches('v').to_string())
+}
+
+/// Canonical path of the internal bitcoind data directory, as passed to its `-datadir` option.
+pub fn internal_bitcoind_datadir_arg(liana_datadir: &PathBuf) -> Result<String, String> {
+    let datadir_path_str = internal_bitcoind_datadir(liana_datadir)
+        .canonicalize()
+        .map_err(|e| e.to_string())?
+        .to_str()
+        .ok_or_else(|| "Couldn't convert path to str.".to_string())?
+        .to_string();
+
+    // See https://github.com/rust-lang/rust/issues/42869.
+    #[cfg(target_os = "windows")]
+    let datadir_path_str = datadir_path_str.replace("\\\\?\\", "").replace("\\\\?", "");
+
+    Ok(datadir_path_str)
+}
+
 /// Path of the `bitcoin.conf` file used by internal bitcoind.
 pub fn internal_bitcoind_config_path(bitcoind_datadir: &PathBuf) -> PathBuf {
     let mut config_path = PathBuf::from(bitcoind_datadir);
@@ -361,6 +488,100 @@ impl InternalBitcoindConfig {
     }
 }
 
+/// Possible errors when installing bitcoind.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum InstallBitcoindError {
+    HashMismatch,
+    UnpackingError(String),
+}
+
+impl std::fmt::Display for InstallBitcoindError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::HashMismatch => {
+                write!(f, "Hashes do not match.")
+            }
+            Self::UnpackingError(e) => {
+                write!(f, "Error unpacking: '{}'.", e)
+            }
+        }
+    }
+}
+
+// The functions below for unpacking the bitcoin download and verifying its hash are based on
+// https://github.com/RCasatta/bitcoind/blob/bada7ebb7197b89fd67e607f815ce1e43e76da7f/build.rs#L73.
+
+/// Unpack the downloaded bytes in the specified directory.
+fn unpack_bitcoind(install_dir: &PathBuf, bytes: &[u8]) -> Result<(), InstallBitcoindError> {
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
+    {
+        let d = GzDecoder::new(bytes);
+
+        let mut archive = Archive::new(d);
+        for mut entry in archive
+            .entries()
+            .map_err(|e| InstallBitcoindError::UnpackingError(e.to_string()))?
+            .flatten()
+        {
+            if let Ok(file) = entry.path() {
+                if file.ends_with("bitcoind") {
+                    if let Err(e) = entry.unpack_in(install_dir) {
+                        return Err(InstallBitcoindError::UnpackingError(e.to_string()));
+                    }
+                }
+            }
+        }
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let cursor = Cursor::new(bytes);
+        let mut archive = zip::ZipArchive::new(cursor)
+            .map_err(|e| InstallBitcoindError::UnpackingError(e.to_string()))?;
+        for i in 0..zip::ZipArchive::len(&archive) {
+            let mut file = archive
+                .by_index(i)
+                .map_err(|e| InstallBitcoindError::UnpackingError(e.to_string()))?;
+            let outpath = match file.enclosed_name() {
+                Some(path) => path.to_owned(),
+                None => continue,
+            };
+            if outpath.file_name().map(|s| s.to_str()) == Some(Some("bitcoind.exe")) {
+                let mut exe_path = PathBuf::from(install_dir);
+                for d in outpath.iter() {
+                    exe_path.push(d);
+                }
+                let parent = exe_path.parent().expect("bitcoind.exe should have parent.");
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| InstallBitcoindError::UnpackingError(e.to_string()))?;
+                let mut outfile = std::fs::File::create(&exe_path)
+                    .map_err(|e| InstallBitcoindError::UnpackingError(e.to_string()))?;
+                io::copy(&mut file, &mut outfile)
+                    .map_err(|e| InstallBitcoindError::UnpackingError(e.to_string()))?;
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Verify the download hash against the expected value.
+fn verify_hash(bytes: &[u8]) -> bool {
+    let bytes_hash = sha256::Hash::hash(bytes);
+    info!("Download hash: '{}'.", bytes_hash);
+    let expected_hash = SHA256SUM
+        .parse::<sha256::Hash>()
+        .expect("This cannot fail.");
+    expected_hash == bytes_hash
+}
+
+/// Install bitcoind by verifying the download hash and unpacking in the specified directory.
+pub fn install_bitcoind(install_dir: &PathBuf, bytes: &[u8]) -> Result<(), InstallBitcoindError> {
+    if !verify_hash(bytes) {
+        return Err(InstallBitcoindError::HashMismatch);
+    };
+    unpack_bitcoind(install_dir, bytes)
+}
+
 /// Possible errors when starting bitcoind.
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub enum StartInternalBitcoindError {
@@ -369,6 +590,7 @@ pub enum StartInternalBitcoindError {
     BitcoinDError(String),
     ExecutableNotFound,
     ProcessExited(std::process::ExitStatus),
+    Service(String),
 }
 
 impl std::fmt::Display for StartInternalBitcoindError {
@@ -385,12 +607,13 @@ impl std::fmt::Display for StartInternalBitcoindError {
             Self::ProcessExited(status) => {
                 write!(f, "bitcoind process exited with status '{}'.", status)
             }
+            Self::Service(e) => write!(f, "bitcoind service failed to start: {}", e),
         }
     }
 }
 #[derive(Debug, Clone)]
 pub struct Bitcoind {
-    _process: Arc<std::process::Child>,
+    process: Arc<Mutex<std::process::Child>>,
     pub config: BitcoindConfig,
 }
 
@@ -401,38 +624,14 @@ impl Bitcoind {
         config: BitcoindConfig,
         liana_datadir: &PathBuf,
     ) -> Result<Self, StartInternalBitcoindError> {
-        let bitcoind_datadir = internal_bitcoind_datadir(liana_datadir);
-        // Find most recent bitcoind version available.
-        let bitcoind_exe_path = VERSIONS
-            .iter()
-            .filter_map(|v| {
-                let path = internal_bitcoind_exe_path(liana_datadir, v);
-                if path.exists() {
-                    Some(path)
-                } else {
-                    None
-                }
-            })
-            .next()
+        let bitcoind_exe_path = internal_bitcoind_exe(liana_datadir)
             .ok_or(StartInternalBitcoindError::ExecutableNotFound)?;
         info!(
             "Found bitcoind executable at '{}'.",
             bitcoind_exe_path.to_string_lossy()
         );
-        let datadir_path_str = bitcoind_datadir
-            .canonicalize()
-            .map_err(|e| StartInternalBitcoindError::CouldNotCanonicalizeDataDir(e.to_string()))?
-            .to_str()
-            .ok_or_else(|| {
-                StartInternalBitcoindError::CouldNotCanonicalizeDataDir(
-                    "Couldn't convert path to str.".to_string(),
-                )
-            })?
-            .to_string();
-
-        // See https://github.com/rust-lang/rust/issues/42869.
-        #[cfg(target_os = "windows")]
-        let datadir_path_str = datadir_path_str.replace("\\\\?\\", "").replace("\\\\?", "");
+        let datadir_path_str = internal_bitcoind_datadir_arg(liana_datadir)
+            .map_err(StartInternalBitcoindError::CouldNotCanonicalizeDataDir)?;
 
         let args = vec![
             format!("-chain={}", network.to_core_arg()),
@@ -467,7 +666,7 @@ impl Bitcoind {
                     log::info!("Bitcoind seems to have successfully started.");
                     return Ok(Self {
                         config,
-                        _process: Arc::new(process),
+                        process: Arc::new(Mutex::new(process)),
                     });
                 }
                 Err(lianad::BitcoindError::CookieFile(_)) => {
@@ -490,9 +689,61 @@ impl Bitcoind {
         }
     }
 
-    /// Stop (internal) bitcoind.
+    /// Stop (internal) bitcoind, and wait for it to flush its state to disk and exit.
     pub fn stop(&self) {
-        stop_bitcoind(&self.config);
+        if !stop_bitcoind(&self.config) {
+            return;
+        }
+        let deadline = time::Instant::now() + STOP_TIMEOUT;
+        while time::Instant::now() < deadline {
+            match self.process.lock().unwrap().try_wait() {
+                Ok(Some(status)) => {
+                    info!("Bitcoind exited with status '{}'.", status);
+                    return;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    warn!("Error while waiting for bitcoind to exit: '{}'.", e);
+                    return;
+                }
+            }
+            thread::sleep(time::Duration::from_millis(100));
+        }
+        warn!(
+            "Bitcoind did not exit within {} seconds.",
+            STOP_TIMEOUT.as_secs()
+        );
+    }
+}
+
+/// Start the service running internal bitcoind and wait for its JSONRPC interface to be available.
+pub fn start_bitcoind_service(
+    network: &Network,
+    config: &BitcoindConfig,
+) -> Result<(), StartInternalBitcoindError> {
+    service::start(*network).map_err(|e| StartInternalBitcoindError::Service(e.to_string()))?;
+    // The service manager may fail to start it, don't wait forever.
+    let deadline = time::Instant::now() + time::Duration::from_secs(300);
+    loop {
+        match lianad::BitcoinD::new(config, "internal_bitcoind_start".to_string()) {
+            Ok(_) => {
+                log::info!("Bitcoind service seems to have successfully started.");
+                return Ok(());
+            }
+            Err(lianad::BitcoindError::CookieFile(_)) => {}
+            Err(e) => {
+                if !e.is_transient() {
+                    return Err(StartInternalBitcoindError::BitcoinDError(e.to_string()));
+                }
+            }
+        }
+        if time::Instant::now() > deadline {
+            return Err(StartInternalBitcoindError::Service(
+                "timed out waiting for bitcoind to be available".to_string(),
+            ));
+        }
+        log::info!("Waiting for the bitcoind service to start.");
+        thread::sleep(time::Duration::from_millis(500));
     }
 }
 
@@ -635,4 +886,46 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn hash() {
+        let bytes = "this is not bitcoin".as_bytes().to_vec();
+        assert!(!verify_hash(&bytes));
+    }
+
+    #[test]
+    fn version_compatibility() {
+        assert_eq!(parse_version("28.0"), Some((28, 0)));
+        assert_eq!(parse_version("v27.1"), Some((27, 1)));
+        assert_eq!(parse_version("28"), None);
+        assert!(parse_version("26.1") < parse_version(VERSION));
+
+        let liana_datadir =
+            std::env::temp_dir().join(format!("liana-bitcoind-versions-{}", std::process::id()));
+        let blocks_dir = internal_bitcoind_datadir(&liana_datadir)
+            .join("signet")
+            .join("blocks");
+        std::fs::create_dir_all(&blocks_dir).unwrap();
+        // Block files which aren't obfuscated can be read by any version.
+        std::fs::write(blocks_dir.join("xor.dat"), [0u8; 8]).unwrap();
+        assert!(check_version_compatibility(&liana_datadir, &Network::Signet, "27.1").is_ok());
+        std::fs::write(blocks_dir.join("xor.dat"), [1u8; 8]).unwrap();
+        assert!(check_version_compatibility(&liana_datadir, &Network::Signet, "27.1").is_err());
+        assert!(check_version_compatibility(&liana_datadir, &Network::Signet, "28.0").is_ok());
+        assert!(check_version_compatibility(&liana_datadir, &Network::Testnet, "27.1").is_ok());
+
+        // Only installed versions can be selected, the most recent one by default.
+        assert_eq!(selected_version(&liana_datadir), None);
+        for version in ["27.1", "28.0"] {
+            let exe_path = internal_bitcoind_exe_path(&liana_datadir, version);
+            std::fs::create_dir_all(exe_path.parent().unwrap()).unwrap();
+            std::fs::write(exe_path, b"").unwrap();
+        }
+        assert_eq!(selected_version(&liana_datadir), Some("28.0"));
+        assert!(select_version(&liana_datadir, &Network::Testnet, "26.1").is_err());
+        select_version(&liana_datadir, &Network::Testnet, "27.1").unwrap();
+        assert_eq!(selected_version(&liana_datadir), Some("27.1"));
+
+        std::fs::remove_dir_all(&liana_datadir).unwrap();
+    }
 }
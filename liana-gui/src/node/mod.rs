@@ -2,6 +2,7 @@ use lianad::config::BitcoinBackend;
 
 pub mod bitcoind;
 pub mod electrum;
+pub mod service;
 
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
 pub enum NodeType {
@@ -0,0 +1,365 @@
+//! Run the managed bitcoind as a user-level service of the operating system (a systemd user
+//! unit on Linux, a launchd agent on MacOS and a Task Scheduler task on Windows), so the node
+//! keeps syncing when the GUI isn't running.
+//!
+//! The service is started on login and stopped by the service manager, which leaves bitcoind the
+//! time to shut down cleanly. The GUI attaches to it instead of spawning its own bitcoind.
+
+use std::path::PathBuf;
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+use std::process::Command;
+
+use liana::miniscript::bitcoin::Network;
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+use crate::node::bitcoind::{internal_bitcoind_datadir_arg, internal_bitcoind_exe};
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+#[cfg(target_os = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServiceError {
+    ExecutableNotFound,
+    Datadir(String),
+    Io(String),
+    Command(String),
+    Unsupported,
+}
+
+impl std::fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::ExecutableNotFound => write!(f, "bitcoind executable not found."),
+            Self::Datadir(e) => write!(f, "Failed to canonicalize datadir: {}", e),
+            Self::Io(e) => write!(f, "Failed to write the service definition: {}", e),
+            Self::Command(e) => write!(f, "Service manager returned an error: {}", e),
+            Self::Unsupported => write!(
+                f,
+                "Running bitcoind as a service is not supported on this platform."
+            ),
+        }
+    }
+}
+
+/// Name of the service running the managed bitcoind for this network.
+pub fn service_name(network: Network) -> String {
+    if cfg!(target_os = "macos") {
+        format!("org.wizardsardine.liana.bitcoind.{}", network)
+    } else if cfg!(target_os = "windows") {
+        format!("Liana\\bitcoind-{}", network)
+    } else {
+        format!("liana-bitcoind-{}", network)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+fn run(program: &str, args: &[&str]) -> Result<String, ServiceError> {
+    let mut command = Command::new(program);
+    #[cfg(target_os = "windows")]
+    let command = command.creation_flags(CREATE_NO_WINDOW);
+    let output = command
+        .args(args)
+        .output()
+        .map_err(|e| ServiceError::Command(format!("{}: {}", program, e)))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(ServiceError::Command(format!(
+            "{} exited with status '{}': {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn definition_path(network: Network) -> Option<PathBuf> {
+    #[cfg(target_os = "linux")]
+    let path = dirs::config_dir().map(|dir| {
+        dir.join("systemd")
+            .join("user")
+            .join(format!("{}.service", service_name(network)))
+    });
+    #[cfg(target_os = "macos")]
+    let path = dirs::home_dir().map(|dir| {
+        dir.join("Library")
+            .join("LaunchAgents")
+            .join(format!("{}.plist", service_name(network)))
+    });
+    path
+}
+
+#[cfg(target_os = "linux")]
+fn definition(network: Network, exe: &str, datadir: &str) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Bitcoin Core node managed by Liana ({network})\n\
+         After=network-online.target\n\
+         \n\
+         [Service]\n\
+         ExecStart=\"{exe}\" -chain={chain} \"-datadir={datadir}\"\n\
+         Restart=on-failure\n\
+         RestartSec=30\n\
+         TimeoutStopSec=600\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        network = network,
+        exe = exe,
+        chain = network.to_core_arg(),
+        datadir = datadir,
+    )
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(target_os = "macos")]
+fn definition(network: Network, exe: &str, datadir: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{exe}</string>
+        <string>-chain={chain}</string>
+        <string>-datadir={datadir}</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+    <key>ThrottleInterval</key>
+    <integer>30</integer>
+    <key>ExitTimeOut</key>
+    <integer>600</integer>
+</dict>
+</plist>
+"#,
+        label = service_name(network),
+        exe = xml_escape(exe),
+        chain = network.to_core_arg(),
+        datadir = xml_escape(datadir),
+    )
+}
+
+#[cfg(target_os = "windows")]
+fn definition(network: Network, exe: &str, datadir: &str) -> String {
+    // Task Scheduler stops tasks after 3 days by default, disable the time limit.
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-16"?>
+<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
+  <RegistrationInfo>
+    <Description>Bitcoin Core node managed by Liana ({network})</Description>
+  </RegistrationInfo>
+  <Triggers>
+    <LogonTrigger>
+      <Enabled>true</Enabled>
+      <UserId>{user}</UserId>
+    </LogonTrigger>
+  </Triggers>
+  <Settings>
+    <MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>
+    <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>
+    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>
+    <ExecutionTimeLimit>PT0S</ExecutionTimeLimit>
+    <RestartOnFailure>
+      <Interval>PT1M</Interval>
+      <Count>999</Count>
+    </RestartOnFailure>
+    <Hidden>true</Hidden>
+  </Settings>
+  <Actions Context="Author">
+    <Exec>
+      <Command>{exe}</Command>
+      <Arguments>-chain={chain} "-datadir={datadir}"</Arguments>
+    </Exec>
+  </Actions>
+</Task>
+"#,
+        network = network,
+        user = xml_escape(&format!(
+            "{}\\{}",
+            std::env::var("USERDOMAIN").unwrap_or_default(),
+            std::env::var("USERNAME").unwrap_or_default()
+        )),
+        exe = xml_escape(exe),
+        chain = network.to_core_arg(),
+        datadir = xml_escape(datadir),
+    )
+}
+
+/// Whether the service running the managed bitcoind of this Liana data directory is installed.
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+pub fn is_installed(liana_datadir: &PathBuf, network: Network) -> bool {
+    let datadir = match internal_bitcoind_datadir_arg(liana_datadir) {
+        Ok(datadir) => datadir,
+        Err(_) => return false,
+    };
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    let content = definition_path(network).and_then(|path| std::fs::read_to_string(path).ok());
+    #[cfg(target_os = "windows")]
+    let content = run(
+        "schtasks",
+        &["/Query", "/XML", "/TN", &service_name(network)],
+    )
+    .ok();
+    // The service of another data directory may be installed for this network.
+    content.is_some_and(|content| content.contains(&datadir))
+}
+
+/// Install the service running the most recent managed bitcoind executable and start it. If
+/// bitcoind is already running, the service will take over once it's stopped.
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+pub fn install(liana_datadir: &PathBuf, network: Network) -> Result<(), ServiceError> {
+    let exe = internal_bitcoind_exe(liana_datadir).ok_or(ServiceError::ExecutableNotFound)?;
+    let datadir = internal_bitcoind_datadir_arg(liana_datadir).map_err(ServiceError::Datadir)?;
+    let definition = definition(network, &exe.to_string_lossy(), &datadir);
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    {
+        let path = definition_path(network)
+            .ok_or_else(|| ServiceError::Io("Unknown home directory".to_string()))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| ServiceError::Io(e.to_string()))?;
+        }
+        std::fs::write(&path, definition).map_err(|e| ServiceError::Io(e.to_string()))?;
+        #[cfg(target_os = "linux")]
+        {
+            run("systemctl", &["--user", "daemon-reload"])?;
+            run(
+                "systemctl",
+                &["--user", "enable", "--now", &service_name(network)],
+            )?;
+        }
+        #[cfg(target_os = "macos")]
+        run("launchctl", &["load", "-w", &path.to_string_lossy()])?;
+    }
+    #[cfg(target_os = "windows")]
+    {
+        // Task Scheduler expects the XML definition to be encoded in UTF-16.
+        let path = liana_datadir.join("bitcoind_service.xml");
+        let mut bytes = vec![0xFF, 0xFE];
+        for unit in definition.encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        std::fs::write(&path, bytes).map_err(|e| ServiceError::Io(e.to_string()))?;
+        let name = service_name(network);
+        let res = run(
+            "schtasks",
+            &[
+                "/Create",
+                "/F",
+                "/TN",
+                &name,
+                "/XML",
+                &path.to_string_lossy(),
+            ],
+        );
+        let _ = std::fs::remove_file(&path);
+        res?;
+        run("schtasks", &["/Run", "/TN", &name])?;
+    }
+
+    tracing::info!(
+        "Installed the managed bitcoind service '{}'",
+        service_name(network)
+    );
+    Ok(())
+}
+
+/// Stop the service, waiting for bitcoind to shut down, and remove it.
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+pub fn uninstall(network: Network) -> Result<(), ServiceError> {
+    let name = service_name(network);
+    #[cfg(target_os = "linux")]
+    {
+        run("systemctl", &["--user", "disable", "--now", &name])?;
+        if let Some(path) = definition_path(network) {
+            std::fs::remove_file(path).map_err(|e| ServiceError::Io(e.to_string()))?;
+        }
+        run("systemctl", &["--user", "daemon-reload"])?;
+    }
+    #[cfg(target_os = "macos")]
+    if let Some(path) = definition_path(network) {
+        run("launchctl", &["unload", "-w", &path.to_string_lossy()])?;
+        std::fs::remove_file(path).map_err(|e| ServiceError::Io(e.to_string()))?;
+    }
+    #[cfg(target_os = "windows")]
+    {
+        // The task may not be running.
+        let _ = run("schtasks", &["/End", "/TN", &name]);
+        run("schtasks", &["/Delete", "/F", "/TN", &name])?;
+    }
+    tracing::info!("Uninstalled the managed bitcoind service '{}'", name);
+    Ok(())
+}
+
+/// Ask the service manager to start the service, if it isn't running already.
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+pub fn start(network: Network) -> Result<(), ServiceError> {
+    let name = service_name(network);
+    #[cfg(target_os = "linux")]
+    run("systemctl", &["--user", "start", &name])?;
+    #[cfg(target_os = "macos")]
+    run("launchctl", &["start", &name])?;
+    #[cfg(target_os = "windows")]
+    run("schtasks", &["/Run", "/TN", &name])?;
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn is_installed(_liana_datadir: &PathBuf, _network: Network) -> bool {
+    false
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn install(_liana_datadir: &PathBuf, _network: Network) -> Result<(), ServiceError> {
+    Err(ServiceError::Unsupported)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn uninstall(_network: Network) -> Result<(), ServiceError> {
+    Err(ServiceError::Unsupported)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn start(_network: Network) -> Result<(), ServiceError> {
+    Err(ServiceError::Unsupported)
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn systemd_unit() {
+        let unit = definition(
+            Network::Signet,
+            "/home/user/.liana/bitcoind/bitcoin-28.0/bin/bitcoind",
+            "/home/user/.liana/bitcoind/datadir",
+        );
+        assert_eq!(service_name(Network::Signet), "liana-bitcoind-signet");
+        assert!(unit.contains(
+            "ExecStart=\"/home/user/.liana/bitcoind/bitcoin-28.0/bin/bitcoind\" -chain=signet \
+             \"-datadir=/home/user/.liana/bitcoind/datadir\"\n"
+        ));
+        assert!(unit.contains("WantedBy=default.target\n"));
+    }
+}
@@ -0,0 +1,222 @@
+//! Payment requests, as encoded in the `bitcoin:` URIs of BIP21.
+
+use std::{fmt, str::FromStr};
+
+use liana::miniscript::bitcoin::{
+    address::NetworkUnchecked, Address, Amount, Denomination, Network,
+};
+
+const SCHEME: &str = "bitcoin:";
+
+const NETWORKS: [Network; 4] = [
+    Network::Bitcoin,
+    Network::Testnet,
+    Network::Signet,
+    Network::Regtest,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentRequest {
+    pub address: Address<NetworkUnchecked>,
+    pub amount: Option<Amount>,
+    /// The name of the recipient.
+    pub label: Option<String>,
+    /// What the payment is for.
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaymentRequestError {
+    NotBitcoinUri,
+    InvalidAddress(String),
+    InvalidAmount(String),
+    InvalidEncoding(String),
+    DuplicateParameter(String),
+    /// A parameter prefixed with `req-`, which must be understood to make the payment, but isn't.
+    UnsupportedRequirement(String),
+}
+
+impl fmt::Display for PaymentRequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotBitcoinUri => write!(f, "Not a bitcoin: payment link"),
+            Self::InvalidAddress(e) => write!(f, "Invalid address in the payment link: {}", e),
+            Self::InvalidAmount(e) => write!(f, "Invalid amount in the payment link: {}", e),
+            Self::InvalidEncoding(p) => {
+                write!(f, "Invalid percent-encoding of the parameter '{}'", p)
+            }
+            Self::DuplicateParameter(p) => {
+                write!(f, "The parameter '{}' is given more than once", p)
+            }
+            Self::UnsupportedRequirement(p) => write!(
+                f,
+                "The payment link requires '{}', which is not supported",
+                p
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PaymentRequestError {}
+
+impl PaymentRequest {
+    /// The networks the address of the request is valid for.
+    pub fn networks(&self) -> Vec<Network> {
+        NETWORKS
+            .iter()
+            .copied()
+            .filter(|network| self.address.is_valid_for_network(*network))
+            .collect()
+    }
+
+    /// A label for the payment, from the name of the recipient and the purpose of the payment.
+    pub fn description(&self) -> Option<String> {
+        match (&self.label, &self.message) {
+            (Some(label), Some(message)) => Some(format!("{}: {}", label, message)),
+            (Some(s), None) | (None, Some(s)) => Some(s.clone()),
+            (None, None) => None,
+        }
+    }
+}
+
+impl FromStr for PaymentRequest {
+    type Err = PaymentRequestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        // The scheme is case insensitive, for instance in uppercase in QR codes.
+        let rest = match (s.get(..SCHEME.len()), s.get(SCHEME.len()..)) {
+            (Some(scheme), Some(rest)) if scheme.eq_ignore_ascii_case(SCHEME) => rest,
+            _ => return Err(PaymentRequestError::NotBitcoinUri),
+        };
+        let (address, query) = match rest.split_once('?') {
+            Some((address, query)) => (address, Some(query)),
+            None => (rest, None),
+        };
+        let address = Address::from_str(address)
+            .map_err(|e| PaymentRequestError::InvalidAddress(e.to_string()))?;
+
+        let mut request = PaymentRequest {
+            address,
+            amount: None,
+            label: None,
+            message: None,
+        };
+        for param in query.into_iter().flat_map(|q| q.split('&')) {
+            if param.is_empty() {
+                continue;
+            }
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            let decoded = percent_decode(value)
+                .ok_or_else(|| PaymentRequestError::InvalidEncoding(key.to_string()))?;
+            let field = match key {
+                "amount" => {
+                    if request.amount.is_some() {
+                        return Err(PaymentRequestError::DuplicateParameter(key.to_string()));
+                    }
+                    request.amount = Some(
+                        Amount::from_str_in(&decoded, Denomination::Bitcoin)
+                            .map_err(|e| PaymentRequestError::InvalidAmount(e.to_string()))?,
+                    );
+                    continue;
+                }
+                "label" => &mut request.label,
+                "message" => &mut request.message,
+                key if key.starts_with("req-") => {
+                    return Err(PaymentRequestError::UnsupportedRequirement(
+                        key.trim_start_matches("req-").to_string(),
+                    ))
+                }
+                // Parameters we don't know of can be safely ignored.
+                _ => continue,
+            };
+            if field.is_some() {
+                return Err(PaymentRequestError::DuplicateParameter(key.to_string()));
+            }
+            *field = Some(decoded);
+        }
+
+        Ok(request)
+    }
+}
+
+// Decode the percent-encoded characters of a URI component.
+fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_payment_request() {
+        let req =
+            PaymentRequest::from_str("bitcoin:bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq").unwrap();
+        assert_eq!(
+            req.address,
+            Address::from_str("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq").unwrap()
+        );
+        assert_eq!(req.amount, None);
+        assert_eq!(req.description(), None);
+        assert_eq!(req.networks(), vec![Network::Bitcoin]);
+
+        let req = PaymentRequest::from_str(
+            "BITCOIN:TB1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KXPJZSX?amount=0.0005&label=Luke%20Jr&message=Donation%20for%20project%20xyz&lightning=lnbc1",
+        )
+        .unwrap();
+        assert_eq!(req.amount, Some(Amount::from_sat(50_000)));
+        assert_eq!(req.label.as_deref(), Some("Luke Jr"));
+        assert_eq!(
+            req.description().as_deref(),
+            Some("Luke Jr: Donation for project xyz")
+        );
+        // A testnet address is valid for all the test networks but regtest.
+        assert_eq!(req.networks(), vec![Network::Testnet, Network::Signet]);
+
+        assert_eq!(
+            PaymentRequest::from_str("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq"),
+            Err(PaymentRequestError::NotBitcoinUri)
+        );
+        assert!(matches!(
+            PaymentRequest::from_str("bitcoin:notanaddress"),
+            Err(PaymentRequestError::InvalidAddress(_))
+        ));
+        assert!(matches!(
+            PaymentRequest::from_str(
+                "bitcoin:bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq?amount=1,5"
+            ),
+            Err(PaymentRequestError::InvalidAmount(_))
+        ));
+        assert_eq!(
+            PaymentRequest::from_str(
+                "bitcoin:bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq?label=a&label=b"
+            ),
+            Err(PaymentRequestError::DuplicateParameter("label".to_string()))
+        );
+        assert_eq!(
+            PaymentRequest::from_str(
+                "bitcoin:bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq?message=%E2%82"
+            ),
+            Err(PaymentRequestError::InvalidEncoding("message".to_string()))
+        );
+        assert_eq!(
+            PaymentRequest::from_str(
+                "bitcoin:bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq?req-somethingyoudontunderstand=50"
+            ),
+            Err(PaymentRequestError::UnsupportedRequirement(
+                "somethingyoudontunderstand".to_string()
+            ))
+        );
+    }
+}
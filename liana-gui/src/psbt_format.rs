@@ -0,0 +1,484 @@
+//! PSBTs pasted or loaded by the user.
+//!
+//! Tools don't agree on how to exchange PSBTs: BIP174 specifies base64 for text and the raw binary
+//! serialization for files, but hex is common too, and some tools only hand out the signed
+//! transaction. Airgapped signers scanning QR codes use UR (Uniform Resources) instead, possibly
+//! split in several parts. The encoding is detected instead of asked for, and parsing errors point
+//! to the offending character or byte.
+
+use std::{convert::TryFrom, fmt};
+
+use base64::Engine;
+use liana::miniscript::bitcoin::{consensus, psbt::Psbt, Transaction, VarInt};
+
+/// The magic bytes of a binary PSBT, as defined in BIP174.
+pub const PSBT_MAGIC: &[u8] = b"psbt\xff";
+/// The base64 encoding of the PSBT magic bytes, up to the last complete character.
+const BASE64_PSBT_PREFIX: &str = "cHNidP8";
+/// The hex encoding of the PSBT magic bytes.
+const HEX_PSBT_PREFIX: &str = "70736274ff";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Base64,
+    Hex,
+    Binary,
+    Ur,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Base64 => write!(f, "base64"),
+            Self::Hex => write!(f, "hex"),
+            Self::Binary => write!(f, "binary"),
+            Self::Ur => write!(f, "UR"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Content {
+    Psbt(Psbt),
+    /// A transaction with at least one witness, whose signatures are to be merged into the PSBT of
+    /// the same transaction.
+    SignedTransaction(Transaction),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decoded {
+    pub format: Format,
+    pub content: Content,
+}
+
+impl fmt::Display for Decoded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.content {
+            Content::Psbt(_) => write!(f, "{} PSBT", self.format),
+            Content::SignedTransaction(_) => write!(f, "{} signed transaction", self.format),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,
+    /// A character which doesn't belong to the detected encoding, at this offset of the text.
+    InvalidCharacter {
+        format: Format,
+        offset: usize,
+        character: char,
+    },
+    /// The text has an invalid length or padding for the detected encoding.
+    InvalidLength(Format),
+    /// The PSBT ends in the middle of the record starting at this byte offset.
+    Truncated {
+        offset: usize,
+    },
+    /// The PSBT has fewer maps than the number of inputs and outputs of its transaction.
+    MissingMaps {
+        expected: usize,
+        found: usize,
+    },
+    /// The PSBT, or the transaction, ends at this byte offset but more data follows.
+    TrailingData {
+        offset: usize,
+    },
+    /// The PSBT is well-formed but one of its records is invalid.
+    InvalidPsbt(String),
+    InvalidTransaction(String),
+    /// The text is a UR, or parts of it, which could not be decoded.
+    InvalidUr(String),
+    /// A transaction was given instead of a PSBT, but it doesn't contain any signature.
+    UnsignedTransaction,
+    Unrecognized,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "Nothing to import"),
+            Self::InvalidCharacter {
+                format,
+                offset,
+                character,
+            } => write!(
+                f,
+                "Invalid {} character {:?} at offset {}",
+                format, character, offset
+            ),
+            Self::InvalidLength(format) => {
+                write!(f, "Invalid length or padding for {} encoded data", format)
+            }
+            Self::Truncated { offset } => write!(
+                f,
+                "The PSBT is truncated: the record at byte {} runs past the end of the data",
+                offset
+            ),
+            Self::MissingMaps { expected, found } => write!(
+                f,
+                "The PSBT is truncated: expected {} maps for its global data, inputs and outputs but found {}",
+                expected, found
+            ),
+            Self::TrailingData { offset } => {
+                write!(f, "Unexpected data after the end at byte {}", offset)
+            }
+            Self::InvalidPsbt(e) => write!(f, "Invalid PSBT: {}", e),
+            Self::InvalidTransaction(e) => write!(f, "Invalid transaction: {}", e),
+            Self::InvalidUr(e) => write!(f, "Invalid UR: {}", e),
+            Self::UnsignedTransaction => write!(
+                f,
+                "This is an unsigned transaction, not a PSBT or a signed transaction"
+            ),
+            Self::Unrecognized => write!(
+                f,
+                "Not a PSBT (base64, hex, UR or binary) nor a signed transaction (hex or binary)"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Whether this starts like a PSBT, in any encoding.
+pub fn looks_like_psbt(content: &[u8]) -> bool {
+    if content.starts_with(PSBT_MAGIC) {
+        return true;
+    }
+    let text = String::from_utf8_lossy(content);
+    let text = text.trim_start();
+    text.starts_with(BASE64_PSBT_PREFIX)
+        || text
+            .get(..HEX_PSBT_PREFIX.len())
+            .map(|prefix| prefix.eq_ignore_ascii_case(HEX_PSBT_PREFIX))
+            .unwrap_or(false)
+}
+
+/// Detect the encoding of a PSBT, or of a signed transaction, and decode it.
+pub fn decode(content: &[u8]) -> Result<Decoded, ParseError> {
+    if content.starts_with(PSBT_MAGIC) {
+        return decode_psbt(content).map(|psbt| Decoded {
+            format: Format::Binary,
+            content: Content::Psbt(psbt),
+        });
+    }
+    let binary_tx = || {
+        decode_signed_transaction(content).map(|tx| Decoded {
+            format: Format::Binary,
+            content: Content::SignedTransaction(tx),
+        })
+    };
+    match std::str::from_utf8(content) {
+        // A binary transaction may happen to be valid UTF-8, report the error for the text if it
+        // isn't one either.
+        Ok(text) => decode_str(text).or_else(|e| binary_tx().map_err(|_| e)),
+        Err(_) => binary_tx(),
+    }
+}
+
+/// Detect the encoding of a PSBT, or of a signed transaction, pasted as text and decode it.
+/// Whitespaces, such as the line breaks of wrapped text, are ignored. Offsets in the errors are
+/// those of the characters in the given text.
+///
+/// The parts of a multi-part UR, as typed by a QR code scanner, must be separated by whitespaces.
+pub fn decode_str(text: &str) -> Result<Decoded, ParseError> {
+    if text
+        .trim_start()
+        .get(..3)
+        .map(|prefix| prefix.eq_ignore_ascii_case("ur:"))
+        .unwrap_or(false)
+    {
+        return liana::ur::decode_psbt(&text.split_whitespace().collect::<Vec<_>>())
+            .map(|psbt| Decoded {
+                format: Format::Ur,
+                content: Content::Psbt(psbt),
+            })
+            .map_err(|e| ParseError::InvalidUr(e.to_string()));
+    }
+
+    // Keep track of the offset in the original text of each byte of the stripped one.
+    let mut offsets = Vec::with_capacity(text.len());
+    let mut stripped = String::with_capacity(text.len());
+    for (offset, c) in text.char_indices().filter(|(_, c)| !c.is_whitespace()) {
+        offsets.extend(std::iter::repeat(offset).take(c.len_utf8()));
+        stripped.push(c);
+    }
+    if stripped.is_empty() {
+        return Err(ParseError::Empty);
+    }
+    let original_offset = |offset: usize| offsets.get(offset).copied().unwrap_or(text.len());
+
+    let is_hex = stripped.chars().all(|c| c.is_ascii_hexdigit());
+    if stripped.starts_with(BASE64_PSBT_PREFIX) || !is_hex {
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(&stripped)
+            .map_err(|e| match e {
+                base64::DecodeError::InvalidByte(offset, byte)
+                | base64::DecodeError::InvalidLastSymbol(offset, byte) => {
+                    ParseError::InvalidCharacter {
+                        format: Format::Base64,
+                        offset: original_offset(offset),
+                        character: stripped
+                            .get(offset..)
+                            .and_then(|s| s.chars().next())
+                            .unwrap_or(byte as char),
+                    }
+                }
+                base64::DecodeError::InvalidLength | base64::DecodeError::InvalidPadding => {
+                    ParseError::InvalidLength(Format::Base64)
+                }
+            })?;
+        if !bytes.starts_with(PSBT_MAGIC) {
+            return Err(ParseError::Unrecognized);
+        }
+        return decode_psbt(&bytes).map(|psbt| Decoded {
+            format: Format::Base64,
+            content: Content::Psbt(psbt),
+        });
+    }
+
+    let bytes = hex::decode(&stripped).map_err(|e| match e {
+        hex::FromHexError::InvalidHexCharacter { c, index } => ParseError::InvalidCharacter {
+            format: Format::Hex,
+            offset: original_offset(index),
+            character: c,
+        },
+        hex::FromHexError::OddLength | hex::FromHexError::InvalidStringLength => {
+            ParseError::InvalidLength(Format::Hex)
+        }
+    })?;
+    if looks_like_psbt(stripped.as_bytes()) {
+        decode_psbt(&bytes).map(|psbt| Decoded {
+            format: Format::Hex,
+            content: Content::Psbt(psbt),
+        })
+    } else {
+        decode_signed_transaction(&bytes).map(|tx| Decoded {
+            format: Format::Hex,
+            content: Content::SignedTransaction(tx),
+        })
+    }
+}
+
+/// Deserialize a binary PSBT. Its key-value maps are walked first in order to report where the
+/// data is malformed, since the PSBT deserialization errors don't tell.
+fn decode_psbt(bytes: &[u8]) -> Result<Psbt, ParseError> {
+    let maps = read_maps(bytes)?;
+    // The transaction tells how many maps there should be.
+    if let Some(tx) = bytes.get(PSBT_MAGIC.len()..).and_then(unsigned_tx) {
+        let expected = 1 + tx.input.len() + tx.output.len();
+        if maps.len() < expected {
+            return Err(ParseError::MissingMaps {
+                expected,
+                found: maps.len(),
+            });
+        }
+        if let Some(offset) = maps.get(expected) {
+            return Err(ParseError::TrailingData { offset: *offset });
+        }
+    }
+    Psbt::deserialize(bytes).map_err(|e| ParseError::InvalidPsbt(e.to_string()))
+}
+
+/// Walk the key-value maps of a binary PSBT, returning the byte offset at which each map starts.
+fn read_maps(bytes: &[u8]) -> Result<Vec<usize>, ParseError> {
+    let mut maps = Vec::new();
+    let mut offset = PSBT_MAGIC.len();
+    while offset < bytes.len() {
+        maps.push(offset);
+        loop {
+            let record = offset;
+            // A record is a key and a value, each prefixed with its length. A key length of 0
+            // marks the end of the map.
+            let key_len =
+                read_length(bytes, &mut offset).ok_or(ParseError::Truncated { offset: record })?;
+            if key_len == 0 {
+                break;
+            }
+            offset = offset
+                .checked_add(key_len)
+                .filter(|end| *end <= bytes.len())
+                .ok_or(ParseError::Truncated { offset: record })?;
+            let value_len =
+                read_length(bytes, &mut offset).ok_or(ParseError::Truncated { offset: record })?;
+            offset = offset
+                .checked_add(value_len)
+                .filter(|end| *end <= bytes.len())
+                .ok_or(ParseError::Truncated { offset: record })?;
+        }
+    }
+    Ok(maps)
+}
+
+/// Read a compact size length at this offset, moving it past the length.
+fn read_length(bytes: &[u8], offset: &mut usize) -> Option<usize> {
+    let (len, read) = consensus::deserialize_partial::<VarInt>(bytes.get(*offset..)?).ok()?;
+    *offset += read;
+    usize::try_from(len.0).ok()
+}
+
+/// The unsigned transaction of the global map, which must be its first record.
+fn unsigned_tx(global_map: &[u8]) -> Option<Transaction> {
+    let mut offset = 0;
+    let key_len = read_length(global_map, &mut offset)?;
+    // PSBT_GLOBAL_UNSIGNED_TX has a single byte key of type 0x00.
+    if key_len != 1 || global_map.get(offset) != Some(&0x00) {
+        return None;
+    }
+    offset += 1;
+    let value_len = read_length(global_map, &mut offset)?;
+    consensus::deserialize(global_map.get(offset..offset.checked_add(value_len)?)?).ok()
+}
+
+fn decode_signed_transaction(bytes: &[u8]) -> Result<Transaction, ParseError> {
+    let (tx, read): (Transaction, usize) = consensus::deserialize_partial(bytes)
+        .map_err(|e| ParseError::InvalidTransaction(e.to_string()))?;
+    if read < bytes.len() {
+        return Err(ParseError::TrailingData { offset: read });
+    }
+    if tx.input.iter().all(|txin| txin.witness.is_empty()) {
+        return Err(ParseError::UnsignedTransaction);
+    }
+    Ok(tx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const PSBT: &str = "cHNidP8BAHECAAAAAUSHuliRtuCX1S6JxRuDRqDCKkWfKmWL5sV9ukZ/wzvfAAAAAAD9////AogTAAAAAAAAFgAUIxe7UY6LJ6y5mFBoWTOoVispDmdwFwAAAAAAABYAFKqO83TK+t/KdpAt21z2HGC7/Z2FAAAAAAABASsQJwAAAAAAACIAIIIySQjGCTeyx/rKUQx8qobjhJeNCiVCliBJPdyRX6XKAQVBIQI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDKxzZHapFNYASzIYkEdH9bJz6nnqUG3uBB8kiK1asmgAAAA=";
+
+    #[test]
+    fn decode_formats() {
+        let psbt = Psbt::from_str(PSBT).unwrap();
+        let bytes = psbt.serialize();
+
+        // Base64, possibly wrapped on several lines.
+        let decoded = decode_str(PSBT).unwrap();
+        assert_eq!(decoded.format, Format::Base64);
+        assert_eq!(decoded.content, Content::Psbt(psbt.clone()));
+        let wrapped: Vec<String> = PSBT
+            .as_bytes()
+            .chunks(64)
+            .map(|c| String::from_utf8(c.to_vec()).unwrap())
+            .collect();
+        assert_eq!(
+            decode_str(&format!("  {}\n", wrapped.join("\n"))).unwrap(),
+            decoded
+        );
+
+        // Hex, in any case.
+        let decoded = decode_str(&hex::encode(&bytes)).unwrap();
+        assert_eq!(decoded.format, Format::Hex);
+        assert_eq!(decoded.content, Content::Psbt(psbt.clone()));
+        assert_eq!(
+            decode_str(&hex::encode_upper(&bytes)).unwrap().content,
+            Content::Psbt(psbt.clone())
+        );
+
+        // Binary.
+        assert!(looks_like_psbt(&bytes));
+        assert!(looks_like_psbt(PSBT.as_bytes()));
+        assert!(looks_like_psbt(hex::encode_upper(&bytes).as_bytes()));
+        let decoded = decode(&bytes).unwrap();
+        assert_eq!(decoded.format, Format::Binary);
+        assert_eq!(decoded.content, Content::Psbt(psbt.clone()));
+        assert_eq!(decode(PSBT.as_bytes()).unwrap().format, Format::Base64);
+
+        // A signed transaction, in hex or binary. An unsigned one is refused.
+        let mut tx = psbt.unsigned_tx.clone();
+        tx.input[0].witness.push([1; 72]);
+        let tx_bytes = consensus::serialize(&tx);
+        assert_eq!(
+            decode_str(&hex::encode(&tx_bytes)).unwrap(),
+            Decoded {
+                format: Format::Hex,
+                content: Content::SignedTransaction(tx.clone())
+            }
+        );
+        assert_eq!(
+            decode(&tx_bytes).unwrap().content,
+            Content::SignedTransaction(tx)
+        );
+        assert_eq!(
+            decode_str(&consensus::encode::serialize_hex(&psbt.unsigned_tx)),
+            Err(ParseError::UnsignedTransaction)
+        );
+        assert!(!looks_like_psbt(&tx_bytes));
+
+        // UR, in one or several parts in any order.
+        let parts = liana::ur::encode_psbt(&psbt, 100);
+        assert!(parts.len() > 1);
+        let decoded =
+            decode_str(&parts.iter().rev().cloned().collect::<Vec<_>>().join("\n")).unwrap();
+        assert_eq!(decoded.format, Format::Ur);
+        assert_eq!(decoded.content, Content::Psbt(psbt.clone()));
+        let part = liana::ur::encode_psbt(&psbt, usize::MAX).concat();
+        assert_eq!(
+            decode_str(&part.to_lowercase()).unwrap().content,
+            Content::Psbt(psbt)
+        );
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert_eq!(decode_str(" \n"), Err(ParseError::Empty));
+        assert_eq!(decode_str("aGVsbG8="), Err(ParseError::Unrecognized));
+        assert!(matches!(
+            decode_str("ur:crypto-psbt/1-3/lpadaxcsencylobemohsgmoyadhdeynteelblrcygldkzmhlbkgh"),
+            Err(ParseError::InvalidUr(_))
+        ));
+
+        // The offset of an invalid character is in the pasted text, whitespaces included.
+        let mut text = format!("{}\n{}", &PSBT[..10], &PSBT[10..]);
+        text.replace_range(21..22, "*");
+        assert_eq!(
+            decode_str(&text),
+            Err(ParseError::InvalidCharacter {
+                format: Format::Base64,
+                offset: 21,
+                character: '*'
+            })
+        );
+        assert_eq!(
+            decode_str(&PSBT[..PSBT.len() - 1]),
+            Err(ParseError::InvalidLength(Format::Base64))
+        );
+        let hex_psbt = hex::encode(Psbt::from_str(PSBT).unwrap().serialize());
+        assert_eq!(
+            decode_str(&hex_psbt[..hex_psbt.len() - 1]),
+            Err(ParseError::InvalidLength(Format::Hex))
+        );
+
+        // Malformed binary PSBTs report the offset of the faulty record.
+        let bytes = Psbt::from_str(PSBT).unwrap().serialize();
+        // The unsigned transaction is the first record of the global map.
+        assert_eq!(
+            decode(&bytes[..50]),
+            Err(ParseError::Truncated { offset: 5 })
+        );
+        let mut trailing = bytes.clone();
+        trailing.extend_from_slice(&[0x00]);
+        assert_eq!(
+            decode(&trailing),
+            Err(ParseError::TrailingData {
+                offset: bytes.len()
+            })
+        );
+        // The PSBT has a global map, one input and two outputs.
+        let mut missing = bytes.clone();
+        missing.truncate(bytes.len() - 1);
+        assert_eq!(
+            decode(&missing),
+            Err(ParseError::MissingMaps {
+                expected: 4,
+                found: 3
+            })
+        );
+        assert!(matches!(
+            decode(b"psbt\xff\x01\x00\x00\x00"),
+            Err(ParseError::InvalidPsbt(_))
+        ));
+    }
+}
@@ -0,0 +1,196 @@
+//! Airgapped signers exchanging the PSBT through QR codes.
+//!
+//! Stateless signers such as SeedSigner or Krux keep neither the seed nor the wallet: at each
+//! signing session the seed is loaded again, and the descriptor must be scanned for the device to
+//! recognize the change outputs and the spending policy. Signing is hence a sequence of steps,
+//! scripted for each device, instead of a single request to a connected device.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use liana::{
+    descriptors::LianaPolicy,
+    miniscript::bitcoin::{
+        bip32::{ChildNumber, DerivationPath, Fingerprint},
+        psbt::Psbt,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QrSigner {
+    SeedSigner,
+    Krux,
+}
+
+/// A step of a signing session, from the point of view of the user of Liana.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// Choose the key to sign with and load its seed on the device.
+    LoadSeed,
+    /// Display the descriptor for the device to scan it.
+    ShowDescriptor,
+    /// Display the PSBT for the device to scan and sign it.
+    ShowPsbt,
+    /// Scan the signed PSBT displayed by the device.
+    ScanSignedPsbt,
+}
+
+impl QrSigner {
+    pub const ALL: [QrSigner; 2] = [QrSigner::SeedSigner, QrSigner::Krux];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::SeedSigner => "SeedSigner",
+            Self::Krux => "Krux",
+        }
+    }
+
+    pub fn steps(&self) -> &'static [Step] {
+        match self {
+            // SeedSigner verifies the change outputs against the descriptor only if one was
+            // scanned beforehand, in the same session.
+            Self::SeedSigner => &[
+                Step::LoadSeed,
+                Step::ShowDescriptor,
+                Step::ShowPsbt,
+                Step::ScanSignedPsbt,
+            ],
+            // Krux refuses to sign for a miniscript policy it wasn't given.
+            Self::Krux => &[
+                Step::LoadSeed,
+                Step::ShowDescriptor,
+                Step::ShowPsbt,
+                Step::ScanSignedPsbt,
+            ],
+        }
+    }
+
+    /// The size of the fragments of the animated QR codes, small enough for the device camera
+    /// to scan each frame reliably.
+    pub fn max_fragment_len(&self) -> usize {
+        match self {
+            Self::SeedSigner => 100,
+            Self::Krux => 200,
+        }
+    }
+
+    /// How to load the seed of a key, with its derivation paths, on the device.
+    pub fn load_seed_prompt(&self, key: &str, paths: &BTreeSet<DerivationPath>) -> String {
+        let paths = paths
+            .iter()
+            .map(|path| format!("m/{}", path))
+            .collect::<Vec<_>>()
+            .join(", ");
+        match self {
+            Self::SeedSigner => format!(
+                "Scan the SeedQR or enter the words of the seed of {} on your SeedSigner, then \
+                 check its fingerprint. Its key is derived at {}.",
+                key, paths
+            ),
+            Self::Krux => format!(
+                "Load the mnemonic of {} on your Krux, then check its fingerprint. In the wallet \
+                 settings, set the script type to miniscript and the derivation path to {}.",
+                key, paths
+            ),
+        }
+    }
+
+    pub fn prompt(&self, step: Step) -> String {
+        match (self, step) {
+            (_, Step::LoadSeed) => "Select the key to sign with:".to_string(),
+            (Self::SeedSigner, Step::ShowDescriptor) => {
+                "From the Scan menu of your SeedSigner, scan the descriptor of the wallet so it \
+                 can verify the change outputs."
+                    .to_string()
+            }
+            (Self::Krux, Step::ShowDescriptor) => "From the Wallet menu of your Krux, load the \
+                 descriptor of the wallet by scanning it."
+                .to_string(),
+            (_, Step::ShowPsbt) => format!(
+                "Scan the transaction with your {}. Check the amounts and addresses it displays \
+                 before signing.",
+                self.name()
+            ),
+            (_, Step::ScanSignedPsbt) => format!(
+                "Scan the signed transaction displayed by your {} with a QR code scanner, or \
+                 paste it:",
+                self.name()
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for QrSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The keys of the wallet, by the fingerprint of their master key, with the derivation paths of
+/// their accounts. Those are the paths to configure on a device which isn't told by the PSBT.
+pub fn keys_derivation_paths(
+    policy: &LianaPolicy,
+) -> BTreeMap<Fingerprint, BTreeSet<DerivationPath>> {
+    let mut keys: BTreeMap<Fingerprint, BTreeSet<DerivationPath>> = BTreeMap::new();
+    let paths = std::iter::once(policy.primary_path()).chain(policy.recovery_paths().values());
+    for (fingerprint, der_paths) in paths.flat_map(|path| path.thresh_origins().1) {
+        // The origins include the multipath step, which is not part of the account path.
+        keys.entry(fingerprint)
+            .or_default()
+            .extend(der_paths.into_iter().map(|der_path| {
+                let children: &[ChildNumber] = der_path.as_ref();
+                DerivationPath::from(&children[..children.len().saturating_sub(1)])
+            }));
+    }
+    keys
+}
+
+/// Whether the PSBT holds a signature of a key of this master key fingerprint.
+pub fn has_signature_from(psbt: &Psbt, fingerprint: Fingerprint) -> bool {
+    psbt.inputs.iter().any(|psbt_in| {
+        psbt_in.partial_sigs.keys().any(|pk| {
+            psbt_in
+                .bip32_derivation
+                .get(&pk.inner)
+                .map(|(fg, _)| *fg == fingerprint)
+                .unwrap_or(false)
+        }) || psbt_in.tap_script_sigs.keys().any(|(pk, _)| {
+            psbt_in
+                .tap_key_origins
+                .get(pk)
+                .map(|(_, (fg, _))| *fg == fingerprint)
+                .unwrap_or(false)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use liana::descriptors::LianaDescriptor;
+    use std::str::FromStr;
+
+    const DESC: &str = "wsh(or_d(multi(2,[f714c228/48'/1'/0'/2']tpubDEwJnTwfKoMvu8AXXBPydBVWDpzNP5tatjjZ56q4TQioGL7iL9xzTbMoCCQ3tfGihtff7vtR4xsjcRuhZ7HWARVAkGZ1HZcpBhVdou76k7j/<0;1>/*,[2522f23c/48'/1'/0'/2']tpubDEoTU4bDW1EXN1rnLXnRfue1a7DeqjJcs39PkEeLcVXhVKzCnFo9yQX2EeeXJ6kh4hgbz5o9v7YAc1EE97AEJpJbKNmDxE3ZQo4msGPSp2J/<0;1>/*),and_v(v:thresh(1,pkh([f714c228/48'/1'/0'/2']tpubDEwJnTwfKoMvu8AXXBPydBVWDpzNP5tatjjZ56q4TQioGL7iL9xzTbMoCCQ3tfGihtff7vtR4xsjcRuhZ7HWARVAkGZ1HZcpBhVdou76k7j/<2;3>/*),a:pkh([2522f23c/48'/1'/0'/2']tpubDEoTU4bDW1EXN1rnLXnRfue1a7DeqjJcs39PkEeLcVXhVKzCnFo9yQX2EeeXJ6kh4hgbz5o9v7YAc1EE97AEJpJbKNmDxE3ZQo4msGPSp2J/<2;3>/*)),older(65535))))#9s8ekrce";
+
+    #[test]
+    fn derivation_paths() {
+        let desc = LianaDescriptor::from_str(DESC).unwrap();
+        let keys = keys_derivation_paths(&desc.policy());
+        let account = DerivationPath::from_str("m/48'/1'/0'/2'").unwrap();
+        assert_eq!(
+            keys.into_iter().collect::<Vec<_>>(),
+            vec![
+                (
+                    Fingerprint::from_str("2522f23c").unwrap(),
+                    BTreeSet::from([account.clone()])
+                ),
+                (
+                    Fingerprint::from_str("f714c228").unwrap(),
+                    BTreeSet::from([account.clone()])
+                ),
+            ]
+        );
+        assert!(QrSigner::Krux
+            .load_seed_prompt("Alice", &BTreeSet::from([account]))
+            .contains("m/48'/1'/0'/2'"));
+    }
+}
@@ -0,0 +1,293 @@
+//! The hot signer of a lianad running on another machine, with its `signer_server` enabled.
+//!
+//! This allows to keep the always-online key of a wallet on a hardened server rather than on the
+//! desktop running the GUI. The server only signs PSBTs spending coins of the wallet: it runs a
+//! daemon for the same descriptor. Every request is authenticated by a bearer token, which must
+//! not travel in clear: the server is reached through HTTPS, or through an SSH tunnel to a port of
+//! this machine.
+
+use std::{fmt, str::FromStr, time::Duration};
+
+use liana::miniscript::bitcoin::{bip32::Fingerprint, psbt::Psbt, Network};
+use serde::{Deserialize, Serialize};
+
+/// Signing a PSBT with many inputs may take a while on a small server.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A remote signer of a wallet, as stored in its settings. The token is stored in clear along
+/// with the other settings.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RemoteSigner {
+    /// The base URL of the signer server, such as `https://signer.example.com`.
+    pub url: String,
+    pub token: String,
+    /// The master fingerprint of the hot signer of the server, as it answered when it was set.
+    pub fingerprint: Fingerprint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteSignerError {
+    /// The token would be sent in clear to a remote host.
+    InsecureUrl,
+    Http(String),
+    /// The server refused the request.
+    Server {
+        code: String,
+        message: String,
+    },
+    WrongNetwork(Network),
+    /// The key of the server isn't part of the wallet.
+    NotPartOfWallet(Fingerprint),
+    /// The server answered with a different fingerprint than it used to.
+    UnexpectedFingerprint(Fingerprint),
+    /// The server answered something unexpected.
+    Protocol(String),
+}
+
+impl fmt::Display for RemoteSignerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InsecureUrl => write!(
+                f,
+                "The URL must start with https://, or http://localhost for an SSH tunnel"
+            ),
+            Self::Http(e) => write!(f, "Failed to reach the remote signer: {}", e),
+            Self::Server { code, message } => write!(f, "[{}] {}", code, message),
+            Self::WrongNetwork(network) => {
+                write!(f, "The remote signer is for the {} network", network)
+            }
+            Self::NotPartOfWallet(fg) => write!(
+                f,
+                "The key #{} of the remote signer is not part of this wallet",
+                fg
+            ),
+            Self::UnexpectedFingerprint(fg) => write!(
+                f,
+                "The remote signer now signs with key #{}, set it again if this is expected",
+                fg
+            ),
+            Self::Protocol(e) => write!(f, "Unexpected answer of the remote signer: {}", e),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct InfoResponse {
+    fingerprint: Fingerprint,
+    network: Network,
+}
+
+#[derive(Debug, Deserialize)]
+struct SignResponse {
+    fingerprint: Fingerprint,
+    psbt: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    code: String,
+    message: String,
+}
+
+/// Check the token isn't going to be sent in clear to another machine.
+pub fn check_url(url: &str) -> Result<(), RemoteSignerError> {
+    let url = url.trim();
+    if url.starts_with("https://") {
+        return Ok(());
+    }
+    let host = url
+        .strip_prefix("http://")
+        .ok_or(RemoteSignerError::InsecureUrl)?
+        .split('/')
+        .next()
+        .unwrap_or("");
+    // Strip the port, careful about the colons of IPv6 addresses.
+    let host = match host.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => host,
+        _ => host,
+    };
+    if ["localhost", "127.0.0.1", "[::1]"].contains(&host) {
+        Ok(())
+    } else {
+        Err(RemoteSignerError::InsecureUrl)
+    }
+}
+
+fn client() -> Result<reqwest::Client, RemoteSignerError> {
+    reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .map_err(|e| RemoteSignerError::Http(e.to_string()))
+}
+
+fn endpoint(url: &str, path: &str) -> String {
+    format!("{}{}", url.trim().trim_end_matches('/'), path)
+}
+
+// The body of a successful response, or the error the server answered with.
+async fn response_body(res: reqwest::Response) -> Result<Vec<u8>, RemoteSignerError> {
+    let status = res.status();
+    let body = res
+        .bytes()
+        .await
+        .map_err(|e| RemoteSignerError::Http(e.to_string()))?;
+    if status.is_success() {
+        Ok(body.to_vec())
+    } else {
+        Err(match serde_json::from_slice::<ErrorResponse>(&body) {
+            Ok(ErrorResponse { code, message }) => RemoteSignerError::Server { code, message },
+            Err(_) => RemoteSignerError::Http(status.to_string()),
+        })
+    }
+}
+
+/// Query the signer server at this URL, to store it as a remote signer of a wallet on this
+/// network.
+pub async fn connect(
+    url: String,
+    token: String,
+    network: Network,
+) -> Result<RemoteSigner, RemoteSignerError> {
+    check_url(&url)?;
+    let res = client()?
+        .get(endpoint(&url, "/info"))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .map_err(|e| RemoteSignerError::Http(e.to_string()))?;
+    let info: InfoResponse = serde_json::from_slice(&response_body(res).await?)
+        .map_err(|e| RemoteSignerError::Protocol(e.to_string()))?;
+    if info.network != network {
+        return Err(RemoteSignerError::WrongNetwork(info.network));
+    }
+    Ok(RemoteSigner {
+        url: url.trim().trim_end_matches('/').to_string(),
+        token,
+        fingerprint: info.fingerprint,
+    })
+}
+
+impl RemoteSigner {
+    /// The host of the server, to tell it apart from the other signers.
+    pub fn host(&self) -> &str {
+        let url = self
+            .url
+            .split_once("://")
+            .map(|(_, u)| u)
+            .unwrap_or(&self.url);
+        url.split('/').next().unwrap_or(url)
+    }
+
+    /// Get the PSBT signed by the remote signer. Only its signatures are to be used, the server
+    /// could have modified the rest.
+    pub async fn sign_psbt(&self, psbt: &Psbt) -> Result<Psbt, RemoteSignerError> {
+        check_url(&self.url)?;
+        let res = client()?
+            .post(endpoint(&self.url, "/sign"))
+            .bearer_auth(&self.token)
+            .json(&serde_json::json!({ "psbt": psbt.to_string() }))
+            .send()
+            .await
+            .map_err(|e| RemoteSignerError::Http(e.to_string()))?;
+        self.signed_psbt(&response_body(res).await?, psbt)
+    }
+
+    fn signed_psbt(&self, body: &[u8], psbt: &Psbt) -> Result<Psbt, RemoteSignerError> {
+        let res: SignResponse =
+            serde_json::from_slice(body).map_err(|e| RemoteSignerError::Protocol(e.to_string()))?;
+        if res.fingerprint != self.fingerprint {
+            return Err(RemoteSignerError::UnexpectedFingerprint(res.fingerprint));
+        }
+        let signed =
+            Psbt::from_str(&res.psbt).map_err(|e| RemoteSignerError::Protocol(e.to_string()))?;
+        if signed.unsigned_tx != psbt.unsigned_tx {
+            return Err(RemoteSignerError::Protocol(
+                "the signed PSBT is for another transaction".to_string(),
+            ));
+        }
+        Ok(signed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use liana::miniscript::bitcoin::{absolute, transaction, OutPoint, Transaction, TxIn};
+
+    #[test]
+    fn urls() {
+        assert!(check_url("https://signer.example.com").is_ok());
+        assert!(check_url("http://localhost:8090").is_ok());
+        assert!(check_url(" http://127.0.0.1:8090/ ").is_ok());
+        assert!(check_url("http://[::1]:8090").is_ok());
+        assert_eq!(
+            check_url("http://signer.example.com"),
+            Err(RemoteSignerError::InsecureUrl)
+        );
+        assert_eq!(
+            check_url("http://localhost.example.com:8090"),
+            Err(RemoteSignerError::InsecureUrl)
+        );
+        assert_eq!(
+            check_url("signer.example.com"),
+            Err(RemoteSignerError::InsecureUrl)
+        );
+
+        let signer = RemoteSigner {
+            url: "https://signer.example.com:8443/liana".to_string(),
+            token: "token".to_string(),
+            fingerprint: Fingerprint::from_str("aabbccdd").unwrap(),
+        };
+        assert_eq!(signer.host(), "signer.example.com:8443");
+        assert_eq!(
+            endpoint(&signer.url, "/sign"),
+            "https://signer.example.com:8443/liana/sign"
+        );
+    }
+
+    #[test]
+    fn signed_psbt() {
+        let psbt = |vout| {
+            Psbt::from_unsigned_tx(Transaction {
+                version: transaction::Version::TWO,
+                lock_time: absolute::LockTime::ZERO,
+                input: vec![TxIn {
+                    previous_output: OutPoint {
+                        vout,
+                        ..OutPoint::null()
+                    },
+                    ..TxIn::default()
+                }],
+                output: vec![],
+            })
+            .unwrap()
+        };
+        let signer = RemoteSigner {
+            url: "https://signer.example.com".to_string(),
+            token: "token".to_string(),
+            fingerprint: Fingerprint::from_str("aabbccdd").unwrap(),
+        };
+        let body = |fingerprint: &str, psbt: &Psbt| {
+            serde_json::json!({ "fingerprint": fingerprint, "psbt": psbt.to_string() }).to_string()
+        };
+
+        assert_eq!(
+            signer.signed_psbt(body("aabbccdd", &psbt(0)).as_bytes(), &psbt(0)),
+            Ok(psbt(0))
+        );
+        assert_eq!(
+            signer.signed_psbt(body("00112233", &psbt(0)).as_bytes(), &psbt(0)),
+            Err(RemoteSignerError::UnexpectedFingerprint(
+                Fingerprint::from_str("00112233").unwrap()
+            ))
+        );
+        assert!(matches!(
+            signer.signed_psbt(body("aabbccdd", &psbt(1)).as_bytes(), &psbt(0)),
+            Err(RemoteSignerError::Protocol(..))
+        ));
+        assert!(matches!(
+            signer.signed_psbt(b"{}", &psbt(0)),
+            Err(RemoteSignerError::Protocol(..))
+        ));
+    }
+}
@@ -37,7 +37,9 @@ impl Signer {
     }
 
     pub fn mnemonic(&self) -> [&'static str; 12] {
-        self.key.words()
+        self.key
+            .words()
+            .expect("Generated signers are based on a BIP39 mnemonic")
     }
 
     pub fn generate(network: Network) -> Result<Self, SignerError> {
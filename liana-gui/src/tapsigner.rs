@@ -0,0 +1,1004 @@
+//! Coinkite Tapsigner, a NFC card holding a single BIP32 key, used through a USB NFC reader.
+//!
+//! The card speaks the "cktap" protocol: CBOR maps carried in ISO 7816 APDUs. The commands using
+//! the key are authenticated by the CVC printed on the back of the card, which is never sent in
+//! clear: it is masked with a session key agreed on by ECDH with the card, and the card nonce
+//! which changes after each command.
+//!
+//! The card only signs ECDSA digests, for a key derived with at most two unhardened steps from the
+//! path it was last asked to derive. It can't be used for Taproot descriptors nor register one.
+
+use std::{
+    convert::TryFrom,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use liana::{
+    descriptors::LianaDescriptor,
+    miniscript::bitcoin::{
+        bip32::{ChildNumber, DerivationPath, Fingerprint, Xpub},
+        ecdsa,
+        hashes::{sha256, Hash, HashEngine},
+        psbt::Psbt,
+        secp256k1::{self, ecdh::SharedSecret, PublicKey, Secp256k1, SecretKey},
+        sighash::SighashCache,
+    },
+    random,
+};
+
+/// The application identifier of the cktap applet.
+const APPLET_ID: &[u8] = b"\xf0CoinkiteCARDv1";
+/// The status word of a successful command.
+const SW_OK: [u8; 2] = [0x90, 0x00];
+/// The error code of the card when it's rate limiting the CVC attempts.
+const CODE_RATE_LIMITED: u64 = 429;
+/// The error code of the card when the CVC is wrong.
+const CODE_BAD_AUTH: u64 = 401;
+/// The maximum number of unhardened derivation steps the card can apply when signing.
+const MAX_SUBPATH_LEN: usize = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TapsignerError {
+    /// The NFC reader failed to talk to the card.
+    Reader(String),
+    NotTapsigner,
+    /// The CVC must be the 6 digits printed on the back of the card.
+    InvalidCvc,
+    WrongCvc,
+    NotSetUp,
+    AlreadySetUp,
+    /// The card refused the command.
+    Card {
+        code: u64,
+        message: String,
+    },
+    /// The card answered something unexpected.
+    Protocol(String),
+    /// The key of the card can't sign for this derivation path.
+    UnsupportedPath(DerivationPath),
+    NothingToSign,
+    Psbt(String),
+}
+
+impl fmt::Display for TapsignerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Reader(e) => write!(f, "NFC reader error: {}", e),
+            Self::NotTapsigner => write!(f, "The card is not a Tapsigner"),
+            Self::InvalidCvc => write!(f, "The CVC is the 6 digits on the back of the card"),
+            Self::WrongCvc => write!(f, "Wrong CVC"),
+            Self::NotSetUp => write!(f, "The Tapsigner was not set up yet"),
+            Self::AlreadySetUp => write!(f, "The Tapsigner is already set up"),
+            Self::Card { code, message } => write!(f, "Tapsigner error {}: {}", code, message),
+            Self::Protocol(e) => write!(f, "Unexpected answer from the Tapsigner: {}", e),
+            Self::UnsupportedPath(path) => write!(
+                f,
+                "The Tapsigner can't sign for the key at derivation path m/{}",
+                path
+            ),
+            Self::NothingToSign => write!(f, "The Tapsigner has no key to sign this transaction"),
+            Self::Psbt(e) => write!(f, "Cannot sign the PSBT: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TapsignerError {}
+
+/// The link to a card, such as a PC/SC reader.
+pub trait Transport: Send {
+    /// Send an APDU, returning the response with its status word.
+    fn transmit(&mut self, apdu: &[u8]) -> Result<Vec<u8>, TapsignerError>;
+}
+
+struct Session {
+    transport: Box<dyn Transport>,
+    card_nonce: [u8; 16],
+    /// The path the card derived its key for, hence the one it signs for.
+    path: Option<DerivationPath>,
+}
+
+pub struct Tapsigner {
+    session: Mutex<Session>,
+    card_pubkey: PublicKey,
+    pub version: String,
+    /// The block height at which the card was made.
+    pub birth_height: u64,
+}
+
+impl fmt::Debug for Tapsigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tapsigner")
+            .field("version", &self.version)
+            .field("birth_height", &self.birth_height)
+            .finish()
+    }
+}
+
+/// Whether a Tapsigner can sign for this descriptor.
+pub fn is_compatible_with(descriptor: &LianaDescriptor) -> bool {
+    !descriptor.is_taproot()
+}
+
+/// Check the CVC entered by the user before sending it to the card.
+pub fn check_cvc(cvc: &str) -> Result<(), TapsignerError> {
+    if cvc.len() == 6 && cvc.chars().all(|c| c.is_ascii_digit()) {
+        Ok(())
+    } else {
+        Err(TapsignerError::InvalidCvc)
+    }
+}
+
+impl Tapsigner {
+    /// Select the cktap applet of the card and read its status.
+    pub fn new(transport: Box<dyn Transport>) -> Result<Self, TapsignerError> {
+        let mut session = Session {
+            transport,
+            card_nonce: [0; 16],
+            path: None,
+        };
+        let mut apdu = vec![0x00, 0xa4, 0x04, 0x00, APPLET_ID.len() as u8];
+        apdu.extend_from_slice(APPLET_ID);
+        let status = session.exchange(&apdu)?;
+        if status.get("tapsigner") != Some(&cbor::Value::Bool(true)) {
+            return Err(TapsignerError::NotTapsigner);
+        }
+        session.path = status.path("path")?;
+        Ok(Self {
+            card_pubkey: PublicKey::from_slice(status.bytes("pubkey")?)
+                .map_err(|e| TapsignerError::Protocol(e.to_string()))?,
+            version: status.text("ver")?.to_string(),
+            birth_height: status.uint("birth")?,
+            session: Mutex::new(session),
+        })
+    }
+
+    /// Whether the key of the card was picked, which is done once in its lifetime.
+    pub fn is_set_up(&self) -> bool {
+        self.session.lock().unwrap().path.is_some()
+    }
+
+    /// Pick the key of the card, from a chain code of ours mixed with the card's entropy.
+    pub fn set_up(&self, cvc: &str) -> Result<(), TapsignerError> {
+        let mut session = self.session.lock().unwrap();
+        if session.path.is_some() {
+            return Err(TapsignerError::AlreadySetUp);
+        }
+        let chain_code =
+            random::random_bytes().map_err(|e| TapsignerError::Reader(e.to_string()))?;
+        self.authenticated(
+            &mut session,
+            "new",
+            cvc,
+            vec![
+                ("slot", cbor::Value::Uint(0)),
+                ("chain_code", cbor::Value::Bytes(chain_code.to_vec())),
+            ],
+        )?;
+        let status = session.command("status", Vec::new())?;
+        session.path = status.path("path")?;
+        Ok(())
+    }
+
+    pub fn master_fingerprint(&self, cvc: &str) -> Result<Fingerprint, TapsignerError> {
+        let mut session = self.session.lock().unwrap();
+        self.xpub(&mut session, cvc, true)
+            .map(|xpub| xpub.fingerprint())
+    }
+
+    /// The extended public key at this derivation path, with the fingerprint of the master key.
+    pub fn get_extended_pubkey(
+        &self,
+        cvc: &str,
+        path: &DerivationPath,
+    ) -> Result<(Fingerprint, Xpub), TapsignerError> {
+        let mut session = self.session.lock().unwrap();
+        let fingerprint = self.xpub(&mut session, cvc, true)?.fingerprint();
+        self.derive(&mut session, cvc, path)?;
+        Ok((fingerprint, self.xpub(&mut session, cvc, false)?))
+    }
+
+    /// Sign the inputs of the PSBT spending from the key of the card, returning the fingerprint
+    /// of the master key of the card.
+    pub fn sign_psbt(&self, cvc: &str, psbt: &mut Psbt) -> Result<Fingerprint, TapsignerError> {
+        let mut session = self.session.lock().unwrap();
+        if session.path.is_none() {
+            return Err(TapsignerError::NotSetUp);
+        }
+        let fingerprint = self.xpub(&mut session, cvc, true)?.fingerprint();
+        let secp = Secp256k1::verification_only();
+        let tx = psbt.unsigned_tx.clone();
+        let mut cache = SighashCache::new(&tx);
+        let mut signed = 0;
+        for index in 0..psbt.inputs.len() {
+            let keys: Vec<(PublicKey, DerivationPath)> = psbt.inputs[index]
+                .bip32_derivation
+                .iter()
+                .filter(|(_, (fg, _))| *fg == fingerprint)
+                .map(|(pk, (_, path))| (*pk, path.clone()))
+                .collect();
+            if keys.is_empty() {
+                continue;
+            }
+            let (msg, sighash_type) = psbt
+                .sighash_ecdsa(index, &mut cache)
+                .map_err(|e| TapsignerError::Psbt(e.to_string()))?;
+            for (pubkey, path) in keys {
+                let (account, subpath) = split_path(&path)?;
+                if session.path.as_ref() != Some(&account) {
+                    self.derive(&mut session, cvc, &account)?;
+                }
+                let (signature, card_pubkey) =
+                    self.sign_digest(&mut session, cvc, &subpath, msg.as_ref())?;
+                if card_pubkey != pubkey || secp.verify_ecdsa(&msg, &signature, &pubkey).is_err() {
+                    return Err(TapsignerError::Protocol(format!(
+                        "invalid signature for the key at m/{}",
+                        path
+                    )));
+                }
+                psbt.inputs[index].partial_sigs.insert(
+                    pubkey.into(),
+                    ecdsa::Signature {
+                        signature,
+                        sighash_type,
+                    },
+                );
+                signed += 1;
+            }
+        }
+        if signed == 0 {
+            return Err(TapsignerError::NothingToSign);
+        }
+        Ok(fingerprint)
+    }
+
+    fn xpub(&self, session: &mut Session, cvc: &str, master: bool) -> Result<Xpub, TapsignerError> {
+        let res = self.authenticated(
+            session,
+            "xpub",
+            cvc,
+            vec![("master", cbor::Value::Bool(master))],
+        )?;
+        Xpub::decode(res.bytes("xpub")?).map_err(|e| TapsignerError::Protocol(e.to_string()))
+    }
+
+    fn derive(
+        &self,
+        session: &mut Session,
+        cvc: &str,
+        path: &DerivationPath,
+    ) -> Result<(), TapsignerError> {
+        let nonce = random::random_bytes().map_err(|e| TapsignerError::Reader(e.to_string()))?;
+        self.authenticated(
+            session,
+            "derive",
+            cvc,
+            vec![
+                ("path", path_value(path)),
+                ("nonce", cbor::Value::Bytes(nonce[..16].to_vec())),
+            ],
+        )?;
+        session.path = Some(path.clone());
+        Ok(())
+    }
+
+    fn sign_digest(
+        &self,
+        session: &mut Session,
+        cvc: &str,
+        subpath: &DerivationPath,
+        digest: &[u8],
+    ) -> Result<(secp256k1::ecdsa::Signature, PublicKey), TapsignerError> {
+        let res = self.authenticated_with(session, "sign", cvc, |session_key| {
+            // The digest is encrypted with the session key, so that an eavesdropper can't tell
+            // what was signed.
+            let digest = digest
+                .iter()
+                .zip(session_key.iter())
+                .map(|(d, k)| d ^ k)
+                .collect();
+            vec![
+                ("slot", cbor::Value::Uint(0)),
+                ("subpath", path_value(subpath)),
+                ("digest", cbor::Value::Bytes(digest)),
+            ]
+        })?;
+        let mut signature = secp256k1::ecdsa::Signature::from_compact(res.bytes("sig")?)
+            .map_err(|e| TapsignerError::Protocol(e.to_string()))?;
+        signature.normalize_s();
+        let pubkey = PublicKey::from_slice(res.bytes("pubkey")?)
+            .map_err(|e| TapsignerError::Protocol(e.to_string()))?;
+        Ok((signature, pubkey))
+    }
+
+    fn authenticated(
+        &self,
+        session: &mut Session,
+        cmd: &str,
+        cvc: &str,
+        args: Vec<(&'static str, cbor::Value)>,
+    ) -> Result<Response, TapsignerError> {
+        self.authenticated_with(session, cmd, cvc, |_| args.clone())
+    }
+
+    /// Send a command authenticated with the CVC, whose arguments may depend on the session key.
+    /// The card makes us wait after too many wrong CVCs: wait, and try once more.
+    fn authenticated_with(
+        &self,
+        session: &mut Session,
+        cmd: &str,
+        cvc: &str,
+        args: impl Fn(&[u8; 32]) -> Vec<(&'static str, cbor::Value)>,
+    ) -> Result<Response, TapsignerError> {
+        check_cvc(cvc)?;
+        let mut waited = false;
+        loop {
+            let ephemeral = SecretKey::from_slice(
+                &random::random_bytes().map_err(|e| TapsignerError::Reader(e.to_string()))?,
+            )
+            .map_err(|e| TapsignerError::Reader(e.to_string()))?;
+            let session_key = SharedSecret::new(&self.card_pubkey, &ephemeral).secret_bytes();
+            let mut cmd_args = args(&session_key);
+            cmd_args.push((
+                "epubkey",
+                cbor::Value::Bytes(
+                    ephemeral
+                        .public_key(&Secp256k1::signing_only())
+                        .serialize()
+                        .to_vec(),
+                ),
+            ));
+            cmd_args.push((
+                "xcvc",
+                cbor::Value::Bytes(xcvc(cvc, cmd, &session.card_nonce, &session_key)),
+            ));
+            match session.command(cmd, cmd_args) {
+                Err(TapsignerError::Card { code, .. }) if code == CODE_BAD_AUTH => {
+                    return Err(TapsignerError::WrongCvc)
+                }
+                Err(TapsignerError::Card { code, .. }) if code == CODE_RATE_LIMITED && !waited => {
+                    session.wait()?;
+                    waited = true;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+impl Session {
+    fn command(
+        &mut self,
+        cmd: &str,
+        args: Vec<(&'static str, cbor::Value)>,
+    ) -> Result<Response, TapsignerError> {
+        let mut map = vec![("cmd".to_string(), cbor::Value::Text(cmd.to_string()))];
+        map.extend(args.into_iter().map(|(k, v)| (k.to_string(), v)));
+        let data = cbor::encode(&cbor::Value::Map(map));
+        let len = u8::try_from(data.len())
+            .map_err(|_| TapsignerError::Protocol("command too large".to_string()))?;
+        let mut apdu = vec![0x00, 0xcb, 0x00, 0x00, len];
+        apdu.extend_from_slice(&data);
+        self.exchange(&apdu)
+    }
+
+    fn exchange(&mut self, apdu: &[u8]) -> Result<Response, TapsignerError> {
+        let mut res = self.transport.transmit(apdu)?;
+        if res.len() < 2 {
+            return Err(TapsignerError::Protocol("no status word".to_string()));
+        }
+        let sw = res.split_off(res.len() - 2);
+        // The errors are reported in a CBOR map too, along with a status word.
+        let response = if res.is_empty() {
+            Response(Vec::new())
+        } else {
+            match cbor::decode(&res)? {
+                cbor::Value::Map(map) => Response(map),
+                _ => return Err(TapsignerError::Protocol("not a map".to_string())),
+            }
+        };
+        if let Some(cbor::Value::Text(message)) = response.get("error") {
+            return Err(TapsignerError::Card {
+                code: response.uint("code").unwrap_or(0),
+                message: message.clone(),
+            });
+        }
+        if sw != SW_OK {
+            return Err(TapsignerError::Protocol(format!(
+                "status word {:02x}{:02x}",
+                sw[0], sw[1]
+            )));
+        }
+        if let Some(cbor::Value::Bytes(nonce)) = response.get("card_nonce") {
+            self.card_nonce = <[u8; 16]>::try_from(&nonce[..])
+                .map_err(|_| TapsignerError::Protocol("invalid card nonce".to_string()))?;
+        }
+        Ok(response)
+    }
+
+    /// Wait for the card to accept a CVC again, about a second per `wait` command.
+    fn wait(&mut self) -> Result<(), TapsignerError> {
+        loop {
+            let res = self.command("wait", Vec::new())?;
+            if res.uint("auth_delay").unwrap_or(0) == 0 {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// The CVC masked with the session key and the hash of the card nonce and the command, so that it
+/// can't be replayed.
+fn xcvc(cvc: &str, cmd: &str, card_nonce: &[u8; 16], session_key: &[u8; 32]) -> Vec<u8> {
+    let mut engine = sha256::Hash::engine();
+    engine.input(card_nonce);
+    engine.input(cmd.as_bytes());
+    let md = sha256::Hash::from_engine(engine);
+    cvc.as_bytes()
+        .iter()
+        .zip(session_key.iter().zip(md.as_byte_array().iter()))
+        .map(|(c, (k, m))| c ^ k ^ m)
+        .collect()
+}
+
+/// Split a derivation path into the hardened path the card must derive and the unhardened steps
+/// it applies when signing.
+fn split_path(path: &DerivationPath) -> Result<(DerivationPath, DerivationPath), TapsignerError> {
+    let children: &[ChildNumber] = path.as_ref();
+    let hardened = children.iter().take_while(|c| c.is_hardened()).count();
+    let (account, subpath) = children.split_at(hardened);
+    if subpath.len() > MAX_SUBPATH_LEN || subpath.iter().any(|c| c.is_hardened()) {
+        return Err(TapsignerError::UnsupportedPath(path.clone()));
+    }
+    Ok((account.into(), subpath.into()))
+}
+
+fn path_value(path: &DerivationPath) -> cbor::Value {
+    cbor::Value::Array(
+        path.into_iter()
+            .map(|c| cbor::Value::Uint(u32::from(*c).into()))
+            .collect(),
+    )
+}
+
+/// The CBOR map answered by the card.
+struct Response(Vec<(String, cbor::Value)>);
+
+impl Response {
+    fn get(&self, key: &str) -> Option<&cbor::Value> {
+        self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    fn missing(key: &str) -> TapsignerError {
+        TapsignerError::Protocol(format!("missing or invalid field {}", key))
+    }
+
+    fn bytes(&self, key: &str) -> Result<&[u8], TapsignerError> {
+        match self.get(key) {
+            Some(cbor::Value::Bytes(b)) => Ok(b),
+            _ => Err(Self::missing(key)),
+        }
+    }
+
+    fn text(&self, key: &str) -> Result<&str, TapsignerError> {
+        match self.get(key) {
+            Some(cbor::Value::Text(t)) => Ok(t),
+            _ => Err(Self::missing(key)),
+        }
+    }
+
+    fn uint(&self, key: &str) -> Result<u64, TapsignerError> {
+        match self.get(key) {
+            Some(cbor::Value::Uint(n)) => Ok(*n),
+            _ => Err(Self::missing(key)),
+        }
+    }
+
+    /// A derivation path, absent when the card wasn't set up.
+    fn path(&self, key: &str) -> Result<Option<DerivationPath>, TapsignerError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(cbor::Value::Array(children)) => children
+                .iter()
+                .map(|c| match c {
+                    cbor::Value::Uint(n) => u32::try_from(*n)
+                        .map(ChildNumber::from)
+                        .map_err(|_| Self::missing(key)),
+                    _ => Err(Self::missing(key)),
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(|children| Some(children.into())),
+            Some(_) => Err(Self::missing(key)),
+        }
+    }
+}
+
+/// The subset of CBOR used by the cktap protocol.
+mod cbor {
+    use super::TapsignerError;
+    use std::convert::TryFrom;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Value {
+        Uint(u64),
+        Bytes(Vec<u8>),
+        Text(String),
+        Array(Vec<Value>),
+        Map(Vec<(String, Value)>),
+        Bool(bool),
+        Null,
+    }
+
+    fn header(buf: &mut Vec<u8>, major: u8, arg: u64) {
+        let major = major << 5;
+        if arg < 24 {
+            buf.push(major | arg as u8);
+        } else if arg <= u8::MAX.into() {
+            buf.extend_from_slice(&[major | 24, arg as u8]);
+        } else if arg <= u16::MAX.into() {
+            buf.push(major | 25);
+            buf.extend_from_slice(&(arg as u16).to_be_bytes());
+        } else if arg <= u32::MAX.into() {
+            buf.push(major | 26);
+            buf.extend_from_slice(&(arg as u32).to_be_bytes());
+        } else {
+            buf.push(major | 27);
+            buf.extend_from_slice(&arg.to_be_bytes());
+        }
+    }
+
+    fn encode_into(value: &Value, buf: &mut Vec<u8>) {
+        match value {
+            Value::Uint(n) => header(buf, 0, *n),
+            Value::Bytes(b) => {
+                header(buf, 2, b.len() as u64);
+                buf.extend_from_slice(b);
+            }
+            Value::Text(t) => {
+                header(buf, 3, t.len() as u64);
+                buf.extend_from_slice(t.as_bytes());
+            }
+            Value::Array(items) => {
+                header(buf, 4, items.len() as u64);
+                for item in items {
+                    encode_into(item, buf);
+                }
+            }
+            Value::Map(entries) => {
+                header(buf, 5, entries.len() as u64);
+                for (key, value) in entries {
+                    encode_into(&Value::Text(key.clone()), buf);
+                    encode_into(value, buf);
+                }
+            }
+            Value::Bool(b) => buf.push(if *b { 0xf5 } else { 0xf4 }),
+            Value::Null => buf.push(0xf6),
+        }
+    }
+
+    pub fn encode(value: &Value) -> Vec<u8> {
+        let mut buf = Vec::new();
+        encode_into(value, &mut buf);
+        buf
+    }
+
+    /// Decode a single data item taking all the bytes.
+    pub fn decode(bytes: &[u8]) -> Result<Value, TapsignerError> {
+        let mut reader = Reader { bytes, offset: 0 };
+        let value = reader.value(0)?;
+        if reader.offset != bytes.len() {
+            return Err(invalid());
+        }
+        Ok(value)
+    }
+
+    fn invalid() -> TapsignerError {
+        TapsignerError::Protocol("invalid CBOR".to_string())
+    }
+
+    struct Reader<'a> {
+        bytes: &'a [u8],
+        offset: usize,
+    }
+
+    impl Reader<'_> {
+        fn take(&mut self, len: usize) -> Result<&[u8], TapsignerError> {
+            let end = self.offset.checked_add(len).ok_or_else(invalid)?;
+            let bytes = self.bytes.get(self.offset..end).ok_or_else(invalid)?;
+            self.offset = end;
+            Ok(bytes)
+        }
+
+        fn value(&mut self, depth: usize) -> Result<Value, TapsignerError> {
+            // The card answers flat maps, don't recurse forever on garbage.
+            if depth > 4 {
+                return Err(invalid());
+            }
+            let initial = self.take(1)?[0];
+            let (major, info) = (initial >> 5, initial & 0x1f);
+            if major == 7 {
+                return match info {
+                    20 => Ok(Value::Bool(false)),
+                    21 => Ok(Value::Bool(true)),
+                    22 => Ok(Value::Null),
+                    _ => Err(invalid()),
+                };
+            }
+            let arg = match info {
+                0..=23 => info.into(),
+                24 => self.take(1)?[0].into(),
+                25 => u16::from_be_bytes([self.take(1)?[0], self.take(1)?[0]]).into(),
+                26 => {
+                    u32::from_be_bytes(<[u8; 4]>::try_from(self.take(4)?).map_err(|_| invalid())?)
+                        .into()
+                }
+                27 => {
+                    u64::from_be_bytes(<[u8; 8]>::try_from(self.take(8)?).map_err(|_| invalid())?)
+                }
+                _ => return Err(invalid()),
+            };
+            let len = usize::try_from(arg).map_err(|_| invalid());
+            match major {
+                0 => Ok(Value::Uint(arg)),
+                2 => Ok(Value::Bytes(self.take(len?)?.to_vec())),
+                3 => String::from_utf8(self.take(len?)?.to_vec())
+                    .map(Value::Text)
+                    .map_err(|_| invalid()),
+                4 => (0..len?)
+                    .map(|_| self.value(depth + 1))
+                    .collect::<Result<_, _>>()
+                    .map(Value::Array),
+                5 => (0..len?)
+                    .map(|_| match self.value(depth + 1)? {
+                        Value::Text(key) => Ok((key, self.value(depth + 1)?)),
+                        _ => Err(invalid()),
+                    })
+                    .collect::<Result<_, _>>()
+                    .map(Value::Map),
+                _ => Err(invalid()),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tapsigner")]
+impl Transport for pcsc::Card {
+    fn transmit(&mut self, apdu: &[u8]) -> Result<Vec<u8>, TapsignerError> {
+        let mut buf = [0; pcsc::MAX_BUFFER_SIZE];
+        pcsc::Card::transmit(self, apdu, &mut buf)
+            .map(|res| res.to_vec())
+            .map_err(|e| TapsignerError::Reader(e.to_string()))
+    }
+}
+
+/// The names of the NFC readers with a card on them.
+#[cfg(feature = "tapsigner")]
+pub fn readers() -> Result<Vec<String>, TapsignerError> {
+    let ctx = pcsc::Context::establish(pcsc::Scope::User)
+        .map_err(|e| TapsignerError::Reader(e.to_string()))?;
+    let readers = match ctx.list_readers_owned() {
+        Ok(readers) => readers,
+        Err(pcsc::Error::NoReadersAvailable) => return Ok(Vec::new()),
+        Err(e) => return Err(TapsignerError::Reader(e.to_string())),
+    };
+    let mut states: Vec<_> = readers
+        .into_iter()
+        .map(|reader| pcsc::ReaderState::new(reader, pcsc::State::UNAWARE))
+        .collect();
+    ctx.get_status_change(Some(std::time::Duration::ZERO), &mut states)
+        .map_err(|e| TapsignerError::Reader(e.to_string()))?;
+    Ok(states
+        .iter()
+        .filter(|state| state.event_state().contains(pcsc::State::PRESENT))
+        .map(|state| state.name().to_string_lossy().into_owned())
+        .collect())
+}
+
+/// Liana is built without support for the NFC readers.
+#[cfg(not(feature = "tapsigner"))]
+pub fn readers() -> Result<Vec<String>, TapsignerError> {
+    Ok(Vec::new())
+}
+
+#[cfg(feature = "tapsigner")]
+pub fn connect(reader: &str) -> Result<Arc<Tapsigner>, TapsignerError> {
+    let ctx = pcsc::Context::establish(pcsc::Scope::User)
+        .map_err(|e| TapsignerError::Reader(e.to_string()))?;
+    let reader =
+        std::ffi::CString::new(reader).map_err(|e| TapsignerError::Reader(e.to_string()))?;
+    let card = ctx
+        .connect(&reader, pcsc::ShareMode::Shared, pcsc::Protocols::ANY)
+        .map_err(|e| TapsignerError::Reader(e.to_string()))?;
+    Tapsigner::new(Box::new(card)).map(Arc::new)
+}
+
+#[cfg(not(feature = "tapsigner"))]
+pub fn connect(_reader: &str) -> Result<Arc<Tapsigner>, TapsignerError> {
+    Err(TapsignerError::Reader(
+        "Liana was built without support for NFC readers".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use liana::miniscript::bitcoin::{
+        absolute, bip32::Xpriv, blockdata::script::Builder, opcodes, transaction, Amount, Network,
+        OutPoint, ScriptBuf, Transaction, TxIn, TxOut, WScriptHash,
+    };
+    use std::str::FromStr;
+
+    const CVC: &str = "123456";
+
+    /// A card following the cktap protocol, as far as the Tapsigner module uses it.
+    struct MockCard {
+        identity: SecretKey,
+        master: Xpriv,
+        path: Option<DerivationPath>,
+        nonce: u8,
+    }
+
+    impl MockCard {
+        fn new() -> Self {
+            Self {
+                identity: SecretKey::from_slice(&[0x01; 32]).unwrap(),
+                master: Xpriv::new_master(Network::Testnet, &[0x02; 32]).unwrap(),
+                path: None,
+                nonce: 0,
+            }
+        }
+
+        fn answer(&mut self, mut map: Vec<(&str, cbor::Value)>) -> Vec<u8> {
+            self.nonce += 1;
+            map.push(("card_nonce", cbor::Value::Bytes(vec![self.nonce; 16])));
+            let mut res = cbor::encode(&cbor::Value::Map(
+                map.into_iter().map(|(k, v)| (k.to_string(), v)).collect(),
+            ));
+            res.extend_from_slice(&SW_OK);
+            res
+        }
+
+        fn error(&self, code: u64, message: &str) -> Vec<u8> {
+            let mut res = cbor::encode(&cbor::Value::Map(vec![
+                ("error".to_string(), cbor::Value::Text(message.to_string())),
+                ("code".to_string(), cbor::Value::Uint(code)),
+            ]));
+            res.extend_from_slice(&[0x6a, 0x80]);
+            res
+        }
+
+        fn status(&mut self) -> Vec<u8> {
+            let secp = Secp256k1::new();
+            let mut map = vec![
+                ("proto", cbor::Value::Uint(1)),
+                ("ver", cbor::Value::Text("1.0.3".to_string())),
+                ("birth", cbor::Value::Uint(700_000)),
+                ("tapsigner", cbor::Value::Bool(true)),
+                (
+                    "pubkey",
+                    cbor::Value::Bytes(self.identity.public_key(&secp).serialize().to_vec()),
+                ),
+            ];
+            if let Some(path) = &self.path {
+                map.push(("path", path_value(path)));
+            }
+            self.answer(map)
+        }
+    }
+
+    impl Transport for MockCard {
+        fn transmit(&mut self, apdu: &[u8]) -> Result<Vec<u8>, TapsignerError> {
+            if apdu[..4] == [0x00, 0xa4, 0x04, 0x00] {
+                assert_eq!(&apdu[5..], APPLET_ID);
+                return Ok(self.status());
+            }
+            assert_eq!(apdu[..4], [0x00, 0xcb, 0x00, 0x00]);
+            assert_eq!(apdu[4] as usize, apdu.len() - 5);
+            let req = match cbor::decode(&apdu[5..]).unwrap() {
+                cbor::Value::Map(map) => Response(map),
+                _ => panic!("not a map"),
+            };
+            let cmd = req.text("cmd").unwrap().to_string();
+            if cmd == "status" {
+                return Ok(self.status());
+            }
+            let epubkey = PublicKey::from_slice(req.bytes("epubkey").unwrap()).unwrap();
+            let session_key = SharedSecret::new(&epubkey, &self.identity).secret_bytes();
+            let card_nonce = [self.nonce; 16];
+            if req.bytes("xcvc").unwrap() != xcvc(CVC, &cmd, &card_nonce, &session_key) {
+                return Ok(self.error(CODE_BAD_AUTH, "bad auth"));
+            }
+            let secp = Secp256k1::new();
+            let res = match cmd.as_str() {
+                "new" => {
+                    self.path = Some(DerivationPath::from_str("m/84'/0'/0'").unwrap());
+                    self.answer(vec![("slot", cbor::Value::Uint(0))])
+                }
+                "derive" => {
+                    self.path = req.path("path").unwrap();
+                    self.answer(Vec::new())
+                }
+                "xpub" => {
+                    let path = if req.get("master") == Some(&cbor::Value::Bool(true)) {
+                        DerivationPath::master()
+                    } else {
+                        self.path.clone().unwrap()
+                    };
+                    let xpriv = self.master.derive_priv(&secp, &path).unwrap();
+                    let xpub = Xpub::from_priv(&secp, &xpriv);
+                    self.answer(vec![("xpub", cbor::Value::Bytes(xpub.encode().to_vec()))])
+                }
+                "sign" => {
+                    let path = self
+                        .path
+                        .clone()
+                        .unwrap()
+                        .extend(req.path("subpath").unwrap().unwrap());
+                    let key = self.master.derive_priv(&secp, &path).unwrap().private_key;
+                    let digest: Vec<u8> = req
+                        .bytes("digest")
+                        .unwrap()
+                        .iter()
+                        .zip(session_key.iter())
+                        .map(|(d, k)| d ^ k)
+                        .collect();
+                    let msg = secp256k1::Message::from_digest_slice(&digest).unwrap();
+                    let sig = secp.sign_ecdsa(&msg, &key);
+                    self.answer(vec![
+                        ("slot", cbor::Value::Uint(0)),
+                        ("sig", cbor::Value::Bytes(sig.serialize_compact().to_vec())),
+                        (
+                            "pubkey",
+                            cbor::Value::Bytes(key.public_key(&secp).serialize().to_vec()),
+                        ),
+                    ])
+                }
+                _ => self.error(404, "unknown command"),
+            };
+            Ok(res)
+        }
+    }
+
+    #[test]
+    fn cbor_roundtrip() {
+        let value = cbor::Value::Map(vec![
+            ("cmd".to_string(), cbor::Value::Text("sign".to_string())),
+            ("slot".to_string(), cbor::Value::Uint(0)),
+            (
+                "subpath".to_string(),
+                cbor::Value::Array(vec![cbor::Value::Uint(1), cbor::Value::Uint(0x8000_0030)]),
+            ),
+            ("digest".to_string(), cbor::Value::Bytes(vec![0xab; 32])),
+            ("master".to_string(), cbor::Value::Bool(true)),
+            ("big".to_string(), cbor::Value::Uint(u64::MAX)),
+            ("null".to_string(), cbor::Value::Null),
+        ]);
+        let bytes = cbor::encode(&value);
+        assert_eq!(&bytes[..5], &[0xa7, 0x63, b'c', b'm', b'd']);
+        assert_eq!(cbor::decode(&bytes).unwrap(), value);
+        assert!(cbor::decode(&bytes[..bytes.len() - 1]).is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0x00);
+        assert!(cbor::decode(&trailing).is_err());
+        // Maps keyed by something else than text aren't used by the card.
+        assert!(cbor::decode(&[0xa1, 0x01, 0x02]).is_err());
+    }
+
+    #[test]
+    fn paths() {
+        let (account, subpath) =
+            split_path(&DerivationPath::from_str("m/48'/1'/0'/2'/1/42").unwrap()).unwrap();
+        assert_eq!(account, DerivationPath::from_str("m/48'/1'/0'/2'").unwrap());
+        assert_eq!(subpath, DerivationPath::from_str("m/1/42").unwrap());
+        for path in ["m/48'/1'/0'/2'/0/1/2", "m/48'/1/0'"] {
+            let path = DerivationPath::from_str(path).unwrap();
+            assert_eq!(
+                split_path(&path),
+                Err(TapsignerError::UnsupportedPath(path.clone()))
+            );
+        }
+        assert!(check_cvc(CVC).is_ok());
+        assert_eq!(check_cvc("12345"), Err(TapsignerError::InvalidCvc));
+        assert_eq!(check_cvc("12345a"), Err(TapsignerError::InvalidCvc));
+    }
+
+    #[test]
+    fn set_up_export_and_sign() {
+        let card = MockCard::new();
+        let secp = Secp256k1::new();
+        let master = card.master;
+        let tapsigner = Tapsigner::new(Box::new(card)).unwrap();
+        assert_eq!(tapsigner.version, "1.0.3");
+        assert_eq!(tapsigner.birth_height, 700_000);
+        assert!(!tapsigner.is_set_up());
+        assert_eq!(
+            tapsigner.sign_psbt(CVC, &mut Psbt::from_unsigned_tx(dummy_tx()).unwrap()),
+            Err(TapsignerError::NotSetUp)
+        );
+
+        assert_eq!(tapsigner.set_up("654321"), Err(TapsignerError::WrongCvc));
+        tapsigner.set_up(CVC).unwrap();
+        assert!(tapsigner.is_set_up());
+        assert_eq!(tapsigner.set_up(CVC), Err(TapsignerError::AlreadySetUp));
+
+        let fingerprint = master.fingerprint(&secp);
+        assert_eq!(tapsigner.master_fingerprint(CVC).unwrap(), fingerprint);
+        let account = DerivationPath::from_str("m/48'/1'/0'/2'").unwrap();
+        let (fg, xpub) = tapsigner.get_extended_pubkey(CVC, &account).unwrap();
+        assert_eq!(fg, fingerprint);
+        assert_eq!(
+            xpub,
+            Xpub::from_priv(&secp, &master.derive_priv(&secp, &account).unwrap())
+        );
+
+        // A P2WSH input spendable by a key of the card, and one by another key.
+        let path = account.extend(DerivationPath::from_str("m/0/3").unwrap());
+        let pubkey = master
+            .derive_priv(&secp, &path)
+            .unwrap()
+            .private_key
+            .public_key(&secp);
+        let other = SecretKey::from_slice(&[0x03; 32])
+            .unwrap()
+            .public_key(&secp);
+        let mut psbt = Psbt::from_unsigned_tx(dummy_tx()).unwrap();
+        for (psbt_in, key) in psbt.inputs.iter_mut().zip([pubkey, other]) {
+            let script = Builder::new()
+                .push_slice(key.serialize())
+                .push_opcode(opcodes::all::OP_CHECKSIG)
+                .into_script();
+            psbt_in.witness_utxo = Some(TxOut {
+                value: Amount::from_sat(10_000),
+                script_pubkey: ScriptBuf::new_p2wsh(&WScriptHash::hash(script.as_bytes())),
+            });
+            psbt_in.witness_script = Some(script);
+            psbt_in
+                .bip32_derivation
+                .insert(key, (Fingerprint::from([0xaa; 4]), path.clone()));
+        }
+        psbt.inputs[0]
+            .bip32_derivation
+            .insert(pubkey, (fingerprint, path.clone()));
+        assert_eq!(
+            Tapsigner::new(Box::new(MockCard::new()))
+                .unwrap()
+                .sign_psbt(CVC, &mut psbt.clone()),
+            Err(TapsignerError::NotSetUp)
+        );
+        assert_eq!(tapsigner.sign_psbt(CVC, &mut psbt), Ok(fingerprint));
+        let mut cache = SighashCache::new(&psbt.unsigned_tx);
+        let (msg, _) = psbt.sighash_ecdsa(0, &mut cache).unwrap();
+        let sig = psbt.inputs[0].partial_sigs.get(&pubkey.into()).unwrap();
+        secp.verify_ecdsa(&msg, &sig.signature, &pubkey).unwrap();
+        assert!(psbt.inputs[1].partial_sigs.is_empty());
+
+        // Nothing to sign for a key which isn't the card's.
+        psbt.inputs[0].bip32_derivation.remove(&pubkey);
+        assert_eq!(
+            tapsigner.sign_psbt(CVC, &mut psbt),
+            Err(TapsignerError::NothingToSign)
+        );
+    }
+
+    fn dummy_tx() -> Transaction {
+        Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: (0..2)
+                .map(|vout| TxIn {
+                    previous_output: OutPoint::new(
+                        "0000000000000000000000000000000000000000000000000000000000000001"
+                            .parse()
+                            .unwrap(),
+                        vout,
+                    ),
+                    ..Default::default()
+                })
+                .collect(),
+            output: vec![TxOut {
+                value: Amount::from_sat(19_000),
+                script_pubkey: ScriptBuf::new_op_return([]),
+            }],
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! System tray icon, allowing to keep the wallet running in the background once its window is
+//! closed.
+//!
+//! On Linux the tray icon must live on a thread running a GTK event loop, elsewhere it lives on
+//! the main thread. The actions of its menu are received through a global channel which the GUI
+//! polls.
+
+use tray_icon::{
+    menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem},
+    Icon, TrayIcon, TrayIconBuilder,
+};
+
+const OPEN_ID: &str = "open";
+const RECEIVE_ID: &str = "receive";
+const QUIT_ID: &str = "quit";
+
+/// An action of the tray menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayAction {
+    /// Show the window.
+    Open,
+    /// Show the window on the receive panel.
+    ReceiveAddress,
+    /// Quit the application, stopping the daemon and the managed node.
+    Quit,
+}
+
+fn icon() -> Result<Icon, String> {
+    let image = image::load_from_memory(liana_ui::image::liana_app_icon_data())
+        .map_err(|e| e.to_string())?
+        .into_rgba8();
+    let (width, height) = image.dimensions();
+    Icon::from_rgba(image.into_raw(), width, height).map_err(|e| e.to_string())
+}
+
+fn build() -> Result<TrayIcon, String> {
+    let menu = Menu::new();
+    menu.append_items(&[
+        &MenuItem::with_id(OPEN_ID, "Open Liana", true, None),
+        &MenuItem::with_id(RECEIVE_ID, "Receive address", true, None),
+        &PredefinedMenuItem::separator(),
+        &MenuItem::with_id(QUIT_ID, "Quit and stop node", true, None),
+    ])
+    .map_err(|e| e.to_string())?;
+    TrayIconBuilder::new()
+        .with_menu(Box::new(menu))
+        .with_tooltip("Liana")
+        .with_icon(icon()?)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+pub struct Tray {
+    #[cfg(not(target_os = "linux"))]
+    _icon: TrayIcon,
+}
+
+impl Tray {
+    pub fn new() -> Result<Tray, String> {
+        #[cfg(target_os = "linux")]
+        {
+            std::thread::Builder::new()
+                .name("System tray".to_string())
+                .spawn(|| {
+                    if let Err(e) = gtk::init() {
+                        tracing::error!("Failed to initialize GTK for the system tray: {}", e);
+                        return;
+                    }
+                    match build() {
+                        Ok(_icon) => gtk::main(),
+                        Err(e) => tracing::error!("Failed to create the system tray icon: {}", e),
+                    }
+                })
+                .map_err(|e| e.to_string())?;
+            Ok(Tray {})
+        }
+        #[cfg(not(target_os = "linux"))]
+        Ok(Tray { _icon: build()? })
+    }
+
+    /// The actions selected in the tray menu since the last call.
+    pub fn actions(&self) -> Vec<TrayAction> {
+        MenuEvent::receiver()
+            .try_iter()
+            .filter_map(|event| match event.id.0.as_str() {
+                OPEN_ID => Some(TrayAction::Open),
+                RECEIVE_ID => Some(TrayAction::ReceiveAddress),
+                QUIT_ID => Some(TrayAction::Quit),
+                _ => None,
+            })
+            .collect()
+    }
+}
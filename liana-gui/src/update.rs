@@ -0,0 +1,225 @@
+//! Check for new releases of Liana.
+//!
+//! The maintainers publish a manifest of the latest release along with their signatures of it.
+//! The manifest is only trusted if it's signed by enough of the keys pinned below. We never
+//! install anything, only tell the user a new release is available.
+
+use liana::miniscript::bitcoin::{
+    hashes::{sha256, Hash},
+    secp256k1::{self, schnorr, XOnlyPublicKey},
+};
+use serde::{Deserialize, Serialize};
+
+use std::str::FromStr;
+
+use crate::VERSION;
+
+/// Where the signed manifest of the latest release is published.
+pub const MANIFEST_URL: &str = "https://wizardsardine.com/liana/release.json";
+
+/// The BIP340 public keys of the maintainers signing the release manifests.
+const RELEASE_SIGNING_KEYS: [&str; 2] = [
+    "51a01a63b529b6a83ba8e11f60e1233784e73402a13b1bff9cafc3dda44f0ba4",
+    "f127fc339a8288c790b849034c4bad165b671af9c47748b41674278bfbcb78dd",
+];
+
+/// How many of the maintainers must have signed a manifest for it to be trusted.
+const SIGNATURES_THRESHOLD: usize = 2;
+
+/// Settings of the update check, in the `[update_check]` section of the GUI configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct UpdateCheckConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Proxy to fetch the manifest through, for instance `socks5h://127.0.0.1:9050` to use Tor.
+    pub proxy: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for UpdateCheckConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            proxy: None,
+        }
+    }
+}
+
+/// A release of Liana, as described by its manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Release {
+    pub version: String,
+    /// The main changes of the release, one per line.
+    pub changelog: Vec<String>,
+    /// Where to get the release from.
+    pub url: String,
+}
+
+impl Release {
+    /// Whether this release is more recent than the running one.
+    pub fn is_newer(&self) -> bool {
+        parse_version(&self.version)
+            .map(|v| v > (VERSION.major, VERSION.minor, VERSION.patch))
+            .unwrap_or(false)
+    }
+}
+
+fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = version.trim_start_matches('v').split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().unwrap_or("0").parse().ok()?;
+    let patch = parts.next().unwrap_or("0").parse().ok()?;
+    Some((major, minor, patch))
+}
+
+/// The manifest as published: the serialized release and the maintainers' signatures of its
+/// SHA256 hash.
+#[derive(Debug, Clone, Deserialize)]
+struct SignedManifest {
+    manifest: String,
+    signatures: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum UpdateError {
+    Http(String),
+    InvalidManifest(String),
+    /// The manifest isn't signed by enough maintainers.
+    InsufficientSignatures(usize),
+}
+
+impl std::fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Http(e) => write!(f, "Failed to fetch the release manifest: {}", e),
+            Self::InvalidManifest(e) => write!(f, "Invalid release manifest: {}", e),
+            Self::InsufficientSignatures(n) => write!(
+                f,
+                "Release manifest signed by {} maintainer(s), {} required",
+                n, SIGNATURES_THRESHOLD
+            ),
+        }
+    }
+}
+
+// Parse the signed manifest and check it was signed by at least `threshold` of the given keys.
+fn verify(
+    content: &[u8],
+    keys: &[XOnlyPublicKey],
+    threshold: usize,
+) -> Result<Release, UpdateError> {
+    let signed: SignedManifest =
+        serde_json::from_slice(content).map_err(|e| UpdateError::InvalidManifest(e.to_string()))?;
+    let msg = secp256k1::Message::from_digest(
+        sha256::Hash::hash(signed.manifest.as_bytes()).to_byte_array(),
+    );
+    let secp = secp256k1::Secp256k1::verification_only();
+    let signers = keys
+        .iter()
+        .filter(|key| {
+            signed.signatures.iter().any(|sig| {
+                hex::decode(sig)
+                    .ok()
+                    .and_then(|sig| schnorr::Signature::from_slice(&sig).ok())
+                    .map(|sig| secp.verify_schnorr(&sig, &msg, key).is_ok())
+                    .unwrap_or(false)
+            })
+        })
+        .count();
+    if signers < threshold {
+        return Err(UpdateError::InsufficientSignatures(signers));
+    }
+    serde_json::from_str(&signed.manifest).map_err(|e| UpdateError::InvalidManifest(e.to_string()))
+}
+
+/// Fetch the manifest of the latest release and return it if it's newer than the running version.
+pub async fn check(config: UpdateCheckConfig) -> Result<Option<Release>, UpdateError> {
+    let mut client = reqwest::Client::builder();
+    if let Some(proxy) = &config.proxy {
+        client =
+            client.proxy(reqwest::Proxy::all(proxy).map_err(|e| UpdateError::Http(e.to_string()))?);
+    }
+    let content = client
+        .build()
+        .map_err(|e| UpdateError::Http(e.to_string()))?
+        .get(MANIFEST_URL)
+        .header("User-Agent", "liana-gui")
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+        .map_err(|e| UpdateError::Http(e.to_string()))?
+        .bytes()
+        .await
+        .map_err(|e| UpdateError::Http(e.to_string()))?;
+
+    let keys: Vec<_> = RELEASE_SIGNING_KEYS
+        .iter()
+        .map(|key| XOnlyPublicKey::from_str(key).expect("Valid hardcoded keys"))
+        .collect();
+    let release = verify(&content, &keys, SIGNATURES_THRESHOLD)?;
+    Ok(Some(release).filter(Release::is_newer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn release_signatures() {
+        let secp = secp256k1::Secp256k1::new();
+        let keypairs: Vec<_> = (1..=3u8)
+            .map(|i| {
+                let sk = secp256k1::SecretKey::from_slice(&[i; 32]).unwrap();
+                secp256k1::Keypair::from_secret_key(&secp, &sk)
+            })
+            .collect();
+        let keys: Vec<_> = keypairs.iter().map(|kp| kp.x_only_public_key().0).collect();
+
+        let manifest =
+            r#"{"version":"99.0.0","changelog":["New feature."],"url":"https://example.com"}"#;
+        let msg = secp256k1::Message::from_digest(
+            sha256::Hash::hash(manifest.as_bytes()).to_byte_array(),
+        );
+        let signed = |signers: &[usize]| {
+            serde_json::json!({
+                "manifest": manifest,
+                "signatures": signers
+                    .iter()
+                    .map(|i| hex::encode(secp.sign_schnorr_no_aux_rand(&msg, &keypairs[*i]).serialize()))
+                    .collect::<Vec<_>>(),
+            })
+            .to_string()
+        };
+
+        let release = verify(signed(&[0, 2]).as_bytes(), &keys, 2).unwrap();
+        assert_eq!(release.version, "99.0.0");
+        assert!(release.is_newer());
+
+        // The same signature twice doesn't count for two maintainers.
+        assert!(matches!(
+            verify(signed(&[1, 1]).as_bytes(), &keys, 2),
+            Err(UpdateError::InsufficientSignatures(1))
+        ));
+        // A signature by an unknown key doesn't count.
+        assert!(matches!(
+            verify(signed(&[0, 1]).as_bytes(), &keys[..1], 2),
+            Err(UpdateError::InsufficientSignatures(1))
+        ));
+        // A tampered manifest isn't trusted.
+        let tampered = signed(&[0, 1, 2]).replace("99.0.0", "98.0.0");
+        assert!(matches!(
+            verify(tampered.as_bytes(), &keys, 2),
+            Err(UpdateError::InsufficientSignatures(0))
+        ));
+    }
+
+    #[test]
+    fn release_versions() {
+        assert_eq!(parse_version("v9.1"), Some((9, 1, 0)));
+        assert_eq!(parse_version("10.0.2"), Some((10, 0, 2)));
+        assert_eq!(parse_version("ten"), None);
+    }
+}
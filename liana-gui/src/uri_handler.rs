@@ -0,0 +1,226 @@
+//! Register the GUI as the handler of the `bitcoin:` payment links of the operating system, so
+//! clicking such a link opens the send screen of the wallet prefilled with the payment request.
+//!
+//! This is opt-in: the registration replaces the handler the user may have set, and is undone on
+//! request.
+
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+use std::process::Command;
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+#[cfg(target_os = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+#[cfg(target_os = "linux")]
+const DESKTOP_FILE_NAME: &str = "liana-bitcoin-uri.desktop";
+#[cfg(target_os = "linux")]
+const MIME_TYPE: &str = "x-scheme-handler/bitcoin";
+
+#[cfg(target_os = "windows")]
+const REGISTRY_KEY: &str = "HKCU\\Software\\Classes\\bitcoin";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UriHandlerError {
+    ExecutableNotFound(String),
+    Io(String),
+    Command(String),
+    Unsupported,
+}
+
+impl std::fmt::Display for UriHandlerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::ExecutableNotFound(e) => write!(f, "Liana executable not found: {}", e),
+            Self::Io(e) => write!(f, "Failed to write the handler definition: {}", e),
+            Self::Command(e) => write!(f, "Failed to register the handler: {}", e),
+            Self::Unsupported => write!(
+                f,
+                "Opening payment links is not supported on this platform."
+            ),
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+fn run(program: &str, args: &[&str]) -> Result<String, UriHandlerError> {
+    let mut command = Command::new(program);
+    #[cfg(target_os = "windows")]
+    let command = command.creation_flags(CREATE_NO_WINDOW);
+    let output = command
+        .args(args)
+        .output()
+        .map_err(|e| UriHandlerError::Command(format!("{}: {}", program, e)))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(UriHandlerError::Command(format!(
+            "{} exited with status '{}': {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+fn current_exe() -> Result<String, UriHandlerError> {
+    std::env::current_exe()
+        .map(|exe| exe.to_string_lossy().to_string())
+        .map_err(|e| UriHandlerError::ExecutableNotFound(e.to_string()))
+}
+
+#[cfg(target_os = "linux")]
+fn desktop_file_path() -> Option<std::path::PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("applications").join(DESKTOP_FILE_NAME))
+}
+
+#[cfg(target_os = "linux")]
+fn desktop_entry(exe: &str) -> String {
+    format!(
+        "[Desktop Entry]\n\
+         Name=Liana\n\
+         Comment=Open bitcoin: payment links with Liana\n\
+         Exec=\"{}\" %u\n\
+         Type=Application\n\
+         NoDisplay=true\n\
+         MimeType={};\n",
+        exe.replace('\\', "\\\\").replace('"', "\\\""),
+        MIME_TYPE,
+    )
+}
+
+/// Whether this executable is the handler of the `bitcoin:` links.
+#[cfg(target_os = "linux")]
+pub fn is_registered() -> bool {
+    let (Ok(exe), Some(path)) = (current_exe(), desktop_file_path()) else {
+        return false;
+    };
+    let registered = std::fs::read_to_string(path)
+        .map(|content| content == desktop_entry(&exe))
+        .unwrap_or(false);
+    registered
+        && run("xdg-mime", &["query", "default", MIME_TYPE])
+            .map(|default| default.trim() == DESKTOP_FILE_NAME)
+            .unwrap_or(false)
+}
+
+#[cfg(target_os = "linux")]
+pub fn register() -> Result<(), UriHandlerError> {
+    let exe = current_exe()?;
+    let path = desktop_file_path()
+        .ok_or_else(|| UriHandlerError::Io("Unknown data directory".to_string()))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| UriHandlerError::Io(e.to_string()))?;
+    }
+    std::fs::write(&path, desktop_entry(&exe)).map_err(|e| UriHandlerError::Io(e.to_string()))?;
+    run("xdg-mime", &["default", DESKTOP_FILE_NAME, MIME_TYPE])?;
+    tracing::info!("Registered {} as the handler of bitcoin: links", exe);
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+pub fn unregister() -> Result<(), UriHandlerError> {
+    // Without the desktop file, the default association is ignored by xdg-open.
+    if let Some(path) = desktop_file_path() {
+        match std::fs::remove_file(path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(UriHandlerError::Io(e.to_string())),
+        }
+    }
+    tracing::info!("Unregistered the handler of bitcoin: links");
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn command_value(exe: &str) -> String {
+    format!("\"{}\" \"%1\"", exe)
+}
+
+#[cfg(target_os = "windows")]
+pub fn is_registered() -> bool {
+    let Ok(exe) = current_exe() else {
+        return false;
+    };
+    run(
+        "reg",
+        &[
+            "query",
+            &format!("{}\\shell\\open\\command", REGISTRY_KEY),
+            "/ve",
+        ],
+    )
+    .map(|output| output.contains(&command_value(&exe)))
+    .unwrap_or(false)
+}
+
+#[cfg(target_os = "windows")]
+pub fn register() -> Result<(), UriHandlerError> {
+    let exe = current_exe()?;
+    run(
+        "reg",
+        &[
+            "add",
+            REGISTRY_KEY,
+            "/ve",
+            "/d",
+            "URL:Bitcoin payment",
+            "/f",
+        ],
+    )?;
+    run(
+        "reg",
+        &["add", REGISTRY_KEY, "/v", "URL Protocol", "/d", "", "/f"],
+    )?;
+    run(
+        "reg",
+        &[
+            "add",
+            &format!("{}\\shell\\open\\command", REGISTRY_KEY),
+            "/ve",
+            "/d",
+            &command_value(&exe),
+            "/f",
+        ],
+    )?;
+    tracing::info!("Registered {} as the handler of bitcoin: links", exe);
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+pub fn unregister() -> Result<(), UriHandlerError> {
+    run("reg", &["delete", REGISTRY_KEY, "/f"])?;
+    tracing::info!("Unregistered the handler of bitcoin: links");
+    Ok(())
+}
+
+// On MacOS the URL schemes are declared by the application bundle and can't be registered at
+// runtime.
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+pub fn is_registered() -> bool {
+    false
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+pub fn register() -> Result<(), UriHandlerError> {
+    Err(UriHandlerError::Unsupported)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+pub fn unregister() -> Result<(), UriHandlerError> {
+    Err(UriHandlerError::Unsupported)
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn desktop_entry_exec() {
+        let entry = desktop_entry("/opt/liana/liana-gui");
+        assert!(entry.contains("Exec=\"/opt/liana/liana-gui\" %u\n"));
+        assert!(entry.contains("MimeType=x-scheme-handler/bitcoin;\n"));
+    }
+}
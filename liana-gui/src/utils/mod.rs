@@ -3,3 +3,18 @@ pub mod sandbox;
 
 #[cfg(test)]
 pub mod mock;
+
+/// Open a file or a URL in the default application of the system.
+pub fn open(target: &std::ffi::OsStr) -> std::io::Result<()> {
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut cmd = std::process::Command::new("xdg-open");
+    #[cfg(target_os = "macos")]
+    let mut cmd = std::process::Command::new("open");
+    #[cfg(target_os = "windows")]
+    let mut cmd = {
+        let mut cmd = std::process::Command::new("cmd");
+        cmd.args(["/C", "start", ""]);
+        cmd
+    };
+    cmd.arg(target).spawn().map(|_| ())
+}
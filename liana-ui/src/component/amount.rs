@@ -1,8 +1,234 @@
 pub use bitcoin::Amount;
+use bitcoin::Denomination;
 use iced::Color;
 
+use std::{
+    fmt,
+    str::FromStr,
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
+};
+
 use crate::{color, component::text::*, widget::*};
 
+/// The unit in which amounts are displayed and entered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitcoinDisplayUnit {
+    #[default]
+    BTC,
+    MilliBTC,
+    Sats,
+}
+
+impl BitcoinDisplayUnit {
+    pub const ALL: [BitcoinDisplayUnit; 3] = [Self::BTC, Self::MilliBTC, Self::Sats];
+
+    /// The label displayed next to amounts.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::BTC => "BTC",
+            Self::MilliBTC => "mBTC",
+            Self::Sats => "sats",
+        }
+    }
+
+    /// The number of decimals an amount in this unit may have.
+    pub fn decimals(&self) -> usize {
+        match self {
+            Self::BTC => 8,
+            Self::MilliBTC => 5,
+            Self::Sats => 0,
+        }
+    }
+
+    pub fn denomination(&self) -> Denomination {
+        match self {
+            Self::BTC => Denomination::Bitcoin,
+            Self::MilliBTC => Denomination::MilliBitcoin,
+            Self::Sats => Denomination::Satoshi,
+        }
+    }
+
+    fn from_u8(n: u8) -> Self {
+        match n {
+            1 => Self::MilliBTC,
+            2 => Self::Sats,
+            _ => Self::BTC,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::BTC => 0,
+            Self::MilliBTC => 1,
+            Self::Sats => 2,
+        }
+    }
+}
+
+impl fmt::Display for BitcoinDisplayUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+impl FromStr for BitcoinDisplayUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "btc" => Ok(Self::BTC),
+            "mbtc" => Ok(Self::MilliBTC),
+            "sat" | "sats" => Ok(Self::Sats),
+            _ => Err(format!("Unknown bitcoin display unit: '{}'", s)),
+        }
+    }
+}
+
+/// The digit grouping and decimal mark conventions used to display and parse amounts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberFormat {
+    /// Digits are space-separated in groups of three, on both sides of a dot decimal mark.
+    /// Ex: 1 000.00 012 340
+    #[default]
+    Spaced,
+    /// Comma as a thousands separator and a dot decimal mark, as in English locales.
+    /// Ex: 1,000.00012340
+    Comma,
+    /// Dot as a thousands separator and a comma decimal mark, as in most continental European
+    /// locales.
+    /// Ex: 1.000,00012340
+    Period,
+}
+
+impl NumberFormat {
+    pub const ALL: [NumberFormat; 3] = [Self::Spaced, Self::Comma, Self::Period];
+
+    /// Guess the number format from a locale identifier such as `en_US.UTF-8` or `de-DE`.
+    pub fn from_locale(locale: &str) -> Option<Self> {
+        let lang = locale
+            .split(['_', '-', '.', '@'])
+            .next()
+            .filter(|l| !l.is_empty())?
+            .to_lowercase();
+        match lang.as_str() {
+            "en" | "ja" | "ko" | "zh" | "th" | "he" | "hi" => Some(Self::Comma),
+            "de" | "es" | "it" | "nl" | "pt" | "id" | "tr" | "da" | "el" | "ro" | "sl" | "hr"
+            | "sr" | "vi" => Some(Self::Period),
+            "fr" | "sv" | "nb" | "nn" | "no" | "fi" | "pl" | "cs" | "sk" | "ru" | "uk" | "hu"
+            | "bg" | "et" | "lt" | "lv" => Some(Self::Spaced),
+            _ => None,
+        }
+    }
+
+    /// Guess the number format from the locale of the system, if it's known.
+    pub fn from_system_locale() -> Option<Self> {
+        ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|locale| !locale.is_empty())
+            .and_then(|locale| Self::from_locale(&locale))
+    }
+
+    fn group_separator(&self) -> char {
+        match self {
+            Self::Spaced => ' ',
+            Self::Comma => ',',
+            Self::Period => '.',
+        }
+    }
+
+    fn decimal_mark(&self) -> char {
+        match self {
+            Self::Spaced | Self::Comma => '.',
+            Self::Period => ',',
+        }
+    }
+
+    // Whether the fractional part of the number is also split in groups of three digits.
+    fn groups_fraction(&self) -> bool {
+        matches!(self, Self::Spaced)
+    }
+
+    fn from_u8(n: u8) -> Self {
+        match n {
+            1 => Self::Comma,
+            2 => Self::Period,
+            _ => Self::Spaced,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::Spaced => 0,
+            Self::Comma => 1,
+            Self::Period => 2,
+        }
+    }
+}
+
+impl fmt::Display for NumberFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Spaced => write!(f, "spaced"),
+            Self::Comma => write!(f, "comma"),
+            Self::Period => write!(f, "period"),
+        }
+    }
+}
+
+impl FromStr for NumberFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spaced" => Ok(Self::Spaced),
+            "comma" => Ok(Self::Comma),
+            "period" => Ok(Self::Period),
+            _ => Err(format!("Unknown number format: '{}'", s)),
+        }
+    }
+}
+
+// The display preferences are global to the application, so every view formats amounts the
+// same way without having to carry them around.
+static DISPLAY_UNIT: AtomicU8 = AtomicU8::new(0);
+static NUMBER_FORMAT: AtomicU8 = AtomicU8::new(0);
+static AMOUNTS_HIDDEN: AtomicBool = AtomicBool::new(false);
+
+/// What is displayed instead of the amounts while they are hidden.
+pub const HIDDEN_AMOUNT: &str = "*****";
+
+/// Set the unit used to display and parse amounts across the application.
+pub fn set_display_unit(unit: BitcoinDisplayUnit) {
+    DISPLAY_UNIT.store(unit.to_u8(), Ordering::Relaxed);
+}
+
+/// The unit currently used to display and parse amounts.
+pub fn display_unit() -> BitcoinDisplayUnit {
+    BitcoinDisplayUnit::from_u8(DISPLAY_UNIT.load(Ordering::Relaxed))
+}
+
+/// Set the number format used to display and parse amounts across the application.
+pub fn set_number_format(format: NumberFormat) {
+    NUMBER_FORMAT.store(format.to_u8(), Ordering::Relaxed);
+}
+
+/// The number format currently used to display and parse amounts.
+pub fn number_format() -> NumberFormat {
+    NumberFormat::from_u8(NUMBER_FORMAT.load(Ordering::Relaxed))
+}
+
+/// Mask the amounts displayed across the application, for instance while sharing the screen. The
+/// amounts entered by the user are still displayed.
+pub fn set_amounts_hidden(hidden: bool) {
+    AMOUNTS_HIDDEN.store(hidden, Ordering::Relaxed);
+}
+
+/// Whether the amounts are currently masked.
+pub fn amounts_hidden() -> bool {
+    AMOUNTS_HIDDEN.load(Ordering::Relaxed)
+}
+
 /// Amount with default size and colors.
 pub fn amount<'a, T: 'a>(a: &Amount) -> Row<'a, T> {
     amount_with_size(a, P1_SIZE)
@@ -38,30 +264,130 @@ pub fn unconfirmed_amount_with_size<'a, T: 'a>(a: &Amount, size: u16) -> Row<'a,
 // Helpers
 //
 
-// Format a BTC amount as a string for display.
+// Format an amount as a string for display, in the current display unit and number format.
+// Masked if the amounts are hidden.
 pub fn amount_as_string(a: Amount) -> String {
-    let amount = a.to_btc().to_string();
+    if amounts_hidden() {
+        return HIDDEN_AMOUNT.to_string();
+    }
+    format_amount(a, display_unit(), number_format())
+}
+
+/// Format an amount as a string for display in the given unit and number format.
+pub fn format_amount(a: Amount, unit: BitcoinDisplayUnit, format: NumberFormat) -> String {
+    let amount = a.to_string_in(unit.denomination());
 
-    // Reformat the integer portion of the amount with space separation.
+    // Reformat the integer portion of the amount with the separator of the number format.
     let (integer, fraction) = match amount.split_once('.') {
         Some((i, f)) => (i, f),
-        None => (amount.as_str(), "00000000"),
+        None => (amount.as_str(), ""),
+    };
+
+    let integer = format_amount_number_part(integer, format.group_separator());
+    if unit.decimals() == 0 {
+        return integer;
+    }
+    let fraction = format!("{:0<width$}", fraction, width = unit.decimals());
+    let fraction = if format.groups_fraction() {
+        format_amount_number_part(&fraction, format.group_separator())
+    } else {
+        fraction
     };
 
-    let integer = format_amount_number_part(integer);
-    let fraction = format_amount_number_part(&format!("{:0<8}", fraction));
+    format!("{}{}{}", integer, format.decimal_mark(), fraction)
+}
 
-    format!("{integer}.{fraction}")
+// Format an amount as a string suitable to pre-fill an input, in the current display unit and
+// number format. Digits are not grouped.
+pub fn amount_as_input(a: Amount) -> String {
+    a.to_string_in(display_unit().denomination())
+        .replace('.', &number_format().decimal_mark().to_string())
 }
 
-// Format a "part" of a number string with spaces to fit display requirements.
-// Currently using French formatting rules so digits are space-separated in groups
-// of three, starting from the right side. Incidentally, this works for both the
-// integer portion of the number as well as the fraction part.
-// Ex:
+/// An amount entered by the user which can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseAmountError {
+    /// The digit group separators aren't between groups of three digits, or the number could as
+    /// well be read as a decimal one in another number format.
+    InvalidGrouping,
+    /// The amount itself is invalid.
+    Amount(bitcoin::amount::ParseAmountError),
+}
+
+impl fmt::Display for ParseAmountError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidGrouping => write!(f, "Digits aren't grouped by three"),
+            Self::Amount(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Parse an amount entered by the user in the current display unit and number format.
+pub fn parse_amount(s: &str) -> Result<Amount, ParseAmountError> {
+    parse_amount_in(s, display_unit(), number_format())
+}
+
+// Whether these groups of digits are grouped by three as when displayed, starting from the right
+// side. A single group may be of any length.
+fn valid_groups(groups: &[&str], allow_leading_zero: bool) -> bool {
+    match groups {
+        [] | [_] => true,
+        [first, rest @ ..] => {
+            (1..=3).contains(&first.len())
+                && (allow_leading_zero || !first.starts_with('0'))
+                && rest.iter().all(|g| g.len() == 3)
+        }
+    }
+}
+
+/// Parse an amount in the given unit and number format. Digit group separators are only accepted
+/// where the amount would be displayed with one. A number with a single separator and no decimal
+/// mark, such as 1.000 or 1,000, is rejected unless the unit has no decimals: it could as well be
+/// a decimal number written in another format.
+pub fn parse_amount_in(
+    s: &str,
+    unit: BitcoinDisplayUnit,
+    format: NumberFormat,
+) -> Result<Amount, ParseAmountError> {
+    let group_sep = format.group_separator();
+    let (integer, fraction) = match s.trim().split_once(format.decimal_mark()) {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (s.trim(), None),
+    };
+    let integer_groups: Vec<&str> = integer.split(group_sep).collect();
+    let fraction_groups: Vec<&str> = fraction
+        .map(|f| f.split(group_sep).collect())
+        .unwrap_or_default();
+    let ambiguous = fraction.is_none()
+        && integer_groups.len() == 2
+        && unit.decimals() > 0
+        && NumberFormat::ALL
+            .iter()
+            .any(|f| f.decimal_mark() == group_sep);
+    if ambiguous
+        || !valid_groups(&integer_groups, false)
+        || (fraction_groups.len() > 1 && !format.groups_fraction())
+        || !valid_groups(&fraction_groups, true)
+    {
+        return Err(ParseAmountError::InvalidGrouping);
+    }
+
+    let mut normalized = integer_groups.concat();
+    if fraction.is_some() {
+        normalized.push('.');
+        normalized.push_str(&fraction_groups.concat());
+    }
+    Amount::from_str_in(&normalized, unit.denomination()).map_err(ParseAmountError::Amount)
+}
+
+// Format a "part" of a number string with separators to fit display requirements.
+// Digits are separated in groups of three, starting from the right side. Incidentally, this
+// works for both the integer portion of the number as well as the fraction part.
+// Ex (with a space separator):
 //   1000 => 1 000
 //   100000 => 100 000
-fn format_amount_number_part(s: &str) -> String {
+fn format_amount_number_part(s: &str, separator: char) -> String {
     let mut part = s
         .chars()
         .collect::<Vec<_>>()
@@ -70,7 +396,7 @@ fn format_amount_number_part(s: &str) -> String {
         .collect::<Vec<_>>();
     part.reverse();
 
-    part.join(" ")
+    part.join(&separator.to_string())
 }
 
 // Helper functions split a string at the first occurence of a non-zero integer (where
@@ -95,9 +421,13 @@ fn render_amount<'a, T: 'a>(
 ) -> Row<'a, T> {
     let spacing = if size > P1_SIZE { 10 } else { 5 };
 
-    let (before, after) = match split_at_first_non_zero(amount) {
+    let (before, after) = match split_at_first_non_zero(amount.clone()) {
         Some((b, a)) => (b, a),
-        None => (String::from("0.00 000 000"), String::from("")),
+        None if amount == HIDDEN_AMOUNT => (amount, String::new()),
+        None => (
+            format_amount(Amount::ZERO, display_unit(), number_format()),
+            String::from(""),
+        ),
     };
 
     let mut child_after = text(after).size(size).bold();
@@ -110,7 +440,10 @@ fn render_amount<'a, T: 'a>(
 
     Row::with_children(vec![
         row.into(),
-        text("BTC").size(size).color(color_before).into(),
+        text(display_unit().label())
+            .size(size)
+            .color(color_before)
+            .into(),
     ])
     .spacing(spacing)
     .align_y(iced::Alignment::Center)
@@ -122,7 +455,10 @@ fn render_unconfirmed_amount<'a, T: 'a>(amount: String, size: u16) -> Row<'a, T>
 
     Row::with_children(vec![
         text(amount).size(size).color(color::GREY_3).into(),
-        text("BTC").size(size).color(color::GREY_3).into(),
+        text(display_unit().label())
+            .size(size)
+            .color(color::GREY_3)
+            .into(),
     ])
     .spacing(spacing)
     .align_y(iced::Alignment::Center)
@@ -149,6 +485,120 @@ mod tests {
         assert_eq!(
             "0.00 012 340",
             amount_as_string(bitcoin::Amount::from_btc(0.00012340).unwrap())
-        )
+        );
+
+        set_amounts_hidden(true);
+        assert_eq!(
+            HIDDEN_AMOUNT,
+            amount_as_string(bitcoin::Amount::from_btc(0.00012340).unwrap())
+        );
+        set_amounts_hidden(false);
+    }
+
+    #[test]
+    fn test_format_amount() {
+        let a = bitcoin::Amount::from_sat(100_079_980_000);
+        assert_eq!(
+            "1 000.79 980 000",
+            format_amount(a, BitcoinDisplayUnit::BTC, NumberFormat::Spaced)
+        );
+        assert_eq!(
+            "1,000.79980000",
+            format_amount(a, BitcoinDisplayUnit::BTC, NumberFormat::Comma)
+        );
+        assert_eq!(
+            "1.000,79980000",
+            format_amount(a, BitcoinDisplayUnit::BTC, NumberFormat::Period)
+        );
+        assert_eq!(
+            "1 000 799.80 000",
+            format_amount(a, BitcoinDisplayUnit::MilliBTC, NumberFormat::Spaced)
+        );
+        assert_eq!(
+            "100,079,980,000",
+            format_amount(a, BitcoinDisplayUnit::Sats, NumberFormat::Comma)
+        );
+        assert_eq!(
+            "0",
+            format_amount(Amount::ZERO, BitcoinDisplayUnit::Sats, NumberFormat::Spaced)
+        );
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        let a = bitcoin::Amount::from_sat(100_079_980_000);
+        for unit in BitcoinDisplayUnit::ALL {
+            for format in NumberFormat::ALL {
+                assert_eq!(
+                    parse_amount_in(&format_amount(a, unit, format), unit, format).unwrap(),
+                    a
+                );
+            }
+        }
+        assert_eq!(
+            parse_amount_in("0,001", BitcoinDisplayUnit::BTC, NumberFormat::Period).unwrap(),
+            Amount::from_sat(100_000)
+        );
+        assert_eq!(
+            parse_amount_in("1 000", BitcoinDisplayUnit::Sats, NumberFormat::Spaced).unwrap(),
+            Amount::from_sat(1_000)
+        );
+        assert!(parse_amount_in("0.5", BitcoinDisplayUnit::Sats, NumberFormat::Spaced).is_err());
+
+        // Decimal input in each format. The group separators must be where they would be
+        // displayed, and input which reads as a decimal number in another format is rejected.
+        let parse = |s: &str, unit: BitcoinDisplayUnit, format: NumberFormat| {
+            parse_amount_in(s, unit, format).map(|a| a.to_sat())
+        };
+        let (btc, sats) = (BitcoinDisplayUnit::BTC, BitcoinDisplayUnit::Sats);
+        let invalid = Err(ParseAmountError::InvalidGrouping);
+
+        let spaced = NumberFormat::Spaced;
+        assert_eq!(parse("0.001", btc, spaced), Ok(100_000));
+        assert_eq!(parse("0.00 100 000", btc, spaced), Ok(100_000));
+        assert_eq!(parse("1 000.5", btc, spaced), Ok(100_050_000_000));
+        assert_eq!(parse(" 12.5 ", btc, spaced), Ok(1_250_000_000));
+        assert_eq!(parse("10 00.5", btc, spaced), invalid);
+        assert_eq!(parse("0.001 00", btc, spaced), invalid);
+        assert_eq!(parse("0 001", sats, spaced), invalid);
+
+        let comma = NumberFormat::Comma;
+        assert_eq!(parse("0.001", btc, comma), Ok(100_000));
+        assert_eq!(parse("1,000.5", btc, comma), Ok(100_050_000_000));
+        assert_eq!(parse("1,000,000", btc, comma), Ok(100_000_000_000_000));
+        assert_eq!(parse("1,000", sats, comma), Ok(1_000));
+        assert_eq!(parse("1,000", btc, comma), invalid);
+        assert_eq!(parse("0,001", btc, comma), invalid);
+        assert_eq!(parse("0,5", btc, comma), invalid);
+        assert_eq!(parse("0.001,5", btc, comma), invalid);
+        assert!(parse("1 000", sats, comma).is_err());
+
+        let period = NumberFormat::Period;
+        assert_eq!(parse("0,001", btc, period), Ok(100_000));
+        assert_eq!(parse("1.000,5", btc, period), Ok(100_050_000_000));
+        assert_eq!(parse("1.000.000", btc, period), Ok(100_000_000_000_000));
+        assert_eq!(parse("1.000", sats, period), Ok(1_000));
+        assert_eq!(parse("0.001", btc, period), invalid);
+        assert_eq!(parse("1.000", btc, period), invalid);
+        assert_eq!(parse("0.5", btc, period), invalid);
+        assert_eq!(parse("1.5", btc, period), invalid);
+        assert!(parse("0,001,5", btc, period).is_err());
+    }
+
+    #[test]
+    fn test_number_format_from_locale() {
+        assert_eq!(
+            NumberFormat::from_locale("en_US.UTF-8"),
+            Some(NumberFormat::Comma)
+        );
+        assert_eq!(
+            NumberFormat::from_locale("de-DE"),
+            Some(NumberFormat::Period)
+        );
+        assert_eq!(
+            NumberFormat::from_locale("fr_FR"),
+            Some(NumberFormat::Spaced)
+        );
+        assert_eq!(NumberFormat::from_locale("C"), None);
     }
 }
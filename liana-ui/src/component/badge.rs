@@ -53,10 +53,24 @@ pub fn unconfirmed<'a, T: 'a>() -> Container<'a, T> {
     )
 }
 
+pub fn immature<'a, T: 'a>() -> Container<'a, T> {
+    badge_pill(
+        "  Immature  ",
+        "This coin comes from a coinbase transaction and can only be spent after 100 confirmations",
+    )
+}
+
 pub fn batch<'a, T: 'a>() -> Container<'a, T> {
     badge_pill("  Batch  ", "This transaction contains multiple payments")
 }
 
+pub fn collaborative<'a, T: 'a>() -> Container<'a, T> {
+    badge_pill(
+        "  Collaborative  ",
+        "Coins of other participants are spent along with yours, only the change of your balance is shown",
+    )
+}
+
 pub fn deprecated<'a, T: 'a>() -> Container<'a, T> {
     badge_pill(
         "  Deprecated  ",
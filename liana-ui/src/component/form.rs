@@ -1,8 +1,12 @@
 use crate::widget::text_input;
-use bitcoin::Denomination;
 use iced::Length;
 
-use crate::{color, component::text, theme, widget::*};
+use crate::{
+    color,
+    component::{amount, text},
+    theme,
+    widget::*,
+};
 
 #[derive(Debug, Clone)]
 pub struct Value<T> {
@@ -64,19 +68,19 @@ where
         }
     }
 
-    /// Creates a new [`Form`] that restrict input values to valid btc amount before applying the
-    /// `on_change` function.
+    /// Creates a new [`Form`] that restrict input values to valid amounts in the current display
+    /// unit before applying the `on_change` function.
     /// It expects:
     /// - a placeholder
     /// - the current value
     /// - a function that produces a message when the [`Form`] changes
-    pub fn new_amount_btc<F>(placeholder: &str, value: &'a Value<String>, on_change: F) -> Self
+    pub fn new_amount<F>(placeholder: &str, value: &'a Value<String>, on_change: F) -> Self
     where
         F: 'static + Fn(String) -> Message,
     {
         Self {
             input: text_input::TextInput::new(placeholder, &value.value).on_input(move |s| {
-                if bitcoin::Amount::from_str_in(&s, Denomination::Bitcoin).is_ok() || s.is_empty() {
+                if amount::parse_amount(&s).is_ok() || s.is_empty() {
                     on_change(s)
                 } else {
                     on_change(value.value.clone())
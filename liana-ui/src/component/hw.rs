@@ -360,6 +360,59 @@ pub fn unsupported_hardware_wallet<'a, T: 'static, K: Display, V: Display>(
     .padding(10)
 }
 
+/// A device whose fingerprint is only known once it was unlocked for a command, such as a
+/// NFC card.
+pub fn unidentified_hardware_wallet<'a, T: 'a, K: Display, V: Display>(
+    kind: K,
+    version: Option<V>,
+    message: &'a str,
+) -> Container<'a, T> {
+    Container::new(
+        column(vec![
+            Row::new()
+                .spacing(5)
+                .push(text::p1_bold(kind.to_string()))
+                .into(),
+            Row::new()
+                .spacing(5)
+                .push(text::caption(message))
+                .push_maybe(version.map(|v| text::caption(v.to_string())))
+                .into(),
+        ])
+        .width(Length::Fill),
+    )
+    .padding(10)
+}
+
+pub fn incompatible_hardware_wallet<'a, T: 'static, K: Display, V: Display>(
+    kind: K,
+    version: Option<V>,
+    reason: &'static str,
+) -> Container<'a, T> {
+    container(
+        row(vec![
+            column(vec![
+                Row::new()
+                    .spacing(5)
+                    .push(text::p1_bold("Incompatible with the wallet"))
+                    .into(),
+                Row::new()
+                    .spacing(5)
+                    .push(text::caption(kind.to_string()))
+                    .push_maybe(version.map(|v| text::caption(v.to_string())))
+                    .into(),
+            ])
+            .width(Length::Fill)
+            .into(),
+            tooltip::Tooltip::new(icon::warning_icon(), reason, tooltip::Position::Bottom)
+                .style(theme::card::simple)
+                .into(),
+        ])
+        .align_y(Alignment::Center),
+    )
+    .padding(10)
+}
+
 pub fn unsupported_version_hardware_wallet<'a, T: 'static, K: Display, V: Display, S: Display>(
     kind: K,
     version: Option<V>,
@@ -50,3 +50,16 @@ pub fn network_banner<'a, T: 'a>(network: Network) -> Container<'a, T> {
     .center_x(Length::Fill)
     .style(theme::banner::network)
 }
+
+/// A badge with the network of the wallet, including mainnet, in the accent color of the wallet.
+pub fn network_badge<'a, T: 'a>(network: Network, color: iced::Color) -> Container<'a, T> {
+    Container::new(
+        text::text(match network {
+            Network::Bitcoin => "MAINNET".to_string(),
+            n => n.to_string().to_uppercase(),
+        })
+        .bold(),
+    )
+    .padding([2, 10])
+    .style(theme::pill::custom(color))
+}
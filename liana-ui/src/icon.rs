@@ -99,6 +99,14 @@ pub fn collapsed_icon() -> Text<'static> {
     bootstrap_icon('\u{F282}')
 }
 
+pub fn eye_icon() -> Text<'static> {
+    bootstrap_icon('\u{F341}')
+}
+
+pub fn eye_slash_icon() -> Text<'static> {
+    bootstrap_icon('\u{F340}')
+}
+
 pub fn down_icon() -> Text<'static> {
     bootstrap_icon('\u{F279}')
 }
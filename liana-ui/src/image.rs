@@ -10,6 +10,11 @@ pub fn liana_app_icon() -> icon::Icon {
     icon::from_file_data(LIANA_APP_ICON, None).unwrap()
 }
 
+/// The PNG encoded application icon.
+pub fn liana_app_icon_data() -> &'static [u8] {
+    LIANA_APP_ICON
+}
+
 pub fn liana_grey_logo() -> Svg<'static> {
     let h = Handle::from_memory(LIANA_LOGO_GREY.to_vec());
     Svg::new(h)
@@ -117,6 +117,8 @@ pub struct TextInputs {
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct TextInput {
     pub active: TextInputPalette,
+    /// When it has the keyboard focus.
+    pub focused: TextInputPalette,
     pub disabled: TextInputPalette,
 }
 
@@ -453,6 +455,14 @@ impl std::default::Default for Palette {
                         selection: color::GREEN,
                         border: Some(color::GREY_7),
                     },
+                    focused: TextInputPalette {
+                        background: color::TRANSPARENT,
+                        icon: color::TRANSPARENT,
+                        placeholder: color::GREY_7,
+                        value: color::GREY_2,
+                        selection: color::GREEN,
+                        border: Some(color::GREEN),
+                    },
                     disabled: TextInputPalette {
                         background: color::TRANSPARENT,
                         icon: color::TRANSPARENT,
@@ -471,6 +481,14 @@ impl std::default::Default for Palette {
                         selection: color::GREEN,
                         border: Some(color::RED),
                     },
+                    focused: TextInputPalette {
+                        background: color::TRANSPARENT,
+                        icon: color::TRANSPARENT,
+                        placeholder: color::GREY_7,
+                        value: color::GREY_2,
+                        selection: color::GREEN,
+                        border: Some(color::RED),
+                    },
                     disabled: TextInputPalette {
                         background: color::TRANSPARENT,
                         icon: color::TRANSPARENT,
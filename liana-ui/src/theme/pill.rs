@@ -32,3 +32,17 @@ pub fn success(theme: &Theme) -> Style {
 pub fn warning(theme: &Theme) -> Style {
     pill(&theme.colors.pills.warning)
 }
+
+/// A pill filled with the given color.
+pub fn custom(color: iced::Color) -> Box<dyn Fn(&Theme) -> Style> {
+    Box::new(move |_theme: &Theme| Style {
+        background: Some(Background::Color(color)),
+        text_color: Some(crate::color::LIGHT_BLACK),
+        border: Border {
+            radius: 25.0.into(),
+            width: 1.0,
+            color,
+        },
+        ..Default::default()
+    })
+}
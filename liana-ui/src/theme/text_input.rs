@@ -3,7 +3,10 @@ use iced::{
     Background, Border,
 };
 
-use super::{palette::TextInput, Theme};
+use super::{
+    palette::{TextInput, TextInputPalette},
+    Theme,
+};
 
 impl Catalog for Theme {
     type Class<'a> = StyleFn<'a, Self>;
@@ -26,40 +29,29 @@ pub fn invalid(theme: &Theme, status: Status) -> Style {
 }
 
 fn text_input(c: &TextInput, status: Status) -> Style {
-    let active = Style {
-        background: Background::Color(c.active.background),
-        border: if let Some(color) = c.active.border {
+    match status {
+        Status::Active | Status::Hovered => style(&c.active, 1.0),
+        // A thicker border shows which input has the keyboard focus.
+        Status::Focused => style(&c.focused, 2.0),
+        Status::Disabled => style(&c.disabled, 1.0),
+    }
+}
+
+fn style(p: &TextInputPalette, border_width: f32) -> Style {
+    Style {
+        background: Background::Color(p.background),
+        border: if let Some(color) = p.border {
             Border {
                 radius: 25.0.into(),
-                width: 1.0,
+                width: border_width,
                 color,
             }
         } else {
             Border::default()
         },
-        icon: c.active.icon,
-        placeholder: c.active.placeholder,
-        value: c.active.value,
-        selection: c.active.selection,
-    };
-
-    match status {
-        Status::Active | Status::Hovered | Status::Focused { .. } => active,
-        Status::Disabled => Style {
-            background: Background::Color(c.disabled.background),
-            border: if let Some(color) = c.disabled.border {
-                Border {
-                    radius: 25.0.into(),
-                    width: 1.0,
-                    color,
-                }
-            } else {
-                Border::default()
-            },
-            icon: c.disabled.icon,
-            placeholder: c.disabled.placeholder,
-            value: c.disabled.value,
-            selection: c.disabled.selection,
-        },
+        icon: p.icon,
+        placeholder: p.placeholder,
+        value: p.value,
+        selection: p.selection,
     }
 }
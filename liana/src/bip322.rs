@@ -0,0 +1,322 @@
+//! Generic signed messages (BIP322) for the addresses of the wallet.
+//!
+//! A message is signed for an address by spending a virtual output paying to it, whose creating
+//! transaction commits to the message. The virtual spending transaction is handed to the signers
+//! as a PSBT like any other, and the signature is the witness of its input once finalized (the
+//! "simple" format of BIP322). As the spend has neither a locktime nor a relative timelock, it may
+//! only be signed through the primary path of the wallet.
+
+use crate::descriptors::DerivedSinglePathLianaDesc;
+
+use std::fmt;
+
+use miniscript::{
+    bitcoin::{
+        self,
+        absolute::LockTime,
+        base64::{self, Engine},
+        blockdata::{opcodes, script},
+        consensus,
+        hashes::{sha256, Hash, HashEngine},
+        psbt::Psbt,
+        secp256k1, sighash,
+        transaction::Version,
+        OutPoint, Script, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness,
+    },
+    psbt::PsbtExt,
+    Interpreter,
+};
+
+const TAG: &[u8] = b"BIP0322-signed-message";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bip322Error {
+    /// The PSBT isn't the one to sign this message for this address.
+    UnexpectedPsbt,
+    /// The PSBT couldn't be finalized, for instance because signatures are missing.
+    Finalization(String),
+    /// The signature isn't a base64-encoded witness.
+    InvalidEncoding(String),
+    /// The signature doesn't sign this message for this address.
+    InvalidSignature(String),
+}
+
+impl fmt::Display for Bip322Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnexpectedPsbt => write!(
+                f,
+                "The PSBT is not the one to sign this message for this address."
+            ),
+            Self::Finalization(e) => write!(f, "Failed to finalize the message PSBT: {}", e),
+            Self::InvalidEncoding(e) => write!(f, "Invalid signature encoding: {}", e),
+            Self::InvalidSignature(e) => write!(f, "Invalid signature: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Bip322Error {}
+
+/// The tagged hash of the message committed to by the virtual output.
+pub fn message_hash(message: &[u8]) -> sha256::Hash {
+    let tag = sha256::Hash::hash(TAG);
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_ref());
+    engine.input(tag.as_ref());
+    engine.input(message);
+    sha256::Hash::from_engine(engine)
+}
+
+/// The virtual transaction creating the output paying to this script which commits to the message.
+pub fn to_spend(script_pubkey: &Script, message: &[u8]) -> Transaction {
+    Transaction {
+        version: Version(0),
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::null(),
+            script_sig: script::Builder::new()
+                .push_opcode(opcodes::OP_0)
+                .push_slice(message_hash(message).to_byte_array())
+                .into_script(),
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: bitcoin::Amount::ZERO,
+            script_pubkey: script_pubkey.to_owned(),
+        }],
+    }
+}
+
+/// The virtual transaction spending the output of [`to_spend`], whose witness is the signature.
+pub fn to_sign(to_spend: &Transaction, witness: Witness) -> Transaction {
+    Transaction {
+        version: Version(0),
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(to_spend.compute_txid(), 0),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ZERO,
+            witness,
+        }],
+        output: vec![TxOut {
+            value: bitcoin::Amount::ZERO,
+            script_pubkey: script::Builder::new()
+                .push_opcode(opcodes::all::OP_RETURN)
+                .into_script(),
+        }],
+    }
+}
+
+/// The PSBT for the signers of the wallet to sign this message for the address of this derived
+/// descriptor.
+pub fn message_psbt(desc: &DerivedSinglePathLianaDesc, message: &[u8]) -> Psbt {
+    let to_spend = to_spend(&desc.script_pubkey(), message);
+    let mut psbt = Psbt::from_unsigned_tx(to_sign(&to_spend, Witness::new()))
+        .expect("The transaction has no script sig nor witness.");
+    psbt.inputs[0].witness_utxo = Some(to_spend.output[0].clone());
+    // Some signing devices want the whole previous transaction to sign for P2WSH inputs.
+    psbt.inputs[0].non_witness_utxo = Some(to_spend);
+    desc.update_psbt_in(&mut psbt.inputs[0]);
+    psbt
+}
+
+/// Finalize a signed message PSBT and get the signature from it. Fails if the PSBT isn't the one
+/// to sign this message for this address.
+pub fn finalize(
+    mut psbt: Psbt,
+    script_pubkey: &Script,
+    message: &[u8],
+    secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+) -> Result<Witness, Bip322Error> {
+    let to_spend = to_spend(script_pubkey, message);
+    if psbt.unsigned_tx != to_sign(&to_spend, Witness::new()) || psbt.inputs.len() != 1 {
+        return Err(Bip322Error::UnexpectedPsbt);
+    }
+    // Don't trust the previous output given in the PSBT, we know what it is.
+    psbt.inputs[0].witness_utxo = Some(to_spend.output[0].clone());
+    psbt.finalize_mut(secp).map_err(|errors| {
+        Bip322Error::Finalization(
+            errors
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        )
+    })?;
+    psbt.inputs[0]
+        .final_script_witness
+        .take()
+        .ok_or_else(|| Bip322Error::Finalization("No witness after finalization.".to_string()))
+}
+
+/// Check this signature signs the message for the address with this script.
+pub fn verify(
+    script_pubkey: &Script,
+    message: &[u8],
+    signature: &Witness,
+    secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+) -> Result<(), Bip322Error> {
+    let to_spend = to_spend(script_pubkey, message);
+    let to_sign = to_sign(&to_spend, signature.clone());
+    let interpreter = Interpreter::from_txdata(
+        &to_spend.output[0].script_pubkey,
+        &to_sign.input[0].script_sig,
+        &to_sign.input[0].witness,
+        to_sign.input[0].sequence,
+        to_sign.lock_time,
+    )
+    .map_err(|e| Bip322Error::InvalidSignature(e.to_string()))?;
+    let prevouts = [to_spend.output[0].clone()];
+    let prevouts = sighash::Prevouts::All(&prevouts);
+    for constraint in interpreter.iter(secp, &to_sign, 0, &prevouts) {
+        constraint.map_err(|e| Bip322Error::InvalidSignature(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// The base64 encoding of the signature.
+pub fn encode_signature(signature: &Witness) -> String {
+    base64::engine::general_purpose::STANDARD.encode(consensus::serialize(signature))
+}
+
+pub fn decode_signature(s: &str) -> Result<Witness, Bip322Error> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(s.trim())
+        .map_err(|e| Bip322Error::InvalidEncoding(e.to_string()))?;
+    consensus::deserialize(&bytes).map_err(|e| Bip322Error::InvalidEncoding(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use miniscript::bitcoin::Address;
+    use std::str::FromStr;
+
+    // From the test vectors of BIP322.
+    #[test]
+    fn bip322_vectors() {
+        let secp = secp256k1::Secp256k1::verification_only();
+        assert_eq!(
+            message_hash(b"").to_string(),
+            "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1"
+        );
+        assert_eq!(
+            message_hash(b"Hello World").to_string(),
+            "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a"
+        );
+
+        let spk = Address::from_str("bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l")
+            .unwrap()
+            .assume_checked()
+            .script_pubkey();
+        let spend = to_spend(&spk, b"");
+        assert_eq!(
+            spend.compute_txid().to_string(),
+            "c5680aa69bb8d860bf82d4e9cd3504b55dde018de765a91bb566283c545a99a7"
+        );
+        assert_eq!(
+            to_sign(&spend, Witness::new()).compute_txid().to_string(),
+            "1e9654e951a5ba44c8604c4de6c67fd78a27e81dcadcfe1edf638ba3aaebaed6"
+        );
+
+        let sig = decode_signature("AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=").unwrap();
+        verify(&spk, b"Hello World", &sig, &secp).unwrap();
+        assert!(matches!(
+            verify(&spk, b"Hello World!", &sig, &secp),
+            Err(Bip322Error::InvalidSignature(_))
+        ));
+        assert!(matches!(
+            decode_signature("not base64"),
+            Err(Bip322Error::InvalidEncoding(_))
+        ));
+    }
+
+    #[cfg(feature = "signer")]
+    fn signer_key(
+        signer: &crate::signer::HotSigner,
+        secp: &secp256k1::Secp256k1<secp256k1::All>,
+    ) -> miniscript::DescriptorPublicKey {
+        use miniscript::{
+            bitcoin::bip32,
+            descriptor::{DerivPaths, DescriptorMultiXKey, DescriptorPublicKey, Wildcard},
+        };
+
+        let origin_der = bip32::DerivationPath::from_str("m/48'/0'/0'/2'").unwrap();
+        DescriptorPublicKey::MultiXPub(DescriptorMultiXKey {
+            origin: Some((signer.fingerprint(secp), origin_der.clone())),
+            xkey: signer.xpub_at(&origin_der, secp),
+            derivation_paths: DerivPaths::new(vec![
+                bip32::DerivationPath::from_str("m/0").unwrap(),
+                bip32::DerivationPath::from_str("m/1").unwrap(),
+            ])
+            .unwrap(),
+            wildcard: Wildcard::Unhardened,
+        })
+    }
+
+    #[cfg(feature = "signer")]
+    #[test]
+    fn sign_and_verify() {
+        use crate::{
+            descriptors::{LianaDescriptor, LianaPolicy, PathInfo},
+            signer::HotSigner,
+        };
+
+        let secp = secp256k1::Secp256k1::new();
+        let network = bitcoin::Network::Bitcoin;
+        let (prim_signer, recov_signer) = (
+            HotSigner::generate(network).unwrap(),
+            HotSigner::generate(network).unwrap(),
+        );
+        for is_taproot in [false, true] {
+            let prim_keys = PathInfo::Single(signer_key(&prim_signer, &secp));
+            let recov_keys = PathInfo::Single(signer_key(&recov_signer, &secp));
+            let recovery_paths = [(52560, recov_keys)].iter().cloned().collect();
+            let policy = if is_taproot {
+                LianaPolicy::new(prim_keys, recovery_paths).unwrap()
+            } else {
+                LianaPolicy::new_legacy(prim_keys, recovery_paths).unwrap()
+            };
+            let desc = LianaDescriptor::new(policy);
+            let derived = desc.receive_descriptor().derive(7.into(), &secp);
+            let spk = derived.script_pubkey();
+            let message = b"Liana receive addresses";
+
+            // It can't be finalized without the signature of the primary path. The recovery
+            // path isn't available as the spend has no timelock.
+            let psbt = message_psbt(&derived, message);
+            assert!(matches!(
+                finalize(psbt.clone(), &spk, message, &secp),
+                Err(Bip322Error::Finalization(_))
+            ));
+            let recov_signed = recov_signer.sign_psbt(psbt.clone(), &secp).unwrap();
+            assert!(matches!(
+                finalize(recov_signed, &spk, message, &secp),
+                Err(Bip322Error::Finalization(_))
+            ));
+
+            // The PSBT for another message or address is refused.
+            let signed = prim_signer.sign_psbt(psbt, &secp).unwrap();
+            assert_eq!(
+                finalize(signed.clone(), &spk, b"Another message", &secp),
+                Err(Bip322Error::UnexpectedPsbt)
+            );
+            let other_spk = desc
+                .receive_descriptor()
+                .derive(8.into(), &secp)
+                .script_pubkey();
+            assert_eq!(
+                finalize(signed.clone(), &other_spk, message, &secp),
+                Err(Bip322Error::UnexpectedPsbt)
+            );
+
+            let sig = finalize(signed, &spk, message, &secp).unwrap();
+            let sig = decode_signature(&encode_signature(&sig)).unwrap();
+            verify(&spk, message, &sig, &secp).unwrap();
+            assert!(verify(&spk, b"Another message", &sig, &secp).is_err());
+            assert!(verify(&other_spk, message, &sig, &secp).is_err());
+        }
+    }
+}
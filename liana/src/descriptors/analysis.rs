@@ -17,6 +17,8 @@ use std::{
     sync,
 };
 
+use serde::{Deserialize, Serialize};
+
 #[derive(Debug)]
 pub enum LianaPolicyError {
     MissingRecoveryPath,
@@ -32,6 +34,8 @@ pub enum LianaPolicyError {
     /// The spending policy is not a valid Miniscript policy: it may for instance be malleable, or
     /// overflow some limit.
     InvalidPolicy(miniscript::Error),
+    /// The descriptor is too deeply nested or too large to be analyzed within the limits.
+    TooComplex,
 }
 
 impl std::fmt::Display for LianaPolicyError {
@@ -62,12 +66,114 @@ impl std::fmt::Display for LianaPolicyError {
             ),
             Self::InvalidPolicy(e) => write!(f, "Invalid Miniscript policy: {}", e),
             Self::PolicyAnalysis(e) => write!(f, "Analyzing the policy of the miniscript: {}", e),
+            Self::TooComplex => write!(
+                f,
+                "The descriptor is too deeply nested or contains too many fragments to be analyzed."
+            ),
         }
     }
 }
 
 impl error::Error for LianaPolicyError {}
 
+/// Bounds on the size of a descriptor we accept to analyze. A Liana descriptor is shallow and
+/// small, these only guard against maliciously crafted ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnalysisLimits {
+    /// The maximum nesting depth of the Miniscript fragments, including the depth of the leaves in
+    /// a Taproot tree.
+    pub max_depth: usize,
+    /// The maximum number of Miniscript fragments.
+    pub max_nodes: usize,
+}
+
+impl Default for AnalysisLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 128,
+            max_nodes: 10_000,
+        }
+    }
+}
+
+impl AnalysisLimits {
+    /// Check the nesting of a descriptor string before parsing it. Each parenthesis, brace and
+    /// wrapper (as in `v:`) counts as a level of nesting.
+    pub fn check_str(&self, s: &str) -> Result<(), LianaPolicyError> {
+        // The depth added by each currently open parenthesis or brace, including the wrappers of
+        // its fragment.
+        let mut levels: Vec<usize> = Vec::new();
+        let (mut depth, mut wrappers, mut name_len) = (0, 0, 0);
+        for c in s.chars() {
+            match c {
+                '(' | '{' => {
+                    levels.push(wrappers + 1);
+                    depth += wrappers + 1;
+                    (wrappers, name_len) = (0, 0);
+                }
+                ')' | '}' => {
+                    depth -= levels.pop().unwrap_or(0);
+                    (wrappers, name_len) = (0, 0);
+                }
+                ',' => (wrappers, name_len) = (0, 0),
+                ':' => {
+                    wrappers += name_len;
+                    name_len = 0;
+                }
+                _ => name_len += 1,
+            }
+            if depth + wrappers > self.max_depth {
+                return Err(LianaPolicyError::TooComplex);
+            }
+        }
+        Ok(())
+    }
+
+    // Walk the Miniscript with an explicit work queue, erroring as soon as a limit is exceeded.
+    // `depth` is the depth of the root of this Miniscript and `nodes` the number of fragments
+    // already visited.
+    fn check_ms<Ctx: ScriptContext>(
+        &self,
+        ms: &miniscript::Miniscript<descriptor::DescriptorPublicKey, Ctx>,
+        depth: usize,
+        nodes: &mut usize,
+    ) -> Result<(), LianaPolicyError> {
+        let mut queue = vec![(ms, depth)];
+        while let Some((node, depth)) = queue.pop() {
+            *nodes += 1;
+            if depth > self.max_depth || *nodes > self.max_nodes {
+                return Err(LianaPolicyError::TooComplex);
+            }
+            queue.extend(node.branches().into_iter().map(|sub| (sub, depth + 1)));
+        }
+        Ok(())
+    }
+
+    /// Check the size of this descriptor's Miniscript(s).
+    pub fn check_desc(
+        &self,
+        desc: &descriptor::Descriptor<descriptor::DescriptorPublicKey>,
+    ) -> Result<(), LianaPolicyError> {
+        let mut nodes = 0;
+        match desc {
+            descriptor::Descriptor::Wsh(wsh_desc) => {
+                if let descriptor::WshInner::Ms(ms) = wsh_desc.as_inner() {
+                    self.check_ms(ms, 0, &mut nodes)?;
+                }
+            }
+            descriptor::Descriptor::Tr(desc) => {
+                if let Some(tree) = desc.tap_tree() {
+                    for (leaf_depth, ms) in tree.iter() {
+                        self.check_ms(ms, leaf_depth.into(), &mut nodes)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
 // Whether a Miniscript policy node represents a key check (or several of them).
 fn is_single_key_or_multisig(policy: &SemanticPolicy<descriptor::DescriptorPublicKey>) -> bool {
     match policy {
@@ -249,7 +355,7 @@ impl PathInfo {
                     _ => return Err(LianaPolicyError::IncompatibleDesc),
                 }
             }
-            assert!(keys.len() > 1); // At least 3 subs, only one of which may be older().
+            debug_assert!(keys.len() > 1); // At least 3 subs, only one of which may be older().
             Ok((
                 tl_value.ok_or(LianaPolicyError::IncompatibleDesc)?,
                 PathInfo::Multi(k - 1, keys),
@@ -261,6 +367,20 @@ impl PathInfo {
         }
     }
 
+    /// Create a spending path requiring `threshold` signatures among these keys. A single key
+    /// must come with a threshold of 1.
+    pub fn from_keys(
+        threshold: usize,
+        mut keys: Vec<descriptor::DescriptorPublicKey>,
+    ) -> Result<PathInfo, LianaPolicyError> {
+        match keys.len() {
+            0 => Err(LianaPolicyError::InvalidMultiKeys(0)),
+            1 if threshold != 1 => Err(LianaPolicyError::InvalidMultiThresh(threshold)),
+            1 => Ok(PathInfo::Single(keys.remove(0))),
+            _ => Ok(PathInfo::Multi(threshold, keys)),
+        }
+    }
+
     /// Add another available key to this `PathInfo`. Note this doesn't change the threshold.
     pub fn with_added_key(mut self, key: descriptor::DescriptorPublicKey) -> Self {
         match self {
@@ -277,6 +397,8 @@ impl PathInfo {
     /// mapping from a master extended key fingerprint, to a set of derivation paths. This is
     /// because we are using multipath descriptors. The derivation paths included the xpub's
     /// derivation path appended to the origin's derivation path (without the wildcard step).
+    /// Keys which aren't multipath xpubs with an origin can't be identified this way and are
+    /// left out of the mapping.
     pub fn thresh_origins(
         &self,
     ) -> (
@@ -286,15 +408,14 @@ impl PathInfo {
         match self {
             PathInfo::Single(key) => {
                 let mut all_origins = HashMap::with_capacity(1);
-                let (fg, der_path) = key_origins(key).expect("Must be a multixpub with an origin.");
-                all_origins.insert(fg, der_path);
+                if let Some((fg, der_paths)) = key_origins(key) {
+                    all_origins.insert(fg, der_paths);
+                }
                 (1, all_origins)
             }
             PathInfo::Multi(k, keys) => {
                 let mut all_origins: HashMap<_, HashSet<_>> = HashMap::with_capacity(keys.len());
-                for key in keys {
-                    let (fg, der_paths) =
-                        key_origins(key).expect("Must be a multixpub with an origin.");
+                for (fg, der_paths) in keys.iter().filter_map(key_origins) {
                     if let Some(existing_der_paths) = all_origins.get_mut(&fg) {
                         existing_der_paths.extend(der_paths)
                     } else {
@@ -573,6 +694,18 @@ impl LianaPolicy {
     pub fn from_multipath_descriptor(
         desc: &descriptor::Descriptor<descriptor::DescriptorPublicKey>,
     ) -> Result<LianaPolicy, LianaPolicyError> {
+        Self::from_multipath_descriptor_with_limits(desc, &AnalysisLimits::default())
+    }
+
+    /// Same as [`LianaPolicy::from_multipath_descriptor`], but with custom bounds on the size of
+    /// the descriptor to analyze. Returns [`LianaPolicyError::TooComplex`] if it exceeds them.
+    pub fn from_multipath_descriptor_with_limits(
+        desc: &descriptor::Descriptor<descriptor::DescriptorPublicKey>,
+        limits: &AnalysisLimits,
+    ) -> Result<LianaPolicy, LianaPolicyError> {
+        // Lifting and normalizing the policy is recursive, bound the descriptor first.
+        limits.check_desc(desc)?;
+
         // Lift a semantic policy out of this Miniscript and normalize it to make sure we compare
         // apples to apples below.
         let policy = match desc {
@@ -679,7 +812,7 @@ impl LianaPolicy {
     /// Timelocks and path info of the recovery paths. Note we guarantee this mapping is never
     /// empty, as there is always at least one recovery path.
     pub fn recovery_paths(&self) -> &BTreeMap<u16, PathInfo> {
-        assert!(!self.recovery_paths.is_empty());
+        debug_assert!(!self.recovery_paths.is_empty());
         &self.recovery_paths
     }
 
@@ -697,7 +830,7 @@ impl LianaPolicy {
         let primary_keys = primary_path.into_ms_policy()?;
 
         // Incrementally create the top-level policy using all recovery paths.
-        assert!(!recovery_paths.is_empty());
+        debug_assert!(!recovery_paths.is_empty());
         recovery_paths
             .into_iter()
             .try_fold(primary_keys, |tl_policy, (timelock, path_info)| {
@@ -797,6 +930,15 @@ pub struct PathSpendInfo {
     pub signed_pubkeys: HashMap<bip32::Fingerprint, usize>,
 }
 
+/// One of the spending paths of a Liana descriptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpendingPath {
+    Primary,
+    /// A recovery path, identified by its relative timelock in blocks.
+    Recovery(u16),
+}
+
 /// Information about a partial spend of Liana coins
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct PartialSpendInfo {
@@ -820,6 +962,34 @@ impl PartialSpendInfo {
     }
 }
 
+/// The signatures still missing to satisfy a spending path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MissingSignatures {
+    pub path: SpendingPath,
+    /// The number of signatures needed on top of those present.
+    pub count: usize,
+    /// The signers of this path who didn't provide a signature yet.
+    pub signers: Vec<bip32::Fingerprint>,
+}
+
+/// Whether a witness can be constructed for a PSBT input with the signatures it contains.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputFinalization {
+    /// The spending path the input can be finalized through, if it can be finalized.
+    pub path: Option<SpendingPath>,
+    /// The signatures missing for each of the other spending paths available to this input.
+    pub missing: Vec<MissingSignatures>,
+    /// Why the witness could not be constructed, if it could not.
+    pub error: Option<String>,
+}
+
+impl InputFinalization {
+    /// Whether a witness can be constructed for this input.
+    pub fn is_finalizable(&self) -> bool {
+        self.path.is_some()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
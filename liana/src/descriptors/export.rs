@@ -0,0 +1,151 @@
+//! Formats to export the descriptor to other wallets and signing devices.
+//!
+//! Besides its string representation, the descriptor can be exported as a BIP388 wallet policy
+//! (a descriptor template along with the list of its keys, as registered on signing devices) and
+//! as a Uniform Resource (BCR-2020-005) to be displayed as a QR code.
+
+use miniscript::{
+    descriptor::{self, DescriptorPublicKey},
+    translate_hash_fail, TranslatePk, Translator,
+};
+
+use std::fmt;
+
+use super::LianaDescriptor;
+use crate::ur::{self, cbor_header};
+
+/// The type of the Uniform Resource the descriptor is exported as.
+///
+/// The `crypto-output` type (BCR-2020-010) can only express a fixed set of script expressions,
+/// which doesn't include Miniscript. Its successor `output-descriptor` (BCR-2023-010) carries the
+/// descriptor in its textual form, so it is used instead.
+pub const UR_TYPE_OUTPUT_DESCRIPTOR: &str = "output-descriptor";
+
+/// A BIP388 wallet policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalletPolicy {
+    /// The descriptor template, with the keys replaced by their index in `keys`.
+    pub template: String,
+    /// The keys information, with their origin.
+    pub keys: Vec<String>,
+}
+
+impl fmt::Display for WalletPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.template)?;
+        for (i, key) in self.keys.iter().enumerate() {
+            write!(f, "\n@{}: {}", i, key)?;
+        }
+        Ok(())
+    }
+}
+
+// Replace each key by its placeholder in the template, recording the keys information.
+struct PolicyTranslator {
+    keys: Vec<String>,
+}
+
+impl Translator<DescriptorPublicKey, String, ()> for PolicyTranslator {
+    fn pk(&mut self, pk: &DescriptorPublicKey) -> Result<String, ()> {
+        let xpub = match pk {
+            DescriptorPublicKey::MultiXPub(xpub) => xpub,
+            _ => return Err(()),
+        };
+        // A wallet policy may not have more than one derivation step after the xpub.
+        let mut steps = Vec::with_capacity(2);
+        for path in xpub.derivation_paths.paths() {
+            match path.as_ref() {
+                [step] => steps.push(u32::from(*step)),
+                _ => return Err(()),
+            }
+        }
+        let key_info = match &xpub.origin {
+            Some((fg, path)) if path.is_empty() => format!("[{}]{}", fg, xpub.xkey),
+            Some((fg, path)) => format!("[{}/{}]{}", fg, path, xpub.xkey),
+            None => xpub.xkey.to_string(),
+        };
+        // The same key may be used with different derivation steps.
+        let index = match self.keys.iter().position(|k| *k == key_info) {
+            Some(i) => i,
+            None => {
+                self.keys.push(key_info);
+                self.keys.len() - 1
+            }
+        };
+        Ok(match steps[..] {
+            [0, 1] => format!("@{}/**", index),
+            [a, b] => format!("@{}/<{};{}>/*", index, a, b),
+            _ => return Err(()),
+        })
+    }
+
+    translate_hash_fail!(DescriptorPublicKey, String, ());
+}
+
+impl LianaDescriptor {
+    /// The BIP388 wallet policy of this descriptor. None if a key can't be expressed in a wallet
+    /// policy, for instance if it has more than one derivation step after the xpub.
+    pub fn wallet_policy(&self) -> Option<WalletPolicy> {
+        let mut translator = PolicyTranslator { keys: Vec::new() };
+        let desc: descriptor::Descriptor<String> =
+            self.multi_desc.translate_pk(&mut translator).ok()?;
+        // The checksum of the template wouldn't be the one of any valid descriptor.
+        let template = desc.to_string();
+        let template = template
+            .split_once('#')
+            .map(|(template, _)| template.to_string())
+            .unwrap_or(template);
+        Some(WalletPolicy {
+            template,
+            keys: translator.keys,
+        })
+    }
+
+    /// The descriptor as a single-part `output-descriptor` Uniform Resource, in upper case so it
+    /// fits in a QR code in alphanumeric mode.
+    pub fn to_ur(&self) -> String {
+        self.to_ur_parts(usize::MAX).concat()
+    }
+
+    /// The descriptor as an `output-descriptor` Uniform Resource, in as many parts as necessary
+    /// to be displayed as an animated QR code. See [`ur::encode`].
+    pub fn to_ur_parts(&self, max_fragment_len: usize) -> Vec<String> {
+        // A CBOR map with the descriptor as text under the key 1 (BCR-2023-010).
+        let desc = self.multi_desc.to_string();
+        let mut cbor = vec![0xa1, 0x01];
+        cbor_header(&mut cbor, 3, desc.len() as u64);
+        cbor.extend_from_slice(desc.as_bytes());
+        ur::encode(UR_TYPE_OUTPUT_DESCRIPTOR, &cbor, max_fragment_len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn wallet_policy() {
+        let desc = LianaDescriptor::from_str("wsh(or_i(and_v(v:thresh(2,pkh([aabb0011/48'/0'/0'/2']xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<2;3>/*),a:pkh([aabb0012/48'/0'/0'/2']xpub6Bw79HbNSeS2xXw1sngPE3ehnk1U3iSPCgLYzC9LpN8m9nDuaKLZvkg8QXxL5pDmEmQtYscmUD8B9MkAAZbh6vxPzNXMaLfGQ9Sb3z85qhR/<2;3>/*),a:pkh([aabb0013/48'/0'/0'/2']xpub67zuTXF9Ln4731avKTBSawoVVNRuMfmRvkL7kLUaLBRqma9ZqdHBJg9qx8cPUm3oNQMiXT4TmGovXNoQPuwg17RFcVJ8YrnbcooN7pxVJqC/<2;3>/*)),older(26352)),and_v(v:and_v(v:pk([aabb0011/48'/0'/0'/2']xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*),pk([aabb0012/48'/0'/0'/2']xpub6Bw79HbNSeS2xXw1sngPE3ehnk1U3iSPCgLYzC9LpN8m9nDuaKLZvkg8QXxL5pDmEmQtYscmUD8B9MkAAZbh6vxPzNXMaLfGQ9Sb3z85qhR/<0;1>/*)),pk([aabb0013/48'/0'/0'/2']xpub67zuTXF9Ln4731avKTBSawoVVNRuMfmRvkL7kLUaLBRqma9ZqdHBJg9qx8cPUm3oNQMiXT4TmGovXNoQPuwg17RFcVJ8YrnbcooN7pxVJqC/<0;1>/*))))#tjdnx6vm").unwrap();
+        let policy = desc.wallet_policy().unwrap();
+        assert_eq!(
+            policy.template,
+            "wsh(or_i(and_v(v:thresh(2,pkh(@0/<2;3>/*),a:pkh(@1/<2;3>/*),a:pkh(@2/<2;3>/*)),older(26352)),and_v(v:and_v(v:pk(@0/**),pk(@1/**)),pk(@2/**))))"
+        );
+        assert_eq!(
+            policy.keys,
+            vec![
+                "[aabb0011/48'/0'/0'/2']xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW".to_string(),
+                "[aabb0012/48'/0'/0'/2']xpub6Bw79HbNSeS2xXw1sngPE3ehnk1U3iSPCgLYzC9LpN8m9nDuaKLZvkg8QXxL5pDmEmQtYscmUD8B9MkAAZbh6vxPzNXMaLfGQ9Sb3z85qhR".to_string(),
+                "[aabb0013/48'/0'/0'/2']xpub67zuTXF9Ln4731avKTBSawoVVNRuMfmRvkL7kLUaLBRqma9ZqdHBJg9qx8cPUm3oNQMiXT4TmGovXNoQPuwg17RFcVJ8YrnbcooN7pxVJqC".to_string(),
+            ]
+        );
+
+        // A map with the descriptor as a text of more than 255 bytes under the key 1.
+        let ur = desc.to_ur();
+        assert!(ur.starts_with("UR:OUTPUT-DESCRIPTOR/OYADKK"), "{}", ur);
+        assert!(ur
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c == ':' || c == '/' || c == '-'));
+    }
+}
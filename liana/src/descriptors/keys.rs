@@ -67,7 +67,7 @@ impl str::FromStr for DerivedPublicKey {
             }
         }
 
-        if s.chars().next().expect("Size checked above") != '[' {
+        if !s.starts_with('[') {
             return Err(DescKeyError::DerivedKeyParsing);
         }
 
@@ -0,0 +1,286 @@
+//! Flag valid but risky constructions in a Liana descriptor.
+//!
+//! None of these make the descriptor unusable, they are warnings to be shown to the user before
+//! they commit to a descriptor.
+
+use miniscript::bitcoin::bip32;
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt,
+};
+
+use super::{LianaDescriptor, PathInfo};
+
+/// Below this timelock (about a month of blocks), a recovery path may become available before the
+/// coins are refreshed.
+pub const DEFAULT_MIN_TIMELOCK: u16 = 4_320;
+
+/// Above this timelock (about a year of blocks), coins would be locked for long if the primary
+/// keys were lost.
+pub const DEFAULT_MAX_TIMELOCK: u16 = 52_560;
+
+/// What to take into account when linting a descriptor.
+#[derive(Debug, Clone)]
+pub struct LintOptions {
+    /// Warn about recovery paths with a shorter timelock, in blocks.
+    pub min_timelock: u16,
+    /// Warn about recovery paths with a longer timelock, in blocks.
+    pub max_timelock: u16,
+    /// The model of the signing device of each key, if known.
+    pub device_models: HashMap<bip32::Fingerprint, String>,
+    /// The keys held by a hot signer, that is stored on the computer.
+    pub hot_signers: HashSet<bip32::Fingerprint>,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        Self {
+            min_timelock: DEFAULT_MIN_TIMELOCK,
+            max_timelock: DEFAULT_MAX_TIMELOCK,
+            device_models: HashMap::new(),
+            hot_signers: HashSet::new(),
+        }
+    }
+}
+
+/// A risky construction in a descriptor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintWarning {
+    /// The recovery path with this timelock becomes available quickly.
+    ShortTimelock { timelock: u16, min: u16 },
+    /// The recovery path with this timelock takes long to become available.
+    LongTimelock { timelock: u16, max: u16 },
+    /// All the keys of the primary path are on the same model of signing device, which makes it
+    /// a single point of failure.
+    SameDeviceModel { model: String },
+    /// The recovery path with this timelock needs the same keys as the primary path.
+    RecoveryWithPrimaryKeys { timelock: u16 },
+    /// The recovery path with this timelock is a single key held by a hot signer.
+    HotSignerRecovery {
+        timelock: u16,
+        fingerprint: bip32::Fingerprint,
+    },
+    /// The recovery path with this timelock has a key on the same signing device as a key of the
+    /// primary path, typically for another account.
+    SharedSigner {
+        timelock: u16,
+        fingerprint: bip32::Fingerprint,
+    },
+}
+
+impl LintWarning {
+    /// A stable identifier for this kind of warning.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::ShortTimelock { .. } => "short_timelock",
+            Self::LongTimelock { .. } => "long_timelock",
+            Self::SameDeviceModel { .. } => "same_device_model",
+            Self::RecoveryWithPrimaryKeys { .. } => "recovery_with_primary_keys",
+            Self::HotSignerRecovery { .. } => "hot_signer_recovery",
+            Self::SharedSigner { .. } => "shared_signer",
+        }
+    }
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ShortTimelock { timelock, min } => write!(
+                f,
+                "The recovery path with a timelock of {} blocks becomes available quickly (less \
+                 than {} blocks). Coins would need to be refreshed very often.",
+                timelock, min
+            ),
+            Self::LongTimelock { timelock, max } => write!(
+                f,
+                "The recovery path with a timelock of {} blocks takes long to become available \
+                 (more than {} blocks). Coins would be locked for long if the primary keys were \
+                 lost.",
+                timelock, max
+            ),
+            Self::SameDeviceModel { model } => write!(
+                f,
+                "All the primary keys are on a {} signing device. A flaw in this model would \
+                 affect all of them.",
+                model
+            ),
+            Self::RecoveryWithPrimaryKeys { timelock } => write!(
+                f,
+                "The recovery path with a timelock of {} blocks needs as many of the same keys as \
+                 the primary path. It doesn't help if the primary keys are lost.",
+                timelock
+            ),
+            Self::HotSignerRecovery {
+                timelock,
+                fingerprint,
+            } => write!(
+                f,
+                "The recovery path with a timelock of {} blocks is a single key ({}) stored on \
+                 this computer. Anyone accessing it could take the coins once the timelock \
+                 expires.",
+                timelock, fingerprint
+            ),
+            Self::SharedSigner {
+                timelock,
+                fingerprint,
+            } => write!(
+                f,
+                "The signing device {} holds keys for both the primary path and the recovery \
+                 path with a timelock of {} blocks. Losing it or having it compromised affects \
+                 both paths.",
+                fingerprint, timelock
+            ),
+        }
+    }
+}
+
+// The signers of the keys of this spending path, and how many of them must sign.
+fn path_signers(path: &PathInfo) -> (usize, BTreeSet<bip32::Fingerprint>) {
+    match path {
+        PathInfo::Single(key) => (1, std::iter::once(key.master_fingerprint()).collect()),
+        PathInfo::Multi(k, keys) => (*k, keys.iter().map(|k| k.master_fingerprint()).collect()),
+    }
+}
+
+/// Check this descriptor for valid but risky constructions.
+pub fn lint_descriptor(desc: &LianaDescriptor, options: &LintOptions) -> Vec<LintWarning> {
+    let mut warnings = Vec::new();
+    let policy = desc.policy();
+    let (primary_thresh, primary_signers) = path_signers(policy.primary_path());
+
+    if primary_signers.len() > 1 {
+        let mut models = primary_signers
+            .iter()
+            .map(|fg| options.device_models.get(fg));
+        if let Some(Some(model)) = models.next() {
+            if models.all(|m| m == Some(model)) {
+                warnings.push(LintWarning::SameDeviceModel {
+                    model: model.clone(),
+                });
+            }
+        }
+    }
+
+    for (timelock, path) in policy.recovery_paths() {
+        let timelock = *timelock;
+        if timelock < options.min_timelock {
+            warnings.push(LintWarning::ShortTimelock {
+                timelock,
+                min: options.min_timelock,
+            });
+        }
+        if timelock > options.max_timelock {
+            warnings.push(LintWarning::LongTimelock {
+                timelock,
+                max: options.max_timelock,
+            });
+        }
+
+        let (thresh, signers) = path_signers(path);
+        if signers == primary_signers && thresh >= primary_thresh {
+            warnings.push(LintWarning::RecoveryWithPrimaryKeys { timelock });
+        } else {
+            warnings.extend(signers.intersection(&primary_signers).map(|fingerprint| {
+                LintWarning::SharedSigner {
+                    timelock,
+                    fingerprint: *fingerprint,
+                }
+            }));
+        }
+        if let PathInfo::Single(key) = path {
+            let fingerprint = key.master_fingerprint();
+            if options.hot_signers.contains(&fingerprint) {
+                warnings.push(LintWarning::HotSignerRecovery {
+                    timelock,
+                    fingerprint,
+                });
+            }
+        }
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn lint_warnings() {
+        // A 1-of-2 primary path and a 2-of-2 recovery path of the same signers after 1000 blocks.
+        let desc = LianaDescriptor::from_str("wsh(or_d(multi(1,[573fb35b/48'/1'/0'/2']tpubDFKp9T7WAYDcENSjoifkrpq1gMDF47KGJcJrpxzX23Qor8wuGbrEVs9utNq1MDS8E2WXJSBk1qoPQLpwyokW7DiUNPwFuxQkL7owNkLAb9W/<0;1>/*,[573fb35c/48'/1'/1'/2']tpubDFGezyzuHJPhdP3jHGW7v7Hwes4Hihqv5W2yyCmRY9VZJCRchETvxrMC8uECeJZdxQ14V4iD4DecoArkUSDwj8ogYE9WEv4MNZr12thNHCs/<0;1>/*),and_v(v:multi(2,[573fb35b/48'/1'/2'/2']tpubDDwxQauiaU964vPzt5Vd7jnDHEUtp2Vc34PaWpEXg5TQ3bRccxnc1MKKh88Hi7xiMeZo9Tm6fBcq4UGXqnDtGUniJLjqAD8SjQ8Eci3aSR7/<0;1>/*,[573fb35c/48'/1'/3'/2']tpubDE37XAVB5CQ1x85md3BQ5uHCoMwT5fgT8X13zzCUQ3x5o2jskYxKjj7Qcxt1Jpj4QB8tqspn2dooPCekRuQDYrDHov7J1ueUNu2wcvgRDxr/<0;1>/*),older(1000))))#fccaqlhh").unwrap();
+        let (fg_a, fg_b) = (
+            bip32::Fingerprint::from_str("573fb35b").unwrap(),
+            bip32::Fingerprint::from_str("573fb35c").unwrap(),
+        );
+
+        let mut options = LintOptions::default();
+        assert_eq!(
+            lint_descriptor(&desc, &options),
+            vec![
+                LintWarning::ShortTimelock {
+                    timelock: 1000,
+                    min: DEFAULT_MIN_TIMELOCK
+                },
+                LintWarning::RecoveryWithPrimaryKeys { timelock: 1000 },
+            ]
+        );
+
+        // The device models are only flagged if they are all known and the same.
+        options.min_timelock = 144;
+        options.max_timelock = 999;
+        options
+            .device_models
+            .insert(fg_a, "Ledger Nano S".to_string());
+        assert_eq!(
+            lint_descriptor(&desc, &options),
+            vec![
+                LintWarning::LongTimelock {
+                    timelock: 1000,
+                    max: 999
+                },
+                LintWarning::RecoveryWithPrimaryKeys { timelock: 1000 },
+            ]
+        );
+        options
+            .device_models
+            .insert(fg_b, "Ledger Nano S".to_string());
+        assert_eq!(
+            lint_descriptor(&desc, &options)[0],
+            LintWarning::SameDeviceModel {
+                model: "Ledger Nano S".to_string()
+            }
+        );
+
+        // A single hot key as recovery path.
+        let desc = LianaDescriptor::from_str("wsh(andor(pk([abcdef01]tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/<0;1>/*),older(10000),pk([abcdef01]tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/<0;1>/*)))#2qj59a9y").unwrap();
+        let fg = bip32::Fingerprint::from_str("abcdef01").unwrap();
+        let options = LintOptions {
+            hot_signers: std::iter::once(fg).collect(),
+            ..LintOptions::default()
+        };
+        assert_eq!(
+            lint_descriptor(&desc, &options),
+            vec![
+                LintWarning::RecoveryWithPrimaryKeys { timelock: 10000 },
+                LintWarning::HotSignerRecovery {
+                    timelock: 10000,
+                    fingerprint: fg
+                },
+            ]
+        );
+
+        // The same signing device in the primary path and in a recovery path, for distinct
+        // accounts.
+        let desc = LianaDescriptor::from_str("wsh(or_d(multi(2,[573fb35b/48'/1'/0'/2']tpubDFKp9T7WAYDcENSjoifkrpq1gMDF47KGJcJrpxzX23Qor8wuGbrEVs9utNq1MDS8E2WXJSBk1qoPQLpwyokW7DiUNPwFuxQkL7owNkLAb9W/<0;1>/*,[573fb35c/48'/1'/1'/2']tpubDFGezyzuHJPhdP3jHGW7v7Hwes4Hihqv5W2yyCmRY9VZJCRchETvxrMC8uECeJZdxQ14V4iD4DecoArkUSDwj8ogYE9WEv4MNZr12thNHCs/<0;1>/*),and_v(v:pkh([573fb35b/48'/1'/2'/2']tpubDDwxQauiaU964vPzt5Vd7jnDHEUtp2Vc34PaWpEXg5TQ3bRccxnc1MKKh88Hi7xiMeZo9Tm6fBcq4UGXqnDtGUniJLjqAD8SjQ8Eci3aSR7/<0;1>/*),older(10000))))").unwrap();
+        assert_eq!(
+            lint_descriptor(&desc, &LintOptions::default()),
+            vec![LintWarning::SharedSigner {
+                timelock: 10000,
+                fingerprint: fg_a
+            }]
+        );
+    }
+}
@@ -0,0 +1,137 @@
+//! What-if analysis of the loss of some signers.
+//!
+//! Given the signers which are lost, tell which spending paths of a descriptor can still be used
+//! and therefore after how long coins would still be spendable.
+
+use miniscript::bitcoin::bip32;
+
+use std::collections::HashSet;
+
+use super::{LianaDescriptor, PathInfo};
+
+/// Whether a spending path can still be used without the lost signers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathAvailability {
+    /// The timelock of the path, `None` for the primary path.
+    pub timelock: Option<u16>,
+    /// How many keys of the path must sign.
+    pub threshold: usize,
+    /// How many keys there are in the path.
+    pub keys: usize,
+    /// How many keys of the path aren't held by a lost signer.
+    pub remaining_keys: usize,
+}
+
+impl PathAvailability {
+    fn new(timelock: Option<u16>, path: &PathInfo, lost: &HashSet<bip32::Fingerprint>) -> Self {
+        let (threshold, keys) = match path {
+            PathInfo::Single(key) => (1, std::slice::from_ref(key)),
+            PathInfo::Multi(k, keys) => (*k, keys.as_slice()),
+        };
+        let remaining_keys = keys
+            .iter()
+            .filter(|key| !lost.contains(&key.master_fingerprint()))
+            .count();
+        Self {
+            timelock,
+            threshold,
+            keys: keys.len(),
+            remaining_keys,
+        }
+    }
+
+    /// Whether enough keys remain to spend through this path.
+    pub fn is_usable(&self) -> bool {
+        self.remaining_keys >= self.threshold
+    }
+}
+
+/// The spending paths of a descriptor which remain usable after the loss of some signers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignerLossAnalysis {
+    /// The primary path first, then the recovery paths by increasing timelock.
+    pub paths: Vec<PathAvailability>,
+}
+
+impl SignerLossAnalysis {
+    /// How many blocks after its confirmation a coin can be spent with the remaining signers.
+    /// This is 0 if the primary path is still usable and `None` if the coins are lost.
+    pub fn spendable_after(&self) -> Option<u16> {
+        self.paths
+            .iter()
+            .find(|path| path.is_usable())
+            .map(|path| path.timelock.unwrap_or(0))
+    }
+}
+
+/// Check which spending paths of this descriptor could still be used if the signers with these
+/// master fingerprints were lost.
+pub fn signer_loss(
+    desc: &LianaDescriptor,
+    lost: &HashSet<bip32::Fingerprint>,
+) -> SignerLossAnalysis {
+    let policy = desc.policy();
+    let paths = std::iter::once(PathAvailability::new(None, policy.primary_path(), lost))
+        .chain(
+            policy
+                .recovery_paths()
+                .iter()
+                .map(|(timelock, path)| PathAvailability::new(Some(*timelock), path, lost)),
+        )
+        .collect();
+    SignerLossAnalysis { paths }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn signer_loss_paths() {
+        // A 1-of-2 primary path and a 2-of-2 recovery path of the same signers after 1000 blocks.
+        let desc = LianaDescriptor::from_str("wsh(or_d(multi(1,[573fb35b/48'/1'/0'/2']tpubDFKp9T7WAYDcENSjoifkrpq1gMDF47KGJcJrpxzX23Qor8wuGbrEVs9utNq1MDS8E2WXJSBk1qoPQLpwyokW7DiUNPwFuxQkL7owNkLAb9W/<0;1>/*,[573fb35c/48'/1'/1'/2']tpubDFGezyzuHJPhdP3jHGW7v7Hwes4Hihqv5W2yyCmRY9VZJCRchETvxrMC8uECeJZdxQ14V4iD4DecoArkUSDwj8ogYE9WEv4MNZr12thNHCs/<0;1>/*),and_v(v:multi(2,[573fb35b/48'/1'/2'/2']tpubDDwxQauiaU964vPzt5Vd7jnDHEUtp2Vc34PaWpEXg5TQ3bRccxnc1MKKh88Hi7xiMeZo9Tm6fBcq4UGXqnDtGUniJLjqAD8SjQ8Eci3aSR7/<0;1>/*,[573fb35c/48'/1'/3'/2']tpubDE37XAVB5CQ1x85md3BQ5uHCoMwT5fgT8X13zzCUQ3x5o2jskYxKjj7Qcxt1Jpj4QB8tqspn2dooPCekRuQDYrDHov7J1ueUNu2wcvgRDxr/<0;1>/*),older(1000))))#fccaqlhh").unwrap();
+        let fg_a = bip32::Fingerprint::from_str("573fb35b").unwrap();
+        let fg_b = bip32::Fingerprint::from_str("573fb35c").unwrap();
+
+        let analysis = signer_loss(&desc, &HashSet::new());
+        assert!(analysis.paths.iter().all(|p| p.is_usable()));
+        assert_eq!(analysis.spendable_after(), Some(0));
+
+        // Losing one signer still allows to spend through the primary path, but not the
+        // recovery path.
+        let analysis = signer_loss(&desc, &HashSet::from([fg_a]));
+        assert_eq!(
+            analysis.paths,
+            vec![
+                PathAvailability {
+                    timelock: None,
+                    threshold: 1,
+                    keys: 2,
+                    remaining_keys: 1,
+                },
+                PathAvailability {
+                    timelock: Some(1000),
+                    threshold: 2,
+                    keys: 2,
+                    remaining_keys: 1,
+                },
+            ]
+        );
+        assert_eq!(analysis.spendable_after(), Some(0));
+
+        // Losing both loses the coins.
+        let analysis = signer_loss(&desc, &HashSet::from([fg_a, fg_b]));
+        assert!(analysis.paths.iter().all(|p| !p.is_usable()));
+        assert_eq!(analysis.spendable_after(), None);
+
+        // Losing the primary key of a single-key wallet leaves the recovery path.
+        let desc = LianaDescriptor::from_str("wsh(or_d(pk([aabbccdd]tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/<0;1>/*),and_v(v:pkh([abcdef01]tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/<0;1>/*),older(10000))))").unwrap();
+        let analysis = signer_loss(
+            &desc,
+            &HashSet::from([bip32::Fingerprint::from_str("aabbccdd").unwrap()]),
+        );
+        assert!(!analysis.paths[0].is_usable());
+        assert_eq!(analysis.spendable_after(), Some(10000));
+    }
+}
@@ -8,7 +8,7 @@ use miniscript::{
     descriptor,
     miniscript::satisfy::Placeholder,
     plan::{Assets, CanSign},
-    psbt::{PsbtInputExt, PsbtOutputExt},
+    psbt::{PsbtExt, PsbtInputExt, PsbtOutputExt},
     translate_hash_clone, ForEachKey, TranslatePk, Translator,
 };
 
@@ -27,6 +27,18 @@ pub use keys::*;
 pub mod analysis;
 pub use analysis::*;
 
+pub mod lint;
+pub use lint::*;
+
+pub mod loss;
+pub use loss::*;
+
+pub mod sheet;
+pub use sheet::*;
+
+pub mod export;
+pub use export::*;
+
 #[derive(Debug)]
 pub enum LianaDescError {
     Miniscript(miniscript::Error),
@@ -117,6 +129,8 @@ impl str::FromStr for LianaDescriptor {
         // spending policy.
         // Sanity checks are not always performed when calling `Descriptor::from_str`, so we perform
         // them explicitly. See https://github.com/rust-bitcoin/rust-miniscript/issues/734.
+        // Parsing is recursive, so make sure the string isn't too deeply nested beforehand.
+        AnalysisLimits::default().check_str(s)?;
         let desc = descriptor::Descriptor::<descriptor::DescriptorPublicKey>::from_str(s)
             .and_then(|desc| desc.sanity_check().map(|_| desc))
             .map_err(LianaDescError::Miniscript)?;
@@ -317,10 +331,8 @@ impl LianaDescriptor {
     /// size of the witness stack length varint.
     pub fn max_sat_vbytes(&self, use_primary_path: bool) -> usize {
         self.max_sat_weight(use_primary_path)
-            .checked_add(WITNESS_SCALE_FACTOR - 1)
-            .unwrap()
-            .checked_div(WITNESS_SCALE_FACTOR)
-            .unwrap()
+            .saturating_add(WITNESS_SCALE_FACTOR - 1)
+            / WITNESS_SCALE_FACTOR
     }
 
     /// Get the maximum size in virtual bytes of the whole input in a transaction spending
@@ -335,62 +347,190 @@ impl LianaDescriptor {
         matches!(self.multi_desc, descriptor::Descriptor::Tr(..))
     }
 
+    /// Get the origins of the keys of this descriptor which provided a valid signature for this
+    /// PSBT input. A signature is only accounted for if it was made by the key derived from this
+    /// descriptor at the origin given for it, and it verifies for the signature hash of the input.
+    /// For Taproot, a signature for a script path spend is only accounted for if the key is part of
+    /// the leaf it signs for. For P2WSH, the signatures are only accounted for if the witness script
+    /// of the input is the one of the spent coin.
+    fn valid_signers(
+        &self,
+        psbt: &Psbt,
+        input_index: usize,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+    ) -> Vec<(bip32::Fingerprint, bip32::DerivationPath)> {
+        let (psbt_in, utxo) = match psbt
+            .inputs
+            .get(input_index)
+            .and_then(|psbt_in| Some((psbt_in, psbt_in.witness_utxo.as_ref()?)))
+        {
+            Some(input) => input,
+            None => return Vec::new(),
+        };
+
+        // The keys of the descriptor derived at the derivation indexes of the origins given in the
+        // PSBT input.
+        let der_indexes: BTreeSet<bip32::ChildNumber> = psbt_in
+            .bip32_derivation
+            .values()
+            .chain(psbt_in.tap_key_origins.values().map(|(_, origin)| origin))
+            .filter_map(|(_, der_path)| der_path.as_ref().last().copied())
+            .filter(|index| index.is_normal())
+            .collect();
+        // For Taproot, also record the leaves of the tree of scripts each key appears in.
+        let mut keys = Vec::new();
+        let mut leaf_keys = Vec::new();
+        for index in der_indexes {
+            for desc in [&self.receive_desc, &self.change_desc] {
+                let derived_desc = desc.derive(index, secp);
+                derived_desc.0.for_each_key(|k| {
+                    keys.push(k.clone());
+                    true
+                });
+                if let descriptor::Descriptor::Tr(ref tr) = derived_desc.0 {
+                    for (_, ms) in tr.iter_scripts() {
+                        let leaf_hash = bitcoin::TapLeafHash::from_script(
+                            &ms.encode(),
+                            bitcoin::taproot::LeafVersion::TapScript,
+                        );
+                        leaf_keys.extend(ms.iter_pk().map(|k| (leaf_hash, k)));
+                    }
+                }
+            }
+        }
+        let is_derived =
+            |pubkey: &secp256k1::PublicKey,
+             origin: &(bip32::Fingerprint, bip32::DerivationPath)| {
+                keys.iter()
+                    .any(|k| k.origin == *origin && k.key.inner == *pubkey)
+            };
+        let is_derived_xonly =
+            |pubkey: &secp256k1::XOnlyPublicKey,
+             origin: &(bip32::Fingerprint, bip32::DerivationPath)| {
+                keys.iter()
+                    .any(|k| k.origin == *origin && k.key.inner.x_only_public_key().0 == *pubkey)
+            };
+        // A signature for a script path spend only counts for the leaf it commits to.
+        let is_in_leaf = |pubkey: &secp256k1::XOnlyPublicKey,
+                          origin: &(bip32::Fingerprint, bip32::DerivationPath),
+                          leaf_hash: &bitcoin::TapLeafHash| {
+            leaf_keys.iter().any(|(leaf, k)| {
+                leaf == leaf_hash
+                    && k.origin == *origin
+                    && k.key.inner.x_only_public_key().0 == *pubkey
+            })
+        };
+
+        let mut sighash_cache = bitcoin::sighash::SighashCache::new(&psbt.unsigned_tx);
+        let mut signers = Vec::new();
+        if self.is_taproot() {
+            let prevouts: Option<Vec<&bitcoin::TxOut>> = psbt
+                .inputs
+                .iter()
+                .map(|psbt_in| psbt_in.witness_utxo.as_ref())
+                .collect();
+            let mut verify =
+                |sig: &bitcoin::taproot::Signature,
+                 pubkey: &secp256k1::XOnlyPublicKey,
+                 leaf_hash: Option<bitcoin::TapLeafHash>| {
+                    taproot_sighash(
+                        &mut sighash_cache,
+                        input_index,
+                        prevouts.as_deref(),
+                        utxo,
+                        sig.sighash_type,
+                        leaf_hash,
+                    )
+                    .map(|msg| secp.verify_schnorr(&sig.signature, &msg, pubkey).is_ok())
+                        == Some(true)
+                };
+
+            for ((pubkey, leaf_hash), sig) in &psbt_in.tap_script_sigs {
+                if let Some((_, origin)) = psbt_in.tap_key_origins.get(pubkey) {
+                    if is_in_leaf(pubkey, origin, leaf_hash)
+                        && verify(sig, pubkey, Some(*leaf_hash))
+                    {
+                        signers.push(origin.clone());
+                    }
+                }
+            }
+
+            // A key path spend is signed by the output key, which is the internal key tweaked with
+            // the root of the tree of scripts.
+            if let (Some(sig), Some(internal_key)) = (psbt_in.tap_key_sig, psbt_in.tap_internal_key)
+            {
+                use bitcoin::key::TapTweak;
+                let (output_key, _) = internal_key.tap_tweak(secp, psbt_in.tap_merkle_root);
+                if let Some((_, origin)) = psbt_in.tap_key_origins.get(&internal_key) {
+                    if utxo.script_pubkey == bitcoin::ScriptBuf::new_p2tr_tweaked(output_key)
+                        && is_derived_xonly(&internal_key, origin)
+                        && verify(&sig, &output_key.to_x_only_public_key(), None)
+                    {
+                        signers.push(origin.clone());
+                    }
+                }
+            }
+        } else if let Some(witness_script) = psbt_in
+            .witness_script
+            .as_ref()
+            // The signatures commit to the witness script, it must be the one of the spent coin.
+            .filter(|ws| utxo.script_pubkey == bitcoin::ScriptBuf::new_p2wsh(&ws.wscript_hash()))
+        {
+            for (pubkey, sig) in &psbt_in.partial_sigs {
+                if let Some(origin) = psbt_in.bip32_derivation.get(&pubkey.inner) {
+                    let valid = is_derived(&pubkey.inner, origin)
+                        && sighash_cache
+                            .p2wsh_signature_hash(
+                                input_index,
+                                witness_script,
+                                utxo.value,
+                                sig.sighash_type,
+                            )
+                            .map(|sighash| {
+                                secp.verify_ecdsa(&sighash.into(), &sig.signature, &pubkey.inner)
+                                    .is_ok()
+                            })
+                            .unwrap_or(false);
+                    if valid {
+                        signers.push(origin.clone());
+                    }
+                }
+            }
+        }
+
+        signers
+    }
+
     /// Get some information about a PSBT input spending Liana coins.
-    /// This analysis assumes that:
-    /// - The PSBT input actually spend a Liana coin for this descriptor. Otherwise the analysis will be off.
-    /// - The signatures contained in the PSBT input are valid for this script.
+    /// This analysis assumes that the PSBT input actually spends a Liana coin for this descriptor.
+    /// Otherwise the analysis will be off. Only the signatures which are valid for the keys of
+    /// this descriptor are accounted for.
     pub fn partial_spend_info_txin(
         &self,
-        psbt_in: &PsbtIn,
-        txin: &bitcoin::TxIn,
+        psbt: &Psbt,
+        input_index: usize,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
     ) -> PartialSpendInfo {
-        let is_taproot = self.is_taproot();
-        // Get the origin ECDSA or Schnorr signatures, depending on the descriptor type.
-        let pubkeys_signed = (!is_taproot)
-            .then(|| {
-                // ECDSA sigs.
-                psbt_in
-                    .partial_sigs
-                    .iter()
-                    .filter_map(|(pk, _)| psbt_in.bip32_derivation.get(&pk.inner))
-            })
-            .into_iter()
-            .flatten()
-            .chain(
-                is_taproot
-                    .then(|| {
-                        // Tapscript Schnorr sigs.
-                        psbt_in
-                            .tap_script_sigs
-                            .iter()
-                            .filter_map(|((pk, _), _)| {
-                                psbt_in.tap_key_origins.get(pk).map(|or| &or.1)
-                            })
-                            // Tapkey Schnorr sig.
-                            .chain(psbt_in.tap_key_sig.and_then(|_| {
-                                psbt_in
-                                    .tap_internal_key
-                                    .and_then(|pk| psbt_in.tap_key_origins.get(&pk).map(|or| &or.1))
-                            }))
-                    })
-                    .into_iter()
-                    .flatten(),
-            );
+        let pubkeys_signed = self.valid_signers(psbt, input_index, secp);
+        let sequence = psbt
+            .unsigned_tx
+            .input
+            .get(input_index)
+            .map(|txin| txin.sequence);
 
         // Determine the structure of the descriptor. Then compute the spend info for the primary
         // and recovery paths. Only provide the spend info for the recovery path if it is available
         // (ie if the nSequence is >= to the chosen CSV value).
         let desc_info = self.policy();
-        let primary_path = desc_info.primary_path.spend_info(pubkeys_signed.clone());
+        let primary_path = desc_info.primary_path.spend_info(pubkeys_signed.iter());
         let recovery_paths = desc_info
             .recovery_paths
             .iter()
-            .filter_map(|(timelock, path_info)| {
-                if txin.sequence.is_height_locked() && txin.sequence.0 >= *timelock as u32 {
-                    Some((*timelock, path_info.spend_info(pubkeys_signed.clone())))
-                } else {
-                    None
+            .filter_map(|(timelock, path_info)| match sequence {
+                Some(sequence) if sequence.is_height_locked() && sequence.0 >= *timelock as u32 => {
+                    Some((*timelock, path_info.spend_info(pubkeys_signed.iter())))
                 }
+                _ => None,
             })
             .collect();
 
@@ -400,16 +540,19 @@ impl LianaDescriptor {
         }
     }
 
-    // TODO: decide whether we should check the signatures too. To be useful it should check pubkeys
-    // correspond to those in the script. And we could be checking the witness scripts are all for
-    // our descriptor too..
     /// Get some information about a PSBT spending Liana coins.
     /// This analysis assumes that:
     /// - The PSBT only contains input that spends Liana coins. Otherwise the analysis will be off.
     /// - The PSBT is consistent across inputs (the sequence is the same across inputs, the
     ///   signatures are either absent or present for all inputs, ..)
-    /// - The provided signatures are valid for this script.
-    pub fn partial_spend_info(&self, psbt: &Psbt) -> Result<PartialSpendInfo, LianaDescError> {
+    ///
+    /// Signatures which don't verify for the keys derived from this descriptor for the coins spent
+    /// are not accounted for.
+    pub fn partial_spend_info(
+        &self,
+        psbt: &Psbt,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+    ) -> Result<PartialSpendInfo, LianaDescError> {
         // Check the PSBT isn't empty or malformed.
         if psbt.inputs.len() != psbt.unsigned_tx.input.len()
             || psbt.outputs.len() != psbt.unsigned_tx.output.len()
@@ -424,19 +567,17 @@ impl LianaDescriptor {
         // must be.
         // This gets the information needed to analyze the number of signatures from the
         // first input, and checks that this info matches on all inputs.
-        let (mut psbt_ins, mut txins) = (psbt.inputs.iter(), psbt.unsigned_tx.input.iter());
-        let (first_psbt_in, first_txin) = (
-            psbt_ins
-                .next()
-                .expect("We checked at least one is present."),
-            txins.next().expect("We checked at least one is present."),
-        );
-        let spend_info = self.partial_spend_info_txin(first_psbt_in, first_txin);
-        for (psbt_in, txin) in psbt_ins.zip(txins) {
+        let mut txins = psbt.unsigned_tx.input.iter().enumerate();
+        let first_txin = match txins.next() {
+            Some((_, txin)) => txin,
+            None => return Err(LianaDescError::InsanePsbt),
+        };
+        let spend_info = self.partial_spend_info_txin(psbt, 0, secp);
+        for (index, txin) in txins {
             // TODO: maybe it's better to not error if one of the input has more, or different
             // signatures? Instead of erroring we could ignore the superfluous data?
             if txin.sequence != first_txin.sequence
-                || spend_info != self.partial_spend_info_txin(psbt_in, txin)
+                || spend_info != self.partial_spend_info_txin(psbt, index, secp)
             {
                 return Err(LianaDescError::InconsistentPsbt);
             }
@@ -445,6 +586,169 @@ impl LianaDescriptor {
         Ok(spend_info)
     }
 
+    /// Attempt to construct the witness of each input of this PSBT with the signatures it
+    /// contains, without modifying it. For each input, report the spending path it can be
+    /// finalized through if any, and the signatures missing for the other spending paths
+    /// available to it.
+    pub fn finalizability(
+        &self,
+        psbt: &Psbt,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+    ) -> Vec<InputFinalization> {
+        let policy = self.policy();
+        let mut dry_run = psbt.clone();
+        (0..psbt.inputs.len())
+            .map(|index| {
+                let info = self.partial_spend_info_txin(psbt, index, secp);
+                let paths = std::iter::once((
+                    SpendingPath::Primary,
+                    policy.primary_path(),
+                    info.primary_path(),
+                ))
+                .chain(info.recovery_paths().iter().filter_map(
+                    |(timelock, info)| {
+                        policy
+                            .recovery_paths()
+                            .get(timelock)
+                            .map(|path| (SpendingPath::Recovery(*timelock), path, info))
+                    },
+                ));
+                let mut satisfied = None;
+                let mut missing = Vec::new();
+                for (spending_path, path, info) in paths {
+                    if info.sigs_count >= info.threshold {
+                        satisfied = satisfied.or(Some(spending_path));
+                    } else {
+                        let mut signers: Vec<_> = path
+                            .thresh_origins()
+                            .1
+                            .into_keys()
+                            .filter(|fg| !info.signed_pubkeys.contains_key(fg))
+                            .collect();
+                        signers.sort();
+                        missing.push(MissingSignatures {
+                            path: spending_path,
+                            count: info.threshold - info.sigs_count,
+                            signers,
+                        });
+                    }
+                }
+
+                // Only report a path as usable if a witness can actually be constructed for it.
+                match dry_run.finalize_inp_mut(secp, index) {
+                    Ok(()) => InputFinalization {
+                        path: satisfied,
+                        missing,
+                        error: satisfied
+                            .is_none()
+                            .then(|| "No spending path of the descriptor is satisfied.".into()),
+                    },
+                    Err(e) => InputFinalization {
+                        path: None,
+                        missing,
+                        error: Some(e.to_string()),
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// Get the spending path used by an input of a signed transaction, which spends a coin of
+    /// this descriptor with the given value and derivation index. This is determined from the
+    /// keys which signed the input, and is `None` if no spending path has enough valid
+    /// signatures from its keys.
+    pub fn signed_input_path(
+        &self,
+        tx: &bitcoin::Transaction,
+        input_index: usize,
+        coin_value: bitcoin::Amount,
+        derivation_index: bip32::ChildNumber,
+        is_change: bool,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+    ) -> Option<SpendingPath> {
+        let txin = tx.input.get(input_index)?;
+        let derived_desc = if is_change {
+            self.change_desc.derive(derivation_index, secp)
+        } else {
+            self.receive_desc.derive(derivation_index, secp)
+        };
+        let mut keys = Vec::new();
+        derived_desc.0.for_each_key(|k| {
+            keys.push(k.clone());
+            true
+        });
+
+        // Get the origins of the keys which signed this input.
+        let signers: Vec<(bip32::Fingerprint, bip32::DerivationPath)> = if self.is_taproot() {
+            match txin.witness.taproot_leaf_script() {
+                // Under Taproot the keys of each spending path are in their own leaf. Therefore
+                // all the keys of the leaf are those of the spending path used. A spend using the
+                // key path is necessarily for the primary path, its internal key.
+                Some(leaf) => leaf
+                    .script
+                    .instructions()
+                    .filter_map(|ins| match ins {
+                        Ok(bitcoin::script::Instruction::PushBytes(bytes)) => {
+                            secp256k1::XOnlyPublicKey::from_slice(bytes.as_bytes()).ok()
+                        }
+                        _ => None,
+                    })
+                    .filter_map(|xonly| {
+                        keys.iter()
+                            .find(|k| k.key.inner.x_only_public_key().0 == xonly)
+                            .map(|k| k.origin.clone())
+                    })
+                    .collect(),
+                None if !txin.witness.is_empty() => return Some(SpendingPath::Primary),
+                None => return None,
+            }
+        } else {
+            let witness_script = bitcoin::Script::from_bytes(txin.witness.last()?);
+            let mut sighash_cache = bitcoin::sighash::SighashCache::new(tx);
+            let mut signers = Vec::new();
+            for elem in txin.witness.iter().take(txin.witness.len() - 1) {
+                let sig = match bitcoin::ecdsa::Signature::from_slice(elem) {
+                    Ok(sig) => sig,
+                    Err(_) => continue,
+                };
+                let sighash = match sighash_cache.p2wsh_signature_hash(
+                    input_index,
+                    witness_script,
+                    coin_value,
+                    sig.sighash_type,
+                ) {
+                    Ok(sighash) => sighash,
+                    Err(_) => continue,
+                };
+                let msg = secp256k1::Message::from(sighash);
+                signers.extend(
+                    keys.iter()
+                        .filter(|k| {
+                            secp.verify_ecdsa(&msg, &sig.signature, &k.key.inner)
+                                .is_ok()
+                        })
+                        .map(|k| k.origin.clone()),
+                );
+            }
+            signers
+        };
+
+        let policy = self.policy();
+        let paths = std::iter::once((SpendingPath::Primary, policy.primary_path())).chain(
+            policy
+                .recovery_paths()
+                .iter()
+                .map(|(timelock, path)| (SpendingPath::Recovery(*timelock), path)),
+        );
+        for (spending_path, path_info) in paths {
+            let info = path_info.spend_info(signers.iter());
+            if info.sigs_count > 0 && info.sigs_count >= info.threshold {
+                return Some(spending_path);
+            }
+        }
+        None
+    }
+
     /// List the indexes of the change outputs in this PSBT. It relies on the PSBT to be
     /// well-formed: sane BIP32 derivations must be set for every change output, the inner
     /// transaction must have the same number of outputs as the PSBT.
@@ -478,6 +782,8 @@ impl LianaDescriptor {
                 .chain(tap_der_index.into_iter())
                 .next()
                 .and_then(|der_path| der_path.into_iter().last())
+                // A hardened index can't be for one of our addresses.
+                .filter(|i| i.is_normal())
             {
                 i
             } else {
@@ -535,8 +841,12 @@ impl LianaDescriptor {
     /// - If there is two recovery paths, and the PSBT's first input nSequence is set to unlock the
     ///     first one, prune all but the first recovery path's bip32 derivations.
     /// - Etc..
-    pub fn prune_bip32_derivs_last_avail(&self, psbt: Psbt) -> Result<Psbt, LianaDescError> {
-        let spend_info = self.partial_spend_info(&psbt)?;
+    pub fn prune_bip32_derivs_last_avail(
+        &self,
+        psbt: Psbt,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+    ) -> Result<Psbt, LianaDescError> {
+        let spend_info = self.partial_spend_info(&psbt, secp)?;
         let policy = self.policy();
         let path_info = spend_info
             .recovery_paths
@@ -556,40 +866,71 @@ impl LianaDescriptor {
     /// after satisfaction, assuming all inputs of `tx` are from this
     /// descriptor.
     fn unsigned_tx_max_weight(&self, tx: &bitcoin::Transaction, use_primary_path: bool) -> u64 {
-        let num_inputs: u64 = tx.input.len().try_into().unwrap();
-        let max_sat_weight: u64 = self.max_sat_weight(use_primary_path).try_into().unwrap();
+        // The transaction may come from a PSBT we were given, so don't assume its size is sane.
+        let num_inputs = tx.input.len() as u64;
+        let max_sat_weight = self.max_sat_weight(use_primary_path) as u64;
         // Add weights together before converting to vbytes to avoid rounding up multiple times.
         tx.weight()
             .to_wu()
-            .checked_add(max_sat_weight.checked_mul(num_inputs).unwrap())
-            .and_then(|weight| {
-                weight.checked_add(
-                    // Make sure the Segwit marker and flag are included:
-                    // https://docs.rs/bitcoin/0.31.0/src/bitcoin/blockdata/transaction.rs.html#752-753
-                    // https://docs.rs/bitcoin/0.31.0/src/bitcoin/blockdata/transaction.rs.html#968-979
-                    if num_inputs > 0 && tx.input.iter().all(|txin| txin.witness.is_empty()) {
-                        2
-                    } else {
-                        0
-                    },
-                )
-            })
-            .unwrap()
+            .saturating_add(max_sat_weight.saturating_mul(num_inputs))
+            .saturating_add(
+                // Make sure the Segwit marker and flag are included:
+                // https://docs.rs/bitcoin/0.31.0/src/bitcoin/blockdata/transaction.rs.html#752-753
+                // https://docs.rs/bitcoin/0.31.0/src/bitcoin/blockdata/transaction.rs.html#968-979
+                if num_inputs > 0 && tx.input.iter().all(|txin| txin.witness.is_empty()) {
+                    2
+                } else {
+                    0
+                },
+            )
     }
 
     /// Maximum possible size in vbytes of an unsigned transaction, `tx`,
     /// after satisfaction, assuming all inputs of `tx` are from this
     /// descriptor.
     pub fn unsigned_tx_max_vbytes(&self, tx: &bitcoin::Transaction, use_primary_path: bool) -> u64 {
-        let witness_factor: u64 = WITNESS_SCALE_FACTOR.try_into().unwrap();
+        let witness_factor = WITNESS_SCALE_FACTOR as u64;
         self.unsigned_tx_max_weight(tx, use_primary_path)
-            .checked_add(witness_factor.checked_sub(1).unwrap())
-            .unwrap()
-            .checked_div(witness_factor)
-            .unwrap()
+            .saturating_add(witness_factor - 1)
+            / witness_factor
     }
 }
 
+/// Compute the message signed by a Schnorr signature of this input, for a script path spend if
+/// a leaf hash is given or a key path spend otherwise. The signature hash commits to the previous
+/// outputs of all the inputs, unless the signature only commits to this input. Returns `None` if
+/// the previous outputs it commits to aren't all known.
+pub(crate) fn taproot_sighash(
+    sighash_cache: &mut bitcoin::sighash::SighashCache<&bitcoin::Transaction>,
+    input_index: usize,
+    prevouts: Option<&[&bitcoin::TxOut]>,
+    utxo: &bitcoin::TxOut,
+    sighash_type: bitcoin::TapSighashType,
+    leaf_hash: Option<bitcoin::TapLeafHash>,
+) -> Option<secp256k1::Message> {
+    let prevouts = match sighash_type {
+        bitcoin::TapSighashType::AllPlusAnyoneCanPay
+        | bitcoin::TapSighashType::NonePlusAnyoneCanPay
+        | bitcoin::TapSighashType::SinglePlusAnyoneCanPay => {
+            bitcoin::sighash::Prevouts::One(input_index, utxo)
+        }
+        _ => bitcoin::sighash::Prevouts::All(prevouts?),
+    };
+    match leaf_hash {
+        Some(leaf_hash) => sighash_cache.taproot_script_spend_signature_hash(
+            input_index,
+            &prevouts,
+            leaf_hash,
+            sighash_type,
+        ),
+        None => {
+            sighash_cache.taproot_key_spend_signature_hash(input_index, &prevouts, sighash_type)
+        }
+    }
+    .ok()
+    .map(secp256k1::Message::from)
+}
+
 impl SinglePathLianaDesc {
     /// Derive this descriptor at a given index for a receiving address.
     ///
@@ -745,21 +1086,146 @@ mod tests {
     use super::*;
 
     use bitcoin::{hashes::Hash, Sequence};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    // Get a key distinct from all the ones previously returned. It does not depend on the hot
+    // signer so the descriptor tests can run without the "signer" feature.
+    fn random_xpriv() -> bip32::Xpriv {
+        static KEY_COUNTER: AtomicU32 = AtomicU32::new(0);
+        let mut seed = [0; 32];
+        seed[..4].copy_from_slice(&KEY_COUNTER.fetch_add(1, Ordering::Relaxed).to_be_bytes());
+        bip32::Xpriv::new_master(bitcoin::Network::Bitcoin, &seed).unwrap()
+    }
 
-    use crate::signer::HotSigner;
-
-    fn random_desc_key(
+    fn desc_key(
         secp: &secp256k1::Secp256k1<impl secp256k1::Signing>,
+        xpriv: &bip32::Xpriv,
     ) -> descriptor::DescriptorPublicKey {
-        let signer = HotSigner::generate(bitcoin::Network::Bitcoin).unwrap();
         let xpub_str = format!(
             "[{}]{}/<0;1>/*",
-            signer.fingerprint(secp),
-            signer.xpub_at(&bip32::DerivationPath::from_str("m").unwrap(), secp)
+            xpriv.fingerprint(secp),
+            bip32::Xpub::from_priv(secp, xpriv)
         );
         descriptor::DescriptorPublicKey::from_str(&xpub_str).unwrap()
     }
 
+    fn random_desc_key(
+        secp: &secp256k1::Secp256k1<impl secp256k1::Signing>,
+    ) -> descriptor::DescriptorPublicKey {
+        desc_key(secp, &random_xpriv())
+    }
+
+    // Sign this P2WSH PSBT input with the key derived from this xpriv at this path.
+    fn sign_p2wsh_input(
+        secp: &secp256k1::Secp256k1<secp256k1::All>,
+        psbt: &mut Psbt,
+        index: usize,
+        xpriv: &bip32::Xpriv,
+        der_path: &bip32::DerivationPath,
+    ) {
+        let privkey = xpriv.derive_priv(secp, der_path).unwrap().to_priv();
+        let pubkey = privkey.public_key(secp);
+        let value = psbt.inputs[index].witness_utxo.as_ref().unwrap().value;
+        let witness_script = psbt.inputs[index].witness_script.clone().unwrap();
+        let sighash = bitcoin::sighash::SighashCache::new(&psbt.unsigned_tx)
+            .p2wsh_signature_hash(
+                index,
+                &witness_script,
+                value,
+                bitcoin::EcdsaSighashType::All,
+            )
+            .unwrap();
+        let sig = secp.sign_ecdsa_low_r(&sighash.into(), &privkey.inner);
+        psbt.inputs[index]
+            .partial_sigs
+            .insert(pubkey, bitcoin::ecdsa::Signature::sighash_all(sig));
+        psbt.inputs[index]
+            .bip32_derivation
+            .insert(pubkey.inner, (xpriv.fingerprint(secp), der_path.clone()));
+    }
+
+    // Sign this Taproot PSBT input with the key derived from this xpriv at this path, for a spend
+    // of the given leaf or through the key path if none is given.
+    fn sign_taproot_input(
+        secp: &secp256k1::Secp256k1<secp256k1::All>,
+        psbt: &mut Psbt,
+        index: usize,
+        xpriv: &bip32::Xpriv,
+        der_path: &bip32::DerivationPath,
+        leaf_hash: Option<bitcoin::TapLeafHash>,
+    ) {
+        use bitcoin::key::TapTweak;
+
+        let keypair = xpriv.derive_priv(secp, der_path).unwrap().to_keypair(secp);
+        let (pubkey, _) = keypair.x_only_public_key();
+        let prevouts: Vec<&bitcoin::TxOut> = psbt
+            .inputs
+            .iter()
+            .map(|psbt_in| psbt_in.witness_utxo.as_ref().unwrap())
+            .collect();
+        let msg = taproot_sighash(
+            &mut bitcoin::sighash::SighashCache::new(&psbt.unsigned_tx),
+            index,
+            Some(&prevouts),
+            prevouts[index],
+            bitcoin::TapSighashType::Default,
+            leaf_hash,
+        )
+        .unwrap();
+        let psbt_in = &mut psbt.inputs[index];
+        let origin = (xpriv.fingerprint(secp), der_path.clone());
+        if let Some(leaf_hash) = leaf_hash {
+            let sig = secp.sign_schnorr_no_aux_rand(&msg, &keypair);
+            psbt_in.tap_script_sigs.insert(
+                (pubkey, leaf_hash),
+                bitcoin::taproot::Signature {
+                    signature: sig,
+                    sighash_type: bitcoin::TapSighashType::Default,
+                },
+            );
+            psbt_in
+                .tap_key_origins
+                .insert(pubkey, (vec![leaf_hash], origin));
+        } else {
+            let keypair = keypair
+                .tap_tweak(secp, psbt_in.tap_merkle_root)
+                .to_keypair();
+            let sig = secp.sign_schnorr_no_aux_rand(&msg, &keypair);
+            psbt_in.tap_key_sig = Some(bitcoin::taproot::Signature {
+                signature: sig,
+                sighash_type: bitcoin::TapSighashType::Default,
+            });
+            psbt_in.tap_internal_key = Some(pubkey);
+            psbt_in.tap_key_origins.insert(pubkey, (vec![], origin));
+        }
+    }
+
+    // Get the leaf of the tree of scripts of this Taproot descriptor containing the key derived
+    // from this xpriv at this path.
+    fn tap_leaf_hash(
+        secp: &secp256k1::Secp256k1<secp256k1::All>,
+        desc: &LianaDescriptor,
+        xpriv: &bip32::Xpriv,
+        der_path: &bip32::DerivationPath,
+    ) -> bitcoin::TapLeafHash {
+        let origin = (xpriv.fingerprint(secp), der_path.clone());
+        let index = *der_path.as_ref().last().unwrap();
+        let derived_desc = desc.receive_descriptor().derive(index, secp);
+        let tr = match derived_desc.0 {
+            descriptor::Descriptor::Tr(ref tr) => tr,
+            _ => panic!("Not a Taproot descriptor."),
+        };
+        tr.iter_scripts()
+            .find(|(_, ms)| ms.iter_pk().any(|k| k.origin == origin))
+            .map(|(_, ms)| {
+                bitcoin::TapLeafHash::from_script(
+                    &ms.encode(),
+                    bitcoin::taproot::LeafVersion::TapScript,
+                )
+            })
+            .unwrap()
+    }
+
     // Convert a size in weight units to a size in virtual bytes, rounding up.
     fn wu_to_vb(vb: usize) -> usize {
         (vb + WITNESS_SCALE_FACTOR - 1)
@@ -1358,6 +1824,23 @@ mod tests {
     // Make sure the string representation of our descriptors roundtrip. The Taproot ones were
     // generated manually with our code because of the potential need to compute the internal key
     // deterministically.
+    #[test]
+    fn path_info_from_keys() {
+        let secp = secp256k1::Secp256k1::signing_only();
+        let (key_a, key_b) = (random_desc_key(&secp), random_desc_key(&secp));
+
+        PathInfo::from_keys(1, vec![]).unwrap_err();
+        PathInfo::from_keys(2, vec![key_a.clone()]).unwrap_err();
+        assert_eq!(
+            PathInfo::from_keys(1, vec![key_a.clone()]).unwrap(),
+            PathInfo::Single(key_a.clone())
+        );
+        assert_eq!(
+            PathInfo::from_keys(2, vec![key_a.clone(), key_b.clone()]).unwrap(),
+            PathInfo::Multi(2, vec![key_a, key_b])
+        );
+    }
+
     #[test]
     fn roundtrip_descriptor() {
         // A descriptor with single keys in both primary and recovery paths
@@ -1377,13 +1860,205 @@ mod tests {
         roundtrip("tr(xpub661MyMwAqRbcGqmqNapgQ9kqrLcDeZLHPktzsBcZXTtNx7aEay8NKQPizKcpu2fUejNbZzhZQaZLeDWL3nt8zg9QbFLBUTRQu4qqcSzeEmF/<0;1>/*,{{and_v(v:multi_a(1,[b4e32970]xpub661MyMwAqRbcEbs6ohRoUqTckEfLeT3vB2EsuWuckrEuDSKqdFXV6so8xJb4kvA4ZxT6hCydyFKsKwJrDm2LgSfTCphVqZgQbLzF49KwaXc/<0;1>/*,[c318e87f]xpub661MyMwAqRbcG2qnrFJ2MhKFSHehbVkK38gFfG7zXwasN51dKrL4kffj1HRd2zFhAZeQsjYKS8YaiN4sC4gVPHR28qXdQf7pf7nbYoefg6T/<0;1>/*),older(1678)),{and_v(v:pk([6c0d38a3]xpub661MyMwAqRbcF87hAvenL8GHW7qxhtn8Y9zHVkQbuTsd6RVtWkhBY5gh6m4Rua9ENmYDx7jTb8kbiyVB9iaLAbyRudxPFVTFoGPp6rTqoZn/<0;1>/*),older(42)),and_v(v:multi_a(2,[2e1370a6]xpub661MyMwAqRbcGRzCgSNLW7VFUFdwvC1dFXmKgWbZwQERj2QfNQuy5diCQSHNXuQYSS9FwXykLeWKtnZ5yRJ4ZHZzYqWf13FUY4PbDpBhipr/<0;1>/*,[fae2633e]xpub661MyMwAqRbcG9qKwZ7F363Mx3Ai3H2aMXAWTjvYCZrH4wqDEDLnsVghWFrwTKwpDGGzsSDCL7vPTiaiY7DhhdV2bY6RdPNGd7bF9om1MFz/<0;1>/*,[2ae87e33]xpub661MyMwAqRbcGw8ZvGfdLEjhCk4YC9hZrrUceKipiH32ANDMQccYFqq91kH8RpcwGiPnCbUWFo1S6ZGY2GxbVdJFsMYXqzpL1byJ1D3G2Mh/<0;1>/*),older(43))}},multi_a(2,[40f48611]xpub661MyMwAqRbcGUkDb45NBcMYwaaSE3fhsMNwvdf2psYhrqhFmRJY9n8irJuEB3juhK5LQPBiiqdr2gixMmC7Nmtg3Mwu4C5wbeagaAzbb9W/<0;1>/*,[a2bdfbe5]xpub661MyMwAqRbcH228eUBaJvc7Va1y7cGyEH9DZ5vPneKgZDX8eMsSd8PHS3uRYCFySyHPy3VfGfS8vKb5FzcS2MbNorNVv2c3Hn7AvVJJZ73/<0;1>/*,[028ece7a]xpub661MyMwAqRbcG9W1pZzs7rvWVtHeW1anzABj8iQRBbnz8yLf7vgUmYkVsydLf1hLffibgfzUjTBcrNCDKaBNnuqLtsp1xyiLSZJyLDtEjkF/<0;1>/*)})#xgzxdvrv");
     }
 
+    // Policies of various shapes, generated deterministically: for each combination of script
+    // context, primary path and set of recovery paths. A path is a (threshold, number of keys)
+    // pair and the keys of each path are distinct.
+    fn policy_vectors() -> Vec<LianaPolicy> {
+        let secp = secp256k1::Secp256k1::signing_only();
+        let key_counter = AtomicU32::new(0);
+        let path = |(thresh, n): (usize, usize)| {
+            let keys: Vec<_> = (0..n)
+                .map(|_| {
+                    let mut seed = [0xfe; 32];
+                    seed[..4].copy_from_slice(
+                        &key_counter.fetch_add(1, Ordering::Relaxed).to_be_bytes(),
+                    );
+                    let xpriv = bip32::Xpriv::new_master(bitcoin::Network::Bitcoin, &seed).unwrap();
+                    descriptor::DescriptorPublicKey::from_str(&format!(
+                        "[{}]{}/<0;1>/*",
+                        xpriv.fingerprint(&secp),
+                        bip32::Xpub::from_priv(&secp, &xpriv)
+                    ))
+                    .unwrap()
+                })
+                .collect();
+            if n == 1 {
+                PathInfo::Single(keys.into_iter().next().unwrap())
+            } else {
+                PathInfo::Multi(thresh, keys)
+            }
+        };
+        let primary_paths = [(1, 1), (2, 3)];
+        let recovery_paths: [&[(u16, (usize, usize))]; 3] = [
+            &[(1, (1, 1))],
+            &[(65_535, (2, 2))],
+            &[(144, (1, 1)), (52_560, (1, 2))],
+        ];
+        let mut policies = Vec::new();
+        for is_taproot in [false, true] {
+            for prim in primary_paths {
+                for rec in recovery_paths {
+                    let prim = path(prim);
+                    let rec = rec.iter().map(|(tl, p)| (*tl, path(*p))).collect();
+                    policies.push(if is_taproot {
+                        LianaPolicy::new(prim, rec).unwrap()
+                    } else {
+                        LianaPolicy::new_legacy(prim, rec).unwrap()
+                    });
+                }
+            }
+        }
+        policies
+    }
+
+    // Invariants which must hold for any policy: policy -> descriptor -> policy roundtrips, and
+    // so does the string representation of the descriptor.
+    #[test]
+    fn policy_roundtrip() {
+        let secp = secp256k1::Secp256k1::verification_only();
+        for policy in policy_vectors() {
+            let desc = LianaDescriptor::new(policy.clone());
+            assert_eq!(desc.policy(), policy, "{}", desc);
+            let desc_str = desc.to_string();
+            let parsed = LianaDescriptor::from_str(&desc_str).unwrap();
+            assert_eq!(parsed, desc);
+            assert_eq!(parsed.to_string(), desc_str);
+            assert_eq!(parsed.policy(), policy);
+
+            // The receive and change descriptors are distinct and derive distinct addresses.
+            let index = 42.into();
+            let receive = desc.receive_descriptor().derive(index, &secp);
+            let change = desc.change_descriptor().derive(index, &secp);
+            assert_ne!(
+                receive.address(bitcoin::Network::Bitcoin),
+                change.address(bitcoin::Network::Bitcoin)
+            );
+        }
+    }
+
+    // Descriptors which aren't Liana descriptors must be rejected, without panicking.
+    #[test]
+    fn invalid_descriptor_vectors() {
+        let key_a = "[aabbccdd]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*";
+        let key_b = "[aabb0011]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*";
+        let vectors = [
+            String::new(),
+            "wsh(".to_string(),
+            "wsh()".to_string(),
+            // Not a Liana script type.
+            format!("wpkh({})", key_a),
+            format!(
+                "sh(wsh(or_d(pk({}),and_v(v:pkh({}),older(1)))))",
+                key_a, key_b
+            ),
+            // No recovery path.
+            format!("wsh(pk({}))", key_a),
+            format!("wsh(multi(1,{},{}))", key_a, key_b),
+            format!("tr({})", key_a),
+            // A timelock in seconds, and a disabled one.
+            format!(
+                "wsh(or_d(pk({}),and_v(v:pkh({}),older(4194305))))",
+                key_a, key_b
+            ),
+            format!(
+                "wsh(or_d(pk({}),and_v(v:pkh({}),older(2147483648))))",
+                key_a, key_b
+            ),
+            // An absolute timelock.
+            format!(
+                "wsh(or_d(pk({}),and_v(v:pkh({}),after(100))))",
+                key_a, key_b
+            ),
+            // Single-path keys, and keys without a wildcard.
+            format!(
+                "wsh(or_d(pk({}),and_v(v:pkh({}),older(1))))",
+                key_a.replace("<0;1>", "0"),
+                key_b.replace("<0;1>", "0")
+            ),
+            format!(
+                "wsh(or_d(pk({}),and_v(v:pkh({}),older(1))))",
+                key_a.replace("/*", ""),
+                key_b.replace("/*", "")
+            ),
+            // The same key in both paths.
+            format!("wsh(or_d(pk({}),and_v(v:pkh({}),older(1))))", key_a, key_a),
+            // A recovery path without a key.
+            format!("wsh(or_d(pk({}),older(1)))", key_a),
+            // A bad checksum.
+            format!(
+                "wsh(or_d(pk({}),and_v(v:pkh({}),older(1))))#aaaaaaaa",
+                key_a, key_b
+            ),
+        ];
+        for vector in vectors {
+            assert!(LianaDescriptor::from_str(&vector).is_err(), "{}", vector);
+        }
+    }
+
+    #[test]
+    fn too_complex_descriptor() {
+        let key_a = "[aabbccdd]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*";
+        let key_b = "[aabb0011]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*";
+
+        // A very deeply nested string is rejected before being parsed.
+        let deep = format!("wsh({}{})", "and_v(".repeat(100_000), ")".repeat(100_000));
+        assert!(matches!(
+            LianaDescriptor::from_str(&deep),
+            Err(LianaDescError::Policy(LianaPolicyError::TooComplex))
+        ));
+        let deep = format!(
+            "tr({},{}{})",
+            key_a,
+            "{".repeat(100_000),
+            "}".repeat(100_000)
+        );
+        assert!(matches!(
+            LianaDescriptor::from_str(&deep),
+            Err(LianaDescError::Policy(LianaPolicyError::TooComplex))
+        ));
+
+        // A regular descriptor is within the default limits but not within tighter ones.
+        let desc_str = format!("wsh(or_d(pk({}),and_v(v:pkh({}),older(1))))", key_a, key_b);
+        let desc = LianaDescriptor::from_str(&desc_str).unwrap();
+        let limits = AnalysisLimits::default();
+        assert!(
+            LianaPolicy::from_multipath_descriptor_with_limits(&desc.multi_desc, &limits).is_ok()
+        );
+        for limits in [
+            AnalysisLimits {
+                max_depth: 2,
+                ..limits
+            },
+            AnalysisLimits {
+                max_nodes: 5,
+                ..limits
+            },
+        ] {
+            assert!(matches!(
+                LianaPolicy::from_multipath_descriptor_with_limits(&desc.multi_desc, &limits),
+                Err(LianaPolicyError::TooComplex)
+            ));
+        }
+        let desc_str = format!("tr({},and_v(v:pk({}),older(1)))", key_a, key_b);
+        let desc =
+            descriptor::Descriptor::<descriptor::DescriptorPublicKey>::from_str(&desc_str).unwrap();
+        assert!(matches!(
+            AnalysisLimits {
+                max_nodes: 2,
+                ..limits
+            }
+            .check_desc(&desc),
+            Err(LianaPolicyError::TooComplex)
+        ));
+    }
+
     fn psbt_from_str(psbt_str: &str) -> Psbt {
         Psbt::from_str(psbt_str).unwrap()
     }
 
     #[test]
     fn partial_spend_info_p2wsh() {
-        let secp = secp256k1::Secp256k1::signing_only();
+        let secp = secp256k1::Secp256k1::new();
 
         // A simple descriptor with 1 keys as primary path and 1 recovery key.
         let desc = LianaDescriptor::from_str("wsh(or_d(pk([f5acc2fd]tpubD6NzVbkrYhZ4YgUx2ZLNt2rLYAMTdYysCRzKoLu2BeSHKvzqPaBDvf17GeBPnExUVPkuBpx4kniP964e2MxyzzazcXLptxLXModSVCVEV1T/<0;1>/*),and_v(v:pkh([8a64f2a9]tpubD6NzVbkrYhZ4WmzFjvQrp7sDa4ECUxTi9oby8K4FZkd3XCBtEdKwUiQyYJaxiJo5y42gyDWEczrFpozEjeLxMPxjf2WtkfcbpUdfvNnozWF/<0;1>/*),older(10))))#d72le4dr").unwrap();
@@ -1397,7 +2072,9 @@ mod tests {
         // A PSBT with a single input and output, no signature. nSequence is not set to use the
         // recovery path.
         let mut unsigned_single_psbt: Psbt = psbt_from_str("cHNidP8BAHECAAAAAUSHuliRtuCX1S6JxRuDRqDCKkWfKmWL5sV9ukZ/wzvfAAAAAAD9////AogTAAAAAAAAFgAUIxe7UY6LJ6y5mFBoWTOoVispDmdwFwAAAAAAABYAFKqO83TK+t/KdpAt21z2HGC7/Z2FAAAAAAABASsQJwAAAAAAACIAIIIySQjGCTeyx/rKUQx8qobjhJeNCiVCliBJPdyRX6XKAQVBIQI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDKxzZHapFNYASzIYkEdH9bJz6nnqUG3uBB8kiK1asmgiBgI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDAz1rML9AAAAAG8AAAAiBgMLcbOxsfLe6+3r1UcjQo77HY0As8OKE4l37yj0/qhIyQyKZPKpAAAAAG8AAAAAAAA=");
-        let info = desc.partial_spend_info(&unsigned_single_psbt).unwrap();
+        let info = desc
+            .partial_spend_info(&unsigned_single_psbt, &secp)
+            .unwrap();
         assert_eq!(info.primary_path.threshold, 1);
         assert_eq!(info.primary_path.sigs_count, 0);
         assert!(info.primary_path.signed_pubkeys.is_empty());
@@ -1406,36 +2083,39 @@ mod tests {
         // If we set the sequence too low we still won't have the recovery path info.
         unsigned_single_psbt.unsigned_tx.input[0].sequence =
             Sequence::from_height(desc_info.recovery_paths.keys().next().unwrap() - 1);
-        let info = desc.partial_spend_info(&unsigned_single_psbt).unwrap();
+        let info = desc
+            .partial_spend_info(&unsigned_single_psbt, &secp)
+            .unwrap();
         assert!(info.recovery_paths.is_empty());
 
         // Now if we set the sequence at the right value we'll have it.
         let timelock = *desc_info.recovery_paths.keys().next().unwrap();
         unsigned_single_psbt.unsigned_tx.input[0].sequence = Sequence::from_height(timelock);
-        let info = desc.partial_spend_info(&unsigned_single_psbt).unwrap();
+        let info = desc
+            .partial_spend_info(&unsigned_single_psbt, &secp)
+            .unwrap();
         assert!(info.recovery_paths.contains_key(&timelock));
 
         // Even if it's a bit too high (as long as it's still a block height and activated)
         unsigned_single_psbt.unsigned_tx.input[0].sequence = Sequence::from_height(timelock + 42);
-        let info = desc.partial_spend_info(&unsigned_single_psbt).unwrap();
+        let info = desc
+            .partial_spend_info(&unsigned_single_psbt, &secp)
+            .unwrap();
         let recov_info = info.recovery_paths.get(&timelock).unwrap();
         assert_eq!(recov_info.threshold, 1);
         assert_eq!(recov_info.sigs_count, 0);
         assert!(recov_info.signed_pubkeys.is_empty());
 
-        // The same PSBT but with an (invalid) signature for the primary key.
+        // The same PSBT but with an (invalid) signature for the primary key. It isn't accounted for.
         let mut signed_single_psbt = psbt_from_str("cHNidP8BAHECAAAAAUSHuliRtuCX1S6JxRuDRqDCKkWfKmWL5sV9ukZ/wzvfAAAAAAD9////AogTAAAAAAAAFgAUIxe7UY6LJ6y5mFBoWTOoVispDmdwFwAAAAAAABYAFKqO83TK+t/KdpAt21z2HGC7/Z2FAAAAAAABASsQJwAAAAAAACIAIIIySQjGCTeyx/rKUQx8qobjhJeNCiVCliBJPdyRX6XKIgICNnKlqXPVAFtoGbdlpCo74vCqDArotNKUZS+sBt9rigxIMEUCIQCYZusUL8bdi2PnjWao4bIDDgMQ9Dj2Lcup3/VmkGbYJAIgX/wF5HsqugC5JzvU2cGOmUWtHr2Pg0N4912qogYgDH4BAQVBIQI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDKxzZHapFNYASzIYkEdH9bJz6nnqUG3uBB8kiK1asmgiBgI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDAz1rML9AAAAAG8AAAAiBgMLcbOxsfLe6+3r1UcjQo77HY0As8OKE4l37yj0/qhIyQyKZPKpAAAAAG8AAAAAAAA=");
-        let info = desc.partial_spend_info(&signed_single_psbt).unwrap();
+        let info = desc.partial_spend_info(&signed_single_psbt, &secp).unwrap();
         assert_eq!(signed_single_psbt.inputs[0].partial_sigs.len(), 1);
         assert_eq!(info.primary_path.threshold, 1);
-        assert_eq!(info.primary_path.sigs_count, 1);
-        assert!(
-            info.primary_path.signed_pubkeys.len() == 1
-                && info.primary_path.signed_pubkeys.contains_key(&prim_key_fg)
-        );
+        assert_eq!(info.primary_path.sigs_count, 0);
+        assert!(info.primary_path.signed_pubkeys.is_empty());
         assert!(info.recovery_paths.is_empty());
 
-        // Now enable the recovery path and add a signature for the recovery key.
+        // Now enable the recovery path and add an (invalid) signature for the recovery key.
         signed_single_psbt.unsigned_tx.input[0].sequence = Sequence::from_height(timelock);
         let recov_pubkey = bitcoin::PublicKey {
             compressed: true,
@@ -1462,22 +2142,19 @@ mod tests {
         signed_single_psbt.inputs[0]
             .partial_sigs
             .insert(recov_pubkey, sig);
-        let info = desc.partial_spend_info(&signed_single_psbt).unwrap();
+        let info = desc.partial_spend_info(&signed_single_psbt, &secp).unwrap();
         assert_eq!(signed_single_psbt.inputs[0].partial_sigs.len(), 1);
         assert_eq!(info.primary_path.threshold, 1);
         assert_eq!(info.primary_path.sigs_count, 0);
         assert!(info.primary_path.signed_pubkeys.is_empty());
         let recov_info = info.recovery_paths.get(&timelock).unwrap();
         assert_eq!(recov_info.threshold, 1);
-        assert_eq!(recov_info.sigs_count, 1);
-        assert!(
-            recov_info.signed_pubkeys.len() == 1
-                && recov_info.signed_pubkeys.contains_key(&recov_key_origin.0)
-        );
+        assert_eq!(recov_info.sigs_count, 0);
+        assert!(recov_info.signed_pubkeys.is_empty());
 
         // A PSBT with multiple inputs, all signed for the primary path.
         let psbt: Psbt = psbt_from_str("cHNidP8BAP0fAQIAAAAGAGo6V8K5MtKcQ8vRFedf5oJiOREiH4JJcEniyRv2800BAAAAAP3///9e3dVLjWKPAGwDeuUOmKFzOYEP5Ipu4LWdOPA+lITrRgAAAAAA/f///7cl9oeu9ssBXKnkWMCUnlgZPXhb+qQO2+OPeLEsbdGkAQAAAAD9////idkxRErbs34vsHUZ7QCYaiVaAFDV9gxNvvtwQLozwHsAAAAAAP3///9EakyJhd2PjwYh1I7zT2cmcTFI5g1nBd3srLeL7wKEewIAAAAA/f///7BcaP77nMaA2NjT/hyI6zueB/2jU/jK4oxmSqMaFkAzAQAAAAD9////AUAfAAAAAAAAFgAUqo7zdMr638p2kC3bXPYcYLv9nYUAAAAAAAEA/X4BAgAAAAABApEoe5xCmSi8hNTtIFwsy46aj3hlcLrtFrug39v5wy+EAQAAAGpHMEQCIDeI8JTWCTyX6opCCJBhWc4FytH8g6fxDaH+Wa/QqUoMAiAgbITpz8TBhwxhv/W4xEXzehZpOjOTjKnPw36GIy6SHAEhA6QnYCHUbU045FVh6ZwRwYTVineqRrB9tbqagxjaaBKh/v///+v1seDE9gGsZiWwewQs3TKuh0KSBIHiEtG8ABbz2DpAAQAAAAD+////Aqhaex4AAAAAFgAUkcVOEjVMct0jyCzhZN6zBT+lvTQvIAAAAAAAACIAIKKDUd/GWjAnwU99llS9TAK2dK80/nSRNLjmrhj0odUEAAJHMEQCICSn+boh4ItAa3/b4gRUpdfblKdcWtMLKZrgSEFFrC+zAiBtXCx/Dq0NutLSu1qmzFF1lpwSCB3w3MAxp5W90z7b/QEhA51S2ERUi0bg+l+bnJMJeAfDknaetMTagfQR9+AOrVKlxdMkAAEBKy8gAAAAAAAAIgAgooNR38ZaMCfBT32WVL1MArZ0rzT+dJE0uOauGPSh1QQiAgN+zbSfdr8oJBtlKomnQTHynF2b/UhovAwf0eS8awRSqUgwRQIhAJhm6xQvxt2LY+eNZqjhsgMOAxD0OPYty6nf9WaQZtgkAiBf/AXkeyq6ALknO9TZwY6ZRa0evY+DQ3j3XaqiBiAMfgEBBUEhA37NtJ92vygkG2UqiadBMfKcXZv9SGi8DB/R5LxrBFKprHNkdqkUxttmGj2sqzzaxSaacJTnJPDCbY6IrVqyaCIGAv9qeBDEB+5kvM/sZ8jQ7QApfZcDrqtq5OAe2gQ1V+pmDIpk8qkAAAAA0AAAACIGA37NtJ92vygkG2UqiadBMfKcXZv9SGi8DB/R5LxrBFKpDPWswv0AAAAA0AAAAAABAOoCAAAAAAEB0OPoVJs9ihvnAwjO16k/wGJuEus1IEE1Yo2KBjC2NSEAAAAAAP7///8C6AMAAAAAAAAiACBfeUS9jQv6O1a96Aw/mPV6gHxHl3mfj+f0frfAs2sMpP1QGgAAAAAAFgAUDS4UAIpdm1RlFYmg0OoCxW0yBT4CRzBEAiAPvbNlnhiUxLNshxN83AuK/lGWwlpXOvmcqoxsMLzIKwIgWwATJuYPf9buLe9z5SnXVnPVL0q6UZaWE5mjCvEl1RUBIQI54LFZmq9Lw0pxKpEGeqI74NnIfQmLMDcv5ySplUS1/wDMJAABASvoAwAAAAAAACIAIF95RL2NC/o7Vr3oDD+Y9XqAfEeXeZ+P5/R+t8CzawykIgICYn4eZbb6KGoxB1PEv/XPiujZFDhfoi/rJPtfHPVML2lHMEQCIDOHEqKdBozXIPLVgtBj3eWC1MeIxcKYDADe4zw0DbcMAiAq4+dbkTNCAjyCxJi0TKz5DWrPulxrqOdjMRHWngXHsQEBBUEhAmJ+HmW2+ihqMQdTxL/1z4ro2RQ4X6Iv6yT7Xxz1TC9prHNkdqkUzc/gCLoe6rQw63CGXhIR3YRz1qCIrVqyaCIGAmJ+HmW2+ihqMQdTxL/1z4ro2RQ4X6Iv6yT7Xxz1TC9pDPWswv0AAAAAqgAAACIGA8JCTIzdSoTJhiKN1pn+NnlkyuKOndiTgH2NIX+yNsYqDIpk8qkAAAAAqgAAAAABAOoCAAAAAAEBRGpMiYXdj48GIdSO809nJnExSOYNZwXd7Ky3i+8ChHsAAAAAAP7///8COMMQAAAAAAAWABQ5rnyuG5T8iuhqfaGAmpzlybo3t+gDAAAAAAAAIgAg7Kz3CX1RBjIvbK9LBYztmi7F1XIxQpX6mtCUkflvvl8CRzBEAiBaYx4sOHckEZwDnSrbb1ivc6seX4Puasm1PBGnBWgSTQIgCeUiXvd90ajI3F4/BHifLUI4fVIgVQFCqLTbbeXQD5oBIQOmGm+gTRx1slzF+wn8NhZoR1xfSYgoKX6bpRSVRjLcEXrOJAABASvoAwAAAAAAACIAIOys9wl9UQYyL2yvSwWM7ZouxdVyMUKV+prQlJH5b75fIgID0X2UJhC5+2jgJqUrihxZxDZHK7jgPFlrUYzoSHQTmP9HMEQCIEM4K8lVACvE2oSMZHDJiOeD81qsYgAvgpRgcSYgKc3AAiAQjdDr2COBea69W+2iVbnODuH3QwacgShW3dS4yeggJAEBBUEhA9F9lCYQufto4CalK4ocWcQ2Ryu44DxZa1GM6Eh0E5j/rHNkdqkU0DTexcgOQQ+BFjgS031OTxcWiH2IrVqyaCIGA9F9lCYQufto4CalK4ocWcQ2Ryu44DxZa1GM6Eh0E5j/DPWswv0AAAAAvwAAACIGA/xg4Uvem3JHVPpyTLP5JWiUH/yk3Y/uUI6JkZasCmHhDIpk8qkAAAAAvwAAAAABAOoCAAAAAAEBmG+mPq0O6QSWEMctsMjvv5LzWHGoT8wsA9Oa05kxIxsBAAAAAP7///8C6AMAAAAAAAAiACDUvIILFr0OxybADV3fB7ms7+ufnFZgicHR0nbI+LFCw1UoGwAAAAAAFgAUC+1ZjCC1lmMcvJ/4JkevqoZF4igCRzBEAiA3d8o96CNgNWHUkaINWHTvAUinjUINvXq0KBeWcsSWuwIgKfzRNWFR2LDbnB/fMBsBY/ylVXcSYwLs8YC+kmko1zIBIQOpEfsLv0htuertA1sgzCwGvHB0vE4zFO69wWEoHClKmAfMJAABASvoAwAAAAAAACIAINS8ggsWvQ7HJsANXd8Huazv65+cVmCJwdHSdsj4sULDIgID96jZc0sCi0IIXf2CpfE7tY+9LRmMsOdSTTHelFxfCwJHMEQCIHlaiMMznx8Cag8Y3X2gXi9Qtg0ZuyHEC6DsOzipSGOKAiAV2eC+S3Mbq6ig5QtRvTBsq5M3hCBdEJQlOrLVhWWt6AEBBUEhA/eo2XNLAotCCF39gqXxO7WPvS0ZjLDnUk0x3pRcXwsCrHNkdqkUyJ+Cbx7vYVY665yjJnMNODyYrAuIrVqyaCIGAt8UyDXk+mW3Y6IZNIBuDJHkdOaZi/UEShkN5L3GiHR5DIpk8qkAAAAAuAAAACIGA/eo2XNLAotCCF39gqXxO7WPvS0ZjLDnUk0x3pRcXwsCDPWswv0AAAAAuAAAAAABAP0JAQIAAAAAAQG7Zoy4I3J9x+OybAlIhxVKcYRuPFrkDFJfxMiC3kIqIAEAAAAA/v///wO5xxAAAAAAABYAFHgBzs9wJNVk6YwR81IMKmckTmC56AMAAAAAAAAWABTQ/LmJix5JoHBOr8LcgEChXHdLROgDAAAAAAAAIgAg7Kz3CX1RBjIvbK9LBYztmi7F1XIxQpX6mtCUkflvvl8CRzBEAiA+sIKnWVE3SmngjUgJdu1K2teW6eqeolfGe0d11b+irAIgL20zSabXaFRNM8dqVlcFsfNJ0exukzvxEOKl/OcF8VsBIQJrUspHq45AMSwbm24//2a9JM8XHFWbOKpyV+gNCtW71nrOJAABASvoAwAAAAAAACIAIOys9wl9UQYyL2yvSwWM7ZouxdVyMUKV+prQlJH5b75fIgID0X2UJhC5+2jgJqUrihxZxDZHK7jgPFlrUYzoSHQTmP9IMEUCIQCmDhJ9fyhlQwPruoOUemDuldtRu3ZkiTM3DA0OhkguSQIgYerNaYdP43DcqI5tnnL3n4jEeMHFCs+TBkOd6hDnqAkBAQVBIQPRfZQmELn7aOAmpSuKHFnENkcruOA8WWtRjOhIdBOY/6xzZHapFNA03sXIDkEPgRY4EtN9Tk8XFoh9iK1asmgiBgPRfZQmELn7aOAmpSuKHFnENkcruOA8WWtRjOhIdBOY/wz1rML9AAAAAL8AAAAiBgP8YOFL3ptyR1T6ckyz+SVolB/8pN2P7lCOiZGWrAph4QyKZPKpAAAAAL8AAAAAAQDqAgAAAAABAT6/vc6qBRzhQyjVtkC25NS2BvGyl2XjjEsw3e8vAesjAAAAAAD+////AgPBAO4HAAAAFgAUEwiWd/qI1ergMUw0F1+qLys5G/foAwAAAAAAACIAIOOPEiwmp2ZXR7ciyrveITXw0tn6zbQUA1Eikd9QlHRhAkcwRAIgJMZdO5A5u2UIMrAOgrR4NcxfNgZI6OfY7GKlZP0O8yUCIDFujbBRnamLEbf0887qidnXo6UgQA9IwTx6Zomd4RvJASEDoNmR2/XcqSyCWrE1tjGJ1oLWlKt4zsFekK9oyB4Hl0HF0yQAAQEr6AMAAAAAAAAiACDjjxIsJqdmV0e3Isq73iE18NLZ+s20FANRIpHfUJR0YSICAo3uyJxKHR9Z8fwvU7cywQCnZyPvtMl3nv54wPW1GSGqSDBFAiEAlLY98zqEL/xTUvm9ZKy5kBa4UWfr4Ryu6BmSZjseXPQCIGy7efKbZLQSDq8RhgNNjl1384gWFTN7nPwWV//SGriyAQEFQSECje7InEodH1nx/C9TtzLBAKdnI++0yXee/njA9bUZIaqsc2R2qRQhPRlaLsh/M/K/9fvbjxF/M20cNoitWrJoIgYCF7Rj5jFhe5L6VDzP5m2BeaG0mA9e7+6fMeWkWxLwpbAMimTyqQAAAADNAAAAIgYCje7InEodH1nx/C9TtzLBAKdnI++0yXee/njA9bUZIaoM9azC/QAAAADNAAAAAAA=");
-        let info = desc.partial_spend_info(&psbt).unwrap();
+        let info = desc.partial_spend_info(&psbt, &secp).unwrap();
         assert!(psbt
             .inputs
             .iter()
@@ -1490,22 +2167,20 @@ mod tests {
         );
         assert!(info.recovery_paths.is_empty());
 
-        // Enable the recovery path, it should show no recovery sig.
+        // Enable the recovery path, it should show no recovery sig. The signatures commit to the
+        // nSequence of the inputs so they aren't valid anymore.
         let mut rec_psbt = psbt.clone();
         for txin in rec_psbt.unsigned_tx.input.iter_mut() {
             txin.sequence = Sequence::from_height(timelock);
         }
-        let info = desc.partial_spend_info(&rec_psbt).unwrap();
+        let info = desc.partial_spend_info(&rec_psbt, &secp).unwrap();
         assert!(rec_psbt
             .inputs
             .iter()
             .all(|psbt_in| psbt_in.partial_sigs.len() == 1));
         assert_eq!(info.primary_path.threshold, 1);
-        assert_eq!(info.primary_path.sigs_count, 1);
-        assert!(
-            info.primary_path.signed_pubkeys.len() == 1
-                && info.primary_path.signed_pubkeys.contains_key(&prim_key_fg)
-        );
+        assert_eq!(info.primary_path.sigs_count, 0);
+        assert!(info.primary_path.signed_pubkeys.is_empty());
         let recov_info = info.recovery_paths.get(&timelock).unwrap();
         assert_eq!(recov_info.threshold, 1);
         assert_eq!(recov_info.sigs_count, 0);
@@ -1516,7 +2191,7 @@ mod tests {
         let mut inconsistent_psbt = psbt.clone();
         inconsistent_psbt.unsigned_tx.input[0].sequence = Sequence::from_height(timelock + 1);
         assert!(desc
-            .partial_spend_info(&inconsistent_psbt)
+            .partial_spend_info(&inconsistent_psbt, &secp)
             .unwrap_err()
             .to_string()
             .contains("Analyzed PSBT is inconsistent across inputs."));
@@ -1525,14 +2200,14 @@ mod tests {
         let mut inconsistent_psbt = psbt.clone();
         inconsistent_psbt.inputs[0].partial_sigs.clear();
         assert!(desc
-            .partial_spend_info(&inconsistent_psbt)
+            .partial_spend_info(&inconsistent_psbt, &secp)
             .unwrap_err()
             .to_string()
             .contains("Analyzed PSBT is inconsistent across inputs."));
 
         // If we analyze a descriptor with a multisig we'll get the right threshold.
         let desc = LianaDescriptor::from_str("wsh(or_d(multi(2,[f5acc2fd]tpubD6NzVbkrYhZ4YgUx2ZLNt2rLYAMTdYysCRzKoLu2BeSHKvzqPaBDvf17GeBPnExUVPkuBpx4kniP964e2MxyzzazcXLptxLXModSVCVEV1T/<0;1>/*,[00112233]xpub6FC8vmQGGfSuQGfKG5L73fZ7WjXit8TzfJYDKwTtHkhrbAhU5Kma41oenVq6aMnpgULJRXpQuxnVysyfdpRhVgD6vYe7XLbFDhmvYmDrAVq/<0;1>/*,[aabbccdd]xpub68XtbpvDM19d39wEKdvadHkZ4FGKf4tnryKzAacttp8BLX3uHj7eK8shRnFBhZ2UL83S9dwXe42Qm6eG6BkR1jy8XwUSNBcHKtET7j4V5FB/<0;1>/*),and_v(v:pkh([8a64f2a9]tpubD6NzVbkrYhZ4WmzFjvQrp7sDa4ECUxTi9oby8K4FZkd3XCBtEdKwUiQyYJaxiJo5y42gyDWEczrFpozEjeLxMPxjf2WtkfcbpUdfvNnozWF/<0;1>/*),older(10))))#2kgxuax5").unwrap();
-        let info = desc.partial_spend_info(&psbt).unwrap();
+        let info = desc.partial_spend_info(&psbt, &secp).unwrap();
         assert!(psbt
             .inputs
             .iter()
@@ -1562,17 +2237,29 @@ mod tests {
             ],
         ))].iter().cloned().collect());
         let mut psbt = psbt_from_str("cHNidP8BAIkCAAAAAWi3OFgkj1CqCDT3Swm8kbxZS9lxz4L3i4W2v9KGC7nqAQAAAAD9////AkANAwAAAAAAIgAg27lNc1rog+dOq80ohRuds4Hgg/RcpxVun2XwgpuLSrFYMwwAAAAAACIAIDyWveqaElWmFGkTbFojg1zXWHODtiipSNjfgi2DqBy9AAAAAAABAOoCAAAAAAEBsRWl70USoAFFozxc86pC7Dovttdg4kvja//3WMEJskEBAAAAAP7///8CWKmCIk4GAAAWABRKBWYWkCNS46jgF0r69Ehdnq+7T0BCDwAAAAAAIgAgTt5fs+CiB+FRzNC8lHcgWLH205sNjz1pT59ghXlG5tQCRzBEAiBXK9MF8z3bX/VnY2aefgBBmiAHPL4tyDbUOe7+KpYA4AIgL5kU0DFG8szKd+szRzz/OTUWJ0tZqij41h2eU9rSe1IBIQNBB1hy+jKsg1TihMT0dXw7etpu9TkO3NuvhBDFJlBj1cP2AQABAStAQg8AAAAAACIAIE7eX7PgogfhUczQvJR3IFix9tObDY89aU+fYIV5RubUIgICSKJsNs0zFJN58yd2aYQ+C3vhMbi0x7k0FV3wBhR4THlIMEUCIQCPWWWOhs2lThxOq/G8X2fYBRvM9MXSm7qPH+dRVYQZEwIgfut2vx3RvwZWcgEj4ohQJD5lNJlwOkA4PAiN1fjx6dABIgID3mvj1zerZKohOVhKCiskYk+3qrCum6PIwDhQ16ePACpHMEQCICZNR+0/1hPkrDQwPFmg5VjUHkh6aK9cXUu3kPbM8hirAiAyE/5NUXKfmFKij30isuyysJbq8HrURjivd+S9vdRGKQEBBZNSIQJIomw2zTMUk3nzJ3ZphD4Le+ExuLTHuTQVXfAGFHhMeSEC9OfCXl+sJOrxUFLBuMV4ZUlJYjuzNGZSld5ioY14y8FSrnNkUSED3mvj1zerZKohOVhKCiskYk+3qrCum6PIwDhQ16ePACohA+ECH+HlR+8Sf3pumaXH3IwSsoqSLCH7H1THiBP93z3ZUq9SsmgiBgJIomw2zTMUk3nzJ3ZphD4Le+ExuLTHuTQVXfAGFHhMeRxjat8/MAAAgAEAAIAAAACAAgAAgAAAAAABAAAAIgYC9OfCXl+sJOrxUFLBuMV4ZUlJYjuzNGZSld5ioY14y8Ec/9Y8jTAAAIABAACAAAAAgAIAAIAAAAAAAQAAACIGA95r49c3q2SqITlYSgorJGJPt6qwrpujyMA4UNenjwAqHGNq3z8wAACAAQAAgAEAAIACAACAAAAAAAEAAAAiBgPhAh/h5UfvEn96bpmlx9yMErKKkiwh+x9Ux4gT/d892Rz/1jyNMAAAgAEAAIABAACAAgAAgAAAAAABAAAAACICAlBQ7gGocg7eF3sXrCio+zusAC9+xfoyIV95AeR69DWvHGNq3z8wAACAAQAAgAEAAIACAACAAAAAAAMAAAAiAgMvVy984eg8Kgvj058PBHetFayWbRGb7L0DMnS9KHSJzBxjat8/MAAAgAEAAIAAAACAAgAAgAAAAAADAAAAIgIDSRIG1dn6njdjsDXenHa2lUvQHWGPLKBVrSzbQOhiIxgc/9Y8jTAAAIABAACAAAAAgAIAAIAAAAAAAwAAACICA0/epE59sVEj7Et0I4R9qJQNuX23RNvDZKCRL7eUps9FHP/WPI0wAACAAQAAgAEAAIACAACAAAAAAAMAAAAAIgICgldCOK6iHscv//2NipgaMABLV5TICU/zlP7HlQmlg08cY2rfPzAAAIABAACAAQAAgAIAAIABAAAAAQAAACICApb0p9rfpJshB3J186PGWrvzQdixcwQZWmebOUMdkquZHP/WPI0wAACAAQAAgAAAAIACAACAAQAAAAEAAAAiAgLY5q+unoDxC/HI5BaNiPq12ei1REZIcUAN304JfKXUwxz/1jyNMAAAgAEAAIABAACAAgAAgAEAAAABAAAAIgIDg6cUVCJB79cMcofiURHojxFARWyS4YEhJNRixuOZZRgcY2rfPzAAAIABAACAAAAAgAIAAIABAAAAAQAAAAA=");
-        let partial_info = desc.partial_spend_info(&psbt).unwrap();
+        let partial_info = desc.partial_spend_info(&psbt, &secp).unwrap();
         assert_eq!(partial_info.primary_path.threshold, 2);
         assert_eq!(partial_info.primary_path.sigs_count, 1);
         assert_eq!(partial_info.primary_path.signed_pubkeys.len(), 1);
         assert!(partial_info.recovery_paths.is_empty());
 
         // A not very well thought-out decaying multisig.
-        let prim_path = PathInfo::Multi(3, (0..3).map(|_| random_desc_key(&secp)).collect());
-        let first_reco_path = PathInfo::Multi(3, (0..5).map(|_| random_desc_key(&secp)).collect());
-        let sec_reco_path = PathInfo::Multi(2, (0..5).map(|_| random_desc_key(&secp)).collect());
-        let third_reco_path = PathInfo::Multi(1, (0..5).map(|_| random_desc_key(&secp)).collect());
+        let prim_xpriv = random_xpriv();
+        let first_reco_xpriv = random_xpriv();
+        let sec_reco_xpriv = random_xpriv();
+        let third_reco_xpriv = random_xpriv();
+        let multi = |xpriv: &bip32::Xpriv, thresh: usize, n_keys: usize| {
+            PathInfo::Multi(
+                thresh,
+                std::iter::once(desc_key(&secp, xpriv))
+                    .chain((1..n_keys).map(|_| random_desc_key(&secp)))
+                    .collect(),
+            )
+        };
+        let prim_path = multi(&prim_xpriv, 3, 3);
+        let first_reco_path = multi(&first_reco_xpriv, 3, 5);
+        let sec_reco_path = multi(&sec_reco_xpriv, 2, 5);
+        let third_reco_path = multi(&third_reco_xpriv, 1, 5);
         let liana_policy = LianaPolicy::new_legacy(
             prim_path.clone(),
             [
@@ -1588,7 +2275,7 @@ mod tests {
         let desc = LianaDescriptor::new(liana_policy.clone());
         let policy = desc.policy();
         assert_eq!(policy, liana_policy);
-        let empty_partial_info = desc.partial_spend_info(&psbt).unwrap();
+        let empty_partial_info = desc.partial_spend_info(&psbt, &secp).unwrap();
         assert_eq!(empty_partial_info.primary_path.threshold, 3);
         assert_eq!(empty_partial_info.primary_path.sigs_count, 0);
         assert_eq!(
@@ -1599,18 +2286,9 @@ mod tests {
 
         // Now set a signature for the primary path. All recovery paths still empty, a signature is
         // present for the primary path.
-        let dummy_pubkey = bitcoin::PublicKey::from_str(
-            "0282574238aea21ec72ffffd8d8a981a30004b5794c8094ff394fec79509a5834f",
-        )
-        .unwrap();
-        let dummy_sig = bitcoin::ecdsa::Signature::from_str("30440220264d47ed3fd613e4ac34303c59a0e558d41e487a68af5c5d4bb790f6ccf218ab02203213fe4d51729f9852a28f7d22b2ecb2b096eaf07ad44638af77e4bdbdd4462901").unwrap();
-        let dummy_der_path = bip32::DerivationPath::from_str("m/0/1").unwrap();
-        let fingerprint = prim_path.thresh_origins().1.into_iter().next().unwrap().0;
-        psbt.inputs[0]
-            .bip32_derivation
-            .insert(dummy_pubkey.inner, (fingerprint, dummy_der_path));
-        psbt.inputs[0].partial_sigs.insert(dummy_pubkey, dummy_sig);
-        let partial_info = desc.partial_spend_info(&psbt).unwrap();
+        let der_path = bip32::DerivationPath::from_str("m/0/1").unwrap();
+        sign_p2wsh_input(&secp, &mut psbt, 0, &prim_xpriv, &der_path);
+        let partial_info = desc.partial_spend_info(&psbt, &secp).unwrap();
         assert_eq!(partial_info.primary_path.threshold, 3);
         assert_eq!(partial_info.primary_path.sigs_count, 1);
         assert_eq!(
@@ -1619,20 +2297,50 @@ mod tests {
         );
         assert!(partial_info.recovery_paths.is_empty());
 
-        // Now enable the first recovery path and make the signature be for this path.
-        let fingerprint = first_reco_path
-            .thresh_origins()
-            .1
-            .into_iter()
-            .next()
+        // The same signature set for another key of the same signer isn't accounted for.
+        let prim_pubkey = prim_xpriv
+            .derive_priv(&secp, &der_path)
             .unwrap()
-            .0;
-        psbt.inputs[0]
-            .bip32_derivation
-            .get_mut(&dummy_pubkey.inner)
+            .to_priv()
+            .public_key(&secp);
+        let other_der_path = bip32::DerivationPath::from_str("m/0/2").unwrap();
+        let other_pubkey = prim_xpriv
+            .derive_priv(&secp, &other_der_path)
             .unwrap()
-            .0 = fingerprint;
-        let partial_info = desc.partial_spend_info(&psbt).unwrap();
+            .to_priv()
+            .public_key(&secp);
+        let mut copied_sig_psbt = psbt.clone();
+        let sig = copied_sig_psbt.inputs[0]
+            .partial_sigs
+            .remove(&prim_pubkey)
+            .unwrap();
+        copied_sig_psbt.inputs[0]
+            .partial_sigs
+            .insert(other_pubkey, sig);
+        copied_sig_psbt.inputs[0].bip32_derivation.insert(
+            other_pubkey.inner,
+            (prim_xpriv.fingerprint(&secp), other_der_path),
+        );
+        let partial_info = desc.partial_spend_info(&copied_sig_psbt, &secp).unwrap();
+        assert_eq!(partial_info.primary_path.sigs_count, 0);
+
+        // A signature for a witness script which isn't the one of the spent coin isn't accounted
+        // for, even if the script is one of the descriptor.
+        let mut wrong_script_psbt = psbt.clone();
+        wrong_script_psbt.inputs[0].partial_sigs.clear();
+        wrong_script_psbt.inputs[0].witness_script = Some(
+            desc.receive_desc
+                .derive(bip32::ChildNumber::from_normal_idx(1).unwrap(), &secp)
+                .witness_script(),
+        );
+        sign_p2wsh_input(&secp, &mut wrong_script_psbt, 0, &prim_xpriv, &der_path);
+        let partial_info = desc.partial_spend_info(&wrong_script_psbt, &secp).unwrap();
+        assert_eq!(partial_info.primary_path.sigs_count, 0);
+
+        // Now enable the first recovery path and make the signature be for this path.
+        psbt.inputs[0].partial_sigs.clear();
+        sign_p2wsh_input(&secp, &mut psbt, 0, &first_reco_xpriv, &der_path);
+        let partial_info = desc.partial_spend_info(&psbt, &secp).unwrap();
         assert_eq!(partial_info.primary_path.threshold, 3);
         assert_eq!(partial_info.primary_path.sigs_count, 0);
         assert_eq!(
@@ -1641,7 +2349,9 @@ mod tests {
         );
         assert!(partial_info.recovery_paths.is_empty());
         psbt.unsigned_tx.input[0].sequence = bitcoin::Sequence::from_height(26784);
-        let partial_info = desc.partial_spend_info(&psbt).unwrap();
+        psbt.inputs[0].partial_sigs.clear();
+        sign_p2wsh_input(&secp, &mut psbt, 0, &first_reco_xpriv, &der_path);
+        let partial_info = desc.partial_spend_info(&psbt, &secp).unwrap();
         assert_eq!(partial_info.recovery_paths.len(), 1);
         assert_eq!(partial_info.recovery_paths[&26784].threshold, 3);
         assert_eq!(partial_info.recovery_paths[&26784].sigs_count, 1);
@@ -1651,20 +2361,10 @@ mod tests {
         );
 
         // Now enable the second recovery path and make the signature be for this path.
-        let fingerprint = sec_reco_path
-            .thresh_origins()
-            .1
-            .into_iter()
-            .next()
-            .unwrap()
-            .0;
-        psbt.inputs[0]
-            .bip32_derivation
-            .get_mut(&dummy_pubkey.inner)
-            .unwrap()
-            .0 = fingerprint;
         psbt.unsigned_tx.input[0].sequence = bitcoin::Sequence::from_height(53568);
-        let partial_info = desc.partial_spend_info(&psbt).unwrap();
+        psbt.inputs[0].partial_sigs.clear();
+        sign_p2wsh_input(&secp, &mut psbt, 0, &sec_reco_xpriv, &der_path);
+        let partial_info = desc.partial_spend_info(&psbt, &secp).unwrap();
         assert_eq!(partial_info.primary_path.threshold, 3);
         assert_eq!(partial_info.primary_path.sigs_count, 0);
         assert_eq!(
@@ -1681,20 +2381,10 @@ mod tests {
         }
 
         // Finally do the same for the third recovery path.
-        let fingerprint = third_reco_path
-            .thresh_origins()
-            .1
-            .into_iter()
-            .next()
-            .unwrap()
-            .0;
-        psbt.inputs[0]
-            .bip32_derivation
-            .get_mut(&dummy_pubkey.inner)
-            .unwrap()
-            .0 = fingerprint;
         psbt.unsigned_tx.input[0].sequence = bitcoin::Sequence::from_height(62496);
-        let partial_info = desc.partial_spend_info(&psbt).unwrap();
+        psbt.inputs[0].partial_sigs.clear();
+        sign_p2wsh_input(&secp, &mut psbt, 0, &third_reco_xpriv, &der_path);
+        let partial_info = desc.partial_spend_info(&psbt, &secp).unwrap();
         assert_eq!(partial_info.primary_path.threshold, 3);
         assert_eq!(partial_info.primary_path.sigs_count, 0);
         assert_eq!(
@@ -1716,35 +2406,36 @@ mod tests {
     // The same as above but adapted for Taproot.
     #[test]
     fn partial_spend_info_taproot() {
-        let secp = secp256k1::Secp256k1::signing_only();
-        let dummy_xonly_pubkeys = [
-            bitcoin::XOnlyPublicKey::from_str(
-                "85899827df71b16f2f0eed47cb920e3963f5204e171f5ef7ea4eeec3d5ecc607",
-            )
-            .unwrap(),
-            bitcoin::XOnlyPublicKey::from_str(
-                "0b71b3b1b1f2deebedebd54723428efb1d8d00b3c38a138977ef28f4fea848c9",
-            )
-            .unwrap(),
-        ];
+        let secp = secp256k1::Secp256k1::new();
         let dummy_sig = bitcoin::taproot::Signature::from_slice(&[0; 64]).unwrap();
         let dummy_leafhash = bitcoin::TapLeafHash::from_slice(&[0; 32]).unwrap();
         let dummy_psbt = psbt_from_str("cHNidP8BAHECAAAAAUSHuliRtuCX1S6JxRuDRqDCKkWfKmWL5sV9ukZ/wzvfAAAAAAD9////AogTAAAAAAAAFgAUIxe7UY6LJ6y5mFBoWTOoVispDmdwFwAAAAAAABYAFKqO83TK+t/KdpAt21z2HGC7/Z2FAAAAAAABASsQJwAAAAAAACIAIIIySQjGCTeyx/rKUQx8qobjhJeNCiVCliBJPdyRX6XKAQVBIQI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDKxzZHapFNYASzIYkEdH9bJz6nnqUG3uBB8kiK1asmgAAAA=");
 
         // A simple descriptor with 1 keys as primary path and 1 recovery key.
-        let desc = LianaDescriptor::from_str("tr([f5acc2fd]tpubD6NzVbkrYhZ4YgUx2ZLNt2rLYAMTdYysCRzKoLu2BeSHKvzqPaBDvf17GeBPnExUVPkuBpx4kniP964e2MxyzzazcXLptxLXModSVCVEV1T/<0;1>/*,and_v(v:pkh([8a64f2a9]tpubD6NzVbkrYhZ4WmzFjvQrp7sDa4ECUxTi9oby8K4FZkd3XCBtEdKwUiQyYJaxiJo5y42gyDWEczrFpozEjeLxMPxjf2WtkfcbpUdfvNnozWF/<0;1>/*),older(10)))").unwrap();
-        let desc_info = desc.policy();
-        let prim_key_fg = bip32::Fingerprint::from_str("f5acc2fd").unwrap();
-        let prim_key_origin = (prim_key_fg, [0.into(), 0.into()][..].into());
-        let recov_key_origin: (_, bip32::DerivationPath) = (
-            bip32::Fingerprint::from_str("8a64f2a9").unwrap(),
-            [0.into(), 4242.into()][..].into(),
+        let prim_xpriv = random_xpriv();
+        let recov_xpriv = random_xpriv();
+        let desc = LianaDescriptor::new(
+            LianaPolicy::new(
+                PathInfo::Single(desc_key(&secp, &prim_xpriv)),
+                [(10, PathInfo::Single(desc_key(&secp, &recov_xpriv)))]
+                    .iter()
+                    .cloned()
+                    .collect(),
+            )
+            .unwrap(),
         );
+        let desc_info = desc.policy();
+        let prim_key_fg = prim_xpriv.fingerprint(&secp);
+        let prim_der_path: bip32::DerivationPath = [0.into(), 0.into()][..].into();
+        let recov_key_fg = recov_xpriv.fingerprint(&secp);
+        let recov_der_path: bip32::DerivationPath = [0.into(), 4242.into()][..].into();
 
         // A PSBT with a single input and output, no signature. nSequence is not set to use the
         // recovery path.
         let mut unsigned_single_psbt: Psbt = dummy_psbt.clone();
-        let info = desc.partial_spend_info(&unsigned_single_psbt).unwrap();
+        let info = desc
+            .partial_spend_info(&unsigned_single_psbt, &secp)
+            .unwrap();
         assert_eq!(info.primary_path.threshold, 1);
         assert_eq!(info.primary_path.sigs_count, 0);
         assert!(info.primary_path.signed_pubkeys.is_empty());
@@ -1753,35 +2444,48 @@ mod tests {
         // If we set the sequence too low we still won't have the recovery path info.
         unsigned_single_psbt.unsigned_tx.input[0].sequence =
             Sequence::from_height(desc_info.recovery_paths.keys().next().unwrap() - 1);
-        let info = desc.partial_spend_info(&unsigned_single_psbt).unwrap();
+        let info = desc
+            .partial_spend_info(&unsigned_single_psbt, &secp)
+            .unwrap();
         assert!(info.recovery_paths.is_empty());
 
         // Now if we set the sequence at the right value we'll have it.
         let timelock = *desc_info.recovery_paths.keys().next().unwrap();
         unsigned_single_psbt.unsigned_tx.input[0].sequence = Sequence::from_height(timelock);
-        let info = desc.partial_spend_info(&unsigned_single_psbt).unwrap();
+        let info = desc
+            .partial_spend_info(&unsigned_single_psbt, &secp)
+            .unwrap();
         assert!(info.recovery_paths.contains_key(&timelock));
 
         // Even if it's a bit too high (as long as it's still a block height and activated)
         unsigned_single_psbt.unsigned_tx.input[0].sequence = Sequence::from_height(timelock + 42);
-        let info = desc.partial_spend_info(&unsigned_single_psbt).unwrap();
+        let info = desc
+            .partial_spend_info(&unsigned_single_psbt, &secp)
+            .unwrap();
         let recov_info = info.recovery_paths.get(&timelock).unwrap();
         assert_eq!(recov_info.threshold, 1);
         assert_eq!(recov_info.sigs_count, 0);
         assert!(recov_info.signed_pubkeys.is_empty());
 
-        // The same PSBT but with an (invalid) signature for the primary key.
+        // The same PSBT but spending a coin from this descriptor with a signature for the primary
+        // key.
         let mut signed_single_psbt = dummy_psbt.clone();
-        signed_single_psbt.inputs[0].tap_internal_key = Some(dummy_xonly_pubkeys[0]);
-        signed_single_psbt.inputs[0].tap_key_sig = Some(dummy_sig);
-        signed_single_psbt.inputs[0].tap_key_origins = [(
-            dummy_xonly_pubkeys[0],
-            (vec![dummy_leafhash], prim_key_origin.clone()),
-        )]
-        .iter()
-        .cloned()
-        .collect();
-        let info = desc.partial_spend_info(&signed_single_psbt).unwrap();
+        let coin_desc = desc.receive_descriptor().derive(0.into(), &secp);
+        signed_single_psbt.inputs[0]
+            .witness_utxo
+            .as_mut()
+            .unwrap()
+            .script_pubkey = coin_desc.script_pubkey();
+        coin_desc.update_psbt_in(&mut signed_single_psbt.inputs[0]);
+        sign_taproot_input(
+            &secp,
+            &mut signed_single_psbt,
+            0,
+            &prim_xpriv,
+            &prim_der_path,
+            None,
+        );
+        let info = desc.partial_spend_info(&signed_single_psbt, &secp).unwrap();
         assert_eq!(info.primary_path.threshold, 1);
         assert_eq!(info.primary_path.sigs_count, 1);
         assert!(
@@ -1790,19 +2494,25 @@ mod tests {
         );
         assert!(info.recovery_paths.is_empty());
 
+        // An invalid signature for the primary key isn't accounted for.
+        let mut invalid_sig_psbt = signed_single_psbt.clone();
+        invalid_sig_psbt.inputs[0].tap_key_sig = Some(dummy_sig);
+        let info = desc.partial_spend_info(&invalid_sig_psbt, &secp).unwrap();
+        assert_eq!(info.primary_path.sigs_count, 0);
+        assert!(info.primary_path.signed_pubkeys.is_empty());
+
         // Now enable the recovery path and add a signature for the recovery key.
         let mut signed_recov_psbt = dummy_psbt.clone();
         signed_recov_psbt.unsigned_tx.input[0].sequence = Sequence::from_height(timelock);
-        let recov_key = dummy_xonly_pubkeys[1];
-        signed_recov_psbt.inputs[0]
-            .tap_script_sigs
-            .insert((recov_key, dummy_leafhash), dummy_sig);
-        signed_recov_psbt.inputs[0].tap_key_origins =
-            [(recov_key, (vec![dummy_leafhash], recov_key_origin.clone()))]
-                .iter()
-                .cloned()
-                .collect();
-        let info = desc.partial_spend_info(&signed_recov_psbt).unwrap();
+        sign_taproot_input(
+            &secp,
+            &mut signed_recov_psbt,
+            0,
+            &recov_xpriv,
+            &recov_der_path,
+            Some(tap_leaf_hash(&secp, &desc, &recov_xpriv, &recov_der_path)),
+        );
+        let info = desc.partial_spend_info(&signed_recov_psbt, &secp).unwrap();
         assert_eq!(info.primary_path.threshold, 1);
         assert_eq!(info.primary_path.sigs_count, 0);
         assert!(info.primary_path.signed_pubkeys.is_empty());
@@ -1811,13 +2521,28 @@ mod tests {
         assert_eq!(recov_info.sigs_count, 1);
         assert!(
             recov_info.signed_pubkeys.len() == 1
-                && recov_info.signed_pubkeys.contains_key(&recov_key_origin.0)
+                && recov_info.signed_pubkeys.contains_key(&recov_key_fg)
+        );
+
+        // The same signature for a leaf which doesn't contain the recovery key isn't accounted
+        // for.
+        let mut wrong_leaf_psbt = dummy_psbt.clone();
+        wrong_leaf_psbt.unsigned_tx.input[0].sequence = Sequence::from_height(timelock);
+        sign_taproot_input(
+            &secp,
+            &mut wrong_leaf_psbt,
+            0,
+            &recov_xpriv,
+            &recov_der_path,
+            Some(dummy_leafhash),
         );
+        let info = desc.partial_spend_info(&wrong_leaf_psbt, &secp).unwrap();
+        assert_eq!(info.recovery_paths.get(&timelock).unwrap().sigs_count, 0);
 
         // A PSBT with multiple inputs, all signed for the primary path but with an ECDSA
         // signature. We must not account for those signatures since this is a Taproot descriptor.
         let psbt: Psbt = psbt_from_str("cHNidP8BAP0fAQIAAAAGAGo6V8K5MtKcQ8vRFedf5oJiOREiH4JJcEniyRv2800BAAAAAP3///9e3dVLjWKPAGwDeuUOmKFzOYEP5Ipu4LWdOPA+lITrRgAAAAAA/f///7cl9oeu9ssBXKnkWMCUnlgZPXhb+qQO2+OPeLEsbdGkAQAAAAD9////idkxRErbs34vsHUZ7QCYaiVaAFDV9gxNvvtwQLozwHsAAAAAAP3///9EakyJhd2PjwYh1I7zT2cmcTFI5g1nBd3srLeL7wKEewIAAAAA/f///7BcaP77nMaA2NjT/hyI6zueB/2jU/jK4oxmSqMaFkAzAQAAAAD9////AUAfAAAAAAAAFgAUqo7zdMr638p2kC3bXPYcYLv9nYUAAAAAAAEA/X4BAgAAAAABApEoe5xCmSi8hNTtIFwsy46aj3hlcLrtFrug39v5wy+EAQAAAGpHMEQCIDeI8JTWCTyX6opCCJBhWc4FytH8g6fxDaH+Wa/QqUoMAiAgbITpz8TBhwxhv/W4xEXzehZpOjOTjKnPw36GIy6SHAEhA6QnYCHUbU045FVh6ZwRwYTVineqRrB9tbqagxjaaBKh/v///+v1seDE9gGsZiWwewQs3TKuh0KSBIHiEtG8ABbz2DpAAQAAAAD+////Aqhaex4AAAAAFgAUkcVOEjVMct0jyCzhZN6zBT+lvTQvIAAAAAAAACIAIKKDUd/GWjAnwU99llS9TAK2dK80/nSRNLjmrhj0odUEAAJHMEQCICSn+boh4ItAa3/b4gRUpdfblKdcWtMLKZrgSEFFrC+zAiBtXCx/Dq0NutLSu1qmzFF1lpwSCB3w3MAxp5W90z7b/QEhA51S2ERUi0bg+l+bnJMJeAfDknaetMTagfQR9+AOrVKlxdMkAAEBKy8gAAAAAAAAIgAgooNR38ZaMCfBT32WVL1MArZ0rzT+dJE0uOauGPSh1QQiAgN+zbSfdr8oJBtlKomnQTHynF2b/UhovAwf0eS8awRSqUgwRQIhAJhm6xQvxt2LY+eNZqjhsgMOAxD0OPYty6nf9WaQZtgkAiBf/AXkeyq6ALknO9TZwY6ZRa0evY+DQ3j3XaqiBiAMfgEBBUEhA37NtJ92vygkG2UqiadBMfKcXZv9SGi8DB/R5LxrBFKprHNkdqkUxttmGj2sqzzaxSaacJTnJPDCbY6IrVqyaCIGAv9qeBDEB+5kvM/sZ8jQ7QApfZcDrqtq5OAe2gQ1V+pmDIpk8qkAAAAA0AAAACIGA37NtJ92vygkG2UqiadBMfKcXZv9SGi8DB/R5LxrBFKpDPWswv0AAAAA0AAAAAABAOoCAAAAAAEB0OPoVJs9ihvnAwjO16k/wGJuEus1IEE1Yo2KBjC2NSEAAAAAAP7///8C6AMAAAAAAAAiACBfeUS9jQv6O1a96Aw/mPV6gHxHl3mfj+f0frfAs2sMpP1QGgAAAAAAFgAUDS4UAIpdm1RlFYmg0OoCxW0yBT4CRzBEAiAPvbNlnhiUxLNshxN83AuK/lGWwlpXOvmcqoxsMLzIKwIgWwATJuYPf9buLe9z5SnXVnPVL0q6UZaWE5mjCvEl1RUBIQI54LFZmq9Lw0pxKpEGeqI74NnIfQmLMDcv5ySplUS1/wDMJAABASvoAwAAAAAAACIAIF95RL2NC/o7Vr3oDD+Y9XqAfEeXeZ+P5/R+t8CzawykIgICYn4eZbb6KGoxB1PEv/XPiujZFDhfoi/rJPtfHPVML2lHMEQCIDOHEqKdBozXIPLVgtBj3eWC1MeIxcKYDADe4zw0DbcMAiAq4+dbkTNCAjyCxJi0TKz5DWrPulxrqOdjMRHWngXHsQEBBUEhAmJ+HmW2+ihqMQdTxL/1z4ro2RQ4X6Iv6yT7Xxz1TC9prHNkdqkUzc/gCLoe6rQw63CGXhIR3YRz1qCIrVqyaCIGAmJ+HmW2+ihqMQdTxL/1z4ro2RQ4X6Iv6yT7Xxz1TC9pDPWswv0AAAAAqgAAACIGA8JCTIzdSoTJhiKN1pn+NnlkyuKOndiTgH2NIX+yNsYqDIpk8qkAAAAAqgAAAAABAOoCAAAAAAEBRGpMiYXdj48GIdSO809nJnExSOYNZwXd7Ky3i+8ChHsAAAAAAP7///8COMMQAAAAAAAWABQ5rnyuG5T8iuhqfaGAmpzlybo3t+gDAAAAAAAAIgAg7Kz3CX1RBjIvbK9LBYztmi7F1XIxQpX6mtCUkflvvl8CRzBEAiBaYx4sOHckEZwDnSrbb1ivc6seX4Puasm1PBGnBWgSTQIgCeUiXvd90ajI3F4/BHifLUI4fVIgVQFCqLTbbeXQD5oBIQOmGm+gTRx1slzF+wn8NhZoR1xfSYgoKX6bpRSVRjLcEXrOJAABASvoAwAAAAAAACIAIOys9wl9UQYyL2yvSwWM7ZouxdVyMUKV+prQlJH5b75fIgID0X2UJhC5+2jgJqUrihxZxDZHK7jgPFlrUYzoSHQTmP9HMEQCIEM4K8lVACvE2oSMZHDJiOeD81qsYgAvgpRgcSYgKc3AAiAQjdDr2COBea69W+2iVbnODuH3QwacgShW3dS4yeggJAEBBUEhA9F9lCYQufto4CalK4ocWcQ2Ryu44DxZa1GM6Eh0E5j/rHNkdqkU0DTexcgOQQ+BFjgS031OTxcWiH2IrVqyaCIGA9F9lCYQufto4CalK4ocWcQ2Ryu44DxZa1GM6Eh0E5j/DPWswv0AAAAAvwAAACIGA/xg4Uvem3JHVPpyTLP5JWiUH/yk3Y/uUI6JkZasCmHhDIpk8qkAAAAAvwAAAAABAOoCAAAAAAEBmG+mPq0O6QSWEMctsMjvv5LzWHGoT8wsA9Oa05kxIxsBAAAAAP7///8C6AMAAAAAAAAiACDUvIILFr0OxybADV3fB7ms7+ufnFZgicHR0nbI+LFCw1UoGwAAAAAAFgAUC+1ZjCC1lmMcvJ/4JkevqoZF4igCRzBEAiA3d8o96CNgNWHUkaINWHTvAUinjUINvXq0KBeWcsSWuwIgKfzRNWFR2LDbnB/fMBsBY/ylVXcSYwLs8YC+kmko1zIBIQOpEfsLv0htuertA1sgzCwGvHB0vE4zFO69wWEoHClKmAfMJAABASvoAwAAAAAAACIAINS8ggsWvQ7HJsANXd8Huazv65+cVmCJwdHSdsj4sULDIgID96jZc0sCi0IIXf2CpfE7tY+9LRmMsOdSTTHelFxfCwJHMEQCIHlaiMMznx8Cag8Y3X2gXi9Qtg0ZuyHEC6DsOzipSGOKAiAV2eC+S3Mbq6ig5QtRvTBsq5M3hCBdEJQlOrLVhWWt6AEBBUEhA/eo2XNLAotCCF39gqXxO7WPvS0ZjLDnUk0x3pRcXwsCrHNkdqkUyJ+Cbx7vYVY665yjJnMNODyYrAuIrVqyaCIGAt8UyDXk+mW3Y6IZNIBuDJHkdOaZi/UEShkN5L3GiHR5DIpk8qkAAAAAuAAAACIGA/eo2XNLAotCCF39gqXxO7WPvS0ZjLDnUk0x3pRcXwsCDPWswv0AAAAAuAAAAAABAP0JAQIAAAAAAQG7Zoy4I3J9x+OybAlIhxVKcYRuPFrkDFJfxMiC3kIqIAEAAAAA/v///wO5xxAAAAAAABYAFHgBzs9wJNVk6YwR81IMKmckTmC56AMAAAAAAAAWABTQ/LmJix5JoHBOr8LcgEChXHdLROgDAAAAAAAAIgAg7Kz3CX1RBjIvbK9LBYztmi7F1XIxQpX6mtCUkflvvl8CRzBEAiA+sIKnWVE3SmngjUgJdu1K2teW6eqeolfGe0d11b+irAIgL20zSabXaFRNM8dqVlcFsfNJ0exukzvxEOKl/OcF8VsBIQJrUspHq45AMSwbm24//2a9JM8XHFWbOKpyV+gNCtW71nrOJAABASvoAwAAAAAAACIAIOys9wl9UQYyL2yvSwWM7ZouxdVyMUKV+prQlJH5b75fIgID0X2UJhC5+2jgJqUrihxZxDZHK7jgPFlrUYzoSHQTmP9IMEUCIQCmDhJ9fyhlQwPruoOUemDuldtRu3ZkiTM3DA0OhkguSQIgYerNaYdP43DcqI5tnnL3n4jEeMHFCs+TBkOd6hDnqAkBAQVBIQPRfZQmELn7aOAmpSuKHFnENkcruOA8WWtRjOhIdBOY/6xzZHapFNA03sXIDkEPgRY4EtN9Tk8XFoh9iK1asmgiBgPRfZQmELn7aOAmpSuKHFnENkcruOA8WWtRjOhIdBOY/wz1rML9AAAAAL8AAAAiBgP8YOFL3ptyR1T6ckyz+SVolB/8pN2P7lCOiZGWrAph4QyKZPKpAAAAAL8AAAAAAQDqAgAAAAABAT6/vc6qBRzhQyjVtkC25NS2BvGyl2XjjEsw3e8vAesjAAAAAAD+////AgPBAO4HAAAAFgAUEwiWd/qI1ergMUw0F1+qLys5G/foAwAAAAAAACIAIOOPEiwmp2ZXR7ciyrveITXw0tn6zbQUA1Eikd9QlHRhAkcwRAIgJMZdO5A5u2UIMrAOgrR4NcxfNgZI6OfY7GKlZP0O8yUCIDFujbBRnamLEbf0887qidnXo6UgQA9IwTx6Zomd4RvJASEDoNmR2/XcqSyCWrE1tjGJ1oLWlKt4zsFekK9oyB4Hl0HF0yQAAQEr6AMAAAAAAAAiACDjjxIsJqdmV0e3Isq73iE18NLZ+s20FANRIpHfUJR0YSICAo3uyJxKHR9Z8fwvU7cywQCnZyPvtMl3nv54wPW1GSGqSDBFAiEAlLY98zqEL/xTUvm9ZKy5kBa4UWfr4Ryu6BmSZjseXPQCIGy7efKbZLQSDq8RhgNNjl1384gWFTN7nPwWV//SGriyAQEFQSECje7InEodH1nx/C9TtzLBAKdnI++0yXee/njA9bUZIaqsc2R2qRQhPRlaLsh/M/K/9fvbjxF/M20cNoitWrJoIgYCF7Rj5jFhe5L6VDzP5m2BeaG0mA9e7+6fMeWkWxLwpbAMimTyqQAAAADNAAAAIgYCje7InEodH1nx/C9TtzLBAKdnI++0yXee/njA9bUZIaoM9azC/QAAAADNAAAAAAA=");
-        let info = desc.partial_spend_info(&psbt).unwrap();
+        let info = desc.partial_spend_info(&psbt, &secp).unwrap();
         assert!(psbt
             .inputs
             .iter()
@@ -1833,17 +2558,14 @@ mod tests {
                 PathInfo::Multi(
                     2,
                     vec![
-                        descriptor::DescriptorPublicKey::from_str("[f5acc2fd]tpubD6NzVbkrYhZ4YgUx2ZLNt2rLYAMTdYysCRzKoLu2BeSHKvzqPaBDvf17GeBPnExUVPkuBpx4kniP964e2MxyzzazcXLptxLXModSVCVEV1T/<0;1>/*").unwrap(),
+                        desc_key(&secp, &prim_xpriv),
                         random_desc_key(&secp),
                         random_desc_key(&secp),
                     ],
                 ),
                 [(
                     42,
-                    PathInfo::Multi(
-                        1,
-                        (0..3).map(|_| random_desc_key(&secp)).collect()
-                    ),
+                    PathInfo::Multi(1, (0..3).map(|_| random_desc_key(&secp)).collect()),
                 )]
                 .iter()
                 .cloned()
@@ -1851,17 +2573,16 @@ mod tests {
             )
             .unwrap(),
         );
-        let prim_key = dummy_xonly_pubkeys[0];
         let mut psbt = dummy_psbt.clone();
-        psbt.inputs[0]
-            .tap_script_sigs
-            .insert((prim_key, dummy_leafhash), dummy_sig);
-        psbt.inputs[0].tap_key_origins =
-            [(prim_key, (vec![dummy_leafhash], prim_key_origin.clone()))]
-                .iter()
-                .cloned()
-                .collect();
-        let info = desc.partial_spend_info(&psbt).unwrap();
+        sign_taproot_input(
+            &secp,
+            &mut psbt,
+            0,
+            &prim_xpriv,
+            &prim_der_path,
+            Some(tap_leaf_hash(&secp, &desc, &prim_xpriv, &prim_der_path)),
+        );
+        let info = desc.partial_spend_info(&psbt, &secp).unwrap();
         assert_eq!(info.primary_path.threshold, 2);
         assert_eq!(info.primary_path.sigs_count, 1);
         assert!(
@@ -1871,14 +2592,21 @@ mod tests {
         assert!(info.recovery_paths.is_empty());
 
         // A not very well thought-out decaying multisig.
-        let prim_path = PathInfo::Multi(3, vec![
-                        descriptor::DescriptorPublicKey::from_str("[f5acc2fd]tpubD6NzVbkrYhZ4YgUx2ZLNt2rLYAMTdYysCRzKoLu2BeSHKvzqPaBDvf17GeBPnExUVPkuBpx4kniP964e2MxyzzazcXLptxLXModSVCVEV1T/<0;1>/*").unwrap(),
-                        random_desc_key(&secp),
-                        random_desc_key(&secp),
-                    ]);
-        let first_reco_path = PathInfo::Multi(3, (0..5).map(|_| random_desc_key(&secp)).collect());
-        let sec_reco_path = PathInfo::Multi(2, (0..5).map(|_| random_desc_key(&secp)).collect());
-        let third_reco_path = PathInfo::Multi(1, (0..5).map(|_| random_desc_key(&secp)).collect());
+        let first_reco_xpriv = random_xpriv();
+        let sec_reco_xpriv = random_xpriv();
+        let third_reco_xpriv = random_xpriv();
+        let multi = |xpriv: &bip32::Xpriv, thresh: usize, n_keys: usize| {
+            PathInfo::Multi(
+                thresh,
+                std::iter::once(desc_key(&secp, xpriv))
+                    .chain((1..n_keys).map(|_| random_desc_key(&secp)))
+                    .collect(),
+            )
+        };
+        let prim_path = multi(&prim_xpriv, 3, 3);
+        let first_reco_path = multi(&first_reco_xpriv, 3, 5);
+        let sec_reco_path = multi(&sec_reco_xpriv, 2, 5);
+        let third_reco_path = multi(&third_reco_xpriv, 1, 5);
         let liana_policy = LianaPolicy::new(
             prim_path.clone(),
             [
@@ -1895,7 +2623,7 @@ mod tests {
         let policy = desc.policy();
         assert_eq!(policy, liana_policy);
         let mut psbt = dummy_psbt.clone();
-        let empty_partial_info = desc.partial_spend_info(&psbt).unwrap();
+        let empty_partial_info = desc.partial_spend_info(&psbt, &secp).unwrap();
         assert_eq!(empty_partial_info.primary_path.threshold, 3);
         assert_eq!(empty_partial_info.primary_path.sigs_count, 0);
         assert_eq!(
@@ -1906,16 +2634,15 @@ mod tests {
 
         // Now set a signature for the primary path. All recovery paths still empty, a signature is
         // present for the primary path.
-        let prim_key = dummy_xonly_pubkeys[0];
-        psbt.inputs[0]
-            .tap_script_sigs
-            .insert((prim_key, dummy_leafhash), dummy_sig);
-        psbt.inputs[0].tap_key_origins =
-            [(prim_key, (vec![dummy_leafhash], prim_key_origin.clone()))]
-                .iter()
-                .cloned()
-                .collect();
-        let partial_info = desc.partial_spend_info(&psbt).unwrap();
+        sign_taproot_input(
+            &secp,
+            &mut psbt,
+            0,
+            &prim_xpriv,
+            &prim_der_path,
+            Some(tap_leaf_hash(&secp, &desc, &prim_xpriv, &prim_der_path)),
+        );
+        let partial_info = desc.partial_spend_info(&psbt, &secp).unwrap();
         assert_eq!(partial_info.primary_path.threshold, 3);
         assert_eq!(partial_info.primary_path.sigs_count, 1);
         assert_eq!(
@@ -1925,20 +2652,21 @@ mod tests {
         assert!(partial_info.recovery_paths.is_empty());
 
         // Now enable the first recovery path and make the signature be for this path.
-        let fingerprint = first_reco_path
-            .thresh_origins()
-            .1
-            .into_iter()
-            .next()
-            .unwrap()
-            .0;
-        psbt.inputs[0]
-            .tap_key_origins
-            .get_mut(&prim_key)
-            .unwrap()
-            .1
-             .0 = fingerprint;
-        let partial_info = desc.partial_spend_info(&psbt).unwrap();
+        psbt.inputs[0].tap_script_sigs.clear();
+        sign_taproot_input(
+            &secp,
+            &mut psbt,
+            0,
+            &first_reco_xpriv,
+            &prim_der_path,
+            Some(tap_leaf_hash(
+                &secp,
+                &desc,
+                &first_reco_xpriv,
+                &prim_der_path,
+            )),
+        );
+        let partial_info = desc.partial_spend_info(&psbt, &secp).unwrap();
         assert_eq!(partial_info.primary_path.threshold, 3);
         assert_eq!(partial_info.primary_path.sigs_count, 0);
         assert_eq!(
@@ -1947,7 +2675,21 @@ mod tests {
         );
         assert!(partial_info.recovery_paths.is_empty());
         psbt.unsigned_tx.input[0].sequence = bitcoin::Sequence::from_height(26784);
-        let partial_info = desc.partial_spend_info(&psbt).unwrap();
+        psbt.inputs[0].tap_script_sigs.clear();
+        sign_taproot_input(
+            &secp,
+            &mut psbt,
+            0,
+            &first_reco_xpriv,
+            &prim_der_path,
+            Some(tap_leaf_hash(
+                &secp,
+                &desc,
+                &first_reco_xpriv,
+                &prim_der_path,
+            )),
+        );
+        let partial_info = desc.partial_spend_info(&psbt, &secp).unwrap();
         assert_eq!(partial_info.recovery_paths.len(), 1);
         assert_eq!(partial_info.recovery_paths[&26784].threshold, 3);
         assert_eq!(partial_info.recovery_paths[&26784].sigs_count, 1);
@@ -1957,21 +2699,17 @@ mod tests {
         );
 
         // Now enable the second recovery path and make the signature be for this path.
-        let fingerprint = sec_reco_path
-            .thresh_origins()
-            .1
-            .into_iter()
-            .next()
-            .unwrap()
-            .0;
-        psbt.inputs[0]
-            .tap_key_origins
-            .get_mut(&prim_key)
-            .unwrap()
-            .1
-             .0 = fingerprint;
         psbt.unsigned_tx.input[0].sequence = bitcoin::Sequence::from_height(53568);
-        let partial_info = desc.partial_spend_info(&psbt).unwrap();
+        psbt.inputs[0].tap_script_sigs.clear();
+        sign_taproot_input(
+            &secp,
+            &mut psbt,
+            0,
+            &sec_reco_xpriv,
+            &prim_der_path,
+            Some(tap_leaf_hash(&secp, &desc, &sec_reco_xpriv, &prim_der_path)),
+        );
+        let partial_info = desc.partial_spend_info(&psbt, &secp).unwrap();
         assert_eq!(partial_info.primary_path.threshold, 3);
         assert_eq!(partial_info.primary_path.sigs_count, 0);
         assert_eq!(
@@ -1988,22 +2726,22 @@ mod tests {
         }
 
         // Finally do the same for the third recovery path.
-        let fingerprint = third_reco_path
-            .thresh_origins()
-            .1
-            .into_iter()
-            .next()
-            .unwrap()
-            .0;
-        psbt.inputs[0]
-            .tap_key_origins
-            .get_mut(&prim_key)
-            .unwrap()
-            .1
-             .0 = fingerprint;
-        psbt.unsigned_tx.input[0].sequence = bitcoin::Sequence::from_height(53568);
         psbt.unsigned_tx.input[0].sequence = bitcoin::Sequence::from_height(62496);
-        let partial_info = desc.partial_spend_info(&psbt).unwrap();
+        psbt.inputs[0].tap_script_sigs.clear();
+        sign_taproot_input(
+            &secp,
+            &mut psbt,
+            0,
+            &third_reco_xpriv,
+            &prim_der_path,
+            Some(tap_leaf_hash(
+                &secp,
+                &desc,
+                &third_reco_xpriv,
+                &prim_der_path,
+            )),
+        );
+        let partial_info = desc.partial_spend_info(&psbt, &secp).unwrap();
         assert_eq!(partial_info.primary_path.threshold, 3);
         assert_eq!(partial_info.primary_path.sigs_count, 0);
         assert_eq!(
@@ -2024,7 +2762,7 @@ mod tests {
 
     #[test]
     fn bip32_derivs_pruning() {
-        let secp = secp256k1::Secp256k1::signing_only();
+        let secp = secp256k1::Secp256k1::new();
         let dummy_leafhash = bitcoin::TapLeafHash::from_slice(&[0; 32]).unwrap();
 
         // A signet descriptor created using Liana v2.
@@ -2051,7 +2789,7 @@ mod tests {
         let pruned_psbt = Psbt::from_str("cHNidP8BAFICAAAAAc+3IQFejOVro5Hlwy18au5Jr5mJX+tNMGk0ZE1hydIbAQAAAAADAAAAAbSFAQAAAAAAFgAUBSY69rqtGQLCmhuT29Ep4ZO5Sk8AAAAAAAEAzQIAAAAAAQEIoAeUdfZj04Ds8EspEK222TJdDNy1WZb/Mg1PJbQekwAAAAAA/f///wKQCQQAAAAAACJRIPJojBgnDc9oUS5lDNx/YJznYR2NPQue7h/d+o5Z+2FQoIYBAAAAAAAiACDZrCBvscZpg+S+IaoZBJjyKDdrNS3oXPaF17DNaB+4mAFAe9yuRS3Vn8A5NUglhwiX7vN0wpQ0Q43ClWtJRnC2HJ66h5HYJ/p8xHgHOhRDUWRzcXLLGl+brc5dW+k0OvIZEyuLAgABASughgEAAAAAACIAINmsIG+xxmmD5L4hqhkEmPIoN2s1Lehc9oXXsM1oH7iYAQX9GQFjdqkU2zK+b9oTL/KfnOSYtq3wmtf4qP6IrGt2qRTSNOD0U7fuHdAnKchIf8GmUO904YisbJNrdqkUE5TQk5mdyYtviaGAsIiOgc4y6wGIrGyTU4hWsmdTIQOirPI1KXBtP2Tg2FQxSo4BjFBTf+dCKtZwDQt056slgCEDDHE7Hpxq++JsjZdbfwsPiA6pmq0dV00tR3hc2sus8KkhA2nPUthIMe1SeFegiZEKZF69yJerP1RFVlyu66C5lOVVU65zZHapFEUmCTccyLJXczvUfPUOCXr7CN0uiKxrdqkUeJmVqUt1Q4aFREOUWKX9U/SuZZ2IrGyTa3apFBDmKn40ceTWVbwxRI21c2qji1tOiKxsk1KIU7JoaCIGAjCZLg7xtlG43xEvns0TRd5gHpPrZWzAaYjo3lheMw/hHJAxFe8wAACAAQAAgAAAAIACAACAAgAAAAgAAAAiBgI0Y2/HRNvXA3niUE3RvrzQcCDiJ4F6vVog0uIanRUWHhwXK6G8MAAAgAEAAIAAAACAAgAAgAIAAAAIAAAAIgYDRIirsiRAlhvtusXHniY/PBSX8PMIaNrSl3AUVyR+bpYcY2rfPzAAAIABAACAAAAAgAIAAIACAAAACAAAAAAA").unwrap();
         assert_ne!(psbt, pruned_psbt);
         assert_eq!(psbt.inputs[0].bip32_derivation.len(), 9);
-        let psbt = desc.prune_bip32_derivs_last_avail(psbt).unwrap();
+        let psbt = desc.prune_bip32_derivs_last_avail(psbt, &secp).unwrap();
         assert_eq!(psbt.inputs[0].bip32_derivation.len(), 3);
         assert_eq!(psbt, pruned_psbt);
 
@@ -2202,5 +2940,229 @@ mod tests {
         LianaDescriptor::from_str("wsh(0)").unwrap_err();
     }
 
+    #[test]
+    fn signed_input_path() {
+        let secp = secp256k1::Secp256k1::new();
+        let xprivs: Vec<_> = (1..=2)
+            .map(|i| bip32::Xpriv::new_master(bitcoin::Network::Bitcoin, &[i; 32]).unwrap())
+            .collect();
+        let desc_key = |xpriv: &bip32::Xpriv| {
+            let xpub = bip32::Xpub::from_priv(&secp, xpriv);
+            let key_str = format!("[{}]{}/<0;1>/*", xpriv.fingerprint(&secp), xpub);
+            descriptor::DescriptorPublicKey::from_str(&key_str).unwrap()
+        };
+        let primary_path = PathInfo::Single(desc_key(&xprivs[0]));
+        let recovery_paths: BTreeMap<_, _> = [(42, PathInfo::Single(desc_key(&xprivs[1])))]
+            .iter()
+            .cloned()
+            .collect();
+        let (index, value) = (bip32::ChildNumber::from_normal_idx(3).unwrap(), 10_000);
+        let value = bitcoin::Amount::from_sat(value);
+        let mut tx = bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![bitcoin::TxIn {
+                sequence: Sequence::from_height(42),
+                ..bitcoin::TxIn::default()
+            }],
+            output: Vec::new(),
+        };
+
+        // Under P2WSH it's determined from the signatures.
+        let desc = LianaDescriptor::new(
+            LianaPolicy::new_legacy(primary_path.clone(), recovery_paths.clone()).unwrap(),
+        );
+        let witness_script = desc.receive_desc.derive(index, &secp).witness_script();
+        let sign = |tx: &bitcoin::Transaction, xpriv: &bip32::Xpriv| {
+            let path = [bip32::ChildNumber::from_normal_idx(0).unwrap(), index];
+            let privkey = xpriv.derive_priv(&secp, &path).unwrap().private_key;
+            let sighash = bitcoin::sighash::SighashCache::new(tx)
+                .p2wsh_signature_hash(0, &witness_script, value, bitcoin::EcdsaSighashType::All)
+                .unwrap();
+            let signature = secp.sign_ecdsa(&secp256k1::Message::from(sighash), &privkey);
+            bitcoin::ecdsa::Signature::sighash_all(signature).to_vec()
+        };
+        let path =
+            |tx: &bitcoin::Transaction| desc.signed_input_path(tx, 0, value, index, false, &secp);
+        assert_eq!(path(&tx), None);
+        tx.input[0].witness = vec![sign(&tx, &xprivs[0]), witness_script.to_bytes()].into();
+        assert_eq!(path(&tx), Some(SpendingPath::Primary));
+        tx.input[0].witness = vec![
+            sign(&tx, &xprivs[1]),
+            vec![0; 33],
+            Vec::new(),
+            witness_script.to_bytes(),
+        ]
+        .into();
+        assert_eq!(path(&tx), Some(SpendingPath::Recovery(42)));
+        // A signature for another coin is not valid.
+        assert_eq!(
+            desc.signed_input_path(&tx, 0, value, index, true, &secp),
+            None
+        );
+
+        // Under Taproot it's determined from the leaf used, or the key path.
+        let desc = LianaDescriptor::new(LianaPolicy::new(primary_path, recovery_paths).unwrap());
+        let leaf_script = match desc.receive_desc.derive(index, &secp).0 {
+            descriptor::Descriptor::Tr(tr) => tr.iter_scripts().next().unwrap().1.encode(),
+            _ => unreachable!(),
+        };
+        tx.input[0].witness = vec![vec![1; 64]].into();
+        assert_eq!(
+            desc.signed_input_path(&tx, 0, value, index, false, &secp),
+            Some(SpendingPath::Primary)
+        );
+        tx.input[0].witness = vec![vec![1; 64], leaf_script.to_bytes(), vec![0xc0; 33]].into();
+        assert_eq!(
+            desc.signed_input_path(&tx, 0, value, index, false, &secp),
+            Some(SpendingPath::Recovery(42))
+        );
+    }
+
+    #[test]
+    fn finalizability() {
+        let secp = secp256k1::Secp256k1::new();
+        let (prim_xpriv, recov_xpriv) = (random_xpriv(), random_xpriv());
+        let prim_fg = prim_xpriv.fingerprint(&secp);
+        let primary_path = PathInfo::Single(desc_key(&secp, &prim_xpriv));
+        let recovery_paths: BTreeMap<_, _> =
+            [(42, PathInfo::Single(desc_key(&secp, &recov_xpriv)))]
+                .iter()
+                .cloned()
+                .collect();
+        let der_path: bip32::DerivationPath = [0.into(), 3.into()][..].into();
+
+        for desc in [
+            LianaDescriptor::new(
+                LianaPolicy::new_legacy(primary_path.clone(), recovery_paths.clone()).unwrap(),
+            ),
+            LianaDescriptor::new(
+                LianaPolicy::new(primary_path.clone(), recovery_paths.clone()).unwrap(),
+            ),
+        ] {
+            // A PSBT spending a coin of this descriptor.
+            let coin_desc = desc.receive_descriptor().derive(3.into(), &secp);
+            let tx = bitcoin::Transaction {
+                version: bitcoin::transaction::Version::TWO,
+                lock_time: bitcoin::absolute::LockTime::ZERO,
+                input: vec![bitcoin::TxIn::default()],
+                output: vec![bitcoin::TxOut {
+                    value: bitcoin::Amount::from_sat(9_000),
+                    script_pubkey: coin_desc.script_pubkey(),
+                }],
+            };
+            let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+            psbt.inputs[0].witness_utxo = Some(bitcoin::TxOut {
+                value: bitcoin::Amount::from_sat(10_000),
+                script_pubkey: coin_desc.script_pubkey(),
+            });
+            coin_desc.update_psbt_in(&mut psbt.inputs[0]);
+            let sign = |psbt: &mut Psbt, xpriv: &bip32::Xpriv| {
+                if desc.is_taproot() {
+                    let leaf_hash =
+                        psbt.inputs[0]
+                            .tap_scripts
+                            .values()
+                            .next()
+                            .map(|(script, version)| {
+                                bitcoin::TapLeafHash::from_script(script, *version)
+                            });
+                    let leaf_hash = if xpriv == &prim_xpriv {
+                        None
+                    } else {
+                        leaf_hash
+                    };
+                    sign_taproot_input(&secp, psbt, 0, xpriv, &der_path, leaf_hash);
+                } else {
+                    sign_p2wsh_input(&secp, psbt, 0, xpriv, &der_path);
+                }
+            };
+
+            // Without signature it can't be finalized. Only the primary path is available.
+            let res = desc.finalizability(&psbt, &secp);
+            assert_eq!(res.len(), 1);
+            assert!(!res[0].is_finalizable());
+            assert!(res[0].error.is_some());
+            assert_eq!(
+                res[0].missing,
+                vec![MissingSignatures {
+                    path: SpendingPath::Primary,
+                    count: 1,
+                    signers: vec![prim_fg],
+                }]
+            );
+
+            // A signature from the recovery key doesn't help as long as the timelock isn't set.
+            let mut recov_psbt = psbt.clone();
+            sign(&mut recov_psbt, &recov_xpriv);
+            assert!(!desc.finalizability(&recov_psbt, &secp)[0].is_finalizable());
+
+            // Once signed by the primary key it can be finalized through the primary path.
+            let mut prim_psbt = psbt.clone();
+            sign(&mut prim_psbt, &prim_xpriv);
+            let res = desc.finalizability(&prim_psbt, &secp);
+            assert_eq!(res[0].path, Some(SpendingPath::Primary));
+            assert!(res[0].missing.is_empty());
+            assert!(res[0].error.is_none());
+            // It wasn't finalized though.
+            assert_eq!(prim_psbt.inputs[0].final_script_witness, None);
+
+            // Through the recovery path once the timelock is set. The primary path is still
+            // available but misses a signature.
+            recov_psbt.unsigned_tx.input[0].sequence = Sequence::from_height(42);
+            recov_psbt.inputs[0].partial_sigs.clear();
+            recov_psbt.inputs[0].tap_script_sigs.clear();
+            sign(&mut recov_psbt, &recov_xpriv);
+            let res = desc.finalizability(&recov_psbt, &secp);
+            assert_eq!(res[0].path, Some(SpendingPath::Recovery(42)));
+            assert_eq!(
+                res[0].missing,
+                vec![MissingSignatures {
+                    path: SpendingPath::Primary,
+                    count: 1,
+                    signers: vec![prim_fg],
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn external_inputs_dont_panic() {
+        let secp = secp256k1::Secp256k1::verification_only();
+        let desc = LianaDescriptor::from_str("wsh(or_d(pk([f5acc2fd]tpubD6NzVbkrYhZ4YgUx2ZLNt2rLYAMTdYysCRzKoLu2BeSHKvzqPaBDvf17GeBPnExUVPkuBpx4kniP964e2MxyzzazcXLptxLXModSVCVEV1T/<0;1>/*),and_v(v:pkh([8a64f2a9]tpubD6NzVbkrYhZ4WmzFjvQrp7sDa4ECUxTi9oby8K4FZkd3XCBtEdKwUiQyYJaxiJo5y42gyDWEczrFpozEjeLxMPxjf2WtkfcbpUdfvNnozWF/<0;1>/*),older(10))))#d72le4dr").unwrap();
+
+        // A key without an origin is left out of the origins of a spending path.
+        let key = descriptor::DescriptorPublicKey::from_str("tpubD6NzVbkrYhZ4YgUx2ZLNt2rLYAMTdYysCRzKoLu2BeSHKvzqPaBDvf17GeBPnExUVPkuBpx4kniP964e2MxyzzazcXLptxLXModSVCVEV1T/<0;1>/*").unwrap();
+        let (k, origins) = PathInfo::Single(key.clone()).thresh_origins();
+        assert_eq!(k, 1);
+        assert!(origins.is_empty());
+        let (k, origins) =
+            PathInfo::Multi(1, vec![key, random_desc_key(&secp256k1::Secp256k1::new())])
+                .thresh_origins();
+        assert_eq!(k, 1);
+        assert_eq!(origins.len(), 1);
+
+        // An output with a hardened derivation index is not our change.
+        let mut psbt = Psbt::from_str("cHNidP8BAHECAAAAAUSHuliRtuCX1S6JxRuDRqDCKkWfKmWL5sV9ukZ/wzvfAAAAAAD9////AogTAAAAAAAAFgAUIxe7UY6LJ6y5mFBoWTOoVispDmdwFwAAAAAAABYAFKqO83TK+t/KdpAt21z2HGC7/Z2FAAAAAAABASsQJwAAAAAAACIAIIIySQjGCTeyx/rKUQx8qobjhJeNCiVCliBJPdyRX6XKAQVBIQI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDKxzZHapFNYASzIYkEdH9bJz6nnqUG3uBB8kiK1asmgiBgI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDAz1rML9AAAAAG8AAAAiBgMLcbOxsfLe6+3r1UcjQo77HY0As8OKE4l37yj0/qhIyQyKZPKpAAAAAG8AAAAAAAA=").unwrap();
+        let pubkey = *psbt.inputs[0].bip32_derivation.keys().next().unwrap();
+        psbt.outputs[0].bip32_derivation.insert(
+            pubkey,
+            (
+                bip32::Fingerprint::from_str("f5acc2fd").unwrap(),
+                bip32::DerivationPath::from_str("m/0/2147483647'").unwrap(),
+            ),
+        );
+        assert!(desc.change_indexes(&psbt, &secp).is_empty());
+
+        // A PSBT without input is rejected.
+        let mut empty_psbt = psbt.clone();
+        empty_psbt.inputs.clear();
+        empty_psbt.unsigned_tx.input.clear();
+        assert!(matches!(
+            desc.partial_spend_info(&empty_psbt, &secp),
+            Err(LianaDescError::InsanePsbt)
+        ));
+    }
+
     // TODO: test error conditions of deserialization.
 }
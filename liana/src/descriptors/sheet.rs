@@ -0,0 +1,217 @@
+//! Per-participant key sheets.
+//!
+//! A key sheet tells a signer what it needs to know about its own keys (their role in the wallet
+//! and how to use them) without revealing the other keys of the descriptor. The full descriptor
+//! is kept by whoever coordinates the wallet.
+
+use miniscript::{bitcoin::bip32, descriptor::DescriptorPublicKey};
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use super::{LianaDescriptor, PathInfo};
+
+/// The role of a signer in a spending path of the descriptor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyRole {
+    /// The signer's keys are part of the primary path, usable at any time.
+    Primary {
+        /// How many keys of the path must sign.
+        threshold: usize,
+        /// How many keys there are in the path.
+        keys: usize,
+    },
+    /// The signer's keys are part of a recovery path, usable once coins haven't moved for this
+    /// number of blocks.
+    Recovery {
+        timelock: u16,
+        /// How many keys of the path must sign.
+        threshold: usize,
+        /// How many keys there are in the path.
+        keys: usize,
+    },
+}
+
+impl KeyRole {
+    fn new(timelock: Option<u16>, path: &PathInfo) -> Self {
+        let (threshold, keys) = match path {
+            PathInfo::Single(_) => (1, 1),
+            PathInfo::Multi(k, keys) => (*k, keys.len()),
+        };
+        match timelock {
+            None => Self::Primary { threshold, keys },
+            Some(timelock) => Self::Recovery {
+                timelock,
+                threshold,
+                keys,
+            },
+        }
+    }
+}
+
+impl fmt::Display for KeyRole {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (threshold, keys) = match self {
+            Self::Primary { threshold, keys } => {
+                write!(f, "Primary: can spend at any time")?;
+                (threshold, keys)
+            }
+            Self::Recovery {
+                timelock,
+                threshold,
+                keys,
+            } => {
+                write!(
+                    f,
+                    "Recovery: can spend coins which haven't moved for {} blocks (about {} days)",
+                    timelock,
+                    timelock / 144
+                )?;
+                (threshold, keys)
+            }
+        };
+        if *keys == 1 {
+            write!(f, ", alone.")
+        } else {
+            write!(f, ", with {} of the {} keys of this path.", threshold, keys)
+        }
+    }
+}
+
+/// What a single signer needs to know about its keys in the descriptor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeySheet {
+    /// Master fingerprint of the signer.
+    pub fingerprint: bip32::Fingerprint,
+    /// The alias of the signer in the wallet, if any.
+    pub name: Option<String>,
+    /// The spending paths the signer's keys are part of.
+    pub roles: Vec<KeyRole>,
+    /// The signer's keys, with their origin.
+    pub keys: Vec<DescriptorPublicKey>,
+}
+
+impl fmt::Display for KeySheet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "LIANA KEY SHEET")?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "Signer: {}",
+            self.name.as_deref().unwrap_or("(no alias)")
+        )?;
+        writeln!(f, "Fingerprint: {}", self.fingerprint)?;
+        writeln!(f)?;
+        writeln!(f, "Roles:")?;
+        for role in &self.roles {
+            writeln!(f, "- {}", role)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "Keys:")?;
+        for key in &self.keys {
+            writeln!(f, "- {}", key)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "Instructions:")?;
+        writeln!(
+            f,
+            "- Keep the signing device and the backup of its seed safe, and separate from \
+             each other."
+        )?;
+        writeln!(
+            f,
+            "- Check the fingerprint above matches the one displayed by your signing device."
+        )?;
+        writeln!(
+            f,
+            "- This sheet doesn't contain the wallet descriptor. The coordinator of the wallet \
+             keeps it and will send you the transactions to sign."
+        )?;
+        writeln!(
+            f,
+            "- Only sign a transaction after checking its recipients and amounts on your \
+             signing device."
+        )
+    }
+}
+
+/// Create a key sheet for each signer of the descriptor, ordered by master fingerprint.
+pub fn key_sheets(
+    desc: &LianaDescriptor,
+    aliases: &HashMap<bip32::Fingerprint, String>,
+) -> Vec<KeySheet> {
+    let policy = desc.policy();
+    let mut sheets: BTreeMap<bip32::Fingerprint, KeySheet> = BTreeMap::new();
+    let paths = std::iter::once((None, policy.primary_path())).chain(
+        policy
+            .recovery_paths()
+            .iter()
+            .map(|(timelock, path)| (Some(*timelock), path)),
+    );
+    for (timelock, path) in paths {
+        let keys = match path {
+            PathInfo::Single(key) => std::slice::from_ref(key),
+            PathInfo::Multi(_, keys) => keys.as_slice(),
+        };
+        for key in keys {
+            let fingerprint = key.master_fingerprint();
+            let sheet = sheets.entry(fingerprint).or_insert_with(|| KeySheet {
+                fingerprint,
+                name: aliases.get(&fingerprint).cloned(),
+                roles: Vec::new(),
+                keys: Vec::new(),
+            });
+            let role = KeyRole::new(timelock, path);
+            // A signer may have several keys in the same path.
+            if sheet.roles.last() != Some(&role) {
+                sheet.roles.push(role);
+            }
+            sheet.keys.push(key.clone());
+        }
+    }
+    sheets.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn per_signer_sheets() {
+        // A 1-of-2 primary path and a 2-of-2 recovery path of the same signers after 1000 blocks.
+        let desc = LianaDescriptor::from_str("wsh(or_d(multi(1,[573fb35b/48'/1'/0'/2']tpubDFKp9T7WAYDcENSjoifkrpq1gMDF47KGJcJrpxzX23Qor8wuGbrEVs9utNq1MDS8E2WXJSBk1qoPQLpwyokW7DiUNPwFuxQkL7owNkLAb9W/<0;1>/*,[573fb35c/48'/1'/1'/2']tpubDFGezyzuHJPhdP3jHGW7v7Hwes4Hihqv5W2yyCmRY9VZJCRchETvxrMC8uECeJZdxQ14V4iD4DecoArkUSDwj8ogYE9WEv4MNZr12thNHCs/<0;1>/*),and_v(v:multi(2,[573fb35b/48'/1'/2'/2']tpubDDwxQauiaU964vPzt5Vd7jnDHEUtp2Vc34PaWpEXg5TQ3bRccxnc1MKKh88Hi7xiMeZo9Tm6fBcq4UGXqnDtGUniJLjqAD8SjQ8Eci3aSR7/<0;1>/*,[573fb35c/48'/1'/3'/2']tpubDE37XAVB5CQ1x85md3BQ5uHCoMwT5fgT8X13zzCUQ3x5o2jskYxKjj7Qcxt1Jpj4QB8tqspn2dooPCekRuQDYrDHov7J1ueUNu2wcvgRDxr/<0;1>/*),older(1000))))#fccaqlhh").unwrap();
+        let fg_a = bip32::Fingerprint::from_str("573fb35b").unwrap();
+        let aliases = HashMap::from([(fg_a, "Alice".to_string())]);
+
+        let sheets = key_sheets(&desc, &aliases);
+        assert_eq!(sheets.len(), 2);
+        let sheet = &sheets[0];
+        assert_eq!(sheet.fingerprint, fg_a);
+        assert_eq!(sheet.name.as_deref(), Some("Alice"));
+        assert_eq!(
+            sheet.roles,
+            vec![
+                KeyRole::Primary {
+                    threshold: 1,
+                    keys: 2
+                },
+                KeyRole::Recovery {
+                    timelock: 1000,
+                    threshold: 2,
+                    keys: 2
+                }
+            ]
+        );
+        assert_eq!(sheet.keys.len(), 2);
+        assert!(sheets[1].name.is_none());
+
+        // The sheet only reveals the signer's own keys.
+        let doc = sheet.to_string();
+        assert!(doc.contains("tpubDFKp9T7WAYDcENSjoifkrpq1gMDF47KGJcJrpxzX23Qor8wuGbrEVs9utNq1MDS8E2WXJSBk1qoPQLpwyokW7DiUNPwFuxQkL7owNkLAb9W"));
+        assert!(!doc.contains("573fb35c"));
+        assert!(!doc.contains("tpubDFGezyzuHJPhdP3jHGW7v7Hwes4Hihqv5W2yyCmRY9VZJCRchETvxrMC8uECeJZdxQ14V4iD4DecoArkUSDwj8ogYE9WEv4MNZr12thNHCs"));
+    }
+}
@@ -1,7 +1,87 @@
+pub mod bip322;
 pub mod descriptors;
+#[cfg(feature = "signer")]
 pub mod random;
+#[cfg(feature = "signer")]
 pub mod signer;
+pub mod silent_payments;
 pub mod spend;
+pub mod ur;
 
+#[cfg(feature = "signer")]
 pub use bip39;
 pub use miniscript;
+
+#[cfg(test)]
+mod tests {
+    // The modules which handle descriptors and PSBTs we may be given, along with the number of
+    // `expect()`s, `unreachable!()`s and `assert!()`s in their non-test code. Those are reserved
+    // to invariants external inputs can't break, errors must be returned otherwise. Raising one
+    // of these numbers should be justified in review.
+    const AUDITED_MODULES: &[(&str, &str, usize)] = &[
+        ("bip322.rs", include_str!("bip322.rs"), 1),
+        (
+            "descriptors/analysis.rs",
+            include_str!("descriptors/analysis.rs"),
+            8,
+        ),
+        (
+            "descriptors/keys.rs",
+            include_str!("descriptors/keys.rs"),
+            0,
+        ),
+        (
+            "descriptors/lint.rs",
+            include_str!("descriptors/lint.rs"),
+            0,
+        ),
+        (
+            "descriptors/loss.rs",
+            include_str!("descriptors/loss.rs"),
+            0,
+        ),
+        ("descriptors/mod.rs", include_str!("descriptors/mod.rs"), 26),
+        (
+            "descriptors/sheet.rs",
+            include_str!("descriptors/sheet.rs"),
+            0,
+        ),
+        ("silent_payments.rs", include_str!("silent_payments.rs"), 0),
+        ("spend.rs", include_str!("spend.rs"), 13),
+        ("ur.rs", include_str!("ur.rs"), 0),
+    ];
+
+    // Never acceptable outside of tests in the audited modules.
+    const DENIED: &[&str] = &[".unwrap()", "panic!(", "todo!(", "unimplemented!("];
+
+    fn count_asserts(code: &str, assert: &str) -> usize {
+        code.matches(assert).count() - code.matches(&format!("debug_{}", assert)).count()
+    }
+
+    #[test]
+    fn panic_policy() {
+        for (name, source, max_panics) in AUDITED_MODULES {
+            let code = source.split("#[cfg(test)]").next().unwrap_or(source);
+            for denied in DENIED {
+                assert!(
+                    !code.contains(denied),
+                    "'{}' is not allowed in {}. Return an error instead.",
+                    denied,
+                    name
+                );
+            }
+            let panics = code.matches(".expect(").count()
+                + code.matches("unreachable!(").count()
+                + count_asserts(code, "assert!(")
+                + count_asserts(code, "assert_eq!(")
+                + count_asserts(code, "assert_ne!(");
+            assert!(
+                panics <= *max_panics,
+                "{} has {} possible panics, at most {} are allowed. Return an error instead.",
+                name,
+                panics,
+                max_panics
+            );
+        }
+    }
+}
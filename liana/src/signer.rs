@@ -3,7 +3,9 @@
 //! Some helpers to facilitate the usage of a signer in client of the Liana daemon. For now
 //! only contains a hot signer.
 
-use crate::random;
+use crate::{random, silent_payments};
+
+pub mod slip39;
 
 use std::{
     convert::TryInto,
@@ -28,6 +30,7 @@ use miniscript::bitcoin::{
 pub enum SignerError {
     Randomness(random::RandomnessError),
     Mnemonic(bip39::Error),
+    Slip39(slip39::Slip39Error),
     Bip32(Bip32Error),
     MnemonicStorage(io::Error),
     InsanePsbt,
@@ -39,6 +42,7 @@ impl fmt::Display for SignerError {
         match self {
             Self::Randomness(s) => write!(f, "Error related to getting randomness: {}", s),
             Self::Mnemonic(s) => write!(f, "Error when working with mnemonics: {}", s),
+            Self::Slip39(e) => write!(f, "Error when combining SLIP-39 shares: {}", e),
             Self::Bip32(e) => write!(f, "BIP32 error: {}", e),
             Self::MnemonicStorage(e) => write!(f, "BIP39 mnemonic storage error: {}", e),
             Self::InsanePsbt => write!(f, "Information contained in the PSBT is wrong."),
@@ -56,12 +60,19 @@ pub const MNEMONICS_FOLDER_NAME: &str = "mnemonics";
 
 // TODO: zeroize, mlock, etc.. For now we don't even encrypt the seed on disk so that'd be
 // overkill.
-/// A signer that keeps the key on the laptop. Based on BIP39.
+/// A signer that keeps the key on the laptop. Based on BIP39, or on SLIP-39 shares.
 pub struct HotSigner {
-    mnemonic: bip39::Mnemonic,
+    backup: Backup,
     master_xpriv: bip32::Xpriv,
 }
 
+// What the master key of a hot signer is derived from.
+enum Backup {
+    Bip39(bip39::Mnemonic),
+    // One mnemonic per share.
+    Slip39(Vec<String>),
+}
+
 // TODO: instead of copying them here we could have a util module with those helpers.
 // Create a directory with no permission for group and other users.
 fn create_dir(path: &path::Path) -> io::Result<()> {
@@ -107,7 +118,32 @@ impl HotSigner {
         let master_xpriv =
             bip32::Xpriv::new_master(network, &mnemonic.to_seed("")).map_err(SignerError::Bip32)?;
         Ok(Self {
-            mnemonic,
+            backup: Backup::Bip39(mnemonic),
+            master_xpriv,
+        })
+    }
+
+    /// Create a hot signer from the master secret shared between these SLIP-39 shares, one
+    /// mnemonic per share, without a passphrase.
+    pub fn from_slip39_shares<S: AsRef<str>>(
+        network: bitcoin::Network,
+        shares: &[S],
+    ) -> Result<Self, SignerError> {
+        let secret = slip39::combine_shares(shares, "").map_err(SignerError::Slip39)?;
+        let master_xpriv =
+            bip32::Xpriv::new_master(network, &secret).map_err(SignerError::Bip32)?;
+        let shares = shares
+            .iter()
+            .map(|s| {
+                s.as_ref()
+                    .split_whitespace()
+                    .map(str::to_lowercase)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+        Ok(Self {
+            backup: Backup::Slip39(shares),
             master_xpriv,
         })
     }
@@ -121,7 +157,14 @@ impl HotSigner {
         Self::from_mnemonic(network, mnemonic)
     }
 
+    /// Create a hot signer from a BIP39 mnemonic, or from SLIP-39 shares one per line.
     pub fn from_str(network: bitcoin::Network, s: &str) -> Result<Self, SignerError> {
+        // BIP39 mnemonics have 12, 15, 18, 21 or 24 words and SLIP-39 shares at least 20.
+        let lines: Vec<&str> = s.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+        let word_count = s.split_whitespace().count();
+        if lines.len() > 1 || (word_count >= 20 && word_count != 21 && word_count != 24) {
+            return Self::from_slip39_shares(network, &lines);
+        }
         let mnemonic = bip39::Mnemonic::from_str(s).map_err(SignerError::Mnemonic)?;
         Self::from_mnemonic(network, mnemonic)
     }
@@ -154,16 +197,25 @@ impl HotSigner {
         Ok(signers)
     }
 
-    /// The BIP39 mnemonics from which the master key of this signer is derived.
-    pub fn words(&self) -> [&'static str; 12] {
-        let words: Vec<&'static str> = self.mnemonic.words().collect();
-        words.try_into().expect("Always 12 words")
+    /// The BIP39 mnemonics from which the master key of this signer is derived, if it wasn't
+    /// created from SLIP-39 shares.
+    pub fn words(&self) -> Option<[&'static str; 12]> {
+        match &self.backup {
+            Backup::Bip39(mnemonic) => {
+                let words: Vec<&'static str> = mnemonic.words().collect();
+                Some(words.try_into().expect("Always 12 words"))
+            }
+            Backup::Slip39(_) => None,
+        }
     }
 
-    /// The BIP39 mnemonic words as a string.
+    /// The BIP39 mnemonic words as a string, or the SLIP-39 shares one per line.
     pub fn mnemonic_str(&self) -> String {
+        let words = match &self.backup {
+            Backup::Bip39(_) => self.words().expect("A BIP39 mnemonic"),
+            Backup::Slip39(shares) => return shares.join("\n"),
+        };
         let mut mnemonic_str = String::with_capacity(12 * 7);
-        let words = self.words();
 
         for (i, word) in words.iter().enumerate() {
             mnemonic_str += word;
@@ -229,6 +281,20 @@ impl HotSigner {
         bip32::Xpub::from_priv(secp, &xpriv)
     }
 
+    /// Get the Silent Payments (BIP352) scan and spend secret keys (in this order) of the first
+    /// account of this signer.
+    pub fn silent_payment_keys(
+        &self,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Signing>,
+    ) -> (secp256k1::SecretKey, secp256k1::SecretKey) {
+        let network = self.master_xpriv.network;
+        let key_at = |path| self.xpriv_at(&path, secp).private_key;
+        (
+            key_at(silent_payments::scan_key_path(network)),
+            key_at(silent_payments::spend_key_path(network)),
+        )
+    }
+
     // Provide an ECDSA signature for this transaction input from the PSBT input information.
     fn sign_p2wsh(
         &self,
@@ -249,7 +315,10 @@ impl HotSigner {
             .as_ref()
             .ok_or(SignerError::IncompletePsbt)?
             .value;
-        let sighash_type = sighash::EcdsaSighashType::All;
+        // Use the sighash type requested in the PSBT input, if any.
+        let sighash_type = psbt_in
+            .ecdsa_hash_ty()
+            .map_err(|_| SignerError::InsanePsbt)?;
         let sighash = sighash_cache
             .p2wsh_signature_hash(input_index, witscript, value, sighash_type)
             .map_err(|_| SignerError::InsanePsbt)?;
@@ -289,7 +358,9 @@ impl HotSigner {
         psbt_in: &mut PsbtIn,
         input_index: usize,
     ) -> Result<(), SignerError> {
-        let sighash_type = sighash::TapSighashType::Default;
+        let sighash_type = psbt_in
+            .taproot_hash_ty()
+            .map_err(|_| SignerError::InsanePsbt)?;
         let prevouts = sighash::Prevouts::All(prevouts);
 
         // If the details of the internal key are filled, provide a keypath signature.
@@ -459,6 +530,27 @@ mod tests {
             &bip32::DerivationPath::from_str("m/42'/43/0987'/0/2").unwrap(),
             &secp,
         );
+
+        // Its Silent Payment keys are at the BIP352 derivation paths.
+        let (scan_key, spend_key) = signer.silent_payment_keys(&secp);
+        assert_eq!(
+            signer
+                .xpub_at(
+                    &bip32::DerivationPath::from_str("m/352'/0'/0'/1'/0").unwrap(),
+                    &secp
+                )
+                .public_key,
+            scan_key.public_key(&secp)
+        );
+        assert_eq!(
+            signer
+                .xpub_at(
+                    &bip32::DerivationPath::from_str("m/352'/0'/0'/0'/0").unwrap(),
+                    &secp
+                )
+                .public_key,
+            spend_key.public_key(&secp)
+        );
     }
 
     #[test]
@@ -485,6 +577,42 @@ mod tests {
         fs::remove_dir_all(tmp_dir).unwrap();
     }
 
+    #[test]
+    fn hot_signer_slip39() {
+        let secp = secp256k1::Secp256k1::signing_only();
+        let tmp_dir = tmp_dir();
+        fs::create_dir_all(&tmp_dir).unwrap();
+        let network = bitcoin::Network::Bitcoin;
+
+        // A 2-of-3 share set.
+        let shares = [
+            "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed",
+            "Shadow Pistol academic acid actress prayer class unknown daughter sweater depict flip twice unkind craft early superior advocate guest smoking",
+        ];
+        let signer = HotSigner::from_slip39_shares(network, &shares).unwrap();
+        assert!(signer.words().is_none());
+        let fingerprint = signer.fingerprint(&secp);
+        assert_eq!(
+            HotSigner::from_str(network, &shares.join("\n"))
+                .unwrap()
+                .fingerprint(&secp),
+            fingerprint
+        );
+        assert!(matches!(
+            HotSigner::from_slip39_shares(network, &shares[..1]),
+            Err(SignerError::Slip39(_))
+        ));
+
+        // It is stored as its shares.
+        signer.store(&tmp_dir, network, &secp).unwrap();
+        let signers = HotSigner::from_datadir(&tmp_dir, network).unwrap();
+        assert_eq!(signers.len(), 1);
+        assert_eq!(signers[0].fingerprint(&secp), fingerprint);
+        assert_eq!(signers[0].mnemonic_str(), shares.join("\n").to_lowercase());
+
+        fs::remove_dir_all(tmp_dir).unwrap();
+    }
+
     #[test]
     fn hot_signer_sign_p2wsh() {
         let secp = secp256k1::Secp256k1::new();
@@ -744,6 +872,20 @@ mod tests {
         let psbt = prim_signer_b.sign_psbt(psbt, &secp).unwrap();
         assert!(psbt.inputs[0].partial_sigs.is_empty());
         assert_eq!(psbt.inputs[1].partial_sigs.len(), 1);
+
+        // It signs with the sighash type requested in the PSBT input.
+        let mut psbt = dummy_psbt.clone();
+        psbt.inputs[0].sighash_type =
+            Some(bitcoin::EcdsaSighashType::SinglePlusAnyoneCanPay.into());
+        let psbt = prim_signer_a.sign_psbt(psbt, &secp).unwrap();
+        assert!(psbt.inputs[0]
+            .partial_sigs
+            .values()
+            .all(|sig| sig.sighash_type == bitcoin::EcdsaSighashType::SinglePlusAnyoneCanPay));
+        assert!(psbt.inputs[1]
+            .partial_sigs
+            .values()
+            .all(|sig| sig.sighash_type == bitcoin::EcdsaSighashType::All));
     }
 
     #[test]
@@ -0,0 +1,492 @@
+//! Recover a master secret from SLIP-39 shares.
+//!
+//! See https://github.com/satoshilabs/slips/blob/master/slip-0039.md. Only the combination of
+//! shares is implemented, we never split secrets.
+
+use std::{collections::BTreeMap, error, fmt};
+
+use miniscript::bitcoin::hashes::{hmac, sha256, Hash, HashEngine};
+
+/// The 1024 words of the SLIP-39 wordlist, sorted.
+const WORDLIST: &str = include_str!("slip39_wordlist.txt");
+
+const RADIX_BITS: usize = 10;
+// Two words for the identifier, extendable flag and iteration exponent, two for the group and
+// member parameters.
+const METADATA_WORDS: usize = 4;
+const CHECKSUM_WORDS: usize = 3;
+// A 128 bits secret takes 13 words.
+const MIN_SHARE_WORDS: usize = METADATA_WORDS + 13 + CHECKSUM_WORDS;
+const MIN_SECRET_BYTES: usize = 16;
+
+const CUSTOMIZATION_STRING: &[u8] = b"shamir";
+const CUSTOMIZATION_STRING_EXTENDABLE: &[u8] = b"shamir_extendable";
+const BASE_ITERATION_COUNT: u32 = 10_000;
+const ROUND_COUNT: u8 = 4;
+
+// The x coordinates of the secret and of its digest in the sharing polynomial.
+const SECRET_INDEX: u8 = 255;
+const DIGEST_INDEX: u8 = 254;
+const DIGEST_BYTES: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Slip39Error {
+    NoShare,
+    UnknownWord(String),
+    InvalidLength(usize),
+    InvalidChecksum,
+    InvalidPadding,
+    InvalidSecretLength,
+    /// The group threshold is higher than the number of groups.
+    InvalidGroupThreshold,
+    /// The shares are not all part of the same set.
+    Mismatch,
+    MemberThresholdMismatch {
+        group: u8,
+    },
+    DuplicateMember {
+        group: u8,
+        member: u8,
+    },
+    /// Not enough groups have enough shares to recover the secret.
+    InsufficientShares {
+        group_threshold: u8,
+    },
+    InvalidDigest,
+}
+
+impl fmt::Display for Slip39Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoShare => write!(f, "No share was given."),
+            Self::UnknownWord(w) => write!(f, "'{}' is not a SLIP-39 word.", w),
+            Self::InvalidLength(l) => write!(f, "Invalid share length of {} words.", l),
+            Self::InvalidChecksum => write!(f, "Invalid share checksum."),
+            Self::InvalidPadding => write!(f, "Invalid share padding."),
+            Self::InvalidSecretLength => write!(f, "Invalid length of the shared secret."),
+            Self::InvalidGroupThreshold => {
+                write!(
+                    f,
+                    "The group threshold is higher than the number of groups."
+                )
+            }
+            Self::Mismatch => write!(f, "The shares are not all part of the same set."),
+            Self::MemberThresholdMismatch { group } => write!(
+                f,
+                "The shares of group {} don't all have the same threshold.",
+                group + 1
+            ),
+            Self::DuplicateMember { group, member } => write!(
+                f,
+                "Two different shares have the index {} in group {}.",
+                member + 1,
+                group + 1
+            ),
+            Self::InsufficientShares { group_threshold } => write!(
+                f,
+                "Not enough shares: {} group(s) must each have enough shares to reach their \
+                 threshold.",
+                group_threshold
+            ),
+            Self::InvalidDigest => write!(
+                f,
+                "The shares don't combine to a valid secret. One of them may be wrong."
+            ),
+        }
+    }
+}
+
+impl error::Error for Slip39Error {}
+
+/// Whether this word is part of the SLIP-39 wordlist.
+pub fn is_word(word: &str) -> bool {
+    word_index(word).is_some()
+}
+
+fn word_index(word: &str) -> Option<u16> {
+    let words: Vec<&str> = WORDLIST.split_whitespace().collect();
+    words.binary_search(&word).ok().map(|i| i as u16)
+}
+
+// The Reed-Solomon code over GF(1024) used for the share checksum.
+fn rs1024_polymod(values: impl Iterator<Item = u32>) -> u32 {
+    const GEN: [u32; 10] = [
+        0x00e0_e040,
+        0x01c1_c080,
+        0x0383_8100,
+        0x0707_0200,
+        0x0e0e_0009,
+        0x1c0c_2412,
+        0x3808_6c24,
+        0x3090_fc48,
+        0x21b1_f890,
+        0x03f3_f120,
+    ];
+    values.fold(1, |chk, value| {
+        let b = chk >> 20;
+        GEN.iter()
+            .enumerate()
+            .filter(|(i, _)| (b >> i) & 1 == 1)
+            .fold(((chk & 0xf_ffff) << 10) ^ value, |chk, (_, gen)| chk ^ gen)
+    })
+}
+
+/// A single share, as decoded from its mnemonic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Share {
+    identifier: u16,
+    extendable: bool,
+    iteration_exponent: u8,
+    group_index: u8,
+    group_threshold: u8,
+    group_count: u8,
+    member_index: u8,
+    member_threshold: u8,
+    value: Vec<u8>,
+}
+
+impl Share {
+    fn from_mnemonic(mnemonic: &str) -> Result<Self, Slip39Error> {
+        let words = mnemonic
+            .split_whitespace()
+            .map(|w| {
+                let w = w.to_lowercase();
+                word_index(&w)
+                    .map(u32::from)
+                    .ok_or(Slip39Error::UnknownWord(w))
+            })
+            .collect::<Result<Vec<u32>, _>>()?;
+        if words.len() < MIN_SHARE_WORDS {
+            return Err(Slip39Error::InvalidLength(words.len()));
+        }
+
+        let extendable = (words[1] >> 4) & 1 == 1;
+        let customization = if extendable {
+            CUSTOMIZATION_STRING_EXTENDABLE
+        } else {
+            CUSTOMIZATION_STRING
+        };
+        let values = customization
+            .iter()
+            .map(|c| u32::from(*c))
+            .chain(words.iter().copied());
+        if rs1024_polymod(values) != 1 {
+            return Err(Slip39Error::InvalidChecksum);
+        }
+
+        // The value is left-padded with zeros to a multiple of the word size.
+        let value_words = &words[METADATA_WORDS..words.len() - CHECKSUM_WORDS];
+        let value_bits = value_words.len() * RADIX_BITS;
+        let padding = value_bits % 16;
+        if padding > 8 {
+            return Err(Slip39Error::InvalidPadding);
+        }
+        let mut bits = value_words
+            .iter()
+            .flat_map(|w| (0..RADIX_BITS).rev().map(move |i| (w >> i) & 1 == 1));
+        if bits.by_ref().take(padding).any(|b| b) {
+            return Err(Slip39Error::InvalidPadding);
+        }
+        let bits: Vec<bool> = bits.collect();
+        let value: Vec<u8> = bits
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0, |acc, b| (acc << 1) | u8::from(*b)))
+            .collect();
+        if value.len() < MIN_SECRET_BYTES || value.len() % 2 != 0 {
+            return Err(Slip39Error::InvalidSecretLength);
+        }
+
+        let share = Share {
+            identifier: ((words[0] << 5) | (words[1] >> 5)) as u16,
+            extendable,
+            iteration_exponent: (words[1] & 0xf) as u8,
+            group_index: (words[2] >> 6) as u8,
+            group_threshold: ((words[2] >> 2) & 0xf) as u8 + 1,
+            group_count: (((words[2] & 0x3) << 2) | (words[3] >> 8)) as u8 + 1,
+            member_index: ((words[3] >> 4) & 0xf) as u8,
+            member_threshold: (words[3] & 0xf) as u8 + 1,
+            value,
+        };
+        if share.group_threshold > share.group_count {
+            return Err(Slip39Error::InvalidGroupThreshold);
+        }
+        Ok(share)
+    }
+
+    // The parameters that must be shared by all the shares of a set.
+    fn common_parameters(&self) -> (u16, bool, u8, u8, u8, usize) {
+        (
+            self.identifier,
+            self.extendable,
+            self.iteration_exponent,
+            self.group_threshold,
+            self.group_count,
+            self.value.len(),
+        )
+    }
+}
+
+// Arithmetic in GF(256) with the Rijndael polynomial, using log and exp tables.
+struct Gf256 {
+    exp: [u8; 255],
+    log: [u8; 256],
+}
+
+impl Gf256 {
+    fn new() -> Self {
+        let mut gf = Self {
+            exp: [0; 255],
+            log: [0; 256],
+        };
+        let mut poly: u16 = 1;
+        for power in 0..255 {
+            gf.exp[power] = poly as u8;
+            gf.log[poly as usize] = power as u8;
+            // Multiply by x + 1 and reduce by x^8 + x^4 + x^3 + x + 1.
+            poly ^= poly << 1;
+            if poly & 0x100 != 0 {
+                poly ^= 0x11b;
+            }
+        }
+        gf
+    }
+
+    /// Evaluate at x the polynomial going through these points, which must have distinct x
+    /// coordinates and values of the same length.
+    fn interpolate(&self, points: &[(u8, &[u8])], x: u8) -> Vec<u8> {
+        if let Some((_, y)) = points.iter().find(|(px, _)| *px == x) {
+            return y.to_vec();
+        }
+        let log = |v: u8| usize::from(self.log[usize::from(v)]);
+        let log_prod: usize = points.iter().map(|(px, _)| log(px ^ x)).sum();
+        let mut res = vec![0; points[0].1.len()];
+        for (px, y) in points {
+            let log_denominator: usize = points
+                .iter()
+                .filter(|(ox, _)| ox != px)
+                .map(|(ox, _)| log(px ^ ox))
+                .sum();
+            let log_basis = (log_prod + 255 * points.len() - log(px ^ x) - log_denominator) % 255;
+            for (r, v) in res.iter_mut().zip(y.iter()) {
+                if *v != 0 {
+                    *r ^= self.exp[(log(*v) + log_basis) % 255];
+                }
+            }
+        }
+        res
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[&[u8]]) -> [u8; 32] {
+    let mut engine = hmac::HmacEngine::<sha256::Hash>::new(key);
+    for d in data {
+        engine.input(d);
+    }
+    hmac::Hmac::<sha256::Hash>::from_engine(engine).to_byte_array()
+}
+
+// Recover the secret shared between these points, of which there must be at least the threshold.
+fn recover_secret(
+    gf: &Gf256,
+    threshold: u8,
+    points: &[(u8, &[u8])],
+) -> Result<Vec<u8>, Slip39Error> {
+    if threshold == 1 {
+        return Ok(points[0].1.to_vec());
+    }
+    let secret = gf.interpolate(points, SECRET_INDEX);
+    let digest = gf.interpolate(points, DIGEST_INDEX);
+    let (digest, random_part) = digest.split_at(DIGEST_BYTES);
+    if hmac_sha256(random_part, &[&secret])[..DIGEST_BYTES] != *digest {
+        return Err(Slip39Error::InvalidDigest);
+    }
+    Ok(secret)
+}
+
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32, out: &mut [u8]) {
+    for (i, chunk) in out.chunks_mut(32).enumerate() {
+        let block_index = (i as u32 + 1).to_be_bytes();
+        let mut u = hmac_sha256(password, &[salt, &block_index]);
+        let mut block = u;
+        for _ in 1..iterations {
+            u = hmac_sha256(password, &[&u]);
+            block.iter_mut().zip(u.iter()).for_each(|(b, u)| *b ^= u);
+        }
+        chunk.copy_from_slice(&block[..chunk.len()]);
+    }
+}
+
+// Decrypt the master secret using the Feistel network defined by SLIP-39.
+fn decrypt(encrypted: &[u8], passphrase: &[u8], share: &Share) -> Vec<u8> {
+    let mut salt = Vec::new();
+    if !share.extendable {
+        salt.extend_from_slice(CUSTOMIZATION_STRING);
+        salt.extend_from_slice(&share.identifier.to_be_bytes());
+    }
+    let iterations = (BASE_ITERATION_COUNT / u32::from(ROUND_COUNT)) << share.iteration_exponent;
+    let (left, right) = encrypted.split_at(encrypted.len() / 2);
+    let (mut left, mut right) = (left.to_vec(), right.to_vec());
+    for round in (0..ROUND_COUNT).rev() {
+        let password: Vec<u8> = std::iter::once(round)
+            .chain(passphrase.iter().copied())
+            .collect();
+        let mut round_salt = salt.clone();
+        round_salt.extend_from_slice(&right);
+        let mut f = vec![0; right.len()];
+        pbkdf2_sha256(&password, &round_salt, iterations, &mut f);
+        let new_right: Vec<u8> = left.iter().zip(f.iter()).map(|(l, f)| l ^ f).collect();
+        left = std::mem::replace(&mut right, new_right);
+    }
+    right.extend_from_slice(&left);
+    right
+}
+
+/// Recover the master secret from a set of SLIP-39 shares, one mnemonic per share.
+///
+/// Groups which don't have enough shares to reach their threshold are ignored, as long as enough
+/// other groups do.
+pub fn combine_shares<S: AsRef<str>>(
+    mnemonics: &[S],
+    passphrase: &str,
+) -> Result<Vec<u8>, Slip39Error> {
+    let shares = mnemonics
+        .iter()
+        .map(|m| Share::from_mnemonic(m.as_ref()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let first = shares.first().ok_or(Slip39Error::NoShare)?;
+    if shares
+        .iter()
+        .any(|s| s.common_parameters() != first.common_parameters())
+    {
+        return Err(Slip39Error::Mismatch);
+    }
+
+    // The member threshold and the shares of each group, by member index.
+    let mut groups: BTreeMap<u8, (u8, BTreeMap<u8, &[u8]>)> = BTreeMap::new();
+    for share in &shares {
+        let (threshold, members) = groups
+            .entry(share.group_index)
+            .or_insert_with(|| (share.member_threshold, BTreeMap::new()));
+        if *threshold != share.member_threshold {
+            return Err(Slip39Error::MemberThresholdMismatch {
+                group: share.group_index,
+            });
+        }
+        if let Some(value) = members.insert(share.member_index, &share.value) {
+            if value != share.value.as_slice() {
+                return Err(Slip39Error::DuplicateMember {
+                    group: share.group_index,
+                    member: share.member_index,
+                });
+            }
+        }
+    }
+
+    let gf = Gf256::new();
+    let complete_groups: Vec<_> = groups
+        .iter()
+        .filter(|(_, (threshold, members))| members.len() >= usize::from(*threshold))
+        .take(usize::from(first.group_threshold))
+        .collect();
+    if complete_groups.len() < usize::from(first.group_threshold) {
+        return Err(Slip39Error::InsufficientShares {
+            group_threshold: first.group_threshold,
+        });
+    }
+    let group_secrets = complete_groups
+        .into_iter()
+        .map(|(index, (threshold, members))| {
+            let points: Vec<(u8, &[u8])> = members
+                .iter()
+                .take(usize::from(*threshold))
+                .map(|(i, v)| (*i, *v))
+                .collect();
+            recover_secret(&gf, *threshold, &points).map(|secret| (*index, secret))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let points: Vec<(u8, &[u8])> = group_secrets
+        .iter()
+        .map(|(i, secret)| (*i, secret.as_slice()))
+        .collect();
+    let encrypted = recover_secret(&gf, first.group_threshold, &points)?;
+
+    Ok(decrypt(&encrypted, passphrase.as_bytes(), first))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miniscript::bitcoin::hex::DisplayHex;
+
+    // Test vectors from the SLIP-39 specification, which use the "TREZOR" passphrase.
+    #[test]
+    fn slip39_vectors() {
+        let valid = [
+            (
+                vec!["duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision keyboard"],
+                "bb54aac4b89dc868ba37d9cc21b2cece",
+            ),
+            (
+                vec![
+                    "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed",
+                    "shadow pistol academic acid actress prayer class unknown daughter sweater depict flip twice unkind craft early superior advocate guest smoking",
+                ],
+                "b43ceb7e57a0ea8766221624d01b0864",
+            ),
+            (
+                vec![
+                    "eraser senior decision smug corner ruin rescue cubic angel tackle skin skunk program roster trash rumor slush angel flea amazing",
+                    "eraser senior beard romp adorn nuclear spill corner cradle style ancient family general leader ambition exchange unusual garlic promise voice",
+                    "eraser senior decision scared cargo theory device idea deliver modify curly include pancake both news skin realize vitamins away join",
+                ],
+                "7c3397a292a5941682d7a4ae2d898d11",
+            ),
+            (
+                vec![
+                    "humidity disease academic always aluminum jewelry energy woman receiver strategy amuse duckling lying evidence network walnut tactics forget hairy rebound impulse brother survive clothes stadium mailman rival ocean reward venture always armed unwrap",
+                    "humidity disease academic agency actress jacket gross physics cylinder solution fake mortgage benefit public busy prepare sharp friar change work slow purchase ruler again tricycle involve viral wireless mixture anatomy desert cargo upgrade",
+                ],
+                "c938b319067687e990e05e0da0ecce1278f75ff58d9853f19dcaeed5de104aae",
+            ),
+        ];
+        for (shares, secret) in valid {
+            assert_eq!(
+                combine_shares(&shares, "TREZOR")
+                    .unwrap()
+                    .to_lower_hex_string(),
+                secret
+            );
+        }
+
+        assert_eq!(
+            combine_shares(&["duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision kidney"], "TREZOR"),
+            Err(Slip39Error::InvalidChecksum)
+        );
+        assert_eq!(
+            combine_shares(&["shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed"], "TREZOR"),
+            Err(Slip39Error::InsufficientShares { group_threshold: 1 })
+        );
+        assert_eq!(
+            combine_shares(
+                &[
+                    "guilt walnut academic acid deliver remove equip listen vampire tactics nylon rhythm failure husband fatigue alive blind enemy teaspoon rebound",
+                    "guilt walnut academic agency brave hamster hobo declare herd taste alpha slim criminal mild arcade formal romp branch pink ambition",
+                ],
+                "TREZOR"
+            ),
+            Err(Slip39Error::InvalidDigest)
+        );
+        assert_eq!(
+            combine_shares(&["duckling enlarge academic"], "TREZOR"),
+            Err(Slip39Error::InvalidLength(3))
+        );
+        assert_eq!(
+            combine_shares(&["duckling enlarge bitcoin"], "TREZOR"),
+            Err(Slip39Error::UnknownWord("bitcoin".to_string()))
+        );
+        assert_eq!(
+            combine_shares::<&str>(&[], "TREZOR"),
+            Err(Slip39Error::NoShare)
+        );
+    }
+}
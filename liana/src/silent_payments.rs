@@ -0,0 +1,674 @@
+//! Silent Payments (BIP352) receiving.
+//!
+//! A Silent Payment address is made of a scan and a spend public keys. A payer tweaks the spend
+//! key with a secret shared between the keys of the inputs of its transaction and the scan key, so
+//! each payment goes to a Taproot output which can't be linked to the address nor to the other
+//! payments. The receiver finds them by computing the shared secret of each transaction with the
+//! scan secret key, and spends them with the spend secret key tweaked the same way.
+//!
+//! The outputs are single-key Taproot outputs, not outputs of the Liana descriptor: they are not
+//! protected by its recovery paths until swept into the wallet. Labels aren't supported.
+
+use std::{convert::TryInto, fmt};
+
+use miniscript::bitcoin::{
+    self,
+    bip32::{ChildNumber, DerivationPath},
+    consensus,
+    hashes::{hash160, sha256t_hash_newtype, Hash, HashEngine},
+    key::{Parity, TweakedPublicKey},
+    secp256k1::{self, PublicKey, Scalar, SecretKey, XOnlyPublicKey},
+    sighash, ScriptBuf, Transaction, TxIn, TxOut, Witness,
+};
+
+use bech32::{
+    primitives::{
+        decode::CheckedHrpstring,
+        iter::{ByteIterExt, Fe32IterExt},
+    },
+    Bech32m, Fe32, Hrp,
+};
+
+sha256t_hash_newtype! {
+    struct InputsTag = hash_str("BIP0352/Inputs");
+    struct InputsHash(_);
+
+    struct SharedSecretTag = hash_str("BIP0352/SharedSecret");
+    struct SharedSecretHash(_);
+}
+
+/// The "nothing up my sleeve" point of BIP341. A script path spend whose internal key is this one
+/// doesn't contribute to the shared secret.
+const NUMS_H: [u8; 32] = [
+    0x50, 0x92, 0x9b, 0x74, 0xc1, 0xa0, 0x49, 0x54, 0xb7, 0x8b, 0x4b, 0x60, 0x35, 0xe9, 0x7a, 0x5e,
+    0x07, 0x8a, 0x5a, 0x0f, 0x28, 0xec, 0x96, 0xd5, 0x47, 0xbf, 0xee, 0x9a, 0xce, 0x80, 0x3a, 0xc0,
+];
+
+// The size of the payload of a version 0 address: the two compressed public keys.
+const PAYLOAD_LEN: usize = 66;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SilentPaymentError {
+    /// The string isn't a Silent Payment address.
+    InvalidAddress(String),
+    /// The address is for another network.
+    NetworkMismatch(bitcoin::Network),
+    /// A payment can't be spent with these keys.
+    InvalidKey(String),
+    /// The transaction spending payments couldn't be signed.
+    Signing(String),
+}
+
+impl fmt::Display for SilentPaymentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidAddress(e) => write!(f, "Invalid Silent Payment address: {}", e),
+            Self::NetworkMismatch(net) => {
+                write!(f, "The Silent Payment address isn't for network '{}'.", net)
+            }
+            Self::InvalidKey(e) => write!(f, "Invalid Silent Payment key: {}", e),
+            Self::Signing(e) => write!(f, "Failed to sign for the Silent Payments: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SilentPaymentError {}
+
+// The human-readable part of the addresses on this network.
+fn hrp(network: bitcoin::Network) -> Hrp {
+    let hrp = match network {
+        bitcoin::Network::Bitcoin => "sp",
+        bitcoin::Network::Regtest => "sprt",
+        _ => "tsp",
+    };
+    Hrp::parse_unchecked(hrp)
+}
+
+// BIP352 derivation path m/352'/coin_type'/0'/<key_type>'/0 for the first account.
+fn key_path(network: bitcoin::NetworkKind, key_type: u32) -> DerivationPath {
+    let coin_type = if network == bitcoin::NetworkKind::Main {
+        0
+    } else {
+        1
+    };
+    [
+        ChildNumber::Hardened { index: 352 },
+        ChildNumber::Hardened { index: coin_type },
+        ChildNumber::Hardened { index: 0 },
+        ChildNumber::Hardened { index: key_type },
+        ChildNumber::Normal { index: 0 },
+    ]
+    .as_ref()
+    .into()
+}
+
+/// The derivation path of the scan key of the first account.
+pub fn scan_key_path(network: bitcoin::NetworkKind) -> DerivationPath {
+    key_path(network, 1)
+}
+
+/// The derivation path of the spend key of the first account.
+pub fn spend_key_path(network: bitcoin::NetworkKind) -> DerivationPath {
+    key_path(network, 0)
+}
+
+/// A Silent Payment address, without label.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SilentPaymentAddress {
+    pub network: bitcoin::Network,
+    pub scan_key: PublicKey,
+    pub spend_key: PublicKey,
+}
+
+impl SilentPaymentAddress {
+    /// Parse an address, which must be for this network.
+    pub fn from_str_network(
+        s: &str,
+        network: bitcoin::Network,
+    ) -> Result<SilentPaymentAddress, SilentPaymentError> {
+        let mut checked = CheckedHrpstring::new::<Bech32m>(s)
+            .map_err(|e| SilentPaymentError::InvalidAddress(e.to_string()))?;
+        if checked.hrp() != hrp(network) {
+            return Err(SilentPaymentError::NetworkMismatch(network));
+        }
+        let version = checked
+            .remove_witness_version()
+            .ok_or_else(|| SilentPaymentError::InvalidAddress("Missing version.".to_string()))?;
+        checked
+            .validate_segwit_padding()
+            .map_err(|e| SilentPaymentError::InvalidAddress(e.to_string()))?;
+        let payload: Vec<u8> = checked.byte_iter().collect();
+        // Later versions are meant to be backward compatible: their payload starts with the keys.
+        let valid_len = if version == Fe32::Q {
+            payload.len() == PAYLOAD_LEN
+        } else {
+            payload.len() >= PAYLOAD_LEN
+        };
+        if version == Fe32::L || !valid_len {
+            return Err(SilentPaymentError::InvalidAddress(format!(
+                "Invalid payload of {} bytes for version {}.",
+                payload.len(),
+                version.to_u8()
+            )));
+        }
+        let key = |bytes: &[u8]| {
+            PublicKey::from_slice(bytes)
+                .map_err(|e| SilentPaymentError::InvalidAddress(e.to_string()))
+        };
+        Ok(SilentPaymentAddress {
+            network,
+            scan_key: key(&payload[..33])?,
+            spend_key: key(&payload[33..PAYLOAD_LEN])?,
+        })
+    }
+}
+
+impl fmt::Display for SilentPaymentAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let hrp = hrp(self.network);
+        let (scan_key, spend_key) = (self.scan_key.serialize(), self.spend_key.serialize());
+        for c in scan_key
+            .iter()
+            .chain(spend_key.iter())
+            .copied()
+            .bytes_to_fes()
+            .with_checksum::<Bech32m>(&hrp)
+            .with_witness_version(Fe32::Q)
+            .chars()
+        {
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+// Parse a compressed public key. Uncompressed keys don't contribute to the shared secret.
+fn compressed_key(bytes: &[u8]) -> Option<PublicKey> {
+    if bytes.len() == 33 {
+        PublicKey::from_slice(bytes).ok()
+    } else {
+        None
+    }
+}
+
+/// The public key this input contributes to the shared secret, if it's an eligible input.
+pub fn input_public_key(txin: &TxIn, prevout: &TxOut) -> Option<PublicKey> {
+    let spk = &prevout.script_pubkey;
+    if spk.is_p2tr() {
+        let mut witness: Vec<&[u8]> = txin.witness.iter().collect();
+        if witness.len() > 1 && witness.last()?.first() == Some(&0x50) {
+            witness.pop();
+        }
+        // A script path spend from an unspendable internal key has no key to contribute.
+        if witness.len() > 1 && witness.last()?.get(1..33)? == NUMS_H {
+            return None;
+        }
+        let output_key = XOnlyPublicKey::from_slice(spk.as_bytes().get(2..34)?).ok()?;
+        Some(output_key.public_key(Parity::Even))
+    } else if spk.is_p2wpkh() {
+        compressed_key(txin.witness.last()?)
+    } else if spk.is_p2sh() {
+        // Only a wrapped P2WPKH is eligible.
+        if txin.script_sig.redeem_script()?.is_p2wpkh() {
+            compressed_key(txin.witness.last()?)
+        } else {
+            None
+        }
+    } else if spk.is_p2pkh() {
+        // The public key hashed in the script pubkey is looked up at the end of the scriptSig,
+        // which may have been malleated.
+        let key_hash = spk.as_bytes().get(3..23)?;
+        let script_sig = txin.script_sig.as_bytes();
+        (33..=script_sig.len())
+            .rev()
+            .map(|end| &script_sig[end - 33..end])
+            .find(|key| hash160::Hash::hash(key).as_byte_array() == key_hash)
+            .and_then(compressed_key)
+    } else {
+        None
+    }
+}
+
+/// The sum of the public keys of the eligible inputs of this transaction and the hash committing
+/// to it and to the transaction's smallest outpoint, if the transaction may contain Silent
+/// Payments.
+fn inputs_key(tx: &Transaction, prevouts: &[TxOut]) -> Option<(PublicKey, Scalar)> {
+    if prevouts.len() != tx.input.len() || !tx.output.iter().any(|o| o.script_pubkey.is_p2tr()) {
+        return None;
+    }
+    // Transactions spending outputs of unknown segwit versions are skipped.
+    if prevouts.iter().any(|prevout| {
+        prevout
+            .script_pubkey
+            .witness_version()
+            .is_some_and(|v| v.to_num() > 1)
+    }) {
+        return None;
+    }
+    let keys: Vec<PublicKey> = tx
+        .input
+        .iter()
+        .zip(prevouts)
+        .filter_map(|(txin, prevout)| input_public_key(txin, prevout))
+        .collect();
+    let keys_sum = PublicKey::combine_keys(&keys.iter().collect::<Vec<_>>()).ok()?;
+
+    let smallest_outpoint = tx
+        .input
+        .iter()
+        .map(|txin| consensus::serialize(&txin.previous_output))
+        .min()?;
+    let mut engine = InputsHash::engine();
+    engine.input(&smallest_outpoint);
+    engine.input(&keys_sum.serialize());
+    let inputs_hash =
+        Scalar::from_be_bytes(InputsHash::from_engine(engine).to_byte_array()).ok()?;
+
+    Some((keys_sum, inputs_hash))
+}
+
+/// The tweak of the spend key for the `k`-th output paying to the same address in a transaction.
+pub fn output_tweak(shared_secret: &PublicKey, k: u32) -> Option<Scalar> {
+    let mut engine = SharedSecretHash::engine();
+    engine.input(&shared_secret.serialize());
+    engine.input(&k.to_be_bytes());
+    Scalar::from_be_bytes(SharedSecretHash::from_engine(engine).to_byte_array()).ok()
+}
+
+/// The script pubkey of the output paying to this spend key tweaked with this tweak.
+pub fn output_script_pubkey<C: secp256k1::Verification>(
+    secp: &secp256k1::Secp256k1<C>,
+    spend_key: &PublicKey,
+    tweak: &Scalar,
+) -> Option<ScriptBuf> {
+    let output_key = spend_key.add_exp_tweak(secp, tweak).ok()?;
+    let output_key = TweakedPublicKey::dangerous_assume_tweaked(output_key.x_only_public_key().0);
+    Some(ScriptBuf::new_p2tr_tweaked(output_key))
+}
+
+/// The secret shared between the sender of this transaction and the owner of this scan key, given
+/// the previous outputs spent by each of its inputs. None if the transaction isn't eligible.
+pub fn shared_secret<C: secp256k1::Verification>(
+    secp: &secp256k1::Secp256k1<C>,
+    scan_key: &SecretKey,
+    tx: &Transaction,
+    prevouts: &[TxOut],
+) -> Option<PublicKey> {
+    let (keys_sum, inputs_hash) = inputs_key(tx, prevouts)?;
+    let key = scan_key.mul_tweak(&inputs_hash).ok()?;
+    keys_sum.mul_tweak(secp, &Scalar::from(key)).ok()
+}
+
+/// A Silent Payment found in a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoundPayment {
+    /// The index of the output paying to us.
+    pub vout: u32,
+    /// The tweak of the spend key for this output.
+    pub tweak: Scalar,
+}
+
+/// Look for Silent Payments to the address of these keys in this transaction, given the previous
+/// outputs spent by each of its inputs.
+pub fn scan_transaction<C: secp256k1::Verification>(
+    secp: &secp256k1::Secp256k1<C>,
+    scan_key: &SecretKey,
+    spend_key: &PublicKey,
+    tx: &Transaction,
+    prevouts: &[TxOut],
+) -> Vec<FoundPayment> {
+    let mut payments = Vec::new();
+    let shared_secret = match shared_secret(secp, scan_key, tx, prevouts) {
+        Some(secret) => secret,
+        None => return payments,
+    };
+
+    // The outputs to the same address are tweaked with increasing k. Stop at the first missing.
+    for k in 0.. {
+        let tweak = match output_tweak(&shared_secret, k) {
+            Some(tweak) => tweak,
+            None => break,
+        };
+        let spk = match output_script_pubkey(secp, spend_key, &tweak) {
+            Some(spk) => spk,
+            None => break,
+        };
+        match tx
+            .output
+            .iter()
+            .position(|txout| txout.script_pubkey == spk)
+            .and_then(|vout| vout.try_into().ok())
+        {
+            Some(vout) => payments.push(FoundPayment { vout, tweak }),
+            None => break,
+        }
+    }
+    payments
+}
+
+/// The key pair to spend a Silent Payment, from the spend secret key and the payment's tweak.
+pub fn payment_keypair<C: secp256k1::Signing>(
+    secp: &secp256k1::Secp256k1<C>,
+    spend_key: &SecretKey,
+    tweak: &Scalar,
+) -> Result<secp256k1::Keypair, SilentPaymentError> {
+    let secret = spend_key
+        .add_tweak(tweak)
+        .map_err(|e| SilentPaymentError::InvalidKey(e.to_string()))?;
+    Ok(secp256k1::Keypair::from_secret_key(secp, &secret))
+}
+
+/// Sign each input of this transaction, which all spend Silent Payments, with the key pair of the
+/// payment it spends. The `prevouts` are the payments spent by each input.
+pub fn sign_transaction<C: secp256k1::Signing>(
+    secp: &secp256k1::Secp256k1<C>,
+    tx: &mut Transaction,
+    prevouts: &[TxOut],
+    keypairs: &[secp256k1::Keypair],
+) -> Result<(), SilentPaymentError> {
+    if prevouts.len() != tx.input.len() || keypairs.len() != tx.input.len() {
+        return Err(SilentPaymentError::Signing(
+            "A previous output and a key are needed for each input.".to_string(),
+        ));
+    }
+    let mut witnesses = Vec::with_capacity(tx.input.len());
+    let mut sighash_cache = sighash::SighashCache::new(&*tx);
+    for (index, keypair) in keypairs.iter().enumerate() {
+        let sighash = sighash_cache
+            .taproot_key_spend_signature_hash(
+                index,
+                &sighash::Prevouts::All(prevouts),
+                sighash::TapSighashType::Default,
+            )
+            .map_err(|e| SilentPaymentError::Signing(e.to_string()))?;
+        let msg = secp256k1::Message::from_digest(sighash.to_byte_array());
+        let signature = bitcoin::taproot::Signature {
+            signature: secp.sign_schnorr_no_aux_rand(&msg, keypair),
+            sighash_type: sighash::TapSighashType::Default,
+        };
+        witnesses.push(Witness::p2tr_key_spend(&signature));
+    }
+    for (txin, witness) in tx.input.iter_mut().zip(witnesses) {
+        txin.witness = witness;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miniscript::bitcoin::{
+        absolute::LockTime, blockdata::script, hashes::hex::FromHex, key::CompressedPublicKey,
+        transaction::Version, Amount, OutPoint, Sequence, Txid,
+    };
+
+    fn secret_key(hex: &str) -> SecretKey {
+        SecretKey::from_slice(&Vec::<u8>::from_hex(hex).unwrap()).unwrap()
+    }
+
+    fn txin(txid: u8, vout: u32) -> TxIn {
+        TxIn {
+            previous_output: OutPoint::new(Txid::from_byte_array([txid; 32]), vout),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::MAX,
+            witness: Witness::new(),
+        }
+    }
+
+    fn txout(script_pubkey: ScriptBuf) -> TxOut {
+        TxOut {
+            value: Amount::from_sat(10_000),
+            script_pubkey,
+        }
+    }
+
+    #[test]
+    fn address_encoding() {
+        let secp = secp256k1::Secp256k1::new();
+        // From the test vectors of BIP352.
+        let addr = SilentPaymentAddress {
+            network: bitcoin::Network::Bitcoin,
+            scan_key: secret_key(
+                "0f694e068028a717f8af6b9411f9a133dd3565258714cc226594b34db90c1f2c",
+            )
+            .public_key(&secp),
+            spend_key: secret_key(
+                "9d6ad855ce3417ef84e836892e5a56392bfba05fa5d97ccea30e266f540e08b3",
+            )
+            .public_key(&secp),
+        };
+        let addr_str = "sp1qqgste7k9hx0qftg6qmwlkqtwuy6cycyavzmzj85c6qdfhjdpdjtdgqjuexzk6murw56suy3e0rd2cgqvycxttddwsvgxe2usfpxumr70xc9pkqwv";
+        assert_eq!(addr.to_string(), addr_str);
+        assert_eq!(
+            SilentPaymentAddress::from_str_network(addr_str, bitcoin::Network::Bitcoin).unwrap(),
+            addr
+        );
+        assert_eq!(
+            SilentPaymentAddress::from_str_network(addr_str, bitcoin::Network::Testnet),
+            Err(SilentPaymentError::NetworkMismatch(
+                bitcoin::Network::Testnet
+            ))
+        );
+
+        // Same keys on the test networks.
+        for network in [bitcoin::Network::Signet, bitcoin::Network::Regtest] {
+            let addr = SilentPaymentAddress { network, ..addr };
+            let addr_str = addr.to_string();
+            assert!(
+                addr_str.starts_with(if network == bitcoin::Network::Regtest {
+                    "sprt1q"
+                } else {
+                    "tsp1q"
+                })
+            );
+            assert_eq!(
+                SilentPaymentAddress::from_str_network(&addr_str, network).unwrap(),
+                addr
+            );
+        }
+
+        // A segwit address is not a Silent Payment address.
+        assert!(SilentPaymentAddress::from_str_network(
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+            bitcoin::Network::Bitcoin
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn scan_and_spend() {
+        let secp = secp256k1::Secp256k1::new();
+        let scan_key =
+            secret_key("0f694e068028a717f8af6b9411f9a133dd3565258714cc226594b34db90c1f2c");
+        let spend_key =
+            secret_key("9d6ad855ce3417ef84e836892e5a56392bfba05fa5d97ccea30e266f540e08b3");
+        let (scan_pub, spend_pub) = (scan_key.public_key(&secp), spend_key.public_key(&secp));
+
+        // The payer spends a P2WPKH, a P2TR key path and a P2PKH outputs.
+        let input_keys = [[1; 32], [2; 32], [3; 32]].map(|k| SecretKey::from_slice(&k).unwrap());
+        let compressed = |key: &SecretKey| CompressedPublicKey(key.public_key(&secp));
+        let (tr_key, tr_parity) = input_keys[1].x_only_public_key(&secp);
+        let prevouts = vec![
+            txout(ScriptBuf::new_p2wpkh(
+                &compressed(&input_keys[0]).wpubkey_hash(),
+            )),
+            txout(ScriptBuf::new_p2tr_tweaked(
+                TweakedPublicKey::dangerous_assume_tweaked(tr_key),
+            )),
+            txout(ScriptBuf::new_p2pkh(
+                &compressed(&input_keys[2]).pubkey_hash(),
+            )),
+        ];
+        let mut inputs = vec![txin(2, 1), txin(1, 7), txin(2, 0)];
+        inputs[0].witness = Witness::from_slice(&[
+            vec![0x30; 71],
+            compressed(&input_keys[0]).to_bytes().to_vec(),
+        ]);
+        inputs[1].witness = Witness::from_slice(&[vec![0x42; 64]]);
+        inputs[2].script_sig = script::Builder::new()
+            .push_slice([0x30; 71])
+            .push_key(&bitcoin::PublicKey::new(input_keys[2].public_key(&secp)))
+            .into_script();
+        for (txin, prevout) in inputs.iter().zip(&prevouts) {
+            assert!(input_public_key(txin, prevout).is_some());
+        }
+
+        // The payer's side: the sum of the input keys, negated for a Taproot output key with an
+        // odd y coordinate.
+        let tr_secret = if tr_parity == Parity::Odd {
+            input_keys[1].negate()
+        } else {
+            input_keys[1]
+        };
+        let inputs_secret = input_keys[0]
+            .add_tweak(&Scalar::from(tr_secret))
+            .unwrap()
+            .add_tweak(&Scalar::from(input_keys[2]))
+            .unwrap();
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: inputs,
+            output: vec![],
+        };
+        let (keys_sum, inputs_hash) = inputs_key(
+            &Transaction {
+                output: vec![prevouts[1].clone()],
+                ..tx.clone()
+            },
+            &prevouts,
+        )
+        .unwrap();
+        assert_eq!(keys_sum, inputs_secret.public_key(&secp));
+        let shared_secret = scan_pub
+            .mul_tweak(
+                &secp,
+                &Scalar::from(inputs_secret.mul_tweak(&inputs_hash).unwrap()),
+            )
+            .unwrap();
+        let payment = |k| {
+            txout(
+                output_script_pubkey(&secp, &spend_pub, &output_tweak(&shared_secret, k).unwrap())
+                    .unwrap(),
+            )
+        };
+
+        // Two payments to us, along with an unrelated Taproot output.
+        let tx = Transaction {
+            output: vec![prevouts[1].clone(), payment(1), payment(0)],
+            ..tx
+        };
+        let payments = scan_transaction(&secp, &scan_key, &spend_pub, &tx, &prevouts);
+        assert_eq!(
+            payments.iter().map(|p| p.vout).collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+        // Without the first payment the second one isn't looked for.
+        let mut partial_tx = tx.clone();
+        partial_tx.output.remove(2);
+        assert!(scan_transaction(&secp, &scan_key, &spend_pub, &partial_tx, &prevouts).is_empty());
+        // Not with another scan key.
+        assert!(scan_transaction(&secp, &spend_key, &spend_pub, &tx, &prevouts).is_empty());
+
+        // The payments can be spent with the tweaked spend key.
+        let mut sweep = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: payments
+                .iter()
+                .map(|p| TxIn {
+                    previous_output: OutPoint::new(tx.compute_txid(), p.vout),
+                    ..txin(0, 0)
+                })
+                .collect(),
+            output: vec![prevouts[0].clone()],
+        };
+        let spent: Vec<TxOut> = payments
+            .iter()
+            .map(|p| tx.output[p.vout as usize].clone())
+            .collect();
+        let keypairs: Vec<_> = payments
+            .iter()
+            .map(|p| payment_keypair(&secp, &spend_key, &p.tweak).unwrap())
+            .collect();
+        sign_transaction(&secp, &mut sweep, &spent, &keypairs).unwrap();
+        let mut sighash_cache = sighash::SighashCache::new(&sweep);
+        for (index, txout) in spent.iter().enumerate() {
+            let sighash = sighash_cache
+                .taproot_key_spend_signature_hash(
+                    index,
+                    &sighash::Prevouts::All(&spent),
+                    sighash::TapSighashType::Default,
+                )
+                .unwrap();
+            let signature = secp256k1::schnorr::Signature::from_slice(
+                sweep.input[index].witness.nth(0).unwrap(),
+            )
+            .unwrap();
+            let output_key =
+                XOnlyPublicKey::from_slice(&txout.script_pubkey.as_bytes()[2..34]).unwrap();
+            secp.verify_schnorr(
+                &signature,
+                &secp256k1::Message::from_digest(sighash.to_byte_array()),
+                &output_key,
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn ineligible_inputs() {
+        let secp = secp256k1::Secp256k1::new();
+        let key = SecretKey::from_slice(&[1; 32]).unwrap();
+        let (xonly, _) = key.x_only_public_key(&secp);
+        let p2tr = txout(ScriptBuf::new_p2tr_tweaked(
+            TweakedPublicKey::dangerous_assume_tweaked(xonly),
+        ));
+
+        // A script path spend whose internal key is the NUMS point.
+        let mut control_block = vec![0xc0];
+        control_block.extend_from_slice(&NUMS_H);
+        let mut script_spend = txin(1, 0);
+        script_spend.witness =
+            Witness::from_slice(&[vec![0x42; 64], vec![0x51], control_block.clone()]);
+        assert_eq!(input_public_key(&script_spend, &p2tr), None);
+        // Not if the internal key is another one.
+        control_block[1..33].copy_from_slice(&xonly.serialize());
+        script_spend.witness = Witness::from_slice(&[vec![0x42; 64], vec![0x51], control_block]);
+        assert!(input_public_key(&script_spend, &p2tr).is_some());
+
+        // Uncompressed keys don't contribute.
+        let uncompressed = bitcoin::PublicKey::new_uncompressed(key.public_key(&secp));
+        let mut p2pkh_spend = txin(1, 0);
+        p2pkh_spend.script_sig = script::Builder::new()
+            .push_slice([0x30; 71])
+            .push_key(&uncompressed)
+            .into_script();
+        assert_eq!(
+            input_public_key(
+                &p2pkh_spend,
+                &txout(ScriptBuf::new_p2pkh(&uncompressed.pubkey_hash()))
+            ),
+            None
+        );
+
+        // Spending an output of an unknown segwit version makes the transaction ineligible.
+        let mut key_spend = txin(2, 0);
+        key_spend.witness = Witness::from_slice(&[vec![0x42; 64]]);
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![key_spend, txin(3, 0)],
+            output: vec![p2tr.clone()],
+        };
+        let unknown = txout(ScriptBuf::from_bytes(vec![0x52, 0x02, 0x00, 0x00]));
+        assert!(inputs_key(&tx, &[p2tr.clone(), p2tr.clone()]).is_some());
+        assert!(inputs_key(&tx, &[p2tr.clone(), unknown]).is_none());
+        // As does not having a Taproot output.
+        let tx = Transaction {
+            output: vec![txout(ScriptBuf::new())],
+            ..tx
+        };
+        assert!(inputs_key(&tx, &[p2tr.clone(), p2tr]).is_none());
+    }
+}
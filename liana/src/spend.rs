@@ -1,9 +1,10 @@
 use crate::descriptors;
 
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{btree_map, BTreeMap, HashMap, HashSet},
     convert::TryInto,
     fmt,
+    str::FromStr,
     time::Duration,
 };
 
@@ -17,8 +18,8 @@ use miniscript::bitcoin::{
     absolute::{Height, LockTime},
     bip32,
     constants::WITNESS_SCALE_FACTOR,
-    psbt::{Input as PsbtIn, Output as PsbtOut, Psbt},
-    secp256k1,
+    psbt::{Input as PsbtIn, Output as PsbtOut, Psbt, PsbtSighashType},
+    secp256k1, EcdsaSighashType, TapSighashType, WitnessVersion,
 };
 use serde::{Deserialize, Serialize};
 
@@ -55,6 +56,15 @@ pub enum SpendCreationError {
     SanityCheckFailure(Psbt),
     FetchingTransaction(bitcoin::OutPoint),
     CoinSelection(InsufficientFunds),
+    DuplicateCandidate(bitcoin::OutPoint),
+    /// There is no output at the index of this input to sign with SIGHASH_SINGLE.
+    SighashSingleMissingOutput(usize),
+    /// There is no destination at this index to deduct the fee from.
+    UnknownFeeDestination(usize),
+    /// Deducting its share of the fee from the destination at this index would make it dust.
+    FeeExceedsDestination(usize),
+    /// The destination at this index doesn't pay to a standard output type.
+    NonStandardOutput(usize),
 }
 
 impl fmt::Display for SpendCreationError {
@@ -79,6 +89,27 @@ impl fmt::Display for SpendCreationError {
                 write!(f, "Could not fetch transaction for coin {}", op)
             }
             Self::CoinSelection(e) => write!(f, "Coin selection error: '{}'", e),
+            Self::DuplicateCandidate(op) => write!(f, "Coin {} was provided more than once.", op),
+            Self::SighashSingleMissingOutput(index) => write!(
+                f,
+                "Input {} can't be signed with SIGHASH_SINGLE: there is no output at this index.",
+                index
+            ),
+            Self::UnknownFeeDestination(index) => write!(
+                f,
+                "No destination at index {} to deduct the fee from.",
+                index
+            ),
+            Self::FeeExceedsDestination(index) => write!(
+                f,
+                "Deducting its share of the fee from destination {} would leave it with less than {} sats.",
+                index, DUST_OUTPUT_SATS
+            ),
+            Self::NonStandardOutput(index) => write!(
+                f,
+                "Destination {} doesn't pay to a standard output type.",
+                index
+            ),
             Self::SanityCheckFailure(psbt) => write!(
                 f,
                 "BUG! Please report this. Failed sanity checks for PSBT '{}'.",
@@ -165,6 +196,46 @@ fn sanity_check_psbt(
     Ok(())
 }
 
+/// The standard types of output scripts we may send to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputType {
+    P2pkh,
+    P2sh,
+    P2wpkh,
+    P2wsh,
+    P2tr,
+    /// A witness program of a version not defined yet. Sending to it is standard, but until a
+    /// soft fork defines this version anyone can spend the coins.
+    FutureWitness(/* witness version */ u8),
+}
+
+impl OutputType {
+    /// The type of this output script, if it's a standard one. Bare multisig and witness v0
+    /// programs of another size than P2WPKH and P2WSH ones are not.
+    pub fn from_script(script: &bitcoin::Script) -> Option<Self> {
+        if script.is_p2pkh() {
+            Some(Self::P2pkh)
+        } else if script.is_p2sh() {
+            Some(Self::P2sh)
+        } else if script.is_p2wpkh() {
+            Some(Self::P2wpkh)
+        } else if script.is_p2wsh() {
+            Some(Self::P2wsh)
+        } else if script.is_p2tr() {
+            Some(Self::P2tr)
+        } else {
+            match script.witness_version() {
+                None | Some(WitnessVersion::V0) => None,
+                Some(version) => Some(Self::FutureWitness(version.to_num())),
+            }
+        }
+    }
+
+    pub fn is_future_witness(&self) -> bool {
+        matches!(self, Self::FutureWitness(..))
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct AncestorInfo {
     pub vsize: u64,
@@ -238,6 +309,18 @@ impl bdk_coin_select::BnbMetric for LowestFeeChangeCondition {
     }
 }
 
+/// How the fee of a transaction is paid, and whether it must have a change output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SelectionMode {
+    /// The coins are selected to pay for the outputs and the fee. There may be a change output.
+    Regular,
+    /// Same, but the transaction must have a change output.
+    MustHaveChange,
+    /// The fee is deducted from the outputs by the caller. The coins are then selected to cover the
+    /// value of the outputs only.
+    FeeFromOutputs,
+}
+
 /// Select coins for spend.
 ///
 /// Returns the selected coins and the change amount, which could be zero.
@@ -261,8 +344,10 @@ impl bdk_coin_select::BnbMetric for LowestFeeChangeCondition {
 /// `max_sat_weight` is the maximum weight difference of an input in the
 /// transaction before and after satisfaction.
 ///
-/// `must_have_change` indicates whether the transaction must have a change output.
-/// If `true`, the returned change amount will be positive.
+/// `mode` tells how the fee is paid and whether the transaction must have a change output. If it
+/// must, the returned change amount will be positive. If the fee is deducted from the outputs of
+/// `base_tx`, the change amount doesn't account for the fee. The fee for ancestors is still
+/// returned.
 fn select_coins_for_spend(
     candidate_coins: &[CandidateCoin],
     base_tx: bitcoin::Transaction,
@@ -270,8 +355,10 @@ fn select_coins_for_spend(
     feerate_vb: f32,
     replaced_fee: Option<u64>,
     max_sat_weight: u64,
-    must_have_change: bool,
+    mode: SelectionMode,
 ) -> Result<CoinSelectionRes, InsufficientFunds> {
+    let must_have_change = mode == SelectionMode::MustHaveChange;
+    let fee_from_outputs = mode == SelectionMode::FeeFromOutputs;
     let out_value_nochange = base_tx.output.iter().map(|o| o.value.to_sat()).sum();
     let out_weight_nochange = {
         let mut total: u64 = 0;
@@ -320,8 +407,9 @@ fn select_coins_for_spend(
                     })
                     .unwrap_or(0);
                 // Store the extra weight for this candidate for use later on.
-                // At the same time, make sure there are no duplicate outpoints.
-                assert!(added_weights.insert(cand.outpoint, extra).is_none());
+                // Duplicate outpoints were ruled out by the caller.
+                let prev = added_weights.insert(cand.outpoint, extra);
+                debug_assert!(prev.is_none());
                 max_input_weight
                     .checked_add(extra)
                     .expect("effective weight must fit in u64")
@@ -362,7 +450,11 @@ fn select_coins_for_spend(
     // find any solution we fall back to selecting coins by descending value.
     let replace = replaced_fee.map(Replace::new);
     let target_fee = TargetFee {
-        rate: feerate,
+        rate: if fee_from_outputs {
+            FeeRate::from_sat_per_vb(0.0)
+        } else {
+            feerate
+        },
         replace,
     };
     let target_outputs = TargetOutputs {
@@ -392,10 +484,19 @@ fn select_coins_for_spend(
     };
     #[cfg(debug_assertions)]
     let bnb_rounds = bnb_rounds / 1_000;
-    if let Err(e) = selector.run_bnb(lowest_fee_change_cond, bnb_rounds) {
+    // If the fee is deducted from the outputs there is no fee to optimize for, so directly select
+    // coins by descending value.
+    let bnb_res = if fee_from_outputs {
+        Err("fee is deducted from the outputs".to_string())
+    } else {
+        selector
+            .run_bnb(lowest_fee_change_cond, bnb_rounds)
+            .map_err(|e| e.to_string())
+    };
+    if let Err(e) = bnb_res {
         log::debug!(
             "Coin selection error: '{}'. Selecting coins by descending value per weight unit...",
-            e.to_string()
+            e
         );
         selector.sort_candidates_by_descending_value_pwu();
         // Select more coins until target is met and change condition satisfied.
@@ -529,7 +630,14 @@ pub trait TxGetter {
     fn get_tx(&mut self, txid: &bitcoin::Txid) -> Option<bitcoin::Transaction>;
 }
 
-/// Specify the fee requirements for a transaction. In both cases set a target feerate in satoshi
+/// Transactions already fetched by the caller, for instance passed along with the coins to spend.
+impl TxGetter for HashMap<bitcoin::Txid, bitcoin::Transaction> {
+    fn get_tx(&mut self, txid: &bitcoin::Txid) -> Option<bitcoin::Transaction> {
+        self.get(txid).cloned()
+    }
+}
+
+/// Specify the fee requirements for a transaction. In all cases set a target feerate in satoshi
 /// per virtual byte. For RBF also set a minimum fee in satoshis for this transaction. See
 /// https://github.com/bitcoin/bitcoin/blob/master/doc/policy/mempool-replacements.md for more
 /// information about how it should be set.
@@ -539,12 +647,137 @@ pub enum SpendTxFees {
     /// The (target feerate in sats/vb, total fees in sats of transaction(s) to be replaced
     /// including descendants) for this transaction.
     Rbf(u64, u64),
+    /// The (target feerate in sats/vb, indexes of the destinations paying for the fee) for this
+    /// transaction. The fee is deducted from the value of these destinations instead of being
+    /// paid on top of it, split evenly between them. The first of them pays for the remainder.
+    DeductedFrom(u64, Vec<usize>),
+}
+
+/// The signature hash type the signers are asked to use for all the inputs of a Spend transaction.
+/// Anything but the default allows another party to modify the transaction once signed, for
+/// instance to add an input paying for more fees.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpendSighash {
+    /// Commit to all the inputs and outputs.
+    #[default]
+    All,
+    /// Commit to all the outputs but only to the input being signed. Inputs may be added.
+    AllAnyonecanpay,
+    /// Commit to all the inputs but only to the output at the index of the input being signed.
+    Single,
+    /// Commit only to the input being signed and the output at the same index.
+    SingleAnyonecanpay,
+}
+
+impl SpendSighash {
+    pub const ALL: [SpendSighash; 4] = [
+        SpendSighash::All,
+        SpendSighash::AllAnyonecanpay,
+        SpendSighash::Single,
+        SpendSighash::SingleAnyonecanpay,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::All => "all",
+            Self::AllAnyonecanpay => "all_anyonecanpay",
+            Self::Single => "single",
+            Self::SingleAnyonecanpay => "single_anyonecanpay",
+        }
+    }
+
+    fn is_single(&self) -> bool {
+        matches!(self, Self::Single | Self::SingleAnyonecanpay)
+    }
+
+    /// The sighash type to set in the PSBT inputs. None for the default, which is SIGHASH_ALL for
+    /// ECDSA signatures and SIGHASH_DEFAULT for Schnorr signatures.
+    fn psbt_sighash_type(&self, is_taproot: bool) -> Option<PsbtSighashType> {
+        let (ecdsa, taproot) = match self {
+            Self::All => return None,
+            Self::AllAnyonecanpay => (
+                EcdsaSighashType::AllPlusAnyoneCanPay,
+                TapSighashType::AllPlusAnyoneCanPay,
+            ),
+            Self::Single => (EcdsaSighashType::Single, TapSighashType::Single),
+            Self::SingleAnyonecanpay => (
+                EcdsaSighashType::SinglePlusAnyoneCanPay,
+                TapSighashType::SinglePlusAnyoneCanPay,
+            ),
+        };
+        Some(if is_taproot {
+            taproot.into()
+        } else {
+            ecdsa.into()
+        })
+    }
+
+    /// The sighash type requested in this PSBT. The PSBT must have the same sighash type set for
+    /// all its inputs.
+    pub fn from_psbt(psbt: &Psbt) -> Option<SpendSighash> {
+        let sighash_type = psbt.inputs.first()?.sighash_type;
+        if psbt
+            .inputs
+            .iter()
+            .any(|psbt_in| psbt_in.sighash_type != sighash_type)
+        {
+            return None;
+        }
+        let sighash_type = match sighash_type {
+            None => return Some(SpendSighash::All),
+            Some(sighash_type) => sighash_type,
+        };
+        Self::ALL.iter().copied().find(|sighash| {
+            [false, true]
+                .iter()
+                .any(|is_taproot| sighash.psbt_sighash_type(*is_taproot) == Some(sighash_type))
+        })
+    }
+}
+
+impl fmt::Display for SpendSighash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for SpendSighash {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|sighash| sighash.as_str() == s)
+            .ok_or_else(|| format!("Unknown sighash type '{}'.", s))
+    }
+}
+
+/// Request the signers to use this signature hash type for all the inputs of this PSBT. With
+/// SIGHASH_SINGLE there must be an output at the index of each input.
+pub fn set_sighash(
+    main_descriptor: &descriptors::LianaDescriptor,
+    psbt: &mut Psbt,
+    sighash: SpendSighash,
+) -> Result<(), SpendCreationError> {
+    if sighash.is_single() && psbt.inputs.len() > psbt.outputs.len() {
+        return Err(SpendCreationError::SighashSingleMissingOutput(
+            psbt.outputs.len(),
+        ));
+    }
+    let sighash_type = sighash.psbt_sighash_type(main_descriptor.is_taproot());
+    for psbt_in in psbt.inputs.iter_mut() {
+        psbt_in.sighash_type = sighash_type;
+    }
+    Ok(())
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum CreateSpendWarning {
     ChangeAddedToFee(u64),
     AdditionalFeeForAncestors(u64),
+    FutureWitnessVersion(u8),
 }
 
 impl fmt::Display for CreateSpendWarning {
@@ -567,6 +800,12 @@ impl fmt::Display for CreateSpendWarning {
                 amt,
                 if *amt > 1 { "s" } else { "" },
             ),
+            CreateSpendWarning::FutureWitnessVersion(version) => write!(
+                f,
+                "Witness version {} is not defined yet: until it is, anyone can spend the \
+                coins sent to this address.",
+                version,
+            ),
         }
     }
 }
@@ -599,7 +838,8 @@ pub struct CreateSpendRes {
 ///   `destinations` is empty, they will all be included as inputs of the transaction. Otherwise, a
 ///   coin selection algorithm will be run to spend the most efficient subset of them to meet the
 ///   `destinations` requirements.
-/// * `fees`: the target feerate (in sats/vb) and, if necessary, minimum absolute fee for this tx.
+/// * `fees`: the target feerate (in sats/vb) and, if necessary, minimum absolute fee for this tx
+///   or the destinations to deduct the fee from.
 /// * `change_addr`: the address to use for a change output if we need to create one. Can be set to
 ///   an external address (if combined with an empty list of `destinations` it's useful to sweep some
 ///   or all coins of a wallet to an external address).
@@ -630,14 +870,30 @@ pub fn create_spend(
     // 4. Finalize the PSBT and sanity check it before returning it.
 
     let mut warnings = Vec::new();
-    let (feerate_vb, replaced_fee) = match fees {
-        SpendTxFees::Regular(feerate) => (feerate, None),
-        SpendTxFees::Rbf(feerate, fee) => (feerate, Some(fee)),
+    let (feerate_vb, replaced_fee, fee_destinations) = match fees {
+        SpendTxFees::Regular(feerate) => (feerate, None, Vec::new()),
+        SpendTxFees::Rbf(feerate, fee) => (feerate, Some(fee), Vec::new()),
+        SpendTxFees::DeductedFrom(feerate, indexes) => (feerate, None, indexes),
     };
     let is_self_send = destinations.is_empty();
     if feerate_vb < 1 {
         return Err(SpendCreationError::InvalidFeerate(feerate_vb));
     }
+    let mut seen_fee_destinations = HashSet::with_capacity(fee_destinations.len());
+    if let Some(index) = fee_destinations
+        .iter()
+        .find(|i| **i >= destinations.len() || !seen_fee_destinations.insert(**i))
+    {
+        return Err(SpendCreationError::UnknownFeeDestination(*index));
+    }
+    let fee_from_outputs = !fee_destinations.is_empty();
+    let mut candidate_outpoints = HashSet::with_capacity(candidate_coins.len());
+    if let Some(cand) = candidate_coins
+        .iter()
+        .find(|cand| !candidate_outpoints.insert(cand.outpoint))
+    {
+        return Err(SpendCreationError::DuplicateCandidate(cand.outpoint));
+    }
 
     // Create transaction with no inputs and no outputs.
     let mut tx = bitcoin::Transaction {
@@ -649,8 +905,15 @@ pub fn create_spend(
     // Add the destinations outputs to the transaction and PSBT. At the same time
     // sanity check each output's value.
     let mut psbt_outs = Vec::with_capacity(destinations.len());
-    for (address, amount) in destinations {
+    for (i, (address, amount)) in destinations.iter().enumerate() {
         check_output_value(*amount)?;
+        match OutputType::from_script(&address.addr.script_pubkey()) {
+            None => return Err(SpendCreationError::NonStandardOutput(i)),
+            Some(OutputType::FutureWitness(version)) => {
+                warnings.push(CreateSpendWarning::FutureWitnessVersion(version))
+            }
+            Some(_) => {}
+        }
 
         tx.output.push(bitcoin::TxOut {
             value: *amount,
@@ -707,6 +970,14 @@ pub fn create_spend(
             fr
         }
         .into();
+        // The fee can't be deducted from the outputs of a self-send, as it has none.
+        let selection_mode = if fee_from_outputs {
+            SelectionMode::FeeFromOutputs
+        } else if is_self_send {
+            SelectionMode::MustHaveChange
+        } else {
+            SelectionMode::Regular
+        };
         let max_sat_wu = main_descriptor
             .max_sat_weight(use_primary_path)
             .try_into()
@@ -718,7 +989,7 @@ pub fn create_spend(
             feerate_vb,
             replaced_fee,
             max_sat_wu,
-            is_self_send,
+            selection_mode,
         )
         .map_err(SpendCreationError::CoinSelection)?
     };
@@ -746,7 +1017,7 @@ pub fn create_spend(
         change_txo.value = change_amount;
         tx.output.push(change_txo);
         psbt_outs.push(psbt_out);
-    } else if max_change_amount.to_sat() > 0 {
+    } else if max_change_amount.to_sat() > 0 && !fee_from_outputs {
         warnings.push(CreateSpendWarning::ChangeAddedToFee(
             max_change_amount.to_sat(),
         ));
@@ -785,6 +1056,33 @@ pub fn create_spend(
         psbt_ins.push(psbt_in);
     }
 
+    // If the fee is deducted from some destinations, the coins were selected to cover the value of
+    // the outputs only. Now the transaction has all its inputs and outputs we know how much fee to
+    // deduct. Without a change output the leftover value already pays for part of it. Note a
+    // change output, possibly sending the max to an external address, keeps its value.
+    if fee_from_outputs {
+        let fee = main_descriptor
+            .unsigned_tx_max_vbytes(&tx, use_primary_path)
+            .checked_mul(feerate_vb)
+            .and_then(|fee| fee.checked_add(fee_for_ancestors.to_sat()))
+            .ok_or(SpendCreationError::InsaneFees(
+                InsaneFeeInfo::TooHighFeerate(feerate_vb),
+            ))?;
+        let leftover = if has_change {
+            0
+        } else {
+            max_change_amount.to_sat()
+        };
+        if leftover > fee {
+            warnings.push(CreateSpendWarning::ChangeAddedToFee(leftover - fee));
+        }
+        deduct_fee(
+            &mut tx.output,
+            &fee_destinations,
+            bitcoin::Amount::from_sat(fee.saturating_sub(leftover)),
+        )?;
+    }
+
     // Finally, create the PSBT with all inputs and outputs, sanity check it and return it.
     let psbt = Psbt {
         unsigned_tx: tx,
@@ -805,13 +1103,334 @@ pub fn create_spend(
     })
 }
 
+// Deduct this fee from the outputs at these indexes, split evenly between them. The first one
+// pays for the remainder of the division.
+fn deduct_fee(
+    outputs: &mut [bitcoin::TxOut],
+    indexes: &[usize],
+    fee: bitcoin::Amount,
+) -> Result<(), SpendCreationError> {
+    let count = indexes.len() as u64;
+    let share = fee.to_sat() / count;
+    let remainder = fee.to_sat() % count;
+    for (i, index) in indexes.iter().enumerate() {
+        let txo = outputs
+            .get_mut(*index)
+            .ok_or(SpendCreationError::UnknownFeeDestination(*index))?;
+        let deducted = if i == 0 { share + remainder } else { share };
+        txo.value = txo
+            .value
+            .to_sat()
+            .checked_sub(deducted)
+            .filter(|value| *value >= DUST_OUTPUT_SATS)
+            .map(bitcoin::Amount::from_sat)
+            .ok_or(SpendCreationError::FeeExceedsDestination(*index))?;
+    }
+    Ok(())
+}
+
+/// A signature of a PSBT input which can't be verified or is invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureError {
+    /// The previous outputs needed to compute the signature hash of this input are missing.
+    MissingUtxo(usize),
+    /// The signature of this input by this public key is invalid.
+    Invalid { input_index: usize, pubkey: String },
+    /// The signature of this input by this public key doesn't use the sighash type requested in
+    /// the PSBT input.
+    UnexpectedSighash { input_index: usize, pubkey: String },
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingUtxo(index) => write!(
+                f,
+                "Missing previous output to verify the signatures of input {}.",
+                index
+            ),
+            Self::Invalid {
+                input_index,
+                pubkey,
+            } => write!(
+                f,
+                "Invalid signature for public key '{}' in input {}.",
+                pubkey, input_index
+            ),
+            Self::UnexpectedSighash {
+                input_index,
+                pubkey,
+            } => write!(
+                f,
+                "Signature for public key '{}' in input {} doesn't use the requested sighash type.",
+                pubkey, input_index
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SignatureError {}
+
+/// Check the ECDSA signatures of the P2WSH inputs and the Schnorr signatures of the Taproot inputs
+/// of this PSBT verify for the public key they are given for, and use the sighash type requested
+/// for the input if any.
+pub fn check_signatures<C: secp256k1::Verification>(
+    secp: &secp256k1::Secp256k1<C>,
+    psbt: &Psbt,
+) -> Result<(), SignatureError> {
+    let mut cache = bitcoin::sighash::SighashCache::new(&psbt.unsigned_tx);
+    let prevouts: Option<Vec<&bitcoin::TxOut>> = psbt
+        .inputs
+        .iter()
+        .map(|psbt_in| psbt_in.witness_utxo.as_ref())
+        .collect();
+
+    for (index, psbt_in) in psbt.inputs.iter().enumerate() {
+        if psbt_in.partial_sigs.is_empty()
+            && psbt_in.tap_script_sigs.is_empty()
+            && psbt_in.tap_key_sig.is_none()
+        {
+            continue;
+        }
+        let utxo = psbt_in
+            .witness_utxo
+            .as_ref()
+            .ok_or(SignatureError::MissingUtxo(index))?;
+        let invalid = |pubkey: String| SignatureError::Invalid {
+            input_index: index,
+            pubkey,
+        };
+        let check_sighash =
+            |sighash_type: PsbtSighashType, pubkey: String| match psbt_in.sighash_type {
+                Some(requested) if requested != sighash_type => {
+                    Err(SignatureError::UnexpectedSighash {
+                        input_index: index,
+                        pubkey,
+                    })
+                }
+                _ => Ok(()),
+            };
+
+        for (pubkey, sig) in &psbt_in.partial_sigs {
+            check_sighash(sig.sighash_type.into(), pubkey.to_string())?;
+            let sighash = psbt_in.witness_script.as_ref().and_then(|script| {
+                cache
+                    .p2wsh_signature_hash(index, script, utxo.value, sig.sighash_type)
+                    .ok()
+            });
+            let valid = sighash
+                .map(|sighash| {
+                    secp.verify_ecdsa(&sighash.into(), &sig.signature, &pubkey.inner)
+                        .is_ok()
+                })
+                .unwrap_or(false);
+            if !valid {
+                return Err(invalid(pubkey.to_string()));
+            }
+        }
+
+        let tap_sighash = |cache: &mut bitcoin::sighash::SighashCache<&bitcoin::Transaction>,
+                           sighash_type: bitcoin::TapSighashType,
+                           leaf_hash: Option<bitcoin::TapLeafHash>| {
+            descriptors::taproot_sighash(
+                cache,
+                index,
+                prevouts.as_deref(),
+                utxo,
+                sighash_type,
+                leaf_hash,
+            )
+            .ok_or(SignatureError::MissingUtxo(index))
+        };
+
+        for ((pubkey, leaf_hash), sig) in &psbt_in.tap_script_sigs {
+            check_sighash(sig.sighash_type.into(), pubkey.to_string())?;
+            let msg = tap_sighash(&mut cache, sig.sighash_type, Some(*leaf_hash))?;
+            if secp.verify_schnorr(&sig.signature, &msg, pubkey).is_err() {
+                return Err(invalid(pubkey.to_string()));
+            }
+        }
+
+        if let Some(sig) = psbt_in.tap_key_sig {
+            // A key path spend is signed by the output key, which is in the Taproot output.
+            let output_key = utxo
+                .script_pubkey
+                .is_p2tr()
+                .then(|| secp256k1::XOnlyPublicKey::from_slice(&utxo.script_pubkey.as_bytes()[2..]))
+                .and_then(|key| key.ok());
+            check_sighash(
+                sig.sighash_type.into(),
+                output_key
+                    .map(|key| key.to_string())
+                    .unwrap_or_else(|| utxo.script_pubkey.to_hex_string()),
+            )?;
+            let valid = match output_key {
+                Some(output_key) => {
+                    let msg = tap_sighash(&mut cache, sig.sighash_type, None)?;
+                    secp.verify_schnorr(&sig.signature, &msg, &output_key)
+                        .is_ok()
+                }
+                None => false,
+            };
+            if !valid {
+                return Err(invalid(
+                    output_key
+                        .map(|key| key.to_string())
+                        .unwrap_or_else(|| utxo.script_pubkey.to_hex_string()),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Add to this PSBT the signatures contained in the witnesses of `tx`, a signed version of its
+/// transaction. A signature is only added if it verifies for one of the keys the PSBT input
+/// declares the origin of (or, for a Taproot key path spend, for the output key). Signatures
+/// already present are kept. Returns the number of signatures added.
+///
+/// Nothing is added if `tx` isn't the transaction of this PSBT.
+pub fn add_signatures_from_tx<C: secp256k1::Verification>(
+    secp: &secp256k1::Secp256k1<C>,
+    psbt: &mut Psbt,
+    tx: &bitcoin::Transaction,
+) -> Result<usize, SignatureError> {
+    if tx.compute_txid() != psbt.unsigned_tx.compute_txid() {
+        return Ok(0);
+    }
+    let unsigned_tx = psbt.unsigned_tx.clone();
+    let mut cache = bitcoin::sighash::SighashCache::new(&unsigned_tx);
+    let witness_utxos: Vec<Option<bitcoin::TxOut>> = psbt
+        .inputs
+        .iter()
+        .map(|psbt_in| psbt_in.witness_utxo.clone())
+        .collect();
+    let prevouts: Option<Vec<&bitcoin::TxOut>> =
+        witness_utxos.iter().map(|utxo| utxo.as_ref()).collect();
+    let mut added = 0;
+
+    for (index, (txin, psbt_in)) in tx.input.iter().zip(psbt.inputs.iter_mut()).enumerate() {
+        if txin.witness.is_empty() {
+            continue;
+        }
+        let utxo = witness_utxos[index]
+            .as_ref()
+            .ok_or(SignatureError::MissingUtxo(index))?;
+        // The last element of a P2WSH witness and the last two elements of a Taproot script
+        // path witness aren't signatures, but trying to parse them as such is harmless.
+        let elements: Vec<&[u8]> = txin.witness.iter().collect();
+
+        if let Some(script) = psbt_in.witness_script.clone() {
+            let pubkeys: Vec<_> = psbt_in.bip32_derivation.keys().copied().collect();
+            for sig in elements
+                .iter()
+                .filter_map(|elem| bitcoin::ecdsa::Signature::from_slice(elem).ok())
+            {
+                let sighash = match cache.p2wsh_signature_hash(
+                    index,
+                    &script,
+                    utxo.value,
+                    sig.sighash_type,
+                ) {
+                    Ok(sighash) => secp256k1::Message::from(sighash),
+                    Err(_) => continue,
+                };
+                if let Some(pubkey) = pubkeys
+                    .iter()
+                    .find(|pubkey| secp.verify_ecdsa(&sighash, &sig.signature, pubkey).is_ok())
+                {
+                    let pubkey = bitcoin::PublicKey::new(*pubkey);
+                    if let btree_map::Entry::Vacant(entry) = psbt_in.partial_sigs.entry(pubkey) {
+                        entry.insert(sig);
+                        added += 1;
+                    }
+                }
+            }
+        } else if utxo.script_pubkey.is_p2tr() {
+            // Drop the annex, if any.
+            let elements = match elements.split_last() {
+                Some((last, rest)) if elements.len() > 1 && last.first() == Some(&0x50) => rest,
+                _ => &elements[..],
+            };
+            if let [key_sig] = elements {
+                let (sig, output_key) = match (
+                    bitcoin::taproot::Signature::from_slice(key_sig),
+                    secp256k1::XOnlyPublicKey::from_slice(&utxo.script_pubkey.as_bytes()[2..]),
+                ) {
+                    (Ok(sig), Ok(output_key)) => (sig, output_key),
+                    _ => continue,
+                };
+                let msg = descriptors::taproot_sighash(
+                    &mut cache,
+                    index,
+                    prevouts.as_deref(),
+                    utxo,
+                    sig.sighash_type,
+                    None,
+                )
+                .ok_or(SignatureError::MissingUtxo(index))?;
+                if psbt_in.tap_key_sig.is_none()
+                    && secp
+                        .verify_schnorr(&sig.signature, &msg, &output_key)
+                        .is_ok()
+                {
+                    psbt_in.tap_key_sig = Some(sig);
+                    added += 1;
+                }
+            } else if let [sigs @ .., script, _control_block] = elements {
+                let leaf_hash = bitcoin::TapLeafHash::from_script(
+                    bitcoin::Script::from_bytes(script),
+                    bitcoin::taproot::LeafVersion::TapScript,
+                );
+                let pubkeys: Vec<_> = psbt_in
+                    .tap_key_origins
+                    .iter()
+                    .filter(|(_, (leaf_hashes, _))| leaf_hashes.contains(&leaf_hash))
+                    .map(|(pubkey, _)| *pubkey)
+                    .collect();
+                for sig in sigs
+                    .iter()
+                    .filter_map(|elem| bitcoin::taproot::Signature::from_slice(elem).ok())
+                {
+                    let msg = descriptors::taproot_sighash(
+                        &mut cache,
+                        index,
+                        prevouts.as_deref(),
+                        utxo,
+                        sig.sighash_type,
+                        Some(leaf_hash),
+                    )
+                    .ok_or(SignatureError::MissingUtxo(index))?;
+                    if let Some(pubkey) = pubkeys
+                        .iter()
+                        .find(|pubkey| secp.verify_schnorr(&sig.signature, &msg, pubkey).is_ok())
+                    {
+                        if let btree_map::Entry::Vacant(entry) =
+                            psbt_in.tap_script_sigs.entry((*pubkey, leaf_hash))
+                        {
+                            entry.insert(sig);
+                            added += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(added)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use std::time::Duration;
 
-    use miniscript::bitcoin::absolute::{Height, LockTime};
+    use miniscript::bitcoin::{
+        absolute::{Height, LockTime},
+        hashes::Hash,
+    };
 
     #[test]
     fn test_anti_fee_sniping_locktime() {
@@ -922,4 +1541,420 @@ mod tests {
             LockTime::from_height(1).unwrap() // subtract 90
         );
     }
+
+    #[test]
+    fn signatures_check() {
+        let secp = secp256k1::Secp256k1::new();
+        let sk = secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+        let pubkey = bitcoin::PublicKey::new(sk.public_key(&secp));
+        let witness_script = bitcoin::script::Builder::new()
+            .push_key(&pubkey)
+            .push_opcode(bitcoin::opcodes::all::OP_CHECKSIG)
+            .into_script();
+        let utxo = bitcoin::TxOut {
+            value: bitcoin::Amount::from_sat(100_000),
+            script_pubkey: bitcoin::ScriptBuf::new_p2wsh(&witness_script.wscript_hash()),
+        };
+        let tx = bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![bitcoin::TxIn {
+                previous_output: bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), 0),
+                ..bitcoin::TxIn::default()
+            }],
+            output: vec![bitcoin::TxOut {
+                value: bitcoin::Amount::from_sat(90_000),
+                script_pubkey: utxo.script_pubkey.clone(),
+            }],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_script = Some(witness_script.clone());
+
+        // Without signatures there is nothing to check.
+        check_signatures(&secp, &psbt).unwrap();
+
+        // We need the previous output to verify a signature.
+        let sighash = bitcoin::sighash::SighashCache::new(&psbt.unsigned_tx)
+            .p2wsh_signature_hash(
+                0,
+                &witness_script,
+                utxo.value,
+                bitcoin::EcdsaSighashType::All,
+            )
+            .unwrap();
+        let sig = bitcoin::ecdsa::Signature::sighash_all(secp.sign_ecdsa(&sighash.into(), &sk));
+        psbt.inputs[0].partial_sigs.insert(pubkey, sig);
+        assert_eq!(
+            check_signatures(&secp, &psbt),
+            Err(SignatureError::MissingUtxo(0))
+        );
+
+        // A valid signature passes.
+        psbt.inputs[0].witness_utxo = Some(utxo);
+        check_signatures(&secp, &psbt).unwrap();
+
+        // Unless another sighash type was requested for this input.
+        psbt.inputs[0].sighash_type = Some(EcdsaSighashType::AllPlusAnyoneCanPay.into());
+        assert_eq!(
+            check_signatures(&secp, &psbt),
+            Err(SignatureError::UnexpectedSighash {
+                input_index: 0,
+                pubkey: pubkey.to_string()
+            })
+        );
+        psbt.inputs[0].sighash_type = Some(EcdsaSighashType::All.into());
+        check_signatures(&secp, &psbt).unwrap();
+        psbt.inputs[0].sighash_type = None;
+
+        // A signature for another transaction, or made by another key, doesn't.
+        let other_sig = bitcoin::ecdsa::Signature::sighash_all(
+            secp.sign_ecdsa(&secp256k1::Message::from_digest([3; 32]), &sk),
+        );
+        psbt.inputs[0].partial_sigs.insert(pubkey, other_sig);
+        assert_eq!(
+            check_signatures(&secp, &psbt),
+            Err(SignatureError::Invalid {
+                input_index: 0,
+                pubkey: pubkey.to_string()
+            })
+        );
+        let other_pubkey = bitcoin::PublicKey::new(
+            secp256k1::SecretKey::from_slice(&[4; 32])
+                .unwrap()
+                .public_key(&secp),
+        );
+        psbt.inputs[0].partial_sigs.clear();
+        psbt.inputs[0].partial_sigs.insert(other_pubkey, sig);
+        assert!(matches!(
+            check_signatures(&secp, &psbt),
+            Err(SignatureError::Invalid { input_index: 0, .. })
+        ));
+
+        // Same for the signatures of Taproot script paths.
+        psbt.inputs[0].partial_sigs.clear();
+        let keypair = secp256k1::Keypair::from_secret_key(&secp, &sk);
+        let (xonly_key, _) = keypair.x_only_public_key();
+        let leaf_script = bitcoin::script::Builder::new()
+            .push_x_only_key(&xonly_key)
+            .push_opcode(bitcoin::opcodes::all::OP_CHECKSIG)
+            .into_script();
+        let leaf_hash = bitcoin::TapLeafHash::from_script(
+            &leaf_script,
+            bitcoin::taproot::LeafVersion::TapScript,
+        );
+        let spend_info = bitcoin::taproot::TaprootBuilder::new()
+            .add_leaf(0, leaf_script)
+            .unwrap()
+            .finalize(&secp, xonly_key)
+            .unwrap();
+        let tr_utxo = bitcoin::TxOut {
+            value: bitcoin::Amount::from_sat(100_000),
+            script_pubkey: bitcoin::ScriptBuf::new_p2tr_tweaked(spend_info.output_key()),
+        };
+        let sighash = bitcoin::sighash::SighashCache::new(&psbt.unsigned_tx)
+            .taproot_script_spend_signature_hash(
+                0,
+                &bitcoin::sighash::Prevouts::All(&[&tr_utxo]),
+                leaf_hash,
+                bitcoin::TapSighashType::Default,
+            )
+            .unwrap();
+        let tap_sig = bitcoin::taproot::Signature {
+            signature: secp.sign_schnorr_no_aux_rand(&sighash.into(), &keypair),
+            sighash_type: bitcoin::TapSighashType::Default,
+        };
+        psbt.inputs[0].witness_utxo = Some(tr_utxo);
+        psbt.inputs[0]
+            .tap_script_sigs
+            .insert((xonly_key, leaf_hash), tap_sig);
+        check_signatures(&secp, &psbt).unwrap();
+        psbt.inputs[0].tap_script_sigs.insert(
+            (xonly_key, leaf_hash),
+            bitcoin::taproot::Signature {
+                signature: secp
+                    .sign_schnorr_no_aux_rand(&secp256k1::Message::from_digest([3; 32]), &keypair),
+                sighash_type: bitcoin::TapSighashType::Default,
+            },
+        );
+        assert_eq!(
+            check_signatures(&secp, &psbt),
+            Err(SignatureError::Invalid {
+                input_index: 0,
+                pubkey: xonly_key.to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn signatures_from_tx() {
+        let secp = secp256k1::Secp256k1::new();
+        let sk = secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+        let pubkey = bitcoin::PublicKey::new(sk.public_key(&secp));
+        let origin = (
+            bip32::Fingerprint::from([1, 2, 3, 4]),
+            bip32::DerivationPath::master(),
+        );
+        let witness_script = bitcoin::script::Builder::new()
+            .push_key(&pubkey)
+            .push_opcode(bitcoin::opcodes::all::OP_CHECKSIG)
+            .into_script();
+        let utxo = bitcoin::TxOut {
+            value: bitcoin::Amount::from_sat(100_000),
+            script_pubkey: bitcoin::ScriptBuf::new_p2wsh(&witness_script.wscript_hash()),
+        };
+        let tx = bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![bitcoin::TxIn {
+                previous_output: bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), 0),
+                ..bitcoin::TxIn::default()
+            }],
+            output: vec![bitcoin::TxOut {
+                value: bitcoin::Amount::from_sat(90_000),
+                script_pubkey: utxo.script_pubkey.clone(),
+            }],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(tx.clone()).unwrap();
+        psbt.inputs[0].witness_script = Some(witness_script.clone());
+        psbt.inputs[0].witness_utxo = Some(utxo.clone());
+        psbt.inputs[0]
+            .bip32_derivation
+            .insert(pubkey.inner, origin.clone());
+
+        // An unsigned transaction has no signature to add.
+        assert_eq!(add_signatures_from_tx(&secp, &mut psbt, &tx), Ok(0));
+
+        // The signature of a P2WSH input is added for the key it verifies against, once.
+        let sighash = bitcoin::sighash::SighashCache::new(&tx)
+            .p2wsh_signature_hash(
+                0,
+                &witness_script,
+                utxo.value,
+                bitcoin::EcdsaSighashType::All,
+            )
+            .unwrap();
+        let sig = bitcoin::ecdsa::Signature::sighash_all(secp.sign_ecdsa(&sighash.into(), &sk));
+        let mut signed_tx = tx.clone();
+        signed_tx.input[0].witness =
+            bitcoin::Witness::from_slice(&[sig.to_vec(), witness_script.to_bytes()]);
+        assert_eq!(add_signatures_from_tx(&secp, &mut psbt, &signed_tx), Ok(1));
+        assert_eq!(psbt.inputs[0].partial_sigs.get(&pubkey), Some(&sig));
+        assert_eq!(add_signatures_from_tx(&secp, &mut psbt, &signed_tx), Ok(0));
+        check_signatures(&secp, &psbt).unwrap();
+
+        // Not if it doesn't verify against any key of the input.
+        psbt.inputs[0].partial_sigs.clear();
+        psbt.inputs[0].bip32_derivation.clear();
+        assert_eq!(add_signatures_from_tx(&secp, &mut psbt, &signed_tx), Ok(0));
+
+        // Nothing is added from another transaction.
+        let mut other_tx = signed_tx.clone();
+        other_tx.lock_time = LockTime::from_height(1).unwrap();
+        psbt.inputs[0]
+            .bip32_derivation
+            .insert(pubkey.inner, origin.clone());
+        assert_eq!(add_signatures_from_tx(&secp, &mut psbt, &other_tx), Ok(0));
+
+        // The signature of a Taproot script path is added for the leaf it was made for.
+        let keypair = secp256k1::Keypair::from_secret_key(&secp, &sk);
+        let (xonly_key, _) = keypair.x_only_public_key();
+        let leaf_script = bitcoin::script::Builder::new()
+            .push_x_only_key(&xonly_key)
+            .push_opcode(bitcoin::opcodes::all::OP_CHECKSIG)
+            .into_script();
+        let leaf_hash = bitcoin::TapLeafHash::from_script(
+            &leaf_script,
+            bitcoin::taproot::LeafVersion::TapScript,
+        );
+        let spend_info = bitcoin::taproot::TaprootBuilder::new()
+            .add_leaf(0, leaf_script.clone())
+            .unwrap()
+            .finalize(&secp, xonly_key)
+            .unwrap();
+        let control_block = spend_info
+            .control_block(&(
+                leaf_script.clone(),
+                bitcoin::taproot::LeafVersion::TapScript,
+            ))
+            .unwrap();
+        let tr_utxo = bitcoin::TxOut {
+            value: bitcoin::Amount::from_sat(100_000),
+            script_pubkey: bitcoin::ScriptBuf::new_p2tr_tweaked(spend_info.output_key()),
+        };
+        let mut psbt = Psbt::from_unsigned_tx(tx.clone()).unwrap();
+        psbt.inputs[0].witness_utxo = Some(tr_utxo.clone());
+        psbt.inputs[0]
+            .tap_key_origins
+            .insert(xonly_key, (vec![leaf_hash], origin));
+        let sighash = bitcoin::sighash::SighashCache::new(&tx)
+            .taproot_script_spend_signature_hash(
+                0,
+                &bitcoin::sighash::Prevouts::All(&[&tr_utxo]),
+                leaf_hash,
+                bitcoin::TapSighashType::Default,
+            )
+            .unwrap();
+        let tap_sig = bitcoin::taproot::Signature {
+            signature: secp.sign_schnorr_no_aux_rand(&sighash.into(), &keypair),
+            sighash_type: bitcoin::TapSighashType::Default,
+        };
+        let mut signed_tx = tx;
+        signed_tx.input[0].witness = bitcoin::Witness::from_slice(&[
+            tap_sig.to_vec(),
+            leaf_script.to_bytes(),
+            control_block.serialize(),
+        ]);
+        assert_eq!(add_signatures_from_tx(&secp, &mut psbt, &signed_tx), Ok(1));
+        assert_eq!(
+            psbt.inputs[0].tap_script_sigs.get(&(xonly_key, leaf_hash)),
+            Some(&tap_sig)
+        );
+        check_signatures(&secp, &psbt).unwrap();
+    }
+
+    #[test]
+    fn sighash_types() {
+        for sighash in SpendSighash::ALL.iter().copied() {
+            assert_eq!(SpendSighash::from_str(sighash.as_str()), Ok(sighash));
+        }
+        assert!(SpendSighash::from_str("none").is_err());
+
+        let desc = descriptors::LianaDescriptor::from_str("wsh(or_d(pk([aabbccdd]tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/<0;1>/*),and_v(v:pkh([abcdef01]tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/<0;1>/*),older(10000))))").unwrap();
+        let txin = |vout| bitcoin::TxIn {
+            previous_output: bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), vout),
+            ..bitcoin::TxIn::default()
+        };
+        let tx = bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![txin(0), txin(1)],
+            output: vec![bitcoin::TxOut {
+                value: bitcoin::Amount::from_sat(90_000),
+                script_pubkey: bitcoin::ScriptBuf::new(),
+            }],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+        assert_eq!(SpendSighash::from_psbt(&psbt), Some(SpendSighash::All));
+
+        // All the inputs are requested to be signed with the sighash type.
+        set_sighash(&desc, &mut psbt, SpendSighash::AllAnyonecanpay).unwrap();
+        assert!(psbt
+            .inputs
+            .iter()
+            .all(|psbt_in| psbt_in.sighash_type
+                == Some(EcdsaSighashType::AllPlusAnyoneCanPay.into())));
+        assert_eq!(
+            SpendSighash::from_psbt(&psbt),
+            Some(SpendSighash::AllAnyonecanpay)
+        );
+        set_sighash(&desc, &mut psbt, SpendSighash::All).unwrap();
+        assert!(psbt
+            .inputs
+            .iter()
+            .all(|psbt_in| psbt_in.sighash_type.is_none()));
+
+        // The second input has no output to commit to with SIGHASH_SINGLE.
+        assert_eq!(
+            set_sighash(&desc, &mut psbt, SpendSighash::Single),
+            Err(SpendCreationError::SighashSingleMissingOutput(1))
+        );
+        psbt.unsigned_tx.input.pop();
+        psbt.inputs.pop();
+        set_sighash(&desc, &mut psbt, SpendSighash::SingleAnyonecanpay).unwrap();
+        assert_eq!(
+            SpendSighash::from_psbt(&psbt),
+            Some(SpendSighash::SingleAnyonecanpay)
+        );
+    }
+
+    #[test]
+    fn fee_deduction() {
+        let txo = |value| bitcoin::TxOut {
+            value: bitcoin::Amount::from_sat(value),
+            script_pubkey: bitcoin::ScriptBuf::new(),
+        };
+        let values = |outputs: &[bitcoin::TxOut]| {
+            outputs
+                .iter()
+                .map(|txo| txo.value.to_sat())
+                .collect::<Vec<_>>()
+        };
+
+        // The fee is split between the destinations, the first one pays for the remainder.
+        let mut outputs = vec![txo(100_000), txo(50_000), txo(20_000)];
+        deduct_fee(&mut outputs, &[0, 2], bitcoin::Amount::from_sat(1_001)).unwrap();
+        assert_eq!(values(&outputs), vec![99_499, 50_000, 19_500]);
+        deduct_fee(&mut outputs, &[1], bitcoin::Amount::from_sat(0)).unwrap();
+        assert_eq!(values(&outputs), vec![99_499, 50_000, 19_500]);
+
+        // A destination can't be left with less than the dust limit.
+        let mut outputs = vec![txo(DUST_OUTPUT_SATS + 500)];
+        deduct_fee(&mut outputs, &[0], bitcoin::Amount::from_sat(500)).unwrap();
+        assert_eq!(
+            deduct_fee(&mut outputs, &[0], bitcoin::Amount::from_sat(1)),
+            Err(SpendCreationError::FeeExceedsDestination(0))
+        );
+        assert_eq!(
+            deduct_fee(&mut outputs, &[1], bitcoin::Amount::from_sat(1)),
+            Err(SpendCreationError::UnknownFeeDestination(1))
+        );
+    }
+
+    #[test]
+    fn output_types() {
+        // The type and the weight of an output to each standard type of address.
+        for (addr, output_type, weight) in [
+            ("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2", OutputType::P2pkh, 136),
+            ("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy", OutputType::P2sh, 128),
+            (
+                "bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv",
+                OutputType::P2wpkh,
+                124,
+            ),
+            (
+                "bc1q9ksrc647hx8zp2cewl8p5f487dgux3777yees8rjcx46t4daqzzqt7yga8",
+                OutputType::P2wsh,
+                172,
+            ),
+            (
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+                OutputType::P2tr,
+                172,
+            ),
+            (
+                "bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs",
+                OutputType::FutureWitness(2),
+                108,
+            ),
+        ] {
+            let spk = bitcoin::Address::from_str(addr)
+                .unwrap()
+                .assume_checked()
+                .script_pubkey();
+            assert_eq!(OutputType::from_script(&spk), Some(output_type));
+            let txo = bitcoin::TxOut {
+                value: bitcoin::Amount::from_sat(10_000),
+                script_pubkey: spk,
+            };
+            assert_eq!(txo.weight().to_wu(), weight);
+        }
+
+        // Bare multisig and witness v0 programs of an unknown size aren't standard.
+        let pubkey = bitcoin::PublicKey::from_str(
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        )
+        .unwrap();
+        let bare_multisig = bitcoin::script::Builder::new()
+            .push_int(1)
+            .push_key(&pubkey)
+            .push_int(1)
+            .push_opcode(bitcoin::opcodes::all::OP_CHECKMULTISIG)
+            .into_script();
+        assert_eq!(OutputType::from_script(&bare_multisig), None);
+        let v0_program = bitcoin::script::Builder::new()
+            .push_int(0)
+            .push_slice([0; 24])
+            .into_script();
+        assert_eq!(OutputType::from_script(&v0_program), None);
+    }
 }
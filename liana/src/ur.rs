@@ -0,0 +1,417 @@
+//! Uniform Resources (BCR-2020-005), to pass data through QR codes: the descriptor to register it
+//! on a signing device, and the PSBTs to and from airgapped signers.
+//!
+//! Data too large for a single QR code is split in fragments displayed in turn as an animated QR
+//! code (multi-part UR). The fountain encoding also defines parts mixing several fragments, for
+//! the receiver to recover from missed frames. Only the "pure" parts, each carrying a single
+//! fragment, are produced: every decoder accepts them. Mixed parts are ignored when decoding.
+
+use miniscript::bitcoin::psbt::Psbt;
+
+use std::{collections::BTreeMap, convert::TryFrom, error, fmt};
+
+/// The type of the Uniform Resource PSBTs are exchanged as (BCR-2020-006).
+pub const UR_TYPE_PSBT: &str = "crypto-psbt";
+/// The type superseding `crypto-psbt`, for the same content, used by newer signers.
+const UR_TYPE_PSBT_V2: &str = "psbt";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UrError {
+    /// The text doesn't start with the `ur:` scheme.
+    NotUr,
+    UnexpectedType(String),
+    InvalidBytewords,
+    InvalidChecksum,
+    InvalidCbor,
+    /// The parts don't belong to the same message.
+    InconsistentParts,
+    /// Some fragments of a multi-part message weren't given.
+    MissingParts {
+        missing: u64,
+        total: u64,
+    },
+    InvalidPsbt(String),
+}
+
+impl fmt::Display for UrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotUr => write!(f, "Not a Uniform Resource"),
+            Self::UnexpectedType(t) => write!(f, "Unexpected type of Uniform Resource: '{}'", t),
+            Self::InvalidBytewords => write!(f, "Invalid Bytewords encoding"),
+            Self::InvalidChecksum => write!(f, "Invalid checksum"),
+            Self::InvalidCbor => write!(f, "Invalid CBOR content"),
+            Self::InconsistentParts => write!(f, "The parts are not of the same message"),
+            Self::MissingParts { missing, total } => {
+                write!(f, "{} of the {} parts are missing", missing, total)
+            }
+            Self::InvalidPsbt(e) => write!(f, "Invalid PSBT: {}", e),
+        }
+    }
+}
+
+impl error::Error for UrError {}
+
+/// Encode a CBOR message as a Uniform Resource of this type, in as many parts as necessary for
+/// none to carry more than `max_fragment_len` bytes of the message. The parts are in upper case
+/// so they fit in QR codes in alphanumeric mode.
+pub fn encode(ur_type: &str, message: &[u8], max_fragment_len: usize) -> Vec<String> {
+    let max_fragment_len = max_fragment_len.max(1);
+    if message.len() <= max_fragment_len {
+        return vec![format!("ur:{}/{}", ur_type, bytewords_minimal(message)).to_uppercase()];
+    }
+    // Fragments of the same length, as close as possible to the maximum.
+    let count = (message.len() + max_fragment_len - 1) / max_fragment_len;
+    let fragment_len = (message.len() + count - 1) / count;
+    let checksum = crc32(message);
+    let mut padded = message.to_vec();
+    padded.resize(count * fragment_len, 0);
+    padded
+        .chunks(fragment_len)
+        .enumerate()
+        .map(|(i, fragment)| {
+            // An array of the sequence number, the sequence length, the length and the checksum
+            // of the message, and the fragment.
+            let mut part = Vec::with_capacity(fragment.len() + 20);
+            cbor_header(&mut part, 4, 5);
+            cbor_header(&mut part, 0, i as u64 + 1);
+            cbor_header(&mut part, 0, count as u64);
+            cbor_header(&mut part, 0, message.len() as u64);
+            cbor_header(&mut part, 0, checksum as u64);
+            cbor_header(&mut part, 2, fragment.len() as u64);
+            part.extend_from_slice(fragment);
+            format!(
+                "ur:{}/{}-{}/{}",
+                ur_type,
+                i + 1,
+                count,
+                bytewords_minimal(&part)
+            )
+            .to_uppercase()
+        })
+        .collect()
+}
+
+/// Decode the parts of a Uniform Resource, in any order, into its type and CBOR message.
+pub fn decode<S: AsRef<str>>(parts: &[S]) -> Result<(String, Vec<u8>), UrError> {
+    let mut ur_type = None;
+    // The message and the fragments of the multi-part one, by sequence number.
+    let mut single = None;
+    let mut fragments = BTreeMap::new();
+    let mut multi_info = None;
+    for part in parts {
+        let part = part.as_ref().trim().to_lowercase();
+        let rest = part.strip_prefix("ur:").ok_or(UrError::NotUr)?;
+        let (part_type, rest) = rest.split_once('/').ok_or(UrError::NotUr)?;
+        if ur_type.get_or_insert_with(|| part_type.to_string()) != part_type {
+            return Err(UrError::InconsistentParts);
+        }
+        match rest.split_once('/') {
+            None => single = Some(bytewords_minimal_decode(rest)?),
+            Some((_, payload)) => {
+                let data = bytewords_minimal_decode(payload)?;
+                let mut cbor = CborReader::new(&data);
+                if cbor.header()? != (4, 5) {
+                    return Err(UrError::InvalidCbor);
+                }
+                let (seq_num, seq_len) = (cbor.uint()?, cbor.uint()?);
+                let info = (seq_len, cbor.uint()?, cbor.uint()?);
+                if *multi_info.get_or_insert(info) != info {
+                    return Err(UrError::InconsistentParts);
+                }
+                let fragment = cbor.bytes()?;
+                if (1..=seq_len).contains(&seq_num) {
+                    fragments.insert(seq_num, fragment.to_vec());
+                }
+            }
+        }
+    }
+    let ur_type = ur_type.ok_or(UrError::NotUr)?;
+
+    let message = match (single, multi_info) {
+        (Some(message), None) => message,
+        (None, Some((seq_len, message_len, checksum))) => {
+            let found = fragments.len() as u64;
+            if found < seq_len {
+                return Err(UrError::MissingParts {
+                    missing: seq_len - found,
+                    total: seq_len,
+                });
+            }
+            let mut message: Vec<u8> = fragments.into_values().flatten().collect();
+            if (message.len() as u64) < message_len {
+                return Err(UrError::InvalidCbor);
+            }
+            message.truncate(message_len as usize);
+            if crc32(&message) as u64 != checksum {
+                return Err(UrError::InvalidChecksum);
+            }
+            message
+        }
+        _ => return Err(UrError::InconsistentParts),
+    };
+    Ok((ur_type, message))
+}
+
+/// Encode a PSBT as a `crypto-psbt` Uniform Resource, see [`encode`].
+pub fn encode_psbt(psbt: &Psbt, max_fragment_len: usize) -> Vec<String> {
+    let psbt = psbt.serialize();
+    let mut message = Vec::with_capacity(psbt.len() + 9);
+    cbor_header(&mut message, 2, psbt.len() as u64);
+    message.extend_from_slice(&psbt);
+    encode(UR_TYPE_PSBT, &message, max_fragment_len)
+}
+
+/// Decode a PSBT from the parts of a `crypto-psbt` or `psbt` Uniform Resource.
+pub fn decode_psbt<S: AsRef<str>>(parts: &[S]) -> Result<Psbt, UrError> {
+    let (ur_type, message) = decode(parts)?;
+    if ur_type != UR_TYPE_PSBT && ur_type != UR_TYPE_PSBT_V2 {
+        return Err(UrError::UnexpectedType(ur_type));
+    }
+    let mut cbor = CborReader::new(&message);
+    let psbt = cbor.bytes()?;
+    Psbt::deserialize(psbt).map_err(|e| UrError::InvalidPsbt(e.to_string()))
+}
+
+// Write the header of a CBOR data item of this major type and argument.
+pub(crate) fn cbor_header(buf: &mut Vec<u8>, major: u8, arg: u64) {
+    let major = major << 5;
+    if arg < 24 {
+        buf.push(major | arg as u8);
+    } else if arg <= u8::MAX as u64 {
+        buf.extend_from_slice(&[major | 24, arg as u8]);
+    } else if arg <= u16::MAX as u64 {
+        buf.push(major | 25);
+        buf.extend_from_slice(&(arg as u16).to_be_bytes());
+    } else if arg <= u32::MAX as u64 {
+        buf.push(major | 26);
+        buf.extend_from_slice(&(arg as u32).to_be_bytes());
+    } else {
+        buf.push(major | 27);
+        buf.extend_from_slice(&arg.to_be_bytes());
+    }
+}
+
+// Read the few CBOR data items found in the Uniform Resources we decode.
+struct CborReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> CborReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], UrError> {
+        let end = self.pos.checked_add(len).ok_or(UrError::InvalidCbor)?;
+        let bytes = self.data.get(self.pos..end).ok_or(UrError::InvalidCbor)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    // The major type and the argument of the next data item. Tags are skipped.
+    fn header(&mut self) -> Result<(u8, u64), UrError> {
+        let initial = *self.take(1)?.first().ok_or(UrError::InvalidCbor)?;
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        let arg = match info {
+            0..=23 => info as u64,
+            24..=27 => self
+                .take(1 << (info - 24))?
+                .iter()
+                .fold(0u64, |arg, b| (arg << 8) | *b as u64),
+            _ => return Err(UrError::InvalidCbor),
+        };
+        if major == 6 {
+            return self.header();
+        }
+        Ok((major, arg))
+    }
+
+    fn uint(&mut self) -> Result<u64, UrError> {
+        match self.header()? {
+            (0, arg) => Ok(arg),
+            _ => Err(UrError::InvalidCbor),
+        }
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], UrError> {
+        match self.header()? {
+            (2, len) => self.take(usize::try_from(len).map_err(|_| UrError::InvalidCbor)?),
+            _ => Err(UrError::InvalidCbor),
+        }
+    }
+}
+
+// The Bytewords (BCR-2020-012), one per byte value.
+const BYTEWORDS: &str = "ableacidalsoapexaquaarchatomauntawayaxisbackbaldbarnbeltbetabiasbluebodybragbrewbulbbuzzcalmcashcatschefcityclawcodecolacookcostcruxcurlcuspcyandarkdatadaysdelidicedietdoordowndrawdropdrumdulldutyeacheasyechoedgeepicevenexamexiteyesfactfairfernfigsfilmfishfizzflapflewfluxfoxyfreefrogfuelfundgalagamegeargemsgiftgirlglowgoodgraygrimgurugushgyrohalfhanghardhawkheathelphighhillholyhopehornhutsicedideaidleinchinkyintoirisironitemjadejazzjoinjoltjowljudojugsjumpjunkjurykeepkenokeptkeyskickkilnkingkitekiwiknoblamblavalazyleaflegsliarlimplionlistlogoloudloveluaulucklungmainmanymathmazememomenumeowmildmintmissmonknailnavyneednewsnextnoonnotenumbobeyoboeomitonyxopenovalowlspaidpartpeckplaypluspoempoolposepuffpumapurrquadquizraceramprealredorichroadrockroofrubyruinrunsrustsafesagascarsetssilkskewslotsoapsolosongstubsurfswantacotasktaxitenttiedtimetinytoiltombtoystriptunatwinuglyundouniturgeuservastveryvetovialvibeviewvisavoidvowswallwandwarmwaspwavewaxywebswhatwhenwhizwolfworkyankyawnyellyogayurtzapszerozestzinczonezoom";
+
+// Encode the data with its CRC32 checksum as minimal Bytewords: the first and last letters of
+// each word.
+pub(crate) fn bytewords_minimal(data: &[u8]) -> String {
+    let words = BYTEWORDS.as_bytes();
+    data.iter()
+        .chain(crc32(data).to_be_bytes().iter())
+        .flat_map(|b| {
+            let i = *b as usize * 4;
+            [words[i] as char, words[i + 3] as char]
+        })
+        .collect()
+}
+
+// Decode minimal Bytewords, checking and removing the CRC32 checksum.
+fn bytewords_minimal_decode(text: &str) -> Result<Vec<u8>, UrError> {
+    let words = BYTEWORDS.as_bytes();
+    let text = text.as_bytes();
+    if text.len() % 2 != 0 {
+        return Err(UrError::InvalidBytewords);
+    }
+    let mut data = text
+        .chunks(2)
+        .map(|pair| {
+            words
+                .chunks(4)
+                .position(|w| w.first() == pair.first() && w.last() == pair.last())
+                .map(|i| i as u8)
+                .ok_or(UrError::InvalidBytewords)
+        })
+        .collect::<Result<Vec<u8>, _>>()?;
+    let checksum_pos = data.len().checked_sub(4).ok_or(UrError::InvalidBytewords)?;
+    let checksum = data.split_off(checksum_pos);
+    if crc32(&data).to_be_bytes()[..] != checksum[..] {
+        return Err(UrError::InvalidChecksum);
+    }
+    Ok(data)
+}
+
+// The CRC-32 (ISO-HDLC) checksum, as used by the Uniform Resources.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miniscript::bitcoin::{
+        absolute, transaction, Amount, OutPoint, ScriptBuf, Transaction, TxIn, TxOut,
+    };
+    use std::collections::HashSet;
+
+    #[test]
+    fn bytewords() {
+        assert_eq!(BYTEWORDS.len(), 256 * 4);
+        // The minimal encoding must be unambiguous.
+        let minimal: HashSet<_> = BYTEWORDS
+            .as_bytes()
+            .chunks(4)
+            .map(|w| (w[0], w[3]))
+            .collect();
+        assert_eq!(minimal.len(), 256);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        // Test vector from BCR-2020-012.
+        assert_eq!(
+            bytewords_minimal(&[0, 1, 2, 128, 255]),
+            "aeadaolazmjendeoti"
+        );
+        assert_eq!(
+            bytewords_minimal_decode("aeadaolazmjendeoti").unwrap(),
+            vec![0, 1, 2, 128, 255]
+        );
+        assert_eq!(
+            bytewords_minimal_decode("aeadaolazmjendeota"),
+            Err(UrError::InvalidChecksum)
+        );
+        assert_eq!(
+            bytewords_minimal_decode("aeadaolazmjendeotx"),
+            Err(UrError::InvalidBytewords)
+        );
+        assert_eq!(
+            bytewords_minimal_decode("ae"),
+            Err(UrError::InvalidBytewords)
+        );
+    }
+
+    fn dummy_psbt(outputs: usize) -> Psbt {
+        let tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                ..TxIn::default()
+            }],
+            output: (0..outputs)
+                .map(|i| TxOut {
+                    value: Amount::from_sat(1_000 + i as u64),
+                    script_pubkey: ScriptBuf::new_op_return([i as u8; 32]),
+                })
+                .collect(),
+        };
+        Psbt::from_unsigned_tx(tx).unwrap()
+    }
+
+    #[test]
+    fn psbt_roundtrip() {
+        // A small PSBT fits in a single part.
+        let psbt = dummy_psbt(1);
+        let parts = encode_psbt(&psbt, 200);
+        assert_eq!(parts.len(), 1);
+        assert!(parts[0].starts_with("UR:CRYPTO-PSBT/"), "{}", parts[0]);
+        assert_eq!(parts[0].matches('/').count(), 1);
+        assert_eq!(decode_psbt(&parts).unwrap(), psbt);
+        assert_eq!(decode_psbt(&[parts[0].to_lowercase()]).unwrap(), psbt);
+
+        // A larger one is split in fragments of the same length, which can be given in any
+        // order.
+        let psbt = dummy_psbt(20);
+        let parts = encode_psbt(&psbt, 100);
+        let len = psbt.serialize().len() + 3;
+        assert_eq!(parts.len(), (len + 99) / 100);
+        for (i, part) in parts.iter().enumerate() {
+            assert!(
+                part.starts_with(&format!("UR:CRYPTO-PSBT/{}-{}/", i + 1, parts.len())),
+                "{}",
+                part
+            );
+        }
+        let mut shuffled = parts.clone();
+        shuffled.reverse();
+        assert_eq!(decode_psbt(&shuffled).unwrap(), psbt);
+        // A part given twice doesn't matter, a missing one does.
+        let mut duplicated = parts.clone();
+        duplicated.push(parts[0].clone());
+        assert_eq!(decode_psbt(&duplicated).unwrap(), psbt);
+        assert_eq!(
+            decode_psbt(&parts[1..]),
+            Err(UrError::MissingParts {
+                missing: 1,
+                total: parts.len() as u64
+            })
+        );
+        // Parts of different messages can't be mixed.
+        let other = encode_psbt(&dummy_psbt(21), 100);
+        assert_eq!(
+            decode_psbt(&[parts[0].clone(), other[1].clone()]),
+            Err(UrError::InconsistentParts)
+        );
+
+        assert_eq!(decode_psbt(&["cHNidP8BAA=="]), Err(UrError::NotUr));
+        assert_eq!(
+            decode_psbt(&["ur:bytes/aeadaolazmjendeoti"]),
+            Err(UrError::UnexpectedType("bytes".to_string()))
+        );
+        let (ur_type, message) = decode(&["ur:bytes/aeadaolazmjendeoti"]).unwrap();
+        assert_eq!(ur_type, "bytes");
+        assert_eq!(message, vec![0, 1, 2, 128, 255]);
+    }
+}
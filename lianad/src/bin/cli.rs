@@ -13,15 +13,442 @@ use serde_json::Value as Json;
 
 use std::os::unix::net::UnixStream;
 
+/// The kind of value expected for a command parameter passed as an option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParamKind {
+    String,
+    Integer,
+    Bool,
+    /// The option may be repeated, each occurrence adds an entry to the list.
+    List,
+    /// The option may be repeated, each occurrence is a `key=value` entry of the object.
+    Object {
+        integer_values: bool,
+    },
+    /// A JSON value, for the parameters too structured to be given as options.
+    Json,
+}
+
+#[derive(Debug)]
+struct Param {
+    /// Name of the parameter in the JSONRPC API.
+    name: &'static str,
+    /// Name of the command line option, without the leading dashes.
+    option: &'static str,
+    kind: ParamKind,
+    required: bool,
+    /// The possible values of the parameter, if restricted. Used for shell completion.
+    values: &'static [&'static str],
+}
+
+impl Param {
+    const fn new(name: &'static str, option: &'static str, kind: ParamKind) -> Self {
+        Self {
+            name,
+            option,
+            kind,
+            required: true,
+            values: &[],
+        }
+    }
+
+    const fn optional(mut self) -> Self {
+        self.required = false;
+        self
+    }
+
+    const fn values(mut self, values: &'static [&'static str]) -> Self {
+        self.values = values;
+        self
+    }
+}
+
+struct Command {
+    name: &'static str,
+    params: &'static [Param],
+}
+
+const COIN_STATUSES: &[&str] = &["unconfirmed", "confirmed", "spending", "spent"];
+const SPEND_STATUSES: &[&str] = &[
+    "needs_signature",
+    "fully_signed",
+    "broadcastable",
+    "conflicting",
+];
+const REVIEW_DECISIONS: &[&str] = &["approved", "changes_requested", "rejected"];
+const SIGHASHES: &[&str] = &["all", "all_anyonecanpay", "single", "single_anyonecanpay"];
+
+// The commands of the JSONRPC API, see doc/API.md.
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "stop",
+        params: &[],
+    },
+    Command {
+        name: "getbalance",
+        params: &[],
+    },
+    Command {
+        name: "getinfo",
+        params: &[],
+    },
+    Command {
+        name: "getmempoolstats",
+        params: &[],
+    },
+    Command {
+        name: "getnewaddress",
+        params: &[],
+    },
+    Command {
+        name: "listaddresses",
+        params: &[
+            Param::new("start_index", "start-index", ParamKind::Integer).optional(),
+            Param::new("count", "count", ParamKind::Integer).optional(),
+        ],
+    },
+    Command {
+        name: "gethotsignerxpubs",
+        params: &[
+            Param::new("derivation_paths", "derivation-path", ParamKind::List),
+            Param::new("fingerprint", "fingerprint", ParamKind::String).optional(),
+        ],
+    },
+    Command {
+        name: "importhotsigner",
+        // A BIP39 mnemonic, or each of the SLIP-39 shares.
+        params: &[Param::new("backup", "backup", ParamKind::List)],
+    },
+    Command {
+        name: "signwithhotsigner",
+        params: &[
+            Param::new("psbt", "psbt", ParamKind::String),
+            Param::new("fingerprint", "fingerprint", ParamKind::String).optional(),
+        ],
+    },
+    Command {
+        name: "getderivationindexes",
+        params: &[],
+    },
+    Command {
+        name: "healthcheck",
+        params: &[],
+    },
+    Command {
+        name: "updatederivationindexes",
+        params: &[
+            Param::new("receive", "receive", ParamKind::Integer).optional(),
+            Param::new("change", "change", ParamKind::Integer).optional(),
+        ],
+    },
+    Command {
+        name: "createaddressstatement",
+        params: &[
+            Param::new("count", "count", ParamKind::Integer),
+            Param::new("start_index", "start-index", ParamKind::Integer).optional(),
+        ],
+    },
+    Command {
+        name: "finalizeaddressstatement",
+        params: &[
+            Param::new("message", "message", ParamKind::String),
+            Param::new("psbt", "psbt", ParamKind::String),
+        ],
+    },
+    Command {
+        name: "verifyaddressstatement",
+        params: &[
+            Param::new("address", "address", ParamKind::String),
+            Param::new("message", "message", ParamKind::String),
+            Param::new("signature", "signature", ParamKind::String),
+        ],
+    },
+    Command {
+        name: "getsilentpaymentaddress",
+        params: &[Param::new("fingerprint", "fingerprint", ParamKind::String).optional()],
+    },
+    Command {
+        name: "listsilentpayments",
+        params: &[],
+    },
+    Command {
+        name: "sweepsilentpayments",
+        params: &[Param::new("feerate", "feerate", ParamKind::Integer)],
+    },
+    Command {
+        name: "lintdescriptor",
+        params: &[
+            Param::new("descriptor", "descriptor", ParamKind::String).optional(),
+            Param::new("min_timelock", "min-timelock", ParamKind::Integer).optional(),
+            Param::new("max_timelock", "max-timelock", ParamKind::Integer).optional(),
+        ],
+    },
+    Command {
+        name: "simulatesignerloss",
+        params: &[Param::new("fingerprints", "lost", ParamKind::List)],
+    },
+    Command {
+        name: "listcoins",
+        params: &[
+            Param::new("statuses", "status", ParamKind::List)
+                .optional()
+                .values(COIN_STATUSES),
+            Param::new("outpoints", "outpoint", ParamKind::List).optional(),
+        ],
+    },
+    Command {
+        name: "createspend",
+        params: &[
+            Param::new(
+                "destinations",
+                "destination",
+                ParamKind::Object {
+                    integer_values: true,
+                },
+            ),
+            Param::new("outpoints", "outpoint", ParamKind::List),
+            Param::new("feerate", "feerate", ParamKind::Integer),
+            Param::new("change_address", "change-address", ParamKind::String).optional(),
+            Param::new("locktime", "locktime", ParamKind::Integer).optional(),
+            Param::new("idempotency_key", "idempotency-key", ParamKind::String).optional(),
+            Param::new("sighash", "sighash", ParamKind::String)
+                .optional()
+                .values(SIGHASHES),
+            Param::new("subtract_fee_from", "subtract-fee-from", ParamKind::List).optional(),
+            Param::new(
+                "allow_future_witness",
+                "allow-future-witness",
+                ParamKind::Bool,
+            )
+            .optional(),
+        ],
+    },
+    Command {
+        name: "updatespend",
+        params: &[
+            Param::new("psbt", "psbt", ParamKind::String),
+            Param::new("idempotency_key", "idempotency-key", ParamKind::String).optional(),
+        ],
+    },
+    Command {
+        name: "finalizepsbt",
+        params: &[Param::new("psbt", "psbt", ParamKind::String)],
+    },
+    Command {
+        name: "listevents",
+        params: &[Param::new("since", "since", ParamKind::Integer).optional()],
+    },
+    Command {
+        name: "listspendtxs",
+        params: &[
+            Param::new("txids", "txid", ParamKind::List).optional(),
+            Param::new("statuses", "status", ParamKind::List)
+                .optional()
+                .values(SPEND_STATUSES),
+            Param::new("signer", "signer", ParamKind::String).optional(),
+        ],
+    },
+    Command {
+        name: "reviewspend",
+        params: &[
+            Param::new("txid", "txid", ParamKind::String),
+            Param::new("signer", "signer", ParamKind::String),
+            Param::new("decision", "decision", ParamKind::String).values(REVIEW_DECISIONS),
+            Param::new("comment", "comment", ParamKind::String).optional(),
+        ],
+    },
+    Command {
+        name: "delspendtx",
+        params: &[Param::new("txid", "txid", ParamKind::String)],
+    },
+    Command {
+        name: "schedulespend",
+        params: &[
+            Param::new("txid", "txid", ParamKind::String),
+            Param::new("confirmed_amount", "confirmed-amount", ParamKind::Integer).optional(),
+        ],
+    },
+    Command {
+        name: "listscheduledspends",
+        params: &[],
+    },
+    Command {
+        name: "unschedulespend",
+        params: &[Param::new("txid", "txid", ParamKind::String)],
+    },
+    Command {
+        name: "savespendtemplate",
+        params: &[Param::new("template", "template", ParamKind::Json)],
+    },
+    Command {
+        name: "listspendtemplates",
+        params: &[],
+    },
+    Command {
+        name: "delspendtemplate",
+        params: &[Param::new("name", "name", ParamKind::String)],
+    },
+    Command {
+        name: "instantiatespendtemplate",
+        params: &[
+            Param::new("name", "name", ParamKind::String),
+            Param::new(
+                "amounts",
+                "amount",
+                ParamKind::Object {
+                    integer_values: true,
+                },
+            )
+            .optional(),
+            Param::new("feerate", "feerate", ParamKind::Integer).optional(),
+        ],
+    },
+    Command {
+        name: "broadcastspend",
+        params: &[
+            Param::new("txid", "txid", ParamKind::String),
+            Param::new("idempotency_key", "idempotency-key", ParamKind::String).optional(),
+            Param::new("confirmed_amount", "confirmed-amount", ParamKind::Integer).optional(),
+        ],
+    },
+    Command {
+        name: "rbfpsbt",
+        params: &[
+            Param::new("txid", "txid", ParamKind::String),
+            Param::new("is_cancel", "cancel", ParamKind::Bool),
+            Param::new("feerate", "feerate", ParamKind::Integer).optional(),
+        ],
+    },
+    Command {
+        name: "startrescan",
+        params: &[Param::new("timestamp", "timestamp", ParamKind::Integer)],
+    },
+    Command {
+        name: "listconfirmed",
+        params: &[
+            Param::new("start", "start", ParamKind::Integer),
+            Param::new("end", "end", ParamKind::Integer),
+            Param::new("limit", "limit", ParamKind::Integer),
+        ],
+    },
+    Command {
+        name: "listtransactions",
+        params: &[Param::new("txids", "txid", ParamKind::List)],
+    },
+    Command {
+        name: "gettransaction",
+        params: &[Param::new("txid", "txid", ParamKind::String)],
+    },
+    Command {
+        name: "getreceipt",
+        params: &[
+            Param::new("txid", "txid", ParamKind::String),
+            Param::new("explorer_url", "explorer_url", ParamKind::String).optional(),
+        ],
+    },
+    Command {
+        name: "exportsnapshot",
+        params: &[],
+    },
+    Command {
+        name: "exporttransactions",
+        params: &[],
+    },
+    Command {
+        name: "createrecovery",
+        params: &[
+            Param::new("address", "address", ParamKind::String),
+            Param::new("feerate", "feerate", ParamKind::Integer),
+            Param::new("timelock", "timelock", ParamKind::Integer).optional(),
+        ],
+    },
+    Command {
+        name: "updatelabels",
+        params: &[Param::new(
+            "labels",
+            "label",
+            ParamKind::Object {
+                integer_values: false,
+            },
+        )],
+    },
+    Command {
+        name: "getlabels",
+        params: &[Param::new("items", "item", ParamKind::List)],
+    },
+    Command {
+        name: "importlabelscsv",
+        params: &[
+            Param::new("csv", "csv", ParamKind::String),
+            Param::new("overwrite", "overwrite", ParamKind::Bool).optional(),
+        ],
+    },
+    Command {
+        name: "getrecoveryreadiness",
+        params: &[],
+    },
+    Command {
+        name: "getrecoveryschedule",
+        params: &[
+            Param::new("timelock", "timelock", ParamKind::Integer).optional(),
+            Param::new("bucket_size", "bucket-size", ParamKind::Integer).optional(),
+        ],
+    },
+    Command {
+        name: "setrecoveryreadiness",
+        params: &[
+            Param::new("item", "item", ParamKind::String),
+            Param::new("done", "done", ParamKind::Bool),
+        ],
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Json,
+    Table,
+}
+
+#[derive(Debug, PartialEq)]
+struct Args {
+    conf_file: Option<PathBuf>,
+    raw: bool,
+    format: OutputFormat,
+    method: String,
+    params: Vec<String>,
+}
+
 // Exits with error
 fn show_usage() {
     eprintln!("Usage:");
-    eprintln!(" liana-cli [--conf conf_path] [--raw] <command> [<param 1> <param 2> ...]");
+    eprintln!(" liana-cli [--conf conf_path] [--raw] [--format json|table] <command> [<param 1> <param 2> ...]");
+    eprintln!(" liana-cli [--conf conf_path] [--raw] [--format json|table] <command> [--<option> <value> ...]");
+    eprintln!(" liana-cli completion bash|zsh|fish");
+    eprintln!();
+    eprintln!("Commands:");
+    for command in COMMANDS {
+        let options: Vec<String> = command.params.iter().map(option_usage).collect();
+        eprintln!("  {} {}", command.name, options.join(" "));
+    }
     process::exit(1);
 }
 
-// Returns (Maybe(special conf file), Raw, Method name, Maybe(List of parameters))
-fn parse_args(mut args: Vec<String>) -> (Option<PathBuf>, bool, String, Vec<String>) {
+fn option_usage(param: &Param) -> String {
+    let usage = match param.kind {
+        ParamKind::String | ParamKind::Integer => format!("--{} <{}>", param.option, param.name),
+        ParamKind::Bool => format!("--{} <true|false>", param.option),
+        ParamKind::List => format!("--{} <value>...", param.option),
+        ParamKind::Object { .. } => format!("--{} <key=value>...", param.option),
+        ParamKind::Json => format!("--{} <json>", param.option),
+    };
+    if param.required {
+        usage
+    } else {
+        format!("[{}]", usage)
+    }
+}
+
+fn parse_args(mut args: Vec<String>) -> Args {
     if args.len() < 2 {
         eprintln!("Not enough arguments.");
         show_usage();
@@ -31,10 +458,12 @@ fn parse_args(mut args: Vec<String>) -> (Option<PathBuf>, bool, String, Vec<Stri
 
     let mut args = args.into_iter();
     let mut raw = false;
+    let mut format = OutputFormat::Json;
     let mut conf_file = None;
 
     loop {
         match args.next().as_deref() {
+            Some("--help") | Some("-h") => show_usage(),
             Some("--conf") => {
                 if args.len() < 2 {
                     eprintln!("Not enough arguments.");
@@ -50,7 +479,30 @@ fn parse_args(mut args: Vec<String>) -> (Option<PathBuf>, bool, String, Vec<Stri
                 }
                 raw = true;
             }
-            Some(method) => return (conf_file, raw, method.to_owned(), args.collect()),
+            Some("--format") => {
+                if args.len() < 2 {
+                    eprintln!("Not enough arguments.");
+                    show_usage();
+                }
+                format = match args.next().as_deref() {
+                    Some("json") => OutputFormat::Json,
+                    Some("table") => OutputFormat::Table,
+                    f => {
+                        eprintln!("Unknown output format: {:?}.", f);
+                        show_usage();
+                        unreachable!("show_usage() exits");
+                    }
+                };
+            }
+            Some(method) => {
+                return Args {
+                    conf_file,
+                    raw,
+                    format,
+                    method: method.to_owned(),
+                    params: args.collect(),
+                }
+            }
             None => {
                 // Should never happen...
                 eprintln!("Not enough arguments.");
@@ -68,9 +520,103 @@ fn from_str_hack(token: String) -> Json {
     }
 }
 
-fn rpc_request(method: String, params: Vec<String>) -> Json {
+fn parse_option_value(param: &Param, value: &str) -> Result<Json, String> {
+    match param.kind {
+        ParamKind::String | ParamKind::List => Ok(Json::String(value.to_string())),
+        ParamKind::Integer => value
+            .parse::<u64>()
+            .map(Json::from)
+            .map_err(|_| format!("Invalid value for --{}: expected an integer.", param.option)),
+        ParamKind::Bool => value
+            .parse::<bool>()
+            .map(Json::Bool)
+            .map_err(|_| format!("Invalid value for --{}: expected a boolean.", param.option)),
+        ParamKind::Json => serde_json::from_str(value)
+            .map_err(|e| format!("Invalid value for --{}: {}.", param.option, e)),
+        ParamKind::Object { .. } => unreachable!("Objects are parsed by entries."),
+    }
+}
+
+/// Get the named parameters of a command from its command line options.
+fn named_params(
+    command: &Command,
+    options: Vec<String>,
+) -> Result<serde_json::Map<String, Json>, String> {
+    let mut params = serde_json::Map::<String, Json>::new();
+    let mut options = options.into_iter();
+    while let Some(option) = options.next() {
+        let param = option
+            .strip_prefix("--")
+            .and_then(|option| command.params.iter().find(|p| p.option == option))
+            .ok_or_else(|| format!("Unknown option '{}' for '{}'.", option, command.name))?;
+        let value = options
+            .next()
+            .ok_or_else(|| format!("Missing value for --{}.", param.option))?;
+        match param.kind {
+            ParamKind::List => {
+                params
+                    .entry(param.name)
+                    .or_insert_with(|| Json::Array(Vec::new()))
+                    .as_array_mut()
+                    .expect("Always an array")
+                    .push(parse_option_value(param, &value)?);
+            }
+            ParamKind::Object { integer_values } => {
+                let (key, value) = value.split_once('=').ok_or_else(|| {
+                    format!("Invalid value for --{}: expected key=value.", param.option)
+                })?;
+                let value = if integer_values {
+                    value.parse::<u64>().map(Json::from).map_err(|_| {
+                        format!("Invalid value for --{}: expected an integer.", param.option)
+                    })?
+                } else {
+                    Json::String(value.to_string())
+                };
+                params
+                    .entry(param.name)
+                    .or_insert_with(|| Json::Object(serde_json::Map::new()))
+                    .as_object_mut()
+                    .expect("Always an object")
+                    .insert(key.to_string(), value);
+            }
+            _ => {
+                if params
+                    .insert(param.name.to_string(), parse_option_value(param, &value)?)
+                    .is_some()
+                {
+                    return Err(format!("Option --{} given more than once.", param.option));
+                }
+            }
+        }
+    }
+
+    for param in command.params.iter().filter(|p| p.required) {
+        if !params.contains_key(param.name) {
+            // A required list may be empty.
+            if param.kind == ParamKind::List {
+                params.insert(param.name.to_string(), Json::Array(Vec::new()));
+            } else {
+                return Err(format!("Missing option --{}.", param.option));
+            }
+        }
+    }
+
+    Ok(params)
+}
+
+fn rpc_request(method: String, params: Vec<String>) -> Result<Json, String> {
+    // Parameters are given as options if the first one starts with a double dash, and are passed
+    // by position as JSON values otherwise.
+    let params = if params.first().map(|p| p.starts_with("--")).unwrap_or(false) {
+        let command = COMMANDS
+            .iter()
+            .find(|c| c.name == method)
+            .ok_or_else(|| format!("Unknown command '{}'.", method))?;
+        Json::Object(named_params(command, params)?)
+    } else {
+        Json::Array(params.into_iter().map(from_str_hack).collect::<Vec<Json>>())
+    };
     let method = Json::String(method);
-    let params = Json::Array(params.into_iter().map(from_str_hack).collect::<Vec<Json>>());
     let mut object = serde_json::Map::<String, Json>::new();
     object.insert("jsonrpc".to_string(), Json::String("2.0".to_string()));
     object.insert(
@@ -80,7 +626,7 @@ fn rpc_request(method: String, params: Vec<String>) -> Json {
     object.insert("method".to_string(), method);
     object.insert("params".to_string(), params);
 
-    Json::Object(object)
+    Ok(Json::Object(object))
 }
 
 fn socket_file(conf_file: Option<PathBuf>) -> PathBuf {
@@ -117,10 +663,199 @@ fn trimmed(mut vec: Vec<u8>, bytes_read: usize) -> Vec<u8> {
     vec
 }
 
+fn table_cell(value: &Json) -> String {
+    match value {
+        Json::Null => "-".to_string(),
+        Json::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+fn format_table(rows: Vec<Vec<String>>) -> String {
+    let widths = rows.iter().fold(Vec::<usize>::new(), |mut widths, row| {
+        for (i, cell) in row.iter().enumerate() {
+            let len = cell.chars().count();
+            match widths.get_mut(i) {
+                Some(w) => *w = (*w).max(len),
+                None => widths.push(len),
+            }
+        }
+        widths
+    });
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .enumerate()
+                .map(|(i, cell)| format!("{:width$}", cell, width = widths[i]))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Format a command result as a table. Lists of objects (coins, transactions, ...) are displayed
+/// with a row per entry and a column per field, other fields as a key value table.
+fn result_as_table(result: &Json) -> String {
+    let entries = match result {
+        Json::Object(map) => map,
+        Json::Array(_) => return result_as_table(&serde_json::json!({ "result": result })),
+        v => return table_cell(v),
+    };
+
+    let mut fields = Vec::new();
+    let mut lists = Vec::new();
+    for (key, value) in entries {
+        match value {
+            Json::Array(items) if items.iter().all(|i| i.is_object()) && !items.is_empty() => {
+                lists.push((key, items))
+            }
+            v => fields.push(vec![key.clone(), table_cell(v)]),
+        }
+    }
+
+    let mut sections = Vec::new();
+    if !fields.is_empty() {
+        sections.push(format_table(fields));
+    }
+    for (key, items) in lists {
+        let mut columns: Vec<&String> = Vec::new();
+        for item in items {
+            for column in item.as_object().expect("Checked above").keys() {
+                if !columns.contains(&column) {
+                    columns.push(column);
+                }
+            }
+        }
+        let mut rows = vec![columns.iter().map(|c| c.to_uppercase()).collect()];
+        rows.extend(items.iter().map(|item| {
+            columns
+                .iter()
+                .map(|c| table_cell(item.get(c.as_str()).unwrap_or(&Json::Null)))
+                .collect()
+        }));
+        sections.push(format!("{}:\n{}", key, format_table(rows)));
+    }
+
+    sections.join("\n\n")
+}
+
+fn completion_script(shell: &str) -> Option<String> {
+    let commands: Vec<&str> = COMMANDS.iter().map(|c| c.name).collect();
+    match shell {
+        "bash" | "zsh" => {
+            let mut script = String::new();
+            if shell == "zsh" {
+                script.push_str("autoload -U +X bashcompinit && bashcompinit\n");
+            }
+            script.push_str(
+                r#"_liana_cli() {
+    local cur="${COMP_WORDS[COMP_CWORD]}" prev="${COMP_WORDS[COMP_CWORD-1]}" cmd="" i
+    case "$prev" in
+        --conf) COMPREPLY=($(compgen -f -- "$cur")); return;;
+        --format) COMPREPLY=($(compgen -W "json table" -- "$cur")); return;;
+    esac
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case "${COMP_WORDS[i]}" in
+            --conf|--format) ((i++));;
+            --*) ;;
+            *) cmd="${COMP_WORDS[i]}"; break;;
+        esac
+    done
+    case "$cmd" in
+"#,
+            );
+            script.push_str(&format!(
+                "        \"\") COMPREPLY=($(compgen -W \"--conf --raw --format completion {}\" -- \"$cur\"));;\n",
+                commands.join(" ")
+            ));
+            script.push_str(
+                "        completion) COMPREPLY=($(compgen -W \"bash zsh fish\" -- \"$cur\"));;\n",
+            );
+            for command in COMMANDS.iter().filter(|c| !c.params.is_empty()) {
+                script.push_str(&format!(
+                    "        {})\n            case \"$prev\" in\n",
+                    command.name
+                ));
+                for param in command.params.iter().filter(|p| !p.values.is_empty()) {
+                    script.push_str(&format!(
+                        "                --{}) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return;;\n",
+                        param.option,
+                        param.values.join(" ")
+                    ));
+                }
+                let options: Vec<String> = command
+                    .params
+                    .iter()
+                    .map(|p| format!("--{}", p.option))
+                    .collect();
+                script.push_str(&format!(
+                    "            esac\n            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"));;\n",
+                    options.join(" ")
+                ));
+            }
+            script.push_str("    esac\n}\ncomplete -F _liana_cli liana-cli\n");
+            Some(script)
+        }
+        "fish" => {
+            let mut script = String::from(
+                "complete -c liana-cli -f\n\
+                 complete -c liana-cli -l conf -r -F -d 'Path to the configuration file'\n\
+                 complete -c liana-cli -l raw -d 'Print the raw JSONRPC response'\n\
+                 complete -c liana-cli -l format -x -a 'json table' -d 'Output format'\n\
+                 complete -c liana-cli -n '__fish_use_subcommand' -a 'completion' -d 'Print a shell completion script'\n\
+                 complete -c liana-cli -n '__fish_seen_subcommand_from completion' -a 'bash zsh fish'\n",
+            );
+            script.push_str(&format!(
+                "complete -c liana-cli -n '__fish_use_subcommand' -a '{}'\n",
+                commands.join(" ")
+            ));
+            for command in COMMANDS {
+                for param in command.params {
+                    script.push_str(&format!(
+                        "complete -c liana-cli -n '__fish_seen_subcommand_from {}' -l {} -x",
+                        command.name, param.option
+                    ));
+                    if !param.values.is_empty() {
+                        script.push_str(&format!(" -a '{}'", param.values.join(" ")));
+                    }
+                    script.push('\n');
+                }
+            }
+            Some(script)
+        }
+        _ => None,
+    }
+}
+
 fn main() {
     let args = env::args().collect();
-    let (conf_file, raw, method, params) = parse_args(args);
-    let request = rpc_request(method, params);
+    let Args {
+        conf_file,
+        raw,
+        format,
+        method,
+        params,
+    } = parse_args(args);
+
+    if method == "completion" {
+        match params.first().and_then(|shell| completion_script(shell)) {
+            Some(script) => print!("{}", script),
+            None => {
+                eprintln!("Expected a shell to generate the completion for: bash, zsh or fish.");
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let request = rpc_request(method, params).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        show_usage();
+        unreachable!("show_usage() exits");
+    });
     let socket_file = socket_file(conf_file);
     let mut raw_response = vec![0; 256];
 
@@ -157,9 +892,22 @@ fn main() {
                     if raw {
                         print!("{}", response);
                     } else if let Some(r) = response.get("result") {
-                        println!("{:#}", serde_json::json!({ "result": r }));
+                        match format {
+                            OutputFormat::Json => {
+                                println!("{:#}", serde_json::json!({ "result": r }))
+                            }
+                            OutputFormat::Table => println!("{}", result_as_table(r)),
+                        }
                     } else if let Some(e) = response.get("error") {
-                        println!("{:#}", serde_json::json!({ "error": e }));
+                        match format {
+                            OutputFormat::Json => {
+                                println!("{:#}", serde_json::json!({ "error": e }))
+                            }
+                            OutputFormat::Table => {
+                                eprintln!("error: {}", result_as_table(e));
+                                process::exit(1);
+                            }
+                        }
                     } else {
                         log::warn!(
                             "lianad response doesn't contain result or error: '{}'",
@@ -174,3 +922,88 @@ fn main() {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split(' ').map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn command_options() {
+        let req = rpc_request(
+            "listcoins".to_string(),
+            args("--status confirmed --status spending"),
+        )
+        .unwrap();
+        assert_eq!(
+            req["params"],
+            serde_json::json!({ "statuses": ["confirmed", "spending"] })
+        );
+
+        let req = rpc_request(
+            "createspend".to_string(),
+            args("--destination bc1qaddr=10000 --feerate 2"),
+        )
+        .unwrap();
+        assert_eq!(
+            req["params"],
+            serde_json::json!({
+                "destinations": { "bc1qaddr": 10000 },
+                "outpoints": [],
+                "feerate": 2,
+            })
+        );
+
+        let req = rpc_request(
+            "savespendtemplate".to_string(),
+            args(r#"--template {"name":"Rent","feerate":2}"#),
+        )
+        .unwrap();
+        assert_eq!(
+            req["params"],
+            serde_json::json!({ "template": { "name": "Rent", "feerate": 2 } })
+        );
+        assert!(rpc_request("savespendtemplate".to_string(), args("--template Rent")).is_err());
+
+        // Positional parameters are still supported.
+        let req = rpc_request("listconfirmed".to_string(), args("0 10 5")).unwrap();
+        assert_eq!(req["params"], serde_json::json!([0, 10, 5]));
+
+        assert!(rpc_request("createspend".to_string(), args("--feerate 2")).is_err());
+        assert!(rpc_request("listcoins".to_string(), args("--feerate 2")).is_err());
+        assert!(rpc_request("listconfirmed".to_string(), args("--start a")).is_err());
+        assert!(rpc_request("unknown".to_string(), args("--start 1")).is_err());
+    }
+
+    #[test]
+    fn table_output() {
+        let table = result_as_table(&serde_json::json!({
+            "coins": [
+                { "amount": 1000, "outpoint": "aa:0", "block_height": null },
+                { "amount": 100000, "outpoint": "bb:1", "block_height": 102 },
+            ]
+        }));
+        assert_eq!(
+            table,
+            "coins:\nAMOUNT  BLOCK_HEIGHT  OUTPOINT\n1000    -             aa:0\n100000  102           bb:1"
+        );
+        assert_eq!(
+            result_as_table(&serde_json::json!({ "txid": "aa", "height": 3 })),
+            "height  3\ntxid    aa"
+        );
+    }
+
+    #[test]
+    fn completion() {
+        assert!(completion_script("bash").unwrap().contains(
+            "--status) COMPREPLY=($(compgen -W \"unconfirmed confirmed spending spent\""
+        ));
+        assert!(completion_script("fish")
+            .unwrap()
+            .contains("__fish_seen_subcommand_from createspend' -l destination"));
+        assert!(completion_script("powershell").is_none());
+    }
+}
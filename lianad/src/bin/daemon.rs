@@ -2,7 +2,9 @@ use std::{
     env,
     io::{self, Write},
     path::PathBuf,
-    process, thread, time,
+    process,
+    sync::mpsc,
+    thread, time,
 };
 
 use lianad::{config::Config, DaemonHandle, VERSION};
@@ -12,6 +14,8 @@ fn print_help_exit(code: i32) {
     eprintln!("A TOML configuration file is required to run lianad. By default lianad looks for a 'config.toml' file in its data directory. A different one may be provided like so: '--conf <config file path>'.");
     eprintln!("A documented sample is available at 'contrib/lianad_config_example.toml' in the source tree (https://github.com/wizardsardine/liana/blob/v1.0/contrib/lianad_config_example.toml).");
     eprintln!("The default data directory path is a 'liana/' folder in the XDG standard configuration directory for all OSes but Linux ones, where it's '~/.liana/'.");
+    eprintln!("Any setting of the configuration file may be set in the environment instead, as 'LIANAD__<SETTING PATH>' (for instance 'LIANAD__BITCOIN_CONFIG__NETWORK=signet'). If no configuration file is given, the configuration may be entirely set in the environment.");
+    eprintln!("With '--init' lianad initializes its data directory and exits.");
     process::exit(code);
 }
 
@@ -20,25 +24,74 @@ fn print_version() {
     process::exit(0);
 }
 
-fn parse_args(args: Vec<String>) -> Option<PathBuf> {
-    if args.len() == 1 {
-        return None;
+struct Args {
+    conf_file: Option<PathBuf>,
+    /// Only initialize the data directory, then exit.
+    init: bool,
+}
+
+fn parse_args(args: Vec<String>) -> Args {
+    let mut parsed = Args {
+        conf_file: None,
+        init: false,
+    };
+    let mut args = args.into_iter().skip(1);
+
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            print_help_exit(0)
+        } else if arg == "--version" || arg == "-v" {
+            print_version()
+        } else if arg == "--init" {
+            parsed.init = true;
+        } else if arg == "--conf" {
+            match args.next() {
+                Some(path) => parsed.conf_file = Some(PathBuf::from(path)),
+                None => print_help_exit(1),
+            }
+        } else {
+            eprintln!("Only the --conf and --init command line arguments are supported. All other configuration parameters must be specified in the configuration file or the environment.");
+            print_help_exit(1);
+        }
     }
 
-    if args[1] == "--help" || args[1] == "-h" {
-        print_help_exit(0)
-    } else if args[1] == "--version" || args[1] == "-v" {
-        print_version()
-    } else if args[1] != "--conf" {
-        eprintln!("Only a single command line argument is supported: --conf. All other configuration parameters must be specified in the configuration file.");
-        print_help_exit(1);
+    parsed
+}
+
+// Stop on SIGTERM (for instance from a container runtime) or SIGINT, instead of being killed in
+// the middle of a database transaction.
+#[cfg(unix)]
+mod signals {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+    extern "C" fn handle_signal(_: libc::c_int) {
+        // Only async-signal-safe operations here.
+        SHUTDOWN_REQUESTED.store(true, Ordering::Relaxed);
     }
 
-    if args.len() != 3 {
-        print_help_exit(1);
+    pub fn setup() {
+        let handler = handle_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        // SAFETY: the handler only stores to an atomic.
+        unsafe {
+            libc::signal(libc::SIGTERM, handler);
+            libc::signal(libc::SIGINT, handler);
+        }
+    }
+
+    pub fn shutdown_requested() -> bool {
+        SHUTDOWN_REQUESTED.load(Ordering::Relaxed)
     }
+}
+
+#[cfg(not(unix))]
+mod signals {
+    pub fn setup() {}
 
-    Some(PathBuf::from(args[2].to_owned()))
+    pub fn shutdown_requested() -> bool {
+        false
+    }
 }
 
 fn setup_logger(log_level: log::LevelFilter) -> Result<(), fern::InitError> {
@@ -67,10 +120,9 @@ fn setup_logger(log_level: log::LevelFilter) -> Result<(), fern::InitError> {
 }
 
 fn main() {
-    let args = env::args().collect();
-    let conf_file = parse_args(args);
+    let args = parse_args(env::args().collect());
 
-    let config = Config::from_file(conf_file).unwrap_or_else(|e| {
+    let config = Config::from_file(args.conf_file).unwrap_or_else(|e| {
         eprintln!("Error parsing config: {}", e);
         print_help_exit(1);
         unreachable!();
@@ -79,19 +131,47 @@ fn main() {
         eprintln!("Error setting up logger: {}", e);
         process::exit(1);
     });
+    signals::setup();
+    let shutdown_timeout = config.shutdown_timeout_secs;
 
-    let handle = DaemonHandle::start_default(config, cfg!(unix)).unwrap_or_else(|e| {
-        log::error!("Error starting Liana daemon: {}", e);
-        process::exit(1);
-    });
-    while handle.is_alive() {
+    let handle =
+        DaemonHandle::start_default(config, cfg!(unix) && !args.init).unwrap_or_else(|e| {
+            log::error!("Error starting Liana daemon: {}", e);
+            process::exit(1);
+        });
+    if args.init {
+        log::info!("Data directory initialized.");
+    }
+    while !args.init && handle.is_alive() && !signals::shutdown_requested() {
         thread::sleep(time::Duration::from_millis(500));
     }
-    if let Err(e) = handle.stop() {
-        log::error!("Error stopping Liana daemon: {}", e);
+    if signals::shutdown_requested() {
+        log::info!("Shutdown requested, stopping the daemon.");
     }
 
+    // Don't hang forever if, for instance, the poller is in the middle of a long rescan.
+    let (stop_sender, stop_receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let res = handle.stop().map_err(|e| e.to_string());
+        let _ = stop_sender.send(res);
+    });
+    let code = match stop_receiver.recv_timeout(shutdown_timeout) {
+        Ok(Ok(())) => 0,
+        Ok(Err(e)) => {
+            log::error!("Error stopping Liana daemon: {}", e);
+            1
+        }
+        Err(_) => {
+            log::error!(
+                "Liana daemon did not stop within {} seconds, exiting anyway.",
+                shutdown_timeout.as_secs()
+            );
+            1
+        }
+    };
+
     // We are always logging to stdout, should it be then piped to the log file (if self) or
     // not. So just make sure that all messages were actually written.
     io::stdout().flush().expect("Flushing stdout");
+    process::exit(code);
 }
@@ -4,7 +4,9 @@
 
 mod utils;
 use crate::{
-    bitcoin::{Block, BlockChainTip},
+    bitcoin::{
+        fee_histogram, Block, BlockChainTip, BlockTransactions, FeeHistogramBucket, MinFeerates,
+    },
     config,
 };
 use liana::descriptors::LianaDescriptor;
@@ -17,7 +19,7 @@ use std::{
     fs, io,
     str::FromStr,
     thread,
-    time::Duration,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use jsonrpc::{
@@ -238,6 +240,20 @@ impl BitcoinD {
     pub fn new(
         config: &config::BitcoindConfig,
         watchonly_wallet_path: String,
+    ) -> Result<BitcoinD, BitcoindError> {
+        Self::connect(config, watchonly_wallet_path, true)
+    }
+
+    /// Create a bitcoind interface only used to query the node, not any wallet. This tests the
+    /// connection to bitcoind.
+    pub fn new_node_only(config: &config::BitcoindConfig) -> Result<BitcoinD, BitcoindError> {
+        Self::connect(config, String::new(), false)
+    }
+
+    fn connect(
+        config: &config::BitcoindConfig,
+        watchonly_wallet_path: String,
+        check_wallet: bool,
     ) -> Result<BitcoinD, BitcoindError> {
         let node_url = format!("http://{}", config.addr);
         let watchonly_url = format!("http://{}/wallet/{}", config.addr, watchonly_wallet_path);
@@ -286,7 +302,11 @@ impl BitcoinD {
             retries: 0,
         };
         log::info!("Checking the connection to bitcoind.");
-        dummy_bitcoind.check_connection()?;
+        if check_wallet {
+            dummy_bitcoind.check_connection()?;
+        } else {
+            dummy_bitcoind.check_client(&dummy_bitcoind.node_client)?;
+        }
         log::info!("Connection to bitcoind checked.");
 
         // Now the connection is checked, create the clients with an appropriate timeout.
@@ -698,6 +718,18 @@ impl BitcoinD {
         }.map(|_| ())
     }
 
+    /// Unload the watchonly wallet from bitcoind, and don't load it on its next startup.
+    pub fn unload_watchonly_wallet(&self) -> Result<(), BitcoindError> {
+        self.make_fallible_node_request(
+            "unloadwallet",
+            params!(
+                Json::String(self.watchonly_wallet_path.clone()),
+                Json::Bool(false), // load_on_startup
+            ),
+        )
+        .map(|_| ())
+    }
+
     /// Perform various non-wallet-related sanity checks on the bitcoind instance.
     pub fn node_sanity_checks(
         &self,
@@ -812,23 +844,8 @@ impl BitcoinD {
     }
 
     pub fn chain_tip(&self) -> BlockChainTip {
-        // We use getblockchaininfo to avoid a race between getblockcount and getblockhash
-        let chain_info = self.block_chain_info();
-        let hash = bitcoin::BlockHash::from_str(
-            chain_info
-                .get("bestblockhash")
-                .and_then(Json::as_str)
-                .expect("No valid 'bestblockhash' in 'getblockchaininfo' response?"),
-        )
-        .expect("Invalid blockhash from bitcoind?");
-        let height: i32 = chain_info
-            .get("blocks")
-            .and_then(Json::as_i64)
-            .expect("No valid 'blocks' in 'getblockchaininfo' response?")
-            .try_into()
-            .expect("Must fit by Bitcoin consensus");
-
-        BlockChainTip { hash, height }
+        self.try_chain_tip()
+            .expect("We must not fail to make a request for more than a minute")
     }
 
     pub fn get_block_hash(&self, height: i32) -> Option<bitcoin::BlockHash> {
@@ -865,6 +882,44 @@ impl BitcoinD {
         .map(|res| res.into())
     }
 
+    /// Like [`Self::chain_tip`] but returns an error instead of panicking if bitcoind can't be
+    /// reached.
+    pub fn try_chain_tip(&self) -> Result<BlockChainTip, BitcoindError> {
+        // We use getblockchaininfo to avoid a race between getblockcount and getblockhash
+        let chain_info = self.make_fallible_node_request("getblockchaininfo", None)?;
+        let hash = bitcoin::BlockHash::from_str(
+            chain_info
+                .get("bestblockhash")
+                .and_then(Json::as_str)
+                .expect("No valid 'bestblockhash' in 'getblockchaininfo' response?"),
+        )
+        .expect("Invalid blockhash from bitcoind?");
+        let height: i32 = chain_info
+            .get("blocks")
+            .and_then(Json::as_i64)
+            .expect("No valid 'blocks' in 'getblockchaininfo' response?")
+            .try_into()
+            .expect("Must fit by Bitcoin consensus");
+        Ok(BlockChainTip { hash, height })
+    }
+
+    /// The number of confirmations of this output if it's unspent and confirmed in the best chain.
+    pub fn utxo_confirmations(&self, op: &bitcoin::OutPoint) -> Result<Option<u32>, BitcoindError> {
+        let res = self.make_fallible_node_request(
+            "gettxout",
+            params!(
+                Json::String(op.txid.to_string()),
+                Json::Number(op.vout.into()),
+                Json::Bool(false) // Whether to include the mempool
+            ),
+        )?;
+        Ok(res
+            .get("confirmations")
+            .and_then(Json::as_u64)
+            .and_then(|c| c.try_into().ok())
+            .filter(|c| *c > 0))
+    }
+
     /// Efficient check that a coin is spent.
     pub fn is_spent(&self, op: &bitcoin::OutPoint) -> bool {
         // The result of gettxout is empty if the outpoint is spent.
@@ -1046,6 +1101,71 @@ impl BitcoinD {
         })
     }
 
+    /// Get the transactions of the block of the best chain at this height along with the previous
+    /// outputs they spend. Those are only available if bitcoind still has the undo data of the
+    /// block.
+    pub fn block_transactions(&self, height: i32) -> Option<BlockTransactions> {
+        let hash = self.get_block_hash(height)?;
+        let res = match self.make_fallible_node_request(
+            "getblock",
+            params!(Json::String(hash.to_string()), Json::Number(3.into())),
+        ) {
+            Ok(res) => res,
+            Err(e) => {
+                log::warn!("Error when fetching block {}: {}", &hash, e);
+                return None;
+            }
+        };
+        let time =
+            res.get("time")
+                .and_then(Json::as_u64)
+                .expect("Invalid timestamp in `getblock` response: not an u64") as u32;
+        let txs = res
+            .get("tx")
+            .and_then(Json::as_array)
+            .expect("Invalid transactions in `getblock` response: not an array")
+            .iter()
+            .map(|tx_res| {
+                let hex = tx_res
+                    .get("hex")
+                    .and_then(Json::as_str)
+                    .expect("Invalid transaction in `getblock` response: no hex");
+                let bytes =
+                    Vec::from_hex(hex).expect("bitcoind returned a wrong transaction format");
+                let tx: bitcoin::Transaction = bitcoin::consensus::encode::deserialize(&bytes)
+                    .expect("bitcoind returned a wrong transaction format");
+                // Missing for the coinbase input, or if the undo data was pruned.
+                let prevouts = tx_res
+                    .get("vin")
+                    .and_then(Json::as_array)?
+                    .iter()
+                    .map(|vin| {
+                        let prevout = vin.get("prevout")?;
+                        let value = prevout
+                            .get("value")
+                            .and_then(Json::as_f64)
+                            .and_then(|v| bitcoin::Amount::from_btc(v).ok())?;
+                        let spk = prevout
+                            .get("scriptPubKey")
+                            .and_then(|spk| spk.get("hex"))
+                            .and_then(Json::as_str)
+                            .and_then(|hex| bitcoin::ScriptBuf::from_hex(hex).ok())?;
+                        Some(bitcoin::TxOut {
+                            value,
+                            script_pubkey: spk,
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .unwrap_or_default();
+                Some((tx, prevouts))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(BlockTransactions {
+            block: Block { hash, height, time },
+            txs,
+        })
+    }
+
     pub fn broadcast_tx(&self, tx: &bitcoin::Transaction) -> Result<(), BitcoindError> {
         self.make_fallible_node_request(
             "sendrawtransaction",
@@ -1054,6 +1174,21 @@ impl BitcoinD {
         Ok(())
     }
 
+    /// Import a confirmed transaction with an unspent output in the watchonly wallet, along with
+    /// the proof of its inclusion in a block. This allows the wallet to find the transactions
+    /// spending its outputs without rescanning the block it was confirmed in.
+    pub fn import_pruned_funds(&self, tx: &bitcoin::Transaction) -> Result<(), BitcoindError> {
+        let proof = self.make_fallible_node_request(
+            "gettxoutproof",
+            params!(Json::Array(vec![tx.compute_txid().to_string().into()])),
+        )?;
+        self.make_faillible_wallet_request(
+            "importprunedfunds",
+            params!(bitcoin::consensus::encode::serialize_hex(tx).into(), proof),
+        )?;
+        Ok(())
+    }
+
     // For the given descriptor strings check if they are imported at this timestamp in the
     // watchonly wallet.
     fn check_descs_timestamp(
@@ -1175,6 +1310,16 @@ impl BitcoinD {
             .and_then(Json::as_f64)
     }
 
+    /// Get the date at which bitcoind was started, as a UNIX timestamp.
+    pub fn start_time(&self) -> Option<u32> {
+        let uptime = self
+            .make_fallible_node_request("uptime", None)
+            .ok()?
+            .as_u64()?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+        now.checked_sub(uptime)?.try_into().ok()
+    }
+
     /// Get the height and hash of the last block with a timestamp below the given one.
     pub fn tip_before_timestamp(&self, timestamp: u32) -> Option<BlockChainTip> {
         block_before_date(
@@ -1207,6 +1352,33 @@ impl BitcoinD {
         }
     }
 
+    /// Get the minimum feerates for a transaction to be accepted in bitcoind's mempool.
+    pub fn min_feerates(&self) -> Option<MinFeerates> {
+        let info = self
+            .make_fallible_node_request("getmempoolinfo", None)
+            .ok()?;
+        let relay = info.get("minrelaytxfee").and_then(Json::as_f64)?;
+        let mempool = info.get("mempoolminfee").and_then(Json::as_f64)?;
+        Some(MinFeerates::from_btc_kvb(relay, mempool))
+    }
+
+    /// Get the histogram of the feerates paid by the transactions in bitcoind's mempool.
+    pub fn fee_histogram(&self) -> Option<Vec<FeeHistogramBucket>> {
+        let mempool = self
+            .make_fallible_node_request("getrawmempool", params!(Json::Bool(true)))
+            .ok()?;
+        let txs = mempool.as_object()?.values().filter_map(|entry| {
+            let vsize = entry.get("vsize").and_then(Json::as_u64)?;
+            let fee = entry
+                .get("fees")
+                .and_then(|fees| fees.get("base"))
+                .and_then(Json::as_f64)
+                .and_then(|a| bitcoin::Amount::from_btc(a).ok())?;
+            (vsize > 0).then(|| (fee.to_sat() as f64 / vsize as f64, vsize))
+        });
+        Some(fee_histogram(txs))
+    }
+
     /// Get the list of txids spending those outpoints in mempool.
     pub fn mempool_txs_spending_prevouts(
         &self,
@@ -1518,6 +1690,11 @@ impl<'a> CachedTxGetter<'a> {
 pub struct MempoolEntry {
     pub vsize: u64,
     pub ancestor_vsize: u64,
+    /// Number of unconfirmed ancestors, including this transaction.
+    pub ancestor_count: u64,
+    pub descendant_vsize: u64,
+    /// Number of unconfirmed descendants, including this transaction.
+    pub descendant_count: u64,
     pub fees: MempoolEntryFees,
 }
 
@@ -1531,6 +1708,18 @@ impl From<Json> for MempoolEntry {
             .get("ancestorsize")
             .and_then(Json::as_u64)
             .expect("Must be present in bitcoind response");
+        let ancestor_count = json
+            .get("ancestorcount")
+            .and_then(Json::as_u64)
+            .expect("Must be present in bitcoind response");
+        let descendant_vsize = json
+            .get("descendantsize")
+            .and_then(Json::as_u64)
+            .expect("Must be present in bitcoind response");
+        let descendant_count = json
+            .get("descendantcount")
+            .and_then(Json::as_u64)
+            .expect("Must be present in bitcoind response");
         let fees = json
             .get("fees")
             .as_ref()
@@ -1540,6 +1729,9 @@ impl From<Json> for MempoolEntry {
         MempoolEntry {
             vsize,
             ancestor_vsize,
+            ancestor_count,
+            descendant_vsize,
+            descendant_count,
             fees,
         }
     }
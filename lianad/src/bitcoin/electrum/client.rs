@@ -7,7 +7,7 @@ use bdk_electrum::{
         spk_client::{FullScanRequest, FullScanResult, SyncRequest, SyncResult},
         BlockId, ChainPosition, ConfirmationHeightAnchor, TxGraph,
     },
-    electrum_client::{self, Config, ElectrumApi},
+    electrum_client::{self, Config, ElectrumApi, GetMerkleRes},
     BdkElectrumClient,
 };
 
@@ -15,7 +15,10 @@ use super::utils::{
     block_id_from_tip, height_i32_from_usize, height_usize_from_i32, outpoints_from_tx,
 };
 use crate::{
-    bitcoin::{electrum::utils::tip_from_block_id, BlockChainTip, MempoolEntry, MempoolEntryFees},
+    bitcoin::{
+        electrum::utils::tip_from_block_id, fee_histogram, BlockChainTip, FeeHistogramBucket,
+        MempoolEntry, MempoolEntryFees, MinFeerates,
+    },
     config,
 };
 
@@ -113,6 +116,85 @@ impl Client {
             .map(|bh| bh.time)
     }
 
+    /// Get the minimum feerates for a transaction to be accepted by the server.
+    ///
+    /// The Electrum protocol only exposes the minimum relay feerate, we use it as the mempool
+    /// minimum too.
+    pub fn min_feerates(&self) -> Result<MinFeerates, Error> {
+        let relay = self.0.inner.relay_fee().map_err(Error::Server)?;
+        Ok(MinFeerates::from_btc_kvb(relay, relay))
+    }
+
+    /// Get the histogram of the feerates paid by the transactions in the server's mempool.
+    ///
+    /// The server returns its own histogram as a list of (feerate in sats/vb, vsize) pairs, which
+    /// we sort into our buckets.
+    pub fn fee_histogram(&self) -> Result<Vec<FeeHistogramBucket>, Error> {
+        let histogram = self
+            .0
+            .inner
+            .raw_call(
+                "mempool.get_fee_histogram",
+                Vec::<electrum_client::Param>::new(),
+            )
+            .map_err(Error::Server)?;
+        let txs = histogram
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let entry = entry.as_array()?;
+                Some((entry.first()?.as_f64()?, entry.get(1)?.as_u64()?))
+            });
+        Ok(fee_histogram(txs))
+    }
+
+    /// Get the merkle proof of the inclusion of this transaction in the block at this height,
+    /// along with the header of this block.
+    pub fn merkle_proof(
+        &self,
+        txid: &bitcoin::Txid,
+        height: i32,
+    ) -> Result<(GetMerkleRes, bitcoin::block::Header), Error> {
+        let height = height_usize_from_i32(height);
+        let proof = self
+            .0
+            .inner
+            .transaction_get_merkle(txid, height)
+            .map_err(Error::Server)?;
+        let header = self.0.inner.block_header(height).map_err(Error::Server)?;
+        Ok((proof, header))
+    }
+
+    /// Get the hash of the block at this height in the server's best chain.
+    pub fn block_hash(&self, height: i32) -> Result<bitcoin::BlockHash, Error> {
+        self.0
+            .inner
+            .block_header(height_usize_from_i32(height))
+            .map_err(Error::Server)
+            .map(|header| header.block_hash())
+    }
+
+    /// Get the height at which this output, paying to this script, was confirmed if it's unspent
+    /// and confirmed in the server's best chain.
+    pub fn utxo_height(
+        &self,
+        outpoint: &bitcoin::OutPoint,
+        script_pubkey: &bitcoin::Script,
+    ) -> Result<Option<i32>, Error> {
+        let unspents = self
+            .0
+            .inner
+            .script_list_unspent(script_pubkey)
+            .map_err(Error::Server)?;
+        Ok(unspents
+            .into_iter()
+            .find(|utxo| utxo.tx_hash == outpoint.txid && utxo.tx_pos == outpoint.vout as usize)
+            // Unconfirmed outputs have a height of 0.
+            .filter(|utxo| utxo.height > 0)
+            .map(|utxo| height_i32_from_usize(utxo.height)))
+    }
+
     /// Returns a reference to the wrapped `BdkElectrumClient`.
     pub fn bdk_electrum_client(&self) -> &BdkElectrumClient<electrum_client::Client> {
         &self.0
@@ -321,8 +403,11 @@ impl Client {
             // Ancestor & descendant fees include those of `txid`.
             let mut desc_fees = base_fee;
             let mut anc_fees = base_fee;
-            // Ancestor size includes that of `txid`.
+            // Ancestor & descendant sizes and counts include those of `txid`.
             let mut anc_size = base_size;
+            let mut anc_count = 1;
+            let mut desc_size = base_size;
+            let mut desc_count = 1;
             for desc_txid in
                 graph.walk_descendants(tx.compute_txid(), |_, desc_txid| Some(desc_txid))
             {
@@ -334,6 +419,8 @@ impl Client {
                     .calculate_fee(&desc_tx)
                     .expect("all required txs are in graph");
                 desc_fees += fee;
+                desc_size += desc_tx.vsize();
+                desc_count += 1;
             }
             for anc_tx in graph.walk_ancestors(tx, |_, anc_tx| Some(anc_tx)) {
                 log::debug!(
@@ -350,6 +437,7 @@ impl Client {
                         .expect("all required txs are in graph");
                     anc_fees += fee;
                     anc_size += anc_tx.vsize();
+                    anc_count += 1;
                 } else {
                     log::debug!(
                         "Ancestor txid '{}' is not unconfirmed.",
@@ -367,6 +455,9 @@ impl Client {
                 vsize: base_size.try_into().expect("tx size must fit into u64"),
                 fees,
                 ancestor_vsize: anc_size.try_into().expect("tx size must fit into u64"),
+                ancestor_count: anc_count,
+                descendant_vsize: desc_size.try_into().expect("tx size must fit into u64"),
+                descendant_count: desc_count,
             };
             entries.push(entry)
         }
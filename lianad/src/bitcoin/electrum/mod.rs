@@ -1,16 +1,19 @@
 use std::collections::HashMap;
 
-use bdk_electrum::bdk_chain::{
-    bitcoin::{self, bip32::ChildNumber, BlockHash, OutPoint},
-    local_chain::LocalChain,
-    spk_client::{FullScanRequest, SyncRequest},
-    ChainPosition,
+use bdk_electrum::{
+    bdk_chain::{
+        bitcoin::{self, bip32::ChildNumber, BlockHash, OutPoint},
+        local_chain::LocalChain,
+        spk_client::{FullScanRequest, SyncRequest},
+        ChainPosition,
+    },
+    electrum_client::utils::validate_merkle_proof,
 };
 
 pub mod client;
 mod utils;
 pub mod wallet;
-use crate::bitcoin::{Block, BlockChainTip, Coin};
+use crate::bitcoin::{Block, BlockChainTip, Coin, InclusionCheck};
 
 /// An error in the Electrum interface.
 #[derive(Debug)]
@@ -113,6 +116,12 @@ impl Electrum {
         self.full_scan || self.local_chain().tip().height() == 0
     }
 
+    /// Load the next chunk of the wallet data from the database. Returns whether it's now fully
+    /// loaded.
+    pub fn load_wallet_step(&mut self) -> bool {
+        self.bdk_wallet.load_step()
+    }
+
     /// Make the poller perform a full scan on the next iteration.
     pub fn trigger_rescan(&mut self) {
         self.full_scan = true;
@@ -125,6 +134,11 @@ impl Electrum {
         receive_index: ChildNumber,
         change_index: ChildNumber,
     ) -> Result<Option<BlockChainTip>, ElectrumError> {
+        // The wallet must have been fully loaded before it's synced, or the coins from the
+        // database missing in the wallet would be considered as gone.
+        while !self.bdk_wallet.is_loaded() {
+            self.bdk_wallet.load_step();
+        }
         self.bdk_wallet.reveal_spks(receive_index, change_index);
         let local_chain_tip = self.local_chain().tip();
         log::debug!(
@@ -254,4 +268,52 @@ impl Electrum {
     ) -> Option<(bitcoin::Transaction, Option<Block>)> {
         self.bdk_wallet.get_transaction(txid)
     }
+
+    /// Check the server's claim that this transaction was confirmed at this height: the header
+    /// of the block must be the one of our local chain and the merkle proof of the inclusion of
+    /// the transaction must be valid against it.
+    pub fn check_inclusion(&self, txid: &bitcoin::Txid, height: i32) -> InclusionCheck {
+        let (proof, header) = match self.client.merkle_proof(txid, height) {
+            Ok(res) => res,
+            Err(e) => return InclusionCheck::Unavailable(e.to_string()),
+        };
+        if utils::height_i32_from_usize(proof.block_height) != height {
+            return InclusionCheck::Invalid(format!(
+                "Merkle proof is for block at height {} instead of {}.",
+                proof.block_height, height
+            ));
+        }
+        let block = BlockChainTip {
+            hash: header.block_hash(),
+            height,
+        };
+        match self.is_in_wallet_chain(block) {
+            Some(true) => {}
+            Some(false) => {
+                return InclusionCheck::Invalid(format!(
+                    "Block '{}' at height {} isn't part of our chain.",
+                    block.hash, height
+                ))
+            }
+            None => {
+                return InclusionCheck::Unavailable(format!(
+                    "No block at height {} in our local chain.",
+                    height
+                ))
+            }
+        }
+        if let Err(e) = header.validate_pow(header.target()) {
+            return InclusionCheck::Invalid(format!(
+                "Invalid proof of work for block '{}': {}.",
+                block.hash, e
+            ));
+        }
+        if !validate_merkle_proof(txid, &header.merkle_root, &proof) {
+            return InclusionCheck::Invalid(format!(
+                "Merkle proof doesn't match the root of block '{}'.",
+                block.hash
+            ));
+        }
+        InclusionCheck::Verified
+    }
 }
@@ -1,4 +1,5 @@
 use std::{
+    cmp,
     collections::{BTreeMap, HashMap},
     convert::TryInto,
     sync::Arc,
@@ -29,22 +30,41 @@ pub enum KeychainType {
     Change,
 }
 
+/// How many SPKs to derive for each keychain at each step of the loading of the wallet.
+const LOAD_CHUNK_SIZE: u32 = 1_000;
+
+// The data from the database which is yet to be loaded into the wallet.
+struct PendingLoad {
+    // The tip as of which the data was valid.
+    tip: BlockChainTip,
+    coins: Vec<Coin>,
+    txs: Vec<bitcoin::Transaction>,
+    // The last used derivation indexes and up to which the SPKs were revealed so far.
+    receive_index: u32,
+    change_index: u32,
+    revealed: (u32, u32),
+}
+
 pub struct BdkWallet {
     graph: IndexedTxGraph<ConfirmationTimeHeightAnchor, KeychainTxOutIndex<KeychainType>>,
     local_chain: LocalChain,
     // Store descriptors for use when getting SPKs.
     receive_desc: Descriptor<DescriptorPublicKey>,
     change_desc: Descriptor<DescriptorPublicKey>,
+    pending: Option<PendingLoad>,
 }
 
 impl BdkWallet {
-    /// Create a new BDK wallet and initialize with the given data that was
+    /// Create a new BDK wallet to be initialized with the given data that was
     /// valid as of `tip`.
     ///
     /// If there is no `tip`, then any provided data will be ignored.
     ///
     /// `receive_index` and `change_index` are the last used derivation
     /// indices for the receive and change descriptors, respectively.
+    ///
+    /// Deriving the SPKs up to these indices may take a while, so the data is not loaded
+    /// immediately. It is loaded by chunks through [`BdkWallet::load_step`].
     pub fn new(
         main_descriptor: &LianaDescriptor,
         genesis_hash: BlockHash,
@@ -54,7 +74,7 @@ impl BdkWallet {
         receive_index: ChildNumber,
         change_index: ChildNumber,
     ) -> Self {
-        let local_chain = LocalChain::from_genesis_hash(genesis_hash).0;
+        let mut local_chain = LocalChain::from_genesis_hash(genesis_hash).0;
         let receive_desc = main_descriptor
             .receive_descriptor()
             .as_descriptor_public_key();
@@ -62,7 +82,25 @@ impl BdkWallet {
             .change_descriptor()
             .as_descriptor_public_key();
 
-        let mut bdk_wallet = BdkWallet {
+        let pending = tip.map(|tip| {
+            // This will be our anchor for any confirmed transactions.
+            if tip.height > 0 {
+                let anchor_block = block_id_from_tip(tip);
+                log::debug!("inserting block into local chain: {:?}", anchor_block);
+                let _ = local_chain
+                    .insert_block(anchor_block)
+                    .expect("local chain only contains genesis block");
+            }
+            PendingLoad {
+                tip,
+                coins: coins.to_vec(),
+                txs: txs.to_vec(),
+                receive_index: receive_index.into(),
+                change_index: change_index.into(),
+                revealed: (0, 0),
+            }
+        });
+        BdkWallet {
             graph: {
                 let mut indexer = KeychainTxOutIndex::<KeychainType>::new(LOOK_AHEAD_LIMIT);
                 let _ = indexer.insert_descriptor(KeychainType::Receive, receive_desc.clone());
@@ -70,71 +108,98 @@ impl BdkWallet {
                 IndexedTxGraph::new(indexer)
             },
             local_chain,
-            receive_desc: receive_desc.clone(),
-            change_desc: change_desc.clone(),
+            receive_desc,
+            change_desc,
+            pending,
+        }
+    }
+
+    /// Whether the data the wallet was created with was fully loaded.
+    pub fn is_loaded(&self) -> bool {
+        self.pending.is_none()
+    }
+
+    /// Make progress on loading the data the wallet was created with. The SPKs are first revealed
+    /// up to the last used derivation indices by chunks, then the coins
+    /// and transactions are inserted along with the last chunk.
+    ///
+    /// Returns whether the wallet is now fully loaded.
+    pub fn load_step(&mut self) -> bool {
+        let pending = match self.pending.as_mut() {
+            Some(pending) => pending,
+            None => return true,
         };
-        if let Some(tip) = tip {
-            // This will be our anchor for any confirmed transactions.
-            let anchor_block = block_id_from_tip(tip);
-            if tip.height > 0 {
-                log::debug!("inserting block into local chain: {:?}", anchor_block);
-                let _ = bdk_wallet
-                    .local_chain
-                    .insert_block(anchor_block)
-                    .expect("local chain only contains genesis block");
-            }
-            // Update the last used derivation index for both change and receive addresses.
-            log::debug!(
-                "revealing SPKs up to receive index {receive_index} and change index {change_index}"
-            );
-            bdk_wallet.reveal_spks(receive_index, change_index);
+        pending.revealed = (
+            cmp::min(
+                pending.revealed.0.saturating_add(LOAD_CHUNK_SIZE),
+                pending.receive_index,
+            ),
+            cmp::min(
+                pending.revealed.1.saturating_add(LOAD_CHUNK_SIZE),
+                pending.change_index,
+            ),
+        );
+        let (receive_index, change_index) = pending.revealed;
+        let done = pending.revealed == (pending.receive_index, pending.change_index);
+        log::debug!(
+            "revealing SPKs up to receive index {receive_index} and change index {change_index}"
+        );
+        self.reveal_spks(receive_index.into(), change_index.into());
 
-            // Update the existing coins and transactions information using a TxGraph changeset.
-            log::debug!("Number of coins to load: {}.", coins.len());
-            log::debug!("Number of txs to load: {}.", txs.len());
-            let mut graph_cs = tx_graph::ChangeSet::default();
-            for tx in txs {
-                graph_cs.txs.insert(Arc::new(tx.clone()));
+        if done {
+            let pending = self.pending.take().expect("Checked above.");
+            self.load_graph(pending.tip, &pending.coins, &pending.txs);
+        }
+        done
+    }
+
+    // Update the existing coins and transactions information using a TxGraph changeset. The SPKs
+    // of the coins must have been revealed for them to be indexed.
+    fn load_graph(&mut self, tip: BlockChainTip, coins: &[Coin], txs: &[bitcoin::Transaction]) {
+        let anchor_block = block_id_from_tip(tip);
+        log::debug!("Number of coins to load: {}.", coins.len());
+        log::debug!("Number of txs to load: {}.", txs.len());
+        let mut graph_cs = tx_graph::ChangeSet::default();
+        for tx in txs {
+            graph_cs.txs.insert(Arc::new(tx.clone()));
+        }
+        for coin in coins {
+            // First of all insert the txout itself.
+            let script_pubkey = self.get_spk(coin.derivation_index, coin.is_change);
+            let txout = TxOut {
+                script_pubkey,
+                value: coin.amount,
+            };
+            graph_cs.txouts.insert(coin.outpoint, txout);
+            // If the coin's deposit transaction is confirmed, tell BDK by inserting an anchor.
+            // Otherwise, we could insert a last seen timestamp but we don't have such data stored in
+            // the table.
+            if let Some(block) = coin.block_info {
+                graph_cs.anchors.insert((
+                    ConfirmationTimeHeightAnchor {
+                        confirmation_height: height_u32_from_i32(block.height),
+                        confirmation_time: block.time.into(),
+                        anchor_block,
+                    },
+                    coin.outpoint.txid,
+                ));
             }
-            for coin in coins {
-                // First of all insert the txout itself.
-                let script_pubkey = bdk_wallet.get_spk(coin.derivation_index, coin.is_change);
-                let txout = TxOut {
-                    script_pubkey,
-                    value: coin.amount,
-                };
-                graph_cs.txouts.insert(coin.outpoint, txout);
-                // If the coin's deposit transaction is confirmed, tell BDK by inserting an anchor.
-                // Otherwise, we could insert a last seen timestamp but we don't have such data stored in
-                // the table.
-                if let Some(block) = coin.block_info {
-                    graph_cs.anchors.insert((
-                        ConfirmationTimeHeightAnchor {
-                            confirmation_height: height_u32_from_i32(block.height),
-                            confirmation_time: block.time.into(),
-                            anchor_block,
-                        },
-                        coin.outpoint.txid,
-                    ));
-                }
-                // If the coin's spending transaction is confirmed, do the same.
-                if let Some(block) = coin.spend_block {
-                    let spend_txid = coin.spend_txid.expect("Must be present if confirmed.");
-                    graph_cs.anchors.insert((
-                        ConfirmationTimeHeightAnchor {
-                            confirmation_height: height_u32_from_i32(block.height),
-                            confirmation_time: block.time.into(),
-                            anchor_block,
-                        },
-                        spend_txid,
-                    ));
-                }
+            // If the coin's spending transaction is confirmed, do the same.
+            if let Some(block) = coin.spend_block {
+                let spend_txid = coin.spend_txid.expect("Must be present if confirmed.");
+                graph_cs.anchors.insert((
+                    ConfirmationTimeHeightAnchor {
+                        confirmation_height: height_u32_from_i32(block.height),
+                        confirmation_time: block.time.into(),
+                        anchor_block,
+                    },
+                    spend_txid,
+                ));
             }
-            let mut graph = TxGraph::default();
-            graph.apply_changeset(graph_cs);
-            let _ = bdk_wallet.graph.apply_update(graph);
         }
-        bdk_wallet
+        let mut graph = TxGraph::default();
+        graph.apply_changeset(graph_cs);
+        let _ = self.graph.apply_update(graph);
     }
 
     /// Get a reference to the local chain.
@@ -239,7 +304,7 @@ impl BdkWallet {
                         let tip_height: i32 = height_i32_from_u32(tip_id.height);
                         tip_height
                             .checked_sub(blk.height)
-                            .map(|confs| confs < COINBASE_MATURITY)
+                            .map(|depth| depth + 1 < COINBASE_MATURITY)
                     })
                     .unwrap_or(true);
 
@@ -13,11 +13,13 @@ use liana::descriptors;
 use std::{fmt, sync};
 
 use miniscript::bitcoin::{self, address, bip32::ChildNumber};
+use serde::{Deserialize, Serialize};
 
 // A spent coin's outpoint together with its spend transaction's txid, height and time.
 type SpentCoin = (bitcoin::OutPoint, bitcoin::Txid, i32, u32);
 
-const COINBASE_MATURITY: i32 = 100;
+/// The number of confirmations a coinbase transaction needs before its outputs can be spent.
+pub const COINBASE_MATURITY: i32 = 100;
 
 /// Information about a block
 #[derive(Debug, Clone, Eq, PartialEq, Copy)]
@@ -27,6 +29,14 @@ pub struct Block {
     pub time: u32,
 }
 
+/// The transactions of a block, each along with the previous outputs spent by its inputs (none for
+/// the coinbase transaction).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockTransactions {
+    pub block: Block,
+    pub txs: Vec<(bitcoin::Transaction, Vec<bitcoin::TxOut>)>,
+}
+
 /// Information about the best block in the chain
 #[derive(Debug, Clone, Eq, PartialEq, Copy)]
 pub struct BlockChainTip {
@@ -34,6 +44,84 @@ pub struct BlockChainTip {
     pub height: i32,
 }
 
+/// The minimum feerates, in sats/vb, a transaction must pay to be accepted in the backend's
+/// mempool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinFeerates {
+    /// The minimum feerate for a transaction to be relayed.
+    pub relay: u64,
+    /// The minimum feerate for a transaction to enter the mempool at the moment. Never lower than
+    /// the relay feerate, higher when the mempool is full.
+    pub mempool: u64,
+}
+
+impl MinFeerates {
+    /// Get the minimum feerates from their value in BTC/kvB, as returned by the backends. They
+    /// are rounded up to the next sat/vb.
+    pub fn from_btc_kvb(relay: f64, mempool: f64) -> Self {
+        let to_sat_vb = |feerate: f64| -> u64 {
+            let sat_kvb = bitcoin::Amount::from_btc(feerate)
+                .map(|a| a.to_sat())
+                .unwrap_or(0);
+            sat_kvb.div_ceil(1_000)
+        };
+        let relay = to_sat_vb(relay);
+        MinFeerates {
+            relay,
+            mempool: std::cmp::max(relay, to_sat_vb(mempool)),
+        }
+    }
+}
+
+/// The lower bounds, in sats/vb, of the buckets of the mempool fee histogram.
+pub const FEE_HISTOGRAM_FEERATES: [u64; 24] = [
+    1000, 500, 300, 200, 150, 125, 100, 80, 60, 50, 40, 30, 25, 20, 15, 12, 10, 8, 6, 5, 4, 3, 2, 1,
+];
+
+/// The size of the transactions in the mempool paying at least this feerate, and less than the
+/// feerate of the previous bucket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeHistogramBucket {
+    /// The lower bound of the bucket, in sats/vb.
+    pub feerate: u64,
+    /// The total virtual size of the transactions in this bucket.
+    pub vsize: u64,
+}
+
+/// Sort the (feerate in sats/vb, virtual size) of the mempool transactions into the buckets of
+/// [`FEE_HISTOGRAM_FEERATES`], from the highest feerate to the lowest. The transactions paying
+/// less than the lowest bound are counted in the last bucket.
+pub fn fee_histogram(txs: impl IntoIterator<Item = (f64, u64)>) -> Vec<FeeHistogramBucket> {
+    let mut histogram: Vec<_> = FEE_HISTOGRAM_FEERATES
+        .iter()
+        .map(|feerate| FeeHistogramBucket {
+            feerate: *feerate,
+            vsize: 0,
+        })
+        .collect();
+    for (feerate, vsize) in txs {
+        let index = FEE_HISTOGRAM_FEERATES
+            .iter()
+            .position(|bound| feerate >= *bound as f64)
+            .unwrap_or(FEE_HISTOGRAM_FEERATES.len() - 1);
+        histogram[index].vsize += vsize;
+    }
+    histogram
+}
+
+/// The result of checking a wallet transaction was included in a block of our chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InclusionCheck {
+    /// The backend fully validates the chain, there is nothing to check.
+    NotNeeded,
+    /// A merkle proof of its inclusion in the block was verified.
+    Verified,
+    /// The proof given by the backend is invalid.
+    Invalid(String),
+    /// The proof couldn't be obtained. It may be retried later.
+    Unavailable(String),
+}
+
 impl fmt::Display for BlockChainTip {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "({},{})", self.height, self.hash)
@@ -60,6 +148,10 @@ pub trait BitcoinInterface: Send {
     /// Check whether this former tip is part of the current best chain.
     fn is_in_chain(&self, tip: &BlockChainTip) -> bool;
 
+    /// Load the next chunk of the wallet data, for the backends which load it after startup.
+    /// Returns whether the wallet is fully loaded.
+    fn load_wallet_step(&mut self) -> bool;
+
     /// Sync the wallet with the current best chain.
     /// `receive_index` and `change_index` are the last derivation indices
     /// that are expected to have been used by the wallet.
@@ -116,6 +208,17 @@ pub trait BitcoinInterface: Send {
     /// Rescan progress percentage. Between 0 and 1.
     fn rescan_progress(&self) -> Option<f64>;
 
+    /// Whether a rescan which was ongoing at this date was interrupted since, for instance by a
+    /// restart of the backend, without being resumed.
+    fn rescan_interrupted_since(&self, timestamp: u32) -> bool;
+
+    /// Restart an interrupted rescan for this descriptor from the block at this height.
+    fn resume_rescan(
+        &mut self,
+        desc: &descriptors::LianaDescriptor,
+        height: i32,
+    ) -> Result<(), String>;
+
     /// Get the last block chain tip with a timestamp below this. Timestamp must be a valid block
     /// timestamp.
     fn block_before_date(&self, timestamp: u32) -> Option<BlockChainTip>;
@@ -133,6 +236,22 @@ pub trait BitcoinInterface: Send {
     ///
     /// Returns `None` if the transaction is not in the mempool.
     fn mempool_entry(&self, txid: &bitcoin::Txid) -> Option<MempoolEntry>;
+
+    /// Get the minimum feerates for a transaction to be accepted in the mempool, if the backend
+    /// exposes them.
+    fn min_feerates(&self) -> Option<MinFeerates>;
+
+    /// Get the histogram of the feerates paid by the transactions in the mempool, if the backend
+    /// exposes it.
+    fn fee_histogram(&self) -> Option<Vec<FeeHistogramBucket>>;
+
+    /// Check this wallet transaction was included in the block at this height, for the backends
+    /// which don't validate the chain themselves.
+    fn check_inclusion(&self, txid: &bitcoin::Txid, height: i32) -> InclusionCheck;
+
+    /// Get the transactions of the block of the best chain at this height along with the
+    /// previous outputs they spend, if the backend exposes them.
+    fn block_transactions(&self, height: i32) -> Option<BlockTransactions>;
 }
 
 impl BitcoinInterface for d::BitcoinD {
@@ -167,6 +286,11 @@ impl BitcoinInterface for d::BitcoinD {
             .unwrap_or(false)
     }
 
+    // The watchonly wallet is loaded by bitcoind.
+    fn load_wallet_step(&mut self) -> bool {
+        true
+    }
+
     // The watchonly wallet handles this for us.
     fn sync_wallet(
         &mut self,
@@ -376,6 +500,27 @@ impl BitcoinInterface for d::BitcoinD {
         self.rescan_progress()
     }
 
+    // bitcoind doesn't resume a rescan on restart. The descriptors keep the timestamp they were
+    // imported at as if they had been rescanned from there.
+    fn rescan_interrupted_since(&self, timestamp: u32) -> bool {
+        self.start_time()
+            .map(|start| start > timestamp)
+            .unwrap_or(false)
+    }
+
+    fn resume_rescan(
+        &mut self,
+        desc: &descriptors::LianaDescriptor,
+        height: i32,
+    ) -> Result<(), String> {
+        let time = self
+            .get_block_hash(height)
+            .and_then(|hash| self.get_block_stats(hash))
+            .map(|stats| stats.time)
+            .ok_or_else(|| format!("Could not get the block at height {}.", height))?;
+        self.start_rescan(desc, time).map_err(|e| e.to_string())
+    }
+
     fn block_before_date(&self, timestamp: u32) -> Option<BlockChainTip> {
         self.tip_before_timestamp(timestamp)
     }
@@ -402,9 +547,30 @@ impl BitcoinInterface for d::BitcoinD {
     fn mempool_entry(&self, txid: &bitcoin::Txid) -> Option<MempoolEntry> {
         self.mempool_entry(txid)
     }
+
+    fn min_feerates(&self) -> Option<MinFeerates> {
+        self.min_feerates()
+    }
+
+    fn fee_histogram(&self) -> Option<Vec<FeeHistogramBucket>> {
+        self.fee_histogram()
+    }
+
+    fn check_inclusion(&self, _: &bitcoin::Txid, _: i32) -> InclusionCheck {
+        // bitcoind validates the blocks it gives us.
+        InclusionCheck::NotNeeded
+    }
+
+    fn block_transactions(&self, height: i32) -> Option<BlockTransactions> {
+        self.block_transactions(height)
+    }
 }
 
 impl BitcoinInterface for electrum::Electrum {
+    fn load_wallet_step(&mut self) -> bool {
+        self.load_wallet_step()
+    }
+
     fn sync_wallet(
         &mut self,
         receive_index: ChildNumber,
@@ -554,6 +720,14 @@ impl BitcoinInterface for electrum::Electrum {
         self.client().mempool_entry(txid).ok()?
     }
 
+    fn min_feerates(&self) -> Option<MinFeerates> {
+        self.client().min_feerates().ok()
+    }
+
+    fn fee_histogram(&self) -> Option<Vec<FeeHistogramBucket>> {
+        self.client().fee_histogram().ok()
+    }
+
     fn mempool_spenders(&self, outpoints: &[bitcoin::OutPoint]) -> Vec<MempoolEntry> {
         self.client()
             .mempool_spenders(outpoints)
@@ -582,6 +756,20 @@ impl BitcoinInterface for electrum::Electrum {
         self.is_rescanning().then_some(0.0)
     }
 
+    // The full scan is performed by us, and started over at startup if it was interrupted.
+    fn rescan_interrupted_since(&self, _timestamp: u32) -> bool {
+        false
+    }
+
+    fn resume_rescan(
+        &mut self,
+        _desc: &descriptors::LianaDescriptor,
+        _height: i32,
+    ) -> Result<(), String> {
+        self.trigger_rescan();
+        Ok(())
+    }
+
     fn block_before_date(&self, _timestamp: u32) -> Option<BlockChainTip> {
         Some(self.genesis_block())
     }
@@ -589,6 +777,15 @@ impl BitcoinInterface for electrum::Electrum {
     fn tip_time(&self) -> Option<u32> {
         self.client().tip_time().ok()
     }
+
+    fn check_inclusion(&self, txid: &bitcoin::Txid, height: i32) -> InclusionCheck {
+        self.check_inclusion(txid, height)
+    }
+
+    // An Electrum server doesn't give the previous outputs of the transactions of a block.
+    fn block_transactions(&self, _: i32) -> Option<BlockTransactions> {
+        None
+    }
 }
 
 // FIXME: do we need to repeat the entire trait implementation? Isn't there a nicer way?
@@ -613,6 +810,10 @@ impl BitcoinInterface for sync::Arc<sync::Mutex<dyn BitcoinInterface + 'static>>
         self.lock().unwrap().is_in_chain(tip)
     }
 
+    fn load_wallet_step(&mut self) -> bool {
+        self.lock().unwrap().load_wallet_step()
+    }
+
     fn sync_wallet(
         &mut self,
         receive_index: ChildNumber,
@@ -672,6 +873,18 @@ impl BitcoinInterface for sync::Arc<sync::Mutex<dyn BitcoinInterface + 'static>>
         self.lock().unwrap().rescan_progress()
     }
 
+    fn rescan_interrupted_since(&self, timestamp: u32) -> bool {
+        self.lock().unwrap().rescan_interrupted_since(timestamp)
+    }
+
+    fn resume_rescan(
+        &mut self,
+        desc: &descriptors::LianaDescriptor,
+        height: i32,
+    ) -> Result<(), String> {
+        self.lock().unwrap().resume_rescan(desc, height)
+    }
+
     fn block_before_date(&self, timestamp: u32) -> Option<BlockChainTip> {
         self.lock().unwrap().block_before_date(timestamp)
     }
@@ -694,6 +907,81 @@ impl BitcoinInterface for sync::Arc<sync::Mutex<dyn BitcoinInterface + 'static>>
     fn mempool_entry(&self, txid: &bitcoin::Txid) -> Option<MempoolEntry> {
         self.lock().unwrap().mempool_entry(txid)
     }
+
+    fn min_feerates(&self) -> Option<MinFeerates> {
+        self.lock().unwrap().min_feerates()
+    }
+
+    fn fee_histogram(&self) -> Option<Vec<FeeHistogramBucket>> {
+        self.lock().unwrap().fee_histogram()
+    }
+
+    fn check_inclusion(&self, txid: &bitcoin::Txid, height: i32) -> InclusionCheck {
+        self.lock().unwrap().check_inclusion(txid, height)
+    }
+
+    fn block_transactions(&self, height: i32) -> Option<BlockTransactions> {
+        self.lock().unwrap().block_transactions(height)
+    }
+}
+
+/// A second, independent, Bitcoin backend against which to cross-check what our main backend
+/// reports. It is only queried about public data of the chain, it doesn't know about our wallet.
+pub trait CrossCheckBackend: Send {
+    /// Get the tip of its best chain.
+    fn chain_tip(&self) -> Result<BlockChainTip, String>;
+
+    /// Get the hash of the block at this height in its best chain.
+    fn block_hash(&self, height: i32) -> Result<Option<bitcoin::BlockHash>, String>;
+
+    /// Get the height at which this output, paying to this script, was confirmed if it is unspent
+    /// and confirmed in its best chain.
+    fn utxo_height(
+        &self,
+        outpoint: &bitcoin::OutPoint,
+        script_pubkey: &bitcoin::Script,
+    ) -> Result<Option<i32>, String>;
+}
+
+impl CrossCheckBackend for d::BitcoinD {
+    fn chain_tip(&self) -> Result<BlockChainTip, String> {
+        self.try_chain_tip().map_err(|e| e.to_string())
+    }
+
+    fn block_hash(&self, height: i32) -> Result<Option<bitcoin::BlockHash>, String> {
+        Ok(self.get_block_hash(height))
+    }
+
+    fn utxo_height(
+        &self,
+        outpoint: &bitcoin::OutPoint,
+        _: &bitcoin::Script,
+    ) -> Result<Option<i32>, String> {
+        let tip = self.try_chain_tip().map_err(|e| e.to_string())?;
+        Ok(self
+            .utxo_confirmations(outpoint)
+            .map_err(|e| e.to_string())?
+            .map(|confs| tip.height - confs as i32 + 1))
+    }
+}
+
+impl CrossCheckBackend for electrum::client::Client {
+    fn chain_tip(&self) -> Result<BlockChainTip, String> {
+        self.chain_tip().map_err(|e| e.to_string())
+    }
+
+    fn block_hash(&self, height: i32) -> Result<Option<bitcoin::BlockHash>, String> {
+        self.block_hash(height).map(Some).map_err(|e| e.to_string())
+    }
+
+    fn utxo_height(
+        &self,
+        outpoint: &bitcoin::OutPoint,
+        script_pubkey: &bitcoin::Script,
+    ) -> Result<Option<i32>, String> {
+        self.utxo_height(outpoint, script_pubkey)
+            .map_err(|e| e.to_string())
+    }
 }
 
 // FIXME: We could avoid this type (and all the conversions entailing allocations) if bitcoind
@@ -0,0 +1,381 @@
+//! Cross-check what our Bitcoin backend reports against a second, independent, backend.
+//!
+//! A backend may be lying to us or be eclipsed from the rest of the network. We compare its tip to
+//! the one of the cross-checking backend, and check our confirmed coins are unspent outputs of the
+//! cross-checking backend's chain, confirmed at about the same height. An alert is raised when they
+//! disagree beyond the configured tolerance.
+
+use crate::{
+    bitcoin::{BlockChainTip, CrossCheckBackend},
+    database::{CoinStatus, DatabaseConnection},
+    events::{Event, Events},
+};
+use liana::descriptors;
+
+use std::{collections::HashSet, fmt, sync};
+
+use miniscript::bitcoin::{self, secp256k1};
+
+/// Maximum number of coins to check at each poll, not to stall it with a slow backend. The coins
+/// are checked in turn across polls.
+const MAX_COINS_PER_CHECK: usize = 50;
+
+/// Something the two backends don't agree about.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Disagreement {
+    /// Their tips are too far apart.
+    TipHeight { height: i32, other_height: i32 },
+    /// They have a different block at the height of our tip.
+    BlockHash { height: i32 },
+    /// A confirmed coin of ours isn't an unspent confirmed output for the other backend.
+    MissingCoin { outpoint: bitcoin::OutPoint },
+    /// They disagree about the confirmation height of one of our coins.
+    CoinHeight {
+        outpoint: bitcoin::OutPoint,
+        height: i32,
+        other_height: i32,
+    },
+}
+
+impl Disagreement {
+    // The coin it is about, if any.
+    fn outpoint(&self) -> Option<&bitcoin::OutPoint> {
+        match self {
+            Self::TipHeight { .. } | Self::BlockHash { .. } => None,
+            Self::MissingCoin { outpoint } | Self::CoinHeight { outpoint, .. } => Some(outpoint),
+        }
+    }
+}
+
+impl fmt::Display for Disagreement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TipHeight {
+                height,
+                other_height,
+            } => write!(
+                f,
+                "Tip at height {} but the cross-checking backend's tip is at height {}",
+                height, other_height
+            ),
+            Self::BlockHash { height } => write!(
+                f,
+                "Different block at height {} for the cross-checking backend",
+                height
+            ),
+            Self::MissingCoin { outpoint } => write!(
+                f,
+                "Coin '{}' is not a confirmed unspent output for the cross-checking backend",
+                outpoint
+            ),
+            Self::CoinHeight {
+                outpoint,
+                height,
+                other_height,
+            } => write!(
+                f,
+                "Coin '{}' confirmed at height {} but at height {} for the cross-checking backend",
+                outpoint, height, other_height
+            ),
+        }
+    }
+}
+
+/// A confirmed unspent coin of ours to check.
+#[derive(Debug, Clone)]
+struct CoinToCheck {
+    outpoint: bitcoin::OutPoint,
+    height: i32,
+    script_pubkey: bitcoin::ScriptBuf,
+}
+
+// Compare our tip and the given coins to what the other backend reports.
+fn disagreements(
+    backend: &dyn CrossCheckBackend,
+    tip: BlockChainTip,
+    coins: &[CoinToCheck],
+    max_height_difference: u32,
+) -> Result<Vec<Disagreement>, String> {
+    let tolerance = max_height_difference as i32;
+    let mut disagreements = Vec::new();
+
+    let other_tip = backend.chain_tip()?;
+    if (tip.height - other_tip.height).abs() > tolerance {
+        disagreements.push(Disagreement::TipHeight {
+            height: tip.height,
+            other_height: other_tip.height,
+        });
+    } else if other_tip.height >= tip.height {
+        // If the other backend is slightly behind we don't have a block to compare to.
+        if let Some(hash) = backend.block_hash(tip.height)? {
+            if hash != tip.hash {
+                disagreements.push(Disagreement::BlockHash { height: tip.height });
+            }
+        }
+    }
+
+    for coin in coins {
+        // The most recent coins may not be known to a backend lagging within the tolerance.
+        if other_tip.height - coin.height < tolerance {
+            continue;
+        }
+        match backend.utxo_height(&coin.outpoint, &coin.script_pubkey)? {
+            None => disagreements.push(Disagreement::MissingCoin {
+                outpoint: coin.outpoint,
+            }),
+            Some(other_height) if (coin.height - other_height).abs() > tolerance => disagreements
+                .push(Disagreement::CoinHeight {
+                    outpoint: coin.outpoint,
+                    height: coin.height,
+                    other_height,
+                }),
+            Some(_) => {}
+        }
+    }
+
+    Ok(disagreements)
+}
+
+/// Compares what our backend reports to a second backend and raises an alert when they disagree.
+pub struct CrossChecker {
+    backend: Box<dyn CrossCheckBackend>,
+    max_height_difference: u32,
+    // The disagreements we already alerted about, not to alert again at each poll.
+    alerted: HashSet<Disagreement>,
+    // Where to resume checking our coins at the next poll.
+    coins_cursor: usize,
+}
+
+impl CrossChecker {
+    pub fn new(backend: Box<dyn CrossCheckBackend>, max_height_difference: u32) -> CrossChecker {
+        CrossChecker {
+            backend,
+            max_height_difference,
+            alerted: HashSet::new(),
+            coins_cursor: 0,
+        }
+    }
+
+    // Record the result of a check, return the disagreements we didn't alert about yet and whether
+    // the backends agree again.
+    fn record(
+        &mut self,
+        found: Vec<Disagreement>,
+        checked_coins: &HashSet<bitcoin::OutPoint>,
+        current_coins: &HashSet<bitcoin::OutPoint>,
+    ) -> (Vec<Disagreement>, bool) {
+        let previous = std::mem::take(&mut self.alerted);
+        let was_disagreeing = !previous.is_empty();
+        let fresh: Vec<_> = found
+            .iter()
+            .filter(|d| !previous.contains(d))
+            .cloned()
+            .collect();
+        // The disagreements about coins which weren't checked this time still hold, unless the
+        // coin isn't confirmed and unspent anymore.
+        self.alerted = previous
+            .into_iter()
+            .filter(|d| match d.outpoint() {
+                None => false,
+                Some(op) => !checked_coins.contains(op) && current_coins.contains(op),
+            })
+            .chain(found)
+            .collect();
+        (fresh, was_disagreeing && self.alerted.is_empty())
+    }
+
+    /// Compare our tip and a batch of our confirmed coins to what the cross-checking backend
+    /// reports. This is to be called after each update of our state from the Bitcoin backend.
+    pub fn check(
+        &mut self,
+        db_conn: &mut Box<dyn DatabaseConnection>,
+        descs: &[descriptors::SinglePathLianaDesc],
+        secp: &secp256k1::Secp256k1<secp256k1::VerifyOnly>,
+        events: &sync::Arc<sync::Mutex<Events>>,
+    ) {
+        let tip = match db_conn.chain_tip() {
+            Some(tip) => tip,
+            None => return,
+        };
+        let mut coins: Vec<_> = db_conn
+            .coins(&[CoinStatus::Confirmed], &[])
+            .into_values()
+            .filter_map(|c| {
+                c.block_info.map(|block| {
+                    let desc = if c.is_change { &descs[1] } else { &descs[0] };
+                    CoinToCheck {
+                        outpoint: c.outpoint,
+                        height: block.height,
+                        script_pubkey: desc.derive(c.derivation_index, secp).script_pubkey(),
+                    }
+                })
+            })
+            .collect();
+        coins.sort_by_key(|c| c.outpoint);
+        let current_coins: HashSet<_> = coins.iter().map(|c| c.outpoint).collect();
+        if self.coins_cursor >= coins.len() {
+            self.coins_cursor = 0;
+        }
+        let batch: Vec<_> = coins
+            .into_iter()
+            .skip(self.coins_cursor)
+            .take(MAX_COINS_PER_CHECK)
+            .collect();
+
+        let found = match disagreements(
+            self.backend.as_ref(),
+            tip,
+            &batch,
+            self.max_height_difference,
+        ) {
+            Ok(found) => found,
+            Err(e) => {
+                log::warn!("Error querying the cross-checking Bitcoin backend: {}", e);
+                return;
+            }
+        };
+        self.coins_cursor += batch.len();
+        let checked_coins: HashSet<_> = batch.iter().map(|c| c.outpoint).collect();
+
+        let (fresh, agree_again) = self.record(found, &checked_coins, &current_coins);
+        if !fresh.is_empty() {
+            events.lock().unwrap().push(Event::BackendsDisagree {
+                disagreements: fresh.iter().map(|d| d.to_string()).collect(),
+            });
+        } else if agree_again {
+            events.lock().unwrap().push(Event::BackendsAgree);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miniscript::bitcoin::hashes::Hash;
+    use std::collections::HashMap;
+
+    struct DummyCrossCheckBackend {
+        tip: BlockChainTip,
+        utxos: HashMap<bitcoin::OutPoint, i32>,
+    }
+
+    impl CrossCheckBackend for DummyCrossCheckBackend {
+        fn chain_tip(&self) -> Result<BlockChainTip, String> {
+            Ok(self.tip)
+        }
+
+        fn block_hash(&self, height: i32) -> Result<Option<bitcoin::BlockHash>, String> {
+            Ok((height <= self.tip.height).then_some(self.tip.hash))
+        }
+
+        fn utxo_height(
+            &self,
+            outpoint: &bitcoin::OutPoint,
+            _: &bitcoin::Script,
+        ) -> Result<Option<i32>, String> {
+            Ok(self.utxos.get(outpoint).copied())
+        }
+    }
+
+    fn tip(height: i32, byte: u8) -> BlockChainTip {
+        BlockChainTip {
+            height,
+            hash: bitcoin::BlockHash::from_slice(&[byte; 32]).unwrap(),
+        }
+    }
+
+    fn coin(i: u8, height: i32) -> CoinToCheck {
+        CoinToCheck {
+            outpoint: bitcoin::OutPoint::new(bitcoin::Txid::from_slice(&[i; 32]).unwrap(), 0),
+            height,
+            script_pubkey: bitcoin::ScriptBuf::new(),
+        }
+    }
+
+    #[test]
+    fn backends_disagreements() {
+        let (coin_a, coin_b, coin_c) = (coin(1, 100), coin(2, 150), coin(3, 199));
+        let coins = vec![coin_a.clone(), coin_b.clone(), coin_c.clone()];
+        let mut other = DummyCrossCheckBackend {
+            tip: tip(200, 1),
+            utxos: HashMap::from([(coin_a.outpoint, 100), (coin_b.outpoint, 151)]),
+        };
+
+        // Within the tolerance. The most recent coin isn't checked.
+        assert_eq!(disagreements(&other, tip(200, 1), &coins, 2), Ok(vec![]));
+        assert_eq!(disagreements(&other, tip(202, 2), &coins, 2), Ok(vec![]));
+
+        // Beyond the tolerance.
+        assert_eq!(
+            disagreements(&other, tip(203, 2), &coins, 2),
+            Ok(vec![Disagreement::TipHeight {
+                height: 203,
+                other_height: 200
+            }])
+        );
+        assert_eq!(
+            disagreements(&other, tip(200, 2), &coins, 0),
+            Ok(vec![
+                Disagreement::BlockHash { height: 200 },
+                Disagreement::CoinHeight {
+                    outpoint: coin_b.outpoint,
+                    height: 150,
+                    other_height: 151
+                },
+                Disagreement::MissingCoin {
+                    outpoint: coin_c.outpoint
+                },
+            ])
+        );
+
+        // A coin which the other backend doesn't know about.
+        other.utxos.remove(&coin_a.outpoint);
+        assert_eq!(
+            disagreements(&other, tip(200, 1), &coins, 2),
+            Ok(vec![Disagreement::MissingCoin {
+                outpoint: coin_a.outpoint
+            }])
+        );
+    }
+
+    #[test]
+    fn alert_once() {
+        let mut checker = CrossChecker::new(
+            Box::new(DummyCrossCheckBackend {
+                tip: tip(200, 1),
+                utxos: HashMap::new(),
+            }),
+            2,
+        );
+        let (op_a, op_b) = (coin(1, 100).outpoint, coin(2, 100).outpoint);
+        let all = HashSet::from([op_a, op_b]);
+        let missing = |outpoint| Disagreement::MissingCoin { outpoint };
+        let tip_height = Disagreement::TipHeight {
+            height: 210,
+            other_height: 200,
+        };
+
+        // We alert about new disagreements only.
+        let (fresh, agree) = checker.record(
+            vec![missing(op_a), tip_height.clone()],
+            &HashSet::from([op_a]),
+            &all,
+        );
+        assert_eq!((fresh.len(), agree), (2, false));
+        let (fresh, agree) = checker.record(
+            vec![tip_height.clone(), missing(op_b)],
+            &HashSet::from([op_b]),
+            &all,
+        );
+        assert_eq!((fresh, agree), (vec![missing(op_b)], false));
+
+        // A coin disagreement holds until the coin is checked again.
+        let (fresh, agree) = checker.record(vec![], &HashSet::from([op_a]), &all);
+        assert_eq!((fresh, agree), (vec![], false));
+        // It's forgotten once the coin is spent.
+        let (fresh, agree) = checker.record(vec![], &HashSet::new(), &HashSet::new());
+        assert_eq!((fresh, agree), (vec![], true));
+        let (fresh, agree) = checker.record(vec![], &HashSet::new(), &HashSet::new());
+        assert_eq!((fresh, agree), (vec![], false));
+    }
+}
@@ -0,0 +1,146 @@
+//! Reconcile the journal of the Spend transactions we broadcast with the state of the Bitcoin
+//! backend.
+//!
+//! A Spend is recorded in the journal before being handed to the backend and removed once we see
+//! it spending our coins. If the daemon crashed in between, the backend may or may not have
+//! accepted it. At startup the Spends the backend doesn't know about are broadcast again.
+
+use crate::{
+    bitcoin::BitcoinInterface,
+    database::{Coin, DatabaseConnection},
+};
+
+use std::collections::HashMap;
+
+use miniscript::bitcoin;
+
+/// What to do with an entry of the broadcast journal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JournalAction {
+    /// Our state already reflects this transaction, or it can't be broadcast anymore.
+    Remove,
+    /// The backend knows about it, our state will be updated at the next poll.
+    Keep,
+    /// The backend never got it, broadcast it again.
+    Rebroadcast,
+}
+
+// Decide what to do with this journal entry given the coins of ours it spends and whether the
+// backend knows about the transaction.
+fn journal_action(
+    txid: &bitcoin::Txid,
+    coins: &HashMap<bitcoin::OutPoint, Coin>,
+    known_by_backend: bool,
+) -> JournalAction {
+    if coins.values().any(|c| c.spend_txid.as_ref() == Some(txid)) {
+        return JournalAction::Remove;
+    }
+    // One of its inputs was spent by another transaction, it's not valid anymore.
+    if coins.values().any(|c| c.spend_txid.is_some()) {
+        return JournalAction::Remove;
+    }
+    if known_by_backend {
+        JournalAction::Keep
+    } else {
+        JournalAction::Rebroadcast
+    }
+}
+
+/// Go through the broadcast journal, removing the entries which are reflected in our state and
+/// broadcasting again those the backend doesn't know about if `rebroadcast` is set. An entry
+/// which the backend rejects upon rebroadcast is removed.
+pub fn reconcile(
+    bit: &impl BitcoinInterface,
+    db_conn: &mut Box<dyn DatabaseConnection>,
+    rebroadcast: bool,
+) {
+    for tx in db_conn.broadcast_journal() {
+        let txid = tx.compute_txid();
+        let outpoints: Vec<_> = tx.input.iter().map(|i| i.previous_output).collect();
+        let coins = db_conn.coins(&[], &outpoints);
+        let known_by_backend =
+            bit.mempool_entry(&txid).is_some() || bit.wallet_transaction(&txid).is_some();
+
+        match journal_action(&txid, &coins, known_by_backend) {
+            JournalAction::Remove => {
+                log::debug!("Removing '{}' from the broadcast journal.", txid);
+                db_conn.remove_broadcast(&txid);
+            }
+            JournalAction::Keep => {}
+            JournalAction::Rebroadcast if rebroadcast => {
+                log::warn!(
+                    "Spend '{}' was not accepted by the Bitcoin backend before shutdown. \
+                     Broadcasting it again.",
+                    txid
+                );
+                if let Err(e) = bit.broadcast_tx(&tx) {
+                    log::error!(
+                        "Error broadcasting '{}' again, removing it from the journal: {}",
+                        txid,
+                        e
+                    );
+                    db_conn.remove_broadcast(&txid);
+                }
+            }
+            JournalAction::Rebroadcast => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miniscript::bitcoin::{bip32, hashes::Hash, Amount};
+
+    fn coin(vout: u32, spend_txid: Option<bitcoin::Txid>) -> Coin {
+        Coin {
+            outpoint: bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), vout),
+            is_immature: false,
+            block_info: None,
+            amount: Amount::from_sat(100_000),
+            derivation_index: bip32::ChildNumber::from_normal_idx(0).unwrap(),
+            is_change: false,
+            spend_txid,
+            spend_block: None,
+            is_from_self: false,
+        }
+    }
+
+    #[test]
+    fn journal_actions() {
+        let txid = bitcoin::Txid::from_slice(&[1; 32]).unwrap();
+        let other_txid = bitcoin::Txid::from_slice(&[2; 32]).unwrap();
+        let coins = |spend_txids: &[Option<bitcoin::Txid>]| {
+            spend_txids
+                .iter()
+                .enumerate()
+                .map(|(i, spend_txid)| {
+                    let coin = coin(i as u32, *spend_txid);
+                    (coin.outpoint, coin)
+                })
+                .collect::<HashMap<_, _>>()
+        };
+
+        // Not seen spending our coins and unknown to the backend: the crash happened before the
+        // broadcast.
+        assert_eq!(
+            journal_action(&txid, &coins(&[None, None]), false),
+            JournalAction::Rebroadcast
+        );
+        // Accepted by the backend before the crash but we didn't poll yet.
+        assert_eq!(
+            journal_action(&txid, &coins(&[None, None]), true),
+            JournalAction::Keep
+        );
+        // Our state already reflects it.
+        assert_eq!(
+            journal_action(&txid, &coins(&[Some(txid), Some(txid)]), true),
+            JournalAction::Remove
+        );
+        // Conflicted by another transaction.
+        assert_eq!(
+            journal_action(&txid, &coins(&[None, Some(other_txid)]), false),
+            JournalAction::Remove
+        );
+    }
+}
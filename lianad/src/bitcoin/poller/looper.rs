@@ -1,21 +1,31 @@
 use crate::{
     bitcoin::{BitcoinInterface, BlockChainTip, UTxO, UTxOAddress},
-    database::{Coin, DatabaseConnection, DatabaseInterface},
+    database::{Coin, DatabaseConnection, DatabaseInterface, PollUpdates},
+    events::{Event, Events},
 };
 
 use std::{collections::HashSet, convert::TryInto, sync, thread, time};
 
 use liana::descriptors;
-use miniscript::bitcoin::{self, secp256k1};
-
-#[derive(Debug, Clone)]
-struct UpdatedCoins {
-    pub received: Vec<Coin>,
-    pub confirmed: Vec<(bitcoin::OutPoint, i32, u32)>,
-    pub expired: Vec<bitcoin::OutPoint>,
-    pub spending: Vec<(bitcoin::OutPoint, bitcoin::Txid)>,
-    pub expired_spending: Vec<bitcoin::OutPoint>,
-    pub spent: Vec<(bitcoin::OutPoint, bitcoin::Txid, i32, u32)>,
+use miniscript::bitcoin::{self, bip32, secp256k1};
+
+// Make sure the next derivation index for the given keychain is past the given one.
+fn maybe_bump_next_deriv_index(
+    db_conn: &mut Box<dyn DatabaseConnection>,
+    index: bip32::ChildNumber,
+    is_change: bool,
+    secp: &secp256k1::Secp256k1<secp256k1::VerifyOnly>,
+) {
+    let next_index = index
+        .increment()
+        .expect("Must not get into hardened territory");
+    if is_change {
+        if db_conn.change_index() < next_index {
+            db_conn.set_change_index(next_index, secp);
+        }
+    } else if db_conn.receive_index() < next_index {
+        db_conn.set_receive_index(next_index, secp);
+    }
 }
 
 // Update the state of our coins. There may be new unspent, and existing ones may become confirmed
@@ -30,7 +40,7 @@ fn update_coins(
     previous_tip: &BlockChainTip,
     descs: &[descriptors::SinglePathLianaDesc],
     secp: &secp256k1::Secp256k1<secp256k1::VerifyOnly>,
-) -> UpdatedCoins {
+) -> PollUpdates {
     let network = db_conn.network();
     let curr_coins = db_conn.coins(&[], &[]);
     log::debug!("Current coins: {:?}", curr_coins);
@@ -71,14 +81,11 @@ fn update_coins(
             }
             UTxOAddress::DerivIndex(index, is_change) => (index, is_change),
         };
-        // First of if we are receiving coins that are beyond our next derivation index,
-        // adjust it.
-        if derivation_index > db_conn.receive_index() {
-            db_conn.set_receive_index(derivation_index, secp);
-        }
-        if derivation_index > db_conn.change_index() {
-            db_conn.set_change_index(derivation_index, secp);
-        }
+        // First of if we are receiving coins that are at or beyond the next derivation index of
+        // the keychain they were received on, adjust it so we never hand out this address again.
+        // Both keychains are treated the same: a third party may well send to one of our change
+        // addresses (for instance if it was shared as part of a previous transaction).
+        maybe_bump_next_deriv_index(db_conn, derivation_index, is_change, secp);
 
         // Now record this coin as a newly received one.
         if !curr_coins.contains_key(&utxo.outpoint) {
@@ -152,22 +159,23 @@ fn update_coins(
     let (spent, expired_spending) = bit.spent_coins(spending_coins.as_slice());
     log::debug!("Newly spent coins: {:?}", spent);
 
-    UpdatedCoins {
+    PollUpdates {
         received,
         confirmed,
         expired,
         spending,
         expired_spending,
         spent,
+        ..Default::default()
     }
 }
 
-// Add new deposit and spend transactions to the database.
-fn add_txs_to_db(
+// Get the new deposit and spend transactions which aren't in database yet.
+fn missing_txs(
     bit: &impl BitcoinInterface,
     db_conn: &mut Box<dyn DatabaseConnection>,
-    updated_coins: &UpdatedCoins,
-) {
+    updated_coins: &PollUpdates,
+) -> Vec<bitcoin::Transaction> {
     let curr_txids: HashSet<_> = db_conn.list_saved_txids().into_iter().collect();
     let mut new_txids = HashSet::new();
     // Get the transaction for all newly received coins. Note we also query it if the coins
@@ -183,13 +191,10 @@ fn add_txs_to_db(
     log::debug!("Missing txids: {:?}", missing_txids);
 
     // Now retrieve txs.
-    let txs: Vec<_> = missing_txids
+    missing_txids
         .map(|txid| bit.wallet_transaction(txid).map(|(tx, _)| tx))
         .collect::<Option<Vec<_>>>()
-        .expect("we must retrieve all txs");
-    if !txs.is_empty() {
-        db_conn.new_txs(&txs);
-    }
+        .expect("we must retrieve all txs")
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -239,11 +244,35 @@ fn new_tip(bit: &impl BitcoinInterface, current_tip: &BlockChainTip) -> TipUpdat
     }
 }
 
+// Record a change in the reachability of the Bitcoin backend. `error` is set if it's unreachable.
+fn backend_reachability(
+    events: &sync::Mutex<Events>,
+    backend_down: &mut bool,
+    error: Option<String>,
+) {
+    match error {
+        Some(error) if !*backend_down => {
+            *backend_down = true;
+            events
+                .lock()
+                .unwrap()
+                .push(Event::BackendUnreachable { error });
+        }
+        None if *backend_down => {
+            *backend_down = false;
+            events.lock().unwrap().push(Event::BackendReachable);
+        }
+        _ => {}
+    }
+}
+
 fn updates(
     db_conn: &mut Box<dyn DatabaseConnection>,
     bit: &mut impl BitcoinInterface,
     descs: &[descriptors::SinglePathLianaDesc],
     secp: &secp256k1::Secp256k1<secp256k1::VerifyOnly>,
+    events: &sync::Mutex<Events>,
+    backend_down: &mut bool,
 ) {
     // Check if there was a new block before we update our state.
     //
@@ -255,7 +284,9 @@ fn updates(
     // an Electrum backend with the bitcoind-specific backend interface.
     let current_tip = db_conn.chain_tip().expect("Always set at first startup");
     let (receive_index, change_index) = (db_conn.receive_index(), db_conn.change_index());
-    let latest_tip = match bit.sync_wallet(receive_index, change_index) {
+    let sync_res = bit.sync_wallet(receive_index, change_index);
+    backend_reachability(events, backend_down, sync_res.as_ref().err().cloned());
+    let latest_tip = match sync_res {
         Ok(None) => {
             match new_tip(bit, &current_tip) {
                 TipUpdate::Same => current_tip,
@@ -265,7 +296,7 @@ fn updates(
                     // between our former chain and the new one, then restart fresh.
                     db_conn.rollback_tip(&new_tip);
                     log::info!("Tip was rolled back to '{}'.", new_tip);
-                    return updates(db_conn, bit, descs, secp);
+                    return updates(db_conn, bit, descs, secp, events, backend_down);
                 }
             }
         }
@@ -286,73 +317,138 @@ fn updates(
                     &reorg_common_ancestor
                 );
             }
-            return updates(db_conn, bit, descs, secp);
+            return updates(db_conn, bit, descs, secp, events, backend_down);
         }
         Err(e) => {
             log::error!("Error syncing wallet: '{}'.", e);
             thread::sleep(time::Duration::from_secs(2));
-            return updates(db_conn, bit, descs, secp);
+            return updates(db_conn, bit, descs, secp, events, backend_down);
         }
     };
 
     // Then check the state of our coins. Do it even if the tip did not change since last poll, as
     // we may have unconfirmed transactions.
-    let updated_coins = update_coins(bit, db_conn, &current_tip, descs, secp);
+    let mut updates = update_coins(bit, db_conn, &current_tip, descs, secp);
 
     // If the tip changed while we were polling our Bitcoin interface, start over.
     if bit.chain_tip() != latest_tip {
         log::info!("Chain tip changed while we were updating our state. Starting over.");
-        return updates(db_conn, bit, descs, secp);
+        return updates(db_conn, bit, descs, secp, events, backend_down);
     }
 
-    // Transactions must be added to the DB before coins due to foreign key constraints.
-    add_txs_to_db(bit, db_conn, &updated_coins);
-    // The chain tip did not change since we started our updates. Record them and the latest tip.
-    // Having the tip in database means that, as far as the chain is concerned, we've got all
-    // updates up to this block. But not more.
-    db_conn.new_unspent_coins(&updated_coins.received);
-    db_conn.remove_coins(&updated_coins.expired);
-    db_conn.confirm_coins(&updated_coins.confirmed);
-    db_conn.unspend_coins(&updated_coins.expired_spending);
-    db_conn.spend_coins(&updated_coins.spending);
-    db_conn.confirm_spend(&updated_coins.spent);
-    // Update info about which coins are from self only after
-    // coins have been inserted & updated above.
-    db_conn.update_coins_from_self(current_tip.height);
-    if latest_tip != current_tip {
-        db_conn.update_tip(&latest_tip);
-        log::debug!("New tip: '{}'", latest_tip);
+    updates.txs = missing_txs(bit, db_conn, &updates);
+    // The chain tip did not change since we started our updates. Record them and the latest tip,
+    // all at once. Having the tip in database means that, as far as the chain is concerned, we've
+    // got all updates up to this block. But not more.
+    let new_tip = (latest_tip != current_tip).then_some(&latest_tip);
+    db_conn.apply_poll_updates(&updates, current_tip.height, new_tip);
+    if let Some(tip) = new_tip {
+        log::debug!("New tip: '{}'", tip);
     }
 
     log::debug!("Updates done.");
 }
 
+// Record the height up to which the rescan from this timestamp processed the chain, given its
+// progress on the backend. `rescan_start` is the height it was (re)started from, if known.
+fn record_rescan_checkpoint(
+    db_conn: &mut Box<dyn DatabaseConnection>,
+    bit: &impl BitcoinInterface,
+    timestamp: u32,
+    progress: f64,
+    rescan_start: &mut Option<i32>,
+) {
+    // If we don't know where the backend started rescanning from, assume it's the start of the
+    // whole rescan. This may only make us underestimate its progress.
+    let start = match *rescan_start {
+        Some(height) => height,
+        None => match bit.block_before_date(timestamp) {
+            Some(block) => *rescan_start.insert(block.height),
+            None => return,
+        },
+    };
+    // The progress reported by the backend is in proportion of the transactions processed, and
+    // recent blocks contain more transactions than older ones. Interpolating the height from it
+    // therefore underestimates it, which is what we want for a checkpoint.
+    let tip_height = bit.chain_tip().height;
+    let scanned =
+        (tip_height.saturating_sub(start).max(0) as f64 * progress.clamp(0.0, 1.0)) as i32;
+    let height = start.saturating_add(scanned);
+    if db_conn
+        .rescan_checkpoint()
+        .map(|h| height > h)
+        .unwrap_or(true)
+    {
+        db_conn.set_rescan_checkpoint(height);
+    }
+}
+
+/// Record the height up to which the ongoing rescan processed the chain, if there is any, for it
+/// to be resumed from there if it gets interrupted. `rescan_start` is the height it was
+/// (re)started from on the backend, if known.
+pub fn checkpoint_rescan(
+    db_conn: &mut Box<dyn DatabaseConnection>,
+    bit: &impl BitcoinInterface,
+    rescan_start: &mut Option<i32>,
+) {
+    if let (Some(timestamp), Some(progress)) = (db_conn.rescan_timestamp(), bit.rescan_progress()) {
+        record_rescan_checkpoint(db_conn, bit, timestamp, progress, rescan_start);
+    }
+}
+
 // Check if there is any rescan of the backend ongoing or one that just finished.
+#[allow(clippy::too_many_arguments)]
 fn rescan_check(
     db_conn: &mut Box<dyn DatabaseConnection>,
     bit: &mut impl BitcoinInterface,
+    desc: &descriptors::LianaDescriptor,
     descs: &[descriptors::SinglePathLianaDesc],
     secp: &secp256k1::Secp256k1<secp256k1::VerifyOnly>,
+    events: &sync::Mutex<Events>,
+    backend_down: &mut bool,
+    rescan_start: &mut Option<i32>,
 ) {
     log::debug!("Checking the state of an ongoing rescan if there is any");
 
     // Check if there is an ongoing rescan. If there isn't and we previously asked for a rescan of
-    // the backend, we treat it as completed.
+    // the backend, we treat it as completed unless it was interrupted, in which case we resume it
+    // from the last checkpoint.
     // Upon completion of the rescan from the given timestamp on the backend, we rollback our state
     // down to the height before this timestamp to rescan everything that happened since then.
     let rescan_timestamp = db_conn.rescan_timestamp();
     if let Some(progress) = bit.rescan_progress() {
         log::info!("Rescan progress: {:.2}%.", progress * 100.0);
-        if rescan_timestamp.is_none() {
+        if let Some(timestamp) = rescan_timestamp {
+            record_rescan_checkpoint(db_conn, bit, timestamp, progress, rescan_start);
+        } else {
             log::warn!("Backend is rescanning but we didn't ask for it.");
         }
     } else if let Some(timestamp) = rescan_timestamp {
+        // The last poll timestamp isn't updated yet, this is the one of the previous poll.
+        if let (Some(checkpoint), Some(last_poll)) =
+            (db_conn.rescan_checkpoint(), db_conn.last_poll_timestamp())
+        {
+            if bit.rescan_interrupted_since(last_poll) {
+                log::warn!(
+                    "The rescan was interrupted. Resuming it from block height {}.",
+                    checkpoint
+                );
+                match bit.resume_rescan(desc, checkpoint) {
+                    Ok(()) => *rescan_start = Some(checkpoint),
+                    Err(e) => log::error!("Error resuming the interrupted rescan: '{}'.", e),
+                }
+                return;
+            }
+        }
+        *rescan_start = None;
+
         log::info!("Rescan completed on the backend.");
         // TODO: we could check if the timestamp of the descriptors in the Bitcoin backend are
         // truly at the rescan timestamp, and trigger a rescan otherwise. Note however it would be
         // no use for the bitcoind implementation of the backend, since bitcoind will always set
         // the timestamp of the descriptors in the wallet first (and therefore consider it as
-        // rescanned from this height even if it aborts the rescan by being stopped).
+        // rescanned from this height even if it aborts the rescan by being stopped). This is why
+        // we resume interrupted rescans from their last checkpoint above instead.
         let rescan_tip = match bit.block_before_date(timestamp) {
             Some(block) => block,
             None => {
@@ -365,11 +461,15 @@ fn rescan_check(
         };
         db_conn.rollback_tip(&rescan_tip);
         db_conn.complete_rescan();
+        events
+            .lock()
+            .unwrap()
+            .push(Event::RescanCompleted { timestamp });
         log::info!(
             "Rolling back our internal tip to '{}' to update our internal state with past transactions.",
             rescan_tip
         );
-        updates(db_conn, bit, descs, secp)
+        updates(db_conn, bit, descs, secp, events, backend_down)
     } else {
         log::debug!("No ongoing rescan.");
     }
@@ -395,16 +495,31 @@ pub fn sync_poll_interval() -> time::Duration {
     time::Duration::from_secs(0)
 }
 
-/// Update our state from the Bitcoin backend.
+/// Update our state from the Bitcoin backend. A change in the reachability of the backend is
+/// recorded in the `events`, `backend_down` tracking whether it was unreachable.
+#[allow(clippy::too_many_arguments)]
 pub fn poll(
     bit: &mut sync::Arc<sync::Mutex<dyn BitcoinInterface>>,
     db: &sync::Arc<sync::Mutex<dyn DatabaseInterface>>,
     secp: &secp256k1::Secp256k1<secp256k1::VerifyOnly>,
+    desc: &descriptors::LianaDescriptor,
     descs: &[descriptors::SinglePathLianaDesc],
+    events: &sync::Mutex<Events>,
+    backend_down: &mut bool,
+    rescan_start: &mut Option<i32>,
 ) {
     let mut db_conn = db.connection();
-    updates(&mut db_conn, bit, descs, secp);
-    rescan_check(&mut db_conn, bit, descs, secp);
+    updates(&mut db_conn, bit, descs, secp, events, backend_down);
+    rescan_check(
+        &mut db_conn,
+        bit,
+        desc,
+        descs,
+        secp,
+        events,
+        backend_down,
+        rescan_start,
+    );
     let now: u32 = time::SystemTime::now()
         .duration_since(time::UNIX_EPOCH)
         .expect("current system time must be later than epoch")
@@ -1,7 +1,29 @@
+mod crosscheck;
+mod journal;
 mod looper;
+mod monitor;
+mod proofs;
+mod scheduled;
+mod silent_payments;
+mod staleness;
+mod timing;
+mod watchtower;
 
-use crate::{bitcoin::BitcoinInterface, database::DatabaseInterface};
+pub use crosscheck::CrossChecker;
+pub use staleness::TipMonitor;
+pub use timing::PollStats;
+pub use watchtower::Watchtower;
+pub(crate) use watchtower::{timelocks_matured, ChainState};
+
+use crate::{
+    bitcoin::BitcoinInterface,
+    database::DatabaseInterface,
+    events::Events,
+    spans::{self, Span, SpanKind},
+};
 use liana::descriptors;
+use monitor::EventMonitor;
+use timing::PollScheduler;
 
 use std::{
     sync::{self, mpsc},
@@ -25,6 +47,19 @@ pub struct Poller {
     secp: secp256k1::Secp256k1<secp256k1::VerifyOnly>,
     // The receive and change descriptors (in this order).
     descs: [descriptors::SinglePathLianaDesc; 2],
+    main_descriptor: descriptors::LianaDescriptor,
+    events: sync::Arc<sync::Mutex<Events>>,
+    monitor: EventMonitor,
+    // Whether the Bitcoin backend was unreachable at the last poll.
+    backend_down: bool,
+    watchtower: Option<Watchtower>,
+    cross_checker: Option<CrossChecker>,
+    tip_monitor: Option<TipMonitor>,
+    // Whether the broadcast journal was reconciled since startup.
+    journal_reconciled: bool,
+    scheduler: PollScheduler,
+    // The height the ongoing rescan was (re)started from on the backend, if known.
+    rescan_start: Option<i32>,
 }
 
 impl Poller {
@@ -32,6 +67,10 @@ impl Poller {
         bit: sync::Arc<sync::Mutex<dyn BitcoinInterface>>,
         db: sync::Arc<sync::Mutex<dyn DatabaseInterface>>,
         desc: descriptors::LianaDescriptor,
+        events: sync::Arc<sync::Mutex<Events>>,
+        watchtower: Option<Watchtower>,
+        cross_checker: Option<CrossChecker>,
+        tip_monitor: Option<TipMonitor>,
     ) -> Poller {
         let secp = secp256k1::Secp256k1::verification_only();
         let descs = [
@@ -41,12 +80,108 @@ impl Poller {
 
         // On first startup the tip may be NULL. Make sure it's set as the poller relies on it.
         looper::maybe_initialize_tip(&bit, &db);
+        let monitor = EventMonitor::new(&mut db.connection(), events.clone());
 
         Poller {
             bit,
             db,
             secp,
             descs,
+            main_descriptor: desc,
+            events,
+            monitor,
+            backend_down: false,
+            watchtower,
+            cross_checker,
+            tip_monitor,
+            journal_reconciled: false,
+            scheduler: PollScheduler::default(),
+            rescan_start: None,
+        }
+    }
+
+    /// The statistics about the polls, kept up to date as polls are performed.
+    pub fn stats(&self) -> sync::Arc<sync::Mutex<PollStats>> {
+        self.scheduler.stats()
+    }
+
+    // Poll, and get when to poll next.
+    fn timed_poll(&mut self, poll_interval: time::Duration) -> time::Instant {
+        let start = time::Instant::now();
+        let number = self.scheduler.stats().lock().unwrap().polls + 1;
+        spans::within(SpanKind::Poll, &format!("#{}", number), || self.poll());
+        start
+            + self
+                .scheduler
+                .record(start.elapsed(), self.backend_down, poll_interval)
+    }
+
+    // Update our state from the Bitcoin backend, then act upon it: verify the confirmations the
+    // backend reported if it doesn't validate the chain, scan the new blocks for Silent Payments,
+    // broadcast the scheduled Spends which became valid, record the events of interest, broadcast
+    // pre-signed transactions if we are a watchtower, compare our state to what a second backend
+    // reports if configured to and check the tip advances and the system clock is right.
+    fn poll(&mut self) {
+        // A rescan may complete during this poll. The coins it finds are not news either.
+        let was_rescanning = self.db.connection().rescan_timestamp().is_some();
+        spans::within(SpanKind::PollStep, "chain update", || {
+            looper::poll(
+                &mut self.bit,
+                &self.db,
+                &self.secp,
+                &self.main_descriptor,
+                &self.descs,
+                &self.events,
+                &mut self.backend_down,
+                &mut self.rescan_start,
+            )
+        });
+        // Spends which may have been lost in a crash are only broadcast again at the first poll,
+        // once the backend is synced.
+        spans::within(SpanKind::PollStep, "journal reconciliation", || {
+            journal::reconcile(
+                &self.bit,
+                &mut self.db.connection(),
+                !self.journal_reconciled,
+            )
+        });
+        self.journal_reconciled = true;
+        spans::within(SpanKind::PollStep, "confirmation proofs", || {
+            proofs::verify(&self.bit, &mut self.db.connection())
+        });
+        spans::within(SpanKind::PollStep, "silent payments scan", || {
+            silent_payments::scan(&self.bit, &mut self.db.connection(), &self.secp)
+        });
+        spans::within(SpanKind::PollStep, "scheduled spends", || {
+            scheduled::check(&self.bit, &mut self.db.connection(), &self.events)
+        });
+        spans::within(SpanKind::PollStep, "events", || {
+            self.monitor.check(
+                &mut self.db.connection(),
+                &self.main_descriptor,
+                &self.secp,
+                was_rescanning,
+            )
+        });
+        if let Some(ref watchtower) = self.watchtower {
+            let _span = Span::enter(SpanKind::PollStep, "watchtower");
+            watchtower.check(&self.bit, &mut self.db.connection());
+        }
+        if let Some(ref mut cross_checker) = self.cross_checker {
+            let _span = Span::enter(SpanKind::PollStep, "cross-check");
+            cross_checker.check(
+                &mut self.db.connection(),
+                &self.descs,
+                &self.secp,
+                &self.events,
+            );
+        }
+        // The tip doesn't advance while the backend is unreachable, that's already reported.
+        if !self.backend_down {
+            if let Some(ref mut tip_monitor) = self.tip_monitor {
+                let _span = Span::enter(SpanKind::PollStep, "tip check");
+                tip_monitor.check(&self.bit, &mut self.db.connection(), &self.events);
+            }
         }
     }
 
@@ -61,31 +196,27 @@ impl Poller {
         poll_interval: time::Duration,
         receiver: mpsc::Receiver<PollerMessage>,
     ) {
-        let mut last_poll = None;
+        // Poll right away at startup.
+        let mut next_poll = time::Instant::now();
         let mut synced = false;
 
+        // Load the wallet data into the Bitcoin backend before the first poll. This is done by
+        // chunks so the backend is only locked for the duration of a chunk and the commands using
+        // it can be answered in the meantime. Messages sent meanwhile are received once done.
+        while !self.bit.load_wallet_step() {}
+
         loop {
-            // How long to wait before the next poll.
-            let time_before_poll = if let Some(last_poll) = last_poll {
-                let time_since_poll = time::Instant::now().duration_since(last_poll);
-                // Until we are synced we poll less often to avoid harassing bitcoind and impeding
-                // the sync. As a function since it's mocked for the tests.
-                let poll_interval = if synced {
-                    poll_interval
-                } else {
-                    looper::sync_poll_interval()
-                };
-                poll_interval.saturating_sub(time_since_poll)
-            } else {
-                // Don't wait before doing the first poll.
-                time::Duration::ZERO
-            };
-
-            // Wait for the duration of the interval between polls, but listen to messages in the
-            // meantime.
+            // Wait until the next poll is due, but listen to messages in the meantime.
+            let time_before_poll = next_poll.saturating_duration_since(time::Instant::now());
             match receiver.recv_timeout(time_before_poll) {
                 Ok(PollerMessage::Shutdown) => {
                     log::info!("Bitcoin poller was told to shut down.");
+                    // Don't lose the progress made by an ongoing rescan since the last poll.
+                    looper::checkpoint_rescan(
+                        &mut self.db.connection(),
+                        &self.bit,
+                        &mut self.rescan_start,
+                    );
                     return;
                 }
                 Ok(PollerMessage::PollNow(sender)) => {
@@ -104,13 +235,13 @@ impl Poller {
                         );
                         synced = progress.is_complete();
                     }
-                    // Update `last_poll` even if we don't poll now so that we don't attempt another
-                    // poll too soon.
-                    last_poll = Some(time::Instant::now());
+                    // Reschedule the next poll even if we don't poll now so that we don't attempt
+                    // another poll too soon.
                     if synced {
-                        looper::poll(&mut self.bit, &self.db, &self.secp, &self.descs);
+                        next_poll = self.timed_poll(poll_interval);
                     } else {
                         log::warn!("Skipped poll as block chain is still synchronizing.");
+                        next_poll = time::Instant::now() + looper::sync_poll_interval();
                     }
                     if let Err(e) = sender.send(()) {
                         log::error!("Error sending immediate poll completion signal: {}.", e);
@@ -118,16 +249,17 @@ impl Poller {
                     continue;
                 }
                 Err(mpsc::RecvTimeoutError::Timeout) => {
-                    // It's been long enough since the last poll.
+                    // It's time to poll.
                 }
                 Err(mpsc::RecvTimeoutError::Disconnected) => {
                     log::error!("Bitcoin poller communication channel got disconnected. Exiting.");
                     return;
                 }
             }
-            last_poll = Some(time::Instant::now());
 
-            // Don't poll until the Bitcoin backend is fully synced.
+            // Don't poll until the Bitcoin backend is fully synced. Until then we poll less often
+            // to avoid harassing bitcoind and impeding the sync. As a function since it's mocked
+            // for the tests.
             if !synced {
                 let progress = self.bit.sync_progress();
                 log::info!(
@@ -138,11 +270,12 @@ impl Poller {
                 );
                 synced = progress.is_complete();
                 if !synced {
+                    next_poll = time::Instant::now() + looper::sync_poll_interval();
                     continue;
                 }
             }
 
-            looper::poll(&mut self.bit, &self.db, &self.secp, &self.descs);
+            next_poll = self.timed_poll(poll_interval);
         }
     }
 }
@@ -0,0 +1,303 @@
+//! Detect the changes to the state of our coins the user should be made aware of.
+
+use crate::{
+    database::{Coin, DatabaseConnection},
+    events::{Event, Events},
+};
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync,
+};
+
+use liana::descriptors;
+use miniscript::bitcoin::{self, secp256k1};
+
+/// Raises events upon new deposits, confirmed spends, spends of our coins by a transaction which
+/// isn't one of our Spend transaction drafts and coins whose recovery path is soon available.
+pub struct EventMonitor {
+    events: sync::Arc<sync::Mutex<Events>>,
+    // The coins we already know about.
+    known_coins: HashSet<bitcoin::OutPoint>,
+    // The spending transactions we already checked.
+    known_spends: HashSet<bitcoin::Txid>,
+    // The spending transactions we already know are confirmed.
+    confirmed_spends: HashSet<bitcoin::Txid>,
+    // The coins we already warned about the recovery path being soon available.
+    expiring_coins: HashSet<bitcoin::OutPoint>,
+}
+
+// The spending transactions of these coins, along with the coins they spend.
+fn spends(coins: &HashMap<bitcoin::OutPoint, Coin>) -> HashMap<bitcoin::Txid, Vec<Coin>> {
+    let mut spends: HashMap<_, Vec<_>> = HashMap::new();
+    for coin in coins.values() {
+        if let Some(txid) = coin.spend_txid {
+            spends.entry(txid).or_default().push(*coin);
+        }
+    }
+    spends
+}
+
+impl EventMonitor {
+    /// The deposits and spends which are already in database are not reported. The coins whose
+    /// recovery path is soon available are.
+    pub fn new(
+        db_conn: &mut Box<dyn DatabaseConnection>,
+        events: sync::Arc<sync::Mutex<Events>>,
+    ) -> EventMonitor {
+        let coins = db_conn.coins(&[], &[]);
+        EventMonitor {
+            events,
+            known_coins: coins.keys().copied().collect(),
+            known_spends: spends(&coins).into_keys().collect(),
+            confirmed_spends: coins
+                .values()
+                .filter(|c| c.spend_block.is_some())
+                .filter_map(|c| c.spend_txid)
+                .collect(),
+            expiring_coins: HashSet::new(),
+        }
+    }
+
+    /// Check the changes since the last call. This is to be called after each update of our state
+    /// from the Bitcoin backend, telling whether a rescan was ongoing before this update.
+    pub fn check(
+        &mut self,
+        db_conn: &mut Box<dyn DatabaseConnection>,
+        desc: &descriptors::LianaDescriptor,
+        secp: &secp256k1::Secp256k1<secp256k1::VerifyOnly>,
+        was_rescanning: bool,
+    ) {
+        // The past deposits and spends we find while rescanning aren't news.
+        let is_rescanning = was_rescanning || db_conn.rescan_timestamp().is_some();
+        let coins = db_conn.coins(&[], &[]);
+        let mut new_events = Vec::new();
+
+        let mut received: Vec<_> = coins
+            .values()
+            .filter(|c| !self.known_coins.contains(&c.outpoint))
+            .collect();
+        received.sort_by_key(|c| c.outpoint);
+        for coin in received {
+            self.known_coins.insert(coin.outpoint);
+            // Change outputs are not deposits.
+            if !coin.is_change && !is_rescanning {
+                new_events.push(Event::CoinReceived {
+                    outpoint: coin.outpoint,
+                    amount: coin.amount,
+                });
+            }
+        }
+
+        for (txid, spent_coins) in spends(&coins) {
+            if let Some(block) = spent_coins.iter().find_map(|c| c.spend_block) {
+                if self.confirmed_spends.insert(txid) && !is_rescanning {
+                    new_events.push(Event::SpendConfirmed {
+                        txid,
+                        height: block.height,
+                    });
+                }
+            }
+            if !self.known_spends.insert(txid) || is_rescanning {
+                continue;
+            }
+            if db_conn.spend_tx(&txid).is_some() {
+                log::debug!("Spend of our coins by '{}' is one of our drafts.", txid);
+                continue;
+            }
+
+            // Determine which keys signed it from the first of our inputs it's possible for.
+            let tx = db_conn
+                .list_wallet_transactions(&[txid])
+                .into_iter()
+                .next()
+                .map(|(tx, _, _)| tx);
+            let spending_path = tx.and_then(|tx| {
+                spent_coins.iter().find_map(|coin| {
+                    let input_index = tx
+                        .input
+                        .iter()
+                        .position(|txin| txin.previous_output == coin.outpoint)?;
+                    desc.signed_input_path(
+                        &tx,
+                        input_index,
+                        coin.amount,
+                        coin.derivation_index,
+                        coin.is_change,
+                        secp,
+                    )
+                })
+            });
+
+            let mut outpoints: Vec<_> = spent_coins.into_iter().map(|c| c.outpoint).collect();
+            outpoints.sort();
+            new_events.push(Event::UnauthorizedSpend {
+                txid,
+                coins: outpoints,
+                spending_path,
+            });
+        }
+
+        // Like the GUI, warn about the coins whose first recovery path is available within 10%
+        // of its timelock.
+        let tip = db_conn.chain_tip().filter(|_| !is_rescanning);
+        if let Some(tip) = tip {
+            let timelock = desc.first_timelock_value();
+            let mut expiring = Vec::new();
+            let mut blocks_remaining = u32::MAX;
+            for coin in coins.values().filter(|c| c.spend_txid.is_none()) {
+                let block = match coin.block_info {
+                    Some(block) => block,
+                    None => continue,
+                };
+                let remaining = (block.height + timelock as i32 - tip.height).max(0) as u32;
+                if remaining <= timelock as u32 / 10 && self.expiring_coins.insert(coin.outpoint) {
+                    expiring.push(coin.outpoint);
+                    blocks_remaining = blocks_remaining.min(remaining);
+                }
+            }
+            if !expiring.is_empty() {
+                expiring.sort();
+                new_events.push(Event::RecoveryPathSoonAvailable {
+                    timelock,
+                    coins: expiring,
+                    blocks_remaining,
+                });
+            }
+        }
+
+        if !new_events.is_empty() {
+            let mut events = self.events.lock().unwrap();
+            for event in new_events {
+                events.push(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bitcoin::BlockChainTip,
+        database::{BlockInfo, DatabaseInterface},
+        events::EventLevel,
+        testutils::DummyDatabase,
+    };
+
+    use std::str::FromStr;
+
+    use miniscript::{
+        bitcoin::{bip32, hashes::Hash, Amount},
+        descriptor,
+    };
+
+    fn coin(vout: u32, height: Option<i32>) -> Coin {
+        Coin {
+            outpoint: bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), vout),
+            is_immature: false,
+            block_info: height.map(|height| BlockInfo {
+                height,
+                time: 1_700_000_000,
+            }),
+            amount: Amount::from_sat(100_000),
+            derivation_index: bip32::ChildNumber::from_normal_idx(vout).unwrap(),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        }
+    }
+
+    #[test]
+    fn event_monitor() {
+        let owner_key = descriptors::PathInfo::Single(descriptor::DescriptorPublicKey::from_str("[aabbccdd]xpub68JJTXc1MWK8KLW4HGLXZBJknja7kDUJuFHnM424LbziEXsfkh1WQCiEjjHw4zLqSUm4rvhgyGkkuRowE9tCJSgt3TQB5J3SKAbZ2SdcKST/<0;1>/*").unwrap());
+        let heir_key = descriptors::PathInfo::Single(descriptor::DescriptorPublicKey::from_str("[aabbccdd]xpub68JJTXc1MWK8PEQozKsRatrUHXKFNkD1Cb1BuQU9Xr5moCv87anqGyXLyUd4KpnDyZgo3gz4aN1r3NiaoweFW8UutBsBbgKHzaD5HkTkifK/<0;1>/*").unwrap());
+        let policy = descriptors::LianaPolicy::new_legacy(
+            owner_key,
+            [(10_000, heir_key)].iter().cloned().collect(),
+        )
+        .unwrap();
+        let desc = descriptors::LianaDescriptor::new(policy);
+        let secp = secp256k1::Secp256k1::verification_only();
+
+        let mut db = DummyDatabase::new();
+        db.connection().update_tip(&BlockChainTip {
+            hash: bitcoin::BlockHash::all_zeros(),
+            height: 100_000,
+        });
+        db.insert_coins(vec![coin(0, Some(99_000))]);
+        let events = sync::Arc::new(sync::Mutex::new(Events::new(None)));
+        let mut monitor = EventMonitor::new(&mut db.connection(), events.clone());
+
+        // The existing coin isn't reported as a deposit, but its recovery path is available in
+        // 9'000 blocks. Nothing happens the second time.
+        monitor.check(&mut db.connection(), &desc, &secp, false);
+        monitor.check(&mut db.connection(), &desc, &secp, false);
+        assert!(events.lock().unwrap().list(None).is_empty());
+
+        // A new deposit and a change output.
+        let mut change = coin(2, None);
+        change.is_change = true;
+        db.insert_coins(vec![coin(1, None), change]);
+        monitor.check(&mut db.connection(), &desc, &secp, false);
+        let list = events.lock().unwrap().list(None);
+        assert_eq!(list.len(), 1);
+        assert_eq!(
+            list[0].event,
+            Event::CoinReceived {
+                outpoint: coin(1, None).outpoint,
+                amount: Amount::from_sat(100_000),
+            }
+        );
+
+        // The coin is now close to its expiry.
+        db.connection().update_tip(&BlockChainTip {
+            hash: bitcoin::BlockHash::all_zeros(),
+            height: 108_500,
+        });
+        monitor.check(&mut db.connection(), &desc, &secp, false);
+        let list = events.lock().unwrap().list(Some(list[0].id));
+        assert_eq!(list.len(), 1);
+        assert_eq!(
+            list[0].event,
+            Event::RecoveryPathSoonAvailable {
+                timelock: 10_000,
+                coins: vec![coin(0, None).outpoint],
+                blocks_remaining: 500,
+            }
+        );
+
+        // It gets spent by a transaction we don't know of, which later confirms.
+        let spend_txid = bitcoin::Txid::from_byte_array([1; 32]);
+        let mut spent = coin(0, Some(99_000));
+        spent.spend_txid = Some(spend_txid);
+        db.insert_coins(vec![spent]);
+        monitor.check(&mut db.connection(), &desc, &secp, false);
+        let list = events.lock().unwrap().list(Some(list[0].id));
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].level, EventLevel::Critical);
+        assert_eq!(
+            list[0].event,
+            Event::UnauthorizedSpend {
+                txid: spend_txid,
+                coins: vec![spent.outpoint],
+                spending_path: None,
+            }
+        );
+        spent.spend_block = Some(BlockInfo {
+            height: 108_501,
+            time: 1_700_000_000,
+        });
+        db.insert_coins(vec![spent]);
+        monitor.check(&mut db.connection(), &desc, &secp, false);
+        let list = events.lock().unwrap().list(Some(list[0].id));
+        assert_eq!(
+            list.into_iter().map(|e| e.event).collect::<Vec<_>>(),
+            vec![Event::SpendConfirmed {
+                txid: spend_txid,
+                height: 108_501,
+            }]
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Verify the inclusion of our confirmed transactions in their block.
+//!
+//! A Bitcoin backend which doesn't validate the chain (an Electrum server) could lie about a
+//! transaction being confirmed. For those we check a merkle proof of the inclusion of each of our
+//! confirmed transactions against the header of the block of our chain at the claimed height, and
+//! record the result.
+
+use crate::{
+    bitcoin::{BitcoinInterface, InclusionCheck},
+    database::DatabaseConnection,
+};
+
+/// Maximum number of transactions to check at each poll, not to stall it after a rescan.
+const MAX_CHECKS_PER_POLL: usize = 100;
+
+/// Check the inclusion of the confirmed transactions which weren't verified yet.
+pub fn verify(bit: &impl BitcoinInterface, db_conn: &mut Box<dyn DatabaseConnection>) {
+    for (txid, height) in db_conn
+        .unverified_transactions()
+        .into_iter()
+        .take(MAX_CHECKS_PER_POLL)
+    {
+        match bit.check_inclusion(&txid, height) {
+            // It's the same for all transactions.
+            InclusionCheck::NotNeeded => return,
+            InclusionCheck::Verified => {
+                log::debug!(
+                    "Verified inclusion of transaction '{}' at height {}.",
+                    txid,
+                    height
+                );
+                db_conn.record_tx_verification(&txid, height, None);
+            }
+            InclusionCheck::Invalid(e) => {
+                log::error!(
+                    "The Bitcoin backend claims transaction '{}' was confirmed at height {} but \
+                     its proof of inclusion is invalid: {}",
+                    txid,
+                    height,
+                    e
+                );
+                db_conn.record_tx_verification(&txid, height, Some(&e));
+            }
+            InclusionCheck::Unavailable(e) => {
+                log::warn!(
+                    "Could not check the inclusion of transaction '{}' at height {}: {}",
+                    txid,
+                    height,
+                    e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::{BlockInfo, Coin, DatabaseInterface},
+        testutils::*,
+    };
+    use miniscript::bitcoin::{self, bip32, hashes::Hash};
+    use std::collections::HashMap;
+
+    fn confirmed_coin(i: u8) -> Coin {
+        Coin {
+            outpoint: bitcoin::OutPoint::new(bitcoin::Txid::from_slice(&[i; 32]).unwrap(), 0),
+            is_immature: false,
+            block_info: Some(BlockInfo {
+                height: 100 + i as i32,
+                time: 1_700_000_000,
+            }),
+            amount: bitcoin::Amount::from_sat(100_000),
+            derivation_index: bip32::ChildNumber::from_normal_idx(i as u32).unwrap(),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        }
+    }
+
+    #[test]
+    fn verify_confirmed_transactions() {
+        let (coin_a, coin_b, coin_c) = (confirmed_coin(1), confirmed_coin(2), confirmed_coin(3));
+        let (txid_a, txid_b, txid_c) = (
+            coin_a.outpoint.txid,
+            coin_b.outpoint.txid,
+            coin_c.outpoint.txid,
+        );
+        let mut db = DummyDatabase::new();
+        db.insert_coins(vec![coin_a, coin_b, coin_c]);
+        let mut db_conn = db.connection();
+
+        // The transactions are never checked with a backend which validates the chain.
+        verify(&DummyBitcoind::new(), &mut db_conn);
+        assert!(db_conn.tx_verification(&txid_a).is_none());
+
+        // The proof of the third transaction can't be obtained, it'll be checked again.
+        let mut bit = DummyBitcoind::new();
+        bit.inclusion_proofs = Some(HashMap::from([(txid_a, true), (txid_b, false)]));
+        verify(&bit, &mut db_conn);
+        let verif_a = db_conn.tx_verification(&txid_a).unwrap();
+        assert_eq!((verif_a.block_height, verif_a.error), (101, None));
+        assert!(db_conn.tx_verification(&txid_b).unwrap().error.is_some());
+        assert!(db_conn.tx_verification(&txid_c).is_none());
+        assert_eq!(db_conn.unverified_transactions(), vec![(txid_c, 103)]);
+    }
+}
@@ -0,0 +1,210 @@
+//! Broadcast the scheduled Spend transactions once their locktime is reached.
+//!
+//! A scheduled Spend is fully signed with a locktime in the future. We hold it until it can be
+//! included in the next block, broadcast it and hand it over to the broadcast journal. If one of
+//! the coins it spends gets spent by another transaction in the meantime it can't ever be valid,
+//! so it's dropped and the user alerted.
+
+use super::watchtower::{timelocks_matured, ChainState};
+use crate::{
+    bitcoin::BitcoinInterface,
+    database::{Coin, DatabaseConnection},
+    events::{Event, Events},
+};
+
+use std::{collections::HashMap, sync};
+
+use miniscript::bitcoin;
+
+/// What to do with a scheduled Spend transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ScheduledAction {
+    /// Its locktime isn't reached yet.
+    Wait,
+    /// It can be included in the next block.
+    Broadcast,
+    /// It's already spending our coins.
+    Remove,
+    /// These coins it spends were spent by another transaction.
+    Conflicted(Vec<bitcoin::OutPoint>),
+}
+
+// Decide what to do with this scheduled transaction given the coins of ours it spends.
+fn scheduled_action(
+    tx: &bitcoin::Transaction,
+    coins: &HashMap<bitcoin::OutPoint, Coin>,
+    chain: ChainState,
+) -> ScheduledAction {
+    let txid = tx.compute_txid();
+    if coins.values().any(|c| c.spend_txid == Some(txid)) {
+        return ScheduledAction::Remove;
+    }
+    let mut conflicted: Vec<_> = tx
+        .input
+        .iter()
+        .map(|txin| txin.previous_output)
+        .filter(|op| {
+            coins
+                .get(op)
+                .map(|c| c.spend_txid.is_some())
+                .unwrap_or(true)
+        })
+        .collect();
+    if !conflicted.is_empty() {
+        conflicted.sort();
+        return ScheduledAction::Conflicted(conflicted);
+    }
+    if timelocks_matured(tx, coins, chain) {
+        ScheduledAction::Broadcast
+    } else {
+        ScheduledAction::Wait
+    }
+}
+
+/// Broadcast the scheduled Spend transactions whose locktime is reached and drop those which were
+/// conflicted. This is to be called after each update of our state from the Bitcoin backend.
+pub fn check(
+    bit: &impl BitcoinInterface,
+    db_conn: &mut Box<dyn DatabaseConnection>,
+    events: &sync::Arc<sync::Mutex<Events>>,
+) {
+    let chain = ChainState {
+        tip_height: match db_conn.chain_tip() {
+            Some(tip) => tip.height,
+            None => return,
+        },
+        tip_time: bit.tip_time(),
+    };
+
+    for scheduled in db_conn.scheduled_spends() {
+        let tx = scheduled.tx;
+        let txid = tx.compute_txid();
+        let outpoints: Vec<_> = tx.input.iter().map(|i| i.previous_output).collect();
+        let coins = db_conn.coins(&[], &outpoints);
+
+        match scheduled_action(&tx, &coins, chain) {
+            ScheduledAction::Wait => {}
+            ScheduledAction::Remove => {
+                log::debug!("Scheduled Spend '{}' was already broadcast.", txid);
+                db_conn.unschedule_spend(&txid);
+            }
+            ScheduledAction::Conflicted(coins) => {
+                db_conn.unschedule_spend(&txid);
+                events
+                    .lock()
+                    .unwrap()
+                    .push(Event::ScheduledSpendConflicted { txid, coins });
+            }
+            ScheduledAction::Broadcast => {
+                log::info!(
+                    "Locktime of scheduled Spend '{}' reached, broadcasting it.",
+                    txid
+                );
+                // From now on the broadcast journal takes care of it.
+                db_conn.record_broadcast(&tx);
+                match bit.broadcast_tx(&tx) {
+                    Ok(()) => {
+                        db_conn.unschedule_spend(&txid);
+                        events
+                            .lock()
+                            .unwrap()
+                            .push(Event::ScheduledSpendBroadcast { txid });
+                    }
+                    Err(e) => {
+                        // It may be the backend's clock being late compared to ours. Try again at
+                        // the next poll.
+                        log::error!("Error broadcasting scheduled Spend '{}': {}", txid, e);
+                        db_conn.remove_broadcast(&txid);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::BlockInfo;
+    use miniscript::bitcoin::{absolute, bip32, hashes::Hash, transaction, Amount, Sequence, TxIn};
+
+    fn coin(vout: u32, spend_txid: Option<bitcoin::Txid>) -> Coin {
+        Coin {
+            outpoint: bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), vout),
+            is_immature: false,
+            block_info: Some(BlockInfo {
+                height: 100,
+                time: 1_700_000_000,
+            }),
+            amount: Amount::from_sat(100_000),
+            derivation_index: bip32::ChildNumber::from_normal_idx(0).unwrap(),
+            is_change: false,
+            spend_txid,
+            spend_block: None,
+            is_from_self: false,
+        }
+    }
+
+    #[test]
+    fn scheduled_actions() {
+        let tx = bitcoin::Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::from_height(1_000).unwrap(),
+            input: (0..2)
+                .map(|vout| TxIn {
+                    previous_output: bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), vout),
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    ..TxIn::default()
+                })
+                .collect(),
+            output: Vec::new(),
+        };
+        let txid = tx.compute_txid();
+        let other_txid = bitcoin::Txid::from_slice(&[2; 32]).unwrap();
+        let coins = |spend_txids: &[Option<bitcoin::Txid>]| {
+            spend_txids
+                .iter()
+                .enumerate()
+                .map(|(i, spend_txid)| {
+                    let coin = coin(i as u32, *spend_txid);
+                    (coin.outpoint, coin)
+                })
+                .collect::<HashMap<_, _>>()
+        };
+        let chain = |tip_height| ChainState {
+            tip_height,
+            tip_time: Some(1_700_000_000),
+        };
+
+        // The locktime must be reached for the transaction to be included in the next block.
+        assert_eq!(
+            scheduled_action(&tx, &coins(&[None, None]), chain(998)),
+            ScheduledAction::Wait
+        );
+        assert_eq!(
+            scheduled_action(&tx, &coins(&[None, None]), chain(999)),
+            ScheduledAction::Broadcast
+        );
+        // Already broadcast.
+        assert_eq!(
+            scheduled_action(&tx, &coins(&[Some(txid), Some(txid)]), chain(1_000)),
+            ScheduledAction::Remove
+        );
+        // One of its coins was spent by another transaction, even before the locktime.
+        assert_eq!(
+            scheduled_action(&tx, &coins(&[None, Some(other_txid)]), chain(998)),
+            ScheduledAction::Conflicted(vec![bitcoin::OutPoint::new(
+                bitcoin::Txid::all_zeros(),
+                1
+            )])
+        );
+        // We don't know about one of its coins anymore.
+        assert_eq!(
+            scheduled_action(&tx, &coins(&[None]), chain(999)),
+            ScheduledAction::Conflicted(vec![bitcoin::OutPoint::new(
+                bitcoin::Txid::all_zeros(),
+                1
+            )])
+        );
+    }
+}
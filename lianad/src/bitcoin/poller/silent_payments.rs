@@ -0,0 +1,253 @@
+//! Scan the new blocks for Silent Payments (BIP352) to the wallet.
+//!
+//! Silent Payments can't be watched for like the addresses of our descriptors: the output paying
+//! us in a transaction depends on a secret shared with the keys of its inputs. Once enabled, we
+//! fetch each new block along with the previous outputs spent by its transactions and compute this
+//! secret for each of them with our scan key.
+
+use crate::{
+    bitcoin::BitcoinInterface,
+    database::{BlockInfo, DatabaseConnection, SilentPayment},
+};
+
+use std::{cmp, collections::HashSet};
+
+use miniscript::bitcoin::{self, secp256k1};
+
+/// Maximum number of blocks to scan at each poll, not to stall it when catching up.
+const MAX_BLOCKS_PER_POLL: i32 = 10;
+
+/// Scan the blocks of our chain which weren't yet, if Silent Payments were enabled.
+pub fn scan(
+    bit: &impl BitcoinInterface,
+    db_conn: &mut Box<dyn DatabaseConnection>,
+    secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+) {
+    let keys = match db_conn.silent_payment_keys() {
+        Some(keys) => keys,
+        None => return,
+    };
+    let tip = match db_conn.chain_tip() {
+        Some(tip) => tip,
+        None => return,
+    };
+    let payments = db_conn.silent_payments();
+    let mut unspent: HashSet<bitcoin::OutPoint> = payments
+        .iter()
+        .filter(|p| p.spend_block.is_none())
+        .map(|p| p.outpoint)
+        .collect();
+
+    let last_height = cmp::min(tip.height, keys.scanned_height + MAX_BLOCKS_PER_POLL);
+    for height in keys.scanned_height + 1..=last_height {
+        let block = match bit.block_transactions(height) {
+            Some(block) => block,
+            None => {
+                log::warn!(
+                    "Could not get the transactions of the block at height {} to scan them for \
+                     Silent Payments.",
+                    height
+                );
+                return;
+            }
+        };
+        // The chain moved under us. The tip will be rolled back at the next poll.
+        if height == tip.height && block.block.hash != tip.hash {
+            return;
+        }
+        let block_info = BlockInfo {
+            height,
+            time: block.block.time,
+        };
+
+        let (mut found, mut spent) = (Vec::new(), Vec::new());
+        for (tx, prevouts) in &block.txs {
+            let txid = tx.compute_txid();
+            for txin in &tx.input {
+                if unspent.remove(&txin.previous_output) {
+                    spent.push((txin.previous_output, txid));
+                }
+            }
+            for payment in liana::silent_payments::scan_transaction(
+                secp,
+                &keys.scan_key,
+                &keys.spend_key,
+                tx,
+                prevouts,
+            ) {
+                let outpoint = bitcoin::OutPoint::new(txid, payment.vout);
+                let amount = tx.output[payment.vout as usize].value;
+                log::info!("Found Silent Payment '{}' of {}.", outpoint, amount);
+                found.push(SilentPayment {
+                    outpoint,
+                    amount,
+                    tweak: payment.tweak,
+                    block: block_info,
+                    spend_txid: None,
+                    spend_block: None,
+                });
+                unspent.insert(outpoint);
+            }
+        }
+        db_conn.record_silent_payment_scan(block_info, &found, &spent);
+    }
+
+    // A sweep which didn't confirm and left the mempool won't. Once we caught up with the chain
+    // make the payments it spent available again.
+    if last_height < tip.height {
+        return;
+    }
+    let dropped: Vec<_> = db_conn
+        .silent_payments()
+        .into_iter()
+        .filter(|p| p.spend_block.is_none())
+        .filter(|p| {
+            p.spend_txid
+                .map(|txid| bit.mempool_entry(&txid).is_none())
+                .unwrap_or(false)
+        })
+        .map(|p| p.outpoint)
+        .collect();
+    if !dropped.is_empty() {
+        log::info!(
+            "The sweep of Silent Payments {:?} was dropped from the mempool.",
+            dropped
+        );
+        db_conn.spend_silent_payments(&dropped, None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bitcoin::{Block, BlockChainTip, BlockTransactions},
+        database::{DatabaseInterface, SilentPaymentKeys},
+        testutils::*,
+    };
+    use miniscript::bitcoin::{
+        absolute, bip32, hashes::Hash, transaction, Amount, ScriptBuf, Sequence, Transaction, TxIn,
+        TxOut, Witness,
+    };
+    use std::collections::HashMap;
+
+    fn block(height: i32, txs: Vec<(Transaction, Vec<TxOut>)>) -> BlockTransactions {
+        BlockTransactions {
+            block: Block {
+                hash: bitcoin::BlockHash::from_slice(&[height as u8; 32]).unwrap(),
+                height,
+                time: 1_700_000_000 + height as u32,
+            },
+            txs,
+        }
+    }
+
+    #[test]
+    fn scan_blocks() {
+        let secp = secp256k1::Secp256k1::new();
+        let scan_key = secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+        let spend_secret = secp256k1::SecretKey::from_slice(&[2; 32]).unwrap();
+        let spend_key = spend_secret.public_key(&secp);
+
+        // A transaction spending a P2WPKH output and paying to our address.
+        let input_secret = secp256k1::SecretKey::from_slice(&[3; 32]).unwrap();
+        let input_key = bitcoin::PublicKey::new(input_secret.public_key(&secp));
+        let prevout = TxOut {
+            value: Amount::from_sat(200_000),
+            script_pubkey: ScriptBuf::new_p2wpkh(&input_key.wpubkey_hash().unwrap()),
+        };
+        let mut witness = Witness::new();
+        witness.push([0; 71]);
+        witness.push(input_key.to_bytes());
+        let mut tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: bitcoin::OutPoint::new(
+                    bitcoin::Txid::from_slice(&[4; 32]).unwrap(),
+                    0,
+                ),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness,
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(190_000),
+                script_pubkey: ScriptBuf::new_p2tr_tweaked(
+                    bitcoin::key::TweakedPublicKey::dangerous_assume_tweaked(
+                        spend_key.x_only_public_key().0,
+                    ),
+                ),
+            }],
+        };
+        let shared =
+            liana::silent_payments::shared_secret(&secp, &scan_key, &tx, &[prevout.clone()])
+                .unwrap();
+        let tweak = liana::silent_payments::output_tweak(&shared, 0).unwrap();
+        tx.output[0].script_pubkey =
+            liana::silent_payments::output_script_pubkey(&secp, &spend_key, &tweak).unwrap();
+        let outpoint = bitcoin::OutPoint::new(tx.compute_txid(), 0);
+
+        // A transaction spending it.
+        let sweep = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: outpoint,
+                ..TxIn::default()
+            }],
+            output: vec![],
+        };
+
+        let db = DummyDatabase::new();
+        let mut db_conn = db.connection();
+        let mut bit = DummyBitcoind::new();
+        bit.blocks = Some(HashMap::from([
+            (101, block(101, vec![(tx.clone(), vec![prevout])])),
+            (102, block(102, vec![])),
+            (
+                103,
+                block(103, vec![(sweep.clone(), vec![tx.output[0].clone()])]),
+            ),
+        ]));
+
+        // Nothing happens before Silent Payments are enabled.
+        db_conn.update_tip(&BlockChainTip {
+            hash: bitcoin::BlockHash::from_slice(&[102; 32]).unwrap(),
+            height: 102,
+        });
+        scan(&bit, &mut db_conn, &secp);
+        assert!(db_conn.silent_payments().is_empty());
+
+        // The payment is found in the first block after the scanned height.
+        db_conn.store_silent_payment_keys(&SilentPaymentKeys {
+            fingerprint: bip32::Fingerprint::default(),
+            scan_key,
+            spend_key,
+            scanned_height: 100,
+        });
+        scan(&bit, &mut db_conn, &secp);
+        let payments = db_conn.silent_payments();
+        assert_eq!(payments.len(), 1);
+        assert_eq!(payments[0].outpoint, outpoint);
+        assert_eq!(payments[0].amount, Amount::from_sat(190_000));
+        assert_eq!(payments[0].tweak, tweak);
+        assert_eq!(payments[0].block.height, 101);
+        assert_eq!(db_conn.silent_payment_keys().unwrap().scanned_height, 102);
+
+        // A sweep which left the mempool is forgotten.
+        db_conn.spend_silent_payments(&[outpoint], Some(&sweep.compute_txid()));
+        scan(&bit, &mut db_conn, &secp);
+        assert!(db_conn.silent_payments()[0].spend_txid.is_none());
+
+        // Its spend is detected in the next block.
+        db_conn.update_tip(&BlockChainTip {
+            hash: bitcoin::BlockHash::from_slice(&[103; 32]).unwrap(),
+            height: 103,
+        });
+        scan(&bit, &mut db_conn, &secp);
+        let payment = db_conn.silent_payments()[0];
+        assert_eq!(payment.spend_txid, Some(sweep.compute_txid()));
+        assert_eq!(payment.spend_block.unwrap().height, 103);
+    }
+}
@@ -0,0 +1,226 @@
+//! Detect when the chain tip stops advancing, or when the system clock is off.
+//!
+//! The timelocks and the availability of the recovery paths are computed from the tip of the chain
+//! and from the current time. A backend stuck on an old tip, or a system clock far from the time of
+//! the blocks, make them wrong. An alert is raised when the tip didn't advance for an abnormal
+//! period of time or when the system time deviates significantly from the time of the latest block.
+
+use crate::{
+    bitcoin::{BitcoinInterface, BlockChainTip},
+    database::DatabaseConnection,
+    events::{Event, Events},
+};
+
+use std::{sync, time};
+
+use miniscript::bitcoin;
+
+/// The tip is considered stale if it didn't advance for this long. With a block every 10 minutes
+/// on average, a two hours gap between blocks happens about once every three years.
+const STALE_TIP_AFTER: time::Duration = time::Duration::from_secs(2 * 60 * 60);
+
+/// Nodes accept blocks with a timestamp up to two hours in the future of their own time. A larger
+/// difference between the time of a new block and the system time means the clock is off.
+const MAX_CLOCK_SKEW_SECS: i64 = 2 * 60 * 60;
+
+// A tip of the chain, as we first saw it.
+struct ObservedTip {
+    tip: BlockChainTip,
+    // When we first saw it.
+    seen_at: time::Instant,
+    // The timestamp of its block, if the backend could tell.
+    time: Option<u32>,
+    // Whether we saw it replace a previous tip. The first tip we see may be an old one.
+    is_new: bool,
+}
+
+/// Raises events when the chain tip didn't advance for too long and when the system clock
+/// deviates from the time of the blocks, and again once back to normal.
+pub struct TipMonitor {
+    tip: Option<ObservedTip>,
+    // Whether we warned about a stale tip.
+    stale: bool,
+    // Whether we warned about the system clock.
+    clock_skewed: bool,
+}
+
+impl TipMonitor {
+    /// Returns `None` on networks where blocks aren't expected to be found at a regular interval.
+    pub fn new(network: bitcoin::Network) -> Option<TipMonitor> {
+        if network == bitcoin::Network::Regtest {
+            return None;
+        }
+        Some(TipMonitor {
+            tip: None,
+            stale: false,
+            clock_skewed: false,
+        })
+    }
+
+    // Record the current tip, getting the time of its block if it's a new one. Returns the events
+    // to be raised.
+    fn update(
+        &mut self,
+        tip: BlockChainTip,
+        tip_time: impl FnOnce() -> Option<u32>,
+        now: time::Instant,
+        system_time: u32,
+    ) -> Vec<Event> {
+        let mut events = Vec::new();
+
+        if self.tip.as_ref().map(|t| t.tip != tip).unwrap_or(true) {
+            self.tip = Some(ObservedTip {
+                tip,
+                seen_at: now,
+                time: tip_time(),
+                is_new: self.tip.is_some(),
+            });
+            if self.stale {
+                self.stale = false;
+                events.push(Event::TipAdvanced { height: tip.height });
+            }
+        }
+        let observed = self.tip.as_ref().expect("Just set if it wasn't");
+
+        let unchanged_for = now.saturating_duration_since(observed.seen_at);
+        if !self.stale && unchanged_for >= STALE_TIP_AFTER {
+            self.stale = true;
+            events.push(Event::StaleTip {
+                height: tip.height,
+                minutes: unchanged_for.as_secs() / 60,
+            });
+        }
+
+        if let Some(block_time) = observed.time {
+            // Compare the time of the block to the system time when we saw it. The system time is
+            // taken anew each time so a clock put back on time is noticed right away.
+            let seen_time = i64::from(system_time) - unchanged_for.as_secs() as i64;
+            let skew = seen_time - i64::from(block_time);
+            // A block can't be too far in the future of the nodes' time. It may be old if it's
+            // the first one we see, but it should be recent if we just saw it being found.
+            let skewed =
+                skew < -MAX_CLOCK_SKEW_SECS || (observed.is_new && skew > MAX_CLOCK_SKEW_SECS);
+            if skewed && !self.clock_skewed {
+                events.push(Event::ClockSkewed { skew_secs: skew });
+            } else if !skewed && self.clock_skewed {
+                events.push(Event::ClockSynced);
+            }
+            self.clock_skewed = skewed;
+        }
+
+        events
+    }
+
+    /// Check the tip of the chain after it was updated from the Bitcoin backend.
+    pub fn check(
+        &mut self,
+        bit: &impl BitcoinInterface,
+        db_conn: &mut Box<dyn DatabaseConnection>,
+        events: &sync::Arc<sync::Mutex<Events>>,
+    ) {
+        let tip = match db_conn.chain_tip() {
+            Some(tip) => tip,
+            None => return,
+        };
+        let system_time = time::SystemTime::now()
+            .duration_since(time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as u32)
+            .unwrap_or(0);
+        let new_events = self.update(tip, || bit.tip_time(), time::Instant::now(), system_time);
+        if !new_events.is_empty() {
+            let mut events = events.lock().unwrap();
+            for event in new_events {
+                events.push(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miniscript::bitcoin::hashes::Hash;
+
+    fn tip(height: i32) -> BlockChainTip {
+        BlockChainTip {
+            height,
+            hash: bitcoin::BlockHash::from_slice(&[height as u8; 32]).unwrap(),
+        }
+    }
+
+    #[test]
+    fn stale_tip_and_clock_skew() {
+        let mut monitor = TipMonitor::new(bitcoin::Network::Bitcoin).unwrap();
+        assert!(TipMonitor::new(bitcoin::Network::Regtest).is_none());
+        let start = time::Instant::now();
+        let at = |secs: u64| start + time::Duration::from_secs(secs);
+        let system_time = 1_700_000_000;
+
+        // The first tip may be old, that's not a skewed clock.
+        assert!(monitor
+            .update(
+                tip(100),
+                || Some(system_time - 3 * 60 * 60),
+                at(0),
+                system_time
+            )
+            .is_empty());
+
+        // A new block from the right time, then none for two hours.
+        assert!(monitor
+            .update(
+                tip(101),
+                || Some(system_time + 60),
+                at(60),
+                system_time + 60
+            )
+            .is_empty());
+        assert!(monitor
+            .update(tip(101), || unreachable!(), at(3_600), system_time + 3_600)
+            .is_empty());
+        assert_eq!(
+            monitor.update(tip(101), || unreachable!(), at(7_300), system_time + 7_300),
+            vec![Event::StaleTip {
+                height: 101,
+                minutes: 120
+            }]
+        );
+        assert!(monitor
+            .update(tip(101), || unreachable!(), at(9_000), system_time + 9_000)
+            .is_empty());
+
+        // It advances again, but the block is from three hours before the system time.
+        assert_eq!(
+            monitor.update(
+                tip(102),
+                || Some(system_time - 1_800),
+                at(9_600),
+                system_time + 9_600
+            ),
+            vec![
+                Event::TipAdvanced { height: 102 },
+                Event::ClockSkewed { skew_secs: 11_400 }
+            ]
+        );
+        assert!(monitor
+            .update(tip(102), || unreachable!(), at(9_700), system_time + 9_700)
+            .is_empty());
+
+        // The clock is put back on time.
+        assert_eq!(
+            monitor.update(tip(102), || unreachable!(), at(9_800), system_time - 1_600),
+            vec![Event::ClockSynced]
+        );
+
+        // A block too far in the future of the system time.
+        assert_eq!(
+            monitor.update(
+                tip(103),
+                || Some(system_time + 3 * 60 * 60),
+                at(9_900),
+                system_time
+            ),
+            vec![Event::ClockSkewed { skew_secs: -10_800 }]
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! When to poll next.
+//!
+//! A poll may take longer than the poll interval with a slow backend. The polls which would have
+//! happened in the meantime are skipped instead of being performed back to back. While the backend
+//! is unreachable the interval between polls is doubled at each failure, with some jitter for the
+//! instances sharing a backend not to hammer it at the same time once it comes back.
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync, time,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// The interval between polls is never increased beyond this when backing off, unless the
+/// configured interval is already longer.
+const MAX_BACKOFF: time::Duration = time::Duration::from_secs(10 * 60);
+
+/// By how much, in percents, a backoff delay may be randomly shortened or lengthened.
+const JITTER_PERCENT: u64 = 20;
+
+/// Statistics about the polls performed since startup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PollStats {
+    /// Number of polls performed.
+    pub polls: u64,
+    /// Duration of the last poll, in milliseconds.
+    pub last_duration_ms: Option<u64>,
+    /// Average duration of a poll, in milliseconds.
+    pub average_duration_ms: Option<u64>,
+    /// Duration of the longest poll, in milliseconds.
+    pub max_duration_ms: Option<u64>,
+    /// Number of polls skipped because the previous one was still running when they were due.
+    pub skipped_polls: u64,
+    /// Number of polls in a row which failed to reach the Bitcoin backend.
+    pub consecutive_failures: u32,
+    /// Delay between the start of the last poll and the next one, in milliseconds. Longer than
+    /// the poll interval when backing off.
+    pub next_poll_delay_ms: u64,
+    #[serde(skip)]
+    total_duration_ms: u64,
+}
+
+// A random factor in percents to apply to a delay.
+fn jitter() -> u64 {
+    let random = RandomState::new().build_hasher().finish();
+    100 - JITTER_PERCENT + random % (2 * JITTER_PERCENT + 1)
+}
+
+// The delay before polling again after the backend failed this many times in a row.
+fn backoff(interval: time::Duration, failures: u32, jitter_percent: u64) -> time::Duration {
+    let max = MAX_BACKOFF.max(interval);
+    let delay = interval
+        .checked_mul(2u32.saturating_pow(failures))
+        .unwrap_or(max)
+        .min(max);
+    delay * jitter_percent as u32 / 100
+}
+
+/// Decides when to poll next, and keeps the statistics about the polls.
+#[derive(Default)]
+pub struct PollScheduler {
+    stats: sync::Arc<sync::Mutex<PollStats>>,
+}
+
+impl PollScheduler {
+    /// The statistics, updated after each poll.
+    pub fn stats(&self) -> sync::Arc<sync::Mutex<PollStats>> {
+        self.stats.clone()
+    }
+
+    /// Record a poll which took this long, and whether it failed to reach the backend. Returns
+    /// the delay between the start of this poll and the next one.
+    pub fn record(
+        &mut self,
+        duration: time::Duration,
+        failed: bool,
+        interval: time::Duration,
+    ) -> time::Duration {
+        self.schedule(duration, failed, interval, jitter())
+    }
+
+    fn schedule(
+        &mut self,
+        duration: time::Duration,
+        failed: bool,
+        interval: time::Duration,
+        jitter_percent: u64,
+    ) -> time::Duration {
+        let mut stats = self.stats.lock().unwrap();
+        let duration_ms = duration.as_millis() as u64;
+        stats.polls += 1;
+        stats.total_duration_ms = stats.total_duration_ms.saturating_add(duration_ms);
+        stats.last_duration_ms = Some(duration_ms);
+        stats.average_duration_ms = Some(stats.total_duration_ms / stats.polls);
+        stats.max_duration_ms = Some(stats.max_duration_ms.unwrap_or(0).max(duration_ms));
+
+        let delay = if failed {
+            stats.consecutive_failures = stats.consecutive_failures.saturating_add(1);
+            duration + backoff(interval, stats.consecutive_failures, jitter_percent)
+        } else {
+            stats.consecutive_failures = 0;
+            if interval.is_zero() || duration < interval {
+                interval.max(duration)
+            } else {
+                // Skip the polls which were due while this one was running, and keep polling at
+                // a multiple of the interval from the start of this one.
+                let missed = (duration.as_nanos() / interval.as_nanos()) as u32;
+                stats.skipped_polls += u64::from(missed);
+                log::warn!(
+                    "Poll took {}ms, longer than the poll interval. Skipping {} poll(s).",
+                    duration_ms,
+                    missed
+                );
+                interval * (missed + 1)
+            }
+        };
+        stats.next_poll_delay_ms = delay.as_millis() as u64;
+        delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poll_schedule() {
+        let interval = time::Duration::from_secs(30);
+        let secs = time::Duration::from_secs;
+        let mut scheduler = PollScheduler::default();
+
+        // A poll shorter than the interval, the next one is an interval after its start.
+        assert_eq!(scheduler.schedule(secs(2), false, interval, 100), interval);
+
+        // A poll longer than the interval, the polls due in the meantime are skipped.
+        assert_eq!(scheduler.schedule(secs(65), false, interval, 100), secs(90));
+        let stats = *scheduler.stats().lock().unwrap();
+        assert_eq!(stats.skipped_polls, 2);
+        assert_eq!(stats.polls, 2);
+        assert_eq!(stats.max_duration_ms, Some(65_000));
+        assert_eq!(stats.average_duration_ms, Some(33_500));
+        assert_eq!(stats.next_poll_delay_ms, 90_000);
+
+        // Backing off exponentially on failures, up to the maximum.
+        assert_eq!(scheduler.schedule(secs(1), true, interval, 100), secs(61));
+        assert_eq!(scheduler.schedule(secs(1), true, interval, 100), secs(121));
+        assert_eq!(scheduler.schedule(secs(1), true, interval, 120), secs(289));
+        for _ in 0..40 {
+            scheduler.schedule(secs(1), true, interval, 100);
+        }
+        assert_eq!(
+            scheduler.schedule(secs(1), true, interval, 100),
+            MAX_BACKOFF + secs(1)
+        );
+        assert_eq!(scheduler.stats().lock().unwrap().consecutive_failures, 44);
+
+        // Back to the regular interval once the backend is reachable again.
+        assert_eq!(scheduler.schedule(secs(1), false, interval, 100), interval);
+        assert_eq!(scheduler.stats().lock().unwrap().consecutive_failures, 0);
+
+        // The jitter stays within bounds.
+        for _ in 0..100 {
+            let j = jitter();
+            assert!((100 - JITTER_PERCENT..=100 + JITTER_PERCENT).contains(&j));
+        }
+    }
+}
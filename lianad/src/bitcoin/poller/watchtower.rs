@@ -0,0 +1,259 @@
+//! Broadcast pre-signed transactions spending our coins when some conditions are met.
+//!
+//! This allows running an unattended guardian which holds no key: for instance to broadcast a
+//! recovery transaction as soon as its timelock matured, or to try to replace a spend which was
+//! not expected by a pre-signed transaction sending the coins to a safe place.
+
+use crate::{
+    bitcoin::BitcoinInterface,
+    config::{WatchtowerConfig, WatchtowerTransaction, WatchtowerTrigger},
+    database::{Coin, DatabaseConnection},
+};
+
+use std::collections::HashMap;
+
+use miniscript::bitcoin::{self, absolute, relative};
+
+/// The state of the chain against which to check the trigger conditions.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ChainState {
+    pub tip_height: i32,
+    pub tip_time: Option<u32>,
+}
+
+// Whether the transaction could be included in the next block as far as the timelocks of the
+// given coins it spends and its own locktime are concerned.
+pub(crate) fn timelocks_matured(
+    tx: &bitcoin::Transaction,
+    coins: &HashMap<bitcoin::OutPoint, Coin>,
+    chain: ChainState,
+) -> bool {
+    let next_height = chain.tip_height + 1;
+    for txin in &tx.input {
+        let coin = match coins.get(&txin.previous_output) {
+            Some(coin) => coin,
+            // Not one of our coins. We don't know when it was confirmed.
+            None => continue,
+        };
+        let block = match coin.block_info {
+            Some(block) if !coin.is_immature => block,
+            _ => return false,
+        };
+        let matured = match txin.sequence.to_relative_lock_time() {
+            None => true,
+            Some(relative::LockTime::Blocks(blocks)) => {
+                next_height - block.height >= blocks.value() as i32
+            }
+            Some(relative::LockTime::Time(time)) => chain
+                .tip_time
+                .map(|tip_time| tip_time.saturating_sub(block.time) >= time.value() as u32 * 512)
+                .unwrap_or(false),
+        };
+        if !matured {
+            return false;
+        }
+    }
+
+    if !tx.is_lock_time_enabled() {
+        return true;
+    }
+    match tx.lock_time {
+        absolute::LockTime::Blocks(height) => height.to_consensus_u32() < next_height as u32,
+        absolute::LockTime::Seconds(time) => chain
+            .tip_time
+            .map(|tip_time| time.to_consensus_u32() < tip_time)
+            .unwrap_or(false),
+    }
+}
+
+// Get the first of the trigger conditions of this transaction which is met, if any. The coins
+// are the ones of the wallet spent by this transaction.
+fn met_trigger(
+    wt_tx: &WatchtowerTransaction,
+    coins: &HashMap<bitcoin::OutPoint, Coin>,
+    chain: ChainState,
+) -> Option<WatchtowerTrigger> {
+    let txid = wt_tx.tx.compute_txid();
+    let spent_by_other = coins
+        .values()
+        .any(|c| c.spend_txid.is_some() && c.spend_txid != Some(txid));
+    wt_tx
+        .triggers
+        .iter()
+        .copied()
+        .find(|trigger| match trigger {
+            WatchtowerTrigger::TimelockMaturity => {
+                !spent_by_other && timelocks_matured(&wt_tx.tx, coins, chain)
+            }
+            WatchtowerTrigger::UnauthorizedSpend => spent_by_other,
+        })
+}
+
+/// Holds the pre-signed transactions and broadcasts them when their trigger conditions are met.
+pub struct Watchtower {
+    transactions: Vec<WatchtowerTransaction>,
+}
+
+impl Watchtower {
+    pub fn new(config: WatchtowerConfig) -> Watchtower {
+        log::info!(
+            "Watchtower holding {} pre-signed transaction(s).",
+            config.transactions.len()
+        );
+        Watchtower {
+            transactions: config.transactions,
+        }
+    }
+
+    /// Broadcast the transactions whose trigger conditions are met. This is to be called after
+    /// each update of our state from the Bitcoin backend. A triggered transaction is broadcast
+    /// again on each call until it is seen spending our coins.
+    pub fn check(&self, bit: &impl BitcoinInterface, db_conn: &mut Box<dyn DatabaseConnection>) {
+        let chain = ChainState {
+            tip_height: match db_conn.chain_tip() {
+                Some(tip) => tip.height,
+                None => return,
+            },
+            tip_time: bit.tip_time(),
+        };
+
+        for wt_tx in &self.transactions {
+            let txid = wt_tx.tx.compute_txid();
+            let outpoints: Vec<_> = wt_tx.tx.input.iter().map(|i| i.previous_output).collect();
+            let coins = db_conn.coins(&[], &outpoints);
+            if coins.is_empty() {
+                log::debug!("Watchtower: no coin of ours spent by '{}' yet.", txid);
+                continue;
+            }
+            if coins.values().any(|c| c.spend_txid == Some(txid)) {
+                log::debug!("Watchtower: '{}' was already broadcast.", txid);
+                continue;
+            }
+            if coins.values().any(|c| c.spend_block.is_some()) {
+                log::debug!(
+                    "Watchtower: '{}' is invalid, a coin it spends was already spent.",
+                    txid
+                );
+                continue;
+            }
+
+            if let Some(trigger) = met_trigger(wt_tx, &coins, chain) {
+                log::warn!(
+                    "Watchtower: broadcasting transaction '{}' upon {}.",
+                    txid,
+                    trigger
+                );
+                if let Err(e) = bit.broadcast_tx(&wt_tx.tx) {
+                    log::error!("Watchtower: error broadcasting '{}': {}", txid, e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::BlockInfo;
+    use miniscript::bitcoin::{bip32, hashes::Hash, transaction, Amount, Sequence, TxIn};
+
+    fn coin(outpoint: bitcoin::OutPoint, height: Option<i32>) -> Coin {
+        Coin {
+            outpoint,
+            is_immature: false,
+            block_info: height.map(|height| BlockInfo {
+                height,
+                time: 1_700_000_000,
+            }),
+            amount: Amount::from_sat(100_000),
+            derivation_index: bip32::ChildNumber::from_normal_idx(0).unwrap(),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        }
+    }
+
+    fn wt_tx(
+        inputs: &[(bitcoin::OutPoint, u16)],
+        triggers: &[WatchtowerTrigger],
+    ) -> WatchtowerTransaction {
+        WatchtowerTransaction {
+            tx: bitcoin::Transaction {
+                version: transaction::Version::TWO,
+                lock_time: absolute::LockTime::ZERO,
+                input: inputs
+                    .iter()
+                    .map(|(outpoint, csv)| TxIn {
+                        previous_output: *outpoint,
+                        sequence: Sequence::from_height(*csv),
+                        ..TxIn::default()
+                    })
+                    .collect(),
+                output: Vec::new(),
+            },
+            triggers: triggers.to_vec(),
+        }
+    }
+
+    #[test]
+    fn watchtower_triggers() {
+        let op_a = bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), 0);
+        let op_b = bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), 1);
+        let chain = ChainState {
+            tip_height: 1_099,
+            tip_time: None,
+        };
+
+        // A recovery transaction spending two coins with a relative timelock of 100 blocks. It
+        // can be included in the next block once both coins have 100 confirmations.
+        let recovery = wt_tx(
+            &[(op_a, 100), (op_b, 100)],
+            &[WatchtowerTrigger::TimelockMaturity],
+        );
+        let mut coins: HashMap<_, _> =
+            vec![(op_a, coin(op_a, Some(1_000))), (op_b, coin(op_b, None))]
+                .into_iter()
+                .collect();
+        assert_eq!(met_trigger(&recovery, &coins, chain), None);
+        coins.insert(op_b, coin(op_b, Some(1_001)));
+        assert_eq!(met_trigger(&recovery, &coins, chain), None);
+        coins.insert(op_b, coin(op_b, Some(1_000)));
+        assert_eq!(
+            met_trigger(&recovery, &coins, chain),
+            Some(WatchtowerTrigger::TimelockMaturity)
+        );
+
+        // It is not broadcast if the coins are being spent by another transaction, unless this
+        // is one of its triggers.
+        let other_txid = bitcoin::Txid::from_byte_array([1; 32]);
+        coins.get_mut(&op_a).unwrap().spend_txid = Some(other_txid);
+        assert_eq!(met_trigger(&recovery, &coins, chain), None);
+        let mut panic_tx = recovery.clone();
+        panic_tx.triggers = vec![
+            WatchtowerTrigger::TimelockMaturity,
+            WatchtowerTrigger::UnauthorizedSpend,
+        ];
+        assert_eq!(
+            met_trigger(&panic_tx, &coins, chain),
+            Some(WatchtowerTrigger::UnauthorizedSpend)
+        );
+        // But it is not considered unauthorized if it's this transaction spending the coins.
+        coins.get_mut(&op_a).unwrap().spend_txid = Some(panic_tx.tx.compute_txid());
+        assert_eq!(
+            met_trigger(&panic_tx, &coins, chain),
+            Some(WatchtowerTrigger::TimelockMaturity)
+        );
+
+        // The absolute locktime is checked too.
+        let mut recovery = recovery;
+        recovery.tx.lock_time = absolute::LockTime::from_height(1_100).unwrap();
+        coins.get_mut(&op_a).unwrap().spend_txid = None;
+        assert_eq!(met_trigger(&recovery, &coins, chain), None);
+        recovery.tx.lock_time = absolute::LockTime::from_height(1_099).unwrap();
+        assert_eq!(
+            met_trigger(&recovery, &coins, chain),
+            Some(WatchtowerTrigger::TimelockMaturity)
+        );
+    }
+}
@@ -2,41 +2,60 @@
 //!
 //! External interface to the Liana daemon.
 
-mod utils;
+pub(crate) mod utils;
 
 use crate::{
-    bitcoin::BitcoinInterface,
-    database::{Coin, DatabaseConnection, DatabaseInterface},
+    bitcoin::{
+        poller::{timelocks_matured, ChainState},
+        BitcoinInterface, COINBASE_MATURITY,
+    },
+    database::{
+        sqlite::LOOK_AHEAD_LIMIT, Coin, DatabaseConnection, DatabaseInterface, SilentPaymentKeys,
+    },
+    events::{Event, EventEntry},
+    labels_csv::{self, RejectedRecord},
     miniscript::bitcoin::absolute::LockTime,
     poller::PollerMessage,
+    readiness::{ReadinessItem, RecoveryReadiness},
+    receipt::{self, Receipt, ReceiptOutput},
+    snapshot::{self, CoinsSnapshot},
+    statement::AddressStatement,
+    templates::SpendTemplate,
     DaemonControl, VERSION,
 };
 
-pub use crate::database::{CoinStatus, LabelItem};
+pub use crate::{
+    bitcoin::{poller::PollStats, FeeHistogramBucket},
+    database::{CoinStatus, LabelItem},
+    reviews::{ReviewDecision, ReviewState, SpendReview},
+};
 
 use liana::{
-    descriptors,
+    bip322, descriptors,
+    signer::{HotSigner, SignerError},
+    silent_payments::{self, SilentPaymentAddress},
     spend::{
-        self, create_spend, AddrInfo, AncestorInfo, CandidateCoin, CreateSpendRes,
-        SpendCreationError, SpendOutputAddress, SpendTxFees, TxGetter,
+        self, create_spend, AddrInfo, AncestorInfo, CandidateCoin, CreateSpendRes, OutputType,
+        SpendCreationError, SpendOutputAddress, SpendSighash, SpendTxFees, TxGetter,
     },
 };
 
 use utils::{
-    deser_addr_assume_checked, deser_amount_from_sats, deser_fromstr, deser_hex, ser_amount,
-    ser_hex, ser_to_string,
+    deser_addr_assume_checked, deser_amount_from_sats, deser_fromstr, deser_hex, deser_opt_fromstr,
+    ser_amount, ser_hex, ser_opt_to_string, ser_to_string,
 };
 
 use std::{
-    collections::{hash_map, HashMap, HashSet},
+    collections::{hash_map, BTreeMap, BTreeSet, HashMap, HashSet},
     convert::TryInto,
-    fmt,
+    fmt, io,
     sync::{self, mpsc},
     time::SystemTime,
 };
 
 use miniscript::{
-    bitcoin::{self, address, bip32, psbt::Psbt},
+    bitcoin::{self, address, base64::Engine, bip32, psbt::Psbt, secp256k1},
+    descriptor,
     psbt::PsbtExt,
 };
 use serde::{Deserialize, Serialize};
@@ -45,6 +64,11 @@ use serde::{Deserialize, Serialize};
 pub enum CommandError {
     NoOutpointForSelfSend,
     InvalidFeerate(/* sats/vb */ u64),
+    /// The feerate is below the minimum the Bitcoin backend's mempool accepts at the moment.
+    FeerateTooLow(/* sats/vb */ u64, /* minimum sats/vb */ u64),
+    /// Spending this unconfirmed coin would exceed the mempool limits on the chains of
+    /// unconfirmed transactions.
+    PackageLimits(bitcoin::OutPoint),
     UnknownOutpoint(bitcoin::OutPoint),
     AlreadySpent(bitcoin::OutPoint),
     ImmatureCoinbase(bitcoin::OutPoint),
@@ -68,6 +92,56 @@ pub enum CommandError {
     InvalidDerivationIndex,
     RbfError(RbfErrorInfo),
     EmptyFilterList,
+    UnknownTransaction(bitcoin::Txid),
+    /// This item of the readiness checklist is computed, it can't be set.
+    ComputedReadinessItem(ReadinessItem),
+    UnknownSpendTemplate(String),
+    InvalidSpendTemplate(String),
+    /// The locktime of a scheduled Spend must not be reached yet.
+    LocktimeReached(LockTime),
+    /// A derivation index can't be increased by more than this at once.
+    DerivationIndexIncrease(u32),
+    NoHotSigner,
+    UnknownHotSigner(bip32::Fingerprint),
+    /// Several hot signers are stored, the one to use must be specified.
+    AmbiguousHotSigner,
+    /// Error accessing the hot signers in the data directory.
+    HotSigner(String),
+    /// The given backup of a hot signer is invalid.
+    InvalidHotSignerBackup(String),
+    /// None of the keys of the descriptor belong to the signer with this master fingerprint.
+    UnknownSigner(bip32::Fingerprint),
+    /// The wallet wasn't synced with the Bitcoin backend yet.
+    NotSynced,
+    /// The PSBT of a stored Spend was altered besides adding signatures.
+    AlteredSpend(bitcoin::Txid, /* what was altered */ String),
+    /// The previous output given for an input doesn't match the coin it spends.
+    UtxoMismatch(bitcoin::OutPoint),
+    InvalidSignature(spend::SignatureError),
+    /// This Spend sends more than the configured threshold out of the wallet, and the value it
+    /// sends wasn't confirmed.
+    SpendNotConfirmed(
+        /* sent */ bitcoin::Amount,
+        /* threshold */ bitcoin::Amount,
+    ),
+    /// The address statement is malformed, can't be signed or its signature is invalid.
+    InvalidAddressStatement(String),
+    /// The Bitcoin backend can't provide what's needed to scan for Silent Payments.
+    SilentPaymentsUnsupported,
+    /// Silent Payments aren't enabled or there is no unspent payment.
+    NoSilentPayments,
+    /// Only confirmed transactions have a receipt.
+    UnconfirmedTransaction(bitcoin::Txid),
+    /// The fee can only be deducted from the destinations of the transaction.
+    UnknownFeeDestination(bitcoin::Address<bitcoin::address::NetworkUnchecked>),
+    /// This destination can't pay for its share of the fee without becoming dust.
+    FeeExceedsDestination(bitcoin::Address<bitcoin::address::NetworkUnchecked>),
+    /// Paying to a witness version not defined yet wasn't allowed.
+    FutureWitnessVersion(bitcoin::Address<bitcoin::address::NetworkUnchecked>),
+    /// This needs data to be stored, which an ephemeral wallet doesn't do.
+    EphemeralWallet,
+    /// The CSV file of labels can't be read.
+    InvalidLabelsCsv(String),
 }
 
 impl fmt::Display for CommandError {
@@ -77,6 +151,16 @@ impl fmt::Display for CommandError {
                 write!(f, "No provided outpoint for self-send. Need at least one.")
             }
             Self::InvalidFeerate(sats_vb) => write!(f, "Invalid feerate: {} sats/vb.", sats_vb),
+            Self::FeerateTooLow(sats_vb, min) => write!(
+                f,
+                "Feerate {} sats/vb is below the minimum feerate of {} sats/vb accepted by the Bitcoin backend.",
+                sats_vb, min
+            ),
+            Self::PackageLimits(op) => write!(
+                f,
+                "Spending coin '{}' would exceed the mempool limits on chains of unconfirmed transactions. It must confirm first.",
+                op
+            ),
             Self::AlreadySpent(op) => write!(f, "Coin at '{}' is already spent.", op),
             Self::ImmatureCoinbase(op) => write!(
                 f,
@@ -121,6 +205,156 @@ impl fmt::Display for CommandError {
             }
             Self::RbfError(e) => write!(f, "RBF error: '{}'.", e),
             Self::EmptyFilterList => write!(f, "Filter list is empty, should supply None instead."),
+            Self::UnknownTransaction(txid) => write!(f, "Unknown transaction '{}'.", txid),
+            Self::ComputedReadinessItem(item) => write!(
+                f,
+                "Readiness item '{}' is computed by the daemon and can't be set.",
+                item
+            ),
+            Self::UnknownSpendTemplate(name) => write!(f, "Unknown spend template '{}'.", name),
+            Self::InvalidSpendTemplate(e) => write!(f, "Invalid spend template: {}", e),
+            Self::LocktimeReached(locktime) => write!(
+                f,
+                "Locktime '{}' is already reached. A scheduled Spend must have a future locktime.",
+                locktime
+            ),
+            Self::DerivationIndexIncrease(max) => write!(
+                f,
+                "A derivation index can't be increased by more than {} at once.",
+                max
+            ),
+            Self::NoHotSigner => write!(f, "No hot signer is stored in the data directory."),
+            Self::UnknownHotSigner(fg) => write!(f, "Unknown hot signer '{}'.", fg),
+            Self::AmbiguousHotSigner => write!(
+                f,
+                "Several hot signers are stored, the fingerprint of the one to use must be given."
+            ),
+            Self::HotSigner(e) => write!(f, "Error accessing the hot signers: '{}'.", e),
+            Self::InvalidHotSignerBackup(e) => write!(f, "Invalid hot signer backup: '{}'.", e),
+            Self::UnknownSigner(fg) => {
+                write!(f, "No key of the descriptor belongs to signer '{}'.", fg)
+            }
+            Self::NotSynced => write!(f, "The wallet wasn't synced yet."),
+            Self::AlteredSpend(txid, e) => write!(
+                f,
+                "The PSBT of Spend transaction '{}' was altered besides adding signatures: {}.",
+                txid, e
+            ),
+            Self::UtxoMismatch(op) => write!(
+                f,
+                "The previous output given for coin '{}' doesn't match this coin.",
+                op
+            ),
+            Self::InvalidSignature(e) => write!(f, "{}", e),
+            Self::InvalidAddressStatement(e) => write!(f, "Invalid address statement: {}", e),
+            Self::SilentPaymentsUnsupported => write!(
+                f,
+                "Silent Payments can only be received with bitcoind as the Bitcoin backend."
+            ),
+            Self::NoSilentPayments => write!(f, "No unspent Silent Payment to sweep."),
+            Self::UnconfirmedTransaction(txid) => {
+                write!(f, "Transaction '{}' is not confirmed.", txid)
+            }
+            Self::UnknownFeeDestination(addr) => write!(
+                f,
+                "Can't deduct the fee from '{}': it isn't a destination of the transaction.",
+                addr.assume_checked_ref()
+            ),
+            Self::FeeExceedsDestination(addr) => write!(
+                f,
+                "Deducting its share of the fee from '{}' would leave it with less than {} sats.",
+                addr.assume_checked_ref(),
+                spend::DUST_OUTPUT_SATS
+            ),
+            Self::FutureWitnessVersion(addr) => write!(
+                f,
+                "Address '{}' uses a witness version which isn't defined yet. Anyone could spend \
+                 the coins sent to it until it is: it must be explicitly allowed.",
+                addr.assume_checked_ref()
+            ),
+            Self::EphemeralWallet => write!(
+                f,
+                "Not available on an ephemeral wallet, which doesn't store anything on disk."
+            ),
+            Self::InvalidLabelsCsv(e) => write!(f, "Invalid CSV file of labels: {}", e),
+            Self::SpendNotConfirmed(sent, threshold) => write!(
+                f,
+                "This Spend sends {} out of the wallet, above the threshold of {}. The value it sends must be confirmed.",
+                sent, threshold
+            ),
+        }
+    }
+}
+
+impl CommandError {
+    /// A stable identifier of this error, for clients to branch on without parsing the message.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NoOutpointForSelfSend => "NO_OUTPOINT_FOR_SELF_SEND",
+            Self::InvalidFeerate(..) => "INVALID_FEERATE",
+            Self::FeerateTooLow(..) => "FEERATE_TOO_LOW",
+            Self::PackageLimits(..) => "PACKAGE_LIMITS",
+            Self::UnknownOutpoint(..) => "UNKNOWN_OUTPOINT",
+            Self::AlreadySpent(..) => "ALREADY_SPENT",
+            Self::ImmatureCoinbase(..) => "IMMATURE_COINBASE",
+            Self::Address(..) => "INVALID_ADDRESS",
+            Self::SpendCreation(e) => match e {
+                SpendCreationError::InvalidFeerate(..) => "INVALID_FEERATE",
+                SpendCreationError::InvalidOutputValue(..) => "INVALID_OUTPUT_VALUE",
+                SpendCreationError::InsaneFees(..) => "INSANE_FEES",
+                SpendCreationError::SanityCheckFailure(..) => "SANITY_CHECK_FAILURE",
+                SpendCreationError::FetchingTransaction(..) => "MISSING_TRANSACTION",
+                SpendCreationError::CoinSelection(..) => "INSUFFICIENT_FUNDS",
+                SpendCreationError::DuplicateCandidate(..) => "DUPLICATE_COIN",
+                SpendCreationError::SighashSingleMissingOutput(..) => {
+                    "SIGHASH_SINGLE_MISSING_OUTPUT"
+                }
+                SpendCreationError::UnknownFeeDestination(..) => "UNKNOWN_FEE_DESTINATION",
+                SpendCreationError::FeeExceedsDestination(..) => "FEE_EXCEEDS_DESTINATION",
+                SpendCreationError::NonStandardOutput(..) => "NON_STANDARD_OUTPUT",
+            },
+            Self::InsufficientFunds(..) => "INSUFFICIENT_FUNDS",
+            Self::UnknownSpend(..) => "UNKNOWN_SPEND",
+            Self::SpendFinalization(..) => "SPEND_FINALIZATION",
+            Self::TxBroadcast(..) => "BROADCAST_FAILED",
+            Self::AlreadyRescanning => "ALREADY_RESCANNING",
+            Self::InsaneRescanTimestamp(..) => "INVALID_RESCAN_TIMESTAMP",
+            Self::RescanTrigger(..) => "RESCAN_FAILED",
+            Self::RecoveryNotAvailable => "RECOVERY_NOT_AVAILABLE",
+            Self::InvalidDerivationIndex => "INVALID_DERIVATION_INDEX",
+            Self::RbfError(e) => match e {
+                RbfErrorInfo::MissingFeerate => "MISSING_FEERATE",
+                RbfErrorInfo::SuperfluousFeerate => "SUPERFLUOUS_FEERATE",
+                RbfErrorInfo::TooLowFeerate(..) => "FEERATE_TOO_LOW",
+                RbfErrorInfo::NotSignaling => "NOT_SIGNALING_RBF",
+            },
+            Self::EmptyFilterList => "EMPTY_FILTER_LIST",
+            Self::UnknownTransaction(..) => "UNKNOWN_TRANSACTION",
+            Self::ComputedReadinessItem(..) => "COMPUTED_READINESS_ITEM",
+            Self::UnknownSpendTemplate(..) => "UNKNOWN_SPEND_TEMPLATE",
+            Self::InvalidSpendTemplate(..) => "INVALID_SPEND_TEMPLATE",
+            Self::LocktimeReached(..) => "LOCKTIME_REACHED",
+            Self::DerivationIndexIncrease(..) => "DERIVATION_INDEX_INCREASE",
+            Self::NoHotSigner => "NO_HOT_SIGNER",
+            Self::UnknownHotSigner(..) => "UNKNOWN_HOT_SIGNER",
+            Self::AmbiguousHotSigner => "AMBIGUOUS_HOT_SIGNER",
+            Self::HotSigner(..) => "HOT_SIGNER_ACCESS",
+            Self::InvalidHotSignerBackup(..) => "INVALID_HOT_SIGNER_BACKUP",
+            Self::UnknownSigner(..) => "UNKNOWN_SIGNER",
+            Self::NotSynced => "NOT_SYNCED",
+            Self::AlteredSpend(..) => "ALTERED_SPEND",
+            Self::UtxoMismatch(..) => "UTXO_MISMATCH",
+            Self::InvalidSignature(..) => "INVALID_SIGNATURE",
+            Self::SpendNotConfirmed(..) => "SPEND_NOT_CONFIRMED",
+            Self::InvalidAddressStatement(..) => "INVALID_ADDRESS_STATEMENT",
+            Self::SilentPaymentsUnsupported => "SILENT_PAYMENTS_UNSUPPORTED",
+            Self::NoSilentPayments => "NO_SILENT_PAYMENTS",
+            Self::UnconfirmedTransaction(..) => "UNCONFIRMED_TRANSACTION",
+            Self::UnknownFeeDestination(..) => "UNKNOWN_FEE_DESTINATION",
+            Self::FeeExceedsDestination(..) => "FEE_EXCEEDS_DESTINATION",
+            Self::FutureWitnessVersion(..) => "FUTURE_WITNESS_VERSION",
+            Self::EphemeralWallet => "EPHEMERAL_WALLET",
+            Self::InvalidLabelsCsv(..) => "INVALID_LABELS_CSV",
         }
     }
 }
@@ -161,15 +395,24 @@ impl fmt::Display for RbfErrorInfo {
 /// A wallet transaction getter which fetches the transaction from our database backend with a cache
 /// to avoid needless redundant calls. Note the cache holds an Option<> so we also avoid redundant
 /// calls when the txid isn't known by our database backend.
+///
+/// Falls back to querying the Bitcoin backend for transactions missing from our database, so the
+/// previous transactions of the inputs may always be included in the PSBTs for the signing devices
+/// which require them.
 struct DbTxGetter<'a> {
     db: &'a sync::Arc<sync::Mutex<dyn DatabaseInterface>>,
+    bitcoin: &'a sync::Arc<sync::Mutex<dyn BitcoinInterface>>,
     cache: HashMap<bitcoin::Txid, Option<bitcoin::Transaction>>,
 }
 
 impl<'a> DbTxGetter<'a> {
-    pub fn new(db: &'a sync::Arc<sync::Mutex<dyn DatabaseInterface>>) -> Self {
+    pub fn new(
+        db: &'a sync::Arc<sync::Mutex<dyn DatabaseInterface>>,
+        bitcoin: &'a sync::Arc<sync::Mutex<dyn BitcoinInterface>>,
+    ) -> Self {
         Self {
             db,
+            bitcoin,
             cache: HashMap::new(),
         }
     }
@@ -183,7 +426,14 @@ impl<'a> TxGetter for DbTxGetter<'a> {
                 .connection()
                 .list_wallet_transactions(&[*txid])
                 .pop()
-                .map(|(tx, _, _)| tx);
+                .map(|(tx, _, _)| tx)
+                .or_else(|| {
+                    log::debug!(
+                        "Transaction '{}' not in database, querying the Bitcoin backend.",
+                        txid
+                    );
+                    self.bitcoin.wallet_transaction(txid).map(|(tx, _)| tx)
+                });
             entry.insert(tx);
         }
         self.cache.get(txid).cloned().flatten()
@@ -207,6 +457,74 @@ fn coin_to_candidate(
     }
 }
 
+/// Check the PSBT of a stored Spend wasn't altered besides adding signatures and derivation
+/// information. Returns what was altered otherwise.
+fn check_unaltered_spend(db_psbt: &Psbt, psbt: &Psbt) -> Result<(), String> {
+    // The transaction, thereby the inputs and outputs, is the same since it has the same txid.
+    if psbt.inputs.len() != db_psbt.inputs.len() || psbt.outputs.len() != db_psbt.outputs.len() {
+        return Err("the number of PSBT inputs or outputs changed".to_string());
+    }
+    // A field set in the stored PSBT must be left as is.
+    fn same<T: PartialEq>(stored: &Option<T>, new: &Option<T>) -> bool {
+        stored.is_none() || stored == new
+    }
+    // Entries of a map may be added but existing ones must be left as is.
+    fn extends<K: Ord, V: PartialEq>(stored: &BTreeMap<K, V>, new: &BTreeMap<K, V>) -> bool {
+        stored.iter().all(|(k, v)| new.get(k) == Some(v))
+    }
+
+    for (i, (db_psbt_in, psbt_in)) in db_psbt.inputs.iter().zip(psbt.inputs.iter()).enumerate() {
+        if !same(&db_psbt_in.witness_utxo, &psbt_in.witness_utxo)
+            || !same(&db_psbt_in.non_witness_utxo, &psbt_in.non_witness_utxo)
+        {
+            return Err(format!("the previous output of input {} changed", i));
+        }
+        if !same(&db_psbt_in.witness_script, &psbt_in.witness_script)
+            || !same(&db_psbt_in.redeem_script, &psbt_in.redeem_script)
+            || !same(&db_psbt_in.tap_internal_key, &psbt_in.tap_internal_key)
+            || !same(&db_psbt_in.tap_merkle_root, &psbt_in.tap_merkle_root)
+            || !extends(&db_psbt_in.tap_scripts, &psbt_in.tap_scripts)
+        {
+            return Err(format!("the script of input {} changed", i));
+        }
+        if !extends(&db_psbt_in.bip32_derivation, &psbt_in.bip32_derivation)
+            || !extends(&db_psbt_in.tap_key_origins, &psbt_in.tap_key_origins)
+        {
+            return Err(format!("the key origins of input {} changed", i));
+        }
+    }
+    for (i, (db_psbt_out, psbt_out)) in db_psbt.outputs.iter().zip(psbt.outputs.iter()).enumerate()
+    {
+        if !same(&db_psbt_out.witness_script, &psbt_out.witness_script)
+            || !same(&db_psbt_out.redeem_script, &psbt_out.redeem_script)
+            || !same(&db_psbt_out.tap_internal_key, &psbt_out.tap_internal_key)
+            || !same(&db_psbt_out.tap_tree, &psbt_out.tap_tree)
+        {
+            return Err(format!("the script of output {} changed", i));
+        }
+        if !extends(&db_psbt_out.bip32_derivation, &psbt_out.bip32_derivation)
+            || !extends(&db_psbt_out.tap_key_origins, &psbt_out.tap_key_origins)
+        {
+            return Err(format!("the key origins of output {} changed", i));
+        }
+    }
+
+    Ok(())
+}
+
+// Until a soft fork defines it, anyone can spend the coins sent to a witness version not defined
+// yet. Make sure the caller is aware before paying to such an address.
+fn check_future_witness(addr: &bitcoin::Address, allow: bool) -> Result<(), CommandError> {
+    if !allow
+        && OutputType::from_script(&addr.script_pubkey()).is_some_and(|t| t.is_future_witness())
+    {
+        return Err(CommandError::FutureWitnessVersion(
+            addr.as_unchecked().clone(),
+        ));
+    }
+    Ok(())
+}
+
 impl DaemonControl {
     // Get the derived descriptor for this coin
     fn derived_desc(&self, coin: &Coin) -> descriptors::DerivedSinglePathLianaDesc {
@@ -326,11 +644,44 @@ impl DaemonControl {
                 main: self.config.main_descriptor.clone(),
             },
             rescan_progress,
+            rescan_checkpoint: wallet.rescan_checkpoint,
             timestamp: wallet.timestamp,
             last_poll_timestamp: wallet.last_poll_timestamp,
+            poller: Some(*self.poll_stats.lock().unwrap()),
+        }
+    }
+
+    /// Get the balances of the wallet, without going through all its coins.
+    pub fn get_balance(&self) -> GetBalanceResult {
+        let mut db_conn = self.db.connection();
+        let balances = db_conn.balances();
+        GetBalanceResult {
+            confirmed: balances.confirmed,
+            unconfirmed_from_self: balances.unconfirmed_from_self,
+            unconfirmed: balances.unconfirmed,
+            immature: balances.immature,
+            spending: balances.spending,
         }
     }
 
+    /// Get the state of the mempool of our backend: the feerates paid by the transactions it
+    /// contains and the minimum feerates it accepts.
+    pub fn get_mempool_stats(&self) -> GetMempoolStatsResult {
+        let min_feerates = self.bitcoin.min_feerates();
+        GetMempoolStatsResult {
+            fee_histogram: self.bitcoin.fee_histogram().unwrap_or_default(),
+            min_relay_feerate: min_feerates.map(|f| f.relay),
+            mempool_min_feerate: min_feerates.map(|f| f.mempool),
+        }
+    }
+
+    /// Export a snapshot of the unspent coins of the wallet as of our current tip, to populate the
+    /// database of another instance of it.
+    pub fn export_snapshot(&self) -> Result<CoinsSnapshot, CommandError> {
+        let mut db_conn = self.db.connection();
+        snapshot::export(&mut *db_conn, &self.config.main_descriptor).ok_or(CommandError::NotSynced)
+    }
+
     /// Get a new deposit address. This will always generate a new deposit address, regardless of
     /// whether it was actually used.
     pub fn get_new_address(&self) -> GetAddressResult {
@@ -406,360 +757,1723 @@ impl DaemonControl {
         Ok(ListAddressesResult::new(addresses?))
     }
 
-    /// Get a list of all known coins, optionally by status and/or outpoint.
-    pub fn list_coins(
+    /// Create a statement listing `count` receive addresses from `start_index`, or from the next
+    /// receive address if not given, to be signed by the signers of the primary path. The
+    /// addresses are considered given out: the next receive address is moved past them.
+    pub fn create_address_statement(
         &self,
-        statuses: &[CoinStatus],
-        outpoints: &[bitcoin::OutPoint],
-    ) -> ListCoinsResult {
+        count: u32,
+        start_index: Option<u32>,
+    ) -> Result<CreateAddressStatementResult, CommandError> {
+        if count == 0 {
+            return Err(CommandError::InvalidAddressStatement(
+                "It must list at least one address.".to_string(),
+            ));
+        }
         let mut db_conn = self.db.connection();
-        let coins: Vec<ListCoinsEntry> = db_conn
-            .coins(statuses, outpoints)
-            .into_values()
-            .map(|coin| {
-                let Coin {
-                    amount,
-                    outpoint,
-                    block_info,
-                    spend_txid,
-                    spend_block,
-                    is_immature,
-                    is_change,
-                    is_from_self,
-                    derivation_index,
-                    ..
-                } = coin;
-                let spend_info = spend_txid.map(|txid| LCSpendInfo {
-                    txid,
-                    height: spend_block.map(|b| b.height),
-                });
-                let block_height = block_info.map(|b| b.height);
-                let address = self
-                    .derived_desc(&coin)
-                    .address(self.config.bitcoin_config.network);
-                ListCoinsEntry {
-                    address,
-                    amount,
-                    derivation_index,
-                    outpoint,
-                    block_height,
-                    spend_info,
-                    is_immature,
-                    is_change,
-                    is_from_self,
+        let receive_index = db_conn.receive_index();
+        let start_index = start_index.unwrap_or_else(|| receive_index.into());
+        let end_index = start_index
+            .checked_add(count)
+            .and_then(|end| bip32::ChildNumber::from_normal_idx(end).ok())
+            .ok_or(CommandError::InvalidDerivationIndex)?;
+        if u32::from(end_index).saturating_sub(receive_index.into()) > MAX_DERIVATION_INDEX_INCREASE
+        {
+            return Err(CommandError::DerivationIndexIncrease(
+                MAX_DERIVATION_INDEX_INCREASE,
+            ));
+        }
+
+        let network = self.config.bitcoin_config.network;
+        let receive_desc = self.config.main_descriptor.receive_descriptor();
+        let derived: Vec<_> = (start_index..u32::from(end_index))
+            .map(|index| (index, receive_desc.derive(index.into(), &self.secp)))
+            .collect();
+        let statement = AddressStatement {
+            network,
+            addresses: derived
+                .iter()
+                .map(|(index, desc)| (*index, desc.address(network)))
+                .collect(),
+        };
+        let message = statement.message();
+        let psbt = bip322::message_psbt(&derived[0].1, message.as_bytes());
+        if end_index > receive_index {
+            db_conn.set_receive_index(end_index, &self.secp);
+        }
+
+        Ok(CreateAddressStatementResult { message, psbt })
+    }
+
+    /// Finalize the PSBT of an address statement once signed, to get the signed statement to
+    /// hand over.
+    pub fn finalize_address_statement(
+        &self,
+        message: &str,
+        psbt: Psbt,
+    ) -> Result<SignedAddressStatement, CommandError> {
+        let network = self.config.bitcoin_config.network;
+        let statement = AddressStatement::from_message(message, network)
+            .map_err(|e| CommandError::InvalidAddressStatement(e.to_string()))?;
+        // Only vouch for our own addresses.
+        let receive_desc = self.config.main_descriptor.receive_descriptor();
+        for (index, address) in &statement.addresses {
+            let child = bip32::ChildNumber::from_normal_idx(*index)
+                .map_err(|_| CommandError::InvalidDerivationIndex)?;
+            if receive_desc.derive(child, &self.secp).address(network) != *address {
+                return Err(CommandError::InvalidAddressStatement(format!(
+                    "Address '{}' is not the receive address of the wallet at index {}.",
+                    address, index
+                )));
+            }
+        }
+        let address = statement.addresses[0].1.clone();
+        let signature = bip322::finalize(
+            psbt,
+            &address.script_pubkey(),
+            message.as_bytes(),
+            &self.secp,
+        )
+        .map_err(|e| CommandError::InvalidAddressStatement(e.to_string()))?;
+
+        Ok(SignedAddressStatement {
+            address,
+            message: message.to_string(),
+            signature: bip322::encode_signature(&signature),
+        })
+    }
+
+    /// Check a statement signed for the first address it lists, and get the addresses it lists.
+    /// It doesn't need to be a statement about this wallet.
+    pub fn verify_address_statement(
+        &self,
+        address: bitcoin::Address<address::NetworkUnchecked>,
+        message: &str,
+        signature: &str,
+    ) -> Result<VerifyAddressStatementResult, CommandError> {
+        let network = self.config.bitcoin_config.network;
+        let address = address
+            .require_network(network)
+            .map_err(CommandError::Address)?;
+        let statement = AddressStatement::from_message(message, network)
+            .map_err(|e| CommandError::InvalidAddressStatement(e.to_string()))?;
+        if statement.signing_address() != Some(&address) {
+            return Err(CommandError::InvalidAddressStatement(
+                "The statement must be signed for the first address it lists.".to_string(),
+            ));
+        }
+        let signature = bip322::decode_signature(signature)
+            .map_err(|e| CommandError::InvalidAddressStatement(e.to_string()))?;
+        bip322::verify(
+            &address.script_pubkey(),
+            message.as_bytes(),
+            &signature,
+            &self.secp,
+        )
+        .map_err(|e| CommandError::InvalidAddressStatement(e.to_string()))?;
+
+        Ok(VerifyAddressStatementResult {
+            addresses: statement
+                .addresses
+                .into_iter()
+                .map(|(index, address)| StatementAddress { index, address })
+                .collect(),
+        })
+    }
+
+    /// Get the Silent Payment (BIP352) address of the wallet. The first call enables scanning the
+    /// new blocks for payments to it, with keys derived from the hot signer with this fingerprint
+    /// (or the only one stored). Later calls return the same address.
+    pub fn get_silent_payment_address(
+        &self,
+        fingerprint: Option<bip32::Fingerprint>,
+    ) -> Result<GetSilentPaymentAddressResult, CommandError> {
+        let secp = secp256k1::Secp256k1::signing_only();
+        let mut db_conn = self.db.connection();
+        let keys = match db_conn.silent_payment_keys() {
+            Some(keys) => keys,
+            None => {
+                let tip = db_conn.chain_tip().ok_or(CommandError::NotSynced)?;
+                // Scanning needs the previous outputs spent by the transactions of each block.
+                if self.bitcoin.block_transactions(tip.height).is_none() {
+                    return Err(CommandError::SilentPaymentsUnsupported);
                 }
+                let signer = self.hot_signer(fingerprint, &secp)?;
+                let (scan_key, spend_key) = signer.silent_payment_keys(&secp);
+                let keys = SilentPaymentKeys {
+                    fingerprint: signer.fingerprint(&secp),
+                    scan_key,
+                    spend_key: spend_key.public_key(&secp),
+                    scanned_height: tip.height,
+                };
+                db_conn.store_silent_payment_keys(&keys);
+                keys
+            }
+        };
+        let address = SilentPaymentAddress {
+            network: self.config.bitcoin_config.network,
+            scan_key: keys.scan_key.public_key(&secp),
+            spend_key: keys.spend_key,
+        };
+        Ok(GetSilentPaymentAddressResult {
+            address: address.to_string(),
+            fingerprint: keys.fingerprint,
+            scanned_height: keys.scanned_height,
+        })
+    }
+
+    /// List the Silent Payments to the wallet found so far.
+    pub fn list_silent_payments(&self) -> ListSilentPaymentsResult {
+        let payments = self
+            .db
+            .connection()
+            .silent_payments()
+            .into_iter()
+            .map(|payment| ListSilentPaymentsEntry {
+                outpoint: payment.outpoint,
+                amount: payment.amount,
+                block_height: payment.block.height,
+                spend_info: payment.spend_txid.map(|txid| LCSpendInfo {
+                    txid,
+                    height: payment.spend_block.map(|b| b.height),
+                }),
             })
             .collect();
-        ListCoinsResult { coins }
+        ListSilentPaymentsResult { payments }
     }
 
-    pub fn create_spend(
+    /// Sweep the unspent Silent Payments to a new receive address of the wallet, with a
+    /// transaction signed by the hot signer the Silent Payment keys were derived from.
+    pub fn sweep_silent_payments(
         &self,
-        destinations: &HashMap<bitcoin::Address<bitcoin::address::NetworkUnchecked>, u64>,
-        coins_outpoints: &[bitcoin::OutPoint],
         feerate_vb: u64,
-        change_address: Option<bitcoin::Address<bitcoin::address::NetworkUnchecked>>,
-    ) -> Result<CreateSpendResult, CommandError> {
-        let is_self_send = destinations.is_empty();
-        // For self-send, the coins must be specified.
-        if is_self_send && coins_outpoints.is_empty() {
-            return Err(CommandError::NoOutpointForSelfSend);
-        }
-        if feerate_vb < 1 {
+    ) -> Result<SweepSilentPaymentsResult, CommandError> {
+        if !(1..=spend::MAX_FEERATE).contains(&feerate_vb) {
             return Err(CommandError::InvalidFeerate(feerate_vb));
         }
         let mut db_conn = self.db.connection();
-        let mut tx_getter = DbTxGetter::new(&self.db);
-
-        // Prepare the destination addresses.
-        let mut destinations_checked = Vec::with_capacity(destinations.len());
-        for (address, value_sat) in destinations {
-            let address = self.validate_address(address.clone())?;
-            let amount = bitcoin::Amount::from_sat(*value_sat);
-            let address = self.spend_addr(&mut db_conn, address);
-            destinations_checked.push((address, amount));
+        let keys = db_conn
+            .silent_payment_keys()
+            .ok_or(CommandError::NoSilentPayments)?;
+        let payments: Vec<_> = db_conn
+            .silent_payments()
+            .into_iter()
+            .filter(|p| p.spend_txid.is_none())
+            .collect();
+        if payments.is_empty() {
+            return Err(CommandError::NoSilentPayments);
         }
 
-        // The change address to be used if a change output needs to be created. It may be
-        // specified by the caller (for instance for the purpose of a sweep, or to avoid us
-        // creating a new change address on every call).
-        let change_address = change_address
-            .map(|addr| {
-                Ok::<_, CommandError>(self.spend_addr(&mut db_conn, self.validate_address(addr)?))
+        let secp = secp256k1::Secp256k1::signing_only();
+        let signer = self.hot_signer(Some(keys.fingerprint), &secp)?;
+        let (_, spend_key) = signer.silent_payment_keys(&secp);
+        let prevouts = payments
+            .iter()
+            .map(|p| {
+                silent_payments::output_script_pubkey(&self.secp, &keys.spend_key, &p.tweak)
+                    .map(|script_pubkey| bitcoin::TxOut {
+                        value: p.amount,
+                        script_pubkey,
+                    })
+                    .ok_or_else(|| {
+                        CommandError::SpendFinalization(format!(
+                            "Invalid tweak for Silent Payment '{}'.",
+                            p.outpoint
+                        ))
+                    })
             })
-            .transpose()?
-            .unwrap_or_else(|| self.next_change_addr(&mut db_conn));
+            .collect::<Result<Vec<_>, _>>()?;
+        let keypairs = payments
+            .iter()
+            .map(|p| silent_payments::payment_keypair(&secp, &spend_key, &p.tweak))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| CommandError::SpendFinalization(e.to_string()))?;
 
-        // The candidate coins will be either all optional or all mandatory.
-        // If no coins have been specified, then coins will be selected automatically for
-        // the spend from a set of optional candidates.
-        // Otherwise, only the specified coins will be used, all as mandatory candidates.
-        let candidate_coins: Vec<CandidateCoin> = if coins_outpoints.is_empty() {
-            // From our unconfirmed coins, we only include those that are from self
-            // since unconfirmed external deposits are more at risk of being dropped
-            // unexpectedly from the mempool as they are beyond the user's control.
-            db_conn
-                .coins(&[CoinStatus::Unconfirmed, CoinStatus::Confirmed], &[])
-                .into_iter()
-                .filter_map(|(op, c)| {
-                    if c.block_info.is_some() {
-                        Some((c, None)) // confirmed coins have no ancestor info
-                    } else if c.is_from_self {
-                        // In case the mempool_entry is None, the coin will be included without
-                        // any ancestor info.
-                        Some((
-                            c,
-                            self.bitcoin
-                                .mempool_entry(&op.txid)
-                                .map(|info| AncestorInfo {
-                                    vsize: info.ancestor_vsize,
-                                    fee: info
-                                        .fees
-                                        .ancestor
-                                        .to_sat()
-                                        .try_into()
-                                        .expect("fee in sat should fit in u32"),
-                                }),
-                        ))
-                    } else {
-                        None
-                    }
-                })
-                .map(|(c, ancestor_info)| {
-                    coin_to_candidate(
-                        &c,
-                        /*must_select=*/ false,
-                        /*sequence=*/ None,
-                        ancestor_info,
-                    )
+        // Size the transaction with a Schnorr signature for each input to compute its fee. The
+        // receive index is only bumped once the sweep is created.
+        let in_value: bitcoin::Amount = payments.iter().map(|p| p.amount).sum();
+        let index = db_conn.receive_index();
+        let address = self
+            .config
+            .main_descriptor
+            .receive_descriptor()
+            .derive(index, &self.secp)
+            .address(self.config.bitcoin_config.network);
+        let mut tx = bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: payments
+                .iter()
+                .map(|p| bitcoin::TxIn {
+                    previous_output: p.outpoint,
+                    sequence: bitcoin::Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    witness: bitcoin::Witness::from_slice(&[[0; 64]]),
+                    ..bitcoin::TxIn::default()
                 })
-                .collect()
-        } else {
-            // Query from DB and sanity check the provided coins to spend.
-            let coins = db_conn.coins(&[], coins_outpoints);
-            for op in coins_outpoints {
-                let coin = coins.get(op).ok_or(CommandError::UnknownOutpoint(*op))?;
-                if coin.is_spent() {
-                    return Err(CommandError::AlreadySpent(*op));
-                }
-                if coin.is_immature {
-                    return Err(CommandError::ImmatureCoinbase(*op));
-                }
-            }
-            coins
-                .into_iter()
-                .map(|(op, c)| {
-                    let ancestor_info = if c.block_info.is_none() {
-                        // We include any non-change coins here as they have been selected by the caller.
-                        // If the unconfirmed coin's transaction is no longer in the mempool, keep the
-                        // coin as a candidate but without any ancestor info (same as confirmed candidate).
-                        self.bitcoin
-                            .mempool_entry(&op.txid)
-                            .map(|info| AncestorInfo {
-                                vsize: info.ancestor_vsize,
-                                fee: info
-                                    .fees
-                                    .ancestor
-                                    .to_sat()
-                                    .try_into()
-                                    .expect("fee in sat should fit in u32"),
-                            })
-                    } else {
-                        None
-                    };
-                    coin_to_candidate(
-                        &c,
-                        /*must_select=*/ true,
-                        /*sequence=*/ None,
-                        ancestor_info,
-                    )
-                })
-                .collect()
+                .collect(),
+            output: vec![bitcoin::TxOut {
+                value: in_value,
+                script_pubkey: address.script_pubkey(),
+            }],
         };
+        let fee = bitcoin::Amount::from_sat(tx.vsize() as u64 * feerate_vb);
+        let amount = in_value
+            .checked_sub(fee)
+            .filter(|amount| amount.to_sat() >= spend::DUST_OUTPUT_SATS)
+            .ok_or(CommandError::InsufficientFunds(in_value, None, feerate_vb))?;
+        tx.output[0].value = amount;
+        silent_payments::sign_transaction(&secp, &mut tx, &prevouts, &keypairs)
+            .map_err(|e| CommandError::SpendFinalization(e.to_string()))?;
 
-        // Create the PSBT. If there was no error in doing so make sure to update our next
-        // derivation index in case any address in the transaction outputs was ours and from the
-        // future.
-        let change_info = change_address.info;
-        let locktime = self.anti_fee_sniping_locktime();
-        let CreateSpendRes {
-            psbt,
-            has_change,
-            warnings,
-        } = match create_spend(
-            &self.config.main_descriptor,
-            &self.secp,
-            &mut tx_getter,
-            &destinations_checked,
-            &candidate_coins,
-            SpendTxFees::Regular(feerate_vb),
-            change_address,
-            locktime,
-        ) {
-            Ok(res) => res,
-            Err(SpendCreationError::CoinSelection(e)) => {
-                return Ok(CreateSpendResult::InsufficientFunds { missing: e.missing });
-            }
-            Err(e) => {
-                return Err(e.into());
+        let new_index = index
+            .increment()
+            .expect("Can't get into hardened territory");
+        db_conn.set_receive_index(new_index, &self.secp);
+        self.bitcoin
+            .broadcast_tx(&tx)
+            .map_err(CommandError::TxBroadcast)?;
+        let txid = tx.compute_txid();
+        let outpoints: Vec<_> = payments.iter().map(|p| p.outpoint).collect();
+        db_conn.spend_silent_payments(&outpoints, Some(&txid));
+        Ok(SweepSilentPaymentsResult {
+            txid,
+            address,
+            amount,
+            fee,
+        })
+    }
+
+    /// Get statistics about the usage of the derivation indexes of the receive and change
+    /// addresses.
+    pub fn get_derivation_indexes(&self) -> GetDerivationIndexesResult {
+        let mut db_conn = self.db.connection();
+        let receive_index: u32 = db_conn.receive_index().into();
+        let change_index: u32 = db_conn.change_index().into();
+        let (mut used_receive, mut used_change) = (BTreeSet::new(), BTreeSet::new());
+        for coin in db_conn.coins(&[], &[]).into_values() {
+            if coin.is_change {
+                used_change.insert(u32::from(coin.derivation_index));
+            } else {
+                used_receive.insert(u32::from(coin.derivation_index));
             }
-        };
-        for (addr, _) in destinations_checked {
-            self.maybe_increase_next_deriv_index(&mut db_conn, &addr.info);
         }
-        if has_change {
-            self.maybe_increase_next_deriv_index(&mut db_conn, &change_info);
+        GetDerivationIndexesResult {
+            receive: DerivationIndexStats::new(receive_index, &used_receive),
+            change: DerivationIndexStats::new(change_index, &used_change),
+            lookahead: LOOK_AHEAD_LIMIT,
+            watched_up_to: receive_index.max(change_index) + LOOK_AHEAD_LIMIT - 1,
         }
-
-        Ok(CreateSpendResult::Success {
-            psbt,
-            warnings: warnings.iter().map(|w| w.to_string()).collect(),
-        })
     }
 
-    pub fn update_spend(&self, mut psbt: Psbt) -> Result<(), CommandError> {
+    /// Run a battery of checks of the Bitcoin backend, the database and the coins of the wallet.
+    pub fn health_check(&self) -> HealthCheckResult {
         let mut db_conn = self.db.connection();
-        let tx = &psbt.unsigned_tx;
+        let mut checks = Vec::with_capacity(5);
+
+        // The backend is reachable, synced, and we keep up with it.
+        let mut backend = HealthCheck::new("backend");
+        let sync = self.bitcoin.sync_progress();
+        if !sync.is_complete() {
+            backend.add(
+                HealthStatus::Warning,
+                format!(
+                    "The Bitcoin backend is synchronizing ({:.2}%).",
+                    sync.rounded_up_progress() * 100.0
+                ),
+            );
+        }
+        let backend_height = self.bitcoin.chain_tip().height;
+        let wallet_height = db_conn.chain_tip().map(|tip| tip.height).unwrap_or(0);
+        if wallet_height < backend_height {
+            backend.add(
+                HealthStatus::Warning,
+                format!(
+                    "The wallet is {} block(s) behind the Bitcoin backend.",
+                    backend_height - wallet_height
+                ),
+            );
+        }
+        let now: u32 = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .expect("time measured now cannot be before unix epoch")
+            .as_secs()
+            .try_into()
+            .expect("must fit in u32");
+        let max_poll_delay =
+            (self.config.bitcoin_config.poll_interval_secs.as_secs() * 10).max(MIN_POLL_DELAY_SECS);
+        match db_conn.last_poll_timestamp() {
+            Some(last_poll) if (now.saturating_sub(last_poll) as u64) > max_poll_delay => backend
+                .add(
+                    HealthStatus::Error,
+                    format!(
+                        "The Bitcoin backend wasn't polled for {} seconds.",
+                        now - last_poll
+                    ),
+                ),
+            Some(_) => {}
+            None => backend.add(
+                HealthStatus::Warning,
+                "The Bitcoin backend wasn't polled yet.".to_string(),
+            ),
+        }
+        checks.push(backend);
 
-        // If the transaction already exists in DB, merge the signatures for each input on a best
-        // effort basis.
-        // We work on the newly provided PSBT, in case its content was updated.
-        let txid = tx.compute_txid();
-        if let Some(db_psbt) = db_conn.spend_tx(&txid) {
-            let db_tx = db_psbt.unsigned_tx;
-            for i in 0..db_tx.input.len() {
-                if tx
+        // No draft spends a coin already spent by a confirmed transaction, or not ours anymore.
+        let mut drafts = HealthCheck::new("drafts");
+        let spend_psbts = db_conn.list_spend();
+        let outpoints: Vec<_> = spend_psbts
+            .iter()
+            .flat_map(|(psbt, _)| {
+                psbt.unsigned_tx
                     .input
-                    .get(i)
-                    .map(|tx_in| tx_in.previous_output == db_tx.input[i].previous_output)
-                    != Some(true)
-                {
-                    continue;
-                }
-                let psbtin = match psbt.inputs.get_mut(i) {
-                    Some(psbtin) => psbtin,
-                    None => continue,
-                };
-                let db_psbtin = match db_psbt.inputs.get(i) {
-                    Some(db_psbtin) => db_psbtin,
-                    None => continue,
-                };
-                psbtin
-                    .partial_sigs
-                    .extend(db_psbtin.partial_sigs.clone().into_iter());
-                psbtin
-                    .tap_script_sigs
-                    .extend(db_psbtin.tap_script_sigs.clone().into_iter());
-                if psbtin.tap_key_sig.is_none() {
-                    psbtin.tap_key_sig = db_psbtin.tap_key_sig;
-                }
-            }
-        } else {
-            // If the transaction doesn't exist in DB already, sanity check its inputs.
-            // FIXME: should we allow for external inputs?
-            let outpoints: Vec<bitcoin::OutPoint> =
-                tx.input.iter().map(|txin| txin.previous_output).collect();
-            let coins = db_conn.coins_by_outpoints(&outpoints);
-            if coins.len() != outpoints.len() {
-                for op in outpoints {
-                    if !coins.contains_key(&op) {
-                        return Err(CommandError::UnknownOutpoint(op));
-                    }
-                }
+                    .iter()
+                    .map(|txin| txin.previous_output)
+            })
+            .collect();
+        let coins = db_conn.coins_by_outpoints(&outpoints);
+        let stale: Vec<_> = spend_psbts
+            .iter()
+            .filter_map(|(psbt, _)| {
+                let txid = psbt.unsigned_tx.compute_txid();
+                psbt.unsigned_tx
+                    .input
+                    .iter()
+                    .any(|txin| match coins.get(&txin.previous_output) {
+                        Some(coin) => coin.spend_block.is_some() && coin.spend_txid != Some(txid),
+                        None => true,
+                    })
+                    .then_some(txid)
+            })
+            .collect();
+        if !stale.is_empty() {
+            drafts.add(
+                HealthStatus::Warning,
+                format!(
+                    "{} stored transaction(s) spend coins which were spent by another \
+                     confirmed transaction and can be deleted: {}.",
+                    stale.len(),
+                    stale
+                        .iter()
+                        .map(|txid| txid.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            );
+        }
+        checks.push(drafts);
+
+        // The database isn't corrupted.
+        let mut database = HealthCheck::new("database");
+        let problems = db_conn.quick_check();
+        if !problems.is_empty() {
+            database.add(
+                HealthStatus::Error,
+                format!("The database is corrupted: {}", problems.join("; ")),
+            );
+        }
+        checks.push(database);
+
+        // The coins pay to the scripts we derive for them from the descriptor.
+        let mut coins_check = HealthCheck::new("coins");
+        let all_coins = db_conn.coins(&[], &[]);
+        let txids: Vec<_> = all_coins
+            .keys()
+            .map(|op| op.txid)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let txs: HashMap<_, _> = db_conn
+            .list_wallet_transactions(&txids)
+            .into_iter()
+            .map(|(tx, _, _)| (tx.compute_txid(), tx))
+            .collect();
+        let mismatches: Vec<_> = all_coins
+            .values()
+            .filter(|coin| {
+                txs.get(&coin.outpoint.txid)
+                    .and_then(|tx| tx.output.get(coin.outpoint.vout as usize))
+                    .map(|txo| txo.script_pubkey != self.derived_desc(coin).script_pubkey())
+                    .unwrap_or(true)
+            })
+            .map(|coin| coin.outpoint.to_string())
+            .collect();
+        if !mismatches.is_empty() {
+            coins_check.add(
+                HealthStatus::Error,
+                format!(
+                    "{} coin(s) don't pay to the address derived from the descriptor at their \
+                     derivation index, or their transaction is missing: {}.",
+                    mismatches.len(),
+                    mismatches.join(", ")
+                ),
+            );
+        }
+        checks.push(coins_check);
+
+        // Enough headroom in the gaps of unused addresses for the coins to be found again when
+        // restoring the wallet.
+        let mut indexes = HealthCheck::new("derivation_indexes");
+        let stats = self.get_derivation_indexes();
+        for (kind, stats) in [("receive", &stats.receive), ("change", &stats.change)] {
+            if stats.largest_gap >= LOOK_AHEAD_LIMIT {
+                indexes.add(
+                    HealthStatus::Error,
+                    format!(
+                        "{} consecutive {} addresses never received a coin. Coins received \
+                         past them may not be found when restoring the wallet.",
+                        stats.largest_gap, kind
+                    ),
+                );
+            } else if stats.largest_gap > COMMON_GAP_LIMIT {
+                indexes.add(
+                    HealthStatus::Warning,
+                    format!(
+                        "{} consecutive {} addresses never received a coin. Other wallets \
+                         importing the descriptor may need a gap limit of at least this much.",
+                        stats.largest_gap, kind
+                    ),
+                );
             }
         }
+        checks.push(indexes);
 
-        // Finally, insert (or update) the PSBT in database.
-        db_conn.store_spend(&psbt);
+        HealthCheckResult {
+            status: checks
+                .iter()
+                .map(|c| c.status)
+                .max()
+                .unwrap_or(HealthStatus::Ok),
+            checks,
+        }
+    }
 
-        Ok(())
+    /// Check a descriptor, by default our main descriptor, for valid but risky constructions.
+    /// The timelock bounds default to [`descriptors::DEFAULT_MIN_TIMELOCK`] and
+    /// [`descriptors::DEFAULT_MAX_TIMELOCK`].
+    pub fn lint_descriptor(
+        &self,
+        descriptor: Option<descriptors::LianaDescriptor>,
+        min_timelock: Option<u16>,
+        max_timelock: Option<u16>,
+    ) -> LintDescriptorResult {
+        let desc = descriptor.as_ref().unwrap_or(&self.config.main_descriptor);
+        let defaults = descriptors::LintOptions::default();
+        let options = descriptors::LintOptions {
+            min_timelock: min_timelock.unwrap_or(defaults.min_timelock),
+            max_timelock: max_timelock.unwrap_or(defaults.max_timelock),
+            ..defaults
+        };
+        let warnings = descriptors::lint_descriptor(desc, &options)
+            .into_iter()
+            .map(|w| LintWarningEntry {
+                code: w.code().to_string(),
+                message: w.to_string(),
+            })
+            .collect();
+        LintDescriptorResult { warnings }
     }
 
-    pub fn update_labels(&self, items: &HashMap<LabelItem, Option<String>>) {
+    /// Simulate the loss of the signers with these master fingerprints: which spending paths could
+    /// still be used, how long after their confirmation coins could be spent, and when each of
+    /// the current coins could be spent.
+    pub fn simulate_signer_loss(
+        &self,
+        lost: &[bip32::Fingerprint],
+    ) -> Result<SignerLossResult, CommandError> {
+        let desc = &self.config.main_descriptor;
+        let policy = desc.policy();
+        let signers: HashSet<_> = std::iter::once(policy.primary_path())
+            .chain(policy.recovery_paths().values())
+            .flat_map(|path| path.thresh_origins().1.into_keys())
+            .collect();
+        if let Some(fg) = lost.iter().find(|fg| !signers.contains(fg)) {
+            return Err(CommandError::UnknownSigner(*fg));
+        }
+
+        let analysis = descriptors::signer_loss(desc, &lost.iter().copied().collect());
+        let spendable_after = analysis.spendable_after();
+        let current_height = self.bitcoin.chain_tip().height;
         let mut db_conn = self.db.connection();
-        db_conn.update_labels(items);
+        let coins = db_conn
+            .coins(
+                &[
+                    CoinStatus::Unconfirmed,
+                    CoinStatus::Confirmed,
+                    CoinStatus::Spending,
+                ],
+                &[],
+            )
+            .into_values()
+            .map(|coin| {
+                // Like for recovery, a coin is spendable if it can be spent in the next block.
+                let spendable_at_height = match (spendable_after, coin.block_info) {
+                    (Some(blocks), Some(block)) => Some(block.height + i32::from(blocks)),
+                    _ => None,
+                };
+                let spendable_now = spendable_after == Some(0)
+                    || spendable_at_height
+                        .map(|h| current_height + 1 >= h)
+                        .unwrap_or(false);
+                SignerLossCoin {
+                    outpoint: coin.outpoint,
+                    amount: coin.amount,
+                    spendable_now,
+                    spendable_at_height,
+                }
+            })
+            .collect();
+        let paths = analysis
+            .paths
+            .iter()
+            .map(|path| SignerLossPath {
+                timelock: path.timelock,
+                threshold: path.threshold,
+                keys: path.keys,
+                remaining_keys: path.remaining_keys,
+                usable: path.is_usable(),
+            })
+            .collect();
+
+        Ok(SignerLossResult {
+            paths,
+            spendable_after,
+            coins,
+        })
     }
 
-    pub fn get_labels(&self, items: &HashSet<LabelItem>) -> GetLabelsResult {
-        let mut db_conn = self.db.connection();
-        GetLabelsResult {
-            labels: db_conn.labels(items),
+    /// The data directory the hot signers are stored in. An ephemeral wallet doesn't store nor
+    /// use any.
+    fn hot_signers_dir(&self) -> Result<std::path::PathBuf, CommandError> {
+        if self.config.ephemeral {
+            return Err(CommandError::EphemeralWallet);
         }
+        self.config
+            .data_dir()
+            .ok_or_else(|| CommandError::HotSigner("No data directory.".to_string()))
     }
 
-    pub fn list_spend(
+    /// The hot signer stored in the data directory with this master fingerprint, or the only one
+    /// stored if none is given.
+    fn hot_signer(
         &self,
-        txids: Option<Vec<bitcoin::Txid>>,
-    ) -> Result<ListSpendResult, CommandError> {
-        if let Some(ids) = &txids {
-            if ids.is_empty() {
-                return Err(CommandError::EmptyFilterList);
+        fingerprint: Option<bip32::Fingerprint>,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Signing>,
+    ) -> Result<HotSigner, CommandError> {
+        let network = self.config.bitcoin_config.network;
+        let data_dir = self.hot_signers_dir()?;
+        let mut signers = match HotSigner::from_datadir(&data_dir, network) {
+            Ok(signers) => signers,
+            Err(SignerError::MnemonicStorage(e)) if e.kind() == io::ErrorKind::NotFound => {
+                Vec::new()
             }
+            Err(e) => return Err(CommandError::HotSigner(e.to_string())),
+        };
+        match fingerprint {
+            Some(fg) => signers
+                .into_iter()
+                .find(|s| s.fingerprint(secp) == fg)
+                .ok_or(CommandError::UnknownHotSigner(fg)),
+            None => match signers.len() {
+                0 => Err(CommandError::NoHotSigner),
+                1 => Ok(signers.remove(0)),
+                _ => Err(CommandError::AmbiguousHotSigner),
+            },
         }
+    }
 
-        let mut db_conn = self.db.connection();
-        let spend_psbts = db_conn.list_spend();
+    /// Get the extended public keys of a hot signer stored in the data directory at the given
+    /// derivation paths, which must be fully hardened. The fingerprint of the hot signer is only
+    /// needed if several are stored.
+    pub fn get_hot_signer_xpubs(
+        &self,
+        fingerprint: Option<bip32::Fingerprint>,
+        derivation_paths: &[bip32::DerivationPath],
+    ) -> Result<HotSignerXpubsResult, CommandError> {
+        if derivation_paths
+            .iter()
+            .any(|path| path.as_ref().iter().any(|child| child.is_normal()))
+        {
+            return Err(CommandError::InvalidDerivationIndex);
+        }
 
-        let txids_set: Option<HashSet<_>> = txids.as_ref().map(|list| list.iter().collect());
-        let spend_txs = spend_psbts
-            .into_iter()
-            .filter_map(|(psbt, updated_at)| {
-                if let Some(set) = &txids_set {
-                    if !set.contains(&psbt.unsigned_tx.compute_txid()) {
-                        return None;
-                    }
+        let secp = secp256k1::Secp256k1::signing_only();
+        let signer = self.hot_signer(fingerprint, &secp)?;
+        let fingerprint = signer.fingerprint(&secp);
+        let xpubs = derivation_paths
+            .iter()
+            .map(|path| {
+                let xpub = signer.xpub_at(path, &secp);
+                let key = descriptor::DescriptorPublicKey::XPub(descriptor::DescriptorXKey {
+                    origin: Some((fingerprint, path.clone())),
+                    xkey: xpub,
+                    derivation_path: bip32::DerivationPath::master(),
+                    wildcard: descriptor::Wildcard::None,
+                });
+                HotSignerXpub {
+                    derivation_path: path.clone(),
+                    xpub,
+                    key: key.to_string(),
                 }
-                Some(ListSpendEntry { psbt, updated_at })
             })
             .collect();
-        Ok(ListSpendResult { spend_txs })
+        Ok(HotSignerXpubsResult { fingerprint, xpubs })
     }
 
-    pub fn delete_spend(&self, txid: &bitcoin::Txid) {
-        let mut db_conn = self.db.connection();
-        db_conn.delete_spend(txid);
+    /// Restore a hot signer into the data directory from its backup: either a BIP39 mnemonic or
+    /// enough SLIP-39 shares to reconstruct its secret, one per line. Importing a hot signer which
+    /// is already stored is a no-op.
+    pub fn import_hot_signer(&self, backup: &str) -> Result<ImportHotSignerResult, CommandError> {
+        let network = self.config.bitcoin_config.network;
+        let signer = HotSigner::from_str(network, backup)
+            .map_err(|e| CommandError::InvalidHotSignerBackup(e.to_string()))?;
+        let data_dir = self.hot_signers_dir()?;
+        let secp = secp256k1::Secp256k1::signing_only();
+        match signer.store(&data_dir, network, &secp) {
+            Ok(()) => {}
+            Err(SignerError::MnemonicStorage(e)) if e.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(CommandError::HotSigner(e.to_string())),
+        }
+        Ok(ImportHotSignerResult {
+            fingerprint: signer.fingerprint(&secp),
+        })
     }
 
-    /// Finalize and broadcast this stored Spend transaction.
-    pub fn broadcast_spend(&self, txid: &bitcoin::Txid) -> Result<(), CommandError> {
+    /// Sign a PSBT with a hot signer stored in the data directory, for a client which doesn't hold
+    /// this key itself. The fingerprint of the hot signer is only needed if several are stored.
+    /// Only PSBTs spending coins of the wallet which aren't spent by a confirmed transaction yet
+    /// are signed, and the previous outputs they give must be those of the coins. The PSBT is
+    /// neither stored nor broadcast.
+    pub fn sign_psbt_with_hot_signer(
+        &self,
+        psbt: Psbt,
+        fingerprint: Option<bip32::Fingerprint>,
+    ) -> Result<SignPsbtResult, CommandError> {
         let mut db_conn = self.db.connection();
+        let coins = self.wallet_inputs(db_conn.as_mut(), &psbt)?;
+        if let Some(coin) = coins.values().find(|coin| coin.spend_block.is_some()) {
+            return Err(CommandError::AlreadySpent(coin.outpoint));
+        }
 
-        // First, try to finalize the spending transaction with the elements contained
-        // in the PSBT.
-        let mut spend_psbt = db_conn
-            .spend_tx(txid)
-            .ok_or(CommandError::UnknownSpend(*txid))?;
-        spend_psbt.finalize_mut(&self.secp).map_err(|e| {
-            CommandError::SpendFinalization(
-                e.into_iter()
-                    .next()
-                    .map(|e| e.to_string())
-                    .unwrap_or_default(),
-            )
-        })?;
-
-        // Then, broadcast it (or try to, we never know if we are not going to hit an
-        // error at broadcast time).
-        // These checks are already performed at Spend creation time. TODO: a belt-and-suspenders is still worth it though.
-        let final_tx = spend_psbt.extract_tx_unchecked_fee_rate();
-        self.bitcoin
-            .broadcast_tx(&final_tx)
-            .map_err(CommandError::TxBroadcast)?;
+        let secp = secp256k1::Secp256k1::new();
+        let signer = self.hot_signer(fingerprint, &secp)?;
+        let fingerprint = signer.fingerprint(&secp);
+        let policy = self.config.main_descriptor.policy();
+        let is_signer = std::iter::once(policy.primary_path())
+            .chain(policy.recovery_paths().values())
+            .any(|path| path.thresh_origins().1.contains_key(&fingerprint));
+        if !is_signer {
+            return Err(CommandError::UnknownSigner(fingerprint));
+        }
+        let psbt = signer
+            .sign_psbt(psbt, &secp)
+            .map_err(|e| CommandError::HotSigner(e.to_string()))?;
+        Ok(SignPsbtResult { fingerprint, psbt })
+    }
 
-        // Finally, update our state with the changes from this transaction.
-        let (tx, rx) = mpsc::sync_channel(0);
-        if let Err(e) = self.poller_sender.send(PollerMessage::PollNow(tx)) {
-            log::error!("Error requesting update from poller: {}", e);
+    /// Increase the derivation index of the next receive and/or change address, extending the
+    /// window of addresses watched for coins. Lower indexes are ignored.
+    pub fn update_derivation_indexes(
+        &self,
+        receive: Option<u32>,
+        change: Option<u32>,
+    ) -> Result<GetDerivationIndexesResult, CommandError> {
+        let mut db_conn = self.db.connection();
+        let check_index = |index: u32, current: bip32::ChildNumber| {
+            let index = bip32::ChildNumber::from_normal_idx(index)
+                .map_err(|_| CommandError::InvalidDerivationIndex)?;
+            if u32::from(index).saturating_sub(current.into()) > MAX_DERIVATION_INDEX_INCREASE {
+                return Err(CommandError::DerivationIndexIncrease(
+                    MAX_DERIVATION_INDEX_INCREASE,
+                ));
+            }
+            Ok(index)
+        };
+        let receive = receive
+            .map(|index| check_index(index, db_conn.receive_index()))
+            .transpose()?;
+        let change = change
+            .map(|index| check_index(index, db_conn.change_index()))
+            .transpose()?;
+
+        if let Some(index) = receive {
+            if index > db_conn.receive_index() {
+                db_conn.set_receive_index(index, &self.secp);
+            }
         }
-        if let Err(e) = rx.recv() {
-            log::error!("Error receiving completion signal from poller: {}", e);
+        if let Some(index) = change {
+            if index > db_conn.change_index() {
+                db_conn.set_change_index(index, &self.secp);
+            }
         }
-
-        Ok(())
+        if receive.is_some() || change.is_some() {
+            self.events.lock().unwrap().push(Event::SettingsChanged {
+                change: "Derivation indexes updated.".to_string(),
+            });
+        }
+        Ok(self.get_derivation_indexes())
     }
 
-    /// Create PSBT to replace the given transaction using RBF.
-    ///
+    /// Get a list of all known coins, optionally by status and/or outpoint.
+    pub fn list_coins(
+        &self,
+        statuses: &[CoinStatus],
+        outpoints: &[bitcoin::OutPoint],
+    ) -> ListCoinsResult {
+        let mut db_conn = self.db.connection();
+        let coins: Vec<ListCoinsEntry> = db_conn
+            .coins(statuses, outpoints)
+            .into_values()
+            .map(|coin| {
+                let Coin {
+                    amount,
+                    outpoint,
+                    block_info,
+                    spend_txid,
+                    spend_block,
+                    is_immature,
+                    is_change,
+                    is_from_self,
+                    derivation_index,
+                    ..
+                } = coin;
+                let spend_info = spend_txid.map(|txid| LCSpendInfo {
+                    txid,
+                    height: spend_block.map(|b| b.height),
+                });
+                let block_height = block_info.map(|b| b.height);
+                // Immature coinbase deposits aren't marked as confirmed until they are mature, get
+                // the block of the coinbase transaction from the Bitcoin backend.
+                let maturity_height = if is_immature {
+                    self.bitcoin
+                        .wallet_transaction(&outpoint.txid)
+                        .and_then(|(_, block)| block)
+                        .map(|block| block.height + COINBASE_MATURITY)
+                } else {
+                    None
+                };
+                let address = self
+                    .derived_desc(&coin)
+                    .address(self.config.bitcoin_config.network);
+                ListCoinsEntry {
+                    address,
+                    amount,
+                    derivation_index,
+                    outpoint,
+                    block_height,
+                    spend_info,
+                    is_immature,
+                    maturity_height,
+                    is_change,
+                    is_from_self,
+                }
+            })
+            .collect();
+        ListCoinsResult { coins }
+    }
+
+    /// Check a feerate for a new transaction is valid and high enough for the transaction to be
+    /// accepted in the mempool of our Bitcoin backend.
+    fn check_feerate(&self, feerate_vb: u64) -> Result<(), CommandError> {
+        if feerate_vb < 1 {
+            return Err(CommandError::InvalidFeerate(feerate_vb));
+        }
+        if let Some(min_feerates) = self.bitcoin.min_feerates() {
+            if feerate_vb < min_feerates.mempool {
+                return Err(CommandError::FeerateTooLow(
+                    feerate_vb,
+                    min_feerates.mempool,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a transaction spending these unconfirmed coins wouldn't exceed the mempool limits on
+    /// the number and size of the unconfirmed ancestors and descendants of a transaction.
+    ///
+    /// The ancestors of the coins are counted as if they were distinct. This may overestimate the
+    /// ancestors of the transaction if some of them are shared.
+    fn check_package_limits(
+        &self,
+        tx: &bitcoin::Transaction,
+        unconfirmed_coins: impl Iterator<Item = bitcoin::OutPoint>,
+    ) -> Result<(), CommandError> {
+        let tx_vsize = self
+            .config
+            .main_descriptor
+            .unsigned_tx_max_vbytes(tx, /*use_primary_path=*/ false);
+        let (mut anc_count, mut anc_vsize) = (1, tx_vsize);
+        let mut seen_txids = HashSet::new();
+        for op in unconfirmed_coins {
+            if !seen_txids.insert(op.txid) {
+                continue;
+            }
+            let entry = match self.bitcoin.mempool_entry(&op.txid) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            anc_count += entry.ancestor_count;
+            anc_vsize += entry.ancestor_vsize;
+            if anc_count > MEMPOOL_PACKAGE_COUNT_LIMIT
+                || anc_vsize > MEMPOOL_PACKAGE_VSIZE_LIMIT
+                || entry.descendant_count + 1 > MEMPOOL_PACKAGE_COUNT_LIMIT
+                || entry.descendant_vsize + tx_vsize > MEMPOOL_PACKAGE_VSIZE_LIMIT
+            {
+                return Err(CommandError::PackageLimits(op));
+            }
+        }
+        Ok(())
+    }
+
+    /// Create a Spend transaction paying to these destinations.
+    pub fn create_spend(
+        &self,
+        destinations: &HashMap<bitcoin::Address<bitcoin::address::NetworkUnchecked>, u64>,
+        coins_outpoints: &[bitcoin::OutPoint],
+        feerate_vb: u64,
+        change_address: Option<bitcoin::Address<bitcoin::address::NetworkUnchecked>>,
+        options: &CreateSpendOptions,
+    ) -> Result<CreateSpendResult, CommandError> {
+        let locktime = self.anti_fee_sniping_locktime();
+        self.create_spend_with_locktime(
+            destinations,
+            coins_outpoints,
+            feerate_vb,
+            change_address,
+            options,
+            locktime,
+        )
+    }
+
+    /// Request the signers to use this signature hash type for all the inputs of a created Spend
+    /// transaction, for instance to let another party add an input to it once signed.
+    pub fn set_spend_sighash(
+        &self,
+        psbt: &mut Psbt,
+        sighash: SpendSighash,
+    ) -> Result<(), CommandError> {
+        spend::set_sighash(&self.config.main_descriptor, psbt, sighash)?;
+        Ok(())
+    }
+
+    /// Create a Spend transaction which can only be included in a block once the given locktime
+    /// is reached. Once signed it can be scheduled with [`DaemonControl::schedule_spend`].
+    pub fn create_scheduled_spend(
+        &self,
+        destinations: &HashMap<bitcoin::Address<bitcoin::address::NetworkUnchecked>, u64>,
+        coins_outpoints: &[bitcoin::OutPoint],
+        feerate_vb: u64,
+        change_address: Option<bitcoin::Address<bitcoin::address::NetworkUnchecked>>,
+        options: &CreateSpendOptions,
+        locktime: LockTime,
+    ) -> Result<CreateSpendResult, CommandError> {
+        let is_reached = match locktime {
+            LockTime::Blocks(height) => {
+                height.to_consensus_u32() as i32 <= self.bitcoin.chain_tip().height
+            }
+            LockTime::Seconds(time) => self
+                .bitcoin
+                .tip_time()
+                .map(|tip_time| time.to_consensus_u32() <= tip_time)
+                .unwrap_or(false),
+        };
+        if is_reached {
+            return Err(CommandError::LocktimeReached(locktime));
+        }
+        self.create_spend_with_locktime(
+            destinations,
+            coins_outpoints,
+            feerate_vb,
+            change_address,
+            options,
+            locktime,
+        )
+    }
+
+    fn create_spend_with_locktime(
+        &self,
+        destinations: &HashMap<bitcoin::Address<bitcoin::address::NetworkUnchecked>, u64>,
+        coins_outpoints: &[bitcoin::OutPoint],
+        feerate_vb: u64,
+        change_address: Option<bitcoin::Address<bitcoin::address::NetworkUnchecked>>,
+        options: &CreateSpendOptions,
+        locktime: LockTime,
+    ) -> Result<CreateSpendResult, CommandError> {
+        let CreateSpendOptions {
+            subtract_fee_from,
+            allow_future_witness,
+        } = options;
+        let is_self_send = destinations.is_empty();
+        // For self-send, the coins must be specified.
+        if is_self_send && coins_outpoints.is_empty() {
+            return Err(CommandError::NoOutpointForSelfSend);
+        }
+        self.check_feerate(feerate_vb)?;
+        let mut db_conn = self.db.connection();
+        let mut tx_getter = DbTxGetter::new(&self.db, &self.bitcoin);
+
+        // Prepare the destination addresses, remembering their index in the transaction outputs.
+        let mut destinations_checked = Vec::with_capacity(destinations.len());
+        let mut destinations_indexes = HashMap::with_capacity(destinations.len());
+        for (address, value_sat) in destinations {
+            destinations_indexes.insert(address, destinations_checked.len());
+            let address = self.validate_address(address.clone())?;
+            check_future_witness(&address, *allow_future_witness)?;
+            let amount = bitcoin::Amount::from_sat(*value_sat);
+            let address = self.spend_addr(&mut db_conn, address);
+            destinations_checked.push((address, amount));
+        }
+        // The indexes of the destinations paying for the fee, in the order they were given. The
+        // first one pays for the remainder of the split, not whichever the map iterates first.
+        let mut fee_destinations = Vec::with_capacity(subtract_fee_from.len());
+        for address in subtract_fee_from {
+            let index = *destinations_indexes
+                .get(address)
+                .ok_or_else(|| CommandError::UnknownFeeDestination(address.clone()))?;
+            if !fee_destinations.contains(&index) {
+                fee_destinations.push(index);
+            }
+        }
+        let fees = if fee_destinations.is_empty() {
+            SpendTxFees::Regular(feerate_vb)
+        } else {
+            SpendTxFees::DeductedFrom(feerate_vb, fee_destinations)
+        };
+
+        // The change address to be used if a change output needs to be created. It may be
+        // specified by the caller (for instance for the purpose of a sweep, or to avoid us
+        // creating a new change address on every call).
+        let change_address = change_address
+            .map(|addr| {
+                let addr = self.validate_address(addr)?;
+                check_future_witness(&addr, *allow_future_witness)?;
+                Ok::<_, CommandError>(self.spend_addr(&mut db_conn, addr))
+            })
+            .transpose()?
+            .unwrap_or_else(|| self.next_change_addr(&mut db_conn));
+
+        // The candidate coins will be either all optional or all mandatory.
+        // If no coins have been specified, then coins will be selected automatically for
+        // the spend from a set of optional candidates.
+        // Otherwise, only the specified coins will be used, all as mandatory candidates.
+        let candidate_coins: Vec<CandidateCoin> = if coins_outpoints.is_empty() {
+            // From our unconfirmed coins, we only include those that are from self
+            // since unconfirmed external deposits are more at risk of being dropped
+            // unexpectedly from the mempool as they are beyond the user's control.
+            // Immature coinbase deposits can't be spent yet.
+            db_conn
+                .coins(&[CoinStatus::Unconfirmed, CoinStatus::Confirmed], &[])
+                .into_iter()
+                .filter_map(|(op, c)| {
+                    if c.is_immature {
+                        None
+                    } else if c.block_info.is_some() {
+                        Some((c, None)) // confirmed coins have no ancestor info
+                    } else if c.is_from_self {
+                        // In case the mempool_entry is None, the coin will be included without
+                        // any ancestor info.
+                        Some((
+                            c,
+                            self.bitcoin
+                                .mempool_entry(&op.txid)
+                                .map(|info| AncestorInfo {
+                                    vsize: info.ancestor_vsize,
+                                    fee: info
+                                        .fees
+                                        .ancestor
+                                        .to_sat()
+                                        .try_into()
+                                        .expect("fee in sat should fit in u32"),
+                                }),
+                        ))
+                    } else {
+                        None
+                    }
+                })
+                .map(|(c, ancestor_info)| {
+                    coin_to_candidate(
+                        &c,
+                        /*must_select=*/ false,
+                        /*sequence=*/ None,
+                        ancestor_info,
+                    )
+                })
+                .collect()
+        } else {
+            // Query from DB and sanity check the provided coins to spend.
+            let coins = db_conn.coins(&[], coins_outpoints);
+            for op in coins_outpoints {
+                let coin = coins.get(op).ok_or(CommandError::UnknownOutpoint(*op))?;
+                if coin.is_spent() {
+                    return Err(CommandError::AlreadySpent(*op));
+                }
+                if coin.is_immature {
+                    return Err(CommandError::ImmatureCoinbase(*op));
+                }
+            }
+            coins
+                .into_iter()
+                .map(|(op, c)| {
+                    let ancestor_info = if c.block_info.is_none() {
+                        // We include any non-change coins here as they have been selected by the caller.
+                        // If the unconfirmed coin's transaction is no longer in the mempool, keep the
+                        // coin as a candidate but without any ancestor info (same as confirmed candidate).
+                        self.bitcoin
+                            .mempool_entry(&op.txid)
+                            .map(|info| AncestorInfo {
+                                vsize: info.ancestor_vsize,
+                                fee: info
+                                    .fees
+                                    .ancestor
+                                    .to_sat()
+                                    .try_into()
+                                    .expect("fee in sat should fit in u32"),
+                            })
+                    } else {
+                        None
+                    };
+                    coin_to_candidate(
+                        &c,
+                        /*must_select=*/ true,
+                        /*sequence=*/ None,
+                        ancestor_info,
+                    )
+                })
+                .collect()
+        };
+
+        // Create the PSBT. If there was no error in doing so make sure to update our next
+        // derivation index in case any address in the transaction outputs was ours and from the
+        // future.
+        let change_info = change_address.info;
+        let CreateSpendRes {
+            psbt,
+            has_change,
+            warnings,
+        } = match create_spend(
+            &self.config.main_descriptor,
+            &self.secp,
+            &mut tx_getter,
+            &destinations_checked,
+            &candidate_coins,
+            fees,
+            change_address,
+            locktime,
+        ) {
+            Ok(res) => res,
+            Err(SpendCreationError::CoinSelection(e)) => {
+                return Ok(CreateSpendResult::InsufficientFunds { missing: e.missing });
+            }
+            Err(SpendCreationError::FeeExceedsDestination(index)) => {
+                let (address, _) = &destinations_checked[index];
+                return Err(CommandError::FeeExceedsDestination(
+                    address.addr.as_unchecked().clone(),
+                ));
+            }
+            Err(e) => {
+                return Err(e.into());
+            }
+        };
+        // Coins with ancestor info are the unconfirmed ones which are in the mempool.
+        let unconfirmed_coins: HashSet<_> = candidate_coins
+            .iter()
+            .filter(|c| c.ancestor_info.is_some())
+            .map(|c| c.outpoint)
+            .collect();
+        self.check_package_limits(
+            &psbt.unsigned_tx,
+            psbt.unsigned_tx
+                .input
+                .iter()
+                .map(|txin| txin.previous_output)
+                .filter(|op| unconfirmed_coins.contains(op)),
+        )?;
+        for (addr, _) in destinations_checked {
+            self.maybe_increase_next_deriv_index(&mut db_conn, &addr.info);
+        }
+        if has_change {
+            self.maybe_increase_next_deriv_index(&mut db_conn, &change_info);
+        }
+
+        Ok(CreateSpendResult::Success {
+            psbt,
+            warnings: warnings.iter().map(|w| w.to_string()).collect(),
+        })
+    }
+
+    pub fn update_spend(&self, mut psbt: Psbt) -> Result<(), CommandError> {
+        let mut db_conn = self.db.connection();
+        let tx = &psbt.unsigned_tx;
+        let txid = tx.compute_txid();
+
+        // If the transaction already exists in DB, merge the signatures for each input on a best
+        // effort basis. Only signatures and derivation information may be added to it.
+        // We work on the newly provided PSBT, in case its content was updated.
+        let db_psbt = db_conn.spend_tx(&txid);
+        let is_new = db_psbt.is_none();
+        if let Some(db_psbt) = db_psbt {
+            check_unaltered_spend(&db_psbt, &psbt)
+                .map_err(|e| CommandError::AlteredSpend(txid, e))?;
+            let db_tx = db_psbt.unsigned_tx;
+            for i in 0..db_tx.input.len() {
+                if tx
+                    .input
+                    .get(i)
+                    .map(|tx_in| tx_in.previous_output == db_tx.input[i].previous_output)
+                    != Some(true)
+                {
+                    continue;
+                }
+                let psbtin = match psbt.inputs.get_mut(i) {
+                    Some(psbtin) => psbtin,
+                    None => continue,
+                };
+                let db_psbtin = match db_psbt.inputs.get(i) {
+                    Some(db_psbtin) => db_psbtin,
+                    None => continue,
+                };
+                psbtin
+                    .partial_sigs
+                    .extend(db_psbtin.partial_sigs.clone().into_iter());
+                psbtin
+                    .tap_script_sigs
+                    .extend(db_psbtin.tap_script_sigs.clone().into_iter());
+                if psbtin.tap_key_sig.is_none() {
+                    psbtin.tap_key_sig = db_psbtin.tap_key_sig;
+                }
+            }
+        } else {
+            // If the transaction doesn't exist in DB already, sanity check its inputs.
+            // FIXME: should we allow for external inputs?
+            self.wallet_inputs(db_conn.as_mut(), &psbt)?;
+        }
+
+        // Never store a signature which doesn't verify.
+        spend::check_signatures(&self.secp, &psbt).map_err(CommandError::InvalidSignature)?;
+
+        // Finally, insert (or update) the PSBT in database. Record who signed it if it has new
+        // signers.
+        let prev_signers: HashSet<_> = db_conn
+            .spend_signers(&txid)
+            .into_iter()
+            .map(|(fg, _)| fg)
+            .collect();
+        db_conn.store_spend(&psbt);
+        let new_signers: Vec<_> = db_conn
+            .spend_signers(&txid)
+            .into_iter()
+            .map(|(fg, _)| fg)
+            .filter(|fg| !prev_signers.contains(fg))
+            .collect();
+        if is_new {
+            self.events.lock().unwrap().push(Event::SpendSaved { txid });
+        }
+        if !new_signers.is_empty() {
+            self.events.lock().unwrap().push(Event::SpendSigned {
+                txid,
+                signers: new_signers,
+            });
+        }
+
+        Ok(())
+    }
+
+    // The coins spent by this PSBT, which must all be ours. The signatures would commit to the
+    // previous outputs given in the PSBT: make sure they are those of our coins.
+    fn wallet_inputs(
+        &self,
+        db_conn: &mut dyn DatabaseConnection,
+        psbt: &Psbt,
+    ) -> Result<HashMap<bitcoin::OutPoint, Coin>, CommandError> {
+        let tx = &psbt.unsigned_tx;
+        let outpoints: Vec<bitcoin::OutPoint> =
+            tx.input.iter().map(|txin| txin.previous_output).collect();
+        let coins = db_conn.coins_by_outpoints(&outpoints);
+        if coins.len() != outpoints.len() {
+            for op in outpoints {
+                if !coins.contains_key(&op) {
+                    return Err(CommandError::UnknownOutpoint(op));
+                }
+            }
+        }
+        for (txin, psbt_in) in tx.input.iter().zip(psbt.inputs.iter()) {
+            let coin = &coins[&txin.previous_output];
+            if let Some(utxo) = &psbt_in.witness_utxo {
+                if utxo.value != coin.amount
+                    || utxo.script_pubkey != self.derived_desc(coin).script_pubkey()
+                {
+                    return Err(CommandError::UtxoMismatch(txin.previous_output));
+                }
+            }
+        }
+        Ok(coins)
+    }
+
+    pub fn update_labels(&self, items: &HashMap<LabelItem, Option<String>>) {
+        let mut db_conn = self.db.connection();
+        db_conn.update_labels(items);
+    }
+
+    pub fn get_labels(&self, items: &HashSet<LabelItem>) -> GetLabelsResult {
+        let mut db_conn = self.db.connection();
+        GetLabelsResult {
+            labels: db_conn.labels(items),
+        }
+    }
+
+    /// Label the transactions of the wallet from a CSV file of `txid,label,note` records, as
+    /// exported by another wallet. The transactions which already have a label keep it unless
+    /// `overwrite` is set. The records for transactions the wallet doesn't know about are reported
+    /// along with those which can't be read.
+    pub fn import_labels_csv(
+        &self,
+        csv: &str,
+        overwrite: bool,
+    ) -> Result<ImportLabelsCsvResult, CommandError> {
+        let (labels, rejected) =
+            labels_csv::parse(csv).map_err(|e| CommandError::InvalidLabelsCsv(e.to_string()))?;
+
+        let mut db_conn = self.db.connection();
+        let wallet_txids: HashSet<_> = db_conn.list_saved_txids().into_iter().collect();
+        let existing = db_conn.labels(
+            &labels
+                .iter()
+                .map(|label| LabelItem::Txid(label.txid))
+                .collect(),
+        );
+        let mut items = HashMap::with_capacity(labels.len());
+        let (mut already_labelled, mut unmatched) = (Vec::new(), Vec::new());
+        for label in labels {
+            let record = LabelsCsvRecord {
+                line: label.line,
+                txid: label.txid,
+            };
+            if !wallet_txids.contains(&label.txid) {
+                unmatched.push(record);
+            } else if !overwrite && existing.contains_key(&label.txid.to_string()) {
+                already_labelled.push(record);
+            } else {
+                items.insert(LabelItem::Txid(label.txid), Some(label.label));
+            }
+        }
+        if !items.is_empty() {
+            db_conn.update_labels(&items);
+        }
+
+        Ok(ImportLabelsCsvResult {
+            imported: items.len(),
+            already_labelled,
+            unmatched,
+            rejected,
+        })
+    }
+
+    /// List the stored Spend transactions. They may be filtered by txid, by status and by the
+    /// master fingerprint of a signer which can take part in spending them. If the signer is
+    /// given along with the `needs_signature` status, only the transactions it did not sign yet
+    /// are returned.
+    pub fn list_spend(
+        &self,
+        txids: Option<Vec<bitcoin::Txid>>,
+        statuses: &[SpendTxStatus],
+        signer: Option<bip32::Fingerprint>,
+    ) -> Result<ListSpendResult, CommandError> {
+        if let Some(ids) = &txids {
+            if ids.is_empty() {
+                return Err(CommandError::EmptyFilterList);
+            }
+        }
+
+        let mut db_conn = self.db.connection();
+        let spend_psbts = db_conn.list_spend();
+
+        // The chain state to check the timelocks against is only needed for some statuses.
+        let chain = if statuses.contains(&SpendTxStatus::Broadcastable) {
+            db_conn.chain_tip().map(|tip| ChainState {
+                tip_height: tip.height,
+                tip_time: self.bitcoin.tip_time(),
+            })
+        } else {
+            None
+        };
+
+        // The drafts spending each coin, to tell those conflicting with each other. And the coins
+        // they spend, to tell those invalidated by a confirmed transaction.
+        let mut drafts_by_outpoint: HashMap<bitcoin::OutPoint, Vec<bitcoin::Txid>> = HashMap::new();
+        for (psbt, _) in &spend_psbts {
+            let txid = psbt.unsigned_tx.compute_txid();
+            for txin in &psbt.unsigned_tx.input {
+                drafts_by_outpoint
+                    .entry(txin.previous_output)
+                    .or_default()
+                    .push(txid);
+            }
+        }
+        let outpoints: Vec<_> = drafts_by_outpoint.keys().copied().collect();
+        let coins = db_conn.coins_by_outpoints(&outpoints);
+
+        let txids_set: Option<HashSet<_>> = txids.as_ref().map(|list| list.iter().collect());
+        let spend_txs = spend_psbts
+            .into_iter()
+            .filter_map(|(psbt, updated_at)| {
+                let txid = psbt.unsigned_tx.compute_txid();
+                if let Some(set) = &txids_set {
+                    if !set.contains(&txid) {
+                        return None;
+                    }
+                }
+                if (!statuses.is_empty() || signer.is_some())
+                    && !self.spend_tx_matches(&psbt, &coins, statuses, signer, chain)
+                {
+                    return None;
+                }
+                let conflicting_spends = psbt
+                    .unsigned_tx
+                    .input
+                    .iter()
+                    .filter_map(|txin| drafts_by_outpoint.get(&txin.previous_output))
+                    .flatten()
+                    .filter(|draft_txid| **draft_txid != txid)
+                    .copied()
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .collect();
+                let invalidated_by = psbt
+                    .unsigned_tx
+                    .input
+                    .iter()
+                    .filter_map(|txin| coins.get(&txin.previous_output))
+                    .find_map(|coin| match (coin.spend_txid, coin.spend_block) {
+                        (Some(spend_txid), Some(_)) if spend_txid != txid => Some(spend_txid),
+                        _ => None,
+                    });
+                let reviews = db_conn.spend_reviews(&txid);
+                Some(ListSpendEntry {
+                    psbt,
+                    updated_at,
+                    conflicting_spends,
+                    invalidated_by,
+                    review_state: ReviewState::from_reviews(&reviews),
+                    reviews,
+                })
+            })
+            .collect();
+        Ok(ListSpendResult { spend_txs })
+    }
+
+    // Whether this Spend transaction has one of these statuses (if any is given) and may be
+    // signed by this signer (if one is given).
+    fn spend_tx_matches(
+        &self,
+        psbt: &Psbt,
+        coins: &HashMap<bitcoin::OutPoint, Coin>,
+        statuses: &[SpendTxStatus],
+        signer: Option<bip32::Fingerprint>,
+        chain: Option<ChainState>,
+    ) -> bool {
+        let desc = &self.config.main_descriptor;
+        let txid = psbt.unsigned_tx.compute_txid();
+        let spend_info = match desc.partial_spend_info(psbt, &self.secp) {
+            Ok(info) => info,
+            Err(e) => {
+                log::warn!(
+                    "Could not analyze stored Spend transaction '{}': {}",
+                    txid,
+                    e
+                );
+                return false;
+            }
+        };
+
+        // The spending paths available to this transaction along with their signatures.
+        let policy = desc.policy();
+        let paths: Vec<_> = std::iter::once((policy.primary_path(), spend_info.primary_path()))
+            .chain(
+                spend_info
+                    .recovery_paths()
+                    .iter()
+                    .filter_map(|(timelock, info)| {
+                        policy
+                            .recovery_paths()
+                            .get(timelock)
+                            .map(|path| (path, info))
+                    }),
+            )
+            .collect();
+        let is_signer = |path: &descriptors::PathInfo, fg: &bip32::Fingerprint| {
+            path.thresh_origins().1.contains_key(fg)
+        };
+        if let Some(fg) = signer {
+            if !paths.iter().any(|(path, _)| is_signer(*path, &fg)) {
+                return false;
+            }
+        }
+        if statuses.is_empty() {
+            return true;
+        }
+
+        let fully_signed = paths
+            .iter()
+            .any(|(_, info)| info.sigs_count >= info.threshold);
+        let outpoints: Vec<_> = psbt
+            .unsigned_tx
+            .input
+            .iter()
+            .map(|txin| txin.previous_output)
+            .collect();
+        // One of the coins is unknown to us or spent by another transaction.
+        let conflicting = outpoints.iter().any(|op| {
+            coins
+                .get(op)
+                .map(|coin| coin.spend_txid.map(|t| t != txid).unwrap_or(false))
+                .unwrap_or(true)
+        });
+
+        statuses.iter().any(|status| match status {
+            SpendTxStatus::NeedsSignature => {
+                !fully_signed
+                    && signer
+                        .map(|fg| {
+                            paths.iter().any(|(path, info)| {
+                                is_signer(*path, &fg) && !info.signed_pubkeys.contains_key(&fg)
+                            })
+                        })
+                        .unwrap_or(true)
+            }
+            SpendTxStatus::FullySigned => fully_signed,
+            SpendTxStatus::Conflicting => conflicting,
+            SpendTxStatus::Broadcastable => {
+                // It may replace an unconfirmed transaction, but not a confirmed one. Nor may it
+                // be broadcast if it already was.
+                let spendable = outpoints.iter().all(|op| {
+                    coins
+                        .get(op)
+                        .map(|coin| coin.spend_block.is_none() && coin.spend_txid != Some(txid))
+                        .unwrap_or(false)
+                });
+                fully_signed
+                    && spendable
+                    && chain
+                        .map(|chain| timelocks_matured(&psbt.unsigned_tx, coins, chain))
+                        .unwrap_or(false)
+            }
+        })
+    }
+
+    /// Record the review of a stored Spend transaction by one of the signers of the wallet. It
+    /// replaces its previous review of this transaction, if any.
+    pub fn review_spend(
+        &self,
+        txid: &bitcoin::Txid,
+        signer: bip32::Fingerprint,
+        decision: ReviewDecision,
+        comment: Option<String>,
+    ) -> Result<(), CommandError> {
+        let policy = self.config.main_descriptor.policy();
+        let is_signer = std::iter::once(policy.primary_path())
+            .chain(policy.recovery_paths().values())
+            .any(|path| path.thresh_origins().1.contains_key(&signer));
+        if !is_signer {
+            return Err(CommandError::UnknownSigner(signer));
+        }
+        let mut db_conn = self.db.connection();
+        if db_conn.spend_tx(txid).is_none() {
+            return Err(CommandError::UnknownSpend(*txid));
+        }
+
+        let reviewed_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs() as u32)
+            .unwrap_or(0);
+        db_conn.store_spend_review(
+            txid,
+            &SpendReview {
+                signer,
+                decision,
+                comment: comment.filter(|c| !c.is_empty()),
+                reviewed_at,
+            },
+        );
+        self.events.lock().unwrap().push(Event::SpendReviewed {
+            txid: *txid,
+            signer,
+            decision,
+        });
+        Ok(())
+    }
+
+    pub fn delete_spend(&self, txid: &bitcoin::Txid) {
+        let mut db_conn = self.db.connection();
+        db_conn.delete_spend(txid);
+    }
+
+    /// Finalize and broadcast this stored Spend transaction.
+    ///
+    /// If it sends more than the configured threshold out of the wallet, `confirmed_amount` must
+    /// be the value it sends.
+    pub fn broadcast_spend(
+        &self,
+        txid: &bitcoin::Txid,
+        confirmed_amount: Option<bitcoin::Amount>,
+    ) -> Result<(), CommandError> {
+        let mut db_conn = self.db.connection();
+
+        // First, try to finalize the spending transaction with the elements contained
+        // in the PSBT.
+        let mut spend_psbt = db_conn
+            .spend_tx(txid)
+            .ok_or(CommandError::UnknownSpend(*txid))?;
+        self.check_spend_confirmation(&spend_psbt, confirmed_amount)?;
+        spend_psbt.finalize_mut(&self.secp).map_err(|e| {
+            CommandError::SpendFinalization(
+                e.into_iter()
+                    .next()
+                    .map(|e| e.to_string())
+                    .unwrap_or_default(),
+            )
+        })?;
+
+        // Then, broadcast it (or try to, we never know if we are not going to hit an
+        // error at broadcast time).
+        // These checks are already performed at Spend creation time. TODO: a belt-and-suspenders is still worth it though.
+        // It's recorded in the broadcast journal beforehand so it can be broadcast again at
+        // startup if we crash before the backend accepted it.
+        let final_tx = spend_psbt.extract_tx_unchecked_fee_rate();
+        db_conn.record_broadcast(&final_tx);
+        if let Err(e) = self.bitcoin.broadcast_tx(&final_tx) {
+            db_conn.remove_broadcast(&final_tx.compute_txid());
+            return Err(CommandError::TxBroadcast(e));
+        }
+        self.events
+            .lock()
+            .unwrap()
+            .push(Event::SpendBroadcast { txid: *txid });
+
+        // Finally, update our state with the changes from this transaction.
+        let (tx, rx) = mpsc::sync_channel(0);
+        if let Err(e) = self.poller_sender.send(PollerMessage::PollNow(tx)) {
+            log::error!("Error requesting update from poller: {}", e);
+        }
+        if let Err(e) = rx.recv() {
+            log::error!("Error receiving completion signal from poller: {}", e);
+        }
+
+        Ok(())
+    }
+
+    // A Spend sending more than the configured threshold out of the wallet must be confirmed with
+    // the value it sends, to catch a mistyped amount before it's too late.
+    fn check_spend_confirmation(
+        &self,
+        psbt: &Psbt,
+        confirmed_amount: Option<bitcoin::Amount>,
+    ) -> Result<(), CommandError> {
+        let threshold = match self.config.spend_confirmation_threshold {
+            Some(threshold) => bitcoin::Amount::from_sat(threshold),
+            None => return Ok(()),
+        };
+        let change_indexes: Vec<usize> = self
+            .config
+            .main_descriptor
+            .change_indexes(psbt, &self.secp)
+            .into_iter()
+            .map(|change| change.index())
+            .collect();
+        let sent = bitcoin::Amount::from_sat(
+            psbt.unsigned_tx
+                .output
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !change_indexes.contains(i))
+                .map(|(_, txo)| txo.value.to_sat())
+                .sum(),
+        );
+        if sent > threshold && confirmed_amount != Some(sent) {
+            return Err(CommandError::SpendNotConfirmed(sent, threshold));
+        }
+        Ok(())
+    }
+
+    /// Check whether this PSBT could be finalized with the signatures it contains. For each input,
+    /// report the spending path it can be finalized through or the signatures it misses. The PSBT
+    /// is neither modified nor stored.
+    pub fn finalize_psbt(&self, psbt: &Psbt) -> FinalizePsbtResult {
+        let inputs = self.config.main_descriptor.finalizability(psbt, &self.secp);
+        FinalizePsbtResult {
+            complete: !inputs.is_empty() && inputs.iter().all(|input| input.is_finalizable()),
+            inputs,
+        }
+    }
+
+    /// Finalize this stored Spend transaction and schedule it to be broadcast by the daemon once
+    /// its locktime is reached. The value it sends must be confirmed as for
+    /// [`DaemonControl::broadcast_spend`].
+    pub fn schedule_spend(
+        &self,
+        txid: &bitcoin::Txid,
+        confirmed_amount: Option<bitcoin::Amount>,
+    ) -> Result<(), CommandError> {
+        let mut db_conn = self.db.connection();
+        let mut spend_psbt = db_conn
+            .spend_tx(txid)
+            .ok_or(CommandError::UnknownSpend(*txid))?;
+        self.check_spend_confirmation(&spend_psbt, confirmed_amount)?;
+        spend_psbt.finalize_mut(&self.secp).map_err(|e| {
+            CommandError::SpendFinalization(
+                e.into_iter()
+                    .next()
+                    .map(|e| e.to_string())
+                    .unwrap_or_default(),
+            )
+        })?;
+        let final_tx = spend_psbt.extract_tx_unchecked_fee_rate();
+
+        // Don't schedule a transaction which can already never be valid.
+        let outpoints: Vec<_> = final_tx.input.iter().map(|i| i.previous_output).collect();
+        let coins = db_conn.coins(&[], &outpoints);
+        for op in &outpoints {
+            let coin = coins.get(op).ok_or(CommandError::UnknownOutpoint(*op))?;
+            if coin.is_spent() {
+                return Err(CommandError::AlreadySpent(*op));
+            }
+        }
+
+        db_conn.schedule_spend(&final_tx);
+        Ok(())
+    }
+
+    /// List the Spend transactions waiting for their locktime to be broadcast.
+    pub fn list_scheduled_spends(&self) -> ListScheduledSpendsResult {
+        let mut db_conn = self.db.connection();
+        let scheduled_spends = db_conn
+            .scheduled_spends()
+            .into_iter()
+            .map(|scheduled| ScheduledSpendEntry {
+                txid: scheduled.tx.compute_txid(),
+                locktime: scheduled.tx.lock_time.to_consensus_u32(),
+                scheduled_at: scheduled.scheduled_at,
+                tx: scheduled.tx,
+            })
+            .collect();
+        ListScheduledSpendsResult { scheduled_spends }
+    }
+
+    /// Don't broadcast this scheduled Spend transaction. It's still stored as a Spend.
+    pub fn unschedule_spend(&self, txid: &bitcoin::Txid) -> Result<(), CommandError> {
+        let mut db_conn = self.db.connection();
+        if !db_conn
+            .scheduled_spends()
+            .iter()
+            .any(|s| s.tx.compute_txid() == *txid)
+        {
+            return Err(CommandError::UnknownSpend(*txid));
+        }
+        db_conn.unschedule_spend(txid);
+        Ok(())
+    }
+
+    /// Create PSBT to replace the given transaction using RBF.
+    ///
     /// `txid` must either point to a PSBT in our database (not necessarily broadcast) or an
     /// unconfirmed spend transaction (whether or not any associated PSBT is saved in our database).
     ///
@@ -787,7 +2501,7 @@ impl DaemonControl {
         feerate_vb: Option<u64>,
     ) -> Result<CreateSpendResult, CommandError> {
         let mut db_conn = self.db.connection();
-        let mut tx_getter = DbTxGetter::new(&self.db);
+        let mut tx_getter = DbTxGetter::new(&self.db, &self.bitcoin);
 
         if is_cancel && feerate_vb.is_some() {
             return Err(CommandError::RbfError(RbfErrorInfo::SuperfluousFeerate));
@@ -832,13 +2546,19 @@ impl DaemonControl {
         // rules #3, #4 and #6 (see
         // https://github.com/bitcoin/bitcoin/blob/master/doc/policy/mempool-replacements.md). By
         // default (ie if the transaction we are replacing was dropped from the mempool) there is
-        // no minimum absolute fee and the minimum feerate is 1, the minimum relay feerate.
+        // no minimum absolute fee and the minimum feerate is the minimum our backend's mempool
+        // accepts.
+        let mempool_min_feerate = self
+            .bitcoin
+            .min_feerates()
+            .map(|min_feerates| min_feerates.mempool)
+            .unwrap_or(1);
         let (min_feerate_vb, descendant_fees) = self
             .bitcoin
             .mempool_spenders(&prev_outpoints)
             .into_iter()
             .fold(
-                (1, bitcoin::Amount::from_sat(0)),
+                (mempool_min_feerate, bitcoin::Amount::from_sat(0)),
                 |(min_feerate, descendant_fee), entry| {
                     let entry_feerate = entry
                         .fees
@@ -1018,7 +2738,7 @@ impl DaemonControl {
     /// Trigger a rescan of the block chain for transactions involving our main descriptor between
     /// the given date and the current tip.
     /// The date must be after the genesis block time and before the current tip blocktime.
-    pub fn start_rescan(&mut self, timestamp: u32) -> Result<(), CommandError> {
+    pub fn start_rescan(&self, timestamp: u32) -> Result<(), CommandError> {
         let mut db_conn = self.db.connection();
         let genesis_timestamp = self.bitcoin.genesis_block_timestamp();
 
@@ -1034,9 +2754,8 @@ impl DaemonControl {
             return Err(CommandError::AlreadyRescanning);
         }
 
-        // TODO: there is a race with the above check for whether the backend is already
-        // rescanning. This could make us crash with the bitcoind backend if someone triggered a
-        // rescan of the wallet just after we checked above and did now.
+        // NOTE: callers hold the lock on the writes to the wallet, so no other rescan can be
+        // triggered between the above check and now.
         self.bitcoin
             .start_rescan(&self.config.main_descriptor, timestamp)
             .map_err(CommandError::RescanTrigger)?;
@@ -1070,6 +2789,338 @@ impl DaemonControl {
         ListTransactionsResult { transactions }
     }
 
+    /// Get a transaction of the wallet along with what we know about it: the PSBT stored for it
+    /// if any, the coins of ours it spends and creates, its fee if it only spends our coins and
+    /// its label.
+    pub fn get_transaction(
+        &self,
+        txid: &bitcoin::Txid,
+    ) -> Result<GetTransactionResult, CommandError> {
+        let mut db_conn = self.db.connection();
+        let (tx, height, time) = db_conn
+            .list_wallet_transactions(&[*txid])
+            .into_iter()
+            .next()
+            .ok_or(CommandError::UnknownTransaction(*txid))?;
+        let psbt = db_conn.spend_tx(txid);
+        // Only report the verification of the inclusion in the block it's currently confirmed in.
+        let inclusion_verified = db_conn
+            .tx_verification(txid)
+            .filter(|verif| Some(verif.block_height) == height)
+            .map(|verif| verif.error.is_none());
+        let label = db_conn
+            .labels(&HashSet::from([LabelItem::Txid(*txid)]))
+            .remove(&txid.to_string());
+
+        // Don't query with an empty list of outpoints as it would return all the coins.
+        let coins = |outpoints: Vec<bitcoin::OutPoint>| {
+            if outpoints.is_empty() {
+                Vec::new()
+            } else {
+                self.list_coins(&[], &outpoints).coins
+            }
+        };
+        let spent_coins = coins(tx.input.iter().map(|txin| txin.previous_output).collect());
+        let received_coins = coins(
+            (0..tx.output.len())
+                .map(|vout| bitcoin::OutPoint::new(*txid, vout as u32))
+                .collect(),
+        );
+        let fee = if spent_coins.len() == tx.input.len() {
+            spent_coins
+                .iter()
+                .map(|c| c.amount)
+                .sum::<bitcoin::Amount>()
+                .checked_sub(tx.output.iter().map(|txo| txo.value).sum())
+        } else {
+            None
+        };
+
+        Ok(GetTransactionResult {
+            tx,
+            height,
+            time,
+            psbt,
+            spent_coins,
+            received_coins,
+            fee,
+            label,
+            inclusion_verified,
+        })
+    }
+
+    /// Get a printable receipt of a confirmed transaction of the wallet, for accounting. Its QR
+    /// code links to the transaction on the block explorer at `explorer_url`, to which the txid is
+    /// appended. By default mempool.space is used.
+    pub fn get_receipt(
+        &self,
+        txid: &bitcoin::Txid,
+        explorer_url: Option<&str>,
+    ) -> Result<GetReceiptResult, CommandError> {
+        let tx = self.get_transaction(txid)?;
+        let (block_height, block_time) = match (tx.height, tx.time) {
+            (Some(height), Some(time)) => (height, time),
+            _ => return Err(CommandError::UnconfirmedTransaction(*txid)),
+        };
+        let network = self.config.bitcoin_config.network;
+
+        // Label the outputs, or else their address.
+        let outpoints: Vec<_> = (0..tx.tx.output.len())
+            .map(|vout| bitcoin::OutPoint::new(*txid, vout as u32))
+            .collect();
+        let addresses: Vec<_> = tx
+            .tx
+            .output
+            .iter()
+            .map(|txo| bitcoin::Address::from_script(&txo.script_pubkey, network).ok())
+            .collect();
+        let items: HashSet<_> = outpoints
+            .iter()
+            .map(|op| LabelItem::OutPoint(*op))
+            .chain(addresses.iter().flatten().cloned().map(LabelItem::Address))
+            .collect();
+        let labels = self.db.connection().labels(&items);
+        let outputs = tx
+            .tx
+            .output
+            .iter()
+            .zip(outpoints)
+            .zip(addresses)
+            .map(|((txo, outpoint), address)| ReceiptOutput {
+                label: labels
+                    .get(&outpoint.to_string())
+                    .or_else(|| {
+                        address
+                            .as_ref()
+                            .and_then(|addr| labels.get(&addr.to_string()))
+                    })
+                    .cloned(),
+                address: address.map(|addr| addr.to_string()),
+                amount: txo.value,
+                is_ours: tx.received_coins.iter().any(|c| c.outpoint == outpoint),
+            })
+            .collect();
+
+        let value =
+            |coins: &[ListCoinsEntry]| coins.iter().map(|c| c.amount.to_sat() as i64).sum::<i64>();
+        let receipt = Receipt {
+            txid: *txid,
+            network,
+            label: tx.label,
+            block_height,
+            block_time,
+            amount: value(&tx.received_coins) - value(&tx.spent_coins),
+            fee: tx.fee,
+            outputs,
+            explorer_link: format!(
+                "{}{}",
+                explorer_url.unwrap_or_else(|| receipt::default_explorer_url(network)),
+                txid
+            ),
+        };
+        let pdf = bitcoin::base64::engine::general_purpose::STANDARD.encode(receipt.to_pdf());
+        Ok(GetReceiptResult { receipt, pdf })
+    }
+
+    /// Get all the transactions of the wallet, for instance to analyze them with another tool.
+    pub fn export_transactions(&self) -> ListTransactionsResult {
+        let txids = self.db.connection().list_saved_txids();
+        self.list_transactions(&txids)
+    }
+
+    /// Get the latest events of interest to the user, optionally only those following the event
+    /// with the given identifier.
+    pub fn list_events(&self, since: Option<u64>) -> ListEventsResult {
+        let events = self.events.lock().unwrap().list(since);
+        ListEventsResult { events }
+    }
+
+    /// Get the recovery readiness checklist of the wallet, along with how much of it is done.
+    pub fn get_recovery_readiness(&self) -> RecoveryReadiness {
+        let mut db_conn = self.db.connection();
+        let completed = db_conn.readiness_checks();
+
+        // Like the event monitor, count the coins whose first recovery path is available within
+        // 10% of its timelock.
+        let timelock = self.config.main_descriptor.first_timelock_value() as i32;
+        let expiring_coins = db_conn
+            .chain_tip()
+            .map(|tip| {
+                db_conn
+                    .coins(&[CoinStatus::Confirmed], &[])
+                    .values()
+                    .filter_map(|coin| coin.block_info)
+                    .filter(|block| (block.height + timelock - tip.height) <= timelock / 10)
+                    .count()
+            })
+            .unwrap_or(0);
+
+        RecoveryReadiness::new(&completed, expiring_coins)
+    }
+
+    /// Get how much value becomes spendable through a recovery path in each of the next ranges
+    /// of `bucket_size` blocks, to plan refreshing the coins in batches. By default the first
+    /// recovery path is used and the ranges are of a week of blocks.
+    pub fn get_recovery_schedule(
+        &self,
+        timelock: Option<u16>,
+        bucket_size: Option<u32>,
+    ) -> Result<GetRecoveryScheduleResult, CommandError> {
+        let mut db_conn = self.db.connection();
+        let tip_height = db_conn.chain_tip().ok_or(CommandError::NotSynced)?.height;
+        let timelock =
+            timelock.unwrap_or_else(|| self.config.main_descriptor.first_timelock_value());
+        let bucket_size = bucket_size
+            .unwrap_or(DEFAULT_RECOVERY_BUCKET_SIZE)
+            .clamp(1, i32::MAX as u32) as i32;
+
+        // Like for createrecovery, a coin is available if it can be spent in the next block.
+        let first_height = tip_height + 2;
+        let mut available = bitcoin::Amount::ZERO;
+        let mut buckets: Vec<RecoveryScheduleBucket> = Vec::new();
+        for coin in db_conn.coins(&[CoinStatus::Confirmed], &[]).into_values() {
+            let height = match coin.block_info {
+                Some(block) => block.height + i32::from(timelock),
+                None => continue,
+            };
+            if height < first_height {
+                available += coin.amount;
+                continue;
+            }
+            let index = ((height - first_height) / bucket_size) as usize;
+            while buckets.len() <= index {
+                let start_height = first_height + buckets.len() as i32 * bucket_size;
+                buckets.push(RecoveryScheduleBucket {
+                    start_height,
+                    end_height: start_height + bucket_size - 1,
+                    amount: bitcoin::Amount::ZERO,
+                    coins: 0,
+                });
+            }
+            buckets[index].amount += coin.amount;
+            buckets[index].coins += 1;
+        }
+
+        Ok(GetRecoveryScheduleResult {
+            timelock,
+            tip_height,
+            available,
+            buckets,
+        })
+    }
+
+    /// Mark an item of the recovery readiness checklist as done, or not done anymore.
+    pub fn set_recovery_readiness(
+        &self,
+        item: ReadinessItem,
+        done: bool,
+    ) -> Result<(), CommandError> {
+        if !item.is_attested() {
+            return Err(CommandError::ComputedReadinessItem(item));
+        }
+        self.db.connection().set_readiness_check(item, done);
+        Ok(())
+    }
+
+    /// Store a spend template, replacing the one with the same name if any.
+    pub fn save_spend_template(&self, template: SpendTemplate) -> Result<(), CommandError> {
+        template
+            .validate(self.config.bitcoin_config.network)
+            .map_err(CommandError::InvalidSpendTemplate)?;
+        self.db.connection().store_spend_template(&template);
+        self.events.lock().unwrap().push(Event::SettingsChanged {
+            change: format!("Spend template '{}' saved.", template.name),
+        });
+        Ok(())
+    }
+
+    pub fn list_spend_templates(&self) -> ListSpendTemplatesResult {
+        let templates = self.db.connection().spend_templates();
+        ListSpendTemplatesResult { templates }
+    }
+
+    pub fn delete_spend_template(&self, name: &str) {
+        self.db.connection().delete_spend_template(name);
+        self.events.lock().unwrap().push(Event::SettingsChanged {
+            change: format!("Spend template '{}' deleted.", name),
+        });
+    }
+
+    /// Create a Spend transaction from a template. The `amounts` must be given for the
+    /// recipients of the template whose amount is asked, and may override the others. The
+    /// `feerate_vb` overrides the preferred feerate of the template.
+    pub fn instantiate_spend_template(
+        &self,
+        name: &str,
+        amounts: &HashMap<bitcoin::Address<address::NetworkUnchecked>, u64>,
+        feerate_vb: Option<u64>,
+    ) -> Result<CreateSpendResult, CommandError> {
+        let template = self
+            .db
+            .connection()
+            .spend_templates()
+            .into_iter()
+            .find(|t| t.name == name)
+            .ok_or_else(|| CommandError::UnknownSpendTemplate(name.to_string()))?;
+        let destinations = template
+            .destinations(amounts)
+            .map_err(CommandError::InvalidSpendTemplate)?;
+        self.create_spend(
+            &destinations,
+            &template.coins,
+            feerate_vb.unwrap_or(template.feerate),
+            None,
+            &CreateSpendOptions::default(),
+        )
+    }
+
+    /// Create and store a draft for each of the scheduled spend templates which is due at this
+    /// time. The draft is labelled after the template. Returns the txids of the drafts created.
+    pub fn create_scheduled_drafts(&self, now: u32) -> Vec<bitcoin::Txid> {
+        let mut txids = Vec::new();
+        let templates = self.db.connection().spend_templates();
+        for mut template in templates {
+            match &template.schedule {
+                Some(schedule) if schedule.is_due(now) => {}
+                _ => continue,
+            }
+            match self.instantiate_spend_template(&template.name, &HashMap::new(), None) {
+                Ok(CreateSpendResult::Success { psbt, .. }) => {
+                    let txid = psbt.unsigned_tx.compute_txid();
+                    log::info!(
+                        "Created draft '{}' from scheduled template '{}'.",
+                        txid,
+                        template.name
+                    );
+                    let mut db_conn = self.db.connection();
+                    db_conn.store_spend(&psbt);
+                    db_conn.update_labels(&HashMap::from([(
+                        LabelItem::Txid(txid),
+                        Some(template.name.clone()),
+                    )]));
+                    if let Some(schedule) = template.schedule.as_mut() {
+                        schedule.last_draft_at = Some(now);
+                    }
+                    db_conn.store_spend_template(&template);
+                    txids.push(txid);
+                }
+                // We'll try again next time, the coins may have been received by then.
+                Ok(CreateSpendResult::InsufficientFunds { missing }) => log::warn!(
+                    "Not enough funds to create a draft from scheduled template '{}', missing \
+                     {} sats.",
+                    template.name,
+                    missing
+                ),
+                Err(e) => log::error!(
+                    "Error creating a draft from scheduled template '{}': {}",
+                    template.name,
+                    e
+                ),
+            }
+        }
+        txids
+    }
+
     /// Create a transaction that sweeps all coins for which a timelocked recovery path is
     /// currently available to a provided address with the provided feerate.
     ///
@@ -1083,10 +3134,8 @@ impl DaemonControl {
         feerate_vb: u64,
         timelock: Option<u16>,
     ) -> Result<CreateRecoveryResult, CommandError> {
-        if feerate_vb < 1 {
-            return Err(CommandError::InvalidFeerate(feerate_vb));
-        }
-        let mut tx_getter = DbTxGetter::new(&self.db);
+        self.check_feerate(feerate_vb)?;
+        let mut tx_getter = DbTxGetter::new(&self.db, &self.bitcoin);
         let mut db_conn = self.db.connection();
         let sweep_addr = self.spend_addr(&mut db_conn, self.validate_address(address)?);
 
@@ -1157,10 +3206,115 @@ pub struct GetInfoResult {
     pub descriptors: GetInfoDescriptors,
     /// The progress as a percentage (between 0 and 1) of an ongoing rescan if there is any
     pub rescan_progress: Option<f64>,
+    /// Height up to which the ongoing rescan is known to have processed the chain, if any. An
+    /// interrupted rescan is resumed from there.
+    pub rescan_checkpoint: Option<i32>,
     /// Timestamp at wallet creation date
     pub timestamp: u32,
     /// Timestamp of last poll, if any.
     pub last_poll_timestamp: Option<u32>,
+    /// Statistics about the polls since startup. `None` if the wallet isn't polled by this
+    /// daemon.
+    pub poller: Option<PollStats>,
+}
+
+/// The total value of the coins of the wallet which aren't spent yet, by status.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GetBalanceResult {
+    #[serde(
+        serialize_with = "ser_amount",
+        deserialize_with = "deser_amount_from_sats"
+    )]
+    pub confirmed: bitcoin::Amount,
+    /// Unconfirmed coins created by a transaction spending only coins of the wallet.
+    #[serde(
+        serialize_with = "ser_amount",
+        deserialize_with = "deser_amount_from_sats"
+    )]
+    pub unconfirmed_from_self: bitcoin::Amount,
+    #[serde(
+        serialize_with = "ser_amount",
+        deserialize_with = "deser_amount_from_sats"
+    )]
+    pub unconfirmed: bitcoin::Amount,
+    /// Coinbase deposits which can't be spent yet.
+    #[serde(
+        serialize_with = "ser_amount",
+        deserialize_with = "deser_amount_from_sats"
+    )]
+    pub immature: bitcoin::Amount,
+    /// Coins spent by an unconfirmed transaction.
+    #[serde(
+        serialize_with = "ser_amount",
+        deserialize_with = "deser_amount_from_sats"
+    )]
+    pub spending: bitcoin::Amount,
+}
+
+/// The maximum virtual size of the transactions in a block.
+pub const MAX_BLOCK_VSIZE: u64 = 1_000_000;
+
+/// The state of the mempool of the backend.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GetMempoolStatsResult {
+    /// The total virtual size of the transactions in the mempool by feerate bucket, from the
+    /// highest feerate to the lowest. Empty if the backend doesn't expose it.
+    pub fee_histogram: Vec<FeeHistogramBucket>,
+    /// The minimum feerate in sats/vb for a transaction to be relayed, if the backend exposes it.
+    pub min_relay_feerate: Option<u64>,
+    /// The minimum feerate in sats/vb for a transaction to enter the mempool at the moment, if
+    /// the backend exposes it.
+    pub mempool_min_feerate: Option<u64>,
+}
+
+impl GetMempoolStatsResult {
+    /// The number of blocks it would take for a transaction paying this feerate in sats/vb to be
+    /// mined, 1 being the next block, assuming miners pick the transactions paying the highest
+    /// feerate first and no other transaction enters the mempool meanwhile. `None` if the
+    /// histogram is unknown or the transaction would not be accepted in the mempool.
+    pub fn projected_blocks(&self, feerate: u64) -> Option<u64> {
+        if self.fee_histogram.is_empty()
+            || self
+                .mempool_min_feerate
+                .is_some_and(|min_feerate| feerate < min_feerate)
+        {
+            return None;
+        }
+        // The transactions in the bucket of this feerate may pay more or less than it. Be
+        // conservative and assume they all pay more.
+        let vsize_ahead: u64 = self
+            .fee_histogram
+            .iter()
+            .take_while(|bucket| bucket.feerate > feerate)
+            .chain(
+                self.fee_histogram
+                    .iter()
+                    .find(|bucket| bucket.feerate <= feerate),
+            )
+            .map(|bucket| bucket.vsize)
+            .sum();
+        Some(vsize_ahead / MAX_BLOCK_VSIZE + 1)
+    }
+
+    /// The lowest feerate in sats/vb for a transaction to be mined within this number of blocks,
+    /// as projected by [`Self::projected_blocks`]. `None` if the histogram is unknown.
+    pub fn lowest_feerate_within(&self, blocks: u64) -> Option<u64> {
+        let min_feerate = self
+            .mempool_min_feerate
+            .or(self.min_relay_feerate)
+            .unwrap_or(1)
+            .max(1);
+        let max_feerate = self.fee_histogram.first()?.feerate.max(min_feerate);
+        (min_feerate..=max_feerate).find(|feerate| {
+            self.projected_blocks(*feerate)
+                .is_some_and(|projected| projected <= blocks)
+        })
+    }
+
+    /// The total virtual size of the transactions in the mempool.
+    pub fn mempool_vsize(&self) -> u64 {
+        self.fee_histogram.iter().map(|bucket| bucket.vsize).sum()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1179,11 +3333,108 @@ impl GetAddressResult {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateAddressStatementResult {
+    /// The statement to sign.
+    pub message: String,
+    /// The PSBT to sign the statement with the signers of the primary path.
+    #[serde(serialize_with = "ser_to_string", deserialize_with = "deser_fromstr")]
+    pub psbt: Psbt,
+}
+
+/// An address statement along with its BIP322 signature for its first address.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedAddressStatement {
+    #[serde(deserialize_with = "deser_addr_assume_checked")]
+    pub address: bitcoin::Address,
+    pub message: String,
+    /// The base64-encoded witness of the BIP322 "simple" signature.
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatementAddress {
+    pub index: u32,
+    #[serde(deserialize_with = "deser_addr_assume_checked")]
+    pub address: bitcoin::Address,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyAddressStatementResult {
+    pub addresses: Vec<StatementAddress>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GetSilentPaymentAddressResult {
+    pub address: String,
+    /// Master fingerprint of the hot signer the keys of the address are derived from.
+    pub fingerprint: bip32::Fingerprint,
+    /// Height up to which the chain was scanned for payments to the address.
+    pub scanned_height: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListSilentPaymentsEntry {
+    pub outpoint: bitcoin::OutPoint,
+    #[serde(
+        serialize_with = "ser_amount",
+        deserialize_with = "deser_amount_from_sats"
+    )]
+    pub amount: bitcoin::Amount,
+    pub block_height: i32,
+    /// Information about the transaction spending this payment.
+    pub spend_info: Option<LCSpendInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListSilentPaymentsResult {
+    pub payments: Vec<ListSilentPaymentsEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepSilentPaymentsResult {
+    pub txid: bitcoin::Txid,
+    /// The receive address of the wallet the payments were swept to.
+    #[serde(deserialize_with = "deser_addr_assume_checked")]
+    pub address: bitcoin::Address,
+    /// The value received at this address.
+    #[serde(
+        serialize_with = "ser_amount",
+        deserialize_with = "deser_amount_from_sats"
+    )]
+    pub amount: bitcoin::Amount,
+    #[serde(
+        serialize_with = "ser_amount",
+        deserialize_with = "deser_amount_from_sats"
+    )]
+    pub fee: bitcoin::Amount,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GetLabelsResult {
     pub labels: HashMap<String, String>,
 }
 
+/// The transaction of a record of a CSV file of labels.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LabelsCsvRecord {
+    /// The line of the file the record starts on.
+    pub line: usize,
+    pub txid: bitcoin::Txid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImportLabelsCsvResult {
+    /// How many transactions were labelled.
+    pub imported: usize,
+    /// The records for transactions which kept the label they already had.
+    pub already_labelled: Vec<LabelsCsvRecord>,
+    /// The records for transactions which aren't transactions of the wallet.
+    pub unmatched: Vec<LabelsCsvRecord>,
+    /// The records which couldn't be read.
+    pub rejected: Vec<RejectedRecord>,
+}
+
 #[derive(Debug, Clone, Serialize, PartialEq, Eq)]
 pub struct AddressInfo {
     index: u32,
@@ -1202,6 +3453,193 @@ impl ListAddressesResult {
     }
 }
 
+/// Bitcoin Core's default limit on the number of unconfirmed ancestors, or descendants, of a
+/// transaction in its mempool. The transaction itself is counted.
+const MEMPOOL_PACKAGE_COUNT_LIMIT: u64 = 25;
+
+/// Bitcoin Core's default limit on the total size, in vbytes, of the unconfirmed ancestors, or
+/// descendants, of a transaction in its mempool. The transaction itself is counted.
+const MEMPOOL_PACKAGE_VSIZE_LIMIT: u64 = 101_000;
+
+/// Maximum increase of a derivation index through `updatederivationindexes`, not to derive too
+/// many addresses at once.
+pub const MAX_DERIVATION_INDEX_INCREASE: u32 = 1_000;
+
+/// Default size of the ranges of blocks of `getrecoveryschedule`: about a week.
+pub const DEFAULT_RECOVERY_BUCKET_SIZE: u32 = 1_008;
+
+/// How the addresses of the receive or the change descriptor are used.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DerivationIndexStats {
+    /// The derivation index of the next address to be generated.
+    pub next_index: u32,
+    /// The highest derivation index of an address which received a coin.
+    pub highest_used_index: Option<u32>,
+    /// The ranges of consecutive generated addresses which never received a coin, as inclusive
+    /// `[first, last]` derivation indexes.
+    pub gaps: Vec<[u32; 2]>,
+    /// The number of addresses in the largest gap.
+    pub largest_gap: u32,
+}
+
+impl DerivationIndexStats {
+    /// Compute the statistics given the next derivation index and the indexes of the addresses
+    /// which received a coin.
+    pub fn new(next_index: u32, used: &BTreeSet<u32>) -> Self {
+        let mut gaps = Vec::new();
+        let mut gap_start = 0;
+        for index in used.range(..next_index) {
+            if *index > gap_start {
+                gaps.push([gap_start, index - 1]);
+            }
+            gap_start = index + 1;
+        }
+        if next_index > gap_start {
+            gaps.push([gap_start, next_index - 1]);
+        }
+        let largest_gap = gaps.iter().map(|[a, b]| b - a + 1).max().unwrap_or(0);
+        DerivationIndexStats {
+            next_index,
+            highest_used_index: used.iter().next_back().copied(),
+            gaps,
+            largest_gap,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GetDerivationIndexesResult {
+    pub receive: DerivationIndexStats,
+    pub change: DerivationIndexStats,
+    /// How many addresses are watched from the highest of the next derivation indexes.
+    pub lookahead: u32,
+    /// The highest derivation index of the addresses watched for coins.
+    pub watched_up_to: u32,
+}
+
+/// Minimum delay since the last poll of the Bitcoin backend before `healthcheck` reports it.
+const MIN_POLL_DELAY_SECS: u64 = 10 * 60;
+
+/// The gap limit most wallets use when looking for the coins of a descriptor.
+const COMMON_GAP_LIMIT: u32 = 20;
+
+/// The outcome of a check of `healthcheck`, from the best to the worst.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Ok,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HealthCheck {
+    /// What is checked.
+    pub name: String,
+    pub status: HealthStatus,
+    /// The problems found, if any.
+    pub messages: Vec<String>,
+}
+
+impl HealthCheck {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            status: HealthStatus::Ok,
+            messages: Vec::new(),
+        }
+    }
+
+    // Record a problem, the status of the check being the one of its worst problem.
+    fn add(&mut self, status: HealthStatus, message: String) {
+        self.status = self.status.max(status);
+        self.messages.push(message);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HealthCheckResult {
+    /// The status of the worst check.
+    pub status: HealthStatus,
+    pub checks: Vec<HealthCheck>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LintWarningEntry {
+    /// What kind of risky construction this is.
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LintDescriptorResult {
+    pub warnings: Vec<LintWarningEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HotSignerXpub {
+    #[serde(serialize_with = "ser_to_string", deserialize_with = "deser_fromstr")]
+    pub derivation_path: bip32::DerivationPath,
+    pub xpub: bip32::Xpub,
+    /// The xpub with its origin, as used in descriptors.
+    pub key: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HotSignerXpubsResult {
+    pub fingerprint: bip32::Fingerprint,
+    pub xpubs: Vec<HotSignerXpub>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignPsbtResult {
+    /// The master fingerprint of the hot signer which signed.
+    pub fingerprint: bip32::Fingerprint,
+    #[serde(serialize_with = "ser_to_string", deserialize_with = "deser_fromstr")]
+    pub psbt: Psbt,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignerLossPath {
+    /// The timelock of the recovery path, `None` for the primary path.
+    pub timelock: Option<u16>,
+    pub threshold: usize,
+    pub keys: usize,
+    /// How many keys of the path don't belong to a lost signer.
+    pub remaining_keys: usize,
+    pub usable: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignerLossCoin {
+    pub outpoint: bitcoin::OutPoint,
+    #[serde(
+        serialize_with = "ser_amount",
+        deserialize_with = "deser_amount_from_sats"
+    )]
+    pub amount: bitcoin::Amount,
+    /// Whether the coin can be spent in the next block with the remaining signers.
+    pub spendable_now: bool,
+    /// The height from which the coin can be spent with the remaining signers, if it is
+    /// confirmed and can still be spent at all.
+    pub spendable_at_height: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignerLossResult {
+    /// The primary path first, then the recovery paths by increasing timelock.
+    pub paths: Vec<SignerLossPath>,
+    /// How many blocks after its confirmation a coin (including future deposits) can be spent
+    /// with the remaining signers. `None` if coins can't be spent anymore.
+    pub spendable_after: Option<u16>,
+    pub coins: Vec<SignerLossCoin>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImportHotSignerResult {
+    pub fingerprint: bip32::Fingerprint,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct LCSpendInfo {
     pub txid: bitcoin::Txid,
@@ -1229,6 +3667,10 @@ pub struct ListCoinsEntry {
     pub spend_info: Option<LCSpendInfo>,
     /// Whether this coin was created by a coinbase transaction that is still immature.
     pub is_immature: bool,
+    /// For an immature coin, the height of the first block which may include a transaction
+    /// spending it. `None` if the coinbase transaction is unconfirmed.
+    #[serde(default)]
+    pub maturity_height: Option<i32>,
     /// Whether the coin deposit address was derived from the change descriptor.
     pub is_change: bool,
     /// Whether the coin is the output of a transaction whose inputs are all from
@@ -1242,6 +3684,15 @@ pub struct ListCoinsResult {
     pub coins: Vec<ListCoinsEntry>,
 }
 
+/// Options for the creation of a Spend transaction.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CreateSpendOptions {
+    /// Deduct the fee from the value of these destinations instead of paying it on top of it.
+    pub subtract_fee_from: Vec<bitcoin::Address<bitcoin::address::NetworkUnchecked>>,
+    /// Allow paying to a witness version which isn't defined yet.
+    pub allow_future_witness: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(untagged)]
 pub enum CreateSpendResult {
@@ -1255,11 +3706,60 @@ pub enum CreateSpendResult {
     },
 }
 
+/// The status of a stored Spend transaction, as used to filter them in the `listspendtxs` command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpendTxStatus {
+    /// Doesn't have enough signatures yet for any of its available spending paths.
+    NeedsSignature,
+    /// Has enough signatures for one of its available spending paths.
+    FullySigned,
+    /// Fully signed, its timelocks are met and none of its coins is spent by a confirmed
+    /// transaction. It may replace an unconfirmed one.
+    Broadcastable,
+    /// One of its coins is unknown or spent by another transaction.
+    Conflicting,
+}
+
+impl SpendTxStatus {
+    pub fn from_arg(s: &str) -> Option<SpendTxStatus> {
+        match s {
+            "needs_signature" => Some(SpendTxStatus::NeedsSignature),
+            "fully_signed" => Some(SpendTxStatus::FullySigned),
+            "broadcastable" => Some(SpendTxStatus::Broadcastable),
+            "conflicting" => Some(SpendTxStatus::Conflicting),
+            _ => None,
+        }
+    }
+
+    /// Converts a `SpendTxStatus` to its equivalent argument name
+    /// as used in the `listspendtxs` RPC command.
+    pub fn to_arg(&self) -> &'static str {
+        match self {
+            SpendTxStatus::NeedsSignature => "needs_signature",
+            SpendTxStatus::FullySigned => "fully_signed",
+            SpendTxStatus::Broadcastable => "broadcastable",
+            SpendTxStatus::Conflicting => "conflicting",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListSpendEntry {
     #[serde(serialize_with = "ser_to_string", deserialize_with = "deser_fromstr")]
     pub psbt: Psbt,
     pub updated_at: Option<u32>,
+    /// The other stored Spend transactions spending some of the same coins.
+    #[serde(default)]
+    pub conflicting_spends: Vec<bitcoin::Txid>,
+    /// The confirmed transaction which spent one of its coins, if any. It can't be broadcast
+    /// anymore.
+    #[serde(default)]
+    pub invalidated_by: Option<bitcoin::Txid>,
+    /// The latest review of each signer, in the order they were made.
+    #[serde(default)]
+    pub reviews: Vec<SpendReview>,
+    #[serde(default)]
+    pub review_state: ReviewState,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1267,11 +3767,45 @@ pub struct ListSpendResult {
     pub spend_txs: Vec<ListSpendEntry>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledSpendEntry {
+    pub txid: bitcoin::Txid,
+    /// The fully signed transaction.
+    #[serde(serialize_with = "ser_hex", deserialize_with = "deser_hex")]
+    pub tx: bitcoin::Transaction,
+    /// The locktime of the transaction, as a block height or a timestamp.
+    pub locktime: u32,
+    /// When it was scheduled.
+    pub scheduled_at: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FinalizePsbtResult {
+    /// Whether a witness can be constructed for all the inputs.
+    pub complete: bool,
+    pub inputs: Vec<descriptors::InputFinalization>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListScheduledSpendsResult {
+    pub scheduled_spends: Vec<ScheduledSpendEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListSpendTemplatesResult {
+    pub templates: Vec<SpendTemplate>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListTransactionsResult {
     pub transactions: Vec<TransactionInfo>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListEventsResult {
+    pub events: Vec<EventEntry>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionInfo {
     #[serde(serialize_with = "ser_hex", deserialize_with = "deser_hex")]
@@ -1280,6 +3814,69 @@ pub struct TransactionInfo {
     pub time: Option<u32>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetTransactionResult {
+    #[serde(serialize_with = "ser_hex", deserialize_with = "deser_hex")]
+    pub tx: bitcoin::Transaction,
+    pub height: Option<i32>,
+    pub time: Option<u32>,
+    /// The PSBT stored for this transaction, if it's one of our Spend transactions.
+    #[serde(
+        serialize_with = "ser_opt_to_string",
+        deserialize_with = "deser_opt_fromstr"
+    )]
+    pub psbt: Option<Psbt>,
+    /// Our coins spent by this transaction.
+    pub spent_coins: Vec<ListCoinsEntry>,
+    /// Our coins created by this transaction.
+    pub received_coins: Vec<ListCoinsEntry>,
+    /// The fee paid by this transaction, if all its inputs are our coins.
+    #[serde(with = "bitcoin::amount::serde::as_sat::opt")]
+    pub fee: Option<bitcoin::Amount>,
+    pub label: Option<String>,
+    /// Whether a proof of the inclusion of this transaction in its block was verified, for the
+    /// Bitcoin backends which don't validate the chain. `None` if it wasn't checked.
+    pub inclusion_verified: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetReceiptResult {
+    pub receipt: Receipt,
+    /// The receipt as a PDF document, encoded in base64.
+    pub pdf: String,
+}
+
+/// Value becoming spendable through a recovery path within a range of blocks.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RecoveryScheduleBucket {
+    /// First block of the range.
+    pub start_height: i32,
+    /// Last block of the range.
+    pub end_height: i32,
+    #[serde(
+        serialize_with = "ser_amount",
+        deserialize_with = "deser_amount_from_sats"
+    )]
+    pub amount: bitcoin::Amount,
+    /// Number of coins.
+    pub coins: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GetRecoveryScheduleResult {
+    /// The timelock of the recovery path.
+    pub timelock: u16,
+    pub tip_height: i32,
+    /// Value of the coins already spendable through the recovery path.
+    #[serde(
+        serialize_with = "ser_amount",
+        deserialize_with = "deser_amount_from_sats"
+    )]
+    pub available: bitcoin::Amount,
+    /// The ranges of blocks from the next one until the last coin becomes spendable.
+    pub buckets: Vec<RecoveryScheduleBucket>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct CreateRecoveryResult {
     #[serde(serialize_with = "ser_to_string", deserialize_with = "deser_fromstr")]
@@ -1289,12 +3886,20 @@ pub struct CreateRecoveryResult {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{bitcoin::Block, database::BlockInfo, testutils::*};
-    use liana::spend::InsaneFeeInfo;
+    use crate::{
+        bitcoin::{
+            fee_histogram, Block, BlockChainTip, BlockTransactions, MempoolEntry, MempoolEntryFees,
+            MinFeerates,
+        },
+        database::{BlockInfo, SilentPayment},
+        testutils::*,
+    };
+    use liana::spend::{CreateSpendWarning, InsaneFeeInfo};
 
     use bitcoin::{
         bip32::{self, ChildNumber},
         blockdata::transaction::{TxIn, TxOut, Version as TxVersion},
+        hashes::Hash,
         locktime::absolute,
         Amount, OutPoint, ScriptBuf, Sequence, Transaction, Txid, Witness,
     };
@@ -1331,116 +3936,741 @@ mod tests {
     }
 
     #[test]
-    fn listaddresses() {
+    fn listaddresses() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+
+        let control = &ms.control();
+
+        let list = control.list_addresses(Some(2), Some(5)).unwrap();
+
+        assert_eq!(list.addresses[0].index, 2);
+        assert_eq!(list.addresses.last().unwrap().index, 6);
+
+        let addr0 = control.get_new_address().address;
+        let addr1 = control.get_new_address().address;
+        let _addr2 = control.get_new_address().address;
+        let addr3 = control.get_new_address().address;
+        let addr4 = control.get_new_address().address;
+
+        let list = control.list_addresses(Some(0), None).unwrap();
+
+        assert_eq!(list.addresses[0].index, 0);
+        assert_eq!(list.addresses[0].receive, addr0);
+        assert_eq!(list.addresses.last().unwrap().index, 4);
+        assert_eq!(list.addresses.last().unwrap().receive, addr4);
+
+        let list = control.list_addresses(None, None).unwrap();
+
+        assert_eq!(list.addresses[0].index, 0);
+        assert_eq!(list.addresses[0].receive, addr0);
+        assert_eq!(list.addresses.last().unwrap().index, 4);
+        assert_eq!(list.addresses.last().unwrap().receive, addr4);
+
+        let list = control.list_addresses(Some(1), Some(3)).unwrap();
+
+        assert_eq!(list.addresses[0].index, 1);
+        assert_eq!(list.addresses[0].receive, addr1);
+        assert_eq!(list.addresses.last().unwrap().index, 3);
+        assert_eq!(list.addresses.last().unwrap().receive, addr3);
+
+        let addr5 = control.get_new_address().address;
+        let list = control.list_addresses(Some(5), None).unwrap();
+
+        assert_eq!(list.addresses[0].index, 5);
+        assert_eq!(list.addresses[0].receive, addr5);
+        assert_eq!(list.addresses.last().unwrap().index, 5);
+        assert_eq!(list.addresses.last().unwrap().receive, addr5);
+
+        // We can get no address for the last unhardened index.
+        let max_unhardened_index = 2u32.pow(31) - 1;
+        let res = control
+            .list_addresses(Some(max_unhardened_index), Some(0))
+            .unwrap();
+        // This is equivalent to not passing a count.
+        assert_eq!(
+            res,
+            control
+                .list_addresses(Some(max_unhardened_index), None)
+                .unwrap()
+        );
+        // We can also get the one last unhardened index.
+        control
+            .list_addresses(Some(max_unhardened_index), Some(1))
+            .unwrap();
+        // However we can't get into hardened territory.
+        assert_eq!(
+            control
+                .list_addresses(Some(max_unhardened_index), Some(2))
+                .unwrap_err(),
+            CommandError::InvalidDerivationIndex
+        );
+
+        // We also can't pass a hardened start index.
+        let first_hardened_index = max_unhardened_index + 1;
+        assert_eq!(
+            control
+                .list_addresses(Some(first_hardened_index), None)
+                .unwrap_err(),
+            CommandError::InvalidDerivationIndex
+        );
+        assert_eq!(
+            control
+                .list_addresses(Some(first_hardened_index), Some(0))
+                .unwrap_err(),
+            CommandError::InvalidDerivationIndex
+        );
+        assert_eq!(
+            control
+                .list_addresses(Some(first_hardened_index), Some(1))
+                .unwrap_err(),
+            CommandError::InvalidDerivationIndex
+        );
+
+        // Much less so overflow.
+        assert_eq!(
+            control.list_addresses(Some(u32::MAX), None).unwrap_err(),
+            CommandError::InvalidDerivationIndex
+        );
+        assert_eq!(
+            control.list_addresses(Some(u32::MAX), Some(0)).unwrap_err(),
+            CommandError::InvalidDerivationIndex
+        );
+        assert_eq!(
+            control.list_addresses(Some(u32::MAX), Some(1)).unwrap_err(),
+            CommandError::InvalidDerivationIndex
+        );
+
+        // We won't crash if we pass a start index larger than the next derivation index without
+        // passing a count. (ie no underflow.)
+        let next_deriv_index = list.addresses.last().unwrap().index + 1;
+        control
+            .list_addresses(Some(next_deriv_index + 1), None)
+            .unwrap();
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn derivation_indexes() {
+        let stats = DerivationIndexStats::new(10, &BTreeSet::from([0, 3, 4, 7]));
+        assert_eq!(stats.highest_used_index, Some(7));
+        assert_eq!(stats.gaps, vec![[1, 2], [5, 6], [8, 9]]);
+        assert_eq!(stats.largest_gap, 2);
+        let stats = DerivationIndexStats::new(3, &BTreeSet::new());
+        assert_eq!((stats.gaps, stats.largest_gap), (vec![[0, 2]], 3));
+        let stats = DerivationIndexStats::new(0, &BTreeSet::new());
+        assert_eq!((stats.highest_used_index, stats.largest_gap), (None, 0));
+
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = &ms.control();
+        let res = control.update_derivation_indexes(Some(50), None).unwrap();
+        assert_eq!((res.receive.next_index, res.change.next_index), (50, 0));
+        assert_eq!(res.watched_up_to, 50 + LOOK_AHEAD_LIMIT - 1);
+        // Lower indexes are ignored.
+        assert_eq!(
+            control.update_derivation_indexes(Some(10), Some(0)),
+            Ok(res)
+        );
+        assert_eq!(
+            control.update_derivation_indexes(None, Some(1_001)),
+            Err(CommandError::DerivationIndexIncrease(1_000))
+        );
+        assert_eq!(
+            control.update_derivation_indexes(Some(2u32.pow(31)), None),
+            Err(CommandError::InvalidDerivationIndex)
+        );
+        assert_eq!(control.get_derivation_indexes().receive.next_index, 50);
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn lint_descriptor() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = &ms.control();
+
+        // The dummy descriptor uses keys from the same signer in both its spending paths.
+        let res = control.lint_descriptor(None, None, None);
+        let codes: Vec<_> = res.warnings.iter().map(|w| w.code.as_str()).collect();
+        assert_eq!(codes, vec!["recovery_with_primary_keys"]);
+        let res = control.lint_descriptor(None, Some(10_001), Some(9_999));
+        let codes: Vec<_> = res.warnings.iter().map(|w| w.code.as_str()).collect();
+        assert_eq!(
+            codes,
+            vec![
+                "short_timelock",
+                "long_timelock",
+                "recovery_with_primary_keys"
+            ]
+        );
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn get_hot_signer_xpubs() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = &ms.control();
+        let secp = secp256k1::Secp256k1::signing_only();
+        let path = bip32::DerivationPath::from_str("m/48'/0'/1'/2'").unwrap();
+
+        assert_eq!(
+            control.get_hot_signer_xpubs(None, &[path.clone()]),
+            Err(CommandError::NoHotSigner)
+        );
+
+        let data_dir = control.config.data_dir().unwrap();
+        let network = control.config.bitcoin_config.network;
+        let signer = HotSigner::from_str(
+            network,
+            "burger ball theme dog light account produce chest warrior swarm flip equip",
+        )
+        .unwrap();
+        signer.store(&data_dir, network, &secp).unwrap();
+        let fingerprint = signer.fingerprint(&secp);
+
+        let res = control.get_hot_signer_xpubs(None, &[path.clone()]).unwrap();
+        assert_eq!(res.fingerprint, fingerprint);
+        assert_eq!(res.xpubs.len(), 1);
+        assert_eq!(res.xpubs[0].xpub, signer.xpub_at(&path, &secp));
+        assert_eq!(
+            res.xpubs[0].key,
+            format!("[{}/48'/0'/1'/2']{}", fingerprint, res.xpubs[0].xpub)
+        );
+        assert_eq!(
+            control.get_hot_signer_xpubs(Some(fingerprint), &[path]),
+            Ok(res)
+        );
+
+        // Only hardened derivation paths are allowed.
+        let path = bip32::DerivationPath::from_str("m/48'/0'/1'/2").unwrap();
+        assert_eq!(
+            control.get_hot_signer_xpubs(None, &[path.clone()]),
+            Err(CommandError::InvalidDerivationIndex)
+        );
+
+        // A second hot signer must be selected by its fingerprint.
+        let other = HotSigner::generate(network).unwrap();
+        other.store(&data_dir, network, &secp).unwrap();
+        let path = bip32::DerivationPath::from_str("m/84'/0'/0'").unwrap();
+        assert_eq!(
+            control.get_hot_signer_xpubs(None, &[path.clone()]),
+            Err(CommandError::AmbiguousHotSigner)
+        );
+        let res = control
+            .get_hot_signer_xpubs(Some(other.fingerprint(&secp)), &[path])
+            .unwrap();
+        assert_eq!(res.fingerprint, other.fingerprint(&secp));
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn import_hot_signer() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = &ms.control();
+        let secp = secp256k1::Secp256k1::signing_only();
+        let path = bip32::DerivationPath::from_str("m/48'/0'/0'/2'").unwrap();
+
+        // From a BIP39 mnemonic.
+        let mnemonic = "burger ball theme dog light account produce chest warrior swarm flip equip";
+        let res = control.import_hot_signer(mnemonic).unwrap();
+        let signer = HotSigner::from_str(control.config.bitcoin_config.network, mnemonic).unwrap();
+        assert_eq!(res.fingerprint, signer.fingerprint(&secp));
+        assert_eq!(
+            control
+                .get_hot_signer_xpubs(Some(res.fingerprint), &[path.clone()])
+                .unwrap()
+                .xpubs[0]
+                .xpub,
+            signer.xpub_at(&path, &secp)
+        );
+        // Importing it again is fine.
+        assert_eq!(control.import_hot_signer(mnemonic), Ok(res));
+
+        // From SLIP-39 shares (2-of-3, from the SLIP-39 test vectors).
+        let shares = "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed\n\
+                      shadow pistol academic acid actress prayer class unknown daughter sweater depict flip twice unkind craft early superior advocate guest smoking";
+        let res = control.import_hot_signer(shares).unwrap();
+        assert_ne!(res.fingerprint, signer.fingerprint(&secp));
+        assert_eq!(
+            control
+                .get_hot_signer_xpubs(Some(res.fingerprint), &[path.clone()])
+                .unwrap()
+                .fingerprint,
+            res.fingerprint
+        );
+
+        // Not enough shares.
+        assert!(matches!(
+            control.import_hot_signer(shares.lines().next().unwrap()),
+            Err(CommandError::InvalidHotSignerBackup(..))
+        ));
+        assert!(matches!(
+            control.import_hot_signer("burger ball theme"),
+            Err(CommandError::InvalidHotSignerBackup(..))
+        ));
+
+        ms.shutdown();
+
+        // An ephemeral wallet doesn't store any.
+        let ms = DummyLiana::new_with_config(DummyBitcoind::new(), DummyDatabase::new(), |c| {
+            c.ephemeral = true
+        });
+        let control = &ms.control();
+        assert_eq!(
+            control.import_hot_signer(mnemonic),
+            Err(CommandError::EphemeralWallet)
+        );
+        assert!(matches!(
+            control.get_hot_signer_xpubs(None, &[path]),
+            Err(CommandError::EphemeralWallet)
+        ));
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn import_labels_csv() {
         let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
-
         let control = &ms.control();
+        let txs: Vec<_> = (1..=3)
+            .map(|height| bitcoin::Transaction {
+                version: TxVersion::TWO,
+                lock_time: absolute::LockTime::from_height(height).unwrap(),
+                input: vec![],
+                output: vec![],
+            })
+            .collect();
+        let txids: Vec<_> = txs.iter().map(|tx| tx.compute_txid()).collect();
+        control.db().lock().unwrap().connection().new_txs(&txs[..2]);
+        let mut labels = HashMap::new();
+        labels.insert(LabelItem::Txid(txids[1]), Some("Old".to_string()));
+        control.update_labels(&labels);
+
+        let csv = format!(
+            "txid,label,note\n{},Rent,March\n{},Salary,\n{},Coffee,\nnotatxid,Lunch,\n",
+            txids[0], txids[1], txids[2]
+        );
+        let res = control.import_labels_csv(&csv, false).unwrap();
+        assert_eq!(res.imported, 1);
+        assert_eq!(
+            res.already_labelled,
+            vec![LabelsCsvRecord {
+                line: 3,
+                txid: txids[1]
+            }]
+        );
+        assert_eq!(
+            res.unmatched,
+            vec![LabelsCsvRecord {
+                line: 4,
+                txid: txids[2]
+            }]
+        );
+        assert_eq!(
+            res.rejected.iter().map(|r| r.line).collect::<Vec<_>>(),
+            vec![5]
+        );
+        let items: HashSet<_> = txids.iter().map(|txid| LabelItem::Txid(*txid)).collect();
+        let stored = control.get_labels(&items).labels;
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[&txids[0].to_string()], "Rent - March");
+        assert_eq!(stored[&txids[1].to_string()], "Old");
+
+        // The existing labels may be replaced.
+        let res = control.import_labels_csv(&csv, true).unwrap();
+        assert_eq!(res.imported, 2);
+        assert!(res.already_labelled.is_empty());
+        assert_eq!(
+            control.get_labels(&items).labels[&txids[1].to_string()],
+            "Salary"
+        );
 
-        let list = control.list_addresses(Some(2), Some(5)).unwrap();
+        assert!(matches!(
+            control.import_labels_csv("txid,label\n", false),
+            Err(CommandError::InvalidLabelsCsv(..))
+        ));
 
-        assert_eq!(list.addresses[0].index, 2);
-        assert_eq!(list.addresses.last().unwrap().index, 6);
+        ms.shutdown();
+    }
 
-        let addr0 = control.get_new_address().address;
-        let addr1 = control.get_new_address().address;
-        let _addr2 = control.get_new_address().address;
-        let addr3 = control.get_new_address().address;
-        let addr4 = control.get_new_address().address;
+    #[test]
+    fn sign_psbt_with_hot_signer() {
+        let secp = secp256k1::Secp256k1::new();
+        let network = bitcoin::Network::Bitcoin;
+        let signer = HotSigner::from_str(
+            network,
+            "burger ball theme dog light account produce chest warrior swarm flip equip",
+        )
+        .unwrap();
+        let path = bip32::DerivationPath::from_str("m/48'/0'/0'/2'").unwrap();
+        let owner_key = descriptors::PathInfo::Single(
+            descriptor::DescriptorPublicKey::from_str(&format!(
+                "[{}/48'/0'/0'/2']{}/<0;1>/*",
+                signer.fingerprint(&secp),
+                signer.xpub_at(&path, &secp)
+            ))
+            .unwrap(),
+        );
+        let heir_key = descriptors::PathInfo::Single(descriptor::DescriptorPublicKey::from_str("[aabbccdd]xpub68JJTXc1MWK8PEQozKsRatrUHXKFNkD1Cb1BuQU9Xr5moCv87anqGyXLyUd4KpnDyZgo3gz4aN1r3NiaoweFW8UutBsBbgKHzaD5HkTkifK/<0;1>/*").unwrap());
+        let desc = descriptors::LianaDescriptor::new(
+            descriptors::LianaPolicy::new_legacy(
+                owner_key,
+                [(10_000, heir_key)].iter().cloned().collect(),
+            )
+            .unwrap(),
+        );
+        let prev_tx = bitcoin::Transaction {
+            version: TxVersion::TWO,
+            lock_time: absolute::LockTime::Blocks(absolute::Height::ZERO),
+            input: vec![],
+            output: vec![],
+        };
+        let dummy_op = bitcoin::OutPoint::new(prev_tx.compute_txid(), 0);
+        let mut dummy_bitcoind = DummyBitcoind::new();
+        dummy_bitcoind.txs.insert(dummy_op.txid, (prev_tx, None));
+        let ms = DummyLiana::new_with_descriptor(dummy_bitcoind, DummyDatabase::new(), desc);
+        let control = &ms.control();
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_unspent_coins(&[Coin {
+            outpoint: dummy_op,
+            is_immature: false,
+            block_info: None,
+            amount: bitcoin::Amount::from_sat(100_000),
+            derivation_index: bip32::ChildNumber::from(7),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        }]);
+        let dest_addr =
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
+        let destinations: HashMap<bitcoin::Address<address::NetworkUnchecked>, u64> =
+            [(dest_addr, 50_000)].iter().cloned().collect();
+        let psbt = match control
+            .create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default(),
+            )
+            .unwrap()
+        {
+            CreateSpendResult::Success { psbt, .. } => psbt,
+            _ => panic!("expect successful spend creation"),
+        };
 
-        let list = control.list_addresses(Some(0), None).unwrap();
+        assert_eq!(
+            control.sign_psbt_with_hot_signer(psbt.clone(), None),
+            Err(CommandError::NoHotSigner)
+        );
 
-        assert_eq!(list.addresses[0].index, 0);
-        assert_eq!(list.addresses[0].receive, addr0);
-        assert_eq!(list.addresses.last().unwrap().index, 4);
-        assert_eq!(list.addresses.last().unwrap().receive, addr4);
+        // A hot signer which isn't part of the wallet doesn't sign.
+        let data_dir = control.config.data_dir().unwrap();
+        let other = HotSigner::generate(network).unwrap();
+        other.store(&data_dir, network, &secp).unwrap();
+        assert_eq!(
+            control.sign_psbt_with_hot_signer(psbt.clone(), None),
+            Err(CommandError::UnknownSigner(other.fingerprint(&secp)))
+        );
 
-        let list = control.list_addresses(None, None).unwrap();
+        // The signer of the wallet signs, once selected.
+        signer.store(&data_dir, network, &secp).unwrap();
+        assert_eq!(
+            control.sign_psbt_with_hot_signer(psbt.clone(), None),
+            Err(CommandError::AmbiguousHotSigner)
+        );
+        let fingerprint = signer.fingerprint(&secp);
+        let res = control
+            .sign_psbt_with_hot_signer(psbt.clone(), Some(fingerprint))
+            .unwrap();
+        assert_eq!(res.fingerprint, fingerprint);
+        assert_eq!(res.psbt.unsigned_tx, psbt.unsigned_tx);
+        assert_eq!(res.psbt.inputs[0].partial_sigs.len(), 1);
+        // Nothing was stored.
+        assert!(control
+            .list_spend(None, &[], None)
+            .unwrap()
+            .spend_txs
+            .is_empty());
 
-        assert_eq!(list.addresses[0].index, 0);
-        assert_eq!(list.addresses[0].receive, addr0);
-        assert_eq!(list.addresses.last().unwrap().index, 4);
-        assert_eq!(list.addresses.last().unwrap().receive, addr4);
+        // It doesn't sign for coins it doesn't know, nor with other previous outputs than ours.
+        let mut unknown = psbt.clone();
+        unknown.unsigned_tx.input[0].previous_output.vout = 1;
+        let unknown_op = unknown.unsigned_tx.input[0].previous_output;
+        assert_eq!(
+            control.sign_psbt_with_hot_signer(unknown, Some(fingerprint)),
+            Err(CommandError::UnknownOutpoint(unknown_op))
+        );
+        let mut mismatch = psbt.clone();
+        mismatch.inputs[0].witness_utxo.as_mut().unwrap().value = bitcoin::Amount::from_sat(10_000);
+        assert_eq!(
+            control.sign_psbt_with_hot_signer(mismatch, Some(fingerprint)),
+            Err(CommandError::UtxoMismatch(dummy_op))
+        );
 
-        let list = control.list_addresses(Some(1), Some(3)).unwrap();
+        // Nor for coins already spent by a confirmed transaction.
+        let spend_txid = psbt.unsigned_tx.compute_txid();
+        db_conn.spend_coins(&[(dummy_op, spend_txid)]);
+        assert!(control
+            .sign_psbt_with_hot_signer(psbt.clone(), Some(fingerprint))
+            .is_ok());
+        db_conn.confirm_spend(&[(dummy_op, spend_txid, 2, 1_000)]);
+        assert_eq!(
+            control.sign_psbt_with_hot_signer(psbt, Some(fingerprint)),
+            Err(CommandError::AlreadySpent(dummy_op))
+        );
 
-        assert_eq!(list.addresses[0].index, 1);
-        assert_eq!(list.addresses[0].receive, addr1);
-        assert_eq!(list.addresses.last().unwrap().index, 3);
-        assert_eq!(list.addresses.last().unwrap().receive, addr3);
+        ms.shutdown();
+    }
 
-        let addr5 = control.get_new_address().address;
-        let list = control.list_addresses(Some(5), None).unwrap();
+    #[test]
+    fn address_statement() {
+        let secp = secp256k1::Secp256k1::new();
+        let network = bitcoin::Network::Bitcoin;
+        let signer = HotSigner::from_str(
+            network,
+            "burger ball theme dog light account produce chest warrior swarm flip equip",
+        )
+        .unwrap();
+        let path = bip32::DerivationPath::from_str("m/48'/0'/0'/2'").unwrap();
+        let owner_key = descriptors::PathInfo::Single(
+            descriptor::DescriptorPublicKey::from_str(&format!(
+                "[{}/48'/0'/0'/2']{}/<0;1>/*",
+                signer.fingerprint(&secp),
+                signer.xpub_at(&path, &secp)
+            ))
+            .unwrap(),
+        );
+        let heir_key = descriptors::PathInfo::Single(descriptor::DescriptorPublicKey::from_str("[aabbccdd]xpub68JJTXc1MWK8PEQozKsRatrUHXKFNkD1Cb1BuQU9Xr5moCv87anqGyXLyUd4KpnDyZgo3gz4aN1r3NiaoweFW8UutBsBbgKHzaD5HkTkifK/<0;1>/*").unwrap());
+        let desc = descriptors::LianaDescriptor::new(
+            descriptors::LianaPolicy::new_legacy(
+                owner_key,
+                [(10_000, heir_key)].iter().cloned().collect(),
+            )
+            .unwrap(),
+        );
+        let ms = DummyLiana::new_with_descriptor(DummyBitcoind::new(), DummyDatabase::new(), desc);
+        let control = &ms.control();
 
-        assert_eq!(list.addresses[0].index, 5);
-        assert_eq!(list.addresses[0].receive, addr5);
-        assert_eq!(list.addresses.last().unwrap().index, 5);
-        assert_eq!(list.addresses.last().unwrap().receive, addr5);
+        assert!(matches!(
+            control.create_address_statement(0, None),
+            Err(CommandError::InvalidAddressStatement(..))
+        ));
+        assert!(matches!(
+            control.create_address_statement(MAX_DERIVATION_INDEX_INCREASE + 1, None),
+            Err(CommandError::DerivationIndexIncrease(..))
+        ));
 
-        // We can get no address for the last unhardened index.
-        let max_unhardened_index = 2u32.pow(31) - 1;
-        let res = control
-            .list_addresses(Some(max_unhardened_index), Some(0))
-            .unwrap();
-        // This is equivalent to not passing a count.
+        // The next 3 receive addresses are listed, and not given out again.
+        let res = control.create_address_statement(3, None).unwrap();
+        let statement = AddressStatement::from_message(&res.message, network).unwrap();
         assert_eq!(
-            res,
-            control
-                .list_addresses(Some(max_unhardened_index), None)
-                .unwrap()
+            statement
+                .addresses
+                .iter()
+                .map(|(i, _)| *i)
+                .collect::<Vec<_>>(),
+            vec![0, 1, 2]
         );
-        // We can also get the one last unhardened index.
-        control
-            .list_addresses(Some(max_unhardened_index), Some(1))
+        assert_eq!(control.get_new_address().derivation_index, 3.into());
+
+        // It can't be finalized before being signed, nor for another message.
+        assert!(matches!(
+            control.finalize_address_statement(&res.message, res.psbt.clone()),
+            Err(CommandError::InvalidAddressStatement(..))
+        ));
+        let psbt = signer.sign_psbt(res.psbt, &secp).unwrap();
+        let other = control.create_address_statement(2, Some(10)).unwrap();
+        assert!(matches!(
+            control.finalize_address_statement(&other.message, psbt.clone()),
+            Err(CommandError::InvalidAddressStatement(..))
+        ));
+        let signed = control
+            .finalize_address_statement(&res.message, psbt)
+            .unwrap();
+        assert_eq!(signed.address, statement.addresses[0].1);
+
+        let unchecked = |a: &bitcoin::Address| bitcoin::Address::from_str(&a.to_string()).unwrap();
+        let verified = control
+            .verify_address_statement(
+                unchecked(&signed.address),
+                &signed.message,
+                &signed.signature,
+            )
             .unwrap();
-        // However we can't get into hardened territory.
         assert_eq!(
-            control
-                .list_addresses(Some(max_unhardened_index), Some(2))
-                .unwrap_err(),
-            CommandError::InvalidDerivationIndex
+            verified.addresses,
+            statement
+                .addresses
+                .iter()
+                .map(|(index, address)| StatementAddress {
+                    index: *index,
+                    address: address.clone(),
+                })
+                .collect::<Vec<_>>()
         );
 
-        // We also can't pass a hardened start index.
-        let first_hardened_index = max_unhardened_index + 1;
+        // The statement must be signed for its first address, and must not be altered.
+        assert!(matches!(
+            control.verify_address_statement(
+                unchecked(&statement.addresses[1].1),
+                &signed.message,
+                &signed.signature,
+            ),
+            Err(CommandError::InvalidAddressStatement(..))
+        ));
+        let altered = AddressStatement {
+            network,
+            addresses: statement.addresses[..2].to_vec(),
+        };
+        assert!(matches!(
+            control.verify_address_statement(
+                unchecked(&signed.address),
+                &altered.message(),
+                &signed.signature,
+            ),
+            Err(CommandError::InvalidAddressStatement(..))
+        ));
+
+        // The statement can't vouch for addresses of another wallet.
+        let mut foreign = statement.clone();
+        foreign.addresses[2].1 =
+            bitcoin::Address::from_str("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq")
+                .unwrap()
+                .assume_checked();
+        assert!(matches!(
+            control.finalize_address_statement(&foreign.message(), other.psbt),
+            Err(CommandError::InvalidAddressStatement(..))
+        ));
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn silent_payments() {
+        let mut bitcoind = DummyBitcoind::new();
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = &ms.control();
+        let tip = bitcoind.chain_tip();
+        control.db.connection().update_tip(&tip);
+
+        // Only backends exposing the transactions of the blocks are supported.
         assert_eq!(
-            control
-                .list_addresses(Some(first_hardened_index), None)
-                .unwrap_err(),
-            CommandError::InvalidDerivationIndex
+            control.get_silent_payment_address(None),
+            Err(CommandError::SilentPaymentsUnsupported)
         );
+        ms.shutdown();
+        bitcoind.blocks = Some(HashMap::from([(
+            100,
+            BlockTransactions {
+                block: Block {
+                    hash: tip.hash,
+                    height: 100,
+                    time: 1_700_000_000,
+                },
+                txs: Vec::new(),
+            },
+        )]));
+        let db = DummyDatabase::new();
+        db.connection().update_tip(&tip);
+        let ms = DummyLiana::new(bitcoind, db);
+        let control = &ms.control();
         assert_eq!(
-            control
-                .list_addresses(Some(first_hardened_index), Some(0))
-                .unwrap_err(),
-            CommandError::InvalidDerivationIndex
+            control.get_silent_payment_address(None),
+            Err(CommandError::NoHotSigner)
         );
+
+        // The keys are derived from the hot signer and the chain is scanned from the tip.
+        let secp = secp256k1::Secp256k1::new();
+        let data_dir = control.config.data_dir().unwrap();
+        let network = control.config.bitcoin_config.network;
+        let signer = HotSigner::from_str(
+            network,
+            "burger ball theme dog light account produce chest warrior swarm flip equip",
+        )
+        .unwrap();
+        signer.store(&data_dir, network, &secp).unwrap();
+        let res = control.get_silent_payment_address(None).unwrap();
+        assert!(res.address.starts_with("sp1q"));
+        assert_eq!(res.fingerprint, signer.fingerprint(&secp));
+        assert_eq!(res.scanned_height, 100);
+        let (scan_key, spend_key) = signer.silent_payment_keys(&secp);
+        let address = SilentPaymentAddress::from_str_network(&res.address, network).unwrap();
+        assert_eq!(address.scan_key, scan_key.public_key(&secp));
+        assert_eq!(address.spend_key, spend_key.public_key(&secp));
+        // It's the same address even if another hot signer is stored.
+        HotSigner::generate(network)
+            .unwrap()
+            .store(&data_dir, network, &secp)
+            .unwrap();
+        assert_eq!(control.get_silent_payment_address(None), Ok(res));
+
+        assert!(control.list_silent_payments().payments.is_empty());
         assert_eq!(
-            control
-                .list_addresses(Some(first_hardened_index), Some(1))
-                .unwrap_err(),
-            CommandError::InvalidDerivationIndex
+            control.sweep_silent_payments(2),
+            Err(CommandError::NoSilentPayments)
         );
 
-        // Much less so overflow.
+        // A payment found by the poller is swept to a new receive address.
+        let outpoint = OutPoint::new(Txid::from_slice(&[2; 32]).unwrap(), 1);
+        let block = BlockInfo {
+            height: 101,
+            time: 1_700_000_600,
+        };
+        control.db.connection().record_silent_payment_scan(
+            block,
+            &[SilentPayment {
+                outpoint,
+                amount: Amount::from_sat(100_000),
+                tweak: secp256k1::Scalar::from_be_bytes([3; 32]).unwrap(),
+                block,
+                spend_txid: None,
+                spend_block: None,
+            }],
+            &[],
+        );
+        let payments = control.list_silent_payments().payments;
+        assert_eq!(payments.len(), 1);
         assert_eq!(
-            control.list_addresses(Some(u32::MAX), None).unwrap_err(),
-            CommandError::InvalidDerivationIndex
+            (payments[0].outpoint, payments[0].block_height),
+            (outpoint, 101)
         );
+        assert!(payments[0].spend_info.is_none());
         assert_eq!(
-            control.list_addresses(Some(u32::MAX), Some(0)).unwrap_err(),
-            CommandError::InvalidDerivationIndex
+            control.sweep_silent_payments(0),
+            Err(CommandError::InvalidFeerate(0))
         );
+        assert!(matches!(
+            control.sweep_silent_payments(1_000),
+            Err(CommandError::InsufficientFunds(..))
+        ));
+        let receive_index = control.db.connection().receive_index();
+        let sweep = control.sweep_silent_payments(2).unwrap();
+        assert_eq!(sweep.amount + sweep.fee, Amount::from_sat(100_000));
         assert_eq!(
-            control.list_addresses(Some(u32::MAX), Some(1)).unwrap_err(),
-            CommandError::InvalidDerivationIndex
+            sweep.address,
+            control
+                .config
+                .main_descriptor
+                .receive_descriptor()
+                .derive(receive_index, &control.secp)
+                .address(network)
         );
-
-        // We won't crash if we pass a start index larger than the next derivation index without
-        // passing a count. (ie no underflow.)
-        let next_deriv_index = list.addresses.last().unwrap().index + 1;
-        control
-            .list_addresses(Some(next_deriv_index + 1), None)
+        assert_eq!(
+            control.db.connection().receive_index(),
+            receive_index.increment().unwrap()
+        );
+        let spend_info = control.list_silent_payments().payments[0]
+            .spend_info
             .unwrap();
+        assert_eq!((spend_info.txid, spend_info.height), (sweep.txid, None));
+        assert_eq!(
+            control.sweep_silent_payments(2),
+            Err(CommandError::NoSilentPayments)
+        );
 
         ms.shutdown();
     }
@@ -1465,7 +4695,7 @@ mod tests {
         let dummy_value = 10_000;
         let mut destinations = <HashMap<bitcoin::Address<address::NetworkUnchecked>, u64>>::new();
         assert_eq!(
-            control.create_spend(&destinations, &[], 1, None),
+            control.create_spend(&destinations, &[], 1, None, &CreateSpendOptions::default()),
             Err(CommandError::NoOutpointForSelfSend)
         );
         destinations = [(dummy_addr.clone(), dummy_value)]
@@ -1474,18 +4704,30 @@ mod tests {
             .collect();
         // Insufficient funds for coin selection.
         assert!(matches!(
-            control.create_spend(&destinations, &[], 1, None),
+            control.create_spend(&destinations, &[], 1, None, &CreateSpendOptions::default()),
             Ok(CreateSpendResult::InsufficientFunds { .. }),
         ));
         assert_eq!(
-            control.create_spend(&destinations, &[dummy_op], 0, None),
+            control.create_spend(
+                &destinations,
+                &[dummy_op],
+                0,
+                None,
+                &CreateSpendOptions::default()
+            ),
             Err(CommandError::InvalidFeerate(0))
         );
 
         // The coin doesn't exist. If we create a new unspent one at this outpoint with a much
         // higher value, we'll get a Spend transaction with a change output.
         assert_eq!(
-            control.create_spend(&destinations, &[dummy_op], 1, None),
+            control.create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default()
+            ),
             Err(CommandError::UnknownOutpoint(dummy_op))
         );
         db_conn.new_unspent_coins(&[Coin {
@@ -1502,11 +4744,17 @@ mod tests {
         // If we try to use coin selection, the unconfirmed not-from-self coin will not be used
         // as a candidate and so we get a coin selection error due to insufficient funds.
         assert!(matches!(
-            control.create_spend(&destinations, &[], 1, None),
+            control.create_spend(&destinations, &[], 1, None, &CreateSpendOptions::default()),
             Ok(CreateSpendResult::InsufficientFunds { .. }),
         ));
         let (psbt, warnings) = if let CreateSpendResult::Success { psbt, warnings } = control
-            .create_spend(&destinations, &[dummy_op], 1, None)
+            .create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default(),
+            )
             .unwrap()
         {
             (psbt, warnings)
@@ -1536,7 +4784,13 @@ mod tests {
         // At 2sats/vb, it's twice that.
         assert_eq!(tx.output[1].value.to_sat(), 89_839);
         let psbt = if let CreateSpendResult::Success { psbt, .. } = control
-            .create_spend(&destinations, &[dummy_op], 2, None)
+            .create_spend(
+                &destinations,
+                &[dummy_op],
+                2,
+                None,
+                &CreateSpendOptions::default(),
+            )
             .unwrap()
         {
             psbt
@@ -1549,22 +4803,46 @@ mod tests {
         // A feerate of 555 won't trigger the sanity checks (they were previously not taking the
         // satisfaction size into account and overestimating the feerate).
         control
-            .create_spend(&destinations, &[dummy_op], 555, None)
+            .create_spend(
+                &destinations,
+                &[dummy_op],
+                555,
+                None,
+                &CreateSpendOptions::default(),
+            )
             .unwrap();
 
         // If we ask for a too high feerate, or a too large/too small output, it'll fail.
         assert!(matches!(
-            control.create_spend(&destinations, &[dummy_op], 10_000, None),
+            control.create_spend(
+                &destinations,
+                &[dummy_op],
+                10_000,
+                None,
+                &CreateSpendOptions::default()
+            ),
             Ok(CreateSpendResult::InsufficientFunds { .. }),
         ));
         *destinations.get_mut(&dummy_addr).unwrap() = 100_001;
         assert!(matches!(
-            control.create_spend(&destinations, &[dummy_op], 1, None),
+            control.create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default()
+            ),
             Ok(CreateSpendResult::InsufficientFunds { .. }),
         ));
         *destinations.get_mut(&dummy_addr).unwrap() = 4_500;
         assert_eq!(
-            control.create_spend(&destinations, &[dummy_op], 1, None),
+            control.create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default()
+            ),
             Err(CommandError::SpendCreation(
                 SpendCreationError::InvalidOutputValue(bitcoin::Amount::from_sat(4_500))
             ))
@@ -1576,7 +4854,13 @@ mod tests {
         let invalid_destinations: HashMap<bitcoin::Address<address::NetworkUnchecked>, u64> =
             [(invalid_addr, dummy_value)].iter().cloned().collect();
         assert!(matches!(
-            control.create_spend(&invalid_destinations, &[dummy_op], 1, None),
+            control.create_spend(
+                &invalid_destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default()
+            ),
             Err(CommandError::Address(
                 address::error::ParseError::NetworkValidation { .. }
             ))
@@ -1586,7 +4870,13 @@ mod tests {
         // won't create an output lower than 5k sats.
         *destinations.get_mut(&dummy_addr).unwrap() = 95_000;
         let (psbt, warnings) = if let CreateSpendResult::Success { psbt, warnings } = control
-            .create_spend(&destinations, &[dummy_op], 1, None)
+            .create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default(),
+            )
             .unwrap()
         {
             (psbt, warnings)
@@ -1615,7 +4905,13 @@ mod tests {
         // Increase the target value by the change amount and the warning will disappear.
         *destinations.get_mut(&dummy_addr).unwrap() = 95_000 + 4_839;
         let (psbt, warnings) = if let CreateSpendResult::Success { psbt, warnings } = control
-            .create_spend(&destinations, &[dummy_op], 1, None)
+            .create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default(),
+            )
             .unwrap()
         {
             (psbt, warnings)
@@ -1630,7 +4926,13 @@ mod tests {
         *destinations.get_mut(&dummy_addr).unwrap() =
             95_000 + 4_830 + /* fee for change output */ 43;
         let (psbt, warnings) = if let CreateSpendResult::Success { psbt, warnings } = control
-            .create_spend(&destinations, &[dummy_op], 1, None)
+            .create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default(),
+            )
             .unwrap()
         {
             (psbt, warnings)
@@ -1645,7 +4947,13 @@ mod tests {
         *destinations.get_mut(&dummy_addr).unwrap() =
             95_000 + 4_839 + /* fee for change output */ 43 + 1;
         assert_eq!(
-            control.create_spend(&destinations, &[dummy_op], 1, None),
+            control.create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default()
+            ),
             Ok(CreateSpendResult::InsufficientFunds { missing: 1 }),
         );
 
@@ -1653,7 +4961,13 @@ mod tests {
         *destinations.get_mut(&dummy_addr).unwrap() =
             100_000 - /* fee without change */ 118 - /* extra fee for change output */ 43 - 1;
         let warnings = if let CreateSpendResult::Success { warnings, .. } = control
-            .create_spend(&destinations, &[dummy_op], 1, None)
+            .create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default(),
+            )
             .unwrap()
         {
             warnings
@@ -1674,7 +4988,13 @@ mod tests {
         *destinations.get_mut(&dummy_addr).unwrap() =
             95_000 - /* fee without change */ 118 - /* extra fee for change output */ 43;
         let (psbt, warnings) = if let CreateSpendResult::Success { psbt, warnings } = control
-            .create_spend(&destinations, &[dummy_op], 1, None)
+            .create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default(),
+            )
             .unwrap()
         {
             (psbt, warnings)
@@ -1690,7 +5010,13 @@ mod tests {
         *destinations.get_mut(&dummy_addr).unwrap() =
             95_000 - /* fee without change */ 118 - /* extra fee for change output */ 43 + 1;
         let warnings = if let CreateSpendResult::Success { warnings, .. } = control
-            .create_spend(&destinations, &[dummy_op], 1, None)
+            .create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default(),
+            )
             .unwrap()
         {
             warnings
@@ -1716,13 +5042,19 @@ mod tests {
             .unwrap(),
         )]);
         assert_eq!(
-            control.create_spend(&destinations, &[dummy_op], 1, None),
+            control.create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default()
+            ),
             Err(CommandError::AlreadySpent(dummy_op))
         );
         // If we try to use coin selection, the spent coin will not be used as a candidate
         // and so we get a coin selection error due to insufficient funds.
         assert!(matches!(
-            control.create_spend(&destinations, &[], 1, None),
+            control.create_spend(&destinations, &[], 1, None, &CreateSpendOptions::default()),
             Ok(CreateSpendResult::InsufficientFunds { .. }),
         ));
 
@@ -1743,7 +5075,13 @@ mod tests {
             is_from_self: false,
         }]);
         assert_eq!(
-            control.create_spend(&destinations, &[dummy_op_dup], 1_001, None),
+            control.create_spend(
+                &destinations,
+                &[dummy_op_dup],
+                1_001,
+                None,
+                &CreateSpendOptions::default()
+            ),
             Err(CommandError::SpendCreation(SpendCreationError::InsaneFees(
                 InsaneFeeInfo::TooHighFeerate(1_001)
             )))
@@ -1768,14 +5106,14 @@ mod tests {
         db_conn.new_unspent_coins(&[unconfirmed_coin]);
         // Coin selection error due to insufficient funds.
         assert!(matches!(
-            control.create_spend(&destinations, &[], 1, None),
+            control.create_spend(&destinations, &[], 1, None, &CreateSpendOptions::default()),
             Ok(CreateSpendResult::InsufficientFunds { .. }),
         ));
         // Set destination amount equal to value of confirmed coins.
         *destinations.get_mut(&dummy_addr).unwrap() = 80_000;
         // Coin selection error occurs due to insufficient funds to pay fee.
         assert!(matches!(
-            control.create_spend(&destinations, &[], 1, None),
+            control.create_spend(&destinations, &[], 1, None, &CreateSpendOptions::default()),
             Ok(CreateSpendResult::InsufficientFunds { .. }),
         ));
         let confirmed_op_2 = bitcoin::OutPoint {
@@ -1798,8 +5136,9 @@ mod tests {
             is_from_self: false,
         }]);
         // First, create a transaction using auto coin selection.
-        let psbt = if let CreateSpendResult::Success { psbt, .. } =
-            control.create_spend(&destinations, &[], 1, None).unwrap()
+        let psbt = if let CreateSpendResult::Success { psbt, .. } = control
+            .create_spend(&destinations, &[], 1, None, &CreateSpendOptions::default())
+            .unwrap()
         {
             psbt
         } else {
@@ -1824,7 +5163,13 @@ mod tests {
 
         // Create a second transaction using manual coin selection.
         let psbt = if let CreateSpendResult::Success { psbt, .. } = control
-            .create_spend(&destinations, &[confirmed_op_1, confirmed_op_2], 1, None)
+            .create_spend(
+                &destinations,
+                &[confirmed_op_1, confirmed_op_2],
+                1,
+                None,
+                &CreateSpendOptions::default(),
+            )
             .unwrap()
         {
             psbt
@@ -1857,7 +5202,7 @@ mod tests {
         unconfirmed_coin_2.is_change = false;
         db_conn.new_unspent_coins(&[unconfirmed_coin_2]);
         assert!(matches!(
-            control.create_spend(&destinations, &[], 1, None),
+            control.create_spend(&destinations, &[], 1, None, &CreateSpendOptions::default()),
             Ok(CreateSpendResult::InsufficientFunds { .. }),
         ));
         // 2. not from self and change
@@ -1866,7 +5211,7 @@ mod tests {
         unconfirmed_coin_2.is_change = true;
         db_conn.new_unspent_coins(&[unconfirmed_coin_2]);
         assert!(matches!(
-            control.create_spend(&destinations, &[], 1, None),
+            control.create_spend(&destinations, &[], 1, None, &CreateSpendOptions::default()),
             Ok(CreateSpendResult::InsufficientFunds { .. }),
         ));
 
@@ -1887,6 +5232,7 @@ mod tests {
                 &[confirmed_op_1, confirmed_op_2],
                 1,
                 Some(change_address.as_unchecked().clone()),
+                &CreateSpendOptions::default(),
             )
             .unwrap()
         {
@@ -1904,58 +5250,534 @@ mod tests {
         manual_input.sort();
         assert_eq!(auto_input, manual_input);
 
-        // Add a confirmed coin with a value near the dust limit and check that
-        // `InsufficientFunds` error is returned if feerate is too high.
-        let confirmed_op_3 = bitcoin::OutPoint {
-            txid: confirmed_op_2.txid,
-            vout: confirmed_op_2.vout + 10,
+        // Add a confirmed coin with a value near the dust limit and check that
+        // `InsufficientFunds` error is returned if feerate is too high.
+        let confirmed_op_3 = bitcoin::OutPoint {
+            txid: confirmed_op_2.txid,
+            vout: confirmed_op_2.vout + 10,
+        };
+        db_conn.new_unspent_coins(&[Coin {
+            outpoint: confirmed_op_3,
+            is_immature: false,
+            block_info: Some(BlockInfo {
+                height: 174500,
+                time: 174500,
+            }),
+            amount: bitcoin::Amount::from_sat(5_250),
+            derivation_index: bip32::ChildNumber::from(56),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        }]);
+        let empty_dest = &HashMap::<bitcoin::Address<address::NetworkUnchecked>, u64>::new();
+        assert!(matches!(
+            control.create_spend(
+                empty_dest,
+                &[confirmed_op_3],
+                5,
+                None,
+                &CreateSpendOptions::default()
+            ),
+            Ok(CreateSpendResult::InsufficientFunds { .. }),
+        ));
+        // If we use a lower fee, the self-send will succeed.
+        let psbt = if let CreateSpendResult::Success { psbt, .. } = control
+            .create_spend(
+                empty_dest,
+                &[confirmed_op_3],
+                1,
+                None,
+                &CreateSpendOptions::default(),
+            )
+            .unwrap()
+        {
+            psbt
+        } else {
+            panic!("expect successful spend creation")
+        };
+        let tx = psbt.unsigned_tx;
+        let tx_prev_outpoints = tx
+            .input
+            .iter()
+            .map(|txin| txin.previous_output)
+            .collect::<Vec<OutPoint>>();
+        assert_eq!(tx.input.len(), 1);
+        assert_eq!(tx_prev_outpoints, vec![confirmed_op_3]);
+        assert_eq!(tx.output.len(), 1);
+
+        // Can't create a transaction that spends an immature coinbase deposit.
+        let imma_op = bitcoin::OutPoint::from_str(
+            "4753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810:0",
+        )
+        .unwrap();
+        db_conn.new_unspent_coins(&[Coin {
+            outpoint: imma_op,
+            is_immature: true,
+            block_info: None,
+            amount: bitcoin::Amount::from_sat(100_000),
+            derivation_index: bip32::ChildNumber::from(13),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        }]);
+        assert_eq!(
+            control.create_spend(
+                &destinations,
+                &[imma_op],
+                1_001,
+                None,
+                &CreateSpendOptions::default()
+            ),
+            Err(CommandError::ImmatureCoinbase(imma_op))
+        );
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn create_spend_subtract_fee() {
+        let dummy_tx = bitcoin::Transaction {
+            version: TxVersion::TWO,
+            lock_time: absolute::LockTime::Blocks(absolute::Height::ZERO),
+            input: vec![],
+            output: vec![],
+        };
+        let dummy_op = bitcoin::OutPoint::new(dummy_tx.compute_txid(), 0);
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = &ms.control();
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_txs(&[dummy_tx]);
+        db_conn.new_unspent_coins(&[Coin {
+            outpoint: dummy_op,
+            is_immature: false,
+            block_info: None,
+            amount: bitcoin::Amount::from_sat(100_000),
+            derivation_index: bip32::ChildNumber::from(13),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        }]);
+        let dummy_addr =
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
+        let other_addr =
+            bitcoin::Address::from_str("bc1qe2tczyk2rw7u47kzxxee5g7ufkncdmlcpmw3pk").unwrap();
+        let tx_fee = |psbt: &Psbt| {
+            let value_out: u64 = psbt
+                .unsigned_tx
+                .output
+                .iter()
+                .map(|txo| txo.value.to_sat())
+                .sum();
+            100_000 - value_out
+        };
+
+        // Forward the whole value of the coin: it can't pay for the fee on top of it, but the
+        // destination can.
+        let destinations: HashMap<_, _> = [(dummy_addr.clone(), 100_000)].iter().cloned().collect();
+        assert!(matches!(
+            control.create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default()
+            ),
+            Ok(CreateSpendResult::InsufficientFunds { .. }),
+        ));
+        let psbt = if let CreateSpendResult::Success { psbt, warnings } = control
+            .create_spend(
+                &destinations,
+                &[dummy_op],
+                2,
+                None,
+                &CreateSpendOptions {
+                    subtract_fee_from: vec![dummy_addr.clone()],
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        {
+            assert!(warnings.is_empty());
+            psbt
+        } else {
+            panic!("expect successful spend creation")
+        };
+        let tx = &psbt.unsigned_tx;
+        assert_eq!(tx.output.len(), 1);
+        let vbytes = control
+            .config
+            .main_descriptor
+            .unsigned_tx_max_vbytes(tx, /*use_primary_path=*/ true);
+        assert_eq!(tx_fee(&psbt), vbytes * 2);
+        assert_eq!(tx.output[0].value.to_sat(), 100_000 - vbytes * 2);
+
+        // The fee is split between the destinations it's deducted from. A change output, here
+        // sending the max to an external address, keeps its value.
+        let destinations: HashMap<_, _> =
+            [(dummy_addr.clone(), 30_000), (other_addr.clone(), 20_000)]
+                .into_iter()
+                .collect();
+        let change_addr =
+            bitcoin::Address::from_str("bc1q8c37s9sq89v55vuffajkfcd3xj9m67sq4gujr8").unwrap();
+        let psbt = if let CreateSpendResult::Success { psbt, .. } = control
+            .create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                Some(change_addr.clone()),
+                &CreateSpendOptions {
+                    subtract_fee_from: vec![dummy_addr.clone(), other_addr.clone()],
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        {
+            psbt
+        } else {
+            panic!("expect successful spend creation")
+        };
+        let tx = &psbt.unsigned_tx;
+        assert_eq!(tx.output.len(), 3);
+        let value_of = |addr: &bitcoin::Address<address::NetworkUnchecked>| {
+            tx.output
+                .iter()
+                .find(|txo| txo.script_pubkey == addr.assume_checked_ref().script_pubkey())
+                .unwrap()
+                .value
+                .to_sat()
+        };
+        assert_eq!(value_of(&change_addr), 50_000);
+        let fee = tx_fee(&psbt);
+        assert_eq!(value_of(&dummy_addr) + value_of(&other_addr), 50_000 - fee);
+        // The first destination given pays for the remainder of the split.
+        assert_eq!(30_000 - value_of(&dummy_addr), fee / 2 + fee % 2);
+        assert_eq!(20_000 - value_of(&other_addr), fee / 2);
+
+        // The fee can only be deducted from a destination which can pay for its share.
+        assert_eq!(
+            control.create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions {
+                    subtract_fee_from: vec![change_addr.clone()],
+                    ..Default::default()
+                }
+            ),
+            Err(CommandError::UnknownFeeDestination(change_addr))
+        );
+        let destinations: HashMap<_, _> = [(dummy_addr.clone(), 5_000)].iter().cloned().collect();
+        assert_eq!(
+            control.create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions {
+                    subtract_fee_from: vec![dummy_addr.clone()],
+                    ..Default::default()
+                }
+            ),
+            Err(CommandError::FeeExceedsDestination(dummy_addr))
+        );
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn create_spend_output_types() {
+        let dummy_tx = bitcoin::Transaction {
+            version: TxVersion::TWO,
+            lock_time: absolute::LockTime::Blocks(absolute::Height::ZERO),
+            input: vec![],
+            output: vec![],
+        };
+        let dummy_op = bitcoin::OutPoint::new(dummy_tx.compute_txid(), 0);
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = &ms.control();
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_txs(&[dummy_tx]);
+        db_conn.new_unspent_coins(&[Coin {
+            outpoint: dummy_op,
+            is_immature: false,
+            block_info: None,
+            amount: bitcoin::Amount::from_sat(100_000),
+            derivation_index: bip32::ChildNumber::from(13),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        }]);
+
+        // We can pay to all the standard types of addresses, including Taproot ones.
+        for addr in [
+            "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2",
+            "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+            "bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv",
+            "bc1q9ksrc647hx8zp2cewl8p5f487dgux3777yees8rjcx46t4daqzzqt7yga8",
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+        ] {
+            let addr = bitcoin::Address::from_str(addr).unwrap();
+            let destinations: HashMap<_, _> = [(addr.clone(), 10_000)].iter().cloned().collect();
+            let res = control
+                .create_spend(
+                    &destinations,
+                    &[dummy_op],
+                    1,
+                    None,
+                    &CreateSpendOptions::default(),
+                )
+                .unwrap();
+            if let CreateSpendResult::Success { psbt, warnings } = res {
+                assert!(warnings.is_empty());
+                assert_eq!(
+                    psbt.unsigned_tx.output[0].script_pubkey,
+                    addr.assume_checked_ref().script_pubkey()
+                );
+            } else {
+                panic!("expect successful spend creation")
+            }
+        }
+
+        // Paying to a witness version not defined yet must be allowed.
+        let addr = bitcoin::Address::from_str("bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs").unwrap();
+        let destinations: HashMap<_, _> = [(addr.clone(), 10_000)].iter().cloned().collect();
+        assert_eq!(
+            control.create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default()
+            ),
+            Err(CommandError::FutureWitnessVersion(addr))
+        );
+        let res = control
+            .create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions {
+                    allow_future_witness: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        if let CreateSpendResult::Success { warnings, .. } = res {
+            assert_eq!(
+                warnings,
+                vec![CreateSpendWarning::FutureWitnessVersion(2).to_string()]
+            );
+        } else {
+            panic!("expect successful spend creation")
+        }
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn create_spend_previous_transactions() {
+        // The previous transaction isn't in our database but the Bitcoin backend knows about it.
+        let prev_tx = bitcoin::Transaction {
+            version: TxVersion::TWO,
+            lock_time: absolute::LockTime::Blocks(absolute::Height::from_consensus(42).unwrap()),
+            input: vec![],
+            output: vec![],
+        };
+        let dummy_op = bitcoin::OutPoint::new(prev_tx.compute_txid(), 0);
+        let mut dummy_bitcoind = DummyBitcoind::new();
+        dummy_bitcoind
+            .txs
+            .insert(dummy_op.txid, (prev_tx.clone(), None));
+        let ms = DummyLiana::new(dummy_bitcoind, DummyDatabase::new());
+        let control = &ms.control();
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_unspent_coins(&[Coin {
+            outpoint: dummy_op,
+            is_immature: false,
+            block_info: None,
+            amount: bitcoin::Amount::from_sat(100_000),
+            derivation_index: bip32::ChildNumber::from(13),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        }]);
+
+        // It is fetched from the backend and included in the PSBT for the signers requiring it.
+        let dest_addr =
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
+        let destinations: HashMap<bitcoin::Address<address::NetworkUnchecked>, u64> =
+            [(dest_addr, 50_000)].iter().cloned().collect();
+        let psbt = match control
+            .create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default(),
+            )
+            .unwrap()
+        {
+            CreateSpendResult::Success { psbt, .. } => psbt,
+            _ => panic!("expect successful spend creation"),
+        };
+        assert_eq!(psbt.inputs[0].non_witness_utxo, Some(prev_tx));
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn create_spend_taproot() {
+        let prev_tx = bitcoin::Transaction {
+            version: TxVersion::TWO,
+            lock_time: absolute::LockTime::Blocks(absolute::Height::ZERO),
+            input: vec![],
+            output: vec![],
         };
+        let dummy_op = bitcoin::OutPoint::new(prev_tx.compute_txid(), 0);
+        let mut dummy_bitcoind = DummyBitcoind::new();
+        dummy_bitcoind.txs.insert(dummy_op.txid, (prev_tx, None));
+        let secp = secp256k1::Secp256k1::new();
+        let owner_xpriv = bip32::Xpriv::new_master(bitcoin::Network::Bitcoin, &[3; 32]).unwrap();
+        let owner_key = descriptors::PathInfo::Single(
+            descriptor::DescriptorPublicKey::from_str(&format!(
+                "[{}]{}/<0;1>/*",
+                owner_xpriv.fingerprint(&secp),
+                bip32::Xpub::from_priv(&secp, &owner_xpriv)
+            ))
+            .unwrap(),
+        );
+        let heir_key = descriptors::PathInfo::Single(descriptor::DescriptorPublicKey::from_str("[aabbccdd]xpub68JJTXc1MWK8PEQozKsRatrUHXKFNkD1Cb1BuQU9Xr5moCv87anqGyXLyUd4KpnDyZgo3gz4aN1r3NiaoweFW8UutBsBbgKHzaD5HkTkifK/<0;1>/*").unwrap());
+        let desc = descriptors::LianaDescriptor::new(
+            descriptors::LianaPolicy::new(
+                owner_key,
+                [(10_000, heir_key)].iter().cloned().collect(),
+            )
+            .unwrap(),
+        );
+        assert!(desc.is_taproot());
+        let ms = DummyLiana::new_with_descriptor(dummy_bitcoind, DummyDatabase::new(), desc);
+        let control = &ms.control();
+        let mut db_conn = control.db().lock().unwrap().connection();
         db_conn.new_unspent_coins(&[Coin {
-            outpoint: confirmed_op_3,
+            outpoint: dummy_op,
             is_immature: false,
-            block_info: Some(BlockInfo {
-                height: 174500,
-                time: 174500,
-            }),
-            amount: bitcoin::Amount::from_sat(5_250),
-            derivation_index: bip32::ChildNumber::from(56),
+            block_info: None,
+            amount: bitcoin::Amount::from_sat(100_000),
+            derivation_index: bip32::ChildNumber::from(7),
             is_change: false,
             spend_txid: None,
             spend_block: None,
             is_from_self: false,
         }]);
-        let empty_dest = &HashMap::<bitcoin::Address<address::NetworkUnchecked>, u64>::new();
-        assert!(matches!(
-            control.create_spend(empty_dest, &[confirmed_op_3], 5, None),
-            Ok(CreateSpendResult::InsufficientFunds { .. }),
-        ));
-        // If we use a lower fee, the self-send will succeed.
-        let psbt = if let CreateSpendResult::Success { psbt, .. } = control
-            .create_spend(empty_dest, &[confirmed_op_3], 1, None)
+
+        // The Taproot fields are populated for the input and the change output. The previous
+        // transaction isn't necessary for signing a Taproot input.
+        let dest_addr =
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
+        let destinations: HashMap<bitcoin::Address<address::NetworkUnchecked>, u64> =
+            [(dest_addr, 50_000)].iter().cloned().collect();
+        let mut psbt = match control
+            .create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default(),
+            )
             .unwrap()
         {
-            psbt
-        } else {
-            panic!("expect successful spend creation")
+            CreateSpendResult::Success { psbt, .. } => psbt,
+            _ => panic!("expect successful spend creation"),
         };
-        let tx = psbt.unsigned_tx;
-        let tx_prev_outpoints = tx
-            .input
+        let psbt_in = &psbt.inputs[0];
+        assert!(psbt_in.non_witness_utxo.is_none());
+        assert!(psbt_in.witness_script.is_none() && psbt_in.bip32_derivation.is_empty());
+        let internal_key = psbt_in.tap_internal_key.unwrap();
+        assert!(psbt_in.tap_merkle_root.is_some());
+        assert_eq!(psbt_in.tap_scripts.len(), 1);
+        let (leaf_hashes, (fg, der_path)) = &psbt_in.tap_key_origins[&internal_key];
+        assert!(leaf_hashes.is_empty());
+        assert_eq!(*fg, owner_xpriv.fingerprint(&secp));
+        assert_eq!(der_path.to_string(), "0/7");
+        let heir_origin = psbt_in
+            .tap_key_origins
             .iter()
-            .map(|txin| txin.previous_output)
-            .collect::<Vec<OutPoint>>();
-        assert_eq!(tx.input.len(), 1);
-        assert_eq!(tx_prev_outpoints, vec![confirmed_op_3]);
-        assert_eq!(tx.output.len(), 1);
+            .find(|(key, _)| **key != internal_key)
+            .map(|(_, origin)| origin)
+            .unwrap();
+        assert_eq!(heir_origin.0.len(), 1);
+        let change_out = psbt
+            .outputs
+            .iter()
+            .find(|out| out.tap_internal_key.is_some());
+        assert!(
+            change_out.is_some_and(|out| out.tap_tree.is_some() && out.tap_key_origins.len() == 2)
+        );
 
-        // Can't create a transaction that spends an immature coinbase deposit.
-        let imma_op = bitcoin::OutPoint::from_str(
-            "4753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810:0",
-        )
-        .unwrap();
+        // The signature for the key path is accounted for and kept when storing the draft.
+        let owner_key = owner_xpriv.derive_priv(&secp, der_path).unwrap();
+        sign_taproot_key_path(&mut psbt, 0, &owner_key.private_key);
+        let txid = psbt.unsigned_tx.compute_txid();
+        control.update_spend(psbt.clone()).unwrap();
+        let stored = control
+            .list_spend(Some(vec![txid]), &[], None)
+            .unwrap()
+            .spend_txs
+            .remove(0)
+            .psbt;
+        assert_eq!(stored, psbt);
+        assert_eq!(
+            control
+                .list_spend(None, &[SpendTxStatus::FullySigned], None)
+                .unwrap()
+                .spend_txs
+                .len(),
+            1
+        );
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn create_spend_mempool_policy() {
+        let dummy_tx = bitcoin::Transaction {
+            version: TxVersion::TWO,
+            lock_time: absolute::LockTime::Blocks(absolute::Height::ZERO),
+            input: vec![],
+            output: vec![],
+        };
+        let dummy_op = bitcoin::OutPoint::new(dummy_tx.compute_txid(), 0);
+        let mempool_entry = |ancestor_count, descendant_count| MempoolEntry {
+            vsize: 200,
+            ancestor_vsize: 200 * ancestor_count,
+            ancestor_count,
+            descendant_vsize: 200 * descendant_count,
+            descendant_count,
+            fees: MempoolEntryFees {
+                base: Amount::from_sat(1_000),
+                ancestor: Amount::from_sat(1_000 * ancestor_count),
+                descendant: Amount::from_sat(1_000 * descendant_count),
+            },
+        };
+        let mut bitcoind = DummyBitcoind::new();
+        bitcoind.min_feerates = Some(MinFeerates::from_btc_kvb(0.00001, 0.00002001));
+        bitcoind
+            .mempool_entries
+            .insert(dummy_op.txid, mempool_entry(24, 1));
+        let ms = DummyLiana::new(bitcoind, DummyDatabase::new());
+        let control = &ms.control();
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_txs(&[dummy_tx.clone()]);
         db_conn.new_unspent_coins(&[Coin {
-            outpoint: imma_op,
-            is_immature: true,
+            outpoint: dummy_op,
+            is_immature: false,
             block_info: None,
             amount: bitcoin::Amount::from_sat(100_000),
             derivation_index: bip32::ChildNumber::from(13),
@@ -1964,11 +5786,127 @@ mod tests {
             spend_block: None,
             is_from_self: false,
         }]);
+        let dummy_addr =
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
+        let destinations: HashMap<_, _> = [(dummy_addr, 10_000)].into_iter().collect();
+
+        // The mempool minimum feerate is rounded up to the next sat/vb.
         assert_eq!(
-            control.create_spend(&destinations, &[imma_op], 1_001, None),
-            Err(CommandError::ImmatureCoinbase(imma_op))
+            control.create_spend(
+                &destinations,
+                &[dummy_op],
+                2,
+                None,
+                &CreateSpendOptions::default()
+            ),
+            Err(CommandError::FeerateTooLow(2, 3))
+        );
+        assert_eq!(
+            control.create_recovery(
+                bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap(),
+                1,
+                None
+            ),
+            Err(CommandError::FeerateTooLow(1, 3))
         );
+        // 24 ancestors plus our transaction is right at the limit.
+        assert!(matches!(
+            control.create_spend(
+                &destinations,
+                &[dummy_op],
+                3,
+                None,
+                &CreateSpendOptions::default()
+            ),
+            Ok(CreateSpendResult::Success { .. })
+        ));
+        ms.shutdown();
+
+        // One more ancestor and it's above the limit. Same for descendants.
+        for (ancestor_count, descendant_count) in [(25, 1), (1, 25)] {
+            let mut bitcoind = DummyBitcoind::new();
+            bitcoind.mempool_entries.insert(
+                dummy_op.txid,
+                mempool_entry(ancestor_count, descendant_count),
+            );
+            let ms = DummyLiana::new(bitcoind, DummyDatabase::new());
+            let control = &ms.control();
+            let mut db_conn = control.db().lock().unwrap().connection();
+            db_conn.new_txs(&[dummy_tx.clone()]);
+            db_conn.new_unspent_coins(&[Coin {
+                outpoint: dummy_op,
+                is_immature: false,
+                block_info: None,
+                amount: bitcoin::Amount::from_sat(100_000),
+                derivation_index: bip32::ChildNumber::from(13),
+                is_change: false,
+                spend_txid: None,
+                spend_block: None,
+                is_from_self: false,
+            }]);
+            assert_eq!(
+                control.create_spend(
+                    &destinations,
+                    &[dummy_op],
+                    1,
+                    None,
+                    &CreateSpendOptions::default()
+                ),
+                Err(CommandError::PackageLimits(dummy_op))
+            );
+            ms.shutdown();
+        }
+    }
+
+    #[test]
+    fn get_mempool_stats() {
+        // Without a histogram we can't project anything.
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let stats = ms.control().get_mempool_stats();
+        assert!(stats.fee_histogram.is_empty());
+        assert_eq!(stats.min_relay_feerate, None);
+        assert_eq!(stats.projected_blocks(10), None);
+        assert_eq!(stats.lowest_feerate_within(6), None);
+        ms.shutdown();
 
+        // 1.5 blocks worth at 20 sats/vb or more, half a block between 10 and 12 sats/vb and two
+        // blocks at 1 sat/vb. Transactions paying less than 1 sat/vb are counted with the latter.
+        let mut bitcoind = DummyBitcoind::new();
+        bitcoind.min_feerates = Some(MinFeerates::from_btc_kvb(0.00001, 0.00002));
+        bitcoind.fee_histogram = Some(fee_histogram([
+            (25.0, 500_000),
+            (20.0, 1_000_000),
+            (10.5, 500_000),
+            (1.0, 1_900_000),
+            (0.5, 100_000),
+        ]));
+        let ms = DummyLiana::new(bitcoind, DummyDatabase::new());
+        let stats = ms.control().get_mempool_stats();
+        assert_eq!(stats.min_relay_feerate, Some(1));
+        assert_eq!(stats.mempool_min_feerate, Some(2));
+        assert_eq!(stats.mempool_vsize(), 4_000_000);
+        let bucket = |feerate| {
+            stats
+                .fee_histogram
+                .iter()
+                .find(|b| b.feerate == feerate)
+                .unwrap()
+                .vsize
+        };
+        assert_eq!(bucket(25), 500_000);
+        assert_eq!(bucket(20), 1_000_000);
+        assert_eq!(bucket(10), 500_000);
+        assert_eq!(bucket(1), 2_000_000);
+        assert_eq!(bucket(2), 0);
+        // Below the mempool minimum, the transaction wouldn't be accepted.
+        assert_eq!(stats.projected_blocks(1), None);
+        assert_eq!(stats.projected_blocks(2), Some(3));
+        assert_eq!(stats.projected_blocks(10), Some(3));
+        assert_eq!(stats.projected_blocks(13), Some(2));
+        assert_eq!(stats.projected_blocks(30), Some(1));
+        assert_eq!(stats.lowest_feerate_within(1), Some(25));
+        assert_eq!(stats.lowest_feerate_within(2), Some(12));
+        assert_eq!(stats.lowest_feerate_within(6), Some(2));
         ms.shutdown();
     }
 
@@ -2027,7 +5965,7 @@ mod tests {
         let dummy_addr_a =
             bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
         let dummy_addr_b =
-            bitcoin::Address::from_str("bc1q39srgatmkp6k2ne3l52yhkjprdvunvspqydmkx").unwrap();
+            bitcoin::Address::from_str("bc1qe2tczyk2rw7u47kzxxee5g7ufkncdmlcpmw3pk").unwrap();
         let dummy_value_a = 50_000;
         let dummy_value_b = 60_000;
         let destinations_a: HashMap<bitcoin::Address<address::NetworkUnchecked>, u64> =
@@ -2045,69 +5983,505 @@ mod tests {
                 .iter()
                 .cloned()
                 .collect();
-        let mut psbt_a = if let CreateSpendResult::Success { psbt, .. } = control
-            .create_spend(&destinations_a, &[dummy_op_a], 1, None)
-            .unwrap()
-        {
-            psbt
-        } else {
-            panic!("expect successful spend creation")
+        let mut psbt_a = if let CreateSpendResult::Success { psbt, .. } = control
+            .create_spend(
+                &destinations_a,
+                &[dummy_op_a],
+                1,
+                None,
+                &CreateSpendOptions::default(),
+            )
+            .unwrap()
+        {
+            psbt
+        } else {
+            panic!("expect successful spend creation")
+        };
+        let txid_a = psbt_a.unsigned_tx.compute_txid();
+        let psbt_b = if let CreateSpendResult::Success { psbt, .. } = control
+            .create_spend(
+                &destinations_b,
+                &[dummy_op_b],
+                10,
+                None,
+                &CreateSpendOptions::default(),
+            )
+            .unwrap()
+        {
+            psbt
+        } else {
+            panic!("expect successful spend creation")
+        };
+        let txid_b = psbt_b.unsigned_tx.compute_txid();
+        let psbt_c = if let CreateSpendResult::Success { psbt, .. } = control
+            .create_spend(
+                &destinations_c,
+                &[dummy_op_a, dummy_op_b],
+                100,
+                None,
+                &CreateSpendOptions::default(),
+            )
+            .unwrap()
+        {
+            psbt
+        } else {
+            panic!("expect successful spend creation")
+        };
+        let txid_c = psbt_c.unsigned_tx.compute_txid();
+
+        // We can store and query them all
+        control.update_spend(psbt_a.clone()).unwrap();
+        assert_eq!(db_conn.spend_tx(&txid_a).unwrap(), psbt_a);
+        control.update_spend(psbt_b.clone()).unwrap();
+        assert_eq!(db_conn.spend_tx(&txid_b).unwrap(), psbt_b);
+        control.update_spend(psbt_c.clone()).unwrap();
+        assert_eq!(db_conn.spend_tx(&txid_c).unwrap(), psbt_c);
+        let saved: Vec<_> = control
+            .list_events(None)
+            .events
+            .into_iter()
+            .filter_map(|entry| match entry.event {
+                Event::SpendSaved { txid } => Some(txid),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(saved, vec![txid_a, txid_b, txid_c]);
+
+        // As well as update them, with or without new signatures
+        let foreign_key = secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+        sign_input(&mut psbt_a, 0, &foreign_key);
+        control.update_spend(psbt_a.clone()).unwrap();
+        assert_eq!(db_conn.spend_tx(&txid_a).unwrap(), psbt_a);
+        control.update_spend(psbt_b.clone()).unwrap();
+        assert_eq!(db_conn.spend_tx(&txid_b).unwrap(), psbt_b);
+        control.update_spend(psbt_c.clone()).unwrap();
+        assert_eq!(db_conn.spend_tx(&txid_c).unwrap(), psbt_c);
+
+        // A signature by a key of the descriptor is attributed to its signer, once. We don't have
+        // the private keys of the descriptor, sign with another key given the same origin.
+        let origin = psbt_a.inputs[0]
+            .bip32_derivation
+            .values()
+            .next()
+            .cloned()
+            .unwrap();
+        let fingerprint = origin.0;
+        let owner_key = secp256k1::SecretKey::from_slice(&[2; 32]).unwrap();
+        let pubkey = sign_input(&mut psbt_a, 0, &owner_key);
+        psbt_a.inputs[0]
+            .bip32_derivation
+            .insert(pubkey.inner, origin);
+        control.update_spend(psbt_a.clone()).unwrap();
+        assert_eq!(db_conn.spend_tx(&txid_a).unwrap(), psbt_a);
+        let events = control.list_events(None).events;
+        assert_eq!(
+            events.last().map(|entry| &entry.event),
+            Some(&Event::SpendSigned {
+                txid: txid_a,
+                signers: vec![fingerprint],
+            })
+        );
+        control.update_spend(psbt_a.clone()).unwrap();
+        assert_eq!(control.list_events(None).events.len(), events.len());
+
+        // We can't store an invalid signature, neither for a stored Spend nor for a new one.
+        let sig = bitcoin::ecdsa::Signature::from_str("304402204004fcdbb9c0d0cbf585f58cee34dccb012efbd8fc2b0d5e97760045ae35803802201a0bd7ec2383e0b93748abc9946c8e17a8312e314dab85982aeba650e738cbf401").unwrap();
+        let mut invalid_psbt = psbt_b.clone();
+        invalid_psbt.inputs[0].partial_sigs.insert(pubkey, sig);
+        let invalid_sig = Err(CommandError::InvalidSignature(
+            spend::SignatureError::Invalid {
+                input_index: 0,
+                pubkey: pubkey.to_string(),
+            },
+        ));
+        assert_eq!(control.update_spend(invalid_psbt.clone()), invalid_sig);
+        control.delete_spend(&txid_b);
+        assert_eq!(control.update_spend(invalid_psbt), invalid_sig);
+        assert!(db_conn.spend_tx(&txid_b).is_none());
+
+        // The previous outputs given for a new Spend must be those of the coins.
+        let mut altered_psbt = psbt_b.clone();
+        altered_psbt.inputs[0].witness_utxo.as_mut().unwrap().value =
+            bitcoin::Amount::from_sat(1_000_000);
+        assert_eq!(
+            control.update_spend(altered_psbt),
+            Err(CommandError::UtxoMismatch(dummy_op_b))
+        );
+        control.update_spend(psbt_b.clone()).unwrap();
+
+        // Once stored, only signatures and derivation information can be added to a Spend.
+        let mut altered_psbt = psbt_b.clone();
+        altered_psbt.inputs[0].witness_utxo.as_mut().unwrap().value =
+            bitcoin::Amount::from_sat(1_000_000);
+        assert_eq!(
+            control.update_spend(altered_psbt),
+            Err(CommandError::AlteredSpend(
+                txid_b,
+                "the previous output of input 0 changed".to_string()
+            ))
+        );
+        let mut altered_psbt = psbt_b.clone();
+        altered_psbt.inputs[0].witness_script = Some(bitcoin::ScriptBuf::new());
+        assert_eq!(
+            control.update_spend(altered_psbt),
+            Err(CommandError::AlteredSpend(
+                txid_b,
+                "the script of input 0 changed".to_string()
+            ))
+        );
+        let mut altered_psbt = psbt_b.clone();
+        altered_psbt.outputs[0].bip32_derivation.clear();
+        altered_psbt.outputs[1].bip32_derivation.clear();
+        assert!(matches!(
+            control.update_spend(altered_psbt),
+            Err(CommandError::AlteredSpend(txid, _)) if txid == txid_b
+        ));
+        let mut altered_psbt = psbt_b.clone();
+        altered_psbt.inputs[0].bip32_derivation.insert(
+            pubkey.inner,
+            psbt_a.inputs[0].bip32_derivation[&pubkey.inner].clone(),
+        );
+        control.update_spend(altered_psbt).unwrap();
+
+        // We can't store a PSBT spending an external coin
+        let external_op = bitcoin::OutPoint::from_str(
+            "8753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810:2",
+        )
+        .unwrap();
+        psbt_a.unsigned_tx.input[0].previous_output = external_op;
+        assert_eq!(
+            control.update_spend(psbt_a),
+            Err(CommandError::UnknownOutpoint(external_op))
+        );
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn list_spend_filters() {
+        let dummy_op_a = bitcoin::OutPoint::from_str(
+            "3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810:0",
+        )
+        .unwrap();
+        let dummy_op_b = bitcoin::OutPoint::from_str(
+            "4753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810:1",
+        )
+        .unwrap();
+        let mut dummy_bitcoind = DummyBitcoind::new();
+        let dummy_tx = bitcoin::Transaction {
+            version: TxVersion::TWO,
+            lock_time: absolute::LockTime::Blocks(absolute::Height::ZERO),
+            input: vec![],
+            output: vec![],
+        };
+        dummy_bitcoind
+            .txs
+            .insert(dummy_op_a.txid, (dummy_tx.clone(), None));
+        dummy_bitcoind.txs.insert(dummy_op_b.txid, (dummy_tx, None));
+        // Use a descriptor we have the owner's private key for so we can sign the drafts.
+        let secp = secp256k1::Secp256k1::new();
+        let owner_xpriv = bip32::Xpriv::new_master(bitcoin::Network::Bitcoin, &[3; 32]).unwrap();
+        let owner_key = descriptors::PathInfo::Single(
+            descriptor::DescriptorPublicKey::from_str(&format!(
+                "[{}]{}/<0;1>/*",
+                owner_xpriv.fingerprint(&secp),
+                bip32::Xpub::from_priv(&secp, &owner_xpriv)
+            ))
+            .unwrap(),
+        );
+        let heir_key = descriptors::PathInfo::Single(descriptor::DescriptorPublicKey::from_str("[aabbccdd]xpub68JJTXc1MWK8PEQozKsRatrUHXKFNkD1Cb1BuQU9Xr5moCv87anqGyXLyUd4KpnDyZgo3gz4aN1r3NiaoweFW8UutBsBbgKHzaD5HkTkifK/<0;1>/*").unwrap());
+        let desc = descriptors::LianaDescriptor::new(
+            descriptors::LianaPolicy::new_legacy(
+                owner_key,
+                [(10_000, heir_key)].iter().cloned().collect(),
+            )
+            .unwrap(),
+        );
+        let ms = DummyLiana::new_with_descriptor(dummy_bitcoind, DummyDatabase::new(), desc);
+        let control = &ms.control();
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_unspent_coins(
+            &[dummy_op_a, dummy_op_b]
+                .iter()
+                .enumerate()
+                .map(|(i, op)| Coin {
+                    outpoint: *op,
+                    is_immature: false,
+                    block_info: None,
+                    amount: bitcoin::Amount::from_sat(100_000),
+                    derivation_index: bip32::ChildNumber::from(i as u32),
+                    is_change: false,
+                    spend_txid: None,
+                    spend_block: None,
+                    is_from_self: false,
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        // Store a draft spending each coin.
+        let dest_addr =
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
+        let destinations: HashMap<bitcoin::Address<address::NetworkUnchecked>, u64> =
+            [(dest_addr, 50_000)].iter().cloned().collect();
+        let mut psbts = [dummy_op_a, dummy_op_b].iter().map(|op| {
+            match control
+                .create_spend(
+                    &destinations,
+                    &[*op],
+                    1,
+                    None,
+                    &CreateSpendOptions::default(),
+                )
+                .unwrap()
+            {
+                CreateSpendResult::Success { psbt, .. } => psbt,
+                _ => panic!("expect successful spend creation"),
+            }
+        });
+        let (mut psbt_a, psbt_b) = (psbts.next().unwrap(), psbts.next().unwrap());
+        let (txid_a, txid_b) = (
+            psbt_a.unsigned_tx.compute_txid(),
+            psbt_b.unsigned_tx.compute_txid(),
+        );
+        control.update_spend(psbt_a.clone()).unwrap();
+        control.update_spend(psbt_b).unwrap();
+        let list = |statuses: &[SpendTxStatus], signer: Option<bip32::Fingerprint>| {
+            let mut txids: Vec<_> = control
+                .list_spend(None, statuses, signer)
+                .unwrap()
+                .spend_txs
+                .into_iter()
+                .map(|entry| entry.psbt.unsigned_tx.compute_txid())
+                .collect();
+            txids.sort();
+            txids
         };
-        let txid_a = psbt_a.unsigned_tx.compute_txid();
-        let psbt_b = if let CreateSpendResult::Success { psbt, .. } = control
-            .create_spend(&destinations_b, &[dummy_op_b], 10, None)
-            .unwrap()
-        {
-            psbt
-        } else {
-            panic!("expect successful spend creation")
+        let both = {
+            let mut txids = vec![txid_a, txid_b];
+            txids.sort();
+            txids
         };
-        let txid_b = psbt_b.unsigned_tx.compute_txid();
-        let psbt_c = if let CreateSpendResult::Success { psbt, .. } = control
-            .create_spend(&destinations_c, &[dummy_op_a, dummy_op_b], 100, None)
+        let signer = owner_xpriv.fingerprint(&secp);
+        let stranger = bip32::Fingerprint::from_str("00112233").unwrap();
+
+        // None is signed yet.
+        assert_eq!(list(&[], None), both);
+        assert_eq!(list(&[SpendTxStatus::NeedsSignature], None), both);
+        assert_eq!(list(&[SpendTxStatus::NeedsSignature], Some(signer)), both);
+        assert!(list(&[SpendTxStatus::FullySigned], None).is_empty());
+        assert!(list(&[SpendTxStatus::Conflicting], None).is_empty());
+        assert!(list(&[], Some(stranger)).is_empty());
+
+        // A draft spending both coins conflicts with the two others.
+        let psbt_c = match control
+            .create_spend(
+                &destinations,
+                &[dummy_op_a, dummy_op_b],
+                1,
+                None,
+                &CreateSpendOptions::default(),
+            )
             .unwrap()
         {
-            psbt
-        } else {
-            panic!("expect successful spend creation")
+            CreateSpendResult::Success { psbt, .. } => psbt,
+            _ => panic!("expect successful spend creation"),
         };
         let txid_c = psbt_c.unsigned_tx.compute_txid();
-
-        // We can store and query them all
+        control.update_spend(psbt_c).unwrap();
+        let conflicts = |txid: bitcoin::Txid| {
+            control
+                .list_spend(Some(vec![txid]), &[], None)
+                .unwrap()
+                .spend_txs
+                .remove(0)
+                .conflicting_spends
+        };
+        assert_eq!(conflicts(txid_a), vec![txid_c]);
+        assert_eq!(conflicts(txid_b), vec![txid_c]);
+        assert_eq!(conflicts(txid_c), both);
+        control.delete_spend(&txid_c);
+        assert!(conflicts(txid_a).is_empty());
+
+        // A signature by another key advertised with the owner's origin isn't accounted for.
+        let (_, der_path) = psbt_a.inputs[0]
+            .bip32_derivation
+            .values()
+            .find(|(fg, _)| *fg == signer)
+            .cloned()
+            .unwrap();
+        let forged_key = secp256k1::SecretKey::from_slice(&[2; 32]).unwrap();
+        let pubkey = sign_input(&mut psbt_a, 0, &forged_key);
+        psbt_a.inputs[0]
+            .bip32_derivation
+            .insert(pubkey.inner, (signer, der_path.clone()));
         control.update_spend(psbt_a.clone()).unwrap();
-        assert_eq!(db_conn.spend_tx(&txid_a).unwrap(), psbt_a);
-        control.update_spend(psbt_b.clone()).unwrap();
-        assert_eq!(db_conn.spend_tx(&txid_b).unwrap(), psbt_b);
-        control.update_spend(psbt_c.clone()).unwrap();
-        assert_eq!(db_conn.spend_tx(&txid_c).unwrap(), psbt_c);
-
-        // As well as update them, with or without new signatures
-        let sig = bitcoin::ecdsa::Signature::from_str("304402204004fcdbb9c0d0cbf585f58cee34dccb012efbd8fc2b0d5e97760045ae35803802201a0bd7ec2383e0b93748abc9946c8e17a8312e314dab85982aeba650e738cbf401").unwrap();
-        psbt_a.inputs[0].partial_sigs.insert(
-            bitcoin::PublicKey::from_str(
-                "023a664c5617412f0b292665b1fd9d766456a7a3b1614c7e7c5f411200ff1958ef",
-            )
-            .unwrap(),
-            sig,
+        assert!(list(&[SpendTxStatus::FullySigned], None).is_empty());
+        let res = control.finalize_psbt(&psbt_a);
+        assert!(!res.complete);
+        assert_eq!(res.inputs.len(), 1);
+        assert_eq!(res.inputs[0].path, None);
+        assert!(res.inputs[0].error.is_some());
+        assert_eq!(res.inputs[0].missing.len(), 1);
+        assert_eq!(
+            res.inputs[0].missing[0].path,
+            descriptors::SpendingPath::Primary
+        );
+        assert_eq!(res.inputs[0].missing[0].count, 1);
+        assert_eq!(res.inputs[0].missing[0].signers, vec![signer]);
+
+        // Once the first one is signed it doesn't need the signer anymore.
+        let owner_key = owner_xpriv.derive_priv(&secp, &der_path).unwrap();
+        sign_input(&mut psbt_a, 0, &owner_key.private_key);
+        let res = control.finalize_psbt(&psbt_a);
+        assert!(res.complete);
+        assert_eq!(res.inputs[0].path, Some(descriptors::SpendingPath::Primary));
+        assert!(res.inputs[0].missing.is_empty());
+        control.update_spend(psbt_a).unwrap();
+        assert_eq!(list(&[SpendTxStatus::FullySigned], None), vec![txid_a]);
+        assert_eq!(
+            list(&[SpendTxStatus::NeedsSignature], Some(signer)),
+            vec![txid_b]
+        );
+        assert_eq!(
+            list(
+                &[SpendTxStatus::NeedsSignature, SpendTxStatus::FullySigned],
+                None
+            ),
+            both
         );
-        control.update_spend(psbt_a.clone()).unwrap();
-        assert_eq!(db_conn.spend_tx(&txid_a).unwrap(), psbt_a);
-        control.update_spend(psbt_b.clone()).unwrap();
-        assert_eq!(db_conn.spend_tx(&txid_b).unwrap(), psbt_b);
-        control.update_spend(psbt_c.clone()).unwrap();
-        assert_eq!(db_conn.spend_tx(&txid_c).unwrap(), psbt_c);
 
-        // We can't store a PSBT spending an external coin
-        let external_op = bitcoin::OutPoint::from_str(
-            "8753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810:2",
+        // It can only be broadcast once its coin is confirmed.
+        db_conn.update_tip(&BlockChainTip {
+            height: 200,
+            hash: bitcoin::BlockHash::all_zeros(),
+        });
+        assert!(list(&[SpendTxStatus::Broadcastable], None).is_empty());
+        db_conn.confirm_coins(&[(dummy_op_a, 150, 1_700_000_000)]);
+        assert_eq!(list(&[SpendTxStatus::Broadcastable], None), vec![txid_a]);
+
+        // Not anymore once broadcast. The other one conflicts with a transaction spending its coin.
+        db_conn.spend_coins(&[(dummy_op_a, txid_a), (dummy_op_b, txid_a)]);
+        assert!(list(&[SpendTxStatus::Broadcastable], None).is_empty());
+        assert_eq!(list(&[SpendTxStatus::Conflicting], None), vec![txid_b]);
+
+        // Once the transaction spending its coin is confirmed, the draft is invalidated by it.
+        let invalidated_by = |txid: bitcoin::Txid| {
+            control
+                .list_spend(Some(vec![txid]), &[], None)
+                .unwrap()
+                .spend_txs[0]
+                .invalidated_by
+        };
+        assert_eq!(invalidated_by(txid_b), None);
+        db_conn.confirm_spend(&[
+            (dummy_op_a, txid_a, 201, 1_700_000_000),
+            (dummy_op_b, txid_a, 201, 1_700_000_000),
+        ]);
+        assert_eq!(invalidated_by(txid_b), Some(txid_a));
+        assert_eq!(invalidated_by(txid_a), None);
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn review_spend() {
+        let dummy_op = bitcoin::OutPoint::from_str(
+            "3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810:0",
         )
         .unwrap();
-        psbt_a.unsigned_tx.input[0].previous_output = external_op;
+        let mut dummy_bitcoind = DummyBitcoind::new();
+        dummy_bitcoind.txs.insert(
+            dummy_op.txid,
+            (
+                bitcoin::Transaction {
+                    version: TxVersion::TWO,
+                    lock_time: absolute::LockTime::Blocks(absolute::Height::ZERO),
+                    input: vec![],
+                    output: vec![],
+                },
+                None,
+            ),
+        );
+        let ms = DummyLiana::new(dummy_bitcoind, DummyDatabase::new());
+        let control = &ms.control();
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_unspent_coins(&[Coin {
+            outpoint: dummy_op,
+            is_immature: false,
+            block_info: None,
+            amount: bitcoin::Amount::from_sat(100_000),
+            derivation_index: bip32::ChildNumber::from(0),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        }]);
+        let dest_addr =
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
+        let destinations: HashMap<bitcoin::Address<address::NetworkUnchecked>, u64> =
+            [(dest_addr, 50_000)].iter().cloned().collect();
+        let psbt = match control
+            .create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default(),
+            )
+            .unwrap()
+        {
+            CreateSpendResult::Success { psbt, .. } => psbt,
+            _ => panic!("expect successful spend creation"),
+        };
+        let txid = psbt.unsigned_tx.compute_txid();
+        let signer = bip32::Fingerprint::from_str("aabbccdd").unwrap();
+        let stranger = bip32::Fingerprint::from_str("00112233").unwrap();
+
+        // The Spend must be stored and the signer be one of the wallet's.
         assert_eq!(
-            control.update_spend(psbt_a),
-            Err(CommandError::UnknownOutpoint(external_op))
+            control.review_spend(&txid, signer, ReviewDecision::Approved, None),
+            Err(CommandError::UnknownSpend(txid))
+        );
+        control.update_spend(psbt).unwrap();
+        assert_eq!(
+            control.review_spend(&txid, stranger, ReviewDecision::Approved, None),
+            Err(CommandError::UnknownSigner(stranger))
+        );
+        let entry = control.list_spend(None, &[], None).unwrap().spend_txs[0].clone();
+        assert!(entry.reviews.is_empty());
+        assert_eq!(entry.review_state, ReviewState::Pending);
+
+        // The latest review of the signer replaces the previous one.
+        control
+            .review_spend(&txid, signer, ReviewDecision::Approved, None)
+            .unwrap();
+        control
+            .review_spend(
+                &txid,
+                signer,
+                ReviewDecision::Rejected,
+                Some("Wrong address".to_string()),
+            )
+            .unwrap();
+        let entry = control.list_spend(None, &[], None).unwrap().spend_txs[0].clone();
+        assert_eq!(entry.reviews.len(), 1);
+        assert_eq!(entry.reviews[0].signer, signer);
+        assert_eq!(entry.reviews[0].decision, ReviewDecision::Rejected);
+        assert_eq!(entry.reviews[0].comment.as_deref(), Some("Wrong address"));
+        assert_eq!(entry.review_state, ReviewState::Rejected);
+        assert_eq!(
+            control.list_events(None).events.last().map(|e| &e.event),
+            Some(&Event::SpendReviewed {
+                txid,
+                signer,
+                decision: ReviewDecision::Rejected,
+            })
         );
 
+        // The reviews are deleted along with the Spend.
+        control.delete_spend(&txid);
+        assert!(db_conn.spend_reviews(&txid).is_empty());
+
         ms.shutdown();
     }
 
@@ -2327,9 +6701,160 @@ mod tests {
 
         let mut txs_map = HashMap::new();
         txs_map.insert(
-            deposit1.compute_txid(),
+            deposit1.compute_txid(),
+            (
+                deposit1.clone(),
+                Some(Block {
+                    hash: bitcoin::BlockHash::from_str(
+                        "0000000000000000000326b8fca8d3f820647c97ea33ef722096b3c7b2c8ee94",
+                    )
+                    .unwrap(),
+                    time: 1,
+                    height: 1,
+                }),
+            ),
+        );
+        txs_map.insert(
+            deposit2.compute_txid(),
+            (
+                deposit2.clone(),
+                Some(Block {
+                    hash: bitcoin::BlockHash::from_str(
+                        "0000000000000000000326b8fca8d3f820647c97ea33ef722096b3c7b2c8ee94",
+                    )
+                    .unwrap(),
+                    time: 2,
+                    height: 2,
+                }),
+            ),
+        );
+        txs_map.insert(
+            spend_tx.compute_txid(),
+            (
+                spend_tx.clone(),
+                Some(Block {
+                    hash: bitcoin::BlockHash::from_str(
+                        "0000000000000000000326b8fca8d3f820647c97ea33ef722096b3c7b2c8ee94",
+                    )
+                    .unwrap(),
+                    time: 3,
+                    height: 3,
+                }),
+            ),
+        );
+        txs_map.insert(
+            deposit3.compute_txid(),
+            (
+                deposit3.clone(),
+                Some(Block {
+                    hash: bitcoin::BlockHash::from_str(
+                        "0000000000000000000326b8fca8d3f820647c97ea33ef722096b3c7b2c8ee94",
+                    )
+                    .unwrap(),
+                    time: 4,
+                    height: 4,
+                }),
+            ),
+        );
+
+        let ms = DummyLiana::new(DummyBitcoind::new(), db);
+
+        let control = &ms.control();
+        let mut db_conn = control.db.connection();
+        let txs: Vec<_> = txs_map.values().map(|(tx, _)| tx.clone()).collect();
+        db_conn.new_txs(&txs);
+
+        let mut transactions = control.list_confirmed_transactions(0, 4, 10).transactions;
+        transactions.sort_by(|tx1, tx2| tx2.height.cmp(&tx1.height));
+        assert_eq!(transactions.len(), 4);
+
+        assert_eq!(transactions[0].time, Some(4));
+        assert_eq!(transactions[0].tx, deposit3);
+
+        assert_eq!(transactions[1].time, Some(3));
+        assert_eq!(transactions[1].tx, spend_tx);
+
+        assert_eq!(transactions[2].time, Some(2));
+        assert_eq!(transactions[2].tx, deposit2);
+
+        assert_eq!(transactions[3].time, Some(1));
+        assert_eq!(transactions[3].tx, deposit1);
+
+        let mut transactions = control.list_confirmed_transactions(2, 3, 10).transactions;
+        transactions.sort_by(|tx1, tx2| tx2.height.cmp(&tx1.height));
+        assert_eq!(transactions.len(), 2);
+
+        assert_eq!(transactions[0].time, Some(3));
+        assert_eq!(transactions[1].time, Some(2));
+        assert_eq!(transactions[1].tx, deposit2);
+
+        let transactions = control.list_confirmed_transactions(2, 3, 1).transactions;
+        assert_eq!(transactions.len(), 1);
+
+        assert_eq!(transactions[0].time, Some(3));
+        assert_eq!(transactions[0].tx, spend_tx);
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn list_transactions() {
+        let outpoint = OutPoint::new(
+            Txid::from_str("617eab1fc0b03ee7f82ba70166725291783461f1a0e7975eaf8b5f8f674234f3")
+                .unwrap(),
+            0,
+        );
+
+        let tx1: Transaction = Transaction {
+            version: TxVersion::ONE,
+            lock_time: absolute::LockTime::Blocks(absolute::Height::from_consensus(1).unwrap()),
+            input: vec![TxIn {
+                witness: Witness::new(),
+                previous_output: outpoint,
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence(0),
+            }],
+            output: vec![TxOut {
+                script_pubkey: ScriptBuf::new(),
+                value: Amount::from_sat(100_000_000),
+            }],
+        };
+
+        let tx2: Transaction = Transaction {
+            version: TxVersion::ONE,
+            lock_time: absolute::LockTime::Blocks(absolute::Height::from_consensus(1).unwrap()),
+            input: vec![TxIn {
+                witness: Witness::new(),
+                previous_output: outpoint,
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence(0),
+            }],
+            output: vec![TxOut {
+                script_pubkey: ScriptBuf::new(),
+                value: Amount::from_sat(2000),
+            }],
+        };
+
+        let tx3: Transaction = Transaction {
+            version: TxVersion::ONE,
+            lock_time: absolute::LockTime::Blocks(absolute::Height::from_consensus(1).unwrap()),
+            input: vec![TxIn {
+                witness: Witness::new(),
+                previous_output: outpoint,
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence(0),
+            }],
+            output: vec![TxOut {
+                script_pubkey: ScriptBuf::new(),
+                value: Amount::from_sat(3000),
+            }],
+        };
+
+        let mut txs_map = HashMap::new();
+        txs_map.insert(
+            tx1.compute_txid(),
             (
-                deposit1.clone(),
+                tx1.clone(),
                 Some(Block {
                     hash: bitcoin::BlockHash::from_str(
                         "0000000000000000000326b8fca8d3f820647c97ea33ef722096b3c7b2c8ee94",
@@ -2341,9 +6866,9 @@ mod tests {
             ),
         );
         txs_map.insert(
-            deposit2.compute_txid(),
+            tx2.compute_txid(),
             (
-                deposit2.clone(),
+                tx2.clone(),
                 Some(Block {
                     hash: bitcoin::BlockHash::from_str(
                         "0000000000000000000326b8fca8d3f820647c97ea33ef722096b3c7b2c8ee94",
@@ -2355,23 +6880,9 @@ mod tests {
             ),
         );
         txs_map.insert(
-            spend_tx.compute_txid(),
-            (
-                spend_tx.clone(),
-                Some(Block {
-                    hash: bitcoin::BlockHash::from_str(
-                        "0000000000000000000326b8fca8d3f820647c97ea33ef722096b3c7b2c8ee94",
-                    )
-                    .unwrap(),
-                    time: 3,
-                    height: 3,
-                }),
-            ),
-        );
-        txs_map.insert(
-            deposit3.compute_txid(),
+            tx3.compute_txid(),
             (
-                deposit3.clone(),
+                tx3.clone(),
                 Some(Block {
                     hash: bitcoin::BlockHash::from_str(
                         "0000000000000000000326b8fca8d3f820647c97ea33ef722096b3c7b2c8ee94",
@@ -2383,189 +6894,701 @@ mod tests {
             ),
         );
 
-        let ms = DummyLiana::new(DummyBitcoind::new(), db);
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = &ms.control();
+        let mut db_conn = control.db.connection();
+        let txs: Vec<_> = txs_map.values().map(|(tx, _)| tx.clone()).collect();
+        db_conn.new_txs(&txs);
+        // We need coins in the DB in order to get the block info for the transactions.
+        for (txid, (_tx, block)) in txs_map {
+            // Insert more than one coin per transaction to check that the command does not
+            // return duplicate transactions.
+            for vout in 0..4 {
+                db_conn.new_unspent_coins(&[Coin {
+                    outpoint: bitcoin::OutPoint::new(txid, vout),
+                    is_immature: false,
+                    block_info: block.map(|b| BlockInfo {
+                        height: b.height,
+                        time: b.time,
+                    }),
+                    amount: bitcoin::Amount::from_sat(100_000),
+                    derivation_index: bip32::ChildNumber::from(13),
+                    is_change: false,
+                    spend_txid: None,
+                    spend_block: None,
+                    is_from_self: false,
+                }]);
+            }
+        }
+
+        let transactions = control
+            .list_transactions(&[tx1.compute_txid()])
+            .transactions;
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].tx, tx1);
+
+        let transactions = control
+            .list_transactions(&[tx1.compute_txid(), tx2.compute_txid(), tx3.compute_txid()])
+            .transactions;
+        assert_eq!(transactions.len(), 3);
+
+        let txs: Vec<Transaction> = transactions
+            .iter()
+            .map(|transaction| transaction.tx.clone())
+            .collect();
+
+        assert!(txs.contains(&tx1));
+        assert!(txs.contains(&tx2));
+        assert!(txs.contains(&tx3));
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn get_transaction() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = &ms.control();
+        let mut db_conn = control.db.connection();
+
+        // A deposit to the wallet, spent by a Spend transaction paying a third party.
+        let deposit = Transaction {
+            version: TxVersion::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![TxOut {
+                script_pubkey: ScriptBuf::new(),
+                value: Amount::from_sat(100_000),
+            }],
+        };
+        let deposit_op = OutPoint::new(deposit.compute_txid(), 0);
+        let spend = Transaction {
+            version: TxVersion::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: deposit_op,
+                ..TxIn::default()
+            }],
+            output: vec![TxOut {
+                script_pubkey: ScriptBuf::new(),
+                value: Amount::from_sat(99_000),
+            }],
+        };
+        let spend_txid = spend.compute_txid();
+        db_conn.new_txs(&[deposit.clone(), spend.clone()]);
+        db_conn.new_unspent_coins(&[Coin {
+            outpoint: deposit_op,
+            is_immature: false,
+            block_info: Some(BlockInfo {
+                height: 1,
+                time: 1_700_000_000,
+            }),
+            amount: Amount::from_sat(100_000),
+            derivation_index: ChildNumber::from(0),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        }]);
+        db_conn.spend_coins(&[(deposit_op, spend_txid)]);
+        db_conn.store_spend(&Psbt::from_unsigned_tx(spend.clone()).unwrap());
+        db_conn.update_labels(&HashMap::from([(
+            LabelItem::Txid(spend_txid),
+            Some("rent".to_string()),
+        )]));
+
+        let res = control.get_transaction(&spend_txid).unwrap();
+        assert_eq!(res.tx, spend);
+        assert_eq!(res.psbt.unwrap().unsigned_tx, spend);
+        assert_eq!(res.spent_coins.len(), 1);
+        assert_eq!(res.spent_coins[0].outpoint, deposit_op);
+        assert!(res.received_coins.is_empty());
+        assert_eq!(res.fee, Some(Amount::from_sat(1_000)));
+        assert_eq!(res.label.as_deref(), Some("rent"));
+        assert_eq!(res.inclusion_verified, None);
+
+        // We don't know the value of the inputs of the deposit.
+        let res = control.get_transaction(&deposit.compute_txid()).unwrap();
+        assert!(res.psbt.is_none());
+        assert!(res.spent_coins.is_empty());
+        assert_eq!(res.received_coins.len(), 1);
+        assert_eq!(res.fee, None);
+
+        assert!(matches!(
+            control.get_transaction(&OutPoint::null().txid),
+            Err(CommandError::UnknownTransaction(_))
+        ));
+        assert_eq!(control.export_transactions().transactions.len(), 2);
+
+        // Only confirmed transactions get a receipt.
+        assert!(matches!(
+            control.get_receipt(&spend_txid, None),
+            Err(CommandError::UnconfirmedTransaction(_))
+        ));
+        let res = control
+            .get_receipt(
+                &deposit.compute_txid(),
+                Some("https://explorer.example/tx/"),
+            )
+            .unwrap();
+        assert_eq!(res.receipt.block_height, 1);
+        assert_eq!(res.receipt.amount, 100_000);
+        assert_eq!(res.receipt.fee, None);
+        assert!(res.receipt.outputs[0].is_ours);
+        assert_eq!(
+            res.receipt.explorer_link,
+            format!("https://explorer.example/tx/{}", deposit.compute_txid())
+        );
+        let pdf = bitcoin::base64::engine::general_purpose::STANDARD
+            .decode(&res.pdf)
+            .unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn spend_templates() {
+        let dummy_tx = bitcoin::Transaction {
+            version: TxVersion::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![],
+            output: vec![],
+        };
+        let dummy_op = bitcoin::OutPoint::new(dummy_tx.compute_txid(), 0);
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = &ms.control();
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_txs(&[dummy_tx]);
+        db_conn.new_unspent_coins(&[Coin {
+            outpoint: dummy_op,
+            is_immature: false,
+            block_info: None,
+            amount: bitcoin::Amount::from_sat(100_000),
+            derivation_index: bip32::ChildNumber::from(13),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        }]);
+
+        let addr =
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
+        let mut template = SpendTemplate {
+            name: "Rent".to_string(),
+            recipients: vec![crate::templates::TemplateRecipient {
+                address: addr.clone(),
+                amount: None,
+            }],
+            feerate: 1,
+            coins: vec![dummy_op],
+            schedule: None,
+        };
+        control.save_spend_template(template.clone()).unwrap();
+        assert_eq!(
+            control.list_spend_templates().templates,
+            vec![template.clone()]
+        );
+
+        // The asked amount must be given when instantiating it.
+        assert!(matches!(
+            control.instantiate_spend_template("Rent", &HashMap::new(), None),
+            Err(CommandError::InvalidSpendTemplate(_))
+        ));
+        assert!(matches!(
+            control.instantiate_spend_template("Groceries", &HashMap::new(), None),
+            Err(CommandError::UnknownSpendTemplate(_))
+        ));
+        let psbt = match control
+            .instantiate_spend_template("Rent", &HashMap::from([(addr.clone(), 20_000)]), Some(2))
+            .unwrap()
+        {
+            CreateSpendResult::Success { psbt, .. } => psbt,
+            _ => panic!("expect successful spend creation"),
+        };
+        assert_eq!(psbt.unsigned_tx.input[0].previous_output, dummy_op);
+        assert_eq!(psbt.unsigned_tx.output[0].value.to_sat(), 20_000);
+
+        // A template can only be scheduled if it has all its amounts.
+        template.schedule = Some(crate::templates::Schedule {
+            every_days: 7,
+            last_draft_at: None,
+        });
+        assert!(matches!(
+            control.save_spend_template(template.clone()),
+            Err(CommandError::InvalidSpendTemplate(_))
+        ));
+        template.recipients[0].amount = Some(bitcoin::Amount::from_sat(20_000));
+        control.save_spend_template(template).unwrap();
+
+        // A draft is created and labelled once it's due.
+        let now = 1_700_000_000;
+        let txids = control.create_scheduled_drafts(now);
+        assert_eq!(txids.len(), 1);
+        assert_eq!(
+            control.list_spend(None, &[], None).unwrap().spend_txs.len(),
+            1
+        );
+        assert_eq!(
+            control
+                .get_labels(&HashSet::from([LabelItem::Txid(txids[0])]))
+                .labels
+                .get(&txids[0].to_string())
+                .map(String::as_str),
+            Some("Rent")
+        );
+        assert!(control.create_scheduled_drafts(now + 60).is_empty());
+        assert_eq!(
+            control.create_scheduled_drafts(now + 7 * 24 * 3600).len(),
+            1
+        );
+
+        control.delete_spend_template("Rent");
+        assert!(control.list_spend_templates().templates.is_empty());
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn scheduled_spends() {
+        let dummy_tx = bitcoin::Transaction {
+            version: TxVersion::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![],
+            output: vec![],
+        };
+        let dummy_op = bitcoin::OutPoint::new(dummy_tx.compute_txid(), 0);
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = &ms.control();
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_txs(&[dummy_tx]);
+        db_conn.new_unspent_coins(&[Coin {
+            outpoint: dummy_op,
+            is_immature: false,
+            block_info: None,
+            amount: bitcoin::Amount::from_sat(100_000),
+            derivation_index: bip32::ChildNumber::from(13),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        }]);
+        let destinations = HashMap::from([(
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap(),
+            20_000,
+        )]);
+
+        // The locktime must be in the future. The dummy tip is at height 100.
+        let reached = absolute::LockTime::from_height(100).unwrap();
+        assert_eq!(
+            control.create_scheduled_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default(),
+                reached
+            ),
+            Err(CommandError::LocktimeReached(reached))
+        );
+        let locktime = absolute::LockTime::from_height(1_000).unwrap();
+        let psbt = match control
+            .create_scheduled_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default(),
+                locktime,
+            )
+            .unwrap()
+        {
+            CreateSpendResult::Success { psbt, .. } => psbt,
+            _ => panic!("expect successful spend creation"),
+        };
+        assert_eq!(psbt.unsigned_tx.lock_time, locktime);
+        assert!(psbt.unsigned_tx.is_lock_time_enabled());
+
+        // It must be stored and signed to be scheduled.
+        let txid = psbt.unsigned_tx.compute_txid();
+        assert_eq!(
+            control.schedule_spend(&txid, None),
+            Err(CommandError::UnknownSpend(txid))
+        );
+        control.update_spend(psbt).unwrap();
+        assert!(matches!(
+            control.schedule_spend(&txid, None),
+            Err(CommandError::SpendFinalization(_))
+        ));
+        assert!(control.list_scheduled_spends().scheduled_spends.is_empty());
+        assert_eq!(
+            control.unschedule_spend(&txid),
+            Err(CommandError::UnknownSpend(txid))
+        );
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn spend_confirmation() {
+        let dummy_tx = bitcoin::Transaction {
+            version: TxVersion::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![],
+            output: vec![],
+        };
+        let dummy_op = bitcoin::OutPoint::new(dummy_tx.compute_txid(), 0);
+        let ms = DummyLiana::new_with_config(DummyBitcoind::new(), DummyDatabase::new(), |c| {
+            c.spend_confirmation_threshold = Some(10_000)
+        });
+        let control = &ms.control();
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_txs(&[dummy_tx]);
+        db_conn.new_unspent_coins(&[Coin {
+            outpoint: dummy_op,
+            is_immature: false,
+            block_info: None,
+            amount: bitcoin::Amount::from_sat(100_000),
+            derivation_index: bip32::ChildNumber::from(13),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        }]);
+        let destinations = HashMap::from([(
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap(),
+            20_000,
+        )]);
+        let psbt = match control
+            .create_spend(
+                &destinations,
+                &[dummy_op],
+                1,
+                None,
+                &CreateSpendOptions::default(),
+            )
+            .unwrap()
+        {
+            CreateSpendResult::Success { psbt, .. } => psbt,
+            _ => panic!("expect successful spend creation"),
+        };
+        // There is a change output, which isn't sent out of the wallet.
+        assert_eq!(psbt.unsigned_tx.output.len(), 2);
+        let txid = psbt.unsigned_tx.compute_txid();
+        control.update_spend(psbt).unwrap();
+
+        let not_confirmed = Err(CommandError::SpendNotConfirmed(
+            bitcoin::Amount::from_sat(20_000),
+            bitcoin::Amount::from_sat(10_000),
+        ));
+        assert_eq!(control.broadcast_spend(&txid, None), not_confirmed);
+        assert_eq!(
+            control.broadcast_spend(&txid, Some(bitcoin::Amount::from_sat(2_000))),
+            not_confirmed
+        );
+        assert_eq!(control.schedule_spend(&txid, None), not_confirmed);
+        // Once confirmed, it goes on to be finalized. It isn't signed.
+        assert!(matches!(
+            control.broadcast_spend(&txid, Some(bitcoin::Amount::from_sat(20_000))),
+            Err(CommandError::SpendFinalization(_))
+        ));
+
+        ms.shutdown();
+    }
 
+    #[test]
+    fn recovery_readiness() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
         let control = &ms.control();
         let mut db_conn = control.db.connection();
-        let txs: Vec<_> = txs_map.values().map(|(tx, _)| tx.clone()).collect();
-        db_conn.new_txs(&txs);
-
-        let mut transactions = control.list_confirmed_transactions(0, 4, 10).transactions;
-        transactions.sort_by(|tx1, tx2| tx2.height.cmp(&tx1.height));
-        assert_eq!(transactions.len(), 4);
 
-        assert_eq!(transactions[0].time, Some(4));
-        assert_eq!(transactions[0].tx, deposit3);
+        let readiness = control.get_recovery_readiness();
+        assert_eq!(readiness.score, 20);
+        assert_eq!(readiness.expiring_coins, 0);
 
-        assert_eq!(transactions[1].time, Some(3));
-        assert_eq!(transactions[1].tx, spend_tx);
+        control
+            .set_recovery_readiness(ReadinessItem::DescriptorBackup, true)
+            .unwrap();
+        control
+            .set_recovery_readiness(ReadinessItem::HeirsKit, true)
+            .unwrap();
+        let readiness = control.get_recovery_readiness();
+        assert_eq!(readiness.score, 60);
+        assert!(readiness
+            .checks
+            .iter()
+            .find(|c| c.item == ReadinessItem::DescriptorBackup)
+            .unwrap()
+            .completed_at
+            .is_some());
+        control
+            .set_recovery_readiness(ReadinessItem::HeirsKit, false)
+            .unwrap();
+        assert_eq!(control.get_recovery_readiness().score, 40);
 
-        assert_eq!(transactions[2].time, Some(2));
-        assert_eq!(transactions[2].tx, deposit2);
+        // The timelock margin can't be attested.
+        assert_eq!(
+            control.set_recovery_readiness(ReadinessItem::TimelockMargin, true),
+            Err(CommandError::ComputedReadinessItem(
+                ReadinessItem::TimelockMargin
+            ))
+        );
 
-        assert_eq!(transactions[3].time, Some(1));
-        assert_eq!(transactions[3].tx, deposit1);
+        // A coin whose recovery path is available in a single block makes the margin check fail.
+        let timelock = control.config.main_descriptor.first_timelock_value() as i32;
+        db_conn.new_unspent_coins(&[Coin {
+            outpoint: OutPoint::null(),
+            is_immature: false,
+            block_info: Some(BlockInfo {
+                height: 1,
+                time: 1_700_000_000,
+            }),
+            amount: Amount::from_sat(100_000),
+            derivation_index: ChildNumber::from(0),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        }]);
+        db_conn.update_tip(&BlockChainTip {
+            height: timelock,
+            hash: bitcoin::BlockHash::all_zeros(),
+        });
+        let readiness = control.get_recovery_readiness();
+        assert_eq!(readiness.expiring_coins, 1);
+        assert_eq!(readiness.score, 20);
 
-        let mut transactions = control.list_confirmed_transactions(2, 3, 10).transactions;
-        transactions.sort_by(|tx1, tx2| tx2.height.cmp(&tx1.height));
-        assert_eq!(transactions.len(), 2);
+        ms.shutdown();
+    }
 
-        assert_eq!(transactions[0].time, Some(3));
-        assert_eq!(transactions[1].time, Some(2));
-        assert_eq!(transactions[1].tx, deposit2);
+    #[test]
+    fn recovery_schedule() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = &ms.control();
+        let mut db_conn = control.db.connection();
+        assert_eq!(
+            control.get_recovery_schedule(None, None),
+            Err(CommandError::NotSynced)
+        );
 
-        let transactions = control.list_confirmed_transactions(2, 3, 1).transactions;
-        assert_eq!(transactions.len(), 1);
+        let timelock = control.config.main_descriptor.first_timelock_value();
+        let coin = |vout: u32, height: i32, sats: u64| Coin {
+            outpoint: OutPoint::new(Txid::all_zeros(), vout),
+            is_immature: false,
+            block_info: Some(BlockInfo {
+                height,
+                time: 1_700_000_000,
+            }),
+            amount: Amount::from_sat(sats),
+            derivation_index: ChildNumber::from(vout),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        };
+        let tip_height = 20_000;
+        db_conn.update_tip(&BlockChainTip {
+            height: tip_height,
+            hash: bitcoin::BlockHash::all_zeros(),
+        });
+        // The first coin is available at the next block, the others in the first and third
+        // ranges of 10 blocks after it.
+        let first_height = tip_height + 2 - i32::from(timelock);
+        db_conn.new_unspent_coins(&[
+            coin(0, first_height - 1, 10_000),
+            coin(1, first_height, 20_000),
+            coin(2, first_height + 9, 30_000),
+            coin(3, first_height + 25, 40_000),
+        ]);
+        let schedule = control.get_recovery_schedule(None, Some(10)).unwrap();
+        assert_eq!(schedule.timelock, timelock);
+        assert_eq!(schedule.tip_height, tip_height);
+        assert_eq!(schedule.available, Amount::from_sat(10_000));
+        assert_eq!(
+            schedule.buckets,
+            vec![
+                RecoveryScheduleBucket {
+                    start_height: tip_height + 2,
+                    end_height: tip_height + 11,
+                    amount: Amount::from_sat(50_000),
+                    coins: 2,
+                },
+                RecoveryScheduleBucket {
+                    start_height: tip_height + 12,
+                    end_height: tip_height + 21,
+                    amount: Amount::ZERO,
+                    coins: 0,
+                },
+                RecoveryScheduleBucket {
+                    start_height: tip_height + 22,
+                    end_height: tip_height + 31,
+                    amount: Amount::from_sat(40_000),
+                    coins: 1,
+                },
+            ]
+        );
 
-        assert_eq!(transactions[0].time, Some(3));
-        assert_eq!(transactions[0].tx, spend_tx);
+        // With a single range covering them all.
+        let schedule = control.get_recovery_schedule(None, Some(100)).unwrap();
+        assert_eq!(schedule.buckets.len(), 1);
+        assert_eq!(schedule.buckets[0].amount, Amount::from_sat(90_000));
 
         ms.shutdown();
     }
 
     #[test]
-    fn list_transactions() {
-        let outpoint = OutPoint::new(
-            Txid::from_str("617eab1fc0b03ee7f82ba70166725291783461f1a0e7975eaf8b5f8f674234f3")
-                .unwrap(),
-            0,
+    fn simulate_signer_loss() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = &ms.control();
+        let mut db_conn = control.db.connection();
+        let coin = Coin {
+            outpoint: OutPoint::null(),
+            is_immature: false,
+            block_info: Some(BlockInfo {
+                height: 1,
+                time: 1_700_000_000,
+            }),
+            amount: Amount::from_sat(100_000),
+            derivation_index: ChildNumber::from(0),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        };
+        db_conn.new_unspent_coins(&[coin]);
+
+        // Without losing any signer, coins are spendable through the primary path.
+        let res = control.simulate_signer_loss(&[]).unwrap();
+        assert_eq!(res.paths.len(), 2);
+        assert!(res.paths.iter().all(|p| p.usable));
+        assert_eq!(res.paths[0].timelock, None);
+        assert_eq!(res.paths[1].timelock, Some(10_000));
+        assert_eq!(res.spendable_after, Some(0));
+        assert_eq!(
+            res.coins,
+            vec![SignerLossCoin {
+                outpoint: coin.outpoint,
+                amount: coin.amount,
+                spendable_now: true,
+                spendable_at_height: Some(1),
+            }]
         );
 
-        let tx1: Transaction = Transaction {
-            version: TxVersion::ONE,
-            lock_time: absolute::LockTime::Blocks(absolute::Height::from_consensus(1).unwrap()),
-            input: vec![TxIn {
-                witness: Witness::new(),
-                previous_output: outpoint,
-                script_sig: ScriptBuf::new(),
-                sequence: Sequence(0),
-            }],
-            output: vec![TxOut {
-                script_pubkey: ScriptBuf::new(),
-                value: Amount::from_sat(100_000_000),
-            }],
-        };
+        // The test descriptor uses the same signer for both paths.
+        let fg = bip32::Fingerprint::from_str("aabbccdd").unwrap();
+        let res = control.simulate_signer_loss(&[fg]).unwrap();
+        assert!(res.paths.iter().all(|p| !p.usable && p.remaining_keys == 0));
+        assert_eq!(res.spendable_after, None);
+        assert!(!res.coins[0].spendable_now);
+        assert_eq!(res.coins[0].spendable_at_height, None);
 
-        let tx2: Transaction = Transaction {
-            version: TxVersion::ONE,
-            lock_time: absolute::LockTime::Blocks(absolute::Height::from_consensus(1).unwrap()),
-            input: vec![TxIn {
-                witness: Witness::new(),
-                previous_output: outpoint,
-                script_sig: ScriptBuf::new(),
-                sequence: Sequence(0),
-            }],
-            output: vec![TxOut {
-                script_pubkey: ScriptBuf::new(),
-                value: Amount::from_sat(2000),
-            }],
-        };
+        let unknown = bip32::Fingerprint::from_str("00112233").unwrap();
+        assert_eq!(
+            control.simulate_signer_loss(&[unknown]),
+            Err(CommandError::UnknownSigner(unknown))
+        );
 
-        let tx3: Transaction = Transaction {
-            version: TxVersion::ONE,
-            lock_time: absolute::LockTime::Blocks(absolute::Height::from_consensus(1).unwrap()),
-            input: vec![TxIn {
-                witness: Witness::new(),
-                previous_output: outpoint,
-                script_sig: ScriptBuf::new(),
-                sequence: Sequence(0),
-            }],
-            output: vec![TxOut {
-                script_pubkey: ScriptBuf::new(),
-                value: Amount::from_sat(3000),
-            }],
-        };
+        ms.shutdown();
+    }
 
-        let mut txs_map = HashMap::new();
-        txs_map.insert(
-            tx1.compute_txid(),
-            (
-                tx1.clone(),
-                Some(Block {
-                    hash: bitcoin::BlockHash::from_str(
-                        "0000000000000000000326b8fca8d3f820647c97ea33ef722096b3c7b2c8ee94",
-                    )
-                    .unwrap(),
-                    time: 1,
-                    height: 1,
-                }),
-            ),
-        );
-        txs_map.insert(
-            tx2.compute_txid(),
-            (
-                tx2.clone(),
-                Some(Block {
-                    hash: bitcoin::BlockHash::from_str(
-                        "0000000000000000000326b8fca8d3f820647c97ea33ef722096b3c7b2c8ee94",
-                    )
-                    .unwrap(),
-                    time: 2,
-                    height: 2,
-                }),
-            ),
+    #[test]
+    fn get_balance() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = &ms.control();
+        let mut db_conn = control.db.connection();
+        let coin = |vout, amount, block_height: Option<i32>| Coin {
+            outpoint: OutPoint::new(Txid::all_zeros(), vout),
+            is_immature: false,
+            block_info: block_height.map(|height| BlockInfo {
+                height,
+                time: 1_700_000_000,
+            }),
+            amount: Amount::from_sat(amount),
+            derivation_index: ChildNumber::from(vout),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        };
+        assert_eq!(
+            control.get_balance(),
+            GetBalanceResult {
+                confirmed: Amount::ZERO,
+                unconfirmed_from_self: Amount::ZERO,
+                unconfirmed: Amount::ZERO,
+                immature: Amount::ZERO,
+                spending: Amount::ZERO,
+            }
         );
-        txs_map.insert(
-            tx3.compute_txid(),
-            (
-                tx3.clone(),
-                Some(Block {
-                    hash: bitcoin::BlockHash::from_str(
-                        "0000000000000000000326b8fca8d3f820647c97ea33ef722096b3c7b2c8ee94",
-                    )
-                    .unwrap(),
-                    time: 4,
-                    height: 4,
-                }),
-            ),
+
+        db_conn.new_unspent_coins(&[
+            coin(0, 10_000, Some(1)),
+            coin(1, 20_000, Some(2)),
+            coin(2, 30_000, None),
+        ]);
+        let spend_txid = Txid::from_slice(&[1; 32]).unwrap();
+        db_conn.spend_coins(&[(OutPoint::new(Txid::all_zeros(), 1), spend_txid)]);
+        assert_eq!(
+            control.get_balance(),
+            GetBalanceResult {
+                confirmed: Amount::from_sat(10_000),
+                unconfirmed_from_self: Amount::ZERO,
+                unconfirmed: Amount::from_sat(30_000),
+                immature: Amount::ZERO,
+                spending: Amount::from_sat(20_000),
+            }
         );
 
+        ms.shutdown();
+    }
+
+    #[test]
+    fn health_check() {
         let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
         let control = &ms.control();
-        let mut db_conn = control.db.connection();
-        let txs: Vec<_> = txs_map.values().map(|(tx, _)| tx.clone()).collect();
-        db_conn.new_txs(&txs);
-        // We need coins in the DB in order to get the block info for the transactions.
-        for (txid, (_tx, block)) in txs_map {
-            // Insert more than one coin per transaction to check that the command does not
-            // return duplicate transactions.
-            for vout in 0..4 {
-                db_conn.new_unspent_coins(&[Coin {
-                    outpoint: bitcoin::OutPoint::new(txid, vout),
-                    is_immature: false,
-                    block_info: block.map(|b| BlockInfo {
-                        height: b.height,
-                        time: b.time,
-                    }),
-                    amount: bitcoin::Amount::from_sat(100_000),
-                    derivation_index: bip32::ChildNumber::from(13),
-                    is_change: false,
-                    spend_txid: None,
-                    spend_block: None,
-                    is_from_self: false,
-                }]);
-            }
+        let status = |res: &HealthCheckResult, name: &str| {
+            res.checks
+                .iter()
+                .find(|c| c.name == name)
+                .map(|c| c.status)
+                .unwrap()
+        };
+        let res = control.health_check();
+        for name in ["drafts", "database", "coins", "derivation_indexes"] {
+            assert_eq!(status(&res, name), HealthStatus::Ok);
         }
 
-        let transactions = control
-            .list_transactions(&[tx1.compute_txid()])
-            .transactions;
-        assert_eq!(transactions.len(), 1);
-        assert_eq!(transactions[0].tx, tx1);
-
-        let transactions = control
-            .list_transactions(&[tx1.compute_txid(), tx2.compute_txid(), tx3.compute_txid()])
-            .transactions;
-        assert_eq!(transactions.len(), 3);
+        // A coin whose transaction is unknown can't be checked against the descriptor.
+        control.db.connection().new_unspent_coins(&[Coin {
+            outpoint: OutPoint::new(Txid::all_zeros(), 0),
+            is_immature: false,
+            block_info: None,
+            amount: Amount::from_sat(10_000),
+            derivation_index: ChildNumber::from(0),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        }]);
+        let res = control.health_check();
+        assert_eq!(status(&res, "coins"), HealthStatus::Error);
+        assert_eq!(res.status, HealthStatus::Error);
 
-        let txs: Vec<Transaction> = transactions
-            .iter()
-            .map(|transaction| transaction.tx.clone())
-            .collect();
+        ms.shutdown();
+    }
 
-        assert!(txs.contains(&tx1));
-        assert!(txs.contains(&tx2));
-        assert!(txs.contains(&tx3));
+    #[test]
+    fn reads_while_writing() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = ms.control();
+
+        // A command only reading from the wallet doesn't wait for those modifying it.
+        let writes = control.lock_writes();
+        let reader = std::thread::spawn({
+            let control = control.clone();
+            move || control.get_balance()
+        });
+        assert_eq!(reader.join().unwrap().confirmed, Amount::ZERO);
+        drop(writes);
 
         ms.shutdown();
     }
@@ -20,6 +20,27 @@ pub fn ser_to_string<T: std::fmt::Display, S: Serializer>(
     s.serialize_str(&field.to_string())
 }
 
+pub fn deser_opt_fromstr<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    <T as FromStr>::Err: std::fmt::Display,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|string| T::from_str(&string).map_err(de::Error::custom))
+        .transpose()
+}
+
+pub fn ser_opt_to_string<T: std::fmt::Display, S: Serializer>(
+    field: &Option<T>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    match field {
+        Some(field) => s.serialize_str(&field.to_string()),
+        None => s.serialize_none(),
+    }
+}
+
 /// Deserialize an address from string, assuming the network was checked.
 pub fn deser_addr_assume_checked<'de, D>(deserializer: D) -> Result<bitcoin::Address, D::Error>
 where
@@ -2,7 +2,7 @@ use liana::descriptors::LianaDescriptor;
 
 use std::{fmt, net::SocketAddr, path::PathBuf, str::FromStr, time::Duration};
 
-use miniscript::bitcoin::Network;
+use miniscript::bitcoin::{self, consensus::encode, Network};
 
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
@@ -72,6 +72,19 @@ fn serialize_userpass<S: Serializer>(
     s.serialize_str(&format!("{}:{}", user, password))
 }
 
+fn deserialize_tx<'de, D>(deserializer: D) -> Result<bitcoin::Transaction, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let hex = String::deserialize(deserializer)?;
+    encode::deserialize_hex(&hex)
+        .map_err(|e| de::Error::custom(format!("Invalid transaction: {}", e)))
+}
+
+fn serialize_tx<S: Serializer>(tx: &bitcoin::Transaction, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&encode::serialize_hex(tx))
+}
+
 fn default_loglevel() -> log::LevelFilter {
     log::LevelFilter::Info
 }
@@ -80,6 +93,19 @@ fn default_poll_interval() -> Duration {
     Duration::from_secs(30)
 }
 
+// Below the default grace period of container runtimes before they kill the process.
+fn default_shutdown_timeout() -> Duration {
+    Duration::from_secs(8)
+}
+
+fn is_default_shutdown_timeout(timeout: &Duration) -> bool {
+    *timeout == default_shutdown_timeout()
+}
+
+fn default_cross_check_tolerance() -> u32 {
+    2
+}
+
 /// Bitcoin backend config.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum BitcoinBackend {
@@ -143,11 +169,98 @@ pub struct BitcoinConfig {
     pub poll_interval_secs: Duration,
 }
 
+/// The condition upon which the watchtower broadcasts a pre-signed transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchtowerTrigger {
+    /// The timelocks of the transaction matured, that is it can be included in the next block.
+    /// Not if one of the coins it spends is being spent by another transaction.
+    TimelockMaturity,
+    /// One of the coins it spends is being spent by another, unconfirmed, transaction.
+    UnauthorizedSpend,
+}
+
+impl fmt::Display for WatchtowerTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TimelockMaturity => write!(f, "timelock maturity"),
+            Self::UnauthorizedSpend => write!(f, "unauthorized spend"),
+        }
+    }
+}
+
+/// A pre-signed transaction held by the watchtower.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WatchtowerTransaction {
+    /// The hex-encoded, fully signed, transaction.
+    #[serde(deserialize_with = "deserialize_tx", serialize_with = "serialize_tx")]
+    pub tx: bitcoin::Transaction,
+    /// Broadcast the transaction as soon as any of these conditions is met.
+    pub triggers: Vec<WatchtowerTrigger>,
+}
+
+/// Settings for running as a watchtower. In this mode we broadcast pre-signed transactions
+/// spending our coins when some conditions are met.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WatchtowerConfig {
+    pub transactions: Vec<WatchtowerTransaction>,
+}
+
+/// Settings for comparing what our Bitcoin backend reports to what a second, independent, backend
+/// reports. For instance our own bitcoind and an Electrum server reached over Tor.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CrossCheckConfig {
+    /// The second Bitcoin backend.
+    #[serde(flatten)]
+    pub backend: BitcoinBackend,
+    /// By how many blocks the two backends may disagree about the tip or the confirmation height
+    /// of a coin before we raise an alert.
+    #[serde(default = "default_cross_check_tolerance")]
+    pub max_height_difference: u32,
+}
+
+/// Minimum length of the token protecting the dashboard, not to be easily guessed.
+pub const DASHBOARD_TOKEN_MIN_LEN: usize = 16;
+
+/// Settings for serving a read-only status page of the wallet, for instance to the board or the
+/// auditors of an organization keeping its treasury in it.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DashboardConfig {
+    /// The IP:port to listen on. Only plain HTTP is served, use a reverse proxy for TLS.
+    pub addr: SocketAddr,
+    /// The token to give to access the page, as a `token` query parameter or as a bearer token
+    /// in the `Authorization` header.
+    pub token: String,
+}
+
+/// Minimum length of the token protecting the signer server. It gives access to signatures, so
+/// it must be harder to guess than the dashboard's.
+pub const SIGNER_SERVER_TOKEN_MIN_LEN: usize = 32;
+
+/// Settings for signing with the hot signer of this daemon on behalf of the GUI of another machine,
+/// so the always-online key of a wallet can be kept on a hardened server.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SignerServerConfig {
+    /// The IP:port to listen on. Only plain HTTP is served, the GUI must connect through an HTTPS
+    /// reverse proxy or an SSH tunnel.
+    pub addr: SocketAddr,
+    /// The token the GUI must give as a bearer token in the `Authorization` header.
+    pub token: String,
+    /// The master fingerprint of the hot signer to sign with, if several are stored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<bitcoin::bip32::Fingerprint>,
+}
+
 /// Static informations we require to operate
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Config {
     /// An optional custom data directory
     pub data_dir: Option<PathBuf>,
+    /// Keep the database in memory and the other files in a temporary directory wiped on
+    /// shutdown, instead of using the data directory. Nothing is kept about the wallet once
+    /// stopped.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ephemeral: bool,
     /// What messages to log
     #[serde(
         deserialize_with = "deserialize_fromstr",
@@ -161,11 +274,43 @@ pub struct Config {
         serialize_with = "serialize_to_string"
     )]
     pub main_descriptor: LianaDescriptor,
+    /// An optional URL to which to post events, such as the spend of coins by a transaction we
+    /// didn't create. Only plain HTTP is supported.
+    pub webhook_url: Option<String>,
+    /// An optional snapshot of the coins of the wallet, as exported by another instance, to
+    /// populate the database from when creating a new data directory instead of rescanning.
+    pub coins_snapshot: Option<PathBuf>,
+    /// How long to wait for the commands being processed, the current poll and the other
+    /// operations in progress to complete when shutting down, before exiting anyway.
+    #[serde(
+        deserialize_with = "deserialize_duration",
+        serialize_with = "serialize_duration",
+        default = "default_shutdown_timeout",
+        skip_serializing_if = "is_default_shutdown_timeout"
+    )]
+    pub shutdown_timeout_secs: Duration,
+    /// Spends sending more than this value out of the wallet, in satoshis, are only broadcast or
+    /// scheduled if the client confirms the value they send.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spend_confirmation_threshold: Option<u64>,
+    /// The commands, polls and database queries taking at least this long, in milliseconds, are
+    /// logged as slow. Defaults to a second.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slow_operation_threshold_ms: Option<u64>,
     /// Settings for the Bitcoin interface
     pub bitcoin_config: BitcoinConfig,
     /// Settings specific to the Bitcoin backend.
     #[serde(flatten)]
     pub bitcoin_backend: Option<BitcoinBackend>,
+    /// Settings for running as a watchtower.
+    pub watchtower: Option<WatchtowerConfig>,
+    /// Settings for cross-checking the Bitcoin backend against a second one.
+    pub cross_check: Option<CrossCheckConfig>,
+    /// Settings for serving a read-only status page of the wallet.
+    pub dashboard: Option<DashboardConfig>,
+    /// Settings for signing with the hot signer on behalf of the GUI of another machine.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer_server: Option<SignerServerConfig>,
 }
 
 impl Config {
@@ -180,7 +325,8 @@ pub enum ConfigError {
     FileNotFound,
     ReadingFile(String),
     UnexpectedDescriptor(Box<LianaDescriptor>),
-    Unexpected(String),
+    /// All the problems found with the configuration.
+    Invalid(Vec<String>),
 }
 
 impl std::fmt::Display for ConfigError {
@@ -194,7 +340,16 @@ impl std::fmt::Display for ConfigError {
                 "Unexpected descriptor '{}'. We only support wsh() descriptors for now.",
                 desc
             ),
-            Self::Unexpected(e) => write!(f, "Configuration error: {}", e),
+            Self::Invalid(problems) => match problems.as_slice() {
+                [problem] => write!(f, "Invalid configuration: {}", problem),
+                _ => {
+                    write!(f, "Invalid configuration:")?;
+                    for problem in problems {
+                        write!(f, "\n  - {}", problem)?;
+                    }
+                    Ok(())
+                }
+            },
         }
     }
 }
@@ -248,14 +403,51 @@ fn config_file_path() -> Option<PathBuf> {
 impl Config {
     /// Get our static configuration out of a mandatory configuration file.
     ///
-    /// We require all settings to be set in the configuration file, and only in the configuration
-    /// file. We don't allow to set them via the command line or environment variables to avoid a
-    /// futile duplication.
+    /// All the settings are set in the configuration file, but any of them may be overridden by an
+    /// environment variable (see [`ENV_PREFIX`]), which is handy for containerized deployments.
+    /// All the problems with the configuration are reported at once.
+    ///
+    /// If no custom path is given and some settings are set in the environment, the default
+    /// configuration file is optional: the whole configuration may be set in the environment.
     pub fn from_file(custom_path: Option<PathBuf>) -> Result<Config, ConfigError> {
-        let config_file =
-            custom_path.unwrap_or(config_file_path().ok_or(ConfigError::DatadirNotFound)?);
+        let env_vars: Vec<(String, String)> = std::env::vars()
+            .filter(|(var, _)| var.starts_with(ENV_PREFIX))
+            .collect();
+        let from_env_only = custom_path.is_none() && !env_vars.is_empty();
+
+        let content = match custom_path.or_else(config_file_path) {
+            Some(config_file) => match std::fs::read(config_file) {
+                Ok(content) => Some(content),
+                Err(e) if from_env_only && e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e.into()),
+            },
+            None if from_env_only => None,
+            None => return Err(ConfigError::DatadirNotFound),
+        };
+        let mut value = match content {
+            Some(content) => toml::from_slice::<toml::Value>(&content).map_err(|e| {
+                ConfigError::ReadingFile(format!("Parsing configuration file: {}", e))
+            })?,
+            None => toml::Value::Table(Default::default()),
+        };
+        Self::from_value(&mut value, env_vars.into_iter())
+    }
+
+    // Apply the overrides from the environment to the content of the configuration file, check
+    // it against the expected keys and types and only then deserialize it.
+    fn from_value(
+        value: &mut toml::Value,
+        env_vars: impl Iterator<Item = (String, String)>,
+    ) -> Result<Config, ConfigError> {
+        let mut problems = apply_env_overrides(value, env_vars);
+        problems.extend(validate(value));
+        if !problems.is_empty() {
+            return Err(ConfigError::Invalid(problems));
+        }
 
-        let config = toml::from_slice::<Config>(&std::fs::read(config_file)?)
+        let config = value
+            .clone()
+            .try_into::<Config>()
             .map_err(|e| ConfigError::ReadingFile(format!("Parsing configuration file: {}", e)))?;
         config.check()?;
 
@@ -264,29 +456,417 @@ impl Config {
 
     /// Make sure the settings are sane.
     pub fn check(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
         // Check the network of the xpubs in the descriptors
         let expected_network = match self.bitcoin_config.network {
             Network::Bitcoin => Network::Bitcoin,
             _ => Network::Testnet,
         };
         if !self.main_descriptor.all_xpubs_net_is(expected_network) {
-            return Err(ConfigError::Unexpected(format!(
+            problems.push(format!(
                 "Our bitcoin network is {} but one xpub is not for network {}",
                 self.bitcoin_config.network, expected_network
-            )));
+            ));
+        }
+
+        if let Some(url) = &self.webhook_url {
+            if !url.starts_with("http://") {
+                problems.push(format!(
+                    "Invalid webhook URL '{}': only plain HTTP is supported",
+                    url
+                ));
+            }
+        }
+
+        if let Some(watchtower) = &self.watchtower {
+            for wt_tx in &watchtower.transactions {
+                if wt_tx.triggers.is_empty() {
+                    problems.push(format!(
+                        "No trigger for watchtower transaction '{}'",
+                        wt_tx.tx.compute_txid()
+                    ));
+                }
+            }
+        }
+
+        if let Some(cross_check) = &self.cross_check {
+            let same_backend = match (&cross_check.backend, &self.bitcoin_backend) {
+                (BitcoinBackend::Bitcoind(a), Some(BitcoinBackend::Bitcoind(b))) => {
+                    a.addr == b.addr
+                }
+                (BitcoinBackend::Electrum(a), Some(BitcoinBackend::Electrum(b))) => {
+                    a.addr == b.addr
+                }
+                _ => false,
+            };
+            if same_backend {
+                problems.push(
+                    "The cross-checking backend must be different from the main one".to_string(),
+                );
+            }
+        }
+
+        if let Some(dashboard) = &self.dashboard {
+            if dashboard.token.len() < DASHBOARD_TOKEN_MIN_LEN {
+                problems.push(format!(
+                    "The dashboard token must be at least {} characters long",
+                    DASHBOARD_TOKEN_MIN_LEN
+                ));
+            }
+        }
+
+        if let Some(signer_server) = &self.signer_server {
+            if signer_server.token.len() < SIGNER_SERVER_TOKEN_MIN_LEN {
+                problems.push(format!(
+                    "The signer server token must be at least {} characters long",
+                    SIGNER_SERVER_TOKEN_MIN_LEN
+                ));
+            }
+        }
+
+        if self.ephemeral {
+            if self.data_dir.is_some() {
+                problems.push(
+                    "An ephemeral wallet doesn't use a data directory: 'data_dir' can't be set"
+                        .to_string(),
+                );
+            }
+            if self.signer_server.is_some() {
+                problems.push(
+                    "An ephemeral wallet can't store a hot signer: 'signer_server' can't be set"
+                        .to_string(),
+                );
+            }
         }
 
         // TODO: check the semantics of the main descriptor
 
-        Ok(())
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+}
+
+/// The prefix of the environment variables overriding a setting of the configuration file. The
+/// rest of the variable name is the path to the setting, with sections separated by a double
+/// underscore. For instance `LIANAD__BITCOIN_CONFIG__POLL_INTERVAL_SECS=60` or
+/// `LIANAD__BITCOIND_CONFIG__ADDR=127.0.0.1:8332`.
+pub const ENV_PREFIX: &str = "LIANAD__";
+
+// Set the values of the settings overridden by the environment, creating the sections as needed.
+// Values are interpreted as TOML values if possible (for instance integers), as strings otherwise.
+// An empty value removes the setting.
+fn apply_env_overrides(
+    value: &mut toml::Value,
+    env_vars: impl Iterator<Item = (String, String)>,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for (var, var_value) in env_vars {
+        let path = match var.strip_prefix(ENV_PREFIX) {
+            Some(path) if !path.is_empty() => path.to_lowercase(),
+            _ => continue,
+        };
+        let keys: Vec<&str> = path.split("__").collect();
+        let (last_key, sections) = keys.split_last().expect("Never empty");
+
+        // Don't create the sections of a setting which is removed.
+        let remove = var_value.is_empty();
+        let mut table = value.as_table_mut();
+        for section in sections {
+            table = table.and_then(|t| {
+                let setting = if remove {
+                    t.get_mut(*section)
+                } else {
+                    Some(
+                        t.entry(section.to_string())
+                            .or_insert_with(|| toml::Value::Table(Default::default())),
+                    )
+                };
+                setting.and_then(|v| v.as_table_mut())
+            });
+        }
+        let table = match table {
+            Some(table) => table,
+            None if remove => continue,
+            None => {
+                problems.push(format!(
+                    "Environment variable '{}' sets a value within a setting which isn't a section",
+                    var
+                ));
+                continue;
+            }
+        };
+
+        if remove {
+            table.remove(*last_key);
+            continue;
+        }
+        let new_value = toml::from_str::<toml::Value>(&format!("v = {}", var_value))
+            .ok()
+            .and_then(|v| v.get("v").cloned())
+            .unwrap_or(toml::Value::String(var_value));
+        table.insert(last_key.to_string(), new_value);
+    }
+
+    problems
+}
+
+/// The type of value expected for a setting of the configuration file.
+enum Kind {
+    String,
+    Integer,
+    Boolean,
+    Section(&'static [Setting]),
+    List(&'static Kind),
+}
+
+impl Kind {
+    fn matches(&self, value: &toml::Value) -> bool {
+        match self {
+            Kind::String => value.is_str(),
+            Kind::Integer => value.as_integer().map(|i| i >= 0).unwrap_or(false),
+            Kind::Boolean => value.is_bool(),
+            Kind::Section(_) => value.is_table(),
+            Kind::List(_) => value.is_array(),
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Kind::String => "a string",
+            Kind::Integer => "a positive integer",
+            Kind::Boolean => "a boolean",
+            Kind::Section(_) => "a section",
+            Kind::List(_) => "a list",
+        }
+    }
+}
+
+/// A setting of the configuration file.
+struct Setting {
+    name: &'static str,
+    kind: Kind,
+    required: bool,
+}
+
+const fn setting(name: &'static str, kind: Kind, required: bool) -> Setting {
+    Setting {
+        name,
+        kind,
+        required,
+    }
+}
+
+const BITCOIND_SETTINGS: &[Setting] = &[
+    setting("cookie_path", Kind::String, false),
+    setting("auth", Kind::String, false),
+    setting("addr", Kind::String, true),
+];
+
+const ELECTRUM_SETTINGS: &[Setting] = &[setting("addr", Kind::String, true)];
+
+const BITCOIN_CONFIG_SETTINGS: &[Setting] = &[
+    setting("network", Kind::String, true),
+    setting("poll_interval_secs", Kind::Integer, false),
+];
+
+const WATCHTOWER_TX_SETTINGS: &[Setting] = &[
+    setting("tx", Kind::String, true),
+    setting("triggers", Kind::List(&Kind::String), true),
+];
+
+const WATCHTOWER_TX: Kind = Kind::Section(WATCHTOWER_TX_SETTINGS);
+
+const WATCHTOWER_SETTINGS: &[Setting] =
+    &[setting("transactions", Kind::List(&WATCHTOWER_TX), true)];
+
+const CROSS_CHECK_SETTINGS: &[Setting] = &[
+    setting("bitcoind_config", Kind::Section(BITCOIND_SETTINGS), false),
+    setting("electrum_config", Kind::Section(ELECTRUM_SETTINGS), false),
+    setting("max_height_difference", Kind::Integer, false),
+];
+
+const DASHBOARD_SETTINGS: &[Setting] = &[
+    setting("addr", Kind::String, true),
+    setting("token", Kind::String, true),
+];
+
+const SIGNER_SERVER_SETTINGS: &[Setting] = &[
+    setting("addr", Kind::String, true),
+    setting("token", Kind::String, true),
+    setting("fingerprint", Kind::String, false),
+];
+
+const CONFIG_SETTINGS: &[Setting] = &[
+    setting("data_dir", Kind::String, false),
+    setting("ephemeral", Kind::Boolean, false),
+    setting("log_level", Kind::String, false),
+    setting("main_descriptor", Kind::String, true),
+    setting("webhook_url", Kind::String, false),
+    setting("coins_snapshot", Kind::String, false),
+    setting("shutdown_timeout_secs", Kind::Integer, false),
+    setting("spend_confirmation_threshold", Kind::Integer, false),
+    setting("slow_operation_threshold_ms", Kind::Integer, false),
+    setting(
+        "bitcoin_config",
+        Kind::Section(BITCOIN_CONFIG_SETTINGS),
+        true,
+    ),
+    setting("bitcoind_config", Kind::Section(BITCOIND_SETTINGS), false),
+    setting("electrum_config", Kind::Section(ELECTRUM_SETTINGS), false),
+    setting("watchtower", Kind::Section(WATCHTOWER_SETTINGS), false),
+    setting("cross_check", Kind::Section(CROSS_CHECK_SETTINGS), false),
+    setting("dashboard", Kind::Section(DASHBOARD_SETTINGS), false),
+    setting(
+        "signer_server",
+        Kind::Section(SIGNER_SERVER_SETTINGS),
+        false,
+    ),
+];
+
+// The number of single-character edits to go from one string to the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev_row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev_row[j] + usize::from(ca != *cb);
+            row.push(substitution.min(prev_row[j + 1] + 1).min(row[j] + 1));
+        }
+        prev_row = row;
+    }
+    prev_row[b.len()]
+}
+
+// Join the path to a setting for display.
+fn setting_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+// Check the keys and types of the settings of this section, recursively.
+fn validate_section(
+    table: &toml::value::Table,
+    settings: &[Setting],
+    path: &str,
+    problems: &mut Vec<String>,
+) {
+    for (key, value) in table {
+        let key_path = setting_path(path, key);
+        let setting = match settings.iter().find(|s| s.name == key.as_str()) {
+            Some(setting) => setting,
+            None => {
+                let suggestion = settings
+                    .iter()
+                    .map(|s| (edit_distance(key, s.name), s.name))
+                    .filter(|(dist, name)| *dist <= 2.max(name.len() / 3))
+                    .min();
+                problems.push(match suggestion {
+                    Some((_, name)) => format!(
+                        "Unknown setting '{}'. Did you mean '{}'?",
+                        key_path,
+                        setting_path(path, name)
+                    ),
+                    None => format!("Unknown setting '{}'", key_path),
+                });
+                continue;
+            }
+        };
+        validate_value(value, &setting.kind, &key_path, problems);
+    }
+
+    for setting in settings.iter().filter(|s| s.required) {
+        if !table.contains_key(setting.name) {
+            problems.push(format!(
+                "Missing setting '{}'",
+                setting_path(path, setting.name)
+            ));
+        }
+    }
+
+    // There can only be a single Bitcoin backend per section.
+    let has_bitcoind = table.contains_key("bitcoind_config");
+    let has_electrum = table.contains_key("electrum_config");
+    if has_bitcoind && has_electrum {
+        problems.push(format!(
+            "Conflicting sections '{}' and '{}': only one Bitcoin backend can be set",
+            setting_path(path, "bitcoind_config"),
+            setting_path(path, "electrum_config")
+        ));
+    } else if path == "cross_check" && !has_bitcoind && !has_electrum {
+        problems.push(
+            "Missing section 'cross_check.bitcoind_config' or 'cross_check.electrum_config'"
+                .to_string(),
+        );
+    }
+    if path.ends_with("bitcoind_config") {
+        match (
+            table.contains_key("cookie_path"),
+            table.contains_key("auth"),
+        ) {
+            (true, true) => problems.push(format!(
+                "Conflicting settings '{}' and '{}': only one authentication method can be set",
+                setting_path(path, "cookie_path"),
+                setting_path(path, "auth")
+            )),
+            (false, false) => problems.push(format!(
+                "Missing setting '{}' or '{}'",
+                setting_path(path, "cookie_path"),
+                setting_path(path, "auth")
+            )),
+            _ => {}
+        }
+    }
+}
+
+// Check the type of this value and, for sections and lists, of its content.
+fn validate_value(value: &toml::Value, kind: &Kind, path: &str, problems: &mut Vec<String>) {
+    if !kind.matches(value) {
+        problems.push(format!(
+            "Invalid value for '{}': expected {}, found {}",
+            path,
+            kind.description(),
+            value.type_str()
+        ));
+        return;
+    }
+    match (kind, value) {
+        (Kind::Section(settings), toml::Value::Table(table)) => {
+            validate_section(table, settings, path, problems)
+        }
+        (Kind::List(kind), toml::Value::Array(values)) => {
+            for (i, value) in values.iter().enumerate() {
+                validate_value(value, kind, &format!("{}[{}]", path, i), problems);
+            }
+        }
+        _ => {}
     }
 }
 
+// Check the content of a configuration file against the expected settings, reporting all the
+// problems found.
+fn validate(value: &toml::Value) -> Vec<String> {
+    let mut problems = Vec::new();
+    validate_value(value, &Kind::Section(CONFIG_SETTINGS), "", &mut problems);
+    problems
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
 
-    use super::{config_file_path, BitcoindConfig, BitcoindRpcAuth, Config};
+    use super::{
+        config_file_path, BitcoinBackend, BitcoindConfig, BitcoindRpcAuth, Config, ConfigError,
+        CrossCheckConfig, WatchtowerConfig, WatchtowerTrigger,
+    };
 
     // Test the format of the configuration file
     #[test]
@@ -479,6 +1059,265 @@ mod tests {
             .contains("`auth` must be 'user:password'"));
     }
 
+    #[test]
+    fn toml_watchtower_config() {
+        let toml_str = r#"
+            [[transactions]]
+            tx = '02000000000101ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb000000000064000000013075000000000000160014148de9c5a7a44d19e56cd9ae1a554bf67847afb00247043a718774c572bd8a25adbeb1bfcd5c0256ae11cecf9f9c3f925d0e52beaf89043a718774c572bd8a25adbeb1bfcd5c0256ae11cecf9f9c3f925d0e52beaf89043a718774c57221028254c329a92850f6d539dd376f4816ee2764517da5e0235514af433164480d7a00000000'
+            triggers = ['timelock_maturity', 'unauthorized_spend']
+            "#
+        .trim_start()
+        .replace("            ", "");
+        let parsed = toml::from_str::<WatchtowerConfig>(&toml_str).expect("Deserializing toml_str");
+        assert_eq!(
+            parsed.transactions[0].triggers,
+            vec![
+                WatchtowerTrigger::TimelockMaturity,
+                WatchtowerTrigger::UnauthorizedSpend
+            ]
+        );
+        let serialized = toml::to_string_pretty(&parsed).expect("Serializing to toml");
+        let reparsed = toml::from_str::<WatchtowerConfig>(&serialized).expect("Deserializing");
+        assert_eq!(reparsed.transactions[0].tx, parsed.transactions[0].tx);
+
+        // The transaction must be valid.
+        let toml_str = r#"
+            [[transactions]]
+            tx = '0200'
+            triggers = ['timelock_maturity']
+            "#
+        .trim_start()
+        .replace("            ", "");
+        let config_err = toml::from_str::<WatchtowerConfig>(&toml_str)
+            .expect_err("Deserializing an invalid toml_str");
+        assert!(config_err.to_string().contains("Invalid transaction"));
+    }
+
+    #[test]
+    fn toml_cross_check_config() {
+        let toml_str = r#"
+            [electrum_config]
+            addr = "ssl://electrum.example.com:50002"
+            "#
+        .trim_start()
+        .replace("            ", "");
+        let parsed = toml::from_str::<CrossCheckConfig>(&toml_str).expect("Deserializing toml_str");
+        match parsed.backend {
+            BitcoinBackend::Electrum(ref config) => {
+                assert_eq!(config.addr, "ssl://electrum.example.com:50002")
+            }
+            _ => panic!("Expected an Electrum backend"),
+        }
+        assert_eq!(parsed.max_height_difference, 2);
+        let serialized = toml::to_string_pretty(&parsed).expect("Serializing to toml");
+        let reparsed = toml::from_str::<CrossCheckConfig>(&serialized).expect("Deserializing");
+        assert_eq!(reparsed.max_height_difference, 2);
+
+        let toml_str = r#"
+            max_height_difference = 6
+
+            [bitcoind_config]
+            addr = "127.0.0.1:8332"
+            cookie_path = "/home/user/.bitcoin/.cookie"
+            "#
+        .trim_start()
+        .replace("            ", "");
+        let parsed = toml::from_str::<CrossCheckConfig>(&toml_str).expect("Deserializing toml_str");
+        assert!(matches!(parsed.backend, BitcoinBackend::Bitcoind(_)));
+        assert_eq!(parsed.max_height_difference, 6);
+
+        // A backend must be given.
+        toml::from_str::<CrossCheckConfig>("max_height_difference = 6")
+            .expect_err("Deserializing a cross-check config without backend");
+    }
+
+    fn parse_with_env(toml_str: &str, env: &[(&str, &str)]) -> Result<Config, ConfigError> {
+        let mut value = toml::from_str::<toml::Value>(toml_str).expect("Valid TOML");
+        Config::from_value(
+            &mut value,
+            env.iter().map(|(k, v)| (k.to_string(), v.to_string())),
+        )
+    }
+
+    #[test]
+    fn config_validation() {
+        let desc = "wsh(andor(pk([aabbccdd]tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/<0;1>/*),older(10000),pk([aabbccdd]tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/<0;1>/*)))#dw4ulnrs";
+
+        // A valid config.
+        let toml_str = format!(
+            r#"
+            main_descriptor = "{}"
+
+            [bitcoin_config]
+            network = "testnet"
+
+            [bitcoind_config]
+            cookie_path = "/home/user/.bitcoin/.cookie"
+            addr = "127.0.0.1:18332"
+            "#,
+            desc
+        );
+        parse_with_env(&toml_str, &[]).expect("Valid config");
+
+        // All the problems are reported at once.
+        let toml_str = format!(
+            r#"
+            main_descriptor = "{}"
+            log_levl = "debug"
+
+            [bitcoin_config]
+            poll_interval_secs = "30"
+
+            [bitcoind_config]
+            cookie_path = "/home/user/.bitcoin/.cookie"
+            auth = "user:pass"
+            addr = "127.0.0.1:18332"
+
+            [electrum_config]
+            addr = "127.0.0.1:50001"
+
+            [cross_check]
+            max_height_diference = 3
+            "#,
+            desc
+        );
+        match parse_with_env(&toml_str, &[]) {
+            Err(ConfigError::Invalid(mut problems)) => {
+                problems.sort();
+                let mut expected = vec![
+                    "Invalid value for 'bitcoin_config.poll_interval_secs': expected a positive integer, found string",
+                    "Missing setting 'bitcoin_config.network'",
+                    "Conflicting settings 'bitcoind_config.cookie_path' and 'bitcoind_config.auth': only one authentication method can be set",
+                    "Unknown setting 'cross_check.max_height_diference'. Did you mean 'cross_check.max_height_difference'?",
+                    "Missing section 'cross_check.bitcoind_config' or 'cross_check.electrum_config'",
+                    "Unknown setting 'log_levl'. Did you mean 'log_level'?",
+                    "Conflicting sections 'bitcoind_config' and 'electrum_config': only one Bitcoin backend can be set",
+                ];
+                expected.sort();
+                assert_eq!(problems, expected);
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        // The semantic checks report all their problems too.
+        let toml_str = format!(
+            r#"
+            main_descriptor = "{}"
+            webhook_url = "https://example.com"
+
+            [bitcoin_config]
+            network = "bitcoin"
+
+            [dashboard]
+            addr = "127.0.0.1:8080"
+            token = "secret"
+
+            [signer_server]
+            addr = "127.0.0.1:8081"
+            token = "0123456789abcdef"
+            "#,
+            desc
+        );
+        match parse_with_env(&toml_str, &[]) {
+            Err(ConfigError::Invalid(problems)) => assert_eq!(problems.len(), 4),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        // An ephemeral wallet doesn't have anywhere to persist data.
+        let toml_str = format!(
+            r#"
+            main_descriptor = "{}"
+            data_dir = "/home/user/.liana"
+            ephemeral = true
+
+            [bitcoin_config]
+            network = "testnet"
+            "#,
+            desc
+        );
+        match parse_with_env(&toml_str, &[]) {
+            Err(ConfigError::Invalid(problems)) => assert_eq!(
+                problems,
+                vec![
+                    "An ephemeral wallet doesn't use a data directory: 'data_dir' can't be set"
+                        .to_string()
+                ]
+            ),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let config = parse_with_env(&toml_str, &[("LIANAD__DATA_DIR", "")]).unwrap();
+        assert!(config.ephemeral);
+        match parse_with_env(&toml_str, &[("LIANAD__EPHEMERAL", "yes")]) {
+            Err(ConfigError::Invalid(problems)) => assert_eq!(
+                problems,
+                vec!["Invalid value for 'ephemeral': expected a boolean, found string".to_string()]
+            ),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn config_env_overrides() {
+        let desc = "wsh(andor(pk([aabbccdd]tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/<0;1>/*),older(10000),pk([aabbccdd]tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/<0;1>/*)))#dw4ulnrs";
+        let toml_str = format!(
+            r#"
+            main_descriptor = "{}"
+
+            [bitcoin_config]
+            network = "testnet"
+
+            [bitcoind_config]
+            cookie_path = "/home/user/.bitcoin/.cookie"
+            addr = "127.0.0.1:18332"
+            "#,
+            desc
+        );
+
+        let config = parse_with_env(
+            &toml_str,
+            &[
+                ("LIANAD__LOG_LEVEL", "debug"),
+                ("LIANAD__BITCOIN_CONFIG__POLL_INTERVAL_SECS", "60"),
+                ("LIANAD__BITCOIND_CONFIG__COOKIE_PATH", ""),
+                ("LIANAD__BITCOIND_CONFIG__AUTH", "user:pass"),
+                ("LIANAD__BITCOIND_CONFIG__ADDR", "10.0.0.2:18332"),
+                // Not ours.
+                ("LIANAD_PATH", "/usr/bin/lianad"),
+            ],
+        )
+        .expect("Valid overrides");
+        assert_eq!(config.log_level, log::LevelFilter::Debug);
+        assert_eq!(config.bitcoin_config.poll_interval_secs.as_secs(), 60);
+        match config.bitcoin_backend {
+            Some(BitcoinBackend::Bitcoind(ref bitcoind_config)) => {
+                assert_eq!(
+                    bitcoind_config.rpc_auth,
+                    BitcoindRpcAuth::UserPass("user".to_string(), "pass".to_string())
+                );
+                assert_eq!(bitcoind_config.addr.to_string(), "10.0.0.2:18332");
+            }
+            _ => panic!("Expected a bitcoind backend"),
+        }
+
+        // Overrides are validated like the rest of the configuration.
+        match parse_with_env(
+            &toml_str,
+            &[
+                ("LIANAD__BITCOIN_CONFIG__NETWRK", "signet"),
+                ("LIANAD__MAIN_DESCRIPTOR__NETWORK", "signet"),
+            ],
+        ) {
+            Err(ConfigError::Invalid(problems)) => assert_eq!(
+                problems,
+                vec![
+                    "Environment variable 'LIANAD__MAIN_DESCRIPTOR__NETWORK' sets a value within a setting which isn't a section",
+                    "Unknown setting 'bitcoin_config.netwrk'. Did you mean 'bitcoin_config.network'?",
+                ]
+            ),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
     #[test]
     fn config_directory() {
         let filepath = config_file_path().expect("Getting config file path");
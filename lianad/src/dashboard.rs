@@ -0,0 +1,470 @@
+//! A read-only status page of the wallet, for the board or the auditors of an organization keeping
+//! its treasury in it.
+//!
+//! The page shows the balance, the latest transactions and when the coins become spendable through
+//! the recovery path. The same data is available as JSON at `/status.json`, to be exported. The
+//! wallet is only read: nothing can be spent from there. Every request must give the token of the
+//! configuration, either as a `token` query parameter or as a bearer token.
+
+use crate::{
+    commands::{GetBalanceResult, GetRecoveryScheduleResult},
+    database::{DatabaseConnection, DatabaseInterface},
+    DaemonControl,
+};
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Read, Write},
+    net,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread, time,
+};
+
+use miniscript::bitcoin;
+use serde::Serialize;
+
+/// Number of transactions listed on the page.
+const RECENT_TRANSACTIONS: usize = 10;
+
+/// How often to check whether we need to stop when no request is coming.
+const ACCEPT_INTERVAL: time::Duration = time::Duration::from_millis(100);
+
+/// How long to wait for a client to send its request or read our response.
+const REQUEST_TIMEOUT: time::Duration = time::Duration::from_secs(5);
+
+/// We only serve GET requests, their head doesn't need to be larger.
+const MAX_REQUEST_SIZE: u64 = 8 * 1024;
+
+/// Each client is served on its own thread. Connections beyond this number are closed right away.
+const MAX_CLIENTS: usize = 16;
+
+/// A transaction of the wallet and its effect on the balance.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct StatusTransaction {
+    pub txid: bitcoin::Txid,
+    pub height: Option<i32>,
+    pub time: Option<u32>,
+    /// The value received minus the value spent by the wallet in this transaction, in satoshis.
+    pub amount: i64,
+}
+
+/// The data shown on the status page.
+#[derive(Debug, Clone, Serialize)]
+pub struct Status {
+    pub network: bitcoin::Network,
+    pub block_height: i32,
+    pub balance: GetBalanceResult,
+    /// The latest transactions, most recent first.
+    pub transactions: Vec<StatusTransaction>,
+    /// When the coins become spendable through the first recovery path. `None` until we are
+    /// synced.
+    pub recovery: Option<GetRecoveryScheduleResult>,
+}
+
+impl Status {
+    pub fn new(control: &DaemonControl) -> Status {
+        let info = control.get_info();
+
+        // The effect of a transaction on the balance is the value of the coins it created minus
+        // the value of those it spent. Only query the coins of the latest transactions.
+        let transactions = {
+            let mut db_conn = control.db.connection();
+            let recent = db_conn.recent_txids(RECENT_TRANSACTIONS as u64);
+            let txids: Vec<_> = recent.iter().map(|(txid, _)| *txid).collect();
+            let wallet_txs: HashMap<_, _> = db_conn
+                .list_wallet_transactions(&txids)
+                .into_iter()
+                .map(|(tx, _, time)| (tx.compute_txid(), (tx, time)))
+                .collect();
+            let outpoints: Vec<_> = wallet_txs
+                .iter()
+                .flat_map(|(txid, (tx, _))| {
+                    tx.input
+                        .iter()
+                        .map(|txin| txin.previous_output)
+                        .chain(
+                            (0..tx.output.len() as u32)
+                                .map(|vout| bitcoin::OutPoint::new(*txid, vout)),
+                        )
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+            let coins = db_conn.coins_by_outpoints(&outpoints);
+            recent
+                .into_iter()
+                .map(|(txid, height)| {
+                    let amount = coins
+                        .values()
+                        .map(|coin| {
+                            let value = coin.amount.to_sat() as i64;
+                            match (coin.outpoint.txid == txid, coin.spend_txid == Some(txid)) {
+                                (true, false) => value,
+                                (false, true) => -value,
+                                _ => 0,
+                            }
+                        })
+                        .sum();
+                    StatusTransaction {
+                        txid,
+                        height,
+                        time: wallet_txs.get(&txid).and_then(|(_, time)| *time),
+                        amount,
+                    }
+                })
+                .collect()
+        };
+
+        Status {
+            network: info.network,
+            block_height: info.block_height,
+            balance: control.get_balance(),
+            transactions,
+            recovery: control.get_recovery_schedule(None, None).ok(),
+        }
+    }
+
+    /// Render the status as a standalone HTML page.
+    pub fn to_html(&self) -> String {
+        let btc = |sats: i64| format!("{:.8} BTC", sats as f64 / 100_000_000.0);
+        let balance = &self.balance;
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\
+             <title>Liana wallet status</title><style>\
+             body{{font-family:sans-serif;max-width:900px;margin:auto;padding:1em}}\
+             table{{border-collapse:collapse;width:100%}}td,th{{padding:4px 8px;text-align:left;\
+             border-bottom:1px solid #ddd}}.amount{{text-align:right;font-family:monospace}}\
+             </style></head><body>\n<h1>Wallet status</h1>\n\
+             <p>Network {}, block height {}. This page is read-only.</p>\n\
+             <h2>Balance</h2>\n<table>\n",
+            self.network, self.block_height
+        );
+        for (name, amount) in [
+            ("Confirmed", balance.confirmed),
+            (
+                "Unconfirmed (from the wallet)",
+                balance.unconfirmed_from_self,
+            ),
+            ("Unconfirmed", balance.unconfirmed),
+            ("Immature", balance.immature),
+            ("Being spent", balance.spending),
+        ] {
+            html += &format!(
+                "<tr><td>{}</td><td class=\"amount\">{}</td></tr>\n",
+                name,
+                btc(amount.to_sat() as i64)
+            );
+        }
+
+        html += "</table>\n<h2>Latest transactions</h2>\n";
+        if self.transactions.is_empty() {
+            html += "<p>No transaction yet.</p>\n";
+        } else {
+            html += "<table>\n<tr><th>Transaction</th><th>Block</th><th>Amount</th></tr>\n";
+            for tx in &self.transactions {
+                html += &format!(
+                    "<tr><td>{}</td><td>{}</td><td class=\"amount\">{}</td></tr>\n",
+                    tx.txid,
+                    tx.height
+                        .map(|h| h.to_string())
+                        .unwrap_or_else(|| "Unconfirmed".to_string()),
+                    btc(tx.amount)
+                );
+            }
+            html += "</table>\n";
+        }
+
+        html += "<h2>Recovery</h2>\n";
+        match &self.recovery {
+            None => html += "<p>The wallet is not synced yet.</p>\n",
+            Some(recovery) => {
+                html += &format!(
+                    "<p>The recovery path becomes available {} blocks after a coin is confirmed. \
+                     {} can already be spent through it.</p>\n",
+                    recovery.timelock,
+                    btc(recovery.available.to_sat() as i64)
+                );
+                let buckets: Vec<_> = recovery.buckets.iter().filter(|b| b.coins > 0).collect();
+                if !buckets.is_empty() {
+                    html += "<table>\n<tr><th>Available from block</th><th>In about</th>\
+                             <th>Coins</th><th>Amount</th></tr>\n";
+                    for bucket in buckets {
+                        html += &format!(
+                            "<tr><td>{}</td><td>{} day(s)</td><td>{}</td>\
+                             <td class=\"amount\">{}</td></tr>\n",
+                            bucket.start_height,
+                            (bucket.start_height - recovery.tip_height) / 144,
+                            bucket.coins,
+                            btc(bucket.amount.to_sat() as i64)
+                        );
+                    }
+                    html += "</table>\n";
+                }
+            }
+        }
+
+        html += "</body></html>\n";
+        html
+    }
+}
+
+/// The thread serving the status page.
+pub struct Dashboard {
+    shutdown: mpsc::Sender<()>,
+    handle: thread::JoinHandle<()>,
+}
+
+impl Dashboard {
+    pub fn start(
+        listener: net::TcpListener,
+        token: String,
+        control: DaemonControl,
+    ) -> Result<Dashboard, io::Error> {
+        // Don't block on accept, to be able to stop.
+        listener.set_nonblocking(true)?;
+        let (shutdown, receiver) = mpsc::channel();
+        let handle = thread::Builder::new()
+            .name("Dashboard server".to_string())
+            .spawn(move || {
+                log::info!("Dashboard served on {:?}.", listener.local_addr());
+                let clients = Arc::new(AtomicUsize::new(0));
+                loop {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            serve_client(stream, &token, &control, &clients);
+                            continue;
+                        }
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                        Err(e) => log::error!("Error accepting a dashboard connection: {}", e),
+                    }
+                    if receiver.recv_timeout(ACCEPT_INTERVAL)
+                        != Err(mpsc::RecvTimeoutError::Timeout)
+                    {
+                        break;
+                    }
+                }
+                log::info!("Dashboard server stopped.");
+            })
+            .expect("Spawning the dashboard thread must never fail.");
+        Ok(Dashboard { shutdown, handle })
+    }
+
+    pub fn stop(self) {
+        // The thread also stops if we hung up already.
+        let _ = self.shutdown.send(());
+        self.handle.join().expect("Dashboard thread must not panic");
+    }
+}
+
+// Compare the given token to ours without leaking where they differ through timing.
+pub(crate) fn is_token(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+// Whether the request target or headers contain the token.
+fn is_authorized(target: &str, headers: &[String], token: &str) -> bool {
+    let in_query = target
+        .split_once('?')
+        .map(|(_, query)| {
+            query
+                .split('&')
+                .filter_map(|param| param.strip_prefix("token="))
+                .any(|given| is_token(given, token))
+        })
+        .unwrap_or(false);
+    let in_header = headers.iter().any(|header| {
+        header
+            .split_once(':')
+            .filter(|(name, _)| name.trim().eq_ignore_ascii_case("authorization"))
+            .and_then(|(_, value)| value.trim().strip_prefix("Bearer "))
+            .map(|given| is_token(given.trim(), token))
+            .unwrap_or(false)
+    });
+    in_query || in_header
+}
+
+// Serve this client on its own thread, for a slow one not to hold the others.
+fn serve_client(
+    stream: net::TcpStream,
+    token: &str,
+    control: &DaemonControl,
+    clients: &Arc<AtomicUsize>,
+) {
+    if clients.fetch_add(1, Ordering::SeqCst) >= MAX_CLIENTS {
+        clients.fetch_sub(1, Ordering::SeqCst);
+        log::debug!("Too many dashboard clients, closing the connection.");
+        return;
+    }
+    let (token, control, thread_clients) = (token.to_string(), control.clone(), clients.clone());
+    let spawned = thread::Builder::new()
+        .name("Dashboard client".to_string())
+        .spawn(move || {
+            if let Err(e) = handle_request(stream, &token, &control) {
+                log::debug!("Error serving a dashboard request: {}", e);
+            }
+            thread_clients.fetch_sub(1, Ordering::SeqCst);
+        });
+    if let Err(e) = spawned {
+        clients.fetch_sub(1, Ordering::SeqCst);
+        log::error!("Error spawning a thread for a dashboard client: {}", e);
+    }
+}
+
+fn handle_request(
+    stream: net::TcpStream,
+    token: &str,
+    control: &DaemonControl,
+) -> Result<(), io::Error> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = io::BufReader::new((&stream).take(MAX_REQUEST_SIZE));
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        headers.push(line);
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let path = target.split('?').next().unwrap_or("");
+    let (status, content_type, body) = if method != "GET" {
+        (
+            "405 Method Not Allowed",
+            "text/plain",
+            "Only GET is supported.\n".to_string(),
+        )
+    } else if !is_authorized(target, &headers, token) {
+        (
+            "401 Unauthorized",
+            "text/plain",
+            "Invalid or missing token.\n".to_string(),
+        )
+    } else if path == "/" {
+        (
+            "200 OK",
+            "text/html; charset=utf-8",
+            Status::new(control).to_html(),
+        )
+    } else if path == "/status.json" {
+        (
+            "200 OK",
+            "application/json",
+            serde_json::to_string_pretty(&Status::new(control))
+                .expect("Serializing the status can't fail"),
+        )
+    } else {
+        ("404 Not Found", "text/plain", "Not found.\n".to_string())
+    };
+
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\n\
+         Connection: close\r\n{}\r\n",
+        status,
+        content_type,
+        body.len(),
+        if status.starts_with("401") {
+            "WWW-Authenticate: Bearer\r\n"
+        } else {
+            ""
+        }
+    )?;
+    stream.write_all(body.as_bytes())?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutils::*;
+
+    const TOKEN: &str = "0123456789abcdef";
+
+    fn get(addr: net::SocketAddr, target: &str, headers: &str) -> String {
+        let mut stream = net::TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: localhost\r\n{}\r\n",
+            target, headers
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn authorization() {
+        let headers = vec!["Authorization: Bearer 0123456789abcdef\r\n".to_string()];
+        assert!(is_authorized("/", &headers, TOKEN));
+        assert!(is_authorized(
+            "/?lang=en&token=0123456789abcdef",
+            &[],
+            TOKEN
+        ));
+        assert!(!is_authorized("/", &[], TOKEN));
+        assert!(!is_authorized("/?token=0123456789abcde", &[], TOKEN));
+        assert!(!is_authorized(
+            "/",
+            &["Authorization: Bearer nope\r\n".to_string()],
+            TOKEN
+        ));
+    }
+
+    #[test]
+    fn serve_status() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let dashboard =
+            Dashboard::start(listener, TOKEN.to_string(), ms.control().clone()).unwrap();
+
+        // The token is required.
+        let response = get(addr, "/", "");
+        assert!(response.starts_with("HTTP/1.1 401"));
+
+        // An idle client doesn't hold the others.
+        let idle = net::TcpStream::connect(addr).unwrap();
+        let start = time::Instant::now();
+        let response = get(addr, "/?token=0123456789abcdef", "");
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(start.elapsed() < REQUEST_TIMEOUT);
+        drop(idle);
+
+        let response = get(addr, "/?token=0123456789abcdef", "");
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains("<h2>Balance</h2>"));
+
+        let response = get(
+            addr,
+            "/status.json",
+            "Authorization: Bearer 0123456789abcdef\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 200"));
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        let status: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(status["network"], "bitcoin");
+        assert!(status["transactions"].as_array().unwrap().is_empty());
+
+        let response = get(addr, "/spend?token=0123456789abcdef", "");
+        assert!(response.starts_with("HTTP/1.1 404"));
+
+        dashboard.stop();
+        ms.shutdown();
+    }
+}
@@ -10,6 +10,10 @@ use crate::{
         schema::{DbBlockInfo, DbCoin, DbTip},
         SqliteConn, SqliteDb,
     },
+    events::EventEntry,
+    readiness::ReadinessItem,
+    reviews::SpendReview,
+    templates::SpendTemplate,
 };
 
 use std::{
@@ -37,6 +41,8 @@ pub struct Wallet {
     pub rescan_timestamp: Option<u32>,
     /// Timestamp at which the last poll of the blockchain completed, if any,
     pub last_poll_timestamp: Option<u32>,
+    /// Height up to which the ongoing rescan processed the chain, if known.
+    pub rescan_checkpoint: Option<i32>,
 }
 
 pub trait DatabaseInterface: Send {
@@ -101,6 +107,12 @@ pub trait DatabaseConnection {
     /// Mark the rescan as complete.
     fn complete_rescan(&mut self);
 
+    /// Get the height up to which the ongoing rescan processed the chain, if known.
+    fn rescan_checkpoint(&mut self) -> Option<i32>;
+
+    /// Record the height up to which the ongoing rescan processed the chain.
+    fn set_rescan_checkpoint(&mut self, height: i32);
+
     /// Get the timestamp at which the last poll of the blockchain completed, if any,
     /// as the number of seconds since the UNIX epoch.
     fn last_poll_timestamp(&mut self) -> Option<u32>;
@@ -146,6 +158,19 @@ pub trait DatabaseConnection {
     /// Mark a set of coins as spent by a specified txid at a specified block time.
     fn confirm_spend(&mut self, outpoints: &[(bitcoin::OutPoint, bitcoin::Txid, i32, u32)]);
 
+    /// The total value of our unspent and spending coins, by status.
+    fn balances(&mut self) -> Balances;
+
+    /// Apply all the changes found during a poll at once: store the new transactions, update the
+    /// coins as the methods above do, then whether they are from self (see
+    /// [`DatabaseConnection::update_coins_from_self`]) and the tip if it moved.
+    fn apply_poll_updates(
+        &mut self,
+        updates: &PollUpdates,
+        prev_tip_height: i32,
+        new_tip: Option<&BlockChainTip>,
+    );
+
     /// Get specific coins from the database.
     fn coins_by_outpoints(
         &mut self,
@@ -163,6 +188,93 @@ pub trait DatabaseConnection {
     /// Delete a Spend transaction from database.
     fn delete_spend(&mut self, txid: &bitcoin::Txid);
 
+    /// The signers which signed this Spend transaction, along with the time at which their first
+    /// signature was stored, in this order.
+    fn spend_signers(&mut self, txid: &bitcoin::Txid) -> Vec<(bip32::Fingerprint, u32)>;
+
+    /// Store the review of a Spend transaction by a signer, replacing its previous one if any.
+    fn store_spend_review(&mut self, txid: &bitcoin::Txid, review: &SpendReview);
+
+    /// The latest review of this Spend transaction by each signer, in the order they were made.
+    fn spend_reviews(&mut self, txid: &bitcoin::Txid) -> Vec<SpendReview>;
+
+    /// Record in the broadcast journal a Spend transaction we are about to broadcast.
+    fn record_broadcast(&mut self, tx: &bitcoin::Transaction);
+
+    /// The Spend transactions in the broadcast journal, in the order they were recorded.
+    fn broadcast_journal(&mut self) -> Vec<bitcoin::Transaction>;
+
+    /// Remove a Spend transaction from the broadcast journal.
+    fn remove_broadcast(&mut self, txid: &bitcoin::Txid);
+
+    /// Store a fully signed Spend transaction to be broadcast once its locktime is reached.
+    fn schedule_spend(&mut self, tx: &bitcoin::Transaction);
+
+    /// The scheduled Spend transactions, in the order they were scheduled.
+    fn scheduled_spends(&mut self) -> Vec<ScheduledSpend>;
+
+    /// Remove a Spend transaction from the scheduled ones.
+    fn unschedule_spend(&mut self, txid: &bitcoin::Txid);
+
+    /// Our confirmed transactions, along with their confirmation height, whose inclusion in the
+    /// block at this height wasn't checked yet.
+    fn unverified_transactions(&mut self) -> Vec<(bitcoin::Txid, i32)>;
+
+    /// Record the result of checking this transaction was included in the block at this height.
+    /// `error` is the reason it isn't, if so.
+    fn record_tx_verification(&mut self, txid: &bitcoin::Txid, height: i32, error: Option<&str>);
+
+    /// The result of the last check of the inclusion of this transaction in a block, if any.
+    fn tx_verification(&mut self, txid: &bitcoin::Txid) -> Option<TxVerification>;
+
+    /// The keys to scan the chain for Silent Payments to the wallet, if it was enabled.
+    fn silent_payment_keys(&mut self) -> Option<SilentPaymentKeys>;
+
+    /// Scan the chain for Silent Payments to the wallet with these keys, from the block following
+    /// their `scanned_height`.
+    fn store_silent_payment_keys(&mut self, keys: &SilentPaymentKeys);
+
+    /// The Silent Payments to the wallet found so far.
+    fn silent_payments(&mut self) -> Vec<SilentPayment>;
+
+    /// Record the block at this height was scanned for Silent Payments, along with the payments
+    /// found in it and the payments spent by its transactions.
+    fn record_silent_payment_scan(
+        &mut self,
+        block: BlockInfo,
+        payments: &[SilentPayment],
+        spent: &[(bitcoin::OutPoint, bitcoin::Txid)],
+    );
+
+    /// Mark these Silent Payments as being spent by this unconfirmed transaction, or as unspent
+    /// if `None`.
+    fn spend_silent_payments(
+        &mut self,
+        outpoints: &[bitcoin::OutPoint],
+        spend_txid: Option<&bitcoin::Txid>,
+    );
+
+    /// The items of the recovery readiness checklist marked as done, along with the timestamp
+    /// at which they were.
+    fn readiness_checks(&mut self) -> HashMap<ReadinessItem, u32>;
+
+    /// Mark an item of the recovery readiness checklist as done, or not done anymore.
+    fn set_readiness_check(&mut self, item: ReadinessItem, done: bool);
+
+    /// Store an event which happened to the wallet.
+    fn store_event(&mut self, entry: &EventEntry);
+
+    /// The latest stored events, up to this number, from the oldest to the newest.
+    fn events(&mut self, limit: usize) -> Vec<EventEntry>;
+
+    /// Store a spend template, replacing the one with the same name if any.
+    fn store_spend_template(&mut self, template: &SpendTemplate);
+
+    /// All the spend templates, ordered by name.
+    fn spend_templates(&mut self) -> Vec<SpendTemplate>;
+
+    fn delete_spend_template(&mut self, name: &str);
+
     /// Update, for a set of items (as key), their label (as value). A `None` value deletes the
     /// label.
     fn update_labels(&mut self, items: &HashMap<LabelItem, Option<String>>);
@@ -175,6 +287,10 @@ pub trait DatabaseConnection {
     /// Retrieve a limited list of txids that where deposited or spent between the start and end timestamps (inclusive bounds)
     fn list_txids(&mut self, start: u32, end: u32, limit: u64) -> Vec<bitcoin::Txid>;
 
+    /// The latest transactions which deposited or spent coins, up to this number, along with their
+    /// confirmation height. The unconfirmed ones come first, then by decreasing height.
+    fn recent_txids(&mut self, limit: u64) -> Vec<(bitcoin::Txid, Option<i32>)>;
+
     /// Retrieves all txids from the transactions table whether or not they are referenced by a coin.
     fn list_saved_txids(&mut self) -> Vec<bitcoin::Txid>;
 
@@ -190,6 +306,9 @@ pub trait DatabaseConnection {
         &mut self,
         txids: &[bitcoin::Txid],
     ) -> Vec<(bitcoin::Transaction, Option<i32>, Option<u32>)>;
+
+    /// Quickly check the integrity of the database. Returns the problems found, if any.
+    fn quick_check(&mut self) -> Vec<String>;
 }
 
 impl DatabaseConnection for SqliteConn {
@@ -216,6 +335,7 @@ impl DatabaseConnection for SqliteConn {
             change_index: db_wallet.change_derivation_index,
             rescan_timestamp: db_wallet.rescan_timestamp,
             last_poll_timestamp: db_wallet.last_poll_timestamp,
+            rescan_checkpoint: db_wallet.rescan_checkpoint,
         }
     }
 
@@ -263,6 +383,14 @@ impl DatabaseConnection for SqliteConn {
         self.complete_wallet_rescan()
     }
 
+    fn rescan_checkpoint(&mut self) -> Option<i32> {
+        self.wallet().rescan_checkpoint
+    }
+
+    fn set_rescan_checkpoint(&mut self, height: i32) {
+        self.set_wallet_rescan_checkpoint(height)
+    }
+
     fn last_poll_timestamp(&mut self) -> Option<u32> {
         self.wallet().last_poll_timestamp
     }
@@ -314,6 +442,19 @@ impl DatabaseConnection for SqliteConn {
         self.confirm_spend(outpoints)
     }
 
+    fn balances(&mut self) -> Balances {
+        self.db_balances()
+    }
+
+    fn apply_poll_updates(
+        &mut self,
+        updates: &PollUpdates,
+        prev_tip_height: i32,
+        new_tip: Option<&BlockChainTip>,
+    ) {
+        self.apply_poll_updates(updates, prev_tip_height, new_tip)
+    }
+
     fn derivation_index_by_address(
         &mut self,
         address: &bitcoin::Address,
@@ -358,6 +499,111 @@ impl DatabaseConnection for SqliteConn {
         self.delete_spend(txid)
     }
 
+    fn spend_signers(&mut self, txid: &bitcoin::Txid) -> Vec<(bip32::Fingerprint, u32)> {
+        self.db_spend_signers(txid)
+    }
+
+    fn store_spend_review(&mut self, txid: &bitcoin::Txid, review: &SpendReview) {
+        self.store_spend_review(txid, review)
+    }
+
+    fn spend_reviews(&mut self, txid: &bitcoin::Txid) -> Vec<SpendReview> {
+        self.db_spend_reviews(txid)
+    }
+
+    fn record_broadcast(&mut self, tx: &bitcoin::Transaction) {
+        self.record_broadcast(tx)
+    }
+
+    fn broadcast_journal(&mut self) -> Vec<bitcoin::Transaction> {
+        self.broadcast_journal()
+    }
+
+    fn remove_broadcast(&mut self, txid: &bitcoin::Txid) {
+        self.remove_broadcast(txid)
+    }
+
+    fn schedule_spend(&mut self, tx: &bitcoin::Transaction) {
+        self.schedule_spend(tx)
+    }
+
+    fn scheduled_spends(&mut self) -> Vec<ScheduledSpend> {
+        self.scheduled_spends()
+    }
+
+    fn unschedule_spend(&mut self, txid: &bitcoin::Txid) {
+        self.unschedule_spend(txid)
+    }
+
+    fn unverified_transactions(&mut self) -> Vec<(bitcoin::Txid, i32)> {
+        self.unverified_transactions()
+    }
+
+    fn record_tx_verification(&mut self, txid: &bitcoin::Txid, height: i32, error: Option<&str>) {
+        self.record_tx_verification(txid, height, error)
+    }
+
+    fn tx_verification(&mut self, txid: &bitcoin::Txid) -> Option<TxVerification> {
+        self.tx_verification(txid)
+    }
+
+    fn silent_payment_keys(&mut self) -> Option<SilentPaymentKeys> {
+        self.silent_payment_keys()
+    }
+
+    fn store_silent_payment_keys(&mut self, keys: &SilentPaymentKeys) {
+        self.store_silent_payment_keys(keys)
+    }
+
+    fn silent_payments(&mut self) -> Vec<SilentPayment> {
+        self.silent_payments()
+    }
+
+    fn record_silent_payment_scan(
+        &mut self,
+        block: BlockInfo,
+        payments: &[SilentPayment],
+        spent: &[(bitcoin::OutPoint, bitcoin::Txid)],
+    ) {
+        self.record_silent_payment_scan(block, payments, spent)
+    }
+
+    fn spend_silent_payments(
+        &mut self,
+        outpoints: &[bitcoin::OutPoint],
+        spend_txid: Option<&bitcoin::Txid>,
+    ) {
+        self.spend_silent_payments(outpoints, spend_txid)
+    }
+
+    fn readiness_checks(&mut self) -> HashMap<ReadinessItem, u32> {
+        self.readiness_checks()
+    }
+
+    fn set_readiness_check(&mut self, item: ReadinessItem, done: bool) {
+        self.set_readiness_check(item, done)
+    }
+
+    fn store_event(&mut self, entry: &EventEntry) {
+        self.store_event(entry)
+    }
+
+    fn events(&mut self, limit: usize) -> Vec<EventEntry> {
+        self.events(limit)
+    }
+
+    fn store_spend_template(&mut self, template: &SpendTemplate) {
+        self.store_spend_template(template)
+    }
+
+    fn spend_templates(&mut self) -> Vec<SpendTemplate> {
+        self.spend_templates()
+    }
+
+    fn delete_spend_template(&mut self, name: &str) {
+        self.delete_spend_template(name)
+    }
+
     fn update_labels(&mut self, items: &HashMap<LabelItem, Option<String>>) {
         self.update_labels(items)
     }
@@ -375,6 +621,10 @@ impl DatabaseConnection for SqliteConn {
         self.db_list_txids(start, end, limit)
     }
 
+    fn recent_txids(&mut self, limit: u64) -> Vec<(bitcoin::Txid, Option<i32>)> {
+        self.db_recent_txids(limit)
+    }
+
     fn list_saved_txids(&mut self) -> Vec<bitcoin::Txid> {
         self.db_list_saved_txids()
     }
@@ -403,6 +653,77 @@ impl DatabaseConnection for SqliteConn {
             })
             .collect()
     }
+
+    fn quick_check(&mut self) -> Vec<String> {
+        self.db_quick_check()
+    }
+}
+
+/// A fully signed Spend transaction waiting for its locktime to be broadcast.
+/// The total value of our coins which aren't spent yet, by status.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Balances {
+    pub confirmed: bitcoin::Amount,
+    /// Unconfirmed coins created by a transaction spending only our coins.
+    pub unconfirmed_from_self: bitcoin::Amount,
+    pub unconfirmed: bitcoin::Amount,
+    /// Coinbase deposits which can't be spent yet.
+    pub immature: bitcoin::Amount,
+    /// Coins spent by an unconfirmed transaction.
+    pub spending: bitcoin::Amount,
+}
+
+/// The changes to our coins found during a poll, and the transactions they involve which aren't
+/// in database yet.
+#[derive(Debug, Clone, Default)]
+pub struct PollUpdates {
+    pub txs: Vec<bitcoin::Transaction>,
+    pub received: Vec<Coin>,
+    pub confirmed: Vec<(bitcoin::OutPoint, i32, u32)>,
+    pub expired: Vec<bitcoin::OutPoint>,
+    pub spending: Vec<(bitcoin::OutPoint, bitcoin::Txid)>,
+    pub expired_spending: Vec<bitcoin::OutPoint>,
+    pub spent: Vec<(bitcoin::OutPoint, bitcoin::Txid, i32, u32)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledSpend {
+    pub tx: bitcoin::Transaction,
+    /// When it was scheduled.
+    pub scheduled_at: u32,
+}
+
+/// Whether the inclusion of a transaction in the block at this height was verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxVerification {
+    pub block_height: i32,
+    /// Why the proof of inclusion is invalid, `None` if it is valid.
+    pub error: Option<String>,
+    /// When it was checked.
+    pub checked_at: u32,
+}
+
+/// The keys to scan the chain for Silent Payments (BIP352) to the wallet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SilentPaymentKeys {
+    /// Master fingerprint of the hot signer the keys are derived from.
+    pub fingerprint: bip32::Fingerprint,
+    pub scan_key: secp256k1::SecretKey,
+    pub spend_key: secp256k1::PublicKey,
+    /// Height up to which the chain was scanned.
+    pub scanned_height: i32,
+}
+
+/// A confirmed output paying to the Silent Payment address of the wallet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SilentPayment {
+    pub outpoint: bitcoin::OutPoint,
+    pub amount: bitcoin::Amount,
+    /// The tweak of the spend key to spend this output.
+    pub tweak: secp256k1::Scalar,
+    pub block: BlockInfo,
+    pub spend_txid: Option<bitcoin::Txid>,
+    pub spend_block: Option<BlockInfo>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
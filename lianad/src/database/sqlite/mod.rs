@@ -10,29 +10,41 @@
 pub mod schema;
 mod utils;
 
+pub use utils::LOOK_AHEAD_LIMIT;
+
 use crate::{
     bitcoin::BlockChainTip,
     database::{
         sqlite::{
             schema::{
-                DbAddress, DbCoin, DbLabel, DbLabelledKind, DbSpendTransaction, DbTip, DbWallet,
-                DbWalletTransaction, SCHEMA,
+                DbAddress, DbCoin, DbLabel, DbLabelledKind, DbSignatureKind, DbSpendSignature,
+                DbSpendTransaction, DbTip, DbWallet, DbWalletTransaction, SCHEMA,
             },
             utils::{
                 create_fresh_db, curr_timestamp, db_exec, db_query, db_tx_query, db_version,
-                maybe_apply_migration, LOOK_AHEAD_LIMIT,
+                fill_fresh_db, maybe_apply_migration,
             },
         },
-        Coin, CoinStatus, LabelItem,
+        Balances, BlockInfo, Coin, CoinStatus, LabelItem, PollUpdates, ScheduledSpend,
+        SilentPayment, SilentPaymentKeys, TxVerification,
     },
+    events::{Event, EventEntry},
+    readiness::ReadinessItem,
+    reviews::{ReviewDecision, SpendReview},
+    templates::SpendTemplate,
 };
 use liana::descriptors::LianaDescriptor;
 
 use std::{
     cmp,
     collections::{HashMap, HashSet},
-    convert::TryInto,
+    convert::{TryFrom, TryInto},
     fmt, io, path,
+    str::FromStr,
+    sync::{
+        self,
+        atomic::{AtomicU64, Ordering},
+    },
 };
 
 use miniscript::bitcoin::{
@@ -43,7 +55,7 @@ use miniscript::bitcoin::{
     secp256k1,
 };
 
-const DB_VERSION: i64 = 8;
+const DB_VERSION: i64 = 20;
 
 /// Last database version for which Bitcoin transactions were not stored in database. In practice
 /// this meant we relied on the bitcoind watchonly wallet to store them for us.
@@ -136,9 +148,15 @@ impl FreshDbOptions {
     }
 }
 
+/// Used to give a unique name to the databases kept in memory.
+static NEXT_IN_MEMORY_ID: AtomicU64 = AtomicU64::new(0);
+
 #[derive(Debug, Clone)]
 pub struct SqliteDb {
     db_path: path::PathBuf,
+    // For a database kept in memory, a connection held open for as long as we live. Such a
+    // database is freed along with the last connection to it.
+    in_memory: Option<sync::Arc<sync::Mutex<rusqlite::Connection>>>,
 }
 
 impl SqliteDb {
@@ -161,7 +179,34 @@ impl SqliteDb {
 
         log::info!("Checking if the database needs upgrading.");
 
-        Ok(SqliteDb { db_path })
+        Ok(SqliteDb {
+            db_path,
+            in_memory: None,
+        })
+    }
+
+    /// Instantiate a fresh SQLite database kept in memory, for a wallet not to leave any trace on
+    /// disk. It is freed once this and all its clones are dropped.
+    pub fn new_in_memory(
+        options: FreshDbOptions,
+        secp: &secp256k1::Secp256k1<secp256k1::VerifyOnly>,
+    ) -> Result<SqliteDb, SqliteDbError> {
+        // With the "memdb" VFS, an in-memory database whose name starts with a '/' is shared by all
+        // the connections of the process opening it. Unlike with a shared cache, they still lock
+        // it as they would a file, so the busy timeout applies.
+        let db_path = path::PathBuf::from(format!(
+            "file:/lianad-{}-{}?vfs=memdb",
+            std::process::id(),
+            NEXT_IN_MEMORY_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut conn = rusqlite::Connection::open(&db_path)?;
+        fill_fresh_db(&mut conn, options, secp)?;
+        log::info!("Created a fresh database in memory.");
+
+        Ok(SqliteDb {
+            db_path,
+            in_memory: Some(sync::Arc::new(sync::Mutex::new(conn))),
+        })
     }
 
     /// If the database version is older than expected, migrate it to the current version. If
@@ -253,13 +298,32 @@ impl SqliteConn {
 
     /// Update the network tip.
     pub fn update_tip(&mut self, tip: &BlockChainTip) {
+        db_exec(&mut self.conn, |db_tx| update_tip(db_tx, tip)).expect("Database must be available")
+    }
+
+    /// Apply all the changes found during a poll in a single database transaction: store the
+    /// new transactions, update our coins, whether they are from self, and the tip if it moved.
+    pub fn apply_poll_updates(
+        &mut self,
+        updates: &PollUpdates,
+        prev_tip_height: i32,
+        new_tip: Option<&BlockChainTip>,
+    ) {
         db_exec(&mut self.conn, |db_tx| {
-            db_tx
-                .execute(
-                    "UPDATE tip SET blockheight = (?1), blockhash = (?2)",
-                    rusqlite::params![tip.height, tip.hash[..].to_vec()],
-                )
-                .map(|_| ())
+            // Transactions must be added before coins due to foreign key constraints.
+            insert_txs(db_tx, &updates.txs)?;
+            insert_coins(db_tx, &updates.received)?;
+            delete_coins(db_tx, &updates.expired)?;
+            confirm_coins(db_tx, &updates.confirmed)?;
+            unspend_coins(db_tx, &updates.expired_spending)?;
+            spend_coins(db_tx, &updates.spending)?;
+            confirm_spend(db_tx, &updates.spent)?;
+            // Only once the coins were inserted and updated.
+            update_coins_from_self(db_tx, prev_tip_height)?;
+            if let Some(tip) = new_tip {
+                update_tip(db_tx, tip)?;
+            }
+            Ok(())
         })
         .expect("Database must be available")
     }
@@ -339,7 +403,7 @@ impl SqliteConn {
             // NOTE: this will need to be updated if we ever implement multi-wallet support
             db_tx
                 .execute(
-                    "UPDATE wallets SET rescan_timestamp = (?1)",
+                    "UPDATE wallets SET rescan_timestamp = (?1), rescan_checkpoint = NULL",
                     rusqlite::params![timestamp],
                 )
                 .map(|_| ())
@@ -347,6 +411,20 @@ impl SqliteConn {
         .expect("Database must be available")
     }
 
+    /// Record the height up to which the ongoing rescan processed the chain.
+    pub fn set_wallet_rescan_checkpoint(&mut self, height: i32) {
+        db_exec(&mut self.conn, |db_tx| {
+            // NOTE: this will need to be updated if we ever implement multi-wallet support
+            db_tx
+                .execute(
+                    "UPDATE wallets SET rescan_checkpoint = (?1)",
+                    rusqlite::params![height],
+                )
+                .map(|_| ())
+        })
+        .expect("Database must be available")
+    }
+
     /// Drop the rescan timestamp, and set it as the wallet creation timestamp if it
     /// predates it.
     ///
@@ -363,7 +441,7 @@ impl SqliteConn {
             // NOTE: this will need to be updated if we ever implement multi-wallet support
             db_tx
                 .execute(
-                    "UPDATE wallets SET timestamp = (?1), rescan_timestamp = NULL",
+                    "UPDATE wallets SET timestamp = (?1), rescan_timestamp = NULL, rescan_checkpoint = NULL",
                     rusqlite::params![new_timestamp],
                 )
                 .map(|_| ())
@@ -457,41 +535,14 @@ impl SqliteConn {
     /// Store new, unconfirmed and unspent, coins.
     /// Will panic if given a coin that is already in DB.
     pub fn new_unspent_coins<'a>(&mut self, coins: impl IntoIterator<Item = &'a Coin>) {
-        db_exec(&mut self.conn, |db_tx| {
-            for coin in coins {
-                let deriv_index: u32 = coin.derivation_index.into();
-                db_tx.execute(
-                    "INSERT INTO coins (wallet_id, txid, vout, amount_sat, derivation_index, is_change, is_immature) \
-                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-                    rusqlite::params![
-                        WALLET_ID,
-                        coin.outpoint.txid[..].to_vec(),
-                        coin.outpoint.vout,
-                        coin.amount.to_sat(),
-                        deriv_index,
-                        coin.is_change,
-                        coin.is_immature,
-                    ],
-                )?;
-            }
-            Ok(())
-        })
-        .expect("Database must be available")
+        db_exec(&mut self.conn, |db_tx| insert_coins(db_tx, coins))
+            .expect("Database must be available")
     }
 
     /// Remove a set of coins from the database.
     pub fn remove_coins(&mut self, outpoints: &[bitcoin::OutPoint]) {
-        db_exec(&mut self.conn, |db_tx| {
-            for outpoint in outpoints {
-                db_tx.execute(
-                    "DELETE FROM coins WHERE txid = ?1 AND vout = ?2",
-                    rusqlite::params![outpoint.txid[..].to_vec(), outpoint.vout,],
-                )?;
-            }
-
-            Ok(())
-        })
-        .expect("Database must be available")
+        db_exec(&mut self.conn, |db_tx| delete_coins(db_tx, outpoints))
+            .expect("Database must be available")
     }
 
     /// Mark a set of coins as confirmed.
@@ -502,17 +553,8 @@ impl SqliteConn {
         &mut self,
         outpoints: impl IntoIterator<Item = &'a (bitcoin::OutPoint, i32, u32)>,
     ) {
-        db_exec(&mut self.conn, |db_tx| {
-            for (outpoint, height, time) in outpoints {
-                db_tx.execute(
-                    "UPDATE coins SET blockheight = ?1, blocktime = ?2, is_immature = 0 WHERE txid = ?3 AND vout = ?4",
-                    rusqlite::params![height, time, outpoint.txid[..].to_vec(), outpoint.vout,],
-                )?;
-            }
-
-            Ok(())
-        })
-        .expect("Database must be available")
+        db_exec(&mut self.conn, |db_tx| confirm_coins(db_tx, outpoints))
+            .expect("Database must be available")
     }
 
     /// Mark a set of coins as spending.
@@ -520,21 +562,8 @@ impl SqliteConn {
         &mut self,
         outpoints: impl IntoIterator<Item = &'a (bitcoin::OutPoint, bitcoin::Txid)>,
     ) {
-        db_exec(&mut self.conn, |db_tx| {
-            for (outpoint, spend_txid) in outpoints {
-                db_tx.execute(
-                    "UPDATE coins SET spend_txid = ?1 WHERE txid = ?2 AND vout = ?3",
-                    rusqlite::params![
-                        spend_txid[..].to_vec(),
-                        outpoint.txid[..].to_vec(),
-                        outpoint.vout,
-                    ],
-                )?;
-            }
-
-            Ok(())
-        })
-        .expect("Database must be available")
+        db_exec(&mut self.conn, |db_tx| spend_coins(db_tx, outpoints))
+            .expect("Database must be available")
     }
 
     /// Mark a set of coins as not being spent.
@@ -542,20 +571,8 @@ impl SqliteConn {
         &mut self,
         outpoints: impl IntoIterator<Item = &'a bitcoin::OutPoint>,
     ) {
-        db_exec(&mut self.conn, |db_tx| {
-            for outpoint in outpoints {
-                db_tx.execute(
-                    "UPDATE coins SET spend_txid = NULL, spend_block_height = NULL, spend_block_time = NULL WHERE txid = ?1 AND vout = ?2",
-                    rusqlite::params![
-                        outpoint.txid[..].to_vec(),
-                        outpoint.vout,
-                    ],
-                )?;
-            }
-
-            Ok(())
-        })
-        .expect("Database must be available")
+        db_exec(&mut self.conn, |db_tx| unspend_coins(db_tx, outpoints))
+            .expect("Database must be available")
     }
 
     /// Mark the Spend transaction of a given set of coins as being confirmed at a given
@@ -564,23 +581,8 @@ impl SqliteConn {
         &mut self,
         outpoints: impl IntoIterator<Item = &'a (bitcoin::OutPoint, bitcoin::Txid, i32, u32)>,
     ) {
-        db_exec(&mut self.conn, |db_tx| {
-            for (outpoint, spend_txid, height, time) in outpoints {
-                db_tx.execute(
-                    "UPDATE coins SET spend_txid = ?1, spend_block_height = ?2, spend_block_time = ?3 WHERE txid = ?4 AND vout = ?5",
-                    rusqlite::params![
-                        spend_txid[..].to_vec(),
-                        height,
-                        time,
-                        outpoint.txid[..].to_vec(),
-                        outpoint.vout,
-                    ],
-                )?;
-            }
-
-            Ok(())
-        })
-        .expect("Database must be available")
+        db_exec(&mut self.conn, |db_tx| confirm_spend(db_tx, outpoints))
+            .expect("Database must be available")
     }
 
     pub fn db_address(&mut self, address: &bitcoin::Address) -> Option<DbAddress> {
@@ -599,37 +601,191 @@ impl SqliteConn {
     }
 
     pub fn db_spend(&mut self, txid: &bitcoin::Txid) -> Option<DbSpendTransaction> {
-        db_query(
-            &mut self.conn,
-            "SELECT * FROM spend_transactions WHERE txid = ?1",
-            rusqlite::params![txid[..].to_vec()],
-            |row| row.try_into(),
-        )
-        .expect("Db must not fail")
-        .pop()
+        let mut spend = None;
+        db_exec(&mut self.conn, |db_tx| {
+            spend = db_tx_query(
+                db_tx,
+                "SELECT * FROM spend_transactions WHERE txid = ?1",
+                rusqlite::params![txid[..].to_vec()],
+                |row| DbSpendTransaction::try_from(row),
+            )?
+            .pop();
+            if let Some(spend) = spend.as_mut() {
+                for sig in db_tx_query(
+                    db_tx,
+                    "SELECT * FROM spend_signatures WHERE spend_id = ?1",
+                    rusqlite::params![spend.id],
+                    |row| DbSpendSignature::try_from(row),
+                )? {
+                    apply_signature(&mut spend.psbt, &sig);
+                }
+            }
+            Ok(())
+        })
+        .expect("Db must not fail");
+        spend
     }
 
     /// Insert a new Spend transaction or replace an existing one.
+    ///
+    /// The PSBT is stored without its signatures, which are stored separately. The signatures
+    /// which were already stored keep the time at which they were first stored.
     pub fn store_spend(&mut self, psbt: &Psbt) {
         let txid = &psbt.unsigned_tx.compute_txid()[..].to_vec();
+        let mut base_psbt = psbt.clone();
+        let sigs = take_signatures(&mut base_psbt);
+        let now = curr_timestamp();
 
         db_exec(&mut self.conn, |db_tx| {
             db_tx.execute(
                 "INSERT into spend_transactions (psbt, txid, updated_at) VALUES (?1, ?2, ?3) \
                  ON CONFLICT DO UPDATE SET psbt=excluded.psbt",
-                rusqlite::params![psbt.serialize(), txid, curr_timestamp()],
+                rusqlite::params![base_psbt.serialize(), txid, now],
+            )?;
+            let spend_id: i64 = db_tx.query_row(
+                "SELECT id FROM spend_transactions WHERE txid = ?1",
+                rusqlite::params![txid],
+                |row| row.get(0),
             )?;
+
+            // Remove the signatures which aren't part of the new PSBT and insert the new ones.
+            let stored = db_tx_query(
+                db_tx,
+                "SELECT * FROM spend_signatures WHERE spend_id = ?1",
+                rusqlite::params![spend_id],
+                |row| DbSpendSignature::try_from(row),
+            )?;
+            for stored_sig in stored {
+                if !sigs.iter().any(|sig| sig.is_same_key(&stored_sig)) {
+                    db_tx.execute(
+                        "DELETE FROM spend_signatures WHERE spend_id = ?1 AND input_index = ?2 \
+                         AND kind = ?3 AND pubkey = ?4 AND leaf_hash = ?5",
+                        rusqlite::params![
+                            spend_id,
+                            stored_sig.input_index as i64,
+                            stored_sig.kind as i64,
+                            stored_sig.pubkey,
+                            stored_sig.leaf_hash
+                        ],
+                    )?;
+                }
+            }
+            for sig in &sigs {
+                db_tx.execute(
+                    "INSERT INTO spend_signatures (spend_id, input_index, kind, pubkey, leaf_hash, \
+                     signature, fingerprint, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8) \
+                     ON CONFLICT DO UPDATE SET signature=excluded.signature, \
+                     fingerprint=excluded.fingerprint",
+                    rusqlite::params![
+                        spend_id,
+                        sig.input_index as i64,
+                        sig.kind as i64,
+                        sig.pubkey,
+                        sig.leaf_hash,
+                        sig.signature,
+                        sig.fingerprint.map(|fg| fg.as_bytes().to_vec()),
+                        now
+                    ],
+                )?;
+            }
             Ok(())
         })
         .expect("Db must not fail");
     }
 
     pub fn list_spend(&mut self) -> Vec<DbSpendTransaction> {
+        let mut spends = Vec::new();
+        db_exec(&mut self.conn, |db_tx| {
+            spends = db_tx_query(
+                db_tx,
+                "SELECT * FROM spend_transactions",
+                rusqlite::params![],
+                |row| DbSpendTransaction::try_from(row),
+            )?;
+            let mut sigs: HashMap<i64, Vec<DbSpendSignature>> = HashMap::new();
+            for sig in db_tx_query(
+                db_tx,
+                "SELECT * FROM spend_signatures",
+                rusqlite::params![],
+                |row| DbSpendSignature::try_from(row),
+            )? {
+                sigs.entry(sig.spend_id).or_default().push(sig);
+            }
+            for spend in spends.iter_mut() {
+                for sig in sigs.get(&spend.id).into_iter().flatten() {
+                    apply_signature(&mut spend.psbt, sig);
+                }
+            }
+            Ok(())
+        })
+        .expect("Db must not fail");
+        spends
+    }
+
+    /// The signers which signed this Spend transaction, along with the time at which their
+    /// first signature was stored, in this order. Signatures by unknown keys aren't attributed.
+    pub fn db_spend_signers(&mut self, txid: &bitcoin::Txid) -> Vec<(bip32::Fingerprint, u32)> {
         db_query(
             &mut self.conn,
-            "SELECT * FROM spend_transactions",
-            rusqlite::params![],
-            |row| row.try_into(),
+            "SELECT fingerprint, MIN(created_at) FROM spend_signatures \
+             INNER JOIN spend_transactions ON spend_transactions.id = spend_signatures.spend_id \
+             WHERE spend_transactions.txid = ?1 AND fingerprint IS NOT NULL \
+             GROUP BY fingerprint ORDER BY 2, 1",
+            rusqlite::params![txid[..].to_vec()],
+            |row| {
+                let fingerprint: Vec<u8> = row.get(0)?;
+                let fingerprint = bip32::Fingerprint::try_from(&fingerprint[..])
+                    .expect("We only store valid fingerprints");
+                Ok((fingerprint, row.get(1)?))
+            },
+        )
+        .expect("Db must not fail")
+    }
+
+    /// Store the review of a Spend transaction by a signer, replacing its previous one. The Spend
+    /// transaction must be stored.
+    pub fn store_spend_review(&mut self, txid: &bitcoin::Txid, review: &SpendReview) {
+        db_exec(&mut self.conn, |db_tx| {
+            db_tx.execute(
+                "INSERT INTO spend_reviews (spend_id, fingerprint, decision, comment, reviewed_at) \
+                 SELECT id, ?2, ?3, ?4, ?5 FROM spend_transactions WHERE txid = ?1 \
+                 ON CONFLICT (spend_id, fingerprint) DO UPDATE SET decision=excluded.decision, \
+                 comment=excluded.comment, reviewed_at=excluded.reviewed_at",
+                rusqlite::params![
+                    txid[..].to_vec(),
+                    review.signer.as_bytes().to_vec(),
+                    review.decision.as_str(),
+                    review.comment,
+                    review.reviewed_at,
+                ],
+            )?;
+            Ok(())
+        })
+        .expect("Db must not fail");
+    }
+
+    /// The latest review of this Spend transaction by each signer, in the order they were made.
+    pub fn db_spend_reviews(&mut self, txid: &bitcoin::Txid) -> Vec<SpendReview> {
+        db_query(
+            &mut self.conn,
+            "SELECT fingerprint, decision, comment, reviewed_at FROM spend_reviews \
+             INNER JOIN spend_transactions ON spend_transactions.id = spend_reviews.spend_id \
+             WHERE spend_transactions.txid = ?1 ORDER BY reviewed_at, spend_reviews.id",
+            rusqlite::params![txid[..].to_vec()],
+            |row| {
+                let fingerprint: Vec<u8> = row.get(0)?;
+                let signer = bip32::Fingerprint::try_from(&fingerprint[..])
+                    .expect("We only store valid fingerprints");
+                let decision: String = row.get(1)?;
+                let decision =
+                    ReviewDecision::from_str(&decision).expect("We only store valid decisions");
+                Ok(SpendReview {
+                    signer,
+                    decision,
+                    comment: row.get(2)?,
+                    reviewed_at: row.get(3)?,
+                })
+            },
         )
         .expect("Db must not fail")
     }
@@ -710,7 +866,87 @@ impl SqliteConn {
         .expect("Db must not fail")
     }
 
+    /// The latest transactions which deposited or spent coins, unconfirmed first. The confirmed
+    /// ones are read from the indexes on the heights, up to `limit` of each kind.
+    pub fn db_recent_txids(&mut self, limit: u64) -> Vec<(bitcoin::Txid, Option<i32>)> {
+        db_query(
+            &mut self.conn,
+            "SELECT txid, height FROM ( \
+                SELECT txid, NULL AS height FROM coins WHERE blockheight IS NULL \
+                UNION \
+                SELECT spend_txid, NULL FROM coins \
+                WHERE spend_txid IS NOT NULL AND spend_block_height IS NULL \
+                UNION \
+                SELECT * FROM ( \
+                    SELECT DISTINCT txid, blockheight FROM coins \
+                    WHERE blockheight IS NOT NULL \
+                    ORDER BY blockheight DESC LIMIT (?1) \
+                ) \
+                UNION \
+                SELECT * FROM ( \
+                    SELECT DISTINCT spend_txid, spend_block_height FROM coins \
+                    WHERE spend_block_height IS NOT NULL \
+                    ORDER BY spend_block_height DESC LIMIT (?1) \
+                ) \
+            ) \
+            ORDER BY height IS NOT NULL, height DESC LIMIT (?1)",
+            rusqlite::params![limit],
+            |row| {
+                let txid: Vec<u8> = row.get(0)?;
+                let txid: bitcoin::Txid =
+                    encode::deserialize(&txid).expect("We only store valid txids");
+                let height: Option<i32> = row.get(1)?;
+                Ok((txid, height))
+            },
+        )
+        .expect("Db must not fail")
+    }
+
+    /// The total value of our coins by status, as maintained by the database triggers.
+    pub fn db_balances(&mut self) -> Balances {
+        let mut balances = Balances::default();
+        let rows = db_query(
+            &mut self.conn,
+            "SELECT status, amount_sat FROM balances",
+            rusqlite::params![],
+            |row| {
+                let status: String = row.get(0)?;
+                let amount: i64 = row.get(1)?;
+                Ok((status, amount))
+            },
+        )
+        .expect("Db must not fail");
+        for (status, amount) in rows {
+            let amount = bitcoin::Amount::from_sat(
+                amount
+                    .try_into()
+                    .expect("Insane database: negative balance"),
+            );
+            match status.as_str() {
+                "confirmed" => balances.confirmed = amount,
+                "unconfirmed_from_self" => balances.unconfirmed_from_self = amount,
+                "unconfirmed" => balances.unconfirmed = amount,
+                "immature" => balances.immature = amount,
+                "spending" => balances.spending = amount,
+                _ => {}
+            }
+        }
+        balances
+    }
+
     /// Retrieves all txids from the transactions table whether or not they are referenced by a coin.
+    /// Run SQLite's quick integrity check. Returns the problems found, if any.
+    pub fn db_quick_check(&mut self) -> Vec<String> {
+        let res: Vec<String> = db_query(
+            &mut self.conn,
+            "PRAGMA quick_check",
+            rusqlite::params![],
+            |row| row.get(0),
+        )
+        .expect("Db must not fail");
+        res.into_iter().filter(|r| r != "ok").collect()
+    }
+
     pub fn db_list_saved_txids(&mut self) -> Vec<bitcoin::Txid> {
         db_query(
             &mut self.conn,
@@ -750,26 +986,7 @@ impl SqliteConn {
 
     /// Store transactions in database, ignoring any that already exist.
     pub fn new_txs(&mut self, txs: &[bitcoin::Transaction]) {
-        db_exec(&mut self.conn, |db_tx| {
-            for tx in txs {
-                let txid = &tx.compute_txid()[..].to_vec();
-                let tx_ser = bitcoin::consensus::serialize(tx);
-                db_tx.execute(
-                    "INSERT INTO transactions (txid, tx, num_inputs, num_outputs, is_coinbase) \
-                        VALUES (?1, ?2, ?3, ?4, ?5) \
-                        ON CONFLICT DO NOTHING",
-                    rusqlite::params![
-                        txid,
-                        tx_ser,
-                        tx.input.len(),
-                        tx.output.len(),
-                        tx.is_coinbase()
-                    ],
-                )?;
-            }
-            Ok(())
-        })
-        .expect("Database must be available")
+        db_exec(&mut self.conn, |db_tx| insert_txs(db_tx, txs)).expect("Database must be available")
     }
 
     /// Update `is_from_self` in coins table for all unconfirmed coins
@@ -781,79 +998,7 @@ impl SqliteConn {
     /// method is called.
     pub fn update_coins_from_self(&mut self, prev_tip_height: i32) -> Result<(), rusqlite::Error> {
         db_exec(&mut self.conn, |db_tx| {
-            // Given the requirement for unconfirmed coins that all ancestors
-            // be from self, we perform the update in a loop until no further
-            // rows are updated in order to iterate over the unconfirmed coins.
-            // Although we don't expect any unconfirmed transaction to have
-            // more than 25 in-mempool descendants including itself, there
-            // could be more descendants in the DB following a reorg and a
-            // rollback of the tip. The max number of iterations would be
-            // one per unconfirmed coin not from self plus one for all
-            // confirmed coins.
-            // In any case, the query only sets `is_from_self` to 1 for
-            // those coins with value 0 and so the number of rows affected
-            // by each iteration must become 0.
-            let max_iterations = {
-                let num_unconfirmed: u64 = db_tx.query_row(
-                    "SELECT COUNT(*) FROM coins
-                    WHERE blockheight IS NULL AND is_from_self = 0",
-                    [],
-                    |row| row.get(0),
-                )?;
-                // Add 1 for the confirmed coins, which will all
-                // be updated in the first iteration, and another 1
-                // as a final check there's nothing left to update.
-                num_unconfirmed.checked_add(2).expect("must fit")
-            };
-            log::debug!(
-                "Updating is_from_self in up to {} iterations..",
-                max_iterations
-            );
-            let mut updated = 0;
-            for i in 0..max_iterations {
-                updated = db_tx.execute(
-                    "
-                    UPDATE coins
-                    SET is_from_self = 1
-                    FROM transactions t
-                        INNER JOIN (
-                            SELECT
-                                spend_txid,
-                                SUM(
-                                    CASE
-                                        WHEN blockheight IS NOT NULL THEN 1
-                                        -- If the spending coin is unconfirmed, only count
-                                        -- it as an input coin if it is from self.
-                                        WHEN blockheight IS NULL AND is_from_self = 1 THEN 1
-                                        ELSE 0
-                                    END
-                                ) AS cnt
-                            FROM coins
-                            WHERE spend_txid IS NOT NULL
-                            -- We only need to consider spend transactions that are
-                            -- unconfirmed or confirmed after `prev_tip_height
-                            -- as only these transactions will affect the coins that
-                            -- we are updating.
-                            AND (spend_block_height IS NULL OR spend_block_height > ?1)
-                            GROUP BY spend_txid
-                        ) spends
-                        ON t.txid = spends.spend_txid AND t.num_inputs = spends.cnt
-                    WHERE coins.txid = t.txid
-                    AND (coins.blockheight IS NULL OR coins.blockheight > ?1)
-                    AND coins.is_from_self = 0
-                    ",
-                    [prev_tip_height],
-                )?;
-                if updated == 0 {
-                    log::debug!("Finished updating is_from_self in {} iterations.", i + 1);
-                    break;
-                }
-            }
-            assert_eq!(
-                updated, 0,
-                "no rows expected to be updated on final iteration while updating is_from_self",
-            );
-            Ok(())
+            update_coins_from_self(db_tx, prev_tip_height)
         })
     }
 
@@ -903,6 +1048,16 @@ impl SqliteConn {
 
     pub fn delete_spend(&mut self, txid: &bitcoin::Txid) {
         db_exec(&mut self.conn, |db_tx| {
+            db_tx.execute(
+                "DELETE FROM spend_signatures WHERE spend_id IN \
+                 (SELECT id FROM spend_transactions WHERE txid = ?1)",
+                rusqlite::params![txid[..].to_vec()],
+            )?;
+            db_tx.execute(
+                "DELETE FROM spend_reviews WHERE spend_id IN \
+                 (SELECT id FROM spend_transactions WHERE txid = ?1)",
+                rusqlite::params![txid[..].to_vec()],
+            )?;
             db_tx.execute(
                 "DELETE FROM spend_transactions WHERE txid = ?1",
                 rusqlite::params![txid[..].to_vec()],
@@ -912,6 +1067,421 @@ impl SqliteConn {
         .expect("Db must not fail");
     }
 
+    /// Record a Spend transaction we are about to broadcast.
+    pub fn record_broadcast(&mut self, tx: &bitcoin::Transaction) {
+        db_exec(&mut self.conn, |db_tx| {
+            db_tx.execute(
+                "INSERT INTO broadcast_journal (txid, tx, created_at) VALUES (?1, ?2, ?3) \
+                 ON CONFLICT DO NOTHING",
+                rusqlite::params![
+                    tx.compute_txid()[..].to_vec(),
+                    bitcoin::consensus::serialize(tx),
+                    curr_timestamp()
+                ],
+            )?;
+            Ok(())
+        })
+        .expect("Db must not fail");
+    }
+
+    /// The Spend transactions we broadcast but didn't see spending our coins yet, in the order
+    /// they were recorded.
+    pub fn broadcast_journal(&mut self) -> Vec<bitcoin::Transaction> {
+        db_query(
+            &mut self.conn,
+            "SELECT tx FROM broadcast_journal ORDER BY id",
+            rusqlite::params![],
+            |row| {
+                let tx: Vec<u8> = row.get(0)?;
+                Ok(encode::deserialize(&tx).expect("We only store valid transactions"))
+            },
+        )
+        .expect("Db must not fail")
+    }
+
+    pub fn remove_broadcast(&mut self, txid: &bitcoin::Txid) {
+        db_exec(&mut self.conn, |db_tx| {
+            db_tx.execute(
+                "DELETE FROM broadcast_journal WHERE txid = ?1",
+                rusqlite::params![txid[..].to_vec()],
+            )?;
+            Ok(())
+        })
+        .expect("Db must not fail");
+    }
+
+    /// Store a fully signed Spend transaction to be broadcast once its locktime is reached.
+    pub fn schedule_spend(&mut self, tx: &bitcoin::Transaction) {
+        db_exec(&mut self.conn, |db_tx| {
+            db_tx.execute(
+                "INSERT INTO scheduled_spends (txid, tx, scheduled_at) VALUES (?1, ?2, ?3) \
+                 ON CONFLICT (txid) DO UPDATE SET tx = excluded.tx",
+                rusqlite::params![
+                    tx.compute_txid()[..].to_vec(),
+                    bitcoin::consensus::serialize(tx),
+                    curr_timestamp()
+                ],
+            )?;
+            Ok(())
+        })
+        .expect("Db must not fail");
+    }
+
+    /// The scheduled Spend transactions, in the order they were scheduled.
+    pub fn scheduled_spends(&mut self) -> Vec<ScheduledSpend> {
+        db_query(
+            &mut self.conn,
+            "SELECT tx, scheduled_at FROM scheduled_spends ORDER BY id",
+            rusqlite::params![],
+            |row| {
+                let tx: Vec<u8> = row.get(0)?;
+                let scheduled_at: u32 = row.get(1)?;
+                Ok(ScheduledSpend {
+                    tx: encode::deserialize(&tx).expect("We only store valid transactions"),
+                    scheduled_at,
+                })
+            },
+        )
+        .expect("Db must not fail")
+    }
+
+    pub fn unschedule_spend(&mut self, txid: &bitcoin::Txid) {
+        db_exec(&mut self.conn, |db_tx| {
+            db_tx.execute(
+                "DELETE FROM scheduled_spends WHERE txid = ?1",
+                rusqlite::params![txid[..].to_vec()],
+            )?;
+            Ok(())
+        })
+        .expect("Db must not fail");
+    }
+
+    /// Our confirmed transactions whose inclusion in their block wasn't checked yet, or was
+    /// checked for a block at another height.
+    pub fn unverified_transactions(&mut self) -> Vec<(bitcoin::Txid, i32)> {
+        db_query(
+            &mut self.conn,
+            "SELECT confirmed.txid, confirmed.height FROM ( \
+                 SELECT txid, blockheight AS height FROM coins WHERE blockheight IS NOT NULL \
+                 UNION \
+                 SELECT spend_txid, spend_block_height FROM coins WHERE spend_block_height IS NOT NULL \
+             ) AS confirmed \
+             LEFT JOIN tx_verifications AS verif \
+             ON verif.txid = confirmed.txid AND verif.block_height = confirmed.height \
+             WHERE verif.id IS NULL",
+            rusqlite::params![],
+            |row| {
+                let txid: Vec<u8> = row.get(0)?;
+                let txid: bitcoin::Txid =
+                    encode::deserialize(&txid).expect("We only store valid txids");
+                let height: i32 = row.get(1)?;
+                Ok((txid, height))
+            },
+        )
+        .expect("Db must not fail")
+    }
+
+    pub fn record_tx_verification(
+        &mut self,
+        txid: &bitcoin::Txid,
+        height: i32,
+        error: Option<&str>,
+    ) {
+        db_exec(&mut self.conn, |db_tx| {
+            db_tx.execute(
+                "INSERT INTO tx_verifications (txid, block_height, error, checked_at) \
+                 VALUES (?1, ?2, ?3, ?4) \
+                 ON CONFLICT (txid) DO UPDATE SET block_height = excluded.block_height, \
+                 error = excluded.error, checked_at = excluded.checked_at",
+                rusqlite::params![txid[..].to_vec(), height, error, curr_timestamp()],
+            )?;
+            Ok(())
+        })
+        .expect("Db must not fail");
+    }
+
+    pub fn tx_verification(&mut self, txid: &bitcoin::Txid) -> Option<TxVerification> {
+        db_query(
+            &mut self.conn,
+            "SELECT block_height, error, checked_at FROM tx_verifications WHERE txid = ?1",
+            rusqlite::params![txid[..].to_vec()],
+            |row| {
+                Ok(TxVerification {
+                    block_height: row.get(0)?,
+                    error: row.get(1)?,
+                    checked_at: row.get(2)?,
+                })
+            },
+        )
+        .expect("Db must not fail")
+        .pop()
+    }
+
+    pub fn silent_payment_keys(&mut self) -> Option<SilentPaymentKeys> {
+        db_query(
+            &mut self.conn,
+            "SELECT fingerprint, scan_key, spend_key, scanned_height FROM silent_payment_keys",
+            rusqlite::params![],
+            |row| {
+                let fingerprint: Vec<u8> = row.get(0)?;
+                let scan_key: Vec<u8> = row.get(1)?;
+                let spend_key: Vec<u8> = row.get(2)?;
+                Ok(SilentPaymentKeys {
+                    fingerprint: bip32::Fingerprint::try_from(fingerprint.as_slice())
+                        .expect("We only store valid fingerprints"),
+                    scan_key: secp256k1::SecretKey::from_slice(&scan_key)
+                        .expect("We only store valid keys"),
+                    spend_key: secp256k1::PublicKey::from_slice(&spend_key)
+                        .expect("We only store valid keys"),
+                    scanned_height: row.get(3)?,
+                })
+            },
+        )
+        .expect("Db must not fail")
+        .pop()
+    }
+
+    pub fn store_silent_payment_keys(&mut self, keys: &SilentPaymentKeys) {
+        db_exec(&mut self.conn, |db_tx| {
+            db_tx.execute("DELETE FROM silent_payment_keys", rusqlite::params![])?;
+            db_tx.execute(
+                "INSERT INTO silent_payment_keys (fingerprint, scan_key, spend_key, scanned_height) \
+                 VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![
+                    keys.fingerprint.as_bytes().to_vec(),
+                    keys.scan_key.secret_bytes().to_vec(),
+                    keys.spend_key.serialize().to_vec(),
+                    keys.scanned_height
+                ],
+            )?;
+            Ok(())
+        })
+        .expect("Db must not fail");
+    }
+
+    pub fn silent_payments(&mut self) -> Vec<SilentPayment> {
+        db_query(
+            &mut self.conn,
+            "SELECT txid, vout, amount_sat, tweak, blockheight, blocktime, spend_txid, \
+             spend_block_height, spend_block_time FROM silent_payments ORDER BY blockheight, id",
+            rusqlite::params![],
+            |row| {
+                let txid: Vec<u8> = row.get(0)?;
+                let txid: bitcoin::Txid =
+                    encode::deserialize(&txid).expect("We only store valid txids");
+                let amount: u64 = row.get(2)?;
+                let tweak: Vec<u8> = row.get(3)?;
+                let tweak: [u8; 32] = tweak.try_into().expect("We only store valid tweaks");
+                let spend_txid: Option<Vec<u8>> = row.get(6)?;
+                let spend_height: Option<i32> = row.get(7)?;
+                let spend_time: Option<u32> = row.get(8)?;
+                Ok(SilentPayment {
+                    outpoint: bitcoin::OutPoint::new(txid, row.get(1)?),
+                    amount: bitcoin::Amount::from_sat(amount),
+                    tweak: secp256k1::Scalar::from_be_bytes(tweak)
+                        .expect("We only store valid tweaks"),
+                    block: BlockInfo {
+                        height: row.get(4)?,
+                        time: row.get(5)?,
+                    },
+                    spend_txid: spend_txid
+                        .map(|txid| encode::deserialize(&txid).expect("We only store valid txids")),
+                    spend_block: spend_height
+                        .zip(spend_time)
+                        .map(|(height, time)| BlockInfo { height, time }),
+                })
+            },
+        )
+        .expect("Db must not fail")
+    }
+
+    pub fn record_silent_payment_scan(
+        &mut self,
+        block: BlockInfo,
+        payments: &[SilentPayment],
+        spent: &[(bitcoin::OutPoint, bitcoin::Txid)],
+    ) {
+        db_exec(&mut self.conn, |db_tx| {
+            for payment in payments {
+                db_tx.execute(
+                    "INSERT INTO silent_payments (txid, vout, amount_sat, tweak, blockheight, \
+                     blocktime) VALUES (?1, ?2, ?3, ?4, ?5, ?6) ON CONFLICT DO NOTHING",
+                    rusqlite::params![
+                        payment.outpoint.txid[..].to_vec(),
+                        payment.outpoint.vout,
+                        payment.amount.to_sat(),
+                        payment.tweak.to_be_bytes().to_vec(),
+                        payment.block.height,
+                        payment.block.time,
+                    ],
+                )?;
+            }
+            for (outpoint, spend_txid) in spent {
+                db_tx.execute(
+                    "UPDATE silent_payments SET spend_txid = ?1, spend_block_height = ?2, \
+                     spend_block_time = ?3 WHERE txid = ?4 AND vout = ?5",
+                    rusqlite::params![
+                        spend_txid[..].to_vec(),
+                        block.height,
+                        block.time,
+                        outpoint.txid[..].to_vec(),
+                        outpoint.vout,
+                    ],
+                )?;
+            }
+            db_tx.execute(
+                "UPDATE silent_payment_keys SET scanned_height = ?1",
+                rusqlite::params![block.height],
+            )?;
+            Ok(())
+        })
+        .expect("Db must not fail");
+    }
+
+    pub fn spend_silent_payments(
+        &mut self,
+        outpoints: &[bitcoin::OutPoint],
+        spend_txid: Option<&bitcoin::Txid>,
+    ) {
+        db_exec(&mut self.conn, |db_tx| {
+            for outpoint in outpoints {
+                db_tx.execute(
+                    "UPDATE silent_payments SET spend_txid = ?1 \
+                     WHERE txid = ?2 AND vout = ?3 AND spend_block_height IS NULL",
+                    rusqlite::params![
+                        spend_txid.map(|txid| txid[..].to_vec()),
+                        outpoint.txid[..].to_vec(),
+                        outpoint.vout,
+                    ],
+                )?;
+            }
+            Ok(())
+        })
+        .expect("Db must not fail");
+    }
+
+    /// The items of the recovery readiness checklist marked as done, and when.
+    pub fn readiness_checks(&mut self) -> HashMap<ReadinessItem, u32> {
+        db_query(
+            &mut self.conn,
+            "SELECT item, completed_at FROM readiness_checks",
+            rusqlite::params![],
+            |row| {
+                let item: String = row.get(0)?;
+                let completed_at: u32 = row.get(1)?;
+                Ok((item, completed_at))
+            },
+        )
+        .expect("Db must not fail")
+        .into_iter()
+        .filter_map(|(item, completed_at)| {
+            ReadinessItem::from_str(&item)
+                .ok()
+                .map(|item| (item, completed_at))
+        })
+        .collect()
+    }
+
+    pub fn set_readiness_check(&mut self, item: ReadinessItem, done: bool) {
+        db_exec(&mut self.conn, |db_tx| {
+            if done {
+                db_tx.execute(
+                    "INSERT INTO readiness_checks (item, completed_at) VALUES (?1, ?2) \
+                     ON CONFLICT (item) DO UPDATE SET completed_at = excluded.completed_at",
+                    rusqlite::params![item.as_str(), curr_timestamp()],
+                )?;
+            } else {
+                db_tx.execute(
+                    "DELETE FROM readiness_checks WHERE item = ?1",
+                    rusqlite::params![item.as_str()],
+                )?;
+            }
+            Ok(())
+        })
+        .expect("Db must not fail");
+    }
+
+    pub fn store_event(&mut self, entry: &EventEntry) {
+        let ser = serde_json::to_string(&entry.event).expect("Serialization must not fail");
+        db_exec(&mut self.conn, |db_tx| {
+            db_tx.execute(
+                "INSERT INTO events (id, time, event) VALUES (?1, ?2, ?3)",
+                rusqlite::params![entry.id, entry.time, ser],
+            )?;
+            Ok(())
+        })
+        .expect("Db must not fail");
+    }
+
+    /// The latest stored events, up to this number, from the oldest to the newest. The events
+    /// which can't be parsed, for instance if stored by a newer version, are ignored.
+    pub fn events(&mut self, limit: usize) -> Vec<EventEntry> {
+        let mut entries: Vec<EventEntry> = db_query(
+            &mut self.conn,
+            "SELECT id, time, event FROM events ORDER BY id DESC LIMIT ?1",
+            rusqlite::params![limit as i64],
+            |row| {
+                let id: u64 = row.get(0)?;
+                let time: u32 = row.get(1)?;
+                let event: String = row.get(2)?;
+                Ok((id, time, event))
+            },
+        )
+        .expect("Db must not fail")
+        .into_iter()
+        .filter_map(|(id, time, event)| {
+            let event: Event = serde_json::from_str(&event).ok()?;
+            Some(EventEntry {
+                id,
+                time,
+                level: event.level(),
+                event,
+            })
+        })
+        .collect();
+        entries.reverse();
+        entries
+    }
+
+    /// Store a spend template, replacing the one with the same name if any.
+    pub fn store_spend_template(&mut self, template: &SpendTemplate) {
+        let ser = serde_json::to_string(template).expect("Serialization must not fail");
+        db_exec(&mut self.conn, |db_tx| {
+            db_tx.execute(
+                "INSERT INTO spend_templates (name, template) VALUES (?1, ?2) \
+                 ON CONFLICT (name) DO UPDATE SET template = excluded.template",
+                rusqlite::params![template.name, ser],
+            )?;
+            Ok(())
+        })
+        .expect("Db must not fail");
+    }
+
+    /// All the spend templates, ordered by name.
+    pub fn spend_templates(&mut self) -> Vec<SpendTemplate> {
+        db_query(
+            &mut self.conn,
+            "SELECT template FROM spend_templates ORDER BY name",
+            rusqlite::params![],
+            |row| {
+                let template: String = row.get(0)?;
+                Ok(serde_json::from_str(&template).expect("We only store valid templates"))
+            },
+        )
+        .expect("Db must not fail")
+    }
+
+    pub fn delete_spend_template(&mut self, name: &str) {
+        db_exec(&mut self.conn, |db_tx| {
+            db_tx.execute(
+                "DELETE FROM spend_templates WHERE name = ?1",
+                rusqlite::params![name],
+            )?;
+            Ok(())
+        })
+        .expect("Db must not fail");
+    }
+
     // TODO: mark coinbase deposits that were mature and became immature as such.
     /// Unconfirm all data that was marked as being confirmed *after* the given chain
     /// tip, and set it as our new best block seen.
@@ -919,6 +1489,7 @@ impl SqliteConn {
     /// This includes:
     /// - Coins (coinbase deposits that became immature isn't currently implemented)
     /// - Spending transactions confirmation
+    /// - Verifications of the inclusion of transactions in the blocks which were reorged out
     /// - Tip
     ///
     /// The `is_from_self` value for all unconfirmed coins following the rollback is
@@ -943,6 +1514,24 @@ impl SqliteConn {
                 "UPDATE coins SET is_from_self = 0 WHERE blockheight IS NULL",
                 rusqlite::params![],
             )?;
+            db_tx.execute(
+                "DELETE FROM tx_verifications WHERE block_height > ?1",
+                rusqlite::params![new_tip.height],
+            )?;
+            // The Silent Payments are only found in the blocks, which must be scanned again.
+            db_tx.execute(
+                "DELETE FROM silent_payments WHERE blockheight > ?1",
+                rusqlite::params![new_tip.height],
+            )?;
+            db_tx.execute(
+                "UPDATE silent_payments SET spend_block_height = NULL, spend_block_time = NULL \
+                 WHERE spend_block_height > ?1",
+                rusqlite::params![new_tip.height],
+            )?;
+            db_tx.execute(
+                "UPDATE silent_payment_keys SET scanned_height = ?1 WHERE scanned_height > ?1",
+                rusqlite::params![new_tip.height],
+            )?;
             db_tx.execute(
                 "UPDATE tip SET blockheight = (?1), blockhash = (?2)",
                 rusqlite::params![new_tip.height, new_tip.hash[..].to_vec()],
@@ -953,10 +1542,322 @@ impl SqliteConn {
     }
 }
 
+// The coins and transactions writes are performed within a database transaction given by the
+// caller, so that all the updates of a poll can be batched into a single one. The statements are
+// prepared once and reused for every row.
+
+fn insert_txs(db_tx: &rusqlite::Transaction, txs: &[bitcoin::Transaction]) -> rusqlite::Result<()> {
+    let mut stmt = db_tx.prepare_cached(
+        "INSERT INTO transactions (txid, tx, num_inputs, num_outputs, is_coinbase) \
+            VALUES (?1, ?2, ?3, ?4, ?5) \
+            ON CONFLICT DO NOTHING",
+    )?;
+    for tx in txs {
+        let txid = &tx.compute_txid()[..].to_vec();
+        let tx_ser = bitcoin::consensus::serialize(tx);
+        stmt.execute(rusqlite::params![
+            txid,
+            tx_ser,
+            tx.input.len(),
+            tx.output.len(),
+            tx.is_coinbase()
+        ])?;
+    }
+    Ok(())
+}
+
+fn insert_coins<'a>(
+    db_tx: &rusqlite::Transaction,
+    coins: impl IntoIterator<Item = &'a Coin>,
+) -> rusqlite::Result<()> {
+    let mut stmt = db_tx.prepare_cached(
+        "INSERT INTO coins (wallet_id, txid, vout, amount_sat, derivation_index, is_change, is_immature) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+    for coin in coins {
+        let deriv_index: u32 = coin.derivation_index.into();
+        stmt.execute(rusqlite::params![
+            WALLET_ID,
+            coin.outpoint.txid[..].to_vec(),
+            coin.outpoint.vout,
+            coin.amount.to_sat(),
+            deriv_index,
+            coin.is_change,
+            coin.is_immature,
+        ])?;
+    }
+    Ok(())
+}
+
+fn delete_coins(
+    db_tx: &rusqlite::Transaction,
+    outpoints: &[bitcoin::OutPoint],
+) -> rusqlite::Result<()> {
+    let mut stmt = db_tx.prepare_cached("DELETE FROM coins WHERE txid = ?1 AND vout = ?2")?;
+    for outpoint in outpoints {
+        stmt.execute(rusqlite::params![outpoint.txid[..].to_vec(), outpoint.vout])?;
+    }
+    Ok(())
+}
+
+fn confirm_coins<'a>(
+    db_tx: &rusqlite::Transaction,
+    outpoints: impl IntoIterator<Item = &'a (bitcoin::OutPoint, i32, u32)>,
+) -> rusqlite::Result<()> {
+    let mut stmt = db_tx.prepare_cached(
+        "UPDATE coins SET blockheight = ?1, blocktime = ?2, is_immature = 0 WHERE txid = ?3 AND vout = ?4",
+    )?;
+    for (outpoint, height, time) in outpoints {
+        stmt.execute(rusqlite::params![
+            height,
+            time,
+            outpoint.txid[..].to_vec(),
+            outpoint.vout
+        ])?;
+    }
+    Ok(())
+}
+
+fn spend_coins<'a>(
+    db_tx: &rusqlite::Transaction,
+    outpoints: impl IntoIterator<Item = &'a (bitcoin::OutPoint, bitcoin::Txid)>,
+) -> rusqlite::Result<()> {
+    let mut stmt =
+        db_tx.prepare_cached("UPDATE coins SET spend_txid = ?1 WHERE txid = ?2 AND vout = ?3")?;
+    for (outpoint, spend_txid) in outpoints {
+        stmt.execute(rusqlite::params![
+            spend_txid[..].to_vec(),
+            outpoint.txid[..].to_vec(),
+            outpoint.vout,
+        ])?;
+    }
+    Ok(())
+}
+
+fn unspend_coins<'a>(
+    db_tx: &rusqlite::Transaction,
+    outpoints: impl IntoIterator<Item = &'a bitcoin::OutPoint>,
+) -> rusqlite::Result<()> {
+    let mut stmt = db_tx.prepare_cached(
+        "UPDATE coins SET spend_txid = NULL, spend_block_height = NULL, spend_block_time = NULL WHERE txid = ?1 AND vout = ?2",
+    )?;
+    for outpoint in outpoints {
+        stmt.execute(rusqlite::params![outpoint.txid[..].to_vec(), outpoint.vout])?;
+    }
+    Ok(())
+}
+
+fn confirm_spend<'a>(
+    db_tx: &rusqlite::Transaction,
+    outpoints: impl IntoIterator<Item = &'a (bitcoin::OutPoint, bitcoin::Txid, i32, u32)>,
+) -> rusqlite::Result<()> {
+    let mut stmt = db_tx.prepare_cached(
+        "UPDATE coins SET spend_txid = ?1, spend_block_height = ?2, spend_block_time = ?3 WHERE txid = ?4 AND vout = ?5",
+    )?;
+    for (outpoint, spend_txid, height, time) in outpoints {
+        stmt.execute(rusqlite::params![
+            spend_txid[..].to_vec(),
+            height,
+            time,
+            outpoint.txid[..].to_vec(),
+            outpoint.vout,
+        ])?;
+    }
+    Ok(())
+}
+
+fn update_tip(db_tx: &rusqlite::Transaction, tip: &BlockChainTip) -> rusqlite::Result<()> {
+    db_tx
+        .execute(
+            "UPDATE tip SET blockheight = (?1), blockhash = (?2)",
+            rusqlite::params![tip.height, tip.hash[..].to_vec()],
+        )
+        .map(|_| ())
+}
+
+fn update_coins_from_self(
+    db_tx: &rusqlite::Transaction,
+    prev_tip_height: i32,
+) -> rusqlite::Result<()> {
+    // Given the requirement for unconfirmed coins that all ancestors
+    // be from self, we perform the update in a loop until no further
+    // rows are updated in order to iterate over the unconfirmed coins.
+    // Although we don't expect any unconfirmed transaction to have
+    // more than 25 in-mempool descendants including itself, there
+    // could be more descendants in the DB following a reorg and a
+    // rollback of the tip. The max number of iterations would be
+    // one per unconfirmed coin not from self plus one for all
+    // confirmed coins.
+    // In any case, the query only sets `is_from_self` to 1 for
+    // those coins with value 0 and so the number of rows affected
+    // by each iteration must become 0.
+    let max_iterations = {
+        let num_unconfirmed: u64 = db_tx.query_row(
+            "SELECT COUNT(*) FROM coins
+                WHERE blockheight IS NULL AND is_from_self = 0",
+            [],
+            |row| row.get(0),
+        )?;
+        // Add 1 for the confirmed coins, which will all
+        // be updated in the first iteration, and another 1
+        // as a final check there's nothing left to update.
+        num_unconfirmed.checked_add(2).expect("must fit")
+    };
+    log::debug!(
+        "Updating is_from_self in up to {} iterations..",
+        max_iterations
+    );
+    let mut updated = 0;
+    for i in 0..max_iterations {
+        updated = db_tx.execute(
+            "
+                UPDATE coins
+                SET is_from_self = 1
+                FROM transactions t
+                    INNER JOIN (
+                        SELECT
+                            spend_txid,
+                            SUM(
+                                CASE
+                                    WHEN blockheight IS NOT NULL THEN 1
+                                    -- If the spending coin is unconfirmed, only count
+                                    -- it as an input coin if it is from self.
+                                    WHEN blockheight IS NULL AND is_from_self = 1 THEN 1
+                                    ELSE 0
+                                END
+                            ) AS cnt
+                        FROM coins
+                        WHERE spend_txid IS NOT NULL
+                        -- We only need to consider spend transactions that are
+                        -- unconfirmed or confirmed after `prev_tip_height
+                        -- as only these transactions will affect the coins that
+                        -- we are updating.
+                        AND (spend_block_height IS NULL OR spend_block_height > ?1)
+                        GROUP BY spend_txid
+                    ) spends
+                    ON t.txid = spends.spend_txid AND t.num_inputs = spends.cnt
+                WHERE coins.txid = t.txid
+                AND (coins.blockheight IS NULL OR coins.blockheight > ?1)
+                AND coins.is_from_self = 0
+                ",
+            [prev_tip_height],
+        )?;
+        if updated == 0 {
+            log::debug!("Finished updating is_from_self in {} iterations.", i + 1);
+            break;
+        }
+    }
+    assert_eq!(
+        updated, 0,
+        "no rows expected to be updated on final iteration while updating is_from_self",
+    );
+    Ok(())
+}
+
+// A signature of an input of a Spend transaction, with the signer of its key if known.
+struct PsbtSignature {
+    input_index: usize,
+    kind: DbSignatureKind,
+    pubkey: Vec<u8>,
+    leaf_hash: Vec<u8>,
+    signature: Vec<u8>,
+    fingerprint: Option<bip32::Fingerprint>,
+}
+
+impl PsbtSignature {
+    // Whether this is a signature for the same input by the same key as this stored one.
+    fn is_same_key(&self, stored: &DbSpendSignature) -> bool {
+        self.input_index == stored.input_index
+            && self.kind == stored.kind
+            && self.pubkey == stored.pubkey
+            && self.leaf_hash == stored.leaf_hash
+    }
+}
+
+// Remove the signatures from this PSBT and return them.
+fn take_signatures(psbt: &mut Psbt) -> Vec<PsbtSignature> {
+    let mut sigs = Vec::new();
+    for (input_index, psbt_in) in psbt.inputs.iter_mut().enumerate() {
+        for (pubkey, sig) in std::mem::take(&mut psbt_in.partial_sigs) {
+            sigs.push(PsbtSignature {
+                input_index,
+                kind: DbSignatureKind::Ecdsa,
+                pubkey: pubkey.to_bytes(),
+                leaf_hash: Vec::new(),
+                signature: sig.to_vec(),
+                fingerprint: psbt_in
+                    .bip32_derivation
+                    .get(&pubkey.inner)
+                    .map(|(fg, _)| *fg),
+            });
+        }
+        for ((pubkey, leaf_hash), sig) in std::mem::take(&mut psbt_in.tap_script_sigs) {
+            sigs.push(PsbtSignature {
+                input_index,
+                kind: DbSignatureKind::TapScript,
+                pubkey: pubkey.serialize().to_vec(),
+                leaf_hash: leaf_hash.to_byte_array().to_vec(),
+                signature: sig.to_vec(),
+                fingerprint: psbt_in.tap_key_origins.get(&pubkey).map(|(_, (fg, _))| *fg),
+            });
+        }
+        if let Some(sig) = psbt_in.tap_key_sig.take() {
+            sigs.push(PsbtSignature {
+                input_index,
+                kind: DbSignatureKind::TapKey,
+                pubkey: Vec::new(),
+                leaf_hash: Vec::new(),
+                signature: sig.to_vec(),
+                fingerprint: psbt_in
+                    .tap_internal_key
+                    .and_then(|key| psbt_in.tap_key_origins.get(&key))
+                    .map(|(_, (fg, _))| *fg),
+            });
+        }
+    }
+    sigs
+}
+
+// Put back in its PSBT a signature stored apart.
+fn apply_signature(psbt: &mut Psbt, sig: &DbSpendSignature) {
+    let psbt_in = psbt
+        .inputs
+        .get_mut(sig.input_index)
+        .expect("We only store signatures of existing inputs");
+    match sig.kind {
+        DbSignatureKind::Ecdsa => {
+            psbt_in.partial_sigs.insert(
+                bitcoin::PublicKey::from_slice(&sig.pubkey).expect("We only store valid keys"),
+                bitcoin::ecdsa::Signature::from_slice(&sig.signature)
+                    .expect("We only store valid signatures"),
+            );
+        }
+        DbSignatureKind::TapScript => {
+            psbt_in.tap_script_sigs.insert(
+                (
+                    secp256k1::XOnlyPublicKey::from_slice(&sig.pubkey)
+                        .expect("We only store valid keys"),
+                    bitcoin::TapLeafHash::from_slice(&sig.leaf_hash)
+                        .expect("We only store valid leaf hashes"),
+                ),
+                bitcoin::taproot::Signature::from_slice(&sig.signature)
+                    .expect("We only store valid signatures"),
+            );
+        }
+        DbSignatureKind::TapKey => {
+            psbt_in.tap_key_sig = Some(
+                bitcoin::taproot::Signature::from_slice(&sig.signature)
+                    .expect("We only store valid signatures"),
+            );
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::database::{BlockInfo, DbBlockInfo};
+    use crate::events::EventLevel;
     use crate::testutils::*;
     use std::{
         collections::{HashMap, HashSet},
@@ -1343,6 +2244,40 @@ CREATE TABLE labels (
         fs::remove_dir_all(tmp_dir).unwrap();
     }
 
+    #[test]
+    fn db_in_memory() {
+        let secp = secp256k1::Secp256k1::verification_only();
+        let options = dummy_options();
+
+        let db = SqliteDb::new_in_memory(options.clone(), &secp).unwrap();
+        db.maybe_apply_migrations(&[]).unwrap();
+        db.sanity_check(bitcoin::Network::Bitcoin, &options.main_descriptor)
+            .unwrap();
+
+        // All the connections share the same database, but not with another one in memory.
+        let new_tip = BlockChainTip {
+            height: 746756,
+            hash: bitcoin::BlockHash::from_str(
+                "00000000000000000006d50e4c9fd269ddf690c94f422dff85e96f1a84b3a615",
+            )
+            .unwrap(),
+        };
+        db.connection().unwrap().update_tip(&new_tip);
+        let mut conn = db.clone().connection().unwrap();
+        assert_eq!(conn.db_tip().block_height, Some(new_tip.height));
+        let other_db = SqliteDb::new_in_memory(options, &secp).unwrap();
+        assert!(other_db
+            .connection()
+            .unwrap()
+            .db_tip()
+            .block_height
+            .is_none());
+
+        // It is kept until the last connection to it is closed.
+        drop(db);
+        assert_eq!(conn.db_tip().block_height, Some(new_tip.height));
+    }
+
     #[test]
     fn db_tip_update() {
         let (tmp_dir, options, _, db) = dummy_db();
@@ -1371,6 +2306,18 @@ CREATE TABLE labels (
         fs::remove_dir_all(tmp_dir).unwrap();
     }
 
+    #[test]
+    fn db_quick_check() {
+        let (tmp_dir, _, _, db) = dummy_db();
+
+        {
+            let mut conn = db.connection().unwrap();
+            assert!(conn.db_quick_check().is_empty());
+        }
+
+        fs::remove_dir_all(tmp_dir).unwrap();
+    }
+
     #[test]
     fn db_labels_update() {
         let (tmp_dir, _, _, db) = dummy_db();
@@ -1943,6 +2890,124 @@ CREATE TABLE labels (
         fs::remove_dir_all(tmp_dir).unwrap();
     }
 
+    #[test]
+    fn db_poll_updates() {
+        let (tmp_dir, _, _, db) = dummy_db();
+
+        {
+            let mut conn = db.connection().unwrap();
+
+            // The coins are looked up through indexes.
+            let indexes: Vec<String> = db_query(
+                &mut conn.conn,
+                "SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = 'coins' AND sql IS NOT NULL",
+                rusqlite::params![],
+                |row| row.get(0),
+            )
+            .unwrap();
+            for index in [
+                "coins_spend_txid",
+                "coins_blockheight",
+                "coins_spend_block_height",
+            ] {
+                assert!(indexes.iter().any(|i| i == index), "{}", index);
+            }
+
+            let txs: Vec<_> = (0..2)
+                .map(|i| bitcoin::Transaction {
+                    version: bitcoin::transaction::Version::TWO,
+                    lock_time: bitcoin::absolute::LockTime::from_height(i).unwrap(),
+                    input: vec![bitcoin::TxIn::default()],
+                    output: vec![bitcoin::TxOut::minimal_non_dust(ScriptBuf::default())],
+                })
+                .collect();
+            let coins: Vec<_> = (0..100)
+                .map(|vout| Coin {
+                    outpoint: bitcoin::OutPoint::new(txs[0].compute_txid(), vout),
+                    is_immature: false,
+                    block_info: None,
+                    amount: bitcoin::Amount::from_sat(10_000),
+                    derivation_index: bip32::ChildNumber::from_normal_idx(vout).unwrap(),
+                    is_change: false,
+                    spend_txid: None,
+                    spend_block: None,
+                    is_from_self: false,
+                })
+                .collect();
+            let tip = BlockChainTip {
+                height: 100,
+                hash: bitcoin::BlockHash::from_str(
+                    "00000000000000000006d50e4c9fd269ddf690c94f422dff85e96f1a84b3a615",
+                )
+                .unwrap(),
+            };
+
+            // Transactions and coins are stored together, and the coins can be updated in the
+            // same batch they are received in.
+            let updates = PollUpdates {
+                txs: txs.clone(),
+                received: coins.clone(),
+                confirmed: coins.iter().map(|c| (c.outpoint, 90, 1_000)).collect(),
+                spending: vec![(coins[0].outpoint, txs[1].compute_txid())],
+                ..Default::default()
+            };
+            conn.apply_poll_updates(&updates, 0, Some(&tip));
+            assert_eq!(conn.db_list_saved_txids().len(), 2);
+            let db_coins = conn.coins(&[CoinStatus::Confirmed], &[]);
+            assert_eq!(db_coins.len(), 99);
+            assert!(db_coins.iter().all(|c| c.block_info
+                == Some(DbBlockInfo {
+                    height: 90,
+                    time: 1_000
+                })));
+            assert_eq!(conn.coins(&[CoinStatus::Spending], &[]).len(), 1);
+            assert_eq!(conn.db_tip().block_height, Some(tip.height));
+            // The balances were updated along with the coins.
+            assert_eq!(
+                conn.db_balances(),
+                Balances {
+                    confirmed: bitcoin::Amount::from_sat(990_000),
+                    spending: bitcoin::Amount::from_sat(10_000),
+                    ..Default::default()
+                }
+            );
+
+            // Without a new tip, it isn't updated.
+            let updates = PollUpdates {
+                spent: vec![(coins[0].outpoint, txs[1].compute_txid(), 101, 1_001)],
+                expired: vec![coins[99].outpoint],
+                ..Default::default()
+            };
+            conn.apply_poll_updates(&updates, tip.height, None);
+            assert_eq!(conn.coins(&[CoinStatus::Spent], &[]).len(), 1);
+            assert_eq!(conn.coins(&[], &[]).len(), 99);
+            assert_eq!(conn.db_tip().block_height, Some(tip.height));
+            assert_eq!(
+                conn.db_balances(),
+                Balances {
+                    confirmed: bitcoin::Amount::from_sat(980_000),
+                    ..Default::default()
+                }
+            );
+
+            // Rolling back the tip unconfirms the coins and the spend, and the balances follow.
+            conn.rollback_tip(&BlockChainTip {
+                height: 80,
+                hash: tip.hash,
+            });
+            assert_eq!(
+                conn.db_balances(),
+                Balances {
+                    unconfirmed: bitcoin::Amount::from_sat(980_000),
+                    spending: bitcoin::Amount::from_sat(10_000),
+                    ..Default::default()
+                }
+            );
+        }
+
+        fs::remove_dir_all(tmp_dir).unwrap();
+    }
+
     #[test]
     fn sqlite_addresses_cache() {
         let (tmp_dir, options, secp, db) = dummy_db();
@@ -2253,12 +3318,21 @@ CREATE TABLE labels (
             conn.set_wallet_rescan_timestamp(dummy_timestamp);
             assert_eq!(conn.db_wallet().rescan_timestamp, Some(dummy_timestamp));
 
+            // Its progress can be recorded. It's reset if the rescan is started over.
+            assert!(conn.db_wallet().rescan_checkpoint.is_none());
+            conn.set_wallet_rescan_checkpoint(100);
+            assert_eq!(conn.db_wallet().rescan_checkpoint, Some(100));
+            conn.set_wallet_rescan_timestamp(dummy_timestamp);
+            assert!(conn.db_wallet().rescan_checkpoint.is_none());
+            conn.set_wallet_rescan_checkpoint(200);
+
             // Once it's done the rescan timestamp will be erased, and the
             // wallet timestamp will be set to the dummy timestamp since it's
             // lower.
             conn.complete_wallet_rescan();
             let db_wallet = conn.db_wallet();
             assert!(db_wallet.rescan_timestamp.is_none());
+            assert!(db_wallet.rescan_checkpoint.is_none());
             assert_eq!(db_wallet.timestamp, dummy_timestamp);
 
             // If we rescan from a later timestamp, we'll keep the existing
@@ -2274,6 +3348,436 @@ CREATE TABLE labels (
         fs::remove_dir_all(tmp_dir).unwrap();
     }
 
+    #[test]
+    fn db_readiness_checks() {
+        let (tmp_dir, _, _, db) = dummy_db();
+
+        {
+            let mut conn = db.connection().unwrap();
+            assert!(conn.readiness_checks().is_empty());
+
+            conn.set_readiness_check(ReadinessItem::DescriptorBackup, true);
+            conn.set_readiness_check(ReadinessItem::HeirsKit, true);
+            let checks = conn.readiness_checks();
+            assert_eq!(checks.len(), 2);
+            assert!(checks.contains_key(&ReadinessItem::DescriptorBackup));
+
+            // Marking an item as done again updates it, unmarking it removes it.
+            conn.set_readiness_check(ReadinessItem::HeirsKit, true);
+            conn.set_readiness_check(ReadinessItem::DescriptorBackup, false);
+            assert_eq!(
+                conn.readiness_checks().keys().collect::<Vec<_>>(),
+                vec![&ReadinessItem::HeirsKit]
+            );
+        }
+
+        fs::remove_dir_all(tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn db_events() {
+        let (tmp_dir, _, _, db) = dummy_db();
+
+        {
+            let mut conn = db.connection().unwrap();
+            assert!(conn.events(10).is_empty());
+
+            let entries: Vec<EventEntry> = (1..=3)
+                .map(|id| EventEntry {
+                    id,
+                    time: 1_700_000_000 + id as u32,
+                    level: EventLevel::Info,
+                    event: Event::SpendBroadcast {
+                        txid: bitcoin::Txid::from_slice(&[id as u8; 32]).unwrap(),
+                    },
+                })
+                .collect();
+            for entry in &entries {
+                conn.store_event(entry);
+            }
+            // Only the latest ones are returned, from the oldest to the newest.
+            assert_eq!(conn.events(10), entries);
+            assert_eq!(conn.events(2), entries[1..]);
+        }
+
+        fs::remove_dir_all(tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn db_spend_templates() {
+        let (tmp_dir, _, _, db) = dummy_db();
+
+        {
+            let mut conn = db.connection().unwrap();
+            assert!(conn.spend_templates().is_empty());
+
+            let mut rent = SpendTemplate {
+                name: "Rent".to_string(),
+                recipients: vec![crate::templates::TemplateRecipient {
+                    address: bitcoin::Address::from_str(
+                        "bc1qvrl2849aggm6qry9ea7xqp2kk39j8vaa8r3cwg",
+                    )
+                    .unwrap(),
+                    amount: None,
+                }],
+                feerate: 2,
+                coins: Vec::new(),
+                schedule: None,
+            };
+            let mut payroll = rent.clone();
+            payroll.name = "Payroll".to_string();
+            conn.store_spend_template(&rent);
+            conn.store_spend_template(&payroll);
+            assert_eq!(conn.spend_templates(), vec![payroll.clone(), rent.clone()]);
+
+            // Storing a template with an existing name replaces it.
+            rent.feerate = 10;
+            conn.store_spend_template(&rent);
+            assert_eq!(conn.spend_templates(), vec![payroll, rent.clone()]);
+
+            conn.delete_spend_template("Payroll");
+            assert_eq!(conn.spend_templates(), vec![rent]);
+        }
+
+        fs::remove_dir_all(tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn db_broadcast_journal() {
+        let (tmp_dir, _, _, db) = dummy_db();
+
+        {
+            let mut conn = db.connection().unwrap();
+            assert!(conn.broadcast_journal().is_empty());
+
+            let tx_a = bitcoin::Transaction {
+                version: bitcoin::transaction::Version::TWO,
+                lock_time: bitcoin::absolute::LockTime::ZERO,
+                input: vec![bitcoin::TxIn::default()],
+                output: vec![],
+            };
+            let mut tx_b = tx_a.clone();
+            tx_b.lock_time = bitcoin::absolute::LockTime::from_height(1).unwrap();
+
+            // Entries are returned in the order they were recorded, and recording the same
+            // transaction twice is a no-op.
+            conn.record_broadcast(&tx_b);
+            conn.record_broadcast(&tx_a);
+            conn.record_broadcast(&tx_b);
+            assert_eq!(conn.broadcast_journal(), vec![tx_b.clone(), tx_a.clone()]);
+
+            conn.remove_broadcast(&tx_b.compute_txid());
+            assert_eq!(conn.broadcast_journal(), vec![tx_a]);
+        }
+
+        fs::remove_dir_all(tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn db_scheduled_spends() {
+        let (tmp_dir, _, _, db) = dummy_db();
+
+        {
+            let mut conn = db.connection().unwrap();
+            assert!(conn.scheduled_spends().is_empty());
+
+            let tx_a = bitcoin::Transaction {
+                version: bitcoin::transaction::Version::TWO,
+                lock_time: bitcoin::absolute::LockTime::from_height(900_000).unwrap(),
+                input: vec![bitcoin::TxIn::default()],
+                output: vec![],
+            };
+            let mut tx_b = tx_a.clone();
+            tx_b.lock_time = bitcoin::absolute::LockTime::from_height(800_000).unwrap();
+
+            conn.schedule_spend(&tx_a);
+            conn.schedule_spend(&tx_b);
+            let scheduled = conn.scheduled_spends();
+            assert_eq!(
+                scheduled.iter().map(|s| &s.tx).collect::<Vec<_>>(),
+                vec![&tx_a, &tx_b]
+            );
+            assert!(scheduled.iter().all(|s| s.scheduled_at > 0));
+
+            // Scheduling it again updates its witnesses.
+            let mut signed_a = tx_a.clone();
+            signed_a.input[0].witness.push([1; 64]);
+            conn.schedule_spend(&signed_a);
+            let scheduled = conn.scheduled_spends();
+            assert_eq!(scheduled.len(), 2);
+            assert_eq!(scheduled[0].tx, signed_a);
+
+            conn.unschedule_spend(&tx_a.compute_txid());
+            assert_eq!(
+                conn.scheduled_spends()
+                    .into_iter()
+                    .map(|s| s.tx)
+                    .collect::<Vec<_>>(),
+                vec![tx_b]
+            );
+        }
+
+        fs::remove_dir_all(tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn db_spend_signatures() {
+        let (tmp_dir, _, _, db) = dummy_db();
+
+        {
+            let mut conn = db.connection().unwrap();
+            let unsigned_psbt = psbt_from_str("cHNidP8BAHECAAAAAUSHuliRtuCX1S6JxRuDRqDCKkWfKmWL5sV9ukZ/wzvfAAAAAAD9////AogTAAAAAAAAFgAUIxe7UY6LJ6y5mFBoWTOoVispDmdwFwAAAAAAABYAFKqO83TK+t/KdpAt21z2HGC7/Z2FAAAAAAABASsQJwAAAAAAACIAIIIySQjGCTeyx/rKUQx8qobjhJeNCiVCliBJPdyRX6XKAQVBIQI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDKxzZHapFNYASzIYkEdH9bJz6nnqUG3uBB8kiK1asmgiBgI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDAz1rML9AAAAAG8AAAAiBgMLcbOxsfLe6+3r1UcjQo77HY0As8OKE4l37yj0/qhIyQyKZPKpAAAAAG8AAAAAAAA=");
+            let signed_psbt = psbt_from_str("cHNidP8BAHECAAAAAUSHuliRtuCX1S6JxRuDRqDCKkWfKmWL5sV9ukZ/wzvfAAAAAAD9////AogTAAAAAAAAFgAUIxe7UY6LJ6y5mFBoWTOoVispDmdwFwAAAAAAABYAFKqO83TK+t/KdpAt21z2HGC7/Z2FAAAAAAABASsQJwAAAAAAACIAIIIySQjGCTeyx/rKUQx8qobjhJeNCiVCliBJPdyRX6XKIgICNnKlqXPVAFtoGbdlpCo74vCqDArotNKUZS+sBt9rigxIMEUCIQCYZusUL8bdi2PnjWao4bIDDgMQ9Dj2Lcup3/VmkGbYJAIgX/wF5HsqugC5JzvU2cGOmUWtHr2Pg0N4912qogYgDH4BAQVBIQI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDKxzZHapFNYASzIYkEdH9bJz6nnqUG3uBB8kiK1asmgiBgI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDAz1rML9AAAAAG8AAAAiBgMLcbOxsfLe6+3r1UcjQo77HY0As8OKE4l37yj0/qhIyQyKZPKpAAAAAG8AAAAAAAA=");
+            let txid = signed_psbt.unsigned_tx.compute_txid();
+            assert!(conn.db_spend_signers(&txid).is_empty());
+
+            // The PSBT is stored without its signature, but it's returned with it.
+            conn.store_spend(&signed_psbt);
+            let stored_psbt = db_query(
+                &mut conn.conn,
+                "SELECT psbt FROM spend_transactions",
+                rusqlite::params![],
+                |row| {
+                    let psbt: Vec<u8> = row.get(0)?;
+                    Ok(Psbt::deserialize(&psbt).unwrap())
+                },
+            )
+            .unwrap();
+            assert_eq!(stored_psbt, vec![unsigned_psbt.clone()]);
+            assert_eq!(conn.db_spend(&txid).unwrap().psbt, signed_psbt);
+            assert_eq!(
+                conn.list_spend()
+                    .into_iter()
+                    .map(|s| s.psbt)
+                    .collect::<Vec<_>>(),
+                vec![signed_psbt.clone()]
+            );
+
+            // The signature is attributed to its signer, and storing it again doesn't change the
+            // time at which it was first stored.
+            let signers = conn.db_spend_signers(&txid);
+            assert_eq!(
+                signers.iter().map(|(fg, _)| *fg).collect::<Vec<_>>(),
+                vec![bip32::Fingerprint::from_str("f5acc2fd").unwrap()]
+            );
+            conn.store_spend(&signed_psbt);
+            assert_eq!(conn.db_spend_signers(&txid), signers);
+
+            // Storing the PSBT without the signature removes it.
+            conn.store_spend(&unsigned_psbt);
+            assert_eq!(conn.db_spend(&txid).unwrap().psbt, unsigned_psbt);
+            assert!(conn.db_spend_signers(&txid).is_empty());
+
+            // Deleting the Spend deletes its signatures.
+            conn.store_spend(&signed_psbt);
+            conn.delete_spend(&txid);
+            assert!(conn.db_spend(&txid).is_none());
+            let sigs_count = db_query(
+                &mut conn.conn,
+                "SELECT COUNT(*) FROM spend_signatures",
+                rusqlite::params![],
+                |row| row.get::<_, i64>(0),
+            )
+            .unwrap();
+            assert_eq!(sigs_count, vec![0]);
+        }
+
+        fs::remove_dir_all(tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn db_spend_reviews() {
+        let (tmp_dir, _, _, db) = dummy_db();
+
+        {
+            let mut conn = db.connection().unwrap();
+            let psbt = psbt_from_str("cHNidP8BAHECAAAAAUSHuliRtuCX1S6JxRuDRqDCKkWfKmWL5sV9ukZ/wzvfAAAAAAD9////AogTAAAAAAAAFgAUIxe7UY6LJ6y5mFBoWTOoVispDmdwFwAAAAAAABYAFKqO83TK+t/KdpAt21z2HGC7/Z2FAAAAAAABASsQJwAAAAAAACIAIIIySQjGCTeyx/rKUQx8qobjhJeNCiVCliBJPdyRX6XKAQVBIQI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDKxzZHapFNYASzIYkEdH9bJz6nnqUG3uBB8kiK1asmgiBgI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDAz1rML9AAAAAG8AAAAiBgMLcbOxsfLe6+3r1UcjQo77HY0As8OKE4l37yj0/qhIyQyKZPKpAAAAAG8AAAAAAAA=");
+            let txid = psbt.unsigned_tx.compute_txid();
+            let review = |fg: &str, decision, comment: Option<&str>, reviewed_at| SpendReview {
+                signer: bip32::Fingerprint::from_str(fg).unwrap(),
+                decision,
+                comment: comment.map(String::from),
+                reviewed_at,
+            };
+
+            // A review of an unknown Spend isn't stored.
+            let approval = review("f5acc2fd", ReviewDecision::Approved, None, 1);
+            conn.store_spend_review(&txid, &approval);
+            conn.store_spend(&psbt);
+            assert!(conn.db_spend_reviews(&txid).is_empty());
+
+            // The reviews are returned in the order they were made, and only the latest of each
+            // signer is kept.
+            conn.store_spend_review(&txid, &approval);
+            let rejection = review("8a64f2a9", ReviewDecision::Rejected, Some("Too much"), 2);
+            conn.store_spend_review(&txid, &rejection);
+            assert_eq!(
+                conn.db_spend_reviews(&txid),
+                vec![approval.clone(), rejection]
+            );
+            let changes = review(
+                "8a64f2a9",
+                ReviewDecision::ChangesRequested,
+                Some("Lower the fee"),
+                3,
+            );
+            conn.store_spend_review(&txid, &changes);
+            assert_eq!(conn.db_spend_reviews(&txid), vec![approval, changes]);
+
+            // Deleting the Spend deletes its reviews.
+            conn.delete_spend(&txid);
+            let reviews_count = db_query(
+                &mut conn.conn,
+                "SELECT COUNT(*) FROM spend_reviews",
+                rusqlite::params![],
+                |row| row.get::<_, i64>(0),
+            )
+            .unwrap();
+            assert_eq!(reviews_count, vec![0]);
+        }
+
+        fs::remove_dir_all(tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn db_tx_verifications() {
+        let (tmp_dir, _, _, db) = dummy_db();
+
+        {
+            let mut conn = db.connection().unwrap();
+
+            let txs: Vec<_> = (0..2)
+                .map(|i| bitcoin::Transaction {
+                    version: bitcoin::transaction::Version::TWO,
+                    lock_time: bitcoin::absolute::LockTime::from_height(i).unwrap(),
+                    input: vec![bitcoin::TxIn::default()],
+                    output: vec![bitcoin::TxOut::minimal_non_dust(ScriptBuf::default())],
+                })
+                .collect();
+            conn.new_txs(&txs);
+            let (txid_a, txid_b) = (txs[0].compute_txid(), txs[1].compute_txid());
+            let coin = Coin {
+                outpoint: bitcoin::OutPoint::new(txid_a, 0),
+                is_immature: false,
+                block_info: None,
+                amount: bitcoin::Amount::from_sat(98765),
+                derivation_index: bip32::ChildNumber::from_normal_idx(10).unwrap(),
+                is_change: false,
+                spend_txid: None,
+                spend_block: None,
+                is_from_self: false,
+            };
+            conn.new_unspent_coins(&[coin]);
+
+            // Only the confirmed transactions need to be verified.
+            assert!(conn.unverified_transactions().is_empty());
+            conn.confirm_coins(&[(coin.outpoint, 100, 1_700_000_000)]);
+            conn.spend_coins(&[(coin.outpoint, txid_b)]);
+            assert_eq!(conn.unverified_transactions(), vec![(txid_a, 100)]);
+            conn.confirm_spend(&[(coin.outpoint, txid_b, 101, 1_700_000_600)]);
+            let mut unverified = conn.unverified_transactions();
+            unverified.sort_by_key(|(_, height)| *height);
+            assert_eq!(unverified, vec![(txid_a, 100), (txid_b, 101)]);
+
+            conn.record_tx_verification(&txid_a, 100, None);
+            conn.record_tx_verification(&txid_b, 101, Some("Invalid merkle proof."));
+            assert!(conn.unverified_transactions().is_empty());
+            assert_eq!(conn.tx_verification(&txid_a).unwrap().error, None);
+            assert_eq!(
+                conn.tx_verification(&txid_b).unwrap().error.as_deref(),
+                Some("Invalid merkle proof.")
+            );
+
+            // After a reorg the transactions confirmed in the new blocks must be checked again.
+            conn.rollback_tip(&BlockChainTip {
+                hash: bitcoin::BlockHash::from_str(
+                    "000000000000000000016440c591da27679abfa53ef44d45b016640dbd04e126",
+                )
+                .unwrap(),
+                height: 100,
+            });
+            assert!(conn.tx_verification(&txid_b).is_none());
+            conn.confirm_spend(&[(coin.outpoint, txid_b, 102, 1_700_001_200)]);
+            assert_eq!(conn.unverified_transactions(), vec![(txid_b, 102)]);
+            assert_eq!(conn.tx_verification(&txid_a).unwrap().block_height, 100);
+        }
+
+        fs::remove_dir_all(tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn db_silent_payments() {
+        let (tmp_dir, _, _, db) = dummy_db();
+
+        {
+            let mut conn = db.connection().unwrap();
+            assert!(conn.silent_payment_keys().is_none());
+            assert!(conn.silent_payments().is_empty());
+
+            let secp = secp256k1::Secp256k1::signing_only();
+            let scan_key = secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+            let keys = SilentPaymentKeys {
+                fingerprint: bip32::Fingerprint::from([0xaa, 0xbb, 0xcc, 0xdd]),
+                scan_key,
+                spend_key: secp256k1::SecretKey::from_slice(&[2; 32])
+                    .unwrap()
+                    .public_key(&secp),
+                scanned_height: 100,
+            };
+            conn.store_silent_payment_keys(&keys);
+            assert_eq!(conn.silent_payment_keys(), Some(keys));
+
+            let payment = |i: u8, height: i32| SilentPayment {
+                outpoint: bitcoin::OutPoint::new(bitcoin::Txid::from_slice(&[i; 32]).unwrap(), 1),
+                amount: bitcoin::Amount::from_sat(10_000 * i as u64),
+                tweak: secp256k1::Scalar::from_be_bytes([i; 32]).unwrap(),
+                block: BlockInfo {
+                    height,
+                    time: 1_700_000_000 + height as u32,
+                },
+                spend_txid: None,
+                spend_block: None,
+            };
+            let block = |height: i32| BlockInfo {
+                height,
+                time: 1_700_000_000 + height as u32,
+            };
+            let (payment_a, payment_b) = (payment(1, 101), payment(2, 102));
+            conn.record_silent_payment_scan(block(101), &[payment_a], &[]);
+            conn.record_silent_payment_scan(block(102), &[payment_b], &[]);
+            assert_eq!(conn.silent_payment_keys().unwrap().scanned_height, 102);
+            assert_eq!(conn.silent_payments(), vec![payment_a, payment_b]);
+
+            // The first one is being spent, then the spend confirms.
+            let spend_txid = bitcoin::Txid::from_slice(&[3; 32]).unwrap();
+            conn.spend_silent_payments(&[payment_a.outpoint], Some(&spend_txid));
+            assert_eq!(conn.silent_payments()[0].spend_txid, Some(spend_txid));
+            conn.spend_silent_payments(&[payment_a.outpoint], None);
+            assert_eq!(conn.silent_payments()[0].spend_txid, None);
+            conn.record_silent_payment_scan(block(103), &[], &[(payment_a.outpoint, spend_txid)]);
+            assert_eq!(conn.silent_payments()[0].spend_block, Some(block(103)));
+            // A confirmed spend isn't reverted.
+            conn.spend_silent_payments(&[payment_a.outpoint], None);
+            assert_eq!(conn.silent_payments()[0].spend_txid, Some(spend_txid));
+
+            // After a reorg the new blocks must be scanned again.
+            conn.rollback_tip(&BlockChainTip {
+                hash: bitcoin::BlockHash::from_str(
+                    "000000000000000000016440c591da27679abfa53ef44d45b016640dbd04e126",
+                )
+                .unwrap(),
+                height: 101,
+            });
+            assert_eq!(conn.silent_payment_keys().unwrap().scanned_height, 101);
+            let payments = conn.silent_payments();
+            assert_eq!(payments.len(), 1);
+            assert_eq!(payments[0].spend_txid, Some(spend_txid));
+            assert_eq!(payments[0].spend_block, None);
+        }
+
+        fs::remove_dir_all(tmp_dir).unwrap();
+    }
+
     #[test]
     fn sqlite_list_txids() {
         let (tmp_dir, _, _, db) = dummy_db();
@@ -2393,6 +3897,25 @@ CREATE TABLE labels (
             // Ordered by desc block time.
             let expected_txids = [6, 5].map(|i| txs.get(i).unwrap().compute_txid());
             assert_eq!(&db_txids[..], &expected_txids,);
+
+            // The unconfirmed ones first, then by decreasing height.
+            let txid = |i: usize| txs[i].compute_txid();
+            assert_eq!(
+                conn.db_recent_txids(10),
+                vec![
+                    (txid(0), None),
+                    (txid(3), Some(101_199)),
+                    (txid(6), Some(101_105)),
+                    (txid(5), Some(101_102)),
+                    (txid(4), Some(101_100)),
+                    (txid(2), Some(101_099)),
+                    (txid(1), Some(101_095)),
+                ]
+            );
+            assert_eq!(
+                conn.db_recent_txids(2),
+                vec![(txid(0), None), (txid(3), Some(101_199))]
+            );
         }
 
         fs::remove_dir_all(tmp_dir).unwrap();
@@ -3067,7 +4590,7 @@ CREATE TABLE labels (
     }
 
     #[test]
-    fn v0_to_v8_migration() {
+    fn v0_to_v20_migration() {
         let secp = secp256k1::Secp256k1::verification_only();
 
         // Create a database with version 0, using the old schema.
@@ -3173,7 +4696,7 @@ CREATE TABLE labels (
         {
             let mut conn = db.connection().unwrap();
             let version = conn.db_version();
-            assert_eq!(version, 8);
+            assert_eq!(version, 20);
         }
         // We should now be able to insert another PSBT, to query both, and the first PSBT must
         // have no associated timestamp.
@@ -3248,7 +4771,7 @@ CREATE TABLE labels (
     }
 
     #[test]
-    fn v3_to_v8_migration() {
+    fn v3_to_v20_migration() {
         let secp = secp256k1::Secp256k1::verification_only();
 
         // Create a database with version 3, using the old schema.
@@ -3400,10 +4923,10 @@ CREATE TABLE labels (
 
             // Migrate the DB.
             maybe_apply_migration(&db_path, &bitcoin_txs).unwrap();
-            assert_eq!(conn.db_version(), 8);
+            assert_eq!(conn.db_version(), 20);
             // Migrating twice will be a no-op. No need to pass `bitcoin_txs` second time.
             maybe_apply_migration(&db_path, &[]).unwrap();
-            assert!(conn.db_version() == 8);
+            assert!(conn.db_version() == 15);
 
             // Compare the `DbCoin`s with the expected values.
             let coins_post = conn.coins(&[], &[]);
@@ -28,6 +28,8 @@ CREATE TABLE tip (
  * information related to our descriptor(s) that occurred after this date.
  * The optional 'rescan_timestamp' field is a the timestamp we need to rescan the chain
  * for events related to our descriptor(s) from.
+ * The optional 'rescan_checkpoint' field is the height up to which the ongoing rescan is known to
+ * have processed the chain. An interrupted rescan is resumed from there.
  */
 CREATE TABLE wallets (
     id INTEGER PRIMARY KEY NOT NULL,
@@ -36,7 +38,8 @@ CREATE TABLE wallets (
     deposit_derivation_index INTEGER NOT NULL,
     change_derivation_index INTEGER NOT NULL,
     rescan_timestamp INTEGER,
-    last_poll_timestamp INTEGER
+    last_poll_timestamp INTEGER,
+    rescan_checkpoint INTEGER
 );
 
 /* Our (U)TxOs.
@@ -79,6 +82,63 @@ CREATE TABLE coins (
         ON DELETE RESTRICT
 );
 
+/* Coins are looked up by spending transaction and by confirmation height when updating them
+ * after a poll or rolling back the tip. */
+CREATE INDEX coins_spend_txid ON coins (spend_txid);
+CREATE INDEX coins_blockheight ON coins (blockheight);
+CREATE INDEX coins_spend_block_height ON coins (spend_block_height);
+
+/* The total value of our coins by status: 'unconfirmed', 'unconfirmed_from_self', 'confirmed',
+ * 'immature', 'spending' and 'spent'. It is maintained by the triggers below as coins are
+ * inserted, updated and removed, so it never needs to be computed from all the coins.
+ */
+CREATE TABLE balances (
+    status TEXT PRIMARY KEY NOT NULL,
+    amount_sat INTEGER NOT NULL
+);
+CREATE TRIGGER balances_coin_insert AFTER INSERT ON coins BEGIN
+    INSERT INTO balances (status, amount_sat) VALUES (CASE
+        WHEN NEW.spend_block_height IS NOT NULL THEN 'spent'
+        WHEN NEW.spend_txid IS NOT NULL THEN 'spending'
+        WHEN NEW.is_immature = 1 THEN 'immature'
+        WHEN NEW.blockheight IS NOT NULL THEN 'confirmed'
+        WHEN NEW.is_from_self = 1 THEN 'unconfirmed_from_self'
+        ELSE 'unconfirmed'
+    END, NEW.amount_sat)
+        ON CONFLICT (status) DO UPDATE SET amount_sat = amount_sat + excluded.amount_sat;
+END;
+CREATE TRIGGER balances_coin_delete AFTER DELETE ON coins BEGIN
+    UPDATE balances SET amount_sat = amount_sat - OLD.amount_sat WHERE status = CASE
+        WHEN OLD.spend_block_height IS NOT NULL THEN 'spent'
+        WHEN OLD.spend_txid IS NOT NULL THEN 'spending'
+        WHEN OLD.is_immature = 1 THEN 'immature'
+        WHEN OLD.blockheight IS NOT NULL THEN 'confirmed'
+        WHEN OLD.is_from_self = 1 THEN 'unconfirmed_from_self'
+        ELSE 'unconfirmed'
+    END;
+END;
+CREATE TRIGGER balances_coin_update
+AFTER UPDATE OF amount_sat, blockheight, spend_txid, spend_block_height, is_immature, is_from_self ON coins
+BEGIN
+    UPDATE balances SET amount_sat = amount_sat - OLD.amount_sat WHERE status = CASE
+        WHEN OLD.spend_block_height IS NOT NULL THEN 'spent'
+        WHEN OLD.spend_txid IS NOT NULL THEN 'spending'
+        WHEN OLD.is_immature = 1 THEN 'immature'
+        WHEN OLD.blockheight IS NOT NULL THEN 'confirmed'
+        WHEN OLD.is_from_self = 1 THEN 'unconfirmed_from_self'
+        ELSE 'unconfirmed'
+    END;
+    INSERT INTO balances (status, amount_sat) VALUES (CASE
+        WHEN NEW.spend_block_height IS NOT NULL THEN 'spent'
+        WHEN NEW.spend_txid IS NOT NULL THEN 'spending'
+        WHEN NEW.is_immature = 1 THEN 'immature'
+        WHEN NEW.blockheight IS NOT NULL THEN 'confirmed'
+        WHEN NEW.is_from_self = 1 THEN 'unconfirmed_from_self'
+        ELSE 'unconfirmed'
+    END, NEW.amount_sat)
+        ON CONFLICT (status) DO UPDATE SET amount_sat = amount_sat + excluded.amount_sat;
+END;
+
 /* A mapping from descriptor address to derivation index. Necessary until
  * we can get the derivation index from the parent descriptor from bitcoind.
  */
@@ -106,6 +166,120 @@ CREATE TABLE spend_transactions (
     updated_at INTEGER
 );
 
+/* The signatures of the Spend transactions, stored apart from their PSBT so the PSBT isn't stored
+ * again in full at each update. The kind of a signature is ECDSA (0), Taproot key path (1) or
+ * Taproot script path (2). The public key and leaf hash are empty when they don't apply. Each
+ * signature is attributed to the signer of its key, if known.
+ */
+CREATE TABLE spend_signatures (
+    id INTEGER PRIMARY KEY NOT NULL,
+    spend_id INTEGER NOT NULL,
+    input_index INTEGER NOT NULL,
+    kind INTEGER NOT NULL CHECK (kind IN (0,1,2)),
+    pubkey BLOB NOT NULL,
+    leaf_hash BLOB NOT NULL,
+    signature BLOB NOT NULL,
+    fingerprint BLOB,
+    created_at INTEGER NOT NULL,
+    UNIQUE (spend_id, input_index, kind, pubkey, leaf_hash),
+    FOREIGN KEY (spend_id) REFERENCES spend_transactions (id)
+);
+
+/* The latest review of the Spend transactions by each signer: its decision and an optional
+ * comment.
+ */
+CREATE TABLE spend_reviews (
+    id INTEGER PRIMARY KEY NOT NULL,
+    spend_id INTEGER NOT NULL,
+    fingerprint BLOB NOT NULL,
+    decision TEXT NOT NULL CHECK (decision IN ('approved','changes_requested','rejected')),
+    comment TEXT,
+    reviewed_at INTEGER NOT NULL,
+    UNIQUE (spend_id, fingerprint),
+    FOREIGN KEY (spend_id) REFERENCES spend_transactions (id)
+);
+
+/* Spend transactions we are broadcasting, until we see them spending our coins. Allows to
+ * broadcast them again if we stopped before the Bitcoin backend accepted them.
+ */
+CREATE TABLE broadcast_journal (
+    id INTEGER PRIMARY KEY NOT NULL,
+    txid BLOB UNIQUE NOT NULL,
+    tx BLOB NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+/* Fully signed Spend transactions with a future locktime, to be broadcast once it's reached. */
+CREATE TABLE scheduled_spends (
+    id INTEGER PRIMARY KEY NOT NULL,
+    txid BLOB UNIQUE NOT NULL,
+    tx BLOB NOT NULL,
+    scheduled_at INTEGER NOT NULL
+);
+
+/* The result of checking the inclusion of our confirmed transactions in their block, for the
+ * Bitcoin backends which don't validate the chain. The error is NULL if the proof is valid.
+ */
+CREATE TABLE tx_verifications (
+    id INTEGER PRIMARY KEY NOT NULL,
+    txid BLOB UNIQUE NOT NULL,
+    block_height INTEGER NOT NULL,
+    error TEXT,
+    checked_at INTEGER NOT NULL
+);
+
+/* The keys to scan the chain for Silent Payments (BIP352) to the wallet, derived from the hot signer
+ * with this master fingerprint: the secret scan key and the public spend key. The chain was scanned
+ * up to 'scanned_height'. There is at most one row.
+ */
+CREATE TABLE silent_payment_keys (
+    id INTEGER PRIMARY KEY NOT NULL,
+    fingerprint BLOB NOT NULL,
+    scan_key BLOB NOT NULL,
+    spend_key BLOB NOT NULL,
+    scanned_height INTEGER NOT NULL
+);
+
+/* The confirmed outputs paying to the Silent Payment address of the wallet, along with the tweak of
+ * the spend key to spend them. They aren't coins of the descriptor.
+ */
+CREATE TABLE silent_payments (
+    id INTEGER PRIMARY KEY NOT NULL,
+    txid BLOB NOT NULL,
+    vout INTEGER NOT NULL,
+    amount_sat INTEGER NOT NULL,
+    tweak BLOB NOT NULL,
+    blockheight INTEGER NOT NULL,
+    blocktime INTEGER NOT NULL,
+    spend_txid BLOB,
+    spend_block_height INTEGER,
+    spend_block_time INTEGER,
+    UNIQUE (txid, vout)
+);
+
+/* The items of the recovery readiness checklist the user marked as done, and when. */
+CREATE TABLE readiness_checks (
+    id INTEGER PRIMARY KEY NOT NULL,
+    item TEXT UNIQUE NOT NULL,
+    completed_at INTEGER NOT NULL
+);
+
+/* Named templates from which to create Spend transactions, serialized as JSON. */
+CREATE TABLE spend_templates (
+    id INTEGER PRIMARY KEY NOT NULL,
+    name TEXT UNIQUE NOT NULL,
+    template TEXT NOT NULL
+);
+
+/* The events which happened to the wallet, serialized as JSON. Kept across restarts for the
+ * users to catch up with what happened while they were away.
+ */
+CREATE TABLE events (
+    id INTEGER PRIMARY KEY NOT NULL,
+    time INTEGER NOT NULL,
+    event TEXT NOT NULL
+);
+
 /* Labels applied on addresses (0), outpoints (1), txids (2) */
 CREATE TABLE labels (
     id INTEGER PRIMARY KEY NOT NULL,
@@ -156,6 +330,7 @@ pub struct DbWallet {
     pub change_derivation_index: bip32::ChildNumber,
     pub rescan_timestamp: Option<u32>,
     pub last_poll_timestamp: Option<u32>,
+    pub rescan_checkpoint: Option<i32>,
 }
 
 impl TryFrom<&rusqlite::Row<'_>> for DbWallet {
@@ -176,6 +351,7 @@ impl TryFrom<&rusqlite::Row<'_>> for DbWallet {
 
         let rescan_timestamp = row.get(5)?;
         let last_poll_timestamp = row.get(6)?;
+        let rescan_checkpoint = row.get(7)?;
 
         Ok(DbWallet {
             id,
@@ -185,6 +361,7 @@ impl TryFrom<&rusqlite::Row<'_>> for DbWallet {
             change_derivation_index,
             rescan_timestamp,
             last_poll_timestamp,
+            rescan_checkpoint,
         })
     }
 }
@@ -335,6 +512,71 @@ impl TryFrom<&rusqlite::Row<'_>> for DbSpendTransaction {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(i64)]
+pub enum DbSignatureKind {
+    Ecdsa = 0,
+    TapKey = 1,
+    TapScript = 2,
+}
+
+impl From<i64> for DbSignatureKind {
+    fn from(value: i64) -> Self {
+        if value == 0 {
+            Self::Ecdsa
+        } else if value == 1 {
+            Self::TapKey
+        } else {
+            assert_eq!(value, 2);
+            Self::TapScript
+        }
+    }
+}
+
+/// A row in the "spend_signatures" table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbSpendSignature {
+    pub spend_id: i64,
+    pub input_index: usize,
+    pub kind: DbSignatureKind,
+    pub pubkey: Vec<u8>,
+    pub leaf_hash: Vec<u8>,
+    pub signature: Vec<u8>,
+    pub fingerprint: Option<bip32::Fingerprint>,
+    pub created_at: u32,
+}
+
+impl TryFrom<&rusqlite::Row<'_>> for DbSpendSignature {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &rusqlite::Row) -> Result<Self, Self::Error> {
+        let spend_id: i64 = row.get(1)?;
+        let input_index: i64 = row.get(2)?;
+        let input_index = input_index as usize;
+        let kind: i64 = row.get(3)?;
+        let kind = kind.into();
+        let pubkey = row.get(4)?;
+        let leaf_hash = row.get(5)?;
+        let signature = row.get(6)?;
+        let fingerprint: Option<Vec<u8>> = row.get(7)?;
+        let fingerprint = fingerprint.map(|fg| {
+            bip32::Fingerprint::try_from(&fg[..]).expect("We only store valid fingerprints")
+        });
+        let created_at = row.get(8)?;
+
+        Ok(DbSpendSignature {
+            spend_id,
+            input_index,
+            kind,
+            pubkey,
+            leaf_hash,
+            signature,
+            fingerprint,
+            created_at,
+        })
+    }
+}
+
 /// A row in the "labels" table
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DbLabel {
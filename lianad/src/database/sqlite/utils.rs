@@ -1,4 +1,7 @@
-use crate::database::sqlite::{FreshDbOptions, SqliteDbError, DB_VERSION};
+use crate::{
+    database::sqlite::{FreshDbOptions, SqliteDbError, DB_VERSION},
+    spans::{Span, SpanKind},
+};
 
 use std::{convert::TryInto, fs, path, time};
 
@@ -11,6 +14,7 @@ pub fn db_exec<F>(conn: &mut rusqlite::Connection, modifications: F) -> Result<(
 where
     F: FnOnce(&rusqlite::Transaction) -> rusqlite::Result<()>,
 {
+    let _span = Span::enter(SpanKind::DbQuery, "write transaction");
     let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
     modifications(&tx)?;
     tx.commit()
@@ -28,6 +32,7 @@ where
     P::Item: rusqlite::ToSql,
     F: FnMut(&rusqlite::Row<'_>) -> rusqlite::Result<T>,
 {
+    let _span = Span::enter(SpanKind::DbQuery, stmt_str);
     tx.prepare(stmt_str)?
         .query_map(params, f)?
         .collect::<rusqlite::Result<Vec<T>>>()
@@ -45,6 +50,7 @@ where
     P::Item: rusqlite::ToSql,
     F: FnMut(&rusqlite::Row<'_>) -> rusqlite::Result<T>,
 {
+    let _span = Span::enter(SpanKind::DbQuery, stmt_str);
     conn.prepare(stmt_str)?
         .query_map(params, f)?
         .collect::<rusqlite::Result<Vec<T>>>()
@@ -62,6 +68,7 @@ where
     P::Item: rusqlite::ToSql,
     F: FnMut(&rusqlite::Row<'_>) -> rusqlite::Result<T>,
 {
+    let _span = Span::enter(SpanKind::DbQuery, stmt_str);
     conn.prepare(stmt_str)?.query_row(params, f)
 }
 
@@ -104,7 +111,16 @@ pub fn create_fresh_db(
     secp: &secp256k1::Secp256k1<secp256k1::VerifyOnly>,
 ) -> Result<(), SqliteDbError> {
     create_db_file(db_path)?;
+    let mut conn = rusqlite::Connection::open(db_path)?;
+    fill_fresh_db(&mut conn, options, secp)
+}
 
+/// Populate an empty database through this connection with the given schema.
+pub fn fill_fresh_db(
+    conn: &mut rusqlite::Connection,
+    options: FreshDbOptions,
+    secp: &secp256k1::Secp256k1<secp256k1::VerifyOnly>,
+) -> Result<(), SqliteDbError> {
     let timestamp = curr_timestamp();
 
     // Fill the initial addresses. On a fresh database, the deposit_derivation_index is
@@ -127,8 +143,7 @@ pub fn create_fresh_db(
         );
     }
 
-    let mut conn = rusqlite::Connection::open(db_path)?;
-    db_exec(&mut conn, |tx| {
+    db_exec(conn, |tx| {
         tx.execute_batch(options.schema)?;
         tx.execute(
             "INSERT INTO version (version) VALUES (?1)",
@@ -482,6 +497,303 @@ fn migrate_v7_to_v8(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError
     Ok(())
 }
 
+fn migrate_v8_to_v9(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError> {
+    db_exec(conn, |db_tx| {
+        db_tx.execute_batch(
+            "
+            CREATE TABLE broadcast_journal (
+                id INTEGER PRIMARY KEY NOT NULL,
+                txid BLOB UNIQUE NOT NULL,
+                tx BLOB NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+
+            UPDATE version SET version = 9;
+            ",
+        )?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn migrate_v9_to_v10(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError> {
+    db_exec(conn, |db_tx| {
+        db_tx.execute_batch(
+            "
+            CREATE TABLE readiness_checks (
+                id INTEGER PRIMARY KEY NOT NULL,
+                item TEXT UNIQUE NOT NULL,
+                completed_at INTEGER NOT NULL
+            );
+
+            UPDATE version SET version = 10;
+            ",
+        )?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn migrate_v10_to_v11(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError> {
+    db_exec(conn, |db_tx| {
+        db_tx.execute_batch(
+            "
+            CREATE TABLE spend_templates (
+                id INTEGER PRIMARY KEY NOT NULL,
+                name TEXT UNIQUE NOT NULL,
+                template TEXT NOT NULL
+            );
+
+            UPDATE version SET version = 11;
+            ",
+        )?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn migrate_v11_to_v12(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError> {
+    db_exec(conn, |db_tx| {
+        db_tx.execute_batch(
+            "
+            CREATE TABLE scheduled_spends (
+                id INTEGER PRIMARY KEY NOT NULL,
+                txid BLOB UNIQUE NOT NULL,
+                tx BLOB NOT NULL,
+                scheduled_at INTEGER NOT NULL
+            );
+
+            UPDATE version SET version = 12;
+            ",
+        )?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn migrate_v12_to_v13(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError> {
+    db_exec(conn, |db_tx| {
+        db_tx.execute_batch(
+            "
+            CREATE TABLE tx_verifications (
+                id INTEGER PRIMARY KEY NOT NULL,
+                txid BLOB UNIQUE NOT NULL,
+                block_height INTEGER NOT NULL,
+                error TEXT,
+                checked_at INTEGER NOT NULL
+            );
+
+            UPDATE version SET version = 13;
+            ",
+        )?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn migrate_v13_to_v14(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError> {
+    db_exec(conn, |db_tx| {
+        db_tx.execute_batch(
+            "
+            CREATE INDEX coins_spend_txid ON coins (spend_txid);
+            CREATE INDEX coins_blockheight ON coins (blockheight);
+            CREATE INDEX coins_spend_block_height ON coins (spend_block_height);
+
+            UPDATE version SET version = 14;
+            ",
+        )?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn migrate_v14_to_v15(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError> {
+    db_exec(conn, |db_tx| {
+        db_tx.execute_batch(
+            "
+            CREATE TABLE balances (
+                status TEXT PRIMARY KEY NOT NULL,
+                amount_sat INTEGER NOT NULL
+            );
+            INSERT INTO balances (status, amount_sat)
+                SELECT CASE
+                    WHEN spend_block_height IS NOT NULL THEN 'spent'
+                    WHEN spend_txid IS NOT NULL THEN 'spending'
+                    WHEN is_immature = 1 THEN 'immature'
+                    WHEN blockheight IS NOT NULL THEN 'confirmed'
+                    WHEN is_from_self = 1 THEN 'unconfirmed_from_self'
+                    ELSE 'unconfirmed'
+                END, SUM(amount_sat)
+                FROM coins GROUP BY 1;
+
+            CREATE TRIGGER balances_coin_insert AFTER INSERT ON coins BEGIN
+                INSERT INTO balances (status, amount_sat) VALUES (CASE
+                    WHEN NEW.spend_block_height IS NOT NULL THEN 'spent'
+                    WHEN NEW.spend_txid IS NOT NULL THEN 'spending'
+                    WHEN NEW.is_immature = 1 THEN 'immature'
+                    WHEN NEW.blockheight IS NOT NULL THEN 'confirmed'
+                    WHEN NEW.is_from_self = 1 THEN 'unconfirmed_from_self'
+                    ELSE 'unconfirmed'
+                END, NEW.amount_sat)
+                    ON CONFLICT (status) DO UPDATE SET amount_sat = amount_sat + excluded.amount_sat;
+            END;
+            CREATE TRIGGER balances_coin_delete AFTER DELETE ON coins BEGIN
+                UPDATE balances SET amount_sat = amount_sat - OLD.amount_sat WHERE status = CASE
+                    WHEN OLD.spend_block_height IS NOT NULL THEN 'spent'
+                    WHEN OLD.spend_txid IS NOT NULL THEN 'spending'
+                    WHEN OLD.is_immature = 1 THEN 'immature'
+                    WHEN OLD.blockheight IS NOT NULL THEN 'confirmed'
+                    WHEN OLD.is_from_self = 1 THEN 'unconfirmed_from_self'
+                    ELSE 'unconfirmed'
+                END;
+            END;
+            CREATE TRIGGER balances_coin_update
+            AFTER UPDATE OF amount_sat, blockheight, spend_txid, spend_block_height, is_immature, is_from_self ON coins
+            BEGIN
+                UPDATE balances SET amount_sat = amount_sat - OLD.amount_sat WHERE status = CASE
+                    WHEN OLD.spend_block_height IS NOT NULL THEN 'spent'
+                    WHEN OLD.spend_txid IS NOT NULL THEN 'spending'
+                    WHEN OLD.is_immature = 1 THEN 'immature'
+                    WHEN OLD.blockheight IS NOT NULL THEN 'confirmed'
+                    WHEN OLD.is_from_self = 1 THEN 'unconfirmed_from_self'
+                    ELSE 'unconfirmed'
+                END;
+                INSERT INTO balances (status, amount_sat) VALUES (CASE
+                    WHEN NEW.spend_block_height IS NOT NULL THEN 'spent'
+                    WHEN NEW.spend_txid IS NOT NULL THEN 'spending'
+                    WHEN NEW.is_immature = 1 THEN 'immature'
+                    WHEN NEW.blockheight IS NOT NULL THEN 'confirmed'
+                    WHEN NEW.is_from_self = 1 THEN 'unconfirmed_from_self'
+                    ELSE 'unconfirmed'
+                END, NEW.amount_sat)
+                    ON CONFLICT (status) DO UPDATE SET amount_sat = amount_sat + excluded.amount_sat;
+            END;
+
+            UPDATE version SET version = 15;
+            ",
+        )?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+// The PSBTs of the existing Spend transactions are left as they are, with their signatures. These
+// are only moved to their own table the next time the PSBT is stored.
+fn migrate_v15_to_v16(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError> {
+    db_exec(conn, |db_tx| {
+        db_tx.execute_batch(
+            "
+            CREATE TABLE spend_signatures (
+                id INTEGER PRIMARY KEY NOT NULL,
+                spend_id INTEGER NOT NULL,
+                input_index INTEGER NOT NULL,
+                kind INTEGER NOT NULL CHECK (kind IN (0,1,2)),
+                pubkey BLOB NOT NULL,
+                leaf_hash BLOB NOT NULL,
+                signature BLOB NOT NULL,
+                fingerprint BLOB,
+                created_at INTEGER NOT NULL,
+                UNIQUE (spend_id, input_index, kind, pubkey, leaf_hash),
+                FOREIGN KEY (spend_id) REFERENCES spend_transactions (id)
+            );
+
+            UPDATE version SET version = 16;
+            ",
+        )?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn migrate_v16_to_v17(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError> {
+    db_exec(conn, |db_tx| {
+        db_tx.execute_batch(
+            "
+            ALTER TABLE wallets ADD COLUMN rescan_checkpoint INTEGER;
+
+            UPDATE version SET version = 17;
+            ",
+        )?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn migrate_v17_to_v18(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError> {
+    db_exec(conn, |db_tx| {
+        db_tx.execute_batch(
+            "
+            CREATE TABLE spend_reviews (
+                id INTEGER PRIMARY KEY NOT NULL,
+                spend_id INTEGER NOT NULL,
+                fingerprint BLOB NOT NULL,
+                decision TEXT NOT NULL CHECK (decision IN ('approved','changes_requested','rejected')),
+                comment TEXT,
+                reviewed_at INTEGER NOT NULL,
+                UNIQUE (spend_id, fingerprint),
+                FOREIGN KEY (spend_id) REFERENCES spend_transactions (id)
+            );
+
+            UPDATE version SET version = 18;
+            ",
+        )?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn migrate_v18_to_v19(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError> {
+    db_exec(conn, |db_tx| {
+        db_tx.execute_batch(
+            "
+            CREATE TABLE silent_payment_keys (
+                id INTEGER PRIMARY KEY NOT NULL,
+                fingerprint BLOB NOT NULL,
+                scan_key BLOB NOT NULL,
+                spend_key BLOB NOT NULL,
+                scanned_height INTEGER NOT NULL
+            );
+
+            CREATE TABLE silent_payments (
+                id INTEGER PRIMARY KEY NOT NULL,
+                txid BLOB NOT NULL,
+                vout INTEGER NOT NULL,
+                amount_sat INTEGER NOT NULL,
+                tweak BLOB NOT NULL,
+                blockheight INTEGER NOT NULL,
+                blocktime INTEGER NOT NULL,
+                spend_txid BLOB,
+                spend_block_height INTEGER,
+                spend_block_time INTEGER,
+                UNIQUE (txid, vout)
+            );
+
+            UPDATE version SET version = 19;
+            ",
+        )?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn migrate_v19_to_v20(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError> {
+    db_exec(conn, |db_tx| {
+        db_tx.execute_batch(
+            "
+            CREATE TABLE events (
+                id INTEGER PRIMARY KEY NOT NULL,
+                time INTEGER NOT NULL,
+                event TEXT NOT NULL
+            );
+
+            UPDATE version SET version = 20;
+            ",
+        )?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
 /// Check the database version and if necessary apply the migrations to upgrade it to the current
 /// one. The `bitcoin_txs` parameter is here for the migration from versions 4 and earlier, which
 /// did not store the Bitcoin transactions in database, to versions 5 and later, which do. For a
@@ -545,6 +857,66 @@ pub fn maybe_apply_migration(
                 migrate_v7_to_v8(&mut conn)?;
                 log::warn!("Migration from database version 7 to version 8 successful.");
             }
+            8 => {
+                log::warn!("Upgrading database from version 8 to version 9.");
+                migrate_v8_to_v9(&mut conn)?;
+                log::warn!("Migration from database version 8 to version 9 successful.");
+            }
+            9 => {
+                log::warn!("Upgrading database from version 9 to version 10.");
+                migrate_v9_to_v10(&mut conn)?;
+                log::warn!("Migration from database version 9 to version 10 successful.");
+            }
+            10 => {
+                log::warn!("Upgrading database from version 10 to version 11.");
+                migrate_v10_to_v11(&mut conn)?;
+                log::warn!("Migration from database version 10 to version 11 successful.");
+            }
+            11 => {
+                log::warn!("Upgrading database from version 11 to version 12.");
+                migrate_v11_to_v12(&mut conn)?;
+                log::warn!("Migration from database version 11 to version 12 successful.");
+            }
+            12 => {
+                log::warn!("Upgrading database from version 12 to version 13.");
+                migrate_v12_to_v13(&mut conn)?;
+                log::warn!("Migration from database version 12 to version 13 successful.");
+            }
+            13 => {
+                log::warn!("Upgrading database from version 13 to version 14.");
+                migrate_v13_to_v14(&mut conn)?;
+                log::warn!("Migration from database version 13 to version 14 successful.");
+            }
+            14 => {
+                log::warn!("Upgrading database from version 14 to version 15.");
+                migrate_v14_to_v15(&mut conn)?;
+                log::warn!("Migration from database version 14 to version 15 successful.");
+            }
+            15 => {
+                log::warn!("Upgrading database from version 15 to version 16.");
+                migrate_v15_to_v16(&mut conn)?;
+                log::warn!("Migration from database version 15 to version 16 successful.");
+            }
+            16 => {
+                log::warn!("Upgrading database from version 16 to version 17.");
+                migrate_v16_to_v17(&mut conn)?;
+                log::warn!("Migration from database version 16 to version 17 successful.");
+            }
+            17 => {
+                log::warn!("Upgrading database from version 17 to version 18.");
+                migrate_v17_to_v18(&mut conn)?;
+                log::warn!("Migration from database version 17 to version 18 successful.");
+            }
+            18 => {
+                log::warn!("Upgrading database from version 18 to version 19.");
+                migrate_v18_to_v19(&mut conn)?;
+                log::warn!("Migration from database version 18 to version 19 successful.");
+            }
+            19 => {
+                log::warn!("Upgrading database from version 19 to version 20.");
+                migrate_v19_to_v20(&mut conn)?;
+                log::warn!("Migration from database version 19 to version 20 successful.");
+            }
             _ => return Err(SqliteDbError::UnsupportedVersion(version)),
         }
     }
@@ -0,0 +1,315 @@
+//! Events of interest happening to the wallet, which the user should be alerted about.
+//!
+//! The latest events are kept in memory to be queried through the `listevents` command. They are
+//! stored in database to survive restarts, and may also be posted to a webhook as they happen.
+
+use crate::{database::DatabaseInterface, reviews::ReviewDecision};
+use liana::descriptors::SpendingPath;
+
+use std::{collections::VecDeque, sync, thread, time};
+
+use miniscript::bitcoin;
+use serde::{Deserialize, Serialize};
+
+/// How many events we keep in memory.
+const MAX_EVENTS: usize = 1_000;
+
+/// Timeout for posting an event to the webhook.
+const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+
+/// How urgent it is for the user to be made aware of an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventLevel {
+    Info,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// A new coin was received on one of our deposit addresses.
+    CoinReceived {
+        outpoint: bitcoin::OutPoint,
+        #[serde(with = "bitcoin::amount::serde::as_sat")]
+        amount: bitcoin::Amount,
+    },
+    /// A transaction spending our coins was confirmed.
+    SpendConfirmed { txid: bitcoin::Txid, height: i32 },
+    /// The first recovery path of these coins is soon available.
+    RecoveryPathSoonAvailable {
+        timelock: u16,
+        coins: Vec<bitcoin::OutPoint>,
+        /// The number of blocks before the first of these coins is spendable by the recovery
+        /// path.
+        blocks_remaining: u32,
+    },
+    /// We couldn't get updates from the Bitcoin backend.
+    BackendUnreachable { error: String },
+    /// We could get updates from the Bitcoin backend again.
+    BackendReachable,
+    /// Coins were spent by a transaction which was not created by this wallet.
+    UnauthorizedSpend {
+        txid: bitcoin::Txid,
+        coins: Vec<bitcoin::OutPoint>,
+        /// The spending path whose keys signed the transaction, if it could be determined.
+        spending_path: Option<SpendingPath>,
+    },
+    /// The locktime of a scheduled Spend transaction was reached and it was broadcast.
+    ScheduledSpendBroadcast { txid: bitcoin::Txid },
+    /// Coins spent by a scheduled Spend transaction were spent by another transaction. It can't
+    /// be broadcast anymore and was removed from the scheduled ones.
+    ScheduledSpendConflicted {
+        txid: bitcoin::Txid,
+        coins: Vec<bitcoin::OutPoint>,
+    },
+    /// The Bitcoin backend and the cross-checking backend disagree about the chain or our coins
+    /// beyond the configured tolerance. One of them may be lying or eclipsed.
+    BackendsDisagree { disagreements: Vec<String> },
+    /// The Bitcoin backend and the cross-checking backend agree again.
+    BackendsAgree,
+    /// A Spend transaction was updated with the signatures of these signers.
+    SpendSigned {
+        txid: bitcoin::Txid,
+        signers: Vec<bitcoin::bip32::Fingerprint>,
+    },
+    /// A signer reviewed a Spend transaction.
+    SpendReviewed {
+        txid: bitcoin::Txid,
+        signer: bitcoin::bip32::Fingerprint,
+        decision: ReviewDecision,
+    },
+    /// A new Spend transaction draft was saved.
+    SpendSaved { txid: bitcoin::Txid },
+    /// A Spend transaction was broadcast.
+    SpendBroadcast { txid: bitcoin::Txid },
+    /// The rescan of the chain from this timestamp completed.
+    RescanCompleted { timestamp: u32 },
+    /// A setting of the wallet was changed.
+    SettingsChanged { change: String },
+    /// The chain tip didn't advance for this many minutes. The backend may be stuck or isolated
+    /// from the network.
+    StaleTip { height: i32, minutes: u64 },
+    /// The chain tip advanced again after being stale.
+    TipAdvanced { height: i32 },
+    /// The system clock is this many seconds ahead (or behind, if negative) of the time of the
+    /// latest block.
+    ClockSkewed { skew_secs: i64 },
+    /// The system clock agrees with the time of the latest block again.
+    ClockSynced,
+}
+
+impl Event {
+    pub fn level(&self) -> EventLevel {
+        match self {
+            Self::CoinReceived { .. }
+            | Self::SpendConfirmed { .. }
+            | Self::BackendReachable
+            | Self::ScheduledSpendBroadcast { .. }
+            | Self::BackendsAgree
+            | Self::SpendSigned { .. }
+            | Self::SpendSaved { .. }
+            | Self::SpendBroadcast { .. }
+            | Self::RescanCompleted { .. }
+            | Self::SettingsChanged { .. }
+            | Self::TipAdvanced { .. }
+            | Self::ClockSynced
+            | Self::SpendReviewed {
+                decision: ReviewDecision::Approved,
+                ..
+            } => EventLevel::Info,
+            Self::RecoveryPathSoonAvailable { .. }
+            | Self::BackendUnreachable { .. }
+            | Self::ScheduledSpendConflicted { .. }
+            | Self::StaleTip { .. }
+            | Self::ClockSkewed { .. }
+            | Self::SpendReviewed { .. } => EventLevel::Warning,
+            // Someone using a recovery path without a transaction of ours is exactly what the
+            // owners of the wallet must be made aware of.
+            Self::UnauthorizedSpend {
+                spending_path: Some(SpendingPath::Primary),
+                ..
+            } => EventLevel::Warning,
+            Self::UnauthorizedSpend { .. } | Self::BackendsDisagree { .. } => EventLevel::Critical,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventEntry {
+    /// Identifier of the event, increasing with each event.
+    pub id: u64,
+    /// Timestamp at which the event happened.
+    pub time: u32,
+    pub level: EventLevel,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// The latest events, shared between the poller which records them and the commands.
+pub struct Events {
+    entries: VecDeque<EventEntry>,
+    next_id: u64,
+    webhook_url: Option<String>,
+    // Where the events are stored, if they are.
+    db: Option<sync::Arc<sync::Mutex<dyn DatabaseInterface>>>,
+}
+
+impl Events {
+    pub fn new(webhook_url: Option<String>) -> Events {
+        Events {
+            entries: VecDeque::new(),
+            next_id: 1,
+            webhook_url,
+            db: None,
+        }
+    }
+
+    /// Load the latest events from the database, and store the new ones there.
+    pub fn stored(mut self, db: sync::Arc<sync::Mutex<dyn DatabaseInterface>>) -> Events {
+        self.entries = db.connection().events(MAX_EVENTS).into();
+        if let Some(last) = self.entries.back() {
+            self.next_id = last.id + 1;
+        }
+        self.db = Some(db);
+        self
+    }
+
+    /// Record a new event. It's logged and posted to the webhook, if any.
+    pub fn push(&mut self, event: Event) {
+        let time = time::SystemTime::now()
+            .duration_since(time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as u32)
+            .unwrap_or(0);
+        let entry = EventEntry {
+            id: self.next_id,
+            time,
+            level: event.level(),
+            event,
+        };
+        self.next_id += 1;
+
+        match entry.level {
+            EventLevel::Info => log::info!("New event: {:?}", entry.event),
+            EventLevel::Warning => log::warn!("New event: {:?}", entry.event),
+            EventLevel::Critical => log::error!("New critical event: {:?}", entry.event),
+        }
+        if let Some(url) = self.webhook_url.clone() {
+            post_to_webhook(url, &entry);
+        }
+        if let Some(db) = &self.db {
+            db.connection().store_event(&entry);
+        }
+
+        if self.entries.len() >= MAX_EVENTS {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Get the events we have in memory, optionally only those more recent than the given
+    /// identifier.
+    pub fn list(&self, since_id: Option<u64>) -> Vec<EventEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| since_id.map(|id| entry.id > id).unwrap_or(true))
+            .cloned()
+            .collect()
+    }
+}
+
+// Post the event as JSON to the webhook, in a separate thread to not block the caller on the
+// network.
+fn post_to_webhook(url: String, entry: &EventEntry) {
+    let body = serde_json::to_string(entry).expect("Serialization can't fail");
+    let res = thread::Builder::new()
+        .name("Event webhook".to_string())
+        .spawn(move || {
+            let res = minreq::post(&url)
+                .with_header("Content-Type", "application/json")
+                .with_body(body)
+                .with_timeout(WEBHOOK_TIMEOUT_SECS)
+                .send();
+            match res {
+                Ok(resp) if (200..300).contains(&resp.status_code) => {}
+                Ok(resp) => log::error!(
+                    "Webhook responded with status {} when posting event.",
+                    resp.status_code
+                ),
+                Err(e) => log::error!("Error posting event to webhook: {}", e),
+            }
+        });
+    if let Err(e) = res {
+        log::error!("Error spawning thread to post event to webhook: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutils::DummyDatabase;
+    use miniscript::bitcoin::hashes::Hash;
+
+    #[test]
+    fn events_list() {
+        let mut events = Events::new(None);
+        for i in 0..MAX_EVENTS + 2 {
+            events.push(Event::UnauthorizedSpend {
+                txid: bitcoin::Txid::all_zeros(),
+                coins: Vec::new(),
+                spending_path: if i % 2 == 0 {
+                    Some(SpendingPath::Primary)
+                } else {
+                    Some(SpendingPath::Recovery(52_560))
+                },
+            });
+        }
+        // Only the latest events are kept.
+        let all = events.list(None);
+        assert_eq!(all.len(), MAX_EVENTS);
+        assert_eq!(all[0].id, 3);
+        assert_eq!(all[0].level, EventLevel::Warning);
+        assert_eq!(all[1].level, EventLevel::Critical);
+        let latest = events.list(Some(MAX_EVENTS as u64));
+        assert_eq!(latest.len(), 2);
+        assert_eq!(latest[0].id, MAX_EVENTS as u64 + 1);
+
+        let json = serde_json::to_value(&latest[1]).unwrap();
+        assert_eq!(json["type"], "unauthorized_spend");
+        assert_eq!(json["level"], "critical");
+        assert_eq!(
+            json["spending_path"],
+            serde_json::json!({ "recovery": 52_560 })
+        );
+    }
+
+    #[test]
+    fn events_stored() {
+        let db: sync::Arc<sync::Mutex<dyn DatabaseInterface>> =
+            sync::Arc::new(sync::Mutex::new(DummyDatabase::new()));
+        let mut events = Events::new(None).stored(db.clone());
+        events.push(Event::RescanCompleted {
+            timestamp: 1_700_000_000,
+        });
+        events.push(Event::SettingsChanged {
+            change: "Spend template 'rent' saved".to_string(),
+        });
+
+        // The events are found again after a restart, and the identifiers keep increasing.
+        let mut events = Events::new(None).stored(db);
+        assert_eq!(events.list(None).len(), 2);
+        events.push(Event::BackendReachable);
+        let all = events.list(None);
+        assert_eq!(
+            all.iter().map(|entry| entry.id).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(
+            all[0].event,
+            Event::RescanCompleted {
+                timestamp: 1_700_000_000
+            }
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Results of the state-changing commands, by the idempotency key their caller gave.
+//!
+//! A caller which didn't get a response, for instance because of a timeout, can retry a request
+//! with the same key without creating a second draft or broadcasting twice: the result of the first
+//! request is returned instead of performing the command again. The results are kept in memory
+//! for a day.
+
+use std::{collections::HashMap, fmt, time};
+
+/// How long the result of a request is returned for retries of it.
+const KEY_EXPIRY: time::Duration = time::Duration::from_secs(24 * 60 * 60);
+
+/// How many keys we keep at most. The oldest ones are forgotten first.
+const MAX_KEYS: usize = 10_000;
+
+/// The maximum length of an idempotency key.
+pub const MAX_KEY_LENGTH: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdempotencyError {
+    /// The key was already used for a request with a different method or parameters.
+    KeyReused,
+    /// A request with this key is still being processed.
+    InProgress,
+}
+
+impl fmt::Display for IdempotencyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::KeyReused => write!(
+                f,
+                "This idempotency key was already used for a request with different parameters."
+            ),
+            Self::InProgress => write!(
+                f,
+                "A request with this idempotency key is still being processed. Retry later."
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IdempotencyError {}
+
+struct Entry {
+    method: String,
+    params: serde_json::Value,
+    /// `None` while the request is being processed.
+    result: Option<serde_json::Value>,
+    created_at: time::Instant,
+}
+
+#[derive(Default)]
+pub struct IdempotencyKeys {
+    entries: HashMap<String, Entry>,
+}
+
+impl IdempotencyKeys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Claim this key for a request. Returns the result of the previous request with this key if
+    /// it succeeded, or `None` if the request must be performed. In this case the key must be
+    /// released with [`IdempotencyKeys::complete`] once it's done.
+    pub fn claim(
+        &mut self,
+        key: &str,
+        method: &str,
+        params: &serde_json::Value,
+    ) -> Result<Option<serde_json::Value>, IdempotencyError> {
+        self.entries
+            .retain(|_, entry| entry.created_at.elapsed() < KEY_EXPIRY);
+
+        if let Some(entry) = self.entries.get(key) {
+            if entry.method != method || &entry.params != params {
+                return Err(IdempotencyError::KeyReused);
+            }
+            return entry
+                .result
+                .clone()
+                .map(Some)
+                .ok_or(IdempotencyError::InProgress);
+        }
+
+        if self.entries.len() >= MAX_KEYS {
+            if let Some(oldest) = self
+                .entries
+                .iter()
+                .filter(|(_, entry)| entry.result.is_some())
+                .min_by_key(|(_, entry)| entry.created_at)
+                .map(|(key, _)| key.clone())
+            {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(
+            key.to_string(),
+            Entry {
+                method: method.to_string(),
+                params: params.clone(),
+                result: None,
+                created_at: time::Instant::now(),
+            },
+        );
+        Ok(None)
+    }
+
+    /// Record the result of the request which claimed this key. A failed request releases the
+    /// key, for the request to be performed again when retried.
+    pub fn complete(&mut self, key: &str, result: Option<&serde_json::Value>) {
+        match result {
+            Some(result) => {
+                if let Some(entry) = self.entries.get_mut(key) {
+                    entry.result = Some(result.clone());
+                }
+            }
+            None => {
+                self.entries.remove(key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn idempotency_keys() {
+        let mut keys = IdempotencyKeys::new();
+        let params = json!(["txid"]);
+
+        // First request performs the command, retries while it's processed are rejected.
+        assert_eq!(keys.claim("a", "broadcastspend", &params), Ok(None));
+        assert_eq!(
+            keys.claim("a", "broadcastspend", &params),
+            Err(IdempotencyError::InProgress)
+        );
+
+        // Once done, retries get the same result.
+        keys.complete("a", Some(&json!({})));
+        assert_eq!(
+            keys.claim("a", "broadcastspend", &params),
+            Ok(Some(json!({})))
+        );
+
+        // The key can't be used for another request.
+        assert_eq!(
+            keys.claim("a", "broadcastspend", &json!(["other_txid"])),
+            Err(IdempotencyError::KeyReused)
+        );
+        assert_eq!(
+            keys.claim("a", "updatespend", &params),
+            Err(IdempotencyError::KeyReused)
+        );
+
+        // A failed request can be retried.
+        assert_eq!(keys.claim("b", "createspend", &params), Ok(None));
+        keys.complete("b", None);
+        assert_eq!(keys.claim("b", "createspend", &params), Ok(None));
+    }
+}
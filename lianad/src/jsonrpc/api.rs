@@ -1,16 +1,26 @@
 use crate::{
-    commands::{CoinStatus, LabelItem},
-    jsonrpc::rpc::{Error, Params, Request, Response},
+    commands::{
+        CoinStatus, CreateSpendOptions, CreateSpendResult, LabelItem, ReviewDecision, SpendTxStatus,
+    },
+    idempotency,
+    jsonrpc::{
+        rpc::{Error, Params, Request, Response},
+        schema,
+    },
+    readiness::ReadinessItem,
+    spans::{Span, SpanKind},
+    templates::SpendTemplate,
     DaemonControl,
 };
 
 use std::{
     collections::{HashMap, HashSet},
-    convert::TryInto,
+    convert::{TryFrom, TryInto},
     str::FromStr,
 };
 
-use miniscript::bitcoin::{self, psbt::Psbt, Txid};
+use liana::{descriptors::LianaDescriptor, spend::SpendSighash};
+use miniscript::bitcoin::{self, bip32, psbt::Psbt, Txid};
 
 fn create_spend(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
     let destinations = params
@@ -57,8 +67,72 @@ fn create_spend(control: &DaemonControl, params: Params) -> Result<serde_json::V
             })
         })
         .transpose()?;
+    let locktime = params
+        .get(4, "locktime")
+        .map(|locktime| {
+            locktime
+                .as_u64()
+                .and_then(|l| l.try_into().ok())
+                .map(bitcoin::absolute::LockTime::from_consensus)
+                .ok_or_else(|| Error::invalid_params("Invalid 'locktime' parameter."))
+        })
+        .transpose()?;
+    let sighash = params
+        .get(6, "sighash")
+        .map(|sighash| {
+            sighash
+                .as_str()
+                .and_then(|s| SpendSighash::from_str(s).ok())
+                .ok_or_else(|| Error::invalid_params("Invalid 'sighash' parameter."))
+        })
+        .transpose()?
+        .unwrap_or_default();
+    let subtract_fee_from = params
+        .get(7, "subtract_fee_from")
+        .map(|addrs| {
+            addrs
+                .as_array()
+                .and_then(|arr| {
+                    arr.iter()
+                        .map(|addr| {
+                            addr.as_str()
+                                .and_then(|s| bitcoin::Address::from_str(s).ok())
+                        })
+                        .collect::<Option<Vec<bitcoin::Address<bitcoin::address::NetworkUnchecked>>>>()
+                })
+                .ok_or_else(|| Error::invalid_params("Invalid 'subtract_fee_from' parameter."))
+        })
+        .transpose()?
+        .unwrap_or_default();
+    let allow_future_witness = params
+        .get(8, "allow_future_witness")
+        .map(|allow| {
+            allow
+                .as_bool()
+                .ok_or_else(|| Error::invalid_params("Invalid 'allow_future_witness' parameter."))
+        })
+        .transpose()?
+        .unwrap_or(false);
+    let options = CreateSpendOptions {
+        subtract_fee_from,
+        allow_future_witness,
+    };
 
-    let res = control.create_spend(&destinations, &outpoints, feerate, change_address)?;
+    let mut res = if let Some(locktime) = locktime {
+        control.create_scheduled_spend(
+            &destinations,
+            &outpoints,
+            feerate,
+            change_address,
+            &options,
+            locktime,
+        )?
+    } else {
+        control.create_spend(&destinations, &outpoints, feerate, change_address, &options)?
+    };
+    if let CreateSpendResult::Success { ref mut psbt, .. } = res {
+        control.set_spend_sighash(psbt, sighash)?;
+    }
     Ok(serde_json::json!(&res))
 }
 
@@ -74,6 +148,17 @@ fn update_spend(control: &DaemonControl, params: Params) -> Result<serde_json::V
     Ok(serde_json::json!({}))
 }
 
+fn finalize_psbt(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let psbt: Psbt = params
+        .get(0, "psbt")
+        .ok_or_else(|| Error::invalid_params("Missing 'psbt' parameter."))?
+        .as_str()
+        .and_then(|s| Psbt::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'psbt' parameter."))?;
+
+    Ok(serde_json::json!(control.finalize_psbt(&psbt)))
+}
+
 fn delete_spend(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
     let txid = params
         .get(0, "txid")
@@ -86,6 +171,145 @@ fn delete_spend(control: &DaemonControl, params: Params) -> Result<serde_json::V
     Ok(serde_json::json!({}))
 }
 
+fn review_spend(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let txid = params
+        .get(0, "txid")
+        .ok_or_else(|| Error::invalid_params("Missing 'txid' parameter."))?
+        .as_str()
+        .and_then(|s| bitcoin::Txid::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'txid' parameter."))?;
+    let signer = params
+        .get(1, "signer")
+        .ok_or_else(|| Error::invalid_params("Missing 'signer' parameter."))?
+        .as_str()
+        .and_then(|s| bip32::Fingerprint::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'signer' parameter."))?;
+    let decision = params
+        .get(2, "decision")
+        .ok_or_else(|| Error::invalid_params("Missing 'decision' parameter."))?
+        .as_str()
+        .and_then(|s| ReviewDecision::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'decision' parameter."))?;
+    let comment = params
+        .get(3, "comment")
+        .map(|c| {
+            c.as_str()
+                .map(String::from)
+                .ok_or_else(|| Error::invalid_params("Invalid 'comment' parameter."))
+        })
+        .transpose()?;
+    control.review_spend(&txid, signer, decision, comment)?;
+
+    Ok(serde_json::json!({}))
+}
+
+// The value sent out of the wallet by a Spend, as confirmed by the user.
+fn confirmed_amount(params: &Params, index: usize) -> Result<Option<bitcoin::Amount>, Error> {
+    params
+        .get(index, "confirmed_amount")
+        .map(|amount| {
+            amount
+                .as_u64()
+                .map(bitcoin::Amount::from_sat)
+                .ok_or_else(|| Error::invalid_params("Invalid 'confirmed_amount' parameter."))
+        })
+        .transpose()
+}
+
+fn schedule_spend(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let txid = params
+        .get(0, "txid")
+        .ok_or_else(|| Error::invalid_params("Missing 'txid' parameter."))?
+        .as_str()
+        .and_then(|s| bitcoin::Txid::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'txid' parameter."))?;
+    let confirmed_amount = confirmed_amount(&params, 1)?;
+    control.schedule_spend(&txid, confirmed_amount)?;
+
+    Ok(serde_json::json!({}))
+}
+
+fn unschedule_spend(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let txid = params
+        .get(0, "txid")
+        .ok_or_else(|| Error::invalid_params("Missing 'txid' parameter."))?
+        .as_str()
+        .and_then(|s| bitcoin::Txid::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'txid' parameter."))?;
+    control.unschedule_spend(&txid)?;
+
+    Ok(serde_json::json!({}))
+}
+
+fn save_spend_template(
+    control: &DaemonControl,
+    params: Params,
+) -> Result<serde_json::Value, Error> {
+    let template: SpendTemplate = params
+        .get(0, "template")
+        .ok_or_else(|| Error::invalid_params("Missing 'template' parameter."))
+        .and_then(|t| {
+            serde_json::from_value(t.clone())
+                .map_err(|e| Error::invalid_params(format!("Invalid 'template' parameter: {}.", e)))
+        })?;
+    control.save_spend_template(template)?;
+
+    Ok(serde_json::json!({}))
+}
+
+fn delete_spend_template(
+    control: &DaemonControl,
+    params: Params,
+) -> Result<serde_json::Value, Error> {
+    let name = params
+        .get(0, "name")
+        .ok_or_else(|| Error::invalid_params("Missing 'name' parameter."))?
+        .as_str()
+        .ok_or_else(|| Error::invalid_params("Invalid 'name' parameter."))?;
+    control.delete_spend_template(name);
+
+    Ok(serde_json::json!({}))
+}
+
+fn instantiate_spend_template(
+    control: &DaemonControl,
+    params: Params,
+) -> Result<serde_json::Value, Error> {
+    let name = params
+        .get(0, "name")
+        .ok_or_else(|| Error::invalid_params("Missing 'name' parameter."))?
+        .as_str()
+        .ok_or_else(|| Error::invalid_params("Invalid 'name' parameter."))?;
+    let amounts = if let Some(amounts) = params.get(1, "amounts") {
+        amounts
+            .as_object()
+            .and_then(|obj| {
+                obj.into_iter()
+                    .map(|(k, v)| {
+                        let addr = bitcoin::Address::from_str(k).ok()?;
+                        let amount: u64 = v.as_u64()?;
+                        Some((addr, amount))
+                    })
+                    .collect::<Option<HashMap<_, _>>>()
+            })
+            .ok_or_else(|| Error::invalid_params("Invalid 'amounts' parameter."))?
+    } else {
+        HashMap::new()
+    };
+    let feerate = if let Some(feerate) = params.get(2, "feerate") {
+        Some(
+            feerate
+                .as_u64()
+                .ok_or_else(|| Error::invalid_params("Invalid 'feerate' parameter."))?,
+        )
+    } else {
+        None
+    };
+
+    let res = control.instantiate_spend_template(name, &amounts, feerate)?;
+    Ok(serde_json::json!(&res))
+}
+
 fn broadcast_spend(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
     let txid = params
         .get(0, "txid")
@@ -93,7 +317,8 @@ fn broadcast_spend(control: &DaemonControl, params: Params) -> Result<serde_json
         .as_str()
         .and_then(|s| bitcoin::Txid::from_str(s).ok())
         .ok_or_else(|| Error::invalid_params("Invalid 'txid' parameter."))?;
-    control.broadcast_spend(&txid)?;
+    let confirmed_amount = confirmed_amount(&params, 2)?;
+    control.broadcast_spend(&txid, confirmed_amount)?;
 
     Ok(serde_json::json!({}))
 }
@@ -199,6 +424,142 @@ fn list_addresses(
     Ok(serde_json::json!(&res))
 }
 
+fn update_derivation_indexes(
+    control: &DaemonControl,
+    params: Option<Params>,
+) -> Result<serde_json::Value, Error> {
+    let receive = get_opt_u32(&params, 0, "receive")?;
+    let change = get_opt_u32(&params, 1, "change")?;
+
+    let res = &control.update_derivation_indexes(receive, change)?;
+    Ok(serde_json::json!(&res))
+}
+
+fn create_address_statement(
+    control: &DaemonControl,
+    params: Params,
+) -> Result<serde_json::Value, Error> {
+    let params = Some(params);
+    let count = get_opt_u32(&params, 0, "count")?
+        .ok_or_else(|| Error::invalid_params("Missing 'count' parameter."))?;
+    let start_index = get_opt_u32(&params, 1, "start_index")?;
+
+    Ok(serde_json::json!(
+        &control.create_address_statement(count, start_index)?
+    ))
+}
+
+fn get_str_param<'a>(params: &'a Params, index: usize, name: &str) -> Result<&'a str, Error> {
+    params
+        .get(index, name)
+        .ok_or_else(|| Error::invalid_params(format!("Missing '{}' parameter.", name)))?
+        .as_str()
+        .ok_or_else(|| Error::invalid_params(format!("Invalid '{}' parameter.", name)))
+}
+
+fn finalize_address_statement(
+    control: &DaemonControl,
+    params: Params,
+) -> Result<serde_json::Value, Error> {
+    let message = get_str_param(&params, 0, "message")?;
+    let psbt = Psbt::from_str(get_str_param(&params, 1, "psbt")?)
+        .map_err(|_| Error::invalid_params("Invalid 'psbt' parameter."))?;
+
+    Ok(serde_json::json!(
+        &control.finalize_address_statement(message, psbt)?
+    ))
+}
+
+fn verify_address_statement(
+    control: &DaemonControl,
+    params: Params,
+) -> Result<serde_json::Value, Error> {
+    let address = bitcoin::Address::from_str(get_str_param(&params, 0, "address")?)
+        .map_err(|_| Error::invalid_params("Invalid 'address' parameter."))?;
+    let message = get_str_param(&params, 1, "message")?;
+    let signature = get_str_param(&params, 2, "signature")?;
+
+    Ok(serde_json::json!(
+        &control.verify_address_statement(address, message, signature)?
+    ))
+}
+
+fn get_silent_payment_address(
+    control: &DaemonControl,
+    params: Option<Params>,
+) -> Result<serde_json::Value, Error> {
+    let fingerprint = params
+        .as_ref()
+        .and_then(|params| params.get(0, "fingerprint"))
+        .map(|fg| {
+            fg.as_str()
+                .and_then(|fg| bip32::Fingerprint::from_str(fg).ok())
+                .ok_or_else(|| Error::invalid_params("Invalid 'fingerprint' parameter."))
+        })
+        .transpose()?;
+    Ok(serde_json::json!(
+        &control.get_silent_payment_address(fingerprint)?
+    ))
+}
+
+fn sweep_silent_payments(
+    control: &DaemonControl,
+    params: Params,
+) -> Result<serde_json::Value, Error> {
+    let feerate: u64 = params
+        .get(0, "feerate")
+        .ok_or_else(|| Error::invalid_params("Missing 'feerate' parameter."))?
+        .as_u64()
+        .ok_or_else(|| Error::invalid_params("Invalid 'feerate' parameter."))?;
+    Ok(serde_json::json!(&control.sweep_silent_payments(feerate)?))
+}
+
+fn lint_descriptor(
+    control: &DaemonControl,
+    params: Option<Params>,
+) -> Result<serde_json::Value, Error> {
+    let descriptor = params
+        .as_ref()
+        .and_then(|p| p.get(0, "descriptor"))
+        .map(|d| {
+            d.as_str()
+                .and_then(|d| LianaDescriptor::from_str(d).ok())
+                .ok_or_else(|| Error::invalid_params("Invalid 'descriptor' parameter."))
+        })
+        .transpose()?;
+    let timelock = |index: usize, name: &str| {
+        get_opt_u32(&params, index, name)?
+            .map(|t| {
+                u16::try_from(t).map_err(|_| {
+                    Error::invalid_params(format!("Invalid value for '{}': {}", name, t))
+                })
+            })
+            .transpose()
+    };
+    let min_timelock = timelock(1, "min_timelock")?;
+    let max_timelock = timelock(2, "max_timelock")?;
+
+    let res = control.lint_descriptor(descriptor, min_timelock, max_timelock);
+    Ok(serde_json::json!(&res))
+}
+
+fn list_events(
+    control: &DaemonControl,
+    params: Option<Params>,
+) -> Result<serde_json::Value, Error> {
+    let since =
+        if let Some(i) = params.as_ref().and_then(|p| p.get(0, "since")) {
+            Some(i.as_u64().ok_or_else(|| {
+                Error::invalid_params(format!("Invalid value for 'since': {}", i))
+            })?)
+        } else {
+            None
+        };
+
+    let res = control.list_events(since);
+    Ok(serde_json::json!(&res))
+}
+
 fn list_confirmed(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
     let start: u32 = params
         .get(0, "start")
@@ -230,7 +591,7 @@ fn list_spendtxs(
     control: &DaemonControl,
     params: Option<Params>,
 ) -> Result<serde_json::Value, Error> {
-    let txids: Option<Vec<bitcoin::Txid>> = if let Some(p) = params {
+    let txids: Option<Vec<bitcoin::Txid>> = if let Some(p) = &params {
         let tx_ids = p.get(0, "txids");
         if let Some(ids) = tx_ids {
             let ids: Vec<Txid> = ids
@@ -249,7 +610,42 @@ fn list_spendtxs(
         None
     };
 
-    Ok(serde_json::json!(&control.list_spend(txids)?))
+    let statuses = params
+        .as_ref()
+        .and_then(|p| p.get(1, "statuses"))
+        .map(|statuses| {
+            statuses
+                .as_array()
+                .ok_or_else(|| Error::invalid_params("Invalid 'statuses' parameter."))?
+                .iter()
+                .map(|status_arg| {
+                    status_arg
+                        .as_str()
+                        .and_then(SpendTxStatus::from_arg)
+                        .ok_or_else(|| {
+                            Error::invalid_params(format!(
+                                "Invalid value {} in 'statuses' parameter.",
+                                status_arg
+                            ))
+                        })
+                })
+                .collect::<Result<Vec<SpendTxStatus>, Error>>()
+        })
+        .transpose()?
+        .unwrap_or_default();
+    let signer = params
+        .as_ref()
+        .and_then(|p| p.get(2, "signer"))
+        .map(|fg| {
+            fg.as_str()
+                .and_then(|fg| bip32::Fingerprint::from_str(fg).ok())
+                .ok_or_else(|| Error::invalid_params("Invalid 'signer' parameter."))
+        })
+        .transpose()?;
+
+    Ok(serde_json::json!(
+        &control.list_spend(txids, &statuses, signer)?
+    ))
 }
 
 fn list_transactions(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
@@ -266,7 +662,169 @@ fn list_transactions(control: &DaemonControl, params: Params) -> Result<serde_js
     Ok(serde_json::json!(&control.list_transactions(&txids)))
 }
 
-fn start_rescan(control: &mut DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+fn get_transaction(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let txid = params
+        .get(0, "txid")
+        .ok_or_else(|| Error::invalid_params("Missing 'txid' parameter."))?
+        .as_str()
+        .and_then(|s| bitcoin::Txid::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'txid' parameter."))?;
+    Ok(serde_json::json!(&control.get_transaction(&txid)?))
+}
+
+fn get_receipt(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let txid = params
+        .get(0, "txid")
+        .ok_or_else(|| Error::invalid_params("Missing 'txid' parameter."))?
+        .as_str()
+        .and_then(|s| bitcoin::Txid::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'txid' parameter."))?;
+    let explorer_url = params
+        .get(1, "explorer_url")
+        .map(|url| {
+            url.as_str()
+                .ok_or_else(|| Error::invalid_params("Invalid 'explorer_url' parameter."))
+        })
+        .transpose()?;
+    Ok(serde_json::json!(&control.get_receipt(&txid, explorer_url)?))
+}
+
+fn get_hot_signer_xpubs(
+    control: &DaemonControl,
+    params: Params,
+) -> Result<serde_json::Value, Error> {
+    let derivation_paths: Vec<bip32::DerivationPath> = params
+        .get(0, "derivation_paths")
+        .ok_or_else(|| Error::invalid_params("Missing 'derivation_paths' parameter."))?
+        .as_array()
+        .and_then(|arr| {
+            arr.iter()
+                .map(|entry| {
+                    entry
+                        .as_str()
+                        .and_then(|e| bip32::DerivationPath::from_str(e).ok())
+                })
+                .collect()
+        })
+        .ok_or_else(|| Error::invalid_params("Invalid 'derivation_paths' parameter."))?;
+    let fingerprint = params
+        .get(1, "fingerprint")
+        .map(|fg| {
+            fg.as_str()
+                .and_then(|fg| bip32::Fingerprint::from_str(fg).ok())
+                .ok_or_else(|| Error::invalid_params("Invalid 'fingerprint' parameter."))
+        })
+        .transpose()?;
+    Ok(serde_json::json!(
+        &control.get_hot_signer_xpubs(fingerprint, &derivation_paths)?
+    ))
+}
+
+fn import_hot_signer(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    // Either a BIP39 mnemonic or SLIP-39 shares, as a list or one per line.
+    let backup = params
+        .get(0, "backup")
+        .ok_or_else(|| Error::invalid_params("Missing 'backup' parameter."))?;
+    let backup = match backup {
+        serde_json::Value::String(backup) => backup.clone(),
+        serde_json::Value::Array(shares) => shares
+            .iter()
+            .map(|share| share.as_str())
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| Error::invalid_params("Invalid 'backup' parameter."))?
+            .join("\n"),
+        _ => return Err(Error::invalid_params("Invalid 'backup' parameter.")),
+    };
+    Ok(serde_json::json!(&control.import_hot_signer(&backup)?))
+}
+
+fn import_labels_csv(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let csv = params
+        .get(0, "csv")
+        .ok_or_else(|| Error::invalid_params("Missing 'csv' parameter."))?
+        .as_str()
+        .ok_or_else(|| Error::invalid_params("Invalid 'csv' parameter."))?;
+    let overwrite = params
+        .get(1, "overwrite")
+        .map(|overwrite| {
+            overwrite
+                .as_bool()
+                .ok_or_else(|| Error::invalid_params("Invalid 'overwrite' parameter."))
+        })
+        .transpose()?
+        .unwrap_or(false);
+    Ok(serde_json::json!(
+        &control.import_labels_csv(csv, overwrite)?
+    ))
+}
+
+fn sign_with_hot_signer(
+    control: &DaemonControl,
+    params: Params,
+) -> Result<serde_json::Value, Error> {
+    let psbt: Psbt = params
+        .get(0, "psbt")
+        .ok_or_else(|| Error::invalid_params("Missing 'psbt' parameter."))?
+        .as_str()
+        .and_then(|s| Psbt::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'psbt' parameter."))?;
+    let fingerprint = params
+        .get(1, "fingerprint")
+        .map(|fg| {
+            fg.as_str()
+                .and_then(|fg| bip32::Fingerprint::from_str(fg).ok())
+                .ok_or_else(|| Error::invalid_params("Invalid 'fingerprint' parameter."))
+        })
+        .transpose()?;
+    Ok(serde_json::json!(
+        &control.sign_psbt_with_hot_signer(psbt, fingerprint)?
+    ))
+}
+
+fn simulate_signer_loss(
+    control: &DaemonControl,
+    params: Params,
+) -> Result<serde_json::Value, Error> {
+    let fingerprints: Vec<bip32::Fingerprint> = params
+        .get(0, "fingerprints")
+        .ok_or_else(|| Error::invalid_params("Missing 'fingerprints' parameter."))?
+        .as_array()
+        .and_then(|arr| {
+            arr.iter()
+                .map(|entry| {
+                    entry
+                        .as_str()
+                        .and_then(|e| bip32::Fingerprint::from_str(e).ok())
+                })
+                .collect()
+        })
+        .ok_or_else(|| Error::invalid_params("Invalid 'fingerprints' parameter."))?;
+    Ok(serde_json::json!(
+        &control.simulate_signer_loss(&fingerprints)?
+    ))
+}
+
+fn set_recovery_readiness(
+    control: &DaemonControl,
+    params: Params,
+) -> Result<serde_json::Value, Error> {
+    let item = params
+        .get(0, "item")
+        .ok_or_else(|| Error::invalid_params("Missing 'item' parameter."))?
+        .as_str()
+        .and_then(|s| ReadinessItem::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'item' parameter."))?;
+    let done = params
+        .get(1, "done")
+        .ok_or_else(|| Error::invalid_params("Missing 'done' parameter."))?
+        .as_bool()
+        .ok_or_else(|| Error::invalid_params("Invalid 'done' parameter."))?;
+    control.set_recovery_readiness(item, done)?;
+
+    Ok(serde_json::json!({}))
+}
+
+fn start_rescan(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
     let timestamp: u32 = params
         .get(0, "timestamp")
         .ok_or_else(|| Error::invalid_params("Missing 'timestamp' parameter."))?
@@ -303,6 +861,34 @@ fn create_recovery(control: &DaemonControl, params: Params) -> Result<serde_json
     Ok(serde_json::json!(&res))
 }
 
+fn get_recovery_schedule(
+    control: &DaemonControl,
+    params: Option<Params>,
+) -> Result<serde_json::Value, Error> {
+    let timelock: Option<u16> = params
+        .as_ref()
+        .and_then(|params| params.get(0, "timelock"))
+        .map(|tl| {
+            tl.as_u64()
+                .and_then(|tl| tl.try_into().ok())
+                .ok_or_else(|| Error::invalid_params("Invalid 'timelock' parameter."))
+        })
+        .transpose()?;
+    let bucket_size: Option<u32> = params
+        .as_ref()
+        .and_then(|params| params.get(1, "bucket_size"))
+        .map(|size| {
+            size.as_u64()
+                .and_then(|size| size.try_into().ok())
+                .filter(|size| *size > 0)
+                .ok_or_else(|| Error::invalid_params("Invalid 'bucket_size' parameter."))
+        })
+        .transpose()?;
+    Ok(serde_json::json!(
+        &control.get_recovery_schedule(timelock, bucket_size)?
+    ))
+}
+
 fn update_labels(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
     let mut items = HashMap::new();
     for (item, value) in params
@@ -364,8 +950,108 @@ fn get_labels(control: &DaemonControl, params: Params) -> Result<serde_json::Val
     Ok(serde_json::json!(control.get_labels(&items)))
 }
 
+/// Whether this method takes the write lock: write commands run one at a time.
+fn is_write(method: &str) -> bool {
+    matches!(
+        method,
+        "broadcastspend"
+            | "createaddressstatement"
+            | "createrecovery"
+            | "createspend"
+            | "delspendtemplate"
+            | "delspendtx"
+            | "getnewaddress"
+            | "getsilentpaymentaddress"
+            | "importhotsigner"
+            | "importlabelscsv"
+            | "instantiatespendtemplate"
+            | "rbfpsbt"
+            | "reviewspend"
+            | "savespendtemplate"
+            | "schedulespend"
+            | "setrecoveryreadiness"
+            | "startrescan"
+            | "sweepsilentpayments"
+            | "unschedulespend"
+            | "updatederivationindexes"
+            | "updatelabels"
+            | "updatespend"
+    )
+}
+
+/// The position of the `idempotency_key` parameter of the commands which accept one.
+fn idempotency_key_index(method: &str) -> Option<usize> {
+    match method {
+        "broadcastspend" | "updatespend" => Some(1),
+        "createspend" => Some(5),
+        _ => None,
+    }
+}
+
+/// The idempotency key of this request, if any, along with the other parameters of the request.
+fn idempotency_key(req: &Request) -> Result<Option<(String, serde_json::Value)>, Error> {
+    let (index, params) = match (idempotency_key_index(&req.method), &req.params) {
+        (Some(index), Some(params)) => (index, params),
+        _ => return Ok(None),
+    };
+    let key = match params.get(index, "idempotency_key") {
+        Some(key) => key
+            .as_str()
+            .filter(|k| !k.is_empty() && k.len() <= idempotency::MAX_KEY_LENGTH)
+            .ok_or_else(|| Error::invalid_params("Invalid 'idempotency_key' parameter."))?,
+        None => return Ok(None),
+    };
+    let params = match params {
+        Params::Array(params) => serde_json::json!(params
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != index)
+            .map(|(_, param)| param)
+            .collect::<Vec<_>>()),
+        Params::Map(params) => {
+            let mut params = params.clone();
+            params.remove("idempotency_key");
+            serde_json::Value::Object(params)
+        }
+    };
+    Ok(Some((key.to_string(), params)))
+}
+
 /// Handle an incoming JSONRPC2 request.
-pub fn handle_request(control: &mut DaemonControl, req: Request) -> Result<Response, Error> {
+pub fn handle_request(control: &DaemonControl, req: Request) -> Result<Response, Error> {
+    let id = req.id.clone();
+    // Started before waiting for the other writes, which may be what's slow.
+    let _span = Span::enter(SpanKind::Command, &req.method);
+    let _writes = is_write(&req.method).then(|| control.lock_writes());
+    let result = match idempotency_key(&req)? {
+        Some((key, params)) => {
+            // Retries of a request with the same key get the result of the first one.
+            let previous =
+                control
+                    .idempotency_keys
+                    .lock()
+                    .unwrap()
+                    .claim(&key, &req.method, &params)?;
+            if let Some(result) = previous {
+                log::debug!("Returning the result of the request with key '{}'.", key);
+                result
+            } else {
+                let res = request_result(control, req);
+                control
+                    .idempotency_keys
+                    .lock()
+                    .unwrap()
+                    .complete(&key, res.as_ref().ok());
+                res?
+            }
+        }
+        None => request_result(control, req)?,
+    };
+
+    Ok(Response::success(id, result))
+}
+
+fn request_result(control: &DaemonControl, req: Request) -> Result<serde_json::Value, Error> {
     let result = match req.method.as_str() {
         "broadcastspend" => {
             let params = req
@@ -387,6 +1073,18 @@ pub fn handle_request(control: &mut DaemonControl, req: Request) -> Result<Respo
             })?;
             create_spend(control, params)?
         }
+        "delspendtemplate" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'name' parameter."))?;
+            delete_spend_template(control, params)?
+        }
+        "reviewspend" => {
+            let params = req.params.ok_or_else(|| {
+                Error::invalid_params("Missing 'txid', 'signer' and 'decision' parameters.")
+            })?;
+            review_spend(control, params)?
+        }
         "delspendtx" => {
             let params = req
                 .params
@@ -399,8 +1097,72 @@ pub fn handle_request(control: &mut DaemonControl, req: Request) -> Result<Respo
             })?;
             rbf_psbt(control, params)?
         }
+        "createaddressstatement" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'count' parameter."))?;
+            create_address_statement(control, params)?
+        }
+        "finalizeaddressstatement" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'message' and 'psbt' parameters."))?;
+            finalize_address_statement(control, params)?
+        }
+        "finalizepsbt" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'psbt' parameter."))?;
+            finalize_psbt(control, params)?
+        }
+        "exportsnapshot" => serde_json::json!(&control.export_snapshot()?),
+        "exporttransactions" => serde_json::json!(&control.export_transactions()),
+        "getderivationindexes" => serde_json::json!(&control.get_derivation_indexes()),
+        "gethotsignerxpubs" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'derivation_paths' parameter."))?;
+            get_hot_signer_xpubs(control, params)?
+        }
+        "getbalance" => serde_json::json!(&control.get_balance()),
         "getinfo" => serde_json::json!(&control.get_info()),
+        "getmempoolstats" => serde_json::json!(&control.get_mempool_stats()),
         "getnewaddress" => serde_json::json!(&control.get_new_address()),
+        "getreceipt" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'txid' parameter."))?;
+            get_receipt(control, params)?
+        }
+        "getrecoveryreadiness" => serde_json::json!(&control.get_recovery_readiness()),
+        "getrecoveryschedule" => get_recovery_schedule(control, req.params)?,
+        "getsilentpaymentaddress" => get_silent_payment_address(control, req.params)?,
+        "gettransaction" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'txid' parameter."))?;
+            get_transaction(control, params)?
+        }
+        "healthcheck" => serde_json::json!(&control.health_check()),
+        "importhotsigner" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'backup' parameter."))?;
+            import_hot_signer(control, params)?
+        }
+        "importlabelscsv" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'csv' parameter."))?;
+            import_labels_csv(control, params)?
+        }
+        "instantiatespendtemplate" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'name' parameter."))?;
+            instantiate_spend_template(control, params)?
+        }
+        "lintdescriptor" => lint_descriptor(control, req.params)?,
         "listcoins" => {
             let params = req.params;
             list_coins(control, params)?
@@ -417,6 +1179,10 @@ pub fn handle_request(control: &mut DaemonControl, req: Request) -> Result<Respo
             })?;
             list_confirmed(control, params)?
         }
+        "listevents" => list_events(control, req.params)?,
+        "listscheduledspends" => serde_json::json!(&control.list_scheduled_spends()),
+        "listsilentpayments" => serde_json::json!(&control.list_silent_payments()),
+        "listspendtemplates" => serde_json::json!(&control.list_spend_templates()),
         "listspendtxs" => list_spendtxs(control, req.params)?,
         "listtransactions" => {
             let params = req.params.ok_or_else(|| {
@@ -426,6 +1192,37 @@ pub fn handle_request(control: &mut DaemonControl, req: Request) -> Result<Respo
             })?;
             list_transactions(control, params)?
         }
+        "rpc.discover" => schema::openrpc_document(),
+        "schedulespend" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'txid' parameter."))?;
+            schedule_spend(control, params)?
+        }
+        "savespendtemplate" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'template' parameter."))?;
+            save_spend_template(control, params)?
+        }
+        "setrecoveryreadiness" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'item' and 'done' parameters."))?;
+            set_recovery_readiness(control, params)?
+        }
+        "signwithhotsigner" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'psbt' parameter."))?;
+            sign_with_hot_signer(control, params)?
+        }
+        "simulatesignerloss" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'fingerprints' parameter."))?;
+            simulate_signer_loss(control, params)?
+        }
         "startrescan" => {
             let params = req
                 .params
@@ -433,12 +1230,31 @@ pub fn handle_request(control: &mut DaemonControl, req: Request) -> Result<Respo
             start_rescan(control, params)?
         }
         "stop" => serde_json::json!({}),
+        "sweepsilentpayments" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'feerate' parameter."))?;
+            sweep_silent_payments(control, params)?
+        }
         "updatespend" => {
             let params = req
                 .params
                 .ok_or_else(|| Error::invalid_params("Missing 'psbt' parameter."))?;
             update_spend(control, params)?
         }
+        "unschedulespend" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'txid' parameter."))?;
+            unschedule_spend(control, params)?
+        }
+        "updatederivationindexes" => update_derivation_indexes(control, req.params)?,
+        "verifyaddressstatement" => {
+            let params = req.params.ok_or_else(|| {
+                Error::invalid_params("Missing 'address', 'message' and 'signature' parameters.")
+            })?;
+            verify_address_statement(control, params)?
+        }
         "updatelabels" => {
             let params = req
                 .params
@@ -456,5 +1272,5 @@ pub fn handle_request(control: &mut DaemonControl, req: Request) -> Result<Respo
         }
     };
 
-    Ok(Response::success(req.id, result))
+    Ok(result)
 }
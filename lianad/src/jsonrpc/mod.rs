@@ -2,4 +2,6 @@
 mod api;
 #[cfg(unix)]
 pub mod rpc;
+#[cfg(unix)]
+mod schema;
 pub mod server;
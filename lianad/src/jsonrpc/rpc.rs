@@ -1,4 +1,4 @@
-use crate::commands;
+use crate::{commands, idempotency};
 
 use std::{error, fmt};
 
@@ -51,6 +51,15 @@ pub struct Request {
 /// A failure to broadcast a transaction to the P2P network.
 const BROADCAST_ERROR: i64 = 1_000;
 
+/// The command was received after the daemon started to shut down.
+const SHUTTING_DOWN_ERROR: i64 = 1_001;
+
+/// The feerate is below the minimum the Bitcoin backend's mempool accepts.
+const FEERATE_TOO_LOW_ERROR: i64 = 1_002;
+
+/// The transaction would exceed the mempool limits on chains of unconfirmed transactions.
+const PACKAGE_LIMITS_ERROR: i64 = 1_003;
+
 /// JSONRPC2 error codes. See https://www.jsonrpc.org/specification#error_object.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum ErrorCode {
@@ -124,8 +133,19 @@ impl Error {
         }
     }
 
+    /// Set the stable identifier of the error and its details as the data of this error. The
+    /// details must be a JSON object, the identifier is set as its `code` field.
+    pub fn with_data(mut self, code: &str, mut details: serde_json::Value) -> Error {
+        if let Some(details) = details.as_object_mut() {
+            details.insert("code".to_string(), code.into());
+        }
+        self.data = Some(details);
+        self
+    }
+
     pub fn method_not_found() -> Error {
         Error::new(ErrorCode::MethodNotFound, "Method not found")
+            .with_data("METHOD_NOT_FOUND", serde_json::json!({}))
     }
 
     pub fn invalid_params(message: impl Into<String>) -> Error {
@@ -133,6 +153,15 @@ impl Error {
             ErrorCode::InvalidParams,
             format!("Invalid params: {}", message.into()),
         )
+        .with_data("INVALID_PARAMS", serde_json::json!({}))
+    }
+
+    pub fn shutting_down() -> Error {
+        Error::new(
+            ErrorCode::ServerError(SHUTTING_DOWN_ERROR),
+            "The daemon is shutting down.",
+        )
+        .with_data("SHUTTING_DOWN", serde_json::json!({}))
     }
 }
 
@@ -147,7 +176,7 @@ impl error::Error for Error {}
 
 impl From<commands::CommandError> for Error {
     fn from(e: commands::CommandError) -> Error {
-        match e {
+        let code = match e {
             commands::CommandError::NoOutpointForSelfSend
             | commands::CommandError::UnknownOutpoint(..)
             | commands::CommandError::InvalidFeerate(..)
@@ -157,22 +186,158 @@ impl From<commands::CommandError> for Error {
             | commands::CommandError::SpendCreation(..)
             | commands::CommandError::InsufficientFunds(..)
             | commands::CommandError::UnknownSpend(..)
+            | commands::CommandError::UnknownTransaction(..)
+            | commands::CommandError::ComputedReadinessItem(..)
+            | commands::CommandError::UnknownSpendTemplate(..)
+            | commands::CommandError::InvalidSpendTemplate(..)
+            | commands::CommandError::LocktimeReached(..)
             | commands::CommandError::SpendFinalization(..)
             | commands::CommandError::InsaneRescanTimestamp(..)
             | commands::CommandError::AlreadyRescanning
             | commands::CommandError::InvalidDerivationIndex
+            | commands::CommandError::DerivationIndexIncrease(..)
             | commands::CommandError::RbfError(..)
             | commands::CommandError::EmptyFilterList
-            | commands::CommandError::RecoveryNotAvailable => {
-                Error::new(ErrorCode::InvalidParams, e.to_string())
+            | commands::CommandError::NoHotSigner
+            | commands::CommandError::UnknownHotSigner(..)
+            | commands::CommandError::AmbiguousHotSigner
+            | commands::CommandError::InvalidHotSignerBackup(..)
+            | commands::CommandError::UnknownSigner(..)
+            | commands::CommandError::NotSynced
+            | commands::CommandError::AlteredSpend(..)
+            | commands::CommandError::UtxoMismatch(..)
+            | commands::CommandError::InvalidSignature(..)
+            | commands::CommandError::SpendNotConfirmed(..)
+            | commands::CommandError::InvalidAddressStatement(..)
+            | commands::CommandError::SilentPaymentsUnsupported
+            | commands::CommandError::NoSilentPayments
+            | commands::CommandError::RecoveryNotAvailable
+            | commands::CommandError::UnconfirmedTransaction(..)
+            | commands::CommandError::UnknownFeeDestination(..)
+            | commands::CommandError::FeeExceedsDestination(..)
+            | commands::CommandError::FutureWitnessVersion(..)
+            | commands::CommandError::EphemeralWallet
+            | commands::CommandError::InvalidLabelsCsv(..) => ErrorCode::InvalidParams,
+            commands::CommandError::RescanTrigger(..) | commands::CommandError::HotSigner(..) => {
+                ErrorCode::InternalError
+            }
+            commands::CommandError::TxBroadcast(_) => ErrorCode::ServerError(BROADCAST_ERROR),
+            commands::CommandError::FeerateTooLow(..) => {
+                ErrorCode::ServerError(FEERATE_TOO_LOW_ERROR)
             }
-            commands::CommandError::RescanTrigger(..) => {
-                Error::new(ErrorCode::InternalError, e.to_string())
+            commands::CommandError::PackageLimits(..) => {
+                ErrorCode::ServerError(PACKAGE_LIMITS_ERROR)
             }
-            commands::CommandError::TxBroadcast(_) => {
-                Error::new(ErrorCode::ServerError(BROADCAST_ERROR), e.to_string())
+        };
+        Error::new(code, e.to_string()).with_data(e.code(), command_error_details(&e))
+    }
+}
+
+impl From<idempotency::IdempotencyError> for Error {
+    fn from(e: idempotency::IdempotencyError) -> Error {
+        let code = match e {
+            idempotency::IdempotencyError::KeyReused => "IDEMPOTENCY_KEY_REUSED",
+            idempotency::IdempotencyError::InProgress => "IDEMPOTENCY_KEY_IN_PROGRESS",
+        };
+        Error::new(ErrorCode::InvalidParams, e.to_string()).with_data(code, serde_json::json!({}))
+    }
+}
+
+/// The machine-readable details of a command error, documented along with its code.
+fn command_error_details(e: &commands::CommandError) -> serde_json::Value {
+    use commands::{CommandError, RbfErrorInfo};
+    use liana::spend::{InsaneFeeInfo, SignatureError, SpendCreationError, MAX_FEE, MAX_FEERATE};
+    use serde_json::json;
+
+    let feerate_bounds = |feerate: u64| {
+        json!({
+            "feerate": feerate,
+            "minimum_feerate": 1,
+            "maximum_feerate": MAX_FEERATE,
+        })
+    };
+    match e {
+        CommandError::InvalidFeerate(feerate)
+        | CommandError::SpendCreation(SpendCreationError::InvalidFeerate(feerate)) => {
+            feerate_bounds(*feerate)
+        }
+        CommandError::FeerateTooLow(feerate, min_feerate)
+        | CommandError::RbfError(RbfErrorInfo::TooLowFeerate(feerate, min_feerate)) => json!({
+            "feerate": feerate,
+            "minimum_feerate": min_feerate,
+        }),
+        CommandError::PackageLimits(op)
+        | CommandError::UnknownOutpoint(op)
+        | CommandError::AlreadySpent(op)
+        | CommandError::ImmatureCoinbase(op)
+        | CommandError::UtxoMismatch(op)
+        | CommandError::SpendCreation(SpendCreationError::FetchingTransaction(op))
+        | CommandError::SpendCreation(SpendCreationError::DuplicateCandidate(op)) => {
+            json!({ "outpoint": op.to_string() })
+        }
+        CommandError::SpendCreation(SpendCreationError::InvalidOutputValue(amount)) => {
+            json!({ "amount": amount.to_sat() })
+        }
+        CommandError::SpendCreation(SpendCreationError::InsaneFees(info)) => {
+            let mut details = json!({
+                "maximum_fee": MAX_FEE.to_sat(),
+                "maximum_feerate": MAX_FEERATE,
+            });
+            match info {
+                InsaneFeeInfo::TooHighFee(fee) => details["fee"] = json!(fee),
+                InsaneFeeInfo::TooHighFeerate(feerate) => details["feerate"] = json!(feerate),
+                InsaneFeeInfo::NegativeFee | InsaneFeeInfo::InvalidFeerate => {}
             }
+            details
+        }
+        CommandError::SpendCreation(SpendCreationError::CoinSelection(e)) => {
+            json!({ "missing": e.missing })
+        }
+        CommandError::InsufficientFunds(in_value, out_value, feerate) => json!({
+            "input_value": in_value.to_sat(),
+            "output_value": out_value.map(|v| v.to_sat()),
+            "feerate": feerate,
+        }),
+        CommandError::UnknownSpend(txid)
+        | CommandError::UnknownTransaction(txid)
+        | CommandError::UnconfirmedTransaction(txid) => {
+            json!({ "txid": txid.to_string() })
+        }
+        CommandError::InsaneRescanTimestamp(timestamp) => json!({ "timestamp": timestamp }),
+        CommandError::ComputedReadinessItem(item) => json!({ "item": item }),
+        CommandError::UnknownSpendTemplate(name) => json!({ "name": name }),
+        CommandError::LocktimeReached(locktime) => {
+            json!({ "locktime": locktime.to_consensus_u32() })
+        }
+        CommandError::DerivationIndexIncrease(max) => json!({ "maximum_increase": max }),
+        CommandError::UnknownHotSigner(fg) | CommandError::UnknownSigner(fg) => {
+            json!({ "fingerprint": fg.to_string() })
+        }
+        CommandError::AlteredSpend(txid, _) => json!({ "txid": txid.to_string() }),
+        CommandError::SpendNotConfirmed(sent, threshold) => json!({
+            "amount": sent.to_sat(),
+            "threshold": threshold.to_sat(),
+        }),
+        CommandError::InvalidSignature(SignatureError::MissingUtxo(index)) => {
+            json!({ "input_index": index })
+        }
+        CommandError::InvalidSignature(SignatureError::Invalid {
+            input_index,
+            pubkey,
+        })
+        | CommandError::InvalidSignature(SignatureError::UnexpectedSighash {
+            input_index,
+            pubkey,
+        }) => json!({ "input_index": input_index, "pubkey": pubkey }),
+        CommandError::SpendCreation(SpendCreationError::SighashSingleMissingOutput(index)) => {
+            json!({ "input_index": index })
         }
+        CommandError::UnknownFeeDestination(addr)
+        | CommandError::FeeExceedsDestination(addr)
+        | CommandError::FutureWitnessVersion(addr) => {
+            json!({ "address": addr.assume_checked_ref().to_string() })
+        }
+        _ => json!({}),
     }
 }
 
@@ -210,3 +375,49 @@ impl Response {
         Response::new(id, None, Some(error))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use liana::spend::{InsufficientFunds, SpendCreationError, MAX_FEERATE};
+    use serde_json::json;
+
+    #[test]
+    fn command_error_data() {
+        let err: Error = commands::CommandError::FeerateTooLow(1, 3).into();
+        assert_eq!(err.code, ErrorCode::ServerError(FEERATE_TOO_LOW_ERROR));
+        assert_eq!(
+            err.data,
+            Some(json!({ "code": "FEERATE_TOO_LOW", "feerate": 1, "minimum_feerate": 3 }))
+        );
+
+        let err: Error = commands::CommandError::InvalidFeerate(0).into();
+        assert_eq!(err.code, ErrorCode::InvalidParams);
+        assert_eq!(
+            err.data,
+            Some(json!({
+                "code": "INVALID_FEERATE",
+                "feerate": 0,
+                "minimum_feerate": 1,
+                "maximum_feerate": MAX_FEERATE,
+            }))
+        );
+
+        let err: Error = commands::CommandError::SpendCreation(SpendCreationError::CoinSelection(
+            InsufficientFunds { missing: 1_000 },
+        ))
+        .into();
+        assert_eq!(
+            err.data,
+            Some(json!({ "code": "INSUFFICIENT_FUNDS", "missing": 1_000 }))
+        );
+
+        let err: Error = commands::CommandError::NotSynced.into();
+        assert_eq!(err.data, Some(json!({ "code": "NOT_SYNCED" })));
+
+        // The message is left untouched, it's only the data which is structured.
+        let err = Error::invalid_params("Missing 'txid' parameter.");
+        assert_eq!(err.message, "Invalid params: Missing 'txid' parameter.");
+        assert_eq!(err.data, Some(json!({ "code": "INVALID_PARAMS" })));
+    }
+}
@@ -0,0 +1,2709 @@
+//! Machine-readable description of the JSONRPC API, as an [OpenRPC](https://spec.open-rpc.org)
+//! document. It is returned by the `rpc.discover` command and can be used to generate clients.
+//!
+//! The schemas of the results are named after the types of the `commands` module they are the
+//! serialization of. Keep them in sync when modifying these types.
+
+use crate::{readiness::ReadinessItem, reviews::ReviewDecision, VERSION};
+use liana::spend::SpendSighash;
+
+use serde_json::{json, Value};
+
+fn string(description: &str) -> Value {
+    json!({ "type": "string", "description": description })
+}
+
+fn integer(description: &str) -> Value {
+    json!({ "type": "integer", "minimum": 0, "description": description })
+}
+
+fn boolean(description: &str) -> Value {
+    json!({ "type": "boolean", "description": description })
+}
+
+fn number(description: &str) -> Value {
+    json!({ "type": "number", "description": description })
+}
+
+fn nullable(mut schema: Value) -> Value {
+    let ty = schema["type"].take();
+    schema["type"] = json!([ty, "null"]);
+    schema
+}
+
+fn array(items: Value, description: &str) -> Value {
+    json!({ "type": "array", "items": items, "description": description })
+}
+
+fn map(values: Value, description: &str) -> Value {
+    json!({ "type": "object", "additionalProperties": values, "description": description })
+}
+
+fn reference(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+/// An object schema. All the fields are required, optional ones being nullable.
+fn object(fields: &[(&str, Value)]) -> Value {
+    let properties: serde_json::Map<String, Value> = fields
+        .iter()
+        .map(|(name, schema)| (name.to_string(), schema.clone()))
+        .collect();
+    let required: Vec<&str> = fields.iter().map(|(name, _)| *name).collect();
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+fn empty_object() -> Value {
+    object(&[])
+}
+
+fn param(name: &str, required: bool, schema: Value) -> Value {
+    json!({ "name": name, "required": required, "schema": schema })
+}
+
+fn method(name: &str, summary: &str, params: Vec<Value>, result: Value) -> Value {
+    json!({
+        "name": name,
+        "summary": summary,
+        "paramStructure": "either",
+        "params": params,
+        "result": { "name": format!("{}_result", name), "schema": result },
+    })
+}
+
+fn readiness_item() -> Value {
+    json!({
+        "type": "string",
+        "enum": ReadinessItem::ALL.iter().map(ReadinessItem::as_str).collect::<Vec<_>>(),
+    })
+}
+
+fn review_decision() -> Value {
+    json!({
+        "type": "string",
+        "enum": ReviewDecision::ALL.iter().map(ReviewDecision::as_str).collect::<Vec<_>>(),
+    })
+}
+
+fn idempotency_key() -> Value {
+    param(
+        "idempotency_key",
+        false,
+        string(
+            "Unique key of the request. Retrying it with the same key returns the result of the \
+             first request instead of performing it again.",
+        ),
+    )
+}
+
+fn confirmed_amount() -> Value {
+    param(
+        "confirmed_amount",
+        false,
+        integer(
+            "The value in satoshis the Spend sends out of the wallet, as confirmed by the user. \
+             Required if it's above the 'spend_confirmation_threshold' of the configuration.",
+        ),
+    )
+}
+
+fn spending_path(description: &str) -> Value {
+    json!({
+        "description": description,
+        "oneOf": [
+            { "type": "string", "enum": ["primary"] },
+            object(&[("recovery", integer("Timelock of the recovery path."))]),
+        ],
+    })
+}
+
+fn health_status() -> Value {
+    json!({
+        "type": "string",
+        "enum": ["ok", "warning", "error"],
+        "description": "The outcome of a check, or of the worst check.",
+    })
+}
+
+fn components() -> Value {
+    let create_spend_success = object(&[
+        (
+            "psbt",
+            string("PSBT of the spending transaction, encoded as base64."),
+        ),
+        (
+            "warnings",
+            array(
+                json!({ "type": "string" }),
+                "Warnings, if any, generated during spend creation.",
+            ),
+        ),
+    ]);
+    let create_spend_insufficient_funds = object(&[(
+        "missing",
+        integer("Additional sats required to create the spend."),
+    )]);
+    // An event is flattened into its entry: each type of event has its own fields.
+    let event = |ty: &str, fields: &[(&str, Value)]| {
+        let mut entry = vec![
+            (
+                "id",
+                integer("Identifier of the event, increasing with each event."),
+            ),
+            ("time", integer("Timestamp at which the event happened.")),
+            (
+                "level",
+                json!({ "type": "string", "enum": ["info", "warning", "critical"] }),
+            ),
+            ("type", json!({ "type": "string", "enum": [ty] })),
+        ];
+        entry.extend_from_slice(fields);
+        object(&entry)
+    };
+    let outpoints = |description: &str| array(json!({ "type": "string" }), description);
+    let events = vec![
+        event(
+            "coin_received",
+            &[
+                ("outpoint", string("The received coin, as txid:vout.")),
+                ("amount", integer("Value of the coin in satoshis.")),
+            ],
+        ),
+        event(
+            "spend_confirmed",
+            &[
+                (
+                    "txid",
+                    string("Txid of the transaction spending our coins."),
+                ),
+                (
+                    "height",
+                    integer("Height of the block it was confirmed in."),
+                ),
+            ],
+        ),
+        event(
+            "recovery_path_soon_available",
+            &[
+                ("timelock", integer("Timelock of the first recovery path.")),
+                ("coins", outpoints("The coins, as txid:vout.")),
+                (
+                    "blocks_remaining",
+                    integer("Number of blocks before the first of the coins is recoverable."),
+                ),
+            ],
+        ),
+        event(
+            "backend_unreachable",
+            &[("error", string("Error communicating with the backend."))],
+        ),
+        event("backend_reachable", &[]),
+        event(
+            "unauthorized_spend",
+            &[
+                (
+                    "txid",
+                    string("Txid of the transaction which spent the coins."),
+                ),
+                (
+                    "coins",
+                    outpoints("The coins spent by the transaction, as txid:vout."),
+                ),
+                (
+                    "spending_path",
+                    json!({
+                        "description": "Whose keys signed the transaction: \"primary\", or \
+                                        {\"recovery\": timelock}. Null if unknown.",
+                        "oneOf": [
+                            { "type": "null" },
+                            { "type": "string", "enum": ["primary"] },
+                            object(&[("recovery", integer("Timelock of the recovery path."))]),
+                        ],
+                    }),
+                ),
+            ],
+        ),
+        event(
+            "scheduled_spend_broadcast",
+            &[("txid", string("Txid of the scheduled transaction."))],
+        ),
+        event(
+            "scheduled_spend_conflicted",
+            &[
+                ("txid", string("Txid of the scheduled transaction.")),
+                (
+                    "coins",
+                    outpoints("The coins it spends which were spent by another transaction."),
+                ),
+            ],
+        ),
+        event(
+            "backends_disagree",
+            &[(
+                "disagreements",
+                array(
+                    json!({ "type": "string" }),
+                    "Description of each new disagreement between the backends.",
+                ),
+            )],
+        ),
+        event("backends_agree", &[]),
+        event(
+            "spend_signed",
+            &[
+                ("txid", string("Txid of the Spend transaction.")),
+                (
+                    "signers",
+                    array(
+                        json!({ "type": "string" }),
+                        "Master fingerprints of the signers whose signatures were added.",
+                    ),
+                ),
+            ],
+        ),
+        event(
+            "spend_reviewed",
+            &[
+                ("txid", string("Txid of the Spend transaction.")),
+                ("signer", string("Master fingerprint of the signer.")),
+                ("decision", review_decision()),
+            ],
+        ),
+        event(
+            "spend_saved",
+            &[("txid", string("Txid of the new Spend transaction."))],
+        ),
+        event(
+            "spend_broadcast",
+            &[("txid", string("Txid of the Spend transaction."))],
+        ),
+        event(
+            "rescan_completed",
+            &[(
+                "timestamp",
+                integer("Timestamp the chain was rescanned from."),
+            )],
+        ),
+        event(
+            "settings_changed",
+            &[("change", string("Description of the change."))],
+        ),
+        event(
+            "stale_tip",
+            &[
+                ("height", integer("Height of the tip which didn't advance.")),
+                (
+                    "minutes",
+                    integer("For how many minutes the tip didn't advance."),
+                ),
+            ],
+        ),
+        event(
+            "tip_advanced",
+            &[("height", integer("Height of the new tip."))],
+        ),
+        event(
+            "clock_skewed",
+            &[(
+                "skew_secs",
+                json!({
+                    "type": "integer",
+                    "description": "By how many seconds the system clock is ahead of the time of \
+                                    the latest block, negative if behind.",
+                }),
+            )],
+        ),
+        event("clock_synced", &[]),
+    ];
+
+    let schemas =
+        vec![
+        (
+            "GetInfoResult",
+            object(&[
+                ("version", string("Version following the SimVer format.")),
+                (
+                    "network",
+                    string("The Bitcoin network the daemon operates on."),
+                ),
+                (
+                    "block_height",
+                    integer("The block height we are synced at."),
+                ),
+                (
+                    "sync",
+                    number("The synchronization progress, between 0 and 1."),
+                ),
+                (
+                    "descriptors",
+                    object(&[("main", string("The main descriptor of the wallet."))]),
+                ),
+                (
+                    "rescan_progress",
+                    nullable(number(
+                        "Progress of an ongoing rescan, between 0 and 1, if any.",
+                    )),
+                ),
+                (
+                    "rescan_checkpoint",
+                    nullable(integer(
+                        "Height up to which the ongoing rescan is known to have processed the \
+                         chain, if any. An interrupted rescan is resumed from there.",
+                    )),
+                ),
+                (
+                    "timestamp",
+                    integer("Unix timestamp of wallet creation date."),
+                ),
+                (
+                    "last_poll_timestamp",
+                    nullable(integer(
+                        "Unix timestamp of the last poll of the blockchain, if any.",
+                    )),
+                ),
+                (
+                    "poller",
+                    json!({ "oneOf": [reference("PollStats"), { "type": "null" }] }),
+                ),
+            ]),
+        ),
+        (
+            "PollStats",
+            object(&[
+                ("polls", integer("Number of polls performed since startup.")),
+                (
+                    "last_duration_ms",
+                    nullable(integer("Duration of the last poll, in milliseconds.")),
+                ),
+                (
+                    "average_duration_ms",
+                    nullable(integer("Average duration of a poll, in milliseconds.")),
+                ),
+                (
+                    "max_duration_ms",
+                    nullable(integer("Duration of the longest poll, in milliseconds.")),
+                ),
+                (
+                    "skipped_polls",
+                    integer(
+                        "Number of polls skipped because the previous one was still running \
+                         when they were due.",
+                    ),
+                ),
+                (
+                    "consecutive_failures",
+                    integer("Number of polls in a row which failed to reach the Bitcoin backend."),
+                ),
+                (
+                    "next_poll_delay_ms",
+                    integer(
+                        "Delay between the start of the last poll and the next one, in \
+                         milliseconds. Longer than the poll interval when backing off.",
+                    ),
+                ),
+            ]),
+        ),
+        (
+            "SnapshotCoin",
+            object(&[
+                ("outpoint", string("Outpoint of the coin, as 'txid:vout'.")),
+                ("amount", integer("Value of the coin, in satoshis.")),
+                (
+                    "derivation_index",
+                    integer("Derivation index of the address of the coin."),
+                ),
+                ("is_change", boolean("Whether the coin is a change output.")),
+                (
+                    "is_immature",
+                    boolean("Whether the coin is an immature coinbase output."),
+                ),
+                (
+                    "block_height",
+                    nullable(integer("Height of the block the coin was confirmed in, if any.")),
+                ),
+                (
+                    "block_time",
+                    nullable(integer("Time of the block the coin was confirmed in, if any.")),
+                ),
+            ]),
+        ),
+        (
+            "CoinsSnapshot",
+            object(&[
+                (
+                    "descriptor",
+                    string("The descriptor of the wallet the coins belong to."),
+                ),
+                (
+                    "tip_height",
+                    integer("Height of the tip as of which the coins were exported."),
+                ),
+                (
+                    "tip_hash",
+                    string("Hash of the tip as of which the coins were exported."),
+                ),
+                (
+                    "receive_index",
+                    integer("Next derivation index for receive addresses."),
+                ),
+                (
+                    "change_index",
+                    integer("Next derivation index for change addresses."),
+                ),
+                (
+                    "coins",
+                    array(reference("SnapshotCoin"), "The unspent coins of the wallet."),
+                ),
+                (
+                    "transactions",
+                    array(
+                        string("Hex-encoded transaction."),
+                        "The transactions creating the coins.",
+                    ),
+                ),
+            ]),
+        ),
+        (
+            "GetBalanceResult",
+            object(&[
+                (
+                    "confirmed",
+                    integer("Value of the confirmed unspent coins, in satoshis."),
+                ),
+                (
+                    "unconfirmed_from_self",
+                    integer(
+                        "Value of the unconfirmed coins created by a transaction spending only \
+                         coins of the wallet, in satoshis.",
+                    ),
+                ),
+                (
+                    "unconfirmed",
+                    integer("Value of the other unconfirmed coins, in satoshis."),
+                ),
+                (
+                    "immature",
+                    integer("Value of the immature coinbase deposits, in satoshis."),
+                ),
+                (
+                    "spending",
+                    integer("Value of the coins spent by an unconfirmed transaction, in satoshis."),
+                ),
+            ]),
+        ),
+        (
+            "FeeHistogramBucket",
+            object(&[
+                (
+                    "feerate",
+                    integer("Lower bound of the feerates of this bucket, in sats/vb."),
+                ),
+                (
+                    "vsize",
+                    integer("Total virtual size of the mempool transactions in this bucket."),
+                ),
+            ]),
+        ),
+        (
+            "GetMempoolStatsResult",
+            object(&[
+                (
+                    "fee_histogram",
+                    array(
+                        reference("FeeHistogramBucket"),
+                        "The mempool transactions by feerate, from the highest to the lowest. \
+                         Empty if the backend doesn't expose them.",
+                    ),
+                ),
+                (
+                    "min_relay_feerate",
+                    nullable(integer(
+                        "Minimum feerate for a transaction to be relayed, in sats/vb.",
+                    )),
+                ),
+                (
+                    "mempool_min_feerate",
+                    nullable(integer(
+                        "Minimum feerate for a transaction to enter the mempool at the moment, \
+                         in sats/vb.",
+                    )),
+                ),
+            ]),
+        ),
+        (
+            "GetAddressResult",
+            object(&[
+                ("address", string("A Bitcoin address.")),
+                (
+                    "derivation_index",
+                    integer("The derivation index for this address."),
+                ),
+            ]),
+        ),
+        (
+            "AddressInfo",
+            object(&[
+                ("index", integer("Derivation index.")),
+                ("receive", string("Receive address.")),
+                ("change", string("Change address.")),
+            ]),
+        ),
+        (
+            "ListAddressesResult",
+            object(&[(
+                "addresses",
+                array(
+                    reference("AddressInfo"),
+                    "The addresses at each derivation index.",
+                ),
+            )]),
+        ),
+        (
+            "CreateAddressStatementResult",
+            object(&[
+                (
+                    "message",
+                    string("The statement listing the receive addresses, to sign."),
+                ),
+                (
+                    "psbt",
+                    string(
+                        "Base64-encoded PSBT signing the statement (BIP322) for its first \
+                         address, to be signed by the signers of the primary path.",
+                    ),
+                ),
+            ]),
+        ),
+        (
+            "SignedAddressStatement",
+            object(&[
+                (
+                    "address",
+                    string("The first address of the statement, it is signed for."),
+                ),
+                ("message", string("The statement.")),
+                (
+                    "signature",
+                    string("Base64-encoded BIP322 signature of the statement."),
+                ),
+            ]),
+        ),
+        (
+            "StatementAddress",
+            object(&[
+                ("index", integer("Derivation index.")),
+                ("address", string("Receive address.")),
+            ]),
+        ),
+        (
+            "VerifyAddressStatementResult",
+            object(&[(
+                "addresses",
+                array(
+                    reference("StatementAddress"),
+                    "The addresses listed by the statement.",
+                ),
+            )]),
+        ),
+        (
+            "GetSilentPaymentAddressResult",
+            object(&[
+                ("address", string("The Silent Payment (BIP352) address of the wallet.")),
+                (
+                    "fingerprint",
+                    string("Master fingerprint of the hot signer its keys are derived from."),
+                ),
+                (
+                    "scanned_height",
+                    integer("Height up to which the chain was scanned for payments to it."),
+                ),
+            ]),
+        ),
+        (
+            "ListSilentPaymentsEntry",
+            object(&[
+                (
+                    "outpoint",
+                    string("Transaction id and output index of the payment, as txid:vout."),
+                ),
+                ("amount", integer("Value of the payment in satoshis.")),
+                (
+                    "block_height",
+                    integer("Block height the payment was confirmed at."),
+                ),
+                (
+                    "spend_info",
+                    json!({ "oneOf": [reference("LCSpendInfo"), { "type": "null" }] }),
+                ),
+            ]),
+        ),
+        (
+            "ListSilentPaymentsResult",
+            object(&[(
+                "payments",
+                array(
+                    reference("ListSilentPaymentsEntry"),
+                    "The Silent Payments to the wallet found so far.",
+                ),
+            )]),
+        ),
+        (
+            "SweepSilentPaymentsResult",
+            object(&[
+                ("txid", string("Id of the broadcast sweep transaction.")),
+                (
+                    "address",
+                    string("Receive address of the wallet the payments were swept to."),
+                ),
+                ("amount", integer("Value received at this address, in satoshis.")),
+                ("fee", integer("Fee of the sweep transaction, in satoshis.")),
+            ]),
+        ),
+        (
+            "DerivationIndexStats",
+            object(&[
+                (
+                    "next_index",
+                    integer("Derivation index of the next address to be generated."),
+                ),
+                (
+                    "highest_used_index",
+                    nullable(integer(
+                        "Highest derivation index of an address which received a coin, if any.",
+                    )),
+                ),
+                (
+                    "gaps",
+                    array(
+                        json!({
+                            "type": "array",
+                            "items": { "type": "integer", "minimum": 0 },
+                            "minItems": 2,
+                            "maxItems": 2,
+                        }),
+                        "Ranges of generated addresses which never received a coin, as \
+                         inclusive [first, last] derivation indexes.",
+                    ),
+                ),
+                (
+                    "largest_gap",
+                    integer("Number of addresses in the largest gap."),
+                ),
+            ]),
+        ),
+        (
+            "HealthCheck",
+            object(&[
+                (
+                    "name",
+                    json!({
+                        "type": "string",
+                        "enum": ["backend", "drafts", "database", "coins", "derivation_indexes"],
+                        "description": "What is checked.",
+                    }),
+                ),
+                ("status", health_status()),
+                (
+                    "messages",
+                    array(string("A problem found."), "The problems found, if any."),
+                ),
+            ]),
+        ),
+        (
+            "MissingSignatures",
+            object(&[
+                (
+                    "path",
+                    spending_path("\"primary\", or {\"recovery\": timelock}."),
+                ),
+                ("count", integer("Number of signatures still needed.")),
+                (
+                    "signers",
+                    array(
+                        string("Fingerprint of a signer."),
+                        "Fingerprints of the keys of this path which did not sign yet.",
+                    ),
+                ),
+            ]),
+        ),
+        (
+            "InputFinalization",
+            object(&[
+                (
+                    "path",
+                    nullable(spending_path(
+                        "The path this input can be finalized through: \"primary\", or \
+                         {\"recovery\": timelock}. Null if it can't be finalized.",
+                    )),
+                ),
+                (
+                    "missing",
+                    array(
+                        reference("MissingSignatures"),
+                        "The signatures missing for each available path which isn't satisfied.",
+                    ),
+                ),
+                (
+                    "error",
+                    nullable(string("Why the input can't be finalized.")),
+                ),
+            ]),
+        ),
+        (
+            "FinalizePsbtResult",
+            object(&[
+                (
+                    "complete",
+                    boolean("Whether all the inputs can be finalized."),
+                ),
+                (
+                    "inputs",
+                    array(
+                        reference("InputFinalization"),
+                        "The outcome for each input, in order.",
+                    ),
+                ),
+            ]),
+        ),
+        (
+            "HealthCheckResult",
+            object(&[
+                ("status", health_status()),
+                (
+                    "checks",
+                    array(reference("HealthCheck"), "The result of each check."),
+                ),
+            ]),
+        ),
+        (
+            "GetDerivationIndexesResult",
+            object(&[
+                ("receive", reference("DerivationIndexStats")),
+                ("change", reference("DerivationIndexStats")),
+                (
+                    "lookahead",
+                    integer(
+                        "Number of addresses watched from the highest of the next derivation \
+                         indexes.",
+                    ),
+                ),
+                (
+                    "watched_up_to",
+                    integer("Highest derivation index of the addresses watched for coins."),
+                ),
+            ]),
+        ),
+        (
+            "HotSignerXpub",
+            object(&[
+                (
+                    "derivation_path",
+                    string("Hardened derivation path of the xpub from the master key."),
+                ),
+                (
+                    "xpub",
+                    string("Extended public key at this derivation path."),
+                ),
+                (
+                    "key",
+                    string(
+                        "The xpub with its origin (master fingerprint and derivation path), \
+                         as used in descriptors.",
+                    ),
+                ),
+            ]),
+        ),
+        (
+            "SignerLossPath",
+            object(&[
+                (
+                    "timelock",
+                    nullable(integer(
+                        "Timelock of the recovery path in blocks, null for the primary path.",
+                    )),
+                ),
+                ("threshold", integer("How many keys of the path must sign.")),
+                ("keys", integer("How many keys there are in the path.")),
+                (
+                    "remaining_keys",
+                    integer("How many keys of the path don't belong to a lost signer."),
+                ),
+                (
+                    "usable",
+                    boolean("Whether enough keys remain to spend through this path."),
+                ),
+            ]),
+        ),
+        (
+            "SignerLossCoin",
+            object(&[
+                ("outpoint", string("Outpoint of the coin.")),
+                ("amount", integer("Value of the coin in satoshis.")),
+                (
+                    "spendable_now",
+                    boolean(
+                        "Whether the coin can be spent in the next block without the lost signers.",
+                    ),
+                ),
+                (
+                    "spendable_at_height",
+                    nullable(integer(
+                        "Height from which the coin can be spent without the lost signers. Null \
+                         if it is unconfirmed or can't be spent anymore.",
+                    )),
+                ),
+            ]),
+        ),
+        (
+            "SignerLossResult",
+            object(&[
+                (
+                    "paths",
+                    array(
+                        reference("SignerLossPath"),
+                        "The primary path, then the recovery paths by increasing timelock.",
+                    ),
+                ),
+                (
+                    "spendable_after",
+                    nullable(integer(
+                        "How many blocks after its confirmation a coin, including a future \
+                         deposit, can be spent without the lost signers. Null if coins can't be \
+                         spent anymore.",
+                    )),
+                ),
+                (
+                    "coins",
+                    array(
+                        reference("SignerLossCoin"),
+                        "The coins which aren't spent yet.",
+                    ),
+                ),
+            ]),
+        ),
+        (
+            "ImportHotSignerResult",
+            object(&[(
+                "fingerprint",
+                string("Master fingerprint of the imported hot signer."),
+            )]),
+        ),
+        (
+            "SignPsbtResult",
+            object(&[
+                (
+                    "fingerprint",
+                    string("Master fingerprint of the hot signer which signed."),
+                ),
+                (
+                    "psbt",
+                    string("Base64-encoded PSBT, with the signatures of the signer."),
+                ),
+            ]),
+        ),
+        (
+            "HotSignerXpubsResult",
+            object(&[
+                (
+                    "fingerprint",
+                    string("Master fingerprint of the hot signer."),
+                ),
+                (
+                    "xpubs",
+                    array(reference("HotSignerXpub"), "One entry per derivation path."),
+                ),
+            ]),
+        ),
+        (
+            "LintWarningEntry",
+            object(&[
+                (
+                    "code",
+                    string(
+                        "Kind of risky construction: one of 'short_timelock', 'long_timelock', \
+                         'same_device_model', 'recovery_with_primary_keys', \
+                         'hot_signer_recovery' or 'shared_signer'.",
+                    ),
+                ),
+                (
+                    "message",
+                    string("Human readable explanation of the warning."),
+                ),
+            ]),
+        ),
+        (
+            "LintDescriptorResult",
+            object(&[(
+                "warnings",
+                array(
+                    reference("LintWarningEntry"),
+                    "Risky constructions found, if any.",
+                ),
+            )]),
+        ),
+        (
+            "LCSpendInfo",
+            object(&[
+                ("txid", string("Spending transaction's id.")),
+                (
+                    "height",
+                    nullable(integer(
+                        "Block height the spending transaction was included at, if confirmed.",
+                    )),
+                ),
+            ]),
+        ),
+        (
+            "ListCoinsEntry",
+            object(&[
+                ("amount", integer("Value of the coin in satoshis.")),
+                (
+                    "outpoint",
+                    string("Transaction id and output index of this coin, as txid:vout."),
+                ),
+                (
+                    "address",
+                    string("Address containing the script pubkey of the coin."),
+                ),
+                (
+                    "block_height",
+                    nullable(integer(
+                        "Block height the transaction was confirmed at, if any.",
+                    )),
+                ),
+                (
+                    "derivation_index",
+                    integer("Derivation index used to create the coin deposit address."),
+                ),
+                (
+                    "spend_info",
+                    json!({ "oneOf": [reference("LCSpendInfo"), { "type": "null" }] }),
+                ),
+                (
+                    "is_immature",
+                    boolean("Whether the coin is the output of a still immature coinbase."),
+                ),
+                (
+                    "maturity_height",
+                    nullable(integer(
+                        "For an immature coin, the height of the first block which may include a \
+                         transaction spending it.",
+                    )),
+                ),
+                (
+                    "is_change",
+                    boolean("Whether the coin address was derived from the change descriptor."),
+                ),
+                (
+                    "is_from_self",
+                    boolean("Whether the coin and its unconfirmed ancestors are from this wallet."),
+                ),
+            ]),
+        ),
+        (
+            "ListCoinsResult",
+            object(&[(
+                "coins",
+                array(reference("ListCoinsEntry"), "The coins of the wallet."),
+            )]),
+        ),
+        (
+            "CreateSpendResult",
+            json!({ "oneOf": [create_spend_success, create_spend_insufficient_funds] }),
+        ),
+        (
+            "ListSpendEntry",
+            object(&[
+                (
+                    "psbt",
+                    string("Base64-encoded PSBT of the Spend transaction."),
+                ),
+                (
+                    "updated_at",
+                    nullable(integer(
+                        "UNIX timestamp of the last time this PSBT was updated.",
+                    )),
+                ),
+                (
+                    "conflicting_spends",
+                    array(
+                        string("Txid of a stored Spend transaction."),
+                        "The other stored Spend transactions spending some of the same coins.",
+                    ),
+                ),
+                (
+                    "invalidated_by",
+                    nullable(string(
+                        "Txid of the confirmed transaction which spent one of its coins.",
+                    )),
+                ),
+                (
+                    "reviews",
+                    array(
+                        reference("SpendReview"),
+                        "The latest review of each signer, in the order they were made.",
+                    ),
+                ),
+                (
+                    "review_state",
+                    json!({
+                        "type": "string",
+                        "enum": ["pending", "approved", "changes_requested", "rejected"],
+                        "description": "Where the review stands, all signers considered.",
+                    }),
+                ),
+            ]),
+        ),
+        (
+            "SpendReview",
+            object(&[
+                ("signer", string("Master fingerprint of the signer.")),
+                ("decision", review_decision()),
+                ("comment", nullable(string("Comment of the signer."))),
+                (
+                    "reviewed_at",
+                    integer("UNIX timestamp of the review."),
+                ),
+            ]),
+        ),
+        (
+            "ListSpendResult",
+            object(&[(
+                "spend_txs",
+                array(
+                    reference("ListSpendEntry"),
+                    "The stored Spend transactions.",
+                ),
+            )]),
+        ),
+        (
+            "TransactionInfo",
+            object(&[
+                ("tx", string("Hex encoded Bitcoin transaction.")),
+                (
+                    "height",
+                    nullable(integer("Block height of the transaction, if confirmed.")),
+                ),
+                (
+                    "time",
+                    nullable(integer("Block time of the transaction, if confirmed.")),
+                ),
+            ]),
+        ),
+        (
+            "ListTransactionsResult",
+            object(&[(
+                "transactions",
+                array(reference("TransactionInfo"), "The transactions."),
+            )]),
+        ),
+        (
+            "GetTransactionResult",
+            object(&[
+                ("tx", string("Hex encoded Bitcoin transaction.")),
+                (
+                    "height",
+                    nullable(integer("Block height of the transaction, if confirmed.")),
+                ),
+                (
+                    "time",
+                    nullable(integer("Block time of the transaction, if confirmed.")),
+                ),
+                (
+                    "psbt",
+                    nullable(string(
+                        "Base64-encoded PSBT stored for this transaction, if it's a Spend.",
+                    )),
+                ),
+                (
+                    "spent_coins",
+                    array(
+                        reference("ListCoinsEntry"),
+                        "Our coins spent by the transaction.",
+                    ),
+                ),
+                (
+                    "received_coins",
+                    array(
+                        reference("ListCoinsEntry"),
+                        "Our coins created by the transaction.",
+                    ),
+                ),
+                (
+                    "fee",
+                    nullable(integer(
+                        "Fee paid by the transaction in satoshis, if all its inputs are ours.",
+                    )),
+                ),
+                (
+                    "label",
+                    nullable(string("Label of the transaction, if any.")),
+                ),
+                (
+                    "inclusion_verified",
+                    nullable(boolean(
+                        "Whether a merkle proof of the inclusion of the transaction in its block \
+                         was verified. Null if not checked, as with a bitcoind backend.",
+                    )),
+                ),
+            ]),
+        ),
+        (
+            "ReceiptOutput",
+            object(&[
+                (
+                    "address",
+                    nullable(string(
+                        "Address paid. Null for scripts without an address, such as OP_RETURN.",
+                    )),
+                ),
+                ("amount", integer("Value of the output in satoshis.")),
+                (
+                    "label",
+                    nullable(string("Label of the output, or else of its address.")),
+                ),
+                ("is_ours", boolean("Whether the output pays to the wallet.")),
+            ]),
+        ),
+        (
+            "Receipt",
+            object(&[
+                ("txid", string("Id of the transaction.")),
+                ("network", string("The Bitcoin network of the wallet.")),
+                (
+                    "label",
+                    nullable(string("Label of the transaction, if any.")),
+                ),
+                (
+                    "block_height",
+                    integer("Height of the block including the transaction."),
+                ),
+                ("block_time", integer("Time of this block.")),
+                (
+                    "amount",
+                    integer(
+                        "Value received minus value spent by the wallet in the transaction, in \
+                         satoshis.",
+                    ),
+                ),
+                (
+                    "fee",
+                    nullable(integer(
+                        "Fee paid by the transaction in satoshis, if all its inputs are ours.",
+                    )),
+                ),
+                (
+                    "outputs",
+                    array(
+                        reference("ReceiptOutput"),
+                        "The outputs of the transaction.",
+                    ),
+                ),
+                (
+                    "explorer_link",
+                    string("Link to the transaction on a block explorer."),
+                ),
+            ]),
+        ),
+        (
+            "GetReceiptResult",
+            object(&[
+                ("receipt", reference("Receipt")),
+                (
+                    "pdf",
+                    string("The receipt as a PDF document, encoded in base64."),
+                ),
+            ]),
+        ),
+        ("EventEntry", json!({ "oneOf": events })),
+        (
+            "ListEventsResult",
+            object(&[(
+                "events",
+                array(reference("EventEntry"), "The events, oldest first."),
+            )]),
+        ),
+        (
+            "CreateRecoveryResult",
+            object(&[(
+                "psbt",
+                string("PSBT of the recovery transaction, encoded as base64."),
+            )]),
+        ),
+        (
+            "RecoveryScheduleBucket",
+            object(&[
+                ("start_height", integer("First block of the range.")),
+                ("end_height", integer("Last block of the range.")),
+                (
+                    "amount",
+                    integer(
+                        "Value in satoshis of the coins whose recovery path becomes available \
+                         within the range.",
+                    ),
+                ),
+                ("coins", integer("Number of these coins.")),
+            ]),
+        ),
+        (
+            "GetRecoveryScheduleResult",
+            object(&[
+                ("timelock", integer("Timelock of the recovery path, in blocks.")),
+                ("tip_height", integer("Height of the tip of the chain.")),
+                (
+                    "available",
+                    integer(
+                        "Value in satoshis of the coins already spendable through the recovery \
+                         path.",
+                    ),
+                ),
+                (
+                    "buckets",
+                    array(
+                        reference("RecoveryScheduleBucket"),
+                        "The ranges of blocks from the next one until the last coin becomes \
+                         spendable through the recovery path.",
+                    ),
+                ),
+            ]),
+        ),
+        (
+            "ReadinessCheck",
+            object(&[
+                ("item", readiness_item()),
+                (
+                    "done",
+                    boolean("Whether this item of the checklist is done."),
+                ),
+                (
+                    "completed_at",
+                    nullable(integer(
+                        "Timestamp at which the user marked the item as done, if they did.",
+                    )),
+                ),
+            ]),
+        ),
+        (
+            "RecoveryReadiness",
+            object(&[
+                (
+                    "score",
+                    integer("Percentage of the checklist which is done."),
+                ),
+                (
+                    "checks",
+                    array(reference("ReadinessCheck"), "The items of the checklist."),
+                ),
+                (
+                    "expiring_coins",
+                    integer(
+                        "Number of coins whose first recovery path is available within 10% of \
+                         its timelock.",
+                    ),
+                ),
+            ]),
+        ),
+        (
+            "ScheduledSpendEntry",
+            object(&[
+                ("txid", string("Txid of the scheduled transaction.")),
+                ("tx", string("The fully signed transaction, hex encoded.")),
+                (
+                    "locktime",
+                    integer("Locktime of the transaction, as a block height or a timestamp."),
+                ),
+                (
+                    "scheduled_at",
+                    integer("Timestamp at which it was scheduled."),
+                ),
+            ]),
+        ),
+        (
+            "ListScheduledSpendsResult",
+            object(&[(
+                "scheduled_spends",
+                array(
+                    reference("ScheduledSpendEntry"),
+                    "The scheduled transactions, in the order they were scheduled.",
+                ),
+            )]),
+        ),
+        (
+            "TemplateRecipient",
+            object(&[
+                ("address", string("Address of the recipient.")),
+                (
+                    "amount",
+                    nullable(integer(
+                        "Amount to send in sats, or null to ask for it on instantiation.",
+                    )),
+                ),
+            ]),
+        ),
+        (
+            "Schedule",
+            object(&[
+                (
+                    "every_days",
+                    integer("Create a draft every this many days."),
+                ),
+                (
+                    "last_draft_at",
+                    nullable(integer(
+                        "Timestamp at which the last draft was created from the template.",
+                    )),
+                ),
+            ]),
+        ),
+        (
+            "SpendTemplate",
+            object(&[
+                ("name", string("Name identifying the template.")),
+                (
+                    "recipients",
+                    array(
+                        reference("TemplateRecipient"),
+                        "The recipients of the payment.",
+                    ),
+                ),
+                (
+                    "feerate",
+                    integer("Preferred feerate for the transaction, in sats/vb."),
+                ),
+                (
+                    "coins",
+                    array(
+                        json!({ "type": "string" }),
+                        "Coins to spend, as txid:vout. Selected automatically if empty.",
+                    ),
+                ),
+                (
+                    "schedule",
+                    json!({ "oneOf": [reference("Schedule"), { "type": "null" }] }),
+                ),
+            ]),
+        ),
+        (
+            "ListSpendTemplatesResult",
+            object(&[(
+                "templates",
+                array(reference("SpendTemplate"), "The templates, sorted by name."),
+            )]),
+        ),
+        (
+            "GetLabelsResult",
+            object(&[(
+                "labels",
+                map(
+                    json!({ "type": "string" }),
+                    "Mapping from the labelled items to their label.",
+                ),
+            )]),
+        ),
+        (
+            "LabelsCsvRecord",
+            object(&[
+                ("line", integer("Line of the file the record starts on.")),
+                ("txid", string("Txid of the transaction.")),
+            ]),
+        ),
+        (
+            "RejectedRecord",
+            object(&[
+                ("line", integer("Line of the file the record starts on.")),
+                ("reason", string("Why the record couldn't be read.")),
+            ]),
+        ),
+        (
+            "ImportLabelsCsvResult",
+            object(&[
+                ("imported", integer("Number of transactions labelled.")),
+                (
+                    "already_labelled",
+                    array(
+                        reference("LabelsCsvRecord"),
+                        "Records for transactions which kept their existing label.",
+                    ),
+                ),
+                (
+                    "unmatched",
+                    array(
+                        reference("LabelsCsvRecord"),
+                        "Records for transactions which aren't transactions of the wallet.",
+                    ),
+                ),
+                (
+                    "rejected",
+                    array(reference("RejectedRecord"), "Records which couldn't be read."),
+                ),
+            ]),
+        ),
+    ];
+    Value::Object(
+        schemas
+            .into_iter()
+            .map(|(name, schema)| (name.to_string(), schema))
+            .collect(),
+    )
+}
+
+fn methods() -> Vec<Value> {
+    let txids = || {
+        array(
+            json!({ "type": "string" }),
+            "Ids of the transactions to retrieve.",
+        )
+    };
+    vec![
+        method("stop", "Stops liana daemon.", vec![], empty_object()),
+        method(
+            "getinfo",
+            "Get general information about the daemon.",
+            vec![],
+            reference("GetInfoResult"),
+        ),
+        method(
+            "getbalance",
+            "Get the value of the unspent coins of the wallet, by status.",
+            vec![],
+            reference("GetBalanceResult"),
+        ),
+        method(
+            "getmempoolstats",
+            "Get the feerates paid by the transactions in the mempool of the Bitcoin backend and \
+             the minimum feerates it accepts.",
+            vec![],
+            reference("GetMempoolStatsResult"),
+        ),
+        method(
+            "gethotsignerxpubs",
+            "Get the xpubs of a hot signer stored in the data directory at hardened derivation \
+             paths.",
+            vec![
+                param(
+                    "derivation_paths",
+                    true,
+                    array(
+                        string("A fully hardened derivation path, such as m/48'/0'/1'/2'."),
+                        "Derivation paths to get an xpub at.",
+                    ),
+                ),
+                param(
+                    "fingerprint",
+                    false,
+                    string("Master fingerprint of the hot signer, if several are stored."),
+                ),
+            ],
+            reference("HotSignerXpubsResult"),
+        ),
+        method(
+            "importhotsigner",
+            "Restore a hot signer into the data directory from a BIP39 mnemonic or SLIP-39 shares.",
+            vec![param(
+                "backup",
+                true,
+                json!({
+                    "oneOf": [
+                        string("A BIP39 mnemonic, or SLIP-39 shares one per line."),
+                        array(string("A SLIP-39 share."), "SLIP-39 shares."),
+                    ]
+                }),
+            )],
+            reference("ImportHotSignerResult"),
+        ),
+        method(
+            "signwithhotsigner",
+            "Sign a PSBT spending coins of the wallet with a hot signer stored in the data \
+             directory. The PSBT is neither stored nor broadcast.",
+            vec![
+                param("psbt", true, string("Base64-encoded PSBT.")),
+                param(
+                    "fingerprint",
+                    false,
+                    string("Master fingerprint of the hot signer, if several are stored."),
+                ),
+            ],
+            reference("SignPsbtResult"),
+        ),
+        method(
+            "simulatesignerloss",
+            "Check which spending paths remain usable, and when coins can be spent, if some \
+             signers were lost.",
+            vec![param(
+                "fingerprints",
+                true,
+                array(
+                    string("Master fingerprint of a lost signer."),
+                    "Master fingerprints of the lost signers.",
+                ),
+            )],
+            reference("SignerLossResult"),
+        ),
+        method(
+            "getnewaddress",
+            "Get a new receiving address.",
+            vec![],
+            reference("GetAddressResult"),
+        ),
+        method(
+            "listaddresses",
+            "List addresses given start_index and count.",
+            vec![
+                param(
+                    "start_index",
+                    false,
+                    integer("Index of the first address to list."),
+                ),
+                param("count", false, integer("Number of addresses to list.")),
+            ],
+            reference("ListAddressesResult"),
+        ),
+        method(
+            "getderivationindexes",
+            "Get the usage of the derivation indexes of the receive and change addresses.",
+            vec![],
+            reference("GetDerivationIndexesResult"),
+        ),
+        method(
+            "healthcheck",
+            "Check the Bitcoin backend, the database and the coins of the wallet.",
+            vec![],
+            reference("HealthCheckResult"),
+        ),
+        method(
+            "updatederivationindexes",
+            "Increase the next derivation indexes, extending the window of watched addresses.",
+            vec![
+                param(
+                    "receive",
+                    false,
+                    integer("New derivation index of the next receive address."),
+                ),
+                param(
+                    "change",
+                    false,
+                    integer("New derivation index of the next change address."),
+                ),
+            ],
+            reference("GetDerivationIndexesResult"),
+        ),
+        method(
+            "createaddressstatement",
+            "Create a statement listing the next receive addresses, to sign for its first one.",
+            vec![
+                param("count", true, integer("Number of addresses to list.")),
+                param(
+                    "start_index",
+                    false,
+                    integer(
+                        "Index of the first address to list. Defaults to the next receive \
+                         address.",
+                    ),
+                ),
+            ],
+            reference("CreateAddressStatementResult"),
+        ),
+        method(
+            "finalizeaddressstatement",
+            "Finalize the signed PSBT of an address statement.",
+            vec![
+                param("message", true, string("The statement.")),
+                param(
+                    "psbt",
+                    true,
+                    string("Base64-encoded PSBT of the statement, signed."),
+                ),
+            ],
+            reference("SignedAddressStatement"),
+        ),
+        method(
+            "verifyaddressstatement",
+            "Check the signature of an address statement, of any wallet.",
+            vec![
+                param(
+                    "address",
+                    true,
+                    string("The first address of the statement."),
+                ),
+                param("message", true, string("The statement.")),
+                param(
+                    "signature",
+                    true,
+                    string("Base64-encoded BIP322 signature of the statement."),
+                ),
+            ],
+            reference("VerifyAddressStatementResult"),
+        ),
+        method(
+            "getsilentpaymentaddress",
+            "Get the Silent Payment (BIP352) address of the wallet. The first call enables \
+             scanning the new blocks for payments to it, with keys of a hot signer.",
+            vec![param(
+                "fingerprint",
+                false,
+                string(
+                    "Master fingerprint of the hot signer to derive the keys from, if several \
+                     are stored. Ignored once enabled.",
+                ),
+            )],
+            reference("GetSilentPaymentAddressResult"),
+        ),
+        method(
+            "listsilentpayments",
+            "List the Silent Payments to the wallet found so far.",
+            vec![],
+            reference("ListSilentPaymentsResult"),
+        ),
+        method(
+            "sweepsilentpayments",
+            "Sweep the unspent Silent Payments to a new receive address of the wallet.",
+            vec![param(
+                "feerate",
+                true,
+                integer("Feerate of the sweep transaction, in sats/vbyte."),
+            )],
+            reference("SweepSilentPaymentsResult"),
+        ),
+        method(
+            "lintdescriptor",
+            "Check a descriptor for valid but risky constructions.",
+            vec![
+                param(
+                    "descriptor",
+                    false,
+                    string("Descriptor to check. Defaults to the main descriptor of the wallet."),
+                ),
+                param(
+                    "min_timelock",
+                    false,
+                    integer("Warn about recovery paths with a shorter timelock, in blocks."),
+                ),
+                param(
+                    "max_timelock",
+                    false,
+                    integer("Warn about recovery paths with a longer timelock, in blocks."),
+                ),
+            ],
+            reference("LintDescriptorResult"),
+        ),
+        method(
+            "listcoins",
+            "List all wallet transaction outputs.",
+            vec![
+                param(
+                    "statuses",
+                    false,
+                    array(
+                        json!({
+                            "type": "string",
+                            "enum": ["unconfirmed", "confirmed", "spending", "spent"],
+                        }),
+                        "List of statuses to filter coins by.",
+                    ),
+                ),
+                param(
+                    "outpoints",
+                    false,
+                    array(
+                        json!({ "type": "string" }),
+                        "List of outpoints to filter coins by, as txid:vout.",
+                    ),
+                ),
+            ],
+            reference("ListCoinsResult"),
+        ),
+        method(
+            "createspend",
+            "Create a new Spend transaction.",
+            vec![
+                param(
+                    "destinations",
+                    true,
+                    map(
+                        json!({ "type": "integer", "minimum": 0 }),
+                        "Map from Bitcoin address to value in satoshis.",
+                    ),
+                ),
+                param(
+                    "outpoints",
+                    true,
+                    array(
+                        json!({ "type": "string" }),
+                        "Coins to spend, as txid:vout. Selected automatically if empty.",
+                    ),
+                ),
+                param(
+                    "feerate",
+                    true,
+                    integer("Target feerate for the transaction, in satoshis per virtual byte."),
+                ),
+                param(
+                    "change_address",
+                    false,
+                    string("Address to be used for leftover amount, if any."),
+                ),
+                param(
+                    "locktime",
+                    false,
+                    integer(
+                        "Future locktime of the transaction, as a block height or a timestamp, \
+                         to schedule it with 'schedulespend'.",
+                    ),
+                ),
+                idempotency_key(),
+                param(
+                    "sighash",
+                    false,
+                    json!({
+                        "type": "string",
+                        "enum": SpendSighash::ALL.iter().map(SpendSighash::as_str).collect::<Vec<_>>(),
+                        "description": "Signature hash type the signers are asked to use for all \
+                                        the inputs. Defaults to 'all'.",
+                    }),
+                ),
+                param(
+                    "subtract_fee_from",
+                    false,
+                    array(
+                        json!({ "type": "string" }),
+                        "Destination addresses the fee is deducted from, split evenly between them.",
+                    ),
+                ),
+                param(
+                    "allow_future_witness",
+                    false,
+                    boolean(
+                        "Allow paying to a witness version which isn't defined yet. Anyone can \
+                         spend the coins sent to it until it is.",
+                    ),
+                ),
+            ],
+            reference("CreateSpendResult"),
+        ),
+        method(
+            "updatespend",
+            "Store a created Spend transaction.",
+            vec![
+                param(
+                    "psbt",
+                    true,
+                    string("Base64-encoded PSBT of a Spend transaction."),
+                ),
+                idempotency_key(),
+            ],
+            empty_object(),
+        ),
+        method(
+            "listspendtxs",
+            "List all stored Spend transactions.",
+            vec![
+                param("txids", false, txids()),
+                param(
+                    "statuses",
+                    false,
+                    array(
+                        json!({
+                            "type": "string",
+                            "enum": [
+                                "needs_signature",
+                                "fully_signed",
+                                "broadcastable",
+                                "conflicting",
+                            ],
+                        }),
+                        "List of statuses to filter Spend transactions by.",
+                    ),
+                ),
+                param(
+                    "signer",
+                    false,
+                    string(
+                        "Master fingerprint of a signer which can sign the Spend transactions. \
+                         Along with the 'needs_signature' status, only those it did not sign yet.",
+                    ),
+                ),
+            ],
+            reference("ListSpendResult"),
+        ),
+        method(
+            "reviewspend",
+            "Approve a stored Spend transaction, reject it or request changes to it, as a signer.",
+            vec![
+                param(
+                    "txid",
+                    true,
+                    string("Hex encoded txid of the Spend transaction to review."),
+                ),
+                param(
+                    "signer",
+                    true,
+                    string("Master fingerprint of the reviewing signer."),
+                ),
+                param("decision", true, review_decision()),
+                param(
+                    "comment",
+                    false,
+                    string("Why the signer approves, rejects or requests changes."),
+                ),
+            ],
+            empty_object(),
+        ),
+        method(
+            "delspendtx",
+            "Delete a stored Spend transaction.",
+            vec![param(
+                "txid",
+                true,
+                string("Hex encoded txid of the Spend transaction to delete."),
+            )],
+            empty_object(),
+        ),
+        method(
+            "schedulespend",
+            "Finalize a stored Spend PSBT with a future locktime, and broadcast it once reached.",
+            vec![
+                param(
+                    "txid",
+                    true,
+                    string("Hex encoded txid of the Spend transaction to schedule."),
+                ),
+                confirmed_amount(),
+            ],
+            empty_object(),
+        ),
+        method(
+            "listscheduledspends",
+            "List the scheduled Spend transactions.",
+            vec![],
+            reference("ListScheduledSpendsResult"),
+        ),
+        method(
+            "unschedulespend",
+            "Don't broadcast a scheduled Spend transaction.",
+            vec![param(
+                "txid",
+                true,
+                string("Hex encoded txid of the scheduled Spend transaction."),
+            )],
+            empty_object(),
+        ),
+        method(
+            "savespendtemplate",
+            "Store a spend template, replacing the one with the same name if any.",
+            vec![param("template", true, reference("SpendTemplate"))],
+            empty_object(),
+        ),
+        method(
+            "listspendtemplates",
+            "List all stored spend templates.",
+            vec![],
+            reference("ListSpendTemplatesResult"),
+        ),
+        method(
+            "delspendtemplate",
+            "Delete a stored spend template.",
+            vec![param(
+                "name",
+                true,
+                string("Name of the template to delete."),
+            )],
+            empty_object(),
+        ),
+        method(
+            "instantiatespendtemplate",
+            "Create a Spend transaction from a stored template.",
+            vec![
+                param("name", true, string("Name of the template to instantiate.")),
+                param(
+                    "amounts",
+                    false,
+                    map(
+                        integer("Amount in sats."),
+                        "Mapping from the address of a recipient to the amount to send, \
+                         required for the recipients without an amount.",
+                    ),
+                ),
+                param(
+                    "feerate",
+                    false,
+                    integer("Feerate overriding the one of the template, in sats/vb."),
+                ),
+            ],
+            reference("CreateSpendResult"),
+        ),
+        method(
+            "broadcastspend",
+            "Finalize a stored Spend PSBT, and broadcast it.",
+            vec![
+                param(
+                    "txid",
+                    true,
+                    string("Hex encoded txid of the Spend transaction to broadcast."),
+                ),
+                idempotency_key(),
+                confirmed_amount(),
+            ],
+            empty_object(),
+        ),
+        method(
+            "finalizepsbt",
+            "Check whether a PSBT can be finalized, without modifying or storing it.",
+            vec![param(
+                "psbt",
+                true,
+                string("Base64-encoded PSBT of a Spend transaction."),
+            )],
+            reference("FinalizePsbtResult"),
+        ),
+        method(
+            "rbfpsbt",
+            "Create a new RBF Spend transaction.",
+            vec![
+                param(
+                    "txid",
+                    true,
+                    string("Hex encoded txid of the Spend transaction to be replaced."),
+                ),
+                param(
+                    "is_cancel",
+                    true,
+                    boolean("Whether to cancel the transaction or simply bump the fee."),
+                ),
+                param(
+                    "feerate",
+                    false,
+                    integer("Target feerate for the RBF transaction, in sat/vb."),
+                ),
+            ],
+            reference("CreateSpendResult"),
+        ),
+        method(
+            "startrescan",
+            "Start rescanning the block chain from a given date.",
+            vec![param(
+                "timestamp",
+                true,
+                integer("Date to start rescanning from, as a UNIX timestamp."),
+            )],
+            empty_object(),
+        ),
+        method(
+            "listconfirmed",
+            "List of confirmed transactions of incoming and outgoing funds.",
+            vec![
+                param(
+                    "start",
+                    true,
+                    integer("Inclusive lower bound of the time window."),
+                ),
+                param(
+                    "end",
+                    true,
+                    integer("Inclusive upper bound of the time window."),
+                ),
+                param(
+                    "limit",
+                    true,
+                    integer("Maximum number of transactions to retrieve."),
+                ),
+            ],
+            reference("ListTransactionsResult"),
+        ),
+        method(
+            "listtransactions",
+            "List of transactions with the given txids.",
+            vec![param("txids", true, txids())],
+            reference("ListTransactionsResult"),
+        ),
+        method(
+            "gettransaction",
+            "Get a wallet transaction along with the wallet-relevant information about it.",
+            vec![param(
+                "txid",
+                true,
+                string("Id of the transaction to retrieve."),
+            )],
+            reference("GetTransactionResult"),
+        ),
+        method(
+            "getreceipt",
+            "Get a printable receipt of a confirmed transaction of the wallet.",
+            vec![
+                param("txid", true, string("Id of the transaction.")),
+                param(
+                    "explorer_url",
+                    false,
+                    string(
+                        "Prefix of the link to the transaction, to which its txid is appended. \
+                         Defaults to mempool.space.",
+                    ),
+                ),
+            ],
+            reference("GetReceiptResult"),
+        ),
+        method(
+            "exportsnapshot",
+            "Export the unspent coins of the wallet as of the current tip, to populate the \
+             database of another instance of the wallet without a rescan.",
+            vec![],
+            reference("CoinsSnapshot"),
+        ),
+        method(
+            "exporttransactions",
+            "List all the transactions of the wallet.",
+            vec![],
+            reference("ListTransactionsResult"),
+        ),
+        method(
+            "listevents",
+            "List the latest events of interest, such as unexpected spends of our coins.",
+            vec![param(
+                "since",
+                false,
+                integer("Only list the events following the one with this identifier."),
+            )],
+            reference("ListEventsResult"),
+        ),
+        method(
+            "createrecovery",
+            "Create a recovery transaction to sweep expired coins.",
+            vec![
+                param(
+                    "address",
+                    true,
+                    string("The Bitcoin address to sweep the coins to."),
+                ),
+                param(
+                    "feerate",
+                    true,
+                    integer("Target feerate for the transaction, in satoshis per virtual byte."),
+                ),
+                param(
+                    "timelock",
+                    false,
+                    nullable(integer(
+                        "Recovery path to be used, identified by its timelock in blocks.",
+                    )),
+                ),
+            ],
+            reference("CreateRecoveryResult"),
+        ),
+        method(
+            "getrecoveryschedule",
+            "Get how much value becomes spendable through a recovery path in each of the next \
+             ranges of blocks, to plan refreshing the coins in batches.",
+            vec![
+                param(
+                    "timelock",
+                    false,
+                    integer(
+                        "Recovery path, identified by its timelock in blocks. Defaults to the \
+                         first one.",
+                    ),
+                ),
+                param(
+                    "bucket_size",
+                    false,
+                    integer("Number of blocks in each range. Defaults to 1008 (about a week)."),
+                ),
+            ],
+            reference("GetRecoveryScheduleResult"),
+        ),
+        method(
+            "getrecoveryreadiness",
+            "Get the recovery readiness checklist of the wallet.",
+            vec![],
+            reference("RecoveryReadiness"),
+        ),
+        method(
+            "setrecoveryreadiness",
+            "Mark an item of the recovery readiness checklist as done or not done.",
+            vec![
+                param("item", true, readiness_item()),
+                param(
+                    "done",
+                    true,
+                    boolean("Whether the item is done. The 'timelock_margin' item can't be set."),
+                ),
+            ],
+            empty_object(),
+        ),
+        method(
+            "updatelabels",
+            "Update the labels.",
+            vec![param(
+                "labels",
+                true,
+                map(
+                    json!({ "type": ["string", "null"], "maxLength": 100 }),
+                    "Mapping from an address, txid or outpoint to its label, or null.",
+                ),
+            )],
+            empty_object(),
+        ),
+        method(
+            "getlabels",
+            "Get the labels for the given addresses, txids and outpoints.",
+            vec![param(
+                "items",
+                true,
+                array(
+                    json!({ "type": "string" }),
+                    "Items (address, txid or outpoint) of which to fetch the label.",
+                ),
+            )],
+            reference("GetLabelsResult"),
+        ),
+        method(
+            "importlabelscsv",
+            "Label the transactions of the wallet from a CSV file.",
+            vec![
+                param(
+                    "csv",
+                    true,
+                    string("Content of the file, with a txid, a label and an optional note per record."),
+                ),
+                param(
+                    "overwrite",
+                    false,
+                    boolean("Whether to replace the labels the transactions already have."),
+                ),
+            ],
+            reference("ImportLabelsCsvResult"),
+        ),
+    ]
+}
+
+/// The OpenRPC document describing the JSONRPC API of the daemon.
+pub fn openrpc_document() -> Value {
+    json!({
+        "openrpc": "1.2.6",
+        "info": {
+            "title": "Liana daemon API",
+            "version": VERSION.to_string(),
+        },
+        "methods": methods(),
+        "components": { "schemas": components() },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        commands::{
+            CreateAddressStatementResult, DerivationIndexStats, FeeHistogramBucket,
+            FinalizePsbtResult, GetBalanceResult, GetDerivationIndexesResult,
+            GetMempoolStatsResult, GetReceiptResult, GetRecoveryScheduleResult,
+            GetSilentPaymentAddressResult, GetTransactionResult, HealthCheck, HealthCheckResult,
+            HealthStatus, HotSignerXpub, HotSignerXpubsResult, ImportHotSignerResult,
+            ImportLabelsCsvResult, LCSpendInfo, LabelsCsvRecord, LintDescriptorResult,
+            LintWarningEntry, ListCoinsEntry, ListSilentPaymentsEntry, ListSilentPaymentsResult,
+            PollStats, RecoveryScheduleBucket, ScheduledSpendEntry, SignPsbtResult,
+            SignedAddressStatement, SignerLossCoin, SignerLossPath, SignerLossResult,
+            StatementAddress, SweepSilentPaymentsResult, TransactionInfo,
+            VerifyAddressStatementResult,
+        },
+        events::{Event, EventEntry},
+        labels_csv::RejectedRecord,
+        readiness::RecoveryReadiness,
+        receipt::{Receipt, ReceiptOutput},
+        snapshot::{CoinsSnapshot, SnapshotCoin},
+        templates::{Schedule, SpendTemplate, TemplateRecipient},
+    };
+
+    use std::{collections::BTreeSet, str::FromStr};
+
+    use liana::descriptors::{InputFinalization, MissingSignatures, SpendingPath};
+    use miniscript::bitcoin::{self, bip32, Amount, OutPoint};
+
+    // Check the schema of a result type lists the fields of its serialization.
+    fn assert_matches_schema<T: serde::Serialize>(name: &str, value: &T) {
+        let components = components();
+        let schema = &components[name];
+        let fields: BTreeSet<&String> = schema["properties"].as_object().unwrap().keys().collect();
+        let value = serde_json::to_value(value).unwrap();
+        assert_eq!(
+            fields,
+            value.as_object().unwrap().keys().collect(),
+            "{}",
+            name
+        );
+    }
+
+    #[test]
+    fn openrpc_document_consistency() {
+        let doc = openrpc_document();
+        let components = &doc["components"]["schemas"];
+        let mut names = BTreeSet::new();
+        for method in doc["methods"].as_array().unwrap() {
+            assert!(names.insert(method["name"].as_str().unwrap()));
+            // All the references point to a defined schema.
+            for schema in method["params"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|p| &p["schema"])
+                .chain(std::iter::once(&method["result"]["schema"]))
+            {
+                if let Some(r) = schema["$ref"].as_str() {
+                    let name = r.strip_prefix("#/components/schemas/").unwrap();
+                    assert!(components.get(name).is_some(), "{}", name);
+                }
+            }
+        }
+        assert_eq!(names.len(), 51);
+        // All the methods handled by the daemon are listed, but the discovery one itself.
+        let api = include_str!("api.rs");
+        let request_result = &api[api.find("fn request_result(").unwrap()..];
+        let request_result = &request_result[..request_result.find("\n}\n").unwrap()];
+        let handled: Vec<&str> = request_result
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix('"')?.split_once("\" =>"))
+            .map(|(name, _)| name)
+            .filter(|name| *name != "rpc.discover")
+            .collect();
+        assert_eq!(handled.len(), names.len());
+        for name in handled {
+            assert!(names.contains(name), "{} is missing from the schema", name);
+        }
+
+        let txid = bitcoin::Txid::from_str(
+            "0ed9b1c3ba5b2ec4a8d0c6d3f6a7e0a12c3e1b1c3a9ba1b5f5dbe2a8f3e0e6c1",
+        )
+        .unwrap();
+        assert_matches_schema(
+            "GetBalanceResult",
+            &GetBalanceResult {
+                confirmed: Amount::from_sat(100_000),
+                unconfirmed_from_self: Amount::ZERO,
+                unconfirmed: Amount::from_sat(20_000),
+                immature: Amount::ZERO,
+                spending: Amount::from_sat(5_000),
+            },
+        );
+        assert_matches_schema("PollStats", &PollStats::default());
+        let bucket = FeeHistogramBucket {
+            feerate: 10,
+            vsize: 250_000,
+        };
+        assert_matches_schema("FeeHistogramBucket", &bucket);
+        assert_matches_schema(
+            "GetMempoolStatsResult",
+            &GetMempoolStatsResult {
+                fee_histogram: vec![bucket],
+                min_relay_feerate: Some(1),
+                mempool_min_feerate: None,
+            },
+        );
+        let coin = SnapshotCoin {
+            outpoint: OutPoint::new(txid, 1),
+            amount: Amount::from_sat(50_000),
+            derivation_index: 4,
+            is_change: false,
+            is_immature: false,
+            block_height: Some(800_000),
+            block_time: Some(1_700_000_000),
+        };
+        assert_matches_schema("SnapshotCoin", &coin);
+        assert_matches_schema(
+            "CoinsSnapshot",
+            &CoinsSnapshot {
+                descriptor: liana::descriptors::LianaDescriptor::from_str("wsh(or_d(pk([aabbccdd]tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/<0;1>/*),and_v(v:pkh([abcdef01]tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/<0;1>/*),older(10000))))").unwrap(),
+                tip_height: 800_010,
+                tip_hash: bitcoin::BlockHash::from_str(
+                    "000000000000000000016a8a7b87bfa3fc1e6bd7d1f5b4cbef9c94d0bd7cd41e",
+                )
+                .unwrap(),
+                receive_index: 5,
+                change_index: 2,
+                coins: vec![coin],
+                transactions: vec![],
+            },
+        );
+        let stats = DerivationIndexStats::new(10, &BTreeSet::from([2, 3]));
+        assert_matches_schema("DerivationIndexStats", &stats);
+        assert_matches_schema(
+            "GetDerivationIndexesResult",
+            &GetDerivationIndexesResult {
+                receive: stats.clone(),
+                change: stats,
+                lookahead: 200,
+                watched_up_to: 209,
+            },
+        );
+        let check = HealthCheck {
+            name: "backend".to_string(),
+            status: HealthStatus::Warning,
+            messages: vec!["The Bitcoin backend wasn't polled yet.".to_string()],
+        };
+        assert_matches_schema("HealthCheck", &check);
+        assert_matches_schema(
+            "HealthCheckResult",
+            &HealthCheckResult {
+                status: HealthStatus::Warning,
+                checks: vec![check],
+            },
+        );
+        assert_matches_schema(
+            "FinalizePsbtResult",
+            &FinalizePsbtResult {
+                complete: false,
+                inputs: vec![],
+            },
+        );
+        let missing = MissingSignatures {
+            path: SpendingPath::Recovery(10_000),
+            count: 1,
+            signers: vec![bip32::Fingerprint::from_str("aabbccdd").unwrap()],
+        };
+        assert_matches_schema("MissingSignatures", &missing);
+        assert_matches_schema(
+            "InputFinalization",
+            &InputFinalization {
+                path: None,
+                missing: vec![missing],
+                error: Some("Missing signature.".to_string()),
+            },
+        );
+        let spend_info = LCSpendInfo { txid, height: None };
+        assert_matches_schema("LCSpendInfo", &spend_info);
+        let xpub = HotSignerXpub {
+            derivation_path: bip32::DerivationPath::from_str("m/48'/0'/1'/2'").unwrap(),
+            xpub: bip32::Xpub::from_str("xpub68JJTXc1MWK8KLW4HGLXZBJknja7kDUJuFHnM424LbziEXsfkh1WQCiEjjHw4zLqSUm4rvhgyGkkuRowE9tCJSgt3TQB5J3SKAbZ2SdcKST").unwrap(),
+            key: "[aabbccdd/48'/0'/1'/2']xpub68JJTXc1MWK8KLW4HGLXZBJknja7kDUJuFHnM424LbziEXsfkh1WQCiEjjHw4zLqSUm4rvhgyGkkuRowE9tCJSgt3TQB5J3SKAbZ2SdcKST".to_string(),
+        };
+        assert_matches_schema("HotSignerXpub", &xpub);
+        assert_matches_schema(
+            "HotSignerXpubsResult",
+            &HotSignerXpubsResult {
+                fingerprint: bip32::Fingerprint::from_str("aabbccdd").unwrap(),
+                xpubs: vec![xpub],
+            },
+        );
+        assert_matches_schema(
+            "ImportHotSignerResult",
+            &ImportHotSignerResult {
+                fingerprint: bip32::Fingerprint::from_str("aabbccdd").unwrap(),
+            },
+        );
+        assert_matches_schema(
+            "ImportLabelsCsvResult",
+            &ImportLabelsCsvResult {
+                imported: 1,
+                already_labelled: vec![LabelsCsvRecord { line: 2, txid }],
+                unmatched: vec![],
+                rejected: vec![RejectedRecord {
+                    line: 3,
+                    reason: "No label nor note.".to_string(),
+                }],
+            },
+        );
+        let path = SignerLossPath {
+            timelock: Some(10_000),
+            threshold: 1,
+            keys: 1,
+            remaining_keys: 1,
+            usable: true,
+        };
+        assert_matches_schema("SignerLossPath", &path);
+        let coin = SignerLossCoin {
+            outpoint: OutPoint::new(txid, 0),
+            amount: Amount::from_sat(10_000),
+            spendable_now: false,
+            spendable_at_height: Some(10_100),
+        };
+        assert_matches_schema("SignerLossCoin", &coin);
+        assert_matches_schema(
+            "SignerLossResult",
+            &SignerLossResult {
+                paths: vec![path],
+                spendable_after: Some(10_000),
+                coins: vec![coin],
+            },
+        );
+        let address = bitcoin::Address::from_str("bc1qvrl2849aggm6qry9ea7xqp2kk39j8vaa8r3cwg")
+            .unwrap()
+            .assume_checked();
+        assert_matches_schema(
+            "CreateAddressStatementResult",
+            &CreateAddressStatementResult {
+                message: "Liana receive addresses".to_string(),
+                psbt: bitcoin::Psbt::from_unsigned_tx(bitcoin::Transaction {
+                    version: bitcoin::transaction::Version(0),
+                    lock_time: bitcoin::absolute::LockTime::ZERO,
+                    input: vec![],
+                    output: vec![],
+                })
+                .unwrap(),
+            },
+        );
+        assert_matches_schema(
+            "SignPsbtResult",
+            &SignPsbtResult {
+                fingerprint: bip32::Fingerprint::from_str("aabbccdd").unwrap(),
+                psbt: bitcoin::Psbt::from_unsigned_tx(bitcoin::Transaction {
+                    version: bitcoin::transaction::Version(0),
+                    lock_time: bitcoin::absolute::LockTime::ZERO,
+                    input: vec![],
+                    output: vec![],
+                })
+                .unwrap(),
+            },
+        );
+        assert_matches_schema(
+            "SignedAddressStatement",
+            &SignedAddressStatement {
+                address: address.clone(),
+                message: "Liana receive addresses".to_string(),
+                signature: String::new(),
+            },
+        );
+        let statement_address = StatementAddress { index: 0, address };
+        assert_matches_schema("StatementAddress", &statement_address);
+        assert_matches_schema(
+            "VerifyAddressStatementResult",
+            &VerifyAddressStatementResult {
+                addresses: vec![statement_address],
+            },
+        );
+        assert_matches_schema(
+            "GetSilentPaymentAddressResult",
+            &GetSilentPaymentAddressResult {
+                address: "sp1qqgste7k9hx0qftg6qmwlkqtwuy6cycyavzmzj85c6qdfhjdpdjtdgqjuexzk6murw56suy3e0rd2cgqvycxttddwsvgxe2usfpxumr70xc9pkqwv".to_string(),
+                fingerprint: bip32::Fingerprint::default(),
+                scanned_height: 800_000,
+            },
+        );
+        let payment = ListSilentPaymentsEntry {
+            outpoint: OutPoint::new(txid, 0),
+            amount: Amount::from_sat(50_000),
+            block_height: 800_001,
+            spend_info: Some(LCSpendInfo { txid, height: None }),
+        };
+        assert_matches_schema("ListSilentPaymentsEntry", &payment);
+        assert_matches_schema(
+            "ListSilentPaymentsResult",
+            &ListSilentPaymentsResult {
+                payments: vec![payment],
+            },
+        );
+        assert_matches_schema(
+            "SweepSilentPaymentsResult",
+            &SweepSilentPaymentsResult {
+                txid,
+                address: bitcoin::Address::from_str("bc1qvrl2849aggm6qry9ea7xqp2kk39j8vaa8r3cwg")
+                    .unwrap()
+                    .assume_checked(),
+                amount: Amount::from_sat(49_000),
+                fee: Amount::from_sat(1_000),
+            },
+        );
+        let warning = LintWarningEntry {
+            code: "short_timelock".to_string(),
+            message: "Too short.".to_string(),
+        };
+        assert_matches_schema("LintWarningEntry", &warning);
+        assert_matches_schema(
+            "LintDescriptorResult",
+            &LintDescriptorResult {
+                warnings: vec![warning],
+            },
+        );
+        assert_matches_schema(
+            "ListCoinsEntry",
+            &ListCoinsEntry {
+                amount: Amount::from_sat(100_000),
+                outpoint: OutPoint::new(spend_info.txid, 0),
+                address: bitcoin::Address::from_str("bc1qvrl2849aggm6qry9ea7xqp2kk39j8vaa8r3cwg")
+                    .unwrap()
+                    .assume_checked(),
+                block_height: Some(1),
+                derivation_index: bip32::ChildNumber::from_normal_idx(0).unwrap(),
+                spend_info: Some(spend_info),
+                is_immature: false,
+                maturity_height: None,
+                is_change: false,
+                is_from_self: false,
+            },
+        );
+        let tx = bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: Vec::new(),
+            output: Vec::new(),
+        };
+        assert_matches_schema(
+            "TransactionInfo",
+            &TransactionInfo {
+                tx: tx.clone(),
+                height: None,
+                time: None,
+            },
+        );
+        assert_matches_schema(
+            "ScheduledSpendEntry",
+            &ScheduledSpendEntry {
+                txid: tx.compute_txid(),
+                tx: tx.clone(),
+                locktime: 0,
+                scheduled_at: 1_700_000_000,
+            },
+        );
+        assert_matches_schema(
+            "GetTransactionResult",
+            &GetTransactionResult {
+                tx,
+                height: None,
+                time: None,
+                psbt: None,
+                spent_coins: Vec::new(),
+                received_coins: Vec::new(),
+                fee: None,
+                label: None,
+                inclusion_verified: None,
+            },
+        );
+        let receipt = Receipt {
+            txid,
+            network: bitcoin::Network::Bitcoin,
+            label: None,
+            block_height: 800_000,
+            block_time: 1_690_000_000,
+            amount: -50_000,
+            fee: Some(Amount::from_sat(1_000)),
+            outputs: vec![ReceiptOutput {
+                address: Some("bc1qvrl2849aggm6qry9ea7xqp2kk39j8vaa8r3cwg".to_string()),
+                amount: Amount::from_sat(49_000),
+                label: Some("Rent".to_string()),
+                is_ours: false,
+            }],
+            explorer_link: format!("https://mempool.space/tx/{}", txid),
+        };
+        assert_matches_schema("ReceiptOutput", &receipt.outputs[0]);
+        assert_matches_schema("Receipt", &receipt);
+        assert_matches_schema(
+            "GetReceiptResult",
+            &GetReceiptResult {
+                receipt,
+                pdf: String::new(),
+            },
+        );
+        let bucket = RecoveryScheduleBucket {
+            start_height: 800_002,
+            end_height: 801_009,
+            amount: Amount::from_sat(50_000),
+            coins: 2,
+        };
+        assert_matches_schema("RecoveryScheduleBucket", &bucket);
+        assert_matches_schema(
+            "GetRecoveryScheduleResult",
+            &GetRecoveryScheduleResult {
+                timelock: 52_560,
+                tip_height: 800_000,
+                available: Amount::ZERO,
+                buckets: vec![bucket],
+            },
+        );
+        let readiness = RecoveryReadiness::new(&Default::default(), 0);
+        assert_matches_schema("RecoveryReadiness", &readiness);
+        assert_matches_schema("ReadinessCheck", &readiness.checks[0]);
+        let template = SpendTemplate {
+            name: "Rent".to_string(),
+            recipients: vec![TemplateRecipient {
+                address: bitcoin::Address::from_str("bc1qvrl2849aggm6qry9ea7xqp2kk39j8vaa8r3cwg")
+                    .unwrap(),
+                amount: None,
+            }],
+            feerate: 1,
+            coins: vec![OutPoint::new(txid, 0)],
+            schedule: Some(Schedule {
+                every_days: 30,
+                last_draft_at: None,
+            }),
+        };
+        assert_matches_schema("SpendTemplate", &template);
+        assert_matches_schema("TemplateRecipient", &template.recipients[0]);
+        assert_matches_schema("Schedule", template.schedule.as_ref().unwrap());
+        // Each type of event matches its own schema.
+        let events = vec![
+            Event::CoinReceived {
+                outpoint: OutPoint::new(txid, 0),
+                amount: Amount::from_sat(100_000),
+            },
+            Event::SpendConfirmed { txid, height: 1 },
+            Event::RecoveryPathSoonAvailable {
+                timelock: 52_560,
+                coins: vec![OutPoint::new(txid, 0)],
+                blocks_remaining: 10,
+            },
+            Event::BackendUnreachable {
+                error: "Connection refused".to_string(),
+            },
+            Event::BackendReachable,
+            Event::UnauthorizedSpend {
+                txid,
+                coins: vec![OutPoint::new(txid, 0)],
+                spending_path: None,
+            },
+            Event::ScheduledSpendBroadcast { txid },
+            Event::ScheduledSpendConflicted {
+                txid,
+                coins: vec![OutPoint::new(txid, 0)],
+            },
+            Event::BackendsDisagree {
+                disagreements: vec!["Tip height 100 against 90".to_string()],
+            },
+            Event::BackendsAgree,
+            Event::SpendSigned {
+                txid,
+                signers: vec![bip32::Fingerprint::from([0xaa, 0xbb, 0xcc, 0xdd])],
+            },
+            Event::SpendReviewed {
+                txid,
+                signer: bip32::Fingerprint::from([0xaa, 0xbb, 0xcc, 0xdd]),
+                decision: ReviewDecision::Rejected,
+            },
+            Event::SpendSaved { txid },
+            Event::SpendBroadcast { txid },
+            Event::RescanCompleted {
+                timestamp: 1_700_000_000,
+            },
+            Event::SettingsChanged {
+                change: "Derivation indexes updated.".to_string(),
+            },
+            Event::StaleTip {
+                height: 800_000,
+                minutes: 125,
+            },
+            Event::TipAdvanced { height: 800_001 },
+            Event::ClockSkewed { skew_secs: -9_000 },
+            Event::ClockSynced,
+        ];
+        let schemas = components()["EventEntry"]["oneOf"].clone();
+        assert_eq!(schemas.as_array().unwrap().len(), events.len());
+        for event in events {
+            let entry = serde_json::to_value(EventEntry {
+                id: 1,
+                time: 1_700_000_000,
+                level: event.level(),
+                event,
+            })
+            .unwrap();
+            let schema = schemas
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|s| s["properties"]["type"]["enum"][0] == entry["type"])
+                .unwrap();
+            let fields: BTreeSet<&String> =
+                schema["properties"].as_object().unwrap().keys().collect();
+            assert_eq!(fields, entry.as_object().unwrap().keys().collect());
+        }
+    }
+}
@@ -6,7 +6,7 @@
 use crate::{
     jsonrpc::{
         api,
-        rpc::{Request, Response},
+        rpc::{Error, Request, Response},
     },
     DaemonControl,
 };
@@ -22,6 +22,9 @@ use std::{
 // Maximum number of concurrent RPC connections we may accept.
 const MAX_CONNECTIONS: u32 = 16;
 
+// How often an idle connection checks whether we are shutting down.
+const SHUTDOWN_CHECK_INTERVAL: time::Duration = time::Duration::from_millis(100);
+
 // Read a command from the stream.
 //
 // In order to both treat commands separately (respond as soon as we read one), and support
@@ -82,9 +85,10 @@ fn read_command(
     }
 }
 
-// Handle all messages from this connection.
+// Handle all messages from this connection. Once we are shutting down, the command being
+// processed is completed but the following ones are rejected.
 fn connection_handler(
-    mut control: DaemonControl,
+    control: DaemonControl,
     mut stream: net::UnixStream,
     shutdown: sync::Arc<atomic::AtomicBool>,
 ) -> Result<(), io::Error> {
@@ -92,32 +96,47 @@ fn connection_handler(
     let mut end = 0;
     let mut cursor = 0;
 
-    while !shutdown.load(atomic::Ordering::Relaxed) {
-        let req = match read_command(&mut stream, &mut buf, &mut end, &mut cursor)? {
-            Some(req) => req,
-            None => {
+    // Don't block on reading forever, to notice we are shutting down.
+    stream.set_read_timeout(Some(SHUTDOWN_CHECK_INTERVAL))?;
+
+    loop {
+        let req = match read_command(&mut stream, &mut buf, &mut end, &mut cursor) {
+            Ok(Some(req)) => req,
+            Ok(None) => {
                 // Connection closed.
                 return Ok(());
             }
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                if shutdown.load(atomic::Ordering::Relaxed) {
+                    return Ok(());
+                }
+                continue;
+            }
+            Err(e) => return Err(e),
         };
 
         let req_id = req.id.clone();
-        if &req.method == "stop" {
-            shutdown.store(true, atomic::Ordering::Relaxed);
-            log::info!("Stopping the liana daemon.");
-        }
-
         log::trace!("JSONRPC request: {:?}", serde_json::to_string(&req));
-        let response =
-            api::handle_request(&mut control, req).unwrap_or_else(|e| Response::error(req_id, e));
+        let response = if shutdown.load(atomic::Ordering::Relaxed) {
+            Response::error(req_id, Error::shutting_down())
+        } else {
+            if &req.method == "stop" {
+                shutdown.store(true, atomic::Ordering::Relaxed);
+                log::info!("Stopping the liana daemon.");
+            }
+            api::handle_request(&control, req).unwrap_or_else(|e| Response::error(req_id, e))
+        };
         log::trace!("JSONRPC response: {:?}", serde_json::to_string(&response));
         if let Err(e) = serde_json::to_writer(&stream, &response) {
             log::error!("Error writing response: '{}'", e);
             return Ok(());
         }
     }
-
-    Ok(())
 }
 
 // FIXME: have a decent way to share the DaemonControl between connections. Maybe make it Clone?
@@ -166,6 +185,19 @@ pub fn rpcserver_loop(
             })?;
     }
 
+    // We don't accept new connections anymore. Wait for the commands being processed to
+    // complete, the connections are closed once they are done.
+    let remaining = connections_counter.load(atomic::Ordering::Relaxed);
+    if remaining > 0 {
+        log::info!(
+            "Waiting for {} JSONRPC connection(s) to complete their command.",
+            remaining
+        );
+    }
+    while connections_counter.load(atomic::Ordering::Relaxed) > 0 {
+        thread::sleep(time::Duration::from_millis(50));
+    }
+
     Ok(())
 }
 
@@ -398,6 +430,42 @@ mod tests {
         fs::remove_file(&socket_path).unwrap();
     }
 
+    #[test]
+    fn commands_rejected_when_shutting_down() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let (mut client, server) = net::UnixStream::pair().unwrap();
+        let shutdown = sync::Arc::new(atomic::AtomicBool::new(false));
+        let handler = thread::spawn({
+            let control = ms.control().clone();
+            let shutdown = shutdown.clone();
+            move || connection_handler(control, server, shutdown)
+        });
+        let read_response = |client: &mut net::UnixStream| {
+            let mut buf = [0; 4096];
+            let read = io::Read::read(client, &mut buf).unwrap();
+            serde_json::from_slice::<serde_json::Value>(&buf[..read]).unwrap()
+        };
+
+        // A command is processed as usual.
+        client
+            .write_all(b"{\"jsonrpc\": \"2.0\", \"id\": 0, \"method\": \"getinfo\"}\n")
+            .unwrap();
+        assert!(read_response(&mut client)["result"].is_object());
+
+        // Once shutting down, it's rejected.
+        shutdown.store(true, atomic::Ordering::Relaxed);
+        client
+            .write_all(b"{\"jsonrpc\": \"2.0\", \"id\": 1, \"method\": \"getinfo\"}\n")
+            .unwrap();
+        let response = read_response(&mut client);
+        assert_eq!(response["error"]["code"], 1_001);
+        assert!(response.get("result").is_none());
+
+        // The connection is closed when idle.
+        handler.join().unwrap().unwrap();
+        ms.shutdown();
+    }
+
     // TODO: debug on MacOS
     #[cfg(not(target_os = "macos"))]
     #[test]
@@ -0,0 +1,239 @@
+//! Labels of past transactions read from a CSV file, for users migrating from a wallet which can
+//! only export its history this way.
+//!
+//! Each record is a txid, a label and an optional note. The first record may be a header. Fields
+//! may be quoted, with quotes doubled inside them, as spreadsheets export them. The wallet stores a
+//! single label per transaction: the note is appended to the label.
+
+use std::{collections::HashMap, fmt, mem, str::FromStr};
+
+use miniscript::bitcoin;
+use serde::{Deserialize, Serialize};
+
+/// Maximum length of a label, as for the `updatelabels` command.
+pub const MAX_LABEL_LENGTH: usize = 100;
+
+const NOTE_SEPARATOR: &str = " - ";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelsCsvError(String);
+
+impl fmt::Display for LabelsCsvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The label of a transaction read from the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvLabel {
+    /// The line of the file the record starts on.
+    pub line: usize,
+    pub txid: bitcoin::Txid,
+    pub label: String,
+}
+
+/// A record of the file which couldn't be read, and why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RejectedRecord {
+    pub line: usize,
+    pub reason: String,
+}
+
+// Split the file into records of fields, along with the line each record starts on. Blank lines
+// are skipped.
+fn records(csv: &str) -> Result<Vec<(usize, Vec<String>)>, LabelsCsvError> {
+    let mut records = Vec::new();
+    let (mut fields, mut field) = (Vec::new(), String::new());
+    let (mut line, mut record_line) = (1, 1);
+    let mut in_quotes = false;
+
+    // Spreadsheets may start the file with a byte order mark.
+    let mut chars = csv.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                in_quotes = true;
+            }
+            '\n' if in_quotes => {
+                line += 1;
+                field.push(c);
+            }
+            ',' if !in_quotes => fields.push(mem::take(&mut field)),
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                fields.push(mem::take(&mut field));
+                records.push((record_line, mem::take(&mut fields)));
+                line += 1;
+                record_line = line;
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(LabelsCsvError(format!(
+            "Unterminated quoted field in the record on line {}.",
+            record_line
+        )));
+    }
+    if !fields.is_empty() || !field.is_empty() {
+        fields.push(field);
+        records.push((record_line, fields));
+    }
+
+    Ok(records
+        .into_iter()
+        .filter(|(_, fields)| fields.iter().any(|f| !f.trim().is_empty()))
+        .collect())
+}
+
+// The label to store for a transaction from the label and the note of the record.
+fn label_value(label: &str, note: &str) -> Result<String, String> {
+    let value = match (label.is_empty(), note.is_empty()) {
+        (true, true) => return Err("No label nor note.".to_string()),
+        (false, true) => label.to_string(),
+        (true, false) => note.to_string(),
+        (false, false) => format!("{}{}{}", label, NOTE_SEPARATOR, note),
+    };
+    if value.chars().count() > MAX_LABEL_LENGTH {
+        return Err(format!(
+            "The label and note are longer than {} characters together.",
+            MAX_LABEL_LENGTH
+        ));
+    }
+    Ok(value)
+}
+
+/// Read the transaction labels from this CSV file. The records which can't be imported are
+/// returned apart, along with the reason.
+pub fn parse(csv: &str) -> Result<(Vec<CsvLabel>, Vec<RejectedRecord>), LabelsCsvError> {
+    let mut records = records(csv)?;
+    if records
+        .first()
+        .map(|(_, fields)| fields[0].trim().eq_ignore_ascii_case("txid"))
+        .unwrap_or(false)
+    {
+        records.remove(0);
+    }
+    if records.is_empty() {
+        return Err(LabelsCsvError("The file contains no label.".to_string()));
+    }
+
+    let mut labels = Vec::with_capacity(records.len());
+    let mut rejected = Vec::new();
+    let mut lines_by_txid = HashMap::new();
+    for (line, fields) in records {
+        let fields: Vec<&str> = fields.iter().map(|f| f.trim()).collect();
+        let reject = |reason: String| RejectedRecord { line, reason };
+        let (txid, label, note) = match fields.as_slice() {
+            [txid, label] => (txid, label, &""),
+            [txid, label, note] => (txid, label, note),
+            _ => {
+                rejected.push(reject(format!(
+                    "Expected 2 or 3 fields (txid, label and note), found {}.",
+                    fields.len()
+                )));
+                continue;
+            }
+        };
+        let txid = match bitcoin::Txid::from_str(txid) {
+            Ok(txid) => txid,
+            Err(_) => {
+                rejected.push(reject(format!("Invalid txid '{}'.", txid)));
+                continue;
+            }
+        };
+        let label = match label_value(label, note) {
+            Ok(label) => label,
+            Err(reason) => {
+                rejected.push(reject(reason));
+                continue;
+            }
+        };
+        if let Some(first_line) = lines_by_txid.get(&txid) {
+            rejected.push(reject(format!(
+                "Transaction already labelled on line {}.",
+                first_line
+            )));
+            continue;
+        }
+        lines_by_txid.insert(txid, line);
+        labels.push(CsvLabel { line, txid, label });
+    }
+
+    Ok((labels, rejected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TXID_A: &str = "f7c3a8bd4d4a9d8b1d6e2ee6d4f6a1e1cbd6e4cf2e2bb3a8fb1c9a0e5c2d6e7f";
+    const TXID_B: &str = "0e5a8cfd8c1b8e4b69c6f8c6b1a6d3a5a6f6b7c2d8e9f0a1b2c3d4e5f6a7b8c9";
+    const TXID_C: &str = "5d2a7c1e9b3f4a6d8c0e2f4a6b8d0c2e4f6a8b0d2c4e6f8a0b2d4c6e8f0a2b4c";
+
+    #[test]
+    fn labels_csv() {
+        // A header, quoted fields with a comma, doubled quotes and a line break, CRLF line endings
+        // and a byte order mark.
+        let csv = format!(
+            "\u{feff}Txid,Label,Note\r\n{},Rent,\"March, paid late\"\r\n\r\n{},\"The \"\"big\"\" one\",\"two\nlines\"\r\n",
+            TXID_A, TXID_B
+        );
+        let (labels, rejected) = parse(&csv).unwrap();
+        assert!(rejected.is_empty());
+        assert_eq!(
+            labels,
+            vec![
+                CsvLabel {
+                    line: 2,
+                    txid: bitcoin::Txid::from_str(TXID_A).unwrap(),
+                    label: "Rent - March, paid late".to_string(),
+                },
+                CsvLabel {
+                    line: 4,
+                    txid: bitcoin::Txid::from_str(TXID_B).unwrap(),
+                    label: "The \"big\" one - two\nlines".to_string(),
+                }
+            ]
+        );
+
+        // No header, no note, and records which can't be imported.
+        let csv = format!(
+            "{},Salary\n{}, ,Bonus\nnotatxid,Coffee\n{},Again\n{},,\n{},{},Long note\n{}\n",
+            TXID_A,
+            TXID_B,
+            TXID_A,
+            TXID_C,
+            TXID_C,
+            "a".repeat(95),
+            TXID_C
+        );
+        let (labels, rejected) = parse(&csv).unwrap();
+        assert_eq!(
+            labels
+                .iter()
+                .map(|l| (l.line, l.label.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(1, "Salary"), (2, "Bonus")]
+        );
+        assert_eq!(
+            rejected.iter().map(|r| r.line).collect::<Vec<_>>(),
+            vec![3, 4, 5, 6, 7]
+        );
+        assert_eq!(
+            rejected[1].reason,
+            "Transaction already labelled on line 1."
+        );
+
+        // The file must be readable as a whole.
+        assert!(parse("Txid,Label\n").is_err());
+        assert!(parse(&format!("{},\"Unterminated\n", TXID_A)).is_err());
+    }
+}
@@ -1,8 +1,20 @@
 mod bitcoin;
 pub mod commands;
 pub mod config;
+mod dashboard;
 mod database;
+pub mod events;
+pub mod idempotency;
 mod jsonrpc;
+pub mod labels_csv;
+pub mod readiness;
+pub mod receipt;
+pub mod reviews;
+mod signer_server;
+pub mod snapshot;
+pub mod spans;
+pub mod statement;
+pub mod templates;
 #[cfg(test)]
 mod testutils;
 
@@ -17,7 +29,7 @@ pub use crate::bitcoin::{
 
 use crate::jsonrpc::server;
 use crate::{
-    bitcoin::{poller, BitcoinInterface},
+    bitcoin::{poller, BitcoinInterface, CrossCheckBackend},
     config::Config,
     database::{
         sqlite::{FreshDbOptions, SqliteDb, SqliteDbError, MAX_DB_VERSION_NO_TX_DB},
@@ -26,9 +38,9 @@ use crate::{
 };
 
 use std::{
-    error, fmt, fs, io, path,
+    env, error, fmt, fs, io, net, path, process,
     sync::{self, mpsc},
-    thread,
+    thread, time,
 };
 
 use miniscript::bitcoin::{constants::ChainHash, hashes::Hash, secp256k1, BlockHash};
@@ -95,6 +107,11 @@ pub enum StartupError {
     Database(SqliteDbError),
     Bitcoind(BitcoindError),
     Electrum(ElectrumError),
+    CrossCheckBackend(String),
+    Snapshot(snapshot::SnapshotError),
+    UnknownSnapshotTip(BlockHash),
+    Dashboard(io::Error),
+    SignerServer(io::Error),
     #[cfg(windows)]
     NoWatchonlyInDatadir,
 }
@@ -130,6 +147,17 @@ impl fmt::Display for StartupError {
             Self::Database(e) => write!(f, "Error initializing database: '{}'.", e),
             Self::Bitcoind(e) => write!(f, "Error setting up bitcoind interface: '{}'.", e),
             Self::Electrum(e) => write!(f, "Error setting up Electrum interface: '{}'.", e),
+            Self::CrossCheckBackend(e) => write!(
+                f,
+                "Error setting up the cross-checking Bitcoin backend: '{}'.", e
+            ),
+            Self::Snapshot(e) => write!(f, "{}", e),
+            Self::UnknownSnapshotTip(hash) => write!(
+                f,
+                "The tip of the coins snapshot, block '{}', is unknown to bitcoind.", hash
+            ),
+            Self::Dashboard(e) => write!(f, "Error starting the dashboard server: '{}'.", e),
+            Self::SignerServer(e) => write!(f, "Error starting the signer server: '{}'.", e),
             #[cfg(windows)]
             Self::NoWatchonlyInDatadir => {
                 write!(
@@ -187,6 +215,54 @@ fn create_datadir(datadir_path: &path::Path) -> Result<(), StartupError> {
     };
 }
 
+/// The temporary data directory of an ephemeral wallet. When dropped, the watchonly wallet it
+/// contains is unloaded from bitcoind and the directory is wiped.
+#[derive(Debug)]
+pub struct EphemeralDatadir {
+    path: path::PathBuf,
+    // The bitcoind our watchonly wallet was created on, if any.
+    bitcoind_config: Option<config::BitcoindConfig>,
+}
+
+impl EphemeralDatadir {
+    // A new path in the temporary directory of the system. It is created along with the data
+    // directory.
+    fn new() -> EphemeralDatadir {
+        let nonce = time::SystemTime::now()
+            .duration_since(time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        EphemeralDatadir {
+            path: env::temp_dir().join(format!("lianad-{}-{}", process::id(), nonce)),
+            bitcoind_config: None,
+        }
+    }
+}
+
+impl Drop for EphemeralDatadir {
+    fn drop(&mut self) {
+        if let Some(bitcoind_config) = &self.bitcoind_config {
+            if let Err(e) = BitcoinD::new(bitcoind_config, watchonly_wallet_path(&self.path))
+                .and_then(|bitcoind| bitcoind.unload_watchonly_wallet())
+            {
+                log::error!(
+                    "Error unloading the watchonly wallet of the ephemeral wallet from bitcoind: '{}'.",
+                    e
+                );
+            }
+        }
+        match fs::remove_dir_all(&self.path) {
+            Ok(()) => log::info!("Wiped the data directory at '{}'.", self.path.display()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => log::error!(
+                "Error wiping the data directory at '{}': '{}'.",
+                self.path.display(),
+                e
+            ),
+        }
+    }
+}
+
 // Connect to the SQLite database. Create it if starting fresh, and do some sanity checks.
 // If all went well, returns the interface to the SQLite database.
 fn setup_sqlite(
@@ -196,6 +272,19 @@ fn setup_sqlite(
     secp: &secp256k1::Secp256k1<secp256k1::VerifyOnly>,
     bitcoind: &Option<BitcoinD>,
 ) -> Result<SqliteDb, StartupError> {
+    // An ephemeral wallet always starts from a fresh database, and doesn't write it to disk.
+    if config.ephemeral {
+        let sqlite = SqliteDb::new_in_memory(
+            FreshDbOptions::new(
+                config.bitcoin_config.network,
+                config.main_descriptor.clone(),
+            ),
+            secp,
+        )?;
+        sqlite.sanity_check(config.bitcoin_config.network, &config.main_descriptor)?;
+        return Ok(sqlite);
+    }
+
     let db_path: path::PathBuf = [data_dir, path::Path::new("lianad.sqlite3")]
         .iter()
         .collect();
@@ -237,13 +326,47 @@ fn setup_sqlite(
     Ok(sqlite)
 }
 
-// Connect to bitcoind. Setup the watchonly wallet, and do some sanity checks.
-// If all went well, returns the interface to bitcoind.
-fn setup_bitcoind(
+// Populate the fresh database from the coins snapshot. If using bitcoind, the watchonly wallet is
+// made aware of the coins and rescans from the tip of the snapshot only.
+fn import_snapshot(
+    coins_snapshot: &snapshot::CoinsSnapshot,
     config: &Config,
-    data_dir: &path::Path,
-    fresh_data_dir: bool,
-) -> Result<BitcoinD, StartupError> {
+    db_conn: &mut dyn database::DatabaseConnection,
+    bitcoind: Option<&mut BitcoinD>,
+    secp: &secp256k1::Secp256k1<secp256k1::VerifyOnly>,
+) -> Result<(), StartupError> {
+    if let Some(bitcoind) = bitcoind {
+        let tip_time = bitcoind
+            .get_block_stats(coins_snapshot.tip_hash)
+            .ok_or(StartupError::UnknownSnapshotTip(coins_snapshot.tip_hash))?
+            .time;
+        // The spends of the coins confirmed before the rescan height can only be found by the
+        // watchonly wallet if it knows about their transactions.
+        for tx in &coins_snapshot.transactions {
+            if let Err(e) = bitcoind.import_pruned_funds(tx) {
+                log::warn!(
+                    "Error importing transaction '{}' from the coins snapshot in the watchonly wallet: '{}'.",
+                    tx.compute_txid(),
+                    e
+                );
+            }
+        }
+        snapshot::import(db_conn, coins_snapshot, secp).map_err(StartupError::Snapshot)?;
+        bitcoind.start_rescan(&config.main_descriptor, tip_time)?;
+        db_conn.set_rescan(tip_time);
+    } else {
+        snapshot::import(db_conn, coins_snapshot, secp).map_err(StartupError::Snapshot)?;
+    }
+    log::info!(
+        "Imported {} coins from the snapshot at height {}.",
+        coins_snapshot.coins.len(),
+        coins_snapshot.tip_height
+    );
+    Ok(())
+}
+
+// The path to the bitcoind watchonly wallet stored in this data directory, as given to bitcoind.
+fn watchonly_wallet_path(data_dir: &path::Path) -> String {
     let wo_path: path::PathBuf = [data_dir, path::Path::new("lianad_watchonly_wallet")]
         .iter()
         .collect();
@@ -258,8 +381,20 @@ fn setup_bitcoind(
     // See https://stackoverflow.com/questions/71590689/how-to-properly-handle-windows-paths-with-the-long-path-prefix-with-stdfilesys
     // for a discussion of how one C++ STL implementation handles this.
     #[cfg(target_os = "windows")]
-    let wo_path_str = wo_path_str.replace("\\\\?\\", "").replace("\\\\?", "");
+    return wo_path_str.replace("\\\\?\\", "").replace("\\\\?", "");
 
+    #[cfg(not(target_os = "windows"))]
+    return wo_path_str;
+}
+
+// Connect to bitcoind. Setup the watchonly wallet, and do some sanity checks.
+// If all went well, returns the interface to bitcoind.
+fn setup_bitcoind(
+    config: &Config,
+    data_dir: &path::Path,
+    fresh_data_dir: bool,
+) -> Result<BitcoinD, StartupError> {
+    let wo_path_str = watchonly_wallet_path(data_dir);
     let bitcoind_config = match config.bitcoin_backend.as_ref() {
         Some(config::BitcoinBackend::Bitcoind(bitcoind_config)) => bitcoind_config,
         _ => Err(StartupError::MissingBitcoindConfig)?,
@@ -275,7 +410,7 @@ fn setup_bitcoind(
         log::info!("Watchonly wallet created.");
     } else {
         #[cfg(windows)]
-        if !cfg!(test) && !wo_path.exists() {
+        if !cfg!(test) && !data_dir.join("lianad_watchonly_wallet").exists() {
             return Err(StartupError::NoWatchonlyInDatadir);
         }
     }
@@ -352,14 +487,63 @@ fn setup_electrum(
     Ok(electrum)
 }
 
+// Connect to the second Bitcoin backend against which to cross-check the main one, and make sure
+// it's on the same network.
+fn setup_cross_check(
+    config: &Config,
+    cross_check: &config::CrossCheckConfig,
+) -> Result<poller::CrossChecker, StartupError> {
+    let backend: Box<dyn CrossCheckBackend> = match &cross_check.backend {
+        config::BitcoinBackend::Bitcoind(bitcoind_config) => {
+            let bitcoind = BitcoinD::new_node_only(bitcoind_config)
+                .map_err(|e| StartupError::CrossCheckBackend(e.to_string()))?;
+            bitcoind
+                .node_sanity_checks(
+                    config.bitcoin_config.network,
+                    config.main_descriptor.is_taproot(),
+                )
+                .map_err(|e| StartupError::CrossCheckBackend(e.to_string()))?;
+            Box::new(bitcoind)
+        }
+        config::BitcoinBackend::Electrum(electrum_config) => {
+            let client = electrum::client::Client::new(electrum_config)
+                .map_err(|e| StartupError::CrossCheckBackend(e.to_string()))?;
+            let genesis_hash = {
+                let chain_hash = ChainHash::using_genesis_block(config.bitcoin_config.network);
+                BlockHash::from_byte_array(*chain_hash.as_bytes())
+            };
+            let server_hash = client
+                .genesis_block()
+                .map_err(|e| StartupError::CrossCheckBackend(e.to_string()))?
+                .hash;
+            if server_hash != genesis_hash {
+                return Err(StartupError::CrossCheckBackend(format!(
+                    "Expected genesis block '{}' but the server's is '{}'",
+                    genesis_hash, server_hash
+                )));
+            }
+            Box::new(client)
+        }
+    };
+    Ok(poller::CrossChecker::new(
+        backend,
+        cross_check.max_height_difference,
+    ))
+}
+
 #[derive(Clone)]
 pub struct DaemonControl {
     config: Config,
     bitcoin: sync::Arc<sync::Mutex<dyn BitcoinInterface>>,
     poller_sender: mpsc::SyncSender<poller::PollerMessage>,
+    poll_stats: sync::Arc<sync::Mutex<poller::PollStats>>,
     // FIXME: Should we require Sync on DatabaseInterface rather than using a Mutex?
     db: sync::Arc<sync::Mutex<dyn DatabaseInterface>>,
     secp: secp256k1::Secp256k1<secp256k1::VerifyOnly>,
+    events: sync::Arc<sync::Mutex<events::Events>>,
+    idempotency_keys: sync::Arc<sync::Mutex<idempotency::IdempotencyKeys>>,
+    // Held by the commands modifying the wallet, see `DaemonControl::lock_writes`.
+    writes: sync::Arc<sync::Mutex<()>>,
 }
 
 impl DaemonControl {
@@ -367,18 +551,33 @@ impl DaemonControl {
         config: Config,
         bitcoin: sync::Arc<sync::Mutex<dyn BitcoinInterface>>,
         poller_sender: mpsc::SyncSender<poller::PollerMessage>,
+        poll_stats: sync::Arc<sync::Mutex<poller::PollStats>>,
         db: sync::Arc<sync::Mutex<dyn DatabaseInterface>>,
         secp: secp256k1::Secp256k1<secp256k1::VerifyOnly>,
+        events: sync::Arc<sync::Mutex<events::Events>>,
     ) -> DaemonControl {
         DaemonControl {
             config,
             bitcoin,
             poller_sender,
+            poll_stats,
             db,
             secp,
+            events,
+            idempotency_keys: sync::Arc::from(sync::Mutex::from(
+                idempotency::IdempotencyKeys::new(),
+            )),
+            writes: sync::Arc::new(sync::Mutex::new(())),
         }
     }
 
+    /// Wait for the commands modifying the wallet to be done, and prevent new ones from being run
+    /// until the returned guard is dropped. The commands which modify the wallet must be run
+    /// while holding it, those which only read from it can run in parallel with any other.
+    pub fn lock_writes(&self) -> sync::MutexGuard<'_, ()> {
+        self.writes.lock().unwrap()
+    }
+
     // Useful for unit test to directly mess up with the DB
     #[cfg(test)]
     pub fn db(&self) -> sync::Arc<sync::Mutex<dyn DatabaseInterface>> {
@@ -392,13 +591,21 @@ pub enum DaemonHandle {
     Controller {
         poller_sender: mpsc::SyncSender<poller::PollerMessage>,
         poller_handle: thread::JoinHandle<()>,
+        scheduler: templates::Scheduler,
+        dashboard: Option<dashboard::Dashboard>,
+        signer_server: Option<signer_server::SignerServer>,
+        ephemeral_datadir: Option<EphemeralDatadir>,
         control: DaemonControl,
     },
     Server {
         poller_sender: mpsc::SyncSender<poller::PollerMessage>,
         poller_handle: thread::JoinHandle<()>,
+        scheduler: templates::Scheduler,
+        dashboard: Option<dashboard::Dashboard>,
+        signer_server: Option<signer_server::SignerServer>,
         rpcserver_shutdown: sync::Arc<sync::atomic::AtomicBool>,
         rpcserver_handle: thread::JoinHandle<Result<(), io::Error>>,
+        ephemeral_datadir: Option<EphemeralDatadir>,
     },
 }
 
@@ -421,25 +628,60 @@ impl DaemonHandle {
     ) -> Result<Self, StartupError> {
         #[cfg(not(test))]
         setup_panic_hook();
+        spans::set_slow_threshold(
+            config
+                .slow_operation_threshold_ms
+                .unwrap_or(spans::DEFAULT_SLOW_THRESHOLD_MS),
+        );
 
         let secp = secp256k1::Secp256k1::verification_only();
 
-        // First, check the data directory
-        let mut data_dir = config
-            .data_dir()
-            .ok_or(StartupError::DefaultDataDirNotFound)?;
-        data_dir.push(config.bitcoin_config.network.to_string());
+        // First, check the data directory. An ephemeral wallet uses a new temporary one.
+        let mut ephemeral_datadir = if config.ephemeral {
+            Some(EphemeralDatadir::new())
+        } else {
+            None
+        };
+        let data_dir = match &ephemeral_datadir {
+            Some(ephemeral_datadir) => ephemeral_datadir.path.clone(),
+            None => {
+                let mut data_dir = config
+                    .data_dir()
+                    .ok_or(StartupError::DefaultDataDirNotFound)?;
+                data_dir.push(config.bitcoin_config.network.to_string());
+                data_dir
+            }
+        };
         let fresh_data_dir = !data_dir.as_path().exists();
+        // Read the snapshot before creating anything, so a bad one doesn't leave us with a
+        // datadir which won't use it on the next start.
+        let coins_snapshot = match &config.coins_snapshot {
+            Some(path) if fresh_data_dir => Some(
+                snapshot::read(path, &config.main_descriptor).map_err(StartupError::Snapshot)?,
+            ),
+            _ => None,
+        };
         if fresh_data_dir {
             create_datadir(&data_dir)?;
             log::info!("Created a new data directory at '{}'", data_dir.display());
         }
+        if ephemeral_datadir.is_some() {
+            log::info!(
+                "Ephemeral wallet: the database is kept in memory and the data directory is wiped \
+                 on shutdown."
+            );
+        }
 
         // Set up the connection to bitcoind (if using it) first as we may need it for the database
         // migration when setting up SQLite below.
-        let bitcoind = if bitcoin.is_none() {
-            if let Some(config::BitcoinBackend::Bitcoind(_)) = &config.bitcoin_backend {
-                Some(setup_bitcoind(&config, &data_dir, fresh_data_dir)?)
+        let mut bitcoind = if bitcoin.is_none() {
+            if let Some(config::BitcoinBackend::Bitcoind(bitcoind_config)) = &config.bitcoin_backend
+            {
+                let bitcoind = setup_bitcoind(&config, &data_dir, fresh_data_dir)?;
+                if let Some(ephemeral_datadir) = ephemeral_datadir.as_mut() {
+                    ephemeral_datadir.bitcoind_config = Some(bitcoind_config.clone());
+                }
+                Some(bitcoind)
             } else {
                 None
             }
@@ -458,6 +700,15 @@ impl DaemonHandle {
                 &bitcoind,
             )?)) as sync::Arc<sync::Mutex<dyn DatabaseInterface>>,
         };
+        if let Some(coins_snapshot) = coins_snapshot {
+            import_snapshot(
+                &coins_snapshot,
+                &config,
+                &mut *db.lock().unwrap().connection(),
+                bitcoind.as_mut(),
+                &secp,
+            )?;
+        }
 
         // Finally set up the Bitcoin backend.
         let bit = match (bitcoin, &config.bitcoin_backend) {
@@ -474,8 +725,28 @@ impl DaemonHandle {
 
         // Start the poller thread. Keep the thread handle to be able to check if it crashed. Store
         // an atomic to be able to stop it.
-        let mut bitcoin_poller =
-            poller::Poller::new(bit.clone(), db.clone(), config.main_descriptor.clone());
+        let events = sync::Arc::from(sync::Mutex::from(
+            events::Events::new(config.webhook_url.clone()).stored(db.clone()),
+        ));
+        let watchtower = config.watchtower.clone().map(poller::Watchtower::new);
+        let cross_checker = match &config.cross_check {
+            Some(cross_check) => {
+                let cross_checker = setup_cross_check(&config, cross_check)?;
+                log::info!("Cross-checking the Bitcoin backend against a second one.");
+                Some(cross_checker)
+            }
+            None => None,
+        };
+        let mut bitcoin_poller = poller::Poller::new(
+            bit.clone(),
+            db.clone(),
+            config.main_descriptor.clone(),
+            events.clone(),
+            watchtower,
+            cross_checker,
+            poller::TipMonitor::new(config.bitcoin_config.network),
+        );
+        let poll_stats = bitcoin_poller.stats();
         let (poller_sender, poller_receiver) = mpsc::sync_channel(0);
         let poller_handle = thread::Builder::new()
             .name("Bitcoin Network poller".to_string())
@@ -491,7 +762,51 @@ impl DaemonHandle {
 
         // Create the API the external world will use to talk to us, either directly through the Rust
         // structure or through the JSONRPC server we may setup below.
-        let control = DaemonControl::new(config, bit, poller_sender.clone(), db, secp);
+        let dashboard_config = config.dashboard.clone();
+        let signer_server_config = config.signer_server.clone();
+        let control = DaemonControl::new(
+            config,
+            bit,
+            poller_sender.clone(),
+            poll_stats,
+            db,
+            secp,
+            events,
+        );
+
+        // Start the thread creating the drafts of the scheduled spend templates.
+        let scheduler = templates::Scheduler::start(control.clone());
+
+        // Serve the read-only status page, if configured.
+        let dashboard = match dashboard_config {
+            Some(dashboard_config) => {
+                let listener = net::TcpListener::bind(dashboard_config.addr)
+                    .map_err(StartupError::Dashboard)?;
+                Some(
+                    dashboard::Dashboard::start(listener, dashboard_config.token, control.clone())
+                        .map_err(StartupError::Dashboard)?,
+                )
+            }
+            None => None,
+        };
+
+        // Sign with the hot signer on behalf of the GUI of another machine, if configured.
+        let signer_server = match signer_server_config {
+            Some(config) => {
+                let listener =
+                    net::TcpListener::bind(config.addr).map_err(StartupError::SignerServer)?;
+                Some(
+                    signer_server::SignerServer::start(
+                        listener,
+                        config.token,
+                        config.fingerprint,
+                        control.clone(),
+                    )
+                    .map_err(StartupError::SignerServer)?,
+                )
+            }
+            None => None,
+        };
 
         if with_rpc_server {
             let rpcserver_shutdown = sync::Arc::from(sync::atomic::AtomicBool::from(false));
@@ -511,14 +826,22 @@ impl DaemonHandle {
             return Ok(DaemonHandle::Server {
                 poller_sender,
                 poller_handle,
+                scheduler,
+                dashboard,
+                signer_server,
                 rpcserver_shutdown,
                 rpcserver_handle,
+                ephemeral_datadir,
             });
         }
 
         Ok(DaemonHandle::Controller {
             poller_sender,
             poller_handle,
+            scheduler,
+            dashboard,
+            signer_server,
+            ephemeral_datadir,
             control,
         })
     }
@@ -559,28 +882,55 @@ impl DaemonHandle {
             Self::Controller {
                 poller_sender,
                 poller_handle,
+                scheduler,
+                dashboard,
+                signer_server,
+                ephemeral_datadir,
                 ..
             } => {
+                if let Some(signer_server) = signer_server {
+                    signer_server.stop();
+                }
+                if let Some(dashboard) = dashboard {
+                    dashboard.stop();
+                }
+                scheduler.stop();
                 poller_sender
                     .send(poller::PollerMessage::Shutdown)
                     .expect("The other end should never have hung up before this.");
                 poller_handle.join().expect("Poller thread must not panic");
+                drop(ephemeral_datadir);
                 Ok(())
             }
             Self::Server {
                 poller_sender,
                 poller_handle,
+                scheduler,
+                dashboard,
+                signer_server,
                 rpcserver_shutdown,
                 rpcserver_handle,
+                ephemeral_datadir,
             } => {
+                // First stop accepting commands and let the ones being processed complete, as
+                // they may need the poller. Then let the poller complete its current poll.
+                rpcserver_shutdown.store(true, sync::atomic::Ordering::Relaxed);
+                let rpcserver_res = rpcserver_handle
+                    .join()
+                    .expect("JSONRPC server thread must not panic");
+                if let Some(signer_server) = signer_server {
+                    signer_server.stop();
+                }
+                if let Some(dashboard) = dashboard {
+                    dashboard.stop();
+                }
+                scheduler.stop();
                 poller_sender
                     .send(poller::PollerMessage::Shutdown)
                     .expect("The other end should never have hung up before this.");
-                rpcserver_shutdown.store(true, sync::atomic::Ordering::Relaxed);
-                rpcserver_handle
-                    .join()
-                    .expect("Poller thread must not panic")?;
                 poller_handle.join().expect("Poller thread must not panic");
+                drop(ephemeral_datadir);
+                rpcserver_res?;
                 Ok(())
             }
         }
@@ -815,11 +1165,21 @@ mod tests {
         let receive_desc = desc.receive_descriptor().clone();
         let change_desc = desc.change_descriptor().clone();
         let config = Config {
+            webhook_url: None,
+            coins_snapshot: None,
+            shutdown_timeout_secs: time::Duration::from_secs(8),
+            spend_confirmation_threshold: None,
+            slow_operation_threshold_ms: None,
             bitcoin_config,
             bitcoin_backend: Some(config::BitcoinBackend::Bitcoind(bitcoind_config)),
             data_dir: Some(data_dir),
+            ephemeral: false,
             log_level: log::LevelFilter::Debug,
             main_descriptor: desc,
+            watchtower: None,
+            cross_check: None,
+            dashboard: None,
+            signer_server: None,
         };
 
         // Start the daemon in a new thread so the current one acts as the bitcoind server.
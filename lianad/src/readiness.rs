@@ -0,0 +1,160 @@
+//! How ready the wallet is to be recovered if keys are lost or the owner passes away.
+//!
+//! Most of the checklist can't be verified by the daemon (whether the descriptor was backed up,
+//! whether the recovery keys were tested, ..) so the user attests it's done. The margin before
+//! the recovery paths become available is computed from the coins.
+
+use serde::{Deserialize, Serialize};
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+/// An item of the recovery readiness checklist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadinessItem {
+    /// The descriptor was backed up and the backup was verified.
+    DescriptorBackup,
+    /// The descriptor was registered on all the signing devices.
+    DevicesRegistered,
+    /// The recovery keys were tested, for instance by signing a recovery transaction.
+    RecoveryKeysTested,
+    /// A kit allowing heirs to recover the funds was generated and handed over.
+    HeirsKit,
+    /// None of the coins has its first recovery path about to become available.
+    TimelockMargin,
+}
+
+impl ReadinessItem {
+    pub const ALL: [ReadinessItem; 5] = [
+        Self::DescriptorBackup,
+        Self::DevicesRegistered,
+        Self::RecoveryKeysTested,
+        Self::HeirsKit,
+        Self::TimelockMargin,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::DescriptorBackup => "descriptor_backup",
+            Self::DevicesRegistered => "devices_registered",
+            Self::RecoveryKeysTested => "recovery_keys_tested",
+            Self::HeirsKit => "heirs_kit",
+            Self::TimelockMargin => "timelock_margin",
+        }
+    }
+
+    /// Whether this item is attested by the user, as opposed to computed by the daemon.
+    pub fn is_attested(&self) -> bool {
+        !matches!(self, Self::TimelockMargin)
+    }
+}
+
+impl fmt::Display for ReadinessItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ReadinessItem {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|item| item.as_str() == s)
+            .ok_or_else(|| format!("Unknown readiness item '{}'", s))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadinessCheck {
+    pub item: ReadinessItem,
+    pub done: bool,
+    /// When the user marked this item as done, for the attested items.
+    pub completed_at: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecoveryReadiness {
+    /// The percentage of the checklist which is done.
+    pub score: u8,
+    pub checks: Vec<ReadinessCheck>,
+    /// The number of unspent coins whose first recovery path is available within 10% of its
+    /// timelock.
+    pub expiring_coins: usize,
+}
+
+impl RecoveryReadiness {
+    /// Compute the checklist from the items the user marked as done (and when) and the number of
+    /// coins whose recovery path is about to become available.
+    pub fn new(completed: &HashMap<ReadinessItem, u32>, expiring_coins: usize) -> Self {
+        let checks: Vec<_> = ReadinessItem::ALL
+            .iter()
+            .copied()
+            .map(|item| {
+                if item.is_attested() {
+                    let completed_at = completed.get(&item).copied();
+                    ReadinessCheck {
+                        item,
+                        done: completed_at.is_some(),
+                        completed_at,
+                    }
+                } else {
+                    ReadinessCheck {
+                        item,
+                        done: expiring_coins == 0,
+                        completed_at: None,
+                    }
+                }
+            })
+            .collect();
+        let done = checks.iter().filter(|c| c.done).count();
+        let score = (done * 100 / checks.len()) as u8;
+        Self {
+            score,
+            checks,
+            expiring_coins,
+        }
+    }
+
+    /// The items which remain to be done.
+    pub fn pending(&self) -> impl Iterator<Item = &ReadinessCheck> {
+        self.checks.iter().filter(|c| !c.done)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readiness_score() {
+        let readiness = RecoveryReadiness::new(&HashMap::new(), 0);
+        assert_eq!(readiness.score, 20);
+        assert_eq!(readiness.pending().count(), 4);
+
+        let completed: HashMap<_, _> = ReadinessItem::ALL
+            .iter()
+            .copied()
+            .filter(ReadinessItem::is_attested)
+            .map(|item| (item, 1_700_000_000))
+            .collect();
+        let readiness = RecoveryReadiness::new(&completed, 0);
+        assert_eq!(readiness.score, 100);
+        assert_eq!(readiness.pending().count(), 0);
+
+        // Coins about to become spendable through the recovery path need to be refreshed.
+        let readiness = RecoveryReadiness::new(&completed, 2);
+        assert_eq!(readiness.score, 80);
+        assert_eq!(
+            readiness.pending().map(|c| c.item).collect::<Vec<_>>(),
+            vec![ReadinessItem::TimelockMargin]
+        );
+
+        for item in ReadinessItem::ALL.iter().copied() {
+            assert_eq!(ReadinessItem::from_str(item.as_str()), Ok(item));
+        }
+        assert!(ReadinessItem::from_str("backup").is_err());
+    }
+}
@@ -0,0 +1,371 @@
+//! Printable receipts of the confirmed transactions of the wallet, as accounting departments ask
+//! for a document per payment.
+//!
+//! A receipt is a single page PDF listing the transaction, the block confirming it, its amount and
+//! fee, the outputs along with their labels, and a QR code linking to the transaction on a block
+//! explorer. The PDF is written by hand rather than pulling a library: it only needs the standard
+//! Helvetica font, and the QR code is drawn as filled squares.
+
+use miniscript::bitcoin::{self, Amount};
+use serde::{Deserialize, Serialize};
+
+/// Width of an A4 page, in points.
+const PAGE_WIDTH: f64 = 595.0;
+/// Height of an A4 page, in points.
+const PAGE_HEIGHT: f64 = 842.0;
+const MARGIN: f64 = 50.0;
+/// Size of the side of the QR code, in points.
+const QR_SIZE: f64 = 110.0;
+/// Number of outputs listed before we run out of room on the page.
+const MAX_OUTPUTS: usize = 40;
+/// Number of characters of a label shown.
+const MAX_LABEL_LEN: usize = 60;
+
+/// An output of the transaction on the receipt.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReceiptOutput {
+    /// The address paid. `None` for scripts without an address, such as an `OP_RETURN`.
+    pub address: Option<String>,
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub amount: Amount,
+    pub label: Option<String>,
+    /// Whether this output pays to the wallet, for instance the change.
+    pub is_ours: bool,
+}
+
+/// Everything printed on the receipt of a transaction.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Receipt {
+    pub txid: bitcoin::Txid,
+    pub network: bitcoin::Network,
+    pub label: Option<String>,
+    pub block_height: i32,
+    pub block_time: u32,
+    /// The value received minus the value spent by the wallet in this transaction, in satoshis.
+    pub amount: i64,
+    /// The fee paid, if all the inputs of the transaction are coins of the wallet.
+    #[serde(default, with = "bitcoin::amount::serde::as_sat::opt")]
+    pub fee: Option<Amount>,
+    pub outputs: Vec<ReceiptOutput>,
+    /// The page of the transaction on a block explorer.
+    pub explorer_link: String,
+}
+
+/// The explorer the receipts link to by default: the transaction id is appended to this URL.
+pub fn default_explorer_url(network: bitcoin::Network) -> &'static str {
+    match network {
+        bitcoin::Network::Testnet => "https://mempool.space/testnet/tx/",
+        bitcoin::Network::Signet => "https://mempool.space/signet/tx/",
+        _ => "https://mempool.space/tx/",
+    }
+}
+
+/// Format a timestamp as a UTC date and time, such as `2024-01-31 12:00:00 UTC`.
+pub fn utc_datetime(timestamp: u32) -> String {
+    let (days, secs) = (timestamp as i64 / 86_400, timestamp as i64 % 86_400);
+    // Convert the number of days since the epoch to a civil date (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+fn btc(sats: i64) -> String {
+    let sign = if sats < 0 { "-" } else { "" };
+    let sats = sats.unsigned_abs();
+    format!(
+        "{}{}.{:08} BTC",
+        sign,
+        sats / 100_000_000,
+        sats % 100_000_000
+    )
+}
+
+// Encode a string as a PDF literal string in the WinAnsi encoding of the standard fonts. It
+// matches Latin-1 for the characters we keep, the others are replaced.
+fn pdf_string(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len() + 2);
+    encoded.push('(');
+    for c in s.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                encoded.push('\\');
+                encoded.push(c);
+            }
+            ' '..='~' => encoded.push(c),
+            '\u{a0}'..='\u{ff}' => encoded.push_str(&format!("\\{:03o}", c as u32)),
+            _ => encoded.push('?'),
+        }
+    }
+    encoded.push(')');
+    encoded
+}
+
+fn truncated(s: &str, len: usize) -> String {
+    if s.chars().count() > len {
+        format!("{}...", s.chars().take(len).collect::<String>())
+    } else {
+        s.to_string()
+    }
+}
+
+// The drawing operations of a page.
+#[derive(Default)]
+struct Page {
+    content: String,
+}
+
+impl Page {
+    fn text(&mut self, x: f64, y: f64, size: f64, bold: bool, text: &str) {
+        self.content += &format!(
+            "BT /{} {} Tf {:.2} {:.2} Td {} Tj ET\n",
+            if bold { "F2" } else { "F1" },
+            size,
+            x,
+            y,
+            pdf_string(text)
+        );
+    }
+
+    fn square(&mut self, x: f64, y: f64, size: f64) {
+        self.content += &format!("{:.2} {:.2} {:.2} {:.2} re f\n", x, y, size, size);
+    }
+
+    // A single page document with this content.
+    fn into_pdf(self) -> Vec<u8> {
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 4 0 R /F2 5 0 R >> >> /Contents 6 0 R >>",
+                PAGE_WIDTH, PAGE_HEIGHT
+            ),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_string(),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+                .to_string(),
+            format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                self.content.len(),
+                self.content
+            ),
+        ];
+
+        let mut pdf = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf += &format!("{} 0 obj\n{}\nendobj\n", i + 1, object);
+        }
+        let xref_offset = pdf.len();
+        pdf += &format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            pdf += &format!("{:010} 00000 n \n", offset);
+        }
+        pdf += &format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref_offset
+        );
+        // Only ASCII was written, the other characters are escaped.
+        pdf.into_bytes()
+    }
+}
+
+// The modules of the QR code encoding this data, by row, along with its width.
+fn qr_modules(data: &str) -> Option<(usize, Vec<bool>)> {
+    let code = qrcode::QrCode::new(data.as_bytes()).ok()?;
+    let modules = code
+        .to_colors()
+        .into_iter()
+        .map(|color| color == qrcode::Color::Dark)
+        .collect();
+    Some((code.width(), modules))
+}
+
+impl Receipt {
+    /// Render the receipt as a PDF document.
+    pub fn to_pdf(&self) -> Vec<u8> {
+        let mut page = Page::default();
+        let mut y = PAGE_HEIGHT - MARGIN - 18.0;
+        page.text(MARGIN, y, 18.0, true, "Transaction receipt");
+        y -= 30.0;
+
+        let mut lines = vec![
+            ("Network", self.network.to_string()),
+            ("Block", self.block_height.to_string()),
+            ("Date", utc_datetime(self.block_time)),
+            ("Amount", btc(self.amount)),
+            (
+                "Fee",
+                self.fee
+                    .map(|fee| btc(fee.to_sat() as i64))
+                    .unwrap_or_else(|| "Unknown".to_string()),
+            ),
+        ];
+        if let Some(label) = &self.label {
+            lines.insert(0, ("Label", truncated(label, MAX_LABEL_LEN)));
+        }
+        for (name, value) in lines {
+            page.text(MARGIN, y, 11.0, true, name);
+            page.text(MARGIN + 70.0, y, 11.0, false, &value);
+            y -= 16.0;
+        }
+
+        // The QR code on the right of the summary, the explorer link below it.
+        if let Some((width, modules)) = qr_modules(&self.explorer_link) {
+            let module_size = QR_SIZE / width as f64;
+            let (left, top) = (PAGE_WIDTH - MARGIN - QR_SIZE, PAGE_HEIGHT - MARGIN);
+            for (i, _) in modules.iter().enumerate().filter(|(_, dark)| **dark) {
+                let (col, row) = (i % width, i / width);
+                page.square(
+                    left + col as f64 * module_size,
+                    top - (row + 1) as f64 * module_size,
+                    module_size,
+                );
+            }
+        }
+
+        y -= 10.0;
+        page.text(MARGIN, y, 9.0, true, "Transaction");
+        page.text(MARGIN + 70.0, y, 9.0, false, &self.txid.to_string());
+        y -= 13.0;
+        page.text(MARGIN, y, 9.0, true, "Explorer");
+        page.text(MARGIN + 70.0, y, 9.0, false, &self.explorer_link);
+        y -= 30.0;
+
+        page.text(MARGIN, y, 13.0, true, "Outputs");
+        y -= 18.0;
+        for output in self.outputs.iter().take(MAX_OUTPUTS) {
+            let address = output
+                .address
+                .clone()
+                .unwrap_or_else(|| "(no address)".to_string());
+            page.text(MARGIN, y, 9.0, false, &address);
+            page.text(
+                PAGE_WIDTH - MARGIN - 100.0,
+                y,
+                9.0,
+                false,
+                &btc(output.amount.to_sat() as i64),
+            );
+            y -= 12.0;
+            let note = match (&output.label, output.is_ours) {
+                (Some(label), true) => format!("{} (this wallet)", truncated(label, MAX_LABEL_LEN)),
+                (Some(label), false) => truncated(label, MAX_LABEL_LEN),
+                (None, true) => "This wallet".to_string(),
+                (None, false) => String::new(),
+            };
+            if !note.is_empty() {
+                page.text(MARGIN + 10.0, y, 8.0, false, &note);
+                y -= 12.0;
+            }
+            y -= 4.0;
+        }
+        if self.outputs.len() > MAX_OUTPUTS {
+            page.text(
+                MARGIN,
+                y,
+                9.0,
+                false,
+                &format!("... and {} more outputs.", self.outputs.len() - MAX_OUTPUTS),
+            );
+        }
+
+        page.text(
+            MARGIN,
+            MARGIN / 2.0,
+            8.0,
+            false,
+            "Generated by Liana from the data of the wallet.",
+        );
+        page.into_pdf()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miniscript::bitcoin::hashes::Hash;
+
+    #[test]
+    fn dates() {
+        assert_eq!(utc_datetime(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(utc_datetime(951_782_400), "2000-02-29 00:00:00 UTC");
+        assert_eq!(utc_datetime(1_706_702_400), "2024-01-31 12:00:00 UTC");
+    }
+
+    #[test]
+    fn pdf_strings() {
+        assert_eq!(pdf_string("Rent (May)"), "(Rent \\(May\\))");
+        assert_eq!(pdf_string("a\\b"), "(a\\\\b)");
+        assert_eq!(pdf_string("Café"), "(Caf\\351)");
+        assert_eq!(pdf_string("₿"), "(?)");
+        assert_eq!(btc(-150_000_000), "-1.50000000 BTC");
+        assert_eq!(btc(1_000), "0.00001000 BTC");
+    }
+
+    #[test]
+    fn receipt_pdf() {
+        let txid = bitcoin::Txid::from_slice(&[1; 32]).unwrap();
+        let receipt = Receipt {
+            txid,
+            network: bitcoin::Network::Bitcoin,
+            label: Some("Invoice #42".to_string()),
+            block_height: 800_000,
+            block_time: 1_690_168_629,
+            amount: -101_000,
+            fee: Some(Amount::from_sat(1_000)),
+            outputs: vec![ReceiptOutput {
+                address: Some("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".to_string()),
+                amount: Amount::from_sat(100_000),
+                label: Some("Supplier".to_string()),
+                is_ours: false,
+            }],
+            explorer_link: format!(
+                "{}{}",
+                default_explorer_url(bitcoin::Network::Bitcoin),
+                txid
+            ),
+        };
+        let pdf = String::from_utf8(receipt.to_pdf()).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("(Invoice #42)"));
+        assert!(pdf.contains("(-0.00101000 BTC)"));
+        assert!(pdf.contains(&format!("(https://mempool.space/tx/{})", txid)));
+        // The QR code was drawn.
+        assert!(pdf.contains(" re f\n"));
+
+        // The cross-reference table points to the objects.
+        let startxref: usize = pdf
+            .rsplit("startxref\n")
+            .next()
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(pdf[startxref..].starts_with("xref\n0 7\n"));
+        let first_offset: usize = pdf[startxref..].lines().nth(3).unwrap()[..10]
+            .parse()
+            .unwrap();
+        assert!(pdf[first_offset..].starts_with("1 0 obj\n"));
+    }
+}
@@ -0,0 +1,123 @@
+//! The reviews of the stored Spend transactions by the signers of the wallet.
+//!
+//! Without them a co-signer who won't sign a transaction can't be told apart from one who didn't
+//! look at it yet. A signer may approve a Spend transaction, reject it or request changes to it,
+//! with a comment. Only its latest review is kept. A Spend transaction whose content changed has
+//! a different txid and is reviewed anew.
+
+use serde::{Deserialize, Serialize};
+
+use std::{fmt, str::FromStr};
+
+use miniscript::bitcoin::bip32;
+
+/// What a signer decided about a Spend transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewDecision {
+    Approved,
+    ChangesRequested,
+    Rejected,
+}
+
+impl ReviewDecision {
+    pub const ALL: [ReviewDecision; 3] = [Self::Approved, Self::ChangesRequested, Self::Rejected];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Approved => "approved",
+            Self::ChangesRequested => "changes_requested",
+            Self::Rejected => "rejected",
+        }
+    }
+}
+
+impl fmt::Display for ReviewDecision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ReviewDecision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|decision| decision.as_str() == s)
+            .ok_or_else(|| format!("Unknown review decision '{}'", s))
+    }
+}
+
+/// The latest review of a Spend transaction by a signer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendReview {
+    /// Master fingerprint of the signer.
+    pub signer: bip32::Fingerprint,
+    pub decision: ReviewDecision,
+    pub comment: Option<String>,
+    /// When the signer reviewed the transaction.
+    pub reviewed_at: u32,
+}
+
+/// Where the review of a Spend transaction stands, all signers considered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewState {
+    /// No signer reviewed it yet.
+    #[default]
+    Pending,
+    /// All the signers who reviewed it approved it.
+    Approved,
+    /// A signer requested changes and none rejected it.
+    ChangesRequested,
+    /// A signer rejected it.
+    Rejected,
+}
+
+impl ReviewState {
+    /// The state of the review of a Spend transaction given the reviews of its signers. A single
+    /// rejection outweighs any number of approvals.
+    pub fn from_reviews(reviews: &[SpendReview]) -> ReviewState {
+        match reviews.iter().map(|review| review.decision).max() {
+            None => Self::Pending,
+            Some(ReviewDecision::Approved) => Self::Approved,
+            Some(ReviewDecision::ChangesRequested) => Self::ChangesRequested,
+            Some(ReviewDecision::Rejected) => Self::Rejected,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn review_state() {
+        let review = |fg: &str, decision| SpendReview {
+            signer: bip32::Fingerprint::from_str(fg).unwrap(),
+            decision,
+            comment: None,
+            reviewed_at: 1_700_000_000,
+        };
+        assert_eq!(ReviewState::from_reviews(&[]), ReviewState::Pending);
+        let mut reviews = vec![review("aabbccdd", ReviewDecision::Approved)];
+        assert_eq!(ReviewState::from_reviews(&reviews), ReviewState::Approved);
+        reviews.push(review("00112233", ReviewDecision::ChangesRequested));
+        assert_eq!(
+            ReviewState::from_reviews(&reviews),
+            ReviewState::ChangesRequested
+        );
+        reviews.push(review("44556677", ReviewDecision::Rejected));
+        assert_eq!(ReviewState::from_reviews(&reviews), ReviewState::Rejected);
+
+        for decision in ReviewDecision::ALL.iter() {
+            assert_eq!(
+                ReviewDecision::from_str(decision.as_str()).unwrap(),
+                *decision
+            );
+        }
+        assert!(ReviewDecision::from_str("approve").is_err());
+    }
+}
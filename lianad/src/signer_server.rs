@@ -0,0 +1,326 @@
+//! Sign with the hot signer of this daemon on behalf of the GUI of another machine.
+//!
+//! This allows to keep the always-online key of a wallet, for instance the third key of a 2-of-3,
+//! on a hardened server rather than on the desktop running the GUI. The server runs a daemon for
+//! the same wallet with the hot signer in its data directory, and only signs PSBTs spending coins
+//! of this wallet (see `DaemonControl::sign_psbt_with_hot_signer`). It never stores nor broadcasts
+//! them.
+//!
+//! Two endpoints are served, and every request must give the token of the configuration as a
+//! bearer token in the `Authorization` header:
+//! - `GET /info` returns the `fingerprint` of the hot signer and the `network`.
+//! - `POST /sign` takes a JSON object with the base64-encoded `psbt` and returns the signed one
+//!   along with the `fingerprint` of the signer.
+//!
+//! Only plain HTTP is served: the GUI must reach it through an HTTPS reverse proxy or an SSH
+//! tunnel.
+
+use crate::{commands::CommandError, dashboard::is_token, DaemonControl};
+
+use std::{
+    io::{self, BufRead, Read, Write},
+    net,
+    str::FromStr,
+    sync::mpsc,
+    thread, time,
+};
+
+use miniscript::bitcoin::{bip32, psbt::Psbt};
+use serde::Deserialize;
+
+/// How often to check whether we need to stop when no request is coming.
+const ACCEPT_INTERVAL: time::Duration = time::Duration::from_millis(100);
+
+/// How long to wait for a client to send its request or read our response. Signing a large PSBT
+/// may take a little while.
+const REQUEST_TIMEOUT: time::Duration = time::Duration::from_secs(30);
+
+/// The PSBTs may contain the whole previous transactions of their inputs.
+const MAX_REQUEST_SIZE: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+struct SignRequest {
+    psbt: String,
+}
+
+/// The thread serving the sign requests.
+pub struct SignerServer {
+    shutdown: mpsc::Sender<()>,
+    handle: thread::JoinHandle<()>,
+}
+
+impl SignerServer {
+    /// Serve the hot signer with this master fingerprint, or the only one stored if none is given.
+    pub fn start(
+        listener: net::TcpListener,
+        token: String,
+        fingerprint: Option<bip32::Fingerprint>,
+        control: DaemonControl,
+    ) -> Result<SignerServer, io::Error> {
+        // Don't block on accept, to be able to stop.
+        listener.set_nonblocking(true)?;
+        let (shutdown, receiver) = mpsc::channel();
+        let handle = thread::Builder::new()
+            .name("Signer server".to_string())
+            .spawn(move || {
+                log::info!("Signer server listening on {:?}.", listener.local_addr());
+                loop {
+                    match listener.accept() {
+                        Ok((stream, peer)) => {
+                            if let Err(e) = handle_request(stream, &token, fingerprint, &control) {
+                                log::debug!("Error serving a sign request from {}: {}", peer, e);
+                            }
+                            continue;
+                        }
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                        Err(e) => log::error!("Error accepting a signer connection: {}", e),
+                    }
+                    if receiver.recv_timeout(ACCEPT_INTERVAL)
+                        != Err(mpsc::RecvTimeoutError::Timeout)
+                    {
+                        break;
+                    }
+                }
+                log::info!("Signer server stopped.");
+            })
+            .expect("Spawning the signer server thread must never fail.");
+        Ok(SignerServer { shutdown, handle })
+    }
+
+    pub fn stop(self) {
+        // The thread also stops if we hung up already.
+        let _ = self.shutdown.send(());
+        self.handle
+            .join()
+            .expect("Signer server thread must not panic");
+    }
+}
+
+// Whether the headers contain the token as a bearer token. Unlike for the dashboard, it's not
+// accepted in the query as it could end up in the logs of a proxy.
+fn is_authorized(headers: &[String], token: &str) -> bool {
+    headers.iter().any(|header| {
+        header
+            .split_once(':')
+            .filter(|(name, _)| name.trim().eq_ignore_ascii_case("authorization"))
+            .and_then(|(_, value)| value.trim().strip_prefix("Bearer "))
+            .map(|given| is_token(given.trim(), token))
+            .unwrap_or(false)
+    })
+}
+
+fn content_length(headers: &[String]) -> Option<u64> {
+    headers.iter().find_map(|header| {
+        header
+            .split_once(':')
+            .filter(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, value)| u64::from_str(value.trim()).ok())
+    })
+}
+
+fn error_body(code: &str, message: &str) -> String {
+    serde_json::json!({ "code": code, "message": message }).to_string()
+}
+
+fn command_error(e: CommandError) -> (&'static str, String) {
+    (
+        "422 Unprocessable Entity",
+        error_body(e.code(), &e.to_string()),
+    )
+}
+
+fn sign(
+    body: &[u8],
+    fingerprint: Option<bip32::Fingerprint>,
+    control: &DaemonControl,
+) -> (&'static str, String) {
+    let psbt = match serde_json::from_slice::<SignRequest>(body)
+        .ok()
+        .and_then(|req| Psbt::from_str(&req.psbt).ok())
+    {
+        Some(psbt) => psbt,
+        None => {
+            return (
+                "400 Bad Request",
+                error_body("INVALID_REQUEST", "Expected a JSON object with a 'psbt'."),
+            )
+        }
+    };
+    let txid = psbt.unsigned_tx.compute_txid();
+    match control.sign_psbt_with_hot_signer(psbt, fingerprint) {
+        Ok(res) => {
+            log::info!("Signed PSBT of transaction '{}' for a remote client.", txid);
+            (
+                "200 OK",
+                serde_json::to_string(&res).expect("Serializing the result can't fail"),
+            )
+        }
+        Err(e) => {
+            log::warn!("Refused to sign PSBT of transaction '{}': {}", txid, e);
+            command_error(e)
+        }
+    }
+}
+
+fn handle_request(
+    stream: net::TcpStream,
+    token: &str,
+    fingerprint: Option<bip32::Fingerprint>,
+    control: &DaemonControl,
+) -> Result<(), io::Error> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = io::BufReader::new((&stream).take(MAX_REQUEST_SIZE));
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        headers.push(line);
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let (status, body) = if !is_authorized(&headers, token) {
+        (
+            "401 Unauthorized",
+            error_body("UNAUTHORIZED", "Invalid or missing token."),
+        )
+    } else if (method, path) == ("GET", "/info") {
+        match control.get_hot_signer_xpubs(fingerprint, &[]) {
+            Ok(res) => (
+                "200 OK",
+                serde_json::json!({
+                    "fingerprint": res.fingerprint,
+                    "network": control.get_info().network,
+                })
+                .to_string(),
+            ),
+            Err(e) => command_error(e),
+        }
+    } else if (method, path) == ("POST", "/sign") {
+        let mut body = Vec::new();
+        match content_length(&headers) {
+            Some(len) => {
+                reader.take(len).read_to_end(&mut body)?;
+            }
+            None => {
+                reader.read_to_end(&mut body)?;
+            }
+        }
+        sign(&body, fingerprint, control)
+    } else if path == "/info" || path == "/sign" {
+        (
+            "405 Method Not Allowed",
+            error_body("METHOD_NOT_ALLOWED", "Method not allowed."),
+        )
+    } else {
+        ("404 Not Found", error_body("NOT_FOUND", "Not found."))
+    };
+
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n{}\r\n",
+        status,
+        body.len(),
+        if status.starts_with("401") {
+            "WWW-Authenticate: Bearer\r\n"
+        } else {
+            ""
+        }
+    )?;
+    stream.write_all(body.as_bytes())?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutils::*;
+    use miniscript::bitcoin;
+
+    const TOKEN: &str = "0123456789abcdef0123456789abcdef";
+
+    fn request(addr: net::SocketAddr, method: &str, path: &str, token: &str, body: &str) -> String {
+        let mut stream = net::TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {}\r\n\
+             Content-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            token,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn authorization() {
+        let headers = vec![format!("Authorization: Bearer {}\r\n", TOKEN)];
+        assert!(is_authorized(&headers, TOKEN));
+        assert!(!is_authorized(&[], TOKEN));
+        assert!(!is_authorized(
+            &["Authorization: Bearer 0123456789abcdef\r\n".to_string()],
+            TOKEN
+        ));
+    }
+
+    #[test]
+    fn serve_sign_requests() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server =
+            SignerServer::start(listener, TOKEN.to_string(), None, ms.control().clone()).unwrap();
+
+        // The token is required.
+        let response = request(addr, "GET", "/info", "nope", "");
+        assert!(response.starts_with("HTTP/1.1 401"));
+
+        // There is no hot signer in the data directory.
+        let response = request(addr, "GET", "/info", TOKEN, "");
+        assert!(response.starts_with("HTTP/1.1 422"));
+        assert!(response.contains("NO_HOT_SIGNER"));
+
+        let response = request(addr, "POST", "/sign", TOKEN, "{\"psbt\": \"nope\"}");
+        assert!(response.starts_with("HTTP/1.1 400"));
+
+        // The PSBT must spend coins of the wallet.
+        let psbt = Psbt::from_unsigned_tx(bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![bitcoin::TxIn::default()],
+            output: vec![],
+        })
+        .unwrap();
+        let response = request(
+            addr,
+            "POST",
+            "/sign",
+            TOKEN,
+            &serde_json::json!({ "psbt": psbt.to_string() }).to_string(),
+        );
+        assert!(response.starts_with("HTTP/1.1 422"));
+        assert!(response.contains("UNKNOWN_OUTPOINT"));
+
+        let response = request(addr, "GET", "/sign", TOKEN, "");
+        assert!(response.starts_with("HTTP/1.1 405"));
+        let response = request(addr, "GET", "/status.json", TOKEN, "");
+        assert!(response.starts_with("HTTP/1.1 404"));
+
+        server.stop();
+        ms.shutdown();
+    }
+}
@@ -0,0 +1,360 @@
+//! Snapshots of the coins of the wallet, to bring up another instance of it without rescanning
+//! the whole history of the wallet.
+//!
+//! A snapshot contains the unspent coins of the wallet as of a given tip, along with the
+//! transactions which created them. It doesn't contain any private data. An instance whose data
+//! directory is created from a snapshot only has to sync what happened after this tip.
+
+use crate::{
+    bitcoin::BlockChainTip,
+    commands::utils::{deser_fromstr, ser_to_string},
+    database::{BlockInfo, Coin, CoinStatus, DatabaseConnection, PollUpdates},
+};
+use liana::descriptors::LianaDescriptor;
+
+use std::{fmt, fs, io, path};
+
+use miniscript::bitcoin::{
+    self, bip32,
+    consensus::{self, encode},
+    hashes::hex::FromHex,
+    secp256k1,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// An unspent coin of the wallet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotCoin {
+    pub outpoint: bitcoin::OutPoint,
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub amount: bitcoin::Amount,
+    pub derivation_index: u32,
+    pub is_change: bool,
+    pub is_immature: bool,
+    /// Height and time of the block the coin was confirmed in, if it was.
+    pub block_height: Option<i32>,
+    pub block_time: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoinsSnapshot {
+    /// The descriptor of the wallet the coins belong to.
+    #[serde(serialize_with = "ser_to_string", deserialize_with = "deser_fromstr")]
+    pub descriptor: LianaDescriptor,
+    /// The tip as of which the coins were exported.
+    pub tip_height: i32,
+    pub tip_hash: bitcoin::BlockHash,
+    /// The next derivation indexes for receive and change addresses.
+    pub receive_index: u32,
+    pub change_index: u32,
+    pub coins: Vec<SnapshotCoin>,
+    /// The transactions creating the coins.
+    #[serde(serialize_with = "ser_txs", deserialize_with = "deser_txs")]
+    pub transactions: Vec<bitcoin::Transaction>,
+}
+
+fn ser_txs<S: Serializer>(txs: &[bitcoin::Transaction], s: S) -> Result<S::Ok, S::Error> {
+    s.collect_seq(txs.iter().map(encode::serialize_hex))
+}
+
+fn deser_txs<'de, D>(d: D) -> Result<Vec<bitcoin::Transaction>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(d)?
+        .into_iter()
+        .map(|s| {
+            let bytes = Vec::from_hex(&s).map_err(de::Error::custom)?;
+            consensus::deserialize(&bytes).map_err(de::Error::custom)
+        })
+        .collect()
+}
+
+impl CoinsSnapshot {
+    pub fn tip(&self) -> BlockChainTip {
+        BlockChainTip {
+            height: self.tip_height,
+            hash: self.tip_hash,
+        }
+    }
+
+    // Make sure the snapshot can be imported, before anything is written.
+    fn check(&self) -> Result<(), SnapshotError> {
+        let indexes = [self.receive_index, self.change_index]
+            .iter()
+            .copied()
+            .chain(self.coins.iter().map(|c| c.derivation_index));
+        for index in indexes {
+            if bip32::ChildNumber::from_normal_idx(index).is_err() {
+                return Err(SnapshotError::InvalidDerivationIndex(index));
+            }
+        }
+        for coin in &self.coins {
+            if !self
+                .transactions
+                .iter()
+                .any(|tx| tx.compute_txid() == coin.outpoint.txid)
+            {
+                return Err(SnapshotError::MissingTransaction(coin.outpoint.txid));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Reading(io::Error),
+    Parsing(serde_json::Error),
+    /// The snapshot is of another wallet.
+    DescriptorMismatch(Box<LianaDescriptor>),
+    /// A coin was created by a transaction missing from the snapshot.
+    MissingTransaction(bitcoin::Txid),
+    InvalidDerivationIndex(u32),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Reading(e) => write!(f, "Error reading the coins snapshot: '{}'.", e),
+            Self::Parsing(e) => write!(f, "Error parsing the coins snapshot: '{}'.", e),
+            Self::DescriptorMismatch(desc) => write!(
+                f,
+                "The coins snapshot is of another wallet, with descriptor '{}'.",
+                desc
+            ),
+            Self::MissingTransaction(txid) => write!(
+                f,
+                "The transaction '{}' creating a coin is missing from the coins snapshot.",
+                txid
+            ),
+            Self::InvalidDerivationIndex(index) => write!(
+                f,
+                "Invalid derivation index '{}' in the coins snapshot.",
+                index
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+/// Read the snapshot at this path and check it is of the wallet with this descriptor.
+pub fn read(path: &path::Path, desc: &LianaDescriptor) -> Result<CoinsSnapshot, SnapshotError> {
+    let content = fs::read(path).map_err(SnapshotError::Reading)?;
+    let snapshot: CoinsSnapshot =
+        serde_json::from_slice(&content).map_err(SnapshotError::Parsing)?;
+    if &snapshot.descriptor != desc {
+        return Err(SnapshotError::DescriptorMismatch(Box::new(
+            snapshot.descriptor,
+        )));
+    }
+    snapshot.check()?;
+    Ok(snapshot)
+}
+
+/// Export the unspent coins of the wallet as of the current tip. Returns `None` if the wallet
+/// was never synced.
+pub(crate) fn export(
+    db_conn: &mut dyn DatabaseConnection,
+    desc: &LianaDescriptor,
+) -> Option<CoinsSnapshot> {
+    // Get the tip before the coins: the coins confirmed after it, if the tip moved in the
+    // meantime, are left to be found when syncing from this tip.
+    let tip = db_conn.chain_tip()?;
+    let mut coins: Vec<Coin> = db_conn
+        .coins(
+            &[
+                CoinStatus::Unconfirmed,
+                CoinStatus::Confirmed,
+                CoinStatus::Spending,
+            ],
+            &[],
+        )
+        .into_values()
+        .filter(|c| c.block_info.map(|b| b.height <= tip.height).unwrap_or(true))
+        .collect();
+    coins.sort_by_key(|c| c.outpoint);
+    let mut txids: Vec<_> = coins.iter().map(|c| c.outpoint.txid).collect();
+    txids.dedup();
+    let transactions = db_conn
+        .list_wallet_transactions(&txids)
+        .into_iter()
+        .map(|(tx, _, _)| tx)
+        .collect();
+
+    Some(CoinsSnapshot {
+        descriptor: desc.clone(),
+        tip_height: tip.height,
+        tip_hash: tip.hash,
+        receive_index: db_conn.receive_index().into(),
+        change_index: db_conn.change_index().into(),
+        // A coin being spent is exported as unspent, the spend will be noticed again when
+        // syncing.
+        coins: coins
+            .into_iter()
+            .map(|c| SnapshotCoin {
+                outpoint: c.outpoint,
+                amount: c.amount,
+                derivation_index: c.derivation_index.into(),
+                is_change: c.is_change,
+                is_immature: c.is_immature,
+                block_height: c.block_info.map(|b| b.height),
+                block_time: c.block_info.map(|b| b.time),
+            })
+            .collect(),
+        transactions,
+    })
+}
+
+/// Store the coins of this snapshot in a fresh database, and set its tip to the tip of the
+/// snapshot.
+pub(crate) fn import(
+    db_conn: &mut dyn DatabaseConnection,
+    snapshot: &CoinsSnapshot,
+    secp: &secp256k1::Secp256k1<secp256k1::VerifyOnly>,
+) -> Result<(), SnapshotError> {
+    snapshot.check()?;
+    let index = |index: u32| bip32::ChildNumber::from_normal_idx(index).expect("Checked above.");
+    let mut updates = PollUpdates {
+        txs: snapshot.transactions.clone(),
+        ..Default::default()
+    };
+    for coin in &snapshot.coins {
+        let block_info = match (coin.block_height, coin.block_time) {
+            (Some(height), Some(time)) => Some(BlockInfo { height, time }),
+            _ => None,
+        };
+        updates.received.push(Coin {
+            outpoint: coin.outpoint,
+            is_immature: coin.is_immature,
+            block_info: None,
+            amount: coin.amount,
+            derivation_index: index(coin.derivation_index),
+            is_change: coin.is_change,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        });
+        if let Some(BlockInfo { height, time }) = block_info {
+            updates.confirmed.push((coin.outpoint, height, time));
+        }
+    }
+
+    db_conn.apply_poll_updates(&updates, snapshot.tip_height, Some(&snapshot.tip()));
+    db_conn.set_receive_index(index(snapshot.receive_index), secp);
+    db_conn.set_change_index(index(snapshot.change_index), secp);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutils::*;
+
+    use std::str::FromStr;
+
+    #[test]
+    fn snapshot_roundtrip() {
+        let secp = secp256k1::Secp256k1::verification_only();
+        let desc = LianaDescriptor::from_str("wsh(or_d(pk([aabbccdd]tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/<0;1>/*),and_v(v:pkh([abcdef01]tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/<0;1>/*),older(10000))))").unwrap();
+        let tx = bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![bitcoin::TxIn::default()],
+            output: vec![
+                bitcoin::TxOut {
+                    value: bitcoin::Amount::from_sat(100_000),
+                    script_pubkey: bitcoin::ScriptBuf::new(),
+                },
+                bitcoin::TxOut {
+                    value: bitcoin::Amount::from_sat(200_000),
+                    script_pubkey: bitcoin::ScriptBuf::new(),
+                },
+            ],
+        };
+        let txid = tx.compute_txid();
+        let coin = |vout: u32, amount: u64, block_info: Option<BlockInfo>| Coin {
+            outpoint: bitcoin::OutPoint::new(txid, vout),
+            is_immature: false,
+            block_info,
+            amount: bitcoin::Amount::from_sat(amount),
+            derivation_index: bip32::ChildNumber::from(vout + 3),
+            is_change: vout == 1,
+            spend_txid: None,
+            spend_block: None,
+            is_from_self: false,
+        };
+
+        // The coins confirmed after the tip are left out.
+        let mut db = DummyDatabase::new();
+        let tip = BlockChainTip {
+            height: 100,
+            hash: bitcoin::BlockHash::from_str(
+                "000000000000000000016a8a7b87bfa3fc1e6bd7d1f5b4cbef9c94d0bd7cd41e",
+            )
+            .unwrap(),
+        };
+        db.update_tip(&tip);
+        db.new_txs(&[tx.clone()]);
+        db.new_unspent_coins(&[
+            coin(
+                0,
+                100_000,
+                Some(BlockInfo {
+                    height: 90,
+                    time: 1_700_000_000,
+                }),
+            ),
+            coin(
+                1,
+                200_000,
+                Some(BlockInfo {
+                    height: 101,
+                    time: 1_700_001_000,
+                }),
+            ),
+        ]);
+        let snapshot = export(&mut db, &desc).unwrap();
+        assert_eq!(snapshot.tip(), tip);
+        assert_eq!(
+            snapshot.coins,
+            vec![SnapshotCoin {
+                outpoint: bitcoin::OutPoint::new(txid, 0),
+                amount: bitcoin::Amount::from_sat(100_000),
+                derivation_index: 3,
+                is_change: false,
+                is_immature: false,
+                block_height: Some(90),
+                block_time: Some(1_700_000_000),
+            }]
+        );
+        assert_eq!(snapshot.transactions, vec![tx]);
+
+        // It can be serialized and read back.
+        let snapshot: CoinsSnapshot =
+            serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+
+        // Importing it restores the coin as confirmed and the tip.
+        let mut new_db = DummyDatabase::new();
+        import(&mut new_db, &snapshot, &secp).unwrap();
+        assert_eq!(new_db.chain_tip(), Some(tip));
+        let coins = new_db.coins(&[], &[]);
+        assert_eq!(coins.len(), 1);
+        assert_eq!(
+            coins[&bitcoin::OutPoint::new(txid, 0)].block_info,
+            Some(BlockInfo {
+                height: 90,
+                time: 1_700_000_000
+            })
+        );
+
+        // A snapshot missing the transaction of a coin is rejected.
+        let mut snapshot = snapshot;
+        snapshot.transactions.clear();
+        assert!(matches!(
+            import(&mut DummyDatabase::new(), &snapshot, &secp),
+            Err(SnapshotError::MissingTransaction(t)) if t == txid
+        ));
+    }
+}
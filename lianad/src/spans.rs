@@ -0,0 +1,194 @@
+//! Spans around the operations of the daemon: the commands, the polls of the Bitcoin backend and
+//! the database queries.
+//!
+//! Each span gets an identifier, and remembers the spans ongoing on the same thread when it
+//! started: the poll or command a database query is part of. Their start and end are logged at the
+//! trace level. The operations taking longer than a configurable threshold are logged as a warning
+//! along with the ones they are part of, for a user experiencing a frozen GUI to be able to tell
+//! what it's waiting for.
+
+use std::{
+    cell::RefCell,
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+    time,
+};
+
+/// Operations taking at least this long, in milliseconds, are logged as slow unless configured
+/// otherwise.
+pub const DEFAULT_SLOW_THRESHOLD_MS: u64 = 1_000;
+
+/// How many characters of a database query are logged.
+const MAX_QUERY_LENGTH: usize = 80;
+
+static SLOW_THRESHOLD_MS: AtomicU64 = AtomicU64::new(DEFAULT_SLOW_THRESHOLD_MS);
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    // The spans ongoing on this thread, the innermost last, with their description.
+    static ONGOING: RefCell<Vec<(u64, String)>> = RefCell::new(Vec::new());
+}
+
+/// Set the duration from which an operation is logged as slow, in milliseconds. Set to 0 to log
+/// them all.
+pub fn set_slow_threshold(threshold_ms: u64) {
+    SLOW_THRESHOLD_MS.store(threshold_ms, Ordering::Relaxed);
+}
+
+/// The kind of operation a span covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanKind {
+    /// A command of the API, named after its method.
+    Command,
+    /// An update of our state from the Bitcoin backend.
+    Poll,
+    /// A step of a poll.
+    PollStep,
+    /// A database query, named after its statement.
+    DbQuery,
+}
+
+impl fmt::Display for SpanKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Command => write!(f, "command"),
+            Self::Poll => write!(f, "poll"),
+            Self::PollStep => write!(f, "poll step"),
+            Self::DbQuery => write!(f, "database query"),
+        }
+    }
+}
+
+// A database statement on a single line, shortened.
+fn query_summary(statement: &str) -> String {
+    let summary = statement.split_whitespace().collect::<Vec<_>>().join(" ");
+    if summary.chars().count() > MAX_QUERY_LENGTH {
+        format!(
+            "{}...",
+            summary.chars().take(MAX_QUERY_LENGTH).collect::<String>()
+        )
+    } else {
+        summary
+    }
+}
+
+// Describe the spans an operation is part of, innermost first.
+fn parents_description(ongoing: &[(u64, String)]) -> String {
+    ongoing
+        .iter()
+        .rev()
+        .map(|(id, desc)| format!(", within {} (span {})", desc, id))
+        .collect()
+}
+
+/// An ongoing operation. It ends when dropped.
+pub struct Span {
+    id: u64,
+    description: String,
+    start: time::Instant,
+}
+
+impl Span {
+    /// Start a span. It must be dropped on the thread it was started on.
+    pub fn enter(kind: SpanKind, name: &str) -> Span {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let name = match kind {
+            SpanKind::DbQuery => query_summary(name),
+            _ => name.to_string(),
+        };
+        let description = format!("{} '{}'", kind, name);
+        ONGOING.with(|ongoing| {
+            let mut ongoing = ongoing.borrow_mut();
+            log::trace!(
+                "[span {}] Started {}{}.",
+                id,
+                description,
+                parents_description(&ongoing)
+            );
+            ongoing.push((id, description.clone()));
+        });
+        Span {
+            id,
+            description,
+            start: time::Instant::now(),
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let elapsed_ms = self.start.elapsed().as_millis() as u64;
+        ONGOING.with(|ongoing| {
+            let mut ongoing = ongoing.borrow_mut();
+            if let Some(position) = ongoing.iter().rposition(|(id, _)| *id == self.id) {
+                ongoing.remove(position);
+            }
+            if elapsed_ms >= SLOW_THRESHOLD_MS.load(Ordering::Relaxed) {
+                log::warn!(
+                    "[span {}] Slow {}: took {}ms{}.",
+                    self.id,
+                    self.description,
+                    elapsed_ms,
+                    parents_description(&ongoing)
+                );
+            } else {
+                log::trace!(
+                    "[span {}] Completed {} in {}ms.",
+                    self.id,
+                    self.description,
+                    elapsed_ms
+                );
+            }
+        });
+    }
+}
+
+/// Run this operation within a span.
+pub fn within<T>(kind: SpanKind, name: &str, operation: impl FnOnce() -> T) -> T {
+    let _span = Span::enter(kind, name);
+    operation()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ongoing() -> Vec<String> {
+        ONGOING.with(|ongoing| ongoing.borrow().iter().map(|(_, d)| d.clone()).collect())
+    }
+
+    #[test]
+    fn spans() {
+        assert_eq!(
+            query_summary("SELECT * FROM coins\n         WHERE blocktime IS NOT NULL"),
+            "SELECT * FROM coins WHERE blocktime IS NOT NULL"
+        );
+        assert_eq!(
+            query_summary(&"a".repeat(100)),
+            format!("{}...", "a".repeat(80))
+        );
+
+        // The spans started within another one are part of it until they end.
+        let command = Span::enter(SpanKind::Command, "listcoins");
+        within(SpanKind::DbQuery, "SELECT *\n FROM coins", || {
+            assert_eq!(
+                ongoing(),
+                vec![
+                    "command 'listcoins'".to_string(),
+                    "database query 'SELECT * FROM coins'".to_string()
+                ]
+            );
+        });
+        assert_eq!(ongoing(), vec!["command 'listcoins'".to_string()]);
+        assert_eq!(
+            parents_description(&[
+                (1, "poll '#1'".to_string()),
+                (2, "poll step 'x'".to_string())
+            ]),
+            ", within poll step 'x' (span 2), within poll '#1' (span 1)"
+        );
+        drop(command);
+        assert!(ongoing().is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+//! Statements listing the next receive addresses of the wallet, for a counterparty paying in
+//! installments to check the addresses it's given later on belong to the same wallet without
+//! having to get in touch.
+//!
+//! The statement is a text message listing the addresses along with their derivation index,
+//! signed (BIP322) for the first of them by the signers of the primary path. It proves whoever
+//! controls the first address vouches for the others: the counterparty is expected to have
+//! checked this one, for instance by paying to it a first time.
+
+use std::fmt;
+
+use miniscript::bitcoin::{self, address::NetworkUnchecked, Address};
+
+const HEADER: &str = "Liana receive addresses";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementError(String);
+
+impl fmt::Display for StatementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The receive addresses listed by a statement, along with their derivation index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressStatement {
+    pub network: bitcoin::Network,
+    pub addresses: Vec<(u32, Address)>,
+}
+
+impl AddressStatement {
+    /// The address the statement is signed for.
+    pub fn signing_address(&self) -> Option<&Address> {
+        self.addresses.first().map(|(_, address)| address)
+    }
+
+    /// The message to sign.
+    pub fn message(&self) -> String {
+        let mut message = format!("{}\nnetwork: {}\n", HEADER, self.network);
+        for (index, address) in &self.addresses {
+            message.push_str(&format!("{}: {}\n", index, address));
+        }
+        message
+    }
+
+    /// Parse the message of a statement for addresses on this network.
+    pub fn from_message(
+        message: &str,
+        network: bitcoin::Network,
+    ) -> Result<AddressStatement, StatementError> {
+        let mut lines = message.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(StatementError("Not an address statement.".to_string()));
+        }
+        if lines.next() != Some(format!("network: {}", network).as_str()) {
+            return Err(StatementError(format!(
+                "The statement is not for network '{}'.",
+                network
+            )));
+        }
+        let addresses = lines
+            .map(|line| {
+                let (index, address) = line
+                    .split_once(": ")
+                    .ok_or_else(|| StatementError(format!("Invalid line '{}'.", line)))?;
+                let index = index
+                    .parse::<u32>()
+                    .map_err(|_| StatementError(format!("Invalid index '{}'.", index)))?;
+                let address = address
+                    .parse::<Address<NetworkUnchecked>>()
+                    .ok()
+                    .and_then(|a| a.require_network(network).ok())
+                    .ok_or_else(|| StatementError(format!("Invalid address '{}'.", address)))?;
+                Ok((index, address))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if addresses.is_empty() {
+            return Err(StatementError(
+                "The statement lists no address.".to_string(),
+            ));
+        }
+        Ok(AddressStatement { network, addresses })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use liana::descriptors::LianaDescriptor;
+
+    use miniscript::bitcoin::secp256k1;
+    use std::str::FromStr;
+
+    #[test]
+    fn statement_message() {
+        let secp = secp256k1::Secp256k1::verification_only();
+        let desc = LianaDescriptor::from_str("wsh(or_d(pk([aabbccdd]tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/<0;1>/*),and_v(v:pkh([abcdef01]tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/<0;1>/*),older(10000))))").unwrap();
+        let network = bitcoin::Network::Testnet;
+        let address = |index: u32| {
+            desc.receive_descriptor()
+                .derive(index.into(), &secp)
+                .address(network)
+        };
+        let statement = AddressStatement {
+            network,
+            addresses: vec![(4, address(4)), (5, address(5))],
+        };
+        let message = statement.message();
+        assert_eq!(
+            message,
+            format!(
+                "Liana receive addresses\nnetwork: testnet\n4: {}\n5: {}\n",
+                address(4),
+                address(5)
+            )
+        );
+        assert_eq!(
+            AddressStatement::from_message(&message, network).unwrap(),
+            statement
+        );
+        assert_eq!(statement.signing_address(), Some(&address(4)));
+
+        // It must be for the expected network, list at least an address and be well formed.
+        assert!(AddressStatement::from_message(&message, bitcoin::Network::Bitcoin).is_err());
+        assert!(AddressStatement::from_message(
+            "Liana receive addresses\nnetwork: testnet\n",
+            network
+        )
+        .is_err());
+        assert!(AddressStatement::from_message(&message.replace("4:", "x:"), network).is_err());
+        assert!(
+            AddressStatement::from_message(&message.replace("Liana", "Other"), network).is_err()
+        );
+    }
+}
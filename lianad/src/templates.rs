@@ -0,0 +1,240 @@
+//! Spend templates: named sets of recipients, feerate and coins from which to quickly create
+//! recurring payments.
+//!
+//! The amount of a recipient may be left to be given each time the template is instantiated. A
+//! template whose amounts are all set may be scheduled, in which case a draft is created from it
+//! at a fixed interval and stored awaiting signatures.
+
+use crate::DaemonControl;
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::mpsc,
+    thread, time,
+};
+
+use miniscript::bitcoin::{self, address::NetworkUnchecked, Address, Amount};
+use serde::{Deserialize, Serialize};
+
+/// How often we check whether drafts of the scheduled templates are due.
+const SCHEDULER_INTERVAL: time::Duration = time::Duration::from_secs(10 * 60);
+
+const SECS_IN_DAY: u32 = 24 * 60 * 60;
+
+/// Maximum length of the name of a template.
+const MAX_NAME_LENGTH: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateRecipient {
+    pub address: Address<NetworkUnchecked>,
+    /// The amount to send, or `None` to ask for it when instantiating the template.
+    #[serde(default, with = "bitcoin::amount::serde::as_sat::opt")]
+    pub amount: Option<Amount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Schedule {
+    /// Create a draft every this many days.
+    pub every_days: u32,
+    /// When the last draft was created from this template.
+    #[serde(default)]
+    pub last_draft_at: Option<u32>,
+}
+
+impl Schedule {
+    /// Whether a new draft should be created at this time.
+    pub fn is_due(&self, now: u32) -> bool {
+        self.last_draft_at
+            .map(|last| now >= last.saturating_add(self.every_days.saturating_mul(SECS_IN_DAY)))
+            .unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendTemplate {
+    /// The name identifying this template.
+    pub name: String,
+    pub recipients: Vec<TemplateRecipient>,
+    /// The preferred feerate for the transaction, in sats/vb.
+    pub feerate: u64,
+    /// The coins to spend. If empty, coins are selected automatically.
+    #[serde(default)]
+    pub coins: Vec<bitcoin::OutPoint>,
+    #[serde(default)]
+    pub schedule: Option<Schedule>,
+}
+
+impl SpendTemplate {
+    /// Check the template is consistent and its addresses are for this network.
+    pub fn validate(&self, network: bitcoin::Network) -> Result<(), String> {
+        if self.name.is_empty() || self.name.len() > MAX_NAME_LENGTH {
+            return Err(format!(
+                "The name must be between 1 and {} characters long.",
+                MAX_NAME_LENGTH
+            ));
+        }
+        if self.recipients.is_empty() {
+            return Err("A template must have at least one recipient.".to_string());
+        }
+        let mut addresses = HashSet::new();
+        for recipient in &self.recipients {
+            if !recipient.address.is_valid_for_network(network) {
+                return Err(format!(
+                    "Address '{}' is not for network '{}'.",
+                    recipient.address.assume_checked_ref(),
+                    network
+                ));
+            }
+            if !addresses.insert(&recipient.address) {
+                return Err(format!(
+                    "Address '{}' appears more than once.",
+                    recipient.address.assume_checked_ref()
+                ));
+            }
+        }
+        if !(1..=liana::spend::MAX_FEERATE).contains(&self.feerate) {
+            return Err(format!("Invalid feerate: {} sats/vb.", self.feerate));
+        }
+        if let Some(schedule) = &self.schedule {
+            if schedule.every_days == 0 {
+                return Err("The schedule interval must be at least one day.".to_string());
+            }
+            if self.recipients.iter().any(|r| r.amount.is_none()) {
+                return Err("All the amounts must be set to schedule a template.".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// The destinations of a transaction instantiating this template, given the amounts for the
+    /// recipients whose amount is asked.
+    pub fn destinations(
+        &self,
+        amounts: &HashMap<Address<NetworkUnchecked>, u64>,
+    ) -> Result<HashMap<Address<NetworkUnchecked>, u64>, String> {
+        self.recipients
+            .iter()
+            .map(|recipient| {
+                let amount = amounts
+                    .get(&recipient.address)
+                    .copied()
+                    .or_else(|| recipient.amount.map(|a| a.to_sat()))
+                    .ok_or_else(|| {
+                        format!(
+                            "Missing amount for recipient '{}'.",
+                            recipient.address.assume_checked_ref()
+                        )
+                    })?;
+                Ok((recipient.address.clone(), amount))
+            })
+            .collect()
+    }
+}
+
+fn now() -> u32 {
+    time::SystemTime::now()
+        .duration_since(time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as u32)
+        .unwrap_or(0)
+}
+
+/// A thread creating the drafts of the scheduled templates once they are due.
+pub struct Scheduler {
+    shutdown: mpsc::Sender<()>,
+    handle: thread::JoinHandle<()>,
+}
+
+impl Scheduler {
+    pub fn start(control: DaemonControl) -> Scheduler {
+        let (shutdown, receiver) = mpsc::channel();
+        let handle = thread::Builder::new()
+            .name("Spend templates scheduler".to_string())
+            .spawn(move || {
+                // Leave the poller the time to update our coins before the first check.
+                while receiver.recv_timeout(SCHEDULER_INTERVAL)
+                    == Err(mpsc::RecvTimeoutError::Timeout)
+                {
+                    let _writes = control.lock_writes();
+                    control.create_scheduled_drafts(now());
+                }
+            })
+            .expect("Spawning the scheduler thread must never fail.");
+        Scheduler { shutdown, handle }
+    }
+
+    pub fn stop(self) {
+        // The thread also stops if we hung up already.
+        let _ = self.shutdown.send(());
+        self.handle.join().expect("Scheduler thread must not panic");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn template() -> SpendTemplate {
+        SpendTemplate {
+            name: "Payroll".to_string(),
+            recipients: vec![
+                TemplateRecipient {
+                    address: Address::from_str("bc1qvrl2849aggm6qry9ea7xqp2kk39j8vaa8r3cwg")
+                        .unwrap(),
+                    amount: Some(Amount::from_sat(100_000)),
+                },
+                TemplateRecipient {
+                    address: Address::from_str("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4")
+                        .unwrap(),
+                    amount: None,
+                },
+            ],
+            feerate: 2,
+            coins: Vec::new(),
+            schedule: None,
+        }
+    }
+
+    #[test]
+    fn spend_template() {
+        let mut template = template();
+        template.validate(bitcoin::Network::Bitcoin).unwrap();
+        assert!(template.validate(bitcoin::Network::Testnet).is_err());
+
+        // The asked amount must be given.
+        assert!(template.destinations(&HashMap::new()).is_err());
+        let asked = template.recipients[1].address.clone();
+        let destinations = template
+            .destinations(&HashMap::from([(asked.clone(), 50_000)]))
+            .unwrap();
+        assert_eq!(destinations.len(), 2);
+        assert_eq!(destinations[&asked], 50_000);
+        assert_eq!(destinations[&template.recipients[0].address], 100_000);
+
+        // Only templates with all their amounts set can be scheduled.
+        template.schedule = Some(Schedule {
+            every_days: 30,
+            last_draft_at: None,
+        });
+        assert!(template.validate(bitcoin::Network::Bitcoin).is_err());
+        template.recipients[1].amount = Some(Amount::from_sat(50_000));
+        template.validate(bitcoin::Network::Bitcoin).unwrap();
+
+        let mut duplicated = template.clone();
+        duplicated.recipients.push(duplicated.recipients[0].clone());
+        assert!(duplicated.validate(bitcoin::Network::Bitcoin).is_err());
+
+        let schedule = template.schedule.as_mut().unwrap();
+        assert!(schedule.is_due(1_700_000_000));
+        schedule.last_draft_at = Some(1_700_000_000);
+        assert!(!schedule.is_due(1_700_000_000 + 29 * SECS_IN_DAY));
+        assert!(schedule.is_due(1_700_000_000 + 30 * SECS_IN_DAY));
+
+        // It round trips through its serialization, which is also how it's stored.
+        let ser = serde_json::to_string(&template).unwrap();
+        assert_eq!(
+            serde_json::from_str::<SpendTemplate>(&ser).unwrap(),
+            template
+        );
+    }
+}
@@ -1,9 +1,17 @@
 use crate::{
-    bitcoin::{BitcoinInterface, Block, BlockChainTip, MempoolEntry, SyncProgress, UTxO},
+    bitcoin::{
+        BitcoinInterface, Block, BlockChainTip, BlockTransactions, FeeHistogramBucket,
+        InclusionCheck, MempoolEntry, MinFeerates, SyncProgress, UTxO,
+    },
     config::{BitcoinConfig, Config},
     database::{
-        BlockInfo, Coin, CoinStatus, DatabaseConnection, DatabaseInterface, LabelItem, Wallet,
+        BlockInfo, Coin, CoinStatus, DatabaseConnection, DatabaseInterface, LabelItem, PollUpdates,
+        ScheduledSpend, SilentPayment, SilentPaymentKeys, TxVerification, Wallet,
     },
+    events::EventEntry,
+    readiness::ReadinessItem,
+    reviews::SpendReview,
+    templates::SpendTemplate,
     DaemonControl, DaemonHandle,
 };
 use liana::descriptors;
@@ -24,6 +32,14 @@ use miniscript::{
 
 pub struct DummyBitcoind {
     pub txs: HashMap<Txid, (Transaction, Option<Block>)>,
+    /// Whether the proof of inclusion of each transaction is valid, to act as a backend which
+    /// doesn't validate the chain. `None` for a backend which does.
+    pub inclusion_proofs: Option<HashMap<Txid, bool>>,
+    pub mempool_entries: HashMap<Txid, MempoolEntry>,
+    pub min_feerates: Option<MinFeerates>,
+    pub fee_histogram: Option<Vec<FeeHistogramBucket>>,
+    /// The blocks by height, for the backends which expose their transactions.
+    pub blocks: Option<HashMap<i32, BlockTransactions>>,
 }
 
 impl DummyBitcoind {}
@@ -32,6 +48,11 @@ impl DummyBitcoind {
     pub fn new() -> Self {
         Self {
             txs: HashMap::new(),
+            inclusion_proofs: None,
+            mempool_entries: HashMap::new(),
+            min_feerates: None,
+            fee_histogram: None,
+            blocks: None,
         }
     }
 }
@@ -67,6 +88,10 @@ impl BitcoinInterface for DummyBitcoind {
         true
     }
 
+    fn load_wallet_step(&mut self) -> bool {
+        true
+    }
+
     fn sync_wallet(
         &mut self,
         _receive_index: bip32::ChildNumber,
@@ -109,7 +134,7 @@ impl BitcoinInterface for DummyBitcoind {
     }
 
     fn broadcast_tx(&self, _: &bitcoin::Transaction) -> Result<(), String> {
-        todo!()
+        Ok(())
     }
 
     fn start_rescan(&mut self, _: &descriptors::LianaDescriptor, _: u32) -> Result<(), String> {
@@ -120,6 +145,14 @@ impl BitcoinInterface for DummyBitcoind {
         None
     }
 
+    fn rescan_interrupted_since(&self, _: u32) -> bool {
+        false
+    }
+
+    fn resume_rescan(&mut self, _: &descriptors::LianaDescriptor, _: i32) -> Result<(), String> {
+        todo!()
+    }
+
     fn block_before_date(&self, _: u32) -> Option<BlockChainTip> {
         todo!()
     }
@@ -139,8 +172,33 @@ impl BitcoinInterface for DummyBitcoind {
         Vec::new()
     }
 
-    fn mempool_entry(&self, _: &bitcoin::Txid) -> Option<MempoolEntry> {
-        None
+    fn mempool_entry(&self, txid: &bitcoin::Txid) -> Option<MempoolEntry> {
+        self.mempool_entries.get(txid).cloned()
+    }
+
+    fn min_feerates(&self) -> Option<MinFeerates> {
+        self.min_feerates
+    }
+
+    fn fee_histogram(&self) -> Option<Vec<FeeHistogramBucket>> {
+        self.fee_histogram.clone()
+    }
+
+    fn check_inclusion(&self, txid: &bitcoin::Txid, _: i32) -> InclusionCheck {
+        match self
+            .inclusion_proofs
+            .as_ref()
+            .map(|proofs| proofs.get(txid))
+        {
+            None => InclusionCheck::NotNeeded,
+            Some(Some(true)) => InclusionCheck::Verified,
+            Some(Some(false)) => InclusionCheck::Invalid("Invalid merkle proof.".to_string()),
+            Some(None) => InclusionCheck::Unavailable("Unknown transaction.".to_string()),
+        }
+    }
+
+    fn block_transactions(&self, height: i32) -> Option<BlockTransactions> {
+        self.blocks.as_ref()?.get(&height).cloned()
     }
 }
 
@@ -151,6 +209,16 @@ struct DummyDbState {
     coins: HashMap<bitcoin::OutPoint, Coin>,
     txs: HashMap<bitcoin::Txid, bitcoin::Transaction>,
     spend_txs: HashMap<bitcoin::Txid, (Psbt, Option<u32>)>,
+    spend_reviews: HashMap<bitcoin::Txid, Vec<SpendReview>>,
+    broadcast_journal: Vec<bitcoin::Transaction>,
+    scheduled_spends: Vec<ScheduledSpend>,
+    tx_verifications: HashMap<bitcoin::Txid, TxVerification>,
+    silent_payment_keys: Option<SilentPaymentKeys>,
+    silent_payments: Vec<SilentPayment>,
+    readiness_checks: HashMap<ReadinessItem, u32>,
+    events: Vec<EventEntry>,
+    spend_templates: Vec<SpendTemplate>,
+    labels: HashMap<String, String>,
     timestamp: u32,
     rescan_timestamp: Option<u32>,
     last_poll_timestamp: Option<u32>,
@@ -184,7 +252,17 @@ impl DummyDatabase {
                 curr_tip: None,
                 coins: HashMap::new(),
                 txs: HashMap::new(),
+                broadcast_journal: Vec::new(),
+                scheduled_spends: Vec::new(),
+                tx_verifications: HashMap::new(),
+                silent_payment_keys: None,
+                silent_payments: Vec::new(),
+                readiness_checks: HashMap::new(),
+                events: Vec::new(),
+                spend_templates: Vec::new(),
+                labels: HashMap::new(),
                 spend_txs: HashMap::new(),
+                spend_reviews: HashMap::new(),
                 timestamp: now,
                 rescan_timestamp: None,
                 last_poll_timestamp: None,
@@ -216,6 +294,7 @@ impl DatabaseConnection for DummyDatabase {
             change_index: db_wallet.change_index,
             rescan_timestamp: db_wallet.rescan_timestamp,
             last_poll_timestamp: db_wallet.last_poll_timestamp,
+            rescan_checkpoint: None,
         }
     }
 
@@ -358,6 +437,46 @@ impl DatabaseConnection for DummyDatabase {
         }
     }
 
+    fn balances(&mut self) -> Balances {
+        let mut balances = Balances::default();
+        for coin in self.db.read().unwrap().coins.values() {
+            let balance = if coin.spend_block.is_some() {
+                continue;
+            } else if coin.spend_txid.is_some() {
+                &mut balances.spending
+            } else if coin.is_immature {
+                &mut balances.immature
+            } else if coin.block_info.is_some() {
+                &mut balances.confirmed
+            } else if coin.is_from_self {
+                &mut balances.unconfirmed_from_self
+            } else {
+                &mut balances.unconfirmed
+            };
+            *balance += coin.amount;
+        }
+        balances
+    }
+
+    fn apply_poll_updates(
+        &mut self,
+        updates: &PollUpdates,
+        prev_tip_height: i32,
+        new_tip: Option<&BlockChainTip>,
+    ) {
+        self.new_txs(&updates.txs);
+        self.new_unspent_coins(&updates.received);
+        self.remove_coins(&updates.expired);
+        self.confirm_coins(&updates.confirmed);
+        self.unspend_coins(&updates.expired_spending);
+        self.spend_coins(&updates.spending);
+        self.confirm_spend(&updates.spent);
+        self.update_coins_from_self(prev_tip_height);
+        if let Some(tip) = new_tip {
+            self.update_tip(tip);
+        }
+    }
+
     fn derivation_index_by_address(
         &mut self,
         _: &bitcoin::Address,
@@ -410,7 +529,238 @@ impl DatabaseConnection for DummyDatabase {
     }
 
     fn delete_spend(&mut self, txid: &bitcoin::Txid) {
-        self.db.write().unwrap().spend_txs.remove(txid);
+        let mut db = self.db.write().unwrap();
+        db.spend_txs.remove(txid);
+        db.spend_reviews.remove(txid);
+    }
+
+    fn spend_signers(&mut self, txid: &bitcoin::Txid) -> Vec<(bip32::Fingerprint, u32)> {
+        let psbt = match self.spend_tx(txid) {
+            Some(psbt) => psbt,
+            None => return Vec::new(),
+        };
+        let mut signers = std::collections::BTreeSet::new();
+        for psbt_in in psbt.inputs {
+            for pk in psbt_in.partial_sigs.keys() {
+                if let Some((fg, _)) = psbt_in.bip32_derivation.get(&pk.inner) {
+                    signers.insert(*fg);
+                }
+            }
+            for (pk, _) in psbt_in.tap_script_sigs.keys() {
+                if let Some((_, (fg, _))) = psbt_in.tap_key_origins.get(pk) {
+                    signers.insert(*fg);
+                }
+            }
+        }
+        signers.into_iter().map(|fg| (fg, 0)).collect()
+    }
+
+    fn store_spend_review(&mut self, txid: &bitcoin::Txid, review: &SpendReview) {
+        let mut db = self.db.write().unwrap();
+        let reviews = db.spend_reviews.entry(*txid).or_default();
+        reviews.retain(|r| r.signer != review.signer);
+        reviews.push(review.clone());
+    }
+
+    fn spend_reviews(&mut self, txid: &bitcoin::Txid) -> Vec<SpendReview> {
+        self.db
+            .read()
+            .unwrap()
+            .spend_reviews
+            .get(txid)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn record_broadcast(&mut self, tx: &bitcoin::Transaction) {
+        let mut db = self.db.write().unwrap();
+        if !db.broadcast_journal.contains(tx) {
+            db.broadcast_journal.push(tx.clone());
+        }
+    }
+
+    fn broadcast_journal(&mut self) -> Vec<bitcoin::Transaction> {
+        self.db.read().unwrap().broadcast_journal.clone()
+    }
+
+    fn remove_broadcast(&mut self, txid: &bitcoin::Txid) {
+        self.db
+            .write()
+            .unwrap()
+            .broadcast_journal
+            .retain(|tx| tx.compute_txid() != *txid);
+    }
+
+    fn schedule_spend(&mut self, tx: &bitcoin::Transaction) {
+        let mut db = self.db.write().unwrap();
+        let txid = tx.compute_txid();
+        if let Some(scheduled) = db
+            .scheduled_spends
+            .iter_mut()
+            .find(|s| s.tx.compute_txid() == txid)
+        {
+            scheduled.tx = tx.clone();
+        } else {
+            let scheduled_at = db.timestamp;
+            db.scheduled_spends.push(ScheduledSpend {
+                tx: tx.clone(),
+                scheduled_at,
+            });
+        }
+    }
+
+    fn scheduled_spends(&mut self) -> Vec<ScheduledSpend> {
+        self.db.read().unwrap().scheduled_spends.clone()
+    }
+
+    fn unschedule_spend(&mut self, txid: &bitcoin::Txid) {
+        self.db
+            .write()
+            .unwrap()
+            .scheduled_spends
+            .retain(|s| s.tx.compute_txid() != *txid);
+    }
+
+    fn unverified_transactions(&mut self) -> Vec<(bitcoin::Txid, i32)> {
+        let db = self.db.read().unwrap();
+        let confirmed: HashSet<_> = db
+            .coins
+            .values()
+            .flat_map(|c| {
+                let deposit = c.block_info.map(|b| (c.outpoint.txid, b.height));
+                let spend = c
+                    .spend_txid
+                    .zip(c.spend_block)
+                    .map(|(txid, b)| (txid, b.height));
+                deposit.into_iter().chain(spend)
+            })
+            .collect();
+        confirmed
+            .into_iter()
+            .filter(|(txid, height)| {
+                db.tx_verifications
+                    .get(txid)
+                    .map(|v| v.block_height != *height)
+                    .unwrap_or(true)
+            })
+            .collect()
+    }
+
+    fn record_tx_verification(&mut self, txid: &bitcoin::Txid, height: i32, error: Option<&str>) {
+        let mut db = self.db.write().unwrap();
+        let checked_at = db.timestamp;
+        db.tx_verifications.insert(
+            *txid,
+            TxVerification {
+                block_height: height,
+                error: error.map(|e| e.to_string()),
+                checked_at,
+            },
+        );
+    }
+
+    fn tx_verification(&mut self, txid: &bitcoin::Txid) -> Option<TxVerification> {
+        self.db.read().unwrap().tx_verifications.get(txid).cloned()
+    }
+
+    fn silent_payment_keys(&mut self) -> Option<SilentPaymentKeys> {
+        self.db.read().unwrap().silent_payment_keys
+    }
+
+    fn store_silent_payment_keys(&mut self, keys: &SilentPaymentKeys) {
+        self.db.write().unwrap().silent_payment_keys = Some(*keys);
+    }
+
+    fn silent_payments(&mut self) -> Vec<SilentPayment> {
+        self.db.read().unwrap().silent_payments.clone()
+    }
+
+    fn record_silent_payment_scan(
+        &mut self,
+        block: BlockInfo,
+        payments: &[SilentPayment],
+        spent: &[(bitcoin::OutPoint, bitcoin::Txid)],
+    ) {
+        let mut db = self.db.write().unwrap();
+        for payment in payments {
+            if !db
+                .silent_payments
+                .iter()
+                .any(|p| p.outpoint == payment.outpoint)
+            {
+                db.silent_payments.push(*payment);
+            }
+        }
+        for (outpoint, txid) in spent {
+            if let Some(payment) = db
+                .silent_payments
+                .iter_mut()
+                .find(|p| p.outpoint == *outpoint)
+            {
+                payment.spend_txid = Some(*txid);
+                payment.spend_block = Some(block);
+            }
+        }
+        if let Some(keys) = db.silent_payment_keys.as_mut() {
+            keys.scanned_height = block.height;
+        }
+    }
+
+    fn spend_silent_payments(
+        &mut self,
+        outpoints: &[bitcoin::OutPoint],
+        spend_txid: Option<&bitcoin::Txid>,
+    ) {
+        for payment in self.db.write().unwrap().silent_payments.iter_mut() {
+            if outpoints.contains(&payment.outpoint) && payment.spend_block.is_none() {
+                payment.spend_txid = spend_txid.copied();
+            }
+        }
+    }
+
+    fn readiness_checks(&mut self) -> HashMap<ReadinessItem, u32> {
+        self.db.read().unwrap().readiness_checks.clone()
+    }
+
+    fn set_readiness_check(&mut self, item: ReadinessItem, done: bool) {
+        let mut db = self.db.write().unwrap();
+        if done {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs() as u32;
+            db.readiness_checks.insert(item, now);
+        } else {
+            db.readiness_checks.remove(&item);
+        }
+    }
+
+    fn store_event(&mut self, entry: &EventEntry) {
+        self.db.write().unwrap().events.push(entry.clone());
+    }
+
+    fn events(&mut self, limit: usize) -> Vec<EventEntry> {
+        let events = &self.db.read().unwrap().events;
+        events[events.len().saturating_sub(limit)..].to_vec()
+    }
+
+    fn store_spend_template(&mut self, template: &SpendTemplate) {
+        let templates = &mut self.db.write().unwrap().spend_templates;
+        templates.retain(|t| t.name != template.name);
+        templates.push(template.clone());
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    fn spend_templates(&mut self) -> Vec<SpendTemplate> {
+        self.db.read().unwrap().spend_templates.clone()
+    }
+
+    fn delete_spend_template(&mut self, name: &str) {
+        self.db
+            .write()
+            .unwrap()
+            .spend_templates
+            .retain(|t| t.name != name);
     }
 
     fn rollback_tip(&mut self, _: &BlockChainTip) {
@@ -429,6 +779,14 @@ impl DatabaseConnection for DummyDatabase {
         todo!()
     }
 
+    fn rescan_checkpoint(&mut self) -> Option<i32> {
+        None
+    }
+
+    fn set_rescan_checkpoint(&mut self, _: i32) {
+        todo!()
+    }
+
     fn last_poll_timestamp(&mut self) -> Option<u32> {
         self.db.read().unwrap().last_poll_timestamp
     }
@@ -437,12 +795,26 @@ impl DatabaseConnection for DummyDatabase {
         self.db.write().unwrap().last_poll_timestamp = Some(timestamp);
     }
 
-    fn update_labels(&mut self, _items: &HashMap<LabelItem, Option<String>>) {
-        todo!()
+    fn update_labels(&mut self, items: &HashMap<LabelItem, Option<String>>) {
+        let labels = &mut self.db.write().unwrap().labels;
+        for (item, label) in items {
+            if let Some(label) = label {
+                labels.insert(item.to_string(), label.clone());
+            } else {
+                labels.remove(&item.to_string());
+            }
+        }
     }
 
-    fn labels(&mut self, _items: &HashSet<LabelItem>) -> HashMap<String, String> {
-        todo!()
+    fn labels(&mut self, items: &HashSet<LabelItem>) -> HashMap<String, String> {
+        let labels = &self.db.read().unwrap().labels;
+        items
+            .iter()
+            .filter_map(|item| {
+                let item = item.to_string();
+                labels.get(&item).map(|label| (item, label.clone()))
+            })
+            .collect()
     }
 
     fn list_txids(&mut self, start: u32, end: u32, limit: u64) -> Vec<bitcoin::Txid> {
@@ -474,6 +846,24 @@ impl DatabaseConnection for DummyDatabase {
         txids_and_time.into_iter().map(|(txid, _)| txid).collect()
     }
 
+    fn recent_txids(&mut self, limit: u64) -> Vec<(bitcoin::Txid, Option<i32>)> {
+        let mut txids = Vec::new();
+        for coin in self.db.read().unwrap().coins.values() {
+            let mut rows = vec![(coin.outpoint.txid, coin.block_info.map(|b| b.height))];
+            if let Some(txid) = coin.spend_txid {
+                rows.push((txid, coin.spend_block.map(|b| b.height)));
+            }
+            for row in rows {
+                if !txids.contains(&row) {
+                    txids.push(row);
+                }
+            }
+        }
+        txids.sort_by_key(|(_, height)| std::cmp::Reverse(height.unwrap_or(i32::MAX)));
+        txids.truncate(limit as usize);
+        txids
+    }
+
     fn list_saved_txids(&mut self) -> Vec<bitcoin::Txid> {
         self.db.read().unwrap().txs.keys().cloned().collect()
     }
@@ -523,6 +913,10 @@ impl DatabaseConnection for DummyDatabase {
         }
         wallet_txs
     }
+
+    fn quick_check(&mut self) -> Vec<String> {
+        Vec::new()
+    }
 }
 
 pub struct DummyLiana {
@@ -548,12 +942,70 @@ pub fn tmp_dir() -> path::PathBuf {
     ))
 }
 
+/// Add a valid signature of this P2WSH input of the PSBT by this key. Returns the public key.
+pub fn sign_input(
+    psbt: &mut Psbt,
+    index: usize,
+    secret_key: &secp256k1::SecretKey,
+) -> bitcoin::PublicKey {
+    let secp = secp256k1::Secp256k1::new();
+    let psbt_in = &psbt.inputs[index];
+    let sighash = bitcoin::sighash::SighashCache::new(&psbt.unsigned_tx)
+        .p2wsh_signature_hash(
+            index,
+            psbt_in.witness_script.as_ref().unwrap(),
+            psbt_in.witness_utxo.as_ref().unwrap().value,
+            bitcoin::EcdsaSighashType::All,
+        )
+        .unwrap();
+    let sig = bitcoin::ecdsa::Signature::sighash_all(secp.sign_ecdsa(&sighash.into(), secret_key));
+    let pubkey = bitcoin::PublicKey::new(secret_key.public_key(&secp));
+    psbt.inputs[index].partial_sigs.insert(pubkey, sig);
+    pubkey
+}
+
+/// Sign this Taproot PSBT input through the key path with the given internal secret key. Returns
+/// the internal key.
+pub fn sign_taproot_key_path(
+    psbt: &mut Psbt,
+    index: usize,
+    secret_key: &secp256k1::SecretKey,
+) -> secp256k1::XOnlyPublicKey {
+    use bitcoin::key::TapTweak;
+
+    let secp = secp256k1::Secp256k1::new();
+    let prevouts: Vec<bitcoin::TxOut> = psbt
+        .inputs
+        .iter()
+        .map(|psbt_in| psbt_in.witness_utxo.clone().unwrap())
+        .collect();
+    let sighash = bitcoin::sighash::SighashCache::new(&psbt.unsigned_tx)
+        .taproot_key_spend_signature_hash(
+            index,
+            &bitcoin::sighash::Prevouts::All(&prevouts),
+            bitcoin::TapSighashType::Default,
+        )
+        .unwrap();
+    let keypair = secp256k1::Keypair::from_secret_key(&secp, secret_key);
+    let (internal_key, _) = keypair.x_only_public_key();
+    let tweaked = keypair
+        .tap_tweak(&secp, psbt.inputs[index].tap_merkle_root)
+        .to_inner();
+    psbt.inputs[index].tap_key_sig = Some(bitcoin::taproot::Signature {
+        signature: secp.sign_schnorr_no_aux_rand(&sighash.into(), &tweaked),
+        sighash_type: bitcoin::TapSighashType::Default,
+    });
+    internal_key
+}
+
 impl DummyLiana {
     /// Creates a new DummyLiana interface
     pub fn _new(
         bitcoin_interface: impl BitcoinInterface + 'static,
         database: impl DatabaseInterface + 'static,
+        main_descriptor: Option<descriptors::LianaDescriptor>,
         rpc_server: bool,
+        configure: impl FnOnce(&mut Config),
     ) -> DummyLiana {
         let tmp_dir = tmp_dir();
         fs::create_dir_all(&tmp_dir).unwrap();
@@ -566,21 +1018,34 @@ impl DummyLiana {
             poll_interval_secs: time::Duration::from_secs(2),
         };
 
-        let owner_key = descriptors::PathInfo::Single(descriptor::DescriptorPublicKey::from_str("[aabbccdd]xpub68JJTXc1MWK8KLW4HGLXZBJknja7kDUJuFHnM424LbziEXsfkh1WQCiEjjHw4zLqSUm4rvhgyGkkuRowE9tCJSgt3TQB5J3SKAbZ2SdcKST/<0;1>/*").unwrap());
-        let heir_key = descriptors::PathInfo::Single(descriptor::DescriptorPublicKey::from_str("[aabbccdd]xpub68JJTXc1MWK8PEQozKsRatrUHXKFNkD1Cb1BuQU9Xr5moCv87anqGyXLyUd4KpnDyZgo3gz4aN1r3NiaoweFW8UutBsBbgKHzaD5HkTkifK/<0;1>/*").unwrap());
-        let policy = descriptors::LianaPolicy::new_legacy(
-            owner_key,
-            [(10_000, heir_key)].iter().cloned().collect(),
-        )
-        .unwrap();
-        let desc = descriptors::LianaDescriptor::new(policy);
-        let config = Config {
+        let desc = main_descriptor.unwrap_or_else(|| {
+            let owner_key = descriptors::PathInfo::Single(descriptor::DescriptorPublicKey::from_str("[aabbccdd]xpub68JJTXc1MWK8KLW4HGLXZBJknja7kDUJuFHnM424LbziEXsfkh1WQCiEjjHw4zLqSUm4rvhgyGkkuRowE9tCJSgt3TQB5J3SKAbZ2SdcKST/<0;1>/*").unwrap());
+            let heir_key = descriptors::PathInfo::Single(descriptor::DescriptorPublicKey::from_str("[aabbccdd]xpub68JJTXc1MWK8PEQozKsRatrUHXKFNkD1Cb1BuQU9Xr5moCv87anqGyXLyUd4KpnDyZgo3gz4aN1r3NiaoweFW8UutBsBbgKHzaD5HkTkifK/<0;1>/*").unwrap());
+            let policy = descriptors::LianaPolicy::new_legacy(
+                owner_key,
+                [(10_000, heir_key)].iter().cloned().collect(),
+            )
+            .unwrap();
+            descriptors::LianaDescriptor::new(policy)
+        });
+        let mut config = Config {
+            webhook_url: None,
+            coins_snapshot: None,
+            shutdown_timeout_secs: time::Duration::from_secs(8),
+            spend_confirmation_threshold: None,
+            slow_operation_threshold_ms: None,
             bitcoin_config,
             bitcoin_backend: None,
             data_dir: Some(data_dir),
+            ephemeral: false,
             log_level: log::LevelFilter::Debug,
             main_descriptor: desc,
+            watchtower: None,
+            cross_check: None,
+            dashboard: None,
+            signer_server: None,
         };
+        configure(&mut config);
 
         let handle =
             DaemonHandle::start(config, Some(bitcoin_interface), Some(database), rpc_server)
@@ -593,7 +1058,31 @@ impl DummyLiana {
         bitcoin_interface: impl BitcoinInterface + 'static,
         database: impl DatabaseInterface + 'static,
     ) -> DummyLiana {
-        Self::_new(bitcoin_interface, database, false)
+        Self::_new(bitcoin_interface, database, None, false, |_| {})
+    }
+
+    /// Creates a new DummyLiana interface with these changes to the default configuration.
+    pub fn new_with_config(
+        bitcoin_interface: impl BitcoinInterface + 'static,
+        database: impl DatabaseInterface + 'static,
+        configure: impl FnOnce(&mut Config),
+    ) -> DummyLiana {
+        Self::_new(bitcoin_interface, database, None, false, configure)
+    }
+
+    /// Creates a new DummyLiana interface for this descriptor.
+    pub fn new_with_descriptor(
+        bitcoin_interface: impl BitcoinInterface + 'static,
+        database: impl DatabaseInterface + 'static,
+        main_descriptor: descriptors::LianaDescriptor,
+    ) -> DummyLiana {
+        Self::_new(
+            bitcoin_interface,
+            database,
+            Some(main_descriptor),
+            false,
+            |_| {},
+        )
     }
 
     /// Creates a new DummyLiana interface which also spins up an RPC server.
@@ -601,7 +1090,7 @@ impl DummyLiana {
         bitcoin_interface: impl BitcoinInterface + 'static,
         database: impl DatabaseInterface + 'static,
     ) -> DummyLiana {
-        Self::_new(bitcoin_interface, database, true)
+        Self::_new(bitcoin_interface, database, None, true, |_| {})
     }
 
     pub fn control(&self) -> &DaemonControl {
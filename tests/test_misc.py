@@ -210,22 +210,33 @@ def test_coinbase_deposit(lianad, bitcoind):
     # Create a new deposit in a coinbase transaction. We must detect it and treat it as immature.
     addr = lianad.rpc.getnewaddress()["address"]
     bitcoind.rpc.generatetoaddress(1, addr)
+    coinbase_height = bitcoind.rpc.getblockcount()
     wait_for_sync()
     coins = lianad.rpc.listcoins()["coins"]
     assert (
         len(coins) == 1
         and coins[0]["is_immature"]
+        and coins[0]["maturity_height"] == coinbase_height + 100
         and coins[0]["spend_info"] is None
         and not coins[0]["is_from_self"]
     )
 
-    # Generate 100 blocks to make the coinbase mature. We should detect it as such.
-    # It remains as not from self.
-    bitcoind.generate_block(100)
+    # It isn't considered for automated coin selection.
+    destinations = {bitcoind.rpc.getnewaddress(): int(0.5 * COIN)}
+    assert "missing" in lianad.rpc.createspend(destinations, [], 2)
+
+    # The coinbase is mature once it has a hundred confirmations, as a transaction spending it
+    # can then be included in the next block. We should detect it as such. It remains as not
+    # from self.
+    bitcoind.generate_block(98)
+    wait_for_sync()
+    assert lianad.rpc.listcoins()["coins"][0]["is_immature"]
+    bitcoind.generate_block(1)
     wait_for_sync()
     coin = lianad.rpc.listcoins()["coins"][0]
     assert (
         not coin["is_immature"]
+        and coin["maturity_height"] is None
         and coin["block_height"] is not None
         and not coins[0]["is_from_self"]
     )
@@ -270,6 +281,32 @@ def test_coinbase_deposit(lianad, bitcoind):
     )
 
 
+def test_external_deposit_to_change(lianad, bitcoind):
+    """Deposits from a third party to one of our change addresses are detected as such."""
+    change_desc = lianad.multi_desc.singlepath_descriptors()[1]
+    change_addr = bitcoind.rpc.deriveaddresses(str(change_desc), [0, 0])[0]
+    txid = bitcoind.rpc.sendtoaddress(change_addr, 0.01)
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 1)
+    coin = lianad.rpc.listcoins()["coins"][0]
+    assert coin["is_change"] and not coin["is_from_self"]
+    assert coin["outpoint"].startswith(txid)
+
+    # The deposit transaction is part of our history, like any other deposit.
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: lianad.rpc.listcoins()["coins"][0]["block_height"] is not None)
+    block_time = bitcoind.rpc.getblockheader(bitcoind.rpc.getbestblockhash())["time"]
+    txs = lianad.rpc.listconfirmed(0, block_time + 1, 10)["transactions"]
+    assert len(txs) == 1
+
+    # The change address that received the deposit must not be reused for our own change.
+    destinations = {bitcoind.rpc.getnewaddress(): 200_000}
+    res = lianad.rpc.createspend(destinations, [coin["outpoint"]], 2)
+    psbt = PSBT.from_base64(res["psbt"])
+    assert len(psbt.o) == 2
+    change_spk = bitcoind.rpc.validateaddress(change_addr)["scriptPubKey"]
+    assert all(o.scriptPubKey.hex() != change_spk for o in psbt.tx.vout)
+
+
 @pytest.mark.skipif(
     OLD_LIANAD_PATH is None or USE_TAPROOT,
     reason="Need the old lianad binary to create the datadir.",
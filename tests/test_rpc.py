@@ -1,3 +1,5 @@
+import importlib.util
+import os
 import pytest
 import random
 import re
@@ -18,9 +20,46 @@ from test_framework.utils import (
     sign_and_broadcast,
     sign_and_broadcast_psbt,
     USE_TAPROOT,
+    BITCOIN_BACKEND_TYPE,
+    BitcoinBackendType,
 )
 
 
+def test_rpc_discover(lianad):
+    doc = lianad.rpc.call("rpc.discover")
+    assert doc["info"]["version"] == "9.0.0-dev"
+    methods = {m["name"]: m for m in doc["methods"]}
+    assert len(methods) == 29
+    assert [p["name"] for p in methods["createspend"]["params"]] == [
+        "destinations",
+        "outpoints",
+        "feerate",
+        "change_address",
+        "locktime",
+    ]
+
+    # Clients generated from the schema can talk to the daemon.
+    generator_path = os.path.join(
+        os.path.dirname(__file__), "..", "contrib", "rpc_bindings", "generate.py"
+    )
+    spec = importlib.util.spec_from_file_location("generate", generator_path)
+    generate = importlib.util.module_from_spec(spec)
+    spec.loader.exec_module(generate)
+    namespace = {}
+    exec(generate.python_client(doc), namespace)
+    assert "export class LianaClient" in generate.typescript_client(doc)
+
+    class Client(namespace["LianaClient"]):
+        def _call(self, method, params):
+            return lianad.rpc.call(method, params)
+
+    client = Client(lianad.rpc.socket_path)
+    assert client.getinfo()["network"] == "regtest"
+    coins = lianad.rpc.listcoins(["confirmed"])
+    assert client.listcoins(statuses=["confirmed"]) == coins
+    assert client.listaddresses(start_index=2, count=1)["addresses"][0]["index"] == 2
+
+
 def test_getinfo(lianad):
     res = lianad.rpc.getinfo()
     assert "timestamp" in res.keys()
@@ -31,11 +70,67 @@ def test_getinfo(lianad):
     assert res["sync"] == 1.0
     assert "main" in res["descriptors"]
     assert res["rescan_progress"] is None
+    assert res["rescan_checkpoint"] is None
     last_poll_timestamp = res["last_poll_timestamp"]
     assert last_poll_timestamp is not None
     time.sleep(lianad.poll_interval_secs + 1)
     res = lianad.rpc.getinfo()
     assert res["last_poll_timestamp"] > last_poll_timestamp
+    poller = res["poller"]
+    assert poller["polls"] > 0
+    assert poller["consecutive_failures"] == 0
+    assert poller["max_duration_ms"] >= poller["last_duration_ms"]
+
+
+def test_getbalance(lianad, bitcoind):
+    def balance():
+        return lianad.rpc.getbalance()
+
+    assert all(v == 0 for v in balance().values())
+
+    # An unconfirmed deposit.
+    addr = lianad.rpc.getnewaddress()["address"]
+    txid = bitcoind.rpc.sendtoaddress(addr, 1)
+    wait_for(lambda: balance()["unconfirmed"] == COIN)
+    assert balance()["confirmed"] == 0
+
+    # Once confirmed, it moves to the confirmed balance.
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: balance()["confirmed"] == COIN)
+    assert balance()["unconfirmed"] == 0
+
+    # The balances match the coins.
+    coins = lianad.rpc.listcoins(["confirmed"])["coins"]
+    assert balance()["confirmed"] == sum(c["amount"] for c in coins)
+
+    # A coin being spent is accounted as such.
+    spend_coins(lianad, bitcoind, coins)
+    wait_for(lambda: balance()["spending"] == COIN)
+    assert balance()["confirmed"] == 0
+
+
+def test_exportsnapshot(lianad, bitcoind):
+    # A confirmed and an unconfirmed deposit.
+    addr = lianad.rpc.getnewaddress()["address"]
+    txid = bitcoind.rpc.sendtoaddress(addr, 1)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    addr = lianad.rpc.getnewaddress()["address"]
+    bitcoind.rpc.sendtoaddress(addr, 0.5)
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 2)
+    wait_for(
+        lambda: lianad.rpc.getinfo()["block_height"] == bitcoind.rpc.getblockcount()
+    )
+
+    snapshot = lianad.rpc.exportsnapshot()
+    assert snapshot["descriptor"] == lianad.rpc.getinfo()["descriptors"]["main"]
+    assert snapshot["tip_height"] == bitcoind.rpc.getblockcount()
+    assert snapshot["tip_hash"] == bitcoind.rpc.getbestblockhash()
+    assert snapshot["receive_index"] >= 2
+    coins = sorted(snapshot["coins"], key=lambda c: c["amount"])
+    assert [c["amount"] for c in coins] == [COIN // 2, COIN]
+    assert coins[0]["block_height"] is None
+    assert coins[1]["block_height"] == snapshot["tip_height"]
+    assert len(snapshot["transactions"]) == 2
 
 
 def test_getaddress(lianad):
@@ -81,6 +176,113 @@ def test_listaddresses(lianad):
         lianad.rpc.listaddresses(0, "blb")
 
 
+def test_derivation_indexes(lianad, bitcoind):
+    res = lianad.rpc.getderivationindexes()
+    assert res["receive"] == {
+        "next_index": 0,
+        "highest_used_index": None,
+        "gaps": [],
+        "largest_gap": 0,
+    }
+    assert res["watched_up_to"] == res["lookahead"] - 1
+
+    # Receive a coin on the third generated address.
+    addrs = [lianad.rpc.getnewaddress()["address"] for _ in range(3)]
+    bitcoind.rpc.sendtoaddress(addrs[2], 0.01)
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 1)
+    res = lianad.rpc.getderivationindexes()
+    assert res["receive"]["highest_used_index"] == 2
+    assert res["receive"]["gaps"] == [[0, 1]]
+    assert res["receive"]["largest_gap"] == 2
+
+    # Extend the window of watched addresses.
+    lookahead = res["lookahead"]
+    res = lianad.rpc.updatederivationindexes(lookahead + 10)
+    assert res["receive"]["next_index"] == lookahead + 10
+    assert res["watched_up_to"] == 2 * lookahead + 9
+    assert res["receive"]["gaps"] == [[0, 1], [3, lookahead + 9]]
+    # Lower indexes are ignored.
+    assert lianad.rpc.updatederivationindexes(receive=5) == res
+    with pytest.raises(RpcError, match="can't be increased by more than 1000"):
+        lianad.rpc.updatederivationindexes(change=1_001)
+
+
+def test_get_hot_signer_xpubs(lianad):
+    with pytest.raises(RpcError, match="No hot signer is stored in the data directory."):
+        lianad.rpc.gethotsignerxpubs(["m/48'/1'/0'/2'"])
+
+    # Store a hot signer in the data directory, as the GUI does.
+    mnemonics_dir = os.path.join(lianad.datadir, "regtest", "mnemonics")
+    os.makedirs(mnemonics_dir)
+    with open(os.path.join(mnemonics_dir, "mnemonic-aabbccdd.txt"), "w") as f:
+        f.write("burger ball theme dog light account produce chest warrior swarm flip equip")
+    res = lianad.rpc.gethotsignerxpubs(["m/48'/1'/0'/2'", "m/48'/1'/5'/2'"])
+    assert len(res["xpubs"]) == 2
+    for entry in res["xpubs"]:
+        assert entry["key"].startswith(f"[{res['fingerprint']}/48'/1'/")
+        assert entry["key"].endswith(entry["xpub"])
+    with pytest.raises(RpcError, match="Unhardened or overflowing BIP32 derivation index."):
+        lianad.rpc.gethotsignerxpubs(["m/48'/1'/0'/2"])
+
+
+def test_import_hot_signer(lianad):
+    mnemonic = "burger ball theme dog light account produce chest warrior swarm flip equip"
+    fingerprint = lianad.rpc.importhotsigner(mnemonic)["fingerprint"]
+    res = lianad.rpc.gethotsignerxpubs(["m/48'/1'/0'/2'"], fingerprint)
+    assert res["fingerprint"] == fingerprint
+    # Importing it again is a no-op.
+    assert lianad.rpc.importhotsigner(mnemonic)["fingerprint"] == fingerprint
+
+    # 2-of-3 SLIP-39 shares, from the SLIP-39 test vectors.
+    shares = [
+        "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed",
+        "shadow pistol academic acid actress prayer class unknown daughter sweater depict flip twice unkind craft early superior advocate guest smoking",
+    ]
+    res = lianad.rpc.importhotsigner(shares)
+    assert res["fingerprint"] != fingerprint
+    assert lianad.rpc.importhotsigner("\n".join(shares)) == res
+    with pytest.raises(RpcError, match="Invalid hot signer backup"):
+        lianad.rpc.importhotsigner(shares[:1])
+
+
+def test_simulate_signer_loss(lianad, bitcoind):
+    # Receive a coin.
+    addr = lianad.rpc.getnewaddress()["address"]
+    txid = bitcoind.rpc.sendtoaddress(addr, 0.01)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lianad.rpc.listcoins(["confirmed"])["coins"]) == 1)
+    height = lianad.rpc.listcoins()["coins"][0]["block_height"]
+
+    # Without losing a signer, the coin is spendable right away.
+    res = lianad.rpc.simulatesignerloss([])
+    assert res["spendable_after"] == 0
+    assert all(p["usable"] for p in res["paths"])
+    assert res["coins"][0]["spendable_now"]
+
+    # Losing the primary signer leaves the recovery path.
+    desc = lianad.rpc.getinfo()["descriptors"]["main"]
+    primary_fg = desc.split("[")[1][:8]
+    res = lianad.rpc.simulatesignerloss([primary_fg])
+    assert res["paths"][0]["timelock"] is None and not res["paths"][0]["usable"]
+    timelock = res["paths"][1]["timelock"]
+    assert res["spendable_after"] == timelock
+    assert res["coins"][0]["spendable_at_height"] == height + timelock
+
+    with pytest.raises(RpcError, match="No key of the descriptor belongs to signer"):
+        lianad.rpc.simulatesignerloss(["00000000"])
+
+
+def test_lint_descriptor(lianad):
+    # The recovery path of the test descriptor is available after only a few blocks.
+    res = lianad.rpc.lintdescriptor()
+    assert [w["code"] for w in res["warnings"]] == ["short_timelock"]
+    assert lianad.rpc.lintdescriptor(min_timelock=1)["warnings"] == []
+    desc = lianad.rpc.getinfo()["descriptors"]["main"]
+    assert lianad.rpc.lintdescriptor(desc, 1, 5)["warnings"][0]["code"] == "long_timelock"
+    with pytest.raises(RpcError, match="Invalid 'descriptor' parameter."):
+        lianad.rpc.lintdescriptor("wsh(pk(invalid))")
+
+
 def test_listcoins(lianad, bitcoind):
     # Initially empty
     res = lianad.rpc.listcoins()
@@ -519,12 +721,35 @@ def test_list_spend(lianad, bitcoind):
     second_psbt = next(entry for entry in list_res if entry["psbt"] == res_b["psbt"])
     assert time_before_update <= second_psbt["updated_at"] <= int(time.time())
 
+    # The second one spends the coin of the first one. They conflict with each other.
+    first_txid = PSBT.from_base64(res["psbt"]).tx.txid().hex()
+    second_txid = PSBT.from_base64(res_b["psbt"]).tx.txid().hex()
+    assert first_psbt["conflicting_spends"] == [second_txid]
+    assert second_psbt["conflicting_spends"] == [first_txid]
+    assert first_psbt["invalidated_by"] is None
+
+    # They can be filtered by status and by signer.
+    assert len(lianad.rpc.listspendtxs(statuses=["needs_signature"])["spend_txs"]) == 2
+    assert len(lianad.rpc.listspendtxs(statuses=["fully_signed"])["spend_txs"]) == 0
+    assert len(lianad.rpc.listspendtxs(statuses=["conflicting"])["spend_txs"]) == 0
+    assert len(lianad.rpc.listspendtxs(signer="00000000")["spend_txs"]) == 0
+    signed_psbt = lianad.signer.sign_psbt(PSBT.from_base64(res_b["psbt"]))
+    lianad.rpc.updatespend(signed_psbt.to_base64())
+    list_res = lianad.rpc.listspendtxs(statuses=["fully_signed"])["spend_txs"]
+    assert len(list_res) == 1
+    assert PSBT.from_base64(list_res[0]["psbt"]).tx.txid() == signed_psbt.tx.txid()
+    assert len(lianad.rpc.listspendtxs(statuses=["needs_signature"])["spend_txs"]) == 1
+    with pytest.raises(RpcError, match="Invalid value \"signed\" in 'statuses'"):
+        lianad.rpc.listspendtxs(statuses=["signed"])
+    with pytest.raises(RpcError, match="Invalid params: Invalid 'signer' parameter."):
+        lianad.rpc.listspendtxs(signer="zz")
+
     # If we delete the first one, we'll get only the second one.
     first_psbt = PSBT.from_base64(res["psbt"])
     lianad.rpc.delspendtx(first_psbt.tx.txid().hex())
     list_res = lianad.rpc.listspendtxs()["spend_txs"]
     assert len(list_res) == 1
-    assert list_res[0]["psbt"] == res_b["psbt"]
+    assert PSBT.from_base64(list_res[0]["psbt"]).tx.txid() == signed_psbt.tx.txid()
 
     # If we delete the second one, result will be empty.
     second_psbt = PSBT.from_base64(res_b["psbt"])
@@ -533,6 +758,44 @@ def test_list_spend(lianad, bitcoind):
     assert len(list_res) == 0
 
 
+def test_review_spend(lianad, bitcoind):
+    # Create and store a Spend PSBT.
+    addr = lianad.rpc.getnewaddress()["address"]
+    bitcoind.rpc.sendtoaddress(addr, 0.2567)
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 1)
+    outpoints = [c["outpoint"] for c in lianad.rpc.listcoins()["coins"]]
+    destinations = {bitcoind.rpc.getnewaddress(): 200_000}
+    psbt = lianad.rpc.createspend(destinations, outpoints, 6)["psbt"]
+    txid = PSBT.from_base64(psbt).tx.txid().hex()
+    signer = lianad.signer.primary_hd.get_fingerprint().hex()
+
+    # It can only be reviewed once stored, by a signer of the wallet.
+    with pytest.raises(RpcError, match="Unknown spend transaction"):
+        lianad.rpc.reviewspend(txid, signer, "approved")
+    lianad.rpc.updatespend(psbt)
+    with pytest.raises(RpcError, match="00000000"):
+        lianad.rpc.reviewspend(txid, "00000000", "approved")
+    with pytest.raises(RpcError, match="Invalid 'decision' parameter"):
+        lianad.rpc.reviewspend(txid, signer, "approve")
+    entry = lianad.rpc.listspendtxs()["spend_txs"][0]
+    assert entry["reviews"] == []
+    assert entry["review_state"] == "pending"
+
+    # The latest review of the signer is kept, and an event recorded.
+    lianad.rpc.reviewspend(txid, signer, "approved")
+    lianad.rpc.reviewspend(txid, signer, "changes_requested", "Lower the fee")
+    entry = lianad.rpc.listspendtxs()["spend_txs"][0]
+    assert len(entry["reviews"]) == 1
+    assert entry["reviews"][0]["signer"] == signer
+    assert entry["reviews"][0]["decision"] == "changes_requested"
+    assert entry["reviews"][0]["comment"] == "Lower the fee"
+    assert entry["review_state"] == "changes_requested"
+    event = lianad.rpc.listevents()["events"][-1]
+    assert event["type"] == "spend_reviewed"
+    assert event["level"] == "warning"
+    assert event["decision"] == "changes_requested"
+
+
 def test_update_spend(lianad, bitcoind):
     # Start by creating a Spend PSBT
     addr = lianad.rpc.getnewaddress()["address"]
@@ -668,6 +931,8 @@ def test_start_rescan(lianad, bitcoind):
     # we can rescan from genesis block
     lianad.rpc.startrescan(genesis_timestamp)
     wait_for(lambda: lianad.rpc.getinfo()["rescan_progress"] is None)
+    # The checkpoint of the rescan is dropped once it completed.
+    assert lianad.rpc.getinfo()["rescan_checkpoint"] is None
 
     # First, get some coins
     for _ in range(10):
@@ -894,6 +1159,57 @@ def test_listtransactions(lianad, bitcoind):
     assert bit_txids == txids
 
 
+def test_gettransaction(lianad, bitcoind):
+    """Test getting a wallet transaction along with its wallet-relevant information."""
+    # Unknown transactions are rejected.
+    with pytest.raises(RpcError, match="Unknown transaction"):
+        lianad.rpc.gettransaction("00" * 32)
+
+    # Receive a coin and spend it, leaving a change output.
+    addr = lianad.rpc.getnewaddress()["address"]
+    deposit_txid = bitcoind.rpc.sendtoaddress(addr, 0.1)
+    bitcoind.generate_block(1, wait_for_mempool=deposit_txid)
+    wait_for(lambda: len(lianad.rpc.listcoins(["confirmed"])["coins"]) == 1)
+    outpoint = lianad.rpc.listcoins()["coins"][0]["outpoint"]
+    res = lianad.rpc.createspend({bitcoind.rpc.getnewaddress(): 100_000}, [outpoint], 2)
+    psbt = PSBT.from_base64(res["psbt"])
+    spend_txid = sign_and_broadcast_psbt(lianad, psbt)
+    lianad.rpc.updatelabels({spend_txid: "payment"})
+
+    # The deposit spends no coin of ours, we can't tell its fee.
+    deposit = lianad.rpc.gettransaction(deposit_txid)
+    assert deposit["height"] is not None
+    assert deposit["psbt"] is None
+    assert deposit["spent_coins"] == []
+    assert [c["outpoint"] for c in deposit["received_coins"]] == [outpoint]
+    assert deposit["fee"] is None
+    # With an Electrum backend the proof of inclusion of confirmed transactions is checked.
+    if BITCOIN_BACKEND_TYPE is BitcoinBackendType.Electrs:
+        wait_for(
+            lambda: lianad.rpc.gettransaction(deposit_txid)["inclusion_verified"] is True
+        )
+    else:
+        assert deposit["inclusion_verified"] is None
+
+    spend = lianad.rpc.gettransaction(spend_txid)
+    assert bitcoind.rpc.decoderawtransaction(spend["tx"])["txid"] == spend_txid
+    assert spend["height"] is None
+    assert spend["psbt"] is not None
+    assert [c["outpoint"] for c in spend["spent_coins"]] == [outpoint]
+    assert len(spend["received_coins"]) == 1
+    mempool_fee = bitcoind.rpc.getmempoolentry(spend_txid)["fees"]["base"]
+    assert spend["fee"] == int(round(mempool_fee * COIN))
+    assert spend["label"] == "payment"
+    assert spend["inclusion_verified"] is None
+
+    # Both are part of the export of all the wallet transactions.
+    txids = set(
+        bitcoind.rpc.decoderawtransaction(tx["tx"])["txid"]
+        for tx in lianad.rpc.exporttransactions()["transactions"]
+    )
+    assert txids == {deposit_txid, spend_txid}
+
+
 def test_create_recovery(lianad, bitcoind):
     """Test the sweep of coins that are available through the timelocked path."""
     # Generate blocks in order to test locktime set correctly.
@@ -1490,3 +1806,130 @@ def test_rbfpsbt_cancel(lianad, bitcoind):
             for c in lianad.rpc.listcoins([], [rbf_1_outpoint])["coins"]
         )
     )
+
+
+def test_recovery_readiness(lianad):
+    """Test the recovery readiness checklist is persisted and scored."""
+    res = lianad.rpc.getrecoveryreadiness()
+    assert res["expiring_coins"] == 0
+    assert res["score"] == 20
+    assert all(c["done"] == (c["item"] == "timelock_margin") for c in res["checks"])
+
+    lianad.rpc.setrecoveryreadiness("descriptor_backup", True)
+    lianad.rpc.setrecoveryreadiness("heirs_kit", True)
+    lianad.rpc.setrecoveryreadiness("heirs_kit", False)
+    res = lianad.rpc.getrecoveryreadiness()
+    assert res["score"] == 40
+    backup = next(c for c in res["checks"] if c["item"] == "descriptor_backup")
+    assert backup["done"] and backup["completed_at"] is not None
+
+    # It survives a restart.
+    lianad.stop()
+    lianad.start()
+    assert lianad.rpc.getrecoveryreadiness()["score"] == 40
+
+    # The timelock margin is computed, unknown items are rejected.
+    with pytest.raises(RpcError, match="can't be set"):
+        lianad.rpc.setrecoveryreadiness("timelock_margin", True)
+    with pytest.raises(RpcError, match="Invalid 'item' parameter"):
+        lianad.rpc.setrecoveryreadiness("backup", True)
+
+
+def test_spend_templates(lianad, bitcoind):
+    """Test spend templates can be stored and instantiated."""
+    addr = lianad.rpc.getnewaddress()["address"]
+    txid = bitcoind.rpc.sendtoaddress(addr, 0.01)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lianad.rpc.listcoins(["confirmed"])["coins"]) == 1)
+
+    rent, savings = bitcoind.rpc.getnewaddress(), bitcoind.rpc.getnewaddress()
+    template = {
+        "name": "Rent",
+        "recipients": [
+            {"address": rent, "amount": 200_000},
+            {"address": savings, "amount": None},
+        ],
+        "feerate": 2,
+        "coins": [],
+        "schedule": None,
+    }
+    lianad.rpc.savespendtemplate(template)
+    assert lianad.rpc.listspendtemplates()["templates"] == [template]
+
+    # The amount left to be asked must be given.
+    with pytest.raises(RpcError, match="Missing amount"):
+        lianad.rpc.instantiatespendtemplate("Rent")
+    res = lianad.rpc.instantiatespendtemplate("Rent", {savings: 100_000})
+    psbt = PSBT.from_base64(res["psbt"])
+    assert {o.nValue for o in psbt.tx.vout} >= {200_000, 100_000}
+
+    # A template with an amount to ask can't be scheduled.
+    template["schedule"] = {"every_days": 30, "last_draft_at": None}
+    with pytest.raises(RpcError, match="All the amounts must be set"):
+        lianad.rpc.savespendtemplate(template)
+
+    lianad.rpc.delspendtemplate("Rent")
+    assert lianad.rpc.listspendtemplates()["templates"] == []
+    with pytest.raises(RpcError, match="Unknown spend template"):
+        lianad.rpc.instantiatespendtemplate("Rent", {savings: 100_000})
+
+
+def test_scheduled_spend(lianad, bitcoind):
+    """Test a signed Spend with a future locktime is broadcast once it's reached."""
+    addr = lianad.rpc.getnewaddress()["address"]
+    txid = bitcoind.rpc.sendtoaddress(addr, 0.01)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lianad.rpc.listcoins(["confirmed"])["coins"]) == 1)
+    coin = lianad.rpc.listcoins(["confirmed"])["coins"][0]
+
+    # The locktime must be in the future.
+    height = bitcoind.rpc.getblockcount()
+    destinations = {bitcoind.rpc.getnewaddress(): 200_000}
+    with pytest.raises(RpcError, match="already reached"):
+        lianad.rpc.createspend(
+            destinations=destinations,
+            outpoints=[coin["outpoint"]],
+            feerate=2,
+            locktime=height,
+        )
+    res = lianad.rpc.createspend(
+        destinations=destinations,
+        outpoints=[coin["outpoint"]],
+        feerate=2,
+        locktime=height + 5,
+    )
+    psbt = PSBT.from_base64(res["psbt"])
+    assert psbt.tx.nLockTime == height + 5
+    spend_txid = psbt.tx.txid().hex()
+
+    # It can only be scheduled once signed.
+    lianad.rpc.updatespend(res["psbt"])
+    with pytest.raises(RpcError):
+        lianad.rpc.schedulespend(spend_txid)
+    signed_psbt = lianad.signer.sign_psbt(psbt)
+    lianad.rpc.updatespend(signed_psbt.to_base64())
+    lianad.rpc.schedulespend(spend_txid)
+    scheduled = lianad.rpc.listscheduledspends()["scheduled_spends"]
+    assert len(scheduled) == 1
+    assert scheduled[0]["txid"] == spend_txid
+    assert scheduled[0]["locktime"] == height + 5
+
+    # It can be unscheduled and scheduled again.
+    lianad.rpc.unschedulespend(spend_txid)
+    assert lianad.rpc.listscheduledspends()["scheduled_spends"] == []
+    with pytest.raises(RpcError, match="Unknown spend"):
+        lianad.rpc.unschedulespend(spend_txid)
+    lianad.rpc.schedulespend(spend_txid)
+
+    # It's not broadcast before it can be included in the next block.
+    bitcoind.generate_block(3)
+    wait_for(lambda: lianad.rpc.getinfo()["block_height"] == height + 3)
+    time.sleep(2)
+    assert spend_txid not in bitcoind.rpc.getrawmempool()
+    bitcoind.generate_block(1)
+    wait_for(lambda: spend_txid in bitcoind.rpc.getrawmempool())
+    wait_for(lambda: lianad.rpc.listscheduledspends()["scheduled_spends"] == [])
+    assert any(
+        e["type"] == "scheduled_spend_broadcast" and e["txid"] == spend_txid
+        for e in lianad.rpc.listevents()["events"]
+    )